@@ -3,14 +3,19 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use pixy_ai::{Message, Model, UserContent};
+use pixy_ai::{EventStream, Message, Model, StopReason, ToolValidationOptions, UserContent};
 use tokio::sync::Notify;
 use tracing::error;
 
+use crate::agent_hooks::AgentHooks;
 use crate::agent_loop::{agent_loop, try_agent_loop_continue};
+use crate::context_compaction::ContextCompaction;
+use crate::queued_messages::{dequeue_messages, QueuePriority};
+use crate::state_store::AgentStateStore;
 use crate::types::{
     AgentAbortController, AgentContext, AgentEvent, AgentLoopConfig, AgentMessage,
-    AgentRetryConfig, AgentTool, ConvertToLlmFn, IdentityMessageConverter, StreamFn,
+    AgentRetryConfig, AgentRunMetrics, AgentTool, ConvertToLlmFn, IdentityMessageConverter,
+    StreamFn,
 };
 
 const ERR_PROMPT_MESSAGES_EMPTY: &str = "Prompt messages cannot be empty";
@@ -19,19 +24,19 @@ const ERR_CANNOT_CONTINUE_FROM_ASSISTANT: &str = "Cannot continue from message r
 const ERR_AGENT_ALREADY_RUNNING: &str =
     "Agent is already processing. Wait for completion before prompting again.";
 const ERR_LOOP_WITHOUT_RESULT: &str = "Agent loop ended without a final result";
+const ERR_RESTORE_WHILE_RUNNING: &str =
+    "Agent is already processing. Wait for completion before restoring a checkpoint.";
+const ERR_LOAD_WHILE_RUNNING: &str =
+    "Agent is already processing. Wait for completion before loading saved state.";
+const ERR_NO_STATE_STORE: &str = "No state store is attached to the agent";
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
 pub enum QueueMode {
     All,
+    #[default]
     OneAtATime,
 }
 
-impl Default for QueueMode {
-    fn default() -> Self {
-        Self::OneAtATime
-    }
-}
-
 #[derive(Clone)]
 pub struct AgentConfig {
     pub system_prompt: String,
@@ -42,8 +47,12 @@ pub struct AgentConfig {
     pub convert_to_llm: ConvertToLlmFn,
     pub stream_fn: StreamFn,
     pub retry: AgentRetryConfig,
+    pub tool_parallelism: usize,
+    pub tool_validation: Option<ToolValidationOptions>,
     pub steering_mode: QueueMode,
     pub follow_up_mode: QueueMode,
+    pub hooks: Option<Arc<dyn AgentHooks>>,
+    pub context_compaction: Option<ContextCompaction>,
 }
 
 impl AgentConfig {
@@ -57,8 +66,12 @@ impl AgentConfig {
             convert_to_llm: Arc::new(IdentityMessageConverter),
             stream_fn,
             retry: AgentRetryConfig::default(),
+            tool_parallelism: 1,
+            tool_validation: None,
             steering_mode: QueueMode::OneAtATime,
             follow_up_mode: QueueMode::OneAtATime,
+            hooks: None,
+            context_compaction: None,
         }
     }
 }
@@ -73,6 +86,18 @@ pub struct AgentState {
     pub stream_message: Option<AgentMessage>,
     pub pending_tool_calls: Vec<String>,
     pub error: Option<String>,
+    /// Totals over every run since the agent was created or last restored.
+    pub metrics: AgentRunMetrics,
+}
+
+/// Conversation state saved by [`Agent::checkpoint`].
+#[derive(Clone)]
+struct AgentCheckpoint {
+    id: u64,
+    messages: Vec<AgentMessage>,
+    steering_queue: VecDeque<AgentMessage>,
+    follow_up_queue: VecDeque<AgentMessage>,
+    metrics: AgentRunMetrics,
 }
 
 struct AgentInner {
@@ -89,7 +114,24 @@ struct AgentInner {
     steering_mode: QueueMode,
     follow_up_mode: QueueMode,
     retry: AgentRetryConfig,
+    tool_parallelism: usize,
+    tool_validation: Option<ToolValidationOptions>,
     abort_controller: Option<AgentAbortController>,
+    metrics: AgentRunMetrics,
+    checkpoints: Vec<AgentCheckpoint>,
+    next_checkpoint_id: u64,
+    event_subscribers: Vec<EventStream<AgentEvent, ()>>,
+    /// Where finished runs are saved, and under which id.
+    state_store: Option<(Arc<dyn AgentStateStore>, String)>,
+}
+
+impl Drop for AgentInner {
+    /// Ends every subscription once the last [`Agent`] handle is gone.
+    fn drop(&mut self) {
+        for subscriber in &self.event_subscribers {
+            subscriber.end(None);
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -97,6 +139,8 @@ pub struct Agent {
     inner: Arc<Mutex<AgentInner>>,
     convert_to_llm: ConvertToLlmFn,
     stream_fn: StreamFn,
+    hooks: Option<Arc<dyn AgentHooks>>,
+    context_compaction: Option<ContextCompaction>,
     is_running: Arc<AtomicBool>,
     idle_notify: Arc<Notify>,
 }
@@ -118,10 +162,19 @@ impl Agent {
                 steering_mode: config.steering_mode,
                 follow_up_mode: config.follow_up_mode,
                 retry: config.retry,
+                tool_parallelism: config.tool_parallelism,
+                tool_validation: config.tool_validation,
                 abort_controller: None,
+                metrics: AgentRunMetrics::default(),
+                checkpoints: Vec::new(),
+                next_checkpoint_id: 1,
+                event_subscribers: Vec::new(),
+                state_store: None,
             })),
             convert_to_llm: config.convert_to_llm,
             stream_fn: config.stream_fn,
+            hooks: config.hooks,
+            context_compaction: config.context_compaction,
             is_running: Arc::new(AtomicBool::new(false)),
             idle_notify: Arc::new(Notify::new()),
         }
@@ -145,7 +198,91 @@ impl Agent {
             stream_message: inner.stream_message.clone(),
             pending_tool_calls,
             error: inner.error.clone(),
+            metrics: inner.metrics.clone(),
+        }
+    }
+
+    /// Metrics of every run finished so far, without copying the rest of the state.
+    pub fn metrics_snapshot(&self) -> AgentRunMetrics {
+        self.lock_inner().metrics.clone()
+    }
+
+    /// Streams every event of later runs, in order. The stream ends once the agent and all of
+    /// its clones are dropped, and stops being fed when the subscriber drops it.
+    pub fn subscribe(&self) -> EventStream<AgentEvent, ()> {
+        let stream = EventStream::new(|_| None);
+        let mut inner = self.lock_inner();
+        inner.event_subscribers.push(stream.clone());
+        stream
+    }
+
+    /// Snapshots messages, queued messages and metrics, returning an id for [`Self::restore`].
+    /// Taken during a run, the snapshot holds the messages completed so far.
+    pub fn checkpoint(&self) -> u64 {
+        let mut inner = self.lock_inner();
+        let id = inner.next_checkpoint_id;
+        inner.next_checkpoint_id += 1;
+        let checkpoint = AgentCheckpoint {
+            id,
+            messages: inner.messages.clone(),
+            steering_queue: inner.steering_queue.clone(),
+            follow_up_queue: inner.follow_up_queue.clone(),
+            metrics: inner.metrics.clone(),
+        };
+        inner.checkpoints.push(checkpoint);
+        id
+    }
+
+    /// Rewinds messages, queued messages and metrics to a checkpoint. The checkpoint stays
+    /// available, so the same point can be restored again.
+    pub fn restore(&self, checkpoint_id: u64) -> Result<(), String> {
+        if self.is_running.load(Ordering::SeqCst) {
+            return Err(ERR_RESTORE_WHILE_RUNNING.to_string());
         }
+        let mut inner = self.lock_inner();
+        let checkpoint = inner
+            .checkpoints
+            .iter()
+            .find(|checkpoint| checkpoint.id == checkpoint_id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown checkpoint: {checkpoint_id}"))?;
+        inner.messages = checkpoint.messages;
+        inner.steering_queue = checkpoint.steering_queue;
+        inner.follow_up_queue = checkpoint.follow_up_queue;
+        inner.metrics = checkpoint.metrics;
+        inner.error = None;
+        Ok(())
+    }
+
+    /// Saves the messages of every later run to `store` under `id`.
+    pub fn set_state_store(&self, store: Arc<dyn AgentStateStore>, id: impl Into<String>) {
+        let mut inner = self.lock_inner();
+        inner.state_store = Some((store, id.into()));
+    }
+
+    /// Replaces the conversation with the messages saved under the attached store's id and
+    /// returns how many were loaded.
+    pub async fn load_state(&self) -> Result<usize, String> {
+        if self.is_running.load(Ordering::SeqCst) {
+            return Err(ERR_LOAD_WHILE_RUNNING.to_string());
+        }
+        let (store, id) = self
+            .lock_inner()
+            .state_store
+            .clone()
+            .ok_or_else(|| ERR_NO_STATE_STORE.to_string())?;
+        let messages = store.load(&id).await?;
+        let count = messages.len();
+        let mut inner = self.lock_inner();
+        inner.messages = messages;
+        inner.error = None;
+        Ok(count)
+    }
+
+    /// Forgets every checkpoint taken so far.
+    pub fn clear_checkpoints(&self) {
+        let mut inner = self.lock_inner();
+        inner.checkpoints.clear();
     }
 
     pub fn set_system_prompt(&self, system_prompt: String) {
@@ -168,6 +305,11 @@ impl Agent {
         inner.retry = retry;
     }
 
+    pub fn set_tool_parallelism(&self, tool_parallelism: usize) {
+        let mut inner = self.lock_inner();
+        inner.tool_parallelism = tool_parallelism;
+    }
+
     pub fn set_tools(&self, tools: Vec<AgentTool>) {
         let mut inner = self.lock_inner();
         inner.tools = tools;
@@ -218,6 +360,14 @@ impl Agent {
         inner.follow_up_queue.push_back(message);
     }
 
+    /// Queues `message` for the current run or the next one, depending on `priority`.
+    pub fn enqueue(&self, message: AgentMessage, priority: QueuePriority) {
+        match priority {
+            QueuePriority::SteerNow => self.steer(message),
+            QueuePriority::AfterRun => self.follow_up(message),
+        }
+    }
+
     pub fn clear_steering_queue(&self) {
         let mut inner = self.lock_inner();
         inner.steering_queue.clear();
@@ -336,7 +486,7 @@ impl Agent {
             let controller = AgentAbortController::new();
             let signal = controller.signal();
 
-            let (context, model, fallback_models, retry) = {
+            let (context, model, fallback_models, retry, tool_parallelism, tool_validation) = {
                 let mut inner = self.lock_inner();
                 inner.error = None;
                 inner.stream_message = None;
@@ -352,6 +502,8 @@ impl Agent {
                     inner.model.clone(),
                     inner.fallback_models.clone(),
                     inner.retry.clone(),
+                    inner.tool_parallelism,
+                    inner.tool_validation,
                 )
             };
 
@@ -382,8 +534,17 @@ impl Agent {
                 convert_to_llm: self.convert_to_llm.clone(),
                 stream_fn: self.stream_fn.clone(),
                 retry,
+                tool_parallelism,
+                tool_validation,
                 get_steering_messages: Some(get_steering_messages),
                 get_follow_up_messages: Some(get_follow_up_messages),
+                turn_timeout: None,
+                run_timeout: None,
+                tool_policy: None,
+                tool_approval: None,
+                loop_guard: None,
+                hooks: self.hooks.clone(),
+                context_compaction: self.context_compaction.clone(),
             };
 
             let stream = match prompts {
@@ -396,10 +557,18 @@ impl Agent {
                 self.apply_event(event);
             }
 
-            stream
+            let produced = stream
                 .result()
                 .await
-                .ok_or_else(|| ERR_LOOP_WITHOUT_RESULT.to_string())
+                .ok_or_else(|| ERR_LOOP_WITHOUT_RESULT.to_string())?;
+            let state_store = self.lock_inner().state_store.clone();
+            if let Some((store, id)) = state_store {
+                store
+                    .save_turn(&id, &produced)
+                    .await
+                    .map_err(|error| format!("Failed to save agent state: {error}"))?;
+            }
+            Ok(produced)
         }
         .await;
 
@@ -417,6 +586,12 @@ impl Agent {
 
     fn apply_event(&self, event: AgentEvent) {
         let mut inner = self.lock_inner();
+        inner
+            .event_subscribers
+            .retain(|subscriber| subscriber.has_other_handles());
+        for subscriber in &inner.event_subscribers {
+            subscriber.push(event.clone());
+        }
 
         match event {
             AgentEvent::MessageStart { message } => {
@@ -444,13 +619,29 @@ impl Agent {
                     inner.error = Some(error_message);
                 }
             }
+            AgentEvent::Metrics { metrics } => {
+                inner.metrics.merge(&metrics);
+            }
+            AgentEvent::Compacted { .. } => {
+                // The loop retries the failed response, so it leaves the history.
+                if matches!(
+                    inner.messages.last(),
+                    Some(Message::Assistant {
+                        stop_reason: StopReason::Error,
+                        ..
+                    })
+                ) {
+                    inner.messages.pop();
+                }
+            }
             AgentEvent::AgentStart
             | AgentEvent::AgentEnd { .. }
             | AgentEvent::TurnStart
             | AgentEvent::ToolExecutionUpdate { .. }
             | AgentEvent::RetryScheduled { .. }
             | AgentEvent::ModelFallback { .. }
-            | AgentEvent::Metrics { .. } => {}
+            | AgentEvent::TimedOut { .. }
+            | AgentEvent::ApprovalRequest { .. } => {}
         }
     }
 }
@@ -465,13 +656,6 @@ fn recover_lock<'a>(inner: &'a Arc<Mutex<AgentInner>>) -> std::sync::MutexGuard<
     }
 }
 
-fn dequeue_messages(queue: &mut VecDeque<AgentMessage>, mode: QueueMode) -> Vec<AgentMessage> {
-    match mode {
-        QueueMode::All => queue.drain(..).collect(),
-        QueueMode::OneAtATime => queue.pop_front().into_iter().collect(),
-    }
-}
-
 fn now_millis() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -503,6 +687,9 @@ mod tests {
             },
             context_window: 128_000,
             max_tokens: 8_192,
+            deployment: None,
+            api_version: None,
+            compat: None,
         }
     }
 
@@ -0,0 +1,37 @@
+//! Callbacks at fixed points of an agent run.
+//!
+//! [`AgentHooks`] lets embedders observe and adjust a run without changing the loop: inspect the
+//! context before each model request, rewrite tool results (e.g. append lint output after an
+//! edit), log each finished turn, add messages between turns, or answer a failed response with
+//! a recovery message.
+
+use async_trait::async_trait;
+use pixy_ai::ToolCall;
+
+use crate::types::{AgentContext, AgentMessage, AgentToolResult};
+
+/// Every method defaults to doing nothing, so implementations override only what they need.
+#[async_trait]
+pub trait AgentHooks: Send + Sync {
+    /// Runs before each model request, with the context it will be sent.
+    async fn before_turn(&self, _context: &AgentContext) {}
+
+    /// Runs after each tool call, before its result is reported or sent to the model.
+    async fn after_tool(&self, _call: &ToolCall, _result: &mut AgentToolResult, _is_error: bool) {}
+
+    /// Runs when a turn ends normally, with the assistant message and its tool results.
+    async fn after_turn(&self, _message: &AgentMessage, _tool_results: &[AgentMessage]) {}
+
+    /// Runs after a turn when the run continues with another model request. Returned messages
+    /// are sent with that request, after any steering messages.
+    async fn before_next_turn(&self) -> Vec<AgentMessage> {
+        Vec::new()
+    }
+
+    /// Runs when a model response fails. Returning a message continues the run with it as the
+    /// next user message; `None` ends the run with the error. Aborted and timed-out runs end
+    /// without asking.
+    async fn on_error(&self, _message: &AgentMessage) -> Option<AgentMessage> {
+        None
+    }
+}
@@ -1,18 +1,31 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use futures_util::future::join_all;
 use pixy_ai::{
-    AssistantContentBlock, AssistantMessage, AssistantMessageEvent, Context, EventStream, Message,
-    PiAiError, PiAiErrorCode, StopReason, ToolResultContentBlock,
+    duplicate_tool_call_id_error, validate_tool_arguments_with, AssistantContentBlock,
+    AssistantMessage, AssistantMessageEvent, Context, EventStream, Message, PiAiError,
+    PiAiErrorCode, StopReason, ToolCall, ToolResultContentBlock, ToolValidationOptions,
+    UserContent,
 };
 use serde_json::{json, Value};
+use tokio::sync::Mutex as AsyncMutex;
 use tracing::{debug, warn};
 
+use crate::agent_hooks::AgentHooks;
+use crate::context_compaction::is_context_overflow;
+use crate::loop_guard::{LoopTracker, LoopVerdict};
+use crate::tool_approval::{unattended_tool_error, ApprovalDecision, ToolApproval, ToolRisk};
+use crate::tool_policy::ToolPolicy;
+use crate::tool_progress::ToolProgress;
 use crate::types::{
     AgentAbortSignal, AgentContext, AgentEvent, AgentLoopConfig, AgentMessage, AgentRunMetrics,
-    AgentTool, AgentToolResult, MessageQueueFn,
+    AgentTool, AgentToolResult, MessageQueueFn, TimeoutScope, ToolRunMetrics,
 };
 
 const MAX_AUTO_CONTINUATIONS_ON_LENGTH: usize = 6;
+const ABORTED_MESSAGE: &str = "Request was aborted";
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AgentLoopError {
@@ -108,6 +121,8 @@ struct AgentLoopRunner {
     metrics: AgentRunMetrics,
     pending_messages: Vec<AgentMessage>,
     first_assistant_turn: bool,
+    run_limit: Option<TimeLimit>,
+    loop_tracker: LoopTracker,
 }
 
 impl AgentLoopRunner {
@@ -118,6 +133,9 @@ impl AgentLoopRunner {
         stream: EventStream<AgentEvent, Vec<AgentMessage>>,
     ) -> Self {
         let pending_messages = pull_queue_messages(config.get_steering_messages.as_ref());
+        let run_limit = config
+            .run_timeout
+            .map(|timeout| TimeLimit::start(TimeoutScope::Run, timeout));
         Self {
             context,
             config,
@@ -127,6 +145,8 @@ impl AgentLoopRunner {
             metrics: AgentRunMetrics::default(),
             pending_messages,
             first_assistant_turn: true,
+            run_limit,
+            loop_tracker: LoopTracker::default(),
         }
     }
 
@@ -137,13 +157,16 @@ impl AgentLoopRunner {
             let mut has_more_tool_calls = true;
             let mut steering_after_tools: Option<Vec<AgentMessage>> = None;
 
-            while has_more_tool_calls || !self.pending_messages.is_empty() {
+            'turns: while has_more_tool_calls || !self.pending_messages.is_empty() {
                 self.start_turn_if_needed();
                 self.flush_pending_messages();
 
                 if self.end_turn_on_abort() {
                     return;
                 }
+                if let Some(hooks) = &self.config.hooks {
+                    hooks.before_turn(&self.context).await;
+                }
 
                 let assistant_outcome = self.request_assistant_response().await;
                 self.record_assistant_metrics(&assistant_outcome);
@@ -151,10 +174,10 @@ impl AgentLoopRunner {
 
                 self.new_messages.push(assistant_message.clone());
                 if is_error_or_aborted(&assistant_message) {
-                    self.stream.push(AgentEvent::TurnEnd {
-                        message: assistant_message,
-                        tool_results: vec![],
-                    });
+                    if self.end_failed_turn(assistant_message).await {
+                        has_more_tool_calls = false;
+                        continue 'turns;
+                    }
                     self.finish();
                     return;
                 }
@@ -175,10 +198,10 @@ impl AgentLoopRunner {
                     self.new_messages.push(assistant_message.clone());
 
                     if is_error_or_aborted(&assistant_message) {
-                        self.stream.push(AgentEvent::TurnEnd {
-                            message: assistant_message,
-                            tool_results: vec![],
-                        });
+                        if self.end_failed_turn(assistant_message).await {
+                            has_more_tool_calls = false;
+                            continue 'turns;
+                        }
                         self.finish();
                         return;
                     }
@@ -193,15 +216,27 @@ impl AgentLoopRunner {
 
                 let mut tool_results = Vec::new();
                 let mut aborted_during_tools = false;
-                has_more_tool_calls = !extract_tool_calls(&assistant_message).is_empty();
+                let mut loop_warning = None;
+                let tool_calls = extract_tool_calls(&assistant_message);
+                has_more_tool_calls = !tool_calls.is_empty();
 
                 if has_more_tool_calls {
+                    match self.check_tool_loop(&tool_calls) {
+                        LoopVerdict::Continue => {}
+                        LoopVerdict::Warn(message) => loop_warning = Some(message),
+                        LoopVerdict::Stop(error) => {
+                            self.stop_tool_loop(assistant_message, &tool_calls, error);
+                            return;
+                        }
+                    }
+
                     let outcome = execute_tool_calls(
                         &self.context.tools,
                         &assistant_message,
                         &self.stream,
                         self.signal.as_ref(),
-                        self.config.get_steering_messages.as_ref(),
+                        &self.config,
+                        self.run_limit,
                     )
                     .await;
                     self.record_tool_metrics(&outcome);
@@ -211,6 +246,12 @@ impl AgentLoopRunner {
                 }
 
                 self.append_tool_results(&tool_results);
+                if aborted_during_tools {
+                    self.emit_timed_out_if_expired(self.run_limit);
+                }
+                if let Some(hooks) = &self.config.hooks {
+                    hooks.after_turn(&assistant_message, &tool_results).await;
+                }
                 self.stream.push(AgentEvent::TurnEnd {
                     message: assistant_message,
                     tool_results,
@@ -223,6 +264,18 @@ impl AgentLoopRunner {
 
                 self.pending_messages =
                     self.resolve_next_pending_messages(steering_after_tools.take());
+                if let Some(warning) = loop_warning {
+                    self.pending_messages.push(Message::User {
+                        content: UserContent::Text(warning),
+                        timestamp: now_millis(),
+                    });
+                }
+                if has_more_tool_calls || !self.pending_messages.is_empty() {
+                    if let Some(hooks) = &self.config.hooks {
+                        let messages = hooks.before_next_turn().await;
+                        self.pending_messages.extend(messages);
+                    }
+                }
             }
 
             let follow_up_messages =
@@ -236,6 +289,32 @@ impl AgentLoopRunner {
         self.finish();
     }
 
+    /// Ends a turn whose response failed. Returns whether the run goes on, because the hooks
+    /// answered the error with a recovery message, now pending for the next turn.
+    async fn end_failed_turn(&mut self, message: AgentMessage) -> bool {
+        let recovery = match (&self.config.hooks, &message) {
+            (
+                Some(hooks),
+                Message::Assistant {
+                    stop_reason: StopReason::Error,
+                    ..
+                },
+            ) => hooks.on_error(&message).await,
+            _ => None,
+        };
+        self.stream.push(AgentEvent::TurnEnd {
+            message,
+            tool_results: vec![],
+        });
+        match recovery {
+            Some(recovery) => {
+                self.pending_messages.push(recovery);
+                true
+            }
+            None => false,
+        }
+    }
+
     fn initialize(&mut self, prompts: Vec<AgentMessage>) {
         self.new_messages = prompts.clone();
         self.stream.push(AgentEvent::AgentStart);
@@ -274,13 +353,18 @@ impl AgentLoopRunner {
     }
 
     fn end_turn_on_abort(&mut self) -> bool {
-        if !is_aborted(self.signal.as_ref()) {
+        let timed_out = self.run_limit.filter(TimeLimit::expired);
+        if timed_out.is_none() && !is_aborted(self.signal.as_ref()) {
             return false;
         }
 
         let (api, provider, model) = self.primary_model_identity();
-        let message = aborted_assistant_message(api, provider, model);
+        let message = match timed_out {
+            Some(limit) => timed_out_assistant_message(api, provider, model, limit),
+            None => aborted_assistant_message(api, provider, model),
+        };
         self.push_terminal_message(message.clone());
+        self.emit_timed_out_if_expired(timed_out);
         self.stream.push(AgentEvent::TurnEnd {
             message,
             tool_results: vec![],
@@ -289,16 +373,132 @@ impl AgentLoopRunner {
         true
     }
 
+    fn check_tool_loop(&mut self, tool_calls: &[ToolCall]) -> LoopVerdict {
+        match &self.config.loop_guard {
+            Some(guard) => self.loop_tracker.observe(guard, tool_calls),
+            None => LoopVerdict::Continue,
+        }
+    }
+
+    /// Answers the calls of `assistant_message` without running them, then ends the run with
+    /// the loop guard's error.
+    fn stop_tool_loop(
+        &mut self,
+        assistant_message: AgentMessage,
+        tool_calls: &[ToolCall],
+        error: PiAiError,
+    ) {
+        warn!(error = %error.message, "agent loop stopped by loop guard");
+        let tool_results = tool_calls
+            .iter()
+            .map(|call| {
+                skip_tool_call(
+                    &call.id,
+                    &call.name,
+                    &call.arguments,
+                    &self.stream,
+                    "Skipped: the loop guard stopped the run.",
+                )
+            })
+            .collect::<Vec<_>>();
+        self.append_tool_results(&tool_results);
+        self.stream.push(AgentEvent::TurnEnd {
+            message: assistant_message,
+            tool_results,
+        });
+
+        self.stream.push(AgentEvent::TurnStart);
+        let (api, provider, model) = self.primary_model_identity();
+        let message = error_assistant_message(api, provider, model, error.as_compact_json());
+        self.push_terminal_message(message.clone());
+        self.stream.push(AgentEvent::TurnEnd {
+            message,
+            tool_results: vec![],
+        });
+        self.finish();
+    }
+
     async fn request_assistant_response(&mut self) -> AssistantResponseOutcome {
+        let outcome = self.request_assistant_response_once().await;
+        if !is_context_overflow(&outcome.message)
+            || self
+                .compact_after_overflow(&outcome.message)
+                .await
+                .is_none()
+        {
+            return outcome;
+        }
+        let retried = self.request_assistant_response_once().await;
+        AssistantResponseOutcome {
+            message: retried.message,
+            duration_ms: outcome.duration_ms.saturating_add(retried.duration_ms),
+            retries: outcome
+                .retries
+                .saturating_add(retried.retries)
+                .saturating_add(1),
+        }
+    }
+
+    /// Drops the failed `response` and compacts the context for one more attempt. Returns the
+    /// tokens saved, or `None` when compaction is off, fails or finds nothing to remove.
+    async fn compact_after_overflow(&mut self, response: &AgentMessage) -> Option<u64> {
+        let compaction = self.config.context_compaction.as_ref()?;
+        let mut messages = self.context.messages.clone();
+        if messages.last() == Some(response) {
+            messages.pop();
+        }
+        let context = Context {
+            system_prompt: Some(self.context.system_prompt.clone()),
+            messages,
+            tools: llm_tools(&self.context.tools),
+            system_prompt_cache: None,
+            branch: None,
+        };
+        let (compacted, dropped_tokens) =
+            match compaction.compact(&context, &self.config.model).await {
+                Ok(result) => result,
+                Err(error) => {
+                    warn!(error = error.message.as_str(), "context compaction failed");
+                    return None;
+                }
+            };
+        if dropped_tokens == 0 {
+            return None;
+        }
+
+        debug!(
+            dropped_tokens,
+            "compacted context after overflow; retrying the request"
+        );
+        self.context.messages = compacted.messages;
+        if self.new_messages.last() == Some(response) {
+            self.new_messages.pop();
+        }
+        self.stream.push(AgentEvent::Compacted { dropped_tokens });
+        Some(dropped_tokens)
+    }
+
+    async fn request_assistant_response_once(&mut self) -> AssistantResponseOutcome {
+        let turn_limit = self
+            .config
+            .turn_timeout
+            .map(|timeout| TimeLimit::start(TimeoutScope::Turn, timeout));
+        let limit = TimeLimit::earliest(turn_limit, self.run_limit);
         match stream_assistant_response(
             &mut self.context,
             &self.config,
             &self.stream,
             self.signal.as_ref(),
+            limit,
         )
         .await
         {
-            Ok(outcome) => outcome,
+            Ok(outcome) => {
+                if is_error_or_aborted(&outcome.message) {
+                    self.emit_timed_out_if_expired(limit);
+                }
+                outcome
+            }
             Err(error) => {
                 let (api, provider, model) = self.primary_model_identity();
                 let message =
@@ -313,6 +513,21 @@ impl AgentLoopRunner {
         }
     }
 
+    fn emit_timed_out_if_expired(&self, limit: Option<TimeLimit>) {
+        let Some(limit) = limit.filter(TimeLimit::expired) else {
+            return;
+        };
+        warn!(
+            scope = limit.scope.as_str(),
+            timeout_ms = limit.timeout_ms(),
+            "agent loop timed out"
+        );
+        self.stream.push(AgentEvent::TimedOut {
+            scope: limit.scope,
+            timeout_ms: limit.timeout_ms(),
+        });
+    }
+
     fn record_assistant_metrics(&mut self, outcome: &AssistantResponseOutcome) {
         self.metrics.assistant_request_count =
             self.metrics.assistant_request_count.saturating_add(1);
@@ -321,6 +536,9 @@ impl AgentLoopRunner {
             .assistant_request_total_ms
             .saturating_add(outcome.duration_ms);
         self.metrics.retry_count = self.metrics.retry_count.saturating_add(outcome.retries);
+        if let Message::Assistant { usage, .. } = &outcome.message {
+            self.metrics.turn_tokens.push(usage.total_tokens);
+        }
     }
 
     fn record_tool_metrics(&mut self, outcome: &ToolExecutionOutcome) {
@@ -332,6 +550,13 @@ impl AgentLoopRunner {
             .metrics
             .tool_execution_total_ms
             .saturating_add(outcome.executed_total_duration_ms);
+        for (name, tool) in &outcome.tool_metrics {
+            self.metrics
+                .tools
+                .entry(name.clone())
+                .or_default()
+                .merge(tool);
+        }
     }
 
     fn append_tool_results(&mut self, tool_results: &[AgentMessage]) {
@@ -391,8 +616,9 @@ async fn stream_assistant_response(
     config: &AgentLoopConfig,
     stream: &EventStream<AgentEvent, Vec<AgentMessage>>,
     signal: Option<&AgentAbortSignal>,
+    limit: Option<TimeLimit>,
 ) -> Result<AssistantResponseOutcome, PiAiError> {
-    AssistantRequestRunner::new(context, config, stream, signal)
+    AssistantRequestRunner::new(context, config, stream, signal, limit)
         .run()
         .await
 }
@@ -402,6 +628,7 @@ struct AssistantRequestRunner<'a> {
     config: &'a AgentLoopConfig,
     stream: &'a EventStream<AgentEvent, Vec<AgentMessage>>,
     signal: Option<&'a AgentAbortSignal>,
+    limit: Option<TimeLimit>,
     models: Vec<pixy_ai::Model>,
     max_attempts: usize,
     started_at: Instant,
@@ -413,12 +640,14 @@ impl<'a> AssistantRequestRunner<'a> {
         config: &'a AgentLoopConfig,
         stream: &'a EventStream<AgentEvent, Vec<AgentMessage>>,
         signal: Option<&'a AgentAbortSignal>,
+        limit: Option<TimeLimit>,
     ) -> Self {
         Self {
             context,
             config,
             stream,
             signal,
+            limit,
             models: attempt_models(config),
             max_attempts: config.retry.max_attempts.max(1),
             started_at: Instant::now(),
@@ -434,6 +663,7 @@ impl<'a> AssistantRequestRunner<'a> {
                 self.config,
                 self.stream,
                 self.signal,
+                self.limit,
                 &active_model,
             )
             .await
@@ -560,26 +790,24 @@ impl<'a> AssistantRequestRunner<'a> {
             return None;
         }
 
-        if let Some(signal_ref) = self.signal {
-            tokio::select! {
-                _ = signal_ref.cancelled() => {
-                    return Some(AssistantResponseOutcome {
-                        message: aborted_assistant_message(
-                            active_model.api.clone(),
-                            active_model.provider.clone(),
-                            active_model.id.clone(),
-                        ),
-                        duration_ms: self.elapsed_ms(),
-                        retries: attempt.saturating_sub(1),
-                    });
-                }
-                _ = tokio::time::sleep(Duration::from_millis(delay_ms)) => {}
+        let (api, provider, model) = (
+            active_model.api.clone(),
+            active_model.provider.clone(),
+            active_model.id.clone(),
+        );
+        let message = tokio::select! {
+            _ = abort_requested(self.signal) => aborted_assistant_message(api, provider, model),
+            _ = deadline_reached(self.limit) => {
+                let limit = self.limit.expect("deadline reached without a limit");
+                timed_out_assistant_message(api, provider, model, limit)
             }
-        } else {
-            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
-        }
-
-        None
+            _ = tokio::time::sleep(Duration::from_millis(delay_ms)) => return None,
+        };
+        Some(AssistantResponseOutcome {
+            message,
+            duration_ms: self.elapsed_ms(),
+            retries: attempt.saturating_sub(1),
+        })
     }
 
     fn elapsed_ms(&self) -> u64 {
@@ -592,6 +820,7 @@ async fn stream_assistant_response_once(
     config: &AgentLoopConfig,
     stream: &EventStream<AgentEvent, Vec<AgentMessage>>,
     signal: Option<&AgentAbortSignal>,
+    limit: Option<TimeLimit>,
     model: &pixy_ai::Model,
 ) -> Result<AgentMessage, PiAiError> {
     let llm_context = build_llm_context(context, config);
@@ -603,17 +832,19 @@ async fn stream_assistant_response_once(
     let mut state = AssistantStreamState::default();
 
     loop {
-        let next_event = if let Some(signal_ref) = signal {
-            tokio::select! {
-                _ = signal_ref.cancelled() => None,
-                event = response.next() => event,
-            }
-        } else {
-            response.next().await
+        let next_event = tokio::select! {
+            _ = abort_requested(signal) => None,
+            _ = deadline_reached(limit) => None,
+            event = response.next() => event,
         };
 
-        if next_event.is_none() && is_aborted(signal) {
-            return Ok(state.finalize_aborted(context, stream, model));
+        if next_event.is_none() {
+            if let Some(limit) = limit.filter(TimeLimit::expired) {
+                return Ok(state.finalize_timed_out(context, stream, model, limit));
+            }
+            if is_aborted(signal) {
+                return Ok(state.finalize_aborted(context, stream, model));
+            }
         }
 
         let Some(event) = next_event else {
@@ -632,7 +863,8 @@ async fn stream_assistant_response_once(
             | AssistantMessageEvent::ThinkingEnd { partial, .. }
             | AssistantMessageEvent::ToolcallStart { partial, .. }
             | AssistantMessageEvent::ToolcallDelta { partial, .. }
-            | AssistantMessageEvent::ToolcallEnd { partial, .. } => {
+            | AssistantMessageEvent::ToolcallEnd { partial, .. }
+            | AssistantMessageEvent::UsageDelta { partial, .. } => {
                 state.handle_update(context, stream, event.clone(), partial.clone());
             }
             AssistantMessageEvent::Done { message, .. } => {
@@ -655,22 +887,21 @@ async fn stream_assistant_response_once(
 
 fn build_llm_context(context: &AgentContext, config: &AgentLoopConfig) -> Context {
     let llm_messages = config.convert_to_llm.convert(context.messages.clone());
-    let llm_tools = if context.tools.is_empty() {
-        None
-    } else {
-        Some(
-            context
-                .tools
-                .iter()
-                .map(AgentTool::to_llm_tool)
-                .collect::<Vec<_>>(),
-        )
-    };
 
     Context {
         system_prompt: Some(context.system_prompt.clone()),
         messages: llm_messages,
-        tools: llm_tools,
+        tools: llm_tools(&context.tools),
+        system_prompt_cache: None,
+        branch: None,
+    }
+}
+
+fn llm_tools(tools: &[AgentTool]) -> Option<Vec<pixy_ai::Tool>> {
+    if tools.is_empty() {
+        None
+    } else {
+        Some(tools.iter().map(AgentTool::to_llm_tool).collect())
     }
 }
 
@@ -752,10 +983,28 @@ impl AssistantStreamState {
             model.api.clone(),
             model.provider.clone(),
             model.id.clone(),
+            ABORTED_MESSAGE.to_string(),
         );
         self.finalize_message(context, stream, aborted)
     }
 
+    fn finalize_timed_out(
+        &mut self,
+        context: &mut AgentContext,
+        stream: &EventStream<AgentEvent, Vec<AgentMessage>>,
+        model: &pixy_ai::Model,
+        limit: TimeLimit,
+    ) -> AgentMessage {
+        let timed_out = to_aborted_message(
+            self.last_message.clone(),
+            model.api.clone(),
+            model.provider.clone(),
+            model.id.clone(),
+            limit.message(),
+        );
+        self.finalize_message(context, stream, timed_out)
+    }
+
     fn last_message_or_error(self) -> Result<AgentMessage, PiAiError> {
         self.last_message.ok_or_else(|| {
             PiAiError::new(
@@ -766,12 +1015,20 @@ impl AssistantStreamState {
     }
 }
 
+enum ToolGate {
+    Run,
+    Refused(PiAiError),
+    DryRun(AgentToolResult),
+    ApprovalRequired(ToolRisk),
+}
+
 struct ToolExecutionOutcome {
     tool_results: Vec<AgentMessage>,
     steering_messages: Option<Vec<AgentMessage>>,
     aborted: bool,
     executed_count: usize,
     executed_total_duration_ms: u64,
+    tool_metrics: BTreeMap<String, ToolRunMetrics>,
 }
 
 async fn execute_tool_calls(
@@ -779,30 +1036,32 @@ async fn execute_tool_calls(
     assistant_message: &AgentMessage,
     stream: &EventStream<AgentEvent, Vec<AgentMessage>>,
     signal: Option<&AgentAbortSignal>,
-    get_steering_messages: Option<&MessageQueueFn>,
+    config: &AgentLoopConfig,
+    run_limit: Option<TimeLimit>,
 ) -> ToolExecutionOutcome {
-    ToolExecutionRunner::new(
-        tools,
-        assistant_message,
-        stream,
-        signal,
-        get_steering_messages,
-    )
-    .run()
-    .await
+    ToolExecutionRunner::new(tools, assistant_message, stream, signal, config, run_limit)
+        .run()
+        .await
 }
 
 struct ToolExecutionRunner<'a> {
     tools: &'a [AgentTool],
     stream: &'a EventStream<AgentEvent, Vec<AgentMessage>>,
     signal: Option<&'a AgentAbortSignal>,
+    run_limit: Option<TimeLimit>,
     get_steering_messages: Option<&'a MessageQueueFn>,
-    tool_calls: Vec<(String, String, Value)>,
+    parallelism: usize,
+    validation: Option<ToolValidationOptions>,
+    policy: Option<&'a ToolPolicy>,
+    approval: Option<&'a ToolApproval>,
+    hooks: Option<&'a Arc<dyn AgentHooks>>,
+    tool_calls: Vec<ToolCall>,
     results: Vec<AgentMessage>,
     steering_messages: Option<Vec<AgentMessage>>,
     aborted: bool,
     executed_count: usize,
     executed_total_duration_ms: u64,
+    tool_metrics: BTreeMap<String, ToolRunMetrics>,
 }
 
 impl<'a> ToolExecutionRunner<'a> {
@@ -811,84 +1070,65 @@ impl<'a> ToolExecutionRunner<'a> {
         assistant_message: &'a AgentMessage,
         stream: &'a EventStream<AgentEvent, Vec<AgentMessage>>,
         signal: Option<&'a AgentAbortSignal>,
-        get_steering_messages: Option<&'a MessageQueueFn>,
+        config: &'a AgentLoopConfig,
+        run_limit: Option<TimeLimit>,
     ) -> Self {
         Self {
             tools,
             stream,
             signal,
-            get_steering_messages,
+            run_limit,
+            get_steering_messages: config.get_steering_messages.as_ref(),
+            parallelism: config.tool_parallelism,
+            validation: config.tool_validation,
+            policy: config.tool_policy.as_ref(),
+            approval: config.tool_approval.as_ref(),
+            hooks: config.hooks.as_ref(),
             tool_calls: extract_tool_calls(assistant_message),
             results: Vec::new(),
             steering_messages: None,
             aborted: false,
             executed_count: 0,
             executed_total_duration_ms: 0,
+            tool_metrics: BTreeMap::new(),
         }
     }
 
     async fn run(mut self) -> ToolExecutionOutcome {
-        for index in 0..self.tool_calls.len() {
-            if self.is_aborted() {
-                self.skip_remaining_calls(index, "Skipped due to abort signal.");
-                self.aborted = true;
+        let batch_size = self.parallelism.max(1);
+        let mut index = 0;
+        while index < self.tool_calls.len() {
+            if self.stop_on_abort(index) {
                 break;
             }
 
-            let (tool_call_id, tool_name, args) = self.tool_calls[index].clone();
-            self.stream.push(AgentEvent::ToolExecutionStart {
-                tool_call_id: tool_call_id.clone(),
-                tool_name: tool_name.clone(),
-                args: args.clone(),
-            });
-
-            let tool_execution_started = Instant::now();
-            let (result, is_error) = self
-                .execute_single_call(&tool_call_id, &tool_name, args.clone())
-                .await;
-            let duration_ms = tool_execution_started.elapsed().as_millis() as u64;
-            self.executed_count = self.executed_count.saturating_add(1);
-            self.executed_total_duration_ms =
-                self.executed_total_duration_ms.saturating_add(duration_ms);
-            debug!(
-                tool_call_id = tool_call_id.as_str(),
-                tool_name = tool_name.as_str(),
-                duration_ms,
-                is_error,
-                "tool execution finished"
-            );
-
-            self.stream.push(AgentEvent::ToolExecutionEnd {
-                tool_call_id: tool_call_id.clone(),
-                tool_name: tool_name.clone(),
-                result: result.clone(),
-                is_error,
-                duration_ms,
-            });
+            let batch_end = (index + batch_size).min(self.tool_calls.len());
+            let batch = self.tool_calls[index..batch_end].to_vec();
+            for tool_call in &batch {
+                self.stream.push(AgentEvent::ToolExecutionStart {
+                    tool_call_id: tool_call.id.clone(),
+                    tool_name: tool_call.name.clone(),
+                    args: tool_call.arguments.clone(),
+                });
+            }
 
-            let message = Message::ToolResult {
-                tool_call_id,
-                tool_name,
-                content: result.content.clone(),
-                details: Some(result.details.clone()),
-                is_error,
-                timestamp: now_millis(),
-            };
-            self.stream.push(AgentEvent::MessageStart {
-                message: message.clone(),
-            });
-            self.stream.push(AgentEvent::MessageEnd {
-                message: message.clone(),
-            });
-            self.results.push(message);
+            let locks = self.conflict_locks(&batch);
+            let executions = join_all(batch.iter().zip(locks).enumerate().map(
+                |(offset, (tool_call, locks))| {
+                    self.execute_timed_call(index + offset, tool_call, locks)
+                },
+            ))
+            .await;
+            for (tool_call, (result, is_error, duration_ms)) in batch.into_iter().zip(executions) {
+                self.record_call_result(tool_call, result, is_error, duration_ms);
+            }
+            index = batch_end;
 
-            if self.is_aborted() {
-                self.skip_remaining_calls(index + 1, "Skipped due to abort signal.");
-                self.aborted = true;
+            if self.stop_on_abort(index) {
                 break;
             }
 
-            if self.stop_on_steering(index + 1) {
+            if self.stop_on_steering(index) {
                 break;
             }
         }
@@ -899,11 +1139,130 @@ impl<'a> ToolExecutionRunner<'a> {
             aborted: self.aborted,
             executed_count: self.executed_count,
             executed_total_duration_ms: self.executed_total_duration_ms,
+            tool_metrics: self.tool_metrics,
         }
     }
 
-    fn is_aborted(&self) -> bool {
-        is_aborted(self.signal)
+    /// One lock per conflict key in `batch`, and for each call the locks of its keys in key
+    /// order. Calls queue on them in the order the model issued them, so calls touching the
+    /// same resource run one after the other while the rest of the batch runs in parallel;
+    /// taking several locks in key order cannot deadlock.
+    fn conflict_locks(&self, batch: &[ToolCall]) -> Vec<Vec<Arc<AsyncMutex<()>>>> {
+        let mut locks = HashMap::<String, Arc<AsyncMutex<()>>>::new();
+        batch
+            .iter()
+            .map(|tool_call| {
+                let Some(conflict_key) = self
+                    .tools
+                    .iter()
+                    .find(|tool| tool.name == tool_call.name)
+                    .and_then(|tool| tool.conflict_key.as_ref())
+                else {
+                    return Vec::new();
+                };
+                let mut keys = conflict_key(&tool_call.arguments);
+                keys.sort();
+                keys.dedup();
+                keys.into_iter()
+                    .map(|key| locks.entry(key).or_default().clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    async fn execute_timed_call(
+        &self,
+        index: usize,
+        tool_call: &ToolCall,
+        locks: Vec<Arc<AsyncMutex<()>>>,
+    ) -> (AgentToolResult, bool, u64) {
+        let mut _guards = Vec::with_capacity(locks.len());
+        for lock in &locks {
+            _guards.push(lock.lock().await);
+        }
+        let tool_execution_started = Instant::now();
+        let (mut result, is_error) =
+            match duplicate_tool_call_id_error(&self.tool_calls[..index], tool_call) {
+                Some(error) => (tool_error_result(error), true),
+                None => {
+                    self.execute_single_call(
+                        &tool_call.id,
+                        &tool_call.name,
+                        tool_call.arguments.clone(),
+                    )
+                    .await
+                }
+            };
+        let duration_ms = tool_execution_started.elapsed().as_millis() as u64;
+        if let Some(hooks) = self.hooks {
+            hooks.after_tool(tool_call, &mut result, is_error).await;
+        }
+        (result, is_error, duration_ms)
+    }
+
+    fn record_call_result(
+        &mut self,
+        tool_call: ToolCall,
+        result: AgentToolResult,
+        is_error: bool,
+        duration_ms: u64,
+    ) {
+        let ToolCall {
+            id: tool_call_id,
+            name: tool_name,
+            ..
+        } = tool_call;
+        self.executed_count = self.executed_count.saturating_add(1);
+        self.executed_total_duration_ms =
+            self.executed_total_duration_ms.saturating_add(duration_ms);
+        self.tool_metrics
+            .entry(tool_name.clone())
+            .or_default()
+            .record(duration_ms, is_error);
+        debug!(
+            tool_call_id = tool_call_id.as_str(),
+            tool_name = tool_name.as_str(),
+            duration_ms,
+            is_error,
+            "tool execution finished"
+        );
+
+        self.stream.push(AgentEvent::ToolExecutionEnd {
+            tool_call_id: tool_call_id.clone(),
+            tool_name: tool_name.clone(),
+            result: result.clone(),
+            is_error,
+            duration_ms,
+        });
+
+        let message = Message::ToolResult {
+            tool_call_id,
+            tool_name,
+            content: result.content.clone(),
+            details: Some(result.details.clone()),
+            is_error,
+            timestamp: now_millis(),
+        };
+        self.stream.push(AgentEvent::MessageStart {
+            message: message.clone(),
+        });
+        self.stream.push(AgentEvent::MessageEnd {
+            message: message.clone(),
+        });
+        self.results.push(message);
+    }
+
+    fn stop_on_abort(&mut self, next_index: usize) -> bool {
+        let reason = if self.run_limit.is_some_and(|limit| limit.expired()) {
+            "Skipped due to run timeout."
+        } else if is_aborted(self.signal) {
+            "Skipped due to abort signal."
+        } else {
+            return false;
+        };
+        self.skip_remaining_calls(next_index, reason);
+        self.aborted = true;
+        true
     }
 
     async fn execute_single_call(
@@ -914,17 +1273,69 @@ impl<'a> ToolExecutionRunner<'a> {
     ) -> (AgentToolResult, bool) {
         let tool = self.tools.iter().find(|tool| tool.name == tool_name);
         if let Some(tool) = tool {
-            let execute_future = tool.execute.execute(tool_call_id.to_string(), args);
-            let execution = if let Some(signal_ref) = self.signal {
-                tokio::select! {
-                    _ = signal_ref.cancelled() => Err(tool_execution_aborted_error()),
-                    result = execute_future => result,
+            let args = match self.validation {
+                Some(options) => {
+                    let tool_call = ToolCall {
+                        id: tool_call_id.to_string(),
+                        name: tool_name.to_string(),
+                        arguments: args,
+                    };
+                    match validate_tool_arguments_with(&tool.to_llm_tool(), &tool_call, options) {
+                        Ok(args) => args,
+                        Err(error) => return (tool_error_result(error), true),
+                    }
+                }
+                None => args,
+            };
+            match self.gate(tool_name, &args) {
+                ToolGate::Run => {}
+                ToolGate::Refused(error) => return (tool_error_result(error), true),
+                ToolGate::DryRun(result) => return (result, false),
+                ToolGate::ApprovalRequired(risk) => {
+                    let call = ToolCall {
+                        id: tool_call_id.to_string(),
+                        name: tool_name.to_string(),
+                        arguments: args.clone(),
+                    };
+                    if let Err(error) = self.wait_for_approval(call, risk).await {
+                        return (tool_error_result(error), true);
+                    }
+                }
+            }
+            let stream = self.stream.clone();
+            let (progress_call_id, progress_tool_name, progress_args) = (
+                tool_call_id.to_string(),
+                tool_name.to_string(),
+                args.clone(),
+            );
+            let progress = ToolProgress::new(move |partial_result| {
+                stream.push(AgentEvent::ToolExecutionUpdate {
+                    tool_call_id: progress_call_id.clone(),
+                    tool_name: progress_tool_name.clone(),
+                    args: progress_args.clone(),
+                    partial_result,
+                });
+            });
+            let execute_future =
+                progress.scope(tool.execute.execute(tool_call_id.to_string(), args));
+            // Tools that start child runs derive their abort signals from the current one.
+            let signal = self.signal.cloned();
+            let execute_future = async move {
+                match signal {
+                    Some(signal) => signal.scope(execute_future).await,
+                    None => execute_future.await,
                 }
-            } else {
-                execute_future.await
+            };
+            let execution = tokio::select! {
+                _ = hard_abort_requested(self.signal) => Err(tool_execution_aborted_error()),
+                _ = deadline_reached(self.run_limit) => Err(tool_execution_timed_out_error()),
+                result = execute_future => result,
             };
             return match execution {
-                Ok(result) => (result, false),
+                Ok(result) => match self.policy {
+                    Some(policy) => (policy.limit_output(result), false),
+                    None => (result, false),
+                },
                 Err(error) => (tool_error_result(error), true),
             };
         }
@@ -932,6 +1343,49 @@ impl<'a> ToolExecutionRunner<'a> {
         (tool_error_result(tool_not_found_error(tool_name)), true)
     }
 
+    /// What happens to a validated call before it runs: policy refusals and dry runs answer
+    /// it right away, flagged tools wait for approval.
+    fn gate(&self, tool_name: &str, args: &Value) -> ToolGate {
+        if let Some(policy) = self.policy {
+            if let Err(error) = policy.check(tool_name, args) {
+                return ToolGate::Refused(error);
+            }
+            if policy.dry_run {
+                return ToolGate::DryRun(policy.dry_run_result(tool_name, args));
+            }
+        }
+        let Some(approval) = self.approval else {
+            return ToolGate::Run;
+        };
+        match approval.risk(tool_name) {
+            Some(_) if approval.approver.is_none() => {
+                ToolGate::Refused(unattended_tool_error(tool_name))
+            }
+            Some(risk) => ToolGate::ApprovalRequired(risk),
+            None => ToolGate::Run,
+        }
+    }
+
+    async fn wait_for_approval(&self, call: ToolCall, risk: ToolRisk) -> Result<(), PiAiError> {
+        let Some(approval) = self.approval else {
+            return Ok(());
+        };
+        self.stream.push(AgentEvent::ApprovalRequest {
+            call: call.clone(),
+            risk,
+        });
+        let tool_name = call.name.clone();
+        let decision = tokio::select! {
+            _ = hard_abort_requested(self.signal) => return Err(tool_execution_aborted_error()),
+            _ = deadline_reached(self.run_limit) => return Err(tool_execution_timed_out_error()),
+            decision = approval.request(call, risk) => decision,
+        };
+        match decision {
+            ApprovalDecision::Approve => Ok(()),
+            ApprovalDecision::Deny { reason } => Err(tool_call_denied_error(&tool_name, reason)),
+        }
+    }
+
     fn stop_on_steering(&mut self, next_index: usize) -> bool {
         let Some(queue) = self.get_steering_messages else {
             return false;
@@ -948,41 +1402,25 @@ impl<'a> ToolExecutionRunner<'a> {
     }
 
     fn skip_remaining_calls(&mut self, start_index: usize, reason: &str) {
-        for (id, name, args) in self.tool_calls.iter().skip(start_index) {
-            self.results
-                .push(skip_tool_call(id, name, args, self.stream, reason));
+        for tool_call in self.tool_calls.iter().skip(start_index) {
+            self.results.push(skip_tool_call(
+                &tool_call.id,
+                &tool_call.name,
+                &tool_call.arguments,
+                self.stream,
+                reason,
+            ));
         }
     }
 }
 
-fn extract_tool_calls(message: &AgentMessage) -> Vec<(String, String, Value)> {
+fn extract_tool_calls(message: &AgentMessage) -> Vec<ToolCall> {
     match message {
         Message::Assistant {
             content,
             stop_reason,
             ..
-        } => {
-            if stop_reason != &StopReason::ToolUse {
-                return Vec::new();
-            }
-
-            content
-                .iter()
-                .filter_map(|block| {
-                    if let AssistantContentBlock::ToolCall {
-                        id,
-                        name,
-                        arguments,
-                        ..
-                    } = block
-                    {
-                        Some((id.clone(), name.clone(), arguments.clone()))
-                    } else {
-                        None
-                    }
-                })
-                .collect()
-        }
+        } if stop_reason == &StopReason::ToolUse => ToolCall::from_content(content),
         _ => vec![],
     }
 }
@@ -1055,6 +1493,78 @@ fn is_aborted(signal: Option<&AgentAbortSignal>) -> bool {
     signal.map(|signal| signal.is_aborted()).unwrap_or(false)
 }
 
+async fn abort_requested(signal: Option<&AgentAbortSignal>) {
+    match signal {
+        Some(signal) => signal.cancelled().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn hard_abort_requested(signal: Option<&AgentAbortSignal>) {
+    match signal {
+        Some(signal) => signal.hard_cancelled().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn deadline_reached(limit: Option<TimeLimit>) {
+    match limit {
+        Some(limit) => tokio::time::sleep_until(limit.deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// A running wall-clock limit from [`AgentLoopConfig::turn_timeout`] or
+/// [`AgentLoopConfig::run_timeout`].
+#[derive(Clone, Copy)]
+struct TimeLimit {
+    scope: TimeoutScope,
+    timeout: Duration,
+    deadline: tokio::time::Instant,
+}
+
+impl TimeLimit {
+    fn start(scope: TimeoutScope, timeout: Duration) -> Self {
+        Self {
+            scope,
+            timeout,
+            deadline: tokio::time::Instant::now() + timeout,
+        }
+    }
+
+    fn earliest(left: Option<Self>, right: Option<Self>) -> Option<Self> {
+        match (left, right) {
+            (Some(left), Some(right)) if right.deadline < left.deadline => Some(right),
+            (Some(left), _) => Some(left),
+            (None, right) => right,
+        }
+    }
+
+    fn expired(&self) -> bool {
+        tokio::time::Instant::now() >= self.deadline
+    }
+
+    fn timeout_ms(&self) -> u64 {
+        self.timeout.as_millis() as u64
+    }
+
+    fn message(&self) -> String {
+        let label = match self.scope {
+            TimeoutScope::Turn => "Turn",
+            TimeoutScope::Run => "Run",
+        };
+        format!("{label} timed out after {}", format_timeout(self.timeout))
+    }
+}
+
+fn format_timeout(timeout: Duration) -> String {
+    if timeout.subsec_millis() == 0 {
+        format!("{}s", timeout.as_secs())
+    } else {
+        format!("{}ms", timeout.as_millis())
+    }
+}
+
 fn attempt_models(config: &AgentLoopConfig) -> Vec<pixy_ai::Model> {
     let mut models = vec![config.model.clone()];
     for fallback in &config.fallback_models {
@@ -1093,6 +1603,7 @@ fn to_agent_assistant_message(message: AssistantMessage) -> AgentMessage {
         stop_reason: message.stop_reason,
         error_message: message.error_message,
         timestamp: message.timestamp,
+        stats: message.stats,
     }
 }
 
@@ -1101,6 +1612,7 @@ fn to_aborted_message(
     api: String,
     provider: String,
     model: String,
+    error_message: String,
 ) -> AgentMessage {
     if let Some(Message::Assistant {
         content,
@@ -1119,12 +1631,19 @@ fn to_aborted_message(
             model,
             usage,
             stop_reason: StopReason::Aborted,
-            error_message: Some("Request was aborted".to_string()),
+            error_message: Some(error_message),
             timestamp,
+            stats: None,
         };
     }
 
-    aborted_assistant_message(api, provider, model)
+    status_assistant_message(
+        api,
+        provider,
+        model,
+        StopReason::Aborted,
+        Some(error_message),
+    )
 }
 
 fn error_assistant_message(
@@ -1142,7 +1661,22 @@ fn aborted_assistant_message(api: String, provider: String, model: String) -> Ag
         provider,
         model,
         StopReason::Aborted,
-        Some("Request was aborted".to_string()),
+        Some(ABORTED_MESSAGE.to_string()),
+    )
+}
+
+fn timed_out_assistant_message(
+    api: String,
+    provider: String,
+    model: String,
+    limit: TimeLimit,
+) -> AgentMessage {
+    status_assistant_message(
+        api,
+        provider,
+        model,
+        StopReason::Aborted,
+        Some(limit.message()),
     )
 }
 
@@ -1165,6 +1699,7 @@ fn status_assistant_message(
         stop_reason,
         error_message,
         timestamp: now_millis(),
+        stats: None,
     }
 }
 
@@ -1174,6 +1709,7 @@ fn zero_usage() -> pixy_ai::Usage {
         output: 0,
         cache_read: 0,
         cache_write: 0,
+        reasoning: 0,
         total_tokens: 0,
         cost: pixy_ai::Cost {
             input: 0.0,
@@ -1201,6 +1737,24 @@ fn tool_execution_aborted_error() -> PiAiError {
     PiAiError::new(PiAiErrorCode::ToolExecutionFailed, "Tool execution aborted")
 }
 
+fn tool_execution_timed_out_error() -> PiAiError {
+    PiAiError::new(
+        PiAiErrorCode::ToolExecutionFailed,
+        "Tool execution stopped: run timed out",
+    )
+}
+
+fn tool_call_denied_error(tool_name: &str, reason: Option<String>) -> PiAiError {
+    let message = match reason.filter(|reason| !reason.trim().is_empty()) {
+        Some(reason) => format!(
+            "The user denied the {tool_name} call; it was not run. Reason: {}",
+            reason.trim()
+        ),
+        None => format!("The user denied the {tool_name} call; it was not run."),
+    };
+    PiAiError::new(PiAiErrorCode::ToolPolicyDenied, message)
+}
+
 fn tool_not_found_error(tool_name: &str) -> PiAiError {
     PiAiError::new(
         PiAiErrorCode::ToolNotFound,
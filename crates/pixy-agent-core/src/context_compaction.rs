@@ -0,0 +1,78 @@
+//! Recovery from responses that fail because the context no longer fits the model.
+//!
+//! With a [`ContextCompaction`] configured, a context-window error does not end the run: the loop
+//! compresses its working context with the configured strategies, reports the saving as
+//! [`crate::AgentEvent::Compacted`] and sends the request once more.
+
+use pixy_ai::{
+    is_context_overflow_error_text, CompressionStrategy, Context, Message, Model, PiAiError,
+    PiAiErrorCode, StopReason, TokenBudget,
+};
+
+use crate::types::AgentMessage;
+
+#[derive(Debug, Clone)]
+pub struct ContextCompaction {
+    /// Applied in order, each to the result of the one before, until the context fits.
+    pub strategies: Vec<CompressionStrategy>,
+    /// Prompt tokens the compacted context has to fit in; `None` uses half the model's
+    /// context window.
+    pub max_tokens: Option<u64>,
+}
+
+impl Default for ContextCompaction {
+    fn default() -> Self {
+        Self {
+            strategies: vec![
+                CompressionStrategy::DropOldToolResults { keep_recent: 4 },
+                CompressionStrategy::TruncateToBudget,
+            ],
+            max_tokens: None,
+        }
+    }
+}
+
+impl ContextCompaction {
+    /// `context` compressed for `model`, with the number of tokens that saved. The saving is
+    /// zero when the strategies found nothing to remove.
+    pub(crate) async fn compact(
+        &self,
+        context: &Context,
+        model: &Model,
+    ) -> Result<(Context, u64), PiAiError> {
+        let max_tokens = self
+            .max_tokens
+            .unwrap_or(u64::from(model.context_window) / 2);
+        let budget = TokenBudget::for_model(model, max_tokens);
+        let before = budget.tokenizer.context_tokens(context);
+        let mut compacted = context.clone();
+        for strategy in &self.strategies {
+            if budget.tokenizer.context_tokens(&compacted) <= max_tokens {
+                break;
+            }
+            compacted = compacted.compress(strategy, &budget).await?;
+        }
+        let after = budget.tokenizer.context_tokens(&compacted);
+        Ok((compacted, before.saturating_sub(after)))
+    }
+}
+
+/// Whether `message` is a failed response reporting that the request exceeded the model's
+/// context window.
+pub(crate) fn is_context_overflow(message: &AgentMessage) -> bool {
+    let Message::Assistant {
+        stop_reason: StopReason::Error,
+        error_message: Some(error_message),
+        ..
+    } = message
+    else {
+        return false;
+    };
+    match serde_json::from_str::<PiAiError>(error_message) {
+        Ok(error) => {
+            error.code == PiAiErrorCode::ContextTooLarge
+                || is_context_overflow_error_text(&error.message)
+        }
+        Err(_) => is_context_overflow_error_text(error_message),
+    }
+}
@@ -1,15 +1,42 @@
 //! Stateful agent loop built on top of `pi-ai`.
 
 mod agent;
+mod agent_hooks;
 mod agent_loop;
+mod context_compaction;
+mod loop_guard;
+mod queued_messages;
+mod state_store;
+mod tool_approval;
+mod tool_args;
+mod tool_policy;
+mod tool_progress;
 mod types;
 
 pub use agent::{Agent, AgentConfig, AgentState, QueueMode};
+pub use agent_hooks::AgentHooks;
 pub use agent_loop::{agent_loop, agent_loop_continue, try_agent_loop_continue, AgentLoopError};
+pub use context_compaction::ContextCompaction;
+pub use loop_guard::ToolLoopGuard;
+pub use pixy_agent_macros::AgentToolArgs;
+pub use queued_messages::{QueuePriority, QueuedMessages};
+pub use state_store::{AgentStateStore, JsonlStateStore};
+pub use tool_approval::{
+    unattended_tool_error, ApprovalDecision, ToolApproval, ToolApprovalRequest, ToolApproverFn,
+    ToolRisk,
+};
+pub use tool_args::AgentToolArgs;
+pub use tool_policy::{resolve_tool_path, ToolPolicy};
+pub use tool_progress::{ToolOutputKind, ToolOutputStream, ToolProgress};
 pub use types::{
-    AgentAbortController, AgentAbortSignal, AgentContext, AgentEvent, AgentLoopConfig,
+    AbortReason, AgentAbortController, AgentAbortSignal, AgentContext, AgentEvent, AgentLoopConfig,
     AgentMessage, AgentRetryConfig, AgentRunMetrics, AgentTool, AgentToolExecuteFn,
     AgentToolExecutor, AgentToolResult, ConvertToLlmFn, IdentityMessageConverter, MessageConverter,
     MessageQueue, MessageQueueFn, ParentChildRunEvent, ParentChildRunEventSink, StreamExecutor,
-    StreamFn, ToolFuture,
+    StreamFn, TimeoutScope, ToolConflictKeyFn, ToolFuture, ToolRunMetrics,
 };
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde_json;
+}
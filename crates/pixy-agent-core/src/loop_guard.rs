@@ -0,0 +1,95 @@
+//! Protection against runaway tool use within one agent run.
+//!
+//! A [`ToolLoopGuard`] caps how many tool turns a run may take and watches for the model making
+//! the same calls turn after turn. Reaching the repeat limit adds a corrective message to the
+//! conversation; repeating past it, or using up the iterations, stops the run with an error.
+
+use pixy_ai::{PiAiError, PiAiErrorCode, ToolCall};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ToolLoopGuard {
+    /// Most assistant turns with tool calls one run may execute; `None` is unlimited.
+    pub max_tool_iterations: Option<usize>,
+    /// Turns in a row that may make identical calls before the model is told to stop repeating
+    /// itself; one more identical turn ends the run. `None` turns repeat detection off.
+    pub max_identical_turns: Option<usize>,
+}
+
+impl ToolLoopGuard {
+    pub fn is_enabled(&self) -> bool {
+        self.max_tool_iterations.is_some() || self.max_identical_turns.is_some()
+    }
+}
+
+pub(crate) enum LoopVerdict {
+    Continue,
+    /// The calls hit the repeat limit; they still run, followed by this corrective message.
+    Warn(String),
+    Stop(PiAiError),
+}
+
+/// Tool turns seen so far in one run.
+#[derive(Default)]
+pub(crate) struct LoopTracker {
+    iterations: usize,
+    last_calls: Option<Value>,
+    identical_turns: usize,
+}
+
+impl LoopTracker {
+    pub(crate) fn observe(&mut self, guard: &ToolLoopGuard, calls: &[ToolCall]) -> LoopVerdict {
+        self.iterations += 1;
+        if let Some(max) = guard.max_tool_iterations {
+            if self.iterations > max {
+                return LoopVerdict::Stop(
+                    PiAiError::new(
+                        PiAiErrorCode::ToolLoopDetected,
+                        format!("Stopped after {max} tool iterations in one run"),
+                    )
+                    .with_details(json!({ "maxToolIterations": max })),
+                );
+            }
+        }
+
+        let signature = Value::Array(
+            calls
+                .iter()
+                .map(|call| json!([call.name, call.arguments]))
+                .collect(),
+        );
+        if self.last_calls.as_ref() == Some(&signature) {
+            self.identical_turns += 1;
+        } else {
+            self.last_calls = Some(signature);
+            self.identical_turns = 1;
+        }
+
+        let Some(limit) = guard.max_identical_turns else {
+            return LoopVerdict::Continue;
+        };
+        let tools = calls
+            .iter()
+            .map(|call| call.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let turns = self.identical_turns;
+        if turns > limit {
+            LoopVerdict::Stop(
+                PiAiError::new(
+                    PiAiErrorCode::ToolLoopDetected,
+                    format!("Stopped after {turns} identical {tools} calls in a row"),
+                )
+                .with_details(json!({ "tools": tools, "identicalTurns": turns })),
+            )
+        } else if turns == limit {
+            LoopVerdict::Warn(format!(
+                "<loop_guard>You have made the same {tools} call with the same arguments {turns} times in a row. Do not repeat it: use the results you already have, try a different approach, or explain what is blocking you.</loop_guard>"
+            ))
+        } else {
+            LoopVerdict::Continue
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! Messages queued for a run from outside the loop, e.g. typed by the user while it streams.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::agent::QueueMode;
+use crate::types::{AgentMessage, MessageQueueFn};
+
+/// When a queued message reaches the agent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum QueuePriority {
+    /// Injected as a user message before the next model turn of the current run, so the user
+    /// can redirect the agent without interrupting it.
+    #[default]
+    SteerNow,
+    /// Sent once the current run has nothing left to do.
+    AfterRun,
+}
+
+#[derive(Default)]
+struct QueuedMessagesInner {
+    steering: VecDeque<AgentMessage>,
+    follow_up: VecDeque<AgentMessage>,
+    steering_mode: QueueMode,
+    follow_up_mode: QueueMode,
+}
+
+/// A steering and a follow-up queue shared between whoever enqueues messages and the agent
+/// loop, which polls them through [`Self::steering_queue`] and [`Self::follow_up_queue`].
+#[derive(Clone, Default)]
+pub struct QueuedMessages {
+    inner: Arc<Mutex<QueuedMessagesInner>>,
+}
+
+impl QueuedMessages {
+    pub fn new(steering_mode: QueueMode, follow_up_mode: QueueMode) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(QueuedMessagesInner {
+                steering_mode,
+                follow_up_mode,
+                ..QueuedMessagesInner::default()
+            })),
+        }
+    }
+
+    pub fn push(&self, message: AgentMessage, priority: QueuePriority) {
+        let mut inner = self.lock();
+        match priority {
+            QueuePriority::SteerNow => inner.steering.push_back(message),
+            QueuePriority::AfterRun => inner.follow_up.push_back(message),
+        }
+    }
+
+    /// Steering messages the loop has not picked up yet, oldest first.
+    pub fn pending_steering(&self) -> Vec<AgentMessage> {
+        self.lock().steering.iter().cloned().collect()
+    }
+
+    /// Removes and returns the steering messages the loop has not picked up yet.
+    pub fn take_steering(&self) -> Vec<AgentMessage> {
+        self.lock().steering.drain(..).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let inner = self.lock();
+        inner.steering.is_empty() && inner.follow_up.is_empty()
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.steering.clear();
+        inner.follow_up.clear();
+    }
+
+    /// For [`crate::AgentLoopConfig::get_steering_messages`].
+    pub fn steering_queue(&self) -> MessageQueueFn {
+        let queue = self.clone();
+        Arc::new(move || {
+            let mut inner = queue.lock();
+            let mode = inner.steering_mode;
+            dequeue_messages(&mut inner.steering, mode)
+        })
+    }
+
+    /// For [`crate::AgentLoopConfig::get_follow_up_messages`].
+    pub fn follow_up_queue(&self) -> MessageQueueFn {
+        let queue = self.clone();
+        Arc::new(move || {
+            let mut inner = queue.lock();
+            let mode = inner.follow_up_mode;
+            dequeue_messages(&mut inner.follow_up, mode)
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueuedMessagesInner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+pub(crate) fn dequeue_messages(
+    queue: &mut VecDeque<AgentMessage>,
+    mode: QueueMode,
+) -> Vec<AgentMessage> {
+    match mode {
+        QueueMode::All => queue.drain(..).collect(),
+        QueueMode::OneAtATime => queue.pop_front().into_iter().collect(),
+    }
+}
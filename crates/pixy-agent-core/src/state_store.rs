@@ -0,0 +1,123 @@
+//! Persistence for agent conversations.
+//!
+//! An [`AgentStateStore`] keeps the messages of each conversation under an id chosen by the
+//! embedder, e.g. one per chat. [`crate::Agent::set_state_store`] saves every finished run to it;
+//! [`JsonlStateStore`] is the file-backed default, and other backends implement the trait.
+
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use tokio::io::AsyncWriteExt;
+
+use crate::types::AgentMessage;
+
+#[async_trait]
+pub trait AgentStateStore: Send + Sync {
+    /// Appends the messages one run produced to conversation `id`.
+    async fn save_turn(&self, id: &str, messages: &[AgentMessage]) -> Result<(), String>;
+
+    /// Every message saved for `id`, oldest first; empty when nothing was saved yet.
+    async fn load(&self, id: &str) -> Result<Vec<AgentMessage>, String>;
+
+    /// Ids of the saved conversations, sorted.
+    async fn list(&self) -> Result<Vec<String>, String>;
+}
+
+/// Stores each conversation as `<dir>/<id>.jsonl`, one message per line.
+#[derive(Debug, Clone)]
+pub struct JsonlStateStore {
+    dir: PathBuf,
+}
+
+impl JsonlStateStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf, String> {
+        let valid = !id.is_empty()
+            && !id.starts_with('.')
+            && id
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.'));
+        if !valid {
+            return Err(format!(
+                "Invalid state id '{id}': use letters, digits, '-', '_' or '.'"
+            ));
+        }
+        Ok(self.dir.join(format!("{id}.jsonl")))
+    }
+}
+
+#[async_trait]
+impl AgentStateStore for JsonlStateStore {
+    async fn save_turn(&self, id: &str, messages: &[AgentMessage]) -> Result<(), String> {
+        let path = self.path(id)?;
+        let mut lines = String::new();
+        for message in messages {
+            let line = serde_json::to_string(message)
+                .map_err(|error| format!("Failed to encode message for '{id}': {error}"))?;
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|error| format!("Failed to create {}: {error}", self.dir.display()))?;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+            .map_err(|error| format!("Failed to open {}: {error}", path.display()))?;
+        file.write_all(lines.as_bytes())
+            .await
+            .map_err(|error| format!("Failed to write {}: {error}", path.display()))
+    }
+
+    async fn load(&self, id: &str) -> Result<Vec<AgentMessage>, String> {
+        let path = self.path(id)?;
+        let content = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(format!("Failed to read {}: {error}", path.display())),
+        };
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).map_err(|error| {
+                    format!(
+                        "Invalid message at {}:{}: {error}",
+                        path.display(),
+                        index + 1
+                    )
+                })
+            })
+            .collect()
+    }
+
+    async fn list(&self) -> Result<Vec<String>, String> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => {
+                return Err(format!("Failed to list {}: {error}", self.dir.display()));
+            }
+        };
+        let mut ids = Vec::new();
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|error| format!("Failed to list {}: {error}", self.dir.display()))?
+        {
+            let file_name = entry.file_name();
+            if let Some(id) = file_name.to_string_lossy().strip_suffix(".jsonl") {
+                ids.push(id.to_string());
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+}
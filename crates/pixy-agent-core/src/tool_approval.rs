@@ -0,0 +1,133 @@
+//! Human-in-the-loop approval for dangerous tools.
+//!
+//! Before a flagged tool runs, the agent loop emits [`crate::AgentEvent::ApprovalRequest`] and
+//! hands a [`ToolApprovalRequest`] to the approver attached by the front end (the TUI prompt or
+//! a gateway channel). The call waits for the decision; a denial is reported back to the model
+//! as a failed tool call. Without an approver, flagged tools are refused rather than run.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use pixy_ai::{PiAiError, PiAiErrorCode, ToolCall};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::oneshot;
+
+pub type ToolApproverFn = Arc<dyn Fn(ToolApprovalRequest) + Send + Sync>;
+
+/// How much damage a flagged tool can do, shown with the approval prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolRisk {
+    Low,
+    Medium,
+    High,
+}
+
+impl ToolRisk {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "low" => Some(Self::Low),
+            "medium" => Some(Self::Medium),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApprovalDecision {
+    Approve,
+    Deny { reason: Option<String> },
+}
+
+/// A tool call waiting for the user's decision.
+#[derive(Debug)]
+pub struct ToolApprovalRequest {
+    pub call: ToolCall,
+    pub risk: ToolRisk,
+    responder: oneshot::Sender<ApprovalDecision>,
+}
+
+impl ToolApprovalRequest {
+    /// Dropping a request without responding denies the call.
+    pub fn respond(self, decision: ApprovalDecision) {
+        let _ = self.responder.send(decision);
+    }
+}
+
+/// Which tools need approval and who is asked.
+#[derive(Clone)]
+pub struct ToolApproval {
+    pub dangerous_tools: HashMap<String, ToolRisk>,
+    /// `None` when nobody can approve calls, as in headless runs; flagged tools are refused.
+    pub approver: Option<ToolApproverFn>,
+}
+
+impl ToolApproval {
+    pub fn new(
+        dangerous_tools: HashMap<String, ToolRisk>,
+        approver: impl Fn(ToolApprovalRequest) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            dangerous_tools,
+            approver: Some(Arc::new(approver)),
+        }
+    }
+
+    /// Refuses every call to `dangerous_tools`, for sessions nobody watches.
+    pub fn unattended(dangerous_tools: HashMap<String, ToolRisk>) -> Self {
+        Self {
+            dangerous_tools,
+            approver: None,
+        }
+    }
+
+    /// The risk of `tool_name`, or `None` when it runs without approval.
+    pub fn risk(&self, tool_name: &str) -> Option<ToolRisk> {
+        self.dangerous_tools.get(tool_name).copied()
+    }
+
+    /// Asks the approver about `call` and waits for the answer; denies it without an approver.
+    pub async fn request(&self, call: ToolCall, risk: ToolRisk) -> ApprovalDecision {
+        let Some(approver) = &self.approver else {
+            return ApprovalDecision::Deny { reason: None };
+        };
+        let (responder, decision) = oneshot::channel();
+        approver(ToolApprovalRequest {
+            call,
+            risk,
+            responder,
+        });
+        decision
+            .await
+            .unwrap_or(ApprovalDecision::Deny { reason: None })
+    }
+}
+
+/// The error a flagged call gets when nobody can approve it.
+pub fn unattended_tool_error(tool_name: &str) -> PiAiError {
+    PiAiError::new(
+        PiAiErrorCode::ToolPolicyDenied,
+        format!(
+            "The {tool_name} call needs approval, but nobody can approve it in this session; it was not run."
+        ),
+    )
+    .with_details(json!({ "toolName": tool_name }))
+}
+
+impl std::fmt::Debug for ToolApproval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolApproval")
+            .field("dangerous_tools", &self.dangerous_tools)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,30 @@
+//! Typed tool arguments.
+//!
+//! `#[derive(AgentToolArgs)]` on an argument struct generates the JSON Schema for
+//! [`crate::AgentTool::parameters`] and the checks serde cannot express, so a tool's schema and
+//! its parsing come from one definition instead of a hand-written `json!` object.
+
+use pixy_ai::{PiAiError, PiAiErrorCode};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+pub trait AgentToolArgs: DeserializeOwned {
+    /// JSON Schema of the arguments object.
+    fn schema() -> Value;
+
+    /// Checks the constraints declared with `#[tool(...)]`, e.g. numeric minimums.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Deserializes and validates a tool call's arguments. Failures are
+    /// [`PiAiErrorCode::ToolArgumentsInvalid`] errors, which the model sees and can correct.
+    fn parse(args: Value) -> Result<Self, PiAiError> {
+        let invalid =
+            |message: String| PiAiError::new(PiAiErrorCode::ToolArgumentsInvalid, message);
+        let parsed: Self = serde_json::from_value(args)
+            .map_err(|error| invalid(format!("Invalid arguments: {error}")))?;
+        parsed.validate().map_err(invalid)?;
+        Ok(parsed)
+    }
+}
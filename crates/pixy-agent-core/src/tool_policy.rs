@@ -0,0 +1,239 @@
+//! Policy consulted by the agent loop before every tool execution.
+//!
+//! A [`ToolPolicy`] decides which tools may run, which paths their arguments may name and how
+//! much output they may return. Refusals go back to the model as tool errors, so it can pick
+//! another approach instead of ending the run.
+
+use std::path::{Component, Path, PathBuf};
+
+use pixy_ai::{PiAiError, PiAiErrorCode, ToolResultContentBlock};
+use serde_json::{json, Value};
+
+use crate::types::AgentToolResult;
+
+/// Argument keys treated as file system paths by [`ToolPolicy::allowed_paths`].
+const PATH_ARGUMENT_KEYS: [&str; 5] = ["path", "file_path", "cwd", "dir", "directory"];
+/// Argument holding a unified diff whose `---`/`+++` headers name the files it changes.
+const PATCH_ARGUMENT_KEY: &str = "patch";
+/// Argument holding a list of `{ "path": ... }` entries, as `apply_patch` takes them.
+const FILES_ARGUMENT_KEY: &str = "files";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolPolicy {
+    /// Only these tools may run; `None` allows every tool not denied.
+    pub allow_tools: Option<Vec<String>>,
+    /// Tools that never run, even when allowed.
+    pub deny_tools: Vec<String>,
+    /// Roots that path arguments must stay inside; empty leaves paths unrestricted. Symlinks
+    /// are followed, so a link inside a root that points outside it is refused.
+    pub allowed_paths: Vec<PathBuf>,
+    /// Directory relative path arguments are resolved against, as the tools resolve them;
+    /// sessions set it to their working directory. Defaults to the first allowed root.
+    pub cwd: Option<PathBuf>,
+    /// Longest text a tool result may return; longer output is cut with a note.
+    pub max_output_bytes: Option<usize>,
+    /// Checks every call but runs none, answering with what would have run.
+    pub dry_run: bool,
+}
+
+impl ToolPolicy {
+    /// Whether `tool_name` passes the allow and deny lists.
+    pub fn allows_tool(&self, tool_name: &str) -> bool {
+        let allowed = self
+            .allow_tools
+            .as_ref()
+            .is_none_or(|allow| allow.iter().any(|name| name == tool_name));
+        allowed && !self.deny_tools.iter().any(|name| name == tool_name)
+    }
+
+    /// Checks a call against the tool lists and path restrictions.
+    pub fn check(&self, tool_name: &str, args: &Value) -> Result<(), PiAiError> {
+        if !self.allows_tool(tool_name) {
+            return Err(PiAiError::new(
+                PiAiErrorCode::ToolPolicyDenied,
+                format!("Tool {tool_name} is not allowed by the tool policy"),
+            )
+            .with_details(json!({ "toolName": tool_name })));
+        }
+
+        let Some(root) = self.allowed_paths.first() else {
+            return Ok(());
+        };
+        let cwd = self.cwd.as_deref().unwrap_or(root);
+        let allowed = self
+            .allowed_paths
+            .iter()
+            .map(|allowed| {
+                canonicalize_lenient(&resolve_tool_path(cwd, &allowed.to_string_lossy()))
+            })
+            .collect::<Vec<_>>();
+        for (key, path) in path_arguments(args) {
+            let resolved = canonicalize_lenient(&resolve_tool_path(cwd, &path));
+            if !allowed.iter().any(|allowed| resolved.starts_with(allowed)) {
+                return Err(PiAiError::new(
+                    PiAiErrorCode::ToolPolicyDenied,
+                    format!("Path {path} is outside the paths allowed by the tool policy"),
+                )
+                .with_details(json!({ "toolName": tool_name, "argument": key, "path": path })));
+            }
+        }
+        Ok(())
+    }
+
+    /// The result returned instead of running a call in dry-run mode.
+    pub fn dry_run_result(&self, tool_name: &str, args: &Value) -> AgentToolResult {
+        AgentToolResult {
+            content: vec![ToolResultContentBlock::Text {
+                text: format!("Dry run: {tool_name} was not executed."),
+                text_signature: None,
+            }],
+            details: json!({ "dryRun": true, "toolName": tool_name, "args": args }),
+        }
+    }
+
+    /// Cuts the text of `result` to [`Self::max_output_bytes`].
+    pub fn limit_output(&self, mut result: AgentToolResult) -> AgentToolResult {
+        let Some(max_bytes) = self.max_output_bytes else {
+            return result;
+        };
+        let mut remaining = max_bytes;
+        let mut truncated = 0usize;
+        for block in &mut result.content {
+            let ToolResultContentBlock::Text { text, .. } = block else {
+                continue;
+            };
+            if text.len() <= remaining {
+                remaining -= text.len();
+                continue;
+            }
+            let mut cut = remaining;
+            while !text.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            truncated += text.len() - cut;
+            text.truncate(cut);
+            remaining = 0;
+        }
+        if truncated > 0 {
+            result.content.push(ToolResultContentBlock::Text {
+                text: format!(
+                    "[output truncated: {truncated} bytes over the {max_bytes} byte limit]"
+                ),
+                text_signature: None,
+            });
+        }
+        result
+    }
+}
+
+/// Resolves a path argument the way the coding tools do: a leading `@` is dropped, `~` expands
+/// to the home directory and relative paths are joined onto `cwd`.
+pub fn resolve_tool_path(cwd: &Path, path: &str) -> PathBuf {
+    let path = path.strip_prefix('@').unwrap_or(path);
+    let path = PathBuf::from(expand_home(path));
+    if path.is_absolute() {
+        path
+    } else {
+        cwd.join(path)
+    }
+}
+
+fn expand_home(path: &str) -> String {
+    if path == "~" {
+        return std::env::var("HOME").unwrap_or_else(|_| path.to_string());
+    }
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Ok(home) = std::env::var("HOME") {
+            return format!("{home}/{rest}");
+        }
+    }
+    path.to_string()
+}
+
+/// Every path a call names: the [`PATH_ARGUMENT_KEYS`], the `path` of each `files` entry and
+/// both sides of each file header in a `patch`.
+fn path_arguments(args: &Value) -> Vec<(&'static str, String)> {
+    let mut paths = PATH_ARGUMENT_KEYS
+        .iter()
+        .filter_map(|key| Some((*key, args.get(key)?.as_str()?.to_string())))
+        .collect::<Vec<_>>();
+    if let Some(files) = args.get(FILES_ARGUMENT_KEY).and_then(Value::as_array) {
+        paths.extend(
+            files
+                .iter()
+                .filter_map(|file| file.get("path")?.as_str())
+                .map(|path| (FILES_ARGUMENT_KEY, path.to_string())),
+        );
+    }
+    if let Some(patch) = args.get(PATCH_ARGUMENT_KEY).and_then(Value::as_str) {
+        paths.extend(patch_header_paths(patch).map(|path| (PATCH_ARGUMENT_KEY, path.to_string())));
+    }
+    paths
+}
+
+/// Paths in the `--- a/path` and `+++ b/path` headers of a unified diff, without `/dev/null`.
+fn patch_header_paths(patch: &str) -> impl Iterator<Item = &str> {
+    patch.lines().filter_map(|line| {
+        let (raw, prefix) = match line.strip_prefix("--- ") {
+            Some(raw) => (raw, "a/"),
+            None => (line.strip_prefix("+++ ")?, "b/"),
+        };
+        let path = raw.split('\t').next().unwrap_or(raw).trim();
+        (path != "/dev/null").then(|| path.strip_prefix(prefix).unwrap_or(path))
+    })
+}
+
+/// Resolves symlinks in the longest existing prefix of `path` and `.`/`..` in the rest, so
+/// paths that do not exist yet can be checked too.
+fn canonicalize_lenient(path: &Path) -> PathBuf {
+    let path = normalize_path(path);
+    let mut existing = path.as_path();
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return missing
+                .iter()
+                .rev()
+                .fold(canonical, |resolved, component| resolved.join(component));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return path,
+        }
+    }
+}
+
+/// Resolves `.` and `..` without touching the file system.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_path_resolves_parent_components() {
+        assert_eq!(
+            normalize_path(Path::new("/work/src/../../etc/passwd")),
+            PathBuf::from("/etc/passwd")
+        );
+        assert_eq!(
+            normalize_path(Path::new("/work/./src")),
+            PathBuf::from("/work/src")
+        );
+    }
+}
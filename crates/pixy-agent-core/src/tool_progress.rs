@@ -0,0 +1,89 @@
+//! Progress channel for long-running tools.
+//!
+//! The agent loop scopes every tool execution with a [`ToolProgress`] that turns reported values
+//! into [`crate::AgentEvent::ToolExecutionUpdate`] events. Tools look it up with
+//! [`ToolProgress::current`], so wrappers around a tool's executor need no changes to pass it on.
+//! Tools that produce text as they go, such as builds or test suites, write through the
+//! line-oriented [`ToolOutputStream`] instead.
+
+use std::future::Future;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+tokio::task_local! {
+    static CURRENT_TOOL_PROGRESS: ToolProgress;
+}
+
+type ReportFn = dyn Fn(Value) + Send + Sync;
+
+#[derive(Clone)]
+pub struct ToolProgress {
+    report: Arc<ReportFn>,
+}
+
+impl ToolProgress {
+    pub fn new(report: impl Fn(Value) + Send + Sync + 'static) -> Self {
+        Self {
+            report: Arc::new(report),
+        }
+    }
+
+    /// Sends a partial result to whoever is watching the tool call.
+    pub fn report(&self, partial_result: Value) {
+        (self.report)(partial_result);
+    }
+
+    /// The progress channel of the tool call running on the current task, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT_TOOL_PROGRESS.try_with(Clone::clone).ok()
+    }
+
+    /// Runs `future` with `self` as the current progress channel.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT_TOOL_PROGRESS.scope(self, future).await
+    }
+}
+
+impl std::fmt::Debug for ToolProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolProgress").finish_non_exhaustive()
+    }
+}
+
+/// Where a streamed line of tool output came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolOutputKind {
+    Stdout,
+    Stderr,
+    /// A note from the tool itself, e.g. why a command stopped.
+    Status,
+}
+
+/// Incremental text output of a running tool, carried over its [`ToolProgress`] channel.
+///
+/// Each line becomes a `{"stream": <kind>, "line": <text>}` partial result, which sessions show
+/// live while the tool keeps running.
+#[derive(Clone, Debug)]
+pub struct ToolOutputStream {
+    progress: ToolProgress,
+}
+
+impl ToolOutputStream {
+    pub fn new(progress: ToolProgress) -> Self {
+        Self { progress }
+    }
+
+    /// The output stream of the tool call running on the current task, if any.
+    pub fn current() -> Option<Self> {
+        ToolProgress::current().map(Self::new)
+    }
+
+    /// Sends one line, without its trailing newline.
+    pub fn line(&self, kind: ToolOutputKind, line: &str) {
+        self.progress
+            .report(json!({ "stream": kind, "line": line }));
+    }
+}
@@ -1,16 +1,25 @@
+use std::collections::BTreeMap;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::Duration;
 
 use async_trait::async_trait;
 use pixy_ai::{
     AssistantMessageEvent, AssistantMessageEventStream, Context, Message, Model, PiAiError,
-    SimpleStreamOptions, Tool, ToolResultContentBlock,
+    SimpleStreamOptions, Tool, ToolCall, ToolResultContentBlock, ToolValidationOptions,
 };
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tokio::sync::Notify;
 
+use crate::agent_hooks::AgentHooks;
+use crate::context_compaction::ContextCompaction;
+use crate::loop_guard::ToolLoopGuard;
+use crate::tool_approval::{ToolApproval, ToolRisk};
+use crate::tool_policy::ToolPolicy;
+
 pub type AgentMessage = Message;
 
 pub trait StreamExecutor: Send + Sync {
@@ -68,6 +77,15 @@ pub enum ParentChildRunEvent {
         subagent: String,
         error: String,
     },
+    /// The child run started a tool call; `headline` is its one-line summary.
+    ChildToolStart {
+        parent_session_id: String,
+        child_session_file: String,
+        task_id: String,
+        subagent: String,
+        tool_name: String,
+        headline: String,
+    },
 }
 
 impl ParentChildRunEvent {
@@ -76,6 +94,7 @@ impl ParentChildRunEvent {
             Self::ChildRunStart { task_id, .. } => task_id,
             Self::ChildRunEnd { task_id, .. } => task_id,
             Self::ChildRunError { task_id, .. } => task_id,
+            Self::ChildToolStart { task_id, .. } => task_id,
         }
     }
 
@@ -84,6 +103,7 @@ impl ParentChildRunEvent {
             Self::ChildRunStart { .. } => "child_run_start",
             Self::ChildRunEnd { .. } => "child_run_end",
             Self::ChildRunError { .. } => "child_run_error",
+            Self::ChildToolStart { .. } => "child_tool_start",
         }
     }
 }
@@ -156,6 +176,10 @@ where
 
 pub type AgentToolExecuteFn = Arc<dyn AgentToolExecutor>;
 
+/// Maps a call's arguments to the resources it touches, e.g. the file paths; a call touching
+/// nothing shared returns no keys.
+pub type ToolConflictKeyFn = Arc<dyn Fn(&Value) -> Vec<String> + Send + Sync>;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct AgentRetryConfig {
     pub max_attempts: usize,
@@ -173,13 +197,69 @@ impl Default for AgentRetryConfig {
     }
 }
 
-#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct AgentRunMetrics {
     pub assistant_request_count: usize,
     pub assistant_request_total_ms: u64,
     pub tool_execution_count: usize,
     pub tool_execution_total_ms: u64,
     pub retry_count: usize,
+    /// Executions of each tool, by tool name.
+    #[serde(default)]
+    pub tools: BTreeMap<String, ToolRunMetrics>,
+    /// Total tokens of each assistant response, oldest first.
+    #[serde(default)]
+    pub turn_tokens: Vec<u64>,
+}
+
+impl AgentRunMetrics {
+    /// Adds the metrics of a later run.
+    pub fn merge(&mut self, other: &AgentRunMetrics) {
+        self.assistant_request_count += other.assistant_request_count;
+        self.assistant_request_total_ms += other.assistant_request_total_ms;
+        self.tool_execution_count += other.tool_execution_count;
+        self.tool_execution_total_ms += other.tool_execution_total_ms;
+        self.retry_count += other.retry_count;
+        for (name, tool) in &other.tools {
+            self.tools.entry(name.clone()).or_default().merge(tool);
+        }
+        self.turn_tokens.extend(&other.turn_tokens);
+    }
+
+    pub fn tool_failure_count(&self) -> usize {
+        self.tools.values().map(|tool| tool.failure_count).sum()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolRunMetrics {
+    pub invocation_count: usize,
+    pub failure_count: usize,
+    pub total_ms: u64,
+}
+
+impl ToolRunMetrics {
+    pub fn record(&mut self, duration_ms: u64, is_error: bool) {
+        self.invocation_count += 1;
+        self.failure_count += usize::from(is_error);
+        self.total_ms = self.total_ms.saturating_add(duration_ms);
+    }
+
+    pub fn merge(&mut self, other: &ToolRunMetrics) {
+        self.invocation_count += other.invocation_count;
+        self.failure_count += other.failure_count;
+        self.total_ms = self.total_ms.saturating_add(other.total_ms);
+    }
+
+    /// Share of invocations that returned an error, from 0 to 1.
+    pub fn failure_rate(&self) -> f64 {
+        if self.invocation_count == 0 {
+            return 0.0;
+        }
+        self.failure_count as f64 / self.invocation_count as f64
+    }
 }
 
 #[derive(Clone)]
@@ -189,8 +269,29 @@ pub struct AgentLoopConfig {
     pub convert_to_llm: ConvertToLlmFn,
     pub stream_fn: StreamFn,
     pub retry: AgentRetryConfig,
+    /// How many tool calls from one assistant message may run at once; `1` runs them in order.
+    pub tool_parallelism: usize,
+    /// When set, tool arguments are checked against the tool's JSON Schema, and repaired as
+    /// the options allow, before the tool runs. Violations are returned to the model as a
+    /// tool error so it can correct the call.
+    pub tool_validation: Option<ToolValidationOptions>,
+    /// Consulted before every tool execution, after argument validation.
+    pub tool_policy: Option<ToolPolicy>,
+    /// Flagged tools wait for this approver before they run.
+    pub tool_approval: Option<ToolApproval>,
+    /// Limits tool iterations and repeated identical calls within one run.
+    pub loop_guard: Option<ToolLoopGuard>,
+    pub hooks: Option<Arc<dyn AgentHooks>>,
+    /// Compacts the context and retries once when a response fails because the context no
+    /// longer fits the model; without it such a response ends the run.
+    pub context_compaction: Option<ContextCompaction>,
     pub get_steering_messages: Option<MessageQueueFn>,
     pub get_follow_up_messages: Option<MessageQueueFn>,
+    /// Wall-clock limit for one assistant response, retries included. When it passes, the
+    /// stream stops with its partial output kept and the run ends with [`AgentEvent::TimedOut`].
+    pub turn_timeout: Option<Duration>,
+    /// Wall-clock limit for the whole loop, tool execution included.
+    pub run_timeout: Option<Duration>,
 }
 
 #[derive(Clone)]
@@ -199,6 +300,9 @@ pub struct AgentTool {
     pub label: String,
     pub description: String,
     pub parameters: serde_json::Value,
+    /// Calls sharing a key, from this or any other tool, never run at the same time; calls
+    /// without keys run in parallel up to [`AgentLoopConfig::tool_parallelism`].
+    pub conflict_key: Option<ToolConflictKeyFn>,
     pub execute: AgentToolExecuteFn,
 }
 
@@ -212,7 +316,7 @@ impl AgentTool {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct AgentToolResult {
     pub content: Vec<ToolResultContentBlock>,
     pub details: Value,
@@ -225,6 +329,26 @@ pub struct AgentContext {
     pub tools: Vec<AgentTool>,
 }
 
+/// Why a run was aborted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbortReason {
+    UserInterrupt,
+    Timeout,
+    BudgetExceeded,
+    ParentCancelled,
+}
+
+impl AbortReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::UserInterrupt => "user_interrupt",
+            Self::Timeout => "timeout",
+            Self::BudgetExceeded => "budget_exceeded",
+            Self::ParentCancelled => "parent_cancelled",
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct AgentAbortSignal {
     inner: Arc<AbortInner>,
@@ -232,7 +356,50 @@ pub struct AgentAbortSignal {
 
 struct AbortInner {
     aborted: AtomicBool,
+    /// Set while the abort is a soft one: in-flight tools run to completion.
+    soft: AtomicBool,
+    /// The reason given by the first abort.
+    reason: OnceLock<AbortReason>,
     notify: Notify,
+    /// Signals derived with [`AgentAbortSignal::child_controller`]; dropped ones are pruned.
+    children: Mutex<Vec<Weak<AbortInner>>>,
+}
+
+impl AbortInner {
+    fn new() -> Self {
+        Self {
+            aborted: AtomicBool::new(false),
+            soft: AtomicBool::new(false),
+            reason: OnceLock::new(),
+            notify: Notify::new(),
+            children: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn trigger(&self, reason: AbortReason, soft: bool) {
+        if soft && self.aborted.load(Ordering::SeqCst) {
+            return;
+        }
+        let _ = self.reason.set(reason);
+        self.soft.store(soft, Ordering::SeqCst);
+        self.aborted.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+
+        let children = self
+            .children
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect::<Vec<_>>();
+        for child in children {
+            child.trigger(AbortReason::ParentCancelled, soft);
+        }
+    }
+}
+
+tokio::task_local! {
+    static CURRENT_ABORT_SIGNAL: AgentAbortSignal;
 }
 
 impl AgentAbortSignal {
@@ -240,11 +407,66 @@ impl AgentAbortSignal {
         self.inner.aborted.load(Ordering::SeqCst)
     }
 
+    /// Whether the abort lets in-flight tools finish. Streaming stops either way and keeps
+    /// the partial assistant output.
+    pub fn is_soft(&self) -> bool {
+        self.inner.soft.load(Ordering::SeqCst)
+    }
+
+    pub fn reason(&self) -> Option<AbortReason> {
+        self.inner.reason.get().copied()
+    }
+
+    /// Resolves once any abort, soft or hard, is requested.
     pub async fn cancelled(&self) {
+        self.wait_until(Self::is_aborted).await;
+    }
+
+    /// Resolves once a hard abort is requested, including a soft abort upgraded to a hard one.
+    pub async fn hard_cancelled(&self) {
+        self.wait_until(|signal| signal.is_aborted() && !signal.is_soft())
+            .await;
+    }
+
+    /// A controller for a child run, such as a sub-agent dispatched by a tool. Aborting this
+    /// signal aborts the child the same way, soft or hard, with [`AbortReason::ParentCancelled`];
+    /// aborting the child leaves this signal alone.
+    pub fn child_controller(&self) -> AgentAbortController {
+        let controller = AgentAbortController::new();
+        let child = controller.signal.inner.clone();
+        {
+            let mut children = self
+                .inner
+                .children
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            children.retain(|child| child.strong_count() > 0);
+            children.push(Arc::downgrade(&child));
+        }
         if self.is_aborted() {
-            return;
+            child.trigger(AbortReason::ParentCancelled, self.is_soft());
+        }
+        controller
+    }
+
+    /// The abort signal of the run whose tool call is executing on the current task, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT_ABORT_SIGNAL.try_with(Clone::clone).ok()
+    }
+
+    /// Runs `future` with `self` as the current abort signal.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT_ABORT_SIGNAL.scope(self, future).await
+    }
+
+    async fn wait_until(&self, done: impl Fn(&Self) -> bool) {
+        loop {
+            let notified = self.inner.notify.notified();
+            if done(self) {
+                return;
+            }
+            notified.await;
         }
-        self.inner.notify.notified().await;
     }
 }
 
@@ -256,10 +478,7 @@ impl AgentAbortController {
     pub fn new() -> Self {
         Self {
             signal: AgentAbortSignal {
-                inner: Arc::new(AbortInner {
-                    aborted: AtomicBool::new(false),
-                    notify: Notify::new(),
-                }),
+                inner: Arc::new(AbortInner::new()),
             },
         }
     }
@@ -268,13 +487,39 @@ impl AgentAbortController {
         self.signal.clone()
     }
 
+    /// Hard abort on user interrupt: streaming and in-flight tools stop at once.
     pub fn abort(&self) {
-        self.signal.inner.aborted.store(true, Ordering::SeqCst);
-        self.signal.inner.notify.notify_waiters();
+        self.abort_with(AbortReason::UserInterrupt);
+    }
+
+    /// Hard abort with `reason`. Upgrades an earlier soft abort but keeps its reason.
+    pub fn abort_with(&self, reason: AbortReason) {
+        self.signal.inner.trigger(reason, false);
+    }
+
+    /// Graceful drain: streaming stops with its partial output kept, in-flight tools run to
+    /// completion and the remaining tool calls are skipped. Does nothing after any abort.
+    pub fn soft_abort(&self, reason: AbortReason) {
+        self.signal.inner.trigger(reason, true);
     }
 }
 
-#[derive(Clone)]
+impl Default for AgentAbortController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Progress of an agent run. Serializes as JSON tagged by `type` (`turn_start`,
+/// `tool_execution_end`, ...) with camelCase fields, so it can be forwarded to other processes.
+/// Text and thinking deltas arrive as `message_update` events.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+#[allow(clippy::large_enum_variant)]
 pub enum AgentEvent {
     AgentStart,
     AgentEnd {
@@ -328,6 +573,39 @@ pub enum AgentEvent {
     Metrics {
         metrics: AgentRunMetrics,
     },
+    /// A flagged tool is waiting for approval; the call runs once the approver allows it.
+    ApprovalRequest {
+        call: ToolCall,
+        risk: ToolRisk,
+    },
+    /// A [`AgentLoopConfig::turn_timeout`] or [`AgentLoopConfig::run_timeout`] passed; the run
+    /// ends right after this turn.
+    TimedOut {
+        scope: TimeoutScope,
+        timeout_ms: u64,
+    },
+    /// A response failed because the context was too large; the loop dropped the failed
+    /// response, compacted its context by `dropped_tokens` and is sending the request again.
+    Compacted {
+        dropped_tokens: u64,
+    },
+}
+
+/// Which wall-clock limit an [`AgentEvent::TimedOut`] refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeoutScope {
+    Turn,
+    Run,
+}
+
+impl TimeoutScope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Turn => "turn",
+            Self::Run => "run",
+        }
+    }
 }
 
 #[cfg(test)]
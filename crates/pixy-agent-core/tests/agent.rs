@@ -2,7 +2,9 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
-use pixy_agent_core::{Agent, AgentConfig, AgentMessage, QueueMode};
+use pixy_agent_core::{
+    Agent, AgentConfig, AgentEvent, AgentMessage, AgentStateStore, JsonlStateStore, QueueMode,
+};
 use pixy_ai::{
     AssistantContentBlock, AssistantMessage, AssistantMessageEvent, AssistantMessageEventStream,
     Context, Cost, DoneReason, Message, Model, StopReason, Usage, UserContent,
@@ -15,6 +17,7 @@ fn sample_usage() -> Usage {
         output: 5,
         cache_read: 0,
         cache_write: 0,
+        reasoning: 0,
         total_tokens: 15,
         cost: Cost {
             input: 0.01,
@@ -45,6 +48,9 @@ fn sample_model(api: &str) -> Model {
         },
         context_window: 128_000,
         max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
     }
 }
 
@@ -69,6 +75,7 @@ fn assistant_message(text: &str, ts: i64) -> AssistantMessage {
         stop_reason: StopReason::Stop,
         error_message: None,
         timestamp: ts,
+        stats: None,
     }
 }
 
@@ -212,6 +219,157 @@ async fn agent_continue_prioritizes_steering_messages_over_follow_up_messages()
     );
 }
 
+#[tokio::test]
+async fn agent_restore_rewinds_messages_queues_and_metrics_to_a_checkpoint() {
+    let stream_fn = Arc::new(
+        |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            Ok(done_stream(assistant_message("ok", 1_700_000_000_010)))
+        },
+    );
+    let agent = Agent::new(AgentConfig::new(
+        "You are helpful".to_string(),
+        sample_model("test-api"),
+        stream_fn,
+    ));
+
+    let _ = agent.prompt_text("first").await.expect("first prompt");
+    agent.follow_up(user_message("queued", 1_700_000_000_100));
+    let checkpoint = agent.checkpoint();
+    let saved = agent.state();
+    assert_eq!(saved.metrics.assistant_request_count, 1);
+
+    agent.clear_all_queues();
+    let _ = agent.prompt_text("bad run").await.expect("second prompt");
+    assert_eq!(agent.state().messages.len(), 4);
+    assert_eq!(agent.state().metrics.assistant_request_count, 2);
+
+    agent.restore(checkpoint).expect("restore checkpoint");
+    let restored = agent.state();
+    assert_eq!(restored.messages, saved.messages);
+    assert_eq!(restored.metrics, saved.metrics);
+    assert_eq!(agent.metrics_snapshot(), saved.metrics);
+    assert!(agent.has_queued_messages());
+    let continued = agent
+        .continue_run()
+        .await
+        .expect("continue from checkpoint");
+    assert_eq!(user_texts(&continued), vec!["queued"]);
+
+    agent
+        .restore(checkpoint)
+        .expect("checkpoints can be restored again");
+    assert_eq!(agent.state().messages, saved.messages);
+    assert_eq!(
+        agent.restore(99).expect_err("unknown checkpoint"),
+        "Unknown checkpoint: 99"
+    );
+}
+
+#[tokio::test]
+async fn agent_subscribers_receive_the_events_of_each_run() {
+    let stream_fn = Arc::new(
+        |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            Ok(done_stream(assistant_message("ok", 1_700_000_000_010)))
+        },
+    );
+    let agent = Agent::new(AgentConfig::new(
+        "You are helpful".to_string(),
+        sample_model("test-api"),
+        stream_fn,
+    ));
+    let events = agent.subscribe();
+    drop(agent.subscribe());
+
+    let _ = agent.prompt_text("hello").await.expect("prompt");
+    drop(agent);
+
+    let mut received = Vec::new();
+    while let Some(event) = events.next().await {
+        received.push(event);
+    }
+    assert!(matches!(received.first(), Some(AgentEvent::AgentStart)));
+    assert!(matches!(received.last(), Some(AgentEvent::AgentEnd { .. })));
+    assert!(received
+        .iter()
+        .any(|event| matches!(event, AgentEvent::Metrics { metrics } if metrics.assistant_request_count == 1)));
+}
+
+#[tokio::test]
+async fn agent_subscription_ends_when_the_agent_is_dropped() {
+    let stream_fn = Arc::new(
+        |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            Ok(done_stream(assistant_message("ok", 1_700_000_000_010)))
+        },
+    );
+    let agent = Agent::new(AgentConfig::new(
+        "You are helpful".to_string(),
+        sample_model("test-api"),
+        stream_fn,
+    ));
+    let events = agent.subscribe();
+    let collector = tokio::spawn(async move {
+        let mut count = 0;
+        while events.next().await.is_some() {
+            count += 1;
+        }
+        count
+    });
+
+    let clone = agent.clone();
+    let _ = agent.prompt_text("hello").await.expect("prompt");
+    drop(agent);
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    assert!(
+        !collector.is_finished(),
+        "a live clone keeps the stream open"
+    );
+
+    drop(clone);
+    let count = tokio::time::timeout(std::time::Duration::from_secs(1), collector)
+        .await
+        .expect("stream should end after the last agent handle is dropped")
+        .expect("collector task");
+    assert!(count > 0);
+}
+
+#[tokio::test]
+async fn agent_saves_runs_to_its_state_store_and_loads_them_back() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let store = Arc::new(JsonlStateStore::new(dir.path().join("state")));
+    let stream_fn = Arc::new(
+        |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            Ok(done_stream(assistant_message("ok", 1_700_000_000_010)))
+        },
+    );
+    let config = AgentConfig::new(
+        "You are helpful".to_string(),
+        sample_model("test-api"),
+        stream_fn,
+    );
+
+    let agent = Agent::new(config.clone());
+    assert_eq!(
+        agent.load_state().await.expect_err("no store attached"),
+        "No state store is attached to the agent"
+    );
+    agent.set_state_store(store.clone(), "chat-1");
+    let _ = agent.prompt_text("first").await.expect("first prompt");
+    let _ = agent.prompt_text("second").await.expect("second prompt");
+    assert_eq!(store.list().await.expect("list"), vec!["chat-1"]);
+
+    let resumed = Agent::new(config);
+    resumed.set_state_store(store.clone(), "chat-1");
+    assert_eq!(resumed.load_state().await.expect("load state"), 4);
+    assert_eq!(resumed.state().messages, agent.state().messages);
+    assert_eq!(
+        user_texts(&resumed.state().messages),
+        vec!["first", "second"]
+    );
+
+    assert!(store.load("unknown").await.expect("load").is_empty());
+    assert!(store.save_turn("../escape", &[]).await.is_err());
+}
+
 #[tokio::test]
 async fn agent_abort_interrupts_running_prompt_and_wait_for_idle_unblocks() {
     let stream_fn = Arc::new(
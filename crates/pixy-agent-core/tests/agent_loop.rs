@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -5,14 +6,17 @@ use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use pixy_agent_core::{
-    agent_loop, agent_loop_continue, try_agent_loop_continue, AgentAbortController, AgentContext,
-    AgentEvent, AgentLoopConfig, AgentLoopError, AgentMessage, AgentRetryConfig, AgentTool,
-    AgentToolResult,
+    agent_loop, agent_loop_continue, try_agent_loop_continue, AbortReason, AgentAbortController,
+    AgentAbortSignal, AgentContext, AgentEvent, AgentHooks, AgentLoopConfig, AgentLoopError,
+    AgentMessage, AgentRetryConfig, AgentTool, AgentToolResult, ApprovalDecision,
+    ContextCompaction, QueueMode, QueuePriority, QueuedMessages, TimeoutScope, ToolApproval,
+    ToolLoopGuard, ToolOutputKind, ToolOutputStream, ToolPolicy, ToolProgress, ToolRisk,
 };
 use pixy_ai::{
     AssistantContentBlock, AssistantMessage, AssistantMessageEvent, AssistantMessageEventStream,
-    Context, Cost, DoneReason, Message, Model, PiAiError, PiAiErrorCode, StopReason,
-    ToolResultContentBlock, Usage, UserContent,
+    CompressionStrategy, Context, Cost, DoneReason, ErrorReason, Message, Model, PiAiError,
+    PiAiErrorCode, StopReason, ToolCall, ToolResultContentBlock, ToolValidationOptions, Usage,
+    UserContent,
 };
 use serde_json::{json, Value};
 use tokio::time::sleep;
@@ -23,6 +27,7 @@ fn sample_usage() -> Usage {
         output: 5,
         cache_read: 0,
         cache_write: 0,
+        reasoning: 0,
         total_tokens: 15,
         cost: Cost {
             input: 0.01,
@@ -53,6 +58,9 @@ fn sample_model(api: &str) -> Model {
         },
         context_window: 128_000,
         max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
     }
 }
 
@@ -78,6 +86,7 @@ fn assistant_message(
         stop_reason,
         error_message: None,
         timestamp: ts,
+        stats: None,
     }
 }
 
@@ -117,8 +126,17 @@ fn default_loop_config() -> AgentLoopConfig {
         convert_to_llm: Arc::new(|messages| messages),
         stream_fn: default_stream_fn(),
         retry: AgentRetryConfig::default(),
+        tool_parallelism: 1,
+        tool_validation: None,
         get_steering_messages: None,
         get_follow_up_messages: None,
+        turn_timeout: None,
+        run_timeout: None,
+        tool_policy: None,
+        tool_approval: None,
+        loop_guard: None,
+        hooks: None,
+        context_compaction: None,
     }
 }
 
@@ -166,8 +184,17 @@ async fn agent_loop_emits_basic_lifecycle_events() {
         convert_to_llm: Arc::new(|messages| messages),
         stream_fn,
         retry: AgentRetryConfig::default(),
+        tool_parallelism: 1,
+        tool_validation: None,
         get_steering_messages: None,
         get_follow_up_messages: None,
+        turn_timeout: None,
+        run_timeout: None,
+        tool_policy: None,
+        tool_approval: None,
+        loop_guard: None,
+        hooks: None,
+        context_compaction: None,
     };
 
     let stream = agent_loop(prompts, context, config, None);
@@ -189,6 +216,9 @@ async fn agent_loop_emits_basic_lifecycle_events() {
             AgentEvent::RetryScheduled { .. } => "retry_scheduled",
             AgentEvent::ModelFallback { .. } => "model_fallback",
             AgentEvent::Metrics { .. } => "metrics",
+            AgentEvent::TimedOut { .. } => "timed_out",
+            AgentEvent::ApprovalRequest { .. } => "approval_request",
+            AgentEvent::Compacted { .. } => "compacted",
         })
         .collect();
 
@@ -206,10 +236,48 @@ async fn agent_loop_emits_basic_lifecycle_events() {
             "agent_end"
         ]
     );
+    for (event, event_type) in events.iter().zip(&event_types) {
+        let encoded = serde_json::to_value(event).expect("events serialize");
+        assert_eq!(encoded["type"], *event_type);
+        let decoded: AgentEvent = serde_json::from_value(encoded).expect("events deserialize");
+        assert_eq!(&decoded, event);
+    }
 
     assert_eq!(result.len(), 2, "result should include prompt + assistant");
 }
 
+#[test]
+fn agent_events_serialize_with_camel_case_fields() {
+    let event = AgentEvent::ToolExecutionEnd {
+        tool_call_id: "call_1".to_string(),
+        tool_name: "read".to_string(),
+        result: AgentToolResult {
+            content: vec![ToolResultContentBlock::Text {
+                text: "fn main() {}".to_string(),
+                text_signature: None,
+            }],
+            details: json!({ "lines": 1 }),
+        },
+        is_error: false,
+        duration_ms: 12,
+    };
+    let encoded = serde_json::to_value(&event).expect("serialize");
+    assert_eq!(encoded["type"], "tool_execution_end");
+    assert_eq!(encoded["toolCallId"], "call_1");
+    assert_eq!(encoded["durationMs"], 12);
+    assert_eq!(encoded["result"]["details"], json!({ "lines": 1 }));
+
+    let timed_out = serde_json::to_value(AgentEvent::TimedOut {
+        scope: TimeoutScope::Turn,
+        timeout_ms: 50,
+    })
+    .expect("serialize");
+    assert_eq!(
+        timed_out,
+        json!({ "type": "timed_out", "scope": "turn", "timeoutMs": 50 })
+    );
+}
+
 #[tokio::test]
 async fn agent_loop_executes_tool_calls_and_continues() {
     let call_count = Arc::new(AtomicUsize::new(0));
@@ -257,6 +325,7 @@ async fn agent_loop_executes_tool_calls_and_continues() {
                 "required": ["path"],
                 "additionalProperties": false
             }),
+            conflict_key: None,
             execute:
                 Arc::new(
                     |_tool_call_id: String,
@@ -290,8 +359,17 @@ async fn agent_loop_executes_tool_calls_and_continues() {
         convert_to_llm: Arc::new(|messages| messages),
         stream_fn,
         retry: AgentRetryConfig::default(),
+        tool_parallelism: 1,
+        tool_validation: None,
         get_steering_messages: None,
         get_follow_up_messages: None,
+        turn_timeout: None,
+        run_timeout: None,
+        tool_policy: None,
+        tool_approval: None,
+        loop_guard: None,
+        hooks: None,
+        context_compaction: None,
     };
 
     let stream = agent_loop(prompts, context, config, None);
@@ -320,6 +398,344 @@ async fn agent_loop_executes_tool_calls_and_continues() {
     );
 }
 
+#[tokio::test]
+async fn agent_loop_validates_tool_arguments_and_reports_violations() {
+    let call_count = Arc::new(AtomicUsize::new(0));
+    let stream_fn_calls = call_count.clone();
+    let stream_fn = Arc::new(
+        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            if stream_fn_calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                let tool_calls = [
+                    ("call_bad", json!({"count": "0"})),
+                    ("call_ok", json!({"count": "2"})),
+                ]
+                .into_iter()
+                .map(|(id, arguments)| AssistantContentBlock::ToolCall {
+                    id: id.to_string(),
+                    name: "repeat".to_string(),
+                    arguments,
+                    thought_signature: None,
+                })
+                .collect();
+                Ok(done_stream(
+                    assistant_message(tool_calls, StopReason::ToolUse, 1_700_000_000_020),
+                    DoneReason::ToolUse,
+                ))
+            } else {
+                let final_msg = assistant_message(
+                    vec![AssistantContentBlock::Text {
+                        text: "done".to_string(),
+                        text_signature: None,
+                    }],
+                    StopReason::Stop,
+                    1_700_000_000_030,
+                );
+                Ok(done_stream(final_msg, DoneReason::Stop))
+            }
+        },
+    );
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let tool_received = received.clone();
+    let tool = AgentTool {
+        name: "repeat".to_string(),
+        label: "Repeat".to_string(),
+        description: "Repeat a word".to_string(),
+        parameters: json!({
+            "type": "object",
+            "properties": {
+                "count": { "type": "integer", "minimum": 1 },
+                "word": { "type": "string", "default": "hi" }
+            },
+            "required": ["count"]
+        }),
+        conflict_key: None,
+        execute:
+            Arc::new(
+                move |_tool_call_id: String,
+                      args: Value|
+                      -> Pin<
+                    Box<dyn Future<Output = Result<AgentToolResult, PiAiError>> + Send>,
+                > {
+                    tool_received.lock().expect("received lock").push(args);
+                    Box::pin(async move {
+                        Ok(AgentToolResult {
+                            content: vec![ToolResultContentBlock::Text {
+                                text: "repeated".to_string(),
+                                text_signature: None,
+                            }],
+                            details: json!({}),
+                        })
+                    })
+                },
+            ),
+    };
+
+    let config = AgentLoopConfig {
+        stream_fn,
+        tool_validation: Some(ToolValidationOptions::lenient()),
+        ..default_loop_config()
+    };
+    let context = AgentContext {
+        system_prompt: "You are helpful".to_string(),
+        messages: vec![],
+        tools: vec![tool],
+    };
+    let stream = agent_loop(
+        vec![user_message("repeat", 1_700_000_000_000)],
+        context,
+        config,
+        None,
+    );
+    let (_events, result) = collect_events_and_result(stream).await;
+
+    assert_eq!(
+        *received.lock().expect("received lock"),
+        vec![json!({"count": 2, "word": "hi"})]
+    );
+    let tool_results = result
+        .iter()
+        .filter_map(|message| match message {
+            Message::ToolResult {
+                tool_call_id,
+                content,
+                is_error,
+                ..
+            } => Some((tool_call_id.as_str(), content, *is_error)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(tool_results.len(), 2);
+    let (id, content, is_error) = tool_results[0];
+    assert_eq!(id, "call_bad");
+    assert!(is_error);
+    assert!(matches!(
+        &content[0],
+        ToolResultContentBlock::Text { text, .. } if text.contains("- /count: ")
+    ));
+    assert_eq!(tool_results[1].0, "call_ok");
+    assert!(!tool_results[1].2);
+}
+
+#[tokio::test]
+async fn agent_loop_runs_tool_calls_concurrently_and_keeps_result_order() {
+    let call_count = Arc::new(AtomicUsize::new(0));
+    let stream_fn_calls = call_count.clone();
+
+    let stream_fn = Arc::new(
+        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let index = stream_fn_calls.fetch_add(1, Ordering::SeqCst);
+            if index == 0 {
+                let tool_call = |id: &str, delay_ms: u64| AssistantContentBlock::ToolCall {
+                    id: id.to_string(),
+                    name: "wait".to_string(),
+                    arguments: json!({ "delayMs": delay_ms }),
+                    thought_signature: None,
+                };
+                let tool_call_msg = assistant_message(
+                    vec![
+                        tool_call("call_1", 60),
+                        tool_call("call_2", 5),
+                        tool_call("call_3", 5),
+                    ],
+                    StopReason::ToolUse,
+                    1_700_000_000_020,
+                );
+                Ok(done_stream(tool_call_msg, DoneReason::ToolUse))
+            } else {
+                let final_msg = assistant_message(
+                    vec![AssistantContentBlock::Text {
+                        text: "done".to_string(),
+                        text_signature: None,
+                    }],
+                    StopReason::Stop,
+                    1_700_000_000_030,
+                );
+                Ok(done_stream(final_msg, DoneReason::Stop))
+            }
+        },
+    );
+
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let max_in_flight = Arc::new(AtomicUsize::new(0));
+    let tool_in_flight = in_flight.clone();
+    let tool_max_in_flight = max_in_flight.clone();
+    let tool = AgentTool {
+        name: "wait".to_string(),
+        label: "Wait".to_string(),
+        description: "Wait for a while".to_string(),
+        parameters: json!({ "type": "object" }),
+        conflict_key: None,
+        execute:
+            Arc::new(
+                move |tool_call_id: String,
+                      args: Value|
+                      -> Pin<
+                    Box<dyn Future<Output = Result<AgentToolResult, PiAiError>> + Send>,
+                > {
+                    let in_flight = tool_in_flight.clone();
+                    let max_in_flight = tool_max_in_flight.clone();
+                    Box::pin(async move {
+                        let running = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_in_flight.fetch_max(running, Ordering::SeqCst);
+                        sleep(Duration::from_millis(args["delayMs"].as_u64().unwrap_or(0))).await;
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                        Ok(AgentToolResult {
+                            content: vec![ToolResultContentBlock::Text {
+                                text: tool_call_id,
+                                text_signature: None,
+                            }],
+                            details: json!({}),
+                        })
+                    })
+                },
+            ),
+    };
+
+    let context = AgentContext {
+        system_prompt: "You are helpful".to_string(),
+        messages: vec![],
+        tools: vec![tool],
+    };
+    let config = AgentLoopConfig {
+        stream_fn,
+        tool_parallelism: 2,
+        tool_validation: None,
+        ..default_loop_config()
+    };
+
+    let stream = agent_loop(
+        vec![user_message("wait", 1_700_000_000_000)],
+        context,
+        config,
+        None,
+    );
+    let (_events, result) = collect_events_and_result(stream).await;
+
+    assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    let tool_result_ids = result
+        .iter()
+        .filter_map(|message| match message {
+            Message::ToolResult { tool_call_id, .. } => Some(tool_call_id.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(tool_result_ids, vec!["call_1", "call_2", "call_3"]);
+}
+
+#[tokio::test]
+async fn agent_loop_forwards_tool_progress_as_execution_updates() {
+    let call_count = Arc::new(AtomicUsize::new(0));
+    let stream_fn_calls = call_count.clone();
+    let stream_fn = Arc::new(
+        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            if stream_fn_calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                let tool_call_msg = assistant_message(
+                    vec![AssistantContentBlock::ToolCall {
+                        id: "call_1".to_string(),
+                        name: "build".to_string(),
+                        arguments: json!({}),
+                        thought_signature: None,
+                    }],
+                    StopReason::ToolUse,
+                    1_700_000_000_020,
+                );
+                Ok(done_stream(tool_call_msg, DoneReason::ToolUse))
+            } else {
+                let final_msg = assistant_message(
+                    vec![AssistantContentBlock::Text {
+                        text: "built".to_string(),
+                        text_signature: None,
+                    }],
+                    StopReason::Stop,
+                    1_700_000_000_030,
+                );
+                Ok(done_stream(final_msg, DoneReason::Stop))
+            }
+        },
+    );
+
+    let tool =
+        AgentTool {
+            name: "build".to_string(),
+            label: "Build".to_string(),
+            description: "Run the build".to_string(),
+            parameters: json!({ "type": "object" }),
+            conflict_key: None,
+            execute:
+                Arc::new(
+                    |_tool_call_id: String,
+                     _args: Value|
+                     -> Pin<
+                        Box<dyn Future<Output = Result<AgentToolResult, PiAiError>> + Send>,
+                    > {
+                        Box::pin(async move {
+                            let progress =
+                                ToolProgress::current().expect("progress channel in scope");
+                            progress.report(json!({ "line": "compiling" }));
+                            progress.report(json!({ "line": "linking" }));
+                            ToolOutputStream::current()
+                                .expect("output stream in scope")
+                                .line(ToolOutputKind::Stderr, "warning: unused");
+                            Ok(AgentToolResult {
+                                content: vec![ToolResultContentBlock::Text {
+                                    text: "ok".to_string(),
+                                    text_signature: None,
+                                }],
+                                details: json!({}),
+                            })
+                        })
+                    },
+                ),
+        };
+    assert!(ToolProgress::current().is_none());
+
+    let context = AgentContext {
+        system_prompt: "You are helpful".to_string(),
+        messages: vec![],
+        tools: vec![tool],
+    };
+    let config = AgentLoopConfig {
+        stream_fn,
+        ..default_loop_config()
+    };
+    let stream = agent_loop(
+        vec![user_message("build it", 1_700_000_000_000)],
+        context,
+        config,
+        None,
+    );
+    let (events, _) = collect_events_and_result(stream).await;
+
+    let tool_events = events
+        .iter()
+        .filter_map(|event| match event {
+            AgentEvent::ToolExecutionStart { .. } => Some("start".to_string()),
+            AgentEvent::ToolExecutionUpdate {
+                tool_call_id,
+                tool_name,
+                partial_result,
+                ..
+            } => {
+                assert_eq!(tool_call_id, "call_1");
+                assert_eq!(tool_name, "build");
+                partial_result["line"].as_str().map(str::to_string)
+            }
+            AgentEvent::ToolExecutionEnd { .. } => Some("end".to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tool_events,
+        vec!["start", "compiling", "linking", "warning: unused", "end"]
+    );
+    assert!(events.iter().any(|event| matches!(
+        event,
+        AgentEvent::ToolExecutionUpdate { partial_result, .. }
+            if *partial_result == json!({ "stream": "stderr", "line": "warning: unused" })
+    )));
+}
+
 #[tokio::test]
 async fn agent_loop_continue_reuses_existing_context_messages() {
     let observed_message_count = Arc::new(AtomicUsize::new(0));
@@ -351,8 +767,17 @@ async fn agent_loop_continue_reuses_existing_context_messages() {
         convert_to_llm: Arc::new(|messages| messages),
         stream_fn,
         retry: AgentRetryConfig::default(),
+        tool_parallelism: 1,
+        tool_validation: None,
         get_steering_messages: None,
         get_follow_up_messages: None,
+        turn_timeout: None,
+        run_timeout: None,
+        tool_policy: None,
+        tool_approval: None,
+        loop_guard: None,
+        hooks: None,
+        context_compaction: None,
     };
 
     let stream = agent_loop_continue(context, config, None);
@@ -407,8 +832,17 @@ async fn agent_loop_auto_continues_when_stop_reason_is_length() {
         convert_to_llm: Arc::new(|messages| messages),
         stream_fn,
         retry: AgentRetryConfig::default(),
+        tool_parallelism: 1,
+        tool_validation: None,
         get_steering_messages: None,
         get_follow_up_messages: None,
+        turn_timeout: None,
+        run_timeout: None,
+        tool_policy: None,
+        tool_approval: None,
+        loop_guard: None,
+        hooks: None,
+        context_compaction: None,
     };
 
     let stream = agent_loop(prompts, context, config, None);
@@ -481,6 +915,7 @@ async fn agent_loop_ignores_tool_calls_when_stop_reason_is_length() {
             "required": ["path", "content"],
             "additionalProperties": false
         }),
+        conflict_key: None,
         execute:
             Arc::new(
                 move |_tool_call_id: String,
@@ -516,8 +951,17 @@ async fn agent_loop_ignores_tool_calls_when_stop_reason_is_length() {
         convert_to_llm: Arc::new(|messages| messages),
         stream_fn,
         retry: AgentRetryConfig::default(),
+        tool_parallelism: 1,
+        tool_validation: None,
         get_steering_messages: None,
         get_follow_up_messages: None,
+        turn_timeout: None,
+        run_timeout: None,
+        tool_policy: None,
+        tool_approval: None,
+        loop_guard: None,
+        hooks: None,
+        context_compaction: None,
     };
 
     let stream = agent_loop(prompts, context, config, None);
@@ -568,6 +1012,7 @@ fn try_agent_loop_continue_rejects_assistant_tail_context() {
             stop_reason: StopReason::Stop,
             error_message: None,
             timestamp: 1_700_000_000_010,
+            stats: None,
         }],
         tools: vec![],
     };
@@ -638,6 +1083,7 @@ async fn agent_loop_skips_remaining_tool_calls_when_steering_arrives() {
             "required": ["value"],
             "additionalProperties": false
         }),
+        conflict_key: None,
         execute:
             Arc::new(
                 move |_tool_call_id: String,
@@ -683,8 +1129,17 @@ async fn agent_loop_skips_remaining_tool_calls_when_steering_arrives() {
         convert_to_llm: Arc::new(|messages| messages),
         stream_fn,
         retry: AgentRetryConfig::default(),
+        tool_parallelism: 1,
+        tool_validation: None,
         get_steering_messages: Some(get_steering_messages),
         get_follow_up_messages: None,
+        turn_timeout: None,
+        run_timeout: None,
+        tool_policy: None,
+        tool_approval: None,
+        loop_guard: None,
+        hooks: None,
+        context_compaction: None,
     };
 
     let stream = agent_loop(prompts, context, config, None);
@@ -773,8 +1228,17 @@ async fn agent_loop_processes_follow_up_messages_after_turn_completion() {
         convert_to_llm: Arc::new(|messages| messages),
         stream_fn,
         retry: AgentRetryConfig::default(),
+        tool_parallelism: 1,
+        tool_validation: None,
         get_steering_messages: None,
         get_follow_up_messages: Some(get_follow_up_messages),
+        turn_timeout: None,
+        run_timeout: None,
+        tool_policy: None,
+        tool_approval: None,
+        loop_guard: None,
+        hooks: None,
+        context_compaction: None,
     };
 
     let stream = agent_loop(prompts, context, config, None);
@@ -833,8 +1297,17 @@ async fn agent_loop_abort_signal_interrupts_assistant_streaming() {
         convert_to_llm: Arc::new(|messages| messages),
         stream_fn,
         retry: AgentRetryConfig::default(),
+        tool_parallelism: 1,
+        tool_validation: None,
         get_steering_messages: None,
         get_follow_up_messages: None,
+        turn_timeout: None,
+        run_timeout: None,
+        tool_policy: None,
+        tool_approval: None,
+        loop_guard: None,
+        hooks: None,
+        context_compaction: None,
     };
 
     let controller = AgentAbortController::new();
@@ -889,6 +1362,7 @@ async fn agent_loop_abort_signal_interrupts_tool_execution() {
                 "required": ["value"],
                 "additionalProperties": false
             }),
+            conflict_key: None,
             execute:
                 Arc::new(
                     |_tool_call_id: String,
@@ -922,8 +1396,17 @@ async fn agent_loop_abort_signal_interrupts_tool_execution() {
         convert_to_llm: Arc::new(|messages| messages),
         stream_fn,
         retry: AgentRetryConfig::default(),
+        tool_parallelism: 1,
+        tool_validation: None,
         get_steering_messages: None,
         get_follow_up_messages: None,
+        turn_timeout: None,
+        run_timeout: None,
+        tool_policy: None,
+        tool_approval: None,
+        loop_guard: None,
+        hooks: None,
+        context_compaction: None,
     };
 
     let controller = AgentAbortController::new();
@@ -960,55 +1443,735 @@ async fn agent_loop_abort_signal_interrupts_tool_execution() {
 }
 
 #[tokio::test]
-async fn agent_loop_retries_stream_creation_errors_with_backoff() {
-    let attempts = Arc::new(AtomicUsize::new(0));
-    let attempts_in_fn = attempts.clone();
+async fn agent_loop_soft_abort_lets_in_flight_tool_finish() {
     let stream_fn = Arc::new(
-        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
-            let attempt = attempts_in_fn.fetch_add(1, Ordering::SeqCst);
-            if attempt == 0 {
-                return Err(provider_transport_error("temporary upstream failure"));
-            }
+        |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
             let message = assistant_message(
-                vec![AssistantContentBlock::Text {
-                    text: "retry success".to_string(),
-                    text_signature: None,
-                }],
-                StopReason::Stop,
-                1_700_000_000_010 + attempt as i64,
+                vec![
+                    AssistantContentBlock::ToolCall {
+                        id: "call_1".to_string(),
+                        name: "long_tool".to_string(),
+                        arguments: json!({"value": 1}),
+                        thought_signature: None,
+                    },
+                    AssistantContentBlock::ToolCall {
+                        id: "call_2".to_string(),
+                        name: "long_tool".to_string(),
+                        arguments: json!({"value": 2}),
+                        thought_signature: None,
+                    },
+                ],
+                StopReason::ToolUse,
+                1_700_000_000_010,
             );
-            Ok(done_stream(message, DoneReason::Stop))
+            Ok(done_stream(message, DoneReason::ToolUse))
         },
     );
 
-    let config = AgentLoopConfig {
-        model: sample_model("test-api"),
-        fallback_models: vec![],
-        convert_to_llm: Arc::new(|messages| messages),
-        stream_fn,
-        retry: AgentRetryConfig {
-            max_attempts: 2,
-            initial_backoff_ms: 1,
-            max_backoff_ms: 1,
-        },
-        get_steering_messages: None,
-        get_follow_up_messages: None,
-    };
-
-    let prompts = vec![user_message("hello", 1_700_000_000_000)];
-    let context = AgentContext {
-        system_prompt: "You are helpful".to_string(),
-        messages: vec![],
-        tools: vec![],
-    };
-    let stream = agent_loop(prompts, context, config, None);
-    let (events, result) = collect_events_and_result(stream).await;
-
-    assert_eq!(attempts.load(Ordering::SeqCst), 2, "should retry once");
-    assert!(
-        events.iter().any(|event| {
-            matches!(
-                event,
+    let tool =
+        AgentTool {
+            name: "long_tool".to_string(),
+            label: "Long Tool".to_string(),
+            description: "Long running tool".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "value": { "type": "integer" }
+                },
+                "required": ["value"],
+                "additionalProperties": false
+            }),
+            conflict_key: None,
+            execute:
+                Arc::new(
+                    |_tool_call_id: String,
+                     _args: Value|
+                     -> Pin<
+                        Box<dyn Future<Output = Result<AgentToolResult, PiAiError>> + Send>,
+                    > {
+                        Box::pin(async move {
+                            let child = AgentAbortSignal::current()
+                                .expect("tools run with the loop's abort signal")
+                                .child_controller()
+                                .signal();
+                            let _ =
+                                tokio::time::timeout(Duration::from_millis(250), child.cancelled())
+                                    .await;
+                            let text = match child.reason() {
+                                Some(AbortReason::ParentCancelled) if child.is_soft() => "done",
+                                _ => "child run was not soft-cancelled",
+                            };
+                            Ok(AgentToolResult {
+                                content: vec![ToolResultContentBlock::Text {
+                                    text: text.to_string(),
+                                    text_signature: None,
+                                }],
+                                details: json!({}),
+                            })
+                        })
+                    },
+                ),
+        };
+
+    let prompts = vec![user_message("run", 1_700_000_000_000)];
+    let context = AgentContext {
+        system_prompt: "You are helpful".to_string(),
+        messages: vec![],
+        tools: vec![tool],
+    };
+    let config = AgentLoopConfig {
+        model: sample_model("test-api"),
+        fallback_models: vec![],
+        convert_to_llm: Arc::new(|messages| messages),
+        stream_fn,
+        retry: AgentRetryConfig::default(),
+        tool_parallelism: 1,
+        tool_validation: None,
+        get_steering_messages: None,
+        get_follow_up_messages: None,
+        turn_timeout: None,
+        run_timeout: None,
+        tool_policy: None,
+        tool_approval: None,
+        loop_guard: None,
+        hooks: None,
+        context_compaction: None,
+    };
+
+    let controller = AgentAbortController::new();
+    let signal = controller.signal();
+    let stream = agent_loop(prompts, context, config, Some(signal.clone()));
+
+    tokio::spawn(async move {
+        sleep(Duration::from_millis(20)).await;
+        controller.soft_abort(AbortReason::Timeout);
+    });
+
+    let (_events, result) = collect_events_and_result(stream).await;
+    let tool_results = result
+        .iter()
+        .filter_map(|message| match message {
+            Message::ToolResult {
+                tool_call_id,
+                content,
+                is_error,
+                ..
+            } => Some((tool_call_id.as_str(), content.clone(), *is_error)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(signal.reason(), Some(AbortReason::Timeout));
+    assert!(signal.is_soft());
+    assert_eq!(tool_results.len(), 2);
+    assert_eq!(tool_results[0].0, "call_1");
+    assert!(!tool_results[0].2, "in-flight tool should finish");
+    assert!(matches!(
+        tool_results[0].1.first(),
+        Some(ToolResultContentBlock::Text { text, .. }) if text == "done"
+    ));
+    assert_eq!(tool_results[1].0, "call_2");
+    assert!(tool_results[1].2, "remaining tool call should be skipped");
+}
+
+#[test]
+fn abort_controller_records_first_reason_and_upgrades_soft_abort() {
+    let controller = AgentAbortController::new();
+    let signal = controller.signal();
+    assert_eq!(signal.reason(), None);
+
+    controller.soft_abort(AbortReason::BudgetExceeded);
+    assert!(signal.is_aborted() && signal.is_soft());
+
+    controller.abort_with(AbortReason::ParentCancelled);
+    assert!(!signal.is_soft());
+    assert_eq!(signal.reason(), Some(AbortReason::BudgetExceeded));
+
+    controller.soft_abort(AbortReason::Timeout);
+    assert!(!signal.is_soft(), "a hard abort cannot be softened");
+}
+
+#[test]
+fn child_abort_controllers_follow_their_parent_but_not_the_other_way() {
+    let parent = AgentAbortController::new();
+    let first = parent.signal().child_controller();
+    let second = parent.signal().child_controller();
+    let grandchild = first.signal().child_controller();
+
+    second.abort();
+    assert!(!parent.signal().is_aborted());
+    assert!(!first.signal().is_aborted());
+
+    parent.soft_abort(AbortReason::UserInterrupt);
+    assert!(first.signal().is_soft());
+    assert_eq!(first.signal().reason(), Some(AbortReason::ParentCancelled));
+    assert!(grandchild.signal().is_soft());
+    assert_eq!(second.signal().reason(), Some(AbortReason::UserInterrupt));
+
+    parent.abort();
+    assert!(
+        !first.signal().is_soft(),
+        "a hard parent abort upgrades children"
+    );
+    assert!(!grandchild.signal().is_soft());
+
+    let late = parent.signal().child_controller();
+    assert!(late.signal().is_aborted() && !late.signal().is_soft());
+}
+
+#[tokio::test]
+async fn agent_loop_turn_timeout_stops_a_stuck_stream_and_keeps_partial_output() {
+    let stream_fn = Arc::new(
+        |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let stream = AssistantMessageEventStream::new();
+            let partial = assistant_message(
+                vec![AssistantContentBlock::Text {
+                    text: "partial".to_string(),
+                    text_signature: None,
+                }],
+                StopReason::Stop,
+                1_700_000_000_010,
+            );
+            stream.push(AssistantMessageEvent::Start { partial });
+            Ok(stream)
+        },
+    );
+    let config = AgentLoopConfig {
+        stream_fn,
+        turn_timeout: Some(Duration::from_millis(50)),
+        ..default_loop_config()
+    };
+    let context = AgentContext {
+        system_prompt: "You are helpful".to_string(),
+        messages: vec![],
+        tools: vec![],
+    };
+
+    let stream = agent_loop(
+        vec![user_message("hello", 1_700_000_000_000)],
+        context,
+        config,
+        None,
+    );
+    let (events, result) = collect_events_and_result(stream).await;
+
+    assert!(events.iter().any(|event| matches!(
+        event,
+        AgentEvent::TimedOut {
+            scope: TimeoutScope::Turn,
+            timeout_ms: 50,
+        }
+    )));
+    assert!(matches!(
+        result.last(),
+        Some(Message::Assistant {
+            content,
+            stop_reason: StopReason::Aborted,
+            error_message: Some(error),
+            ..
+        }) if error == "Turn timed out after 50ms"
+            && matches!(content.first(), Some(AssistantContentBlock::Text { text, .. }) if text == "partial")
+    ));
+}
+
+#[tokio::test]
+async fn agent_loop_run_timeout_stops_tool_execution() {
+    let stream_fn = Arc::new(
+        |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let message = assistant_message(
+                vec![AssistantContentBlock::ToolCall {
+                    id: "call_1".to_string(),
+                    name: "slow_tool".to_string(),
+                    arguments: json!({}),
+                    thought_signature: None,
+                }],
+                StopReason::ToolUse,
+                1_700_000_000_010,
+            );
+            Ok(done_stream(message, DoneReason::ToolUse))
+        },
+    );
+    let tool =
+        AgentTool {
+            name: "slow_tool".to_string(),
+            label: "Slow Tool".to_string(),
+            description: "Never finishes in time".to_string(),
+            parameters: json!({ "type": "object" }),
+            conflict_key: None,
+            execute:
+                Arc::new(
+                    |_tool_call_id: String,
+                     _args: Value|
+                     -> Pin<
+                        Box<dyn Future<Output = Result<AgentToolResult, PiAiError>> + Send>,
+                    > {
+                        Box::pin(async move {
+                            sleep(Duration::from_secs(5)).await;
+                            Ok(AgentToolResult {
+                                content: vec![],
+                                details: json!({}),
+                            })
+                        })
+                    },
+                ),
+        };
+    let config = AgentLoopConfig {
+        stream_fn,
+        run_timeout: Some(Duration::from_millis(50)),
+        ..default_loop_config()
+    };
+    let context = AgentContext {
+        system_prompt: "You are helpful".to_string(),
+        messages: vec![],
+        tools: vec![tool],
+    };
+
+    let stream = agent_loop(
+        vec![user_message("run", 1_700_000_000_000)],
+        context,
+        config,
+        None,
+    );
+    let (events, result) = collect_events_and_result(stream).await;
+
+    assert!(events.iter().any(|event| matches!(
+        event,
+        AgentEvent::TimedOut {
+            scope: TimeoutScope::Run,
+            ..
+        }
+    )));
+    assert!(matches!(
+        result.last(),
+        Some(Message::ToolResult { is_error: true, .. })
+    ));
+}
+
+#[tokio::test]
+async fn agent_loop_tool_policy_refuses_denied_tools_without_running_them() {
+    let stream_fn = Arc::new(
+        |_model: Model, context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let done = context
+                .messages
+                .iter()
+                .any(|message| matches!(message, Message::ToolResult { .. }));
+            let message = if done {
+                assistant_message(
+                    vec![AssistantContentBlock::Text {
+                        text: "ok".to_string(),
+                        text_signature: None,
+                    }],
+                    StopReason::Stop,
+                    1_700_000_000_020,
+                )
+            } else {
+                assistant_message(
+                    vec![AssistantContentBlock::ToolCall {
+                        id: "call_1".to_string(),
+                        name: "bash".to_string(),
+                        arguments: json!({ "command": "rm -rf /" }),
+                        thought_signature: None,
+                    }],
+                    StopReason::ToolUse,
+                    1_700_000_000_010,
+                )
+            };
+            let reason = if done {
+                DoneReason::Stop
+            } else {
+                DoneReason::ToolUse
+            };
+            Ok(done_stream(message, reason))
+        },
+    );
+    let runs = Arc::new(AtomicUsize::new(0));
+    let runs_in_tool = runs.clone();
+    let tool = AgentTool {
+        name: "bash".to_string(),
+        label: "Bash".to_string(),
+        description: "Runs a command".to_string(),
+        parameters: json!({ "type": "object" }),
+        conflict_key: None,
+        execute:
+            Arc::new(
+                move |_tool_call_id: String,
+                      _args: Value|
+                      -> Pin<
+                    Box<dyn Future<Output = Result<AgentToolResult, PiAiError>> + Send>,
+                > {
+                    runs_in_tool.fetch_add(1, Ordering::SeqCst);
+                    Box::pin(async move {
+                        Ok(AgentToolResult {
+                            content: vec![],
+                            details: json!({}),
+                        })
+                    })
+                },
+            ),
+    };
+    let config = AgentLoopConfig {
+        stream_fn,
+        tool_policy: Some(ToolPolicy {
+            deny_tools: vec!["bash".to_string()],
+            ..ToolPolicy::default()
+        }),
+        ..default_loop_config()
+    };
+    let context = AgentContext {
+        system_prompt: "You are helpful".to_string(),
+        messages: vec![],
+        tools: vec![tool],
+    };
+
+    let stream = agent_loop(
+        vec![user_message("clean up", 1_700_000_000_000)],
+        context,
+        config,
+        None,
+    );
+    let (_events, result) = collect_events_and_result(stream).await;
+
+    assert_eq!(runs.load(Ordering::SeqCst), 0);
+    assert!(result.iter().any(|message| matches!(
+        message,
+        Message::ToolResult { is_error: true, content, .. }
+            if matches!(content.first(), Some(ToolResultContentBlock::Text { text, .. }) if text.contains("not allowed by the tool policy"))
+    )));
+}
+
+/// Runs one `bash` call flagged as high risk under `approval`.
+async fn run_bash_with_approval(approval: ToolApproval) -> (usize, Vec<AgentEvent>, Vec<Message>) {
+    let stream_fn = Arc::new(
+        |_model: Model, context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let done = context
+                .messages
+                .iter()
+                .any(|message| matches!(message, Message::ToolResult { .. }));
+            let message = if done {
+                assistant_message(
+                    vec![AssistantContentBlock::Text {
+                        text: "ok".to_string(),
+                        text_signature: None,
+                    }],
+                    StopReason::Stop,
+                    1_700_000_000_020,
+                )
+            } else {
+                assistant_message(
+                    vec![AssistantContentBlock::ToolCall {
+                        id: "call_1".to_string(),
+                        name: "bash".to_string(),
+                        arguments: json!({ "command": "rm -rf /" }),
+                        thought_signature: None,
+                    }],
+                    StopReason::ToolUse,
+                    1_700_000_000_010,
+                )
+            };
+            let reason = if done {
+                DoneReason::Stop
+            } else {
+                DoneReason::ToolUse
+            };
+            Ok(done_stream(message, reason))
+        },
+    );
+    let runs = Arc::new(AtomicUsize::new(0));
+    let runs_in_tool = runs.clone();
+    let tool = AgentTool {
+        name: "bash".to_string(),
+        label: "Bash".to_string(),
+        description: "Runs a command".to_string(),
+        parameters: json!({ "type": "object" }),
+        conflict_key: None,
+        execute:
+            Arc::new(
+                move |_tool_call_id: String,
+                      _args: Value|
+                      -> Pin<
+                    Box<dyn Future<Output = Result<AgentToolResult, PiAiError>> + Send>,
+                > {
+                    runs_in_tool.fetch_add(1, Ordering::SeqCst);
+                    Box::pin(async move {
+                        Ok(AgentToolResult {
+                            content: vec![],
+                            details: json!({}),
+                        })
+                    })
+                },
+            ),
+    };
+    let config = AgentLoopConfig {
+        stream_fn,
+        tool_approval: Some(approval),
+        ..default_loop_config()
+    };
+    let context = AgentContext {
+        system_prompt: "You are helpful".to_string(),
+        messages: vec![],
+        tools: vec![tool],
+    };
+
+    let stream = agent_loop(
+        vec![user_message("clean up", 1_700_000_000_000)],
+        context,
+        config,
+        None,
+    );
+    let (events, result) = collect_events_and_result(stream).await;
+    (runs.load(Ordering::SeqCst), events, result)
+}
+
+/// Runs a model that reads the same file forever, guarded by `loop_guard`.
+async fn run_repeating_reads(loop_guard: ToolLoopGuard) -> (usize, Vec<Message>) {
+    let turns = Arc::new(AtomicUsize::new(0));
+    let stream_fn = Arc::new(
+        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let turn = turns.fetch_add(1, Ordering::SeqCst);
+            Ok(done_stream(
+                assistant_message(
+                    vec![AssistantContentBlock::ToolCall {
+                        id: format!("call_{turn}"),
+                        name: "read".to_string(),
+                        arguments: json!({ "path": "src/lib.rs" }),
+                        thought_signature: None,
+                    }],
+                    StopReason::ToolUse,
+                    1_700_000_000_010,
+                ),
+                DoneReason::ToolUse,
+            ))
+        },
+    );
+    let runs = Arc::new(AtomicUsize::new(0));
+    let runs_in_tool = runs.clone();
+    let tool = AgentTool {
+        name: "read".to_string(),
+        label: "Read".to_string(),
+        description: "Reads a file".to_string(),
+        parameters: json!({ "type": "object" }),
+        conflict_key: None,
+        execute:
+            Arc::new(
+                move |_tool_call_id: String,
+                      _args: Value|
+                      -> Pin<
+                    Box<dyn Future<Output = Result<AgentToolResult, PiAiError>> + Send>,
+                > {
+                    runs_in_tool.fetch_add(1, Ordering::SeqCst);
+                    Box::pin(async move {
+                        Ok(AgentToolResult {
+                            content: vec![],
+                            details: json!({}),
+                        })
+                    })
+                },
+            ),
+    };
+    let config = AgentLoopConfig {
+        stream_fn,
+        loop_guard: Some(loop_guard),
+        ..default_loop_config()
+    };
+    let context = AgentContext {
+        system_prompt: "You are helpful".to_string(),
+        messages: vec![],
+        tools: vec![tool],
+    };
+
+    let stream = agent_loop(
+        vec![user_message("read it", 1_700_000_000_000)],
+        context,
+        config,
+        None,
+    );
+    let (_events, result) = collect_events_and_result(stream).await;
+    (runs.load(Ordering::SeqCst), result)
+}
+
+fn loop_guard_error(messages: &[Message]) -> Option<PiAiError> {
+    match messages.last() {
+        Some(Message::Assistant {
+            stop_reason: StopReason::Error,
+            error_message: Some(error),
+            ..
+        }) => serde_json::from_str(error).ok(),
+        _ => None,
+    }
+}
+
+#[tokio::test]
+async fn agent_loop_warns_then_stops_on_repeated_identical_tool_calls() {
+    let (runs, result) = run_repeating_reads(ToolLoopGuard {
+        max_identical_turns: Some(2),
+        ..ToolLoopGuard::default()
+    })
+    .await;
+
+    assert_eq!(runs, 2, "the third identical turn is skipped");
+    let warnings = result
+        .iter()
+        .filter(|message| matches!(
+            message,
+            Message::User { content: UserContent::Text(text), .. } if text.contains("<loop_guard>")
+        ))
+        .count();
+    assert_eq!(warnings, 1);
+    let error = loop_guard_error(&result).expect("run ends with a loop guard error");
+    assert_eq!(error.code, PiAiErrorCode::ToolLoopDetected);
+    assert!(error.message.contains("3 identical read calls"));
+}
+
+#[tokio::test]
+async fn agent_loop_stops_after_max_tool_iterations() {
+    let (runs, result) = run_repeating_reads(ToolLoopGuard {
+        max_tool_iterations: Some(4),
+        ..ToolLoopGuard::default()
+    })
+    .await;
+
+    assert_eq!(runs, 4);
+    let error = loop_guard_error(&result).expect("run ends with a loop guard error");
+    assert_eq!(error.code, PiAiErrorCode::ToolLoopDetected);
+    assert_eq!(error.message, "Stopped after 4 tool iterations in one run");
+    let call_ids = result
+        .iter()
+        .filter_map(|message| match message {
+            Message::Assistant { content, .. } => Some(ToolCall::from_content(content)),
+            _ => None,
+        })
+        .flatten()
+        .map(|call| call.id)
+        .collect::<Vec<_>>();
+    let result_ids = result
+        .iter()
+        .filter_map(|message| match message {
+            Message::ToolResult { tool_call_id, .. } => Some(tool_call_id.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        result_ids, call_ids,
+        "the skipped fifth call still gets a result"
+    );
+}
+
+#[tokio::test]
+async fn agent_loop_runs_approved_tool_calls_after_the_approval_request() {
+    let (runs, events, _result) =
+        run_bash_with_approval(answering(ApprovalDecision::Approve)).await;
+
+    assert_eq!(runs, 1);
+    assert!(events.iter().any(|event| matches!(
+        event,
+        AgentEvent::ApprovalRequest { call, risk: ToolRisk::High } if call.name == "bash"
+    )));
+}
+
+#[tokio::test]
+async fn agent_loop_reports_denied_tool_calls_to_the_model() {
+    let (runs, _events, result) = run_bash_with_approval(answering(ApprovalDecision::Deny {
+        reason: Some("use trash instead".to_string()),
+    }))
+    .await;
+
+    assert_eq!(runs, 0);
+    assert!(result.iter().any(|message| matches!(
+        message,
+        Message::ToolResult { is_error: true, content, .. }
+            if matches!(content.first(), Some(ToolResultContentBlock::Text { text, .. })
+                if text.contains("denied the bash call") && text.contains("use trash instead"))
+    )));
+}
+
+#[tokio::test]
+async fn agent_loop_refuses_flagged_tools_when_nobody_can_approve() {
+    let (runs, events, result) =
+        run_bash_with_approval(ToolApproval::unattended(HashMap::from([(
+            "bash".to_string(),
+            ToolRisk::High,
+        )])))
+        .await;
+
+    assert_eq!(runs, 0);
+    assert!(!events
+        .iter()
+        .any(|event| matches!(event, AgentEvent::ApprovalRequest { .. })));
+    assert!(result.iter().any(|message| matches!(
+        message,
+        Message::ToolResult { is_error: true, content, .. }
+            if matches!(content.first(), Some(ToolResultContentBlock::Text { text, .. })
+                if text.contains("nobody can approve it"))
+    )));
+}
+
+/// Flags `bash` as high risk and answers its approval with `decision`.
+fn answering(decision: ApprovalDecision) -> ToolApproval {
+    ToolApproval::new(
+        HashMap::from([("bash".to_string(), ToolRisk::High)]),
+        move |request| request.respond(decision.clone()),
+    )
+}
+
+#[tokio::test]
+async fn agent_loop_retries_stream_creation_errors_with_backoff() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let attempts_in_fn = attempts.clone();
+    let stream_fn = Arc::new(
+        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let attempt = attempts_in_fn.fetch_add(1, Ordering::SeqCst);
+            if attempt == 0 {
+                return Err(provider_transport_error("temporary upstream failure"));
+            }
+            let message = assistant_message(
+                vec![AssistantContentBlock::Text {
+                    text: "retry success".to_string(),
+                    text_signature: None,
+                }],
+                StopReason::Stop,
+                1_700_000_000_010 + attempt as i64,
+            );
+            Ok(done_stream(message, DoneReason::Stop))
+        },
+    );
+
+    let config = AgentLoopConfig {
+        model: sample_model("test-api"),
+        fallback_models: vec![],
+        convert_to_llm: Arc::new(|messages| messages),
+        stream_fn,
+        retry: AgentRetryConfig {
+            max_attempts: 2,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 1,
+        },
+        tool_parallelism: 1,
+        tool_validation: None,
+        get_steering_messages: None,
+        get_follow_up_messages: None,
+        turn_timeout: None,
+        run_timeout: None,
+        tool_policy: None,
+        tool_approval: None,
+        loop_guard: None,
+        hooks: None,
+        context_compaction: None,
+    };
+
+    let prompts = vec![user_message("hello", 1_700_000_000_000)];
+    let context = AgentContext {
+        system_prompt: "You are helpful".to_string(),
+        messages: vec![],
+        tools: vec![],
+    };
+    let stream = agent_loop(prompts, context, config, None);
+    let (events, result) = collect_events_and_result(stream).await;
+
+    assert_eq!(attempts.load(Ordering::SeqCst), 2, "should retry once");
+    assert!(
+        events.iter().any(|event| {
+            matches!(
+                event,
                 AgentEvent::RetryScheduled {
                     attempt: 1,
                     max_attempts: 2,
@@ -1079,8 +2242,17 @@ async fn agent_loop_retries_with_model_fallback() {
             initial_backoff_ms: 0,
             max_backoff_ms: 0,
         },
+        tool_parallelism: 1,
+        tool_validation: None,
         get_steering_messages: None,
         get_follow_up_messages: None,
+        turn_timeout: None,
+        run_timeout: None,
+        tool_policy: None,
+        tool_approval: None,
+        loop_guard: None,
+        hooks: None,
+        context_compaction: None,
     };
 
     let prompts = vec![user_message("hello", 1_700_000_000_000)];
@@ -1168,6 +2340,7 @@ async fn agent_loop_emits_metrics_with_retry_and_tool_duration() {
                 "properties": {},
                 "additionalProperties": false
             }),
+            conflict_key: None,
             execute:
                 Arc::new(
                     |_tool_call_id: String,
@@ -1199,8 +2372,17 @@ async fn agent_loop_emits_metrics_with_retry_and_tool_duration() {
             initial_backoff_ms: 0,
             max_backoff_ms: 0,
         },
+        tool_parallelism: 1,
+        tool_validation: None,
         get_steering_messages: None,
         get_follow_up_messages: None,
+        turn_timeout: None,
+        run_timeout: None,
+        tool_policy: None,
+        tool_approval: None,
+        loop_guard: None,
+        hooks: None,
+        context_compaction: None,
     };
     let prompts = vec![user_message("hello", 1_700_000_000_000)];
     let context = AgentContext {
@@ -1234,8 +2416,453 @@ async fn agent_loop_emits_metrics_with_retry_and_tool_duration() {
     assert_eq!(metrics.assistant_request_count, 2);
     assert_eq!(metrics.tool_execution_count, 1);
     assert_eq!(metrics.retry_count, 1);
+    let tool = &metrics.tools["measure_tool"];
+    assert_eq!((tool.invocation_count, tool.failure_count), (1, 0));
+    assert_eq!(tool.total_ms, tool_duration_ms);
+    assert_eq!(tool.failure_rate(), 0.0);
+    assert_eq!(metrics.turn_tokens, vec![15, 15]);
     assert!(
         metrics.tool_execution_total_ms >= tool_duration_ms,
         "aggregate tool duration should include tool end duration"
     );
 }
+
+#[tokio::test]
+async fn agent_loop_injects_steer_now_messages_before_after_run_messages() {
+    let queued = QueuedMessages::new(QueueMode::All, QueueMode::OneAtATime);
+    let queued_in_stream = queued.clone();
+    let stream_fn_calls = Arc::new(AtomicUsize::new(0));
+    let stream_fn_calls_in_stream = stream_fn_calls.clone();
+    let stream_fn = Arc::new(
+        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            if stream_fn_calls_in_stream.fetch_add(1, Ordering::SeqCst) == 0 {
+                queued_in_stream.push(
+                    user_message("then summarize", 1_700_000_000_010),
+                    QueuePriority::AfterRun,
+                );
+                queued_in_stream.push(
+                    user_message("use rust instead", 1_700_000_000_011),
+                    QueuePriority::SteerNow,
+                );
+            }
+            let message = assistant_message(
+                vec![AssistantContentBlock::Text {
+                    text: "ok".to_string(),
+                    text_signature: None,
+                }],
+                StopReason::Stop,
+                1_700_000_000_020,
+            );
+            Ok(done_stream(message, DoneReason::Stop))
+        },
+    );
+
+    let config = AgentLoopConfig {
+        get_steering_messages: Some(queued.steering_queue()),
+        get_follow_up_messages: Some(queued.follow_up_queue()),
+        stream_fn,
+        ..default_loop_config()
+    };
+    let context = AgentContext {
+        system_prompt: "You are helpful".to_string(),
+        messages: vec![],
+        tools: vec![],
+    };
+    let stream = agent_loop(
+        vec![user_message("write python", 1_700_000_000_000)],
+        context,
+        config,
+        None,
+    );
+    let (_events, result) = collect_events_and_result(stream).await;
+
+    let user_texts = result
+        .iter()
+        .filter_map(|message| match message {
+            Message::User {
+                content: UserContent::Text(text),
+                ..
+            } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        user_texts,
+        vec!["write python", "use rust instead", "then summarize"]
+    );
+    assert_eq!(stream_fn_calls.load(Ordering::SeqCst), 3);
+    assert!(queued.is_empty());
+}
+
+#[tokio::test]
+async fn agent_loop_serializes_parallel_calls_with_the_same_conflict_key() {
+    let stream_fn_calls = Arc::new(AtomicUsize::new(0));
+    let stream_fn_calls_in_stream = stream_fn_calls.clone();
+    let stream_fn = Arc::new(
+        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            if stream_fn_calls_in_stream.fetch_add(1, Ordering::SeqCst) == 0 {
+                let calls = [
+                    ("call_1", "a.txt"),
+                    ("call_2", "b.txt"),
+                    ("call_3", "a.txt"),
+                    ("call_4", "c.txt,b.txt"),
+                ]
+                .into_iter()
+                .map(|(id, path)| AssistantContentBlock::ToolCall {
+                    id: id.to_string(),
+                    name: "touch".to_string(),
+                    arguments: json!({ "path": path }),
+                    thought_signature: None,
+                })
+                .collect();
+                let message = assistant_message(calls, StopReason::ToolUse, 1_700_000_000_010);
+                return Ok(done_stream(message, DoneReason::ToolUse));
+            }
+            let message = assistant_message(
+                vec![AssistantContentBlock::Text {
+                    text: "done".to_string(),
+                    text_signature: None,
+                }],
+                StopReason::Stop,
+                1_700_000_000_020,
+            );
+            Ok(done_stream(message, DoneReason::Stop))
+        },
+    );
+
+    let log = Arc::new(Mutex::new(Vec::<String>::new()));
+    let log_in_tool = log.clone();
+    let tool = AgentTool {
+        name: "touch".to_string(),
+        label: "touch".to_string(),
+        description: "Touch a file".to_string(),
+        parameters: json!({ "type": "object" }),
+        conflict_key: Some(Arc::new(|args: &Value| {
+            args["path"]
+                .as_str()
+                .map(|paths| paths.split(',').map(str::to_string).collect())
+                .unwrap_or_default()
+        })),
+        execute:
+            Arc::new(
+                move |tool_call_id: String,
+                      _args: Value|
+                      -> Pin<
+                    Box<dyn Future<Output = Result<AgentToolResult, PiAiError>> + Send>,
+                > {
+                    let log = log_in_tool.clone();
+                    Box::pin(async move {
+                        log.lock().unwrap().push(format!("start {tool_call_id}"));
+                        sleep(Duration::from_millis(30)).await;
+                        log.lock().unwrap().push(format!("end {tool_call_id}"));
+                        Ok(AgentToolResult {
+                            content: vec![],
+                            details: json!({}),
+                        })
+                    })
+                },
+            ),
+    };
+
+    let config = AgentLoopConfig {
+        stream_fn,
+        tool_parallelism: 4,
+        ..default_loop_config()
+    };
+    let context = AgentContext {
+        system_prompt: "You are helpful".to_string(),
+        messages: vec![],
+        tools: vec![tool],
+    };
+    let stream = agent_loop(
+        vec![user_message("touch files", 1_700_000_000_000)],
+        context,
+        config,
+        None,
+    );
+    let (_events, result) = collect_events_and_result(stream).await;
+
+    let log = log.lock().unwrap().clone();
+    let position = |entry: &str| log.iter().position(|logged| logged == entry).unwrap();
+    assert!(
+        position("start call_2") < position("end call_1"),
+        "calls on different paths run in parallel: {log:?}"
+    );
+    assert!(
+        position("end call_1") < position("start call_3"),
+        "calls on the same path run in order: {log:?}"
+    );
+    assert!(
+        position("end call_2") < position("start call_4"),
+        "a call touching several paths waits for each of them: {log:?}"
+    );
+    let result_ids = result
+        .iter()
+        .filter_map(|message| match message {
+            Message::ToolResult { tool_call_id, .. } => Some(tool_call_id.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(result_ids, vec!["call_1", "call_2", "call_3", "call_4"]);
+}
+
+#[derive(Default)]
+struct RecordingHooks {
+    log: Mutex<Vec<String>>,
+}
+
+#[async_trait::async_trait]
+impl AgentHooks for RecordingHooks {
+    async fn before_turn(&self, context: &AgentContext) {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("before_turn {}", context.messages.len()));
+    }
+
+    async fn after_tool(&self, call: &ToolCall, result: &mut AgentToolResult, is_error: bool) {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("after_tool {} {is_error}", call.name));
+        result.content.push(ToolResultContentBlock::Text {
+            text: "lint: clean".to_string(),
+            text_signature: None,
+        });
+    }
+
+    async fn after_turn(&self, _message: &AgentMessage, tool_results: &[AgentMessage]) {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("after_turn {}", tool_results.len()));
+    }
+
+    async fn before_next_turn(&self) -> Vec<AgentMessage> {
+        self.log
+            .lock()
+            .unwrap()
+            .push("before_next_turn".to_string());
+        vec![user_message("file changed", 1_700_000_000_030)]
+    }
+
+    async fn on_error(&self, _message: &AgentMessage) -> Option<AgentMessage> {
+        let mut log = self.log.lock().unwrap();
+        let first = !log.iter().any(|entry| entry == "on_error");
+        log.push("on_error".to_string());
+        first.then(|| user_message("try again", 1_700_000_000_050))
+    }
+}
+
+#[tokio::test]
+async fn agent_loop_runs_hooks_around_turns_tools_and_errors() {
+    let stream_fn_calls = Arc::new(AtomicUsize::new(0));
+    let stream_fn_calls_in_stream = stream_fn_calls.clone();
+    let stream_fn = Arc::new(
+        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            match stream_fn_calls_in_stream.fetch_add(1, Ordering::SeqCst) {
+                0 => {
+                    let message = assistant_message(
+                        vec![AssistantContentBlock::ToolCall {
+                            id: "call_1".to_string(),
+                            name: "edit".to_string(),
+                            arguments: json!({}),
+                            thought_signature: None,
+                        }],
+                        StopReason::ToolUse,
+                        1_700_000_000_010,
+                    );
+                    Ok(done_stream(message, DoneReason::ToolUse))
+                }
+                _ => Err(PiAiError::new(
+                    PiAiErrorCode::ProviderProtocol,
+                    "malformed response",
+                )),
+            }
+        },
+    );
+    let tool =
+        AgentTool {
+            name: "edit".to_string(),
+            label: "edit".to_string(),
+            description: "Edit a file".to_string(),
+            parameters: json!({ "type": "object" }),
+            conflict_key: None,
+            execute:
+                Arc::new(
+                    |_tool_call_id: String,
+                     _args: Value|
+                     -> Pin<
+                        Box<dyn Future<Output = Result<AgentToolResult, PiAiError>> + Send>,
+                    > {
+                        Box::pin(async move {
+                            Ok(AgentToolResult {
+                                content: vec![ToolResultContentBlock::Text {
+                                    text: "edited".to_string(),
+                                    text_signature: None,
+                                }],
+                                details: json!({}),
+                            })
+                        })
+                    },
+                ),
+        };
+    let hooks = Arc::new(RecordingHooks::default());
+
+    let config = AgentLoopConfig {
+        stream_fn,
+        retry: AgentRetryConfig {
+            max_attempts: 1,
+            initial_backoff_ms: 0,
+            max_backoff_ms: 0,
+        },
+        hooks: Some(hooks.clone()),
+        ..default_loop_config()
+    };
+    let context = AgentContext {
+        system_prompt: "You are helpful".to_string(),
+        messages: vec![],
+        tools: vec![tool],
+    };
+    let stream = agent_loop(
+        vec![user_message("fix it", 1_700_000_000_000)],
+        context,
+        config,
+        None,
+    );
+    let (_events, result) = collect_events_and_result(stream).await;
+
+    assert_eq!(
+        *hooks.log.lock().unwrap(),
+        vec![
+            "before_turn 1",
+            "after_tool edit false",
+            "after_turn 1",
+            "before_next_turn",
+            "before_turn 4",
+            "on_error",
+            "before_turn 6",
+            "on_error",
+        ]
+    );
+    assert!(result.iter().any(|message| matches!(
+        message,
+        Message::User { content: UserContent::Text(text), .. } if text == "file changed"
+    )));
+    assert!(result.iter().any(|message| matches!(
+        message,
+        Message::ToolResult { content, .. }
+            if matches!(content.last(), Some(ToolResultContentBlock::Text { text, .. }) if text == "lint: clean")
+    )));
+    assert!(matches!(
+        result.last(),
+        Some(Message::Assistant {
+            stop_reason: StopReason::Error,
+            ..
+        })
+    ));
+    assert_eq!(stream_fn_calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn agent_loop_compacts_and_retries_once_when_the_context_is_too_large() {
+    let requests = Arc::new(Mutex::new(Vec::<Context>::new()));
+    let requests_in_stream = requests.clone();
+    let stream_fn = Arc::new(
+        move |_model: Model, context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let mut requests = requests_in_stream.lock().unwrap();
+            requests.push(context);
+            if requests.len() == 1 {
+                let mut error = assistant_message(vec![], StopReason::Error, 1_700_000_000_010);
+                error.error_message = Some(
+                    PiAiError::new(
+                        PiAiErrorCode::ContextTooLarge,
+                        "prompt is too long: 210000 tokens > 200000 maximum",
+                    )
+                    .as_compact_json(),
+                );
+                let stream = AssistantMessageEventStream::new();
+                stream.push(AssistantMessageEvent::Error {
+                    reason: ErrorReason::Error,
+                    error,
+                });
+                return Ok(stream);
+            }
+            let message = assistant_message(
+                vec![AssistantContentBlock::Text {
+                    text: "done".to_string(),
+                    text_signature: None,
+                }],
+                StopReason::Stop,
+                1_700_000_000_020,
+            );
+            Ok(done_stream(message, DoneReason::Stop))
+        },
+    );
+    let history = vec![
+        user_message("read the log", 1_699_999_999_000),
+        Message::ToolResult {
+            tool_call_id: "call_1".to_string(),
+            tool_name: "read".to_string(),
+            content: vec![ToolResultContentBlock::Text {
+                text: "log line ".repeat(2_000),
+                text_signature: None,
+            }],
+            details: None,
+            is_error: false,
+            timestamp: 1_699_999_999_100,
+        },
+    ];
+    let config = AgentLoopConfig {
+        stream_fn,
+        context_compaction: Some(ContextCompaction {
+            strategies: vec![CompressionStrategy::DropOldToolResults { keep_recent: 1 }],
+            max_tokens: Some(500),
+        }),
+        ..default_loop_config()
+    };
+    let context = AgentContext {
+        system_prompt: "You are helpful".to_string(),
+        messages: history,
+        tools: vec![],
+    };
+    let stream = agent_loop(
+        vec![user_message("summarize it", 1_700_000_000_000)],
+        context,
+        config,
+        None,
+    );
+    let (events, result) = collect_events_and_result(stream).await;
+
+    let dropped_tokens = events.iter().find_map(|event| match event {
+        AgentEvent::Compacted { dropped_tokens } => Some(*dropped_tokens),
+        _ => None,
+    });
+    assert!(dropped_tokens.is_some_and(|tokens| tokens > 1_000));
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2, "the request is retried exactly once");
+    assert!(matches!(
+        &requests[1].messages[1],
+        Message::ToolResult { content, .. }
+            if matches!(&content[0], ToolResultContentBlock::Text { text, .. } if !text.contains("log line"))
+    ));
+    assert!(
+        !requests[1].messages.iter().any(|message| matches!(
+            message,
+            Message::Assistant {
+                stop_reason: StopReason::Error,
+                ..
+            }
+        )),
+        "the failed response is not sent back to the model"
+    );
+    assert_eq!(result.len(), 2);
+    assert!(matches!(
+        result.last(),
+        Some(Message::Assistant {
+            stop_reason: StopReason::Stop,
+            ..
+        })
+    ));
+}
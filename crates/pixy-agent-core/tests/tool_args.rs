@@ -0,0 +1,95 @@
+use pixy_agent_core::AgentToolArgs;
+use pixy_ai::PiAiErrorCode;
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Debug, Deserialize, AgentToolArgs)]
+#[serde(rename_all = "camelCase")]
+struct SearchArgs {
+    /// Text to look for.
+    query: String,
+    /// Lines of context around each match.
+    #[tool(minimum = 0, maximum = 20)]
+    context_lines: Option<usize>,
+    #[serde(default)]
+    case_sensitive: bool,
+    #[tool(values("files", "content"))]
+    output: Option<String>,
+    filters: Vec<Filter>,
+}
+
+#[derive(Debug, Deserialize, AgentToolArgs)]
+struct Filter {
+    /// Glob the path must match.
+    glob: String,
+    #[tool(exclusive_minimum = 0)]
+    max_size_kb: Option<f64>,
+}
+
+#[test]
+fn derived_schema_describes_fields_and_constraints() {
+    assert_eq!(
+        SearchArgs::schema(),
+        json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "Text to look for." },
+                "contextLines": {
+                    "type": "integer",
+                    "description": "Lines of context around each match.",
+                    "minimum": 0,
+                    "maximum": 20
+                },
+                "caseSensitive": { "type": "boolean" },
+                "output": { "type": "string", "enum": ["files", "content"] },
+                "filters": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "glob": { "type": "string", "description": "Glob the path must match." },
+                            "max_size_kb": { "type": "number", "exclusiveMinimum": 0 }
+                        },
+                        "required": ["glob"],
+                        "additionalProperties": false
+                    }
+                }
+            },
+            "required": ["query", "filters"],
+            "additionalProperties": false
+        })
+    );
+}
+
+#[test]
+fn parse_deserializes_and_checks_declared_constraints() {
+    let args = SearchArgs::parse(json!({
+        "query": "todo",
+        "contextLines": 2,
+        "output": "files",
+        "filters": [{ "glob": "*.rs", "max_size_kb": 1.5 }]
+    }))
+    .expect("valid arguments");
+    assert_eq!(args.query, "todo");
+    assert_eq!(args.context_lines, Some(2));
+    assert!(!args.case_sensitive);
+    assert_eq!(args.filters[0].glob, "*.rs");
+    assert!(Filter::parse(json!({ "glob": "*", "max_size_kb": 0 })).is_err());
+
+    let too_much_context =
+        SearchArgs::parse(json!({ "query": "todo", "contextLines": 21, "filters": [] }))
+            .expect_err("maximum is enforced");
+    assert_eq!(too_much_context.code, PiAiErrorCode::ToolArgumentsInvalid);
+    assert_eq!(too_much_context.message, "`contextLines` must be <= 20");
+
+    let bad_output = SearchArgs::parse(json!({ "query": "todo", "output": "all", "filters": [] }))
+        .expect_err("values are enforced");
+    assert_eq!(
+        bad_output.message,
+        "`output` must be one of: files, content"
+    );
+
+    let missing = SearchArgs::parse(json!({ "filters": [] })).expect_err("query is required");
+    assert_eq!(missing.code, PiAiErrorCode::ToolArgumentsInvalid);
+    assert!(missing.message.contains("missing field `query`"));
+}
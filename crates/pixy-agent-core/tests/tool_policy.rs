@@ -0,0 +1,169 @@
+use std::path::PathBuf;
+
+use pixy_agent_core::{AgentToolResult, ToolPolicy};
+use pixy_ai::{PiAiErrorCode, ToolResultContentBlock};
+use serde_json::json;
+
+fn text_result(text: &str) -> AgentToolResult {
+    AgentToolResult {
+        content: vec![ToolResultContentBlock::Text {
+            text: text.to_string(),
+            text_signature: None,
+        }],
+        details: json!({}),
+    }
+}
+
+fn texts(result: &AgentToolResult) -> Vec<&str> {
+    result
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ToolResultContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn deny_list_wins_over_allow_list() {
+    let policy = ToolPolicy {
+        allow_tools: Some(vec!["read".to_string(), "bash".to_string()]),
+        deny_tools: vec!["bash".to_string()],
+        ..ToolPolicy::default()
+    };
+    assert!(policy.allows_tool("read"));
+    assert!(!policy.allows_tool("bash"));
+    assert!(!policy.allows_tool("write"));
+
+    let error = policy
+        .check("bash", &json!({ "command": "rm -rf /" }))
+        .expect_err("denied tool");
+    assert_eq!(error.code, PiAiErrorCode::ToolPolicyDenied);
+    assert!(ToolPolicy::default().allows_tool("bash"));
+}
+
+#[test]
+fn path_arguments_must_stay_inside_allowed_roots() {
+    let policy = ToolPolicy {
+        allowed_paths: vec![PathBuf::from("/work"), PathBuf::from("/tmp/scratch")],
+        ..ToolPolicy::default()
+    };
+    assert!(policy
+        .check("read", &json!({ "path": "src/lib.rs" }))
+        .is_ok());
+    assert!(policy
+        .check("write", &json!({ "path": "/tmp/scratch/out.txt" }))
+        .is_ok());
+    assert!(policy.check("bash", &json!({ "command": "ls" })).is_ok());
+
+    for path in ["/etc/passwd", "../outside.txt", "src/../../etc/passwd"] {
+        let error = policy
+            .check("read", &json!({ "path": path }))
+            .expect_err("path outside the roots");
+        assert_eq!(error.code, PiAiErrorCode::ToolPolicyDenied);
+    }
+    assert!(policy
+        .check("bash", &json!({ "command": "ls", "cwd": "/" }))
+        .is_err());
+}
+
+#[test]
+fn paths_resolve_like_the_tools_against_the_session_cwd() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let root = dir.path().join("root");
+    let cwd = root.join("project");
+    std::fs::create_dir_all(&cwd).expect("create cwd");
+    let policy = ToolPolicy {
+        allowed_paths: vec![cwd.clone()],
+        cwd: Some(cwd.clone()),
+        ..ToolPolicy::default()
+    };
+
+    assert!(policy
+        .check("write", &json!({ "path": "src/new.rs" }))
+        .is_ok());
+    assert!(policy
+        .check("read", &json!({ "path": "@src/lib.rs" }))
+        .is_ok());
+    for path in ["@/etc/passwd", "~/.ssh/id_rsa", "../sibling.txt"] {
+        let error = policy
+            .check("read", &json!({ "path": path }))
+            .expect_err("path outside the root");
+        assert_eq!(error.code, PiAiErrorCode::ToolPolicyDenied, "{path}");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_out_of_an_allowed_root_are_refused() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let root = dir.path().join("root");
+    let outside = dir.path().join("outside");
+    std::fs::create_dir_all(&root).expect("create root");
+    std::fs::create_dir_all(&outside).expect("create outside");
+    std::os::unix::fs::symlink(&outside, root.join("escape")).expect("symlink");
+    let policy = ToolPolicy {
+        allowed_paths: vec![root.clone()],
+        cwd: Some(root),
+        ..ToolPolicy::default()
+    };
+
+    assert!(policy
+        .check("write", &json!({ "path": "escape/new.txt" }))
+        .is_err());
+    assert!(policy
+        .check("write", &json!({ "path": "inside.txt" }))
+        .is_ok());
+}
+
+#[test]
+fn every_apply_patch_target_is_checked() {
+    let policy = ToolPolicy {
+        allowed_paths: vec![PathBuf::from("/work")],
+        cwd: Some(PathBuf::from("/work")),
+        ..ToolPolicy::default()
+    };
+    let patch = "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-a\n+b\n\
+                 --- /dev/null\n+++ b/../../etc/cron.d/job\n@@ -0,0 +1 @@\n+x\n";
+    assert!(policy
+        .check("apply_patch", &json!({ "patch": patch }))
+        .is_err());
+    assert!(policy
+        .check(
+            "apply_patch",
+            &json!({ "files": [{ "path": "src/lib.rs" }, { "path": "/etc/hosts" }] })
+        )
+        .is_err());
+    assert!(policy
+        .check(
+            "apply_patch",
+            &json!({ "patch": "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-a\n+b\n" })
+        )
+        .is_ok());
+}
+
+#[test]
+fn limit_output_cuts_text_on_a_char_boundary() {
+    let policy = ToolPolicy {
+        max_output_bytes: Some(5),
+        ..ToolPolicy::default()
+    };
+    let limited = policy.limit_output(text_result("abcdé-rest"));
+    assert_eq!(
+        texts(&limited),
+        vec!["abcd", "[output truncated: 7 bytes over the 5 byte limit]"]
+    );
+    assert_eq!(
+        texts(&policy.limit_output(text_result("short"))),
+        vec!["short"]
+    );
+}
+
+#[test]
+fn dry_run_result_describes_the_skipped_call() {
+    let result = ToolPolicy::default().dry_run_result("write", &json!({ "path": "a.txt" }));
+    assert_eq!(texts(&result), vec!["Dry run: write was not executed."]);
+    assert_eq!(result.details["dryRun"], json!(true));
+    assert_eq!(result.details["args"]["path"], json!("a.txt"));
+}
@@ -0,0 +1,382 @@
+//! Derive macros for pixy agents; use them through the `pixy-agent-core` re-exports.
+
+use proc_macro::TokenStream;
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Expr, ExprLit, Fields, GenericArgument, Lit,
+    LitStr, Meta, PathArguments, Type,
+};
+
+/// Implements `AgentToolArgs` for a struct with named fields.
+///
+/// Each field becomes a property of the schema, named as serde names it (`rename` and
+/// `rename_all` are honored) and described by its doc comment. `Option` fields and fields
+/// with `#[serde(default)]` are optional; every other field is required. Fields also accept
+/// `#[tool(minimum = N)]`, `#[tool(exclusive_minimum = N)]`, `#[tool(maximum = N)]` and
+/// `#[tool(values("a", "b"))]`, which are added to the schema and checked after parsing.
+#[proc_macro_derive(AgentToolArgs, attributes(tool))]
+pub fn derive_agent_tool_args(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "AgentToolArgs can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "AgentToolArgs needs a struct with named fields",
+        ));
+    };
+    let rename_all = container_rename_all(&input.attrs)?;
+
+    let mut properties = Vec::new();
+    let mut required = Vec::new();
+    let mut checks = Vec::new();
+    for field in &fields.named {
+        let ident = field.ident.as_ref().expect("named field");
+        let serde = field_serde(&field.attrs)?;
+        if serde.skip {
+            continue;
+        }
+        let rust_name = ident.to_string();
+        let rust_name = rust_name.trim_start_matches("r#");
+        let name = match serde.rename {
+            Some(rename) => rename,
+            None => rename_field(rust_name, rename_all.as_deref(), &input.ident)?,
+        };
+        let constraints = field_constraints(&field.attrs)?;
+        let (optional, value_type) = match option_inner(&field.ty) {
+            Some(inner) => (true, inner),
+            None => (false, &field.ty),
+        };
+        if !optional && !serde.default {
+            required.push(name.clone());
+        }
+
+        let schema = type_schema(value_type);
+        let description = doc_comment(&field.attrs).map(|doc| {
+            quote! { property.insert("description".to_string(), ::pixy_agent_core::__private::serde_json::json!(#doc)); }
+        });
+        let keywords = constraints.schema_keywords();
+        properties.push(quote! {
+            {
+                let mut schema = #schema;
+                if let Some(property) = schema.as_object_mut() {
+                    #description
+                    #(#keywords)*
+                }
+                properties.insert(#name.to_string(), schema);
+            }
+        });
+
+        let field_checks = constraints.checks(&name);
+        if !field_checks.is_empty() {
+            checks.push(if optional {
+                quote! { if let Some(value) = &self.#ident { #(#field_checks)* } }
+            } else {
+                quote! { { let value = &self.#ident; #(#field_checks)* } }
+            });
+        }
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::pixy_agent_core::AgentToolArgs for #ident #type_generics #where_clause {
+            fn schema() -> ::pixy_agent_core::__private::serde_json::Value {
+                let mut properties = ::pixy_agent_core::__private::serde_json::Map::new();
+                #(#properties)*
+                let mut schema = ::pixy_agent_core::__private::serde_json::Map::new();
+                schema.insert("type".to_string(), "object".into());
+                schema.insert("properties".to_string(), properties.into());
+                let required: ::std::vec::Vec<&str> = ::std::vec![#(#required),*];
+                if !required.is_empty() {
+                    schema.insert("required".to_string(), required.into());
+                }
+                schema.insert("additionalProperties".to_string(), false.into());
+                schema.into()
+            }
+
+            fn validate(&self) -> ::std::result::Result<(), ::std::string::String> {
+                #(#checks)*
+                Ok(())
+            }
+        }
+    })
+}
+
+#[derive(Default)]
+struct SerdeField {
+    rename: Option<String>,
+    default: bool,
+    skip: bool,
+}
+
+#[derive(Default)]
+struct Constraints {
+    minimum: Option<Lit>,
+    exclusive_minimum: Option<Lit>,
+    maximum: Option<Lit>,
+    values: Vec<LitStr>,
+}
+
+impl Constraints {
+    fn schema_keywords(&self) -> Vec<TokenStream2> {
+        let mut keywords = Vec::new();
+        let json = quote!(::pixy_agent_core::__private::serde_json::json);
+        for (keyword, bound) in [
+            ("minimum", &self.minimum),
+            ("exclusiveMinimum", &self.exclusive_minimum),
+            ("maximum", &self.maximum),
+        ] {
+            if let Some(bound) = bound {
+                keywords.push(quote! { property.insert(#keyword.to_string(), #json!(#bound)); });
+            }
+        }
+        if !self.values.is_empty() {
+            let values = &self.values;
+            keywords.push(quote! { property.insert("enum".to_string(), #json!([#(#values),*])); });
+        }
+        keywords
+    }
+
+    fn checks(&self, name: &str) -> Vec<TokenStream2> {
+        let mut checks = Vec::new();
+        for (bound, operator, fails) in [
+            (&self.minimum, ">=", quote!(<)),
+            (&self.exclusive_minimum, ">", quote!(<=)),
+            (&self.maximum, "<=", quote!(>)),
+        ] {
+            if let Some(bound) = bound {
+                let message = format!("`{name}` must be {operator} {}", lit_text(bound));
+                checks.push(quote! {
+                    if (*value as f64) #fails (#bound as f64) {
+                        return Err(#message.to_string());
+                    }
+                });
+            }
+        }
+        if !self.values.is_empty() {
+            let values = &self.values;
+            let message = format!(
+                "`{name}` must be one of: {}",
+                self.values
+                    .iter()
+                    .map(LitStr::value)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            checks.push(quote! {
+                if ![#(#values),*].contains(&value.as_str()) {
+                    return Err(#message.to_string());
+                }
+            });
+        }
+        checks
+    }
+}
+
+fn lit_text(lit: &Lit) -> String {
+    match lit {
+        Lit::Int(int) => int.base10_digits().to_string(),
+        Lit::Float(float) => float.base10_digits().to_string(),
+        other => quote!(#other).to_string(),
+    }
+}
+
+fn container_rename_all(attrs: &[Attribute]) -> syn::Result<Option<String>> {
+    let mut rename_all = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename_all") {
+                rename_all = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                skip_meta(&meta)
+            }
+        })?;
+    }
+    Ok(rename_all)
+}
+
+fn field_serde(attrs: &[Attribute]) -> syn::Result<SerdeField> {
+    let mut field = SerdeField::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                field.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                field.default = true;
+                skip_meta(&meta)
+            } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                field.skip = true;
+                Ok(())
+            } else {
+                skip_meta(&meta)
+            }
+        })?;
+    }
+    Ok(field)
+}
+
+fn field_constraints(attrs: &[Attribute]) -> syn::Result<Constraints> {
+    let mut constraints = Constraints::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("tool")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("minimum") {
+                constraints.minimum = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("exclusive_minimum") {
+                constraints.exclusive_minimum = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("maximum") {
+                constraints.maximum = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("values") {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                constraints.values = content
+                    .parse_terminated(|input| input.parse::<LitStr>(), syn::Token![,])?
+                    .into_iter()
+                    .collect();
+            } else {
+                return Err(
+                    meta.error("expected `minimum`, `exclusive_minimum`, `maximum` or `values`")
+                );
+            }
+            Ok(())
+        })?;
+    }
+    Ok(constraints)
+}
+
+/// Consumes the value of a serde option this macro does not need.
+fn skip_meta(meta: &ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(syn::Token![=]) {
+        meta.value()?.parse::<TokenTree>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.input.parse::<TokenTree>()?;
+    }
+    Ok(())
+}
+
+fn rename_field(name: &str, rename_all: Option<&str>, ident: &syn::Ident) -> syn::Result<String> {
+    let words = name.split('_').filter(|word| !word.is_empty());
+    let capitalized = |word: &str| {
+        let mut chars = word.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+            .unwrap_or_default()
+    };
+    let renamed = match rename_all {
+        None | Some("snake_case") | Some("lowercase") => name.to_string(),
+        Some("UPPERCASE") | Some("SCREAMING_SNAKE_CASE") => name.to_uppercase(),
+        Some("kebab-case") => name.replace('_', "-"),
+        Some("SCREAMING-KEBAB-CASE") => name.replace('_', "-").to_uppercase(),
+        Some("PascalCase") => words.map(capitalized).collect(),
+        Some("camelCase") => words
+            .enumerate()
+            .map(|(index, word)| {
+                if index == 0 {
+                    word.to_string()
+                } else {
+                    capitalized(word)
+                }
+            })
+            .collect(),
+        Some(other) => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                format!("unsupported serde rename_all rule `{other}`"),
+            ));
+        }
+    };
+    Ok(renamed)
+}
+
+/// The field's doc comment with its lines joined by spaces.
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(text),
+                    ..
+                }) => Some(text.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
+
+/// The `T` of an `Option<T>` field.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    single_generic_argument(ty, "Option")
+}
+
+fn single_generic_argument<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    match arguments.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+/// Schema expression for a field type; structs must implement `AgentToolArgs` themselves.
+fn type_schema(ty: &Type) -> TokenStream2 {
+    let json = quote!(::pixy_agent_core::__private::serde_json::json);
+    if let Some(item) = single_generic_argument(ty, "Vec") {
+        let items = type_schema(item);
+        return quote!(#json!({ "type": "array", "items": #items }));
+    }
+    let last_ident = match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        Type::Reference(reference) => match reference.elem.as_ref() {
+            Type::Path(path) => path
+                .path
+                .segments
+                .last()
+                .map(|segment| segment.ident.to_string()),
+            _ => None,
+        },
+        _ => None,
+    };
+    let json_type = match last_ident.as_deref() {
+        Some("String" | "str" | "PathBuf" | "char") => "string",
+        Some("bool") => "boolean",
+        Some(
+            "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64" | "i128"
+            | "isize",
+        ) => "integer",
+        Some("f32" | "f64") => "number",
+        Some("Value") => return quote!(#json!({})),
+        _ => return quote!(<#ty as ::pixy_agent_core::AgentToolArgs>::schema()),
+    };
+    quote!(#json!({ "type": #json_type }))
+}
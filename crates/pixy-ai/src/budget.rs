@@ -0,0 +1,184 @@
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::error::{PiAiError, PiAiErrorCode};
+use crate::middleware::{MiddlewareFuture, StreamMiddleware, StreamRequest};
+use crate::pricing::model_pricing;
+use crate::types::AssistantMessage;
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+/// Spend limits in USD. `None` leaves that window unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetLimits {
+    /// Spend allowed over the lifetime of the guard.
+    #[serde(rename = "sessionUsd", skip_serializing_if = "Option::is_none")]
+    pub session_usd: Option<f64>,
+    /// Spend allowed per UTC calendar day.
+    #[serde(rename = "dailyUsd", skip_serializing_if = "Option::is_none")]
+    pub daily_usd: Option<f64>,
+}
+
+/// What a [`BudgetGuard`] does with requests once a limit is at risk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetAction {
+    /// Refuse every request once a limit is spent.
+    #[default]
+    Reject,
+    /// Lower `max_tokens` so the output cannot cost more than what remains, and refuse
+    /// requests once nothing does. Models without known pricing are not capped.
+    Truncate,
+}
+
+/// Spend recorded by a [`BudgetGuard`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BudgetSpend {
+    pub session_usd: f64,
+    /// Spend on the current UTC day.
+    pub daily_usd: f64,
+}
+
+#[derive(Debug, Default)]
+struct BudgetState {
+    spend: BudgetSpend,
+    day: i64,
+}
+
+/// Middleware that tracks the cost of completed requests and enforces [`BudgetLimits`].
+///
+/// Register it globally or per call like any [`StreamMiddleware`]; every request that shares
+/// the guard counts toward the same budget.
+#[derive(Debug)]
+pub struct BudgetGuard {
+    limits: BudgetLimits,
+    action: BudgetAction,
+    state: Mutex<BudgetState>,
+}
+
+impl BudgetGuard {
+    pub fn new(limits: BudgetLimits, action: BudgetAction) -> Self {
+        Self {
+            limits,
+            action,
+            state: Mutex::new(BudgetState {
+                spend: BudgetSpend::default(),
+                day: current_day(),
+            }),
+        }
+    }
+
+    pub fn limits(&self) -> &BudgetLimits {
+        &self.limits
+    }
+
+    pub fn spend(&self) -> BudgetSpend {
+        self.lock_state().spend
+    }
+
+    /// Adds spend made outside the guard, e.g. earlier turns of a resumed session.
+    pub fn record_spend(&self, usd: f64) {
+        if usd <= 0.0 {
+            return;
+        }
+        let mut state = self.lock_state();
+        state.spend.session_usd += usd;
+        state.spend.daily_usd += usd;
+    }
+
+    /// USD left before the tightest limit is reached; `None` when no limit is set.
+    pub fn remaining_usd(&self) -> Option<f64> {
+        let spend = self.spend();
+        let session = self
+            .limits
+            .session_usd
+            .map(|limit| limit - spend.session_usd);
+        let daily = self.limits.daily_usd.map(|limit| limit - spend.daily_usd);
+        match (session, daily) {
+            (Some(session), Some(daily)) => Some(session.min(daily).max(0.0)),
+            (remaining, None) | (None, remaining) => remaining.map(|value| value.max(0.0)),
+        }
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, BudgetState> {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let today = current_day();
+        if state.day != today {
+            state.day = today;
+            state.spend.daily_usd = 0.0;
+        }
+        state
+    }
+
+    fn check(&self, request: &mut StreamRequest) -> Result<(), PiAiError> {
+        let Some(remaining) = self.remaining_usd() else {
+            return Ok(());
+        };
+        if remaining <= 0.0 {
+            return Err(self.exceeded_error());
+        }
+        if self.action == BudgetAction::Reject {
+            return Ok(());
+        }
+
+        let Some(output_price) = model_pricing(&request.model)
+            .map(|pricing| pricing.output)
+            .filter(|price| *price > 0.0)
+        else {
+            return Ok(());
+        };
+        let affordable = (remaining * 1_000_000.0 / output_price).floor() as u64;
+        if affordable == 0 {
+            return Err(self.exceeded_error());
+        }
+        let requested = request
+            .options
+            .max_tokens
+            .unwrap_or(request.model.max_tokens);
+        let capped = u64::from(requested).min(affordable) as u32;
+        if capped < requested {
+            request.options.max_tokens = Some(capped);
+        }
+        Ok(())
+    }
+
+    fn exceeded_error(&self) -> PiAiError {
+        let spend = self.spend();
+        PiAiError::new(
+            PiAiErrorCode::BudgetExceeded,
+            format!(
+                "Spend limit reached: ${:.4} this session, ${:.4} today",
+                spend.session_usd, spend.daily_usd
+            ),
+        )
+        .with_details(json!({
+            "sessionUsd": spend.session_usd,
+            "dailyUsd": spend.daily_usd,
+            "limits": self.limits,
+        }))
+    }
+}
+
+impl StreamMiddleware for BudgetGuard {
+    fn on_request<'a>(&'a self, request: &'a mut StreamRequest) -> MiddlewareFuture<'a> {
+        Box::pin(async move { self.check(request) })
+    }
+
+    fn on_complete(&self, message: &AssistantMessage) {
+        self.record_spend(message.usage.cost.total);
+    }
+}
+
+fn current_day() -> i64 {
+    let now_millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or(0);
+    now_millis.div_euclid(MILLIS_PER_DAY)
+}
@@ -0,0 +1,350 @@
+//! Strategies for fitting a long conversation into a token budget, shared by agent
+//! auto-compaction and the gateway.
+//!
+//! Cuts only fall right before a user message, so no tool result loses the assistant turn
+//! that called it.
+
+use crate::error::{PiAiError, PiAiErrorCode};
+use crate::stream::complete;
+use crate::tokenizer::Tokenizer;
+use crate::types::{
+    now_millis, AssistantContentBlock, Context, Message, Model, StopReason, StreamOptions,
+    ToolResultContentBlock, UserContent, UserContentBlock,
+};
+
+/// Stands in for tool output dropped by [`CompressionStrategy::DropOldToolResults`].
+const DROPPED_TOOL_RESULT: &str = "[tool output omitted to save context]";
+/// Longest text kept per message in a [`transcript`].
+const TRANSCRIPT_MESSAGE_CHARS: usize = 240;
+const SUMMARY_SYSTEM_PROMPT: &str =
+    "You are a context summarization assistant. Summarize conversation history for another assistant.";
+const SUMMARY_PROMPT: &str = "Summarize the conversation above so another LLM can continue it. Include the user's goal, what has been done, the current status and open questions. Preserve exact names, paths, numbers and error messages where relevant. Keep it concise.";
+
+/// Prompt tokens a compressed context has to fit in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenBudget {
+    pub tokenizer: Tokenizer,
+    pub max_tokens: u64,
+}
+
+impl TokenBudget {
+    /// A budget of `max_tokens`, estimated the way `model` tokenizes.
+    pub fn for_model(model: &Model, max_tokens: u64) -> Self {
+        Self {
+            tokenizer: Tokenizer::for_model(model),
+            max_tokens,
+        }
+    }
+}
+
+/// How [`Context::compress`] makes room.
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum CompressionStrategy {
+    /// Replaces the output of old tool results with a short note, oldest first, until the
+    /// context fits. The last `keep_recent` messages are left alone.
+    DropOldToolResults { keep_recent: usize },
+    /// Replaces the turns between the first `keep_first` and the last `keep_recent` messages
+    /// with one summary written by `model`, usually a cheap one.
+    SummarizeMiddle {
+        model: Model,
+        options: Option<StreamOptions>,
+        keep_first: usize,
+        keep_recent: usize,
+    },
+    /// Drops the oldest turns until the context fits, keeping at least the last one.
+    TruncateToBudget,
+}
+
+impl Context {
+    /// A copy of this context compressed with `strategy`, or an unchanged copy when it already
+    /// fits `budget`. The result can still exceed the budget when the strategy has nothing
+    /// left to remove; strategies can be applied one after another.
+    pub async fn compress(
+        &self,
+        strategy: &CompressionStrategy,
+        budget: &TokenBudget,
+    ) -> Result<Context, PiAiError> {
+        let mut context = self.clone();
+        if budget.tokenizer.context_tokens(&context) <= budget.max_tokens {
+            return Ok(context);
+        }
+        match strategy {
+            CompressionStrategy::DropOldToolResults { keep_recent } => {
+                drop_old_tool_results(&mut context, *keep_recent, budget);
+            }
+            CompressionStrategy::SummarizeMiddle {
+                model,
+                options,
+                keep_first,
+                keep_recent,
+            } => {
+                summarize_middle(&mut context, model, options, *keep_first, *keep_recent).await?;
+            }
+            CompressionStrategy::TruncateToBudget => truncate_to_budget(&mut context, budget),
+        }
+        Ok(context)
+    }
+}
+
+/// Renders messages as `[role]: text` paragraphs for a summarization prompt, with each
+/// message flattened to one line and shortened.
+pub fn transcript(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .filter_map(transcript_line)
+        .map(|(role, text)| format!("[{role}]: {text}"))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// The role label and flattened, shortened text of one message, or `None` when it has no
+/// text worth keeping.
+pub fn transcript_line(message: &Message) -> Option<(&'static str, String)> {
+    let (role, text) = match message {
+        Message::User { content, .. } => ("user", user_text(content)),
+        Message::Assistant { content, .. } => ("assistant", assistant_text(content)),
+        Message::ToolResult { content, .. } => ("tool_result", tool_result_text(content)),
+    };
+    let text = truncate_chars(text.replace('\n', " ").trim(), TRANSCRIPT_MESSAGE_CHARS);
+    (!text.is_empty()).then_some((role, text))
+}
+
+fn user_text(content: &UserContent) -> String {
+    match content {
+        UserContent::Text(text) => text.clone(),
+        UserContent::Blocks(blocks) => blocks
+            .iter()
+            .filter_map(|block| match block {
+                UserContentBlock::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+fn assistant_text(content: &[AssistantContentBlock]) -> String {
+    content
+        .iter()
+        .map(|block| match block {
+            AssistantContentBlock::Text { text, .. } => text.clone(),
+            AssistantContentBlock::Thinking { thinking, .. } => thinking.clone(),
+            AssistantContentBlock::ToolCall {
+                name, arguments, ..
+            } => format!(
+                "tool call `{name}` with args {}",
+                truncate_chars(&arguments.to_string(), 200)
+            ),
+            AssistantContentBlock::Image { mime_type, .. } => format!("[image {mime_type}]"),
+            AssistantContentBlock::Audio { mime_type, .. } => format!("[audio {mime_type}]"),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn tool_result_text(content: &[ToolResultContentBlock]) -> String {
+    content
+        .iter()
+        .filter_map(|block| match block {
+            ToolResultContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let truncated = text
+        .chars()
+        .take(max_chars.saturating_sub(3))
+        .collect::<String>();
+    format!("{truncated}...")
+}
+
+/// Indices of user messages, where a cut leaves every tool call with its result.
+fn turn_starts(messages: &[Message]) -> Vec<usize> {
+    messages
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| matches!(message, Message::User { .. }))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+fn drop_old_tool_results(context: &mut Context, keep_recent: usize, budget: &TokenBudget) {
+    let tokenizer = budget.tokenizer;
+    let mut tokens = tokenizer.context_tokens(context);
+    let droppable = context.messages.len().saturating_sub(keep_recent);
+    for message in &mut context.messages[..droppable] {
+        if tokens <= budget.max_tokens {
+            break;
+        }
+        let before = tokenizer.message_tokens(message);
+        let Message::ToolResult { content, .. } = message else {
+            continue;
+        };
+        let dropped = matches!(
+            content.as_slice(),
+            [ToolResultContentBlock::Text { text, .. }] if text == DROPPED_TOOL_RESULT
+        );
+        if dropped {
+            continue;
+        }
+        *content = vec![ToolResultContentBlock::Text {
+            text: DROPPED_TOOL_RESULT.to_string(),
+            text_signature: None,
+        }];
+        tokens = tokens - before + tokenizer.message_tokens(message);
+    }
+}
+
+fn truncate_to_budget(context: &mut Context, budget: &TokenBudget) {
+    let tokenizer = budget.tokenizer;
+    let starts = turn_starts(&context.messages);
+    let Some(&last_start) = starts.last() else {
+        return;
+    };
+    let overhead = tokenizer.context_tokens(&Context {
+        messages: Vec::new(),
+        ..context.clone()
+    });
+    let message_tokens = context
+        .messages
+        .iter()
+        .map(|message| tokenizer.message_tokens(message))
+        .collect::<Vec<_>>();
+    let mut kept_tokens = message_tokens.iter().sum::<u64>();
+    let mut consumed = 0;
+    let cut = starts
+        .iter()
+        .copied()
+        .find(|&start| {
+            kept_tokens -= message_tokens[consumed..start].iter().sum::<u64>();
+            consumed = start;
+            overhead + kept_tokens <= budget.max_tokens
+        })
+        .unwrap_or(last_start);
+    context.messages.drain(..cut);
+}
+
+async fn summarize_middle(
+    context: &mut Context,
+    model: &Model,
+    options: &Option<StreamOptions>,
+    keep_first: usize,
+    keep_recent: usize,
+) -> Result<(), PiAiError> {
+    let starts = turn_starts(&context.messages);
+    let len = context.messages.len();
+    let head_end = starts
+        .iter()
+        .copied()
+        .find(|&start| start >= keep_first)
+        .unwrap_or(len);
+    let tail_start = starts
+        .iter()
+        .copied()
+        .rev()
+        .find(|&start| start <= len.saturating_sub(keep_recent))
+        .unwrap_or(0);
+    if tail_start <= head_end {
+        return Ok(());
+    }
+
+    let conversation = transcript(&context.messages[head_end..tail_start]);
+    let request = Context {
+        system_prompt: Some(SUMMARY_SYSTEM_PROMPT.to_string()),
+        messages: vec![Message::User {
+            content: UserContent::Text(format!(
+                "<conversation>\n{conversation}\n</conversation>\n\n{SUMMARY_PROMPT}"
+            )),
+            timestamp: now_millis(),
+        }],
+        tools: None,
+        system_prompt_cache: None,
+        branch: None,
+    };
+    let message = complete(model.clone(), request, options.clone()).await?;
+    if matches!(message.stop_reason, StopReason::Error | StopReason::Aborted) {
+        return Err(PiAiError::new(
+            PiAiErrorCode::ProviderProtocol,
+            message
+                .error_message
+                .unwrap_or_else(|| "Context summarization failed".to_string()),
+        ));
+    }
+    let summary = message
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            AssistantContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if summary.trim().is_empty() {
+        return Err(PiAiError::new(
+            PiAiErrorCode::ProviderProtocol,
+            "Context summarization returned no text",
+        ));
+    }
+
+    let summary_message = Message::User {
+        content: UserContent::Text(format!(
+            "Summary of the earlier conversation:\n\n{}",
+            summary.trim()
+        )),
+        timestamp: now_millis(),
+    };
+    context
+        .messages
+        .splice(head_end..tail_start, [summary_message]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(text: &str) -> Message {
+        Message::User {
+            content: UserContent::Text(text.to_string()),
+            timestamp: 0,
+        }
+    }
+
+    fn tool_result(text: &str) -> Message {
+        Message::ToolResult {
+            tool_call_id: "call".to_string(),
+            tool_name: "read".to_string(),
+            content: vec![ToolResultContentBlock::Text {
+                text: text.to_string(),
+                text_signature: None,
+            }],
+            details: None,
+            is_error: false,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn turn_starts_are_user_messages_only() {
+        let messages = vec![user("a"), tool_result("b"), user("c")];
+        assert_eq!(turn_starts(&messages), vec![0, 2]);
+    }
+
+    #[test]
+    fn transcript_flattens_and_shortens_messages() {
+        let long = "x".repeat(300);
+        let text = transcript(&[user("line one\nline two"), tool_result(&long), user("  ")]);
+        let lines = text.split("\n\n").collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "[user]: line one line two");
+        assert!(lines[1].starts_with("[tool_result]: xxx"));
+        assert!(lines[1].ends_with("..."));
+    }
+}
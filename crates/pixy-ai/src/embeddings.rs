@@ -0,0 +1,34 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::PiAiError;
+
+/// Embedding model served from an OpenAI-compatible `/embeddings` endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingModel {
+    pub id: String,
+    pub provider: String,
+    pub base_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EmbeddingOptions {
+    pub api_key: Option<String>,
+    pub headers: Option<HashMap<String, String>>,
+}
+
+/// Embeds `inputs` and returns one vector per input, in input order.
+pub async fn embed(
+    model: &EmbeddingModel,
+    inputs: &[String],
+    options: Option<&EmbeddingOptions>,
+) -> Result<Vec<Vec<f32>>, PiAiError> {
+    if inputs.is_empty() {
+        return Ok(vec![]);
+    }
+    crate::providers::run_openai_embeddings(model, inputs, options).await
+}
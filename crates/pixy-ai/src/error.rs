@@ -14,6 +14,83 @@ pub enum PiAiErrorCode {
     ProviderHttp,
     ProviderTransport,
     ProviderProtocol,
+    /// Refused by a [`crate::BudgetGuard`] because the spend limit was reached.
+    BudgetExceeded,
+    /// The provider rejected the credentials (HTTP 401/403).
+    AuthInvalid,
+    /// The account is out of quota or credit, as opposed to being briefly rate limited.
+    QuotaExceeded,
+    /// The request did not fit the model's context window.
+    ContextTooLarge,
+    /// A provider content filter blocked the request or response.
+    ContentFiltered,
+    /// The provider does not know the requested model.
+    ModelNotFound,
+    /// The request timed out before the provider answered.
+    TransportTimeout,
+    /// A [`crate::ContentGuard`] rejected the request or response.
+    ContentRejected,
+    /// A client-side [`crate::RateLimit`] had no room and the request asked not to wait.
+    RateLimitExceeded,
+    /// A tool result answers no earlier tool call, or a tool call id is used twice.
+    ToolCallMismatch,
+    /// A tool policy refused the tool call or one of its paths.
+    ToolPolicyDenied,
+    /// The run stopped because the model kept calling tools without finishing.
+    ToolLoopDetected,
+}
+
+impl PiAiErrorCode {
+    /// A short, actionable hint for the user, for codes where one exists.
+    pub fn remediation(&self) -> Option<&'static str> {
+        let hint = match self {
+            Self::ProviderAuthMissing => {
+                "Set an API key for this provider in pixy.toml or its environment variable."
+            }
+            Self::AuthInvalid => {
+                "The API key was rejected; check that it is current and has access to this model."
+            }
+            Self::QuotaExceeded => {
+                "The provider account is out of quota or credit; add credit or switch provider."
+            }
+            Self::ContextTooLarge => {
+                "The conversation no longer fits the model's context window; compact the session or start a new one."
+            }
+            Self::ContentFiltered => {
+                "The provider's content filter blocked this exchange; rephrase the request."
+            }
+            Self::ModelNotFound => {
+                "The provider does not know this model; check the model id and your account's access."
+            }
+            Self::TransportTimeout => {
+                "The provider did not answer in time; check the network and try again."
+            }
+            Self::ProviderTransport => {
+                "Could not reach the provider; check the network connection and base URL."
+            }
+            Self::ContentRejected => {
+                "A content guard blocked this exchange; rephrase it or adjust the configured guards."
+            }
+            Self::RateLimitExceeded => {
+                "Too many requests are in flight for this provider; retry shortly or raise its rate limit."
+            }
+            Self::BudgetExceeded => {
+                "The spend limit is used up; raise it or wait for the daily budget to reset."
+            }
+            Self::ToolLoopDetected => {
+                "The agent kept repeating tool calls; rephrase the request or raise the [loop_guard] limits."
+            }
+            Self::ToolNotFound
+            | Self::ToolArgumentsInvalid
+            | Self::ToolExecutionFailed
+            | Self::SchemaInvalid
+            | Self::ToolCallMismatch
+            | Self::ToolPolicyDenied
+            | Self::ProviderHttp
+            | Self::ProviderProtocol => return None,
+        };
+        Some(hint)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -38,6 +115,10 @@ impl PiAiError {
         self
     }
 
+    pub fn remediation(&self) -> Option<&'static str> {
+        self.code.remediation()
+    }
+
     pub fn as_compact_json(&self) -> String {
         serde_json::to_string(self).unwrap_or_else(|_| {
             format!(
@@ -55,3 +136,131 @@ impl Display for PiAiError {
 }
 
 impl std::error::Error for PiAiError {}
+
+/// The remediation hint for an assistant `error_message`, which holds a compact JSON
+/// [`PiAiError`] when the failure came from pixy-ai.
+pub fn error_remediation(error_message: &str) -> Option<&'static str> {
+    serde_json::from_str::<PiAiError>(error_message)
+        .ok()
+        .and_then(|error| error.remediation())
+}
+
+/// Narrows a failed HTTP response to the most specific code its status and body support.
+///
+/// Rate limits stay `ProviderHttp` so the retry policy keeps retrying them; only responses
+/// that say the account itself is out of quota become `QuotaExceeded`.
+pub(crate) fn classify_http_error(status: u16, body: &str) -> PiAiErrorCode {
+    let normalized = body.to_ascii_lowercase();
+    let mentions = |patterns: &[&str]| patterns.iter().any(|pattern| normalized.contains(pattern));
+
+    if matches!(status, 401 | 403) {
+        return PiAiErrorCode::AuthInvalid;
+    }
+    if status == 402
+        || mentions(&[
+            "insufficient_quota",
+            "exceeded your current quota",
+            "credit balance is too low",
+            "billing",
+        ])
+    {
+        return PiAiErrorCode::QuotaExceeded;
+    }
+    if matches!(status, 400 | 413 | 422) && is_context_overflow_error_text(&normalized) {
+        return PiAiErrorCode::ContextTooLarge;
+    }
+    if mentions(&[
+        "content_filter",
+        "content management policy",
+        "content_policy_violation",
+        "blocked by safety",
+    ]) {
+        return PiAiErrorCode::ContentFiltered;
+    }
+    if status == 404 && mentions(&["model"]) {
+        return PiAiErrorCode::ModelNotFound;
+    }
+    PiAiErrorCode::ProviderHttp
+}
+
+/// Whether a provider error message reports that the request exceeded the model's context window.
+pub fn is_context_overflow_error_text(error: &str) -> bool {
+    let normalized = error.to_ascii_lowercase();
+    let patterns = [
+        "prompt is too long",
+        "input is too long for requested model",
+        "exceeds the context window",
+        "input token count",
+        "maximum prompt length",
+        "reduce the length of the messages",
+        "maximum context length",
+        "exceeds the available context size",
+        "greater than the context length",
+        "context window exceeds limit",
+        "exceeded model token limit",
+        "context length exceeded",
+        "too many tokens",
+        "token limit exceeded",
+    ];
+
+    patterns.iter().any(|pattern| normalized.contains(pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_http_error_picks_specific_codes() {
+        assert_eq!(
+            classify_http_error(401, "invalid x-api-key"),
+            PiAiErrorCode::AuthInvalid
+        );
+        assert_eq!(
+            classify_http_error(429, r#"{"error":{"code":"insufficient_quota"}}"#),
+            PiAiErrorCode::QuotaExceeded
+        );
+        assert_eq!(
+            classify_http_error(400, "This model's maximum context length is 128000 tokens"),
+            PiAiErrorCode::ContextTooLarge
+        );
+        assert_eq!(
+            classify_http_error(400, r#"{"error":{"code":"content_filter"}}"#),
+            PiAiErrorCode::ContentFiltered
+        );
+        assert_eq!(
+            classify_http_error(404, "The model `gpt-9` does not exist"),
+            PiAiErrorCode::ModelNotFound
+        );
+    }
+
+    #[test]
+    fn classify_http_error_keeps_rate_limits_and_bare_404s_generic() {
+        assert_eq!(
+            classify_http_error(429, "Rate limit reached: too many tokens per minute"),
+            PiAiErrorCode::ProviderHttp
+        );
+        assert_eq!(
+            classify_http_error(404, "404 page not found"),
+            PiAiErrorCode::ProviderHttp
+        );
+        assert_eq!(
+            classify_http_error(503, "overloaded"),
+            PiAiErrorCode::ProviderHttp
+        );
+    }
+
+    #[test]
+    fn error_remediation_reads_compact_json_messages() {
+        let error = PiAiError::new(PiAiErrorCode::QuotaExceeded, "out of credit");
+        assert_eq!(
+            error_remediation(&error.as_compact_json()),
+            PiAiErrorCode::QuotaExceeded.remediation()
+        );
+        assert!(error_remediation("plain provider text").is_none());
+        assert!(error_remediation(
+            &PiAiError::new(PiAiErrorCode::ProviderHttp, "HTTP 500").as_compact_json()
+        )
+        .is_none());
+    }
+}
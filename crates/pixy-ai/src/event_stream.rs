@@ -1,6 +1,7 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
+use serde_json::Value;
 use tokio::sync::{mpsc, Mutex as AsyncMutex, Notify};
 
 use crate::types::{AssistantMessage, AssistantMessageEvent, DoneReason, ErrorReason};
@@ -97,6 +98,11 @@ where
         self.inner.final_notify.notify_waiters();
     }
 
+    /// Whether a clone of this stream is still alive elsewhere, e.g. held by a consumer.
+    pub fn has_other_handles(&self) -> bool {
+        Arc::strong_count(&self.inner) > 1
+    }
+
     pub async fn next(&self) -> Option<T> {
         loop {
             {
@@ -207,3 +213,215 @@ impl AssistantStreamWriter {
         self.stream.end(None);
     }
 }
+
+/// Incrementally parses a JSON document that arrives in chunks, such as streamed tool-call
+/// arguments, so fields can be read before the document is complete.
+///
+/// Each [`push`](Self::push) scans only the new text. [`value`](Self::value) closes whatever
+/// is still open: a string value being streamed is cut where it stands, while an unfinished
+/// key or number is left out until it completes.
+#[derive(Debug, Clone, Default)]
+pub struct PartialJsonParser {
+    buffer: String,
+    stack: Vec<PartialJsonFrame>,
+    in_string: bool,
+    string_is_key: bool,
+    escaped: bool,
+    in_scalar: bool,
+    /// The longest prefix that closes into valid JSON, with the text that closes it.
+    safe_prefix: Option<(usize, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PartialJsonFrame {
+    Object { expecting_key: bool },
+    Array,
+}
+
+impl PartialJsonParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The text received so far.
+    pub fn buffer(&self) -> &str {
+        &self.buffer
+    }
+
+    pub fn push(&mut self, chunk: &str) {
+        let start = self.buffer.len();
+        self.buffer.push_str(chunk);
+        for (offset, byte) in chunk.bytes().enumerate() {
+            self.scan(start + offset, byte);
+        }
+    }
+
+    /// The value of the document so far, or `None` before any of it can be read.
+    pub fn value(&self) -> Option<Value> {
+        if self.stack.is_empty() && !self.in_string {
+            return serde_json::from_str(&self.buffer).ok();
+        }
+
+        if self.in_string && !self.string_is_key {
+            let closers = self.closers();
+            // Back off over an escape sequence cut in half, e.g. a trailing `\u00`.
+            let mut end = self.buffer.len();
+            for _ in 0..6 {
+                if self.buffer.is_char_boundary(end) {
+                    let candidate = format!("{}\"{closers}", &self.buffer[..end]);
+                    if let Ok(value) = serde_json::from_str(&candidate) {
+                        return Some(value);
+                    }
+                }
+                let Some(previous) = end.checked_sub(1) else {
+                    break;
+                };
+                end = previous;
+            }
+        }
+
+        let (end, closers) = self.safe_prefix.as_ref()?;
+        serde_json::from_str(&format!("{}{closers}", &self.buffer[..*end])).ok()
+    }
+
+    fn scan(&mut self, index: usize, byte: u8) {
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if byte == b'\\' {
+                self.escaped = true;
+            } else if byte == b'"' {
+                self.in_string = false;
+                if self.string_is_key {
+                    if let Some(PartialJsonFrame::Object { expecting_key }) = self.stack.last_mut()
+                    {
+                        *expecting_key = false;
+                    }
+                } else {
+                    self.complete_value(index + 1);
+                }
+            }
+            return;
+        }
+
+        if self.in_scalar {
+            if matches!(byte, b',' | b'}' | b']') || byte.is_ascii_whitespace() {
+                self.in_scalar = false;
+                self.complete_value(index);
+            } else {
+                return;
+            }
+        }
+
+        match byte {
+            b'"' => {
+                self.in_string = true;
+                self.string_is_key = matches!(
+                    self.stack.last(),
+                    Some(PartialJsonFrame::Object {
+                        expecting_key: true
+                    })
+                );
+            }
+            b'{' => {
+                self.stack.push(PartialJsonFrame::Object {
+                    expecting_key: true,
+                });
+                self.mark_safe(index + 1);
+            }
+            b'[' => {
+                self.stack.push(PartialJsonFrame::Array);
+                self.mark_safe(index + 1);
+            }
+            b'}' | b']' => {
+                self.stack.pop();
+                self.complete_value(index + 1);
+            }
+            b',' => {
+                if let Some(PartialJsonFrame::Object { expecting_key }) = self.stack.last_mut() {
+                    *expecting_key = true;
+                }
+            }
+            b':' => {}
+            byte if byte.is_ascii_whitespace() => {}
+            _ => self.in_scalar = true,
+        }
+    }
+
+    fn complete_value(&mut self, end: usize) {
+        if !self.stack.is_empty() {
+            self.mark_safe(end);
+        }
+    }
+
+    fn mark_safe(&mut self, end: usize) {
+        self.safe_prefix = Some((end, self.closers()));
+    }
+
+    fn closers(&self) -> String {
+        self.stack
+            .iter()
+            .rev()
+            .map(|frame| match frame {
+                PartialJsonFrame::Object { .. } => '}',
+                PartialJsonFrame::Array => ']',
+            })
+            .collect()
+    }
+}
+
+/// Parses a possibly incomplete JSON document; see [`PartialJsonParser`].
+pub fn parse_partial_json(text: &str) -> Option<Value> {
+    let mut parser = PartialJsonParser::new();
+    parser.push(text);
+    parser.value()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn partial_json_exposes_fields_as_they_complete() {
+        let mut parser = PartialJsonParser::new();
+        assert_eq!(parser.value(), None);
+
+        parser.push(r#"{"pa"#);
+        assert_eq!(parser.value(), Some(json!({})));
+        parser.push(r#"th": "src/li"#);
+        assert_eq!(parser.value(), Some(json!({ "path": "src/li" })));
+        parser.push(r#"b.rs", "line": 1"#);
+        assert_eq!(parser.value(), Some(json!({ "path": "src/lib.rs" })));
+        parser.push(r#"2, "edits": [{"old": "a\"#);
+        assert_eq!(
+            parser.value(),
+            Some(json!({ "path": "src/lib.rs", "line": 12, "edits": [{ "old": "a" }] }))
+        );
+        parser.push(r#"nb"}, tr"#);
+        assert_eq!(
+            parser.value(),
+            Some(json!({ "path": "src/lib.rs", "line": 12, "edits": [{ "old": "a\nb" }] }))
+        );
+        parser.push("ue]}");
+        assert_eq!(
+            parser.value(),
+            Some(json!({ "path": "src/lib.rs", "line": 12, "edits": [{ "old": "a\nb" }, true] }))
+        );
+    }
+
+    #[test]
+    fn partial_json_drops_half_received_escapes_and_keys() {
+        assert_eq!(
+            parse_partial_json(r#"{"text": "caf\u00"#),
+            Some(json!({ "text": "caf" }))
+        );
+        assert_eq!(
+            parse_partial_json(r#"{"a": {"b": [1, 2"#),
+            Some(json!({ "a": { "b": [1] } }))
+        );
+        assert_eq!(parse_partial_json(r#"{"a": 1, "#), Some(json!({ "a": 1 })));
+        assert_eq!(parse_partial_json("not json"), None);
+    }
+}
@@ -0,0 +1,259 @@
+//! Content guards inspect what is sent to a model and what it answers, to redact PII, scrub
+//! secrets or enforce a policy. They run on every [`crate::stream`] call whose
+//! `StreamOptions::guards` lists them.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde_json::json;
+
+use crate::error::{PiAiError, PiAiErrorCode};
+use crate::middleware::{MiddlewareFuture, StreamMiddleware, StreamRequest};
+use crate::types::{
+    AssistantContentBlock, AssistantMessage, AssistantMessageEvent, Context, ErrorReason, Message,
+    StopReason, UserContent, UserContentBlock,
+};
+
+pub type GuardFuture<'a> = MiddlewareFuture<'a>;
+
+/// Inspects, rewrites or rejects content on its way to and from the model.
+///
+/// Guards run in order after all middleware, so they see the context exactly as it is sent
+/// and have the last word on the text the caller receives.
+pub trait ContentGuard: Send + Sync {
+    /// Runs on the outgoing context. It may be rewritten in place; an error rejects the
+    /// request before it is sent.
+    fn check_context<'a>(&'a self, _context: &'a mut Context) -> GuardFuture<'a> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Runs on assistant text before the caller sees it: on each text delta as it arrives,
+    /// then on each complete text block. It may be rewritten in place; an error ends the
+    /// response with that error instead.
+    ///
+    /// A delta is only a fragment, so content split across deltas is caught once the block
+    /// completes; the final message is always checked in full.
+    fn check_output(&self, _text: &mut String) -> Result<(), PiAiError> {
+        Ok(())
+    }
+}
+
+pub type ContentGuardRef = Arc<dyn ContentGuard>;
+
+impl std::fmt::Debug for dyn ContentGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ContentGuard(..)")
+    }
+}
+
+impl PartialEq for dyn ContentGuard {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(self, other)
+    }
+}
+
+/// Rejects prompts and answers that mention any of a list of terms, ignoring case.
+///
+/// Only the user messages added since the last assistant turn are checked, so a rejected
+/// prompt left in the history does not block the rest of the conversation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockedTermsGuard {
+    terms: Vec<String>,
+}
+
+impl BlockedTermsGuard {
+    pub fn new<I, S>(terms: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            terms: terms
+                .into_iter()
+                .map(|term| term.into().trim().to_lowercase())
+                .filter(|term| !term.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    fn check(&self, text: &str, direction: &str) -> Result<(), PiAiError> {
+        let text = text.to_lowercase();
+        match self.terms.iter().find(|term| text.contains(term.as_str())) {
+            Some(term) => Err(content_rejected(
+                format!("Blocked term '{term}' in the {direction}"),
+                direction,
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ContentGuard for BlockedTermsGuard {
+    fn check_context<'a>(&'a self, context: &'a mut Context) -> GuardFuture<'a> {
+        Box::pin(async move {
+            let new_turn = context
+                .messages
+                .iter()
+                .rev()
+                .take_while(|message| !matches!(message, Message::Assistant { .. }));
+            for message in new_turn {
+                let Message::User { content, .. } = message else {
+                    continue;
+                };
+                match content {
+                    UserContent::Text(text) => self.check(text, "prompt")?,
+                    UserContent::Blocks(blocks) => {
+                        for block in blocks {
+                            if let UserContentBlock::Text { text, .. } = block {
+                                self.check(text, "prompt")?;
+                            }
+                        }
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn check_output(&self, text: &mut String) -> Result<(), PiAiError> {
+        self.check(text, "response")
+    }
+}
+
+/// A `ContentRejected` error for guards to return.
+pub fn content_rejected(message: impl Into<String>, direction: &str) -> PiAiError {
+    PiAiError::new(PiAiErrorCode::ContentRejected, message)
+        .with_details(json!({ "direction": direction }))
+}
+
+/// Runs a request's guards as the innermost-ordered middleware. It keeps the guarded text of
+/// each block so far, so it is created per request.
+pub(crate) struct GuardMiddleware {
+    guards: Vec<ContentGuardRef>,
+    guarded_text: Mutex<HashMap<usize, String>>,
+}
+
+impl GuardMiddleware {
+    pub(crate) fn new(guards: Vec<ContentGuardRef>) -> Self {
+        Self {
+            guards,
+            guarded_text: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn check_output(&self, text: &mut String) -> Result<(), PiAiError> {
+        for guard in &self.guards {
+            guard.check_output(text)?;
+        }
+        Ok(())
+    }
+
+    fn guard_event(&self, event: &mut AssistantMessageEvent) -> Result<(), PiAiError> {
+        let mut guarded_text = self
+            .guarded_text
+            .lock()
+            .expect("guarded text mutex poisoned");
+        let partial = match event {
+            AssistantMessageEvent::TextDelta {
+                content_index,
+                delta,
+                partial,
+            } => {
+                self.check_output(delta)?;
+                guarded_text
+                    .entry(*content_index)
+                    .or_default()
+                    .push_str(delta);
+                partial
+            }
+            AssistantMessageEvent::TextEnd {
+                content_index,
+                content,
+                partial,
+            } => {
+                self.check_output(content)?;
+                guarded_text.insert(*content_index, content.clone());
+                partial
+            }
+            AssistantMessageEvent::Done { message, .. }
+            | AssistantMessageEvent::Error { error: message, .. } => {
+                for block in &mut message.content {
+                    if let AssistantContentBlock::Text { text, .. } = block {
+                        self.check_output(text)?;
+                    }
+                }
+                return Ok(());
+            }
+            AssistantMessageEvent::Start { partial }
+            | AssistantMessageEvent::TextStart { partial, .. }
+            | AssistantMessageEvent::ThinkingStart { partial, .. }
+            | AssistantMessageEvent::ThinkingDelta { partial, .. }
+            | AssistantMessageEvent::ThinkingEnd { partial, .. }
+            | AssistantMessageEvent::ToolcallStart { partial, .. }
+            | AssistantMessageEvent::ToolcallDelta { partial, .. }
+            | AssistantMessageEvent::ToolcallEnd { partial, .. }
+            | AssistantMessageEvent::UsageDelta { partial, .. } => partial,
+        };
+        substitute_guarded_text(partial, &guarded_text);
+        Ok(())
+    }
+
+    /// The response ended by `error`, with its text dropped.
+    fn rejected_message(event: &AssistantMessageEvent, error: PiAiError) -> AssistantMessage {
+        let mut message = match event {
+            AssistantMessageEvent::Start { partial }
+            | AssistantMessageEvent::TextStart { partial, .. }
+            | AssistantMessageEvent::TextDelta { partial, .. }
+            | AssistantMessageEvent::TextEnd { partial, .. }
+            | AssistantMessageEvent::ThinkingStart { partial, .. }
+            | AssistantMessageEvent::ThinkingDelta { partial, .. }
+            | AssistantMessageEvent::ThinkingEnd { partial, .. }
+            | AssistantMessageEvent::ToolcallStart { partial, .. }
+            | AssistantMessageEvent::ToolcallDelta { partial, .. }
+            | AssistantMessageEvent::ToolcallEnd { partial, .. }
+            | AssistantMessageEvent::UsageDelta { partial, .. } => partial.clone(),
+            AssistantMessageEvent::Done { message, .. } => message.clone(),
+            AssistantMessageEvent::Error { error, .. } => error.clone(),
+        };
+        message
+            .content
+            .retain(|block| !matches!(block, AssistantContentBlock::Text { .. }));
+        message.stop_reason = StopReason::Error;
+        message.error_message = Some(error.as_compact_json());
+        message
+    }
+}
+
+impl StreamMiddleware for GuardMiddleware {
+    fn on_request<'a>(&'a self, request: &'a mut StreamRequest) -> MiddlewareFuture<'a> {
+        Box::pin(async move {
+            for guard in &self.guards {
+                guard.check_context(&mut request.context).await?;
+            }
+            Ok(())
+        })
+    }
+
+    fn on_event(&self, event: &mut AssistantMessageEvent) {
+        if let Err(error) = self.guard_event(event) {
+            *event = AssistantMessageEvent::Error {
+                reason: ErrorReason::Error,
+                error: Self::rejected_message(event, error),
+            };
+        }
+    }
+}
+
+/// Replaces text blocks of `message` with their guarded text; blocks with none stay empty
+/// until their first delta has been checked.
+fn substitute_guarded_text(message: &mut AssistantMessage, guarded_text: &HashMap<usize, String>) {
+    for (index, block) in message.content.iter_mut().enumerate() {
+        if let AssistantContentBlock::Text { text, .. } = block {
+            *text = guarded_text.get(&index).cloned().unwrap_or_default();
+        }
+    }
+}
@@ -0,0 +1,332 @@
+//! How provider HTTP clients reach the network: an explicit proxy, extra trusted roots for
+//! TLS-intercepting proxies, and client certificates for mutual TLS. Like retry policies, a
+//! transport is set for the whole process and can be overridden per provider.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+use reqwest::{Certificate, Client, ClientBuilder, Identity, NoProxy, Proxy};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{PiAiError, PiAiErrorCode};
+
+/// Keepalive probes hold connections open through long answers, and pooled connections are
+/// dropped before providers close them from their side during long tool runs.
+const TCP_KEEPALIVE: Duration = Duration::from_secs(30);
+/// Below the idle limit of common provider load balancers.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpTransport {
+    /// `http://` or `https://` proxy for every request. Unset honors `HTTPS_PROXY`,
+    /// `HTTP_PROXY` and `NO_PROXY`.
+    pub proxy: Option<String>,
+    /// Hosts, domains (`.corp.example`) or CIDR ranges reached without the proxy.
+    #[serde(rename = "noProxy")]
+    pub no_proxy: Vec<String>,
+    /// PEM bundle of root certificates trusted in addition to the built-in ones.
+    #[serde(rename = "caBundle")]
+    pub ca_bundle: Option<PathBuf>,
+    /// PEM certificate chain presented for mutual TLS. It may hold the private key too.
+    #[serde(rename = "clientCert")]
+    pub client_cert: Option<PathBuf>,
+    /// PEM private key for `client_cert`, when it is kept in a separate file.
+    #[serde(rename = "clientKey")]
+    pub client_key: Option<PathBuf>,
+}
+
+impl HttpTransport {
+    /// `self` with the settings it leaves unset taken from `fallback`.
+    pub fn or(&self, fallback: &HttpTransport) -> HttpTransport {
+        HttpTransport {
+            proxy: self.proxy.clone().or_else(|| fallback.proxy.clone()),
+            no_proxy: if self.no_proxy.is_empty() {
+                fallback.no_proxy.clone()
+            } else {
+                self.no_proxy.clone()
+            },
+            ca_bundle: self
+                .ca_bundle
+                .clone()
+                .or_else(|| fallback.ca_bundle.clone()),
+            client_cert: self
+                .client_cert
+                .clone()
+                .or_else(|| fallback.client_cert.clone()),
+            client_key: self
+                .client_key
+                .clone()
+                .or_else(|| fallback.client_key.clone()),
+        }
+    }
+
+    fn build_clients(&self) -> Result<TransportClients, PiAiError> {
+        Ok(TransportClients {
+            remote: Arc::new(self.client_builder(false)?.build().map_err(invalid)?),
+            loopback: Arc::new(self.client_builder(true)?.build().map_err(invalid)?),
+        })
+    }
+
+    fn client_builder(&self, loopback: bool) -> Result<ClientBuilder, PiAiError> {
+        let mut builder = Client::builder()
+            .tcp_keepalive(TCP_KEEPALIVE)
+            .pool_idle_timeout(POOL_IDLE_TIMEOUT);
+        if loopback {
+            // Local servers such as Ollama are never behind the proxy.
+            builder = builder.no_proxy();
+        } else if let Some(proxy) = self.proxy.as_deref().or_else(|| {
+            // reqwest reads the environment itself, but then ignores `no_proxy` from config.
+            (!self.no_proxy.is_empty())
+                .then(environment_proxy)
+                .flatten()
+                .map(String::as_str)
+        }) {
+            let no_proxy = NoProxy::from_string(&self.no_proxy.join(","));
+            builder = builder.proxy(Proxy::all(proxy).map_err(invalid)?.no_proxy(no_proxy));
+        }
+
+        if let Some(path) = &self.ca_bundle {
+            for certificate in Certificate::from_pem_bundle(&read_pem(path)?).map_err(invalid)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert), key) => {
+                let mut pem = read_pem(cert)?;
+                if let Some(key) = key {
+                    pem.push(b'\n');
+                    pem.extend(read_pem(key)?);
+                }
+                builder = builder.identity(Identity::from_pem(&pem).map_err(invalid)?);
+            }
+            (None, Some(_)) => {
+                return Err(invalid("client_key is set without client_cert"));
+            }
+            (None, None) => {}
+        }
+        Ok(builder)
+    }
+}
+
+struct TransportClients {
+    remote: Arc<Client>,
+    loopback: Arc<Client>,
+}
+
+impl TransportClients {
+    fn get(&self, base_url: &str) -> Arc<Client> {
+        if is_loopback_base_url(base_url) {
+            self.loopback.clone()
+        } else {
+            self.remote.clone()
+        }
+    }
+}
+
+#[derive(Default)]
+struct TransportRegistry {
+    default: HttpTransport,
+    default_clients: Option<TransportClients>,
+    /// Each provider's own settings and the clients built from them over the default.
+    providers: HashMap<String, (HttpTransport, TransportClients)>,
+}
+
+fn registry() -> &'static RwLock<TransportRegistry> {
+    static REGISTRY: OnceLock<RwLock<TransportRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(TransportRegistry::default()))
+}
+
+/// Sets the transport for every provider without its own. Fails without changing anything
+/// when a proxy URL or certificate file is invalid.
+pub fn set_default_http_transport(transport: HttpTransport) -> Result<(), PiAiError> {
+    let mut registry = registry()
+        .write()
+        .expect("http transport registry lock poisoned");
+    if registry.default == transport && registry.default_clients.is_some() {
+        return Ok(());
+    }
+    let default_clients = transport.build_clients()?;
+    let mut providers = HashMap::new();
+    for (provider, (own, _)) in &registry.providers {
+        let clients = own.or(&transport).build_clients()?;
+        providers.insert(provider.clone(), (own.clone(), clients));
+    }
+    registry.default = transport;
+    registry.default_clients = Some(default_clients);
+    registry.providers = providers;
+    Ok(())
+}
+
+/// Sets the transport for `provider`; settings it leaves unset fall back to the default.
+pub fn set_provider_http_transport(
+    provider: impl Into<String>,
+    transport: HttpTransport,
+) -> Result<(), PiAiError> {
+    let provider = provider.into();
+    let mut registry = registry()
+        .write()
+        .expect("http transport registry lock poisoned");
+    if registry
+        .providers
+        .get(&provider)
+        .is_some_and(|(own, _)| *own == transport)
+    {
+        return Ok(());
+    }
+    let clients = transport.or(&registry.default).build_clients()?;
+    registry.providers.insert(provider, (transport, clients));
+    Ok(())
+}
+
+/// The transport requests to `provider` use.
+pub fn http_transport_for_provider(provider: &str) -> HttpTransport {
+    let registry = registry()
+        .read()
+        .expect("http transport registry lock poisoned");
+    match registry.providers.get(provider) {
+        Some((own, _)) => own.or(&registry.default),
+        None => registry.default.clone(),
+    }
+}
+
+/// The client for requests from `provider` to `base_url`. Clients are built once per
+/// transport, so connections are pooled across requests.
+pub(crate) fn http_client(provider: &str, base_url: &str) -> Arc<Client> {
+    {
+        let registry = registry()
+            .read()
+            .expect("http transport registry lock poisoned");
+        if let Some((_, clients)) = registry.providers.get(provider) {
+            return clients.get(base_url);
+        }
+        if let Some(clients) = &registry.default_clients {
+            return clients.get(base_url);
+        }
+    }
+
+    let mut registry = registry()
+        .write()
+        .expect("http transport registry lock poisoned");
+    let default = registry.default.clone();
+    registry
+        .default_clients
+        .get_or_insert_with(|| {
+            default
+                .build_clients()
+                .unwrap_or_else(|_| TransportClients {
+                    remote: Arc::new(Client::new()),
+                    loopback: Arc::new(Client::new()),
+                })
+        })
+        .get(base_url)
+}
+
+pub(crate) fn is_loopback_base_url(base_url: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(base_url) else {
+        return false;
+    };
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    host.eq_ignore_ascii_case("localhost") || host == "127.0.0.1" || host == "::1"
+}
+
+fn environment_proxy() -> Option<&'static String> {
+    static PROXY: OnceLock<Option<String>> = OnceLock::new();
+    PROXY
+        .get_or_init(|| {
+            [
+                "HTTPS_PROXY",
+                "https_proxy",
+                "HTTP_PROXY",
+                "http_proxy",
+                "ALL_PROXY",
+                "all_proxy",
+            ]
+            .into_iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.trim().is_empty())
+        })
+        .as_ref()
+}
+
+fn read_pem(path: &Path) -> Result<Vec<u8>, PiAiError> {
+    std::fs::read(path).map_err(|error| invalid(format!("cannot read {}: {error}", path.display())))
+}
+
+fn invalid(error: impl std::fmt::Display) -> PiAiError {
+    PiAiError::new(
+        PiAiErrorCode::ProviderTransport,
+        format!("Invalid HTTP transport: {error}"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn provider_transport_falls_back_to_the_default_per_setting() {
+        let provider = HttpTransport {
+            ca_bundle: Some(PathBuf::from("/etc/corp/ca.pem")),
+            ..HttpTransport::default()
+        };
+        let default = HttpTransport {
+            proxy: Some("http://proxy.corp:3128".to_string()),
+            no_proxy: vec![".corp".to_string()],
+            ca_bundle: Some(PathBuf::from("/etc/ssl/ca.pem")),
+            ..HttpTransport::default()
+        };
+
+        let merged = provider.or(&default);
+        assert_eq!(merged.proxy.as_deref(), Some("http://proxy.corp:3128"));
+        assert_eq!(merged.no_proxy, vec![".corp"]);
+        assert_eq!(merged.ca_bundle, Some(PathBuf::from("/etc/corp/ca.pem")));
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected_when_set() {
+        let missing_ca = HttpTransport {
+            ca_bundle: Some(PathBuf::from("/nonexistent/pixy-ca.pem")),
+            ..HttpTransport::default()
+        };
+        let error = set_provider_http_transport("pixy-test-missing-ca", missing_ca)
+            .expect_err("missing CA bundle should be rejected");
+        assert_eq!(error.code, PiAiErrorCode::ProviderTransport);
+        assert!(error.message.contains("pixy-ca.pem"), "{}", error.message);
+
+        let key_only = HttpTransport {
+            client_key: Some(PathBuf::from("/etc/corp/client.key")),
+            ..HttpTransport::default()
+        };
+        assert!(set_provider_http_transport("pixy-test-key-only", key_only).is_err());
+        assert_eq!(
+            http_transport_for_provider("pixy-test-key-only"),
+            http_transport_for_provider("pixy-test-unset")
+        );
+    }
+
+    #[test]
+    fn provider_clients_are_reused_and_kept_apart() {
+        let proxied = HttpTransport {
+            proxy: Some("http://127.0.0.1:3128".to_string()),
+            ..HttpTransport::default()
+        };
+        set_provider_http_transport("pixy-test-proxied", proxied.clone()).expect("valid proxy");
+
+        let first = http_client("pixy-test-proxied", "https://api.example.com/v1");
+        let second = http_client("pixy-test-proxied", "https://api.example.com/v1");
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(!Arc::ptr_eq(
+            &first,
+            &http_client("pixy-test-other", "https://api.example.com/v1")
+        ));
+        assert_eq!(
+            http_transport_for_provider("pixy-test-proxied").proxy,
+            proxied.proxy
+        );
+    }
+}
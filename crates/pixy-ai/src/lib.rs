@@ -1,10 +1,25 @@
 //! Core abstractions for provider-agnostic LLM streaming.
 
 mod api_registry;
+mod budget;
+mod context_tools;
+mod embeddings;
 mod error;
 mod event_stream;
+mod guard;
+mod http_transport;
+mod message_adapter;
+mod middleware;
+mod model_catalog;
+mod pricing;
 mod providers;
+mod rate_limit;
+mod realtime;
+pub mod record;
+mod response_cache;
 mod stream;
+mod telemetry;
+pub mod tokenizer;
 mod transport_retry;
 mod types;
 mod validation;
@@ -14,18 +29,58 @@ pub use api_registry::{
     unregister_api_providers, ApiProvider, ApiProviderRef, ApiStreamFunction,
     ApiStreamSimpleFunction, ClosureApiProvider,
 };
-pub use error::{PiAiError, PiAiErrorCode};
-pub use event_stream::{AssistantMessageEventStream, AssistantStreamWriter, EventStream};
-pub use providers::{register_builtin_api_providers, reset_api_providers, ReliableProvider};
-pub use stream::{complete, complete_simple, stream, stream_simple};
+pub use budget::{BudgetAction, BudgetGuard, BudgetLimits, BudgetSpend};
+pub use context_tools::{transcript, transcript_line, CompressionStrategy, TokenBudget};
+pub use embeddings::{embed, EmbeddingModel, EmbeddingOptions};
+pub use error::{error_remediation, is_context_overflow_error_text, PiAiError, PiAiErrorCode};
+pub use event_stream::{
+    parse_partial_json, AssistantMessageEventStream, AssistantStreamWriter, EventStream,
+    PartialJsonParser,
+};
+pub use guard::{content_rejected, BlockedTermsGuard, ContentGuard, ContentGuardRef, GuardFuture};
+pub use http_transport::{
+    http_transport_for_provider, set_default_http_transport, set_provider_http_transport,
+    HttpTransport,
+};
+pub use message_adapter::{MessageAdapter, MessageRule, SystemRole};
+pub use middleware::{
+    clear_stream_middleware, register_stream_middleware, unregister_stream_middleware,
+    MiddlewareFuture, StreamMiddleware, StreamMiddlewareRef, StreamRequest,
+};
+pub use model_catalog::{list_models, lookup_model_info, ModelCatalog, ModelInfo, ModelSource};
+pub use pricing::{calculate_cost, lookup_model_pricing, model_pricing};
+pub use providers::{
+    list_ollama_models, register_builtin_api_providers, reset_api_providers, FallbackCondition,
+    ReliableProvider,
+};
+pub use rate_limit::{
+    clear_rate_limit, rate_limit, rate_limit_metrics, set_rate_limit, RateLimit, RateLimitMetrics,
+    RateLimitPolicy,
+};
+pub use realtime::{RealtimeEvent, RealtimeOptions, RealtimeSession};
+pub use response_cache::{
+    clear_response_cache, set_response_cache, FileResponseCache, MemoryResponseCache,
+    ResponseCache, ResponseCacheRef,
+};
+pub use stream::{
+    complete, complete_batch, complete_simple, complete_structured, stream, stream_simple,
+    BatchOptions, BatchRequest, BatchResultStream,
+};
+pub use telemetry::{llm_metrics, LlmMetrics};
 pub use transport_retry::{
-    set_transport_retry_count, transport_retry_count, transport_retry_count_with_override,
+    default_retry_policy, retry_metrics, retry_policy_for_provider, set_default_retry_policy,
+    set_provider_retry_policy, RetryMetrics, RetryPolicy, StreamTransport,
     DEFAULT_TRANSPORT_RETRY_COUNT,
 };
 pub use types::{
-    Api, AssistantContentBlock, AssistantMessage, AssistantMessageEvent, Context, Cost, DoneReason,
-    ErrorReason, Message, Model, Provider, SimpleStreamOptions, StopReason, StreamOptions,
-    ThinkingLevel, Tool, ToolResultContentBlock, ToolResultMessage, Usage, UserContent,
-    UserContentBlock, UserMessage,
+    Api, AssistantContentBlock, AssistantMessage, AssistantMessageEvent, CacheHint, Context,
+    ContextBranch, Cost, DoneReason, ErrorReason, Message, Model, OpenAICompletionsCompat,
+    Provider, ResponseSchema, SimpleStreamOptions, StopPredicate, StopReason, StreamOptions,
+    StreamStats, ThinkingLevel, Tool, ToolResultContentBlock, ToolResultMessage, Usage,
+    UserContent, UserContentBlock, UserMessage,
+};
+pub use validation::{
+    duplicate_tool_call_id_error, tool_argument_violations, validate_tool_arguments,
+    validate_tool_arguments_with, validate_tool_call, validate_tool_call_ids, validate_tool_calls,
+    ToolArgumentViolation, ToolCall, ToolCallBuilder, ToolValidationOptions,
 };
-pub use validation::{validate_tool_arguments, validate_tool_call, ToolCall};
@@ -0,0 +1,304 @@
+//! Rewrites the generic [`Context`] into the shape a provider accepts before its payload is
+//! built. Each provider declares an adapter: the role its system prompt is sent with and a list
+//! of rules applied in order, so conventions like strict role alternation live in one place.
+
+use crate::types::{AssistantContentBlock, Context, Message, Model, UserContent, UserContentBlock};
+
+/// Role a provider sends the system prompt with, when it takes one in the message list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SystemRole {
+    #[default]
+    System,
+    /// OpenAI reasoning models take instructions as `developer` messages.
+    Developer,
+}
+
+impl SystemRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::Developer => "developer",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageRule {
+    /// Moves the system prompt into a user message at the start of the conversation, for
+    /// models without system instructions.
+    SystemPromptAsFirstUser,
+    /// Joins adjacent user messages into one, for providers that require user and assistant
+    /// turns to alternate. Tool results are left alone.
+    MergeConsecutiveUserMessages,
+    /// Drops thinking produced through a different API than the model's, which cannot be
+    /// replayed there.
+    StripForeignThinking,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageAdapter {
+    pub system_role: SystemRole,
+    pub rules: Vec<MessageRule>,
+}
+
+impl MessageAdapter {
+    pub fn new(rules: impl IntoIterator<Item = MessageRule>) -> Self {
+        Self {
+            system_role: SystemRole::System,
+            rules: rules.into_iter().collect(),
+        }
+    }
+
+    pub fn with_system_role(mut self, system_role: SystemRole) -> Self {
+        self.system_role = system_role;
+        self
+    }
+
+    /// The context as `model` should receive it.
+    pub fn apply(&self, model: &Model, context: &Context) -> Context {
+        let mut context = context.clone();
+        for rule in &self.rules {
+            match rule {
+                MessageRule::SystemPromptAsFirstUser => system_prompt_as_first_user(&mut context),
+                MessageRule::MergeConsecutiveUserMessages => {
+                    merge_consecutive_user_messages(&mut context)
+                }
+                MessageRule::StripForeignThinking => strip_foreign_thinking(model, &mut context),
+            }
+        }
+        context
+    }
+}
+
+fn system_prompt_as_first_user(context: &mut Context) {
+    let Some(system_prompt) = context.system_prompt.take() else {
+        return;
+    };
+    context.system_prompt_cache = None;
+    let timestamp = context
+        .messages
+        .first()
+        .map(message_timestamp)
+        .unwrap_or_default();
+    context.messages.insert(
+        0,
+        Message::User {
+            content: UserContent::Text(system_prompt),
+            timestamp,
+        },
+    );
+}
+
+fn merge_consecutive_user_messages(context: &mut Context) {
+    let mut merged: Vec<Message> = Vec::with_capacity(context.messages.len());
+    for message in context.messages.drain(..) {
+        match (merged.last_mut(), message) {
+            (
+                Some(Message::User {
+                    content: previous, ..
+                }),
+                Message::User { content, .. },
+            ) => append_user_content(previous, content),
+            (_, message) => merged.push(message),
+        }
+    }
+    context.messages = merged;
+}
+
+fn append_user_content(previous: &mut UserContent, next: UserContent) {
+    match (previous, next) {
+        (UserContent::Text(previous), UserContent::Text(next)) => {
+            previous.push_str("\n\n");
+            previous.push_str(&next);
+        }
+        (previous, next) => {
+            let mut blocks = into_blocks(std::mem::replace(previous, UserContent::Blocks(vec![])));
+            blocks.extend(into_blocks(next));
+            *previous = UserContent::Blocks(blocks);
+        }
+    }
+}
+
+fn into_blocks(content: UserContent) -> Vec<UserContentBlock> {
+    match content {
+        UserContent::Text(text) => vec![UserContentBlock::Text {
+            text,
+            text_signature: None,
+            cache: None,
+        }],
+        UserContent::Blocks(blocks) => blocks,
+    }
+}
+
+fn strip_foreign_thinking(model: &Model, context: &mut Context) {
+    for message in &mut context.messages {
+        if let Message::Assistant { content, api, .. } = message {
+            if *api != model.api {
+                content.retain(|block| !matches!(block, AssistantContentBlock::Thinking { .. }));
+            }
+        }
+    }
+}
+
+fn message_timestamp(message: &Message) -> i64 {
+    match message {
+        Message::User { timestamp, .. }
+        | Message::Assistant { timestamp, .. }
+        | Message::ToolResult { timestamp, .. } => *timestamp,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Cost, StopReason, Usage};
+
+    fn model(api: &str) -> Model {
+        Model {
+            id: "test-model".to_string(),
+            name: "Test".to_string(),
+            api: api.to_string(),
+            provider: "test".to_string(),
+            base_url: String::new(),
+            reasoning: false,
+            reasoning_effort: None,
+            input: vec!["text".to_string()],
+            cost: Cost {
+                input: 0.0,
+                output: 0.0,
+                cache_read: 0.0,
+                cache_write: 0.0,
+                total: 0.0,
+            },
+            context_window: 0,
+            max_tokens: 0,
+            deployment: None,
+            api_version: None,
+            compat: None,
+        }
+    }
+
+    fn user(text: &str) -> Message {
+        Message::User {
+            content: UserContent::Text(text.to_string()),
+            timestamp: 1,
+        }
+    }
+
+    fn assistant(api: &str, content: Vec<AssistantContentBlock>) -> Message {
+        Message::Assistant {
+            content,
+            api: api.to_string(),
+            provider: "test".to_string(),
+            model: "test-model".to_string(),
+            usage: Usage {
+                input: 0,
+                output: 0,
+                cache_read: 0,
+                cache_write: 0,
+                reasoning: 0,
+                total_tokens: 0,
+                cost: Cost {
+                    input: 0.0,
+                    output: 0.0,
+                    cache_read: 0.0,
+                    cache_write: 0.0,
+                    total: 0.0,
+                },
+            },
+            stop_reason: StopReason::Stop,
+            error_message: None,
+            timestamp: 2,
+            stats: None,
+        }
+    }
+
+    fn context(messages: Vec<Message>) -> Context {
+        Context {
+            system_prompt: Some("Be brief.".to_string()),
+            messages,
+            tools: None,
+            system_prompt_cache: None,
+            branch: None,
+        }
+    }
+
+    #[test]
+    fn system_prompt_moves_into_the_first_user_turn() {
+        let adapter = MessageAdapter::new([
+            MessageRule::SystemPromptAsFirstUser,
+            MessageRule::MergeConsecutiveUserMessages,
+        ]);
+        let adapted = adapter.apply(&model("test"), &context(vec![user("hi"), user("there")]));
+
+        assert_eq!(adapted.system_prompt, None);
+        assert_eq!(adapted.messages.len(), 1);
+        assert!(matches!(
+            &adapted.messages[0],
+            Message::User { content: UserContent::Text(text), .. } if text == "Be brief.\n\nhi\n\nthere"
+        ));
+    }
+
+    #[test]
+    fn merging_mixed_user_content_keeps_every_block() {
+        let image = Message::User {
+            content: UserContent::Blocks(vec![UserContentBlock::Image {
+                data: "aGk=".to_string(),
+                mime_type: "image/png".to_string(),
+            }]),
+            timestamp: 1,
+        };
+        let adapter = MessageAdapter::new([MessageRule::MergeConsecutiveUserMessages]);
+        let adapted = adapter.apply(
+            &model("test"),
+            &context(vec![
+                user("look"),
+                image,
+                assistant("test", vec![]),
+                user("ok"),
+            ]),
+        );
+
+        assert_eq!(adapted.messages.len(), 3);
+        let Message::User {
+            content: UserContent::Blocks(blocks),
+            ..
+        } = &adapted.messages[0]
+        else {
+            panic!("expected merged blocks");
+        };
+        assert!(matches!(&blocks[0], UserContentBlock::Text { text, .. } if text == "look"));
+        assert!(matches!(&blocks[1], UserContentBlock::Image { .. }));
+    }
+
+    #[test]
+    fn only_thinking_from_other_apis_is_stripped() {
+        let thinking = || AssistantContentBlock::Thinking {
+            thinking: "hmm".to_string(),
+            thinking_signature: Some("sig".to_string()),
+        };
+        let text = AssistantContentBlock::Text {
+            text: "done".to_string(),
+            text_signature: None,
+        };
+        let adapter = MessageAdapter::new([MessageRule::StripForeignThinking]);
+        let adapted = adapter.apply(
+            &model("anthropic-messages"),
+            &context(vec![
+                assistant("openai-responses", vec![thinking(), text.clone()]),
+                assistant("anthropic-messages", vec![thinking(), text]),
+            ]),
+        );
+
+        let block_counts = adapted
+            .messages
+            .iter()
+            .map(|message| match message {
+                Message::Assistant { content, .. } => content.len(),
+                _ => 0,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(block_counts, vec![1, 2]);
+        assert_eq!(adapter.system_role.as_str(), "system");
+    }
+}
@@ -0,0 +1,169 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::error::PiAiError;
+use crate::guard::GuardMiddleware;
+use crate::types::{AssistantMessage, AssistantMessageEvent, Context, Model, StreamOptions};
+use crate::AssistantMessageEventStream;
+
+pub type MiddlewareFuture<'a> = Pin<Box<dyn Future<Output = Result<(), PiAiError>> + Send + 'a>>;
+
+/// What a middleware may inspect or rewrite before the request reaches the provider.
+#[derive(Debug, Clone)]
+pub struct StreamRequest {
+    pub model: Model,
+    pub context: Context,
+    pub options: StreamOptions,
+}
+
+/// Hooks around every [`crate::stream`] and [`crate::stream_simple`] call.
+///
+/// Global middleware runs first, in registration order, followed by the request's
+/// `StreamOptions::middleware`.
+pub trait StreamMiddleware: Send + Sync {
+    /// Runs before the provider is called. An error fails the request without sending it.
+    fn on_request<'a>(&'a self, _request: &'a mut StreamRequest) -> MiddlewareFuture<'a> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Runs for every event before the caller sees it.
+    fn on_event(&self, _event: &mut AssistantMessageEvent) {}
+
+    /// Runs once with the final message, whether the request finished or failed.
+    fn on_complete(&self, _message: &AssistantMessage) {}
+}
+
+pub type StreamMiddlewareRef = Arc<dyn StreamMiddleware>;
+
+impl std::fmt::Debug for dyn StreamMiddleware {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("StreamMiddleware(..)")
+    }
+}
+
+impl PartialEq for dyn StreamMiddleware {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(self, other)
+    }
+}
+
+#[derive(Clone)]
+struct RegisteredMiddleware {
+    middleware: StreamMiddlewareRef,
+    source_id: Option<String>,
+}
+
+fn middleware_registry() -> &'static RwLock<Vec<RegisteredMiddleware>> {
+    static REGISTRY: OnceLock<RwLock<Vec<RegisteredMiddleware>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(Vec::new()))
+}
+
+pub fn register_stream_middleware(middleware: StreamMiddlewareRef, source_id: Option<String>) {
+    let mut registry = middleware_registry()
+        .write()
+        .expect("middleware registry lock poisoned");
+    registry.push(RegisteredMiddleware {
+        middleware,
+        source_id,
+    });
+}
+
+pub fn unregister_stream_middleware(source_id: &str) {
+    let mut registry = middleware_registry()
+        .write()
+        .expect("middleware registry lock poisoned");
+    registry.retain(|entry| entry.source_id.as_deref() != Some(source_id));
+}
+
+pub fn clear_stream_middleware() {
+    let mut registry = middleware_registry()
+        .write()
+        .expect("middleware registry lock poisoned");
+    registry.clear();
+}
+
+/// Global middleware followed by the per-call middleware in `options`, then its guards.
+pub(crate) fn collect_middleware(options: Option<&StreamOptions>) -> Vec<StreamMiddlewareRef> {
+    let registry = middleware_registry()
+        .read()
+        .expect("middleware registry lock poisoned");
+    let mut middleware = registry
+        .iter()
+        .map(|entry| entry.middleware.clone())
+        .chain(
+            options
+                .into_iter()
+                .flat_map(|options| options.middleware.clone()),
+        )
+        .collect::<Vec<_>>();
+    if let Some(options) = options.filter(|options| !options.guards.is_empty()) {
+        middleware.push(Arc::new(GuardMiddleware::new(options.guards.clone())));
+    }
+    middleware
+}
+
+/// Passes the request through every `on_request` hook. Without middleware the request is
+/// returned untouched, so `None` options stay `None`.
+pub(crate) async fn apply_request_middleware(
+    middleware: &[StreamMiddlewareRef],
+    model: Model,
+    context: Context,
+    options: Option<StreamOptions>,
+) -> Result<(Model, Context, Option<StreamOptions>), PiAiError> {
+    if middleware.is_empty() {
+        return Ok((model, context, options));
+    }
+
+    let mut request = StreamRequest {
+        model,
+        context,
+        options: options.unwrap_or_default(),
+    };
+    for middleware in middleware {
+        middleware.on_request(&mut request).await?;
+    }
+    Ok((request.model, request.context, Some(request.options)))
+}
+
+/// Runs `run` against an inner stream and forwards its events to `target` through the
+/// `on_event` hooks. `on_complete` runs before the terminal event is forwarded, so it has
+/// finished by the time the caller sees the result. Events after the first terminal one,
+/// which a hook may have produced by rewriting an event, are dropped.
+pub(crate) async fn run_with_middleware<F, Fut>(
+    middleware: Vec<StreamMiddlewareRef>,
+    target: AssistantMessageEventStream,
+    run: F,
+) where
+    F: FnOnce(AssistantMessageEventStream) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    if middleware.is_empty() {
+        run(target).await;
+        return;
+    }
+
+    let source = AssistantMessageEventStream::new();
+    let forward = async {
+        while let Some(mut event) = source.next().await {
+            for middleware in &middleware {
+                middleware.on_event(&mut event);
+            }
+            let final_message = match &event {
+                AssistantMessageEvent::Done { message, .. } => Some(message),
+                AssistantMessageEvent::Error { error, .. } => Some(error),
+                _ => None,
+            };
+            if let Some(message) = final_message {
+                for middleware in &middleware {
+                    middleware.on_complete(message);
+                }
+                target.push(event);
+                break;
+            }
+            target.push(event);
+        }
+        target.end(None);
+    };
+    tokio::join!(run(source.clone()), forward);
+}
@@ -0,0 +1,181 @@
+//! A catalog of chat models built from configuration, provider `/models` listings and the
+//! limits bundled with this crate.
+
+use crate::error::PiAiError;
+use crate::pricing::lookup_model_pricing;
+use crate::providers::list_source_models;
+use crate::types::{Cost, Model};
+
+/// Built-in limits: `(model id prefix, context window, max output tokens, reasoning, image
+/// input)`. The longest matching prefix wins, as for built-in pricing.
+const BUNDLED_MODELS: &[(&str, u32, u32, bool, bool)] = &[
+    ("claude-3-5-haiku", 200_000, 8_192, false, true),
+    ("claude-3-5-sonnet", 200_000, 8_192, false, true),
+    ("claude-3-7-sonnet", 200_000, 64_000, true, true),
+    ("claude-3-haiku", 200_000, 4_096, false, true),
+    ("claude-3-opus", 200_000, 4_096, false, true),
+    ("claude-haiku-4", 200_000, 64_000, true, true),
+    ("claude-opus-4", 200_000, 32_000, true, true),
+    ("claude-opus-4-5", 200_000, 64_000, true, true),
+    ("claude-sonnet-4", 200_000, 64_000, true, true),
+    ("deepseek-chat", 128_000, 8_192, false, false),
+    ("deepseek-reasoner", 128_000, 64_000, true, false),
+    ("gemini-1.5-flash", 1_048_576, 8_192, false, true),
+    ("gemini-1.5-pro", 2_097_152, 8_192, false, true),
+    ("gemini-2.0-flash", 1_048_576, 8_192, false, true),
+    ("gemini-2.5-flash", 1_048_576, 65_536, true, true),
+    ("gemini-2.5-pro", 1_048_576, 65_536, true, true),
+    ("gpt-3.5-turbo", 16_385, 4_096, false, false),
+    ("gpt-4-turbo", 128_000, 4_096, false, true),
+    ("gpt-4.1", 1_047_576, 32_768, false, true),
+    ("gpt-4o", 128_000, 16_384, false, true),
+    ("gpt-5", 400_000, 128_000, true, true),
+    ("grok-3", 131_072, 16_384, false, false),
+    ("grok-3-mini", 131_072, 16_384, true, false),
+    ("grok-4", 256_000, 64_000, true, true),
+    ("grok-4-fast", 2_000_000, 30_000, true, true),
+    ("grok-code-fast", 256_000, 10_000, true, false),
+    ("mistral-large", 128_000, 8_192, false, false),
+    ("mistral-small", 128_000, 8_192, false, false),
+    ("o1", 200_000, 100_000, true, true),
+    ("o1-mini", 128_000, 65_536, true, false),
+    ("o3", 200_000, 100_000, true, true),
+    ("o3-mini", 200_000, 100_000, true, false),
+    ("o4-mini", 200_000, 100_000, true, true),
+];
+
+/// Limits assumed for listed models the bundled catalog does not know.
+const DEFAULT_CONTEXT_WINDOW: u32 = 128_000;
+const DEFAULT_MAX_TOKENS: u32 = 8_192;
+
+/// Known limits and capabilities of a model family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelInfo {
+    pub context_window: u32,
+    pub max_tokens: u32,
+    pub reasoning: bool,
+    /// Whether the model accepts image input.
+    pub images: bool,
+}
+
+/// Looks up bundled limits by model id, ignoring any `provider/` prefix.
+pub fn lookup_model_info(model_id: &str) -> Option<ModelInfo> {
+    let id = model_id
+        .rsplit_once('/')
+        .map(|(_, id)| id)
+        .unwrap_or(model_id)
+        .to_ascii_lowercase();
+    BUNDLED_MODELS
+        .iter()
+        .filter(|(prefix, ..)| id.starts_with(prefix))
+        .max_by_key(|(prefix, ..)| prefix.len())
+        .map(
+            |&(_, context_window, max_tokens, reasoning, images)| ModelInfo {
+                context_window,
+                max_tokens,
+                reasoning,
+                images,
+            },
+        )
+}
+
+/// A provider endpoint to list models from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelSource {
+    /// Provider name given to the listed models, e.g. the key in `pixy.toml`.
+    pub provider: String,
+    /// One of `openai-completions`, `openai-responses`, `anthropic-messages`,
+    /// `google-generative-ai` or `ollama`; other apis cannot be listed.
+    pub api: String,
+    pub base_url: String,
+    pub api_key: Option<String>,
+}
+
+impl ModelSource {
+    /// A model for `id` as served by this source, filled in from the bundled catalog.
+    pub fn model(&self, id: &str, name: Option<&str>) -> Model {
+        let info = lookup_model_info(id);
+        Model {
+            id: id.to_string(),
+            name: name.unwrap_or(id).to_string(),
+            api: self.api.clone(),
+            provider: self.provider.clone(),
+            base_url: self.base_url.clone(),
+            reasoning: info.is_some_and(|info| info.reasoning),
+            reasoning_effort: None,
+            input: if info.is_some_and(|info| info.images) {
+                vec!["text".to_string(), "image".to_string()]
+            } else {
+                vec!["text".to_string()]
+            },
+            cost: lookup_model_pricing(id).unwrap_or(Cost {
+                input: 0.0,
+                output: 0.0,
+                cache_read: 0.0,
+                cache_write: 0.0,
+                total: 0.0,
+            }),
+            context_window: info.map_or(DEFAULT_CONTEXT_WINDOW, |info| info.context_window),
+            max_tokens: info.map_or(DEFAULT_MAX_TOKENS, |info| info.max_tokens),
+            deployment: None,
+            api_version: None,
+            compat: None,
+        }
+    }
+}
+
+/// Lists the chat models `source` serves through its `/models` endpoint.
+pub async fn list_models(source: &ModelSource) -> Result<Vec<Model>, PiAiError> {
+    list_source_models(source).await
+}
+
+/// Chat models available for selection, unique by provider and id, in insertion order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelCatalog {
+    models: Vec<Model>,
+}
+
+impl ModelCatalog {
+    pub fn new(models: Vec<Model>) -> Self {
+        let mut catalog = Self::default();
+        catalog.merge(models);
+        catalog
+    }
+
+    pub fn models(&self) -> &[Model] {
+        &self.models
+    }
+
+    pub fn into_models(self) -> Vec<Model> {
+        self.models
+    }
+
+    pub fn find(&self, provider: &str, id: &str) -> Option<&Model> {
+        self.models
+            .iter()
+            .find(|model| model.provider == provider && model.id == id)
+    }
+
+    /// Appends models not already in the catalog. Existing entries win, so configured
+    /// settings are never replaced by listed defaults.
+    pub fn merge(&mut self, models: impl IntoIterator<Item = Model>) {
+        for model in models {
+            if self.find(&model.provider, &model.id).is_none() {
+                self.models.push(model);
+            }
+        }
+    }
+
+    /// Lists every source and merges the results. Sources that fail are returned with their
+    /// error and leave the catalog unchanged.
+    pub async fn refresh(&mut self, sources: &[ModelSource]) -> Vec<(String, PiAiError)> {
+        let mut failures = Vec::new();
+        for source in sources {
+            match list_models(source).await {
+                Ok(models) => self.merge(models),
+                Err(error) => failures.push((source.provider.clone(), error)),
+            }
+        }
+        failures
+    }
+}
@@ -0,0 +1,117 @@
+use crate::types::{AssistantMessage, Cost, Model, Usage};
+
+/// Built-in prices in USD per million tokens: `(model id prefix, input, output, cache read,
+/// cache write)`. The longest matching prefix wins, so dated snapshots such as
+/// `claude-sonnet-4-20250514` resolve to their family entry.
+const MODEL_PRICES: &[(&str, f64, f64, f64, f64)] = &[
+    ("claude-3-5-haiku", 0.8, 4.0, 0.08, 1.0),
+    ("claude-3-5-sonnet", 3.0, 15.0, 0.3, 3.75),
+    ("claude-3-7-sonnet", 3.0, 15.0, 0.3, 3.75),
+    ("claude-3-haiku", 0.25, 1.25, 0.03, 0.3),
+    ("claude-3-opus", 15.0, 75.0, 1.5, 18.75),
+    ("claude-haiku-4", 1.0, 5.0, 0.1, 1.25),
+    ("claude-opus-4", 15.0, 75.0, 1.5, 18.75),
+    ("claude-opus-4-5", 5.0, 25.0, 0.5, 6.25),
+    ("claude-sonnet-4", 3.0, 15.0, 0.3, 3.75),
+    ("deepseek-chat", 0.27, 1.1, 0.07, 0.0),
+    ("deepseek-reasoner", 0.55, 2.19, 0.14, 0.0),
+    ("gemini-1.5-flash", 0.075, 0.3, 0.01875, 0.0),
+    ("gemini-1.5-pro", 1.25, 5.0, 0.3125, 0.0),
+    ("gemini-2.0-flash", 0.1, 0.4, 0.025, 0.0),
+    ("gemini-2.0-flash-lite", 0.075, 0.3, 0.0, 0.0),
+    ("gemini-2.5-flash", 0.3, 2.5, 0.075, 0.0),
+    ("gemini-2.5-flash-lite", 0.1, 0.4, 0.025, 0.0),
+    ("gemini-2.5-pro", 1.25, 10.0, 0.31, 0.0),
+    ("gpt-3.5-turbo", 0.5, 1.5, 0.0, 0.0),
+    ("gpt-4-turbo", 10.0, 30.0, 0.0, 0.0),
+    ("gpt-4.1", 2.0, 8.0, 0.5, 0.0),
+    ("gpt-4.1-mini", 0.4, 1.6, 0.1, 0.0),
+    ("gpt-4.1-nano", 0.1, 0.4, 0.025, 0.0),
+    ("gpt-4o", 2.5, 10.0, 1.25, 0.0),
+    ("gpt-4o-mini", 0.15, 0.6, 0.075, 0.0),
+    ("gpt-5", 1.25, 10.0, 0.125, 0.0),
+    ("gpt-5-mini", 0.25, 2.0, 0.025, 0.0),
+    ("gpt-5-nano", 0.05, 0.4, 0.005, 0.0),
+    ("grok-3", 3.0, 15.0, 0.75, 0.0),
+    ("grok-3-mini", 0.3, 0.5, 0.075, 0.0),
+    ("grok-4", 3.0, 15.0, 0.75, 0.0),
+    ("grok-4-fast", 0.2, 0.5, 0.05, 0.0),
+    ("grok-code-fast", 0.2, 1.5, 0.02, 0.0),
+    ("mistral-large", 2.0, 6.0, 0.0, 0.0),
+    ("mistral-small", 0.1, 0.3, 0.0, 0.0),
+    ("o1", 15.0, 60.0, 7.5, 0.0),
+    ("o1-mini", 1.1, 4.4, 0.55, 0.0),
+    ("o3", 2.0, 8.0, 0.5, 0.0),
+    ("o3-mini", 1.1, 4.4, 0.55, 0.0),
+    ("o4-mini", 1.1, 4.4, 0.275, 0.0),
+];
+
+/// Per-million-token prices for `model`.
+///
+/// Prices configured on the model take precedence; otherwise the built-in registry is consulted
+/// by model id. Returns `None` for models with no known price.
+pub fn model_pricing(model: &Model) -> Option<Cost> {
+    let configured = &model.cost;
+    if configured.input > 0.0
+        || configured.output > 0.0
+        || configured.cache_read > 0.0
+        || configured.cache_write > 0.0
+    {
+        return Some(configured.clone());
+    }
+    lookup_model_pricing(&model.id)
+}
+
+/// Looks up built-in per-million-token prices by model id, ignoring any `provider/` prefix.
+pub fn lookup_model_pricing(model_id: &str) -> Option<Cost> {
+    let id = model_id
+        .rsplit_once('/')
+        .map(|(_, id)| id)
+        .unwrap_or(model_id)
+        .to_ascii_lowercase();
+    MODEL_PRICES
+        .iter()
+        .filter(|(prefix, ..)| id.starts_with(prefix))
+        .max_by_key(|(prefix, ..)| prefix.len())
+        .map(|&(_, input, output, cache_read, cache_write)| Cost {
+            input,
+            output,
+            cache_read,
+            cache_write,
+            total: 0.0,
+        })
+}
+
+/// Computes the USD cost of `usage` at `pricing` (per million tokens).
+pub fn calculate_cost(pricing: &Cost, usage: &Usage) -> Cost {
+    let per_token = |tokens: u64, price: f64| tokens as f64 * price / 1_000_000.0;
+    let input = per_token(usage.input, pricing.input);
+    let output = per_token(usage.output, pricing.output);
+    let cache_read = per_token(usage.cache_read, pricing.cache_read);
+    let cache_write = per_token(usage.cache_write, pricing.cache_write);
+    Cost {
+        input,
+        output,
+        cache_read,
+        cache_write,
+        total: input + output + cache_read + cache_write,
+    }
+}
+
+/// Fills in `message.usage.cost` unless the provider already priced it.
+///
+/// A message served by a fallback model is priced by its own id, since `model`'s configured
+/// cost belongs to the model that was requested.
+pub(crate) fn price_message(model: &Model, message: &mut AssistantMessage) {
+    if message.usage.cost.total > 0.0 {
+        return;
+    }
+    let pricing = if message.model == model.id {
+        model_pricing(model)
+    } else {
+        lookup_model_pricing(&message.model)
+    };
+    if let Some(pricing) = pricing {
+        message.usage.cost = calculate_cost(&pricing, &message.usage);
+    }
+}
@@ -3,11 +3,11 @@ use std::collections::HashMap;
 use serde_json::{json, Map, Value};
 
 use crate::error::{PiAiError, PiAiErrorCode};
-use crate::providers::common::debug_provider_event;
+use crate::providers::common::{debug_provider_event, push_usage_delta};
 use crate::types::{
     AssistantContentBlock, AssistantMessage, AssistantMessageEvent, DoneReason, StopReason, Usage,
 };
-use crate::AssistantMessageEventStream;
+use crate::{AssistantMessageEventStream, PartialJsonParser};
 
 #[derive(Clone, Debug)]
 enum BlockState {
@@ -19,7 +19,7 @@ enum BlockState {
     },
     ToolCall {
         content_index: usize,
-        partial_json: String,
+        partial_json: PartialJsonParser,
     },
 }
 
@@ -82,7 +82,9 @@ pub(super) fn apply_response_body(
                     .and_then(Value::as_object)
                     .and_then(|message| message.get("usage"))
                 {
+                    let previous = output.usage.clone();
                     update_usage_from_anthropic(&mut output.usage, usage);
+                    push_usage_delta(stream, output, &previous);
                 }
             }
             "content_block_start" => {
@@ -166,7 +168,7 @@ pub(super) fn apply_response_body(
                             block_index,
                             BlockState::ToolCall {
                                 content_index,
-                                partial_json: String::new(),
+                                partial_json: PartialJsonParser::new(),
                             },
                         );
                         stream.push(AssistantMessageEvent::ToolcallStart {
@@ -255,11 +257,13 @@ pub(super) fn apply_response_body(
                                 .and_then(Value::as_str)
                                 .unwrap_or_default()
                                 .to_string();
-                            partial_json.push_str(&delta_text);
+                            partial_json.push(&delta_text);
                             if let Some(AssistantContentBlock::ToolCall { arguments, .. }) =
                                 output.content.get_mut(*content_index)
                             {
-                                *arguments = parse_partial_json(partial_json);
+                                *arguments = partial_json
+                                    .value()
+                                    .unwrap_or_else(|| Value::Object(Map::new()));
                             }
                             stream.push(AssistantMessageEvent::ToolcallDelta {
                                 content_index: *content_index,
@@ -275,11 +279,13 @@ pub(super) fn apply_response_body(
                             _,
                         ) => {
                             if let Some(delta_text) = extract_tool_argument_json_fragment(delta) {
-                                partial_json.push_str(&delta_text);
+                                partial_json.push(&delta_text);
                                 if let Some(AssistantContentBlock::ToolCall { arguments, .. }) =
                                     output.content.get_mut(*content_index)
                                 {
-                                    *arguments = parse_partial_json(partial_json);
+                                    *arguments = partial_json
+                                        .value()
+                                        .unwrap_or_else(|| Value::Object(Map::new()));
                                 }
                                 stream.push(AssistantMessageEvent::ToolcallDelta {
                                     content_index: *content_index,
@@ -351,7 +357,9 @@ pub(super) fn apply_response_body(
                     output.stop_reason = map_anthropic_stop_reason(stop_reason);
                 }
                 if let Some(usage) = event.get("usage") {
+                    let previous = output.usage.clone();
                     update_usage_from_anthropic(&mut output.usage, usage);
+                    push_usage_delta(stream, output, &previous);
                 }
             }
             "message_stop" => {}
@@ -634,13 +642,13 @@ fn map_done_reason(reason: StopReason) -> Option<DoneReason> {
     }
 }
 
-fn parse_partial_json(buffer: &str) -> Value {
+fn parse_json_or_empty(buffer: &str) -> Value {
     serde_json::from_str::<Value>(buffer).unwrap_or_else(|_| Value::Object(Map::new()))
 }
 
 fn parse_tool_arguments_value(value: Option<&Value>) -> Value {
     match value {
-        Some(Value::String(raw)) => parse_partial_json(raw),
+        Some(Value::String(raw)) => parse_json_or_empty(raw),
         Some(Value::Null) | None => Value::Object(Map::new()),
         Some(other) => other.clone(),
     }
@@ -655,7 +663,7 @@ fn extract_tool_argument_json_fragment(delta: &Map<String, Value>) -> Option<Str
     None
 }
 
-fn extract_tool_argument_value<'a>(delta: &'a Map<String, Value>) -> Option<&'a Value> {
+fn extract_tool_argument_value(delta: &Map<String, Value>) -> Option<&Value> {
     for key in ["input", "arguments", "args", "input_json"] {
         if let Some(value) = delta.get(key) {
             if !value.is_null() && !value.is_string() {
@@ -728,6 +736,7 @@ mod tests {
                 output: 4,
                 cache_read: 5,
                 cache_write: 6,
+                reasoning: 0,
                 total_tokens: 18,
                 cost: Cost {
                     input: 0.0,
@@ -740,6 +749,7 @@ mod tests {
             stop_reason: StopReason::Stop,
             error_message: None,
             timestamp: 1_700_000_000_000,
+            stats: None,
         }
     }
 
@@ -774,6 +784,33 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn apply_response_body_emits_usage_deltas_while_streaming() {
+        let mut output = sample_assistant_message();
+        output.usage.input = 0;
+        output.usage.output = 0;
+        output.usage.cache_read = 0;
+        output.usage.cache_write = 0;
+        output.usage.total_tokens = 0;
+        let stream = AssistantMessageEventStream::new();
+        let body = concat!(
+            "data: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":12,\"output_tokens\":1}}}\n\n",
+            "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":7}}\n\n",
+            "data: {\"type\":\"message_stop\"}\n\n",
+        );
+
+        apply_response_body(body, &mut output, &stream).expect("body should parse");
+        stream.end(None);
+
+        let mut deltas = Vec::new();
+        while let Some(event) = stream.next().await {
+            if let AssistantMessageEvent::UsageDelta { usage, partial } = event {
+                deltas.push((usage.input, usage.output, partial.usage.total_tokens));
+            }
+        }
+        assert_eq!(deltas, vec![(12, 1, 13), (0, 6, 19)]);
+    }
+
     #[test]
     fn parse_sse_data_events_ignores_done_sentinel() {
         let body = concat!(
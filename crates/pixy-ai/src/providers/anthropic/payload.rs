@@ -1,8 +1,24 @@
 use serde_json::{json, Value};
 
+use crate::message_adapter::{MessageAdapter, MessageRule};
+use crate::providers::common::{has_extended_cache_hint, unsupported_audio_note, SamplingFields};
+
 use crate::types::{
-    AssistantContentBlock, Context, Message, Model, StreamOptions, Tool, ToolResultContentBlock,
-    UserContent, UserContentBlock,
+    AssistantContentBlock, CacheHint, Context, Message, Model, StreamOptions, ThinkingLevel, Tool,
+    ToolResultContentBlock, UserContent, UserContentBlock,
+};
+
+/// Smallest thinking budget the Messages API accepts.
+const MIN_THINKING_BUDGET: u32 = 1024;
+/// Most `cache_control` breakpoints a single request may carry.
+const MAX_CACHE_BREAKPOINTS: usize = 4;
+
+const SAMPLING_FIELDS: SamplingFields = SamplingFields {
+    top_p: Some("top_p"),
+    top_k: Some("top_k"),
+    seed: None,
+    frequency_penalty: None,
+    presence_penalty: None,
 };
 
 pub(super) fn build_anthropic_payload(
@@ -11,6 +27,7 @@ pub(super) fn build_anthropic_payload(
     options: Option<&StreamOptions>,
     thinking_enabled: bool,
 ) -> Value {
+    let context = &message_adapter().apply(model, context);
     let mut payload = json!({
         "model": model.id,
         "stream": true,
@@ -20,25 +37,166 @@ pub(super) fn build_anthropic_payload(
             .unwrap_or(model.max_tokens),
     });
 
+    // Cache breakpoints on the system prompt, the last tool and the latest message let each
+    // turn reuse the prefix written by the previous one. Longer-lived breakpoints must come
+    // first, so the prefix takes the extended TTL whenever any hint asks for it.
+    let prefix_cache_control = if has_extended_cache_hint(context) {
+        cache_control(CacheHint::Extended)
+    } else {
+        ephemeral_cache_control()
+    };
     if let Some(system_prompt) = &context.system_prompt {
-        payload["system"] = Value::String(system_prompt.clone());
+        payload["system"] = json!([{
+            "type": "text",
+            "text": system_prompt,
+            "cache_control": prefix_cache_control,
+        }]);
     }
-    if let Some(tools) = &context.tools {
-        payload["tools"] = convert_tools(tools);
+    let response_format = options.and_then(|options| options.response_format.as_ref());
+    if context.tools.is_some() || response_format.is_some() {
+        let mut tools = convert_tools(context.tools.as_deref().unwrap_or_default());
+        // The Messages API has no JSON schema mode; structured output is a forced tool call.
+        if let Some(format) = response_format {
+            tools.push(json!({
+                "name": format.name,
+                "description": "Respond by calling this tool with the final answer.",
+                "input_schema": format.schema,
+            }));
+            payload["tool_choice"] = json!({ "type": "tool", "name": format.name });
+        }
+        payload["tools"] = Value::Array(tools);
+        if let Some(tool) = payload["tools"]
+            .as_array_mut()
+            .and_then(|tools| tools.last_mut())
+        {
+            tool["cache_control"] = prefix_cache_control.clone();
+        }
+    }
+    if let Some(last_message) = payload["messages"]
+        .as_array_mut()
+        .and_then(|messages| messages.last_mut())
+    {
+        if let Some(text) = last_message["content"].as_str() {
+            last_message["content"] = json!([{ "type": "text", "text": text }]);
+        }
+        mark_last_block_cacheable(last_message["content"].as_array_mut());
     }
-    if let Some(temperature) = options.and_then(|options| options.temperature) {
-        payload["temperature"] = json!(temperature);
+    limit_cache_breakpoints(&mut payload);
+    if let Some(stop) = options
+        .map(|options| &options.stop_sequences)
+        .filter(|stop| !stop.is_empty())
+    {
+        payload["stop_sequences"] = json!(stop);
     }
-    if thinking_enabled {
+
+    let max_tokens = payload["max_tokens"].as_u64().unwrap_or_default() as u32;
+    // Forced tool use cannot be combined with extended thinking.
+    let requested_budget = options.and_then(|options| options.thinking_budget);
+    let thinking_budget = (thinking_enabled && response_format.is_none())
+        .then(|| {
+            thinking_budget(
+                model.reasoning_effort.as_ref(),
+                requested_budget,
+                max_tokens,
+            )
+        })
+        .flatten();
+    if let Some(budget_tokens) = thinking_budget {
         payload["thinking"] = json!({
             "type": "enabled",
-            "budget_tokens": 1024,
+            "budget_tokens": budget_tokens,
         });
+    } else {
+        // Extended thinking only runs at the default sampling settings.
+        if let Some(temperature) = options.and_then(|options| options.temperature) {
+            payload["temperature"] = json!(temperature);
+        }
+        if let Some(fields) = payload.as_object_mut() {
+            SAMPLING_FIELDS.insert(fields, options);
+        }
     }
 
     payload
 }
 
+/// Thinking is only replayable to the API that signed it. Consecutive user turns are left
+/// apart so each keeps its own cache breakpoint.
+fn message_adapter() -> MessageAdapter {
+    MessageAdapter::new([MessageRule::StripForeignThinking])
+}
+
+/// Thinking budget for `effort`, or the `requested` one, kept below `max_tokens` as the API
+/// requires. `None` when thinking is turned off with a zero budget or `max_tokens` leaves no
+/// room for the minimum budget.
+fn thinking_budget(
+    effort: Option<&ThinkingLevel>,
+    requested: Option<u32>,
+    max_tokens: u32,
+) -> Option<u32> {
+    let budget = match (requested, effort) {
+        (Some(0), _) => return None,
+        (Some(requested), _) => requested.max(MIN_THINKING_BUDGET),
+        (None, Some(ThinkingLevel::Minimal)) => MIN_THINKING_BUDGET,
+        (None, Some(ThinkingLevel::Low)) => 4_096,
+        (None, None | Some(ThinkingLevel::Medium)) => 8_192,
+        (None, Some(ThinkingLevel::High)) => 16_384,
+        (None, Some(ThinkingLevel::Xhigh)) => 32_768,
+    };
+    let budget = budget.min(max_tokens.saturating_sub(1));
+    (budget >= MIN_THINKING_BUDGET).then_some(budget)
+}
+
+fn ephemeral_cache_control() -> Value {
+    cache_control(CacheHint::Ephemeral)
+}
+
+fn cache_control(hint: CacheHint) -> Value {
+    match hint {
+        CacheHint::Ephemeral => json!({ "type": "ephemeral" }),
+        CacheHint::Extended => json!({ "type": "ephemeral", "ttl": "1h" }),
+    }
+}
+
+fn mark_last_block_cacheable(blocks: Option<&mut Vec<Value>>) {
+    if let Some(block) = blocks.and_then(|blocks| blocks.last_mut()) {
+        if block.get("cache_control").is_none() {
+            block["cache_control"] = ephemeral_cache_control();
+        }
+    }
+}
+
+/// Drops the earliest message breakpoints beyond what the Messages API accepts.
+fn limit_cache_breakpoints(payload: &mut Value) {
+    let fixed = ["tools", "system"]
+        .into_iter()
+        .filter_map(|key| payload[key].as_array())
+        .flatten()
+        .filter(|block| block.get("cache_control").is_some())
+        .count();
+    let mut remaining = MAX_CACHE_BREAKPOINTS.saturating_sub(fixed);
+    let Some(messages) = payload["messages"].as_array_mut() else {
+        return;
+    };
+    for message in messages.iter_mut().rev() {
+        let Some(blocks) = message["content"].as_array_mut() else {
+            continue;
+        };
+        for block in blocks.iter_mut().rev() {
+            let Some(block) = block.as_object_mut() else {
+                continue;
+            };
+            if !block.contains_key("cache_control") {
+                continue;
+            }
+            if remaining == 0 {
+                block.remove("cache_control");
+            } else {
+                remaining -= 1;
+            }
+        }
+    }
+}
+
 fn convert_messages(context: &Context) -> Vec<Value> {
     let mut messages = Vec::new();
 
@@ -63,37 +221,41 @@ fn convert_messages(context: &Context) -> Vec<Value> {
             Message::Assistant { content, .. } => {
                 let converted = content
                     .iter()
-                    .map(|block| match block {
-                        AssistantContentBlock::Text { text, .. } => json!({
+                    .filter_map(|block| match block {
+                        AssistantContentBlock::Text { text, .. } => Some(json!({
                             "type": "text",
                             "text": text,
-                        }),
+                        })),
+                        // Unsigned thinking would be rejected.
                         AssistantContentBlock::Thinking {
                             thinking,
-                            thinking_signature,
-                        } => {
-                            let mut block = json!({
-                                "type": "thinking",
-                                "thinking": thinking,
-                            });
-                            if let Some(signature) = thinking_signature {
-                                block["signature"] = Value::String(signature.clone());
-                            }
-                            block
-                        }
+                            thinking_signature: Some(signature),
+                        } => Some(json!({
+                            "type": "thinking",
+                            "thinking": thinking,
+                            "signature": signature,
+                        })),
+                        AssistantContentBlock::Thinking { .. } => None,
                         AssistantContentBlock::ToolCall {
                             id,
                             name,
                             arguments,
                             ..
-                        } => json!({
+                        } => Some(json!({
                             "type": "tool_use",
                             "id": id,
                             "name": name,
                             "input": arguments,
-                        }),
+                        })),
+                        // Assistant turns cannot carry images; audio is replayed through its
+                        // transcript text block.
+                        AssistantContentBlock::Image { .. }
+                        | AssistantContentBlock::Audio { .. } => None,
                     })
                     .collect::<Vec<_>>();
+                if converted.is_empty() {
+                    continue;
+                }
                 messages.push(json!({
                     "role": "assistant",
                     "content": converted,
@@ -123,10 +285,16 @@ fn convert_messages(context: &Context) -> Vec<Value> {
 
 fn convert_user_block_to_anthropic(block: &UserContentBlock) -> Value {
     match block {
-        UserContentBlock::Text { text, .. } => json!({
-            "type": "text",
-            "text": text,
-        }),
+        UserContentBlock::Text { text, cache, .. } => {
+            let mut block = json!({
+                "type": "text",
+                "text": text,
+            });
+            if let Some(hint) = cache {
+                block["cache_control"] = cache_control(*hint);
+            }
+            block
+        }
         UserContentBlock::Image { data, mime_type } => json!({
             "type": "image",
             "source": {
@@ -135,25 +303,51 @@ fn convert_user_block_to_anthropic(block: &UserContentBlock) -> Value {
                 "data": data,
             },
         }),
+        UserContentBlock::Audio { mime_type, .. } => json!({
+            "type": "text",
+            "text": unsupported_audio_note(mime_type),
+        }),
     }
 }
 
-fn convert_tools(tools: &[Tool]) -> Value {
-    Value::Array(
-        tools
-            .iter()
-            .map(|tool| {
-                json!({
-                    "name": tool.name,
-                    "description": tool.description,
-                    "input_schema": tool.parameters,
-                })
+fn convert_tools(tools: &[Tool]) -> Vec<Value> {
+    tools
+        .iter()
+        .map(|tool| {
+            json!({
+                "name": tool.name,
+                "description": tool.description,
+                "input_schema": tool.parameters,
             })
-            .collect(),
-    )
+        })
+        .collect()
 }
 
 fn convert_tool_result_content(content: &[ToolResultContentBlock]) -> Value {
+    if content
+        .iter()
+        .any(|block| matches!(block, ToolResultContentBlock::Image { .. }))
+    {
+        return Value::Array(
+            content
+                .iter()
+                .map(|block| match block {
+                    ToolResultContentBlock::Text { text, .. } => {
+                        json!({ "type": "text", "text": text })
+                    }
+                    ToolResultContentBlock::Image { data, mime_type } => json!({
+                        "type": "image",
+                        "source": {
+                            "type": "base64",
+                            "media_type": mime_type,
+                            "data": data,
+                        },
+                    }),
+                })
+                .collect(),
+        );
+    }
+
     let text_blocks = content
         .iter()
         .filter_map(|block| match block {
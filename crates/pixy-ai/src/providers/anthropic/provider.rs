@@ -5,7 +5,9 @@ use super::parser::apply_response_body;
 use super::payload::build_anthropic_payload;
 use crate::api_registry::{ApiProvider, ApiProviderFuture};
 use crate::error::{PiAiError, PiAiErrorCode};
-use crate::providers::common::{empty_assistant_message, join_url, shared_http_client};
+use crate::providers::common::{
+    empty_assistant_message, fetch_response_body, join_url, shared_http_client,
+};
 use crate::types::{AssistantMessageEvent, Model, SimpleStreamOptions, StopReason, StreamOptions};
 use crate::{ApiProviderRef, AssistantMessageEventStream};
 
@@ -51,7 +53,7 @@ async fn run_anthropic(
     let mut output = empty_assistant_message(&model);
     let payload = build_anthropic_payload(&model, &context, options.as_ref(), model.reasoning);
     let endpoint = join_url(&model.base_url, "messages");
-    let client = shared_http_client(&model.base_url);
+    let client = shared_http_client(&model.provider, &model.base_url);
 
     let execution = async {
         let mut request = client
@@ -66,31 +68,13 @@ async fn run_anthropic(
             }
         }
 
-        let response = request.json(&payload).send().await.map_err(|error| {
-            PiAiError::new(
-                PiAiErrorCode::ProviderTransport,
-                format!("Anthropic transport failed: {error}"),
-            )
-        })?;
-
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let body = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "unable to read error body".to_string());
-            return Err(PiAiError::new(
-                PiAiErrorCode::ProviderHttp,
-                format!("Anthropic HTTP {status}: {body}"),
-            ));
-        }
-
-        let body = response.text().await.map_err(|error| {
-            PiAiError::new(
-                PiAiErrorCode::ProviderTransport,
-                format!("Anthropic stream read failed: {error}"),
-            )
-        })?;
+        let body = fetch_response_body(
+            "Anthropic",
+            &model,
+            request.json(&payload),
+            options.as_ref(),
+        )
+        .await?;
 
         stream.push(AssistantMessageEvent::Start {
             partial: output.clone(),
@@ -208,6 +192,189 @@ mod tests {
         );
     }
 
+    #[test]
+    fn anthropic_payload_sets_cache_breakpoints_and_scales_thinking_budget() {
+        let mut model = sample_model("http://localhost".to_string(), true);
+        model.reasoning_effort = Some(crate::types::ThinkingLevel::High);
+        let mut context = sample_context();
+        context.tools = Some(vec![
+            crate::types::Tool {
+                name: "read".to_string(),
+                description: "Read a file".to_string(),
+                parameters: serde_json::json!({ "type": "object" }),
+            },
+            crate::types::Tool {
+                name: "write".to_string(),
+                description: "Write a file".to_string(),
+                parameters: serde_json::json!({ "type": "object" }),
+            },
+        ]);
+        let options = StreamOptions {
+            temperature: Some(0.2),
+            top_k: Some(40),
+            seed: Some(7),
+            ..StreamOptions::default()
+        };
+
+        let payload = build_anthropic_payload(&model, &context, Some(&options), true);
+
+        let ephemeral = serde_json::json!({ "type": "ephemeral" });
+        assert_eq!(payload["system"][0]["cache_control"], ephemeral);
+        assert!(payload["tools"][0].get("cache_control").is_none());
+        assert_eq!(payload["tools"][1]["cache_control"], ephemeral);
+        assert_eq!(payload["messages"][0]["content"][0]["text"], "hello");
+        assert_eq!(
+            payload["messages"][0]["content"][0]["cache_control"],
+            ephemeral
+        );
+        assert_eq!(payload["thinking"]["budget_tokens"], 8_191);
+        assert!(payload.get("temperature").is_none());
+        assert!(payload.get("top_k").is_none());
+
+        model.max_tokens = 1_000;
+        let payload = build_anthropic_payload(&model, &context, Some(&options), true);
+        assert!(payload.get("thinking").is_none());
+        assert_eq!(payload["temperature"], 0.2);
+        assert_eq!(payload["top_k"], 40);
+        assert!(payload.get("seed").is_none());
+    }
+
+    #[test]
+    fn anthropic_payload_prefers_the_requested_thinking_budget() {
+        let mut model = sample_model("http://localhost".to_string(), true);
+        model.reasoning_effort = Some(crate::types::ThinkingLevel::High);
+        let budget = |thinking_budget| {
+            let options = StreamOptions {
+                thinking_budget: Some(thinking_budget),
+                ..StreamOptions::default()
+            };
+            build_anthropic_payload(&model, &sample_context(), Some(&options), true)["thinking"]
+                .clone()
+        };
+
+        assert_eq!(budget(2_000)["budget_tokens"], 2_000);
+        assert_eq!(budget(100)["budget_tokens"], 1_024);
+        assert_eq!(budget(0), serde_json::Value::Null);
+    }
+
+    #[test]
+    fn anthropic_payload_drops_thinking_from_other_providers() {
+        let model = sample_model("http://localhost".to_string(), false);
+        let mut context = sample_context();
+        let mut reply = crate::providers::common::empty_assistant_message(&model);
+        reply.api = "openai-responses".to_string();
+        reply.content = vec![
+            crate::types::AssistantContentBlock::Thinking {
+                thinking: "plan".to_string(),
+                thinking_signature: Some("{}".to_string()),
+            },
+            crate::types::AssistantContentBlock::Text {
+                text: "hi".to_string(),
+                text_signature: None,
+            },
+        ];
+        context.messages.push(Message::Assistant {
+            content: reply.content,
+            api: reply.api,
+            provider: reply.provider,
+            model: reply.model,
+            usage: reply.usage,
+            stop_reason: reply.stop_reason,
+            error_message: None,
+            timestamp: 0,
+            stats: None,
+        });
+
+        let payload = build_anthropic_payload(&model, &context, None, false);
+
+        assert_eq!(
+            payload["messages"][1]["content"],
+            serde_json::json!([{
+                "type": "text",
+                "text": "hi",
+                "cache_control": { "type": "ephemeral" },
+            }])
+        );
+    }
+
+    #[test]
+    fn anthropic_payload_forces_response_schema_tool_without_thinking() {
+        let model = sample_model("http://localhost".to_string(), true);
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": { "answer": { "type": "string" } },
+            "required": ["answer"],
+        });
+        let options = StreamOptions {
+            response_format: Some(crate::types::ResponseSchema {
+                name: "final_answer".to_string(),
+                schema: schema.clone(),
+                strict: true,
+            }),
+            ..StreamOptions::default()
+        };
+
+        let payload = build_anthropic_payload(&model, &sample_context(), Some(&options), true);
+
+        assert_eq!(payload["tools"][0]["name"], "final_answer");
+        assert_eq!(payload["tools"][0]["input_schema"], schema);
+        assert_eq!(
+            payload["tool_choice"],
+            serde_json::json!({ "type": "tool", "name": "final_answer" })
+        );
+        assert!(payload.get("thinking").is_none());
+    }
+
+    #[test]
+    fn anthropic_payload_honors_cache_hints_within_breakpoint_limit() {
+        let model = sample_model("http://localhost".to_string(), false);
+        let mut context = sample_context();
+        context.system_prompt_cache = Some(crate::types::CacheHint::Extended);
+        context.tools = Some(vec![crate::types::Tool {
+            name: "read".to_string(),
+            description: "Read a file".to_string(),
+            parameters: serde_json::json!({ "type": "object" }),
+        }]);
+        let hinted_block = |text: &str| crate::types::UserContentBlock::Text {
+            text: text.to_string(),
+            text_signature: None,
+            cache: Some(crate::types::CacheHint::Ephemeral),
+        };
+        context.messages = vec![
+            Message::User {
+                content: UserContent::Blocks(vec![hinted_block("first doc")]),
+                timestamp: 0,
+            },
+            Message::User {
+                content: UserContent::Blocks(vec![hinted_block("second doc")]),
+                timestamp: 0,
+            },
+            Message::User {
+                content: UserContent::Text("question".to_string()),
+                timestamp: 0,
+            },
+        ];
+
+        let payload = build_anthropic_payload(&model, &context, None, false);
+
+        let extended = serde_json::json!({ "type": "ephemeral", "ttl": "1h" });
+        let ephemeral = serde_json::json!({ "type": "ephemeral" });
+        assert_eq!(payload["tools"][0]["cache_control"], extended);
+        assert_eq!(payload["system"][0]["cache_control"], extended);
+        // Four breakpoints at most: tools, system, the latest hint and the last message.
+        assert!(payload["messages"][0]["content"][0]
+            .get("cache_control")
+            .is_none());
+        assert_eq!(
+            payload["messages"][1]["content"][0]["cache_control"],
+            ephemeral
+        );
+        assert_eq!(
+            payload["messages"][2]["content"][0]["cache_control"],
+            ephemeral
+        );
+    }
+
     fn sample_model(base_url: String, reasoning: bool) -> Model {
         Model {
             id: "claude-test".to_string(),
@@ -227,6 +394,9 @@ mod tests {
             },
             context_window: 200_000,
             max_tokens: 8_192,
+            deployment: None,
+            api_version: None,
+            compat: None,
         }
     }
 
@@ -238,6 +408,8 @@ mod tests {
                 timestamp: 1_700_000_000_000,
             }],
             tools: None,
+            system_prompt_cache: None,
+            branch: None,
         }
     }
 
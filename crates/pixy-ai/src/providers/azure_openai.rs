@@ -0,0 +1,178 @@
+use std::env;
+use std::sync::Arc;
+
+use super::common::join_url;
+use super::openai_completions::{
+    apply_simple_reasoning_to_model, run_openai_completions_at, CompletionsEndpoint,
+};
+use crate::api_registry::{ApiProvider, ApiProviderFuture};
+use crate::error::{PiAiError, PiAiErrorCode};
+use crate::types::{Context, Model, SimpleStreamOptions, StreamOptions};
+use crate::{ApiProviderRef, AssistantMessageEventStream};
+
+const AZURE_OPENAI_FALLBACK_ENV: &str = "AZURE_OPENAI_API_KEY";
+const DEFAULT_AZURE_API_VERSION: &str = "2024-10-21";
+
+struct AzureOpenAIProvider;
+
+impl ApiProvider for AzureOpenAIProvider {
+    fn api(&self) -> &str {
+        "azure-openai"
+    }
+
+    fn stream(
+        &self,
+        model: Model,
+        context: Context,
+        options: Option<StreamOptions>,
+        stream: AssistantMessageEventStream,
+    ) -> ApiProviderFuture {
+        Box::pin(async move { run_azure_openai(model, context, options, stream).await })
+    }
+
+    fn stream_simple(
+        &self,
+        model: Model,
+        context: Context,
+        options: Option<SimpleStreamOptions>,
+        stream: AssistantMessageEventStream,
+    ) -> ApiProviderFuture {
+        Box::pin(async move {
+            let mut model = model;
+            apply_simple_reasoning_to_model(&mut model, options.as_ref());
+            let stream_options = options.map(|simple| simple.stream);
+            run_azure_openai(model, context, stream_options, stream).await
+        })
+    }
+}
+
+pub(super) fn provider() -> ApiProviderRef {
+    Arc::new(AzureOpenAIProvider)
+}
+
+pub async fn run_azure_openai(
+    model: Model,
+    context: Context,
+    options: Option<StreamOptions>,
+    stream: AssistantMessageEventStream,
+) -> Result<(), PiAiError> {
+    let api_key = resolve_api_key(&model.provider, options.as_ref())?;
+    let api_version = options
+        .as_ref()
+        .and_then(|options| options.api_version.as_deref())
+        .or(model.api_version.as_deref());
+    let endpoint = CompletionsEndpoint {
+        url: build_azure_chat_completions_url(&model, api_version),
+        auth_header: Some(("api-key", api_key)),
+    };
+    run_openai_completions_at(model, context, options, stream, endpoint).await
+}
+
+/// Builds `{resource}/openai/deployments/{deployment}/chat/completions?api-version=...`.
+///
+/// `base_url` may be the bare resource URL or already end in `/openai`.
+fn build_azure_chat_completions_url(model: &Model, api_version: Option<&str>) -> String {
+    let base = model.base_url.trim().trim_end_matches('/');
+    let base = base.strip_suffix("/openai").unwrap_or(base);
+    let deployment = model
+        .deployment
+        .as_deref()
+        .map(str::trim)
+        .filter(|deployment| !deployment.is_empty())
+        .unwrap_or(model.id.as_str());
+    let api_version = api_version
+        .map(str::trim)
+        .filter(|version| !version.is_empty())
+        .unwrap_or(DEFAULT_AZURE_API_VERSION);
+
+    let path = format!(
+        "openai/deployments/{}/chat/completions?api-version={}",
+        percent_encode_component(deployment),
+        percent_encode_component(api_version)
+    );
+    join_url(base, &path)
+}
+
+fn percent_encode_component(input: &str) -> String {
+    let mut encoded = String::new();
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+fn resolve_api_key(provider: &str, options: Option<&StreamOptions>) -> Result<String, PiAiError> {
+    if let Some(api_key) = options.and_then(|options| options.api_key.clone()) {
+        if !api_key.trim().is_empty() {
+            return Ok(api_key);
+        }
+    }
+
+    let provider_env = format!("{}_API_KEY", provider.to_uppercase().replace('-', "_"));
+    for name in [provider_env.as_str(), AZURE_OPENAI_FALLBACK_ENV] {
+        if let Ok(value) = env::var(name) {
+            if !value.trim().is_empty() {
+                return Ok(value);
+            }
+        }
+    }
+
+    Err(PiAiError::new(
+        PiAiErrorCode::ProviderAuthMissing,
+        format!(
+            "Missing API key for provider '{provider}'. Pass `StreamOptions.api_key` or set {provider_env} / {AZURE_OPENAI_FALLBACK_ENV}."
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Cost;
+
+    fn sample_model(base_url: &str) -> Model {
+        Model {
+            id: "gpt-4o".to_string(),
+            name: "gpt-4o".to_string(),
+            api: "azure-openai".to_string(),
+            provider: "azure".to_string(),
+            base_url: base_url.to_string(),
+            reasoning: false,
+            reasoning_effort: None,
+            input: vec!["text".to_string()],
+            cost: Cost {
+                input: 0.0,
+                output: 0.0,
+                cache_read: 0.0,
+                cache_write: 0.0,
+                total: 0.0,
+            },
+            context_window: 128_000,
+            max_tokens: 4_096,
+            deployment: None,
+            api_version: None,
+            compat: None,
+        }
+    }
+
+    #[test]
+    fn azure_url_uses_deployment_and_api_version() {
+        let mut model = sample_model("https://example.openai.azure.com/openai/");
+        assert_eq!(
+            build_azure_chat_completions_url(&model, None),
+            "https://example.openai.azure.com/openai/deployments/gpt-4o/chat/completions?api-version=2024-10-21"
+        );
+
+        model.deployment = Some("prod gpt4o".to_string());
+        model.api_version = Some("2025-01-01-preview".to_string());
+        assert_eq!(
+            build_azure_chat_completions_url(&model, model.api_version.as_deref()),
+            "https://example.openai.azure.com/openai/deployments/prod%20gpt4o/chat/completions?api-version=2025-01-01-preview"
+        );
+    }
+}
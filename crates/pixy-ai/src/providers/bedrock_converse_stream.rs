@@ -3,9 +3,13 @@ use std::sync::Arc;
 
 use serde_json::{json, Map, Value};
 
-use super::common::{empty_assistant_message, join_url, shared_http_client};
+use super::common::{
+    empty_assistant_message, fetch_response_body, join_url, shared_http_client,
+    unsupported_audio_note,
+};
 use crate::api_registry::{ApiProvider, ApiProviderFuture};
 use crate::error::{PiAiError, PiAiErrorCode};
+use crate::message_adapter::{MessageAdapter, MessageRule};
 use crate::types::{
     AssistantContentBlock, AssistantMessage, AssistantMessageEvent, Context, DoneReason, Message,
     Model, SimpleStreamOptions, StopReason, StreamOptions, Tool, ToolResultContentBlock, Usage,
@@ -59,11 +63,11 @@ pub async fn run_bedrock_converse_stream(
     let auth_token = resolve_auth_token(&model.provider, options.as_ref(), BEDROCK_FALLBACK_ENVS)?;
 
     let mut output = empty_assistant_message(&model);
-    let payload = build_bedrock_payload(&context, options.as_ref());
+    let payload = build_bedrock_payload(&model, &context, options.as_ref());
     let endpoint = build_bedrock_endpoint(&model);
-    let client = shared_http_client(&model.base_url);
+    let client = shared_http_client(&model.provider, &model.base_url);
 
-    let execution = async {
+    let execution: Result<(), PiAiError> = async {
         let mut request = client
             .post(endpoint.as_str())
             .header("Content-Type", "application/json");
@@ -77,31 +81,8 @@ pub async fn run_bedrock_converse_stream(
                 request = request.header(name, value);
             }
         }
-        let response = request.json(&payload).send().await.map_err(|error| {
-            PiAiError::new(
-                PiAiErrorCode::ProviderTransport,
-                format!("Bedrock transport failed: {error}"),
-            )
-        })?;
-
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let body = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "unable to read error body".to_string());
-            return Err(PiAiError::new(
-                PiAiErrorCode::ProviderHttp,
-                format!("Bedrock HTTP {status}: {body}"),
-            ));
-        }
-
-        let body = response.text().await.map_err(|error| {
-            PiAiError::new(
-                PiAiErrorCode::ProviderTransport,
-                format!("Bedrock response read failed: {error}"),
-            )
-        })?;
+        let body = fetch_response_body("Bedrock", &model, request.json(&payload), options.as_ref())
+            .await?;
         let parsed: Value = serde_json::from_str(&body).map_err(|error| {
             PiAiError::new(
                 PiAiErrorCode::ProviderProtocol,
@@ -164,7 +145,20 @@ pub async fn run_simple_bedrock_converse_stream(
     run_bedrock_converse_stream(model, context, merged, stream).await
 }
 
-fn build_bedrock_payload(context: &Context, options: Option<&StreamOptions>) -> Value {
+/// Converse rejects consecutive user turns and reasoning it did not produce.
+fn message_adapter() -> MessageAdapter {
+    MessageAdapter::new([
+        MessageRule::StripForeignThinking,
+        MessageRule::MergeConsecutiveUserMessages,
+    ])
+}
+
+fn build_bedrock_payload(
+    model: &Model,
+    context: &Context,
+    options: Option<&StreamOptions>,
+) -> Value {
+    let context = &message_adapter().apply(model, context);
     let mut payload = json!({
         "messages": convert_messages(context),
     });
@@ -181,12 +175,27 @@ fn build_bedrock_payload(context: &Context, options: Option<&StreamOptions>) ->
     if let Some(temperature) = options.and_then(|opts| opts.temperature) {
         inference.insert("temperature".to_string(), json!(temperature));
     }
+    if let Some(top_p) = options.and_then(|opts| opts.top_p) {
+        inference.insert("topP".to_string(), json!(top_p));
+    }
     if let Some(max_tokens) = options.and_then(|opts| opts.max_tokens) {
         inference.insert("maxTokens".to_string(), json!(max_tokens));
     }
+    if let Some(stop) = options
+        .map(|opts| &opts.stop_sequences)
+        .filter(|stop| !stop.is_empty())
+    {
+        inference.insert("stopSequences".to_string(), json!(stop));
+    }
     if !inference.is_empty() {
         payload["inferenceConfig"] = Value::Object(inference);
     }
+    // Other sampling fields depend on the model family, so only explicit extras are sent.
+    if let Some(extra) = options.map(|opts| &opts.extra_sampling) {
+        if !extra.is_empty() {
+            payload["additionalModelRequestFields"] = Value::Object(extra.clone());
+        }
+    }
 
     payload
 }
@@ -236,6 +245,10 @@ fn convert_messages(context: &Context) -> Vec<Value> {
                                 "input": arguments,
                             }
                         })),
+                        // Assistant turns cannot carry images; audio is replayed through its
+                        // transcript text block.
+                        AssistantContentBlock::Image { .. }
+                        | AssistantContentBlock::Audio { .. } => None,
                     })
                     .collect();
 
@@ -302,6 +315,9 @@ fn convert_user_block_to_bedrock(block: &UserContentBlock) -> Option<Value> {
                 }
             }))
         }
+        UserContentBlock::Audio { mime_type, .. } => {
+            Some(json!({ "text": unsupported_audio_note(mime_type) }))
+        }
     }
 }
 
@@ -1,8 +1,20 @@
-use std::sync::OnceLock;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
-use reqwest::Client;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, RequestBuilder, Response};
+use serde_json::{json, Map, Value};
 
-use crate::types::{AssistantMessage, Cost, Model, StopReason, Usage};
+use crate::error::{classify_http_error, PiAiError, PiAiErrorCode};
+use crate::http_transport::http_client;
+use crate::record::{next_replayed_body, record_body};
+use crate::transport_retry::{parse_retry_after_ms, StreamTransport};
+use crate::AssistantMessageEventStream;
+
+use crate::types::{
+    AssistantMessage, AssistantMessageEvent, CacheHint, Context, Cost, Message, Model, StopReason,
+    StreamOptions, Usage, UserContent, UserContentBlock,
+};
 
 pub(super) fn debug_provider_event(provider: &str, data: &str) {
     if !provider_debug_enabled() {
@@ -23,6 +35,7 @@ pub(super) fn empty_assistant_message(model: &Model) -> AssistantMessage {
             output: 0,
             cache_read: 0,
             cache_write: 0,
+            reasoning: 0,
             total_tokens: 0,
             cost: Cost {
                 input: 0.0,
@@ -35,41 +48,306 @@ pub(super) fn empty_assistant_message(model: &Model) -> AssistantMessage {
         stop_reason: StopReason::Stop,
         error_message: None,
         timestamp: now_millis(),
+        stats: None,
     }
 }
 
-pub(super) fn join_url(base_url: &str, path: &str) -> String {
-    if base_url.ends_with('/') {
-        format!("{base_url}{path}")
-    } else {
-        format!("{base_url}/{path}")
+/// Pushes a `UsageDelta` for whatever `output.usage` gained since `previous`.
+pub(super) fn push_usage_delta(
+    stream: &AssistantMessageEventStream,
+    output: &AssistantMessage,
+    previous: &Usage,
+) {
+    let current = &output.usage;
+    let delta = Usage {
+        input: current.input.saturating_sub(previous.input),
+        output: current.output.saturating_sub(previous.output),
+        cache_read: current.cache_read.saturating_sub(previous.cache_read),
+        cache_write: current.cache_write.saturating_sub(previous.cache_write),
+        reasoning: current.reasoning.saturating_sub(previous.reasoning),
+        total_tokens: current.total_tokens.saturating_sub(previous.total_tokens),
+        cost: Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+    };
+    if delta.total_tokens == 0 {
+        return;
     }
+    stream.push(AssistantMessageEvent::UsageDelta {
+        usage: delta,
+        partial: output.clone(),
+    });
 }
 
-pub(super) fn shared_http_client(base_url: &str) -> &'static Client {
-    static DEFAULT_CLIENT: OnceLock<Client> = OnceLock::new();
-    static LOOPBACK_CLIENT: OnceLock<Client> = OnceLock::new();
+/// Builds the error for a failed response, classified by [`classify_http_error`].
+///
+/// The status and any `Retry-After` hint go into the details so retries can act on them.
+pub(super) async fn http_error_from_response(label: &str, response: Response) -> PiAiError {
+    let status = response.status().as_u16();
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let retry_after_ms = parse_retry_after_ms(
+        header("retry-after-ms").as_deref(),
+        header(RETRY_AFTER.as_str()).as_deref(),
+    );
+    let body = response
+        .text()
+        .await
+        .unwrap_or_else(|_| "unable to read error body".to_string());
+
+    let mut details = json!({ "status": status });
+    if let Some(retry_after_ms) = retry_after_ms {
+        details["retryAfterMs"] = json!(retry_after_ms);
+    }
+    PiAiError::new(
+        classify_http_error(status, &body),
+        format!("{label} HTTP {status}: {body}"),
+    )
+    .with_details(details)
+}
 
-    if is_loopback_base_url(base_url) {
-        LOOPBACK_CLIENT.get_or_init(|| {
-            Client::builder()
-                .no_proxy()
-                .build()
-                .unwrap_or_else(|_| Client::new())
+/// Sends `request` and reads the whole response body for the provider to parse.
+///
+/// The body is read chunk by chunk under the request's [`StreamTransport`] timeouts. When the
+/// connection drops or stalls after events with an `id:` arrived, the request is sent again
+/// with `Last-Event-ID` and the resumed events are appended after the last complete one.
+///
+/// While a cassette is replaying, the request is never sent and the next recorded body is
+/// returned instead; while recording, the body is captured before it is returned.
+pub(super) async fn fetch_response_body(
+    label: &str,
+    model: &Model,
+    request: RequestBuilder,
+    options: Option<&StreamOptions>,
+) -> Result<String, PiAiError> {
+    if let Some(replayed) = next_replayed_body(model) {
+        return replayed;
+    }
+
+    let transport = options
+        .and_then(|options| options.stream_transport.clone())
+        .unwrap_or_default();
+    let read = read_resumable_body(label, request, &transport);
+    let body = match transport.total_timeout_ms {
+        Some(total_timeout_ms) => {
+            tokio::time::timeout(Duration::from_millis(total_timeout_ms), read)
+                .await
+                .map_err(|_| {
+                    PiAiError::new(
+                        PiAiErrorCode::TransportTimeout,
+                        format!("{label} request exceeded {total_timeout_ms}ms"),
+                    )
+                })??
+        }
+        None => read.await?,
+    };
+    let body = String::from_utf8_lossy(&body).into_owned();
+    record_body(model, &body);
+    Ok(body)
+}
+
+async fn read_resumable_body(
+    label: &str,
+    request: RequestBuilder,
+    transport: &StreamTransport,
+) -> Result<Vec<u8>, PiAiError> {
+    let mut body = Vec::new();
+    let mut request = request;
+    let mut resumes = 0;
+    loop {
+        let retry = request.try_clone();
+        let error = match read_response_body(label, request, &mut body, transport).await {
+            Ok(()) => return Ok(body),
+            Err(error) => error,
+        };
+        let resumable = matches!(
+            error.code,
+            PiAiErrorCode::ProviderTransport | PiAiErrorCode::TransportTimeout
+        );
+        body.truncate(complete_events_len(&body));
+        let (Some(retry), Some(event_id)) = (retry, last_event_id(&body)) else {
+            return Err(error);
+        };
+        if !resumable || resumes >= transport.max_resumes {
+            return Err(error);
+        }
+        resumes += 1;
+        request = retry.header("Last-Event-ID", event_id);
+    }
+}
+
+/// Sends `request` and appends its body to `body`, failing on errors and idle timeouts.
+async fn read_response_body(
+    label: &str,
+    request: RequestBuilder,
+    body: &mut Vec<u8>,
+    transport: &StreamTransport,
+) -> Result<(), PiAiError> {
+    let mut response = request.send().await.map_err(|error| {
+        let code = if error.is_timeout() {
+            PiAiErrorCode::TransportTimeout
+        } else {
+            PiAiErrorCode::ProviderTransport
+        };
+        PiAiError::new(code, format!("{label} transport failed: {error}"))
+    })?;
+    if !response.status().is_success() {
+        return Err(http_error_from_response(label, response).await);
+    }
+
+    loop {
+        let chunk = match transport.idle_timeout_ms {
+            Some(idle_timeout_ms) => {
+                tokio::time::timeout(Duration::from_millis(idle_timeout_ms), response.chunk())
+                    .await
+                    .map_err(|_| {
+                        PiAiError::new(
+                            PiAiErrorCode::TransportTimeout,
+                            format!("{label} stream was idle for {idle_timeout_ms}ms"),
+                        )
+                    })?
+            }
+            None => response.chunk().await,
+        };
+        match chunk.map_err(|error| {
+            PiAiError::new(
+                PiAiErrorCode::ProviderTransport,
+                format!("{label} stream read failed: {error}"),
+            )
+        })? {
+            Some(chunk) => body.extend_from_slice(&chunk),
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Length of `body` up to the end of its last complete SSE event.
+fn complete_events_len(body: &[u8]) -> usize {
+    [&b"\n\n"[..], &b"\r\n\r\n"[..]]
+        .iter()
+        .filter_map(|separator| {
+            body.windows(separator.len())
+                .rposition(|window| window == *separator)
+                .map(|start| start + separator.len())
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// The `id:` of the last SSE event in `body` that set one.
+fn last_event_id(body: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(body)
+        .lines()
+        .rev()
+        .find_map(|line| line.strip_prefix("id:"))
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+}
+
+/// Whether the system prompt or any user block asks for the longer cache lifetime.
+pub(super) fn has_extended_cache_hint(context: &Context) -> bool {
+    context.system_prompt_cache == Some(CacheHint::Extended)
+        || context.messages.iter().any(|message| match message {
+            Message::User {
+                content: UserContent::Blocks(blocks),
+                ..
+            } => blocks.iter().any(|block| {
+                matches!(
+                    block,
+                    UserContentBlock::Text {
+                        cache: Some(CacheHint::Extended),
+                        ..
+                    }
+                )
+            }),
+            _ => false,
         })
+}
+
+/// Wraps raw 16-bit mono PCM in a WAV container so clients can play it directly.
+pub(super) fn pcm16_to_wav(pcm: &[u8], sample_rate: u32) -> Vec<u8> {
+    let data_len = pcm.len() as u32;
+    let mut wav = Vec::with_capacity(44 + pcm.len());
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    wav.extend_from_slice(pcm);
+    wav
+}
+
+/// Request field names a provider uses for the sampling parameters of [`StreamOptions`];
+/// `None` for parameters it does not accept, which are then left out.
+pub(super) struct SamplingFields {
+    pub top_p: Option<&'static str>,
+    pub top_k: Option<&'static str>,
+    pub seed: Option<&'static str>,
+    pub frequency_penalty: Option<&'static str>,
+    pub presence_penalty: Option<&'static str>,
+}
+
+impl SamplingFields {
+    /// Inserts the parameters set in `options`, then `extra_sampling`.
+    pub(super) fn insert(&self, target: &mut Map<String, Value>, options: Option<&StreamOptions>) {
+        let Some(options) = options else {
+            return;
+        };
+        let fields = [
+            (self.top_p, options.top_p.map(Value::from)),
+            (self.top_k, options.top_k.map(Value::from)),
+            (self.seed, options.seed.map(Value::from)),
+            (
+                self.frequency_penalty,
+                options.frequency_penalty.map(Value::from),
+            ),
+            (
+                self.presence_penalty,
+                options.presence_penalty.map(Value::from),
+            ),
+        ];
+        for (name, value) in fields {
+            if let (Some(name), Some(value)) = (name, value) {
+                target.insert(name.to_string(), value);
+            }
+        }
+        target.extend(options.extra_sampling.clone());
+    }
+}
+
+/// Text stand-in for an audio clip sent to a model without audio input.
+pub(super) fn unsupported_audio_note(mime_type: &str) -> String {
+    format!("[{mime_type} attachment omitted: this model does not accept audio input]")
+}
+
+pub(super) fn join_url(base_url: &str, path: &str) -> String {
+    if base_url.ends_with('/') {
+        format!("{base_url}{path}")
     } else {
-        DEFAULT_CLIENT.get_or_init(Client::new)
+        format!("{base_url}/{path}")
     }
 }
 
-pub(super) fn is_loopback_base_url(base_url: &str) -> bool {
-    let Ok(url) = reqwest::Url::parse(base_url) else {
-        return false;
-    };
-    let Some(host) = url.host_str() else {
-        return false;
-    };
-    host.eq_ignore_ascii_case("localhost") || host == "127.0.0.1" || host == "::1"
+/// The pooled client for requests from `provider` to `base_url`, built with the provider's
+/// [`crate::HttpTransport`].
+pub(super) fn shared_http_client(provider: &str, base_url: &str) -> Arc<Client> {
+    http_client(provider, base_url)
 }
 
 pub(super) fn now_millis() -> i64 {
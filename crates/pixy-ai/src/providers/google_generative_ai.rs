@@ -2,16 +2,21 @@ use std::env;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
+use base64::prelude::{Engine as _, BASE64_STANDARD};
 use reqwest::RequestBuilder;
 use serde_json::{json, Map, Value};
 
-use super::common::{empty_assistant_message, join_url, shared_http_client};
+use super::common::{
+    empty_assistant_message, fetch_response_body, join_url, pcm16_to_wav, push_usage_delta,
+    shared_http_client, SamplingFields,
+};
 use crate::api_registry::{ApiProvider, ApiProviderFuture};
 use crate::error::{PiAiError, PiAiErrorCode};
+use crate::message_adapter::{MessageAdapter, MessageRule};
 use crate::types::{
     AssistantContentBlock, AssistantMessage, AssistantMessageEvent, Context, DoneReason, Message,
-    Model, SimpleStreamOptions, StopReason, StreamOptions, Tool, ToolResultContentBlock, Usage,
-    UserContent, UserContentBlock,
+    Model, SimpleStreamOptions, StopReason, StreamOptions, ThinkingLevel, Tool,
+    ToolResultContentBlock, Usage, UserContent, UserContentBlock,
 };
 use crate::{ApiProviderRef, AssistantMessageEventStream};
 
@@ -87,6 +92,12 @@ pub(super) async fn run_simple_google_with_mode(
     fallback_envs: &[&str],
     stream: AssistantMessageEventStream,
 ) -> Result<(), PiAiError> {
+    let mut model = model;
+    if model.reasoning {
+        if let Some(reasoning) = options.as_ref().and_then(|simple| simple.reasoning.clone()) {
+            model.reasoning_effort = Some(reasoning);
+        }
+    }
     let merged = options.map(|simple| simple.stream);
     run_google_with_mode(model, context, merged, auth_mode, fallback_envs, stream).await
 }
@@ -104,7 +115,7 @@ pub(super) async fn run_google_with_mode(
     let mut output = empty_assistant_message(&model);
     let payload = build_google_payload(&model, &context, options.as_ref());
     let endpoint = build_google_endpoint(&model);
-    let client = shared_http_client(&model.base_url);
+    let client = shared_http_client(&model.provider, &model.base_url);
 
     let execution = async {
         let mut request = client
@@ -117,31 +128,8 @@ pub(super) async fn run_google_with_mode(
                 request = request.header(name, value);
             }
         }
-        let response = request.json(&payload).send().await.map_err(|error| {
-            PiAiError::new(
-                PiAiErrorCode::ProviderTransport,
-                format!("Google transport failed: {error}"),
-            )
-        })?;
-
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let body = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "unable to read error body".to_string());
-            return Err(PiAiError::new(
-                PiAiErrorCode::ProviderHttp,
-                format!("Google HTTP {status}: {body}"),
-            ));
-        }
-
-        let body = response.text().await.map_err(|error| {
-            PiAiError::new(
-                PiAiErrorCode::ProviderTransport,
-                format!("Google response read failed: {error}"),
-            )
-        })?;
+        let body =
+            fetch_response_body("Google", &model, request.json(&payload), options.as_ref()).await?;
 
         stream.push(AssistantMessageEvent::Start {
             partial: output.clone(),
@@ -256,7 +244,9 @@ fn apply_google_payload(
         .get("usageMetadata")
         .or_else(|| payload.get("usage_metadata"))
     {
+        let previous = output.usage.clone();
         update_usage_from_google(&mut output.usage, usage);
+        push_usage_delta(stream, output, &previous);
         handled = true;
     }
 
@@ -379,9 +369,66 @@ fn apply_google_part(
         });
     }
 
+    if let Some(inline_data) = part
+        .get("inlineData")
+        .or_else(|| part.get("inline_data"))
+        .and_then(Value::as_object)
+    {
+        let mime_type = inline_data
+            .get("mimeType")
+            .or_else(|| inline_data.get("mime_type"))
+            .and_then(Value::as_str)
+            .unwrap_or("image/png");
+        let data = inline_data
+            .get("data")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        if mime_type.starts_with("image/") && !data.is_empty() {
+            close_current_google_block(output, stream, state);
+            output.content.push(AssistantContentBlock::Image {
+                data: data.to_string(),
+                mime_type: mime_type.to_string(),
+            });
+        } else if mime_type.starts_with("audio/") && !data.is_empty() {
+            close_current_google_block(output, stream, state);
+            output
+                .content
+                .push(google_audio_block(data, mime_type).map_err(|error| {
+                    PiAiError::new(
+                        PiAiErrorCode::ProviderProtocol,
+                        format!("Invalid Gemini audio data: {error}"),
+                    )
+                })?);
+        }
+    }
+
     Ok(())
 }
 
+/// Speech comes back as raw PCM (`audio/L16;codec=pcm;rate=24000`); wrap it as WAV so it can be
+/// played directly. Other audio formats are kept as-is.
+fn google_audio_block(data: &str, mime_type: &str) -> Result<AssistantContentBlock, String> {
+    let mut params = mime_type.split(';').map(str::trim);
+    let base = params.next().unwrap_or_default();
+    if !base.eq_ignore_ascii_case("audio/L16") && !base.eq_ignore_ascii_case("audio/pcm") {
+        return Ok(AssistantContentBlock::Audio {
+            data: data.to_string(),
+            mime_type: mime_type.to_string(),
+        });
+    }
+    let sample_rate = params
+        .find_map(|param| param.strip_prefix("rate="))
+        .and_then(|rate| rate.parse::<u32>().ok())
+        .unwrap_or(24_000);
+    let pcm = BASE64_STANDARD
+        .decode(data)
+        .map_err(|error| error.to_string())?;
+    Ok(AssistantContentBlock::Audio {
+        data: BASE64_STANDARD.encode(pcm16_to_wav(&pcm, sample_rate)),
+        mime_type: "audio/wav".to_string(),
+    })
+}
+
 fn append_google_text_delta(
     output: &mut AssistantMessage,
     stream: &AssistantMessageEventStream,
@@ -504,11 +551,20 @@ fn close_current_google_block(
     }
 }
 
+const SAMPLING_FIELDS: SamplingFields = SamplingFields {
+    top_p: Some("topP"),
+    top_k: Some("topK"),
+    seed: Some("seed"),
+    frequency_penalty: Some("frequencyPenalty"),
+    presence_penalty: Some("presencePenalty"),
+};
+
 fn build_google_payload(
     model: &Model,
     context: &Context,
     options: Option<&StreamOptions>,
 ) -> Value {
+    let context = &message_adapter(model).apply(model, context);
     let mut payload = json!({
         "contents": convert_messages(context),
     });
@@ -529,11 +585,41 @@ fn build_google_payload(
     if let Some(temperature) = options.and_then(|opts| opts.temperature) {
         generation_config.insert("temperature".to_string(), json!(temperature));
     }
+    SAMPLING_FIELDS.insert(&mut generation_config, options);
     if let Some(max_tokens) = options.and_then(|opts| opts.max_tokens) {
         generation_config.insert("maxOutputTokens".to_string(), json!(max_tokens));
     } else if model.max_tokens > 0 {
         generation_config.insert("maxOutputTokens".to_string(), json!(model.max_tokens));
     }
+    if let Some(stop) = options
+        .map(|opts| &opts.stop_sequences)
+        .filter(|stop| !stop.is_empty())
+    {
+        generation_config.insert("stopSequences".to_string(), json!(stop));
+    }
+    if model.reasoning {
+        generation_config.insert(
+            "thinkingConfig".to_string(),
+            json!({
+                "includeThoughts": true,
+                "thinkingBudget": thinking_budget(model, options),
+            }),
+        );
+    }
+    // Image models only return pictures when asked for the IMAGE modality.
+    if model.id.contains("-image") || options.is_some_and(|opts| opts.image_generation) {
+        generation_config.insert("responseModalities".to_string(), json!(["TEXT", "IMAGE"]));
+    }
+    // Text-to-speech models answer with audio only.
+    if model.id.contains("-tts") {
+        generation_config.insert("responseModalities".to_string(), json!(["AUDIO"]));
+        if let Some(voice) = options.and_then(|opts| opts.audio_voice.as_deref()) {
+            generation_config.insert(
+                "speechConfig".to_string(),
+                json!({ "voiceConfig": { "prebuiltVoiceConfig": { "voiceName": voice } } }),
+            );
+        }
+    }
     if !generation_config.is_empty() {
         payload["generationConfig"] = Value::Object(generation_config);
     }
@@ -541,6 +627,39 @@ fn build_google_payload(
     payload
 }
 
+/// Gemma models served by the Gemini API take no system instruction.
+fn message_adapter(model: &Model) -> MessageAdapter {
+    let mut rules = Vec::new();
+    if model.id.starts_with("gemma") {
+        rules.push(MessageRule::SystemPromptAsFirstUser);
+    }
+    rules.extend([
+        MessageRule::StripForeignThinking,
+        MessageRule::MergeConsecutiveUserMessages,
+    ]);
+    MessageAdapter::new(rules)
+}
+
+/// Gemini thinking budget requested for this call or implied by the model's reasoning effort;
+/// `-1` lets the model decide.
+fn thinking_budget(model: &Model, options: Option<&StreamOptions>) -> i64 {
+    if let Some(budget) = options.and_then(|options| options.thinking_budget) {
+        return i64::from(budget);
+    }
+    let Some(effort) = &model.reasoning_effort else {
+        return -1;
+    };
+    match effort {
+        ThinkingLevel::Minimal => 128,
+        ThinkingLevel::Low => 2_048,
+        ThinkingLevel::Medium => 8_192,
+        ThinkingLevel::High => 16_384,
+        // Flash models cap the budget lower than Pro.
+        ThinkingLevel::Xhigh if model.id.contains("flash") => 24_576,
+        ThinkingLevel::Xhigh => 32_768,
+    }
+}
+
 fn convert_messages(context: &Context) -> Vec<Value> {
     let mut messages = Vec::new();
 
@@ -555,7 +674,8 @@ fn convert_messages(context: &Context) -> Vec<Value> {
                             UserContentBlock::Text { text, .. } => json!({
                                 "text": text,
                             }),
-                            UserContentBlock::Image { data, mime_type } => json!({
+                            UserContentBlock::Image { data, mime_type }
+                            | UserContentBlock::Audio { data, mime_type } => json!({
                                 "inlineData": {
                                     "mimeType": mime_type,
                                     "data": data,
@@ -619,6 +739,13 @@ fn convert_messages(context: &Context) -> Vec<Value> {
                             }
                             part
                         }
+                        AssistantContentBlock::Image { data, mime_type }
+                        | AssistantContentBlock::Audio { data, mime_type } => json!({
+                            "inlineData": {
+                                "mimeType": mime_type,
+                                "data": data,
+                            }
+                        }),
                     })
                     .collect();
 
@@ -636,13 +763,23 @@ fn convert_messages(context: &Context) -> Vec<Value> {
                 is_error,
                 ..
             } => {
-                let tool_part = json!({
+                let mut tool_parts = vec![json!({
                     "functionResponse": {
                         "name": tool_name,
                         "id": tool_call_id,
                         "response": convert_tool_result_response(content, *is_error),
                     }
-                });
+                })];
+                // Images ride along as inline data next to the function response.
+                tool_parts.extend(content.iter().filter_map(|block| match block {
+                    ToolResultContentBlock::Image { data, mime_type } => Some(json!({
+                        "inlineData": {
+                            "mimeType": mime_type,
+                            "data": data,
+                        }
+                    })),
+                    ToolResultContentBlock::Text { .. } => None,
+                }));
 
                 let merged = if let Some(last) = messages.last_mut() {
                     let is_user = last.get("role").and_then(Value::as_str) == Some("user");
@@ -658,7 +795,7 @@ fn convert_messages(context: &Context) -> Vec<Value> {
 
                     if is_user && has_function_response {
                         if let Some(parts) = last.get_mut("parts").and_then(Value::as_array_mut) {
-                            parts.push(tool_part.clone());
+                            parts.extend(tool_parts.iter().cloned());
                             true
                         } else {
                             false
@@ -673,7 +810,7 @@ fn convert_messages(context: &Context) -> Vec<Value> {
                 if !merged {
                     messages.push(json!({
                         "role": "user",
-                        "parts": [tool_part],
+                        "parts": tool_parts,
                     }));
                 }
             }
@@ -848,6 +985,7 @@ fn update_usage_from_google(usage: &mut Usage, value: &Value) {
 
     usage.input = prompt_tokens.saturating_sub(cache_read);
     usage.output = output_tokens + thoughts_tokens;
+    usage.reasoning = thoughts_tokens;
     usage.cache_read = cache_read;
     usage.cache_write = 0;
     usage.total_tokens = total_tokens.max(usage.input + usage.output + usage.cache_read);
@@ -907,3 +1045,241 @@ fn truncate_for_details(text: &str, limit: usize) -> String {
     let prefix: String = text.chars().take(limit - 3).collect();
     format!("{prefix}...")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Cost;
+
+    fn sample_model(id: &str) -> Model {
+        Model {
+            id: id.to_string(),
+            name: id.to_string(),
+            api: "google-generative-ai".to_string(),
+            provider: "google".to_string(),
+            base_url: DEFAULT_GOOGLE_BASE_URL.to_string(),
+            reasoning: true,
+            reasoning_effort: None,
+            input: vec!["text".to_string()],
+            cost: Cost {
+                input: 0.0,
+                output: 0.0,
+                cache_read: 0.0,
+                cache_write: 0.0,
+                total: 0.0,
+            },
+            context_window: 1_000_000,
+            max_tokens: 8_192,
+            deployment: None,
+            api_version: None,
+            compat: None,
+        }
+    }
+
+    fn sample_context() -> Context {
+        Context {
+            system_prompt: None,
+            messages: vec![Message::User {
+                content: UserContent::Text("hello".to_string()),
+                timestamp: 0,
+            }],
+            tools: None,
+            system_prompt_cache: None,
+            branch: None,
+        }
+    }
+
+    #[test]
+    fn google_payload_sets_sampling_in_generation_config() {
+        let options = StreamOptions {
+            top_p: Some(0.8),
+            top_k: Some(20),
+            seed: Some(3),
+            ..StreamOptions::default()
+        };
+        let payload = build_google_payload(
+            &sample_model("gemini-2.5-flash"),
+            &sample_context(),
+            Some(&options),
+        );
+        let config = &payload["generationConfig"];
+        assert_eq!(config["topP"], 0.8);
+        assert_eq!(config["topK"], 20);
+        assert_eq!(config["seed"], 3);
+    }
+
+    #[test]
+    fn gemma_payload_sends_the_system_prompt_as_the_first_user_turn() {
+        let context = Context {
+            system_prompt: Some("Be brief.".to_string()),
+            ..sample_context()
+        };
+        let payload = build_google_payload(&sample_model("gemma-3-27b-it"), &context, None);
+        assert!(payload.get("systemInstruction").is_none());
+        assert_eq!(
+            payload["contents"],
+            json!([{ "role": "user", "parts": [{ "text": "Be brief.\n\nhello" }] }])
+        );
+
+        let payload = build_google_payload(&sample_model("gemini-2.5-pro"), &context, None);
+        assert_eq!(
+            payload["systemInstruction"]["parts"][0]["text"],
+            "Be brief."
+        );
+    }
+
+    #[test]
+    fn google_payload_maps_thinking_level_to_budget() {
+        let mut model = sample_model("gemini-2.5-pro");
+        let payload = build_google_payload(&model, &sample_context(), None);
+        assert_eq!(
+            payload["generationConfig"]["thinkingConfig"],
+            json!({"includeThoughts": true, "thinkingBudget": -1})
+        );
+
+        model.reasoning_effort = Some(ThinkingLevel::Low);
+        let payload = build_google_payload(&model, &sample_context(), None);
+        assert_eq!(
+            payload["generationConfig"]["thinkingConfig"]["thinkingBudget"],
+            2_048
+        );
+
+        model.reasoning_effort = Some(ThinkingLevel::Xhigh);
+        let payload = build_google_payload(&model, &sample_context(), None);
+        assert_eq!(
+            payload["generationConfig"]["thinkingConfig"]["thinkingBudget"],
+            32_768
+        );
+
+        let mut flash = sample_model("gemini-2.5-flash");
+        flash.reasoning_effort = Some(ThinkingLevel::Xhigh);
+        let payload = build_google_payload(&flash, &sample_context(), None);
+        assert_eq!(
+            payload["generationConfig"]["thinkingConfig"]["thinkingBudget"],
+            24_576
+        );
+
+        let options = StreamOptions {
+            thinking_budget: Some(0),
+            ..StreamOptions::default()
+        };
+        let payload = build_google_payload(&flash, &sample_context(), Some(&options));
+        assert_eq!(
+            payload["generationConfig"]["thinkingConfig"]["thinkingBudget"],
+            0
+        );
+    }
+
+    #[test]
+    fn google_usage_reports_thoughts_as_reasoning_tokens() {
+        let mut usage = empty_assistant_message(&sample_model("gemini-2.5-pro")).usage;
+        update_usage_from_google(
+            &mut usage,
+            &json!({
+                "promptTokenCount": 10,
+                "candidatesTokenCount": 4,
+                "thoughtsTokenCount": 20,
+            }),
+        );
+        assert_eq!(usage.output, 24);
+        assert_eq!(usage.reasoning, 20);
+    }
+
+    #[test]
+    fn google_payload_omits_thinking_config_for_non_reasoning_models() {
+        let mut model = sample_model("gemini-2.0-flash");
+        model.reasoning = false;
+        let payload = build_google_payload(&model, &sample_context(), None);
+        assert!(payload["generationConfig"].get("thinkingConfig").is_none());
+    }
+
+    #[test]
+    fn google_image_models_request_image_output_and_parse_inline_images() {
+        let model = sample_model("gemini-2.5-flash-image");
+        let payload = build_google_payload(&model, &sample_context(), None);
+        assert_eq!(
+            payload["generationConfig"]["responseModalities"],
+            json!(["TEXT", "IMAGE"])
+        );
+
+        let mut output = empty_assistant_message(&model);
+        let stream = AssistantMessageEventStream::new();
+        let mut state = GoogleStreamState::default();
+        apply_google_payload(
+            &json!({
+                "candidates": [{
+                    "content": {
+                        "parts": [
+                            { "text": "Here is your cat." },
+                            { "inlineData": { "mimeType": "image/png", "data": "iVBORw0K" } }
+                        ]
+                    },
+                    "finishReason": "STOP"
+                }]
+            }),
+            &mut output,
+            &stream,
+            &mut state,
+        )
+        .expect("apply payload");
+
+        assert_eq!(output.content.len(), 2);
+        assert_eq!(
+            output.content[1],
+            AssistantContentBlock::Image {
+                data: "iVBORw0K".to_string(),
+                mime_type: "image/png".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn google_tts_models_request_audio_and_wrap_pcm_as_wav() {
+        let model = sample_model("gemini-2.5-flash-preview-tts");
+        let options = StreamOptions {
+            audio_voice: Some("Kore".to_string()),
+            ..StreamOptions::default()
+        };
+        let payload = build_google_payload(&model, &sample_context(), Some(&options));
+        assert_eq!(
+            payload["generationConfig"]["responseModalities"],
+            json!(["AUDIO"])
+        );
+        assert_eq!(
+            payload["generationConfig"]["speechConfig"]["voiceConfig"]["prebuiltVoiceConfig"]
+                ["voiceName"],
+            "Kore"
+        );
+
+        let mut output = empty_assistant_message(&model);
+        let stream = AssistantMessageEventStream::new();
+        let mut state = GoogleStreamState::default();
+        apply_google_payload(
+            &json!({
+                "candidates": [{
+                    "content": {
+                        "parts": [{
+                            "inlineData": {
+                                "mimeType": "audio/L16;codec=pcm;rate=16000",
+                                "data": BASE64_STANDARD.encode([1u8, 0, 2, 0]),
+                            }
+                        }]
+                    },
+                    "finishReason": "STOP"
+                }]
+            }),
+            &mut output,
+            &stream,
+            &mut state,
+        )
+        .expect("apply payload");
+
+        let AssistantContentBlock::Audio { data, mime_type } = &output.content[0] else {
+            panic!("expected audio block, got {:?}", output.content);
+        };
+        assert_eq!(mime_type, "audio/wav");
+        let wav = BASE64_STANDARD.decode(data).expect("valid base64");
+        assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), 16_000);
+        assert_eq!(&wav[44..], &[1, 0, 2, 0]);
+    }
+}
@@ -4,17 +4,30 @@ use crate::api_registry::{clear_api_providers, register_api_provider};
 use crate::ApiProviderRef;
 
 mod anthropic;
+mod azure_openai;
 mod bedrock_converse_stream;
 mod common;
 mod google_gemini_cli;
 mod google_generative_ai;
 mod google_vertex;
+mod model_list;
+mod ollama;
+mod openai_batch;
 mod openai_compat;
 mod openai_completions;
+mod openai_embeddings;
+mod openai_realtime;
 mod openai_responses;
+mod openai_vendors;
 mod reliable;
+mod websocket;
 
-pub use reliable::ReliableProvider;
+pub(crate) use model_list::list_source_models;
+pub use ollama::list_ollama_models;
+pub(crate) use openai_batch::{run_openai_batch, OpenAiBatchItem};
+pub(crate) use openai_embeddings::run_openai_embeddings;
+pub(crate) use openai_realtime::connect_openai_realtime;
+pub use reliable::{FallbackCondition, ReliableProvider};
 
 const BUILTIN_SOURCE_ID: &str = "pixy-ai-builtins";
 
@@ -28,11 +41,17 @@ pub fn register_builtin_api_providers() {
     register_builtin_provider(anthropic::provider());
     register_builtin_provider(openai_compat::openai_responses_provider());
     register_builtin_provider(openai_compat::azure_openai_responses_provider());
+    register_builtin_provider(azure_openai::provider());
     register_builtin_provider(openai_compat::openai_codex_responses_provider());
     register_builtin_provider(google_generative_ai::provider());
     register_builtin_provider(google_gemini_cli::provider());
     register_builtin_provider(google_vertex::provider());
     register_builtin_provider(bedrock_converse_stream::provider());
+    register_builtin_provider(ollama::provider());
+    register_builtin_provider(openai_vendors::xai_provider());
+    register_builtin_provider(openai_vendors::deepseek_provider());
+    register_builtin_provider(openai_vendors::openrouter_provider());
+    register_builtin_provider(openai_vendors::openai_compatible_provider());
 }
 
 pub fn reset_api_providers() {
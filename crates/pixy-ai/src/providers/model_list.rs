@@ -0,0 +1,177 @@
+use reqwest::RequestBuilder;
+use serde_json::Value;
+
+use super::common::{http_error_from_response, join_url, shared_http_client};
+use super::ollama::list_ollama_models;
+use crate::error::{PiAiError, PiAiErrorCode};
+use crate::model_catalog::ModelSource;
+use crate::types::Model;
+
+/// Id fragments of models that `/models` lists but that cannot chat.
+const NON_CHAT_MODEL_MARKERS: &[&str] = &[
+    "embedding",
+    "moderation",
+    "whisper",
+    "tts",
+    "dall-e",
+    "image",
+    "transcribe",
+    "realtime",
+    "search",
+    "babbage",
+    "davinci",
+    "aqa",
+];
+
+pub(crate) async fn list_source_models(source: &ModelSource) -> Result<Vec<Model>, PiAiError> {
+    let client = shared_http_client(&source.provider, &source.base_url);
+    let endpoint = join_url(&source.base_url, "models");
+    let api_key = source.api_key.as_deref().unwrap_or_default();
+    let models: Vec<Model> = match source.api.as_str() {
+        "openai-completions" | "openai-responses" | "openai-compatible" | "xai" | "deepseek"
+        | "openrouter" => {
+            let body = fetch_listing(
+                client
+                    .get(endpoint.as_str())
+                    .header("Authorization", format!("Bearer {api_key}")),
+            )
+            .await?;
+            listed_entries(&body, "data")?
+                .iter()
+                .filter_map(|entry| entry.get("id").and_then(Value::as_str))
+                .map(|id| source.model(id, None))
+                .collect()
+        }
+        "anthropic-messages" => {
+            let body = fetch_listing(
+                client
+                    .get(endpoint.as_str())
+                    .header("x-api-key", api_key)
+                    .header("anthropic-version", "2023-06-01"),
+            )
+            .await?;
+            listed_entries(&body, "data")?
+                .iter()
+                .filter_map(|entry| {
+                    let id = entry.get("id").and_then(Value::as_str)?;
+                    let name = entry.get("display_name").and_then(Value::as_str);
+                    Some(source.model(id, name))
+                })
+                .collect()
+        }
+        "google-generative-ai" => {
+            let body = fetch_listing(
+                client
+                    .get(endpoint.as_str())
+                    .header("x-goog-api-key", api_key),
+            )
+            .await?;
+            listed_entries(&body, "models")?
+                .iter()
+                .filter(|entry| supports_generate_content(entry))
+                .filter_map(|entry| google_model(source, entry))
+                .collect()
+        }
+        "ollama" => list_ollama_models(&source.base_url)
+            .await?
+            .into_iter()
+            .map(|model| Model {
+                provider: source.provider.clone(),
+                ..model
+            })
+            .collect(),
+        api => {
+            return Err(PiAiError::new(
+                PiAiErrorCode::ProviderProtocol,
+                format!("Listing models is not supported for api '{api}'"),
+            ))
+        }
+    };
+
+    Ok(models
+        .into_iter()
+        .filter(|model| is_chat_model_id(&model.id))
+        .collect())
+}
+
+async fn fetch_listing(request: RequestBuilder) -> Result<Value, PiAiError> {
+    let response = request.send().await.map_err(|error| {
+        PiAiError::new(
+            PiAiErrorCode::ProviderTransport,
+            format!("Model listing transport failed: {error}"),
+        )
+    })?;
+    if !response.status().is_success() {
+        return Err(http_error_from_response("Model listing", response).await);
+    }
+    response.json().await.map_err(|error| {
+        PiAiError::new(
+            PiAiErrorCode::ProviderProtocol,
+            format!("Model listing is not valid JSON: {error}"),
+        )
+    })
+}
+
+fn listed_entries<'a>(body: &'a Value, field: &str) -> Result<&'a Vec<Value>, PiAiError> {
+    body.get(field).and_then(Value::as_array).ok_or_else(|| {
+        PiAiError::new(
+            PiAiErrorCode::ProviderProtocol,
+            format!("Model listing is missing `{field}`"),
+        )
+    })
+}
+
+fn supports_generate_content(entry: &Value) -> bool {
+    entry
+        .get("supportedGenerationMethods")
+        .and_then(Value::as_array)
+        .is_none_or(|methods| {
+            methods
+                .iter()
+                .any(|method| method.as_str() == Some("generateContent"))
+        })
+}
+
+/// Gemini listings carry their own token limits, which take precedence over bundled ones.
+fn google_model(source: &ModelSource, entry: &Value) -> Option<Model> {
+    let id = entry.get("name").and_then(Value::as_str)?;
+    let id = id.strip_prefix("models/").unwrap_or(id);
+    let mut model = source.model(id, entry.get("displayName").and_then(Value::as_str));
+    let limit = |field: &str| {
+        entry
+            .get(field)
+            .and_then(Value::as_u64)
+            .map(|limit| limit as u32)
+    };
+    if let Some(context_window) = limit("inputTokenLimit") {
+        model.context_window = context_window;
+    }
+    if let Some(max_tokens) = limit("outputTokenLimit") {
+        model.max_tokens = max_tokens;
+    }
+    if entry.get("thinking").and_then(Value::as_bool) == Some(true) {
+        model.reasoning = true;
+    }
+    Some(model)
+}
+
+fn is_chat_model_id(id: &str) -> bool {
+    let id = id.to_ascii_lowercase();
+    !NON_CHAT_MODEL_MARKERS
+        .iter()
+        .any(|marker| id.contains(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_chat_models_are_filtered_out() {
+        assert!(is_chat_model_id("gpt-4o-mini"));
+        assert!(is_chat_model_id("claude-sonnet-4-20250514"));
+        assert!(!is_chat_model_id("text-embedding-3-small"));
+        assert!(!is_chat_model_id("gpt-4o-mini-tts"));
+        assert!(!is_chat_model_id("whisper-1"));
+    }
+}
@@ -0,0 +1,800 @@
+use std::env;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde_json::{json, Map, Value};
+
+use super::common::{
+    debug_provider_event, empty_assistant_message, fetch_response_body, http_error_from_response,
+    join_url, shared_http_client, unsupported_audio_note, SamplingFields,
+};
+use crate::api_registry::{ApiProvider, ApiProviderFuture};
+use crate::error::{PiAiError, PiAiErrorCode};
+use crate::types::{
+    AssistantContentBlock, AssistantMessage, AssistantMessageEvent, Context, Cost, DoneReason,
+    Message, Model, SimpleStreamOptions, StopReason, StreamOptions, Tool, ToolResultContentBlock,
+    Usage, UserContent, UserContentBlock,
+};
+use crate::{ApiProviderRef, AssistantMessageEventStream};
+
+const OLLAMA_API: &str = "ollama";
+const DEFAULT_OLLAMA_BASE_URL: &str = "http://localhost:11434";
+const DEFAULT_OLLAMA_CONTEXT_WINDOW: u32 = 32_768;
+const DEFAULT_OLLAMA_MAX_TOKENS: u32 = 8_192;
+/// Openings that may start a tool call written into the text channel.
+const TEXT_TOOL_CALL_PREFIXES: &[&str] = &["{", "[", "<tool_call>", "```"];
+
+static OLLAMA_TOOL_CALL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+struct OllamaProvider;
+
+impl ApiProvider for OllamaProvider {
+    fn api(&self) -> &str {
+        OLLAMA_API
+    }
+
+    fn stream(
+        &self,
+        model: Model,
+        context: Context,
+        options: Option<StreamOptions>,
+        stream: AssistantMessageEventStream,
+    ) -> ApiProviderFuture {
+        Box::pin(async move { run_ollama_chat(model, context, options, stream).await })
+    }
+
+    fn stream_simple(
+        &self,
+        model: Model,
+        context: Context,
+        options: Option<SimpleStreamOptions>,
+        stream: AssistantMessageEventStream,
+    ) -> ApiProviderFuture {
+        Box::pin(async move { run_simple_ollama_chat(model, context, options, stream).await })
+    }
+}
+
+pub(super) fn provider() -> ApiProviderRef {
+    Arc::new(OllamaProvider)
+}
+
+pub async fn run_ollama_chat(
+    model: Model,
+    context: Context,
+    options: Option<StreamOptions>,
+    stream: AssistantMessageEventStream,
+) -> Result<(), PiAiError> {
+    let mut output = empty_assistant_message(&model);
+    let payload = build_ollama_payload(&model, &context, options.as_ref());
+    let base_url = ollama_base_url(&model.base_url);
+    let endpoint = join_url(base_url, "api/chat");
+    let client = shared_http_client(&model.provider, base_url);
+    let api_key = resolve_api_key(&model.provider, options.as_ref());
+
+    let execution = async {
+        let mut request = client
+            .post(endpoint.as_str())
+            .header("Content-Type", "application/json");
+        if let Some(api_key) = api_key.as_ref() {
+            request = request.header("Authorization", format!("Bearer {api_key}"));
+        }
+        if let Some(headers) = options.as_ref().and_then(|stream| stream.headers.as_ref()) {
+            for (name, value) in headers {
+                request = request.header(name, value);
+            }
+        }
+
+        let body =
+            fetch_response_body("Ollama", &model, request.json(&payload), options.as_ref()).await?;
+
+        stream.push(AssistantMessageEvent::Start {
+            partial: output.clone(),
+        });
+
+        let mut state = OllamaStreamState::new(&context);
+        for line in body.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            debug_provider_event(OLLAMA_API, line);
+            let chunk: Value = serde_json::from_str(line).map_err(|error| {
+                PiAiError::new(
+                    PiAiErrorCode::ProviderProtocol,
+                    format!("Invalid Ollama chunk JSON: {error}"),
+                )
+                .with_details(json!({ "chunk": line }))
+            })?;
+            if let Some(error) = chunk.get("error").and_then(Value::as_str) {
+                return Err(PiAiError::new(
+                    PiAiErrorCode::ProviderProtocol,
+                    format!("Ollama error: {error}"),
+                ));
+            }
+            state.apply_chunk(&chunk, &mut output, &stream);
+        }
+        state.finish(&mut output, &stream);
+
+        if output
+            .content
+            .iter()
+            .any(|block| matches!(block, AssistantContentBlock::ToolCall { .. }))
+        {
+            output.stop_reason = StopReason::ToolUse;
+        }
+        let reason = map_done_reason(output.stop_reason.clone()).ok_or_else(|| {
+            PiAiError::new(
+                PiAiErrorCode::ProviderProtocol,
+                "Ollama response ended with non-terminal done reason",
+            )
+        })?;
+        stream.push(AssistantMessageEvent::Done {
+            reason,
+            message: output.clone(),
+        });
+        Ok(())
+    }
+    .await;
+
+    if let Err(error) = execution {
+        output.stop_reason = StopReason::Error;
+        output.error_message = Some(error.message.clone());
+        stream.push(AssistantMessageEvent::Error {
+            reason: crate::types::ErrorReason::Error,
+            error: output,
+        });
+    }
+
+    Ok(())
+}
+
+pub async fn run_simple_ollama_chat(
+    model: Model,
+    context: Context,
+    options: Option<SimpleStreamOptions>,
+    stream: AssistantMessageEventStream,
+) -> Result<(), PiAiError> {
+    let merged = options.map(|simple| simple.stream);
+    run_ollama_chat(model, context, merged, stream).await
+}
+
+/// Lists the models pulled into the local Ollama server (`GET /api/tags`).
+pub async fn list_ollama_models(base_url: &str) -> Result<Vec<Model>, PiAiError> {
+    let base_url = ollama_base_url(base_url);
+    let endpoint = join_url(base_url, "api/tags");
+    let response = shared_http_client("ollama", base_url)
+        .get(endpoint.as_str())
+        .send()
+        .await
+        .map_err(|error| {
+            PiAiError::new(
+                PiAiErrorCode::ProviderTransport,
+                format!("Ollama transport failed: {error}"),
+            )
+        })?;
+    if !response.status().is_success() {
+        return Err(http_error_from_response("Ollama", response).await);
+    }
+
+    let body: Value = response.json().await.map_err(|error| {
+        PiAiError::new(
+            PiAiErrorCode::ProviderProtocol,
+            format!("Ollama model list is not valid JSON: {error}"),
+        )
+    })?;
+    let models = body
+        .get("models")
+        .and_then(Value::as_array)
+        .ok_or_else(|| {
+            PiAiError::new(
+                PiAiErrorCode::ProviderProtocol,
+                "Ollama model list is missing `models`",
+            )
+        })?;
+
+    Ok(models
+        .iter()
+        .filter_map(|entry| {
+            entry
+                .get("name")
+                .or_else(|| entry.get("model"))
+                .and_then(Value::as_str)
+        })
+        .map(|name| Model {
+            id: name.to_string(),
+            name: name.to_string(),
+            api: OLLAMA_API.to_string(),
+            provider: OLLAMA_API.to_string(),
+            base_url: base_url.to_string(),
+            reasoning: false,
+            reasoning_effort: None,
+            input: vec!["text".to_string()],
+            cost: Cost {
+                input: 0.0,
+                output: 0.0,
+                cache_read: 0.0,
+                cache_write: 0.0,
+                total: 0.0,
+            },
+            context_window: DEFAULT_OLLAMA_CONTEXT_WINDOW,
+            max_tokens: DEFAULT_OLLAMA_MAX_TOKENS,
+            deployment: None,
+            api_version: None,
+            compat: None,
+        })
+        .collect())
+}
+
+/// Tracks open content blocks while NDJSON chunks arrive.
+///
+/// Local models without native tool calling often answer with the call as
+/// JSON in the text channel. When tools are available, text that opens like
+/// a tool call is held back until the stream ends and then either converted
+/// into `ToolCall` blocks or flushed as ordinary text.
+struct OllamaStreamState {
+    tool_names: Vec<String>,
+    text_index: Option<usize>,
+    thinking_index: Option<usize>,
+    held_text: String,
+}
+
+impl OllamaStreamState {
+    fn new(context: &Context) -> Self {
+        Self {
+            tool_names: context
+                .tools
+                .iter()
+                .flatten()
+                .map(|tool| tool.name.clone())
+                .collect(),
+            text_index: None,
+            thinking_index: None,
+            held_text: String::new(),
+        }
+    }
+
+    fn apply_chunk(
+        &mut self,
+        chunk: &Value,
+        output: &mut AssistantMessage,
+        stream: &AssistantMessageEventStream,
+    ) {
+        let message = chunk.get("message");
+
+        if let Some(thinking) = message
+            .and_then(|message| message.get("thinking"))
+            .and_then(Value::as_str)
+            .filter(|thinking| !thinking.is_empty())
+        {
+            self.push_thinking(thinking, output, stream);
+        }
+
+        if let Some(content) = message
+            .and_then(|message| message.get("content"))
+            .and_then(Value::as_str)
+            .filter(|content| !content.is_empty())
+        {
+            self.close_thinking(output, stream);
+            if self.text_index.is_none() && !self.tool_names.is_empty() {
+                self.held_text.push_str(content);
+                if !may_start_text_tool_call(&self.held_text) {
+                    let held = std::mem::take(&mut self.held_text);
+                    self.push_text(&held, output, stream);
+                }
+            } else {
+                self.push_text(content, output, stream);
+            }
+        }
+
+        if let Some(tool_calls) = message
+            .and_then(|message| message.get("tool_calls"))
+            .and_then(Value::as_array)
+        {
+            self.close_thinking(output, stream);
+            self.close_text(output, stream);
+            for tool_call in tool_calls {
+                let function = tool_call.get("function").unwrap_or(tool_call);
+                let Some(name) = function.get("name").and_then(Value::as_str) else {
+                    continue;
+                };
+                let id = tool_call
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+                push_tool_call(
+                    id,
+                    name,
+                    parse_arguments(function.get("arguments")),
+                    output,
+                    stream,
+                );
+            }
+        }
+
+        if chunk.get("done").and_then(Value::as_bool) == Some(true) {
+            if let Some(reason) = chunk.get("done_reason").and_then(Value::as_str) {
+                output.stop_reason = map_ollama_stop_reason(reason);
+            }
+            update_usage_from_ollama(&mut output.usage, chunk);
+        }
+    }
+
+    fn finish(&mut self, output: &mut AssistantMessage, stream: &AssistantMessageEventStream) {
+        self.close_thinking(output, stream);
+        if !self.held_text.is_empty() {
+            let held = std::mem::take(&mut self.held_text);
+            match extract_text_tool_calls(&held, &self.tool_names) {
+                Some(calls) => {
+                    for (name, arguments) in calls {
+                        push_tool_call(None, &name, arguments, output, stream);
+                    }
+                }
+                None => self.push_text(&held, output, stream),
+            }
+        }
+        self.close_text(output, stream);
+    }
+
+    fn push_text(
+        &mut self,
+        delta: &str,
+        output: &mut AssistantMessage,
+        stream: &AssistantMessageEventStream,
+    ) {
+        let index = *self.text_index.get_or_insert_with(|| {
+            let index = output.content.len();
+            output.content.push(AssistantContentBlock::Text {
+                text: String::new(),
+                text_signature: None,
+            });
+            stream.push(AssistantMessageEvent::TextStart {
+                content_index: index,
+                partial: output.clone(),
+            });
+            index
+        });
+        if let Some(AssistantContentBlock::Text { text, .. }) = output.content.get_mut(index) {
+            text.push_str(delta);
+        }
+        stream.push(AssistantMessageEvent::TextDelta {
+            content_index: index,
+            delta: delta.to_string(),
+            partial: output.clone(),
+        });
+    }
+
+    fn push_thinking(
+        &mut self,
+        delta: &str,
+        output: &mut AssistantMessage,
+        stream: &AssistantMessageEventStream,
+    ) {
+        let index = *self.thinking_index.get_or_insert_with(|| {
+            let index = output.content.len();
+            output.content.push(AssistantContentBlock::Thinking {
+                thinking: String::new(),
+                thinking_signature: None,
+            });
+            stream.push(AssistantMessageEvent::ThinkingStart {
+                content_index: index,
+                partial: output.clone(),
+            });
+            index
+        });
+        if let Some(AssistantContentBlock::Thinking { thinking, .. }) =
+            output.content.get_mut(index)
+        {
+            thinking.push_str(delta);
+        }
+        stream.push(AssistantMessageEvent::ThinkingDelta {
+            content_index: index,
+            delta: delta.to_string(),
+            partial: output.clone(),
+        });
+    }
+
+    fn close_text(&mut self, output: &AssistantMessage, stream: &AssistantMessageEventStream) {
+        let Some(index) = self.text_index.take() else {
+            return;
+        };
+        let content = match output.content.get(index) {
+            Some(AssistantContentBlock::Text { text, .. }) => text.clone(),
+            _ => String::new(),
+        };
+        stream.push(AssistantMessageEvent::TextEnd {
+            content_index: index,
+            content,
+            partial: output.clone(),
+        });
+    }
+
+    fn close_thinking(&mut self, output: &AssistantMessage, stream: &AssistantMessageEventStream) {
+        let Some(index) = self.thinking_index.take() else {
+            return;
+        };
+        let content = match output.content.get(index) {
+            Some(AssistantContentBlock::Thinking { thinking, .. }) => thinking.clone(),
+            _ => String::new(),
+        };
+        stream.push(AssistantMessageEvent::ThinkingEnd {
+            content_index: index,
+            content,
+            partial: output.clone(),
+        });
+    }
+}
+
+fn push_tool_call(
+    id: Option<String>,
+    name: &str,
+    arguments: Value,
+    output: &mut AssistantMessage,
+    stream: &AssistantMessageEventStream,
+) {
+    let id = id.filter(|id| !id.is_empty()).unwrap_or_else(|| {
+        let sequence = OLLAMA_TOOL_CALL_COUNTER.fetch_add(1, Ordering::Relaxed);
+        format!("ollama_call_{sequence}")
+    });
+    let content_index = output.content.len();
+    output.content.push(AssistantContentBlock::ToolCall {
+        id: id.clone(),
+        name: name.to_string(),
+        arguments: arguments.clone(),
+        thought_signature: None,
+    });
+    stream.push(AssistantMessageEvent::ToolcallStart {
+        content_index,
+        partial: output.clone(),
+    });
+    stream.push(AssistantMessageEvent::ToolcallDelta {
+        content_index,
+        delta: arguments.to_string(),
+        partial: output.clone(),
+    });
+    stream.push(AssistantMessageEvent::ToolcallEnd {
+        content_index,
+        tool_call: json!({
+            "type": "toolCall",
+            "id": id,
+            "name": name,
+            "arguments": arguments,
+            "thoughtSignature": Value::Null,
+        }),
+        partial: output.clone(),
+    });
+}
+
+fn may_start_text_tool_call(text: &str) -> bool {
+    let trimmed = text.trim_start();
+    trimmed.is_empty()
+        || TEXT_TOOL_CALL_PREFIXES
+            .iter()
+            .any(|prefix| trimmed.starts_with(prefix) || prefix.starts_with(trimmed))
+}
+
+/// Parses tool calls a model wrote as JSON text: a bare object or array,
+/// a fenced code block, or one or more `<tool_call>` tags. Only calls to
+/// known tools count, so ordinary JSON answers stay text.
+fn extract_text_tool_calls(text: &str, tool_names: &[String]) -> Option<Vec<(String, Value)>> {
+    let trimmed = text.trim();
+    let segments: Vec<&str> = if trimmed.contains("<tool_call>") {
+        trimmed
+            .split("<tool_call>")
+            .skip(1)
+            .map(|segment| segment.split("</tool_call>").next().unwrap_or(segment))
+            .collect()
+    } else {
+        vec![trimmed]
+    };
+
+    let mut calls = Vec::new();
+    for segment in segments {
+        let segment = strip_code_fence(segment.trim());
+        let parsed: Value = serde_json::from_str(segment).ok()?;
+        let candidates = match parsed {
+            Value::Array(items) => items,
+            other => vec![other],
+        };
+        for candidate in candidates {
+            let function = candidate.get("function").unwrap_or(&candidate);
+            let name = function.get("name").and_then(Value::as_str)?;
+            if !tool_names.iter().any(|tool| tool == name) {
+                return None;
+            }
+            let arguments = parse_arguments(
+                function
+                    .get("arguments")
+                    .or_else(|| function.get("parameters")),
+            );
+            calls.push((name.to_string(), arguments));
+        }
+    }
+
+    (!calls.is_empty()).then_some(calls)
+}
+
+fn strip_code_fence(text: &str) -> &str {
+    let Some(rest) = text.strip_prefix("```") else {
+        return text;
+    };
+    let rest = rest.strip_prefix("json").unwrap_or(rest);
+    rest.strip_suffix("```").unwrap_or(rest).trim()
+}
+
+fn parse_arguments(value: Option<&Value>) -> Value {
+    match value {
+        Some(Value::String(raw)) => {
+            serde_json::from_str(raw).unwrap_or_else(|_| Value::Object(Map::new()))
+        }
+        Some(value @ Value::Object(_)) => value.clone(),
+        _ => Value::Object(Map::new()),
+    }
+}
+
+const SAMPLING_FIELDS: SamplingFields = SamplingFields {
+    top_p: Some("top_p"),
+    top_k: Some("top_k"),
+    seed: Some("seed"),
+    frequency_penalty: Some("frequency_penalty"),
+    presence_penalty: Some("presence_penalty"),
+};
+
+fn build_ollama_payload(
+    model: &Model,
+    context: &Context,
+    options: Option<&StreamOptions>,
+) -> Value {
+    let mut payload = json!({
+        "model": model.id,
+        "messages": convert_messages(context),
+        "stream": true,
+    });
+
+    if let Some(tools) = &context.tools {
+        payload["tools"] = convert_tools(tools);
+    }
+    if model.reasoning {
+        payload["think"] = json!(true);
+    }
+
+    let mut model_options = Map::new();
+    if let Some(temperature) = options.and_then(|opts| opts.temperature) {
+        model_options.insert("temperature".to_string(), json!(temperature));
+    }
+    SAMPLING_FIELDS.insert(&mut model_options, options);
+    if let Some(max_tokens) = options.and_then(|opts| opts.max_tokens) {
+        model_options.insert("num_predict".to_string(), json!(max_tokens));
+    }
+    if let Some(stop) = options
+        .map(|opts| &opts.stop_sequences)
+        .filter(|stop| !stop.is_empty())
+    {
+        model_options.insert("stop".to_string(), json!(stop));
+    }
+    // Ollama otherwise loads models with a small default context that
+    // truncates agent system prompts.
+    if model.context_window > 0 {
+        model_options.insert("num_ctx".to_string(), json!(model.context_window));
+    }
+    if !model_options.is_empty() {
+        payload["options"] = Value::Object(model_options);
+    }
+
+    payload
+}
+
+fn convert_messages(context: &Context) -> Vec<Value> {
+    let mut converted = Vec::new();
+    if let Some(system_prompt) = &context.system_prompt {
+        converted.push(json!({
+            "role": "system",
+            "content": system_prompt,
+        }));
+    }
+
+    for message in &context.messages {
+        match message {
+            Message::User { content, .. } => {
+                let (text, images) = match content {
+                    UserContent::Text(text) => (text.clone(), Vec::new()),
+                    UserContent::Blocks(blocks) => {
+                        let mut texts = Vec::new();
+                        let mut images = Vec::new();
+                        let mut notes = Vec::new();
+                        for block in blocks {
+                            match block {
+                                UserContentBlock::Text { text, .. } => texts.push(text.as_str()),
+                                UserContentBlock::Image { data, .. } => images.push(data.clone()),
+                                UserContentBlock::Audio { mime_type, .. } => {
+                                    notes.push(unsupported_audio_note(mime_type))
+                                }
+                            }
+                        }
+                        texts.extend(notes.iter().map(String::as_str));
+                        (texts.join("\n"), images)
+                    }
+                };
+                let mut value = json!({
+                    "role": "user",
+                    "content": text,
+                });
+                if !images.is_empty() {
+                    value["images"] = json!(images);
+                }
+                converted.push(value);
+            }
+            Message::Assistant { content, .. } => {
+                let mut texts = Vec::new();
+                let mut thinking = Vec::new();
+                let mut tool_calls = Vec::new();
+                for block in content {
+                    match block {
+                        AssistantContentBlock::Text { text, .. } => texts.push(text.as_str()),
+                        AssistantContentBlock::Thinking { thinking: text, .. } => {
+                            thinking.push(text.as_str())
+                        }
+                        AssistantContentBlock::ToolCall {
+                            name, arguments, ..
+                        } => tool_calls.push(json!({
+                            "function": {
+                                "name": name,
+                                "arguments": arguments,
+                            }
+                        })),
+                        // Assistant messages cannot carry images; audio is replayed through its
+                        // transcript text block.
+                        AssistantContentBlock::Image { .. }
+                        | AssistantContentBlock::Audio { .. } => {}
+                    }
+                }
+                if texts.is_empty() && tool_calls.is_empty() {
+                    continue;
+                }
+
+                let mut value = json!({
+                    "role": "assistant",
+                    "content": texts.join(""),
+                });
+                if !thinking.is_empty() {
+                    value["thinking"] = json!(thinking.join(""));
+                }
+                if !tool_calls.is_empty() {
+                    value["tool_calls"] = Value::Array(tool_calls);
+                }
+                converted.push(value);
+            }
+            Message::ToolResult {
+                tool_name, content, ..
+            } => {
+                let mut texts = Vec::new();
+                let mut images = Vec::new();
+                for block in content {
+                    match block {
+                        ToolResultContentBlock::Text { text, .. } => texts.push(text.as_str()),
+                        ToolResultContentBlock::Image { data, .. } => images.push(data.clone()),
+                    }
+                }
+                let text = if texts.is_empty() {
+                    "(no text result)".to_string()
+                } else {
+                    texts.join("\n")
+                };
+                let mut value = json!({
+                    "role": "tool",
+                    "tool_name": tool_name,
+                    "content": text,
+                });
+                if !images.is_empty() {
+                    value["images"] = json!(images);
+                }
+                converted.push(value);
+            }
+        }
+    }
+
+    converted
+}
+
+fn convert_tools(tools: &[Tool]) -> Value {
+    Value::Array(
+        tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "type": "function",
+                    "function": {
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": tool.parameters,
+                    }
+                })
+            })
+            .collect(),
+    )
+}
+
+/// A local server needs no key, but one is forwarded when configured for
+/// proxies that put Ollama behind auth.
+fn resolve_api_key(provider: &str, options: Option<&StreamOptions>) -> Option<String> {
+    if let Some(api_key) = options.and_then(|opts| opts.api_key.clone()) {
+        if !api_key.trim().is_empty() {
+            return Some(api_key);
+        }
+    }
+    let provider_env = format!("{}_API_KEY", provider.to_uppercase().replace('-', "_"));
+    let api_key = [provider_env.as_str(), "OLLAMA_API_KEY"]
+        .into_iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.trim().is_empty());
+    api_key
+}
+
+fn ollama_base_url(base_url: &str) -> &str {
+    let trimmed = base_url.trim().trim_end_matches('/');
+    if trimmed.is_empty() {
+        return DEFAULT_OLLAMA_BASE_URL;
+    }
+    // Accept the OpenAI-compatible `/v1` URL users often copy from Ollama docs.
+    trimmed.strip_suffix("/v1").unwrap_or(trimmed)
+}
+
+fn map_ollama_stop_reason(reason: &str) -> StopReason {
+    match reason {
+        "length" => StopReason::Length,
+        _ => StopReason::Stop,
+    }
+}
+
+fn map_done_reason(reason: StopReason) -> Option<DoneReason> {
+    match reason {
+        StopReason::Stop => Some(DoneReason::Stop),
+        StopReason::Length => Some(DoneReason::Length),
+        StopReason::ToolUse => Some(DoneReason::ToolUse),
+        StopReason::Error | StopReason::Aborted => None,
+    }
+}
+
+fn update_usage_from_ollama(usage: &mut Usage, chunk: &Value) {
+    let input = chunk
+        .get("prompt_eval_count")
+        .and_then(Value::as_u64)
+        .unwrap_or(usage.input);
+    let output = chunk
+        .get("eval_count")
+        .and_then(Value::as_u64)
+        .unwrap_or(usage.output);
+    usage.input = input;
+    usage.output = output;
+    usage.total_tokens = input + output + usage.cache_read + usage.cache_write;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_text_tool_calls_accepts_known_tools_only() {
+        let tools = vec!["read".to_string()];
+        let calls = extract_text_tool_calls(
+            "<tool_call>\n{\"name\": \"read\", \"arguments\": {\"path\": \"a.rs\"}}\n</tool_call>",
+            &tools,
+        )
+        .expect("tagged tool call");
+        assert_eq!(calls, vec![("read".to_string(), json!({"path": "a.rs"}))]);
+
+        let calls = extract_text_tool_calls(
+            "```json\n{\"name\": \"read\", \"parameters\": \"{\\\"path\\\": \\\"b.rs\\\"}\"}\n```",
+            &tools,
+        )
+        .expect("fenced tool call");
+        assert_eq!(calls, vec![("read".to_string(), json!({"path": "b.rs"}))]);
+
+        assert!(extract_text_tool_calls("{\"name\": \"other\"}", &tools).is_none());
+        assert!(extract_text_tool_calls("{\"answer\": 42}", &tools).is_none());
+    }
+
+    #[test]
+    fn ollama_base_url_strips_openai_compat_suffix() {
+        assert_eq!(ollama_base_url(""), DEFAULT_OLLAMA_BASE_URL);
+        assert_eq!(
+            ollama_base_url("http://localhost:11434/v1/"),
+            "http://localhost:11434"
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::{Client, RequestBuilder};
+use serde_json::{json, Value};
+
+use super::common::{
+    empty_assistant_message, http_error_from_response, join_url, now_millis, shared_http_client,
+};
+use super::openai_completions::{
+    assistant_message_from_completion, build_openai_batch_body, resolve_api_key,
+};
+use crate::error::{classify_http_error, PiAiError, PiAiErrorCode};
+use crate::types::{AssistantMessage, Context, Model, StopReason, StreamOptions};
+
+const BATCH_ENDPOINT: &str = "/v1/chat/completions";
+
+/// One chat completions request submitted as part of a Batch API job.
+pub(crate) struct OpenAiBatchItem {
+    /// Position in the caller's batch; results are reported against it.
+    pub(crate) index: usize,
+    pub(crate) model: Model,
+    pub(crate) context: Context,
+    pub(crate) options: Option<StreamOptions>,
+}
+
+/// What a Batch API job is sent with: provider, base URL, API key and headers. Only items with
+/// equal keys can share a job.
+pub(crate) type OpenAiBatchKey = (String, String, Option<String>, Vec<(String, String)>);
+
+impl OpenAiBatchItem {
+    pub(crate) fn batch_key(&self) -> OpenAiBatchKey {
+        let options = self.options.as_ref();
+        let mut headers = options
+            .and_then(|options| options.headers.as_ref())
+            .map(|headers| {
+                headers
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        headers.sort();
+        (
+            self.model.provider.clone(),
+            self.model.base_url.clone(),
+            options.and_then(|options| options.api_key.clone()),
+            headers,
+        )
+    }
+}
+
+/// Submits `items` as one OpenAI Batch API job and waits for it to finish.
+///
+/// Every item must have the same [`OpenAiBatchItem::batch_key`]; mixed items are rejected
+/// rather than sent with another item's credentials. A job that cannot be created or that
+/// fails as a whole is an error; once it finishes, every item gets a message, with per-request
+/// failures reported as error messages.
+pub(crate) async fn run_openai_batch(
+    items: &[OpenAiBatchItem],
+    poll_interval: Duration,
+) -> Result<Vec<(usize, AssistantMessage)>, PiAiError> {
+    let Some(first) = items.first() else {
+        return Ok(Vec::new());
+    };
+    let key = first.batch_key();
+    if items.iter().any(|item| item.batch_key() != key) {
+        return Err(PiAiError::new(
+            PiAiErrorCode::ProviderProtocol,
+            "OpenAI batch items must share the provider, base URL, API key and headers",
+        ));
+    }
+    let job = BatchJob {
+        client: shared_http_client(&first.model.provider, &first.model.base_url),
+        base_url: &first.model.base_url,
+        authorization: format!(
+            "Bearer {}",
+            resolve_api_key(&first.model.provider, first.options.as_ref())?
+        ),
+        headers: first
+            .options
+            .as_ref()
+            .and_then(|options| options.headers.as_ref()),
+    };
+
+    let input = items
+        .iter()
+        .map(|item| {
+            json!({
+                "custom_id": custom_id(item.index),
+                "method": "POST",
+                "url": BATCH_ENDPOINT,
+                "body": build_openai_batch_body(&item.model, &item.context, item.options.as_ref()),
+            })
+            .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let input_file_id = job.upload_input(input).await?;
+
+    let created = job
+        .send_json(job.post("batches").json(&json!({
+            "input_file_id": input_file_id,
+            "endpoint": BATCH_ENDPOINT,
+            "completion_window": "24h",
+        })))
+        .await?;
+    let batch_id = string_field(&created, "id")?;
+    let batch = job.wait_for(&batch_id, poll_interval).await?;
+    let status = batch
+        .get("status")
+        .and_then(Value::as_str)
+        .unwrap_or("unknown")
+        .to_string();
+
+    let mut lines = Vec::new();
+    for file_field in ["output_file_id", "error_file_id"] {
+        if let Some(file_id) = batch.get(file_field).and_then(Value::as_str) {
+            lines.extend(job.download(file_id).await?);
+        }
+    }
+
+    let mut results = items
+        .iter()
+        .map(|item| (custom_id(item.index), (item, None)))
+        .collect::<HashMap<_, (&OpenAiBatchItem, Option<AssistantMessage>)>>();
+    for line in lines {
+        let Some((item, slot)) = line
+            .get("custom_id")
+            .and_then(Value::as_str)
+            .and_then(|custom_id| results.get_mut(custom_id))
+        else {
+            continue;
+        };
+        *slot = Some(result_message(&item.model, &line));
+    }
+
+    let mut messages = results
+        .into_values()
+        .map(|(item, message)| {
+            let message = message.unwrap_or_else(|| {
+                error_message(
+                    &item.model,
+                    PiAiError::new(
+                        PiAiErrorCode::ProviderProtocol,
+                        format!("OpenAI batch {batch_id} ended {status} without a result"),
+                    ),
+                )
+            });
+            (item.index, message)
+        })
+        .collect::<Vec<_>>();
+    messages.sort_by_key(|(index, _)| *index);
+    Ok(messages)
+}
+
+struct BatchJob<'a> {
+    client: Arc<Client>,
+    base_url: &'a str,
+    authorization: String,
+    headers: Option<&'a HashMap<String, String>>,
+}
+
+impl BatchJob<'_> {
+    fn authorize(&self, mut request: RequestBuilder) -> RequestBuilder {
+        request = request.header("Authorization", self.authorization.as_str());
+        for (name, value) in self.headers.into_iter().flatten() {
+            request = request.header(name, value);
+        }
+        request
+    }
+
+    fn post(&self, path: &str) -> RequestBuilder {
+        self.authorize(self.client.post(join_url(self.base_url, path)))
+    }
+
+    fn get(&self, path: &str) -> RequestBuilder {
+        self.authorize(self.client.get(join_url(self.base_url, path)))
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<String, PiAiError> {
+        let response = request.send().await.map_err(|error| {
+            PiAiError::new(
+                PiAiErrorCode::ProviderTransport,
+                format!("OpenAI batch transport failed: {error}"),
+            )
+        })?;
+        if !response.status().is_success() {
+            return Err(http_error_from_response("OpenAI batch", response).await);
+        }
+        response.text().await.map_err(|error| {
+            PiAiError::new(
+                PiAiErrorCode::ProviderTransport,
+                format!("OpenAI batch read failed: {error}"),
+            )
+        })
+    }
+
+    async fn send_json(&self, request: RequestBuilder) -> Result<Value, PiAiError> {
+        let body = self.send(request).await?;
+        serde_json::from_str(&body).map_err(|error| {
+            PiAiError::new(
+                PiAiErrorCode::ProviderProtocol,
+                format!("OpenAI batch response is not valid JSON: {error}"),
+            )
+            .with_details(json!({ "body": body }))
+        })
+    }
+
+    /// Uploads the JSONL input with `purpose=batch` and returns its file id.
+    async fn upload_input(&self, input: String) -> Result<String, PiAiError> {
+        let boundary = format!("pixy-batch-{}", now_millis());
+        let body = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"purpose\"\r\n\r\nbatch\r\n\
+             --{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"batch.jsonl\"\r\n\
+             Content-Type: application/jsonl\r\n\r\n{input}\r\n--{boundary}--\r\n"
+        );
+        let uploaded = self
+            .send_json(
+                self.post("files")
+                    .header(
+                        "Content-Type",
+                        format!("multipart/form-data; boundary={boundary}"),
+                    )
+                    .body(body),
+            )
+            .await?;
+        string_field(&uploaded, "id")
+    }
+
+    /// Polls the batch until it stops running. `failed` is an error; `completed`, `expired`
+    /// and `cancelled` jobs are returned so whatever finished can still be read.
+    async fn wait_for(&self, batch_id: &str, poll_interval: Duration) -> Result<Value, PiAiError> {
+        loop {
+            let batch = self
+                .send_json(self.get(&format!("batches/{batch_id}")))
+                .await?;
+            match batch.get("status").and_then(Value::as_str) {
+                Some("completed" | "expired" | "cancelled") => return Ok(batch),
+                Some("failed") => {
+                    return Err(PiAiError::new(
+                        PiAiErrorCode::ProviderProtocol,
+                        format!("OpenAI batch {batch_id} failed"),
+                    )
+                    .with_details(json!({ "errors": batch.get("errors") })))
+                }
+                _ => tokio::time::sleep(poll_interval).await,
+            }
+        }
+    }
+
+    async fn download(&self, file_id: &str) -> Result<Vec<Value>, PiAiError> {
+        let content = self
+            .send(self.get(&format!("files/{file_id}/content")))
+            .await?;
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+/// The message for one line of a batch output or error file.
+fn result_message(model: &Model, line: &Value) -> AssistantMessage {
+    if let Some(error) = line.get("error").filter(|error| !error.is_null()) {
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("request failed");
+        return error_message(
+            model,
+            PiAiError::new(
+                PiAiErrorCode::ProviderProtocol,
+                format!("OpenAI batch request failed: {message}"),
+            )
+            .with_details(error.clone()),
+        );
+    }
+
+    let response = line.get("response");
+    let status = response
+        .and_then(|response| response.get("status_code"))
+        .and_then(Value::as_u64)
+        .unwrap_or(0) as u16;
+    let body = response
+        .and_then(|response| response.get("body"))
+        .cloned()
+        .unwrap_or(Value::Null);
+    if status != 200 {
+        let text = body.to_string();
+        return error_message(
+            model,
+            PiAiError::new(
+                classify_http_error(status, &text),
+                format!("OpenAI batch HTTP {status}: {text}"),
+            )
+            .with_details(json!({ "status": status })),
+        );
+    }
+    assistant_message_from_completion(model, &body)
+        .unwrap_or_else(|error| error_message(model, error))
+}
+
+fn error_message(model: &Model, error: PiAiError) -> AssistantMessage {
+    let mut message = empty_assistant_message(model);
+    message.stop_reason = StopReason::Error;
+    message.error_message = Some(error.as_compact_json());
+    message
+}
+
+fn custom_id(index: usize) -> String {
+    format!("request-{index}")
+}
+
+fn string_field(value: &Value, field: &str) -> Result<String, PiAiError> {
+    value
+        .get(field)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| {
+            PiAiError::new(
+                PiAiErrorCode::ProviderProtocol,
+                format!("OpenAI batch response is missing `{field}`"),
+            )
+            .with_details(value.clone())
+        })
+}
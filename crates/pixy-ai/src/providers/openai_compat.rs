@@ -2,7 +2,8 @@ use std::collections::HashSet;
 use std::sync::{Arc, Mutex, OnceLock};
 
 use crate::api_registry::{ApiProvider, ApiProviderFuture};
-use crate::error::{PiAiError, PiAiErrorCode};
+use crate::error::PiAiError;
+use crate::transport_retry::http_error_status;
 use crate::types::{Context, Model, SimpleStreamOptions, StreamOptions};
 use crate::{ApiProviderRef, AssistantMessageEventStream};
 
@@ -172,5 +173,5 @@ fn as_openai_completions_model(mut model: Model) -> Model {
 }
 
 fn is_responses_404_error(error: &PiAiError) -> bool {
-    error.code == PiAiErrorCode::ProviderHttp && error.message.contains("HTTP 404")
+    http_error_status(error) == Some(404)
 }
@@ -3,17 +3,38 @@ use std::env;
 use std::io::{BufRead, BufReader, Read};
 use std::sync::Arc;
 
+use base64::prelude::{Engine as _, BASE64_STANDARD};
 use serde_json::{json, Map, Value};
 use tracing::info;
 
-use super::common::{debug_provider_event, empty_assistant_message, join_url, shared_http_client};
+/// Streamed audio output is only offered as raw 16-bit little-endian mono PCM at this rate.
+const STREAMED_AUDIO_SAMPLE_RATE: u32 = 24_000;
+
+use super::common::{
+    debug_provider_event, empty_assistant_message, fetch_response_body, has_extended_cache_hint,
+    join_url, pcm16_to_wav, push_usage_delta, shared_http_client, unsupported_audio_note,
+    SamplingFields,
+};
+use super::openai_vendors::{DEEPSEEK_API, OPENROUTER_API, XAI_API};
 use crate::api_registry::{ApiProvider, ApiProviderFuture};
 use crate::error::{PiAiError, PiAiErrorCode};
+use crate::message_adapter::{MessageAdapter, SystemRole};
+use crate::telemetry::record_upstream_provider;
 use crate::types::{
-    AssistantContentBlock, AssistantMessageEvent, Context, DoneReason, Message, Model,
-    SimpleStreamOptions, StopReason, StreamOptions, Tool, Usage, UserContent, UserContentBlock,
+    AssistantContentBlock, AssistantMessage, AssistantMessageEvent, Context, DoneReason, Message,
+    Model, SimpleStreamOptions, StopReason, StreamOptions, Tool, Usage, UserContent,
+    UserContentBlock,
+};
+use crate::{ApiProviderRef, AssistantMessageEventStream, PartialJsonParser};
+
+/// OpenAI has no `top_k`; send it through `extra_sampling` to compatible servers that do.
+const SAMPLING_FIELDS: SamplingFields = SamplingFields {
+    top_p: Some("top_p"),
+    top_k: None,
+    seed: Some("seed"),
+    frequency_penalty: Some("frequency_penalty"),
+    presence_penalty: Some("presence_penalty"),
 };
-use crate::{ApiProviderRef, AssistantMessageEventStream};
 
 struct OpenAICompletionsProvider;
 
@@ -56,43 +77,48 @@ pub async fn run_openai_completions(
     stream: AssistantMessageEventStream,
 ) -> Result<(), PiAiError> {
     let api_key = resolve_api_key(&model.provider, options.as_ref())?;
+    let endpoint = CompletionsEndpoint {
+        url: join_url(&model.base_url, "chat/completions"),
+        auth_header: Some(("Authorization", format!("Bearer {api_key}"))),
+    };
+    run_openai_completions_at(model, context, options, stream, endpoint).await
+}
+
+/// Where a chat completions request goes and how it authenticates.
+pub(super) struct CompletionsEndpoint {
+    pub(super) url: String,
+    /// Self-hosted servers may take no key at all.
+    pub(super) auth_header: Option<(&'static str, String)>,
+}
 
+pub(super) async fn run_openai_completions_at(
+    model: Model,
+    context: Context,
+    options: Option<StreamOptions>,
+    stream: AssistantMessageEventStream,
+    endpoint: CompletionsEndpoint,
+) -> Result<(), PiAiError> {
     let mut output = empty_assistant_message(&model);
     let payload = build_openai_payload(&model, &context, options.as_ref());
-    let endpoint = join_url(&model.base_url, "chat/completions");
-    let client = shared_http_client(&model.base_url);
+    let client = shared_http_client(&model.provider, &model.base_url);
 
     info!("OpenAI completions payload: {}", payload);
 
-    let execution = async {
+    let execution: Result<(), PiAiError> = async {
         let mut request = client
-            .post(endpoint.as_str())
-            .header("Authorization", format!("Bearer {api_key}"))
+            .post(endpoint.url.as_str())
             .header("Content-Type", "application/json");
+        if let Some((name, value)) = &endpoint.auth_header {
+            request = request.header(*name, value);
+        }
 
         if let Some(headers) = options.as_ref().and_then(|stream| stream.headers.as_ref()) {
             for (name, value) in headers {
                 request = request.header(name, value);
             }
         }
-        let response = request.json(&payload).send().await.map_err(|error| {
-            PiAiError::new(
-                PiAiErrorCode::ProviderTransport,
-                format!("OpenAI transport failed: {error}"),
-            )
-        })?;
-
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let body = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "unable to read error body".to_string());
-            return Err(PiAiError::new(
-                PiAiErrorCode::ProviderHttp,
-                format!("OpenAI HTTP {status}: {body}"),
-            ));
-        }
+        let body =
+            fetch_response_body("OpenAI", &model, request.json(&payload), options.as_ref()).await?;
 
         stream.push(AssistantMessageEvent::Start {
             partial: output.clone(),
@@ -100,14 +126,9 @@ pub async fn run_openai_completions(
 
         let mut text_block_index: Option<usize> = None;
         let mut thinking_block_index: Option<usize> = None;
-        let mut tool_arg_buffers: HashMap<usize, String> = HashMap::new();
+        let mut tool_arg_buffers: HashMap<usize, PartialJsonParser> = HashMap::new();
         let mut tool_block_indices: HashMap<usize, usize> = HashMap::new();
-        let body = response.text().await.map_err(|error| {
-            PiAiError::new(
-                PiAiErrorCode::ProviderTransport,
-                format!("OpenAI stream read failed: {error}"),
-            )
-        })?;
+        let mut audio_pcm: Vec<u8> = Vec::new();
         let mut reader = std::io::Cursor::new(body.into_bytes());
         process_sse_data_events(&mut reader, |data| {
             debug_provider_event("openai-completions", &data);
@@ -125,7 +146,12 @@ pub async fn run_openai_completions(
             })?;
 
             if let Some(usage_value) = chunk.get("usage") {
+                let previous = output.usage.clone();
                 update_usage_from_openai(&mut output.usage, usage_value);
+                push_usage_delta(&stream, &output, &previous);
+            }
+            if model.api == OPENROUTER_API {
+                apply_openrouter_routing(&mut output, &chunk);
             }
 
             let choice = chunk
@@ -183,9 +209,31 @@ pub async fn run_openai_completions(
                 }
             }
 
+            let audio_delta = delta
+                .and_then(|delta| delta.get("audio"))
+                .and_then(Value::as_object);
+            if let Some(chunk) = audio_delta
+                .and_then(|audio| audio.get("data"))
+                .and_then(Value::as_str)
+            {
+                let bytes = BASE64_STANDARD.decode(chunk).map_err(|error| {
+                    PiAiError::new(
+                        PiAiErrorCode::ProviderProtocol,
+                        format!("Invalid OpenAI audio chunk: {error}"),
+                    )
+                })?;
+                audio_pcm.extend_from_slice(&bytes);
+            }
+
+            // With audio output the reply text arrives as the audio transcript.
             if let Some(content_delta) = delta
                 .and_then(|delta| delta.get("content"))
                 .and_then(Value::as_str)
+                .or_else(|| {
+                    audio_delta
+                        .and_then(|audio| audio.get("transcript"))
+                        .and_then(Value::as_str)
+                })
             {
                 let idx = if let Some(idx) = text_block_index {
                     idx
@@ -261,7 +309,7 @@ pub async fn run_openai_completions(
                                 arguments: json!({}),
                                 thought_signature: None,
                             });
-                            tool_arg_buffers.insert(tool_provider_index, String::new());
+                            tool_arg_buffers.insert(tool_provider_index, PartialJsonParser::new());
                             tool_block_indices.insert(tool_provider_index, new_index);
                             stream.push(AssistantMessageEvent::ToolcallStart {
                                 content_index: new_index,
@@ -299,8 +347,8 @@ pub async fn run_openai_completions(
                             if !arg_delta.is_empty() {
                                 if let Some(buffer) = tool_arg_buffers.get_mut(&tool_provider_index)
                                 {
-                                    buffer.push_str(arg_delta);
-                                    *arguments = parse_partial_json(buffer);
+                                    buffer.push(arg_delta);
+                                    *arguments = buffer.value().unwrap_or_else(|| json!({}));
                                 }
                                 delta_text = arg_delta.to_string();
                             }
@@ -339,6 +387,13 @@ pub async fn run_openai_completions(
             });
         }
 
+        if !audio_pcm.is_empty() {
+            output.content.push(AssistantContentBlock::Audio {
+                data: BASE64_STANDARD.encode(pcm16_to_wav(&audio_pcm, STREAMED_AUDIO_SAMPLE_RATE)),
+                mime_type: "audio/wav".to_string(),
+            });
+        }
+
         let mut ordered_tool_indices = tool_block_indices.values().copied().collect::<Vec<_>>();
         ordered_tool_indices.sort_unstable();
         ordered_tool_indices.dedup();
@@ -421,28 +476,186 @@ fn build_openai_payload(
     let mut payload = json!({
         "model": model.id,
         "stream": true,
-        "messages": convert_messages(context),
+        "messages": convert_messages(&message_adapter(model), model, context),
     });
 
+    let compat = model.compat.clone().unwrap_or_default();
     if let Some(max_tokens) = options.and_then(|options| options.max_tokens) {
-        payload["max_tokens"] = json!(max_tokens);
+        payload[compat.max_tokens_field.as_str()] = json!(max_tokens);
+    }
+    if let Some(stop) = options
+        .map(|options| &options.stop_sequences)
+        .filter(|stop| !stop.is_empty())
+    {
+        payload[compat.stop_field.as_str()] = json!(stop);
+    }
+    if compat.stream_options {
+        payload["stream_options"] = json!({ "include_usage": true });
     }
     if let Some(temperature) = options.and_then(|options| options.temperature) {
         payload["temperature"] = json!(temperature);
     }
+    if let Some(fields) = payload.as_object_mut() {
+        SAMPLING_FIELDS.insert(fields, options);
+    }
     if let Some(tools) = &context.tools {
         payload["tools"] = convert_tools(tools);
+        if compat.tool_choice {
+            payload["tool_choice"] = json!("auto");
+        }
+    }
+    // Prefix caching is automatic; an extended hint only asks for longer retention.
+    if has_extended_cache_hint(context) {
+        payload["prompt_cache_retention"] = json!("24h");
+    }
+    if let Some(format) = options.and_then(|options| options.response_format.as_ref()) {
+        payload["response_format"] = json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": format.name,
+                "schema": format.schema,
+                "strict": format.strict,
+            },
+        });
+    }
+    // DeepSeek's reasoning models always think and take no effort setting.
+    if model.reasoning && model.api != DEEPSEEK_API {
+        let effort = options
+            .and_then(|options| options.thinking_budget)
+            .map(crate::types::ThinkingLevel::from_budget_tokens)
+            .or_else(|| model.reasoning_effort.clone());
+        if let Some(effort) = effort {
+            payload["reasoning_effort"] = json!(thinking_level_to_effort(model, &effort));
+        }
     }
-    if model.reasoning {
-        if let Some(effort) = model.reasoning_effort.as_ref() {
-            payload["reasoning_effort"] = json!(thinking_level_to_effort(model, effort));
+    if model.api == OPENROUTER_API {
+        if let Some(options) = options {
+            if !options.fallback_models.is_empty() {
+                payload["models"] = json!(options.fallback_models);
+            }
+            if !options.provider_preferences.is_empty() {
+                payload["provider"] = Value::Object(options.provider_preferences.clone());
+            }
         }
+        // Asks for the billed cost in the final usage chunk.
+        payload["usage"] = json!({ "include": true });
     }
+    if let Some(voice) = options.and_then(|options| options.audio_voice.as_deref()) {
+        payload["modalities"] = json!(["text", "audio"]);
+        payload["audio"] = json!({ "voice": voice, "format": "pcm16" });
+    }
+
+    payload
+}
 
+/// The non-streaming request body for one line of a Batch API input file.
+pub(super) fn build_openai_batch_body(
+    model: &Model,
+    context: &Context,
+    options: Option<&StreamOptions>,
+) -> Value {
+    let mut payload = build_openai_payload(model, context, options);
+    if let Some(payload) = payload.as_object_mut() {
+        payload.remove("stream");
+        payload.remove("stream_options");
+    }
     payload
 }
 
-fn apply_simple_reasoning_to_model(model: &mut Model, options: Option<&SimpleStreamOptions>) {
+/// Builds the final message from a non-streaming `chat.completion` body.
+pub(super) fn assistant_message_from_completion(
+    model: &Model,
+    body: &Value,
+) -> Result<AssistantMessage, PiAiError> {
+    let mut output = empty_assistant_message(model);
+    let choice = body
+        .get("choices")
+        .and_then(Value::as_array)
+        .and_then(|choices| choices.first())
+        .ok_or_else(|| {
+            PiAiError::new(
+                PiAiErrorCode::ProviderProtocol,
+                "OpenAI completion missing choices[0]",
+            )
+            .with_details(json!({ "body": body }))
+        })?;
+
+    if let Some(usage) = body.get("usage") {
+        update_usage_from_openai(&mut output.usage, usage);
+    }
+    if let Some(finish_reason) = choice.get("finish_reason").and_then(Value::as_str) {
+        output.stop_reason = map_openai_stop_reason(finish_reason);
+    }
+
+    let message = choice.get("message");
+    let text_field = |name: &str| {
+        message
+            .and_then(|message| message.get(name))
+            .and_then(Value::as_str)
+            .filter(|text| !text.is_empty())
+    };
+    if let Some(thinking) = text_field("reasoning_content") {
+        output.content.push(AssistantContentBlock::Thinking {
+            thinking: thinking.to_string(),
+            thinking_signature: None,
+        });
+    }
+    if let Some(text) = text_field("content") {
+        output.content.push(AssistantContentBlock::Text {
+            text: text.to_string(),
+            text_signature: None,
+        });
+    }
+    let tool_calls = message
+        .and_then(|message| message.get("tool_calls"))
+        .and_then(Value::as_array);
+    for tool_call in tool_calls.into_iter().flatten() {
+        let function = tool_call.get("function");
+        output.content.push(AssistantContentBlock::ToolCall {
+            id: tool_call
+                .get("id")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            name: function
+                .and_then(|function| function.get("name"))
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            arguments: parse_tool_arguments_value(
+                function.and_then(|function| function.get("arguments")),
+            ),
+            thought_signature: None,
+        });
+    }
+
+    if output.stop_reason == StopReason::Error {
+        output.error_message = Some(
+            PiAiError::new(
+                PiAiErrorCode::ContentFiltered,
+                "OpenAI completion stopped by the content filter",
+            )
+            .as_compact_json(),
+        );
+    }
+    Ok(output)
+}
+
+/// `input_audio` format name for a MIME type, e.g. `audio/mpeg` -> `mp3`; `None` for formats
+/// the API does not take, which is everything but WAV and MP3.
+fn openai_audio_format(mime_type: &str) -> Option<&'static str> {
+    let subtype = mime_type.strip_prefix("audio/").unwrap_or(mime_type);
+    match subtype {
+        "mpeg" | "mp3" => Some("mp3"),
+        "wav" | "x-wav" | "wave" => Some("wav"),
+        _ => None,
+    }
+}
+
+pub(super) fn apply_simple_reasoning_to_model(
+    model: &mut Model,
+    options: Option<&SimpleStreamOptions>,
+) {
     if !model.reasoning {
         return;
     }
@@ -452,6 +665,13 @@ fn apply_simple_reasoning_to_model(model: &mut Model, options: Option<&SimpleStr
 }
 
 fn thinking_level_to_effort(model: &Model, level: &crate::types::ThinkingLevel) -> &'static str {
+    // Grok only knows `low` and `high`.
+    if model.api == XAI_API {
+        return match level {
+            crate::types::ThinkingLevel::Minimal | crate::types::ThinkingLevel::Low => "low",
+            _ => "high",
+        };
+    }
     let is_openai = model.name.to_lowercase().contains("openai");
     if !is_openai && level == &crate::types::ThinkingLevel::Xhigh {
         return "high";
@@ -466,17 +686,45 @@ fn thinking_level_to_effort(model: &Model, level: &crate::types::ThinkingLevel)
     }
 }
 
-fn convert_messages(context: &Context) -> Vec<Value> {
+/// OpenAI's reasoning models take instructions as `developer` messages; compatible servers
+/// only know `system`.
+fn message_adapter(model: &Model) -> MessageAdapter {
+    let system_role = if model.provider == "openai" && model.reasoning {
+        SystemRole::Developer
+    } else {
+        SystemRole::System
+    };
+    MessageAdapter::default().with_system_role(system_role)
+}
+
+fn convert_messages(adapter: &MessageAdapter, model: &Model, context: &Context) -> Vec<Value> {
+    let context = &adapter.apply(model, context);
     let mut messages = Vec::new();
 
     if let Some(system_prompt) = &context.system_prompt {
         messages.push(json!({
-            "role": "system",
+            "role": adapter.system_role.as_str(),
             "content": system_prompt,
         }));
     }
 
-    for message in &context.messages {
+    // DeepSeek wants the reasoning of the turn in progress back as `reasoning_content` so tool
+    // calls can continue it, and the reasoning of earlier turns left out.
+    let replay_reasoning_from = (model.api == DEEPSEEK_API).then(|| {
+        context
+            .messages
+            .iter()
+            .rposition(|message| matches!(message, Message::User { .. }))
+            .unwrap_or(0)
+    });
+
+    // Chat completions only accept text in tool messages, so images returned by tools are sent
+    // in a user message once the run of tool results ends.
+    let mut tool_images = Vec::new();
+    for (index, message) in context.messages.iter().enumerate() {
+        if !matches!(message, Message::ToolResult { .. }) {
+            flush_tool_result_images(&mut messages, &mut tool_images);
+        }
         match message {
             Message::User { content, .. } => match content {
                 UserContent::Text(text) => {
@@ -488,17 +736,29 @@ fn convert_messages(context: &Context) -> Vec<Value> {
                 UserContent::Blocks(blocks) => {
                     let converted = blocks
                         .iter()
-                        .filter_map(|block| match block {
-                            UserContentBlock::Text { text, .. } => Some(json!({
+                        .map(|block| match block {
+                            UserContentBlock::Text { text, .. } => json!({
                                 "type": "text",
                                 "text": text,
-                            })),
-                            UserContentBlock::Image { data, mime_type } => Some(json!({
+                            }),
+                            UserContentBlock::Image { data, mime_type } => json!({
                                 "type": "image_url",
                                 "image_url": {
                                     "url": format!("data:{mime_type};base64,{data}"),
                                 }
-                            })),
+                            }),
+                            UserContentBlock::Audio { data, mime_type } => {
+                                match openai_audio_format(mime_type) {
+                                    Some(format) => json!({
+                                        "type": "input_audio",
+                                        "input_audio": { "data": data, "format": format }
+                                    }),
+                                    None => json!({
+                                        "type": "text",
+                                        "text": unsupported_audio_note(mime_type),
+                                    }),
+                                }
+                            }
                         })
                         .collect::<Vec<_>>();
                     messages.push(json!({
@@ -509,12 +769,19 @@ fn convert_messages(context: &Context) -> Vec<Value> {
             },
             Message::Assistant { content, .. } => {
                 let mut text_parts = Vec::new();
+                let mut reasoning_parts = Vec::new();
                 let mut tool_calls = Vec::new();
                 for block in content {
                     match block {
                         AssistantContentBlock::Text { text, .. } => text_parts.push(text.clone()),
                         AssistantContentBlock::Thinking { thinking, .. } => {
-                            text_parts.push(thinking.clone())
+                            match replay_reasoning_from {
+                                Some(from) if index > from => {
+                                    reasoning_parts.push(thinking.clone())
+                                }
+                                Some(_) => {}
+                                None => text_parts.push(thinking.clone()),
+                            }
                         }
                         AssistantContentBlock::ToolCall {
                             id,
@@ -531,6 +798,10 @@ fn convert_messages(context: &Context) -> Vec<Value> {
                                 }
                             }));
                         }
+                        // Assistant messages cannot carry images, and audio is replayed
+                        // through its transcript text block.
+                        AssistantContentBlock::Image { .. }
+                        | AssistantContentBlock::Audio { .. } => {}
                     }
                 }
 
@@ -541,6 +812,9 @@ fn convert_messages(context: &Context) -> Vec<Value> {
                 if !tool_calls.is_empty() {
                     assistant_message["tool_calls"] = Value::Array(tool_calls);
                 }
+                if !reasoning_parts.is_empty() {
+                    assistant_message["reasoning_content"] = json!(reasoning_parts.join("\n"));
+                }
                 messages.push(assistant_message);
             }
             Message::ToolResult {
@@ -565,13 +839,40 @@ fn convert_messages(context: &Context) -> Vec<Value> {
                     "name": tool_name,
                     "content": if text.is_empty() { "(no text result)" } else { &text },
                 }));
+                tool_images.extend(content.iter().filter_map(|block| match block {
+                    crate::types::ToolResultContentBlock::Image { data, mime_type } => {
+                        Some(json!({
+                            "type": "image_url",
+                            "image_url": {
+                                "url": format!("data:{mime_type};base64,{data}"),
+                            }
+                        }))
+                    }
+                    crate::types::ToolResultContentBlock::Text { .. } => None,
+                }));
             }
         }
     }
+    flush_tool_result_images(&mut messages, &mut tool_images);
 
     messages
 }
 
+fn flush_tool_result_images(messages: &mut Vec<Value>, images: &mut Vec<Value>) {
+    if images.is_empty() {
+        return;
+    }
+    let mut content = vec![json!({
+        "type": "text",
+        "text": "Images returned by the tool calls above:",
+    })];
+    content.append(images);
+    messages.push(json!({
+        "role": "user",
+        "content": content,
+    }));
+}
+
 fn convert_tools(tools: &[Tool]) -> Value {
     Value::Array(
         tools
@@ -590,7 +891,10 @@ fn convert_tools(tools: &[Tool]) -> Value {
     )
 }
 
-fn resolve_api_key(provider: &str, options: Option<&StreamOptions>) -> Result<String, PiAiError> {
+pub(super) fn resolve_api_key(
+    provider: &str,
+    options: Option<&StreamOptions>,
+) -> Result<String, PiAiError> {
     if let Some(api_key) = options.and_then(|options| options.api_key.clone()) {
         return Ok(api_key);
     }
@@ -682,13 +986,13 @@ fn map_done_reason(reason: StopReason) -> Option<DoneReason> {
     }
 }
 
-fn parse_partial_json(buffer: &str) -> Value {
+fn parse_json_or_empty(buffer: &str) -> Value {
     serde_json::from_str::<Value>(buffer).unwrap_or_else(|_| Value::Object(Map::new()))
 }
 
 fn parse_tool_arguments_value(value: Option<&Value>) -> Value {
     match value {
-        Some(Value::String(raw)) => parse_partial_json(raw),
+        Some(Value::String(raw)) => parse_json_or_empty(raw),
         Some(Value::Null) | None => Value::Object(Map::new()),
         Some(other) => other.clone(),
     }
@@ -743,6 +1047,19 @@ fn merge_stream_delta(current: &mut String, incoming: &str) -> Option<String> {
     Some(incoming.to_string())
 }
 
+/// Records which model and upstream provider OpenRouter routed the request to, so the answer
+/// is priced and attributed by what actually served it.
+fn apply_openrouter_routing(output: &mut AssistantMessage, chunk: &Value) {
+    if let Some(served_model) = chunk.get("model").and_then(Value::as_str) {
+        if !served_model.is_empty() {
+            output.model = served_model.to_string();
+        }
+    }
+    if let Some(upstream) = chunk.get("provider").and_then(Value::as_str) {
+        record_upstream_provider(upstream);
+    }
+}
+
 fn update_usage_from_openai(usage: &mut Usage, value: &Value) {
     let prompt_tokens = value
         .get("prompt_tokens")
@@ -752,18 +1069,30 @@ fn update_usage_from_openai(usage: &mut Usage, value: &Value) {
         .get("completion_tokens")
         .and_then(Value::as_u64)
         .unwrap_or(usage.output);
+    // DeepSeek reports cache hits on their own instead of in `prompt_tokens_details`.
     let cached_tokens = value
         .get("prompt_tokens_details")
         .and_then(Value::as_object)
         .and_then(|details| details.get("cached_tokens"))
+        .or_else(|| value.get("prompt_cache_hit_tokens"))
         .and_then(Value::as_u64)
         .unwrap_or(usage.cache_read);
+    let reasoning_tokens = value
+        .get("completion_tokens_details")
+        .and_then(|details| details.get("reasoning_tokens"))
+        .and_then(Value::as_u64)
+        .unwrap_or(usage.reasoning);
 
     usage.input = prompt_tokens.saturating_sub(cached_tokens);
     usage.output = completion_tokens;
+    usage.reasoning = reasoning_tokens;
     usage.cache_read = cached_tokens;
     usage.cache_write = 0;
     usage.total_tokens = usage.input + usage.output + usage.cache_read + usage.cache_write;
+    // OpenRouter bills in USD credits and reports the total, which then replaces our estimate.
+    if let Some(cost) = value.get("cost").and_then(Value::as_f64) {
+        usage.cost.total = cost;
+    }
 }
 
 #[cfg(test)]
@@ -791,6 +1120,9 @@ mod tests {
             },
             context_window: 200_000,
             max_tokens: 8_192,
+            deployment: None,
+            api_version: None,
+            compat: None,
         }
     }
 
@@ -802,6 +1134,8 @@ mod tests {
                 timestamp: 0,
             }],
             tools: None,
+            system_prompt_cache: None,
+            branch: None,
         }
     }
 
@@ -814,6 +1148,233 @@ mod tests {
         assert_eq!(payload["reasoning_effort"], "high");
     }
 
+    #[test]
+    fn grok_and_deepseek_payloads_follow_their_reasoning_settings() {
+        let grok = Model {
+            api: XAI_API.to_string(),
+            provider: "xai".to_string(),
+            reasoning_effort: Some(ThinkingLevel::Medium),
+            ..sample_model()
+        };
+        let payload = build_openai_payload(&grok, &sample_context(), None);
+        assert_eq!(payload["reasoning_effort"], "high");
+
+        let deepseek = Model {
+            api: DEEPSEEK_API.to_string(),
+            provider: "deepseek".to_string(),
+            ..sample_model()
+        };
+        let payload = build_openai_payload(&deepseek, &sample_context(), None);
+        assert!(payload.get("reasoning_effort").is_none());
+    }
+
+    #[test]
+    fn deepseek_replays_only_the_reasoning_of_the_current_turn() {
+        let assistant = |thinking: &str, text: &str| Message::Assistant {
+            content: vec![
+                AssistantContentBlock::Thinking {
+                    thinking: thinking.to_string(),
+                    thinking_signature: None,
+                },
+                AssistantContentBlock::Text {
+                    text: text.to_string(),
+                    text_signature: None,
+                },
+            ],
+            api: DEEPSEEK_API.to_string(),
+            provider: "deepseek".to_string(),
+            model: "deepseek-reasoner".to_string(),
+            usage: Usage {
+                input: 0,
+                output: 0,
+                cache_read: 0,
+                cache_write: 0,
+                reasoning: 0,
+                total_tokens: 0,
+                cost: Cost {
+                    input: 0.0,
+                    output: 0.0,
+                    cache_read: 0.0,
+                    cache_write: 0.0,
+                    total: 0.0,
+                },
+            },
+            stop_reason: StopReason::Stop,
+            error_message: None,
+            timestamp: 0,
+            stats: None,
+        };
+        let user = |text: &str| Message::User {
+            content: UserContent::Text(text.to_string()),
+            timestamp: 0,
+        };
+        let context = Context {
+            messages: vec![
+                user("first"),
+                assistant("old plan", "done"),
+                user("second"),
+                assistant("new plan", "checking"),
+            ],
+            ..sample_context()
+        };
+        let deepseek = Model {
+            api: DEEPSEEK_API.to_string(),
+            provider: "deepseek".to_string(),
+            ..sample_model()
+        };
+
+        let payload = build_openai_payload(&deepseek, &context, None);
+        let messages = payload["messages"].as_array().expect("messages");
+        assert_eq!(messages[2]["content"], "done");
+        assert!(messages[2].get("reasoning_content").is_none());
+        assert_eq!(messages[4]["content"], "checking");
+        assert_eq!(messages[4]["reasoning_content"], "new plan");
+    }
+
+    #[test]
+    fn deepseek_cache_hits_count_as_cache_reads() {
+        let mut usage = empty_assistant_message(&sample_model()).usage;
+        update_usage_from_openai(
+            &mut usage,
+            &json!({
+                "prompt_tokens": 100,
+                "completion_tokens": 20,
+                "prompt_cache_hit_tokens": 60,
+                "prompt_cache_miss_tokens": 40,
+            }),
+        );
+        assert_eq!((usage.input, usage.cache_read, usage.output), (40, 60, 20));
+    }
+
+    #[test]
+    fn openai_payload_passes_supported_sampling_parameters() {
+        let mut extra_sampling = serde_json::Map::new();
+        extra_sampling.insert("repetition_penalty".to_string(), json!(1.1));
+        let options = StreamOptions {
+            temperature: Some(0.0),
+            top_p: Some(0.9),
+            top_k: Some(40),
+            seed: Some(42),
+            frequency_penalty: Some(0.5),
+            extra_sampling,
+            ..StreamOptions::default()
+        };
+
+        let payload = build_openai_payload(&sample_model(), &sample_context(), Some(&options));
+        assert_eq!(payload["temperature"], 0.0);
+        assert_eq!(payload["top_p"], 0.9);
+        assert_eq!(payload["seed"], 42);
+        assert_eq!(payload["frequency_penalty"], 0.5);
+        assert_eq!(payload["repetition_penalty"], 1.1);
+        assert!(payload.get("top_k").is_none());
+        assert!(payload.get("presence_penalty").is_none());
+    }
+
+    #[test]
+    fn openai_reasoning_models_take_the_system_prompt_as_developer() {
+        let model = sample_model();
+        let payload = build_openai_payload(&model, &sample_context(), None);
+        assert_eq!(payload["messages"][0]["role"], "developer");
+
+        let compatible = Model {
+            provider: "deepseek".to_string(),
+            ..sample_model()
+        };
+        let payload = build_openai_payload(&compatible, &sample_context(), None);
+        assert_eq!(payload["messages"][0]["role"], "system");
+    }
+
+    #[test]
+    fn tool_result_images_follow_the_tool_messages_as_a_user_message() {
+        let tool_result = |id: &str, image: bool| {
+            let mut content = vec![crate::types::ToolResultContentBlock::Text {
+                text: format!("result {id}"),
+                text_signature: None,
+            }];
+            if image {
+                content.push(crate::types::ToolResultContentBlock::Image {
+                    data: "aGk=".to_string(),
+                    mime_type: "image/png".to_string(),
+                });
+            }
+            Message::ToolResult {
+                tool_call_id: id.to_string(),
+                tool_name: "read_image".to_string(),
+                content,
+                details: None,
+                is_error: false,
+                timestamp: 0,
+            }
+        };
+        let mut context = sample_context();
+        context
+            .messages
+            .extend([tool_result("a", true), tool_result("b", false)]);
+
+        let messages = convert_messages(&MessageAdapter::default(), &sample_model(), &context);
+        let roles = messages
+            .iter()
+            .map(|message| message["role"].as_str().unwrap_or_default())
+            .collect::<Vec<_>>();
+        assert_eq!(roles, vec!["system", "user", "tool", "tool", "user"]);
+        assert_eq!(messages[2]["content"], "result a");
+        assert_eq!(
+            messages[4]["content"][1]["image_url"]["url"],
+            "data:image/png;base64,aGk="
+        );
+    }
+
+    #[test]
+    fn audio_input_and_voice_output_shape_the_payload() {
+        let mut context = sample_context();
+        context.messages = vec![Message::User {
+            content: UserContent::Blocks(vec![UserContentBlock::Audio {
+                data: "UklGRg==".to_string(),
+                mime_type: "audio/x-wav".to_string(),
+            }]),
+            timestamp: 0,
+        }];
+        let options = StreamOptions {
+            audio_voice: Some("alloy".to_string()),
+            ..StreamOptions::default()
+        };
+
+        let payload = build_openai_payload(&sample_model(), &context, Some(&options));
+        assert_eq!(
+            payload["messages"][1]["content"][0],
+            json!({
+                "type": "input_audio",
+                "input_audio": { "data": "UklGRg==", "format": "wav" }
+            })
+        );
+        assert_eq!(payload["modalities"], json!(["text", "audio"]));
+        assert_eq!(
+            payload["audio"],
+            json!({ "voice": "alloy", "format": "pcm16" })
+        );
+
+        let payload = build_openai_payload(&sample_model(), &context, None);
+        assert!(payload.get("modalities").is_none());
+    }
+
+    #[test]
+    fn audio_in_formats_the_api_does_not_take_becomes_a_note() {
+        let mut context = sample_context();
+        context.messages = vec![Message::User {
+            content: UserContent::Blocks(vec![UserContentBlock::Audio {
+                data: "T2dnUw==".to_string(),
+                mime_type: "audio/ogg".to_string(),
+            }]),
+            timestamp: 0,
+        }];
+
+        let payload = build_openai_payload(&sample_model(), &context, None);
+        assert_eq!(
+            payload["messages"][1]["content"][0],
+            json!({ "type": "text", "text": unsupported_audio_note("audio/ogg") })
+        );
+    }
+
     #[test]
     fn simple_options_reasoning_overrides_model_reasoning_effort() {
         let mut model = sample_model();
@@ -953,8 +1514,68 @@ mod tests {
 
     #[test]
     fn openai_client_is_reused_across_requests() {
-        let first = shared_http_client("https://api.openai.com/v1");
-        let second = shared_http_client("https://api.openai.com/v1");
-        assert!(std::ptr::eq(first, second));
+        let first = shared_http_client("openai", "https://api.openai.com/v1");
+        let second = shared_http_client("openai", "https://api.openai.com/v1");
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+
+    #[test]
+    fn completion_body_converts_to_final_message() {
+        let body = json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": "Checking.",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "read", "arguments": "{\"path\":\"a.rs\"}" },
+                    }],
+                },
+                "finish_reason": "tool_calls",
+            }],
+            "usage": { "prompt_tokens": 12, "completion_tokens": 5 },
+        });
+
+        let message = assistant_message_from_completion(&sample_model(), &body).expect("message");
+        assert_eq!(message.stop_reason, StopReason::ToolUse);
+        assert_eq!(message.usage.input, 12);
+        assert_eq!(message.usage.output, 5);
+        assert_eq!(
+            message.content,
+            vec![
+                AssistantContentBlock::Text {
+                    text: "Checking.".to_string(),
+                    text_signature: None,
+                },
+                AssistantContentBlock::ToolCall {
+                    id: "call_1".to_string(),
+                    name: "read".to_string(),
+                    arguments: json!({ "path": "a.rs" }),
+                    thought_signature: None,
+                },
+            ]
+        );
+        let context = Context {
+            system_prompt: None,
+            messages: Vec::new(),
+            tools: None,
+            system_prompt_cache: None,
+            branch: None,
+        };
+        let batch_body = build_openai_batch_body(&sample_model(), &context, None);
+        assert!(batch_body.get("stream").is_none());
+    }
+
+    #[test]
+    fn pcm16_to_wav_writes_a_playable_header() {
+        let wav = pcm16_to_wav(&[1, 0, 2, 0], 24_000);
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(wav[4..8].try_into().unwrap()), 40);
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), 24_000);
+        assert_eq!(&wav[36..40], b"data");
+        assert_eq!(u32::from_le_bytes(wav[40..44].try_into().unwrap()), 4);
+        assert_eq!(&wav[44..], &[1, 0, 2, 0]);
     }
 }
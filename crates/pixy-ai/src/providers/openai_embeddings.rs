@@ -0,0 +1,115 @@
+use std::env;
+
+use serde_json::{json, Value};
+
+use super::common::{http_error_from_response, join_url, shared_http_client};
+use crate::embeddings::{EmbeddingModel, EmbeddingOptions};
+use crate::error::{PiAiError, PiAiErrorCode};
+
+pub(crate) async fn run_openai_embeddings(
+    model: &EmbeddingModel,
+    inputs: &[String],
+    options: Option<&EmbeddingOptions>,
+) -> Result<Vec<Vec<f32>>, PiAiError> {
+    let api_key = resolve_api_key(&model.provider, options)?;
+    let endpoint = join_url(&model.base_url, "embeddings");
+    let client = shared_http_client(&model.provider, &model.base_url);
+
+    let mut payload = json!({
+        "model": model.id,
+        "input": inputs,
+    });
+    if let Some(dimensions) = model.dimensions {
+        payload["dimensions"] = json!(dimensions);
+    }
+
+    let mut request = client
+        .post(endpoint.as_str())
+        .header("Authorization", format!("Bearer {api_key}"))
+        .header("Content-Type", "application/json");
+    if let Some(headers) = options.and_then(|options| options.headers.as_ref()) {
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+    }
+
+    let response = request.json(&payload).send().await.map_err(|error| {
+        PiAiError::new(
+            PiAiErrorCode::ProviderTransport,
+            format!("Embeddings transport failed: {error}"),
+        )
+    })?;
+    if !response.status().is_success() {
+        return Err(http_error_from_response("Embeddings", response).await);
+    }
+
+    let body: Value = response.json().await.map_err(|error| {
+        PiAiError::new(
+            PiAiErrorCode::ProviderProtocol,
+            format!("Embeddings response is not valid JSON: {error}"),
+        )
+    })?;
+    parse_embeddings_response(&body, inputs.len())
+}
+
+fn parse_embeddings_response(body: &Value, expected: usize) -> Result<Vec<Vec<f32>>, PiAiError> {
+    let protocol_error = |message: String| PiAiError::new(PiAiErrorCode::ProviderProtocol, message);
+    let data = body
+        .get("data")
+        .and_then(Value::as_array)
+        .ok_or_else(|| protocol_error("Embeddings response is missing `data`".to_string()))?;
+
+    let mut vectors = vec![None; expected];
+    for (position, item) in data.iter().enumerate() {
+        let index = item
+            .get("index")
+            .and_then(Value::as_u64)
+            .map(|index| index as usize)
+            .unwrap_or(position);
+        let embedding = item
+            .get("embedding")
+            .and_then(Value::as_array)
+            .ok_or_else(|| protocol_error(format!("Embedding {index} is missing `embedding`")))?
+            .iter()
+            .map(|value| value.as_f64().map(|value| value as f32))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| protocol_error(format!("Embedding {index} has non-numeric values")))?;
+        let slot = vectors
+            .get_mut(index)
+            .ok_or_else(|| protocol_error(format!("Embedding index {index} is out of range")))?;
+        *slot = Some(embedding);
+    }
+
+    vectors
+        .into_iter()
+        .enumerate()
+        .map(|(index, vector)| {
+            vector.ok_or_else(|| protocol_error(format!("Embedding {index} is missing")))
+        })
+        .collect()
+}
+
+fn resolve_api_key(
+    provider: &str,
+    options: Option<&EmbeddingOptions>,
+) -> Result<String, PiAiError> {
+    if let Some(api_key) = options.and_then(|options| options.api_key.clone()) {
+        return Ok(api_key);
+    }
+
+    // Only the provider's own variable: `OPENAI_API_KEY` must not reach third-party hosts, and
+    // for `openai` it is already the provider's variable.
+    let provider_env = format!("{}_API_KEY", provider.to_uppercase().replace('-', "_"));
+    if let Ok(value) = env::var(&provider_env) {
+        if !value.trim().is_empty() {
+            return Ok(value);
+        }
+    }
+
+    Err(PiAiError::new(
+        PiAiErrorCode::ProviderAuthMissing,
+        format!(
+            "Missing API key for embedding provider '{provider}'. Pass `EmbeddingOptions.api_key` or set {provider_env}."
+        ),
+    ))
+}
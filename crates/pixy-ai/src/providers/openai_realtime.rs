@@ -0,0 +1,712 @@
+use std::collections::HashMap;
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+use super::common::{empty_assistant_message, join_url, pcm16_to_wav};
+use super::openai_completions::resolve_api_key;
+use super::websocket::{self, WsMessage, WsWriter};
+use crate::error::{PiAiError, PiAiErrorCode};
+use crate::realtime::{RealtimeEvent, RealtimeInput, RealtimeOptions, RealtimeSession};
+use crate::types::{
+    AssistantContentBlock, AssistantMessage, AssistantMessageEvent, DoneReason, ErrorReason, Model,
+    StopReason, StreamOptions,
+};
+use crate::PartialJsonParser;
+
+/// Realtime audio is 16-bit little-endian mono PCM at this rate, in both directions.
+const REALTIME_AUDIO_SAMPLE_RATE: u32 = 24_000;
+
+pub(crate) async fn connect_openai_realtime(
+    model: &Model,
+    options: RealtimeOptions,
+) -> Result<RealtimeSession, PiAiError> {
+    let api_key = resolve_api_key(
+        &model.provider,
+        Some(&StreamOptions {
+            api_key: options.api_key.clone(),
+            ..StreamOptions::default()
+        }),
+    )?;
+    let url = format!(
+        "{}?model={}",
+        join_url(&model.base_url, "realtime"),
+        model.id
+    );
+    let mut headers = vec![("Authorization".to_string(), format!("Bearer {api_key}"))];
+    headers.extend(options.headers.clone().unwrap_or_default());
+
+    let (mut reader, mut writer) =
+        websocket::connect("OpenAI realtime", &model.provider, &url, &headers).await?;
+    writer
+        .send_text(&session_update(&options).to_string())
+        .await?;
+
+    let (input_sender, mut inputs) = mpsc::unbounded_channel::<RealtimeInput>();
+    let (event_sender, events) = mpsc::unbounded_channel();
+    let (pong_sender, mut pongs) = mpsc::unbounded_channel::<Vec<u8>>();
+
+    tokio::spawn(async move {
+        loop {
+            let sent = tokio::select! {
+                input = inputs.recv() => match input {
+                    Some(input) => send_input(&mut writer, input).await,
+                    None => break,
+                },
+                Some(payload) = pongs.recv() => writer.send_pong(&payload).await,
+            };
+            if sent.is_err() {
+                return;
+            }
+        }
+        let _ = writer.close().await;
+    });
+
+    let model = model.clone();
+    tokio::spawn(async move {
+        let mut state = RealtimeState::new(model);
+        let closed = loop {
+            match reader.next_message().await {
+                Ok(Some(WsMessage::Text(text))) => {
+                    let Ok(event) = serde_json::from_str::<Value>(&text) else {
+                        continue;
+                    };
+                    for event in state.handle(&event) {
+                        if event_sender.send(event).is_err() {
+                            return;
+                        }
+                    }
+                }
+                Ok(Some(WsMessage::Ping(payload))) => {
+                    let _ = pong_sender.send(payload);
+                }
+                Ok(Some(WsMessage::Binary(_))) => {}
+                Ok(Some(WsMessage::Close)) | Ok(None) => {
+                    break PiAiError::new(
+                        PiAiErrorCode::ProviderTransport,
+                        "OpenAI realtime connection closed",
+                    )
+                }
+                Err(error) => break error,
+            };
+        };
+        if let Some(event) = state.fail_response(closed) {
+            let _ = event_sender.send(event);
+        }
+    });
+
+    Ok(RealtimeSession::new(input_sender, events))
+}
+
+fn session_update(options: &RealtimeOptions) -> Value {
+    let mut session = json!({
+        "type": "realtime",
+        "output_modalities": [if options.voice.is_some() { "audio" } else { "text" }],
+        "audio": {
+            "input": {
+                "format": { "type": "audio/pcm", "rate": REALTIME_AUDIO_SAMPLE_RATE },
+                "transcription": { "model": "whisper-1" },
+            },
+        },
+    });
+    if let Some(voice) = &options.voice {
+        session["audio"]["output"] = json!({
+            "format": { "type": "audio/pcm", "rate": REALTIME_AUDIO_SAMPLE_RATE },
+            "voice": voice,
+        });
+    }
+    if let Some(instructions) = &options.instructions {
+        session["instructions"] = json!(instructions);
+    }
+    if !options.tools.is_empty() {
+        session["tools"] = options
+            .tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "type": "function",
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameters": tool.parameters,
+                })
+            })
+            .collect();
+    }
+    json!({ "type": "session.update", "session": session })
+}
+
+/// The client events for `input`, in the order they are sent.
+fn client_events(input: RealtimeInput) -> Vec<Value> {
+    let create_response = json!({ "type": "response.create" });
+    match input {
+        RealtimeInput::Text(text) => vec![
+            json!({
+                "type": "conversation.item.create",
+                "item": {
+                    "type": "message",
+                    "role": "user",
+                    "content": [{ "type": "input_text", "text": text }],
+                },
+            }),
+            create_response,
+        ],
+        RealtimeInput::Audio(audio) => {
+            vec![json!({ "type": "input_audio_buffer.append", "audio": audio })]
+        }
+        RealtimeInput::CommitAudio => vec![
+            json!({ "type": "input_audio_buffer.commit" }),
+            create_response,
+        ],
+        RealtimeInput::ToolResult { call_id, output } => vec![
+            json!({
+                "type": "conversation.item.create",
+                "item": { "type": "function_call_output", "call_id": call_id, "output": output },
+            }),
+            create_response,
+        ],
+        RealtimeInput::CancelResponse => vec![json!({ "type": "response.cancel" })],
+    }
+}
+
+async fn send_input(writer: &mut WsWriter, input: RealtimeInput) -> Result<(), PiAiError> {
+    for event in client_events(input) {
+        writer.send_text(&event.to_string()).await?;
+    }
+    Ok(())
+}
+
+/// The answer being streamed, built from server events the way `stream()` builds a message.
+struct RealtimeResponse {
+    output: AssistantMessage,
+    text_index: Option<usize>,
+    tool_calls: HashMap<String, (usize, PartialJsonParser)>,
+    audio_pcm: Vec<u8>,
+}
+
+struct RealtimeState {
+    model: Model,
+    response: Option<RealtimeResponse>,
+}
+
+impl RealtimeState {
+    fn new(model: Model) -> Self {
+        Self {
+            model,
+            response: None,
+        }
+    }
+
+    fn handle(&mut self, event: &Value) -> Vec<RealtimeEvent> {
+        let event_type = event.get("type").and_then(Value::as_str).unwrap_or("");
+        let str_field = |name: &str| event.get(name).and_then(Value::as_str).unwrap_or("");
+        let mut events = Vec::new();
+        match event_type {
+            "response.created" => {
+                self.response = None;
+                self.response(&mut events);
+            }
+            "response.output_text.delta"
+            | "response.text.delta"
+            | "response.output_audio_transcript.delta"
+            | "response.audio_transcript.delta" => {
+                let response = self.response(&mut events);
+                let content_index = *response.text_index.get_or_insert_with(|| {
+                    response.output.content.push(AssistantContentBlock::Text {
+                        text: String::new(),
+                        text_signature: None,
+                    });
+                    let content_index = response.output.content.len() - 1;
+                    events.push(RealtimeEvent::Message(AssistantMessageEvent::TextStart {
+                        content_index,
+                        partial: response.output.clone(),
+                    }));
+                    content_index
+                });
+                let delta = str_field("delta");
+                if let Some(AssistantContentBlock::Text { text, .. }) =
+                    response.output.content.get_mut(content_index)
+                {
+                    text.push_str(delta);
+                }
+                events.push(RealtimeEvent::Message(AssistantMessageEvent::TextDelta {
+                    content_index,
+                    delta: delta.to_string(),
+                    partial: response.output.clone(),
+                }));
+            }
+            "response.output_audio.delta" | "response.audio.delta" => {
+                let delta = str_field("delta");
+                if let Ok(pcm) = BASE64_STANDARD.decode(delta) {
+                    self.response(&mut events).audio_pcm.extend(pcm);
+                }
+                events.push(RealtimeEvent::AudioDelta(delta.to_string()));
+            }
+            "response.output_item.added"
+                if event.pointer("/item/type").and_then(Value::as_str) == Some("function_call") =>
+            {
+                let item = &event["item"];
+                let response = self.response(&mut events);
+                end_text_block(response, &mut events);
+                response
+                    .output
+                    .content
+                    .push(AssistantContentBlock::ToolCall {
+                        id: item["call_id"].as_str().unwrap_or("").to_string(),
+                        name: item["name"].as_str().unwrap_or("").to_string(),
+                        arguments: json!({}),
+                        thought_signature: None,
+                    });
+                let content_index = response.output.content.len() - 1;
+                response.tool_calls.insert(
+                    item["id"].as_str().unwrap_or("").to_string(),
+                    (content_index, PartialJsonParser::new()),
+                );
+                events.push(RealtimeEvent::Message(
+                    AssistantMessageEvent::ToolcallStart {
+                        content_index,
+                        partial: response.output.clone(),
+                    },
+                ));
+            }
+            "response.function_call_arguments.delta" => {
+                let Some(response) = self.response.as_mut() else {
+                    return events;
+                };
+                let Some((content_index, parser)) =
+                    response.tool_calls.get_mut(str_field("item_id"))
+                else {
+                    return events;
+                };
+                let delta = str_field("delta");
+                parser.push(delta);
+                if let (Some(value), Some(AssistantContentBlock::ToolCall { arguments, .. })) = (
+                    parser.value(),
+                    response.output.content.get_mut(*content_index),
+                ) {
+                    *arguments = value;
+                }
+                events.push(RealtimeEvent::Message(
+                    AssistantMessageEvent::ToolcallDelta {
+                        content_index: *content_index,
+                        delta: delta.to_string(),
+                        partial: response.output.clone(),
+                    },
+                ));
+            }
+            "response.function_call_arguments.done" => {
+                let Some(response) = self.response.as_mut() else {
+                    return events;
+                };
+                let Some((content_index, _)) = response.tool_calls.get(str_field("item_id")) else {
+                    return events;
+                };
+                let content_index = *content_index;
+                let Some(AssistantContentBlock::ToolCall {
+                    id,
+                    name,
+                    arguments,
+                    ..
+                }) = response.output.content.get_mut(content_index)
+                else {
+                    return events;
+                };
+                *arguments = serde_json::from_str(str_field("arguments")).unwrap_or(json!({}));
+                let tool_call = json!({
+                    "type": "toolCall",
+                    "id": id,
+                    "name": name,
+                    "arguments": arguments,
+                });
+                events.push(RealtimeEvent::Message(AssistantMessageEvent::ToolcallEnd {
+                    content_index,
+                    tool_call,
+                    partial: response.output.clone(),
+                }));
+            }
+            "response.done" => {
+                let Some(mut response) = self.response.take() else {
+                    return events;
+                };
+                end_text_block(&mut response, &mut events);
+                if !response.audio_pcm.is_empty() {
+                    response.output.content.push(AssistantContentBlock::Audio {
+                        data: BASE64_STANDARD.encode(pcm16_to_wav(
+                            &response.audio_pcm,
+                            REALTIME_AUDIO_SAMPLE_RATE,
+                        )),
+                        mime_type: "audio/wav".to_string(),
+                    });
+                }
+                let details = &event["response"];
+                update_usage_from_realtime(&mut response.output, &details["usage"]);
+                let has_tool_calls = !response.tool_calls.is_empty();
+                let reason = match details["status"].as_str().unwrap_or("completed") {
+                    "failed" => {
+                        let message = details
+                            .pointer("/status_details/error/message")
+                            .and_then(Value::as_str)
+                            .unwrap_or("OpenAI realtime response failed");
+                        response.output.stop_reason = StopReason::Error;
+                        response.output.error_message = Some(
+                            PiAiError::new(PiAiErrorCode::ProviderProtocol, message)
+                                .as_compact_json(),
+                        );
+                        events.push(RealtimeEvent::Message(AssistantMessageEvent::Error {
+                            reason: ErrorReason::Error,
+                            error: response.output,
+                        }));
+                        return events;
+                    }
+                    "cancelled" => {
+                        response.output.stop_reason = StopReason::Aborted;
+                        events.push(RealtimeEvent::Message(AssistantMessageEvent::Error {
+                            reason: ErrorReason::Aborted,
+                            error: response.output,
+                        }));
+                        return events;
+                    }
+                    "incomplete" => DoneReason::Length,
+                    _ if has_tool_calls => DoneReason::ToolUse,
+                    _ => DoneReason::Stop,
+                };
+                response.output.stop_reason = match reason {
+                    DoneReason::Stop => StopReason::Stop,
+                    DoneReason::Length => StopReason::Length,
+                    DoneReason::ToolUse => StopReason::ToolUse,
+                };
+                events.push(RealtimeEvent::Message(AssistantMessageEvent::Done {
+                    reason,
+                    message: response.output,
+                }));
+            }
+            "conversation.item.input_audio_transcription.completed" => {
+                events.push(RealtimeEvent::InputTranscript(
+                    str_field("transcript").to_string(),
+                ));
+            }
+            "input_audio_buffer.speech_started" => events.push(RealtimeEvent::SpeechStarted),
+            "input_audio_buffer.speech_stopped" => events.push(RealtimeEvent::SpeechStopped),
+            "error" => {
+                let message = event
+                    .pointer("/error/message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("OpenAI realtime error");
+                events.push(RealtimeEvent::Error(PiAiError::new(
+                    PiAiErrorCode::ProviderProtocol,
+                    message,
+                )));
+            }
+            _ => {}
+        }
+        events
+    }
+
+    /// The answer in progress, started with its `Start` event if the server skipped
+    /// `response.created`.
+    fn response(&mut self, events: &mut Vec<RealtimeEvent>) -> &mut RealtimeResponse {
+        let model = &self.model;
+        self.response.get_or_insert_with(|| {
+            let output = empty_assistant_message(model);
+            events.push(RealtimeEvent::Message(AssistantMessageEvent::Start {
+                partial: output.clone(),
+            }));
+            RealtimeResponse {
+                output,
+                text_index: None,
+                tool_calls: HashMap::new(),
+                audio_pcm: Vec::new(),
+            }
+        })
+    }
+
+    /// Ends the answer in progress with `error`, when the connection goes away mid-answer.
+    fn fail_response(&mut self, error: PiAiError) -> Option<RealtimeEvent> {
+        let mut response = self.response.take()?;
+        response.output.stop_reason = StopReason::Error;
+        response.output.error_message = Some(error.as_compact_json());
+        Some(RealtimeEvent::Message(AssistantMessageEvent::Error {
+            reason: ErrorReason::Error,
+            error: response.output,
+        }))
+    }
+}
+
+fn end_text_block(response: &mut RealtimeResponse, events: &mut Vec<RealtimeEvent>) {
+    let Some(content_index) = response.text_index.take() else {
+        return;
+    };
+    if let Some(AssistantContentBlock::Text { text, .. }) =
+        response.output.content.get(content_index)
+    {
+        events.push(RealtimeEvent::Message(AssistantMessageEvent::TextEnd {
+            content_index,
+            content: text.clone(),
+            partial: response.output.clone(),
+        }));
+    }
+}
+
+fn update_usage_from_realtime(output: &mut AssistantMessage, usage: &Value) {
+    let input_tokens = usage["input_tokens"].as_u64().unwrap_or(0);
+    let cached_tokens = usage
+        .pointer("/input_token_details/cached_tokens")
+        .and_then(Value::as_u64)
+        .unwrap_or(0);
+    let usage_out = &mut output.usage;
+    usage_out.input = input_tokens.saturating_sub(cached_tokens);
+    usage_out.output = usage["output_tokens"].as_u64().unwrap_or(0);
+    usage_out.cache_read = cached_tokens;
+    usage_out.cache_write = 0;
+    usage_out.total_tokens = usage_out.input + usage_out.output + usage_out.cache_read;
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::*;
+    use crate::types::Cost;
+
+    fn sample_model() -> Model {
+        Model {
+            id: "gpt-realtime".to_string(),
+            name: "GPT Realtime".to_string(),
+            api: "openai-realtime".to_string(),
+            provider: "openai".to_string(),
+            base_url: "https://api.openai.com/v1".to_string(),
+            reasoning: false,
+            reasoning_effort: None,
+            input: vec!["text".to_string(), "audio".to_string()],
+            cost: Cost {
+                input: 0.0,
+                output: 0.0,
+                cache_read: 0.0,
+                cache_write: 0.0,
+                total: 0.0,
+            },
+            context_window: 32_000,
+            max_tokens: 4_096,
+            deployment: None,
+            api_version: None,
+            compat: None,
+        }
+    }
+
+    fn handle_all(state: &mut RealtimeState, events: &[Value]) -> Vec<RealtimeEvent> {
+        events
+            .iter()
+            .flat_map(|event| state.handle(event))
+            .collect()
+    }
+
+    #[test]
+    fn spoken_answers_stream_their_transcript_and_end_with_audio() {
+        let mut state = RealtimeState::new(sample_model());
+        let events = handle_all(
+            &mut state,
+            &[
+                json!({"type": "response.created"}),
+                json!({"type": "response.output_audio_transcript.delta", "delta": "Hi "}),
+                json!({"type": "response.output_audio.delta", "delta": BASE64_STANDARD.encode([1, 0, 2, 0])}),
+                json!({"type": "response.output_audio_transcript.delta", "delta": "there"}),
+                json!({"type": "response.done", "response": {
+                    "status": "completed",
+                    "usage": {"input_tokens": 12, "output_tokens": 5, "input_token_details": {"cached_tokens": 2}},
+                }}),
+            ],
+        );
+
+        assert!(matches!(
+            &events[0],
+            RealtimeEvent::Message(AssistantMessageEvent::Start { .. })
+        ));
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, RealtimeEvent::AudioDelta(_))));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            RealtimeEvent::Message(AssistantMessageEvent::TextEnd { content, .. }) if content == "Hi there"
+        )));
+        let Some(RealtimeEvent::Message(AssistantMessageEvent::Done { reason, message })) =
+            events.last()
+        else {
+            panic!("expected done, got {events:?}");
+        };
+        assert_eq!(*reason, DoneReason::Stop);
+        assert_eq!(message.usage.input, 10);
+        assert_eq!(message.usage.cache_read, 2);
+        assert!(matches!(
+            &message.content[1],
+            AssistantContentBlock::Audio { mime_type, .. } if mime_type == "audio/wav"
+        ));
+    }
+
+    #[test]
+    fn function_calls_end_the_answer_with_tool_use() {
+        let mut state = RealtimeState::new(sample_model());
+        let events = handle_all(
+            &mut state,
+            &[
+                json!({"type": "response.created"}),
+                json!({"type": "response.output_item.added", "item": {"type": "function_call", "id": "item_1", "call_id": "call_1", "name": "lookup"}}),
+                json!({"type": "response.function_call_arguments.delta", "item_id": "item_1", "delta": "{\"q\":"}),
+                json!({"type": "response.function_call_arguments.done", "item_id": "item_1", "arguments": "{\"q\":\"rust\"}"}),
+                json!({"type": "response.done", "response": {"status": "completed"}}),
+            ],
+        );
+
+        assert!(events.iter().any(|event| matches!(
+            event,
+            RealtimeEvent::Message(AssistantMessageEvent::ToolcallEnd { tool_call, .. })
+                if tool_call["arguments"] == json!({"q": "rust"}) && tool_call["id"] == "call_1"
+        )));
+        assert!(matches!(
+            events.last(),
+            Some(RealtimeEvent::Message(AssistantMessageEvent::Done {
+                reason: DoneReason::ToolUse,
+                ..
+            }))
+        ));
+    }
+
+    async fn read_client_text(socket: &mut TcpStream) -> Value {
+        let mut head = [0_u8; 2];
+        socket.read_exact(&mut head).await.expect("frame head");
+        let len = match head[1] & 0x7F {
+            126 => {
+                let mut len = [0_u8; 2];
+                socket.read_exact(&mut len).await.expect("frame length");
+                usize::from(u16::from_be_bytes(len))
+            }
+            len => usize::from(len),
+        };
+        let mut mask = [0_u8; 4];
+        socket.read_exact(&mut mask).await.expect("frame mask");
+        let mut payload = vec![0_u8; len];
+        socket
+            .read_exact(&mut payload)
+            .await
+            .expect("frame payload");
+        for (index, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[index % 4];
+        }
+        serde_json::from_slice(&payload).expect("client event json")
+    }
+
+    async fn write_server_text(socket: &mut TcpStream, event: Value) {
+        let payload = event.to_string();
+        let mut frame = vec![0x81, payload.len() as u8];
+        frame.extend_from_slice(payload.as_bytes());
+        socket.write_all(&frame).await.expect("write frame");
+    }
+
+    #[tokio::test]
+    async fn session_sends_text_and_streams_the_answer() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let address = listener.local_addr().expect("local addr");
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.expect("accept");
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                let mut byte = [0_u8; 1];
+                socket.read_exact(&mut byte).await.expect("read handshake");
+                request.push(byte[0]);
+            }
+            let request = String::from_utf8(request).expect("handshake text");
+            let key = request
+                .lines()
+                .find_map(|line| {
+                    line.strip_prefix("Sec-WebSocket-Key: ")
+                        .or_else(|| line.strip_prefix("sec-websocket-key: "))
+                })
+                .expect("websocket key");
+            let response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                websocket::accept_key(key)
+            );
+            socket
+                .write_all(response.as_bytes())
+                .await
+                .expect("write handshake");
+
+            let mut client_events = vec![];
+            for _ in 0..3 {
+                client_events.push(read_client_text(&mut socket).await);
+            }
+            for event in [
+                json!({"type": "response.created"}),
+                json!({"type": "response.output_text.delta", "delta": "Hello"}),
+                json!({"type": "response.done", "response": {"status": "completed"}}),
+            ] {
+                write_server_text(&mut socket, event).await;
+            }
+            (request, client_events)
+        });
+
+        let mut model = sample_model();
+        model.base_url = format!("http://{address}/v1");
+        let mut session = RealtimeSession::connect(
+            &model,
+            RealtimeOptions {
+                api_key: Some("test-key".to_string()),
+                ..RealtimeOptions::default()
+            },
+        )
+        .await
+        .expect("connect");
+        session.send_text("hi").expect("send text");
+
+        let mut events = vec![];
+        while let Some(event) = session.next_event().await {
+            let done = matches!(
+                event,
+                RealtimeEvent::Message(AssistantMessageEvent::Done { .. })
+            );
+            events.push(event);
+            if done {
+                break;
+            }
+        }
+        let (request, client_events) = server.await.expect("server task");
+
+        assert!(request.starts_with("GET /v1/realtime?model=gpt-realtime "));
+        assert!(request
+            .to_ascii_lowercase()
+            .contains("authorization: bearer test-key"));
+        let client_types = client_events
+            .iter()
+            .map(|event| event["type"].as_str().unwrap_or_default())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            client_types,
+            vec![
+                "session.update",
+                "conversation.item.create",
+                "response.create"
+            ]
+        );
+        assert!(matches!(
+            events.last(),
+            Some(RealtimeEvent::Message(AssistantMessageEvent::Done { message, .. }))
+                if matches!(&message.content[..], [AssistantContentBlock::Text { text, .. }] if text == "Hello")
+        ));
+    }
+
+    #[test]
+    fn text_input_asks_for_an_answer_and_voice_enables_audio_output() {
+        let events = client_events(RealtimeInput::Text("hello".to_string()));
+        assert_eq!(events[0]["item"]["content"][0]["text"], "hello");
+        assert_eq!(events[1]["type"], "response.create");
+
+        let update = session_update(&RealtimeOptions {
+            voice: Some("marin".to_string()),
+            instructions: Some("Be brief.".to_string()),
+            ..RealtimeOptions::default()
+        });
+        assert_eq!(update["session"]["output_modalities"], json!(["audio"]));
+        assert_eq!(update["session"]["audio"]["output"]["voice"], "marin");
+        assert_eq!(update["session"]["instructions"], "Be brief.");
+    }
+}
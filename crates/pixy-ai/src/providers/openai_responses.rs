@@ -4,14 +4,18 @@ use std::env;
 use std::io::{BufRead, BufReader, Read};
 use tracing::info;
 
-use super::common::{debug_provider_event, empty_assistant_message, join_url, shared_http_client};
+use super::common::{
+    debug_provider_event, empty_assistant_message, fetch_response_body, has_extended_cache_hint,
+    join_url, shared_http_client, unsupported_audio_note, SamplingFields,
+};
 use crate::error::{PiAiError, PiAiErrorCode};
+use crate::transport_retry::http_error_status;
 use crate::types::{
     AssistantContentBlock, AssistantMessage, AssistantMessageEvent, Context, DoneReason, Message,
     Model, SimpleStreamOptions, StopReason, StreamOptions, Tool, Usage, UserContent,
     UserContentBlock,
 };
-use crate::AssistantMessageEventStream;
+use crate::{AssistantMessageEventStream, PartialJsonParser};
 
 pub async fn run_openai_responses(
     model: Model,
@@ -24,7 +28,7 @@ pub async fn run_openai_responses(
     let mut output = empty_assistant_message(&model);
     let payload = build_openai_responses_payload(&model, &context, options.as_ref());
     let endpoint = join_url(&model.base_url, "responses");
-    let client = shared_http_client(&model.base_url);
+    let client = shared_http_client(&model.provider, &model.base_url);
 
     let execution = async {
         let mut request = client
@@ -37,39 +41,17 @@ pub async fn run_openai_responses(
                 request = request.header(key, value);
             }
         }
-        let response = request.json(&payload).send().await.map_err(|error| {
-            PiAiError::new(
-                PiAiErrorCode::ProviderTransport,
-                format!("OpenAI transport failed: {error}"),
-            )
-        })?;
-
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let body = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "unable to read error body".to_string());
-            return Err(PiAiError::new(
-                PiAiErrorCode::ProviderHttp,
-                format!("OpenAI HTTP {status}: {body}"),
-            ));
-        }
+        let body =
+            fetch_response_body("OpenAI", &model, request.json(&payload), options.as_ref()).await?;
 
         stream.push(AssistantMessageEvent::Start {
             partial: output.clone(),
         });
 
         let mut text_block_indices: HashMap<String, usize> = HashMap::new();
+        let mut reasoning_block_indices: HashMap<String, usize> = HashMap::new();
         let mut tool_block_indices: HashMap<String, usize> = HashMap::new();
-        let mut tool_arg_buffers: HashMap<String, String> = HashMap::new();
-
-        let body = response.text().await.map_err(|error| {
-            PiAiError::new(
-                PiAiErrorCode::ProviderTransport,
-                format!("OpenAI responses read failed: {error}"),
-            )
-        })?;
+        let mut tool_arg_buffers: HashMap<String, PartialJsonParser> = HashMap::new();
         let mut reader = std::io::Cursor::new(body.into_bytes());
         process_sse_data_events(&mut reader, |data| {
             handle_openai_responses_event(
@@ -77,11 +59,11 @@ pub async fn run_openai_responses(
                 &mut output,
                 &stream,
                 &mut text_block_indices,
+                &mut reasoning_block_indices,
                 &mut tool_block_indices,
                 &mut tool_arg_buffers,
             )
         })?;
-
         if output.content.is_empty() && output.stop_reason == StopReason::Stop {
             return Err(PiAiError::new(
                 PiAiErrorCode::ProviderProtocol,
@@ -135,8 +117,9 @@ fn handle_openai_responses_event(
     output: &mut AssistantMessage,
     stream: &AssistantMessageEventStream,
     text_block_indices: &mut HashMap<String, usize>,
+    reasoning_block_indices: &mut HashMap<String, usize>,
     tool_block_indices: &mut HashMap<String, usize>,
-    tool_arg_buffers: &mut HashMap<String, String>,
+    tool_arg_buffers: &mut HashMap<String, PartialJsonParser>,
 ) -> Result<bool, PiAiError> {
     debug_provider_event("openai-responses", &data);
     info!("OpenAI responses data: {}", data);
@@ -204,8 +187,9 @@ fn handle_openai_responses_event(
                         item.get("arguments").and_then(Value::as_str)
                     {
                         if !initial_arguments_text.is_empty() {
-                            tool_arg_buffers
-                                .insert(tool_key.clone(), initial_arguments_text.to_string());
+                            let mut buffer = PartialJsonParser::new();
+                            buffer.push(initial_arguments_text);
+                            tool_arg_buffers.insert(tool_key.clone(), buffer);
                         }
                     }
                     if let Some(AssistantContentBlock::ToolCall { arguments, .. }) =
@@ -219,9 +203,52 @@ fn handle_openai_responses_event(
                         partial: output.clone(),
                     });
                 }
+                "reasoning" => {
+                    let Some(item_id) = item.get("id").and_then(Value::as_str) else {
+                        return Ok(false);
+                    };
+                    let content_index = output.content.len();
+                    output.content.push(AssistantContentBlock::Thinking {
+                        thinking: String::new(),
+                        thinking_signature: None,
+                    });
+                    reasoning_block_indices.insert(item_id.to_string(), content_index);
+                    stream.push(AssistantMessageEvent::ThinkingStart {
+                        content_index,
+                        partial: output.clone(),
+                    });
+                }
                 _ => {}
             }
         }
+        "response.reasoning_summary_text.delta" | "response.reasoning_text.delta" => {
+            let Some(item_id) = event.get("item_id").and_then(Value::as_str) else {
+                return Ok(false);
+            };
+            let Some(delta) = event.get("delta").and_then(Value::as_str) else {
+                return Ok(false);
+            };
+            let Some(content_index) = reasoning_block_indices.get(item_id).copied() else {
+                return Ok(false);
+            };
+            push_thinking_delta(output, stream, content_index, delta.to_string());
+        }
+        "response.reasoning_summary_part.added" => {
+            // Summary parts arrive as separate paragraphs.
+            let Some(content_index) = event
+                .get("item_id")
+                .and_then(Value::as_str)
+                .and_then(|item_id| reasoning_block_indices.get(item_id).copied())
+            else {
+                return Ok(false);
+            };
+            if matches!(
+                output.content.get(content_index),
+                Some(AssistantContentBlock::Thinking { thinking, .. }) if !thinking.is_empty()
+            ) {
+                push_thinking_delta(output, stream, content_index, "\n\n".to_string());
+            }
+        }
         "response.output_text.delta" | "response.refusal.delta" => {
             let Some(item_id) = event.get("item_id").and_then(Value::as_str) else {
                 return Ok(false);
@@ -256,11 +283,11 @@ fn handle_openai_responses_event(
 
             if let Some(delta) = event.get("delta").and_then(Value::as_str) {
                 let buffer = tool_arg_buffers.entry(tool_key).or_default();
-                buffer.push_str(delta);
+                buffer.push(delta);
                 if let Some(AssistantContentBlock::ToolCall { arguments, .. }) =
                     output.content.get_mut(content_index)
                 {
-                    *arguments = parse_partial_json(buffer);
+                    *arguments = buffer.value().unwrap_or_else(|| Value::Object(Map::new()));
                 }
                 stream.push(AssistantMessageEvent::ToolcallDelta {
                     content_index,
@@ -311,7 +338,9 @@ fn handle_openai_responses_event(
                     *arg_json = parse_tool_arguments_value(Some(arguments_value));
                 }
                 if let Some(arguments) = arguments_value.as_str() {
-                    tool_arg_buffers.insert(tool_key, arguments.to_string());
+                    let mut buffer = PartialJsonParser::new();
+                    buffer.push(arguments);
+                    tool_arg_buffers.insert(tool_key, buffer);
                 } else {
                     tool_arg_buffers.remove(&tool_key);
                 }
@@ -389,7 +418,7 @@ fn handle_openai_responses_event(
                     };
                     let parsed_arguments = if let Some(buffer) = tool_arg_buffers.remove(&tool_key)
                     {
-                        parse_partial_json(&buffer)
+                        parse_json_or_empty(buffer.buffer())
                     } else if item.contains_key("arguments") {
                         parse_tool_arguments_value(item.get("arguments"))
                     } else {
@@ -428,6 +457,49 @@ fn handle_openai_responses_event(
                         partial: output.clone(),
                     });
                 }
+                "reasoning" => {
+                    let Some(item_id) = item.get("id").and_then(Value::as_str) else {
+                        return Ok(false);
+                    };
+                    let Some(content_index) = reasoning_block_indices.remove(item_id) else {
+                        return Ok(false);
+                    };
+                    if let Some(AssistantContentBlock::Thinking {
+                        thinking,
+                        thinking_signature,
+                    }) = output.content.get_mut(content_index)
+                    {
+                        if thinking.is_empty() {
+                            *thinking = reasoning_summary_text(item);
+                        }
+                        // The whole item, including encrypted content, is replayed on the next
+                        // request so the model keeps its reasoning across tool calls.
+                        *thinking_signature = Some(Value::Object(item.clone()).to_string());
+                        let content = thinking.clone();
+                        stream.push(AssistantMessageEvent::ThinkingEnd {
+                            content_index,
+                            content,
+                            partial: output.clone(),
+                        });
+                    }
+                }
+                "image_generation_call" => {
+                    let Some(data) = item
+                        .get("result")
+                        .and_then(Value::as_str)
+                        .filter(|data| !data.is_empty())
+                    else {
+                        return Ok(false);
+                    };
+                    let format = item
+                        .get("output_format")
+                        .and_then(Value::as_str)
+                        .unwrap_or("png");
+                    output.content.push(AssistantContentBlock::Image {
+                        data: data.to_string(),
+                        mime_type: format!("image/{format}"),
+                    });
+                }
                 _ => {}
             }
         }
@@ -481,6 +553,61 @@ fn handle_openai_responses_event(
     Ok(false)
 }
 
+fn push_thinking_delta(
+    output: &mut AssistantMessage,
+    stream: &AssistantMessageEventStream,
+    content_index: usize,
+    delta: String,
+) {
+    if delta.is_empty() {
+        return;
+    }
+    if let Some(AssistantContentBlock::Thinking { thinking, .. }) =
+        output.content.get_mut(content_index)
+    {
+        thinking.push_str(&delta);
+    }
+    stream.push(AssistantMessageEvent::ThinkingDelta {
+        content_index,
+        delta,
+        partial: output.clone(),
+    });
+}
+
+fn reasoning_summary_text(item: &Map<String, Value>) -> String {
+    item.get("summary")
+        .and_then(Value::as_array)
+        .map(|parts| {
+            parts
+                .iter()
+                .filter_map(|part| part.get("text").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        })
+        .unwrap_or_default()
+}
+
+/// A reasoning item captured from an earlier response, if `signature` holds one.
+fn replayable_reasoning_item(signature: Option<&String>) -> Option<Value> {
+    let mut item = serde_json::from_str::<Value>(signature?).ok()?;
+    if item.get("type").and_then(Value::as_str) != Some("reasoning") {
+        return None;
+    }
+    if let Some(item) = item.as_object_mut() {
+        item.remove("status");
+    }
+    Some(item)
+}
+
+/// The Responses API takes `top_p` but no seed or penalties.
+const SAMPLING_FIELDS: SamplingFields = SamplingFields {
+    top_p: Some("top_p"),
+    top_k: None,
+    seed: None,
+    frequency_penalty: None,
+    presence_penalty: None,
+};
+
 fn build_openai_responses_payload(
     model: &Model,
     context: &Context,
@@ -505,17 +632,43 @@ fn build_openai_responses_payload(
     if let Some(temperature) = options.and_then(|options| options.temperature) {
         payload["temperature"] = json!(temperature);
     }
+    if let Some(fields) = payload.as_object_mut() {
+        SAMPLING_FIELDS.insert(fields, options);
+    }
     if let Some(tools) = &context.tools {
         payload["tools"] = convert_responses_tools(tools);
     }
+    if options.is_some_and(|options| options.image_generation) {
+        let mut tools = payload["tools"].as_array().cloned().unwrap_or_default();
+        tools.push(json!({ "type": "image_generation" }));
+        payload["tools"] = Value::Array(tools);
+    }
+    // Prefix caching is automatic; an extended hint only asks for longer retention.
+    if has_extended_cache_hint(context) {
+        payload["prompt_cache_retention"] = json!("24h");
+    }
+    if let Some(format) = options.and_then(|options| options.response_format.as_ref()) {
+        payload["text"] = json!({
+            "format": {
+                "type": "json_schema",
+                "name": format.name,
+                "schema": format.schema,
+                "strict": format.strict,
+            },
+        });
+    }
     if model.reasoning {
-        let effort = model
-            .reasoning_effort
-            .as_ref()
-            .unwrap_or(&crate::types::ThinkingLevel::Medium);
+        let effort = options
+            .and_then(|options| options.thinking_budget)
+            .map(crate::types::ThinkingLevel::from_budget_tokens)
+            .or_else(|| model.reasoning_effort.clone())
+            .unwrap_or(crate::types::ThinkingLevel::Medium);
         payload["reasoning"] = json!({
-            "effort": thinking_level_to_effort(model, effort),
+            "effort": thinking_level_to_effort(model, &effort),
+            "summary": "auto",
         });
+        // Responses are not stored, so reasoning must come back encrypted to be replayed.
+        payload["include"] = json!(["reasoning.encrypted_content"]);
     }
 
     info!(
@@ -559,7 +712,13 @@ fn convert_responses_messages(context: &Context) -> Value {
     let mut messages = Vec::new();
     let mut synthetic_message_index = 0usize;
 
+    // function_call_output only carries text, so images returned by tools are sent in a user
+    // message once the run of tool results ends.
+    let mut tool_images = Vec::new();
     for message in &context.messages {
+        if !matches!(message, Message::ToolResult { .. }) {
+            flush_tool_result_images(&mut messages, &mut tool_images);
+        }
         match message {
             Message::User { content, .. } => match content {
                 UserContent::Text(text) => messages.push(json!({
@@ -572,16 +731,20 @@ fn convert_responses_messages(context: &Context) -> Value {
                 UserContent::Blocks(blocks) => {
                     let converted = blocks
                         .iter()
-                        .filter_map(|block| match block {
-                            UserContentBlock::Text { text, .. } => Some(json!({
+                        .map(|block| match block {
+                            UserContentBlock::Text { text, .. } => json!({
                                 "type": "input_text",
                                 "text": text,
-                            })),
-                            UserContentBlock::Image { data, mime_type } => Some(json!({
+                            }),
+                            UserContentBlock::Image { data, mime_type } => json!({
                                 "type": "input_image",
                                 "detail": "auto",
                                 "image_url": format!("data:{mime_type};base64,{data}"),
-                            })),
+                            }),
+                            UserContentBlock::Audio { mime_type, .. } => json!({
+                                "type": "input_text",
+                                "text": unsupported_audio_note(mime_type),
+                            }),
                         })
                         .collect::<Vec<_>>();
                     if !converted.is_empty() {
@@ -614,7 +777,11 @@ fn convert_responses_messages(context: &Context) -> Value {
                                 }],
                             }));
                         }
-                        AssistantContentBlock::Thinking { .. } => {}
+                        AssistantContentBlock::Thinking {
+                            thinking_signature, ..
+                        } => {
+                            messages.extend(replayable_reasoning_item(thinking_signature.as_ref()));
+                        }
                         AssistantContentBlock::ToolCall {
                             id,
                             name,
@@ -633,6 +800,10 @@ fn convert_responses_messages(context: &Context) -> Value {
                             }
                             messages.push(payload);
                         }
+                        // Generated images are not replayed as input; audio is replayed through
+                        // its transcript text block.
+                        AssistantContentBlock::Image { .. }
+                        | AssistantContentBlock::Audio { .. } => {}
                     }
                 }
             }
@@ -657,13 +828,39 @@ fn convert_responses_messages(context: &Context) -> Value {
                     "call_id": call_id,
                     "output": if text.is_empty() { "(no text result)" } else { text.as_str() },
                 }));
+                tool_images.extend(content.iter().filter_map(|block| match block {
+                    crate::types::ToolResultContentBlock::Image { data, mime_type } => {
+                        Some(json!({
+                            "type": "input_image",
+                            "detail": "auto",
+                            "image_url": format!("data:{mime_type};base64,{data}"),
+                        }))
+                    }
+                    crate::types::ToolResultContentBlock::Text { .. } => None,
+                }));
             }
         }
     }
+    flush_tool_result_images(&mut messages, &mut tool_images);
 
     Value::Array(messages)
 }
 
+fn flush_tool_result_images(messages: &mut Vec<Value>, images: &mut Vec<Value>) {
+    if images.is_empty() {
+        return;
+    }
+    let mut content = vec![json!({
+        "type": "input_text",
+        "text": "Images returned by the tool calls above:",
+    })];
+    content.append(images);
+    messages.push(json!({
+        "role": "user",
+        "content": content,
+    }));
+}
+
 fn convert_responses_tools(tools: &[Tool]) -> Value {
     Value::Array(
         tools
@@ -854,13 +1051,13 @@ fn map_done_reason(reason: StopReason) -> Option<DoneReason> {
     }
 }
 
-fn parse_partial_json(buffer: &str) -> Value {
+fn parse_json_or_empty(buffer: &str) -> Value {
     serde_json::from_str::<Value>(buffer).unwrap_or_else(|_| Value::Object(Map::new()))
 }
 
 fn parse_tool_arguments_value(value: Option<&Value>) -> Value {
     match value {
-        Some(Value::String(raw)) => parse_partial_json(raw),
+        Some(Value::String(raw)) => parse_json_or_empty(raw),
         Some(Value::Null) | None => Value::Object(Map::new()),
         Some(other) => other.clone(),
     }
@@ -881,6 +1078,11 @@ fn update_usage_from_openai_responses(usage: &mut Usage, value: &Value) {
         .and_then(|details| details.get("cached_tokens"))
         .and_then(Value::as_u64)
         .unwrap_or(usage.cache_read);
+    let reasoning_tokens = value
+        .get("output_tokens_details")
+        .and_then(|details| details.get("reasoning_tokens"))
+        .and_then(Value::as_u64)
+        .unwrap_or(usage.reasoning);
     let total_tokens = value
         .get("total_tokens")
         .and_then(Value::as_u64)
@@ -888,13 +1090,14 @@ fn update_usage_from_openai_responses(usage: &mut Usage, value: &Value) {
 
     usage.input = input_tokens.saturating_sub(cached_tokens);
     usage.output = output_tokens;
+    usage.reasoning = reasoning_tokens;
     usage.cache_read = cached_tokens;
     usage.cache_write = 0;
     usage.total_tokens = total_tokens;
 }
 
 fn is_provider_http_404(error: &PiAiError) -> bool {
-    error.code == PiAiErrorCode::ProviderHttp && error.message.contains("HTTP 404")
+    http_error_status(error) == Some(404)
 }
 
 #[cfg(test)]
@@ -922,6 +1125,9 @@ mod tests {
             },
             context_window: 200_000,
             max_tokens: 8_192,
+            deployment: None,
+            api_version: None,
+            compat: None,
         }
     }
 
@@ -933,6 +1139,8 @@ mod tests {
                 timestamp: 0,
             }],
             tools: None,
+            system_prompt_cache: None,
+            branch: None,
         }
     }
 
@@ -955,6 +1163,166 @@ mod tests {
         assert_eq!(payload["reasoning"]["effort"], "high");
     }
 
+    #[test]
+    fn openai_responses_payload_maps_thinking_budget_to_effort() {
+        let mut model = sample_model();
+        model.reasoning_effort = Some(ThinkingLevel::High);
+        let options = StreamOptions {
+            thinking_budget: Some(2_000),
+            ..StreamOptions::default()
+        };
+
+        let payload = build_openai_responses_payload(&model, &sample_context(), Some(&options));
+        assert_eq!(payload["reasoning"]["effort"], "low");
+    }
+
+    #[test]
+    fn openai_responses_usage_reports_reasoning_tokens() {
+        let mut usage = empty_assistant_message(&sample_model()).usage;
+        update_usage_from_openai_responses(
+            &mut usage,
+            &json!({
+                "input_tokens": 10,
+                "output_tokens": 50,
+                "output_tokens_details": { "reasoning_tokens": 32 },
+            }),
+        );
+        assert_eq!(usage.output, 50);
+        assert_eq!(usage.reasoning, 32);
+    }
+
+    #[test]
+    fn openai_responses_image_generation_adds_tool_and_parses_results() {
+        let model = sample_model();
+        let options = StreamOptions {
+            image_generation: true,
+            ..StreamOptions::default()
+        };
+        let payload = build_openai_responses_payload(&model, &sample_context(), Some(&options));
+        assert!(payload["tools"]
+            .as_array()
+            .expect("tools")
+            .contains(&json!({ "type": "image_generation" })));
+
+        let mut output = empty_assistant_message(&model);
+        let stream = AssistantMessageEventStream::new();
+        handle_openai_responses_event(
+            json!({
+                "type": "response.output_item.done",
+                "item": {
+                    "type": "image_generation_call",
+                    "id": "ig_1",
+                    "status": "completed",
+                    "output_format": "webp",
+                    "result": "UklGRg==",
+                }
+            })
+            .to_string(),
+            &mut output,
+            &stream,
+            &mut HashMap::new(),
+            &mut HashMap::new(),
+            &mut HashMap::new(),
+            &mut HashMap::new(),
+        )
+        .expect("handle event");
+
+        assert_eq!(
+            output.content,
+            vec![AssistantContentBlock::Image {
+                data: "UklGRg==".to_string(),
+                mime_type: "image/webp".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn openai_responses_payload_requests_extended_cache_retention() {
+        let mut context = sample_context();
+        assert!(
+            build_openai_responses_payload(&sample_model(), &context, None)
+                .get("prompt_cache_retention")
+                .is_none()
+        );
+
+        context.system_prompt_cache = Some(crate::types::CacheHint::Extended);
+        let payload = build_openai_responses_payload(&sample_model(), &context, None);
+        assert_eq!(payload["prompt_cache_retention"], "24h");
+    }
+
+    #[test]
+    fn openai_responses_payload_sets_json_schema_text_format() {
+        let options = StreamOptions {
+            response_format: Some(crate::types::ResponseSchema {
+                name: "summary".to_string(),
+                schema: json!({ "type": "object" }),
+                strict: true,
+            }),
+            ..StreamOptions::default()
+        };
+
+        let payload =
+            build_openai_responses_payload(&sample_model(), &sample_context(), Some(&options));
+        assert_eq!(
+            payload["text"]["format"],
+            json!({
+                "type": "json_schema",
+                "name": "summary",
+                "schema": { "type": "object" },
+                "strict": true,
+            })
+        );
+    }
+
+    #[test]
+    fn openai_responses_payload_replays_reasoning_items() {
+        let model = sample_model();
+        let mut context = sample_context();
+        context.messages.push(Message::Assistant {
+            content: vec![
+                AssistantContentBlock::Thinking {
+                    thinking: "Plan.".to_string(),
+                    thinking_signature: Some(
+                        json!({
+                            "type": "reasoning",
+                            "id": "rs_1",
+                            "status": "completed",
+                            "summary": [],
+                            "encrypted_content": "opaque",
+                        })
+                        .to_string(),
+                    ),
+                },
+                AssistantContentBlock::Thinking {
+                    thinking: "From another provider.".to_string(),
+                    thinking_signature: Some("sig".to_string()),
+                },
+            ],
+            api: "openai-responses".to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-5".to_string(),
+            usage: empty_assistant_message(&model).usage,
+            stop_reason: StopReason::Stop,
+            error_message: None,
+            timestamp: 0,
+            stats: None,
+        });
+
+        let payload = build_openai_responses_payload(&model, &context, None);
+        assert_eq!(payload["reasoning"]["summary"], "auto");
+        assert_eq!(payload["include"], json!(["reasoning.encrypted_content"]));
+        assert_eq!(
+            payload["input"][1],
+            json!({
+                "type": "reasoning",
+                "id": "rs_1",
+                "summary": [],
+                "encrypted_content": "opaque",
+            })
+        );
+        assert_eq!(payload["input"].as_array().map(Vec::len), Some(2));
+    }
+
     #[test]
     fn process_sse_data_events_stops_reading_after_done_event() {
         struct FailAfterDoneReader {
@@ -1057,8 +1425,8 @@ mod tests {
 
     #[test]
     fn openai_client_is_reused_across_requests() {
-        let first = shared_http_client("https://api.openai.com/v1");
-        let second = shared_http_client("https://api.openai.com/v1");
-        assert!(std::ptr::eq(first, second));
+        let first = shared_http_client("openai", "https://api.openai.com/v1");
+        let second = shared_http_client("openai", "https://api.openai.com/v1");
+        assert!(std::sync::Arc::ptr_eq(&first, &second));
     }
 }
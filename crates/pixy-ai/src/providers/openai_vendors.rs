@@ -0,0 +1,141 @@
+//! Vendors that speak OpenAI chat completions with their own endpoint and key: xAI (Grok),
+//! DeepSeek, the OpenRouter aggregator, and self-hosted servers such as vLLM, TGI and
+//! LM Studio. Their request quirks live in `openai_completions`, keyed by these api names or
+//! set per model with [`crate::OpenAICompletionsCompat`].
+
+use std::env;
+use std::sync::Arc;
+
+use super::common::join_url;
+use super::openai_completions::{
+    apply_simple_reasoning_to_model, run_openai_completions_at, CompletionsEndpoint,
+};
+use crate::api_registry::{ApiProvider, ApiProviderFuture};
+use crate::error::{PiAiError, PiAiErrorCode};
+use crate::types::{Context, Model, SimpleStreamOptions, StreamOptions};
+use crate::{ApiProviderRef, AssistantMessageEventStream};
+
+pub(super) const XAI_API: &str = "xai";
+pub(super) const DEEPSEEK_API: &str = "deepseek";
+pub(super) const OPENROUTER_API: &str = "openrouter";
+pub(super) const OPENAI_COMPATIBLE_API: &str = "openai-compatible";
+
+struct OpenAIVendorProvider {
+    api: &'static str,
+    /// Key read when the provider has no `{PROVIDER}_API_KEY` of its own.
+    api_key_env: &'static str,
+    /// Sends no `Authorization` header when no key is found instead of failing.
+    key_optional: bool,
+}
+
+impl ApiProvider for OpenAIVendorProvider {
+    fn api(&self) -> &str {
+        self.api
+    }
+
+    fn stream(
+        &self,
+        model: Model,
+        context: Context,
+        options: Option<StreamOptions>,
+        stream: AssistantMessageEventStream,
+    ) -> ApiProviderFuture {
+        let auth = (self.api_key_env, self.key_optional);
+        Box::pin(async move { run_vendor(model, context, options, stream, auth).await })
+    }
+
+    fn stream_simple(
+        &self,
+        model: Model,
+        context: Context,
+        options: Option<SimpleStreamOptions>,
+        stream: AssistantMessageEventStream,
+    ) -> ApiProviderFuture {
+        let auth = (self.api_key_env, self.key_optional);
+        Box::pin(async move {
+            let mut model = model;
+            apply_simple_reasoning_to_model(&mut model, options.as_ref());
+            let stream_options = options.map(|simple| simple.stream);
+            run_vendor(model, context, stream_options, stream, auth).await
+        })
+    }
+}
+
+pub(super) fn xai_provider() -> ApiProviderRef {
+    Arc::new(OpenAIVendorProvider {
+        api: XAI_API,
+        api_key_env: "XAI_API_KEY",
+        key_optional: false,
+    })
+}
+
+pub(super) fn deepseek_provider() -> ApiProviderRef {
+    Arc::new(OpenAIVendorProvider {
+        api: DEEPSEEK_API,
+        api_key_env: "DEEPSEEK_API_KEY",
+        key_optional: false,
+    })
+}
+
+pub(super) fn openrouter_provider() -> ApiProviderRef {
+    Arc::new(OpenAIVendorProvider {
+        api: OPENROUTER_API,
+        api_key_env: "OPENROUTER_API_KEY",
+        key_optional: false,
+    })
+}
+
+pub(super) fn openai_compatible_provider() -> ApiProviderRef {
+    Arc::new(OpenAIVendorProvider {
+        api: OPENAI_COMPATIBLE_API,
+        api_key_env: "OPENAI_COMPATIBLE_API_KEY",
+        key_optional: true,
+    })
+}
+
+async fn run_vendor(
+    model: Model,
+    context: Context,
+    options: Option<StreamOptions>,
+    stream: AssistantMessageEventStream,
+    (api_key_env, key_optional): (&str, bool),
+) -> Result<(), PiAiError> {
+    let auth_header = match resolve_api_key(&model.provider, options.as_ref(), api_key_env) {
+        Ok(api_key) => Some(("Authorization", format!("Bearer {api_key}"))),
+        Err(_) if key_optional => None,
+        Err(error) => return Err(error),
+    };
+    let endpoint = CompletionsEndpoint {
+        url: join_url(&model.base_url, "chat/completions"),
+        auth_header,
+    };
+    run_openai_completions_at(model, context, options, stream, endpoint).await
+}
+
+fn resolve_api_key(
+    provider: &str,
+    options: Option<&StreamOptions>,
+    fallback_env: &str,
+) -> Result<String, PiAiError> {
+    if let Some(api_key) = options.and_then(|options| options.api_key.clone()) {
+        if !api_key.trim().is_empty() {
+            return Ok(api_key);
+        }
+    }
+
+    let provider_env = format!("{}_API_KEY", provider.to_uppercase().replace('-', "_"));
+    for name in [provider_env.as_str(), fallback_env] {
+        if let Ok(value) = env::var(name) {
+            if !value.trim().is_empty() {
+                return Ok(value);
+            }
+        }
+    }
+
+    Err(PiAiError::new(
+        PiAiErrorCode::ProviderAuthMissing,
+        format!(
+            "Missing API key for provider '{provider}'. Pass `StreamOptions.api_key` or set {provider_env} / {fallback_env}."
+        ),
+    ))
+}
@@ -1,11 +1,21 @@
 use std::sync::Arc;
+use std::time::Instant;
 
 #[cfg(not(test))]
-use tokio::time::{sleep, Duration};
+use tokio::time::sleep;
+use tokio::time::Duration;
 
-use crate::api_registry::{ApiProvider, ApiProviderFuture};
-use crate::error::{PiAiError, PiAiErrorCode};
-use crate::transport_retry::{transport_retry_count, DEFAULT_TRANSPORT_RETRY_COUNT};
+use serde::{Deserialize, Serialize};
+
+use crate::api_registry::{get_api_provider, ApiProvider, ApiProviderFuture};
+use crate::error::{is_context_overflow_error_text, PiAiError, PiAiErrorCode};
+use crate::telemetry::{
+    is_content_event, record_call_attempt, record_call_retry, start_call_attempt,
+};
+use crate::transport_retry::{
+    http_error_status, record_retries_exhausted, record_retry, retry_policy_for_provider,
+    RetryPolicy,
+};
 use crate::types::{
     AssistantMessage, AssistantMessageEvent, Context, Model, SimpleStreamOptions, StopReason,
     StreamOptions,
@@ -14,37 +24,176 @@ use crate::AssistantMessageEventStream;
 
 pub struct ReliableProvider {
     inner: Arc<dyn ApiProvider>,
-    max_retries: u32,
-    base_backoff_ms: u64,
+    max_retries: Option<usize>,
+    base_backoff_ms: Option<u64>,
+    fallbacks: Vec<Model>,
+    fallback_on: Vec<FallbackCondition>,
+}
+
+/// Failure classes that move a [`ReliableProvider`] on to its next fallback model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FallbackCondition {
+    /// HTTP 429.
+    RateLimited,
+    /// Any HTTP 5xx.
+    ServerError,
+    /// HTTP 408/504 or a transport error that timed out.
+    Timeout,
+    /// The request did not fit the model's context window.
+    ContextOverflow,
+}
+
+impl FallbackCondition {
+    pub const ALL: [FallbackCondition; 4] = [
+        FallbackCondition::RateLimited,
+        FallbackCondition::ServerError,
+        FallbackCondition::Timeout,
+        FallbackCondition::ContextOverflow,
+    ];
+
+    pub fn matches(self, error: &PiAiError) -> bool {
+        let status = http_error_status(error);
+        match self {
+            FallbackCondition::RateLimited => status == Some(429),
+            FallbackCondition::ServerError => status.is_some_and(|status| status >= 500),
+            FallbackCondition::Timeout => {
+                if matches!(status, Some(408 | 504)) {
+                    return true;
+                }
+                let message = error.message.to_ascii_lowercase();
+                error.code == PiAiErrorCode::TransportTimeout
+                    || (error.code == PiAiErrorCode::ProviderTransport
+                        && (message.contains("timed out") || message.contains("timeout")))
+            }
+            FallbackCondition::ContextOverflow => {
+                error.code == PiAiErrorCode::ContextTooLarge
+                    || is_context_overflow_error_text(&error.message)
+            }
+        }
+    }
+}
+
+tokio::task_local! {
+    /// Set while a fallback entry runs so a registered provider's own chain is not followed.
+    static IN_FALLBACK: ();
 }
 
 impl ReliableProvider {
     pub fn wrap(inner: Arc<dyn ApiProvider>) -> Self {
         Self {
             inner,
-            max_retries: DEFAULT_TRANSPORT_RETRY_COUNT as u32,
-            base_backoff_ms: 1_000,
+            max_retries: None,
+            base_backoff_ms: None,
+            fallbacks: Vec::new(),
+            fallback_on: FallbackCondition::ALL.to_vec(),
         }
     }
 
     pub fn max_retries(mut self, max_retries: u32) -> Self {
-        self.max_retries = max_retries;
+        self.max_retries = Some(max_retries as usize);
         self
     }
 
     pub fn base_backoff_ms(mut self, base_backoff_ms: u64) -> Self {
-        self.base_backoff_ms = base_backoff_ms;
+        self.base_backoff_ms = Some(base_backoff_ms);
         self
     }
 
-    fn resolve_retry_count(&self, request_override: Option<usize>) -> u32 {
-        let request_override = request_override.map(|value| value.min(u32::MAX as usize) as u32);
-        request_override.unwrap_or_else(|| {
-            if self.max_retries == DEFAULT_TRANSPORT_RETRY_COUNT as u32 {
-                transport_retry_count().min(u32::MAX as usize) as u32
+    /// Models tried in order once the requested model fails with one of the
+    /// [`fallback_on`](Self::fallback_on) conditions. Models on another API are served by
+    /// that API's registered provider.
+    pub fn with_fallbacks(mut self, fallbacks: Vec<Model>) -> Self {
+        self.fallbacks = fallbacks;
+        self
+    }
+
+    /// Failure conditions that trigger a fallback; defaults to [`FallbackCondition::ALL`].
+    pub fn fallback_on(mut self, conditions: Vec<FallbackCondition>) -> Self {
+        self.fallback_on = conditions;
+        self
+    }
+
+    /// A request's own `retry_policy` wins; otherwise the provider's registered policy is
+    /// adjusted by this wrapper's overrides and the request's `transport_retry_count`.
+    fn resolve_policy(&self, model: &Model, options: Option<&StreamOptions>) -> RetryPolicy {
+        if let Some(policy) = options.and_then(|options| options.retry_policy.clone()) {
+            return policy;
+        }
+
+        let mut policy = retry_policy_for_provider(&model.provider);
+        if let Some(max_retries) = self.max_retries {
+            policy.max_retries = max_retries;
+        }
+        if let Some(base_backoff_ms) = self.base_backoff_ms {
+            policy.base_backoff_ms = base_backoff_ms;
+        }
+        if let Some(retry_count) = options.and_then(|options| options.transport_retry_count) {
+            policy.max_retries = retry_count;
+        }
+        policy
+    }
+
+    /// The primary model followed by every fallback that resolves to a provider. Empty when
+    /// no fallbacks are configured or this call is itself serving a fallback.
+    fn fallback_route(&self, model: &Model, request: &RouteRequest) -> Vec<RouteEntry> {
+        if self.fallbacks.is_empty() || IN_FALLBACK.try_with(|_| ()).is_ok() {
+            return Vec::new();
+        }
+
+        let mut route = vec![RouteEntry {
+            model: model.clone(),
+            target: RouteTarget::Inner(self.resolve_policy(model, request.stream_options())),
+        }];
+        for fallback in &self.fallbacks {
+            let target = if fallback.api == self.api() {
+                RouteTarget::Inner(self.resolve_policy(fallback, request.stream_options()))
+            } else if let Some(provider) = get_api_provider(&fallback.api) {
+                RouteTarget::Registered(provider)
             } else {
-                self.max_retries
-            }
+                tracing::warn!(
+                    api = fallback.api.as_str(),
+                    model = fallback.id.as_str(),
+                    "skipping fallback model without a registered provider"
+                );
+                continue;
+            };
+            route.push(RouteEntry {
+                model: fallback.clone(),
+                target,
+            });
+        }
+        route
+    }
+
+    fn dispatch(
+        &self,
+        model: Model,
+        context: Context,
+        request: RouteRequest,
+        stream: AssistantMessageEventStream,
+    ) -> ApiProviderFuture {
+        let inner = self.inner.clone();
+        let provider_api = self.api().to_string();
+        let route = self.fallback_route(&model, &request);
+        if !route.is_empty() {
+            let conditions = self.fallback_on.clone();
+            return Box::pin(async move {
+                run_fallback_route(inner, route, conditions, context, request, stream).await
+            });
+        }
+
+        let policy = self.resolve_policy(&model, request.stream_options());
+        Box::pin(async move {
+            run_with_retry(provider_api, policy, stream, move |attempt_stream| {
+                request.invoke(
+                    inner.clone(),
+                    model.clone(),
+                    context.clone(),
+                    attempt_stream,
+                )
+            })
+            .await
         })
     }
 }
@@ -61,30 +210,7 @@ impl ApiProvider for ReliableProvider {
         options: Option<StreamOptions>,
         stream: AssistantMessageEventStream,
     ) -> ApiProviderFuture {
-        let inner = self.inner.clone();
-        let provider_api = self.api().to_string();
-        let max_retries = self.resolve_retry_count(
-            options
-                .as_ref()
-                .and_then(|stream_options| stream_options.transport_retry_count),
-        );
-        let base_backoff_ms = self.base_backoff_ms;
-        Box::pin(async move {
-            run_with_retry(
-                provider_api,
-                max_retries,
-                base_backoff_ms,
-                stream,
-                move |attempt_stream| {
-                    let inner = inner.clone();
-                    let model = model.clone();
-                    let context = context.clone();
-                    let options = options.clone();
-                    async move { inner.stream(model, context, options, attempt_stream).await }
-                },
-            )
-            .await
-        })
+        self.dispatch(model, context, RouteRequest::Stream(options), stream)
     }
 
     fn stream_simple(
@@ -94,50 +220,190 @@ impl ApiProvider for ReliableProvider {
         options: Option<SimpleStreamOptions>,
         stream: AssistantMessageEventStream,
     ) -> ApiProviderFuture {
-        let inner = self.inner.clone();
-        let provider_api = self.api().to_string();
-        let max_retries = self.resolve_retry_count(
-            options
-                .as_ref()
-                .and_then(|simple_options| simple_options.stream.transport_retry_count),
-        );
-        let base_backoff_ms = self.base_backoff_ms;
-        Box::pin(async move {
-            run_with_retry(
-                provider_api,
-                max_retries,
-                base_backoff_ms,
-                stream,
-                move |attempt_stream| {
-                    let inner = inner.clone();
-                    let model = model.clone();
-                    let context = context.clone();
-                    let options = options.clone();
-                    async move {
-                        inner
-                            .stream_simple(model, context, options, attempt_stream)
-                            .await
+        self.dispatch(model, context, RouteRequest::Simple(options), stream)
+    }
+}
+
+#[derive(Clone)]
+enum RouteRequest {
+    Stream(Option<StreamOptions>),
+    Simple(Option<SimpleStreamOptions>),
+}
+
+impl RouteRequest {
+    fn stream_options(&self) -> Option<&StreamOptions> {
+        match self {
+            RouteRequest::Stream(options) => options.as_ref(),
+            RouteRequest::Simple(options) => options.as_ref().map(|options| &options.stream),
+        }
+    }
+
+    /// The same request for a fallback model; an explicit API key only applies to the
+    /// provider it was issued for.
+    fn for_fallback(&self, primary: &Model, fallback: &Model) -> Self {
+        let mut request = self.clone();
+        if fallback.provider != primary.provider {
+            match &mut request {
+                RouteRequest::Stream(Some(options)) => options.api_key = None,
+                RouteRequest::Simple(Some(options)) => options.stream.api_key = None,
+                _ => {}
+            }
+        }
+        request
+    }
+
+    fn invoke(
+        &self,
+        provider: Arc<dyn ApiProvider>,
+        model: Model,
+        context: Context,
+        stream: AssistantMessageEventStream,
+    ) -> ApiProviderFuture {
+        match self {
+            RouteRequest::Stream(options) => {
+                provider.stream(model, context, options.clone(), stream)
+            }
+            RouteRequest::Simple(options) => {
+                provider.stream_simple(model, context, options.clone(), stream)
+            }
+        }
+    }
+}
+
+struct RouteEntry {
+    model: Model,
+    target: RouteTarget,
+}
+
+enum RouteTarget {
+    /// The wrapped provider, retried under the given policy.
+    Inner(RetryPolicy),
+    /// Another API's registered provider, which applies its own retries.
+    Registered(Arc<dyn ApiProvider>),
+}
+
+async fn run_fallback_route(
+    inner: Arc<dyn ApiProvider>,
+    route: Vec<RouteEntry>,
+    conditions: Vec<FallbackCondition>,
+    context: Context,
+    request: RouteRequest,
+    output_stream: AssistantMessageEventStream,
+) -> Result<(), PiAiError> {
+    let primary = route[0].model.clone();
+    let last_index = route.len() - 1;
+
+    for (index, entry) in route.into_iter().enumerate() {
+        let entry_request = request.for_fallback(&primary, &entry.model);
+        let attempt_stream = AssistantMessageEventStream::new();
+        let provider_api = entry.model.api.clone();
+        let model = entry.model.clone();
+        let entry_stream = attempt_stream.clone();
+        let inner = inner.clone();
+        let entry_context = context.clone();
+        let run = async move {
+            match entry.target {
+                RouteTarget::Inner(policy) => {
+                    let attempt = run_with_retry(
+                        entry.model.api.clone(),
+                        policy,
+                        entry_stream,
+                        move |retry_stream| {
+                            entry_request.invoke(
+                                inner.clone(),
+                                entry.model.clone(),
+                                entry_context.clone(),
+                                retry_stream,
+                            )
+                        },
+                    );
+                    if index == 0 {
+                        attempt.await
+                    } else {
+                        IN_FALLBACK.scope((), attempt).await
                     }
-                },
-            )
-            .await
-        })
+                }
+                RouteTarget::Registered(provider) => {
+                    let attempt =
+                        entry_request.invoke(provider, entry.model, entry_context, entry_stream);
+                    IN_FALLBACK.scope((), attempt).await
+                }
+            }
+        };
+        let mut attempt = run_attempt(&attempt_stream, run).await;
+        if index > 0 {
+            for event in &mut attempt.events {
+                annotate_served_by(event, &model);
+            }
+        }
+
+        match classify_attempt(&provider_api, &attempt.result, &attempt.events) {
+            AttemptStatus::Success => {
+                attempt.deliver(&output_stream);
+                return Ok(());
+            }
+            AttemptStatus::Failure {
+                error,
+                terminal_emitted,
+            } => {
+                if index < last_index
+                    && conditions.iter().any(|condition| condition.matches(&error))
+                {
+                    tracing::warn!(
+                        provider = model.provider.as_str(),
+                        model = model.id.as_str(),
+                        error = error.message.as_str(),
+                        "falling back to next model"
+                    );
+                    record_call_retry();
+                    continue;
+                }
+
+                if terminal_emitted {
+                    attempt.deliver(&output_stream);
+                    return Ok(());
+                }
+                return Err(error);
+            }
+        }
     }
+
+    unreachable!("fallback route always has a primary entry")
+}
+
+/// Points every message carried by `event` at the model that actually served the request.
+fn annotate_served_by(event: &mut AssistantMessageEvent, model: &Model) {
+    let message = match event {
+        AssistantMessageEvent::Start { partial }
+        | AssistantMessageEvent::TextStart { partial, .. }
+        | AssistantMessageEvent::TextDelta { partial, .. }
+        | AssistantMessageEvent::TextEnd { partial, .. }
+        | AssistantMessageEvent::ThinkingStart { partial, .. }
+        | AssistantMessageEvent::ThinkingDelta { partial, .. }
+        | AssistantMessageEvent::ThinkingEnd { partial, .. }
+        | AssistantMessageEvent::ToolcallStart { partial, .. }
+        | AssistantMessageEvent::ToolcallDelta { partial, .. }
+        | AssistantMessageEvent::ToolcallEnd { partial, .. }
+        | AssistantMessageEvent::UsageDelta { partial, .. } => partial,
+        AssistantMessageEvent::Done { message, .. } => message,
+        AssistantMessageEvent::Error { error, .. } => error,
+    };
+    message.api = model.api.clone();
+    message.provider = model.provider.clone();
+    message.model = model.id.clone();
 }
 
 enum AttemptStatus {
     Success,
     Failure {
         error: PiAiError,
-        retryable: bool,
         terminal_emitted: bool,
     },
 }
 
 async fn run_with_retry<F, Fut>(
     provider_api: String,
-    max_retries: u32,
-    base_backoff_ms: u64,
+    policy: RetryPolicy,
     output_stream: AssistantMessageEventStream,
     mut operation: F,
 ) -> Result<(), PiAiError>
@@ -145,32 +411,42 @@ where
     F: FnMut(AssistantMessageEventStream) -> Fut,
     Fut: std::future::Future<Output = Result<(), PiAiError>>,
 {
-    let mut retries_used = 0u32;
+    let mut retries_used = 0usize;
 
     loop {
         let attempt_stream = AssistantMessageEventStream::new();
-        let attempt_result = operation(attempt_stream.clone()).await;
-        attempt_stream.end(None);
-        let attempt_events = drain_events(&attempt_stream).await;
+        let attempt = run_attempt(&attempt_stream, operation(attempt_stream.clone())).await;
 
-        match classify_attempt(&provider_api, &attempt_result, &attempt_events) {
+        match classify_attempt(&provider_api, &attempt.result, &attempt.events) {
             AttemptStatus::Success => {
-                replay_events(&output_stream, attempt_events);
+                attempt.deliver(&output_stream);
                 return Ok(());
             }
             AttemptStatus::Failure {
                 error,
-                retryable,
                 terminal_emitted,
             } => {
-                if retryable && retries_used < max_retries {
-                    sleep_backoff(base_backoff_ms, retries_used).await;
+                let retryable = policy.is_retryable(&error);
+                if retryable && retries_used < policy.max_retries {
+                    let delay = policy.retry_delay(retries_used as u32, &error);
+                    record_retry(&policy, &error);
+                    tracing::debug!(
+                        provider_api = provider_api.as_str(),
+                        retry = retries_used + 1,
+                        delay_ms = delay.as_millis() as u64,
+                        error = error.message.as_str(),
+                        "retrying provider request"
+                    );
+                    sleep_backoff(delay).await;
                     retries_used += 1;
                     continue;
                 }
+                if retryable {
+                    record_retries_exhausted();
+                }
 
                 if terminal_emitted {
-                    replay_events(&output_stream, attempt_events);
+                    attempt.deliver(&output_stream);
                     return Ok(());
                 }
                 return Err(error);
@@ -202,7 +478,6 @@ fn classify_attempt(
                     )
                 });
                 AttemptStatus::Failure {
-                    retryable: parsed.code == PiAiErrorCode::ProviderTransport,
                     error: parsed,
                     terminal_emitted: true,
                 }
@@ -213,14 +488,12 @@ fn classify_attempt(
 
     if let Err(error) = attempt_result {
         return AttemptStatus::Failure {
-            retryable: error.code == PiAiErrorCode::ProviderTransport,
             error: error.clone(),
             terminal_emitted: false,
         };
     }
 
     AttemptStatus::Failure {
-        retryable: false,
         error: PiAiError::new(
             PiAiErrorCode::ProviderProtocol,
             format!("Provider '{provider_api}' returned without a terminal event"),
@@ -239,12 +512,56 @@ fn parse_error_message(error: &AssistantMessage) -> Option<PiAiError> {
         .and_then(|value| serde_json::from_str::<PiAiError>(value).ok())
 }
 
-async fn drain_events(stream: &AssistantMessageEventStream) -> Vec<AssistantMessageEvent> {
-    let mut events = Vec::new();
-    while let Some(event) = stream.next().await {
-        events.push(event);
+/// One attempt's events, collected as they arrive, and when it started and first produced
+/// content. The events are held back until the attempt is judged, so telemetry downstream
+/// cannot time them itself.
+struct AttemptOutcome {
+    result: Result<(), PiAiError>,
+    events: Vec<AssistantMessageEvent>,
+    started: Instant,
+    first_token_at: Option<Instant>,
+}
+
+impl AttemptOutcome {
+    /// Hands the events to `output_stream`, timing the call by this attempt.
+    fn deliver(self, output_stream: &AssistantMessageEventStream) {
+        record_call_attempt(self.started, self.first_token_at);
+        replay_events(output_stream, self.events);
+    }
+}
+
+async fn run_attempt<Fut>(
+    attempt_stream: &AssistantMessageEventStream,
+    attempt: Fut,
+) -> AttemptOutcome
+where
+    Fut: std::future::Future<Output = Result<(), PiAiError>>,
+{
+    start_call_attempt();
+    let started = Instant::now();
+    let run = async {
+        let result = attempt.await;
+        attempt_stream.end(None);
+        result
+    };
+    let collect = async {
+        let mut events = Vec::new();
+        let mut first_token_at = None;
+        while let Some(event) = attempt_stream.next().await {
+            if first_token_at.is_none() && is_content_event(&event) {
+                first_token_at = Some(Instant::now());
+            }
+            events.push(event);
+        }
+        (events, first_token_at)
+    };
+    let (result, (events, first_token_at)) = tokio::join!(run, collect);
+    AttemptOutcome {
+        result,
+        events,
+        started,
+        first_token_at,
     }
-    events
 }
 
 fn replay_events(output_stream: &AssistantMessageEventStream, events: Vec<AssistantMessageEvent>) {
@@ -254,15 +571,12 @@ fn replay_events(output_stream: &AssistantMessageEventStream, events: Vec<Assist
 }
 
 #[cfg(not(test))]
-async fn sleep_backoff(base_backoff_ms: u64, retry_index: u32) {
-    let shift = retry_index.min(63);
-    let multiplier = 1u64.checked_shl(shift).unwrap_or(u64::MAX);
-    let delay_ms = base_backoff_ms.saturating_mul(multiplier);
-    sleep(Duration::from_millis(delay_ms)).await;
+async fn sleep_backoff(delay: Duration) {
+    sleep(delay).await;
 }
 
 #[cfg(test)]
-async fn sleep_backoff(_base_backoff_ms: u64, _retry_index: u32) {}
+async fn sleep_backoff(_delay: Duration) {}
 
 #[cfg(test)]
 mod tests {
@@ -274,6 +588,14 @@ mod tests {
         AssistantContentBlock, Cost, DoneReason, ErrorReason, Message, Usage, UserContent,
     };
 
+    async fn drain_events(stream: &AssistantMessageEventStream) -> Vec<AssistantMessageEvent> {
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            events.push(event);
+        }
+        events
+    }
+
     #[derive(Clone)]
     struct TestProvider {
         api: &'static str,
@@ -456,6 +778,161 @@ mod tests {
         assert_eq!(attempts.load(Ordering::SeqCst), 1);
     }
 
+    #[tokio::test]
+    async fn reliable_provider_retries_retryable_http_statuses_per_request_policy() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let provider = Arc::new(TestProvider {
+            api: "test",
+            attempts: attempts.clone(),
+            behavior: Arc::new(|attempt, stream| {
+                Box::pin(async move {
+                    if attempt == 0 {
+                        return Err(PiAiError::new(PiAiErrorCode::ProviderHttp, "HTTP 429")
+                            .with_details(serde_json::json!({
+                                "status": 429,
+                                "retryAfterMs": 10,
+                            })));
+                    }
+                    if attempt == 1 {
+                        return Err(PiAiError::new(PiAiErrorCode::ProviderHttp, "HTTP 400")
+                            .with_details(serde_json::json!({ "status": 400 })));
+                    }
+                    let message = assistant_message(StopReason::Stop, None);
+                    stream.push(AssistantMessageEvent::Done {
+                        reason: DoneReason::Stop,
+                        message,
+                    });
+                    Ok(())
+                })
+            }),
+        });
+        let reliable = ReliableProvider::wrap(provider);
+        let options = StreamOptions {
+            retry_policy: Some(RetryPolicy {
+                max_retries: 3,
+                ..RetryPolicy::default()
+            }),
+            ..StreamOptions::default()
+        };
+        let retry_after_waits_before = crate::retry_metrics().retry_after_waits;
+
+        let out = AssistantMessageEventStream::new();
+        let result = reliable
+            .stream(
+                sample_model("test"),
+                sample_context(),
+                Some(options),
+                out.clone(),
+            )
+            .await;
+        out.end(None);
+
+        let error = result.expect_err("HTTP 400 should not be retried");
+        assert_eq!(error.message, "HTTP 400");
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        assert!(crate::retry_metrics().retry_after_waits > retry_after_waits_before);
+    }
+
+    #[tokio::test]
+    async fn reliable_provider_falls_back_on_rate_limits_and_annotates_serving_model() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let provider = Arc::new(TestProvider {
+            api: "test",
+            attempts: attempts.clone(),
+            behavior: Arc::new(|attempt, stream| {
+                Box::pin(async move {
+                    if attempt == 0 {
+                        return Err(PiAiError::new(PiAiErrorCode::ProviderHttp, "HTTP 429")
+                            .with_details(serde_json::json!({ "status": 429 })));
+                    }
+                    let message = assistant_message(StopReason::Stop, None);
+                    stream.push(AssistantMessageEvent::Start {
+                        partial: message.clone(),
+                    });
+                    stream.push(AssistantMessageEvent::Done {
+                        reason: DoneReason::Stop,
+                        message,
+                    });
+                    Ok(())
+                })
+            }),
+        });
+        let mut backup = sample_model("test");
+        backup.provider = "backup".to_string();
+        backup.id = "backup-model".to_string();
+        let reliable = ReliableProvider::wrap(provider)
+            .max_retries(0)
+            .with_fallbacks(vec![backup]);
+
+        let out = AssistantMessageEventStream::new();
+        let result = reliable
+            .stream(sample_model("test"), sample_context(), None, out.clone())
+            .await;
+        out.end(None);
+        let events = drain_events(&out).await;
+
+        assert!(result.is_ok());
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        let done = events
+            .iter()
+            .find_map(|event| match event {
+                AssistantMessageEvent::Done { message, .. } => Some(message),
+                _ => None,
+            })
+            .expect("fallback should finish the request");
+        assert_eq!(done.provider, "backup");
+        assert_eq!(done.model, "backup-model");
+    }
+
+    #[tokio::test]
+    async fn reliable_provider_skips_fallbacks_for_unlisted_failures() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let provider = Arc::new(TestProvider {
+            api: "test",
+            attempts: attempts.clone(),
+            behavior: Arc::new(|_, _| {
+                Box::pin(async move {
+                    Err(PiAiError::new(PiAiErrorCode::ProviderHttp, "HTTP 503")
+                        .with_details(serde_json::json!({ "status": 503 })))
+                })
+            }),
+        });
+        let reliable = ReliableProvider::wrap(provider)
+            .max_retries(0)
+            .with_fallbacks(vec![sample_model("test")])
+            .fallback_on(vec![FallbackCondition::RateLimited]);
+
+        let out = AssistantMessageEventStream::new();
+        let result = reliable
+            .stream(sample_model("test"), sample_context(), None, out.clone())
+            .await;
+        out.end(None);
+
+        let error = result.expect_err("503 is not a configured fallback condition");
+        assert_eq!(error.message, "HTTP 503");
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn fallback_conditions_classify_provider_errors() {
+        let http = |status: u16| {
+            PiAiError::new(PiAiErrorCode::ProviderHttp, format!("HTTP {status}"))
+                .with_details(serde_json::json!({ "status": status }))
+        };
+        assert!(FallbackCondition::RateLimited.matches(&http(429)));
+        assert!(FallbackCondition::ServerError.matches(&http(502)));
+        assert!(!FallbackCondition::ServerError.matches(&http(400)));
+        assert!(FallbackCondition::Timeout.matches(&http(408)));
+        assert!(FallbackCondition::Timeout.matches(&PiAiError::new(
+            PiAiErrorCode::ProviderTransport,
+            "operation timed out"
+        )));
+        assert!(FallbackCondition::ContextOverflow.matches(&PiAiError::new(
+            PiAiErrorCode::ProviderHttp,
+            "HTTP 400: prompt is too long: 210000 tokens > 200000 maximum"
+        )));
+    }
+
     fn transport_error_json(message: &str) -> String {
         PiAiError::new(PiAiErrorCode::ProviderTransport, message).as_compact_json()
     }
@@ -475,6 +952,7 @@ mod tests {
                 output: 0,
                 cache_read: 0,
                 cache_write: 0,
+                reasoning: 0,
                 total_tokens: 0,
                 cost: Cost {
                     input: 0.0,
@@ -487,6 +965,7 @@ mod tests {
             stop_reason,
             error_message,
             timestamp: 0,
+            stats: None,
         }
     }
 
@@ -509,6 +988,9 @@ mod tests {
             },
             context_window: 128_000,
             max_tokens: 4_096,
+            deployment: None,
+            api_version: None,
+            compat: None,
         }
     }
 
@@ -520,6 +1002,8 @@ mod tests {
                 timestamp: 0,
             }],
             tools: None,
+            system_prompt_cache: None,
+            branch: None,
         }
     }
 }
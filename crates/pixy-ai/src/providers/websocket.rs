@@ -0,0 +1,320 @@
+//! Minimal RFC 6455 client for realtime APIs. The handshake is an HTTP/1.1 upgrade through the
+//! shared HTTP client, so TLS, proxies and loopback handling match every other request.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use reqwest::{StatusCode, Upgraded};
+use sha1::{Digest, Sha1};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf};
+
+use super::common::{http_error_from_response, shared_http_client};
+use crate::error::{PiAiError, PiAiErrorCode};
+
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Realtime events are small JSON documents; anything larger is a broken or hostile peer.
+const MAX_MESSAGE_LEN: u64 = 16 * 1024 * 1024;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum WsMessage {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Close,
+}
+
+pub(crate) struct WsReader<R = ReadHalf<Upgraded>> {
+    io: R,
+    /// Opcode and data of a fragmented message; control frames may arrive between its frames.
+    fragments: Option<(u8, Vec<u8>)>,
+}
+
+pub(crate) struct WsWriter {
+    io: WriteHalf<Upgraded>,
+}
+
+/// Opens a WebSocket to `url` (`ws://`, `wss://` or their `http` forms) with extra `headers`.
+pub(crate) async fn connect(
+    label: &str,
+    provider: &str,
+    url: &str,
+    headers: &[(String, String)],
+) -> Result<(WsReader, WsWriter), PiAiError> {
+    let url = http_url(url);
+    let key = BASE64_STANDARD.encode(random_bytes::<16>());
+    let mut request = shared_http_client(provider, &url)
+        .get(url.as_str())
+        .header("Connection", "Upgrade")
+        .header("Upgrade", "websocket")
+        .header("Sec-WebSocket-Version", "13")
+        .header("Sec-WebSocket-Key", key.as_str());
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+
+    let response = request.send().await.map_err(|error| {
+        PiAiError::new(
+            PiAiErrorCode::ProviderTransport,
+            format!("{label} WebSocket connect failed: {error}"),
+        )
+    })?;
+    if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+        return Err(http_error_from_response(label, response).await);
+    }
+    let accept = response
+        .headers()
+        .get("sec-websocket-accept")
+        .and_then(|value| value.to_str().ok());
+    if accept != Some(accept_key(&key).as_str()) {
+        return Err(PiAiError::new(
+            PiAiErrorCode::ProviderProtocol,
+            format!("{label} WebSocket handshake returned a wrong Sec-WebSocket-Accept"),
+        ));
+    }
+
+    let upgraded = response.upgrade().await.map_err(|error| {
+        PiAiError::new(
+            PiAiErrorCode::ProviderTransport,
+            format!("{label} WebSocket upgrade failed: {error}"),
+        )
+    })?;
+    let (reader, writer) = tokio::io::split(upgraded);
+    Ok((WsReader::new(reader), WsWriter { io: writer }))
+}
+
+impl<R: AsyncRead + Unpin> WsReader<R> {
+    fn new(io: R) -> Self {
+        Self {
+            io,
+            fragments: None,
+        }
+    }
+
+    /// The next complete message, or `None` once the connection is gone. A ping in the middle of
+    /// a fragmented message is returned on its own; the partial message survives for later calls.
+    pub(crate) async fn next_message(&mut self) -> Result<Option<WsMessage>, PiAiError> {
+        loop {
+            let Some((fin, opcode, payload)) = self.read_frame().await? else {
+                return Ok(None);
+            };
+            match opcode {
+                OPCODE_PING => return Ok(Some(WsMessage::Ping(payload))),
+                OPCODE_PONG => continue,
+                OPCODE_CLOSE => return Ok(Some(WsMessage::Close)),
+                OPCODE_TEXT | OPCODE_BINARY if self.fragments.is_none() => {
+                    self.fragments = Some((opcode, payload));
+                }
+                OPCODE_CONTINUATION if self.fragments.is_some() => {
+                    if let Some((_, data)) = self.fragments.as_mut() {
+                        data.extend_from_slice(&payload);
+                        if data.len() as u64 > MAX_MESSAGE_LEN {
+                            return Err(protocol_error("WebSocket message is too large"));
+                        }
+                    }
+                }
+                _ => return Err(protocol_error("unexpected WebSocket frame")),
+            }
+            if fin {
+                let (opcode, data) = self.fragments.take().expect("message started");
+                return match opcode {
+                    OPCODE_TEXT => String::from_utf8(data)
+                        .map(|text| Some(WsMessage::Text(text)))
+                        .map_err(|_| protocol_error("WebSocket text message is not UTF-8")),
+                    _ => Ok(Some(WsMessage::Binary(data))),
+                };
+            }
+        }
+    }
+
+    async fn read_frame(&mut self) -> Result<Option<(bool, u8, Vec<u8>)>, PiAiError> {
+        let mut head = [0_u8; 2];
+        match self.io.read_exact(&mut head).await {
+            Ok(_) => {}
+            Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(error) => return Err(transport_error(error)),
+        }
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0F;
+        let masked = head[1] & 0x80 != 0;
+        let len = match head[1] & 0x7F {
+            126 => {
+                let mut len = [0_u8; 2];
+                self.io
+                    .read_exact(&mut len)
+                    .await
+                    .map_err(transport_error)?;
+                u64::from(u16::from_be_bytes(len))
+            }
+            127 => {
+                let mut len = [0_u8; 8];
+                self.io
+                    .read_exact(&mut len)
+                    .await
+                    .map_err(transport_error)?;
+                u64::from_be_bytes(len)
+            }
+            len => u64::from(len),
+        };
+        if len > MAX_MESSAGE_LEN {
+            return Err(protocol_error("WebSocket message is too large"));
+        }
+        let mut mask = [0_u8; 4];
+        if masked {
+            self.io
+                .read_exact(&mut mask)
+                .await
+                .map_err(transport_error)?;
+        }
+        let mut payload = vec![0_u8; len as usize];
+        self.io
+            .read_exact(&mut payload)
+            .await
+            .map_err(transport_error)?;
+        if masked {
+            apply_mask(&mut payload, mask);
+        }
+        Ok(Some((fin, opcode, payload)))
+    }
+}
+
+impl WsWriter {
+    pub(crate) async fn send_text(&mut self, text: &str) -> Result<(), PiAiError> {
+        self.write_frame(OPCODE_TEXT, text.as_bytes()).await
+    }
+
+    pub(crate) async fn send_pong(&mut self, payload: &[u8]) -> Result<(), PiAiError> {
+        self.write_frame(OPCODE_PONG, payload).await
+    }
+
+    pub(crate) async fn close(&mut self) -> Result<(), PiAiError> {
+        self.write_frame(OPCODE_CLOSE, &1000_u16.to_be_bytes())
+            .await?;
+        self.io.shutdown().await.map_err(transport_error)
+    }
+
+    /// Writes one final frame; clients must mask everything they send.
+    async fn write_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<(), PiAiError> {
+        let mut frame = Vec::with_capacity(payload.len() + 14);
+        frame.push(0x80 | opcode);
+        match payload.len() {
+            len if len < 126 => frame.push(0x80 | len as u8),
+            len if len <= usize::from(u16::MAX) => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        let mask = random_bytes::<4>();
+        frame.extend_from_slice(&mask);
+        let start = frame.len();
+        frame.extend_from_slice(payload);
+        apply_mask(&mut frame[start..], mask);
+        self.io.write_all(&frame).await.map_err(transport_error)?;
+        self.io.flush().await.map_err(transport_error)
+    }
+}
+
+fn http_url(url: &str) -> String {
+    if let Some(rest) = url.strip_prefix("wss://") {
+        format!("https://{rest}")
+    } else if let Some(rest) = url.strip_prefix("ws://") {
+        format!("http://{rest}")
+    } else {
+        url.to_string()
+    }
+}
+
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+    for (index, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[index % 4];
+    }
+}
+
+/// Unpredictable enough for handshake keys and frame masks, which only defeat proxy caches.
+fn random_bytes<const N: usize>() -> [u8; N] {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut bytes = [0_u8; N];
+    for chunk in bytes.chunks_mut(8) {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        chunk.copy_from_slice(&hasher.finish().to_le_bytes()[..chunk.len()]);
+    }
+    bytes
+}
+
+/// The `Sec-WebSocket-Accept` a server must answer `key` with.
+pub(crate) fn accept_key(key: &str) -> String {
+    BASE64_STANDARD.encode(Sha1::digest(format!("{key}{WEBSOCKET_GUID}").as_bytes()))
+}
+
+fn transport_error(error: std::io::Error) -> PiAiError {
+    PiAiError::new(
+        PiAiErrorCode::ProviderTransport,
+        format!("WebSocket connection failed: {error}"),
+    )
+}
+
+fn protocol_error(message: &str) -> PiAiError {
+    PiAiError::new(PiAiErrorCode::ProviderProtocol, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_key_matches_the_rfc_example() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    fn frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![
+            if fin { 0x80 | opcode } else { opcode },
+            payload.len() as u8,
+        ];
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[tokio::test]
+    async fn ping_between_fragments_keeps_the_partial_message() {
+        let mut stream = frame(false, OPCODE_TEXT, b"hel");
+        stream.extend(frame(true, OPCODE_PING, b"are you there"));
+        stream.extend(frame(true, OPCODE_CONTINUATION, b"lo"));
+        let mut reader = WsReader::new(stream.as_slice());
+
+        assert_eq!(
+            reader.next_message().await.expect("ping"),
+            Some(WsMessage::Ping(b"are you there".to_vec()))
+        );
+        assert_eq!(
+            reader.next_message().await.expect("text"),
+            Some(WsMessage::Text("hello".to_string()))
+        );
+        assert_eq!(reader.next_message().await.expect("eof"), None);
+    }
+
+    #[test]
+    fn websocket_urls_map_to_http_schemes() {
+        assert_eq!(
+            http_url("wss://api.openai.com/v1/realtime"),
+            "https://api.openai.com/v1/realtime"
+        );
+        assert_eq!(http_url("ws://127.0.0.1:8080"), "http://127.0.0.1:8080");
+    }
+}
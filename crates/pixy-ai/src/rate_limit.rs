@@ -0,0 +1,291 @@
+//! Client-side request limits per provider and model, so many sessions sharing one account
+//! (gateway channels, multi-agent workers) queue up here instead of tripping provider 429s.
+//!
+//! A limit is keyed by provider name (`openai`) or by provider and model id
+//! (`openai/gpt-5.3-codex`); a request waits for both when both are set.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::error::{PiAiError, PiAiErrorCode};
+use crate::types::Model;
+
+/// Limits for one provider or model. Unset fields are unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimit {
+    /// Requests started per minute, refilled continuously.
+    #[serde(rename = "requestsPerMinute", skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<f64>,
+    /// Requests that may start at once after an idle period. Defaults to one second's worth of
+    /// `requests_per_minute`, at least one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub burst: Option<u32>,
+    /// Requests in flight at once, counted until their response stream ends.
+    #[serde(rename = "maxConcurrent", skip_serializing_if = "Option::is_none")]
+    pub max_concurrent: Option<usize>,
+}
+
+/// What a request does when a limit has no room for it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitPolicy {
+    /// Queue until the request fits.
+    #[default]
+    Wait,
+    /// Fail at once with [`PiAiErrorCode::RateLimitExceeded`].
+    Fail,
+}
+
+/// Current state of one limit, from [`rate_limit_metrics`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimitMetrics {
+    /// Requests waiting for room right now.
+    pub queued: usize,
+    pub in_flight: usize,
+    /// Requests that had to wait since the limit was set.
+    pub throttled: u64,
+    /// Requests failed under [`RateLimitPolicy::Fail`] since the limit was set.
+    pub rejected: u64,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+#[derive(Debug)]
+struct Limiter {
+    limit: RateLimit,
+    bucket: Mutex<Bucket>,
+    concurrency: Option<Arc<Semaphore>>,
+    queued: AtomicUsize,
+    in_flight: Arc<AtomicUsize>,
+    throttled: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl Limiter {
+    fn new(limit: RateLimit) -> Self {
+        let concurrency = limit
+            .max_concurrent
+            .map(|max| Arc::new(Semaphore::new(max.max(1))));
+        let bucket = Bucket {
+            tokens: burst_size(&limit),
+            refilled_at: Instant::now(),
+        };
+        Self {
+            limit,
+            bucket: Mutex::new(bucket),
+            concurrency,
+            queued: AtomicUsize::new(0),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            throttled: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// Takes a request token, or returns how long until one is available.
+    fn take_token(&self) -> Result<(), Duration> {
+        let Some(per_minute) = self.limit.requests_per_minute.filter(|rate| *rate > 0.0) else {
+            return Ok(());
+        };
+        let per_second = per_minute / 60.0;
+        let mut bucket = self
+            .bucket
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_second).min(burst_size(&self.limit));
+        bucket.refilled_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+        }
+    }
+
+    async fn acquire(
+        &self,
+        key: &str,
+        policy: RateLimitPolicy,
+    ) -> Result<LimiterPermit, PiAiError> {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        let result = self.acquire_queued(key, policy).await;
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+        result
+    }
+
+    async fn acquire_queued(
+        &self,
+        key: &str,
+        policy: RateLimitPolicy,
+    ) -> Result<LimiterPermit, PiAiError> {
+        let mut waited = false;
+        // A concurrency slot first, so no request holds a rate token while it waits.
+        let permit = match &self.concurrency {
+            None => None,
+            Some(semaphore) => match semaphore.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) if policy == RateLimitPolicy::Fail => {
+                    return Err(self.reject(key, "concurrent request"));
+                }
+                Err(_) => {
+                    waited = true;
+                    let permit = semaphore
+                        .clone()
+                        .acquire_owned()
+                        .await
+                        .expect("rate limit semaphore is never closed");
+                    Some(permit)
+                }
+            },
+        };
+
+        loop {
+            match self.take_token() {
+                Ok(()) => break,
+                Err(_) if policy == RateLimitPolicy::Fail => {
+                    return Err(self.reject(key, "request rate"));
+                }
+                Err(wait) => {
+                    waited = true;
+                    tokio::time::sleep(wait).await;
+                }
+            }
+        }
+
+        if waited {
+            self.throttled.fetch_add(1, Ordering::SeqCst);
+        }
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        Ok(LimiterPermit {
+            _permit: permit,
+            in_flight: self.in_flight.clone(),
+        })
+    }
+
+    fn reject(&self, key: &str, what: &str) -> PiAiError {
+        self.rejected.fetch_add(1, Ordering::SeqCst);
+        PiAiError::new(
+            PiAiErrorCode::RateLimitExceeded,
+            format!("Client-side {what} limit for '{key}' reached"),
+        )
+    }
+
+    fn metrics(&self) -> RateLimitMetrics {
+        RateLimitMetrics {
+            queued: self.queued.load(Ordering::SeqCst),
+            in_flight: self.in_flight.load(Ordering::SeqCst),
+            throttled: self.throttled.load(Ordering::SeqCst),
+            rejected: self.rejected.load(Ordering::SeqCst),
+        }
+    }
+}
+
+fn burst_size(limit: &RateLimit) -> f64 {
+    match limit.burst {
+        Some(burst) => f64::from(burst.max(1)),
+        None => (limit.requests_per_minute.unwrap_or(0.0) / 60.0).max(1.0),
+    }
+}
+
+#[derive(Debug)]
+struct LimiterPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for LimiterPermit {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Room taken for one request under every limit that applies to it. Dropping it frees the
+/// concurrency slots.
+#[derive(Debug, Default)]
+pub(crate) struct RateLimitPermit {
+    _permits: Vec<LimiterPermit>,
+}
+
+fn limiters() -> &'static RwLock<HashMap<String, Arc<Limiter>>> {
+    static LIMITERS: OnceLock<RwLock<HashMap<String, Arc<Limiter>>>> = OnceLock::new();
+    LIMITERS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Sets the limit for `key`, a provider name or `provider/model-id`. Replacing a limit resets
+/// its counters; requests already in flight keep their slots under the old one.
+pub fn set_rate_limit(key: impl Into<String>, limit: RateLimit) {
+    let key = key.into();
+    let mut limiters = limiters()
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if limiters
+        .get(&key)
+        .is_some_and(|limiter| limiter.limit == limit)
+    {
+        return;
+    }
+    limiters.insert(key, Arc::new(Limiter::new(limit)));
+}
+
+pub fn clear_rate_limit(key: &str) {
+    limiters()
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .remove(key);
+}
+
+pub fn rate_limit(key: &str) -> Option<RateLimit> {
+    limiters()
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(key)
+        .map(|limiter| limiter.limit.clone())
+}
+
+/// Queue depth and counters of every configured limit, by key.
+pub fn rate_limit_metrics() -> BTreeMap<String, RateLimitMetrics> {
+    limiters()
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .map(|(key, limiter)| (key.clone(), limiter.metrics()))
+        .collect()
+}
+
+/// Waits for room for a request to `model`, or fails under [`RateLimitPolicy::Fail`].
+pub(crate) async fn acquire_rate_limit(
+    model: &Model,
+    policy: RateLimitPolicy,
+) -> Result<RateLimitPermit, PiAiError> {
+    let model_key = format!("{}/{}", model.provider, model.id);
+    let applicable = {
+        let limiters = limiters()
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        [model.provider.as_str(), model_key.as_str()]
+            .into_iter()
+            .filter_map(|key| {
+                limiters
+                    .get(key)
+                    .map(|limiter| (key.to_string(), limiter.clone()))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut permits = Vec::with_capacity(applicable.len());
+    for (key, limiter) in applicable {
+        permits.push(limiter.acquire(&key, policy).await?);
+    }
+    Ok(RateLimitPermit { _permits: permits })
+}
@@ -0,0 +1,123 @@
+//! Realtime sessions keep one WebSocket open to a provider and interleave text and audio input
+//! with streamed answers, for voice channels. Each answer streams the same
+//! [`AssistantMessageEvent`] sequence a [`crate::stream`] call produces, so renderers and
+//! transcripts handle both alike.
+
+use std::collections::HashMap;
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use tokio::sync::mpsc;
+
+use crate::error::{PiAiError, PiAiErrorCode};
+use crate::types::{AssistantMessageEvent, Model, Tool};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RealtimeOptions {
+    pub api_key: Option<String>,
+    pub headers: Option<HashMap<String, String>>,
+    pub instructions: Option<String>,
+    pub tools: Vec<Tool>,
+    /// Voice for spoken answers. Without one, answers are text only.
+    pub voice: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+pub enum RealtimeEvent {
+    /// An event of the answer in progress.
+    Message(AssistantMessageEvent),
+    /// Base64 16-bit PCM speech, 24 kHz mono, as it is generated. The whole answer is also
+    /// attached to the final message as a WAV audio block.
+    AudioDelta(String),
+    /// Transcript of what the user said, once the turn is committed.
+    InputTranscript(String),
+    /// The provider heard the user start speaking; clients should stop playback.
+    SpeechStarted,
+    SpeechStopped,
+    /// An error outside any answer, e.g. a rejected input. The session stays open.
+    Error(PiAiError),
+}
+
+/// Input to a session, translated into client events by the provider.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum RealtimeInput {
+    Text(String),
+    Audio(String),
+    CommitAudio,
+    ToolResult { call_id: String, output: String },
+    CancelResponse,
+}
+
+/// An open realtime connection. Dropping it closes the connection.
+#[derive(Debug)]
+pub struct RealtimeSession {
+    inputs: mpsc::UnboundedSender<RealtimeInput>,
+    events: mpsc::UnboundedReceiver<RealtimeEvent>,
+}
+
+impl RealtimeSession {
+    /// Connects to `model`, which must use a realtime API such as `openai-realtime`.
+    pub async fn connect(model: &Model, options: RealtimeOptions) -> Result<Self, PiAiError> {
+        match model.api.as_str() {
+            "openai-realtime" => crate::providers::connect_openai_realtime(model, options).await,
+            api => Err(PiAiError::new(
+                PiAiErrorCode::ProviderProtocol,
+                format!("API '{api}' does not support realtime sessions"),
+            )),
+        }
+    }
+
+    pub(crate) fn new(
+        inputs: mpsc::UnboundedSender<RealtimeInput>,
+        events: mpsc::UnboundedReceiver<RealtimeEvent>,
+    ) -> Self {
+        Self { inputs, events }
+    }
+
+    /// Sends a user message and asks for an answer.
+    pub fn send_text(&self, text: impl Into<String>) -> Result<(), PiAiError> {
+        self.send(RealtimeInput::Text(text.into()))
+    }
+
+    /// Appends 16-bit PCM audio, 24 kHz mono, to the user's turn. The provider detects the end
+    /// of speech and answers on its own; [`Self::commit_audio`] ends the turn early.
+    pub fn append_audio(&self, pcm16: &[u8]) -> Result<(), PiAiError> {
+        self.send(RealtimeInput::Audio(BASE64_STANDARD.encode(pcm16)))
+    }
+
+    /// Ends the user's spoken turn and asks for an answer.
+    pub fn commit_audio(&self) -> Result<(), PiAiError> {
+        self.send(RealtimeInput::CommitAudio)
+    }
+
+    /// Answers a tool call of the last response and asks for the next answer.
+    pub fn send_tool_result(
+        &self,
+        call_id: impl Into<String>,
+        output: impl Into<String>,
+    ) -> Result<(), PiAiError> {
+        self.send(RealtimeInput::ToolResult {
+            call_id: call_id.into(),
+            output: output.into(),
+        })
+    }
+
+    /// Stops the answer in progress, e.g. when the user interrupts it.
+    pub fn cancel_response(&self) -> Result<(), PiAiError> {
+        self.send(RealtimeInput::CancelResponse)
+    }
+
+    /// The next event, or `None` once the connection has closed.
+    pub async fn next_event(&mut self) -> Option<RealtimeEvent> {
+        self.events.recv().await
+    }
+
+    fn send(&self, input: RealtimeInput) -> Result<(), PiAiError> {
+        self.inputs.send(input).map_err(|_| {
+            PiAiError::new(
+                PiAiErrorCode::ProviderTransport,
+                "Realtime session is closed",
+            )
+        })
+    }
+}
@@ -0,0 +1,185 @@
+//! Recording and replay of raw provider responses.
+//!
+//! While recording, every successful provider response body (the SSE stream or JSON document
+//! a provider parses) is appended to a cassette file. While replaying, providers skip the
+//! network entirely and parse the recorded bodies in order, so [`crate::stream`] produces the
+//! same events on every run. The mode is process-wide; tests that use it should not run
+//! concurrently with tests that expect live requests.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::error::{PiAiError, PiAiErrorCode};
+use crate::types::Model;
+
+/// One recorded provider response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub api: String,
+    pub provider: String,
+    pub model: String,
+    /// The raw response body, exactly as the provider received it.
+    pub body: String,
+}
+
+/// An ordered list of recorded responses, stored as JSON on disk.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PiAiError> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path).map_err(|error| {
+            PiAiError::new(
+                PiAiErrorCode::ProviderTransport,
+                format!("Failed to read cassette {}: {error}", path.display()),
+            )
+        })?;
+        serde_json::from_str(&data).map_err(|error| {
+            PiAiError::new(
+                PiAiErrorCode::ProviderProtocol,
+                format!("Invalid cassette {}: {error}", path.display()),
+            )
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), PiAiError> {
+        let path = path.as_ref();
+        let data = serde_json::to_string_pretty(self).map_err(|error| {
+            PiAiError::new(
+                PiAiErrorCode::ProviderProtocol,
+                format!("Failed to serialize cassette: {error}"),
+            )
+        })?;
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            let _ = fs::create_dir_all(parent);
+        }
+        fs::write(path, data).map_err(|error| {
+            PiAiError::new(
+                PiAiErrorCode::ProviderTransport,
+                format!("Failed to write cassette {}: {error}", path.display()),
+            )
+        })
+    }
+}
+
+/// Whether provider responses are currently being recorded or replayed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RecordMode {
+    #[default]
+    Off,
+    Record,
+    Replay,
+}
+
+enum State {
+    Off,
+    Record { path: PathBuf, cassette: Cassette },
+    Replay { remaining: VecDeque<Interaction> },
+}
+
+fn state() -> &'static Mutex<State> {
+    static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+    STATE.get_or_init(|| Mutex::new(State::Off))
+}
+
+fn lock_state() -> std::sync::MutexGuard<'static, State> {
+    state()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Records every provider response to `path`, rewriting the file after each one.
+pub fn start_recording(path: impl Into<PathBuf>) {
+    *lock_state() = State::Record {
+        path: path.into(),
+        cassette: Cassette::default(),
+    };
+}
+
+/// Replays the cassette at `path` instead of sending provider requests.
+pub fn start_replay(path: impl AsRef<Path>) -> Result<(), PiAiError> {
+    let cassette = Cassette::load(path)?;
+    replay_cassette(cassette);
+    Ok(())
+}
+
+/// Replays an in-memory cassette instead of sending provider requests.
+pub fn replay_cassette(cassette: Cassette) {
+    *lock_state() = State::Replay {
+        remaining: cassette.interactions.into(),
+    };
+}
+
+/// Returns to live requests. Interactions not yet replayed are discarded.
+pub fn stop() {
+    *lock_state() = State::Off;
+}
+
+pub fn record_mode() -> RecordMode {
+    match *lock_state() {
+        State::Off => RecordMode::Off,
+        State::Record { .. } => RecordMode::Record,
+        State::Replay { .. } => RecordMode::Replay,
+    }
+}
+
+/// The next recorded body for `model`, or `None` when not replaying.
+///
+/// Replay is strictly ordered: a mismatched api or an exhausted cassette is an error rather
+/// than a fall-through to the network.
+pub(crate) fn next_replayed_body(model: &Model) -> Option<Result<String, PiAiError>> {
+    let mut state = lock_state();
+    let State::Replay { remaining } = &mut *state else {
+        return None;
+    };
+    let Some(interaction) = remaining.pop_front() else {
+        return Some(Err(PiAiError::new(
+            PiAiErrorCode::ProviderProtocol,
+            format!(
+                "Cassette exhausted: no recorded response left for {}/{}",
+                model.provider, model.id
+            ),
+        )));
+    };
+    if interaction.api != model.api {
+        return Some(Err(PiAiError::new(
+            PiAiErrorCode::ProviderProtocol,
+            format!(
+                "Cassette mismatch: next recorded response is for api '{}', request is for '{}'",
+                interaction.api, model.api
+            ),
+        )
+        .with_details(
+            json!({ "provider": interaction.provider, "model": interaction.model }),
+        )));
+    }
+    Some(Ok(interaction.body))
+}
+
+/// Appends `body` to the active recording, if any.
+pub(crate) fn record_body(model: &Model, body: &str) {
+    let mut state = lock_state();
+    let State::Record { path, cassette } = &mut *state else {
+        return;
+    };
+    cassette.interactions.push(Interaction {
+        api: model.api.clone(),
+        provider: model.provider.clone(),
+        model: model.id.clone(),
+        body: body.to_string(),
+    });
+    if let Err(error) = cassette.save(path.as_path()) {
+        tracing::warn!("{}", error.message);
+    }
+}
@@ -0,0 +1,435 @@
+//! Caching of finished responses, so identical [`crate::complete`] and
+//! [`crate::complete_simple`] requests are answered without calling the provider again.
+//!
+//! A request is identified by a hash of the model, the context as sent (message timestamps and
+//! usage left out) and its serializable options (API key and headers left out). Only answers
+//! that finished normally are stored. A cache set with [`set_response_cache`] serves every
+//! request; `StreamOptions::response_cache` replaces it for one request.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::types::{AssistantMessage, Context, Model, StopReason};
+
+/// Storage for cached responses, keyed by a hash of the request.
+pub trait ResponseCache: Send + Sync {
+    /// The response stored under `key`, unless it is missing or expired.
+    fn get(&self, key: &str) -> Option<AssistantMessage>;
+
+    /// Stores `message` under `key`, evicting older entries to stay within the cache's limits.
+    fn put(&self, key: &str, message: &AssistantMessage);
+}
+
+pub type ResponseCacheRef = Arc<dyn ResponseCache>;
+
+impl std::fmt::Debug for dyn ResponseCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ResponseCache(..)")
+    }
+}
+
+impl PartialEq for dyn ResponseCache {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(self, other)
+    }
+}
+
+/// Keeps responses in process memory, for test suites and short-lived tools.
+#[derive(Debug)]
+pub struct MemoryResponseCache {
+    ttl: Option<Duration>,
+    max_entries: usize,
+    entries: Mutex<HashMap<String, (Instant, AssistantMessage)>>,
+}
+
+impl MemoryResponseCache {
+    /// Holds at most `max_entries` responses, dropping the oldest first. Entries older than
+    /// `ttl` are never served.
+    pub fn new(max_entries: usize, ttl: Option<Duration>) -> Self {
+        Self {
+            ttl,
+            max_entries: max_entries.max(1),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock_entries().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock_entries(
+        &self,
+    ) -> std::sync::MutexGuard<'_, HashMap<String, (Instant, AssistantMessage)>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl ResponseCache for MemoryResponseCache {
+    fn get(&self, key: &str) -> Option<AssistantMessage> {
+        let mut entries = self.lock_entries();
+        let (stored_at, message) = entries.get(key)?;
+        if self.ttl.is_some_and(|ttl| stored_at.elapsed() > ttl) {
+            entries.remove(key);
+            return None;
+        }
+        Some(message.clone())
+    }
+
+    fn put(&self, key: &str, message: &AssistantMessage) {
+        let mut entries = self.lock_entries();
+        entries.insert(key.to_string(), (Instant::now(), message.clone()));
+        while entries.len() > self.max_entries {
+            let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, (stored_at, _))| *stored_at)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            entries.remove(&oldest);
+        }
+    }
+}
+
+/// Keeps responses as one JSON file per request in a directory, so they survive across runs.
+#[derive(Debug, Clone)]
+pub struct FileResponseCache {
+    dir: PathBuf,
+    ttl: Option<Duration>,
+    max_bytes: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    #[serde(rename = "storedAt")]
+    stored_at: u64,
+    message: AssistantMessage,
+}
+
+impl FileResponseCache {
+    /// Stores responses under `dir`, created on first write. Entries older than `ttl` are never
+    /// served, and the oldest files are deleted once the directory holds more than
+    /// `max_bytes`.
+    pub fn new(dir: impl Into<PathBuf>, ttl: Option<Duration>, max_bytes: Option<u64>) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+            max_bytes,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+
+    fn write_entry(&self, key: &str, message: &AssistantMessage) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let entry = CachedResponse {
+            stored_at: unix_millis(),
+            message: message.clone(),
+        };
+        let data = serde_json::to_vec(&entry).map_err(std::io::Error::other)?;
+        // Written aside and renamed, so a concurrent reader never sees a partial file.
+        let partial = self.dir.join(format!("{key}.json.tmp"));
+        fs::write(&partial, data)?;
+        fs::rename(&partial, self.entry_path(key))
+    }
+
+    fn enforce_max_bytes(&self) -> std::io::Result<()> {
+        let Some(max_bytes) = self.max_bytes else {
+            return Ok(());
+        };
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            files.push((metadata.modified()?, metadata.len(), path));
+        }
+        let mut total = files.iter().map(|(_, len, _)| len).sum::<u64>();
+        files.sort();
+        for (_, len, path) in files {
+            if total <= max_bytes {
+                break;
+            }
+            fs::remove_file(path)?;
+            total = total.saturating_sub(len);
+        }
+        Ok(())
+    }
+}
+
+impl ResponseCache for FileResponseCache {
+    fn get(&self, key: &str) -> Option<AssistantMessage> {
+        let path = self.entry_path(key);
+        let data = fs::read(&path).ok()?;
+        let entry = serde_json::from_slice::<CachedResponse>(&data).ok()?;
+        let age = Duration::from_millis(unix_millis().saturating_sub(entry.stored_at));
+        if self.ttl.is_some_and(|ttl| age > ttl) {
+            let _ = fs::remove_file(path);
+            return None;
+        }
+        Some(entry.message)
+    }
+
+    fn put(&self, key: &str, message: &AssistantMessage) {
+        if let Err(error) = self
+            .write_entry(key, message)
+            .and_then(|()| self.enforce_max_bytes())
+        {
+            tracing::warn!(
+                "Failed to write response cache {}: {error}",
+                self.dir.display()
+            );
+        }
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn global_cache() -> &'static RwLock<Option<ResponseCacheRef>> {
+    static CACHE: OnceLock<RwLock<Option<ResponseCacheRef>>> = OnceLock::new();
+    CACHE.get_or_init(|| RwLock::new(None))
+}
+
+/// Sets the cache every request without its own `StreamOptions::response_cache` uses.
+pub fn set_response_cache(cache: ResponseCacheRef) {
+    *global_cache()
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(cache);
+}
+
+pub fn clear_response_cache() {
+    *global_cache()
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+}
+
+/// The cache a request with `own` as its per-request cache uses.
+pub(crate) fn response_cache_for(own: Option<&ResponseCacheRef>) -> Option<ResponseCacheRef> {
+    own.cloned().or_else(|| {
+        global_cache()
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    })
+}
+
+/// Hex SHA-256 identifying a request: what is sent to the provider, without credentials.
+/// `entry` tells [`crate::complete`] and [`crate::complete_simple`] requests apart, and
+/// `options` is the [`crate::StreamOptions`] or [`crate::SimpleStreamOptions`] passed to it.
+pub(crate) fn response_cache_key(
+    entry: &str,
+    model: &Model,
+    context: &Context,
+    options: Option<&impl Serialize>,
+) -> String {
+    let mut context = serde_json::to_value(context).unwrap_or(Value::Null);
+    // A fork asks the same question as the history it copied.
+    if let Some(context) = context.as_object_mut() {
+        context.remove("branch");
+    }
+    if let Some(messages) = context.get_mut("messages").and_then(Value::as_array_mut) {
+        for message in messages.iter_mut().filter_map(Value::as_object_mut) {
+            for volatile in ["timestamp", "usage", "stats"] {
+                message.remove(volatile);
+            }
+        }
+    }
+    let mut options = serde_json::to_value(options).unwrap_or(Value::Null);
+    if let Some(options) = options.as_object_mut() {
+        for credential in ["apiKey", "headers"] {
+            options.remove(credential);
+        }
+    }
+    let request = serde_json::json!({
+        "entry": entry,
+        "api": model.api,
+        "provider": model.provider,
+        "model": model.id,
+        "baseUrl": model.base_url,
+        "context": context,
+        "options": options,
+    });
+    let digest = Sha256::digest(request.to_string().as_bytes());
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Whether `message` is a complete answer worth serving again.
+pub(crate) fn is_cacheable(message: &AssistantMessage) -> bool {
+    matches!(
+        message.stop_reason,
+        StopReason::Stop | StopReason::Length | StopReason::ToolUse
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AssistantContentBlock, Cost, Message, Usage, UserContent};
+
+    fn model() -> Model {
+        Model {
+            id: "gpt-5.3-codex".to_string(),
+            name: "GPT-5.3 Codex".to_string(),
+            api: "openai-responses".to_string(),
+            provider: "openai".to_string(),
+            base_url: "https://api.openai.com/v1".to_string(),
+            reasoning: false,
+            reasoning_effort: None,
+            input: vec!["text".to_string()],
+            cost: Cost {
+                input: 0.0,
+                output: 0.0,
+                cache_read: 0.0,
+                cache_write: 0.0,
+                total: 0.0,
+            },
+            context_window: 128_000,
+            max_tokens: 8_192,
+            deployment: None,
+            api_version: None,
+            compat: None,
+        }
+    }
+
+    fn context(timestamp: i64) -> Context {
+        Context {
+            system_prompt: Some("Be brief.".to_string()),
+            system_prompt_cache: None,
+            branch: None,
+            messages: vec![Message::User {
+                content: UserContent::Text("hi".to_string()),
+                timestamp,
+            }],
+            tools: None,
+        }
+    }
+
+    fn answer(text: &str) -> AssistantMessage {
+        AssistantMessage {
+            role: "assistant".to_string(),
+            content: vec![AssistantContentBlock::Text {
+                text: text.to_string(),
+                text_signature: None,
+            }],
+            api: "openai-responses".to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-5.3-codex".to_string(),
+            usage: Usage {
+                input: 0,
+                output: 0,
+                cache_read: 0,
+                cache_write: 0,
+                reasoning: 0,
+                total_tokens: 0,
+                cost: Cost {
+                    input: 0.0,
+                    output: 0.0,
+                    cache_read: 0.0,
+                    cache_write: 0.0,
+                    total: 0.0,
+                },
+            },
+            stop_reason: StopReason::Stop,
+            error_message: None,
+            timestamp: 0,
+            stats: None,
+        }
+    }
+
+    #[test]
+    fn key_ignores_timestamps_and_credentials() {
+        let with_key = crate::StreamOptions {
+            api_key: Some("sk-one".to_string()),
+            ..crate::StreamOptions::default()
+        };
+        let other_key = crate::StreamOptions {
+            api_key: Some("sk-two".to_string()),
+            ..crate::StreamOptions::default()
+        };
+        assert_eq!(
+            response_cache_key("complete", &model(), &context(1), Some(&with_key)),
+            response_cache_key("complete", &model(), &context(2), Some(&other_key))
+        );
+
+        let hotter = crate::StreamOptions {
+            temperature: Some(0.9),
+            ..crate::StreamOptions::default()
+        };
+        assert_ne!(
+            response_cache_key("complete", &model(), &context(1), Some(&with_key)),
+            response_cache_key("complete", &model(), &context(1), Some(&hotter))
+        );
+    }
+
+    #[test]
+    fn memory_cache_evicts_the_oldest_entry_and_expires_entries() {
+        let cache = MemoryResponseCache::new(2, None);
+        cache.put("a", &answer("a"));
+        cache.put("b", &answer("b"));
+        cache.put("c", &answer("c"));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.get("c"), Some(answer("c")));
+
+        let expiring = MemoryResponseCache::new(8, Some(Duration::ZERO));
+        expiring.put("a", &answer("a"));
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(expiring.get("a").is_none());
+        assert!(expiring.is_empty());
+    }
+
+    #[test]
+    fn file_cache_round_trips_and_stays_under_its_size_limit() {
+        let dir = std::env::temp_dir().join(format!(
+            "pixy-response-cache-{}-{}",
+            std::process::id(),
+            unix_millis()
+        ));
+        let cache = FileResponseCache::new(&dir, None, None);
+        cache.put("first", &answer("first"));
+        assert_eq!(cache.get("first"), Some(answer("first")));
+        assert!(cache.get("missing").is_none());
+
+        let entry_size = fs::metadata(dir.join("first.json")).expect("entry").len();
+        let bounded = FileResponseCache::new(&dir, None, Some(entry_size * 2));
+        std::thread::sleep(Duration::from_millis(10));
+        bounded.put("second", &answer("second"));
+        std::thread::sleep(Duration::from_millis(10));
+        bounded.put("third", &answer("third"));
+        assert!(bounded.get("first").is_none());
+        assert_eq!(bounded.get("third"), Some(answer("third")));
+
+        let expired = FileResponseCache::new(&dir, Some(Duration::ZERO), None);
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(expired.get("third").is_none());
+        assert!(!dir.join("third.json").exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
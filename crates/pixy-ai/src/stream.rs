@@ -1,9 +1,32 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
 use crate::api_registry::get_api_provider;
 use crate::error::{PiAiError, PiAiErrorCode};
-use crate::providers::ensure_builtin_api_providers_registered;
+use crate::event_stream::EventStream;
+use crate::middleware::{
+    apply_request_middleware, collect_middleware, run_with_middleware, StreamMiddlewareRef,
+};
+use crate::pricing::price_message;
+use crate::providers::{
+    ensure_builtin_api_providers_registered, run_openai_batch, OpenAiBatchItem,
+};
+use crate::rate_limit::{acquire_rate_limit, RateLimitPolicy};
+use crate::response_cache::{
+    is_cacheable, response_cache_for, response_cache_key, ResponseCacheRef,
+};
+use crate::telemetry::run_with_telemetry;
 use crate::types::{
-    AssistantMessage, Context, Cost, Model, SimpleStreamOptions, StopReason, StreamOptions, Usage,
+    AssistantContentBlock, AssistantMessage, AssistantMessageEvent, Context, Cost, DoneReason,
+    ErrorReason, Model, SimpleStreamOptions, StopPredicate, StopReason, StreamOptions, Usage,
 };
+use crate::validation::validate_structured_response;
 use crate::{AssistantMessageEventStream, AssistantStreamWriter};
 
 fn resolve_provider(api: &str) -> Result<crate::ApiProviderRef, PiAiError> {
@@ -23,35 +46,108 @@ pub fn stream(
 ) -> Result<AssistantMessageEventStream, PiAiError> {
     let provider = resolve_provider(&model.api)?;
     let stream = AssistantMessageEventStream::new();
-    let writer = AssistantStreamWriter::new(stream.clone());
+    let stop_predicate = options
+        .as_ref()
+        .and_then(|options| options.stop_predicate.clone());
+    let middleware = collect_middleware(options.as_ref());
     let error_model = model.clone();
-    spawn_provider_task(async move {
-        let result = provider
-            .stream(model, context, options, writer.stream())
-            .await;
-        if let Err(error) = result {
-            writer.error(
-                crate::types::ErrorReason::Error,
-                transport_error_message(&error_model, error),
-            );
-        }
-        writer.close();
-    });
+    let telemetry_model = model.clone();
+    let target = stream.clone();
+    spawn_provider_task(run_with_middleware(
+        middleware.clone(),
+        target,
+        move |target| {
+            run_with_telemetry(telemetry_model, target, move |target| {
+                run_with_stop_predicate(stop_predicate, target, move |writer| async move {
+                    let result = match apply_request_middleware(
+                        &middleware,
+                        model,
+                        context,
+                        options,
+                    )
+                    .await
+                    {
+                        Ok((model, context, options)) => {
+                            let policy = rate_limit_policy(options.as_ref());
+                            match acquire_rate_limit(&model, policy).await {
+                                Ok(_permit) => {
+                                    provider
+                                        .stream(model, context, options, writer.stream())
+                                        .await
+                                }
+                                Err(error) => Err(error),
+                            }
+                        }
+                        Err(error) => Err(error),
+                    };
+                    if let Err(error) = result {
+                        writer.error(
+                            crate::types::ErrorReason::Error,
+                            transport_error_message(&error_model, error),
+                        );
+                    }
+                    writer.close();
+                })
+            })
+        },
+    ));
     Ok(stream)
 }
 
+fn rate_limit_policy(options: Option<&StreamOptions>) -> RateLimitPolicy {
+    options
+        .and_then(|options| options.rate_limit_policy)
+        .unwrap_or_default()
+}
+
 pub async fn complete(
     model: Model,
     context: Context,
     options: Option<StreamOptions>,
 ) -> Result<AssistantMessage, PiAiError> {
+    let cache = response_cache_for(
+        options
+            .as_ref()
+            .and_then(|options| options.response_cache.as_ref()),
+    );
+    let key = cache
+        .as_ref()
+        .map(|_| response_cache_key("complete", &model, &context, options.as_ref()));
+    if let Some(message) = cached_response(cache.as_ref(), key.as_deref()) {
+        return Ok(message);
+    }
     let event_stream = stream(model, context, options)?;
-    event_stream.result().await.ok_or_else(|| {
+    let message = event_stream.result().await.ok_or_else(|| {
         PiAiError::new(
             PiAiErrorCode::ProviderProtocol,
             "Stream ended without terminal message",
         )
-    })
+    })?;
+    store_response(cache.as_ref(), key.as_deref(), &message);
+    Ok(message)
+}
+
+fn cached_response(
+    cache: Option<&ResponseCacheRef>,
+    key: Option<&str>,
+) -> Option<AssistantMessage> {
+    cache?.get(key?)
+}
+
+/// Stores a complete answer without its stats, which describe a call a cache hit never makes.
+fn store_response(cache: Option<&ResponseCacheRef>, key: Option<&str>, message: &AssistantMessage) {
+    let (Some(cache), Some(key)) = (cache, key) else {
+        return;
+    };
+    if is_cacheable(message) {
+        cache.put(
+            key,
+            &AssistantMessage {
+                stats: None,
+                ..message.clone()
+            },
+        );
+    }
 }
 
 pub fn stream_simple(
@@ -61,20 +157,54 @@ pub fn stream_simple(
 ) -> Result<AssistantMessageEventStream, PiAiError> {
     let provider = resolve_provider(&model.api)?;
     let stream = AssistantMessageEventStream::new();
-    let writer = AssistantStreamWriter::new(stream.clone());
+    let stop_predicate = options
+        .as_ref()
+        .and_then(|options| options.stream.stop_predicate.clone());
+    let middleware = collect_middleware(options.as_ref().map(|options| &options.stream));
     let error_model = model.clone();
-    spawn_provider_task(async move {
-        let result = provider
-            .stream_simple(model, context, options, writer.stream())
-            .await;
-        if let Err(error) = result {
-            writer.error(
-                crate::types::ErrorReason::Error,
-                transport_error_message(&error_model, error),
-            );
-        }
-        writer.close();
-    });
+    let telemetry_model = model.clone();
+    let target = stream.clone();
+    spawn_provider_task(run_with_middleware(
+        middleware.clone(),
+        target,
+        move |target| {
+            run_with_telemetry(telemetry_model, target, move |target| {
+                run_with_stop_predicate(stop_predicate, target, move |writer| async move {
+                    let reasoning = options
+                        .as_ref()
+                        .and_then(|options| options.reasoning.clone());
+                    let stream_options = options.map(|options| options.stream);
+                    let result =
+                        match apply_request_middleware(&middleware, model, context, stream_options)
+                            .await
+                        {
+                            Ok((model, context, stream_options)) => {
+                                let policy = rate_limit_policy(stream_options.as_ref());
+                                match acquire_rate_limit(&model, policy).await {
+                                    Ok(_permit) => {
+                                        let options = stream_options.map(|stream| {
+                                            SimpleStreamOptions { stream, reasoning }
+                                        });
+                                        provider
+                                            .stream_simple(model, context, options, writer.stream())
+                                            .await
+                                    }
+                                    Err(error) => Err(error),
+                                }
+                            }
+                            Err(error) => Err(error),
+                        };
+                    if let Err(error) = result {
+                        writer.error(
+                            crate::types::ErrorReason::Error,
+                            transport_error_message(&error_model, error),
+                        );
+                    }
+                    writer.close();
+                })
+            })
+        },
+    ));
     Ok(stream)
 }
 
@@ -83,12 +213,308 @@ pub async fn complete_simple(
     context: Context,
     options: Option<SimpleStreamOptions>,
 ) -> Result<AssistantMessage, PiAiError> {
+    let cache = response_cache_for(
+        options
+            .as_ref()
+            .and_then(|options| options.stream.response_cache.as_ref()),
+    );
+    let key = cache
+        .as_ref()
+        .map(|_| response_cache_key("complete_simple", &model, &context, options.as_ref()));
+    if let Some(message) = cached_response(cache.as_ref(), key.as_deref()) {
+        return Ok(message);
+    }
     let event_stream = stream_simple(model, context, options)?;
-    event_stream.result().await.ok_or_else(|| {
+    let message = event_stream.result().await.ok_or_else(|| {
         PiAiError::new(
             PiAiErrorCode::ProviderProtocol,
             "Stream ended without terminal message",
         )
+    })?;
+    store_response(cache.as_ref(), key.as_deref(), &message);
+    Ok(message)
+}
+
+/// One request in a [`complete_batch`] call.
+#[derive(Debug, Clone)]
+pub struct BatchRequest {
+    pub model: Model,
+    pub context: Context,
+    pub options: Option<StreamOptions>,
+}
+
+/// How [`complete_batch`] schedules its requests.
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// Requests in flight at once when fanning out through [`complete`].
+    pub concurrency: usize,
+    /// Submits `openai-completions` requests as OpenAI Batch API jobs, one per provider and
+    /// base URL. Jobs are billed at a discount but may take up to 24 hours, so this is for
+    /// offline work such as evaluations. Other requests still fan out.
+    pub provider_batch: bool,
+    /// How often a submitted batch job is checked for completion.
+    pub poll_interval: Duration,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            provider_batch: false,
+            poll_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Results of [`complete_batch`] as `(index, message)` pairs, in completion order.
+pub type BatchResultStream = EventStream<(usize, AssistantMessage), ()>;
+
+/// Completes every request without streaming and yields each final message tagged with the
+/// request's index.
+///
+/// Each request gets exactly one message; failures arrive as messages with
+/// `StopReason::Error`, like [`complete`] returns them. The stream ends once all have arrived.
+pub fn complete_batch(requests: Vec<BatchRequest>, options: BatchOptions) -> BatchResultStream {
+    let results = BatchResultStream::new(|_| None);
+    let target = results.clone();
+    spawn_provider_task(async move {
+        let mut provider_jobs = BTreeMap::<(String, String), Vec<(usize, BatchRequest)>>::new();
+        let mut fan_out = Vec::new();
+        for (index, request) in requests.into_iter().enumerate() {
+            if options.provider_batch && request.model.api == "openai-completions" {
+                let key = (
+                    request.model.provider.clone(),
+                    request.model.base_url.clone(),
+                );
+                provider_jobs.entry(key).or_default().push((index, request));
+            } else {
+                fan_out.push((index, request));
+            }
+        }
+
+        let mut tasks = JoinSet::new();
+        for job in provider_jobs.into_values() {
+            tasks.spawn(run_provider_batch(
+                job,
+                options.poll_interval,
+                target.clone(),
+            ));
+        }
+        let permits = Arc::new(Semaphore::new(options.concurrency.max(1)));
+        for (index, request) in fan_out {
+            let permits = Arc::clone(&permits);
+            let target = target.clone();
+            tasks.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                let error_model = request.model.clone();
+                let message = complete(request.model, request.context, request.options)
+                    .await
+                    .unwrap_or_else(|error| transport_error_message(&error_model, error));
+                target.push((index, message));
+            });
+        }
+        while tasks.join_next().await.is_some() {}
+        target.end(Some(()));
+    });
+    results
+}
+
+/// Runs OpenAI Batch API jobs for `requests`, one per provider, base URL, API key and headers
+/// as they stand after the request middleware. Middleware sees each request and result as it
+/// would through [`stream`], each result as the terminal event; a job that fails as a whole
+/// fails each of its requests.
+async fn run_provider_batch(
+    requests: Vec<(usize, BatchRequest)>,
+    poll_interval: Duration,
+    target: BatchResultStream,
+) {
+    let mut jobs = BTreeMap::<_, (Vec<OpenAiBatchItem>, Vec<Vec<StreamMiddlewareRef>>)>::new();
+    for (index, request) in requests {
+        let request_middleware = collect_middleware(request.options.as_ref());
+        let error_model = request.model.clone();
+        match apply_request_middleware(
+            &request_middleware,
+            request.model,
+            request.context,
+            request.options,
+        )
+        .await
+        {
+            Ok((model, context, options)) => {
+                let item = OpenAiBatchItem {
+                    index,
+                    model,
+                    context,
+                    options,
+                };
+                let (items, middleware) = jobs.entry(item.batch_key()).or_default();
+                items.push(item);
+                middleware.push(request_middleware);
+            }
+            Err(error) => {
+                let message = transport_error_message(&error_model, error);
+                for middleware in &request_middleware {
+                    middleware.on_complete(&message);
+                }
+                target.push((index, message));
+            }
+        }
+    }
+
+    for (items, middleware) in jobs.into_values() {
+        let messages = match run_openai_batch(&items, poll_interval).await {
+            Ok(messages) => messages,
+            Err(error) => items
+                .iter()
+                .map(|item| {
+                    (
+                        item.index,
+                        transport_error_message(&item.model, error.clone()),
+                    )
+                })
+                .collect(),
+        };
+        for ((index, mut message), (item, middleware)) in
+            messages.into_iter().zip(items.iter().zip(&middleware))
+        {
+            price_message(&item.model, &mut message);
+            let message = apply_terminal_middleware(middleware, message);
+            for middleware in middleware {
+                middleware.on_complete(&message);
+            }
+            target.push((index, message));
+        }
+    }
+}
+
+/// Passes a finished message through the `on_event` hooks as the event a stream would end
+/// with, and returns the message that event carries afterwards. A hook that turns the event
+/// into a non-terminal one fails the request with an error message instead.
+fn apply_terminal_middleware(
+    middleware: &[StreamMiddlewareRef],
+    message: AssistantMessage,
+) -> AssistantMessage {
+    let original = message.clone();
+    let mut event = match message.stop_reason {
+        StopReason::Stop => AssistantMessageEvent::Done {
+            reason: DoneReason::Stop,
+            message,
+        },
+        StopReason::Length => AssistantMessageEvent::Done {
+            reason: DoneReason::Length,
+            message,
+        },
+        StopReason::ToolUse => AssistantMessageEvent::Done {
+            reason: DoneReason::ToolUse,
+            message,
+        },
+        StopReason::Error => AssistantMessageEvent::Error {
+            reason: ErrorReason::Error,
+            error: message,
+        },
+        StopReason::Aborted => AssistantMessageEvent::Error {
+            reason: ErrorReason::Aborted,
+            error: message,
+        },
+    };
+    for middleware in middleware {
+        middleware.on_event(&mut event);
+    }
+    match event {
+        AssistantMessageEvent::Done { message, .. } => message,
+        AssistantMessageEvent::Error { error, .. } => error,
+        _ => AssistantMessage {
+            content: vec![],
+            stop_reason: StopReason::Error,
+            error_message: Some(
+                PiAiError::new(
+                    PiAiErrorCode::ProviderProtocol,
+                    "Stream middleware replaced a terminal event with a non-terminal one",
+                )
+                .as_compact_json(),
+            ),
+            ..original
+        },
+    }
+}
+
+/// Completes with `options.response_format` and returns the response as typed data.
+///
+/// The response is validated against the schema before it is deserialized, so a model that
+/// ignores the schema surfaces as an error rather than as partially filled data.
+pub async fn complete_structured<T: DeserializeOwned>(
+    model: Model,
+    context: Context,
+    options: Option<StreamOptions>,
+) -> Result<T, PiAiError> {
+    let format = options
+        .as_ref()
+        .and_then(|options| options.response_format.clone())
+        .ok_or_else(|| {
+            PiAiError::new(
+                PiAiErrorCode::SchemaInvalid,
+                "complete_structured requires `StreamOptions.response_format`",
+            )
+        })?;
+
+    let message = complete(model, context, options).await?;
+    if matches!(message.stop_reason, StopReason::Error | StopReason::Aborted) {
+        return Err(PiAiError::new(
+            PiAiErrorCode::ProviderProtocol,
+            message
+                .error_message
+                .unwrap_or_else(|| "Structured completion failed".to_string()),
+        ));
+    }
+
+    let value = structured_response_value(&message, &format.name)?;
+    validate_structured_response(&format, &value)?;
+    serde_json::from_value(value).map_err(|error| {
+        PiAiError::new(
+            PiAiErrorCode::ProviderProtocol,
+            format!(
+                "Response for schema '{}' did not deserialize: {error}",
+                format.name
+            ),
+        )
+    })
+}
+
+/// Prefers the forced tool call (Anthropic) and falls back to JSON in the text blocks.
+fn structured_response_value(
+    message: &AssistantMessage,
+    schema_name: &str,
+) -> Result<Value, PiAiError> {
+    let tool_arguments = message.content.iter().find_map(|block| match block {
+        AssistantContentBlock::ToolCall {
+            name, arguments, ..
+        } if name == schema_name => Some(arguments.clone()),
+        _ => None,
+    });
+    if let Some(arguments) = tool_arguments {
+        return Ok(arguments);
+    }
+
+    let text = message
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            AssistantContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<String>();
+    let trimmed = text.trim();
+    let json_text = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .unwrap_or(trimmed);
+    serde_json::from_str(json_text.trim()).map_err(|error| {
+        PiAiError::new(
+            PiAiErrorCode::ProviderProtocol,
+            format!("Response for schema '{schema_name}' is not valid JSON: {error}"),
+        )
+        .with_details(serde_json::json!({ "text": text }))
     })
 }
 
@@ -104,6 +530,7 @@ fn transport_error_message(model: &Model, error: PiAiError) -> AssistantMessage
             output: 0,
             cache_read: 0,
             cache_write: 0,
+            reasoning: 0,
             total_tokens: 0,
             cost: Cost {
                 input: 0.0,
@@ -119,7 +546,68 @@ fn transport_error_message(model: &Model, error: PiAiError) -> AssistantMessage
             .duration_since(std::time::UNIX_EPOCH)
             .map(|duration| duration.as_millis() as i64)
             .unwrap_or(0),
+        stats: None,
+    }
+}
+
+/// Runs `run` against `target`, or, with a stop predicate, against an inner stream whose
+/// events are forwarded until the predicate fires. Firing drops the provider future, which
+/// cancels the request, and ends `target` with the partial message marked aborted.
+async fn run_with_stop_predicate<F, Fut>(
+    stop_predicate: Option<StopPredicate>,
+    target: AssistantMessageEventStream,
+    run: F,
+) where
+    F: FnOnce(AssistantStreamWriter) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    let Some(stop_predicate) = stop_predicate else {
+        run(AssistantStreamWriter::new(target)).await;
+        return;
+    };
+
+    let source = AssistantMessageEventStream::new();
+    let provider_run = run(AssistantStreamWriter::new(source.clone()));
+    let forward = forward_until_stop(&source, &target, &stop_predicate);
+    tokio::pin!(provider_run);
+    tokio::pin!(forward);
+
+    // A finished provider future must not be polled again, so remember which side won.
+    let (stopped, provider_finished) = tokio::select! {
+        stopped = &mut forward => (stopped, false),
+        _ = &mut provider_run => (forward.await, true),
+    };
+    if !stopped && !provider_finished {
+        provider_run.await;
+    }
+    target.end(None);
+}
+
+async fn forward_until_stop(
+    source: &AssistantMessageEventStream,
+    target: &AssistantMessageEventStream,
+    stop_predicate: &StopPredicate,
+) -> bool {
+    let mut text = String::new();
+    while let Some(event) = source.next().await {
+        let stop_partial = match &event {
+            AssistantMessageEvent::TextDelta { delta, partial, .. } => {
+                text.push_str(delta);
+                stop_predicate.should_stop(&text).then(|| partial.clone())
+            }
+            _ => None,
+        };
+        target.push(event);
+        if let Some(mut partial) = stop_partial {
+            partial.stop_reason = StopReason::Aborted;
+            target.push(AssistantMessageEvent::Error {
+                reason: ErrorReason::Aborted,
+                error: partial,
+            });
+            return true;
+        }
     }
+    false
 }
 
 fn spawn_provider_task<F>(task: F)
@@ -0,0 +1,351 @@
+//! Tracing spans and counters for every provider call made through [`crate::stream`].
+//!
+//! Each call runs inside an `llm_call` span recording the model, provider, latency to the first
+//! token, output tokens per second, reasoning tokens, cost and, on failure, the error code. The
+//! final message is priced here when the provider did not price it. Counters are kept in-process
+//! ([`llm_metrics`]) and, with the `otel` feature, are also reported to the global
+//! OpenTelemetry meter provider under the `pixy-ai` meter.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::field::Empty;
+use tracing::{Instrument, Span};
+
+use crate::error::PiAiError;
+use crate::pricing::price_message;
+use crate::types::{AssistantMessage, AssistantMessageEvent, Model, StopReason, StreamStats};
+use crate::AssistantMessageEventStream;
+
+/// Provider calls made through [`crate::stream`] and [`crate::stream_simple`] since process
+/// start.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LlmMetrics {
+    pub requests: u64,
+    /// Calls that ended with `StopReason::Error`.
+    pub errors: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+static REQUESTS: AtomicU64 = AtomicU64::new(0);
+static ERRORS: AtomicU64 = AtomicU64::new(0);
+static INPUT_TOKENS: AtomicU64 = AtomicU64::new(0);
+static OUTPUT_TOKENS: AtomicU64 = AtomicU64::new(0);
+
+pub fn llm_metrics() -> LlmMetrics {
+    LlmMetrics {
+        requests: REQUESTS.load(Ordering::SeqCst),
+        errors: ERRORS.load(Ordering::SeqCst),
+        input_tokens: INPUT_TOKENS.load(Ordering::SeqCst),
+        output_tokens: OUTPUT_TOKENS.load(Ordering::SeqCst),
+    }
+}
+
+/// What a provider reports about the call running on its task, read into its [`StreamStats`].
+#[derive(Default)]
+struct CallState {
+    retries: AtomicU32,
+    upstream_provider: Mutex<Option<String>>,
+    /// The attempt that produced the delivered events, when a wrapper such as
+    /// [`crate::ReliableProvider`] held them back until it finished.
+    attempt: Mutex<Option<AttemptTiming>>,
+}
+
+#[derive(Clone, Copy)]
+struct AttemptTiming {
+    started: Instant,
+    first_token_at: Option<Instant>,
+}
+
+tokio::task_local! {
+    static CALL_STATE: Arc<CallState>;
+}
+
+/// Counts a retry or fallback against the call in progress, if any.
+pub(crate) fn record_call_retry() {
+    let _ = CALL_STATE.try_with(|state| state.retries.fetch_add(1, Ordering::SeqCst));
+}
+
+/// Names the provider an aggregator such as OpenRouter routed the call in progress to.
+pub(crate) fn record_upstream_provider(provider: &str) {
+    let _ = CALL_STATE.try_with(|state| {
+        *state
+            .upstream_provider
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(provider.to_string());
+    });
+}
+
+/// Forgets the timing of an earlier attempt of the call in progress, before a new one starts.
+pub(crate) fn start_call_attempt() {
+    let _ = CALL_STATE.try_with(|state| {
+        *state
+            .attempt
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    });
+}
+
+/// Times the call in progress by the attempt whose buffered events are about to be delivered,
+/// unless a provider nested inside that attempt already did.
+pub(crate) fn record_call_attempt(started: Instant, first_token_at: Option<Instant>) {
+    let _ = CALL_STATE.try_with(|state| {
+        state
+            .attempt
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get_or_insert(AttemptTiming {
+                started,
+                first_token_at,
+            });
+    });
+}
+
+/// Measurements for one finished call.
+struct CallRecord<'a> {
+    model: &'a Model,
+    message: &'a AssistantMessage,
+    duration: Duration,
+    time_to_first_token: Option<Duration>,
+    tokens_per_sec: Option<f64>,
+    error_code: Option<String>,
+}
+
+/// Runs `run` inside an `llm_call` span against an inner stream, forwarding its events to
+/// `target` and recording the call once the terminal event arrives.
+pub(crate) async fn run_with_telemetry<F, Fut>(
+    model: Model,
+    target: AssistantMessageEventStream,
+    run: F,
+) where
+    F: FnOnce(AssistantMessageEventStream) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let span = tracing::info_span!(
+        "llm_call",
+        api = %model.api,
+        provider = %model.provider,
+        model = %model.id,
+        duration_ms = Empty,
+        ttft_ms = Empty,
+        tokens_per_sec = Empty,
+        input_tokens = Empty,
+        output_tokens = Empty,
+        reasoning_tokens = Empty,
+        cost_usd = Empty,
+        stop_reason = Empty,
+        error_code = Empty,
+    );
+
+    let source = AssistantMessageEventStream::new();
+    let state = Arc::new(CallState::default());
+    let started = Instant::now();
+    let forward = async {
+        let mut first_token_at = None;
+        while let Some(mut event) = source.next().await {
+            if first_token_at.is_none() && is_content_event(&event) {
+                first_token_at = Some(Instant::now());
+            }
+            let final_message = match &mut event {
+                AssistantMessageEvent::Done { message, .. } => Some(message),
+                AssistantMessageEvent::Error { error, .. } => Some(error),
+                _ => None,
+            };
+            if let Some(message) = final_message {
+                price_message(&model, message);
+                let finished = Instant::now();
+                let attempt = *state
+                    .attempt
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                let (attempt_started, first_token_at) = match attempt {
+                    Some(attempt) => (attempt.started, attempt.first_token_at),
+                    None => (started, first_token_at),
+                };
+                let generation_time = first_token_at.map(|first| finished - first);
+                let record = CallRecord {
+                    model: &model,
+                    message,
+                    duration: finished - started,
+                    time_to_first_token: first_token_at.map(|first| first - attempt_started),
+                    tokens_per_sec: tokens_per_sec(message.usage.output, generation_time),
+                    error_code: error_code(message),
+                };
+                record_call(&span, &record);
+                let stats = StreamStats {
+                    time_to_first_token_ms: record
+                        .time_to_first_token
+                        .map(|ttft| ttft.as_millis() as u64),
+                    duration_ms: record.duration.as_millis() as u64,
+                    tokens_per_sec: record.tokens_per_sec,
+                    retries: state.retries.load(Ordering::SeqCst),
+                    provider: match state
+                        .upstream_provider
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .as_deref()
+                    {
+                        Some(upstream) => format!("{}/{upstream}", message.provider),
+                        None => message.provider.clone(),
+                    },
+                };
+                message.stats = Some(stats);
+            }
+            target.push(event);
+        }
+        target.end(None);
+    };
+    tokio::join!(
+        CALL_STATE.scope(state.clone(), run(source.clone()).instrument(span.clone())),
+        forward
+    );
+}
+
+pub(crate) fn is_content_event(event: &AssistantMessageEvent) -> bool {
+    !matches!(
+        event,
+        AssistantMessageEvent::Start { .. }
+            | AssistantMessageEvent::UsageDelta { .. }
+            | AssistantMessageEvent::Done { .. }
+            | AssistantMessageEvent::Error { .. }
+    )
+}
+
+fn tokens_per_sec(output_tokens: u64, generation_time: Option<Duration>) -> Option<f64> {
+    let seconds = generation_time?.as_secs_f64();
+    (output_tokens > 0 && seconds > 0.0).then(|| output_tokens as f64 / seconds)
+}
+
+/// The `PiAiErrorCode` carried in a failed message, in its serialized snake_case form.
+fn error_code(message: &AssistantMessage) -> Option<String> {
+    if message.stop_reason != StopReason::Error {
+        return None;
+    }
+    let code = message
+        .error_message
+        .as_deref()
+        .and_then(|text| serde_json::from_str::<PiAiError>(text).ok())
+        .and_then(|error| serde_json::to_value(error.code).ok())
+        .and_then(|code| code.as_str().map(str::to_string));
+    Some(code.unwrap_or_else(|| "unknown".to_string()))
+}
+
+fn record_call(span: &Span, record: &CallRecord<'_>) {
+    let usage = &record.message.usage;
+    span.record("duration_ms", record.duration.as_millis() as u64);
+    if let Some(ttft) = record.time_to_first_token {
+        span.record("ttft_ms", ttft.as_millis() as u64);
+    }
+    if let Some(tokens_per_sec) = record.tokens_per_sec {
+        span.record("tokens_per_sec", tokens_per_sec);
+    }
+    span.record("input_tokens", usage.input);
+    span.record("output_tokens", usage.output);
+    span.record("reasoning_tokens", usage.reasoning);
+    span.record("cost_usd", usage.cost.total);
+    span.record("stop_reason", format!("{:?}", record.message.stop_reason));
+    if let Some(error_code) = &record.error_code {
+        span.record("error_code", error_code.as_str());
+    }
+    tracing::debug!(
+        parent: span,
+        model = %record.model.id,
+        duration_ms = record.duration.as_millis() as u64,
+        "LLM call finished"
+    );
+
+    REQUESTS.fetch_add(1, Ordering::SeqCst);
+    if record.error_code.is_some() {
+        ERRORS.fetch_add(1, Ordering::SeqCst);
+    }
+    INPUT_TOKENS.fetch_add(usage.input, Ordering::SeqCst);
+    OUTPUT_TOKENS.fetch_add(usage.output, Ordering::SeqCst);
+
+    #[cfg(feature = "otel")]
+    otel::record(record);
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use std::sync::OnceLock;
+
+    use opentelemetry::metrics::{Counter, Histogram};
+    use opentelemetry::{global, KeyValue};
+
+    use super::CallRecord;
+
+    struct Instruments {
+        requests: Counter<u64>,
+        errors: Counter<u64>,
+        tokens: Counter<u64>,
+        duration: Histogram<f64>,
+        time_to_first_token: Histogram<f64>,
+        tokens_per_sec: Histogram<f64>,
+    }
+
+    /// Built on first use, so a meter provider installed before the first call is picked up.
+    fn instruments() -> &'static Instruments {
+        static INSTRUMENTS: OnceLock<Instruments> = OnceLock::new();
+        INSTRUMENTS.get_or_init(|| {
+            let meter = global::meter("pixy-ai");
+            Instruments {
+                requests: meter.u64_counter("pixy_ai.requests").build(),
+                errors: meter.u64_counter("pixy_ai.errors").build(),
+                tokens: meter
+                    .u64_counter("pixy_ai.tokens")
+                    .with_unit("{token}")
+                    .build(),
+                duration: meter
+                    .f64_histogram("pixy_ai.duration")
+                    .with_unit("ms")
+                    .build(),
+                time_to_first_token: meter
+                    .f64_histogram("pixy_ai.time_to_first_token")
+                    .with_unit("ms")
+                    .build(),
+                tokens_per_sec: meter
+                    .f64_histogram("pixy_ai.tokens_per_second")
+                    .with_unit("{token}/s")
+                    .build(),
+            }
+        })
+    }
+
+    pub(super) fn record(record: &CallRecord<'_>) {
+        let instruments = instruments();
+        let attributes = [
+            KeyValue::new("api", record.model.api.clone()),
+            KeyValue::new("provider", record.model.provider.clone()),
+            KeyValue::new("model", record.model.id.clone()),
+        ];
+
+        instruments.requests.add(1, &attributes);
+        if let Some(error_code) = &record.error_code {
+            let mut error_attributes = attributes.to_vec();
+            error_attributes.push(KeyValue::new("error_code", error_code.clone()));
+            instruments.errors.add(1, &error_attributes);
+        }
+        let usage = &record.message.usage;
+        for (kind, tokens) in [("input", usage.input), ("output", usage.output)] {
+            let mut token_attributes = attributes.to_vec();
+            token_attributes.push(KeyValue::new("token_type", kind));
+            instruments.tokens.add(tokens, &token_attributes);
+        }
+        instruments
+            .duration
+            .record(record.duration.as_secs_f64() * 1000.0, &attributes);
+        if let Some(ttft) = record.time_to_first_token {
+            instruments
+                .time_to_first_token
+                .record(ttft.as_secs_f64() * 1000.0, &attributes);
+        }
+        if let Some(tokens_per_sec) = record.tokens_per_sec {
+            instruments
+                .tokens_per_sec
+                .record(tokens_per_sec, &attributes);
+        }
+    }
+}
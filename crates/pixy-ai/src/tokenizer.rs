@@ -0,0 +1,309 @@
+//! Offline token estimates for sizing prompts against a model's context window.
+//!
+//! OpenAI models get a tiktoken-style count: text is split with the same pre-tokenizer rules as
+//! `cl100k_base`/`o200k_base` and each piece is priced by how BPE usually merges it. Every other
+//! model falls back to a characters-per-token heuristic. Neither is exact, but both track the
+//! provider-reported usage closely enough for compaction thresholds and status displays.
+
+use crate::types::{
+    AssistantContentBlock, Context, Message, Model, Tool, ToolResultContentBlock, UserContent,
+    UserContentBlock,
+};
+
+/// Role markers and separators the chat format adds around every message.
+const MESSAGE_OVERHEAD_TOKENS: u64 = 4;
+/// Tokens that prime the assistant reply.
+const REPLY_PRIMING_TOKENS: u64 = 3;
+/// Framing around each tool definition.
+const TOOL_OVERHEAD_TOKENS: u64 = 8;
+/// Typical cost of one image input; providers bill by resolution, which is not known here.
+const IMAGE_TOKENS: u64 = 1_200;
+/// Typical cost of a short voice clip; the clip's duration is not known here.
+const AUDIO_TOKENS: u64 = 1_000;
+
+/// Token encoding used to estimate text for a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tokenizer {
+    /// GPT-3.5 / GPT-4 era encoding.
+    Cl100k,
+    /// GPT-4o, GPT-4.1, GPT-5 and o-series encoding.
+    O200k,
+    /// Characters-per-token approximation for non-OpenAI models.
+    Heuristic,
+}
+
+impl Tokenizer {
+    /// Picks the encoding from the model id, ignoring any `provider/` prefix.
+    pub fn for_model(model: &Model) -> Self {
+        let id = model
+            .id
+            .rsplit_once('/')
+            .map(|(_, id)| id)
+            .unwrap_or(&model.id)
+            .to_ascii_lowercase();
+        const O200K_PREFIXES: &[&str] = &[
+            "gpt-4o",
+            "gpt-4.1",
+            "gpt-4.5",
+            "gpt-5",
+            "chatgpt-4o",
+            "codex-",
+            "o1",
+            "o3",
+            "o4",
+        ];
+        const CL100K_PREFIXES: &[&str] = &["gpt-4", "gpt-3.5", "text-embedding"];
+        if O200K_PREFIXES.iter().any(|prefix| id.starts_with(prefix)) {
+            Tokenizer::O200k
+        } else if CL100K_PREFIXES.iter().any(|prefix| id.starts_with(prefix)) {
+            Tokenizer::Cl100k
+        } else {
+            Tokenizer::Heuristic
+        }
+    }
+
+    /// Estimated token count of `text`.
+    pub fn count(self, text: &str) -> u64 {
+        if text.is_empty() {
+            return 0;
+        }
+        match self {
+            Tokenizer::Heuristic => heuristic_count(text),
+            Tokenizer::Cl100k | Tokenizer::O200k => pre_tokenize(text)
+                .map(|piece| self.piece_tokens(piece))
+                .sum(),
+        }
+    }
+
+    /// Estimated prompt tokens contributed by one message, including chat framing.
+    pub fn message_tokens(self, message: &Message) -> u64 {
+        let content = match message {
+            Message::User { content, .. } => match content {
+                UserContent::Text(text) => self.count(text),
+                UserContent::Blocks(blocks) => blocks
+                    .iter()
+                    .map(|block| match block {
+                        UserContentBlock::Text { text, .. } => self.count(text),
+                        UserContentBlock::Image { .. } => IMAGE_TOKENS,
+                        UserContentBlock::Audio { .. } => AUDIO_TOKENS,
+                    })
+                    .sum(),
+            },
+            Message::Assistant { content, .. } => content
+                .iter()
+                .map(|block| match block {
+                    AssistantContentBlock::Text { text, .. } => self.count(text),
+                    AssistantContentBlock::Thinking { thinking, .. } => self.count(thinking),
+                    AssistantContentBlock::ToolCall {
+                        name, arguments, ..
+                    } => self.count(name) + self.count(&arguments.to_string()),
+                    AssistantContentBlock::Image { .. } => IMAGE_TOKENS,
+                    // Replayed through its transcript text block.
+                    AssistantContentBlock::Audio { .. } => 0,
+                })
+                .sum(),
+            Message::ToolResult {
+                tool_name, content, ..
+            } => {
+                self.count(tool_name)
+                    + content
+                        .iter()
+                        .map(|block| match block {
+                            ToolResultContentBlock::Text { text, .. } => self.count(text),
+                            ToolResultContentBlock::Image { .. } => IMAGE_TOKENS,
+                        })
+                        .sum::<u64>()
+            }
+        };
+        content + MESSAGE_OVERHEAD_TOKENS
+    }
+
+    /// Estimated prompt tokens for sending `context`: system prompt, tool definitions and
+    /// every message.
+    pub fn context_tokens(self, context: &Context) -> u64 {
+        let system = context
+            .system_prompt
+            .as_deref()
+            .map(|prompt| self.count(prompt) + MESSAGE_OVERHEAD_TOKENS)
+            .unwrap_or(0);
+        let tools = context
+            .tools
+            .iter()
+            .flatten()
+            .map(|tool| estimate_tool_tokens(self, tool))
+            .sum::<u64>();
+        let messages = context
+            .messages
+            .iter()
+            .map(|message| self.message_tokens(message))
+            .sum::<u64>();
+        system + tools + messages + REPLY_PRIMING_TOKENS
+    }
+
+    /// Approximates how many BPE tokens one pre-tokenized piece merges into.
+    fn piece_tokens(self, piece: &str) -> u64 {
+        // Longest run of ASCII letters (including a leading space) that reliably merges into a
+        // single token; o200k's larger vocabulary covers longer words.
+        let single_word_len = match self {
+            Tokenizer::O200k => 8,
+            _ => 7,
+        };
+        let trimmed = piece.trim_start_matches(' ');
+        let Some(first) = trimmed.chars().next() else {
+            // Runs of spaces and newlines merge aggressively.
+            return (piece.len() as u64).div_ceil(16).max(1);
+        };
+
+        if first.is_whitespace() || first.is_numeric() {
+            return 1;
+        }
+        if trimmed.chars().any(char::is_alphabetic) {
+            if piece.is_ascii() {
+                let len = piece.len() as u64;
+                return if len <= single_word_len {
+                    1
+                } else {
+                    1 + (len - single_word_len).div_ceil(4)
+                };
+            }
+            let chars = trimmed.chars().count() as u64;
+            return match self {
+                Tokenizer::O200k => (chars * 3).div_ceil(4),
+                _ => chars,
+            };
+        }
+        (trimmed.chars().count() as u64).div_ceil(2).max(1)
+    }
+}
+
+/// Estimated token count of `text` for `model`.
+pub fn estimate_tokens(model: &Model, text: &str) -> u64 {
+    Tokenizer::for_model(model).count(text)
+}
+
+/// Estimated prompt tokens contributed by one message, including chat framing.
+pub fn estimate_message_tokens(model: &Model, message: &Message) -> u64 {
+    Tokenizer::for_model(model).message_tokens(message)
+}
+
+/// Estimated prompt tokens for sending `context` to `model`: system prompt, tool definitions
+/// and every message.
+pub fn estimate_context_tokens(model: &Model, context: &Context) -> u64 {
+    Tokenizer::for_model(model).context_tokens(context)
+}
+
+fn estimate_tool_tokens(tokenizer: Tokenizer, tool: &Tool) -> u64 {
+    tokenizer.count(&tool.name)
+        + tokenizer.count(&tool.description)
+        + tokenizer.count(&tool.parameters.to_string())
+        + TOOL_OVERHEAD_TOKENS
+}
+
+/// About four ASCII characters per token; other scripts are closer to one token per character.
+fn heuristic_count(text: &str) -> u64 {
+    let (ascii, other) = text.chars().fold((0u64, 0u64), |(ascii, other), ch| {
+        if ch.is_ascii() {
+            (ascii + 1, other)
+        } else {
+            (ascii, other + 1)
+        }
+    });
+    ascii.div_ceil(4) + other
+}
+
+/// Splits text the way tiktoken's pre-tokenizer regex does: contractions, words with an
+/// optional leading space or symbol, digit groups of at most three, punctuation runs and
+/// whitespace runs.
+fn pre_tokenize(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let len = next_piece_len(rest);
+        let (piece, tail) = rest.split_at(len);
+        rest = tail;
+        Some(piece)
+    })
+}
+
+fn next_piece_len(text: &str) -> usize {
+    let mut chars = text.char_indices().peekable();
+    let (_, first) = chars.next().expect("text is not empty");
+
+    if first == '\'' {
+        let lower = text[1..]
+            .chars()
+            .take(2)
+            .collect::<String>()
+            .to_ascii_lowercase();
+        for suffix in ["re", "ve", "ll", "s", "t", "m", "d"] {
+            if lower.starts_with(suffix) && !next_is_letter(&text[1 + suffix.len()..]) {
+                return 1 + suffix.len();
+            }
+        }
+    }
+
+    if first.is_alphabetic() || (!first.is_numeric() && !first.is_whitespace()) {
+        // A letter run may carry one leading non-letter, non-digit character (usually a space
+        // or punctuation like `_` or `.`).
+        let start = if first.is_alphabetic() {
+            0
+        } else if next_is_letter(&text[first.len_utf8()..]) {
+            first.len_utf8()
+        } else {
+            return punctuation_run_len(text);
+        };
+        return start + run_len(&text[start..], char::is_alphabetic);
+    }
+
+    if first.is_numeric() {
+        return text
+            .char_indices()
+            .take_while(|(_, ch)| ch.is_numeric())
+            .take(3)
+            .map(|(index, ch)| index + ch.len_utf8())
+            .last()
+            .unwrap_or(first.len_utf8());
+    }
+
+    if first == ' ' {
+        if let Some(&(index, next)) = chars.peek() {
+            if next.is_alphabetic() {
+                return index + run_len(&text[index..], char::is_alphabetic);
+            }
+            if !next.is_whitespace() && !next.is_numeric() {
+                return index + punctuation_run_len(&text[index..]);
+            }
+        }
+    }
+
+    // Whitespace: newline runs stay together; a space run leaves its last space to prefix the
+    // following word.
+    let whitespace = run_len(text, char::is_whitespace);
+    let tail = &text[whitespace..];
+    if whitespace > 1 && !tail.is_empty() && text[..whitespace].ends_with(' ') {
+        whitespace - 1
+    } else {
+        whitespace
+    }
+}
+
+fn punctuation_run_len(text: &str) -> usize {
+    let len = run_len(text, |ch| {
+        !ch.is_alphabetic() && !ch.is_numeric() && !ch.is_whitespace()
+    });
+    // Trailing newlines join the punctuation run, as in `[^\s\p{L}\p{N}]+[\r\n]*`.
+    len + run_len(&text[len..], |ch| ch == '\r' || ch == '\n')
+}
+
+fn run_len(text: &str, predicate: impl Fn(char) -> bool) -> usize {
+    text.char_indices()
+        .find(|(_, ch)| !predicate(*ch))
+        .map(|(index, _)| index)
+        .unwrap_or(text.len())
+}
+
+fn next_is_letter(text: &str) -> bool {
+    text.chars().next().is_some_and(char::is_alphabetic)
+}
@@ -1,27 +1,232 @@
+use std::collections::HashMap;
 use std::future::Future;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-#[cfg(not(test))]
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{PiAiError, PiAiErrorCode};
 
 pub const DEFAULT_TRANSPORT_RETRY_COUNT: usize = 5;
 
-static TRANSPORT_RETRY_COUNT: AtomicUsize = AtomicUsize::new(DEFAULT_TRANSPORT_RETRY_COUNT);
+const DEFAULT_BASE_BACKOFF_MS: u64 = 1_000;
+const DEFAULT_MAX_BACKOFF_MS: u64 = 60_000;
+
+/// How failed provider requests are retried by [`crate::ReliableProvider`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    #[serde(rename = "maxRetries")]
+    pub max_retries: usize,
+    #[serde(rename = "baseBackoffMs")]
+    pub base_backoff_ms: u64,
+    /// Upper bound for any single wait, including waits requested via `Retry-After`.
+    #[serde(rename = "maxBackoffMs")]
+    pub max_backoff_ms: u64,
+    /// Fraction of each backoff that is randomized away, from `0.0` to `1.0`.
+    pub jitter: f64,
+    #[serde(rename = "honorRetryAfter")]
+    pub honor_retry_after: bool,
+    #[serde(rename = "retryableCodes")]
+    pub retryable_codes: Vec<PiAiErrorCode>,
+    /// Statuses retried when `ProviderHttp` is in `retryable_codes`.
+    #[serde(rename = "retryableHttpStatuses")]
+    pub retryable_http_statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_TRANSPORT_RETRY_COUNT,
+            base_backoff_ms: DEFAULT_BASE_BACKOFF_MS,
+            max_backoff_ms: DEFAULT_MAX_BACKOFF_MS,
+            jitter: 0.2,
+            honor_retry_after: true,
+            retryable_codes: vec![
+                PiAiErrorCode::ProviderTransport,
+                PiAiErrorCode::TransportTimeout,
+                PiAiErrorCode::ProviderHttp,
+            ],
+            retryable_http_statuses: vec![408, 429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn is_retryable(&self, error: &PiAiError) -> bool {
+        if !self.retryable_codes.contains(&error.code) {
+            return false;
+        }
+        if error.code != PiAiErrorCode::ProviderHttp {
+            return true;
+        }
+        http_error_status(error)
+            .is_some_and(|status| self.retryable_http_statuses.contains(&status))
+    }
+
+    /// Wait before retry number `retry_index` (zero-based) of a request that failed with `error`.
+    pub fn retry_delay(&self, retry_index: u32, error: &PiAiError) -> Duration {
+        if let Some(retry_after_ms) = self.retry_after_ms(error) {
+            return Duration::from_millis(retry_after_ms);
+        }
+
+        let multiplier = 1u64.checked_shl(retry_index.min(63)).unwrap_or(u64::MAX);
+        let backoff_ms = self
+            .base_backoff_ms
+            .saturating_mul(multiplier)
+            .min(self.max_backoff_ms);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let jitter_ms = (backoff_ms as f64 * jitter * jitter_sample()) as u64;
+        Duration::from_millis(backoff_ms.saturating_sub(jitter_ms))
+    }
+
+    fn retry_after_ms(&self, error: &PiAiError) -> Option<u64> {
+        if !self.honor_retry_after {
+            return None;
+        }
+        error
+            .details
+            .as_ref()
+            .and_then(|details| details.get("retryAfterMs"))
+            .and_then(Value::as_u64)
+            .map(|retry_after_ms| retry_after_ms.min(self.max_backoff_ms))
+    }
+}
+
+/// How a response stream is read, for connections that stall or drop mid-answer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamTransport {
+    /// Fails the read once the provider has sent nothing for this long. Unset waits forever.
+    #[serde(rename = "idleTimeoutMs")]
+    pub idle_timeout_ms: Option<u64>,
+    /// Caps the whole request, from sending it to the last byte of the answer.
+    #[serde(rename = "totalTimeoutMs")]
+    pub total_timeout_ms: Option<u64>,
+    /// Times a dropped or stalled stream is reopened with `Last-Event-ID`. Only streams whose
+    /// events carry an `id:` can be resumed; the others fail and are left to the retry policy.
+    #[serde(rename = "maxResumes")]
+    pub max_resumes: usize,
+}
+
+impl Default for StreamTransport {
+    fn default() -> Self {
+        Self {
+            idle_timeout_ms: None,
+            total_timeout_ms: None,
+            max_resumes: 2,
+        }
+    }
+}
+
+/// Retries performed by every [`crate::ReliableProvider`] since process start.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetryMetrics {
+    pub retries: u64,
+    /// Retries whose wait came from a `Retry-After` hint.
+    pub retry_after_waits: u64,
+    /// Requests that still failed with a retryable error after the last attempt.
+    pub exhausted: u64,
+}
+
+static RETRIES: AtomicU64 = AtomicU64::new(0);
+static RETRY_AFTER_WAITS: AtomicU64 = AtomicU64::new(0);
+static RETRIES_EXHAUSTED: AtomicU64 = AtomicU64::new(0);
+
+pub fn retry_metrics() -> RetryMetrics {
+    RetryMetrics {
+        retries: RETRIES.load(Ordering::SeqCst),
+        retry_after_waits: RETRY_AFTER_WAITS.load(Ordering::SeqCst),
+        exhausted: RETRIES_EXHAUSTED.load(Ordering::SeqCst),
+    }
+}
+
+pub(crate) fn record_retry(policy: &RetryPolicy, error: &PiAiError) {
+    RETRIES.fetch_add(1, Ordering::SeqCst);
+    crate::telemetry::record_call_retry();
+    if policy.retry_after_ms(error).is_some() {
+        RETRY_AFTER_WAITS.fetch_add(1, Ordering::SeqCst);
+    }
+}
 
-pub fn set_transport_retry_count(retries: usize) {
-    TRANSPORT_RETRY_COUNT.store(retries, Ordering::SeqCst);
+pub(crate) fn record_retries_exhausted() {
+    RETRIES_EXHAUSTED.fetch_add(1, Ordering::SeqCst);
 }
 
-pub fn transport_retry_count() -> usize {
-    TRANSPORT_RETRY_COUNT.load(Ordering::SeqCst)
+fn default_policy_slot() -> &'static RwLock<RetryPolicy> {
+    static DEFAULT_POLICY: OnceLock<RwLock<RetryPolicy>> = OnceLock::new();
+    DEFAULT_POLICY.get_or_init(|| RwLock::new(RetryPolicy::default()))
 }
 
-pub fn transport_retry_count_with_override(request_override: Option<usize>) -> usize {
-    resolve_transport_retry_count(request_override, transport_retry_count())
+fn provider_policy_slot() -> &'static RwLock<HashMap<String, RetryPolicy>> {
+    static PROVIDER_POLICIES: OnceLock<RwLock<HashMap<String, RetryPolicy>>> = OnceLock::new();
+    PROVIDER_POLICIES.get_or_init(|| RwLock::new(HashMap::new()))
 }
 
-fn resolve_transport_retry_count(request_override: Option<usize>, runtime_default: usize) -> usize {
-    request_override.unwrap_or(runtime_default)
+/// Sets the policy used for providers without one of their own.
+pub fn set_default_retry_policy(policy: RetryPolicy) {
+    let mut slot = default_policy_slot()
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *slot = policy;
+}
+
+pub fn default_retry_policy() -> RetryPolicy {
+    default_policy_slot()
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// Sets the policy for requests to `provider` (the `Model::provider` name).
+pub fn set_provider_retry_policy(provider: impl Into<String>, policy: RetryPolicy) {
+    provider_policy_slot()
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(provider.into(), policy);
+}
+
+pub fn retry_policy_for_provider(provider: &str) -> RetryPolicy {
+    provider_policy_slot()
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(provider)
+        .cloned()
+        .unwrap_or_else(default_retry_policy)
+}
+
+/// Parses `Retry-After` (delta seconds) or `retry-after-ms` header values.
+pub(crate) fn parse_retry_after_ms(
+    retry_after_ms: Option<&str>,
+    retry_after: Option<&str>,
+) -> Option<u64> {
+    if let Some(value) = retry_after_ms.and_then(|value| value.trim().parse::<f64>().ok()) {
+        if value.is_finite() && value >= 0.0 {
+            return Some(value.ceil() as u64);
+        }
+    }
+    retry_after
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(|seconds| seconds.saturating_mul(1_000))
+}
+
+pub(crate) fn http_error_status(error: &PiAiError) -> Option<u16> {
+    error
+        .details
+        .as_ref()
+        .and_then(|details| details.get("status"))
+        .and_then(Value::as_u64)
+        .and_then(|status| u16::try_from(status).ok())
+}
+
+fn jitter_sample() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos())
+        .unwrap_or(0);
+    f64::from(nanos % 1_000) / 1_000.0
 }
 
 #[allow(dead_code)]
@@ -98,20 +303,102 @@ mod tests {
     }
 
     #[test]
-    fn resolve_transport_retry_count_prefers_request_override() {
-        assert_eq!(resolve_transport_retry_count(Some(2), 8), 2);
+    fn retry_policy_retries_transport_errors_and_listed_http_statuses() {
+        let policy = RetryPolicy::default();
+        let http = |status: u16| {
+            PiAiError::new(PiAiErrorCode::ProviderHttp, format!("HTTP {status}"))
+                .with_details(serde_json::json!({ "status": status }))
+        };
+
+        assert!(policy.is_retryable(&PiAiError::new(
+            PiAiErrorCode::ProviderTransport,
+            "connection reset"
+        )));
+        assert!(policy.is_retryable(&http(429)));
+        assert!(policy.is_retryable(&http(503)));
+        assert!(!policy.is_retryable(&http(400)));
+        assert!(!policy.is_retryable(&PiAiError::new(
+            PiAiErrorCode::ProviderProtocol,
+            "bad payload"
+        )));
+
+        let transport_only = RetryPolicy {
+            retryable_codes: vec![PiAiErrorCode::ProviderTransport],
+            ..RetryPolicy::default()
+        };
+        assert!(!transport_only.is_retryable(&http(429)));
     }
 
     #[test]
-    fn resolve_transport_retry_count_uses_runtime_default_when_no_override() {
-        assert_eq!(resolve_transport_retry_count(None, 7), 7);
+    fn retry_delay_grows_exponentially_within_jitter_and_cap() {
+        let policy = RetryPolicy {
+            base_backoff_ms: 100,
+            max_backoff_ms: 1_000,
+            jitter: 0.5,
+            ..RetryPolicy::default()
+        };
+        let error = PiAiError::new(PiAiErrorCode::ProviderTransport, "reset");
+
+        for (retry_index, full_ms) in [(0, 100u64), (2, 400), (8, 1_000)] {
+            let delay = policy.retry_delay(retry_index, &error).as_millis() as u64;
+            assert!(
+                delay <= full_ms && delay >= full_ms / 2,
+                "retry {retry_index}: {delay}ms"
+            );
+        }
+    }
+
+    #[test]
+    fn retry_delay_honors_retry_after_up_to_max_backoff() {
+        let policy = RetryPolicy {
+            max_backoff_ms: 5_000,
+            ..RetryPolicy::default()
+        };
+        let error = |retry_after_ms: u64| {
+            PiAiError::new(PiAiErrorCode::ProviderHttp, "HTTP 429")
+                .with_details(serde_json::json!({ "status": 429, "retryAfterMs": retry_after_ms }))
+        };
+
+        assert_eq!(policy.retry_delay(0, &error(2_000)), Duration::from_secs(2));
+        assert_eq!(
+            policy.retry_delay(0, &error(90_000)),
+            Duration::from_secs(5)
+        );
+
+        let ignoring = RetryPolicy {
+            honor_retry_after: false,
+            jitter: 0.0,
+            base_backoff_ms: 10,
+            ..policy
+        };
+        assert_eq!(
+            ignoring.retry_delay(0, &error(2_000)),
+            Duration::from_millis(10)
+        );
+    }
+
+    #[test]
+    fn parse_retry_after_prefers_millisecond_header() {
+        assert_eq!(parse_retry_after_ms(Some("1500.2"), Some("9")), Some(1_501));
+        assert_eq!(parse_retry_after_ms(None, Some("3")), Some(3_000));
+        assert_eq!(
+            parse_retry_after_ms(None, Some("Wed, 21 Oct 2015 07:28:00 GMT")),
+            None
+        );
     }
 
     #[test]
-    fn resolve_transport_retry_count_supports_constant_default_fallback() {
+    fn provider_retry_policy_falls_back_to_default() {
+        let custom = RetryPolicy {
+            max_retries: 1,
+            ..RetryPolicy::default()
+        };
+        set_provider_retry_policy("retry-test-provider", custom.clone());
+
+        assert_eq!(retry_policy_for_provider("retry-test-provider"), custom);
         assert_eq!(
-            resolve_transport_retry_count(None, DEFAULT_TRANSPORT_RETRY_COUNT),
-            DEFAULT_TRANSPORT_RETRY_COUNT
+            retry_policy_for_provider("retry-test-unknown").retryable_codes,
+            default_retry_policy().retryable_codes
         );
     }
 }
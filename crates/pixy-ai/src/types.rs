@@ -1,6 +1,15 @@
+use std::sync::Arc;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::error::{PiAiError, PiAiErrorCode};
+use crate::guard::ContentGuardRef;
+use crate::middleware::StreamMiddlewareRef;
+use crate::response_cache::ResponseCacheRef;
+use crate::transport_retry::{RetryPolicy, StreamTransport};
+use crate::validation::ToolCall;
+
 pub type Api = String;
 pub type Provider = String;
 
@@ -18,12 +27,62 @@ pub enum ThinkingLevel {
     Xhigh,
 }
 
+impl ThinkingLevel {
+    /// The lowest level whose usual budget covers `budget_tokens`, for providers that take an
+    /// effort instead of a token budget.
+    pub fn from_budget_tokens(budget_tokens: u32) -> Self {
+        match budget_tokens {
+            0..=1_024 => Self::Minimal,
+            1_025..=4_096 => Self::Low,
+            4_097..=8_192 => Self::Medium,
+            8_193..=16_384 => Self::High,
+            _ => Self::Xhigh,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct StreamOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f64>,
+    /// Nucleus sampling cutoff. Like the other sampling parameters below, it is only sent to
+    /// providers that accept it and ignored elsewhere.
+    #[serde(rename = "topP", skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    /// Anthropic, Gemini and Ollama only.
+    #[serde(rename = "topK", skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    /// Makes sampling repeatable on OpenAI chat completions, Gemini and Ollama.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(rename = "frequencyPenalty", skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    #[serde(rename = "presencePenalty", skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+    /// Provider-specific sampling fields sent as-is next to the ones above, e.g. `min_p` for
+    /// Ollama or `repetition_penalty` for vLLM.
+    #[serde(
+        rename = "extraSampling",
+        default,
+        skip_serializing_if = "serde_json::Map::is_empty"
+    )]
+    pub extra_sampling: serde_json::Map<String, Value>,
     #[serde(rename = "maxTokens", skip_serializing_if = "Option::is_none")]
     pub max_tokens: Option<u32>,
+    /// Sequences that end the answer when generated. The OpenAI Responses API has no such
+    /// field and ignores them.
+    #[serde(
+        rename = "stopSequences",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub stop_sequences: Vec<String>,
+    /// Tokens the model may spend thinking, instead of the budget its reasoning effort implies.
+    /// Sent as Anthropic `budget_tokens` and Gemini `thinkingBudget`; OpenAI only takes an
+    /// effort, so the closest [`ThinkingLevel`] is sent. `0` turns thinking off on Anthropic
+    /// and on Gemini models that allow it.
+    #[serde(rename = "thinkingBudget", skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<u32>,
     #[serde(rename = "apiKey", skip_serializing_if = "Option::is_none")]
     pub api_key: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -33,6 +92,110 @@ pub struct StreamOptions {
         skip_serializing_if = "Option::is_none"
     )]
     pub transport_retry_count: Option<usize>,
+    /// Replaces the provider's retry policy for this request.
+    #[serde(rename = "retryPolicy", skip_serializing_if = "Option::is_none")]
+    pub retry_policy: Option<RetryPolicy>,
+    /// What to do when a client-side [`crate::RateLimit`] has no room; waits by default.
+    #[serde(rename = "rateLimitPolicy", skip_serializing_if = "Option::is_none")]
+    pub rate_limit_policy: Option<crate::RateLimitPolicy>,
+    /// Timeouts and resumption for reading the response stream.
+    #[serde(rename = "streamTransport", skip_serializing_if = "Option::is_none")]
+    pub stream_transport: Option<StreamTransport>,
+    /// OpenRouter only: models tried in order when the requested one is down, rate limited or
+    /// refuses the request. The answer's `model` names the one that served it.
+    #[serde(
+        rename = "fallbackModels",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub fallback_models: Vec<String>,
+    /// OpenRouter only: `provider` routing preferences sent as-is, e.g. `order`,
+    /// `allow_fallbacks` or `sort`.
+    #[serde(
+        rename = "providerPreferences",
+        default,
+        skip_serializing_if = "serde_json::Map::is_empty"
+    )]
+    pub provider_preferences: serde_json::Map<String, Value>,
+    /// Overrides `Model::api_version` for Azure-style endpoints.
+    #[serde(rename = "apiVersion", skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+    #[serde(rename = "responseFormat", skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseSchema>,
+    /// Lets the model return generated images: enables OpenAI's `image_generation` tool and
+    /// Gemini's IMAGE response modality.
+    #[serde(
+        rename = "imageGeneration",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub image_generation: bool,
+    /// Asks audio-capable models to also answer in speech with this voice, e.g. `alloy`.
+    #[serde(
+        rename = "audioVoice",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub audio_voice: Option<String>,
+    /// Stops generation once it returns true for the text streamed so far.
+    #[serde(skip)]
+    pub stop_predicate: Option<StopPredicate>,
+    /// Runs after the globally registered middleware; see [`crate::StreamMiddleware`].
+    #[serde(skip)]
+    pub middleware: Vec<StreamMiddlewareRef>,
+    /// Checks the outgoing context and the streamed answer; see [`crate::ContentGuard`].
+    #[serde(skip)]
+    pub guards: Vec<ContentGuardRef>,
+    /// Serves [`crate::complete`] from this cache instead of the one set with
+    /// [`crate::set_response_cache`]; see [`crate::ResponseCache`].
+    #[serde(skip)]
+    pub response_cache: Option<ResponseCacheRef>,
+}
+
+/// Closure over the accumulated response text; see [`StreamOptions::stop_predicate`].
+#[derive(Clone)]
+pub struct StopPredicate(Arc<dyn Fn(&str) -> bool + Send + Sync>);
+
+impl StopPredicate {
+    pub fn new(predicate: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(predicate))
+    }
+
+    /// Stops as soon as `marker` appears in the response.
+    pub fn on_marker(marker: impl Into<String>) -> Self {
+        let marker = marker.into();
+        Self::new(move |text| text.contains(&marker))
+    }
+
+    pub fn should_stop(&self, text: &str) -> bool {
+        (self.0)(text)
+    }
+}
+
+impl std::fmt::Debug for StopPredicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("StopPredicate(..)")
+    }
+}
+
+impl PartialEq for StopPredicate {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// JSON schema the final response must conform to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseSchema {
+    /// Schema name; Anthropic also uses it as the name of the forced tool.
+    pub name: String,
+    pub schema: Value,
+    #[serde(default = "default_response_schema_strict")]
+    pub strict: bool,
+}
+
+fn default_response_schema_strict() -> bool {
+    true
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
@@ -64,6 +227,50 @@ pub struct Model {
     pub context_window: u32,
     #[serde(rename = "maxTokens")]
     pub max_tokens: u32,
+    /// Azure deployment name; the model id is used when unset.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub deployment: Option<String>,
+    /// `api-version` query parameter for Azure-style endpoints.
+    #[serde(
+        rename = "apiVersion",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub api_version: Option<String>,
+    /// Request quirks of a self-hosted OpenAI-compatible server; see
+    /// [`OpenAICompletionsCompat`]. Chat completions requests only.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub compat: Option<OpenAICompletionsCompat>,
+}
+
+/// How an OpenAI-compatible chat completions server (vLLM, TGI, LM Studio) differs from
+/// OpenAI. The defaults send the fields every such server accepts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenAICompletionsCompat {
+    /// Sends `stream_options: {"include_usage": true}` so the last chunk carries usage.
+    #[serde(rename = "streamOptions")]
+    pub stream_options: bool,
+    /// Sends `tool_choice: "auto"` next to the tools.
+    #[serde(rename = "toolChoice")]
+    pub tool_choice: bool,
+    /// Field that carries the output token limit, e.g. `max_completion_tokens`.
+    #[serde(rename = "maxTokensField")]
+    pub max_tokens_field: String,
+    /// Field that carries [`StreamOptions::stop_sequences`], e.g. `stop_sequences`.
+    #[serde(rename = "stopField")]
+    pub stop_field: String,
+}
+
+impl Default for OpenAICompletionsCompat {
+    fn default() -> Self {
+        Self {
+            stream_options: false,
+            tool_choice: false,
+            max_tokens_field: "max_tokens".to_string(),
+            stop_field: "stop".to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -117,6 +324,10 @@ pub struct Usage {
     pub cache_read: u64,
     #[serde(rename = "cacheWrite")]
     pub cache_write: u64,
+    /// Output tokens spent on reasoning, already included in `output`. Anthropic does not
+    /// report them separately, so they stay zero there.
+    #[serde(default)]
+    pub reasoning: u64,
     #[serde(rename = "totalTokens")]
     pub total_tokens: u64,
     pub cost: Cost,
@@ -130,6 +341,9 @@ pub enum UserContentBlock {
         text: String,
         #[serde(rename = "textSignature", skip_serializing_if = "Option::is_none")]
         text_signature: Option<String>,
+        /// Caches the prompt up to and including this block.
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        cache: Option<CacheHint>,
     },
     #[serde(rename = "image")]
     Image {
@@ -137,6 +351,25 @@ pub enum UserContentBlock {
         #[serde(rename = "mimeType")]
         mime_type: String,
     },
+    /// Base64 audio clip, e.g. a voice note, for models that accept audio input.
+    #[serde(rename = "audio")]
+    Audio {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+}
+
+/// Prompt caching hint. Anthropic turns it into a `cache_control` breakpoint; OpenAI caches
+/// prompt prefixes automatically and only uses `Extended` to ask for longer retention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CacheHint {
+    /// Provider default lifetime (about five minutes).
+    #[serde(rename = "ephemeral")]
+    Ephemeral,
+    /// Longer lifetime (one hour on Anthropic, 24 hours on OpenAI).
+    #[serde(rename = "extended")]
+    Extended,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -162,6 +395,20 @@ pub enum AssistantContentBlock {
         #[serde(rename = "thoughtSignature", skip_serializing_if = "Option::is_none")]
         thought_signature: Option<String>,
     },
+    /// Base64 image produced by the model, e.g. gpt-image or Gemini image output.
+    #[serde(rename = "image")]
+    Image {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    /// Base64 speech produced by the model. Its transcript streams as a separate text block.
+    #[serde(rename = "audio")]
+    Audio {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -208,6 +455,27 @@ pub struct AssistantMessage {
     #[serde(rename = "errorMessage", skip_serializing_if = "Option::is_none")]
     pub error_message: Option<String>,
     pub timestamp: i64,
+    /// How the call performed; set on final messages from [`crate::stream`].
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub stats: Option<StreamStats>,
+}
+
+/// Latency and throughput of the call that produced a message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamStats {
+    /// From sending the request to the first streamed content; unset when none arrived.
+    #[serde(rename = "timeToFirstTokenMs", skip_serializing_if = "Option::is_none")]
+    pub time_to_first_token_ms: Option<u64>,
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+    /// Output tokens per second after the first token.
+    #[serde(rename = "tokensPerSec", skip_serializing_if = "Option::is_none")]
+    pub tokens_per_sec: Option<f64>,
+    /// Attempts after the first, including fallbacks to other models.
+    pub retries: u32,
+    /// Provider that served the answer, which differs from the requested one after a fallback.
+    /// Aggregators add the upstream they routed to, e.g. `openrouter/Anthropic`.
+    pub provider: Provider,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -245,6 +513,8 @@ pub enum Message {
         #[serde(rename = "errorMessage", skip_serializing_if = "Option::is_none")]
         error_message: Option<String>,
         timestamp: i64,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        stats: Option<StreamStats>,
     },
     #[serde(rename = "toolResult")]
     ToolResult {
@@ -272,9 +542,100 @@ pub struct Tool {
 pub struct Context {
     #[serde(rename = "systemPrompt", skip_serializing_if = "Option::is_none")]
     pub system_prompt: Option<String>,
+    #[serde(
+        rename = "systemPromptCache",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub system_prompt_cache: Option<CacheHint>,
     pub messages: Vec<Message>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<Tool>>,
+    /// Set on contexts made by [`Context::fork_at`].
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub branch: Option<ContextBranch>,
+}
+
+/// Where a forked [`Context`] branched off its parent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextBranch {
+    /// Parent messages kept by the fork, which are all of the fork's messages at first.
+    #[serde(rename = "forkedAt")]
+    pub forked_at: usize,
+    /// Messages the parent had when it was forked.
+    #[serde(rename = "parentLength")]
+    pub parent_length: usize,
+    /// The parent's own branch, when it was a fork too.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub parent: Option<Box<ContextBranch>>,
+}
+
+impl From<ToolResultMessage> for Message {
+    fn from(message: ToolResultMessage) -> Self {
+        Message::ToolResult {
+            tool_call_id: message.tool_call_id,
+            tool_name: message.tool_name,
+            content: message.content,
+            details: message.details,
+            is_error: message.is_error,
+            timestamp: message.timestamp,
+        }
+    }
+}
+
+pub(crate) fn now_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+impl Context {
+    /// Estimated prompt tokens for sending this context to `model`; see [`crate::tokenizer`].
+    pub fn estimate_tokens(&self, model: &Model) -> u64 {
+        crate::tokenizer::estimate_context_tokens(model, self)
+    }
+
+    /// A copy that keeps the first `index` messages, to continue differently from there; an
+    /// `index` past the end keeps them all. Fails when the cut would separate a tool call
+    /// from its result, since providers reject either half alone.
+    pub fn fork_at(&self, index: usize) -> Result<Context, PiAiError> {
+        let index = index.min(self.messages.len());
+        let (kept, dropped) = self.messages.split_at(index);
+        let kept_calls = kept
+            .iter()
+            .filter_map(|message| match message {
+                Message::Assistant { content, .. } => Some(content),
+                _ => None,
+            })
+            .flat_map(|content| ToolCall::from_content(content))
+            .collect::<Vec<_>>();
+        for message in dropped {
+            if let Message::ToolResult { tool_call_id, .. } = message {
+                if let Some(call) = kept_calls.iter().find(|call| &call.id == tool_call_id) {
+                    return Err(PiAiError::new(
+                        PiAiErrorCode::ToolCallMismatch,
+                        format!(
+                            "Forking at message {index} would separate tool call '{}' ({}) from its result",
+                            call.id, call.name
+                        ),
+                    ));
+                }
+            }
+        }
+
+        Ok(Context {
+            system_prompt: self.system_prompt.clone(),
+            system_prompt_cache: self.system_prompt_cache,
+            messages: kept.to_vec(),
+            tools: self.tools.clone(),
+            branch: Some(ContextBranch {
+                forked_at: index,
+                parent_length: self.messages.len(),
+                parent: self.branch.clone().map(Box::new),
+            }),
+        })
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -342,6 +703,12 @@ pub enum AssistantMessageEvent {
         tool_call: Value,
         partial: AssistantMessage,
     },
+    /// Tokens reported since the previous `UsageDelta`; `partial.usage` holds the running total.
+    #[serde(rename = "usage_delta")]
+    UsageDelta {
+        usage: Usage,
+        partial: AssistantMessage,
+    },
     #[serde(rename = "done")]
     Done {
         reason: DoneReason,
@@ -1,9 +1,18 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use jsonschema::JSONSchema;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
 
 use crate::error::{PiAiError, PiAiErrorCode};
-use crate::types::Tool;
+use crate::types::{
+    now_millis, AssistantContentBlock, Message, ResponseSchema, Tool, ToolResultContentBlock,
+    ToolResultMessage,
+};
+
+static TOOL_CALL_COUNTER: AtomicU64 = AtomicU64::new(0);
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ToolCall {
@@ -12,6 +21,296 @@ pub struct ToolCall {
     pub arguments: Value,
 }
 
+impl ToolCall {
+    /// Collects every tool call of an assistant message, in the order the model emitted them.
+    pub fn from_content(content: &[AssistantContentBlock]) -> Vec<ToolCall> {
+        content
+            .iter()
+            .filter_map(|block| match block {
+                AssistantContentBlock::ToolCall {
+                    id,
+                    name,
+                    arguments,
+                    ..
+                } => Some(ToolCall {
+                    id: id.clone(),
+                    name: name.clone(),
+                    arguments: arguments.clone(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Starts a call built argument by argument; see [`ToolCallBuilder`].
+    pub fn builder() -> ToolCallBuilder {
+        ToolCallBuilder::default()
+    }
+
+    /// The arguments deserialized into `T`.
+    pub fn parse_arguments<T: DeserializeOwned>(&self) -> Result<T, PiAiError> {
+        serde_json::from_value(self.arguments.clone()).map_err(|error| {
+            PiAiError::new(
+                PiAiErrorCode::ToolArgumentsInvalid,
+                format!(
+                    "Arguments for tool '{}' did not deserialize: {error}",
+                    self.name
+                ),
+            )
+            .with_details(json!({
+                "toolName": self.name,
+                "toolCallId": self.id,
+            }))
+        })
+    }
+
+    /// This call as a content block of an assistant message.
+    pub fn to_content_block(&self) -> AssistantContentBlock {
+        AssistantContentBlock::ToolCall {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            arguments: self.arguments.clone(),
+            thought_signature: None,
+        }
+    }
+}
+
+/// Builds a [`ToolCall`], e.g. to replay or fake one in tests and few-shot prompts.
+#[derive(Debug, Clone, Default)]
+pub struct ToolCallBuilder {
+    id: Option<String>,
+    name: String,
+    arguments: Map<String, Value>,
+    error: Option<String>,
+}
+
+impl ToolCallBuilder {
+    /// Defaults to a fresh `pixy_call_<n>` id.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn argument(mut self, key: impl Into<String>, value: impl Serialize) -> Self {
+        let key = key.into();
+        match serde_json::to_value(value) {
+            Ok(value) => {
+                self.arguments.insert(key, value);
+            }
+            Err(error) => {
+                self.error
+                    .get_or_insert_with(|| format!("argument '{key}' did not serialize: {error}"));
+            }
+        }
+        self
+    }
+
+    /// Replaces the arguments with `arguments`, which must serialize to a JSON object.
+    pub fn arguments(mut self, arguments: impl Serialize) -> Self {
+        match serde_json::to_value(arguments) {
+            Ok(Value::Object(arguments)) => self.arguments = arguments,
+            Ok(_) => {
+                self.error = Some("arguments must serialize to a JSON object".to_string());
+            }
+            Err(error) => self.error = Some(format!("arguments did not serialize: {error}")),
+        }
+        self
+    }
+
+    pub fn build(self) -> Result<ToolCall, PiAiError> {
+        if self.name.trim().is_empty() {
+            return Err(PiAiError::new(
+                PiAiErrorCode::ToolArgumentsInvalid,
+                "Tool call has no tool name",
+            ));
+        }
+        if let Some(error) = self.error {
+            return Err(PiAiError::new(
+                PiAiErrorCode::ToolArgumentsInvalid,
+                format!("Tool call for '{}': {error}", self.name),
+            ));
+        }
+        let id = self.id.unwrap_or_else(|| {
+            let sequence = TOOL_CALL_COUNTER.fetch_add(1, Ordering::Relaxed);
+            format!("pixy_call_{sequence}")
+        });
+        Ok(ToolCall {
+            id,
+            name: self.name,
+            arguments: Value::Object(self.arguments),
+        })
+    }
+}
+
+impl ToolResultMessage {
+    /// The result of `tool_call`: `output` as JSON text, or as is when it serializes to a
+    /// string.
+    pub fn from_output<T: Serialize>(tool_call: &ToolCall, output: &T) -> Result<Self, PiAiError> {
+        let text = match serde_json::to_value(output) {
+            Ok(Value::String(text)) => text,
+            Ok(value) => value.to_string(),
+            Err(error) => {
+                return Err(PiAiError::new(
+                    PiAiErrorCode::ToolExecutionFailed,
+                    format!(
+                        "Output of tool '{}' did not serialize: {error}",
+                        tool_call.name
+                    ),
+                ));
+            }
+        };
+        Ok(Self::from_text(tool_call, text, false))
+    }
+
+    /// A failed result of `tool_call`, reported to the model as `message`.
+    pub fn from_error(tool_call: &ToolCall, message: impl Into<String>) -> Self {
+        Self::from_text(tool_call, message.into(), true)
+    }
+
+    fn from_text(tool_call: &ToolCall, text: String, is_error: bool) -> Self {
+        Self {
+            role: "toolResult".to_string(),
+            tool_call_id: tool_call.id.clone(),
+            tool_name: tool_call.name.clone(),
+            content: vec![ToolResultContentBlock::Text {
+                text,
+                text_signature: None,
+            }],
+            details: None,
+            is_error,
+            timestamp: now_millis(),
+        }
+    }
+
+    /// The text content deserialized back into `T`, the inverse of [`Self::from_output`].
+    pub fn parse_output<T: DeserializeOwned>(&self) -> Result<T, PiAiError> {
+        let text = self
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                ToolResultContentBlock::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<String>();
+        serde_json::from_str(&text)
+            .or_else(|_| serde_json::from_value(Value::String(text)))
+            .map_err(|error| {
+                PiAiError::new(
+                    PiAiErrorCode::ToolExecutionFailed,
+                    format!(
+                        "Output of tool '{}' did not deserialize: {error}",
+                        self.tool_name
+                    ),
+                )
+            })
+    }
+}
+
+/// Checks that tool call ids in `messages` line up before they are sent: every tool result
+/// answers an earlier call, and no call id is used by two calls or answered twice. Providers
+/// reject such histories with an opaque 400.
+pub fn validate_tool_call_ids(messages: &[Message]) -> Result<(), PiAiError> {
+    let mut calls = HashSet::new();
+    let mut answered = HashSet::new();
+    for (index, message) in messages.iter().enumerate() {
+        match message {
+            Message::Assistant { content, .. } => {
+                for call in ToolCall::from_content(content) {
+                    if !calls.insert(call.id.clone()) {
+                        return Err(tool_call_mismatch(
+                            format!(
+                                "Duplicate tool call id '{}' for tool '{}'",
+                                call.id, call.name
+                            ),
+                            &call.id,
+                            index,
+                        ));
+                    }
+                }
+            }
+            Message::ToolResult {
+                tool_call_id,
+                tool_name,
+                ..
+            } => {
+                if !calls.contains(tool_call_id) {
+                    return Err(tool_call_mismatch(
+                        format!(
+                            "Tool result for '{tool_name}' answers unknown tool call id '{tool_call_id}'"
+                        ),
+                        tool_call_id,
+                        index,
+                    ));
+                }
+                if !answered.insert(tool_call_id.clone()) {
+                    return Err(tool_call_mismatch(
+                        format!("Tool call id '{tool_call_id}' is answered more than once"),
+                        tool_call_id,
+                        index,
+                    ));
+                }
+            }
+            Message::User { .. } => {}
+        }
+    }
+    Ok(())
+}
+
+fn tool_call_mismatch(message: String, tool_call_id: &str, index: usize) -> PiAiError {
+    PiAiError::new(PiAiErrorCode::ToolCallMismatch, message).with_details(json!({
+        "toolCallId": tool_call_id,
+        "messageIndex": index,
+    }))
+}
+
+/// Validates all tool calls of one assistant turn, returning one result per call in order.
+///
+/// A call that reuses the id of an earlier call is rejected, since its result could not be
+/// told apart from the first one.
+pub fn validate_tool_calls(
+    tools: &[Tool],
+    tool_calls: &[ToolCall],
+) -> Vec<Result<Value, PiAiError>> {
+    tool_calls
+        .iter()
+        .enumerate()
+        .map(|(index, tool_call)| {
+            if let Some(error) = duplicate_tool_call_id_error(&tool_calls[..index], tool_call) {
+                return Err(error);
+            }
+            validate_tool_call(tools, tool_call)
+        })
+        .collect()
+}
+
+/// Returns an error when `tool_call` reuses the id of one of the `earlier` calls.
+pub fn duplicate_tool_call_id_error(
+    earlier: &[ToolCall],
+    tool_call: &ToolCall,
+) -> Option<PiAiError> {
+    earlier
+        .iter()
+        .any(|previous| previous.id == tool_call.id)
+        .then(|| {
+            PiAiError::new(
+                PiAiErrorCode::ToolArgumentsInvalid,
+                format!(
+                    "Duplicate tool call id '{}' for tool '{}'",
+                    tool_call.id, tool_call.name
+                ),
+            )
+            .with_details(json!({
+                "toolName": tool_call.name,
+                "toolCallId": tool_call.id,
+            }))
+        })
+}
+
 pub fn validate_tool_call(tools: &[Tool], tool_call: &ToolCall) -> Result<Value, PiAiError> {
     let Some(tool) = tools.iter().find(|tool| tool.name == tool_call.name) else {
         let available = tools
@@ -31,7 +330,64 @@ pub fn validate_tool_call(tools: &[Tool], tool_call: &ToolCall) -> Result<Value,
     validate_tool_arguments(tool, tool_call)
 }
 
+/// How [`validate_tool_arguments_with`] may repair arguments before checking them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolValidationOptions {
+    /// Converts strings such as `"3"` or `"true"` into the number or boolean the schema
+    /// expects, and a lone value into a one-element array.
+    #[serde(rename = "coerceTypes")]
+    pub coerce_types: bool,
+    /// Fills in missing properties whose schema declares a `default`.
+    #[serde(rename = "applyDefaults")]
+    pub apply_defaults: bool,
+}
+
+impl ToolValidationOptions {
+    /// Coercion and defaults both enabled, as used for model-generated tool calls.
+    pub fn lenient() -> Self {
+        Self {
+            coerce_types: true,
+            apply_defaults: true,
+        }
+    }
+}
+
+/// One way in which tool arguments break their schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolArgumentViolation {
+    /// JSON pointer to the offending value; empty for the arguments object itself.
+    pub path: String,
+    /// The schema keyword that failed, e.g. `type`, `enum`, `minimum` or `required`.
+    pub keyword: String,
+    pub message: String,
+}
+
+/// The violations recorded on a `ToolArgumentsInvalid` error, in the order they were found.
+pub fn tool_argument_violations(error: &PiAiError) -> Vec<ToolArgumentViolation> {
+    error
+        .details
+        .as_ref()
+        .and_then(|details| details.get("validationErrors"))
+        .cloned()
+        .and_then(|violations| serde_json::from_value(violations).ok())
+        .unwrap_or_default()
+}
+
 pub fn validate_tool_arguments(tool: &Tool, tool_call: &ToolCall) -> Result<Value, PiAiError> {
+    validate_tool_arguments_with(tool, tool_call, ToolValidationOptions::default())
+}
+
+/// Validates `tool_call` against the full JSON Schema of `tool`, after applying `options`.
+///
+/// Returns the arguments as repaired by `options`. On failure the error message lists every
+/// violation, so it can be shown to the model as is, and the details carry them as
+/// `validationErrors` (see [`tool_argument_violations`]).
+pub fn validate_tool_arguments_with(
+    tool: &Tool,
+    tool_call: &ToolCall,
+    options: ToolValidationOptions,
+) -> Result<Value, PiAiError> {
     let compiled = JSONSchema::compile(&tool.parameters).map_err(|error| {
         PiAiError::new(
             PiAiErrorCode::SchemaInvalid,
@@ -42,7 +398,160 @@ pub fn validate_tool_arguments(tool: &Tool, tool_call: &ToolCall) -> Result<Valu
         }))
     })?;
 
-    if let Err(errors) = compiled.validate(&tool_call.arguments) {
+    let mut arguments = tool_call.arguments.clone();
+    prepare_arguments(&tool.parameters, &mut arguments, options);
+
+    if let Err(errors) = compiled.validate(&arguments) {
+        let violations = errors
+            .map(|error| ToolArgumentViolation {
+                path: error.instance_path.to_string(),
+                keyword: error
+                    .schema_path
+                    .to_string()
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                message: error.to_string(),
+            })
+            .collect::<Vec<_>>();
+        let listed = violations
+            .iter()
+            .map(|violation| {
+                let path = if violation.path.is_empty() {
+                    "arguments"
+                } else {
+                    violation.path.as_str()
+                };
+                format!("- {path}: {}", violation.message)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        return Err(PiAiError::new(
+            PiAiErrorCode::ToolArgumentsInvalid,
+            format!("Validation failed for tool '{}':\n{listed}", tool.name),
+        )
+        .with_details(json!({
+            "toolName": tool.name,
+            "toolCallId": tool_call.id,
+            "arguments": tool_call.arguments,
+            "validationErrors": violations,
+        })));
+    }
+
+    Ok(arguments)
+}
+
+/// Applies coercion and defaults to `value` in place, following `properties` and `items`.
+fn prepare_arguments(schema: &Value, value: &mut Value, options: ToolValidationOptions) {
+    if options.coerce_types {
+        coerce_value(schema, value);
+    }
+    match value {
+        Value::Object(object) => {
+            let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+                return;
+            };
+            for (name, property_schema) in properties {
+                match object.get_mut(name) {
+                    Some(property) => prepare_arguments(property_schema, property, options),
+                    None if options.apply_defaults => {
+                        if let Some(default) = property_schema.get("default") {
+                            object.insert(name.clone(), default.clone());
+                        }
+                    }
+                    None => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items").filter(|items| items.is_object()) {
+                for item in items {
+                    prepare_arguments(item_schema, item, options);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Converts `value` to the first type the schema allows that it can be read as. Values that
+/// already match, or that cannot be converted, are left for validation to report.
+fn coerce_value(schema: &Value, value: &mut Value) {
+    let types = match schema.get("type") {
+        Some(Value::String(name)) => vec![name.as_str()],
+        Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+        _ => return,
+    };
+    if types.iter().any(|name| matches_type(name, value)) {
+        return;
+    }
+    for name in types {
+        let coerced = match (name, &*value) {
+            ("integer", Value::String(text)) => text.trim().parse::<i64>().ok().map(Value::from),
+            ("number", Value::String(text)) => text
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|number| number.is_finite())
+                .map(|number| {
+                    if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+                        Value::from(number as i64)
+                    } else {
+                        Value::from(number)
+                    }
+                }),
+            ("integer", Value::Number(number)) => number
+                .as_f64()
+                .filter(|number| number.fract() == 0.0)
+                .map(|number| Value::from(number as i64)),
+            ("boolean", Value::String(text)) => match text.trim() {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            ("string", Value::Number(number)) => Some(Value::String(number.to_string())),
+            ("string", Value::Bool(flag)) => Some(Value::String(flag.to_string())),
+            ("array", other) if !other.is_null() => Some(Value::Array(vec![other.clone()])),
+            _ => None,
+        };
+        if let Some(coerced) = coerced {
+            *value = coerced;
+            return;
+        }
+    }
+}
+
+fn matches_type(name: &str, value: &Value) -> bool {
+    match name {
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
+
+/// Checks a structured response against the schema it was requested with.
+pub(crate) fn validate_structured_response(
+    format: &ResponseSchema,
+    value: &Value,
+) -> Result<(), PiAiError> {
+    let compiled = JSONSchema::compile(&format.schema).map_err(|error| {
+        PiAiError::new(
+            PiAiErrorCode::SchemaInvalid,
+            format!(
+                "Invalid JSON schema for response '{}': {error}",
+                format.name
+            ),
+        )
+    })?;
+
+    if let Err(errors) = compiled.validate(value) {
         let validation_errors = errors
             .map(|error| {
                 json!({
@@ -53,16 +562,14 @@ pub fn validate_tool_arguments(tool: &Tool, tool_call: &ToolCall) -> Result<Valu
             .collect::<Vec<_>>();
 
         return Err(PiAiError::new(
-            PiAiErrorCode::ToolArgumentsInvalid,
-            format!("Validation failed for tool '{}'", tool.name),
+            PiAiErrorCode::ProviderProtocol,
+            format!("Response does not match schema '{}'", format.name),
         )
         .with_details(json!({
-            "toolName": tool.name,
-            "toolCallId": tool_call.id,
-            "arguments": tool_call.arguments,
+            "response": value,
             "validationErrors": validation_errors,
         })));
     }
 
-    Ok(tool_call.arguments.clone())
+    Ok(())
 }
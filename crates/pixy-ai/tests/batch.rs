@@ -0,0 +1,328 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use pixy_ai::{
+    complete_batch, AssistantContentBlock, AssistantMessage, AssistantMessageEvent, BatchOptions,
+    BatchRequest, Context, Cost, Message, Model, StopReason, StreamMiddleware, StreamOptions,
+    UserContent,
+};
+
+const COMPLETIONS_BODY: &str = concat!(
+    "data: {\"choices\":[{\"delta\":{\"content\":\"streamed\"},\"finish_reason\":\"stop\"}]}\n\n",
+    "data: [DONE]\n\n",
+);
+
+fn sample_model(base_url: &str) -> Model {
+    Model {
+        id: "gpt-4o-mini".to_string(),
+        name: "GPT-4o mini".to_string(),
+        api: "openai-completions".to_string(),
+        provider: "openai".to_string(),
+        base_url: base_url.to_string(),
+        reasoning: false,
+        reasoning_effort: None,
+        input: vec!["text".to_string()],
+        cost: Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+        context_window: 128_000,
+        max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
+    }
+}
+
+fn request(base_url: &str, prompt: &str) -> BatchRequest {
+    BatchRequest {
+        model: sample_model(base_url),
+        context: Context {
+            system_prompt: None,
+            messages: vec![Message::User {
+                content: UserContent::Text(prompt.to_string()),
+                timestamp: 1_700_000_000_000,
+            }],
+            tools: None,
+            system_prompt_cache: None,
+            branch: None,
+        },
+        options: Some(StreamOptions {
+            api_key: Some("test-key".to_string()),
+            ..StreamOptions::default()
+        }),
+    }
+}
+
+fn text_of(message: &AssistantMessage) -> String {
+    message
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            AssistantContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Reads one HTTP request, body included, and returns its method, path and body.
+fn read_request(socket: &mut TcpStream) -> (String, String, String) {
+    socket
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .expect("set read timeout");
+    let mut data = Vec::new();
+    let mut buffer = [0_u8; 8192];
+    loop {
+        let read_len = socket.read(&mut buffer).unwrap_or(0);
+        data.extend_from_slice(&buffer[..read_len]);
+        let text = String::from_utf8_lossy(&data).to_string();
+        if let Some(header_end) = text.find("\r\n\r\n") {
+            let content_length = text[..header_end]
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if read_len == 0 || data.len() >= header_end + 4 + content_length {
+                let mut request_line = text.lines().next().unwrap_or("").split_whitespace();
+                let method = request_line.next().unwrap_or("").to_string();
+                let path = request_line.next().unwrap_or("").to_string();
+                return (method, path, text[header_end + 4..].to_string());
+            }
+        }
+        if read_len == 0 {
+            return (String::new(), String::new(), text);
+        }
+    }
+}
+
+fn respond(socket: &mut TcpStream, content_type: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    socket
+        .write_all(response.as_bytes())
+        .expect("write response");
+    let _ = socket.flush();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn complete_batch_fans_out_within_the_concurrency_limit() {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind local test server");
+    let base_url = format!("http://{}/v1", listener.local_addr().expect("local addr"));
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let max_in_flight = Arc::new(AtomicUsize::new(0));
+    let server_in_flight = Arc::clone(&in_flight);
+    let server_max = Arc::clone(&max_in_flight);
+    thread::spawn(move || {
+        for mut socket in listener.incoming().flatten() {
+            let in_flight = Arc::clone(&server_in_flight);
+            let max_in_flight = Arc::clone(&server_max);
+            thread::spawn(move || {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+                read_request(&mut socket);
+                thread::sleep(Duration::from_millis(50));
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                respond(&mut socket, "text/event-stream", COMPLETIONS_BODY);
+            });
+        }
+    });
+
+    let requests = (0..5)
+        .map(|index| request(&base_url, &format!("prompt {index}")))
+        .collect();
+    let results = complete_batch(
+        requests,
+        BatchOptions {
+            concurrency: 2,
+            ..BatchOptions::default()
+        },
+    );
+
+    let mut indices = Vec::new();
+    while let Some((index, message)) = results.next().await {
+        assert_eq!(message.stop_reason, StopReason::Stop);
+        assert_eq!(text_of(&message), "streamed");
+        indices.push(index);
+    }
+    indices.sort_unstable();
+    assert_eq!(indices, vec![0, 1, 2, 3, 4]);
+    assert_eq!(results.result().await, Some(()));
+    assert!(max_in_flight.load(Ordering::SeqCst) <= 2);
+}
+
+#[tokio::test]
+async fn complete_batch_submits_openai_batch_jobs() {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind local test server");
+    let base_url = format!("http://{}/v1", listener.local_addr().expect("local addr"));
+    let uploaded = Arc::new(Mutex::new(String::new()));
+    let server_uploaded = Arc::clone(&uploaded);
+    thread::spawn(move || {
+        let mut status_polls = 0;
+        for mut socket in listener.incoming().flatten() {
+            let (method, path, body) = read_request(&mut socket);
+            let response = match (method.as_str(), path.as_str()) {
+                ("POST", "/v1/files") => {
+                    *server_uploaded.lock().expect("upload lock") = body;
+                    r#"{"id":"file-in"}"#.to_string()
+                }
+                ("POST", "/v1/batches") => {
+                    assert!(body.contains(r#""input_file_id":"file-in""#));
+                    r#"{"id":"batch_1","status":"validating"}"#.to_string()
+                }
+                ("GET", "/v1/batches/batch_1") => {
+                    status_polls += 1;
+                    if status_polls == 1 {
+                        r#"{"id":"batch_1","status":"in_progress"}"#.to_string()
+                    } else {
+                        r#"{"id":"batch_1","status":"completed","output_file_id":"file-out"}"#
+                            .to_string()
+                    }
+                }
+                ("GET", "/v1/files/file-out/content") => [
+                    r#"{"custom_id":"request-1","response":{"status_code":400,"body":{"error":{"message":"bad request"}}},"error":null}"#,
+                    r#"{"custom_id":"request-0","response":{"status_code":200,"body":{"choices":[{"message":{"role":"assistant","content":"batched"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1000000,"completion_tokens":10}}},"error":null}"#,
+                ]
+                .join("\n"),
+                _ => r#"{"error":"unknown path"}"#.to_string(),
+            };
+            respond(&mut socket, "application/json", &response);
+        }
+    });
+
+    let results = complete_batch(
+        vec![
+            request(&base_url, "first"),
+            request(&base_url, "second"),
+            request(&base_url, "third"),
+        ],
+        BatchOptions {
+            provider_batch: true,
+            poll_interval: Duration::from_millis(10),
+            ..BatchOptions::default()
+        },
+    );
+    let mut messages = Vec::new();
+    while let Some(result) = results.next().await {
+        messages.push(result);
+    }
+    messages.sort_by_key(|(index, _)| *index);
+
+    let upload = uploaded.lock().expect("upload lock").clone();
+    assert!(upload.contains("name=\"purpose\"\r\n\r\nbatch"));
+    assert!(upload.contains(r#""custom_id":"request-2""#));
+    assert!(upload.contains(r#""url":"/v1/chat/completions""#));
+    assert!(!upload.contains(r#""stream":true"#));
+
+    assert_eq!(messages.len(), 3);
+    let (_, first) = &messages[0];
+    assert_eq!(first.stop_reason, StopReason::Stop);
+    assert_eq!(text_of(first), "batched");
+    assert_eq!(first.usage.input, 1_000_000);
+    assert!(first.usage.cost.total > 0.0);
+
+    let (_, second) = &messages[1];
+    assert_eq!(second.stop_reason, StopReason::Error);
+    assert!(second
+        .error_message
+        .as_deref()
+        .is_some_and(|message| message.contains("bad request")));
+
+    let (_, third) = &messages[2];
+    assert_eq!(third.stop_reason, StopReason::Error);
+    assert!(third
+        .error_message
+        .as_deref()
+        .is_some_and(|message| message.contains("without a result")));
+}
+
+/// Rewrites the terminal event into a text delta, which no terminal event may become.
+struct DemotingMiddleware;
+
+impl StreamMiddleware for DemotingMiddleware {
+    fn on_event(&self, event: &mut AssistantMessageEvent) {
+        if let AssistantMessageEvent::Done { message, .. } = event {
+            *event = AssistantMessageEvent::TextDelta {
+                content_index: 0,
+                delta: String::new(),
+                partial: message.clone(),
+            };
+        }
+    }
+}
+
+#[tokio::test]
+async fn complete_batch_splits_jobs_by_credentials_and_survives_demoting_middleware() {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind local test server");
+    let base_url = format!("http://{}/v1", listener.local_addr().expect("local addr"));
+    let created_jobs = Arc::new(AtomicUsize::new(0));
+    let server_jobs = Arc::clone(&created_jobs);
+    thread::spawn(move || {
+        for mut socket in listener.incoming().flatten() {
+            let (method, path, _) = read_request(&mut socket);
+            let response = match (method.as_str(), path.as_str()) {
+                ("POST", "/v1/files") => r#"{"id":"file-in"}"#.to_string(),
+                ("POST", "/v1/batches") => {
+                    server_jobs.fetch_add(1, Ordering::SeqCst);
+                    r#"{"id":"batch_1","status":"validating"}"#.to_string()
+                }
+                ("GET", "/v1/batches/batch_1") => {
+                    r#"{"id":"batch_1","status":"completed","output_file_id":"file-out"}"#
+                        .to_string()
+                }
+                ("GET", "/v1/files/file-out/content") => [
+                    r#"{"custom_id":"request-0","response":{"status_code":200,"body":{"choices":[{"message":{"role":"assistant","content":"batched"},"finish_reason":"stop"}]}},"error":null}"#,
+                    r#"{"custom_id":"request-1","response":{"status_code":200,"body":{"choices":[{"message":{"role":"assistant","content":"batched"},"finish_reason":"stop"}]}},"error":null}"#,
+                ]
+                .join("\n"),
+                _ => r#"{"error":"unknown path"}"#.to_string(),
+            };
+            respond(&mut socket, "application/json", &response);
+        }
+    });
+
+    let mut first = request(&base_url, "first");
+    let mut second = request(&base_url, "second");
+    if let Some(options) = second.options.as_mut() {
+        options.api_key = Some("other-key".to_string());
+    }
+    if let Some(options) = first.options.as_mut() {
+        options.middleware = vec![Arc::new(DemotingMiddleware)];
+    }
+    let results = complete_batch(
+        vec![first, second],
+        BatchOptions {
+            provider_batch: true,
+            poll_interval: Duration::from_millis(10),
+            ..BatchOptions::default()
+        },
+    );
+    let mut messages = Vec::new();
+    while let Some(result) = results.next().await {
+        messages.push(result);
+    }
+    messages.sort_by_key(|(index, _)| *index);
+
+    assert_eq!(created_jobs.load(Ordering::SeqCst), 2);
+    assert_eq!(messages.len(), 2);
+    let (_, demoted) = &messages[0];
+    assert_eq!(demoted.stop_reason, StopReason::Error);
+    assert!(demoted
+        .error_message
+        .as_deref()
+        .is_some_and(|message| message.contains("non-terminal")));
+    let (_, other) = &messages[1];
+    assert_eq!(other.stop_reason, StopReason::Stop);
+    assert_eq!(text_of(other), "batched");
+}
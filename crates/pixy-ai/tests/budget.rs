@@ -0,0 +1,139 @@
+use pixy_ai::{
+    AssistantMessage, BudgetAction, BudgetGuard, BudgetLimits, Context, Cost, Model, PiAiErrorCode,
+    StopReason, StreamMiddleware, StreamOptions, StreamRequest, Usage,
+};
+
+fn sample_model(id: &str) -> Model {
+    Model {
+        id: id.to_string(),
+        name: id.to_string(),
+        api: "openai-responses".to_string(),
+        provider: "openai".to_string(),
+        base_url: "https://api.openai.com/v1".to_string(),
+        reasoning: false,
+        reasoning_effort: None,
+        input: vec!["text".to_string()],
+        cost: Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+        context_window: 128_000,
+        max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
+    }
+}
+
+fn sample_request(model_id: &str) -> StreamRequest {
+    StreamRequest {
+        model: sample_model(model_id),
+        context: Context {
+            system_prompt: None,
+            messages: Vec::new(),
+            tools: None,
+            system_prompt_cache: None,
+            branch: None,
+        },
+        options: StreamOptions::default(),
+    }
+}
+
+fn message_costing(total: f64) -> AssistantMessage {
+    AssistantMessage {
+        role: "assistant".to_string(),
+        content: Vec::new(),
+        api: "openai-responses".to_string(),
+        provider: "openai".to_string(),
+        model: "gpt-4o".to_string(),
+        usage: Usage {
+            input: 0,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            reasoning: 0,
+            total_tokens: 0,
+            cost: Cost {
+                input: 0.0,
+                output: total,
+                cache_read: 0.0,
+                cache_write: 0.0,
+                total,
+            },
+        },
+        stop_reason: StopReason::Stop,
+        error_message: None,
+        timestamp: 0,
+        stats: None,
+    }
+}
+
+#[tokio::test]
+async fn reject_budget_refuses_requests_once_the_session_limit_is_spent() {
+    let guard = BudgetGuard::new(
+        BudgetLimits {
+            session_usd: Some(1.0),
+            daily_usd: None,
+        },
+        BudgetAction::Reject,
+    );
+
+    let mut request = sample_request("gpt-4o");
+    guard.on_request(&mut request).await.expect("within budget");
+    assert_eq!(request.options.max_tokens, None);
+
+    guard.on_complete(&message_costing(0.6));
+    guard.on_complete(&message_costing(0.5));
+    assert!((guard.spend().session_usd - 1.1).abs() < 1e-9);
+    assert_eq!(guard.remaining_usd(), Some(0.0));
+
+    let error = guard
+        .on_request(&mut sample_request("gpt-4o"))
+        .await
+        .expect_err("over budget");
+    assert_eq!(error.code, PiAiErrorCode::BudgetExceeded);
+}
+
+#[tokio::test]
+async fn truncate_budget_caps_max_tokens_to_the_affordable_output() {
+    let guard = BudgetGuard::new(
+        BudgetLimits {
+            session_usd: Some(10.0),
+            daily_usd: Some(0.01),
+        },
+        BudgetAction::Truncate,
+    );
+
+    // gpt-4o output is $10 per million tokens, so $0.01 buys 1,000 tokens.
+    let mut request = sample_request("gpt-4o");
+    guard.on_request(&mut request).await.expect("within budget");
+    assert_eq!(request.options.max_tokens, Some(1_000));
+
+    // Models without known pricing are never capped.
+    let mut local = sample_request("my-local-llama");
+    guard.on_request(&mut local).await.expect("unpriced model");
+    assert_eq!(local.options.max_tokens, None);
+
+    guard.record_spend(0.01);
+    let error = guard
+        .on_request(&mut sample_request("gpt-4o"))
+        .await
+        .expect_err("daily budget spent");
+    assert_eq!(error.code, PiAiErrorCode::BudgetExceeded);
+}
+
+#[tokio::test]
+async fn unlimited_budget_only_tracks_spend() {
+    let guard = BudgetGuard::new(BudgetLimits::default(), BudgetAction::Reject);
+    guard.on_complete(&message_costing(25.0));
+
+    assert_eq!(guard.remaining_usd(), None);
+    assert!((guard.spend().daily_usd - 25.0).abs() < 1e-9);
+    guard
+        .on_request(&mut sample_request("gpt-4o"))
+        .await
+        .expect("no limit configured");
+}
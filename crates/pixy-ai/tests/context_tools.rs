@@ -0,0 +1,280 @@
+use std::sync::{Arc, Mutex};
+
+use pixy_ai::{
+    register_api_provider, AssistantContentBlock, AssistantMessage, AssistantMessageEvent,
+    ClosureApiProvider, CompressionStrategy, Context, ContextBranch, Cost, DoneReason, Message,
+    Model, StopReason, TokenBudget, ToolResultContentBlock, Usage, UserContent,
+};
+use serde_json::json;
+
+fn sample_model(api: &str) -> Model {
+    Model {
+        id: "summarizer".to_string(),
+        name: "Summarizer".to_string(),
+        api: api.to_string(),
+        provider: api.to_string(),
+        base_url: "http://localhost".to_string(),
+        reasoning: false,
+        reasoning_effort: None,
+        input: vec!["text".to_string()],
+        cost: Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+        context_window: 128_000,
+        max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
+    }
+}
+
+fn empty_usage() -> Usage {
+    Usage {
+        input: 0,
+        output: 0,
+        cache_read: 0,
+        cache_write: 0,
+        reasoning: 0,
+        total_tokens: 0,
+        cost: Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+    }
+}
+
+fn user(text: &str) -> Message {
+    Message::User {
+        content: UserContent::Text(text.to_string()),
+        timestamp: 0,
+    }
+}
+
+fn tool_call(id: &str) -> Message {
+    Message::Assistant {
+        content: vec![AssistantContentBlock::ToolCall {
+            id: id.to_string(),
+            name: "read".to_string(),
+            arguments: json!({ "path": "src/lib.rs" }),
+            thought_signature: None,
+        }],
+        api: "test".to_string(),
+        provider: "test".to_string(),
+        model: "test".to_string(),
+        usage: empty_usage(),
+        stop_reason: StopReason::ToolUse,
+        error_message: None,
+        timestamp: 0,
+        stats: None,
+    }
+}
+
+fn tool_result(id: &str, text: &str) -> Message {
+    Message::ToolResult {
+        tool_call_id: id.to_string(),
+        tool_name: "read".to_string(),
+        content: vec![ToolResultContentBlock::Text {
+            text: text.to_string(),
+            text_signature: None,
+        }],
+        details: None,
+        is_error: false,
+        timestamp: 0,
+    }
+}
+
+/// Three turns, each a question, a tool call and a long tool result.
+fn long_context() -> Context {
+    let output = "fn main() {}\n".repeat(200);
+    Context {
+        system_prompt: Some("You are a file assistant".to_string()),
+        messages: (0..3)
+            .flat_map(|turn| {
+                [
+                    user(&format!("question {turn}")),
+                    tool_call(&format!("call-{turn}")),
+                    tool_result(&format!("call-{turn}"), &output),
+                ]
+            })
+            .collect(),
+        tools: None,
+        system_prompt_cache: None,
+        branch: None,
+    }
+}
+
+fn budget(max_tokens: u64) -> TokenBudget {
+    TokenBudget::for_model(&sample_model("context-tools"), max_tokens)
+}
+
+fn tool_result_text(message: &Message) -> Option<&str> {
+    match message {
+        Message::ToolResult { content, .. } => match content.as_slice() {
+            [ToolResultContentBlock::Text { text, .. }] => Some(text),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[tokio::test]
+async fn contexts_within_budget_are_returned_unchanged() {
+    let context = long_context();
+    let compressed = context
+        .compress(&CompressionStrategy::TruncateToBudget, &budget(1_000_000))
+        .await
+        .expect("compress");
+    assert_eq!(compressed, context);
+}
+
+#[tokio::test]
+async fn drop_old_tool_results_replaces_oldest_output_first() {
+    let context = long_context();
+    let full = budget(0).tokenizer.context_tokens(&context);
+    let one_result = budget(0).tokenizer.message_tokens(&context.messages[2]);
+    let compressed = context
+        .compress(
+            &CompressionStrategy::DropOldToolResults { keep_recent: 3 },
+            &budget(full - one_result / 2),
+        )
+        .await
+        .expect("compress");
+
+    assert_eq!(compressed.messages.len(), context.messages.len());
+    assert!(tool_result_text(&compressed.messages[2]).is_some_and(|text| text.contains("omitted")));
+    assert_eq!(compressed.messages[5], context.messages[5]);
+    assert_eq!(compressed.messages[8], context.messages[8]);
+}
+
+#[tokio::test]
+async fn truncate_to_budget_cuts_whole_turns_from_the_front() {
+    let context = long_context();
+    let tokenizer = budget(0).tokenizer;
+    let full = tokenizer.context_tokens(&context);
+    let first_turn = context.messages[..3]
+        .iter()
+        .map(|message| tokenizer.message_tokens(message))
+        .sum::<u64>();
+    let compressed = context
+        .compress(
+            &CompressionStrategy::TruncateToBudget,
+            &budget(full - first_turn),
+        )
+        .await
+        .expect("compress");
+
+    assert_eq!(compressed.messages, context.messages[3..]);
+
+    let last_turn_only = context
+        .compress(&CompressionStrategy::TruncateToBudget, &budget(1))
+        .await
+        .expect("compress");
+    assert_eq!(last_turn_only.messages, context.messages[6..]);
+}
+
+#[tokio::test]
+async fn summarize_middle_replaces_middle_turns_with_a_model_summary() {
+    let prompts = Arc::new(Mutex::new(Vec::new()));
+    let seen = prompts.clone();
+    register_api_provider(
+        Arc::new(ClosureApiProvider {
+            api: "context-summarizer".to_string(),
+            stream: Arc::new(move |model, context, _, stream| {
+                if let Some(Message::User {
+                    content: UserContent::Text(text),
+                    ..
+                }) = context.messages.first()
+                {
+                    seen.lock().expect("prompts").push(text.clone());
+                }
+                Box::pin(async move {
+                    stream.push(AssistantMessageEvent::Done {
+                        reason: DoneReason::Stop,
+                        message: AssistantMessage {
+                            role: "assistant".to_string(),
+                            content: vec![AssistantContentBlock::Text {
+                                text: "The user asked about question 1.".to_string(),
+                                text_signature: None,
+                            }],
+                            api: model.api.clone(),
+                            provider: model.provider.clone(),
+                            model: model.id.clone(),
+                            usage: empty_usage(),
+                            stop_reason: StopReason::Stop,
+                            error_message: None,
+                            timestamp: 0,
+                            stats: None,
+                        },
+                    });
+                    Ok(())
+                })
+            }),
+            stream_simple: Arc::new(|_, _, _, _| Box::pin(async { Ok(()) })),
+        }),
+        Some("context-tools-test".to_string()),
+    );
+
+    let context = long_context();
+    let compressed = context
+        .compress(
+            &CompressionStrategy::SummarizeMiddle {
+                model: sample_model("context-summarizer"),
+                options: None,
+                keep_first: 1,
+                keep_recent: 2,
+            },
+            &budget(1),
+        )
+        .await
+        .expect("compress");
+
+    assert_eq!(compressed.messages.len(), 7);
+    assert_eq!(compressed.messages[..3], context.messages[..3]);
+    assert!(matches!(
+        &compressed.messages[3],
+        Message::User { content: UserContent::Text(text), .. }
+            if text.contains("The user asked about question 1.")
+    ));
+    assert_eq!(compressed.messages[4..], context.messages[6..]);
+    let prompts = prompts.lock().expect("prompts");
+    assert_eq!(prompts.len(), 1);
+    assert!(prompts[0].contains("[user]: question 1"));
+    assert!(!prompts[0].contains("question 2"));
+}
+
+#[test]
+fn fork_at_keeps_earlier_turns_and_records_the_branch() {
+    let context = long_context();
+    let fork = context.fork_at(3).expect("fork between turns");
+    assert_eq!(fork.messages, context.messages[..3]);
+    assert_eq!(fork.system_prompt, context.system_prompt);
+    assert_eq!(
+        fork.branch,
+        Some(ContextBranch {
+            forked_at: 3,
+            parent_length: 9,
+            parent: None,
+        })
+    );
+
+    let nested = fork.fork_at(1).expect("fork after the first question");
+    let branch = nested.branch.expect("branch");
+    assert_eq!(branch.forked_at, 1);
+    assert_eq!(branch.parent.expect("parent").forked_at, 3);
+
+    let error = context
+        .fork_at(2)
+        .expect_err("cut between a tool call and its result");
+    assert_eq!(error.code, pixy_ai::PiAiErrorCode::ToolCallMismatch);
+    assert_eq!(
+        context.fork_at(99).expect("whole context").messages.len(),
+        9
+    );
+}
@@ -0,0 +1,109 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use pixy_ai::{embed, EmbeddingModel, EmbeddingOptions, PiAiErrorCode};
+use serde_json::{json, Value};
+
+fn spawn_embeddings_server(status: &str, body: Value) -> (String, Arc<Mutex<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind local test server");
+    let address = listener.local_addr().expect("server local addr");
+    let captured = Arc::new(Mutex::new(String::new()));
+    let captured_request = captured.clone();
+    let status = status.to_string();
+    let body = body.to_string();
+    thread::spawn(move || {
+        if let Ok((mut socket, _)) = listener.accept() {
+            socket
+                .set_read_timeout(Some(Duration::from_millis(500)))
+                .expect("set read timeout");
+            let mut request = Vec::new();
+            let mut buffer = [0_u8; 8192];
+            while let Ok(read) = socket.read(&mut buffer) {
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request);
+                if text.contains("\r\n\r\n") && text.trim_end().ends_with('}') {
+                    break;
+                }
+            }
+            *captured_request.lock().expect("capture lock") =
+                String::from_utf8_lossy(&request).to_string();
+
+            let response = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket
+                .write_all(response.as_bytes())
+                .expect("write response");
+            let _ = socket.flush();
+        }
+    });
+    (format!("http://{address}/v1"), captured)
+}
+
+fn sample_model(base_url: String) -> EmbeddingModel {
+    EmbeddingModel {
+        id: "text-embedding-3-small".to_string(),
+        provider: "openai".to_string(),
+        base_url,
+        dimensions: None,
+    }
+}
+
+#[tokio::test]
+async fn embed_returns_vectors_in_input_order() {
+    let (base_url, captured) = spawn_embeddings_server(
+        "200 OK",
+        json!({
+            "data": [
+                { "index": 1, "embedding": [0.0, 1.0] },
+                { "index": 0, "embedding": [1.0, 0.0] }
+            ]
+        }),
+    );
+    let options = EmbeddingOptions {
+        api_key: Some("test-key".to_string()),
+        headers: None,
+    };
+
+    let vectors = embed(
+        &sample_model(base_url),
+        &["first".to_string(), "second".to_string()],
+        Some(&options),
+    )
+    .await
+    .expect("embeddings should succeed");
+
+    assert_eq!(vectors, vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+    let request = captured.lock().expect("capture lock").clone();
+    assert!(request.starts_with("POST /v1/embeddings"));
+    assert!(request.contains("Bearer test-key"));
+    assert!(request.contains("\"input\":[\"first\",\"second\"]"));
+}
+
+#[tokio::test]
+async fn embed_reports_http_errors() {
+    let (base_url, _) = spawn_embeddings_server("401 Unauthorized", json!({ "error": "bad key" }));
+    let options = EmbeddingOptions {
+        api_key: Some("test-key".to_string()),
+        headers: None,
+    };
+
+    let error = embed(
+        &sample_model(base_url),
+        &["text".to_string()],
+        Some(&options),
+    )
+    .await
+    .expect_err("unauthorized should fail");
+
+    assert_eq!(error.code, PiAiErrorCode::AuthInvalid);
+    assert!(error.message.contains("401"));
+}
@@ -54,6 +54,9 @@ fn sample_model(api: &str, provider: &str, base_url: String) -> Model {
         },
         context_window: 128_000,
         max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
     }
 }
 
@@ -75,6 +78,8 @@ fn sample_context() -> Context {
                 "required": ["path"]
             }),
         }]),
+        system_prompt_cache: None,
+        branch: None,
     }
 }
 
@@ -126,9 +131,30 @@ fn google_provider_parses_text_tool_and_usage() {
         Some(StreamOptions {
             api_key: Some("AIza-test-key".to_string()),
             temperature: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            extra_sampling: Default::default(),
             max_tokens: None,
             headers: None,
             transport_retry_count: None,
+            api_version: None,
+            response_format: None,
+            stop_predicate: None,
+            middleware: Vec::new(),
+            guards: Vec::new(),
+            response_cache: None,
+            fallback_models: Vec::new(),
+            provider_preferences: Default::default(),
+            stop_sequences: Vec::new(),
+            retry_policy: None,
+            thinking_budget: None,
+            rate_limit_policy: None,
+            stream_transport: None,
+            image_generation: false,
+            audio_voice: None,
         }),
     )
     .expect("stream should start");
@@ -198,9 +224,30 @@ fn bedrock_provider_parses_text_tool_and_usage() {
         Some(StreamOptions {
             api_key: Some("bedrock-token".to_string()),
             temperature: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            extra_sampling: Default::default(),
             max_tokens: None,
             headers: None,
             transport_retry_count: None,
+            api_version: None,
+            response_format: None,
+            stop_predicate: None,
+            middleware: Vec::new(),
+            guards: Vec::new(),
+            response_cache: None,
+            fallback_models: Vec::new(),
+            provider_preferences: Default::default(),
+            stop_sequences: Vec::new(),
+            retry_policy: None,
+            thinking_budget: None,
+            rate_limit_policy: None,
+            stream_transport: None,
+            image_generation: false,
+            audio_voice: None,
         }),
     )
     .expect("stream should start");
@@ -0,0 +1,157 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+use pixy_ai::{
+    list_models, lookup_model_info, Cost, Model, ModelCatalog, ModelSource, PiAiErrorCode,
+};
+
+fn spawn_json_server(body: &'static str) -> (String, thread::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind local test server");
+    let address = listener.local_addr().expect("server local addr");
+    let handle = thread::spawn(move || {
+        let (mut socket, _) = listener.accept().expect("accept request");
+        socket
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .expect("set read timeout");
+        let mut buffer = [0_u8; 8192];
+        let read_len = socket.read(&mut buffer).unwrap_or(0);
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        socket
+            .write_all(response.as_bytes())
+            .expect("write response");
+        String::from_utf8_lossy(&buffer[..read_len]).to_string()
+    });
+    (format!("http://{address}/v1"), handle)
+}
+
+fn configured_model(id: &str) -> Model {
+    Model {
+        id: id.to_string(),
+        name: id.to_string(),
+        api: "openai-completions".to_string(),
+        provider: "openai".to_string(),
+        base_url: "https://api.openai.com/v1".to_string(),
+        reasoning: false,
+        reasoning_effort: None,
+        input: vec!["text".to_string()],
+        cost: Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+        context_window: 32_000,
+        max_tokens: 1_000,
+        deployment: None,
+        api_version: None,
+        compat: None,
+    }
+}
+
+#[test]
+fn bundled_model_info_matches_the_longest_prefix() {
+    let sonnet = lookup_model_info("anthropic/claude-sonnet-4-20250514").expect("sonnet info");
+    assert_eq!(sonnet.context_window, 200_000);
+    assert!(sonnet.reasoning);
+
+    let mini = lookup_model_info("o3-mini-2025-01-31").expect("o3-mini info");
+    assert!(!mini.images);
+    assert!(lookup_model_info("o3-2025-04-16").expect("o3 info").images);
+    assert_eq!(lookup_model_info("unknown-model"), None);
+}
+
+#[tokio::test]
+async fn refresh_merges_listed_models_behind_configured_ones() {
+    let (base_url, request) = spawn_json_server(
+        r#"{"object":"list","data":[{"id":"gpt-4o"},{"id":"o4-mini"},{"id":"text-embedding-3-small"},{"id":"custom-chat"}]}"#,
+    );
+    let mut catalog = ModelCatalog::new(vec![configured_model("gpt-4o")]);
+    let failures = catalog
+        .refresh(&[ModelSource {
+            provider: "openai".to_string(),
+            api: "openai-completions".to_string(),
+            base_url,
+            api_key: Some("test-key".to_string()),
+        }])
+        .await;
+
+    assert!(failures.is_empty());
+    let request = request.join().expect("server thread");
+    assert!(request.starts_with("GET /v1/models "));
+    assert!(request
+        .to_ascii_lowercase()
+        .contains("authorization: bearer test-key"));
+
+    let ids = catalog
+        .models()
+        .iter()
+        .map(|model| model.id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec!["gpt-4o", "o4-mini", "custom-chat"]);
+    assert_eq!(
+        catalog
+            .find("openai", "gpt-4o")
+            .expect("gpt-4o")
+            .context_window,
+        32_000
+    );
+
+    let o4_mini = catalog.find("openai", "o4-mini").expect("o4-mini");
+    assert!(o4_mini.reasoning);
+    assert_eq!(o4_mini.context_window, 200_000);
+    assert_eq!(o4_mini.input, vec!["text", "image"]);
+    assert!(o4_mini.cost.output > 0.0);
+
+    let custom = catalog.find("openai", "custom-chat").expect("custom-chat");
+    assert_eq!(custom.context_window, 128_000);
+    assert_eq!(custom.cost.output, 0.0);
+}
+
+#[tokio::test]
+async fn google_listings_keep_their_own_token_limits() {
+    let (base_url, _request) = spawn_json_server(
+        r#"{"models":[
+            {"name":"models/gemini-2.5-flash","displayName":"Gemini 2.5 Flash","inputTokenLimit":1000000,"outputTokenLimit":64000,"supportedGenerationMethods":["generateContent","countTokens"]},
+            {"name":"models/gemini-embedding-001","supportedGenerationMethods":["embedContent"]}
+        ]}"#,
+    );
+    let models = list_models(&ModelSource {
+        provider: "google".to_string(),
+        api: "google-generative-ai".to_string(),
+        base_url,
+        api_key: Some("test-key".to_string()),
+    })
+    .await
+    .expect("list models");
+
+    assert_eq!(models.len(), 1);
+    assert_eq!(models[0].id, "gemini-2.5-flash");
+    assert_eq!(models[0].name, "Gemini 2.5 Flash");
+    assert_eq!(models[0].context_window, 1_000_000);
+    assert_eq!(models[0].max_tokens, 64_000);
+}
+
+#[tokio::test]
+async fn refresh_reports_sources_that_cannot_be_listed() {
+    let mut catalog = ModelCatalog::new(vec![configured_model("gpt-4o")]);
+    let failures = catalog
+        .refresh(&[ModelSource {
+            provider: "vertex".to_string(),
+            api: "google-vertex".to_string(),
+            base_url: "http://127.0.0.1:9".to_string(),
+            api_key: None,
+        }])
+        .await;
+
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].0, "vertex");
+    assert_eq!(failures[0].1.code, PiAiErrorCode::ProviderProtocol);
+    assert_eq!(catalog.models().len(), 1);
+}
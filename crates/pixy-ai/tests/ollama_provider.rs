@@ -0,0 +1,204 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+use pixy_ai::{
+    list_ollama_models, stream, AssistantContentBlock, Context, Cost, Message, Model, StopReason,
+    Tool, UserContent,
+};
+use serde_json::json;
+
+fn spawn_ndjson_server(body: String) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind local test server");
+    let address = listener.local_addr().expect("server local addr");
+    thread::spawn(move || {
+        if let Ok((mut socket, _)) = listener.accept() {
+            socket
+                .set_read_timeout(Some(Duration::from_secs(2)))
+                .expect("set read timeout");
+            let mut buffer = [0_u8; 8192];
+            let _ = socket.read(&mut buffer);
+
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket
+                .write_all(response.as_bytes())
+                .expect("write response");
+            let _ = socket.flush();
+        }
+    });
+
+    format!("http://{address}")
+}
+
+fn sample_model(base_url: String) -> Model {
+    Model {
+        id: "qwen3:8b".to_string(),
+        name: "qwen3:8b".to_string(),
+        api: "ollama".to_string(),
+        provider: "ollama".to_string(),
+        base_url,
+        reasoning: true,
+        reasoning_effort: None,
+        input: vec!["text".to_string()],
+        cost: Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+        context_window: 32_768,
+        max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
+    }
+}
+
+fn sample_context() -> Context {
+    Context {
+        system_prompt: Some("You are a file assistant".to_string()),
+        messages: vec![Message::User {
+            content: UserContent::Text("Read Cargo.toml".to_string()),
+            timestamp: 1_700_000_000_000,
+        }],
+        tools: Some(vec![Tool {
+            name: "read".to_string(),
+            description: "Read a file".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": { "path": { "type": "string" } },
+                "required": ["path"]
+            }),
+        }]),
+        system_prompt_cache: None,
+        branch: None,
+    }
+}
+
+fn ndjson(lines: &[serde_json::Value]) -> String {
+    lines
+        .iter()
+        .map(|line| format!("{line}\n"))
+        .collect::<String>()
+}
+
+#[tokio::test]
+async fn ollama_provider_streams_thinking_text_tool_calls_and_usage() {
+    let body = ndjson(&[
+        json!({"message": {"role": "assistant", "content": "", "thinking": "Need the file."}, "done": false}),
+        json!({"message": {"role": "assistant", "content": "Reading it now."}, "done": false}),
+        json!({"message": {"role": "assistant", "content": "", "tool_calls": [
+            {"function": {"name": "read", "arguments": {"path": "Cargo.toml"}}}
+        ]}, "done": false}),
+        json!({"message": {"role": "assistant", "content": ""}, "done": true, "done_reason": "stop",
+            "prompt_eval_count": 42, "eval_count": 7}),
+    ]);
+    let base_url = spawn_ndjson_server(body);
+
+    let message = stream(sample_model(base_url), sample_context(), None)
+        .expect("stream starts")
+        .result()
+        .await
+        .expect("final message");
+
+    assert_eq!(message.stop_reason, StopReason::ToolUse);
+    assert_eq!(message.usage.input, 42);
+    assert_eq!(message.usage.output, 7);
+    assert_eq!(message.usage.total_tokens, 49);
+    assert!(matches!(
+        &message.content[0],
+        AssistantContentBlock::Thinking { thinking, .. } if thinking == "Need the file."
+    ));
+    assert!(matches!(
+        &message.content[1],
+        AssistantContentBlock::Text { text, .. } if text == "Reading it now."
+    ));
+    match &message.content[2] {
+        AssistantContentBlock::ToolCall {
+            id,
+            name,
+            arguments,
+            ..
+        } => {
+            assert!(!id.is_empty());
+            assert_eq!(name, "read");
+            assert_eq!(arguments, &json!({"path": "Cargo.toml"}));
+        }
+        other => panic!("expected tool call, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn ollama_provider_extracts_tool_calls_written_as_text() {
+    let body = ndjson(&[
+        json!({"message": {"role": "assistant", "content": "<tool_call>\n{\"name\": \"read\", "}, "done": false}),
+        json!({"message": {"role": "assistant", "content": "\"arguments\": {\"path\": \"src/lib.rs\"}}\n</tool_call>"}, "done": false}),
+        json!({"message": {"role": "assistant", "content": ""}, "done": true, "done_reason": "stop"}),
+    ]);
+    let base_url = spawn_ndjson_server(body);
+
+    let message = stream(sample_model(base_url), sample_context(), None)
+        .expect("stream starts")
+        .result()
+        .await
+        .expect("final message");
+
+    assert_eq!(message.stop_reason, StopReason::ToolUse);
+    assert_eq!(message.content.len(), 1);
+    assert!(matches!(
+        &message.content[0],
+        AssistantContentBlock::ToolCall { name, arguments, .. }
+            if name == "read" && arguments == &json!({"path": "src/lib.rs"})
+    ));
+}
+
+#[tokio::test]
+async fn ollama_provider_keeps_json_answers_for_unknown_tools_as_text() {
+    let body = ndjson(&[
+        json!({"message": {"role": "assistant", "content": "{\"name\": \"pixy\"}"}, "done": true, "done_reason": "stop"}),
+    ]);
+    let base_url = spawn_ndjson_server(body);
+
+    let message = stream(sample_model(base_url), sample_context(), None)
+        .expect("stream starts")
+        .result()
+        .await
+        .expect("final message");
+
+    assert_eq!(message.stop_reason, StopReason::Stop);
+    assert!(matches!(
+        &message.content[0],
+        AssistantContentBlock::Text { text, .. } if text == "{\"name\": \"pixy\"}"
+    ));
+}
+
+#[tokio::test]
+async fn list_ollama_models_reads_local_tags() {
+    let body = json!({
+        "models": [
+            {"name": "qwen3:8b", "model": "qwen3:8b", "size": 5_200_000_000_u64},
+            {"name": "llama3.2:latest", "model": "llama3.2:latest"}
+        ]
+    })
+    .to_string();
+    let base_url = spawn_ndjson_server(body);
+
+    let models = list_ollama_models(&format!("{base_url}/v1"))
+        .await
+        .expect("model list");
+
+    let ids = models
+        .iter()
+        .map(|model| model.id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec!["qwen3:8b", "llama3.2:latest"]);
+    assert!(models
+        .iter()
+        .all(|model| model.api == "ollama" && model.base_url == base_url));
+}
@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use pixy_ai::{
+    calculate_cost, complete, lookup_model_pricing, model_pricing, register_api_provider,
+    AssistantMessage, AssistantMessageEvent, ClosureApiProvider, Context, Cost, DoneReason,
+    Message, Model, StopReason, Usage, UserContent,
+};
+
+fn zero_cost() -> Cost {
+    Cost {
+        input: 0.0,
+        output: 0.0,
+        cache_read: 0.0,
+        cache_write: 0.0,
+        total: 0.0,
+    }
+}
+
+fn sample_model(id: &str, cost: Cost) -> Model {
+    Model {
+        id: id.to_string(),
+        name: id.to_string(),
+        api: "openai-responses".to_string(),
+        provider: "openai".to_string(),
+        base_url: "https://api.openai.com/v1".to_string(),
+        reasoning: false,
+        reasoning_effort: None,
+        input: vec!["text".to_string()],
+        cost,
+        context_window: 128_000,
+        max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
+    }
+}
+
+#[test]
+fn lookup_model_pricing_prefers_longest_prefix_and_ignores_provider() {
+    let mini = lookup_model_pricing("openai/gpt-4o-mini-2024-07-18").expect("gpt-4o-mini price");
+    assert_eq!(mini.input, 0.15);
+    assert_eq!(mini.output, 0.6);
+
+    let sonnet = lookup_model_pricing("claude-sonnet-4-20250514").expect("sonnet price");
+    assert_eq!(sonnet.cache_write, 3.75);
+
+    assert!(lookup_model_pricing("my-local-llama").is_none());
+}
+
+#[test]
+fn model_pricing_prefers_configured_cost() {
+    let configured = Cost {
+        input: 1.0,
+        output: 2.0,
+        ..zero_cost()
+    };
+    assert_eq!(
+        model_pricing(&sample_model("gpt-4o", configured.clone())),
+        Some(configured)
+    );
+    assert_eq!(
+        model_pricing(&sample_model("gpt-4o", zero_cost())).map(|cost| cost.input),
+        Some(2.5)
+    );
+}
+
+#[test]
+fn calculate_cost_scales_tokens_per_million() {
+    let pricing = lookup_model_pricing("gpt-4o").expect("gpt-4o price");
+    let usage = Usage {
+        input: 1_000_000,
+        output: 200_000,
+        cache_read: 400_000,
+        cache_write: 0,
+        reasoning: 0,
+        total_tokens: 1_600_000,
+        cost: zero_cost(),
+    };
+
+    let cost = calculate_cost(&pricing, &usage);
+
+    assert!((cost.input - 2.5).abs() < 1e-9);
+    assert!((cost.output - 2.0).abs() < 1e-9);
+    assert!((cost.cache_read - 0.5).abs() < 1e-9);
+    assert!((cost.total - 5.0).abs() < 1e-9);
+}
+
+#[test]
+fn lookup_model_pricing_covers_newer_model_families() {
+    let opus = lookup_model_pricing("claude-opus-4-5-20251101").expect("opus 4.5 price");
+    assert_eq!(opus.input, 5.0);
+    let opus_4 = lookup_model_pricing("claude-opus-4-1").expect("opus 4.1 price");
+    assert_eq!(opus_4.input, 15.0);
+    let o3_mini = lookup_model_pricing("o3-mini").expect("o3-mini price");
+    assert_eq!(o3_mini.output, 4.4);
+    assert!(lookup_model_pricing("deepseek-chat").is_some());
+}
+
+fn usage_without_cost(input: u64, output: u64) -> Usage {
+    Usage {
+        input,
+        output,
+        cache_read: 0,
+        cache_write: 0,
+        reasoning: 0,
+        total_tokens: input + output,
+        cost: zero_cost(),
+    }
+}
+
+#[tokio::test]
+async fn complete_prices_messages_the_provider_left_unpriced() {
+    register_api_provider(
+        Arc::new(ClosureApiProvider {
+            api: "pricing-test-api".to_string(),
+            stream: Arc::new(|model, _, _, stream| {
+                Box::pin(async move {
+                    stream.push(AssistantMessageEvent::Done {
+                        reason: DoneReason::Stop,
+                        message: AssistantMessage {
+                            role: "assistant".to_string(),
+                            content: Vec::new(),
+                            api: model.api.clone(),
+                            provider: model.provider.clone(),
+                            model: model.id.clone(),
+                            usage: usage_without_cost(1_000_000, 100_000),
+                            stop_reason: StopReason::Stop,
+                            error_message: None,
+                            timestamp: 0,
+                            stats: None,
+                        },
+                    });
+                    Ok(())
+                })
+            }),
+            stream_simple: Arc::new(|_, _, _, _| Box::pin(async { Ok(()) })),
+        }),
+        None,
+    );
+    let mut model = sample_model("gpt-4o", zero_cost());
+    model.api = "pricing-test-api".to_string();
+    let context = Context {
+        system_prompt: None,
+        messages: vec![Message::User {
+            content: UserContent::Text("hi".to_string()),
+            timestamp: 0,
+        }],
+        tools: None,
+        system_prompt_cache: None,
+        branch: None,
+    };
+
+    let message = complete(model, context, None).await.expect("complete");
+
+    assert!((message.usage.cost.input - 2.5).abs() < 1e-9);
+    assert!((message.usage.cost.output - 1.0).abs() < 1e-9);
+    assert!((message.usage.cost.total - 3.5).abs() < 1e-9);
+}
@@ -8,7 +8,8 @@ use std::thread;
 use std::time::Duration;
 
 use pixy_ai::{
-    stream, AssistantContentBlock, AssistantMessageEvent, Context, Cost, Message, Model,
+    complete_structured, stream, AssistantContentBlock, AssistantMessageEvent, Context, Cost,
+    Message, Model, OpenAICompletionsCompat, PiAiError, PiAiErrorCode, ResponseSchema,
     StreamOptions, Tool, UserContent,
 };
 use serde::Deserialize;
@@ -67,6 +68,9 @@ fn sample_model(api: &str, base_url: String) -> Model {
         },
         context_window: 128_000,
         max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
     }
 }
 
@@ -88,6 +92,8 @@ fn sample_context() -> Context {
                 "required": ["path"]
             }),
         }]),
+        system_prompt_cache: None,
+        branch: None,
     }
 }
 
@@ -136,7 +142,7 @@ fn spawn_sse_server(body: String) -> String {
 
             let response = format!(
                 "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-                body.as_bytes().len(),
+                body.len(),
                 body
             );
             socket
@@ -302,9 +308,30 @@ fn openai_fixture_matches_expected_contract() {
         Some(StreamOptions {
             api_key: Some("test-key".to_string()),
             temperature: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            extra_sampling: Default::default(),
             max_tokens: None,
             headers: None,
             transport_retry_count: None,
+            api_version: None,
+            response_format: None,
+            stop_predicate: None,
+            middleware: Vec::new(),
+            guards: Vec::new(),
+            response_cache: None,
+            fallback_models: Vec::new(),
+            provider_preferences: Default::default(),
+            stop_sequences: Vec::new(),
+            retry_policy: None,
+            thinking_budget: None,
+            rate_limit_policy: None,
+            stream_transport: None,
+            image_generation: false,
+            audio_voice: None,
         }),
     )
     .expect("stream should start");
@@ -366,9 +393,30 @@ fn openai_completions_reasoning_content_maps_to_thinking_blocks() {
         Some(StreamOptions {
             api_key: Some("test-key".to_string()),
             temperature: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            extra_sampling: Default::default(),
             max_tokens: None,
             headers: None,
             transport_retry_count: None,
+            api_version: None,
+            response_format: None,
+            stop_predicate: None,
+            middleware: Vec::new(),
+            guards: Vec::new(),
+            response_cache: None,
+            fallback_models: Vec::new(),
+            provider_preferences: Default::default(),
+            stop_sequences: Vec::new(),
+            retry_policy: None,
+            thinking_budget: None,
+            rate_limit_policy: None,
+            stream_transport: None,
+            image_generation: false,
+            audio_voice: None,
         }),
     )
     .expect("stream should start");
@@ -397,6 +445,63 @@ fn openai_completions_reasoning_content_maps_to_thinking_blocks() {
     );
 }
 
+#[test]
+fn openai_completions_audio_output_becomes_wav_block_with_transcript_text() {
+    use base64::prelude::{Engine as _, BASE64_STANDARD};
+
+    let chunks = vec![
+        json!({
+            "choices": [{
+                "index": 0,
+                "delta": { "audio": { "id": "audio_1", "transcript": "Hello ", "data": BASE64_STANDARD.encode([1u8, 0]) } },
+                "finish_reason": null
+            }]
+        }),
+        json!({
+            "choices": [{
+                "index": 0,
+                "delta": { "audio": { "transcript": "there", "data": BASE64_STANDARD.encode([2u8, 0]) } },
+                "finish_reason": "stop"
+            }]
+        }),
+    ];
+    let base_url = spawn_sse_server(sse_body(&chunks, true));
+    let model = sample_model("openai-completions", base_url);
+    let event_stream = stream(
+        model,
+        sample_context(),
+        Some(StreamOptions {
+            api_key: Some("test-key".to_string()),
+            audio_voice: Some("alloy".to_string()),
+            ..StreamOptions::default()
+        }),
+    )
+    .expect("stream should start");
+
+    let runtime = tokio::runtime::Runtime::new().expect("create runtime");
+    let message = runtime.block_on(async {
+        while event_stream.next().await.is_some() {}
+        event_stream
+            .result()
+            .await
+            .expect("stream should produce final message")
+    });
+
+    assert_eq!(collect_text(&message.content), "Hello there");
+    let audio = message
+        .content
+        .iter()
+        .find_map(|block| match block {
+            AssistantContentBlock::Audio { data, mime_type } => Some((data, mime_type)),
+            _ => None,
+        })
+        .expect("audio block");
+    assert_eq!(audio.1, "audio/wav");
+    let wav = BASE64_STANDARD.decode(audio.0).expect("valid base64");
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(&wav[44..], &[1, 0, 2, 0]);
+}
+
 #[test]
 fn openai_completions_reasoning_snapshot_chunks_do_not_duplicate_thinking() {
     let chunks = vec![
@@ -438,9 +543,30 @@ fn openai_completions_reasoning_snapshot_chunks_do_not_duplicate_thinking() {
         Some(StreamOptions {
             api_key: Some("test-key".to_string()),
             temperature: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            extra_sampling: Default::default(),
             max_tokens: None,
             headers: None,
             transport_retry_count: None,
+            api_version: None,
+            response_format: None,
+            stop_predicate: None,
+            middleware: Vec::new(),
+            guards: Vec::new(),
+            response_cache: None,
+            fallback_models: Vec::new(),
+            provider_preferences: Default::default(),
+            stop_sequences: Vec::new(),
+            retry_policy: None,
+            thinking_budget: None,
+            rate_limit_policy: None,
+            stream_transport: None,
+            image_generation: false,
+            audio_voice: None,
         }),
     )
     .expect("stream should start");
@@ -487,9 +613,30 @@ fn anthropic_fixture_matches_expected_contract() {
         Some(StreamOptions {
             api_key: Some("test-key".to_string()),
             temperature: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            extra_sampling: Default::default(),
             max_tokens: None,
             headers: None,
             transport_retry_count: None,
+            api_version: None,
+            response_format: None,
+            stop_predicate: None,
+            middleware: Vec::new(),
+            guards: Vec::new(),
+            response_cache: None,
+            fallback_models: Vec::new(),
+            provider_preferences: Default::default(),
+            stop_sequences: Vec::new(),
+            retry_policy: None,
+            thinking_budget: None,
+            rate_limit_policy: None,
+            stream_transport: None,
+            image_generation: false,
+            audio_voice: None,
         }),
     )
     .expect("stream should start");
@@ -577,9 +724,30 @@ fn anthropic_stream_tool_use_accepts_arguments_alias_field() {
         Some(StreamOptions {
             api_key: Some("test-key".to_string()),
             temperature: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            extra_sampling: Default::default(),
             max_tokens: None,
             headers: None,
             transport_retry_count: None,
+            api_version: None,
+            response_format: None,
+            stop_predicate: None,
+            middleware: Vec::new(),
+            guards: Vec::new(),
+            response_cache: None,
+            fallback_models: Vec::new(),
+            provider_preferences: Default::default(),
+            stop_sequences: Vec::new(),
+            retry_policy: None,
+            thinking_budget: None,
+            rate_limit_policy: None,
+            stream_transport: None,
+            image_generation: false,
+            audio_voice: None,
         }),
     )
     .expect("stream should start");
@@ -703,9 +871,30 @@ fn openai_responses_stream_events_are_parsed_to_text_and_tool_call() {
         Some(StreamOptions {
             api_key: Some("test-key".to_string()),
             temperature: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            extra_sampling: Default::default(),
             max_tokens: None,
             headers: None,
             transport_retry_count: None,
+            api_version: None,
+            response_format: None,
+            stop_predicate: None,
+            middleware: Vec::new(),
+            guards: Vec::new(),
+            response_cache: None,
+            fallback_models: Vec::new(),
+            provider_preferences: Default::default(),
+            stop_sequences: Vec::new(),
+            retry_policy: None,
+            thinking_budget: None,
+            rate_limit_policy: None,
+            stream_transport: None,
+            image_generation: false,
+            audio_voice: None,
         }),
     )
     .expect("stream should start");
@@ -744,6 +933,111 @@ fn openai_responses_stream_events_are_parsed_to_text_and_tool_call() {
     assert_eq!(message.usage.total_tokens, 120);
 }
 
+#[test]
+fn openai_responses_reasoning_items_map_to_thinking_and_usage_is_priced() {
+    let reasoning_item = json!({
+        "type": "reasoning",
+        "id": "rs_1",
+        "summary": [
+            { "type": "summary_text", "text": "Look at the file." },
+            { "type": "summary_text", "text": "Then answer." },
+        ],
+        "encrypted_content": "opaque",
+    });
+    let chunks = vec![
+        json!({
+            "type": "response.output_item.added",
+            "item": { "type": "reasoning", "id": "rs_1", "summary": [] },
+        }),
+        json!({
+            "type": "response.reasoning_summary_part.added",
+            "item_id": "rs_1",
+            "summary_index": 0,
+        }),
+        json!({
+            "type": "response.reasoning_summary_text.delta",
+            "item_id": "rs_1",
+            "delta": "Look at the file.",
+        }),
+        json!({
+            "type": "response.reasoning_summary_part.added",
+            "item_id": "rs_1",
+            "summary_index": 1,
+        }),
+        json!({
+            "type": "response.reasoning_summary_text.delta",
+            "item_id": "rs_1",
+            "delta": "Then answer.",
+        }),
+        json!({ "type": "response.output_item.done", "item": reasoning_item }),
+        json!({
+            "type": "response.output_item.added",
+            "item": { "type": "message", "id": "msg_1", "content": [] },
+        }),
+        json!({
+            "type": "response.output_text.delta",
+            "item_id": "msg_1",
+            "delta": "Done.",
+        }),
+        json!({
+            "type": "response.output_item.done",
+            "item": { "type": "message", "id": "msg_1", "content": [] },
+        }),
+        json!({
+            "type": "response.completed",
+            "response": {
+                "status": "completed",
+                "usage": {
+                    "input_tokens": 1_000_000,
+                    "output_tokens": 500_000,
+                    "total_tokens": 1_500_000,
+                },
+            },
+        }),
+    ];
+    let base_url = spawn_sse_server(sse_body(&chunks, false));
+    let mut model = sample_model("openai-responses", base_url);
+    model.cost.input = 2.0;
+    model.cost.output = 8.0;
+    let event_stream = stream(
+        model,
+        sample_context(),
+        Some(StreamOptions {
+            api_key: Some("test-key".to_string()),
+            ..StreamOptions::default()
+        }),
+    )
+    .expect("stream should start");
+
+    let runtime = tokio::runtime::Runtime::new().expect("create runtime");
+    let message = runtime
+        .block_on(event_stream.result())
+        .expect("stream should produce final message");
+
+    assert_eq!(
+        collect_thinking(&message.content),
+        "Look at the file.\n\nThen answer."
+    );
+    assert_eq!(collect_text(&message.content), "Done.");
+    let signature = message
+        .content
+        .iter()
+        .find_map(|block| match block {
+            AssistantContentBlock::Thinking {
+                thinking_signature, ..
+            } => thinking_signature.clone(),
+            _ => None,
+        })
+        .expect("reasoning item should be kept for replay");
+    assert_eq!(
+        serde_json::from_str::<Value>(&signature).unwrap(),
+        reasoning_item
+    );
+    assert_eq!(message.usage.cost.input, 2.0);
+    assert_eq!(message.usage.cost.output, 4.0);
+    assert_eq!(message.usage.cost.total, 6.0);
+}
+
 #[test]
 fn openai_responses_function_call_accepts_object_arguments() {
     let chunks = vec![
@@ -795,9 +1089,30 @@ fn openai_responses_function_call_accepts_object_arguments() {
         Some(StreamOptions {
             api_key: Some("test-key".to_string()),
             temperature: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            extra_sampling: Default::default(),
             max_tokens: None,
             headers: None,
             transport_retry_count: None,
+            api_version: None,
+            response_format: None,
+            stop_predicate: None,
+            middleware: Vec::new(),
+            guards: Vec::new(),
+            response_cache: None,
+            fallback_models: Vec::new(),
+            provider_preferences: Default::default(),
+            stop_sequences: Vec::new(),
+            retry_policy: None,
+            thinking_budget: None,
+            rate_limit_policy: None,
+            stream_transport: None,
+            image_generation: false,
+            audio_voice: None,
         }),
     )
     .expect("stream should start");
@@ -888,9 +1203,30 @@ fn openai_responses_tool_arguments_resolve_when_arg_events_only_have_item_id() {
         Some(StreamOptions {
             api_key: Some("test-key".to_string()),
             temperature: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            extra_sampling: Default::default(),
             max_tokens: None,
             headers: None,
             transport_retry_count: None,
+            api_version: None,
+            response_format: None,
+            stop_predicate: None,
+            middleware: Vec::new(),
+            guards: Vec::new(),
+            response_cache: None,
+            fallback_models: Vec::new(),
+            provider_preferences: Default::default(),
+            stop_sequences: Vec::new(),
+            retry_policy: None,
+            thinking_budget: None,
+            rate_limit_policy: None,
+            stream_transport: None,
+            image_generation: false,
+            audio_voice: None,
         }),
     )
     .expect("stream should start");
@@ -945,9 +1281,30 @@ fn openai_completions_tool_call_accepts_object_arguments() {
         Some(StreamOptions {
             api_key: Some("test-key".to_string()),
             temperature: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            extra_sampling: Default::default(),
             max_tokens: None,
             headers: None,
             transport_retry_count: None,
+            api_version: None,
+            response_format: None,
+            stop_predicate: None,
+            middleware: Vec::new(),
+            guards: Vec::new(),
+            response_cache: None,
+            fallback_models: Vec::new(),
+            provider_preferences: Default::default(),
+            stop_sequences: Vec::new(),
+            retry_policy: None,
+            thinking_budget: None,
+            rate_limit_policy: None,
+            stream_transport: None,
+            image_generation: false,
+            audio_voice: None,
         }),
     )
     .expect("stream should start");
@@ -1021,9 +1378,30 @@ fn openai_responses_404_falls_back_to_completions_and_caches_base_url() {
             Some(StreamOptions {
                 api_key: Some("test-key".to_string()),
                 temperature: None,
+                top_p: None,
+                top_k: None,
+                seed: None,
+                frequency_penalty: None,
+                presence_penalty: None,
+                extra_sampling: Default::default(),
                 max_tokens: None,
                 headers: None,
                 transport_retry_count: None,
+                api_version: None,
+                response_format: None,
+                stop_predicate: None,
+                middleware: Vec::new(),
+                guards: Vec::new(),
+                response_cache: None,
+                fallback_models: Vec::new(),
+                provider_preferences: Default::default(),
+                stop_sequences: Vec::new(),
+                retry_policy: None,
+                thinking_budget: None,
+                rate_limit_policy: None,
+                stream_transport: None,
+                image_generation: false,
+                audio_voice: None,
             }),
         )
         .expect("stream should start");
@@ -1038,3 +1416,374 @@ fn openai_responses_404_falls_back_to_completions_and_caches_base_url() {
     assert_eq!(responses_hits.load(Ordering::SeqCst), 1);
     assert_eq!(completions_hits.load(Ordering::SeqCst), 2);
 }
+
+#[test]
+fn azure_openai_routes_to_deployment_with_api_version_and_api_key_header() {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind local test server");
+    let address = listener.local_addr().expect("server local addr");
+    let body = sse_body(
+        &[json!({
+            "choices": [{
+                "delta": { "content": "hello from azure" },
+                "finish_reason": "stop"
+            }]
+        })],
+        true,
+    );
+    let server = thread::spawn(move || {
+        let (mut socket, _) = listener.accept().expect("accept request");
+        socket
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .expect("set read timeout");
+        let mut buffer = [0_u8; 16384];
+        let read_len = socket.read(&mut buffer).unwrap_or(0);
+        let request = String::from_utf8_lossy(&buffer[..read_len]).to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        socket
+            .write_all(response.as_bytes())
+            .expect("write response");
+        let _ = socket.flush();
+        request
+    });
+
+    let mut model = sample_model("azure-openai", format!("http://{address}/"));
+    model.deployment = Some("prod-gpt4o".to_string());
+    model.api_version = Some("2024-06-01".to_string());
+    let event_stream = stream(
+        model,
+        sample_context(),
+        Some(StreamOptions {
+            api_key: Some("azure-key".to_string()),
+            temperature: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            extra_sampling: Default::default(),
+            max_tokens: None,
+            headers: None,
+            transport_retry_count: None,
+            api_version: Some("2025-01-01-preview".to_string()),
+            response_format: None,
+            stop_predicate: None,
+            middleware: Vec::new(),
+            guards: Vec::new(),
+            response_cache: None,
+            fallback_models: Vec::new(),
+            provider_preferences: Default::default(),
+            stop_sequences: Vec::new(),
+            retry_policy: None,
+            thinking_budget: None,
+            rate_limit_policy: None,
+            stream_transport: None,
+            image_generation: false,
+            audio_voice: None,
+        }),
+    )
+    .expect("stream should start");
+
+    let runtime = tokio::runtime::Runtime::new().expect("create runtime");
+    let message = runtime
+        .block_on(event_stream.result())
+        .expect("stream should produce final message");
+    let request = server.join().expect("server thread");
+
+    assert_eq!(collect_text(&message.content), "hello from azure");
+    let request_line = request.lines().next().unwrap_or_default();
+    assert_eq!(
+        request_line,
+        "POST /openai/deployments/prod-gpt4o/chat/completions?api-version=2025-01-01-preview HTTP/1.1"
+    );
+    let lowered = request.to_ascii_lowercase();
+    assert!(lowered.contains("api-key: azure-key"));
+    assert!(!lowered.contains("authorization:"));
+}
+
+#[test]
+fn openrouter_sends_routing_preferences_and_reports_the_serving_model() {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind local test server");
+    let address = listener.local_addr().expect("server local addr");
+    let body = sse_body(
+        &[
+            json!({
+                "model": "anthropic/claude-sonnet-4",
+                "provider": "Anthropic",
+                "choices": [{
+                    "delta": { "content": "routed" },
+                    "finish_reason": null
+                }]
+            }),
+            json!({
+                "model": "anthropic/claude-sonnet-4",
+                "provider": "Anthropic",
+                "choices": [{ "delta": {}, "finish_reason": "stop" }],
+                "usage": { "prompt_tokens": 12, "completion_tokens": 3, "cost": 0.0042 }
+            }),
+        ],
+        true,
+    );
+    let server = thread::spawn(move || {
+        let (mut socket, _) = listener.accept().expect("accept request");
+        socket
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .expect("set read timeout");
+        let mut buffer = [0_u8; 16384];
+        let read_len = socket.read(&mut buffer).unwrap_or(0);
+        let request = String::from_utf8_lossy(&buffer[..read_len]).to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        socket
+            .write_all(response.as_bytes())
+            .expect("write response");
+        let _ = socket.flush();
+        request
+    });
+
+    let mut model = sample_model("openrouter", format!("http://{address}/api/v1"));
+    model.id = "openai/gpt-5".to_string();
+    model.provider = "openrouter".to_string();
+    let mut provider_preferences = serde_json::Map::new();
+    provider_preferences.insert("order".to_string(), json!(["anthropic"]));
+    let event_stream = stream(
+        model,
+        sample_context(),
+        Some(StreamOptions {
+            api_key: Some("router-key".to_string()),
+            fallback_models: vec!["anthropic/claude-sonnet-4".to_string()],
+            provider_preferences,
+            ..StreamOptions::default()
+        }),
+    )
+    .expect("stream should start");
+
+    let runtime = tokio::runtime::Runtime::new().expect("create runtime");
+    let message = runtime
+        .block_on(event_stream.result())
+        .expect("stream should produce final message");
+    let request = server.join().expect("server thread");
+
+    assert_eq!(collect_text(&message.content), "routed");
+    assert_eq!(message.model, "anthropic/claude-sonnet-4");
+    assert_eq!(message.provider, "openrouter");
+    assert_eq!(message.usage.cost.total, 0.0042);
+    assert_eq!(
+        message.stats.expect("stats").provider,
+        "openrouter/Anthropic"
+    );
+    assert!(request.starts_with("POST /api/v1/chat/completions HTTP/1.1"));
+    let body: Value = serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap_or_default())
+        .expect("request body is JSON");
+    assert_eq!(body["model"], "openai/gpt-5");
+    assert_eq!(body["models"], json!(["anthropic/claude-sonnet-4"]));
+    assert_eq!(body["provider"], json!({ "order": ["anthropic"] }));
+}
+
+#[test]
+fn openai_compatible_server_gets_compat_fields_and_no_key() {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind local test server");
+    let address = listener.local_addr().expect("server local addr");
+    let body = sse_body(
+        &[
+            json!({ "choices": [{ "delta": { "content": "local" }, "finish_reason": null }] }),
+            json!({ "choices": [{ "delta": {}, "finish_reason": "stop" }] }),
+            json!({
+                "choices": [],
+                "usage": { "prompt_tokens": 9, "completion_tokens": 1 }
+            }),
+        ],
+        true,
+    );
+    let server = thread::spawn(move || {
+        let (mut socket, _) = listener.accept().expect("accept request");
+        socket
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .expect("set read timeout");
+        let mut buffer = [0_u8; 16384];
+        let read_len = socket.read(&mut buffer).unwrap_or(0);
+        let request = String::from_utf8_lossy(&buffer[..read_len]).to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        socket
+            .write_all(response.as_bytes())
+            .expect("write response");
+        let _ = socket.flush();
+        request
+    });
+
+    let mut model = sample_model("openai-compatible", format!("http://{address}/v1"));
+    model.provider = "self-hosted-test".to_string();
+    model.compat = Some(OpenAICompletionsCompat {
+        stream_options: true,
+        tool_choice: true,
+        max_tokens_field: "max_completion_tokens".to_string(),
+        stop_field: "stop_sequences".to_string(),
+    });
+    let event_stream = stream(
+        model,
+        sample_context(),
+        Some(StreamOptions {
+            max_tokens: Some(256),
+            stop_sequences: vec!["</answer>".to_string()],
+            ..StreamOptions::default()
+        }),
+    )
+    .expect("stream should start");
+
+    let runtime = tokio::runtime::Runtime::new().expect("create runtime");
+    let message = runtime
+        .block_on(event_stream.result())
+        .expect("stream should produce final message");
+    let request = server.join().expect("server thread");
+
+    assert_eq!(collect_text(&message.content), "local");
+    assert_eq!((message.usage.input, message.usage.output), (9, 1));
+    assert!(request.starts_with("POST /v1/chat/completions HTTP/1.1"));
+    assert!(!request.to_ascii_lowercase().contains("authorization:"));
+    let body: Value = serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap_or_default())
+        .expect("request body is JSON");
+    assert_eq!(body["stream_options"], json!({ "include_usage": true }));
+    assert_eq!(body["tool_choice"], "auto");
+    assert_eq!(body["max_completion_tokens"], 256);
+    assert_eq!(body["stop_sequences"], json!(["</answer>"]));
+    assert!(body.get("max_tokens").is_none());
+    assert!(body.get("stop").is_none());
+}
+
+#[test]
+fn http_errors_carry_status_and_retry_after_details() {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind local test server");
+    let address = listener.local_addr().expect("server local addr");
+    thread::spawn(move || {
+        if let Ok((mut socket, _)) = listener.accept() {
+            socket
+                .set_read_timeout(Some(Duration::from_secs(2)))
+                .expect("set read timeout");
+            let mut buffer = [0_u8; 16384];
+            let _ = socket.read(&mut buffer);
+            let body = r#"{"error":{"message":"slow down"}}"#;
+            let response = format!(
+                "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 7\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket
+                .write_all(response.as_bytes())
+                .expect("write 429 response");
+            let _ = socket.flush();
+        }
+    });
+
+    let event_stream = stream(
+        sample_model("openai-completions", format!("http://{address}/v1")),
+        sample_context(),
+        Some(StreamOptions {
+            api_key: Some("test-key".to_string()),
+            transport_retry_count: Some(0),
+            ..StreamOptions::default()
+        }),
+    )
+    .expect("stream should start");
+
+    let runtime = tokio::runtime::Runtime::new().expect("create runtime");
+    let message = runtime
+        .block_on(event_stream.result())
+        .expect("stream should produce error message");
+    let error: PiAiError = serde_json::from_str(
+        message
+            .error_message
+            .as_deref()
+            .expect("error message should be present"),
+    )
+    .expect("error message should be structured");
+
+    assert_eq!(error.code, PiAiErrorCode::ProviderHttp);
+    assert!(error.message.starts_with("OpenAI HTTP 429"));
+    let details = error.details.expect("http error should carry details");
+    assert_eq!(details["status"], 429);
+    assert_eq!(details["retryAfterMs"], 7_000);
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Verdict {
+    approved: bool,
+    reason: String,
+}
+
+fn verdict_options() -> StreamOptions {
+    StreamOptions {
+        api_key: Some("test-key".to_string()),
+        response_format: Some(ResponseSchema {
+            name: "verdict".to_string(),
+            schema: json!({
+                "type": "object",
+                "properties": {
+                    "approved": { "type": "boolean" },
+                    "reason": { "type": "string" }
+                },
+                "required": ["approved", "reason"],
+                "additionalProperties": false
+            }),
+            strict: true,
+        }),
+        ..StreamOptions::default()
+    }
+}
+
+#[test]
+fn complete_structured_validates_and_deserializes_json_text() {
+    let chunks = vec![
+        json!({"choices": [{"delta": {"content": "```json\n{\"approved\": true, "}}]}),
+        json!({"choices": [{"delta": {"content": "\"reason\": \"tests pass\"}\n```"}, "finish_reason": "stop"}]}),
+    ];
+    let base_url = spawn_sse_server(sse_body(&chunks, true));
+    let model = sample_model("openai-completions", base_url);
+
+    let runtime = tokio::runtime::Runtime::new().expect("create runtime");
+    let verdict: Verdict = runtime
+        .block_on(complete_structured(
+            model,
+            sample_context(),
+            Some(verdict_options()),
+        ))
+        .expect("structured completion");
+
+    assert_eq!(
+        verdict,
+        Verdict {
+            approved: true,
+            reason: "tests pass".to_string(),
+        }
+    );
+}
+
+#[test]
+fn complete_structured_rejects_responses_that_violate_the_schema() {
+    let chunks = vec![json!({
+        "choices": [{"delta": {"content": "{\"approved\": \"yes\"}"}, "finish_reason": "stop"}]
+    })];
+    let base_url = spawn_sse_server(sse_body(&chunks, true));
+    let model = sample_model("openai-completions", base_url);
+
+    let runtime = tokio::runtime::Runtime::new().expect("create runtime");
+    let error = runtime
+        .block_on(complete_structured::<Verdict>(
+            model,
+            sample_context(),
+            Some(verdict_options()),
+        ))
+        .expect_err("schema violation should fail");
+
+    assert_eq!(error.code, PiAiErrorCode::ProviderProtocol);
+    assert!(error.message.contains("does not match schema 'verdict'"));
+}
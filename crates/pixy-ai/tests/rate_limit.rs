@@ -0,0 +1,195 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use pixy_ai::{
+    complete, rate_limit_metrics, register_api_provider, set_rate_limit, AssistantContentBlock,
+    AssistantMessage, AssistantMessageEvent, ClosureApiProvider, Context, Cost, DoneReason, Model,
+    RateLimit, RateLimitPolicy, StopReason, StreamOptions, Usage,
+};
+use tokio::sync::Semaphore;
+
+/// Each test registers its own API under its provider name, so parallel tests keep their own
+/// provider.
+fn sample_model(provider: &str) -> Model {
+    Model {
+        id: "test-model".to_string(),
+        name: "Test Model".to_string(),
+        api: provider.to_string(),
+        provider: provider.to_string(),
+        base_url: "http://localhost".to_string(),
+        reasoning: false,
+        reasoning_effort: None,
+        input: vec!["text".to_string()],
+        cost: Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+        context_window: 128_000,
+        max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
+    }
+}
+
+fn empty_context() -> Context {
+    Context {
+        system_prompt: None,
+        messages: Vec::new(),
+        tools: None,
+        system_prompt_cache: None,
+        branch: None,
+    }
+}
+
+fn done_message(model: &Model) -> AssistantMessage {
+    AssistantMessage {
+        role: "assistant".to_string(),
+        content: vec![AssistantContentBlock::Text {
+            text: "ok".to_string(),
+            text_signature: None,
+        }],
+        api: model.api.clone(),
+        provider: model.provider.clone(),
+        model: model.id.clone(),
+        usage: Usage {
+            input: 0,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            reasoning: 0,
+            total_tokens: 0,
+            cost: Cost {
+                input: 0.0,
+                output: 0.0,
+                cache_read: 0.0,
+                cache_write: 0.0,
+                total: 0.0,
+            },
+        },
+        stop_reason: StopReason::Stop,
+        error_message: None,
+        timestamp: 0,
+        stats: None,
+    }
+}
+
+/// Registers a provider that answers once `gate` hands out a permit.
+fn register_gated_provider(api: &str, gate: Arc<Semaphore>) {
+    let stream_gate = gate.clone();
+    register_api_provider(
+        Arc::new(ClosureApiProvider {
+            api: api.to_string(),
+            stream: Arc::new(move |model, _, _, stream| {
+                let gate = stream_gate.clone();
+                Box::pin(async move {
+                    gate.acquire().await.expect("gate open").forget();
+                    stream.push(AssistantMessageEvent::Done {
+                        reason: DoneReason::Stop,
+                        message: done_message(&model),
+                    });
+                    Ok(())
+                })
+            }),
+            stream_simple: Arc::new(move |model, _, _, stream| {
+                Box::pin(async move {
+                    stream.push(AssistantMessageEvent::Done {
+                        reason: DoneReason::Stop,
+                        message: done_message(&model),
+                    });
+                    Ok(())
+                })
+            }),
+        }),
+        Some("rate-limit-test".to_string()),
+    );
+}
+
+fn fail_fast() -> Option<StreamOptions> {
+    Some(StreamOptions {
+        rate_limit_policy: Some(RateLimitPolicy::Fail),
+        ..StreamOptions::default()
+    })
+}
+
+async fn wait_for_queue(key: &str, queued: usize) {
+    for _ in 0..200 {
+        if rate_limit_metrics()[key].queued == queued {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    panic!("queue for {key} never reached {queued}");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn concurrency_limit_queues_or_rejects_requests_over_the_limit() {
+    let gate = Arc::new(Semaphore::new(0));
+    register_gated_provider("concurrency-test", gate.clone());
+    set_rate_limit(
+        "concurrency-test",
+        RateLimit {
+            max_concurrent: Some(1),
+            ..RateLimit::default()
+        },
+    );
+    let model = sample_model("concurrency-test");
+
+    let first = tokio::spawn(complete(model.clone(), empty_context(), None));
+    let second = tokio::spawn(complete(model.clone(), empty_context(), None));
+    wait_for_queue("concurrency-test", 1).await;
+    assert_eq!(rate_limit_metrics()["concurrency-test"].in_flight, 1);
+
+    let rejected = complete(model.clone(), empty_context(), fail_fast())
+        .await
+        .expect("rejection is reported as an error message");
+    assert_eq!(rejected.stop_reason, StopReason::Error);
+    assert!(rejected
+        .error_message
+        .as_deref()
+        .is_some_and(|message| message.contains("concurrent request limit")));
+
+    gate.add_permits(2);
+    for handle in [first, second] {
+        let message = handle.await.expect("join").expect("complete");
+        assert_eq!(message.stop_reason, StopReason::Stop);
+    }
+    let metrics = rate_limit_metrics()["concurrency-test"];
+    assert_eq!((metrics.queued, metrics.in_flight), (0, 0));
+    assert_eq!(metrics.throttled, 1);
+    assert_eq!(metrics.rejected, 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn request_rate_limit_spaces_out_requests_beyond_the_burst() {
+    let gate = Arc::new(Semaphore::new(Semaphore::MAX_PERMITS));
+    register_gated_provider("rate-test", gate);
+    set_rate_limit(
+        "rate-test/test-model",
+        RateLimit {
+            requests_per_minute: Some(600.0),
+            burst: Some(1),
+            ..RateLimit::default()
+        },
+    );
+    let model = sample_model("rate-test");
+
+    complete(model.clone(), empty_context(), None)
+        .await
+        .expect("first request fits the burst");
+    let rejected = complete(model.clone(), empty_context(), fail_fast())
+        .await
+        .expect("rejection is reported as an error message");
+    assert_eq!(rejected.stop_reason, StopReason::Error);
+
+    let started = std::time::Instant::now();
+    let message = complete(model, empty_context(), None)
+        .await
+        .expect("waiting request completes");
+    assert_eq!(message.stop_reason, StopReason::Stop);
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    assert_eq!(rate_limit_metrics()["rate-test/test-model"].throttled, 1);
+}
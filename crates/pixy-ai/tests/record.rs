@@ -0,0 +1,183 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use pixy_ai::record::{self, Cassette, Interaction, RecordMode};
+use pixy_ai::{
+    complete, AssistantContentBlock, Context, Cost, Message, Model, StopReason, StreamOptions,
+    UserContent,
+};
+use tokio::sync::Mutex;
+
+// Record mode is process-wide, so the tests in this file take turns.
+static RECORD_LOCK: Mutex<()> = Mutex::const_new(());
+
+const COMPLETIONS_BODY: &str = concat!(
+    "data: {\"choices\":[{\"delta\":{\"content\":\"Hello \"}}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"content\":\"replay\"},\"finish_reason\":\"stop\"}]}\n\n",
+    "data: [DONE]\n\n",
+);
+
+fn sample_model(base_url: String) -> Model {
+    Model {
+        id: "test-model".to_string(),
+        name: "Test Model".to_string(),
+        api: "openai-completions".to_string(),
+        provider: "openai".to_string(),
+        base_url,
+        reasoning: false,
+        reasoning_effort: None,
+        input: vec!["text".to_string()],
+        cost: Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+        context_window: 128_000,
+        max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
+    }
+}
+
+fn sample_context() -> Context {
+    Context {
+        system_prompt: None,
+        messages: vec![Message::User {
+            content: UserContent::Text("Say hello".to_string()),
+            timestamp: 1_700_000_000_000,
+        }],
+        tools: None,
+        system_prompt_cache: None,
+        branch: None,
+    }
+}
+
+fn options() -> Option<StreamOptions> {
+    Some(StreamOptions {
+        api_key: Some("test-key".to_string()),
+        ..StreamOptions::default()
+    })
+}
+
+fn spawn_sse_server(body: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind local test server");
+    let address = listener.local_addr().expect("server local addr");
+    thread::spawn(move || {
+        if let Ok((mut socket, _)) = listener.accept() {
+            socket
+                .set_read_timeout(Some(Duration::from_secs(2)))
+                .expect("set read timeout");
+            let mut buffer = [0_u8; 8192];
+            let _ = socket.read(&mut buffer);
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket
+                .write_all(response.as_bytes())
+                .expect("write response");
+            let _ = socket.flush();
+        }
+    });
+    format!("http://{address}/v1")
+}
+
+fn cassette_path(name: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("pixy-record-{}", std::process::id()))
+        .join(name)
+}
+
+fn text_of(content: &[AssistantContentBlock]) -> String {
+    content
+        .iter()
+        .filter_map(|block| match block {
+            AssistantContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn recorded_stream_replays_without_network() {
+    let _guard = RECORD_LOCK.lock().await;
+    let path = cassette_path("hello.json");
+
+    record::start_recording(&path);
+    assert_eq!(record::record_mode(), RecordMode::Record);
+    let live = complete(
+        sample_model(spawn_sse_server(COMPLETIONS_BODY)),
+        sample_context(),
+        options(),
+    )
+    .await
+    .expect("live completion");
+    record::stop();
+
+    let cassette = Cassette::load(&path).expect("load cassette");
+    assert_eq!(cassette.interactions.len(), 1);
+    assert_eq!(cassette.interactions[0].api, "openai-completions");
+    assert_eq!(cassette.interactions[0].body, COMPLETIONS_BODY);
+
+    record::start_replay(&path).expect("start replay");
+    // Nothing listens on the discard port, so a live request would fail.
+    let replayed = complete(
+        sample_model("http://127.0.0.1:9/v1".to_string()),
+        sample_context(),
+        options(),
+    )
+    .await
+    .expect("replayed completion");
+    let exhausted = complete(
+        sample_model("http://127.0.0.1:9/v1".to_string()),
+        sample_context(),
+        options(),
+    )
+    .await
+    .expect("exhausted completion");
+    record::stop();
+
+    assert_eq!(text_of(&live.content), "Hello replay");
+    assert_eq!(replayed.content, live.content);
+    assert_eq!(replayed.stop_reason, StopReason::Stop);
+    assert_eq!(exhausted.stop_reason, StopReason::Error);
+    assert!(exhausted
+        .error_message
+        .as_deref()
+        .is_some_and(|message| message.contains("Cassette exhausted")));
+}
+
+#[tokio::test]
+async fn replay_rejects_a_response_recorded_for_another_api() {
+    let _guard = RECORD_LOCK.lock().await;
+    record::replay_cassette(Cassette {
+        interactions: vec![Interaction {
+            api: "anthropic-messages".to_string(),
+            provider: "anthropic".to_string(),
+            model: "claude".to_string(),
+            body: String::new(),
+        }],
+    });
+
+    let message = complete(
+        sample_model("http://127.0.0.1:9/v1".to_string()),
+        sample_context(),
+        options(),
+    )
+    .await
+    .expect("completion");
+    record::stop();
+
+    assert_eq!(message.stop_reason, StopReason::Error);
+    assert!(message
+        .error_message
+        .as_deref()
+        .is_some_and(|message| message.contains("Cassette mismatch")));
+}
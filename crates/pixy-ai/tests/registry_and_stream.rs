@@ -1,11 +1,18 @@
+#![allow(clippy::await_holding_lock)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::{Mutex, OnceLock};
 
 use pixy_ai::{
-    clear_api_providers, complete, complete_simple, register_api_provider, stream, stream_simple,
-    unregister_api_providers, AssistantContentBlock, AssistantMessage, AssistantMessageEvent,
-    AssistantMessageEventStream, ClosureApiProvider, Context, Cost, DoneReason, Message, Model,
-    SimpleStreamOptions, StopReason, StreamOptions, Usage, UserContent,
+    clear_api_providers, clear_stream_middleware, complete, complete_simple, llm_metrics,
+    register_api_provider, register_stream_middleware, stream, stream_simple,
+    unregister_api_providers, unregister_stream_middleware, AssistantContentBlock,
+    AssistantMessage, AssistantMessageEvent, AssistantMessageEventStream, BlockedTermsGuard,
+    ClosureApiProvider, ContentGuard, Context, Cost, DoneReason, GuardFuture, MemoryResponseCache,
+    Message, MiddlewareFuture, Model, PiAiError, PiAiErrorCode, ReliableProvider, ResponseCacheRef,
+    SimpleStreamOptions, StopPredicate, StopReason, StreamMiddleware, StreamOptions, StreamRequest,
+    Usage, UserContent,
 };
 
 fn sample_usage() -> Usage {
@@ -14,6 +21,7 @@ fn sample_usage() -> Usage {
         output: 2,
         cache_read: 0,
         cache_write: 0,
+        reasoning: 0,
         total_tokens: 12,
         cost: Cost {
             input: 0.01,
@@ -39,6 +47,7 @@ fn sample_assistant(stop_reason: StopReason, text: &str) -> AssistantMessage {
         stop_reason,
         error_message: None,
         timestamp: 1_700_000_000_000,
+        stats: None,
     }
 }
 
@@ -61,6 +70,9 @@ fn sample_model(api: &str) -> Model {
         },
         context_window: 128_000,
         max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
     }
 }
 
@@ -72,6 +84,8 @@ fn sample_context() -> Context {
             timestamp: 1_700_000_000_000,
         }],
         tools: None,
+        system_prompt_cache: None,
+        branch: None,
     }
 }
 
@@ -269,9 +283,30 @@ async fn stream_forwarding_preserves_transport_retry_override() {
         Some(StreamOptions {
             api_key: None,
             temperature: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            extra_sampling: Default::default(),
             max_tokens: None,
             headers: None,
             transport_retry_count: Some(7),
+            api_version: None,
+            response_format: None,
+            stop_predicate: None,
+            middleware: Vec::new(),
+            guards: Vec::new(),
+            response_cache: None,
+            fallback_models: Vec::new(),
+            provider_preferences: Default::default(),
+            stop_sequences: Vec::new(),
+            retry_policy: None,
+            thinking_budget: None,
+            rate_limit_policy: None,
+            stream_transport: None,
+            image_generation: false,
+            audio_voice: None,
         }),
     )
     .expect("stream should resolve");
@@ -284,9 +319,30 @@ async fn stream_forwarding_preserves_transport_retry_override() {
             stream: StreamOptions {
                 api_key: None,
                 temperature: None,
+                top_p: None,
+                top_k: None,
+                seed: None,
+                frequency_penalty: None,
+                presence_penalty: None,
+                extra_sampling: Default::default(),
                 max_tokens: None,
                 headers: None,
                 transport_retry_count: Some(3),
+                api_version: None,
+                response_format: None,
+                stop_predicate: None,
+                middleware: Vec::new(),
+                guards: Vec::new(),
+                response_cache: None,
+                fallback_models: Vec::new(),
+                provider_preferences: Default::default(),
+                stop_sequences: Vec::new(),
+                retry_policy: None,
+                thinking_budget: None,
+                rate_limit_policy: None,
+                stream_transport: None,
+                image_generation: false,
+                audio_voice: None,
             },
             reasoning: None,
         }),
@@ -312,3 +368,635 @@ async fn stream_forwarding_preserves_transport_retry_override() {
         &[Some(3)]
     );
 }
+
+#[tokio::test]
+async fn stop_predicate_aborts_stream_with_partial_message() {
+    let _guard = registry_guard();
+    clear_api_providers();
+
+    let provider_finished = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let finished = provider_finished.clone();
+    register_api_provider(
+        Arc::new(ClosureApiProvider {
+            api: "test-api".to_string(),
+            stream: Arc::new(move |_, _, _, stream| {
+                let finished = finished.clone();
+                Box::pin(async move {
+                    let mut text = String::new();
+                    for delta in ["Working", "\n```end", " never sent"] {
+                        text.push_str(delta);
+                        stream.push(AssistantMessageEvent::TextDelta {
+                            content_index: 0,
+                            delta: delta.to_string(),
+                            partial: sample_assistant(StopReason::Stop, &text),
+                        });
+                        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                    }
+                    finished.store(true, std::sync::atomic::Ordering::SeqCst);
+                    emit_done(&stream, &text);
+                    Ok(())
+                })
+            }),
+            stream_simple: Arc::new(|_, _, _, stream| {
+                Box::pin(async move {
+                    emit_done(&stream, "unused");
+                    Ok(())
+                })
+            }),
+        }),
+        None,
+    );
+
+    let options = StreamOptions {
+        stop_predicate: Some(StopPredicate::on_marker("```end")),
+        ..StreamOptions::default()
+    };
+    let event_stream = stream(sample_model("test-api"), sample_context(), Some(options))
+        .expect("stream should start");
+
+    let mut deltas = Vec::new();
+    while let Some(event) = event_stream.next().await {
+        if let AssistantMessageEvent::TextDelta { delta, .. } = event {
+            deltas.push(delta);
+        }
+    }
+    let message = event_stream.result().await.expect("final message");
+
+    assert_eq!(deltas, vec!["Working", "\n```end"]);
+    assert_eq!(message.stop_reason, StopReason::Aborted);
+    assert!(matches!(
+        &message.content[0],
+        AssistantContentBlock::Text { text, .. } if text == "Working\n```end"
+    ));
+    tokio::time::sleep(std::time::Duration::from_millis(60)).await;
+    assert!(!provider_finished.load(std::sync::atomic::Ordering::SeqCst));
+
+    clear_api_providers();
+}
+
+#[tokio::test]
+async fn stop_predicate_that_never_fires_lets_the_stream_finish() {
+    let _guard = registry_guard();
+    clear_api_providers();
+
+    register_api_provider(
+        Arc::new(ClosureApiProvider {
+            api: "test-api".to_string(),
+            stream: Arc::new(|_, _, _, stream| {
+                Box::pin(async move {
+                    stream.push(AssistantMessageEvent::TextDelta {
+                        content_index: 0,
+                        delta: "all of it".to_string(),
+                        partial: sample_assistant(StopReason::Stop, "all of it"),
+                    });
+                    emit_done(&stream, "all of it");
+                    Ok(())
+                })
+            }),
+            stream_simple: Arc::new(|_, _, _, stream| {
+                Box::pin(async move {
+                    emit_done(&stream, "unused");
+                    Ok(())
+                })
+            }),
+        }),
+        None,
+    );
+
+    let options = StreamOptions {
+        stop_predicate: Some(StopPredicate::on_marker("```end")),
+        ..StreamOptions::default()
+    };
+    let event_stream = stream(sample_model("test-api"), sample_context(), Some(options))
+        .expect("stream should start");
+    let message = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        while event_stream.next().await.is_some() {}
+        event_stream.result().await
+    })
+    .await
+    .expect("stream ends")
+    .expect("final message");
+
+    assert_eq!(message.stop_reason, StopReason::Stop);
+    assert!(matches!(
+        &message.content[0],
+        AssistantContentBlock::Text { text, .. } if text == "all of it"
+    ));
+
+    clear_api_providers();
+}
+
+struct RedactingMiddleware {
+    completed: Mutex<Vec<String>>,
+}
+
+impl StreamMiddleware for RedactingMiddleware {
+    fn on_request<'a>(&'a self, request: &'a mut StreamRequest) -> MiddlewareFuture<'a> {
+        Box::pin(async move {
+            request
+                .options
+                .headers
+                .get_or_insert_with(Default::default)
+                .insert("x-trace".to_string(), "trace-1".to_string());
+            Ok(())
+        })
+    }
+
+    fn on_event(&self, event: &mut AssistantMessageEvent) {
+        if let AssistantMessageEvent::Done { message, .. } = event {
+            for block in &mut message.content {
+                if let AssistantContentBlock::Text { text, .. } = block {
+                    *text = text.replace("secret", "[redacted]");
+                }
+            }
+        }
+    }
+
+    fn on_complete(&self, message: &AssistantMessage) {
+        self.completed
+            .lock()
+            .expect("completed lock poisoned")
+            .push(format!("{:?}", message.stop_reason));
+    }
+}
+
+fn register_header_echo_provider() {
+    register_api_provider(
+        Arc::new(ClosureApiProvider {
+            api: "test-api".to_string(),
+            stream: Arc::new(|_, _, options, stream| {
+                let trace = options
+                    .and_then(|options| options.headers)
+                    .and_then(|headers| headers.get("x-trace").cloned())
+                    .unwrap_or_default();
+                Box::pin(async move {
+                    emit_done(&stream, &format!("{trace} secret"));
+                    Ok(())
+                })
+            }),
+            stream_simple: Arc::new(|_, _, options, stream| {
+                let trace = options
+                    .and_then(|options| options.stream.headers)
+                    .and_then(|headers| headers.get("x-trace").cloned())
+                    .unwrap_or_default();
+                Box::pin(async move {
+                    emit_done(&stream, &format!("{trace} secret"));
+                    Ok(())
+                })
+            }),
+        }),
+        None,
+    );
+}
+
+fn final_text(message: &AssistantMessage) -> String {
+    message
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            AssistantContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn per_call_middleware_rewrites_request_and_events() {
+    let _guard = registry_guard();
+    clear_api_providers();
+    clear_stream_middleware();
+    register_header_echo_provider();
+
+    let middleware = Arc::new(RedactingMiddleware {
+        completed: Mutex::new(Vec::new()),
+    });
+    let options = StreamOptions {
+        middleware: vec![middleware.clone()],
+        ..StreamOptions::default()
+    };
+    let message = complete(sample_model("test-api"), sample_context(), Some(options))
+        .await
+        .expect("complete should resolve");
+    let untouched = complete(sample_model("test-api"), sample_context(), None)
+        .await
+        .expect("complete should resolve");
+
+    assert_eq!(final_text(&message), "trace-1 [redacted]");
+    assert_eq!(final_text(&untouched), " secret");
+    assert_eq!(
+        middleware
+            .completed
+            .lock()
+            .expect("completed lock poisoned")
+            .as_slice(),
+        &["Stop".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn global_middleware_applies_to_stream_simple_until_unregistered() {
+    let _guard = registry_guard();
+    clear_api_providers();
+    clear_stream_middleware();
+    register_header_echo_provider();
+
+    register_stream_middleware(
+        Arc::new(RedactingMiddleware {
+            completed: Mutex::new(Vec::new()),
+        }),
+        Some("test-middleware".to_string()),
+    );
+    let message = complete_simple(sample_model("test-api"), sample_context(), None)
+        .await
+        .expect("complete_simple should resolve");
+    unregister_stream_middleware("test-middleware");
+    let after = complete_simple(sample_model("test-api"), sample_context(), None)
+        .await
+        .expect("complete_simple should resolve");
+
+    assert_eq!(final_text(&message), "trace-1 [redacted]");
+    assert_eq!(final_text(&after), " secret");
+}
+
+struct RejectingMiddleware;
+
+impl StreamMiddleware for RejectingMiddleware {
+    fn on_request<'a>(&'a self, _request: &'a mut StreamRequest) -> MiddlewareFuture<'a> {
+        Box::pin(async {
+            Err(PiAiError::new(
+                PiAiErrorCode::ProviderTransport,
+                "rate limit exceeded",
+            ))
+        })
+    }
+}
+
+#[tokio::test]
+async fn failing_request_middleware_skips_the_provider() {
+    let _guard = registry_guard();
+    clear_api_providers();
+    clear_stream_middleware();
+    register_header_echo_provider();
+
+    let options = StreamOptions {
+        middleware: vec![Arc::new(RejectingMiddleware)],
+        ..StreamOptions::default()
+    };
+    let message = complete(sample_model("test-api"), sample_context(), Some(options))
+        .await
+        .expect("complete should resolve");
+
+    assert_eq!(message.stop_reason, StopReason::Error);
+    assert!(message.content.is_empty());
+    assert!(message
+        .error_message
+        .as_deref()
+        .is_some_and(|error| error.contains("rate limit exceeded")));
+}
+
+struct SecretScrubber;
+
+impl ContentGuard for SecretScrubber {
+    fn check_context<'a>(&'a self, context: &'a mut Context) -> GuardFuture<'a> {
+        Box::pin(async move {
+            for message in &mut context.messages {
+                if let Message::User {
+                    content: UserContent::Text(text),
+                    ..
+                } = message
+                {
+                    *text = text.replace("hunter2", "[redacted]");
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn check_output(&self, text: &mut String) -> Result<(), PiAiError> {
+        *text = text.replace("secret", "[redacted]");
+        Ok(())
+    }
+}
+
+/// Streams `reply` in two deltas after echoing the prompt it was sent.
+fn register_delta_provider(reply: [&'static str; 2]) {
+    let stream_fn = move |_: Model, context: Context, stream: AssistantMessageEventStream| {
+        let prompt = match context.messages.first() {
+            Some(Message::User {
+                content: UserContent::Text(text),
+                ..
+            }) => text.clone(),
+            _ => String::new(),
+        };
+        let full = format!("{prompt}: {}{}", reply[0], reply[1]);
+        let partial = sample_assistant(StopReason::Stop, &full);
+        stream.push(AssistantMessageEvent::TextStart {
+            content_index: 0,
+            partial: partial.clone(),
+        });
+        for delta in [format!("{prompt}: {}", reply[0]), reply[1].to_string()] {
+            stream.push(AssistantMessageEvent::TextDelta {
+                content_index: 0,
+                delta,
+                partial: partial.clone(),
+            });
+        }
+        stream.push(AssistantMessageEvent::TextEnd {
+            content_index: 0,
+            content: full.clone(),
+            partial: partial.clone(),
+        });
+        emit_done(&stream, &full);
+    };
+    register_api_provider(
+        Arc::new(ClosureApiProvider {
+            api: "test-api".to_string(),
+            stream: Arc::new(move |model, context, _, stream| {
+                stream_fn(model, context, stream);
+                Box::pin(async { Ok(()) })
+            }),
+            stream_simple: Arc::new(move |model, context, _, stream| {
+                stream_fn(model, context, stream);
+                Box::pin(async { Ok(()) })
+            }),
+        }),
+        None,
+    );
+}
+
+#[tokio::test]
+async fn content_guards_rewrite_the_prompt_and_every_streamed_text() {
+    let _guard = registry_guard();
+    clear_api_providers();
+    clear_stream_middleware();
+    register_delta_provider(["sec", "ret"]);
+
+    let mut context = sample_context();
+    context.messages = vec![Message::User {
+        content: UserContent::Text("password hunter2".to_string()),
+        timestamp: 1_700_000_000_000,
+    }];
+    let options = StreamOptions {
+        guards: vec![Arc::new(SecretScrubber)],
+        ..StreamOptions::default()
+    };
+    let events = stream(sample_model("test-api"), context, Some(options)).expect("stream");
+    let mut deltas = Vec::new();
+    let mut text_end = String::new();
+    while let Some(event) = events.next().await {
+        match event {
+            AssistantMessageEvent::TextDelta { delta, .. } => deltas.push(delta),
+            AssistantMessageEvent::TextEnd {
+                content, partial, ..
+            } => {
+                assert_eq!(final_text(&partial), content);
+                text_end = content;
+            }
+            _ => {}
+        }
+    }
+    let message = events.result().await.expect("final message");
+
+    assert_eq!(deltas, vec!["password [redacted]: sec", "ret"]);
+    assert_eq!(text_end, "password [redacted]: [redacted]");
+    assert_eq!(final_text(&message), "password [redacted]: [redacted]");
+}
+
+#[tokio::test]
+async fn blocked_terms_reject_prompts_and_responses() {
+    let _guard = registry_guard();
+    clear_api_providers();
+    clear_stream_middleware();
+    register_delta_provider(["launch ", "CODES"]);
+    let options = || StreamOptions {
+        guards: vec![Arc::new(BlockedTermsGuard::new(["Launch Codes"]))],
+        ..StreamOptions::default()
+    };
+
+    let mut context = sample_context();
+    context.messages = vec![Message::User {
+        content: UserContent::Text("share the launch codes".to_string()),
+        timestamp: 1_700_000_000_000,
+    }];
+    let rejected_prompt = complete(sample_model("test-api"), context, Some(options()))
+        .await
+        .expect("complete should resolve");
+    assert_eq!(rejected_prompt.stop_reason, StopReason::Error);
+    assert!(rejected_prompt.content.is_empty());
+    assert!(rejected_prompt
+        .error_message
+        .as_deref()
+        .is_some_and(|error| error.contains("content_rejected") && error.contains("prompt")));
+
+    let rejected_response = complete(sample_model("test-api"), sample_context(), Some(options()))
+        .await
+        .expect("complete should resolve");
+    assert_eq!(rejected_response.stop_reason, StopReason::Error);
+    assert_eq!(final_text(&rejected_response), "");
+    assert!(rejected_response
+        .error_message
+        .as_deref()
+        .is_some_and(|error| error.contains("Blocked term 'launch codes' in the response")));
+}
+
+#[tokio::test]
+async fn llm_metrics_count_requests_tokens_and_errors() {
+    let _guard = registry_guard();
+    clear_api_providers();
+    clear_stream_middleware();
+    register_header_echo_provider();
+
+    let before = llm_metrics();
+    complete(sample_model("test-api"), sample_context(), None)
+        .await
+        .expect("complete should resolve");
+    let options = StreamOptions {
+        middleware: vec![Arc::new(RejectingMiddleware)],
+        ..StreamOptions::default()
+    };
+    complete(sample_model("test-api"), sample_context(), Some(options))
+        .await
+        .expect("complete should resolve");
+    let after = llm_metrics();
+
+    assert_eq!(after.requests - before.requests, 2);
+    assert_eq!(after.errors - before.errors, 1);
+    assert_eq!(after.input_tokens - before.input_tokens, 10);
+    assert_eq!(after.output_tokens - before.output_tokens, 2);
+}
+
+#[tokio::test]
+async fn completed_messages_carry_stream_stats() {
+    let _guard = registry_guard();
+    clear_api_providers();
+    clear_stream_middleware();
+    let stream_fn = |stream: AssistantMessageEventStream| {
+        Box::pin(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            stream.push(AssistantMessageEvent::TextDelta {
+                content_index: 0,
+                delta: "ok".to_string(),
+                partial: sample_assistant(StopReason::Stop, "ok"),
+            });
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            emit_done(&stream, "ok");
+            Ok(())
+        }) as _
+    };
+    register_api_provider(
+        Arc::new(ClosureApiProvider {
+            api: "test-api".to_string(),
+            stream: Arc::new(move |_, _, _, stream| stream_fn(stream)),
+            stream_simple: Arc::new(move |_, _, _, stream| stream_fn(stream)),
+        }),
+        None,
+    );
+
+    let message = complete(sample_model("test-api"), sample_context(), None)
+        .await
+        .expect("complete should resolve");
+    let stats = message.stats.expect("final message should carry stats");
+    assert_eq!(stats.provider, "test");
+    assert_eq!(stats.retries, 0);
+    let ttft = stats.time_to_first_token_ms.expect("a delta was streamed");
+    assert!(ttft >= 20, "ttft {ttft}ms");
+    assert!(stats.duration_ms >= ttft + 20);
+    assert!(stats.tokens_per_sec.is_some_and(|rate| rate > 0.0));
+}
+
+#[tokio::test]
+async fn stream_stats_time_the_attempt_a_reliable_provider_delivered() {
+    let _guard = registry_guard();
+    clear_api_providers();
+    clear_stream_middleware();
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let stream_fn = move |stream: AssistantMessageEventStream| {
+        let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+        Box::pin(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            if attempt == 0 {
+                return Err(PiAiError::new(
+                    PiAiErrorCode::ProviderTransport,
+                    "connection reset",
+                ));
+            }
+            stream.push(AssistantMessageEvent::TextDelta {
+                content_index: 0,
+                delta: "ok".to_string(),
+                partial: sample_assistant(StopReason::Stop, "ok"),
+            });
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            emit_done(&stream, "ok");
+            Ok(())
+        }) as _
+    };
+    let stream_fn = Arc::new(stream_fn);
+    let simple_fn = stream_fn.clone();
+    let provider = ClosureApiProvider {
+        api: "test-api".to_string(),
+        stream: Arc::new(move |_, _, _, stream| stream_fn(stream)),
+        stream_simple: Arc::new(move |_, _, _, stream| simple_fn(stream)),
+    };
+    register_api_provider(
+        Arc::new(
+            ReliableProvider::wrap(Arc::new(provider))
+                .max_retries(1)
+                .base_backoff_ms(0),
+        ),
+        None,
+    );
+
+    let message = complete(sample_model("test-api"), sample_context(), None)
+        .await
+        .expect("complete should resolve");
+    let stats = message.stats.expect("final message should carry stats");
+    assert_eq!(stats.retries, 1);
+    // Measured from the start of the second attempt, not the first, and not when the held
+    // back events were handed on.
+    let ttft = stats.time_to_first_token_ms.expect("a delta was streamed");
+    assert!((100..200).contains(&ttft), "ttft {ttft}ms");
+    assert!(stats.duration_ms >= 300, "duration {}ms", stats.duration_ms);
+    // Two output tokens over about 100ms of generation.
+    let rate = stats.tokens_per_sec.expect("a rate");
+    assert!(rate <= 20.0, "rate {rate}");
+}
+
+#[tokio::test]
+async fn response_cache_serves_identical_completions_without_the_provider() {
+    let _guard = registry_guard();
+    clear_api_providers();
+    clear_stream_middleware();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let stream_calls = calls.clone();
+    let simple_calls = calls.clone();
+    register_api_provider(
+        Arc::new(ClosureApiProvider {
+            api: "test-api".to_string(),
+            stream: Arc::new(move |_, _, _, stream| {
+                let call = stream_calls.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async move {
+                    emit_done(&stream, &format!("answer {call}"));
+                    Ok(())
+                })
+            }),
+            stream_simple: Arc::new(move |_, _, _, stream| {
+                let call = simple_calls.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async move {
+                    emit_done(&stream, &format!("answer {call}"));
+                    Ok(())
+                })
+            }),
+        }),
+        None,
+    );
+    let cache: ResponseCacheRef = Arc::new(MemoryResponseCache::new(16, None));
+    let options = |temperature| StreamOptions {
+        temperature: Some(temperature),
+        response_cache: Some(cache.clone()),
+        ..StreamOptions::default()
+    };
+
+    let first = complete(
+        sample_model("test-api"),
+        sample_context(),
+        Some(options(0.0)),
+    )
+    .await
+    .expect("complete should resolve");
+    let repeated = complete(
+        sample_model("test-api"),
+        sample_context(),
+        Some(options(0.0)),
+    )
+    .await
+    .expect("complete should resolve");
+    assert_eq!(final_text(&first), "answer 0");
+    assert_eq!(final_text(&repeated), "answer 0");
+    assert!(repeated.stats.is_none());
+
+    let changed = complete(
+        sample_model("test-api"),
+        sample_context(),
+        Some(options(0.5)),
+    )
+    .await
+    .expect("complete should resolve");
+    assert_eq!(final_text(&changed), "answer 1");
+
+    let simple = SimpleStreamOptions {
+        stream: options(0.0),
+        reasoning: None,
+    };
+    complete_simple(
+        sample_model("test-api"),
+        sample_context(),
+        Some(simple.clone()),
+    )
+    .await
+    .expect("complete_simple should resolve");
+    let simple_repeated = complete_simple(sample_model("test-api"), sample_context(), Some(simple))
+        .await
+        .expect("complete_simple should resolve");
+    assert_eq!(final_text(&simple_repeated), "answer 2");
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
@@ -0,0 +1,164 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use pixy_ai::{
+    complete, AssistantContentBlock, Context, Cost, Message, Model, RetryPolicy, StopReason,
+    StreamOptions, StreamTransport, UserContent,
+};
+
+fn sample_model(base_url: String) -> Model {
+    Model {
+        id: "test-model".to_string(),
+        name: "Test Model".to_string(),
+        api: "openai-completions".to_string(),
+        provider: "openai".to_string(),
+        base_url,
+        reasoning: false,
+        reasoning_effort: None,
+        input: vec!["text".to_string()],
+        cost: Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+        context_window: 32_000,
+        max_tokens: 1_000,
+        deployment: None,
+        api_version: None,
+        compat: None,
+    }
+}
+
+fn sample_context() -> Context {
+    Context {
+        system_prompt: None,
+        messages: vec![Message::User {
+            content: UserContent::Text("hello".to_string()),
+            timestamp: 1_700_000_000_000,
+        }],
+        tools: None,
+        system_prompt_cache: None,
+        branch: None,
+    }
+}
+
+fn options(transport: StreamTransport) -> StreamOptions {
+    StreamOptions {
+        api_key: Some("test-key".to_string()),
+        retry_policy: Some(RetryPolicy {
+            max_retries: 0,
+            ..RetryPolicy::default()
+        }),
+        stream_transport: Some(transport),
+        ..StreamOptions::default()
+    }
+}
+
+fn text_event(id: u32, text: &str) -> String {
+    format!(
+        "id: {id}\ndata: {{\"id\":\"chatcmpl-1\",\"choices\":[{{\"index\":0,\"delta\":{{\"content\":\"{text}\"}}}}]}}\n\n"
+    )
+}
+
+fn read_request(socket: &mut TcpStream) -> String {
+    socket
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .expect("set read timeout");
+    let mut buffer = [0_u8; 8192];
+    let read_len = socket.read(&mut buffer).unwrap_or(0);
+    String::from_utf8_lossy(&buffer[..read_len]).to_string()
+}
+
+/// Answers the first request with one and a half events before dropping the connection, and
+/// the second with the rest of the stream. Returns the second request.
+fn spawn_dropping_server() -> (String, thread::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind local test server");
+    let address = listener.local_addr().expect("server local addr");
+    let handle = thread::spawn(move || {
+        let (mut socket, _) = listener.accept().expect("accept first request");
+        read_request(&mut socket);
+        let partial = format!("{}id: 2\ndata: {{\"id\":", text_event(1, "Hel"));
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: 4096\r\nConnection: close\r\n\r\n{partial}"
+        );
+        socket
+            .write_all(response.as_bytes())
+            .expect("write first response");
+        drop(socket);
+
+        let (mut socket, _) = listener.accept().expect("accept resumed request");
+        let request = read_request(&mut socket);
+        let body = format!(
+            "{}data: {{\"id\":\"chatcmpl-1\",\"choices\":[{{\"index\":0,\"delta\":{{}},\"finish_reason\":\"stop\"}}]}}\n\ndata: [DONE]\n\n",
+            text_event(2, "lo")
+        );
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        socket
+            .write_all(response.as_bytes())
+            .expect("write resumed response");
+        request
+    });
+    (format!("http://{address}/v1"), handle)
+}
+
+#[tokio::test]
+async fn dropped_streams_resume_from_the_last_event_id() {
+    let (base_url, resumed_request) = spawn_dropping_server();
+    let message = complete(
+        sample_model(base_url),
+        sample_context(),
+        Some(options(StreamTransport::default())),
+    )
+    .await
+    .expect("complete");
+
+    let resumed_request = resumed_request.join().expect("server thread");
+    assert!(resumed_request
+        .to_ascii_lowercase()
+        .contains("last-event-id: 1"));
+    assert_eq!(message.stop_reason, StopReason::Stop);
+    assert!(matches!(
+        message.content.as_slice(),
+        [AssistantContentBlock::Text { text, .. }] if text == "Hello"
+    ));
+}
+
+#[tokio::test]
+async fn stalled_streams_fail_after_the_idle_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind local test server");
+    let address = listener.local_addr().expect("server local addr");
+    let server = thread::spawn(move || {
+        let (mut socket, _) = listener.accept().expect("accept request");
+        read_request(&mut socket);
+        socket
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: 4096\r\n\r\ndata: {\"id\":")
+            .expect("write response head");
+        thread::sleep(Duration::from_millis(1_000));
+    });
+
+    let message = complete(
+        sample_model(format!("http://{address}/v1")),
+        sample_context(),
+        Some(options(StreamTransport {
+            idle_timeout_ms: Some(100),
+            ..StreamTransport::default()
+        })),
+    )
+    .await
+    .expect("complete");
+
+    assert_eq!(message.stop_reason, StopReason::Error);
+    let error_message = message.error_message.expect("error message");
+    assert!(
+        error_message.contains("transport_timeout"),
+        "{error_message}"
+    );
+    server.join().expect("server thread");
+}
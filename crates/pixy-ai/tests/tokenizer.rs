@@ -0,0 +1,108 @@
+use pixy_ai::tokenizer::{estimate_message_tokens, estimate_tokens, Tokenizer};
+use pixy_ai::{Context, Cost, Message, Model, Tool, UserContent, UserContentBlock};
+use serde_json::json;
+
+fn sample_model(id: &str) -> Model {
+    Model {
+        id: id.to_string(),
+        name: id.to_string(),
+        api: "openai-responses".to_string(),
+        provider: "openai".to_string(),
+        base_url: "https://api.openai.com/v1".to_string(),
+        reasoning: false,
+        reasoning_effort: None,
+        input: vec!["text".to_string()],
+        cost: Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+        context_window: 128_000,
+        max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
+    }
+}
+
+#[test]
+fn tokenizer_for_model_picks_encoding_from_model_id() {
+    assert_eq!(
+        Tokenizer::for_model(&sample_model("gpt-4o-mini")),
+        Tokenizer::O200k
+    );
+    assert_eq!(
+        Tokenizer::for_model(&sample_model("openai/gpt-5")),
+        Tokenizer::O200k
+    );
+    assert_eq!(
+        Tokenizer::for_model(&sample_model("gpt-4-turbo")),
+        Tokenizer::Cl100k
+    );
+    assert_eq!(
+        Tokenizer::for_model(&sample_model("claude-sonnet-4-20250514")),
+        Tokenizer::Heuristic
+    );
+}
+
+#[test]
+fn bpe_estimate_splits_words_punctuation_and_digit_groups() {
+    let model = sample_model("gpt-4o");
+    assert_eq!(estimate_tokens(&model, ""), 0);
+    assert_eq!(estimate_tokens(&model, "Hello, world!"), 4);
+    assert_eq!(estimate_tokens(&model, "I'm here"), 3);
+    assert_eq!(estimate_tokens(&model, "1234567"), 3);
+    assert!(
+        estimate_tokens(&model, "internationalization") > estimate_tokens(&model, "international")
+    );
+}
+
+#[test]
+fn heuristic_estimate_counts_ascii_by_four_and_other_scripts_by_char() {
+    let model = sample_model("claude-sonnet-4");
+    assert_eq!(estimate_tokens(&model, "abcdefgh"), 2);
+    assert_eq!(estimate_tokens(&model, "abcdefghi"), 3);
+    assert_eq!(estimate_tokens(&model, "你好"), 2);
+}
+
+#[test]
+fn context_estimate_covers_system_prompt_tools_and_messages() {
+    let model = sample_model("gpt-4o");
+    let user = Message::User {
+        content: UserContent::Blocks(vec![
+            UserContentBlock::Text {
+                text: "Describe this image".to_string(),
+                text_signature: None,
+                cache: None,
+            },
+            UserContentBlock::Image {
+                data: "aGVsbG8=".to_string(),
+                mime_type: "image/png".to_string(),
+            },
+        ]),
+        timestamp: 0,
+    };
+    let bare = Context {
+        system_prompt: None,
+        system_prompt_cache: None,
+        branch: None,
+        messages: vec![user.clone()],
+        tools: None,
+    };
+    let full = Context {
+        system_prompt: Some("You are a careful assistant.".to_string()),
+        tools: Some(vec![Tool {
+            name: "read".to_string(),
+            description: "Read a file".to_string(),
+            parameters: json!({"type": "object", "properties": {"path": {"type": "string"}}}),
+        }]),
+        ..bare.clone()
+    };
+
+    let message_tokens = estimate_message_tokens(&model, &user);
+    assert!(message_tokens > 1_000, "image inputs carry a fixed cost");
+    assert_eq!(bare.estimate_tokens(&model), message_tokens + 3);
+    assert!(full.estimate_tokens(&model) > bare.estimate_tokens(&model) + 20);
+}
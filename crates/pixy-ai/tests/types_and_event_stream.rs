@@ -13,6 +13,7 @@ fn sample_usage() -> Usage {
         output: 5,
         cache_read: 0,
         cache_write: 0,
+        reasoning: 0,
         total_tokens: 15,
         cost: Cost {
             input: 0.01,
@@ -38,6 +39,7 @@ fn sample_message(stop_reason: StopReason) -> AssistantMessage {
         stop_reason,
         error_message: None,
         timestamp: 1_700_000_000_000,
+        stats: None,
     }
 }
 
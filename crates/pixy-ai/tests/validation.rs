@@ -1,4 +1,10 @@
-use pixy_ai::{validate_tool_call, PiAiErrorCode, Tool, ToolCall};
+use pixy_ai::{
+    tool_argument_violations, validate_tool_arguments_with, validate_tool_call,
+    validate_tool_call_ids, validate_tool_calls, AssistantContentBlock, Cost, Message,
+    PiAiErrorCode, StopReason, Tool, ToolCall, ToolResultMessage, ToolValidationOptions, Usage,
+    UserContent,
+};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 
 fn sample_tool() -> Tool {
@@ -70,3 +76,258 @@ fn validate_tool_call_rejects_invalid_arguments_with_structured_details() {
         .expect("validationErrors should be array");
     assert!(!validation_errors.is_empty());
 }
+
+#[test]
+fn validate_tool_calls_checks_each_call_in_order_and_rejects_duplicate_ids() {
+    let tools = vec![sample_tool()];
+    let content = vec![
+        AssistantContentBlock::ToolCall {
+            id: "tool-1".to_string(),
+            name: "read".to_string(),
+            arguments: json!({ "path": "a.txt" }),
+            thought_signature: None,
+        },
+        AssistantContentBlock::Text {
+            text: "and another".to_string(),
+            text_signature: None,
+        },
+        AssistantContentBlock::ToolCall {
+            id: "tool-2".to_string(),
+            name: "read".to_string(),
+            arguments: json!({ "path": 7 }),
+            thought_signature: None,
+        },
+        AssistantContentBlock::ToolCall {
+            id: "tool-1".to_string(),
+            name: "read".to_string(),
+            arguments: json!({ "path": "b.txt" }),
+            thought_signature: None,
+        },
+    ];
+
+    let calls = ToolCall::from_content(&content);
+    assert_eq!(
+        calls
+            .iter()
+            .map(|call| call.id.as_str())
+            .collect::<Vec<_>>(),
+        vec!["tool-1", "tool-2", "tool-1"]
+    );
+
+    let results = validate_tool_calls(&tools, &calls);
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap()["path"], json!("a.txt"));
+    assert_eq!(
+        results[1].as_ref().unwrap_err().code,
+        PiAiErrorCode::ToolArgumentsInvalid
+    );
+    let duplicate = results[2].as_ref().unwrap_err();
+    assert!(duplicate
+        .message
+        .contains("Duplicate tool call id 'tool-1'"));
+}
+
+fn edit_tool() -> Tool {
+    Tool {
+        name: "edit".to_string(),
+        description: "Edit a file".to_string(),
+        parameters: json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string" },
+                "mode": { "type": "string", "enum": ["replace", "append"], "default": "replace" },
+                "line": { "type": "integer", "minimum": 1, "maximum": 1000 },
+                "dryRun": { "type": "boolean", "default": false },
+                "tags": { "type": "array", "items": { "type": "integer" } }
+            },
+            "required": ["path"]
+        }),
+    }
+}
+
+#[test]
+fn validate_tool_arguments_with_coerces_types_and_injects_defaults() {
+    let call = ToolCall {
+        id: "tool-5".to_string(),
+        name: "edit".to_string(),
+        arguments: json!({ "path": "src/lib.rs", "line": "3", "tags": ["1", 2] }),
+    };
+
+    let validated =
+        validate_tool_arguments_with(&edit_tool(), &call, ToolValidationOptions::lenient())
+            .expect("coerced arguments should pass");
+    assert_eq!(
+        validated,
+        json!({
+            "path": "src/lib.rs",
+            "mode": "replace",
+            "line": 3,
+            "dryRun": false,
+            "tags": [1, 2]
+        })
+    );
+
+    let error = validate_tool_arguments_with(&edit_tool(), &call, ToolValidationOptions::default())
+        .expect_err("strict validation should reject string numbers");
+    assert_eq!(error.code, PiAiErrorCode::ToolArgumentsInvalid);
+}
+
+#[test]
+fn validate_tool_arguments_with_lists_every_violation() {
+    let call = ToolCall {
+        id: "tool-6".to_string(),
+        name: "edit".to_string(),
+        arguments: json!({ "mode": "delete", "line": "0", "dryRun": "maybe" }),
+    };
+
+    let error = validate_tool_arguments_with(&edit_tool(), &call, ToolValidationOptions::lenient())
+        .expect_err("invalid arguments should fail");
+    let mut violations = tool_argument_violations(&error)
+        .into_iter()
+        .map(|violation| (violation.path, violation.keyword))
+        .collect::<Vec<_>>();
+    violations.sort();
+    assert_eq!(
+        violations,
+        vec![
+            (String::new(), "required".to_string()),
+            ("/dryRun".to_string(), "type".to_string()),
+            ("/line".to_string(), "minimum".to_string()),
+            ("/mode".to_string(), "enum".to_string()),
+        ]
+    );
+    assert!(error.message.contains("- /line: "));
+    assert!(error
+        .message
+        .contains("- arguments: \"path\" is a required property"));
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct ReadArgs {
+    path: String,
+    offset: u32,
+}
+
+#[test]
+fn tool_call_builder_round_trips_typed_arguments() {
+    let call = ToolCall::builder()
+        .name("read")
+        .argument("path", "README.md")
+        .argument("offset", 10)
+        .build()
+        .expect("build");
+
+    assert!(call.id.starts_with("pixy_call_"));
+    assert!(validate_tool_call(&[sample_tool()], &call).is_ok());
+    assert_eq!(
+        call.parse_arguments::<ReadArgs>().expect("parse"),
+        ReadArgs {
+            path: "README.md".to_string(),
+            offset: 10,
+        }
+    );
+
+    let error = ToolCall::builder()
+        .name("read")
+        .arguments(vec![1, 2])
+        .build()
+        .expect_err("arguments must be an object");
+    assert_eq!(error.code, PiAiErrorCode::ToolArgumentsInvalid);
+}
+
+#[test]
+fn tool_result_from_output_round_trips_and_answers_its_call() {
+    let call = ToolCall::builder()
+        .id("call-1")
+        .name("read")
+        .arguments(ReadArgs {
+            path: "a.txt".to_string(),
+            offset: 0,
+        })
+        .build()
+        .expect("build");
+    let output = ReadArgs {
+        path: "a.txt".to_string(),
+        offset: 3,
+    };
+    let result = ToolResultMessage::from_output(&call, &output).expect("serialize");
+    assert_eq!(result.tool_call_id, "call-1");
+    assert!(!result.is_error);
+    assert_eq!(result.parse_output::<ReadArgs>().expect("parse"), output);
+
+    let text = ToolResultMessage::from_output(&call, &"plain text").expect("serialize");
+    assert_eq!(text.parse_output::<String>().expect("parse"), "plain text");
+    assert!(ToolResultMessage::from_error(&call, "boom").is_error);
+}
+
+fn assistant_with_calls(calls: &[&ToolCall]) -> Message {
+    Message::Assistant {
+        content: calls.iter().map(|call| call.to_content_block()).collect(),
+        api: "test".to_string(),
+        provider: "test".to_string(),
+        model: "test".to_string(),
+        usage: Usage {
+            input: 0,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            reasoning: 0,
+            total_tokens: 0,
+            cost: Cost {
+                input: 0.0,
+                output: 0.0,
+                cache_read: 0.0,
+                cache_write: 0.0,
+                total: 0.0,
+            },
+        },
+        stop_reason: StopReason::ToolUse,
+        error_message: None,
+        timestamp: 0,
+        stats: None,
+    }
+}
+
+#[test]
+fn validate_tool_call_ids_flags_orphans_and_duplicates() {
+    let first = ToolCall::builder().name("read").build().expect("build");
+    let second = ToolCall::builder().name("read").build().expect("build");
+    let answer = |call: &ToolCall| -> Message {
+        ToolResultMessage::from_output(call, &"ok")
+            .expect("serialize")
+            .into()
+    };
+    let question = Message::User {
+        content: UserContent::Text("read both".to_string()),
+        timestamp: 0,
+    };
+
+    let valid = vec![
+        question.clone(),
+        assistant_with_calls(&[&first, &second]),
+        answer(&first),
+        answer(&second),
+    ];
+    assert!(validate_tool_call_ids(&valid).is_ok());
+
+    let orphaned = vec![question.clone(), answer(&first)];
+    let error = validate_tool_call_ids(&orphaned).expect_err("orphaned result");
+    assert_eq!(error.code, PiAiErrorCode::ToolCallMismatch);
+    assert_eq!(error.details.expect("details")["messageIndex"], json!(1));
+
+    let duplicate_call = vec![
+        assistant_with_calls(&[&first]),
+        answer(&first),
+        assistant_with_calls(&[&first]),
+    ];
+    let error = validate_tool_call_ids(&duplicate_call).expect_err("duplicate call id");
+    assert!(error.message.contains("Duplicate tool call id"));
+
+    let answered_twice = vec![
+        assistant_with_calls(&[&first]),
+        answer(&first),
+        answer(&first),
+    ];
+    let error = validate_tool_call_ids(&answered_twice).expect_err("answered twice");
+    assert!(error.message.contains("answered more than once"));
+}
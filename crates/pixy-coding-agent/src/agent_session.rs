@@ -1,37 +1,77 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use chrono::{Local, TimeZone};
 use pixy_agent_core::{
-    agent_loop, agent_loop_continue, AgentAbortSignal, AgentContext, AgentEvent, AgentLoopConfig,
-    AgentMessage, AgentRetryConfig, AgentTool, IdentityMessageConverter, ParentChildRunEvent,
-    StreamFn,
+    agent_loop, agent_loop_continue, AgentAbortController, AgentAbortSignal, AgentContext,
+    AgentEvent, AgentHooks, AgentLoopConfig, AgentMessage, AgentRetryConfig, AgentRunMetrics,
+    AgentTool, IdentityMessageConverter, ParentChildRunEvent, QueueMode, QueuedMessages, StreamFn,
+    ToolApproval, ToolApprovalRequest, ToolApproverFn, ToolLoopGuard, ToolPolicy, ToolRisk,
 };
 use pixy_ai::{
-    AssistantContentBlock, AssistantMessageEvent, Context as LlmContext, Message, Model,
-    SimpleStreamOptions, StopReason, ToolResultContentBlock, Usage, UserContent, UserContentBlock,
+    is_context_overflow_error_text, lookup_model_pricing, model_pricing, AssistantContentBlock,
+    AssistantMessageEvent, AssistantMessageEventStream, Context as LlmContext, Message, Model,
+    SimpleStreamOptions, StopReason, StreamStats, ToolResultContentBlock, ToolValidationOptions,
+    Usage, UserContent, UserContentBlock,
 };
 use serde_json::Value;
-
-use crate::system_prompt::append_multi_agent_prompt_section;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+use crate::diff_review::{DiffReview, DiffReviewRequest, SharedDiffReview};
+use crate::file_changes::{FileChangeNotices, FileChangeTracker, SharedFileChangeTracker};
+use crate::file_snapshots::{FileSnapshotStore, SharedFileSnapshots};
+use crate::lifecycle_hooks::{LifecycleHookEvent, LifecycleHooks};
+use crate::mcp::load_mcp_tools;
+use crate::multi_agent::PROJECT_AGENTS_DIR;
+use crate::post_edit::PostEditChecks;
+use crate::secret_redaction::SecretRedactor;
+use crate::session_cost::{apply_pricing, SessionCostReport};
+use crate::system_prompt::{append_multi_agent_prompt_section, PromptVariables};
+use crate::telemetry::{SessionTelemetry, TelemetryTurn};
+use crate::tool_failures::ToolFailureFeedback;
+use crate::tool_output::{artifact_dir_for_session, ToolOutputLimiter};
+use crate::tools::{
+    create_bash_background_tool, create_coding_tools_with_snapshots, create_git_tools,
+    create_read_image_tool, create_shell_tool, create_todo_tool, create_web_tools,
+    in_git_repository, todos_from_messages, todos_from_tool_result, BackgroundProcesses,
+    PersistentShell, TodoItem,
+};
 use crate::{
     agent_session_services::{
         AutoCompactionService, SessionResumeService, StreamingToolLineRenderer,
     },
     bash_command::normalize_nested_bash_lc,
-    build_system_prompt, create_coding_tools_with_extra, create_memory_tool,
+    build_system_prompt, create_memory_tool_with_semantic_index,
     create_multi_agent_plugin_runtime_from_specs, create_task_tool, load_and_merge_plugins,
-    memory::{MemoryConfig as PersistMemoryConfig, MemoryFlushContext, MemoryManager},
-    BeforeToolDefinitionHookContext, BeforeUserMessageHookContext, ChildSessionStore,
-    DefaultSubAgentRegistry, DispatchPolicyConfig, MergedPluginConfig, MultiAgentPluginRuntime,
-    ResolvedRuntime, RuntimeLoadOptions, SessionContext, SessionManager, TaskDispatcher,
-    TaskDispatcherConfig, BRANCH_SUMMARY_PREFIX, COMPACTION_SUMMARY_PREFIX,
+    load_project_subagents,
+    memory::{
+        MemoryConfig as PersistMemoryConfig, MemoryFlushContext, MemoryManager,
+        PixyAiMemoryEmbedder, SemanticMemoryIndex,
+    },
+    parse_skill_invocation,
+    project_memory::{
+        parse_learnings, DISTILL_PROMPT as PROJECT_MEMORY_DISTILL_PROMPT,
+        DISTILL_SYSTEM_PROMPT as PROJECT_MEMORY_DISTILL_SYSTEM_PROMPT,
+    },
+    render_skill, BeforeToolDefinitionHookContext, BeforeUserMessageHookContext,
+    ChildSessionControls, ChildSessionStore, ChildShellToolFn, DefaultSubAgentRegistry,
+    DispatchPolicyConfig, LoadProjectSubAgentsResult, LoadSkillsResult, MergedPluginConfig,
+    MultiAgentPluginRuntime, ProjectMemoryConfig, ProjectMemoryFile, ResolvedRuntime,
+    RuntimeLoadOptions, SessionContext, SessionManager, SharedChildSessionControls, Skill,
+    SkillCatalog, SubAgentSpec, TaskDispatcher, TaskDispatcherConfig, BRANCH_SUMMARY_PREFIX,
+    COMPACTION_SUMMARY_PREFIX,
 };
 
 const AUTO_COMPACTION_SUMMARIZATION_SYSTEM_PROMPT: &str = "You are a context summarization assistant. Summarize conversation history for another coding assistant.";
 const AUTO_COMPACTION_SUMMARIZATION_PROMPT: &str = "Summarize the conversation above so another LLM can continue the task. Include: user goal, completed work, current status, and concrete next steps. Preserve exact file paths, commands, and error messages where relevant. Keep it concise.";
+const PROJECT_MEMORY_MAX_CONVERSATION_CHARS: usize = 60_000;
+const SESSION_TITLE_SYSTEM_PROMPT: &str = "You name coding sessions. Reply with a title only.";
+const SESSION_TITLE_PROMPT: &str = "Write a short title (at most 8 words) for the coding session above, describing the user's task. Reply with the title only: no quotes, no trailing punctuation.";
+const SESSION_TITLE_MAX_CONVERSATION_CHARS: usize = 4_000;
+const SESSION_TITLE_MAX_CHARS: usize = 72;
 const PLAN_MODE_PROMPT_INSTRUCTION: &str = "You are in PLAN MODE. Your output must be a bulleted list of technical steps. Do not emit any tool calls that modify the filesystem. Wrap your plan in <plan>";
 
 pub struct AgentSessionConfig {
@@ -46,20 +86,46 @@ pub enum AgentSessionStreamUpdate {
     AssistantTextDelta(String),
     AssistantLine(String),
     ToolLine(String),
+    /// The todo list after a successful `todo` tool call.
+    Todos(Vec<TodoItem>),
+    /// Tokens the provider reported while the assistant message was still streaming.
+    UsageDelta(Usage),
+    /// What a tool call still being streamed will do, e.g. `Editing src/lib.rs...`, read
+    /// from its partially parsed arguments.
+    ToolCallPreview(String),
+    /// Latency and throughput of the model call behind an assistant message that just ended.
+    Stats(StreamStats),
+    /// The full text of an assistant message that just ended, when it has any.
+    AssistantMessageEnd(String),
+    /// One step of a subagent run spawned by the task tool, forwarded while it runs: `started`,
+    /// the headline of each tool call it makes, then `completed ...` or `failed: ...`.
+    SubagentProgress {
+        task_id: String,
+        subagent: String,
+        line: String,
+    },
+    /// A tool call is about to execute.
+    ToolCallStart {
+        tool_call_id: String,
+        tool_name: String,
+        args: Value,
+    },
+    /// A tool call finished executing; `content` is what the model will see.
+    ToolCallEnd {
+        tool_call_id: String,
+        tool_name: String,
+        content: Vec<ToolResultContentBlock>,
+        is_error: bool,
+    },
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
 pub enum AgentMode {
     Plan,
+    #[default]
     Act,
 }
 
-impl Default for AgentMode {
-    fn default() -> Self {
-        Self::Act
-    }
-}
-
 impl AgentMode {
     fn cycle(self) -> Self {
         match self {
@@ -76,11 +142,20 @@ impl AgentMode {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub(crate) struct SessionResumeCandidate {
     pub path: PathBuf,
     pub title: String,
+    pub first_prompt: Option<String>,
     pub updated_at: String,
+    /// Number of user prompts on the current branch.
+    pub turn_count: usize,
+    /// Number of messages on the current branch.
+    pub message_count: usize,
+    /// Model of the latest assistant reply.
+    pub model: Option<String>,
+    pub total_cost: f64,
+    pub cwd: String,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -110,13 +185,64 @@ pub struct AgentSession {
     mode: AgentMode,
     plugin_runtime: Arc<MultiAgentPluginRuntime>,
     memory_runtime: Option<SessionMemoryRuntime>,
+    file_snapshots: Option<SharedFileSnapshots>,
+    file_changes: Option<SharedFileChangeTracker>,
+    diff_review: Option<SharedDiffReview>,
+    max_turns: Option<usize>,
+    turn_limit_reached: bool,
+    lifecycle_hooks: Option<Arc<LifecycleHooks>>,
+    tool_failures: Option<Arc<ToolFailureFeedback>>,
+    tool_output: Option<Arc<ToolOutputLimiter>>,
+    telemetry: Option<Arc<SessionTelemetry>>,
+    session_start_fired: bool,
+    skills: Option<SessionSkills>,
+    prompt: Option<SessionPrompt>,
+    project_memory: Option<SessionProjectMemory>,
+    subagent_progress: Option<UnboundedReceiver<AgentSessionStreamUpdate>>,
+    background_processes: Option<BackgroundProcesses>,
+    persistent_shell: Option<PersistentShell>,
     auto_compaction: AutoCompactionConfig,
     model_catalog: Vec<Model>,
     current_model_index: usize,
     retry_config: AgentRetryConfig,
+    tool_parallelism: usize,
+    tool_policy: Option<ToolPolicy>,
+    /// Where the restrictions below are handed on to the sessions of subagents.
+    child_controls: Option<SharedChildSessionControls>,
+    /// Tools that wait for the attached approver, from `[approval]`.
+    approval_tools: HashMap<String, ToolRisk>,
+    tool_approver: Option<ToolApproverFn>,
+    loop_guard: Option<ToolLoopGuard>,
+    /// Messages queued while a run streams; steering ones join the run before its next turn.
+    queued_messages: QueuedMessages,
+    /// Totals of every run of this session object, including ones not saved to the session file.
+    run_metrics: AgentRunMetrics,
     resume_service: SessionResumeService,
     compaction_service: AutoCompactionService,
     stream_renderer: StreamingToolLineRenderer,
+    auto_title: bool,
+    title_attempted: bool,
+}
+
+struct SessionSkills {
+    catalog: SkillCatalog,
+}
+
+/// Inputs needed to rebuild the act-mode system prompt when the enabled skills, the model or a
+/// template variable changes.
+struct SessionPrompt {
+    custom_system_prompt: Option<String>,
+    cwd: PathBuf,
+    subagents: Vec<SubAgentSpec>,
+    /// Used when the session has no skill catalog.
+    skills: Vec<Skill>,
+    /// Values the current prompt was rendered with.
+    variables: PromptVariables,
+}
+
+struct SessionProjectMemory {
+    file: ProjectMemoryFile,
+    auto: bool,
 }
 
 #[derive(Clone)]
@@ -139,13 +265,39 @@ impl AgentSession {
             mode: AgentMode::default(),
             plugin_runtime: Arc::new(MultiAgentPluginRuntime::default()),
             memory_runtime: None,
+            file_snapshots: None,
+            file_changes: None,
+            diff_review: None,
+            max_turns: None,
+            turn_limit_reached: false,
+            lifecycle_hooks: None,
+            tool_failures: None,
+            tool_output: None,
+            telemetry: None,
+            session_start_fired: false,
+            skills: None,
+            prompt: None,
+            project_memory: None,
+            subagent_progress: None,
+            background_processes: None,
+            persistent_shell: None,
             auto_compaction: AutoCompactionConfig::default(),
             model_catalog: vec![current_model],
             current_model_index: 0,
             retry_config: AgentRetryConfig::default(),
+            tool_parallelism: 1,
+            tool_policy: None,
+            child_controls: None,
+            approval_tools: HashMap::new(),
+            tool_approver: None,
+            loop_guard: None,
+            queued_messages: QueuedMessages::new(QueueMode::All, QueueMode::OneAtATime),
+            run_metrics: AgentRunMetrics::default(),
             resume_service: SessionResumeService::new(),
             compaction_service: AutoCompactionService::new(),
             stream_renderer: StreamingToolLineRenderer::new(),
+            auto_title: false,
+            title_attempted: false,
         }
     }
 
@@ -153,6 +305,15 @@ impl AgentSession {
         self.session_manager.session_file()
     }
 
+    /// Generates a session title with one extra model call after the first exchange.
+    pub fn set_auto_title(&mut self, enabled: bool) {
+        self.auto_title = enabled;
+    }
+
+    pub fn session_title(&self) -> Option<&str> {
+        self.session_manager.header().title.as_deref()
+    }
+
     pub fn build_session_context(&self) -> SessionContext {
         self.session_manager.build_session_context()
     }
@@ -161,16 +322,459 @@ impl AgentSession {
         self.plugin_runtime = plugin_runtime;
     }
 
+    fn set_subagent_progress(
+        &mut self,
+        subagent_progress: Option<UnboundedReceiver<AgentSessionStreamUpdate>>,
+    ) {
+        self.subagent_progress = subagent_progress;
+    }
+
+    /// Todo list most recently set by the `todo` tool on the current branch.
+    pub fn todos(&self) -> Vec<TodoItem> {
+        todos_from_messages(&self.session_manager.current_path_messages())
+    }
+
+    /// Processes started by `bash_background`; they are killed by [`Self::end_session`].
+    pub fn set_background_processes(&mut self, processes: Option<BackgroundProcesses>) {
+        self.background_processes = processes;
+    }
+
+    /// The shell of the `shell` tool; it is closed by [`Self::end_session`].
+    pub fn set_persistent_shell(&mut self, shell: Option<PersistentShell>) {
+        self.persistent_shell = shell;
+    }
+
     fn set_memory_runtime(&mut self, memory_runtime: Option<SessionMemoryRuntime>) {
         self.memory_runtime = memory_runtime;
     }
 
+    /// Caps the number of assistant requests per run. When the loop needs another request after
+    /// the cap, the run is aborted and [`Self::turn_limit_reached`] reports `true`.
+    pub fn set_max_turns(&mut self, max_turns: Option<usize>) {
+        self.max_turns = max_turns.filter(|max_turns| *max_turns > 0);
+    }
+
+    pub fn turn_limit_reached(&self) -> bool {
+        self.turn_limit_reached
+    }
+
+    fn begin_turn_limit(
+        &mut self,
+        abort_signal: Option<AgentAbortSignal>,
+    ) -> (Option<AgentAbortSignal>, Option<TurnLimit>) {
+        self.turn_limit_reached = false;
+        match self.max_turns {
+            Some(max_turns) => {
+                let turn_limit = TurnLimit::new(max_turns, abort_signal);
+                (Some(turn_limit.signal()), Some(turn_limit))
+            }
+            None => (abort_signal, None),
+        }
+    }
+
+    pub fn set_lifecycle_hooks(&mut self, lifecycle_hooks: Option<Arc<LifecycleHooks>>) {
+        self.lifecycle_hooks = lifecycle_hooks;
+    }
+
+    /// Failure tracking shared with the wrapped tools; its retry counts reset at each request.
+    pub fn set_tool_failure_feedback(&mut self, tool_failures: Option<Arc<ToolFailureFeedback>>) {
+        self.tool_failures = tool_failures;
+    }
+
+    /// Output limiter shared with the wrapped tools; spills go next to the current session file.
+    pub fn set_tool_output_limiter(&mut self, tool_output: Option<Arc<ToolOutputLimiter>>) {
+        self.tool_output = tool_output;
+    }
+
+    /// Span recorder shared with the wrapped tools and stream function; each turn is exported
+    /// when it finishes.
+    pub fn set_telemetry(&mut self, telemetry: Option<Arc<SessionTelemetry>>) {
+        self.telemetry = telemetry;
+    }
+
+    /// Runs `session_end` hooks for the active session if `session_start` already fired, and
+    /// distills project learnings first when automatic project memory is enabled. Background
+    /// processes and the persistent shell are killed every time, including for sessions whose
+    /// start never fired or was reset by `/new`, `/resume` or `/fork`.
+    pub async fn end_session(&mut self) {
+        let started = std::mem::take(&mut self.session_start_fired);
+        if started
+            && self
+                .project_memory
+                .as_ref()
+                .is_some_and(|project_memory| project_memory.auto)
+        {
+            if let Err(error) = self.remember(None).await {
+                eprintln!("warning: project memory update failed: {error}");
+            }
+        }
+        if let Some(processes) = &self.background_processes {
+            let killed = processes.kill_all();
+            if killed > 0 {
+                tracing::info!(killed, "killed background processes at session end");
+            }
+        }
+        if let Some(shell) = &self.persistent_shell {
+            if shell.close().await {
+                tracing::info!("closed the persistent shell at session end");
+            }
+        }
+        if !started {
+            return;
+        }
+        self.run_session_hook(LifecycleHookEvent::SessionEnd).await;
+        if let Some(telemetry) = &self.telemetry {
+            telemetry.flush().await;
+        }
+    }
+
+    /// Enables `/remember` for this session, writing learnings to the configured target under
+    /// `cwd`.
+    pub fn set_project_memory(&mut self, cwd: &Path, config: &ProjectMemoryConfig) {
+        self.project_memory = Some(SessionProjectMemory {
+            file: ProjectMemoryFile::new(cwd, config),
+            auto: config.auto,
+        });
+    }
+
+    /// Records learnings in the project memory file. A `note` is recorded as-is; without one the
+    /// model distills learnings from the current session. Returns a one-line status.
+    pub async fn remember(&mut self, note: Option<&str>) -> Result<String, String> {
+        let file = self
+            .project_memory
+            .as_ref()
+            .map(|project_memory| project_memory.file.clone())
+            .ok_or_else(|| "project memory is not available in this session".to_string())?;
+
+        let learnings = match note.map(str::trim).filter(|note| !note.is_empty()) {
+            Some(note) => vec![note.to_string()],
+            None => self.distill_learnings(&file).await?,
+        };
+        let added = file.append(&learnings)?;
+        if added.is_empty() {
+            return Ok("No new learnings to remember".to_string());
+        }
+        Ok(format!(
+            "Remembered {} learning{} in {}",
+            added.len(),
+            if added.len() == 1 { "" } else { "s" },
+            file.path().display()
+        ))
+    }
+
+    async fn distill_learnings(&self, file: &ProjectMemoryFile) -> Result<Vec<String>, String> {
+        let messages = self.build_session_context().messages;
+        let conversation = pixy_ai::transcript(&messages);
+        if conversation.trim().is_empty() {
+            return Err("No conversation to learn from yet".to_string());
+        }
+        let conversation = tail_chars(&conversation, PROJECT_MEMORY_MAX_CONVERSATION_CHARS);
+        let existing = file
+            .learnings()
+            .iter()
+            .map(|learning| format!("- {learning}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let prompt = format!(
+            "<existing_learnings>\n{existing}\n</existing_learnings>\n\n<conversation>\n{conversation}\n</conversation>\n\n{PROJECT_MEMORY_DISTILL_PROMPT}"
+        );
+        let text = self
+            .complete_text(
+                PROJECT_MEMORY_DISTILL_SYSTEM_PROMPT,
+                prompt,
+                "Project memory",
+            )
+            .await?;
+        Ok(parse_learnings(&text))
+    }
+
+    fn set_skills(&mut self, skills: Option<SessionSkills>) {
+        self.skills = skills;
+    }
+
+    fn set_prompt(&mut self, prompt: Option<SessionPrompt>) {
+        self.prompt = prompt;
+    }
+
+    /// Lists loaded skills with their source, enabled state and load diagnostics.
+    pub fn describe_skills(&self) -> Result<Vec<String>, String> {
+        Ok(self.session_skills()?.catalog.describe())
+    }
+
+    /// Reloads skills from disk and rebuilds the system prompt.
+    pub fn reload_skills(&mut self) -> Result<usize, String> {
+        let skills = self.session_skills_mut()?;
+        skills.catalog.reload();
+        let count = skills.catalog.skills().len();
+        self.rebuild_system_prompt();
+        Ok(count)
+    }
+
+    /// Enables or disables a skill for the rest of this session.
+    pub fn set_skill_enabled(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        if self
+            .session_skills_mut()?
+            .catalog
+            .set_enabled(name, enabled)?
+        {
+            self.rebuild_system_prompt();
+        }
+        Ok(())
+    }
+
+    /// Renders `<name> key=value ...` into the prompt text for an enabled skill.
+    pub fn render_skill_invocation(&self, args: &str) -> Result<String, String> {
+        let (name, arguments) = parse_skill_invocation(args)?;
+        self.render_skill(&name, &arguments)
+    }
+
+    /// Renders an enabled skill with `arguments` into its prompt text.
+    pub fn render_skill(
+        &self,
+        name: &str,
+        arguments: &HashMap<String, String>,
+    ) -> Result<String, String> {
+        let catalog = &self.session_skills()?.catalog;
+        let skill = catalog
+            .skills()
+            .iter()
+            .find(|skill| skill.name == name)
+            .ok_or_else(|| format!("unknown skill: {name}"))?;
+        if !catalog.is_enabled(name) {
+            return Err(format!("skill is disabled: {name}"));
+        }
+        render_skill(skill, arguments)
+    }
+
+    /// Skills enabled for this session, in catalog order; empty when skills are not loaded.
+    pub fn enabled_skills(&self) -> Vec<&Skill> {
+        let Some(skills) = &self.skills else {
+            return vec![];
+        };
+        skills
+            .catalog
+            .skills()
+            .iter()
+            .filter(|skill| skills.catalog.is_enabled(&skill.name))
+            .collect()
+    }
+
+    fn reload_skills_if_changed(&mut self) {
+        let changed = self
+            .skills
+            .as_mut()
+            .is_some_and(|skills| skills.catalog.reload_if_changed());
+        if changed {
+            self.rebuild_system_prompt();
+        }
+    }
+
+    fn session_skills(&self) -> Result<&SessionSkills, String> {
+        self.skills
+            .as_ref()
+            .ok_or_else(|| "Skills are not loaded for this session".to_string())
+    }
+
+    fn session_skills_mut(&mut self) -> Result<&mut SessionSkills, String> {
+        self.skills
+            .as_mut()
+            .ok_or_else(|| "Skills are not loaded for this session".to_string())
+    }
+
+    fn rebuild_system_prompt(&mut self) {
+        let Some(prompt) = &self.prompt else {
+            return;
+        };
+        let skills = match &self.skills {
+            Some(skills) => skills.catalog.enabled_skills(),
+            None => prompt.skills.clone(),
+        };
+        let mut system_prompt = build_system_prompt(
+            prompt.custom_system_prompt.as_deref(),
+            &prompt.cwd,
+            &self.act_tools,
+            &skills,
+            Some(&self.config.model),
+        );
+        append_multi_agent_prompt_section(&mut system_prompt, &self.act_tools, &prompt.subagents);
+        let variables = PromptVariables::current(&prompt.cwd, Some(&self.config.model));
+        if let Some(prompt) = self.prompt.as_mut() {
+            prompt.variables = variables;
+        }
+        self.act_system_prompt = system_prompt;
+        self.set_mode(self.mode);
+    }
+
+    /// Re-renders the system prompt when a template variable (branch, date, ...) has changed
+    /// since the last render.
+    fn start_telemetry_turn(&self, kind: &'static str) -> Option<TelemetryTurn> {
+        self.telemetry
+            .as_ref()
+            .map(|telemetry| telemetry.start_turn(kind, &self.session_manager.header().id))
+    }
+
+    async fn finish_telemetry_turn(&self, turn: Option<TelemetryTurn>, produced: &[AgentMessage]) {
+        if let (Some(telemetry), Some(turn)) = (&self.telemetry, turn) {
+            telemetry.finish_turn(turn, produced).await;
+        }
+    }
+
+    fn prepare_tool_wrappers(&self) {
+        if let Some(tool_failures) = &self.tool_failures {
+            tool_failures.reset();
+        }
+        if let Some(tool_output) = &self.tool_output {
+            tool_output.set_artifact_dir(
+                self.session_manager
+                    .session_file()
+                    .map(|path| artifact_dir_for_session(path)),
+            );
+        }
+    }
+
+    fn refresh_prompt_variables(&mut self) {
+        let changed = self.prompt.as_ref().is_some_and(|prompt| {
+            PromptVariables::current(&prompt.cwd, Some(&self.config.model)) != prompt.variables
+        });
+        if changed {
+            self.rebuild_system_prompt();
+        }
+    }
+
+    async fn ensure_session_started(&mut self) {
+        if self.session_start_fired {
+            return;
+        }
+        self.session_start_fired = true;
+        self.run_session_hook(LifecycleHookEvent::SessionStart)
+            .await;
+    }
+
+    async fn run_session_hook(&self, event: LifecycleHookEvent) {
+        let Some(hooks) = self.lifecycle_hooks.clone() else {
+            return;
+        };
+        let payload = serde_json::json!({
+            "sessionId": self.session_manager.header().id,
+            "sessionFile": self
+                .session_manager
+                .session_file()
+                .map(|path| path.display().to_string()),
+        });
+        hooks.run(event, None, payload).await;
+    }
+
+    fn set_file_snapshots(&mut self, file_snapshots: Option<SharedFileSnapshots>) {
+        self.file_snapshots = file_snapshots;
+    }
+
+    fn set_file_changes(&mut self, file_changes: Option<SharedFileChangeTracker>) {
+        self.file_changes = file_changes;
+    }
+
+    fn set_diff_review(&mut self, diff_review: Option<SharedDiffReview>) {
+        self.diff_review = diff_review;
+    }
+
+    /// Sends proposed edits to `reviewer` for approval before they are written. Returns `false`
+    /// when diff review is not enabled for this session.
+    pub fn attach_diff_reviewer(
+        &self,
+        reviewer: impl Fn(DiffReviewRequest) + Send + Sync + 'static,
+    ) -> bool {
+        match &self.diff_review {
+            Some(review) => {
+                review.attach(reviewer);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reverts files changed by tools during the most recent run that touched the filesystem.
+    /// The model is told about the reverted files with the next prompt.
+    pub fn undo_file_changes(&mut self) -> Result<Vec<PathBuf>, String> {
+        let paths = self.with_file_snapshots(FileSnapshotStore::undo)?;
+        self.record_file_changes(&paths);
+        Ok(paths)
+    }
+
+    /// Re-applies the change set most recently reverted by [`Self::undo_file_changes`].
+    pub fn redo_file_changes(&mut self) -> Result<Vec<PathBuf>, String> {
+        let paths = self.with_file_snapshots(FileSnapshotStore::redo)?;
+        self.record_file_changes(&paths);
+        Ok(paths)
+    }
+
+    fn record_file_changes(&self, paths: &[PathBuf]) {
+        if let Some(Ok(mut tracker)) = self.file_changes.as_ref().map(|tracker| tracker.lock()) {
+            for path in paths {
+                tracker.record_change(path);
+            }
+        }
+    }
+
+    /// `<file_changes>` notice for tracked files that changed on disk since they were last
+    /// checked, sent with the next prompt. During a run the same check runs between turns.
+    fn take_external_change_notice(&self) -> Option<String> {
+        self.file_changes
+            .as_ref()?
+            .lock()
+            .ok()?
+            .take_external_change_notice()
+    }
+
+    fn observe_file_changes(&self, paths: &[PathBuf]) {
+        if let Some(Ok(mut tracker)) = self.file_changes.as_ref().map(|tracker| tracker.lock()) {
+            for path in paths {
+                tracker.observe(path);
+            }
+        }
+    }
+
+    fn with_file_snapshots<T>(
+        &self,
+        action: impl FnOnce(&mut FileSnapshotStore) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let snapshots = self
+            .file_snapshots
+            .as_ref()
+            .ok_or_else(|| "File snapshots are not enabled for this session".to_string())?;
+        let mut store = snapshots
+            .lock()
+            .map_err(|_| "file snapshot store lock poisoned".to_string())?;
+        action(&mut store)
+    }
+
+    /// Closes the run's change set. The files its tools wrote are re-baselined, so only later
+    /// edits to them are reported as file changes.
+    fn commit_file_snapshots(&self) {
+        if let Some(snapshots) = &self.file_snapshots {
+            if let Ok(mut store) = snapshots.lock() {
+                self.observe_file_changes(&store.pending_paths());
+                store.commit_pending();
+            }
+        }
+    }
+
+    /// Forgets snapshots of a run that failed before its messages were persisted.
+    fn discard_file_snapshots(&self) {
+        if let Some(snapshots) = &self.file_snapshots {
+            if let Ok(mut store) = snapshots.lock() {
+                self.observe_file_changes(&store.pending_paths());
+                store.discard_pending();
+            }
+        }
+    }
+
     pub fn resume(&mut self, target: Option<&str>) -> Result<PathBuf, String> {
         let target_path = self
             .resume_service
             .resolve_resume_session_target(target, self.session_manager.session_file().cloned())?;
         let loaded = SessionManager::load(&target_path)?;
         self.session_manager = loaded;
+        self.session_start_fired = false;
+        self.title_attempted = false;
         self.sync_model_from_session_state();
         Ok(target_path)
     }
@@ -195,6 +799,31 @@ impl AgentSession {
             .cloned()
             .ok_or_else(|| "session manager did not return session file path".to_string())?;
         self.session_manager = manager;
+        self.session_start_fired = false;
+        self.title_attempted = false;
+        Ok(new_path)
+    }
+
+    pub fn fork_session(&mut self, name: Option<&str>) -> Result<PathBuf, String> {
+        let current_session_path = self
+            .session_manager
+            .session_file()
+            .cloned()
+            .ok_or_else(|| "Current session file unavailable; cannot fork session".to_string())?;
+        let session_dir = current_session_path.parent().ok_or_else(|| {
+            format!(
+                "Cannot determine session directory from {}",
+                current_session_path.display()
+            )
+        })?;
+        let manager = self.session_manager.fork(session_dir, name)?;
+        let new_path = manager
+            .session_file()
+            .cloned()
+            .ok_or_else(|| "session manager did not return session file path".to_string())?;
+        self.session_manager = manager;
+        self.session_start_fired = false;
+        self.title_attempted = false;
         Ok(new_path)
     }
 
@@ -250,6 +879,11 @@ impl AgentSession {
         &self.model_catalog
     }
 
+    /// Token and cost totals for the current branch, read from the session file.
+    pub fn cost_report(&self) -> SessionCostReport {
+        SessionCostReport::from_messages(&self.session_manager.current_path_messages())
+    }
+
     pub fn set_auto_compaction_config(&mut self, config: AutoCompactionConfig) {
         self.auto_compaction = config;
     }
@@ -262,6 +896,108 @@ impl AgentSession {
         self.retry_config = retry_config;
     }
 
+    pub fn tool_parallelism(&self) -> usize {
+        self.tool_parallelism
+    }
+
+    pub fn set_tool_parallelism(&mut self, tool_parallelism: usize) {
+        self.tool_parallelism = tool_parallelism.max(1);
+    }
+
+    pub fn tool_policy(&self) -> Option<&ToolPolicy> {
+        self.tool_policy.as_ref()
+    }
+
+    /// Restricts which tools this session and its subagents may run and what they may touch.
+    /// Relative paths are checked against the session's working directory unless the policy
+    /// names another.
+    pub fn set_tool_policy(&mut self, tool_policy: Option<ToolPolicy>) {
+        self.tool_policy = tool_policy.map(|mut policy| {
+            policy
+                .cwd
+                .get_or_insert_with(|| PathBuf::from(self.session_manager.cwd()));
+            policy
+        });
+        self.update_child_controls(|controls| controls.tool_policy = self.tool_policy.clone());
+    }
+
+    fn set_child_controls(&mut self, child_controls: SharedChildSessionControls) {
+        self.child_controls = Some(child_controls);
+        self.update_child_controls(|controls| {
+            *controls = ChildSessionControls {
+                tool_policy: self.tool_policy.clone(),
+                approval_tools: self.approval_tools.clone(),
+                tool_approver: self.tool_approver.clone(),
+            }
+        });
+    }
+
+    fn update_child_controls(&self, update: impl FnOnce(&mut ChildSessionControls)) {
+        if let Some(child_controls) = &self.child_controls {
+            update(
+                &mut child_controls
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            );
+        }
+    }
+
+    /// Stops runs that use too many tool iterations or keep repeating the same calls.
+    pub fn set_loop_guard(&mut self, loop_guard: Option<ToolLoopGuard>) {
+        self.loop_guard = loop_guard.filter(ToolLoopGuard::is_enabled);
+    }
+
+    /// Handle for queueing messages into runs of this session, usable while a run streams.
+    pub fn queued_messages(&self) -> QueuedMessages {
+        self.queued_messages.clone()
+    }
+
+    /// Request, tool and token metrics of the runs finished so far.
+    pub fn metrics_snapshot(&self) -> AgentRunMetrics {
+        self.run_metrics.clone()
+    }
+
+    pub fn approval_tools(&self) -> &HashMap<String, ToolRisk> {
+        &self.approval_tools
+    }
+
+    pub fn set_approval_tools(&mut self, approval_tools: HashMap<String, ToolRisk>) {
+        self.approval_tools = approval_tools;
+        self.update_child_controls(|controls| {
+            controls.approval_tools = self.approval_tools.clone();
+        });
+    }
+
+    /// Sends calls to flagged tools to `approver` before they run, replacing any previous
+    /// approver. Returns `false` when no tool needs approval in this session.
+    pub fn attach_tool_approver(
+        &mut self,
+        approver: impl Fn(ToolApprovalRequest) + Send + Sync + 'static,
+    ) -> bool {
+        if self.approval_tools.is_empty() {
+            return false;
+        }
+        self.tool_approver = Some(Arc::new(approver));
+        self.update_child_controls(|controls| {
+            controls.tool_approver = self.tool_approver.clone();
+        });
+        true
+    }
+
+    /// Which tools wait for approval and who is asked. Without an attached approver, as in
+    /// headless and `mcp-serve` runs, the flagged tools are refused.
+    pub fn tool_approval(&self) -> Option<ToolApproval> {
+        (!self.approval_tools.is_empty()).then(|| ToolApproval {
+            dangerous_tools: self.approval_tools.clone(),
+            approver: self.tool_approver.clone(),
+        })
+    }
+
+    /// Tools of act mode, wrapped with the session's hooks, redaction and output limits.
+    pub fn tools(&self) -> &[AgentTool] {
+        &self.act_tools
+    }
+
     pub fn current_mode(&self) -> AgentMode {
         self.mode
     }
@@ -343,7 +1079,16 @@ impl AgentSession {
     }
 
     pub async fn prompt(&mut self, input: &str) -> Result<Vec<AgentMessage>, String> {
-        self.prompt_internal(input, true).await
+        self.prompt_internal(input, None, true).await
+    }
+
+    /// Like [`Self::prompt`], stopping the run when `abort_signal` is aborted.
+    pub async fn prompt_with_abort(
+        &mut self,
+        input: &str,
+        abort_signal: Option<AgentAbortSignal>,
+    ) -> Result<Vec<AgentMessage>, String> {
+        self.prompt_internal(input, abort_signal, true).await
     }
 
     pub async fn prompt_streaming<F>(
@@ -399,9 +1144,10 @@ impl AgentSession {
     async fn prompt_internal(
         &mut self,
         input: &str,
+        abort_signal: Option<AgentAbortSignal>,
         allow_overflow_retry: bool,
     ) -> Result<Vec<AgentMessage>, String> {
-        let mut produced = self.run_prompt_once(input).await?;
+        let mut produced = self.run_prompt_once(input, abort_signal).await?;
         if allow_overflow_retry {
             if let Some(mut retry_messages) =
                 self.maybe_handle_overflow_and_retry(&produced).await?
@@ -412,21 +1158,41 @@ impl AgentSession {
         Ok(produced)
     }
 
-    async fn run_prompt_once(&mut self, input: &str) -> Result<Vec<AgentMessage>, String> {
-        let input = self.apply_before_user_message_hooks(input);
+    async fn run_prompt_once(
+        &mut self,
+        input: &str,
+        abort_signal: Option<AgentAbortSignal>,
+    ) -> Result<Vec<AgentMessage>, String> {
+        self.ensure_session_started().await;
+        self.reload_skills_if_changed();
+        self.refresh_prompt_variables();
+        self.prepare_tool_wrappers();
+        let telemetry_turn = self.start_telemetry_turn("prompt");
+        let mut input = self.apply_before_user_message_hooks(input);
+        if let Some(notice) = self.take_external_change_notice() {
+            input = format!("{notice}\n\n{input}");
+        }
         let prompt = Message::User {
             content: UserContent::Text(input),
             timestamp: now_millis(),
         };
 
         let context = self.agent_context_from_session();
-        let stream = agent_loop(vec![prompt], context, self.loop_config(), None);
-        let produced = stream
-            .result()
+        let (abort_signal, turn_limit) = self.begin_turn_limit(abort_signal);
+        let stream = agent_loop(
+            vec![prompt],
+            context,
+            self.run_loop_config(turn_limit.as_ref()),
+            abort_signal,
+        );
+        let mut produced = finish_agent_loop(stream, &mut self.run_metrics)
             .await
-            .ok_or_else(|| "Agent loop ended without a final result".to_string())?;
+            .inspect_err(|_| self.discard_file_snapshots())?;
+        self.finish_turn_limit(turn_limit);
 
-        self.persist_messages_and_maybe_compact(&produced).await?;
+        self.persist_messages_and_maybe_compact(&mut produced)
+            .await?;
+        self.finish_telemetry_turn(telemetry_turn, &produced).await;
         Ok(produced)
     }
 
@@ -437,10 +1203,33 @@ impl AgentSession {
         abort_signal: Option<AgentAbortSignal>,
         on_update: Option<&mut dyn FnMut(AgentSessionStreamUpdate)>,
     ) -> Result<Vec<AgentMessage>, String> {
-        let input = self.apply_before_user_message_hooks(input);
+        self.ensure_session_started().await;
+        self.reload_skills_if_changed();
+        self.refresh_prompt_variables();
+        self.prepare_tool_wrappers();
+        let telemetry_turn = self.start_telemetry_turn("prompt");
+        let mut input = self.apply_before_user_message_hooks(input);
+        let notice = self.take_external_change_notice();
         let content = match blocks {
-            Some(blocks) => UserContent::Blocks(blocks),
-            None => UserContent::Text(input),
+            Some(mut blocks) => {
+                if let Some(notice) = notice {
+                    blocks.insert(
+                        0,
+                        UserContentBlock::Text {
+                            text: notice,
+                            text_signature: None,
+                            cache: None,
+                        },
+                    );
+                }
+                UserContent::Blocks(blocks)
+            }
+            None => {
+                if let Some(notice) = notice {
+                    input = format!("{notice}\n\n{input}");
+                }
+                UserContent::Text(input)
+            }
         };
         let prompt = Message::User {
             content,
@@ -448,13 +1237,34 @@ impl AgentSession {
         };
 
         let context = self.agent_context_from_session();
-        let stream = agent_loop(vec![prompt], context, self.loop_config(), abort_signal);
-        let produced = collect_agent_loop_result(stream, on_update, &self.stream_renderer).await?;
+        let (abort_signal, turn_limit) = self.begin_turn_limit(abort_signal);
+        let stream = agent_loop(
+            vec![prompt],
+            context,
+            self.run_loop_config(turn_limit.as_ref()),
+            abort_signal,
+        );
+        let mut produced = collect_agent_loop_result(
+            stream,
+            on_update,
+            &self.stream_renderer,
+            self.subagent_progress.as_mut(),
+            &mut self.run_metrics,
+        )
+        .await
+        .inspect_err(|_| self.discard_file_snapshots())?;
+        self.finish_turn_limit(turn_limit);
 
-        self.persist_messages_and_maybe_compact(&produced).await?;
+        self.persist_messages_and_maybe_compact(&mut produced)
+            .await?;
+        self.finish_telemetry_turn(telemetry_turn, &produced).await;
         Ok(produced)
     }
 
+    fn finish_turn_limit(&mut self, turn_limit: Option<TurnLimit>) {
+        self.turn_limit_reached = turn_limit.is_some_and(|turn_limit| turn_limit.reached());
+    }
+
     fn apply_before_user_message_hooks(&self, input: &str) -> String {
         let mut ctx = BeforeUserMessageHookContext {
             message: input.to_string(),
@@ -517,22 +1327,39 @@ impl AgentSession {
     }
 
     async fn run_continue_once(&mut self) -> Result<Vec<AgentMessage>, String> {
+        self.ensure_session_started().await;
+        self.reload_skills_if_changed();
+        self.refresh_prompt_variables();
+        self.prepare_tool_wrappers();
+        let telemetry_turn = self.start_telemetry_turn("continue");
         let context = self.agent_context_from_session();
         if context.messages.is_empty() {
             return Err("No messages to continue from".to_string());
         }
 
+        let (abort_signal, turn_limit) = self.begin_turn_limit(None);
         let stream = if matches!(context.messages.last(), Some(Message::Assistant { .. })) {
-            agent_loop(vec![], context, self.loop_config(), None)
+            agent_loop(
+                vec![],
+                context,
+                self.run_loop_config(turn_limit.as_ref()),
+                abort_signal,
+            )
         } else {
-            agent_loop_continue(context, self.loop_config(), None)
+            agent_loop_continue(
+                context,
+                self.run_loop_config(turn_limit.as_ref()),
+                abort_signal,
+            )
         };
-        let produced = stream
-            .result()
+        let mut produced = finish_agent_loop(stream, &mut self.run_metrics)
             .await
-            .ok_or_else(|| "Agent loop ended without a final result".to_string())?;
+            .inspect_err(|_| self.discard_file_snapshots())?;
+        self.finish_turn_limit(turn_limit);
 
-        self.persist_messages_and_maybe_compact(&produced).await?;
+        self.persist_messages_and_maybe_compact(&mut produced)
+            .await?;
+        self.finish_telemetry_turn(telemetry_turn, &produced).await;
         Ok(produced)
     }
 
@@ -541,19 +1368,45 @@ impl AgentSession {
         abort_signal: Option<AgentAbortSignal>,
         on_update: Option<&mut dyn FnMut(AgentSessionStreamUpdate)>,
     ) -> Result<Vec<AgentMessage>, String> {
+        self.ensure_session_started().await;
+        self.reload_skills_if_changed();
+        self.refresh_prompt_variables();
+        self.prepare_tool_wrappers();
+        let telemetry_turn = self.start_telemetry_turn("continue");
         let context = self.agent_context_from_session();
         if context.messages.is_empty() {
             return Err("No messages to continue from".to_string());
         }
 
+        let (abort_signal, turn_limit) = self.begin_turn_limit(abort_signal);
         let stream = if matches!(context.messages.last(), Some(Message::Assistant { .. })) {
-            agent_loop(vec![], context, self.loop_config(), abort_signal)
+            agent_loop(
+                vec![],
+                context,
+                self.run_loop_config(turn_limit.as_ref()),
+                abort_signal,
+            )
         } else {
-            agent_loop_continue(context, self.loop_config(), abort_signal)
+            agent_loop_continue(
+                context,
+                self.run_loop_config(turn_limit.as_ref()),
+                abort_signal,
+            )
         };
-        let produced = collect_agent_loop_result(stream, on_update, &self.stream_renderer).await?;
+        let mut produced = collect_agent_loop_result(
+            stream,
+            on_update,
+            &self.stream_renderer,
+            self.subagent_progress.as_mut(),
+            &mut self.run_metrics,
+        )
+        .await
+        .inspect_err(|_| self.discard_file_snapshots())?;
+        self.finish_turn_limit(turn_limit);
 
-        self.persist_messages_and_maybe_compact(&produced).await?;
+        self.persist_messages_and_maybe_compact(&mut produced)
+            .await?;
+        self.finish_telemetry_turn(telemetry_turn, &produced).await;
         Ok(produced)
     }
 
@@ -587,6 +1440,7 @@ impl AgentSession {
         {
             self.current_model_index = index;
             self.config.model = self.model_catalog[index].clone();
+            self.rebuild_system_prompt();
         }
     }
 
@@ -600,7 +1454,10 @@ impl AgentSession {
             .session_manager
             .append_model_change(&model.provider, &model.id)
         {
-            Ok(_) => Ok(model),
+            Ok(_) => {
+                self.rebuild_system_prompt();
+                Ok(model)
+            }
             Err(error) => {
                 self.current_model_index = previous_index;
                 self.config.model = previous_model;
@@ -694,6 +1551,14 @@ impl AgentSession {
         }
     }
 
+    fn run_loop_config(&self, turn_limit: Option<&TurnLimit>) -> AgentLoopConfig {
+        let mut config = self.loop_config();
+        if let Some(turn_limit) = turn_limit {
+            config.stream_fn = turn_limit.wrap_stream_fn(config.stream_fn);
+        }
+        config
+    }
+
     fn loop_config(&self) -> AgentLoopConfig {
         let fallback_models = self
             .model_catalog
@@ -713,8 +1578,20 @@ impl AgentSession {
             convert_to_llm: Arc::new(IdentityMessageConverter),
             stream_fn: self.config.stream_fn.clone(),
             retry: self.retry_config.clone(),
-            get_steering_messages: None,
-            get_follow_up_messages: None,
+            tool_parallelism: self.tool_parallelism,
+            tool_validation: Some(ToolValidationOptions::lenient()),
+            get_steering_messages: Some(self.queued_messages.steering_queue()),
+            get_follow_up_messages: Some(self.queued_messages.follow_up_queue()),
+            turn_timeout: None,
+            run_timeout: None,
+            tool_policy: self.tool_policy.clone(),
+            tool_approval: self.tool_approval(),
+            loop_guard: self.loop_guard.clone(),
+            hooks: self
+                .file_changes
+                .clone()
+                .map(|tracker| Arc::new(FileChangeNotices::new(tracker)) as Arc<dyn AgentHooks>),
+            context_compaction: None,
         }
     }
 
@@ -727,13 +1604,70 @@ impl AgentSession {
         }
     }
 
+    /// Estimated prompt tokens for sending the current session to the active model.
+    pub fn estimate_context_tokens(&self) -> u64 {
+        let context = self.agent_context_from_session();
+        LlmContext {
+            system_prompt: Some(context.system_prompt),
+            system_prompt_cache: None,
+            branch: None,
+            messages: context.messages,
+            tools: Some(
+                context
+                    .tools
+                    .iter()
+                    .map(|tool| tool.to_llm_tool())
+                    .collect(),
+            ),
+        }
+        .estimate_tokens(&self.config.model)
+    }
+
+    /// Estimated context tokens alongside the active model's context window.
+    pub fn context_usage(&self) -> (u64, u64) {
+        (
+            self.estimate_context_tokens(),
+            self.config.model.context_window as u64,
+        )
+    }
+
+    fn price_assistant_messages(&self, produced: &mut [AgentMessage]) {
+        for message in produced {
+            let Message::Assistant {
+                provider,
+                model,
+                usage,
+                ..
+            } = message
+            else {
+                continue;
+            };
+            let pricing =
+                if self.config.model.provider == *provider && self.config.model.id == *model {
+                    model_pricing(&self.config.model)
+                } else {
+                    self.model_catalog
+                        .iter()
+                        .find(|candidate| candidate.provider == *provider && candidate.id == *model)
+                        .and_then(model_pricing)
+                        .or_else(|| lookup_model_pricing(model))
+                };
+            if let Some(pricing) = pricing {
+                apply_pricing(usage, &pricing);
+            }
+        }
+    }
+
     async fn persist_messages_and_maybe_compact(
         &mut self,
-        produced: &[AgentMessage],
+        produced: &mut [AgentMessage],
     ) -> Result<(), String> {
-        for message in produced {
+        self.commit_file_snapshots();
+        self.price_assistant_messages(produced);
+        for message in produced.iter() {
             self.session_manager.append_message(message.clone())?;
         }
+        self.maybe_generate_title().await;
         let _ = self.maybe_auto_compact(produced).await?;
         Ok(())
     }
@@ -809,11 +1743,13 @@ impl AgentSession {
             return Ok(None);
         }
 
-        let Some(context_tokens) = self
+        // Providers that stream without usage leave nothing to go on, so estimate instead.
+        let context_tokens = match self
             .compaction_service
             .latest_context_tokens_from_messages(produced)
-        else {
-            return Ok(None);
+        {
+            Some(tokens) if tokens > 0 => tokens,
+            _ => self.estimate_context_tokens(),
         };
 
         let context_window = self.config.model.context_window as u64;
@@ -880,7 +1816,7 @@ impl AgentSession {
             return Err("No messages available for summarization".to_string());
         }
 
-        let conversation = serialize_messages_for_summary(messages_to_summarize);
+        let conversation = pixy_ai::transcript(messages_to_summarize);
         if conversation.trim().is_empty() {
             return Err("No textual content to summarize".to_string());
         }
@@ -888,36 +1824,91 @@ impl AgentSession {
         let prompt = format!(
             "Context tokens before compaction: {context_tokens}/{context_window}.\n\n<conversation>\n{conversation}\n</conversation>\n\n{AUTO_COMPACTION_SUMMARIZATION_PROMPT}"
         );
-        let summary_context = LlmContext {
-            system_prompt: Some(AUTO_COMPACTION_SUMMARIZATION_SYSTEM_PROMPT.to_string()),
+        self.complete_text(
+            AUTO_COMPACTION_SUMMARIZATION_SYSTEM_PROMPT,
+            prompt,
+            "Compaction summary",
+        )
+        .await
+    }
+
+    /// Titles the session once it has a completed exchange. Failures are logged and not
+    /// retried, so a model that cannot produce a title costs at most one request per session.
+    async fn maybe_generate_title(&mut self) {
+        if !self.auto_title || self.title_attempted || self.session_title().is_some() {
+            return;
+        }
+        let messages = self.session_manager.current_path_messages();
+        let answered = messages.iter().any(|message| {
+            matches!(
+                message,
+                Message::Assistant { stop_reason, .. }
+                    if !matches!(stop_reason, StopReason::Error | StopReason::Aborted)
+            )
+        });
+        if !answered {
+            return;
+        }
+        self.title_attempted = true;
+
+        let conversation = pixy_ai::transcript(&messages);
+        let conversation = truncate_chars(&conversation, SESSION_TITLE_MAX_CONVERSATION_CHARS);
+        let prompt =
+            format!("<conversation>\n{conversation}\n</conversation>\n\n{SESSION_TITLE_PROMPT}");
+        let title = match self
+            .complete_text(SESSION_TITLE_SYSTEM_PROMPT, prompt, "Session title")
+            .await
+        {
+            Ok(text) => clean_session_title(&text),
+            Err(error) => {
+                tracing::warn!(%error, "session title generation failed");
+                return;
+            }
+        };
+        let Some(title) = title else {
+            return;
+        };
+        if let Err(error) = self.session_manager.set_title(&title) {
+            tracing::warn!(%error, "storing session title failed");
+        }
+    }
+
+    /// Runs a single tool-less request against the current model and returns its text.
+    async fn complete_text(
+        &self,
+        system_prompt: &str,
+        prompt: String,
+        label: &str,
+    ) -> Result<String, String> {
+        let context = LlmContext {
+            system_prompt: Some(system_prompt.to_string()),
             messages: vec![Message::User {
                 content: UserContent::Text(prompt),
                 timestamp: now_millis(),
             }],
             tools: None,
+            system_prompt_cache: None,
+            branch: None,
         };
 
         let stream_fn = self.config.stream_fn.clone();
         let model = self.config.model.clone();
         let response = stream_fn
-            .stream(model, summary_context, None)
+            .stream(model, context, None)
             .map_err(|error| error.as_compact_json())?;
 
-        let summary_message = response
+        let message = response
             .result()
             .await
-            .ok_or_else(|| "Compaction summary stream ended without final result".to_string())?;
+            .ok_or_else(|| format!("{label} stream ended without final result"))?;
 
-        if matches!(
-            summary_message.stop_reason,
-            StopReason::Error | StopReason::Aborted
-        ) {
-            return Err(summary_message
+        if matches!(message.stop_reason, StopReason::Error | StopReason::Aborted) {
+            return Err(message
                 .error_message
-                .unwrap_or_else(|| "Compaction summary model returned an error".to_string()));
+                .unwrap_or_else(|| format!("{label} model returned an error")));
         }
 
-        let summary = summary_message
+        let text = message
             .content
             .iter()
             .filter_map(|block| match block {
@@ -927,11 +1918,11 @@ impl AgentSession {
             .collect::<Vec<_>>()
             .join("\n");
 
-        if summary.trim().is_empty() {
-            return Err("Compaction summary model returned empty text".to_string());
+        if text.trim().is_empty() {
+            return Err(format!("{label} model returned empty text"));
         }
 
-        Ok(summary)
+        Ok(text)
     }
 }
 
@@ -1022,25 +2013,85 @@ pub fn create_session_from_runtime(
     let mut extra_tools = Vec::new();
     if !no_tools && runtime.memory.search.enabled {
         if let Some(memory_runtime) = &session_memory_runtime {
-            extra_tools.push(create_memory_tool(
+            let semantic_index = runtime.memory.search.embedding.clone().map(|embedding| {
+                let embedder = PixyAiMemoryEmbedder::new(embedding.model, embedding.api_key);
+                Arc::new(SemanticMemoryIndex::new(
+                    Arc::new(embedder),
+                    &runtime.memory.dir,
+                ))
+            });
+            extra_tools.push(create_memory_tool_with_semantic_index(
                 memory_runtime.manager.clone(),
+                semantic_index,
                 runtime.memory.search.max_results,
                 runtime.memory.search.min_score,
             ));
         }
     }
 
+    let background_processes = (!no_tools).then(BackgroundProcesses::new);
+    let persistent_shell = (!no_tools).then(PersistentShell::new);
+    if let Some(shell) = &persistent_shell {
+        extra_tools.push(create_shell_tool(cwd, shell.clone()));
+    }
+    if let Some(processes) = &background_processes {
+        extra_tools.push(create_bash_background_tool(cwd, processes.clone()));
+        extra_tools.push(create_read_image_tool(cwd));
+        extra_tools.push(create_todo_tool());
+    }
+    if !no_tools {
+        let mcp = load_mcp_tools(cwd, &runtime.mcp);
+        for error in &mcp.errors {
+            eprintln!("warning: {error}, its tools are disabled");
+        }
+        extra_tools.extend(mcp.tools);
+        extra_tools.extend(create_web_tools(Some(&runtime.web_search)));
+        if in_git_repository(cwd) {
+            extra_tools.extend(create_git_tools(cwd));
+        }
+    }
+
+    let file_snapshots = (!no_tools).then(|| Arc::new(Mutex::new(FileSnapshotStore::new(cwd))));
+    let file_changes = (!no_tools).then(|| Arc::new(Mutex::new(FileChangeTracker::new(cwd))));
+    let diff_review = (!no_tools && runtime.review.enabled).then(|| Arc::new(DiffReview::new()));
+    let lifecycle_hooks = (!runtime.hooks.is_empty())
+        .then(|| Arc::new(LifecycleHooks::new(cwd, runtime.hooks.clone())))
+        .filter(|hooks| !hooks.is_empty());
+    let post_edit = PostEditChecks::new(cwd, &runtime.post_edit)
+        .map(|checks| Arc::new(checks.with_file_changes(file_changes.clone())));
+    let secret_redactor = SecretRedactor::from_config(&runtime.redaction).map(Arc::new);
+    let tool_output = Arc::new(ToolOutputLimiter::new(runtime.tool_output.clone()));
+    let tool_failures = ToolFailureFeedback::from_config(&runtime.tool_failures).map(Arc::new);
+    let telemetry = SessionTelemetry::from_config(&runtime.telemetry).map(Arc::new);
     let mut child_tools = if no_tools {
         vec![]
     } else {
-        create_coding_tools_with_extra(cwd, extra_tools)
+        let mut tools = create_coding_tools_with_snapshots(
+            cwd,
+            file_snapshots.clone(),
+            file_changes.clone(),
+            diff_review.clone(),
+        );
+        tools.append(&mut extra_tools);
+        tools
     };
     let runtime_api_key = runtime.api_key.clone();
     let runtime_provider_api_keys = runtime.provider_api_keys.clone();
     let runtime_default_provider = runtime.model.provider.clone();
-    let stream_fn = Arc::new(
+    let content_guards = runtime.guards.guards(secret_redactor.as_ref());
+    let sampling = runtime.sampling.clone();
+    let stream_transport = runtime.stream_transport.clone();
+    let stream_fn: StreamFn = Arc::new(
         move |model: Model, context: pixy_ai::Context, options: Option<SimpleStreamOptions>| {
             let mut resolved_options = options.unwrap_or_default();
+            resolved_options
+                .stream
+                .guards
+                .extend(content_guards.iter().cloned());
+            sampling.apply(&mut resolved_options.stream);
+            if resolved_options.stream.stream_transport.is_none() {
+                resolved_options.stream.stream_transport = Some(stream_transport.clone());
+            }
             if resolved_options.stream.api_key.is_none() {
                 resolved_options.stream.api_key = resolve_runtime_api_key_for_model(
                     &model.provider,
@@ -1052,10 +2103,19 @@ pub fn create_session_from_runtime(
             pixy_ai::stream_simple(model, context, Some(resolved_options))
         },
     );
+    let stream_fn = match &telemetry {
+        Some(telemetry) => telemetry.wrap_stream_fn(stream_fn),
+        None => stream_fn,
+    };
     let (merged_plugins, plugin_merge_error) =
         if runtime.multi_agent.enabled && !runtime.multi_agent.plugin_paths.is_empty() {
             match load_and_merge_plugins(&runtime.multi_agent.plugin_paths) {
-                Ok(merged) => (merged, None),
+                Ok(merged) => {
+                    for warning in &merged.warnings {
+                        eprintln!("warning: skipped plugin: {warning}");
+                    }
+                    (merged, None)
+                }
                 Err(error) => (MergedPluginConfig::default(), Some(error)),
             }
         } else {
@@ -1074,11 +2134,34 @@ pub fn create_session_from_runtime(
             Arc::new(MultiAgentPluginRuntime::default())
         }
     };
-    apply_before_tool_definition_hooks(plugin_runtime.as_ref(), &mut child_tools);
+    let child_controls = SharedChildSessionControls::default();
+    let tool_wrappers = SessionToolWrappers {
+        plugin_runtime: plugin_runtime.clone(),
+        post_edit: post_edit.clone(),
+        lifecycle_hooks: lifecycle_hooks.clone(),
+        secret_redactor: secret_redactor.clone(),
+        tool_output: tool_output.clone(),
+        tool_failures: tool_failures.clone(),
+        telemetry: telemetry.clone(),
+    };
+    child_tools = child_tools
+        .into_iter()
+        .map(|tool| tool_wrappers.wrap(tool))
+        .collect();
 
     let mut tools = child_tools.clone();
     let mut prompt_subagents = vec![];
-    if !no_tools && runtime.multi_agent.enabled {
+    let mut subagent_progress = None;
+    let project_subagents = if no_tools {
+        LoadProjectSubAgentsResult::default()
+    } else {
+        load_project_subagents(&cwd.join(PROJECT_AGENTS_DIR))
+    };
+    for warning in &project_subagents.warnings {
+        eprintln!("warning: skipped project subagent: {warning}");
+    }
+    // Project agent files enable the task tool on their own, without `[multi_agent]` config.
+    if !no_tools && (runtime.multi_agent.enabled || !project_subagents.subagents.is_empty()) {
         let configured_subagents = runtime
             .multi_agent
             .agents
@@ -1103,6 +2186,22 @@ pub fn create_session_from_runtime(
             }
         }
 
+        if init_error.is_none() {
+            for project_spec in &project_subagents.subagents {
+                match registry_builder
+                    .register_project_subagent_mut(&project_spec.path, project_spec.spec.clone())
+                {
+                    Ok(()) => {
+                        effective_subagents.push(project_spec.spec.clone());
+                    }
+                    Err(error) => {
+                        init_error = Some(error);
+                        break;
+                    }
+                }
+            }
+        }
+
         if init_error.is_none() {
             if let Some(error) = plugin_merge_error.clone() {
                 init_error = Some(error);
@@ -1133,6 +2232,8 @@ pub fn create_session_from_runtime(
             );
         } else if !effective_subagents.is_empty() {
             let registry = registry_builder.build();
+            let (progress_tx, progress_rx) = unbounded_channel();
+            subagent_progress = Some(progress_rx);
             let dispatch_parent_session_id = parent_session_id.clone();
             let dispatch_parent_session_dir = parent_session_dir.clone();
             let dispatcher = TaskDispatcher::new(TaskDispatcherConfig {
@@ -1146,28 +2247,63 @@ pub fn create_session_from_runtime(
                     cwd,
                     &child_tools,
                     &runtime.skills,
+                    Some(&runtime.model),
                 ),
                 stream_fn: stream_fn.clone(),
-                child_tools: child_tools.clone(),
+                // Children get a shell of their own instead of sharing the parent's.
+                child_tools: child_tools
+                    .iter()
+                    .filter(|tool| tool.name != "shell")
+                    .cloned()
+                    .collect(),
+                child_shell_tool: persistent_shell.is_some().then(|| {
+                    let cwd = cwd.to_path_buf();
+                    let tool_wrappers = tool_wrappers.clone();
+                    Arc::new(move |shell| tool_wrappers.wrap(create_shell_tool(&cwd, shell)))
+                        as ChildShellToolFn
+                }),
+                child_controls: child_controls.clone(),
                 subagent_registry: Arc::new(registry),
                 session_store: Arc::new(tokio::sync::Mutex::new(ChildSessionStore::new(
                     dispatch_parent_session_id,
                 ))),
                 dispatch_policy: merged_policy,
                 plugin_runtime: plugin_runtime.clone(),
-                lifecycle_event_sink: Some(Arc::new(log_parent_child_run_event)),
+                lifecycle_event_sink: Some(Arc::new(move |event: ParentChildRunEvent| {
+                    let _ = progress_tx.send(format_child_run_progress(&event));
+                    log_parent_child_run_event(event);
+                })),
             });
             let mut task_tool = create_task_tool(Arc::new(dispatcher));
             apply_before_tool_definition_hooks(
                 plugin_runtime.as_ref(),
                 std::slice::from_mut(&mut task_tool),
             );
+            if let Some(hooks) = &lifecycle_hooks {
+                task_tool = hooks.wrap_tool(task_tool);
+            }
+            if let Some(redactor) = &secret_redactor {
+                task_tool = redactor.wrap_tool(task_tool);
+            }
+            task_tool = tool_output.wrap_tool(task_tool);
+            if let Some(tool_failures) = &tool_failures {
+                task_tool = tool_failures.wrap_tool(task_tool);
+            }
+            if let Some(telemetry) = &telemetry {
+                task_tool = telemetry.wrap_tool(task_tool);
+            }
             tools.push(task_tool);
             prompt_subagents = effective_subagents;
         }
     }
 
-    let mut system_prompt = build_system_prompt(custom_system_prompt, cwd, &tools, &runtime.skills);
+    let mut system_prompt = build_system_prompt(
+        custom_system_prompt,
+        cwd,
+        &tools,
+        &runtime.skills,
+        Some(&runtime.model),
+    );
     append_multi_agent_prompt_section(&mut system_prompt, &tools, &prompt_subagents);
 
     let config = AgentSessionConfig {
@@ -1179,12 +2315,86 @@ pub fn create_session_from_runtime(
     let mut session = AgentSession::new(session_manager, config);
     session.set_multi_agent_plugin_runtime(plugin_runtime);
     session.set_memory_runtime(session_memory_runtime);
+    session.set_subagent_progress(subagent_progress);
+    session.set_file_snapshots(file_snapshots);
+    session.set_file_changes(file_changes);
+    session.set_diff_review(diff_review);
+    if !no_tools {
+        session.set_approval_tools(runtime.approval.tools.clone());
+    }
+    session.set_loop_guard(Some(runtime.loop_guard.clone()));
+    session.set_tool_parallelism(runtime.tool_parallelism);
+    session.set_background_processes(background_processes);
+    session.set_persistent_shell(persistent_shell);
+    session.set_lifecycle_hooks(lifecycle_hooks);
+    session.set_tool_output_limiter(Some(tool_output));
+    session.set_tool_failure_feedback(tool_failures);
+    session.set_telemetry(telemetry);
+    session.set_child_controls(child_controls);
+    session.set_project_memory(cwd, &runtime.project_memory);
+    session.set_skills(runtime.skill_options.clone().map(|options| SessionSkills {
+        catalog: SkillCatalog::from_loaded(
+            options,
+            LoadSkillsResult {
+                skills: runtime.skills.clone(),
+                diagnostics: runtime.skill_diagnostics.clone(),
+            },
+        ),
+    }));
+    session.set_prompt(Some(SessionPrompt {
+        custom_system_prompt: custom_system_prompt.map(ToOwned::to_owned),
+        cwd: cwd.to_path_buf(),
+        subagents: prompt_subagents.clone(),
+        skills: runtime.skills.clone(),
+        variables: PromptVariables::current(cwd, Some(&runtime.model)),
+    }));
     if !runtime.model_catalog.is_empty() {
         session.set_model_catalog(runtime.model_catalog.clone());
     }
     session
 }
 
+/// The wrappers every tool of a session goes through, shared with the tools its subagents get.
+#[derive(Clone)]
+struct SessionToolWrappers {
+    plugin_runtime: Arc<MultiAgentPluginRuntime>,
+    post_edit: Option<Arc<PostEditChecks>>,
+    lifecycle_hooks: Option<Arc<LifecycleHooks>>,
+    secret_redactor: Option<Arc<SecretRedactor>>,
+    tool_output: Arc<ToolOutputLimiter>,
+    tool_failures: Option<Arc<ToolFailureFeedback>>,
+    telemetry: Option<Arc<SessionTelemetry>>,
+}
+
+impl SessionToolWrappers {
+    fn wrap(&self, mut tool: AgentTool) -> AgentTool {
+        apply_before_tool_definition_hooks(
+            self.plugin_runtime.as_ref(),
+            std::slice::from_mut(&mut tool),
+        );
+        // Formatters run first so hooks, redaction and limits all see their notes.
+        if let Some(post_edit) = &self.post_edit {
+            tool = post_edit.wrap_tool(tool);
+        }
+        if let Some(hooks) = &self.lifecycle_hooks {
+            tool = hooks.wrap_tool(tool);
+        }
+        // Redaction wraps the hooks so `after_tool` hooks still see the raw output.
+        if let Some(redactor) = &self.secret_redactor {
+            tool = redactor.wrap_tool(tool);
+        }
+        // Limits apply after redaction so spilled output files never hold unredacted secrets.
+        tool = self.tool_output.wrap_tool(tool);
+        if let Some(tool_failures) = &self.tool_failures {
+            tool = tool_failures.wrap_tool(tool);
+        }
+        if let Some(telemetry) = &self.telemetry {
+            tool = telemetry.wrap_tool(tool);
+        }
+        tool
+    }
+}
+
 fn apply_before_tool_definition_hooks(runtime: &MultiAgentPluginRuntime, tools: &mut [AgentTool]) {
     for tool in tools.iter_mut() {
         let mut ctx = BeforeToolDefinitionHookContext {
@@ -1196,6 +2406,45 @@ fn apply_before_tool_definition_hooks(runtime: &MultiAgentPluginRuntime, tools:
     }
 }
 
+fn format_child_run_progress(event: &ParentChildRunEvent) -> AgentSessionStreamUpdate {
+    let (task_id, subagent, line) = match event {
+        ParentChildRunEvent::ChildRunStart {
+            task_id, subagent, ..
+        } => (task_id, subagent, "started".to_string()),
+        ParentChildRunEvent::ChildToolStart {
+            task_id,
+            subagent,
+            headline,
+            ..
+        } => (task_id, subagent, headline.clone()),
+        ParentChildRunEvent::ChildRunEnd {
+            task_id,
+            subagent,
+            duration_ms,
+            ..
+        } => {
+            let total_seconds = duration_ms / 1000;
+            let line = format!(
+                "completed in {} m {} s",
+                total_seconds / 60,
+                total_seconds % 60
+            );
+            (task_id, subagent, line)
+        }
+        ParentChildRunEvent::ChildRunError {
+            task_id,
+            subagent,
+            error,
+            ..
+        } => (task_id, subagent, format!("failed: {error}")),
+    };
+    AgentSessionStreamUpdate::SubagentProgress {
+        task_id: task_id.clone(),
+        subagent: subagent.clone(),
+        line,
+    }
+}
+
 fn log_parent_child_run_event(event: ParentChildRunEvent) {
     match event {
         ParentChildRunEvent::ChildRunStart {
@@ -1246,34 +2495,185 @@ fn log_parent_child_run_event(event: ParentChildRunEvent) {
                 "child run failed"
             );
         }
+        ParentChildRunEvent::ChildToolStart {
+            parent_session_id,
+            child_session_file,
+            task_id,
+            subagent,
+            tool_name,
+            ..
+        } => {
+            tracing::debug!(
+                parent_session_id,
+                child_session_file,
+                task_id,
+                subagent,
+                tool_name,
+                "child tool started"
+            );
+        }
+    }
+}
+
+/// Enforces `max_turns` by counting assistant requests made through the loop's stream function.
+/// Once the cap is exceeded the run is aborted before the next request reaches the provider.
+/// The internal controller is a child of the external abort signal, if any, so aborting that
+/// still stops the run.
+struct TurnLimit {
+    max_turns: usize,
+    requests: Arc<AtomicUsize>,
+    reached: Arc<AtomicBool>,
+    controller: Arc<AgentAbortController>,
+}
+
+impl TurnLimit {
+    fn new(max_turns: usize, external_signal: Option<AgentAbortSignal>) -> Self {
+        let controller = external_signal
+            .map(|external_signal| external_signal.child_controller())
+            .unwrap_or_default();
+        Self {
+            max_turns,
+            requests: Arc::new(AtomicUsize::new(0)),
+            reached: Arc::new(AtomicBool::new(false)),
+            controller: Arc::new(controller),
+        }
+    }
+
+    fn signal(&self) -> AgentAbortSignal {
+        self.controller.signal()
+    }
+
+    fn reached(&self) -> bool {
+        self.reached.load(Ordering::SeqCst)
+    }
+
+    fn wrap_stream_fn(&self, inner: StreamFn) -> StreamFn {
+        let max_turns = self.max_turns;
+        let requests = self.requests.clone();
+        let reached = self.reached.clone();
+        let controller = self.controller.clone();
+        Arc::new(
+            move |model: Model, context: LlmContext, options: Option<SimpleStreamOptions>| {
+                if requests.fetch_add(1, Ordering::SeqCst) >= max_turns {
+                    reached.store(true, Ordering::SeqCst);
+                    controller.abort();
+                    let stream = AssistantMessageEventStream::new();
+                    stream.end(None);
+                    return Ok(stream);
+                }
+                inner.stream(model, context, options)
+            },
+        )
+    }
+}
+
+/// Runs a loop to completion without rendering it, keeping its metrics.
+async fn finish_agent_loop(
+    stream: pixy_ai::EventStream<AgentEvent, Vec<AgentMessage>>,
+    run_metrics: &mut AgentRunMetrics,
+) -> Result<Vec<AgentMessage>, String> {
+    while let Some(event) = stream.next().await {
+        if let AgentEvent::Metrics { metrics } = event {
+            run_metrics.merge(&metrics);
+        }
     }
+    stream
+        .result()
+        .await
+        .ok_or_else(|| "Agent loop ended without a final result".to_string())
 }
 
 async fn collect_agent_loop_result(
     stream: pixy_ai::EventStream<AgentEvent, Vec<AgentMessage>>,
     mut on_update: Option<&mut dyn FnMut(AgentSessionStreamUpdate)>,
     renderer: &StreamingToolLineRenderer,
+    subagent_progress: Option<&mut UnboundedReceiver<AgentSessionStreamUpdate>>,
+    run_metrics: &mut AgentRunMetrics,
 ) -> Result<Vec<AgentMessage>, String> {
     let mut saw_assistant_text_delta = false;
     let mut saw_assistant_thinking_delta = false;
     let mut thinking_buffer = String::new();
+    let mut tool_call_preview = String::new();
+    // Tool calls whose output was already streamed line by line; their final result text is
+    // not repeated.
+    let mut streamed_tool_calls = HashSet::new();
+    let mut subagent_progress = subagent_progress;
+    if let Some(progress) = subagent_progress.as_mut() {
+        while progress.try_recv().is_ok() {}
+    }
 
-    while let Some(event) = stream.next().await {
+    loop {
+        // Child progress arrives while the task tool is still executing, so it is interleaved with
+        // loop events rather than waiting for the tool result.
+        let next_progress = async {
+            match subagent_progress.as_mut() {
+                Some(progress) => progress.recv().await,
+                None => std::future::pending().await,
+            }
+        };
+        let event = tokio::select! {
+            event = stream.next() => event,
+            Some(update) = next_progress => {
+                if let Some(callback) = on_update.as_mut() {
+                    callback(update);
+                }
+                continue;
+            }
+        };
+        let Some(event) = event else {
+            break;
+        };
         match event {
             AgentEvent::MessageStart { message } => {
                 if matches!(message, Message::Assistant { .. }) {
                     saw_assistant_text_delta = false;
                     saw_assistant_thinking_delta = false;
                     thinking_buffer.clear();
+                    tool_call_preview.clear();
                 }
             }
             AgentEvent::ToolExecutionStart {
-                tool_name, args, ..
+                tool_call_id,
+                tool_name,
+                args,
             } => {
                 if let Some(callback) = on_update.as_mut() {
                     callback(AgentSessionStreamUpdate::ToolLine(
                         renderer.format_tool_start_line(&tool_name, &args),
                     ));
+                    callback(AgentSessionStreamUpdate::ToolCallStart {
+                        tool_call_id,
+                        tool_name,
+                        args,
+                    });
+                }
+            }
+            AgentEvent::ToolExecutionEnd {
+                tool_call_id,
+                tool_name,
+                result,
+                is_error,
+                ..
+            } => {
+                if let Some(callback) = on_update.as_mut() {
+                    callback(AgentSessionStreamUpdate::ToolCallEnd {
+                        tool_call_id,
+                        tool_name,
+                        content: result.content,
+                        is_error,
+                    });
+                }
+            }
+            AgentEvent::ToolExecutionUpdate {
+                tool_call_id,
+                partial_result,
+                ..
+            } => {
+                if let Some(line) = partial_result.get("line").and_then(Value::as_str) {
+                    streamed_tool_calls.insert(tool_call_id);
+                    if let Some(callback) = on_update.as_mut() {
+                        callback(AgentSessionStreamUpdate::ToolLine(line.to_string()));
+                    }
                 }
             }
             AgentEvent::MessageUpdate {
@@ -1289,6 +2689,31 @@ async fn collect_agent_loop_result(
                             callback(AgentSessionStreamUpdate::AssistantTextDelta(delta));
                             saw_assistant_text_delta = true;
                         }
+                        AssistantMessageEvent::UsageDelta { usage, .. } => {
+                            callback(AgentSessionStreamUpdate::UsageDelta(usage));
+                        }
+                        AssistantMessageEvent::ToolcallStart {
+                            content_index,
+                            partial,
+                        }
+                        | AssistantMessageEvent::ToolcallDelta {
+                            content_index,
+                            partial,
+                            ..
+                        } => {
+                            let preview = match partial.content.get(content_index) {
+                                Some(AssistantContentBlock::ToolCall {
+                                    name, arguments, ..
+                                }) => format_tool_call_preview(name, arguments),
+                                _ => None,
+                            };
+                            if let Some(preview) =
+                                preview.filter(|preview| *preview != tool_call_preview)
+                            {
+                                tool_call_preview = preview.clone();
+                                callback(AgentSessionStreamUpdate::ToolCallPreview(preview));
+                            }
+                        }
                         AssistantMessageEvent::ThinkingDelta {
                             content_index,
                             delta,
@@ -1326,7 +2751,7 @@ async fn collect_agent_loop_result(
             }
             AgentEvent::MessageEnd { message } => {
                 if let Some(callback) = on_update.as_mut() {
-                    if let Message::Assistant { .. } = &message {
+                    if let Message::Assistant { content, stats, .. } = &message {
                         for update in render_assistant_message_for_streaming(
                             &message,
                             saw_assistant_text_delta,
@@ -1334,23 +2759,39 @@ async fn collect_agent_loop_result(
                         ) {
                             callback(update);
                         }
+                        if let Some(stats) = stats {
+                            callback(AgentSessionStreamUpdate::Stats(stats.clone()));
+                        }
+                        let text = assistant_message_text(content);
+                        if !text.is_empty() {
+                            callback(AgentSessionStreamUpdate::AssistantMessageEnd(text));
+                        }
                     } else if let Message::ToolResult {
+                        tool_call_id,
                         tool_name,
                         content,
                         details,
+                        is_error,
                         ..
                     } = &message
                     {
+                        if let Some(todos) =
+                            todos_from_tool_result(tool_name, details.as_ref(), *is_error)
+                        {
+                            callback(AgentSessionStreamUpdate::Todos(todos));
+                        }
                         if renderer.should_render_tool_result_content(tool_name) {
-                            for block in content {
-                                match block {
-                                    ToolResultContentBlock::Text { text, .. } => {
-                                        callback(AgentSessionStreamUpdate::ToolLine(text.clone()))
-                                    }
-                                    ToolResultContentBlock::Image { .. } => {
-                                        callback(AgentSessionStreamUpdate::ToolLine(
-                                            "(image tool result omitted)".to_string(),
-                                        ))
+                            if !streamed_tool_calls.contains(tool_call_id) {
+                                for block in content {
+                                    match block {
+                                        ToolResultContentBlock::Text { text, .. } => callback(
+                                            AgentSessionStreamUpdate::ToolLine(text.clone()),
+                                        ),
+                                        ToolResultContentBlock::Image { data, mime_type } => {
+                                            callback(AgentSessionStreamUpdate::ToolLine(
+                                                attachment_placeholder(mime_type, data),
+                                            ))
+                                        }
                                     }
                                 }
                             }
@@ -1363,6 +2804,7 @@ async fn collect_agent_loop_result(
                     }
                 }
             }
+            AgentEvent::Metrics { metrics } => run_metrics.merge(&metrics),
             _ => {}
         }
     }
@@ -1373,18 +2815,40 @@ async fn collect_agent_loop_result(
         .ok_or_else(|| "Agent loop ended without a final result".to_string())
 }
 
+/// Updates for messages that were not streamed live, e.g. the run retried after compaction, in
+/// the order a live run emits them, so the headless output sees their tool calls and replies.
 pub(crate) fn render_messages_for_streaming(
     messages: &[AgentMessage],
 ) -> Vec<AgentSessionStreamUpdate> {
     let mut updates = vec![];
     for message in messages {
         match message {
-            Message::Assistant { .. } => {
+            Message::Assistant { content, .. } => {
                 updates.extend(render_assistant_message_for_streaming(
                     message, false, false,
                 ));
+                let text = assistant_message_text(content);
+                if !text.is_empty() {
+                    updates.push(AgentSessionStreamUpdate::AssistantMessageEnd(text));
+                }
+                for block in content {
+                    if let AssistantContentBlock::ToolCall {
+                        id,
+                        name,
+                        arguments,
+                        ..
+                    } = block
+                    {
+                        updates.push(AgentSessionStreamUpdate::ToolCallStart {
+                            tool_call_id: id.clone(),
+                            tool_name: name.clone(),
+                            args: arguments.clone(),
+                        });
+                    }
+                }
             }
             Message::ToolResult {
+                tool_call_id,
                 tool_name,
                 content,
                 details,
@@ -1403,9 +2867,9 @@ pub(crate) fn render_messages_for_streaming(
                             ToolResultContentBlock::Text { text, .. } => {
                                 updates.push(AgentSessionStreamUpdate::ToolLine(text.clone()));
                             }
-                            ToolResultContentBlock::Image { .. } => {
+                            ToolResultContentBlock::Image { data, mime_type } => {
                                 updates.push(AgentSessionStreamUpdate::ToolLine(
-                                    "(image tool result omitted)".to_string(),
+                                    attachment_placeholder(mime_type, data),
                                 ));
                             }
                         }
@@ -1414,6 +2878,12 @@ pub(crate) fn render_messages_for_streaming(
                 if let Some(line) = format_task_tool_finish_line(tool_name, details.as_ref()) {
                     updates.push(AgentSessionStreamUpdate::ToolLine(line));
                 }
+                updates.push(AgentSessionStreamUpdate::ToolCallEnd {
+                    tool_call_id: tool_call_id.clone(),
+                    tool_name: tool_name.clone(),
+                    content: content.clone(),
+                    is_error: *is_error,
+                });
             }
             Message::User { .. } => {}
         }
@@ -1421,6 +2891,20 @@ pub(crate) fn render_messages_for_streaming(
     updates
 }
 
+/// One-line stand-in for media a terminal cannot show, e.g. `[image] image/png, 12 KB`.
+pub(crate) fn attachment_placeholder(mime_type: &str, base64_data: &str) -> String {
+    let kind = mime_type.split('/').next().unwrap_or("attachment");
+    let bytes = base64_data.trim_end_matches('=').len() * 3 / 4;
+    if bytes >= 1024 * 1024 {
+        format!(
+            "[{kind}] {mime_type}, {:.1} MB",
+            bytes as f64 / (1024.0 * 1024.0)
+        )
+    } else {
+        format!("[{kind}] {mime_type}, {} KB", bytes.div_ceil(1024))
+    }
+}
+
 pub(crate) fn should_render_tool_result_content(tool_name: &str) -> bool {
     tool_name != "read"
 }
@@ -1435,6 +2919,19 @@ pub(crate) fn format_tool_start_line(tool_name: &str, args: &Value) -> String {
 }
 
 fn format_task_tool_start_line(args: &Value) -> String {
+    if let Some(tasks) = args.get("tasks").and_then(Value::as_array) {
+        let subagents = tasks
+            .iter()
+            .filter_map(|task| task.get("subagent_type").and_then(Value::as_str))
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .collect::<Vec<_>>();
+        return format!(
+            "• Ran task tasks={} subagents={}",
+            tasks.len(),
+            subagents.join(",")
+        );
+    }
     let subagent = args
         .get("subagent_type")
         .and_then(Value::as_str)
@@ -1489,6 +2986,37 @@ fn format_bash_tool_start_line(args: &Value) -> String {
     format!("• Ran bash -lc '{}'", shell_quote_single(command.as_ref()))
 }
 
+/// Describes a tool call from the arguments streamed so far; `None` until the name is known.
+fn format_tool_call_preview(tool_name: &str, args: &Value) -> Option<String> {
+    if tool_name.is_empty() {
+        return None;
+    }
+    let field = |name: &str| {
+        args.get(name)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    let verb = match tool_name {
+        "read" => "Reading",
+        "write" => "Writing",
+        "edit" => "Editing",
+        "bash" => {
+            return Some(
+                match field("command").and_then(|command| command.lines().next()) {
+                    Some(command) => format!("Running {command}..."),
+                    None => "Running bash...".to_string(),
+                },
+            );
+        }
+        _ => return Some(format!("Calling {tool_name}...")),
+    };
+    Some(match field("path") {
+        Some(path) => format!("{verb} {path}..."),
+        None => format!("{verb}..."),
+    })
+}
+
 fn format_path_tool_start_line(tool_name: &str, args: &Value) -> String {
     let Some(path) = args
         .get("path")
@@ -1505,6 +3033,17 @@ fn shell_quote_single(value: &str) -> String {
     value.replace('\'', r"'\''")
 }
 
+fn assistant_message_text(content: &[AssistantContentBlock]) -> String {
+    content
+        .iter()
+        .filter_map(|block| match block {
+            AssistantContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("")
+}
+
 fn render_assistant_message_for_streaming(
     message: &Message,
     had_text_delta: bool,
@@ -1540,6 +3079,12 @@ fn render_assistant_message_for_streaming(
                 }
             }
             AssistantContentBlock::ToolCall { .. } => {}
+            AssistantContentBlock::Image { data, mime_type }
+            | AssistantContentBlock::Audio { data, mime_type } => {
+                updates.push(AgentSessionStreamUpdate::AssistantLine(
+                    attachment_placeholder(mime_type, data),
+                ));
+            }
         }
     }
 
@@ -1642,28 +3187,6 @@ pub(crate) fn is_context_overflow_message(message: &Message, context_window: u64
     }
 }
 
-fn is_context_overflow_error_text(error: &str) -> bool {
-    let normalized = error.to_ascii_lowercase();
-    let patterns = [
-        "prompt is too long",
-        "input is too long for requested model",
-        "exceeds the context window",
-        "input token count",
-        "maximum prompt length",
-        "reduce the length of the messages",
-        "maximum context length",
-        "exceeds the available context size",
-        "greater than the context length",
-        "context window exceeds limit",
-        "exceeded model token limit",
-        "context length exceeded",
-        "too many tokens",
-        "token limit exceeded",
-    ];
-
-    patterns.iter().any(|pattern| normalized.contains(pattern))
-}
-
 fn is_context_overflow_status_no_body(error: &str) -> bool {
     let normalized = error.to_ascii_lowercase();
     normalized.contains("no body")
@@ -1673,20 +3196,6 @@ fn is_context_overflow_status_no_body(error: &str) -> bool {
             || normalized.contains("413 status code"))
 }
 
-fn serialize_messages_for_summary(messages: &[Message]) -> String {
-    messages
-        .iter()
-        .filter_map(|message| {
-            let content = message_to_summary_text(message);
-            if content.is_empty() {
-                return None;
-            }
-            Some(format!("[{}]: {content}", message_role_label(message)))
-        })
-        .collect::<Vec<_>>()
-        .join("\n\n")
-}
-
 pub(crate) fn build_auto_compaction_summary(
     messages_to_summarize: &[Message],
     context_tokens: u64,
@@ -1698,12 +3207,10 @@ pub(crate) fn build_auto_compaction_summary(
     );
 
     let mut added_message = false;
-    for message in messages_to_summarize {
-        let role = message_role_label(message);
-        let content = message_to_summary_text(message);
-        if content.is_empty() {
-            continue;
-        }
+    for (role, content) in messages_to_summarize
+        .iter()
+        .filter_map(pixy_ai::transcript_line)
+    {
         added_message = true;
         summary.push_str("- ");
         summary.push_str(role);
@@ -1719,74 +3226,6 @@ pub(crate) fn build_auto_compaction_summary(
     truncate_chars(&summary, max_summary_chars)
 }
 
-fn message_role_label(message: &Message) -> &'static str {
-    match message {
-        Message::User { .. } => "user",
-        Message::Assistant { .. } => "assistant",
-        Message::ToolResult { .. } => "tool_result",
-    }
-}
-
-fn message_to_summary_text(message: &Message) -> String {
-    match message {
-        Message::User { content, .. } => user_content_text(content),
-        Message::Assistant { content, .. } => assistant_content_text(content),
-        Message::ToolResult { content, .. } => tool_result_content_text(content),
-    }
-}
-
-fn user_content_text(content: &UserContent) -> String {
-    match content {
-        UserContent::Text(text) => normalize_text(text),
-        UserContent::Blocks(blocks) => normalize_text(
-            &blocks
-                .iter()
-                .filter_map(|block| match block {
-                    UserContentBlock::Text { text, .. } => Some(text.as_str()),
-                    _ => None,
-                })
-                .collect::<Vec<_>>()
-                .join(" "),
-        ),
-    }
-}
-
-fn assistant_content_text(content: &[AssistantContentBlock]) -> String {
-    normalize_text(
-        &content
-            .iter()
-            .map(|block| match block {
-                AssistantContentBlock::Text { text, .. } => text.clone(),
-                AssistantContentBlock::Thinking { thinking, .. } => thinking.clone(),
-                AssistantContentBlock::ToolCall {
-                    name, arguments, ..
-                } => format!(
-                    "tool call `{name}` with args {}",
-                    truncate_chars(&arguments.to_string(), 200)
-                ),
-            })
-            .collect::<Vec<_>>()
-            .join(" "),
-    )
-}
-
-fn tool_result_content_text(content: &[ToolResultContentBlock]) -> String {
-    normalize_text(
-        &content
-            .iter()
-            .filter_map(|block| match block {
-                ToolResultContentBlock::Text { text, .. } => Some(text.as_str()),
-                _ => None,
-            })
-            .collect::<Vec<_>>()
-            .join(" "),
-    )
-}
-
-fn normalize_text(text: &str) -> String {
-    truncate_chars(&text.replace('\n', " ").trim().to_string(), 240)
-}
-
 fn truncate_chars(text: &str, max_chars: usize) -> String {
     if max_chars == 0 {
         return String::new();
@@ -1804,6 +3243,15 @@ fn truncate_chars(text: &str, max_chars: usize) -> String {
     format!("{truncated}...")
 }
 
+/// Keeps the last `max_chars` characters, where the most recent conversation lives.
+fn tail_chars(text: &str, max_chars: usize) -> String {
+    let total = text.chars().count();
+    if total <= max_chars {
+        return text.to_string();
+    }
+    text.chars().skip(total - max_chars).collect()
+}
+
 pub(crate) fn resolve_resume_session_target(
     target: Option<&str>,
     current_session_file: Option<PathBuf>,
@@ -1890,7 +3338,7 @@ fn latest_session_in_dir(session_dir: &Path, exclude: Option<&Path>) -> Result<P
     })
 }
 
-fn list_session_files(session_dir: &Path) -> Result<Vec<PathBuf>, String> {
+pub(crate) fn list_session_files(session_dir: &Path) -> Result<Vec<PathBuf>, String> {
     let entries = std::fs::read_dir(session_dir).map_err(|error| {
         format!(
             "Read session directory failed ({}): {error}",
@@ -1921,19 +3369,47 @@ pub(crate) fn build_session_resume_candidate(
     path: PathBuf,
 ) -> Result<SessionResumeCandidate, String> {
     let manager = SessionManager::load(&path)?;
-    let context = manager.build_session_context();
-    let title = session_candidate_title(&context).unwrap_or_else(|| {
-        path.file_name()
-            .and_then(|name| name.to_str())
-            .map(ToOwned::to_owned)
-            .unwrap_or_else(|| path.display().to_string())
+    Ok(session_resume_candidate_from_manager(path, &manager))
+}
+
+/// Prefers the generated header title, then the first user prompt, then the file name.
+pub(crate) fn session_resume_candidate_from_manager(
+    path: PathBuf,
+    manager: &SessionManager,
+) -> SessionResumeCandidate {
+    let messages = manager.current_path_messages();
+    let first_prompt = session_candidate_title(&manager.build_session_context());
+    let title = manager
+        .header()
+        .title
+        .clone()
+        .or_else(|| first_prompt.clone())
+        .unwrap_or_else(|| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .map(ToOwned::to_owned)
+                .unwrap_or_else(|| path.display().to_string())
+        });
+    let turn_count = messages
+        .iter()
+        .filter(|message| matches!(message, Message::User { .. }))
+        .count();
+    let model = messages.iter().rev().find_map(|message| match message {
+        Message::Assistant { model, .. } => Some(model.clone()),
+        _ => None,
     });
     let updated_at = session_candidate_updated_at(&path).unwrap_or_else(|| "unknown".to_string());
-    Ok(SessionResumeCandidate {
+    SessionResumeCandidate {
         path,
         title,
+        first_prompt,
         updated_at,
-    })
+        turn_count,
+        message_count: messages.len(),
+        model,
+        total_cost: SessionCostReport::from_messages(&messages).session.cost,
+        cwd: manager.cwd().to_string(),
+    }
 }
 
 fn session_candidate_title(context: &SessionContext) -> Option<String> {
@@ -1953,6 +3429,24 @@ fn session_candidate_title(context: &SessionContext) -> Option<String> {
     })
 }
 
+/// First non-empty line of a model-written title, without quotes, a `Title:` label or a
+/// trailing period.
+fn clean_session_title(text: &str) -> Option<String> {
+    let line = text.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line
+        .strip_prefix("Title:")
+        .or_else(|| line.strip_prefix("title:"))
+        .unwrap_or(line);
+    let title = normalize_session_candidate_title(line)
+        .trim_matches(|ch: char| matches!(ch, '"' | '\'' | '`' | '*' | '#') || ch.is_whitespace())
+        .trim_end_matches('.')
+        .to_string();
+    if title.is_empty() {
+        return None;
+    }
+    Some(truncate_chars(&title, SESSION_TITLE_MAX_CHARS))
+}
+
 fn user_content_preview(content: &UserContent) -> Option<String> {
     match content {
         UserContent::Text(text) => Some(text.clone()),
@@ -2015,19 +3509,31 @@ fn paths_equal(left: &Path, right: &Path) -> bool {
 #[cfg(test)]
 mod tests {
     use chrono::Local;
-    use pixy_ai::{Cost, Message, Model, ToolResultContentBlock, UserContent};
+    use pixy_agent_core::{
+        AgentTool, AgentToolResult, ParentChildRunEvent, ToolFuture, ToolLoopGuard,
+    };
+    use pixy_ai::{
+        AssistantContentBlock, AssistantMessage, AssistantMessageEvent,
+        AssistantMessageEventStream, Context, Cost, DoneReason, Message, Model, StopReason,
+        StreamTransport, ToolResultContentBlock, Usage, UserContent,
+    };
     use serde_json::json;
     use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
 
     use super::{
         build_session_resume_candidate, create_session_from_runtime, format_bash_tool_start_line,
-        format_task_tool_finish_line, format_tool_start_line, normalize_session_candidate_title,
-        render_messages_for_streaming, resolve_runtime_api_key_for_model, AgentMode,
-        AgentSessionStreamUpdate, ResolvedRuntime,
+        format_child_run_progress, format_task_tool_finish_line, format_tool_call_preview,
+        format_tool_start_line, normalize_session_candidate_title, render_messages_for_streaming,
+        resolve_runtime_api_key_for_model, AgentMode, AgentSessionStreamUpdate, ResolvedRuntime,
     };
     use crate::{
-        ResolvedMemoryConfig, ResolvedMemorySearchConfig, ResolvedMultiAgentConfig, SessionManager,
-        SubAgentMode, SubAgentSpec,
+        DiffReviewConfig, GuardConfig, HttpTransportConfig, McpConfig, ProjectMemoryConfig,
+        RedactionConfig, ResolvedMemoryConfig, ResolvedMemorySearchConfig,
+        ResolvedMultiAgentConfig, SamplingConfig, SessionManager, SubAgentMode, SubAgentSpec,
+        TelemetryConfig, ToolApprovalConfig, ToolFailureConfig, ToolOutputConfig, WebSearchConfig,
+        WorktreeConfig,
     };
 
     fn sample_model() -> Model {
@@ -2049,6 +3555,9 @@ mod tests {
             },
             context_window: 128_000,
             max_tokens: 8_192,
+            deployment: None,
+            api_version: None,
+            compat: None,
         }
     }
 
@@ -2064,6 +3573,7 @@ mod tests {
                 enabled: true,
                 max_results: 10,
                 min_score: 0.1,
+                embedding: None,
             },
         }
     }
@@ -2077,6 +3587,21 @@ mod tests {
         assert_eq!(line, "• Ran bash -lc 'printf \"hello\"'");
     }
 
+    #[test]
+    fn format_tool_call_preview_reads_partial_arguments() {
+        let arguments = pixy_ai::parse_partial_json(r#"{"path": "src/lib.rs", "oldText": "fn ma"#)
+            .expect("partial arguments");
+        assert_eq!(
+            format_tool_call_preview("edit", &arguments).as_deref(),
+            Some("Editing src/lib.rs...")
+        );
+        assert_eq!(
+            format_tool_call_preview("bash", &json!({})).as_deref(),
+            Some("Running bash...")
+        );
+        assert_eq!(format_tool_call_preview("", &json!({})), None);
+    }
+
     #[test]
     fn format_tool_start_line_includes_subagent_for_task_tool() {
         let line = format_tool_start_line(
@@ -2090,6 +3615,71 @@ mod tests {
         assert_eq!(line, "• Ran task subagent=review task_id=mission-review");
     }
 
+    #[test]
+    fn format_tool_start_line_lists_parallel_task_subagents() {
+        let line = format_tool_start_line(
+            "task",
+            &json!({
+                "tasks": [
+                    {"subagent_type": "review", "prompt": "a"},
+                    {"subagent_type": "code", "prompt": "b"}
+                ]
+            }),
+        );
+
+        assert_eq!(line, "• Ran task tasks=2 subagents=review,code");
+    }
+
+    #[test]
+    fn format_child_run_progress_renders_headlines() {
+        let progress = |line: &str| AgentSessionStreamUpdate::SubagentProgress {
+            task_id: "task-1".to_string(),
+            subagent: "review".to_string(),
+            line: line.to_string(),
+        };
+        assert_eq!(
+            format_child_run_progress(&ParentChildRunEvent::ChildRunStart {
+                parent_session_id: "parent".to_string(),
+                child_session_file: "/tmp/child.jsonl".to_string(),
+                task_id: "task-1".to_string(),
+                subagent: "review".to_string(),
+            }),
+            progress("started")
+        );
+        assert_eq!(
+            format_child_run_progress(&ParentChildRunEvent::ChildToolStart {
+                parent_session_id: "parent".to_string(),
+                child_session_file: "/tmp/child.jsonl".to_string(),
+                task_id: "task-1".to_string(),
+                subagent: "review".to_string(),
+                tool_name: "read".to_string(),
+                headline: "• Ran read src/lib.rs".to_string(),
+            }),
+            progress("• Ran read src/lib.rs")
+        );
+        assert_eq!(
+            format_child_run_progress(&ParentChildRunEvent::ChildRunEnd {
+                parent_session_id: "parent".to_string(),
+                child_session_file: "/tmp/child.jsonl".to_string(),
+                task_id: "task-1".to_string(),
+                subagent: "review".to_string(),
+                duration_ms: 65_432,
+                summary: "ok".to_string(),
+            }),
+            progress("completed in 1 m 5 s")
+        );
+        assert_eq!(
+            format_child_run_progress(&ParentChildRunEvent::ChildRunError {
+                parent_session_id: "parent".to_string(),
+                child_session_file: "/tmp/child.jsonl".to_string(),
+                task_id: "task-1".to_string(),
+                subagent: "review".to_string(),
+                error: "boom".to_string(),
+            }),
+            progress("failed: boom")
+        );
+    }
+
     #[test]
     fn format_tool_start_line_falls_back_when_task_args_missing() {
         let line = format_tool_start_line("task", &json!({}));
@@ -2166,6 +3756,79 @@ mod tests {
         )));
     }
 
+    #[test]
+    fn render_messages_for_streaming_emits_tool_and_assistant_events() {
+        let messages = vec![
+            Message::Assistant {
+                content: vec![
+                    AssistantContentBlock::Text {
+                        text: "reading".to_string(),
+                        text_signature: None,
+                    },
+                    AssistantContentBlock::ToolCall {
+                        id: "tc-1".to_string(),
+                        name: "read".to_string(),
+                        arguments: json!({ "path": "README.md" }),
+                        thought_signature: None,
+                    },
+                ],
+                api: "openai-responses".to_string(),
+                provider: "openai".to_string(),
+                model: "gpt-test".to_string(),
+                usage: Usage {
+                    input: 0,
+                    output: 0,
+                    cache_read: 0,
+                    cache_write: 0,
+                    reasoning: 0,
+                    total_tokens: 0,
+                    cost: sample_model().cost,
+                },
+                stop_reason: StopReason::ToolUse,
+                error_message: None,
+                timestamp: 1,
+                stats: None,
+            },
+            Message::ToolResult {
+                tool_call_id: "tc-1".to_string(),
+                tool_name: "read".to_string(),
+                content: vec![ToolResultContentBlock::Text {
+                    text: "hello".to_string(),
+                    text_signature: None,
+                }],
+                details: None,
+                is_error: false,
+                timestamp: 2,
+            },
+        ];
+
+        let updates = render_messages_for_streaming(&messages);
+        let position = |wanted: &AgentSessionStreamUpdate| {
+            updates
+                .iter()
+                .position(|update| update == wanted)
+                .unwrap_or_else(|| panic!("missing {wanted:?} in {updates:?}"))
+        };
+        let end = position(&AgentSessionStreamUpdate::AssistantMessageEnd(
+            "reading".to_string(),
+        ));
+        let start = position(&AgentSessionStreamUpdate::ToolCallStart {
+            tool_call_id: "tc-1".to_string(),
+            tool_name: "read".to_string(),
+            args: json!({ "path": "README.md" }),
+        });
+        let finish = position(&AgentSessionStreamUpdate::ToolCallEnd {
+            tool_call_id: "tc-1".to_string(),
+            tool_name: "read".to_string(),
+            content: vec![ToolResultContentBlock::Text {
+                text: "hello".to_string(),
+                text_signature: None,
+            }],
+            is_error: false,
+        });
+        assert!(end < start && start < finish);
+    }
+
     #[test]
     fn normalize_session_candidate_title_compacts_whitespace() {
         let normalized = normalize_session_candidate_title("  fix\n\n  websocket\t timeout  ");
@@ -2190,6 +3853,48 @@ mod tests {
         assert_eq!(candidate.path, session_file);
         assert!(candidate.title.contains("investigate flaky test timeout"));
         assert!(!candidate.updated_at.trim().is_empty());
+        assert_eq!(candidate.turn_count, 1);
+        assert_eq!(candidate.cwd, cwd_text);
+
+        manager
+            .append_message(Message::Assistant {
+                content: vec![AssistantContentBlock::Text {
+                    text: "looking".to_string(),
+                    text_signature: None,
+                }],
+                api: "openai-responses".to_string(),
+                provider: "openai".to_string(),
+                model: "gpt-5.3-codex".to_string(),
+                usage: Usage {
+                    input: 0,
+                    output: 0,
+                    cache_read: 0,
+                    cache_write: 0,
+                    reasoning: 0,
+                    total_tokens: 0,
+                    cost: Cost {
+                        total: 0.25,
+                        ..sample_model().cost
+                    },
+                },
+                stop_reason: StopReason::Stop,
+                error_message: None,
+                timestamp: 1_700_000_000_001,
+                stats: None,
+            })
+            .expect("append assistant message");
+        manager
+            .set_title("Flaky test timeout")
+            .expect("set session title");
+        let candidate = build_session_resume_candidate(session_file).expect("titled candidate");
+        assert_eq!(candidate.title, "Flaky test timeout");
+        assert_eq!(
+            candidate.first_prompt.as_deref(),
+            Some("investigate flaky test timeout")
+        );
+        assert_eq!(candidate.message_count, 2);
+        assert_eq!(candidate.model.as_deref(), Some("gpt-5.3-codex"));
+        assert_eq!(candidate.total_cost, 0.25);
     }
 
     #[test]
@@ -2211,10 +3916,31 @@ mod tests {
                 hooks: vec![],
             },
             memory: default_memory_config(),
+            hooks: vec![],
+            post_edit: vec![],
             skills: vec![],
             skill_diagnostics: vec![],
+            skill_options: None,
+            project_memory: ProjectMemoryConfig::default(),
+            redaction: RedactionConfig::default(),
+            guards: GuardConfig::default(),
+            sampling: SamplingConfig::default(),
+            stream_transport: StreamTransport::default(),
+            http_transport: HttpTransportConfig::default(),
+            rate_limits: HashMap::new(),
+            tool_failures: ToolFailureConfig::default(),
+            tool_output: ToolOutputConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            worktree: WorktreeConfig::default(),
+            review: DiffReviewConfig::default(),
+            approval: ToolApprovalConfig::default(),
+            loop_guard: ToolLoopGuard::default(),
+            mcp: McpConfig::default(),
+            web_search: WebSearchConfig::default(),
             theme: None,
             transport_retry_count: 5,
+            tool_parallelism: 1,
+            discover_models: false,
         };
         let session_disabled = create_session_from_runtime(
             cwd,
@@ -2250,10 +3976,31 @@ mod tests {
                 hooks: vec![],
             },
             memory: default_memory_config(),
+            hooks: vec![],
+            post_edit: vec![],
             skills: vec![],
             skill_diagnostics: vec![],
+            skill_options: None,
+            project_memory: ProjectMemoryConfig::default(),
+            redaction: RedactionConfig::default(),
+            guards: GuardConfig::default(),
+            sampling: SamplingConfig::default(),
+            stream_transport: StreamTransport::default(),
+            http_transport: HttpTransportConfig::default(),
+            rate_limits: HashMap::new(),
+            tool_failures: ToolFailureConfig::default(),
+            tool_output: ToolOutputConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            worktree: WorktreeConfig::default(),
+            review: DiffReviewConfig::default(),
+            approval: ToolApprovalConfig::default(),
+            loop_guard: ToolLoopGuard::default(),
+            mcp: McpConfig::default(),
+            web_search: WebSearchConfig::default(),
             theme: None,
             transport_retry_count: 5,
+            tool_parallelism: 1,
+            discover_models: false,
         };
         let session_enabled = create_session_from_runtime(
             cwd,
@@ -2269,6 +4016,77 @@ mod tests {
             .any(|tool| tool.name == "task"));
     }
 
+    #[test]
+    fn create_session_from_runtime_registers_project_agent_files() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let session_dir = dir.path().join("sessions");
+        let cwd = dir.path();
+        let cwd_text = cwd.to_str().expect("utf-8 cwd");
+        std::fs::create_dir_all(cwd.join(".pixy/agents")).expect("create agents dir");
+        std::fs::write(
+            cwd.join(".pixy/agents/reviewer.md"),
+            "---\ndescription: Reviews diffs for bugs\n---\nYou review code.\n",
+        )
+        .expect("write agent file");
+
+        let runtime = ResolvedRuntime {
+            model: sample_model(),
+            model_catalog: vec![sample_model()],
+            api_key: None,
+            provider_api_keys: HashMap::new(),
+            multi_agent: ResolvedMultiAgentConfig::default(),
+            memory: default_memory_config(),
+            hooks: vec![],
+            post_edit: vec![],
+            skills: vec![],
+            skill_diagnostics: vec![],
+            skill_options: None,
+            project_memory: ProjectMemoryConfig::default(),
+            redaction: RedactionConfig::default(),
+            guards: GuardConfig::default(),
+            sampling: SamplingConfig::default(),
+            stream_transport: StreamTransport::default(),
+            http_transport: HttpTransportConfig::default(),
+            rate_limits: HashMap::new(),
+            tool_failures: ToolFailureConfig::default(),
+            tool_output: ToolOutputConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            worktree: WorktreeConfig::default(),
+            review: DiffReviewConfig::default(),
+            approval: ToolApprovalConfig::default(),
+            loop_guard: ToolLoopGuard::default(),
+            mcp: McpConfig::default(),
+            web_search: WebSearchConfig::default(),
+            theme: None,
+            transport_retry_count: 5,
+            tool_parallelism: 1,
+            discover_models: false,
+        };
+        let session = create_session_from_runtime(
+            cwd,
+            SessionManager::create(cwd_text, &session_dir).expect("create session"),
+            &runtime,
+            None,
+            false,
+        );
+
+        assert!(session.config.tools.iter().any(|tool| tool.name == "task"));
+        assert!(session.config.system_prompt.contains("reviewer"));
+
+        let session_without_tools = create_session_from_runtime(
+            cwd,
+            SessionManager::create(cwd_text, &session_dir).expect("create session"),
+            &runtime,
+            None,
+            true,
+        );
+        assert!(!session_without_tools
+            .config
+            .tools
+            .iter()
+            .any(|tool| tool.name == "task"));
+    }
+
     #[test]
     fn create_session_from_runtime_appends_subagent_names_to_prompt_when_task_tool_enabled() {
         let dir = tempfile::tempdir().expect("tempdir");
@@ -2309,10 +4127,31 @@ mod tests {
                 hooks: vec![],
             },
             memory: default_memory_config(),
+            hooks: vec![],
+            post_edit: vec![],
             skills: vec![],
             skill_diagnostics: vec![],
+            skill_options: None,
+            project_memory: ProjectMemoryConfig::default(),
+            redaction: RedactionConfig::default(),
+            guards: GuardConfig::default(),
+            sampling: SamplingConfig::default(),
+            stream_transport: StreamTransport::default(),
+            http_transport: HttpTransportConfig::default(),
+            rate_limits: HashMap::new(),
+            tool_failures: ToolFailureConfig::default(),
+            tool_output: ToolOutputConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            worktree: WorktreeConfig::default(),
+            review: DiffReviewConfig::default(),
+            approval: ToolApprovalConfig::default(),
+            loop_guard: ToolLoopGuard::default(),
+            mcp: McpConfig::default(),
+            web_search: WebSearchConfig::default(),
             theme: None,
             transport_retry_count: 5,
+            tool_parallelism: 1,
+            discover_models: false,
         };
 
         let session = create_session_from_runtime(
@@ -2360,10 +4199,31 @@ mode = "subagent"
                 hooks: vec![],
             },
             memory: default_memory_config(),
+            hooks: vec![],
+            post_edit: vec![],
             skills: vec![],
             skill_diagnostics: vec![],
+            skill_options: None,
+            project_memory: ProjectMemoryConfig::default(),
+            redaction: RedactionConfig::default(),
+            guards: GuardConfig::default(),
+            sampling: SamplingConfig::default(),
+            stream_transport: StreamTransport::default(),
+            http_transport: HttpTransportConfig::default(),
+            rate_limits: HashMap::new(),
+            tool_failures: ToolFailureConfig::default(),
+            tool_output: ToolOutputConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            worktree: WorktreeConfig::default(),
+            review: DiffReviewConfig::default(),
+            approval: ToolApprovalConfig::default(),
+            loop_guard: ToolLoopGuard::default(),
+            mcp: McpConfig::default(),
+            web_search: WebSearchConfig::default(),
             theme: None,
             transport_retry_count: 5,
+            tool_parallelism: 1,
+            discover_models: false,
         };
 
         let session = create_session_from_runtime(
@@ -2420,10 +4280,31 @@ value = "\n[plugin hook active]"
                 hooks: vec![],
             },
             memory: default_memory_config(),
+            hooks: vec![],
+            post_edit: vec![],
             skills: vec![],
             skill_diagnostics: vec![],
+            skill_options: None,
+            project_memory: ProjectMemoryConfig::default(),
+            redaction: RedactionConfig::default(),
+            guards: GuardConfig::default(),
+            sampling: SamplingConfig::default(),
+            stream_transport: StreamTransport::default(),
+            http_transport: HttpTransportConfig::default(),
+            rate_limits: HashMap::new(),
+            tool_failures: ToolFailureConfig::default(),
+            tool_output: ToolOutputConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            worktree: WorktreeConfig::default(),
+            review: DiffReviewConfig::default(),
+            approval: ToolApprovalConfig::default(),
+            loop_guard: ToolLoopGuard::default(),
+            mcp: McpConfig::default(),
+            web_search: WebSearchConfig::default(),
             theme: None,
             transport_retry_count: 5,
+            tool_parallelism: 1,
+            discover_models: false,
         };
 
         let session = create_session_from_runtime(
@@ -2467,12 +4348,34 @@ value = "\n[plugin hook active]"
                     enabled: true,
                     max_results: 8,
                     min_score: 0.1,
+                    embedding: None,
                 },
             },
+            hooks: vec![],
+            post_edit: vec![],
             skills: vec![],
             skill_diagnostics: vec![],
+            skill_options: None,
+            project_memory: ProjectMemoryConfig::default(),
+            redaction: RedactionConfig::default(),
+            guards: GuardConfig::default(),
+            sampling: SamplingConfig::default(),
+            stream_transport: StreamTransport::default(),
+            http_transport: HttpTransportConfig::default(),
+            rate_limits: HashMap::new(),
+            tool_failures: ToolFailureConfig::default(),
+            tool_output: ToolOutputConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            worktree: WorktreeConfig::default(),
+            review: DiffReviewConfig::default(),
+            approval: ToolApprovalConfig::default(),
+            loop_guard: ToolLoopGuard::default(),
+            mcp: McpConfig::default(),
+            web_search: WebSearchConfig::default(),
             theme: None,
             transport_retry_count: 5,
+            tool_parallelism: 1,
+            discover_models: false,
         };
 
         let session = create_session_from_runtime(
@@ -2548,12 +4451,34 @@ value = "\n[plugin hook active]"
                     enabled: true,
                     max_results: 8,
                     min_score: 0.1,
+                    embedding: None,
                 },
             },
+            hooks: vec![],
+            post_edit: vec![],
             skills: vec![],
             skill_diagnostics: vec![],
+            skill_options: None,
+            project_memory: ProjectMemoryConfig::default(),
+            redaction: RedactionConfig::default(),
+            guards: GuardConfig::default(),
+            sampling: SamplingConfig::default(),
+            stream_transport: StreamTransport::default(),
+            http_transport: HttpTransportConfig::default(),
+            rate_limits: HashMap::new(),
+            tool_failures: ToolFailureConfig::default(),
+            tool_output: ToolOutputConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            worktree: WorktreeConfig::default(),
+            review: DiffReviewConfig::default(),
+            approval: ToolApprovalConfig::default(),
+            loop_guard: ToolLoopGuard::default(),
+            mcp: McpConfig::default(),
+            web_search: WebSearchConfig::default(),
             theme: None,
             transport_retry_count: 5,
+            tool_parallelism: 1,
+            discover_models: false,
         };
 
         let mut session = create_session_from_runtime(
@@ -2589,10 +4514,31 @@ value = "\n[plugin hook active]"
             provider_api_keys: HashMap::new(),
             multi_agent: ResolvedMultiAgentConfig::default(),
             memory: default_memory_config(),
+            hooks: vec![],
+            post_edit: vec![],
             skills: vec![],
             skill_diagnostics: vec![],
+            skill_options: None,
+            project_memory: ProjectMemoryConfig::default(),
+            redaction: RedactionConfig::default(),
+            guards: GuardConfig::default(),
+            sampling: SamplingConfig::default(),
+            stream_transport: StreamTransport::default(),
+            http_transport: HttpTransportConfig::default(),
+            rate_limits: HashMap::new(),
+            tool_failures: ToolFailureConfig::default(),
+            tool_output: ToolOutputConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            worktree: WorktreeConfig::default(),
+            review: DiffReviewConfig::default(),
+            approval: ToolApprovalConfig::default(),
+            loop_guard: ToolLoopGuard::default(),
+            mcp: McpConfig::default(),
+            web_search: WebSearchConfig::default(),
             theme: None,
             transport_retry_count: 5,
+            tool_parallelism: 1,
+            discover_models: false,
         };
 
         let mut session = create_session_from_runtime(
@@ -2637,4 +4583,714 @@ value = "\n[plugin hook active]"
             .system_prompt
             .contains("You are in PLAN MODE."));
     }
+
+    #[tokio::test]
+    async fn prompt_reports_files_changed_outside_the_agent() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cwd = dir.path();
+        let cwd_text = cwd.to_str().expect("utf-8 cwd");
+        std::fs::write(cwd.join("note.txt"), "original").expect("seed note");
+
+        let runtime = ResolvedRuntime {
+            model: sample_model(),
+            model_catalog: vec![sample_model()],
+            api_key: None,
+            provider_api_keys: HashMap::new(),
+            multi_agent: ResolvedMultiAgentConfig::default(),
+            memory: default_memory_config(),
+            hooks: vec![],
+            post_edit: vec![],
+            skills: vec![],
+            skill_diagnostics: vec![],
+            skill_options: None,
+            project_memory: ProjectMemoryConfig::default(),
+            redaction: RedactionConfig::default(),
+            guards: GuardConfig::default(),
+            sampling: SamplingConfig::default(),
+            stream_transport: StreamTransport::default(),
+            http_transport: HttpTransportConfig::default(),
+            rate_limits: HashMap::new(),
+            tool_failures: ToolFailureConfig::default(),
+            tool_output: ToolOutputConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            worktree: WorktreeConfig::default(),
+            review: DiffReviewConfig::default(),
+            approval: ToolApprovalConfig::default(),
+            loop_guard: ToolLoopGuard::default(),
+            mcp: McpConfig::default(),
+            web_search: WebSearchConfig::default(),
+            theme: None,
+            transport_retry_count: 5,
+            tool_parallelism: 1,
+            discover_models: false,
+        };
+        let mut session = create_session_from_runtime(
+            cwd,
+            SessionManager::create(cwd_text, cwd.join("sessions")).expect("create session"),
+            &runtime,
+            None,
+            false,
+        );
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let prompts = Arc::new(Mutex::new(Vec::<String>::new()));
+        let calls_in_fn = calls.clone();
+        let prompts_in_fn = prompts.clone();
+        session.config.stream_fn = Arc::new(move |model: Model, context: Context, _options| {
+            if let Some(Message::User {
+                content: UserContent::Text(text),
+                ..
+            }) = context.messages.last()
+            {
+                prompts_in_fn.lock().unwrap().push(text.clone());
+            }
+            let content = if calls_in_fn.fetch_add(1, Ordering::SeqCst) == 0 {
+                AssistantContentBlock::ToolCall {
+                    id: "tool-1".to_string(),
+                    name: "read".to_string(),
+                    arguments: json!({ "path": "note.txt" }),
+                    thought_signature: None,
+                }
+            } else {
+                AssistantContentBlock::Text {
+                    text: "ok".to_string(),
+                    text_signature: None,
+                }
+            };
+            let stop_reason = match content {
+                AssistantContentBlock::ToolCall { .. } => StopReason::ToolUse,
+                _ => StopReason::Stop,
+            };
+            let message = AssistantMessage {
+                role: "assistant".to_string(),
+                content: vec![content],
+                api: model.api,
+                provider: model.provider,
+                model: model.id,
+                usage: Usage {
+                    input: 0,
+                    output: 0,
+                    cache_read: 0,
+                    cache_write: 0,
+                    reasoning: 0,
+                    total_tokens: 0,
+                    cost: sample_model().cost,
+                },
+                stop_reason: stop_reason.clone(),
+                error_message: None,
+                timestamp: 1,
+                stats: None,
+            };
+            let stream = AssistantMessageEventStream::new();
+            stream.push(AssistantMessageEvent::Done {
+                reason: if stop_reason == StopReason::ToolUse {
+                    DoneReason::ToolUse
+                } else {
+                    DoneReason::Stop
+                },
+                message,
+            });
+            Ok(stream)
+        });
+
+        session.prompt("read the note").await.expect("first prompt");
+        session
+            .prompt("anything new?")
+            .await
+            .expect("second prompt");
+        std::fs::write(cwd.join("note.txt"), "edited in the editor").expect("external edit");
+        session.prompt("and now?").await.expect("third prompt");
+
+        let prompts = prompts.lock().unwrap().clone();
+        assert_eq!(prompts.first().map(String::as_str), Some("read the note"));
+        assert_eq!(prompts.get(1).map(String::as_str), Some("anything new?"));
+        let last = prompts.last().expect("third prompt recorded");
+        assert!(last.starts_with("<file_changes>"), "{last}");
+        assert!(last.contains("- note.txt (modified)"), "{last}");
+        assert!(last.ends_with("and now?"), "{last}");
+    }
+
+    #[tokio::test]
+    async fn file_changes_made_during_a_run_are_reported() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cwd = dir.path();
+        let cwd_text = cwd.to_str().expect("utf-8 cwd");
+        std::fs::write(cwd.join("note.txt"), "original").expect("seed note");
+
+        let runtime = ResolvedRuntime {
+            model: sample_model(),
+            model_catalog: vec![sample_model()],
+            api_key: None,
+            provider_api_keys: HashMap::new(),
+            multi_agent: ResolvedMultiAgentConfig::default(),
+            memory: default_memory_config(),
+            hooks: vec![],
+            post_edit: vec![],
+            skills: vec![],
+            skill_diagnostics: vec![],
+            skill_options: None,
+            project_memory: ProjectMemoryConfig::default(),
+            redaction: RedactionConfig::default(),
+            guards: GuardConfig::default(),
+            sampling: SamplingConfig::default(),
+            stream_transport: StreamTransport::default(),
+            http_transport: HttpTransportConfig::default(),
+            rate_limits: HashMap::new(),
+            tool_failures: ToolFailureConfig::default(),
+            tool_output: ToolOutputConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            worktree: WorktreeConfig::default(),
+            review: DiffReviewConfig::default(),
+            approval: ToolApprovalConfig::default(),
+            loop_guard: ToolLoopGuard::default(),
+            mcp: McpConfig::default(),
+            web_search: WebSearchConfig::default(),
+            theme: None,
+            transport_retry_count: 5,
+            tool_parallelism: 1,
+            discover_models: false,
+        };
+        let mut session = create_session_from_runtime(
+            cwd,
+            SessionManager::create(cwd_text, cwd.join("sessions")).expect("create session"),
+            &runtime,
+            None,
+            false,
+        );
+
+        let note = cwd.join("note.txt");
+        let note_in_tool = note.clone();
+        session.config.tools.push(AgentTool {
+            name: "editor".to_string(),
+            label: "editor".to_string(),
+            description: "Stands in for the user's editor".to_string(),
+            parameters: json!({ "type": "object" }),
+            conflict_key: None,
+            execute: Arc::new(move |_tool_call_id: String, _args| -> ToolFuture {
+                let note = note_in_tool.clone();
+                Box::pin(async move {
+                    std::fs::write(&note, "saved while tools ran").expect("edit note");
+                    Ok(AgentToolResult {
+                        content: vec![],
+                        details: json!({}),
+                    })
+                })
+            }),
+        });
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let prompts = Arc::new(Mutex::new(Vec::<String>::new()));
+        let calls_in_fn = calls.clone();
+        let prompts_in_fn = prompts.clone();
+        session.config.stream_fn = Arc::new(move |model: Model, context: Context, _options| {
+            if let Some(Message::User {
+                content: UserContent::Text(text),
+                ..
+            }) = context.messages.last()
+            {
+                prompts_in_fn.lock().unwrap().push(text.clone());
+            }
+            let content = if calls_in_fn.fetch_add(1, Ordering::SeqCst) == 0 {
+                [
+                    ("read", json!({ "path": "note.txt" })),
+                    ("editor", json!({})),
+                ]
+                .into_iter()
+                .map(|(name, arguments)| AssistantContentBlock::ToolCall {
+                    id: format!("{name}-1"),
+                    name: name.to_string(),
+                    arguments,
+                    thought_signature: None,
+                })
+                .collect::<Vec<_>>()
+            } else {
+                // Saved while the model writes its final answer, after the last check.
+                std::fs::write(&note, "saved during the last turn").expect("edit note");
+                vec![AssistantContentBlock::Text {
+                    text: "ok".to_string(),
+                    text_signature: None,
+                }]
+            };
+            let stop_reason = match content.first() {
+                Some(AssistantContentBlock::ToolCall { .. }) => StopReason::ToolUse,
+                _ => StopReason::Stop,
+            };
+            let message = AssistantMessage {
+                role: "assistant".to_string(),
+                content,
+                api: model.api,
+                provider: model.provider,
+                model: model.id,
+                usage: Usage {
+                    input: 0,
+                    output: 0,
+                    cache_read: 0,
+                    cache_write: 0,
+                    reasoning: 0,
+                    total_tokens: 0,
+                    cost: sample_model().cost,
+                },
+                stop_reason: stop_reason.clone(),
+                error_message: None,
+                timestamp: 1,
+                stats: None,
+            };
+            let stream = AssistantMessageEventStream::new();
+            stream.push(AssistantMessageEvent::Done {
+                reason: if stop_reason == StopReason::ToolUse {
+                    DoneReason::ToolUse
+                } else {
+                    DoneReason::Stop
+                },
+                message,
+            });
+            Ok(stream)
+        });
+
+        session.prompt("read the note").await.expect("first prompt");
+        session
+            .prompt("anything new?")
+            .await
+            .expect("second prompt");
+
+        let prompts = prompts.lock().unwrap().clone();
+        assert_eq!(prompts.len(), 3, "{prompts:?}");
+        assert_eq!(prompts[0], "read the note");
+        assert!(prompts[1].starts_with("<file_changes>"), "{}", prompts[1]);
+        assert!(
+            prompts[1].contains("- note.txt (modified)"),
+            "{}",
+            prompts[1]
+        );
+        assert!(prompts[2].starts_with("<file_changes>"), "{}", prompts[2]);
+        assert!(prompts[2].ends_with("anything new?"), "{}", prompts[2]);
+    }
+
+    #[tokio::test]
+    async fn undone_file_changes_are_reported_with_the_next_prompt() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cwd = dir.path();
+        let cwd_text = cwd.to_str().expect("utf-8 cwd");
+        std::fs::write(cwd.join("note.txt"), "original").expect("seed note");
+
+        let runtime = ResolvedRuntime {
+            model: sample_model(),
+            model_catalog: vec![sample_model()],
+            api_key: None,
+            provider_api_keys: HashMap::new(),
+            multi_agent: ResolvedMultiAgentConfig::default(),
+            memory: default_memory_config(),
+            hooks: vec![],
+            post_edit: vec![],
+            skills: vec![],
+            skill_diagnostics: vec![],
+            skill_options: None,
+            project_memory: ProjectMemoryConfig::default(),
+            redaction: RedactionConfig::default(),
+            guards: GuardConfig::default(),
+            sampling: SamplingConfig::default(),
+            stream_transport: StreamTransport::default(),
+            http_transport: HttpTransportConfig::default(),
+            rate_limits: HashMap::new(),
+            tool_failures: ToolFailureConfig::default(),
+            tool_output: ToolOutputConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            worktree: WorktreeConfig::default(),
+            review: DiffReviewConfig::default(),
+            approval: ToolApprovalConfig::default(),
+            loop_guard: ToolLoopGuard::default(),
+            mcp: McpConfig::default(),
+            web_search: WebSearchConfig::default(),
+            theme: None,
+            transport_retry_count: 5,
+            tool_parallelism: 1,
+            discover_models: false,
+        };
+        let mut session = create_session_from_runtime(
+            cwd,
+            SessionManager::create(cwd_text, cwd.join("sessions")).expect("create session"),
+            &runtime,
+            None,
+            false,
+        );
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let prompts = Arc::new(Mutex::new(Vec::<String>::new()));
+        let calls_in_fn = calls.clone();
+        let prompts_in_fn = prompts.clone();
+        session.config.stream_fn = Arc::new(move |model: Model, context: Context, _options| {
+            if let Some(Message::User {
+                content: UserContent::Text(text),
+                ..
+            }) = context.messages.last()
+            {
+                prompts_in_fn.lock().unwrap().push(text.clone());
+            }
+            let content = if calls_in_fn.fetch_add(1, Ordering::SeqCst) == 0 {
+                AssistantContentBlock::ToolCall {
+                    id: "tool-1".to_string(),
+                    name: "write".to_string(),
+                    arguments: json!({ "path": "note.txt", "content": "written by the agent" }),
+                    thought_signature: None,
+                }
+            } else {
+                AssistantContentBlock::Text {
+                    text: "ok".to_string(),
+                    text_signature: None,
+                }
+            };
+            let stop_reason = match content {
+                AssistantContentBlock::ToolCall { .. } => StopReason::ToolUse,
+                _ => StopReason::Stop,
+            };
+            let message = AssistantMessage {
+                role: "assistant".to_string(),
+                content: vec![content],
+                api: model.api,
+                provider: model.provider,
+                model: model.id,
+                usage: Usage {
+                    input: 0,
+                    output: 0,
+                    cache_read: 0,
+                    cache_write: 0,
+                    reasoning: 0,
+                    total_tokens: 0,
+                    cost: sample_model().cost,
+                },
+                stop_reason: stop_reason.clone(),
+                error_message: None,
+                timestamp: 1,
+                stats: None,
+            };
+            let stream = AssistantMessageEventStream::new();
+            stream.push(AssistantMessageEvent::Done {
+                reason: if stop_reason == StopReason::ToolUse {
+                    DoneReason::ToolUse
+                } else {
+                    DoneReason::Stop
+                },
+                message,
+            });
+            Ok(stream)
+        });
+
+        session
+            .prompt("rewrite the note")
+            .await
+            .expect("first prompt");
+        assert_eq!(
+            std::fs::read_to_string(cwd.join("note.txt")).expect("read note"),
+            "written by the agent"
+        );
+        session.undo_file_changes().expect("undo");
+        assert_eq!(
+            std::fs::read_to_string(cwd.join("note.txt")).expect("read note"),
+            "original"
+        );
+        session.prompt("what now?").await.expect("second prompt");
+
+        // The agent's own write is never reported, so no notice came between its turns.
+        let prompts = prompts.lock().unwrap().clone();
+        assert_eq!(prompts.len(), 2, "{prompts:?}");
+        assert_eq!(
+            prompts.first().map(String::as_str),
+            Some("rewrite the note")
+        );
+        let last = prompts.last().expect("second prompt recorded");
+        assert!(last.starts_with("<file_changes>"), "{last}");
+        assert!(last.contains("- note.txt (modified)"), "{last}");
+        assert!(last.ends_with("what now?"), "{last}");
+    }
+
+    #[tokio::test]
+    async fn configured_tool_parallelism_overlaps_calls_in_one_turn() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cwd = dir.path();
+        let cwd_text = cwd.to_str().expect("utf-8 cwd");
+
+        let runtime = ResolvedRuntime {
+            model: sample_model(),
+            model_catalog: vec![sample_model()],
+            api_key: None,
+            provider_api_keys: HashMap::new(),
+            multi_agent: ResolvedMultiAgentConfig::default(),
+            memory: default_memory_config(),
+            hooks: vec![],
+            post_edit: vec![],
+            skills: vec![],
+            skill_diagnostics: vec![],
+            skill_options: None,
+            project_memory: ProjectMemoryConfig::default(),
+            redaction: RedactionConfig::default(),
+            guards: GuardConfig::default(),
+            sampling: SamplingConfig::default(),
+            stream_transport: StreamTransport::default(),
+            http_transport: HttpTransportConfig::default(),
+            rate_limits: HashMap::new(),
+            tool_failures: ToolFailureConfig::default(),
+            tool_output: ToolOutputConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            worktree: WorktreeConfig::default(),
+            review: DiffReviewConfig::default(),
+            approval: ToolApprovalConfig::default(),
+            loop_guard: ToolLoopGuard::default(),
+            mcp: McpConfig::default(),
+            web_search: WebSearchConfig::default(),
+            theme: None,
+            transport_retry_count: 5,
+            tool_parallelism: 2,
+            discover_models: false,
+        };
+        let mut session = create_session_from_runtime(
+            cwd,
+            SessionManager::create(cwd_text, cwd.join("sessions")).expect("create session"),
+            &runtime,
+            None,
+            false,
+        );
+        assert_eq!(session.tool_parallelism(), 2);
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let in_flight_in_tool = in_flight.clone();
+        let max_in_flight_in_tool = max_in_flight.clone();
+        session.config.tools.push(AgentTool {
+            name: "probe".to_string(),
+            label: "probe".to_string(),
+            description: "Wait briefly".to_string(),
+            parameters: json!({ "type": "object" }),
+            conflict_key: None,
+            execute: Arc::new(move |_tool_call_id: String, _args| -> ToolFuture {
+                let in_flight = in_flight_in_tool.clone();
+                let max_in_flight = max_in_flight_in_tool.clone();
+                Box::pin(async move {
+                    let running = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(running, Ordering::SeqCst);
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    Ok(AgentToolResult {
+                        content: vec![],
+                        details: json!({}),
+                    })
+                })
+            }),
+        });
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        session.config.stream_fn = Arc::new(move |model: Model, _context: Context, _options| {
+            let (content, stop_reason, reason) = if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                let calls = ["probe-1", "probe-2"]
+                    .into_iter()
+                    .map(|id| AssistantContentBlock::ToolCall {
+                        id: id.to_string(),
+                        name: "probe".to_string(),
+                        arguments: json!({}),
+                        thought_signature: None,
+                    })
+                    .collect();
+                (calls, StopReason::ToolUse, DoneReason::ToolUse)
+            } else {
+                let text = AssistantContentBlock::Text {
+                    text: "ok".to_string(),
+                    text_signature: None,
+                };
+                (vec![text], StopReason::Stop, DoneReason::Stop)
+            };
+            let message = AssistantMessage {
+                role: "assistant".to_string(),
+                content,
+                api: model.api,
+                provider: model.provider,
+                model: model.id,
+                usage: Usage {
+                    input: 0,
+                    output: 0,
+                    cache_read: 0,
+                    cache_write: 0,
+                    reasoning: 0,
+                    total_tokens: 0,
+                    cost: sample_model().cost,
+                },
+                stop_reason,
+                error_message: None,
+                timestamp: 1,
+                stats: None,
+            };
+            let stream = AssistantMessageEventStream::new();
+            stream.push(AssistantMessageEvent::Done { reason, message });
+            Ok(stream)
+        });
+
+        session.prompt("probe twice").await.expect("prompt");
+
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn skill_changes_rebuild_system_prompt_and_respect_disabled_skills() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let session_dir = dir.path().join("sessions");
+        let cwd = dir.path();
+        let cwd_text = cwd.to_str().expect("utf-8 cwd");
+        std::fs::create_dir_all(cwd.join("extra")).expect("create skills root");
+
+        let mut skill_options =
+            crate::LoadSkillsOptions::new(cwd.to_path_buf(), cwd.join(".pixy/agents"));
+        skill_options.include_defaults = false;
+        skill_options.skill_paths = vec!["extra".to_string()];
+        let runtime = ResolvedRuntime {
+            model: sample_model(),
+            model_catalog: vec![sample_model()],
+            api_key: None,
+            provider_api_keys: HashMap::new(),
+            multi_agent: ResolvedMultiAgentConfig::default(),
+            memory: default_memory_config(),
+            hooks: vec![],
+            post_edit: vec![],
+            skills: vec![],
+            skill_diagnostics: vec![],
+            skill_options: Some(skill_options),
+            project_memory: ProjectMemoryConfig::default(),
+            redaction: RedactionConfig::default(),
+            guards: GuardConfig::default(),
+            sampling: SamplingConfig::default(),
+            stream_transport: StreamTransport::default(),
+            http_transport: HttpTransportConfig::default(),
+            rate_limits: HashMap::new(),
+            tool_failures: ToolFailureConfig::default(),
+            tool_output: ToolOutputConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            worktree: WorktreeConfig::default(),
+            review: DiffReviewConfig::default(),
+            approval: ToolApprovalConfig::default(),
+            loop_guard: ToolLoopGuard::default(),
+            mcp: McpConfig::default(),
+            web_search: WebSearchConfig::default(),
+            theme: None,
+            transport_retry_count: 5,
+            tool_parallelism: 1,
+            discover_models: false,
+        };
+
+        let mut session = create_session_from_runtime(
+            cwd,
+            SessionManager::create(cwd_text, &session_dir).expect("create session"),
+            &runtime,
+            None,
+            false,
+        );
+        assert!(!session.config.system_prompt.contains("hot-skill"));
+
+        let skill_dir = cwd.join("extra").join("hot-skill");
+        std::fs::create_dir_all(&skill_dir).expect("create skill dir");
+        std::fs::write(
+            skill_dir.join("SKILL.md"),
+            "---\nname: hot-skill\ndescription: added after startup.\n---\n",
+        )
+        .expect("write skill");
+
+        session.reload_skills_if_changed();
+        assert!(session
+            .config
+            .system_prompt
+            .contains("<name>hot-skill</name>"));
+
+        session
+            .set_skill_enabled("hot-skill", false)
+            .expect("disable skill");
+        assert!(!session.config.system_prompt.contains("hot-skill"));
+        assert!(session
+            .describe_skills()
+            .expect("describe skills")
+            .iter()
+            .any(|line| line.starts_with("[off] hot-skill")));
+        assert!(session
+            .render_skill_invocation("hot-skill")
+            .expect_err("disabled skill should not render")
+            .contains("disabled"));
+
+        session
+            .set_skill_enabled("hot-skill", true)
+            .expect("enable skill");
+        let rendered = session
+            .render_skill_invocation("hot-skill")
+            .expect("render skill");
+        assert!(rendered.starts_with("Use the \"hot-skill\" skill"));
+        assert!(session
+            .render_skill_invocation("missing-skill")
+            .expect_err("unknown skill")
+            .contains("unknown skill"));
+    }
+
+    #[test]
+    fn prompt_template_re_renders_on_model_switch_and_branch_change() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let session_dir = dir.path().join("sessions");
+        let cwd = dir.path();
+        let cwd_text = cwd.to_str().expect("utf-8 cwd");
+        std::fs::create_dir_all(cwd.join(".git")).expect("create git dir");
+        std::fs::write(cwd.join(".git/HEAD"), "ref: refs/heads/main\n").expect("write HEAD");
+
+        let mut other_model = sample_model();
+        other_model.id = "other-model".to_string();
+        let runtime = ResolvedRuntime {
+            model: sample_model(),
+            model_catalog: vec![sample_model(), other_model],
+            api_key: None,
+            provider_api_keys: HashMap::new(),
+            multi_agent: ResolvedMultiAgentConfig::default(),
+            memory: default_memory_config(),
+            hooks: vec![],
+            post_edit: vec![],
+            skills: vec![],
+            skill_diagnostics: vec![],
+            skill_options: None,
+            project_memory: ProjectMemoryConfig::default(),
+            redaction: RedactionConfig::default(),
+            guards: GuardConfig::default(),
+            sampling: SamplingConfig::default(),
+            stream_transport: StreamTransport::default(),
+            http_transport: HttpTransportConfig::default(),
+            rate_limits: HashMap::new(),
+            tool_failures: ToolFailureConfig::default(),
+            tool_output: ToolOutputConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            worktree: WorktreeConfig::default(),
+            review: DiffReviewConfig::default(),
+            approval: ToolApprovalConfig::default(),
+            loop_guard: ToolLoopGuard::default(),
+            mcp: McpConfig::default(),
+            web_search: WebSearchConfig::default(),
+            theme: None,
+            transport_retry_count: 5,
+            tool_parallelism: 1,
+            discover_models: false,
+        };
+
+        let mut session = create_session_from_runtime(
+            cwd,
+            SessionManager::create(cwd_text, &session_dir).expect("create session"),
+            &runtime,
+            Some("Model {model} on branch {git_branch} in {cwd}."),
+            false,
+        );
+        assert!(session.config.system_prompt.contains(&format!(
+            "Model openai/test-model on branch main in {cwd_text}."
+        )));
+
+        session.cycle_model_forward().expect("switch model");
+        assert!(session
+            .config
+            .system_prompt
+            .contains("Model openai/other-model on branch main"));
+
+        std::fs::write(cwd.join(".git/HEAD"), "ref: refs/heads/feature/x\n")
+            .expect("switch branch");
+        session.refresh_prompt_variables();
+        assert!(session
+            .config
+            .system_prompt
+            .contains("Model openai/other-model on branch feature/x"));
+    }
 }
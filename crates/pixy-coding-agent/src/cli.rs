@@ -1,19 +1,32 @@
 use std::collections::HashMap;
-use std::io::{self, Write};
+use std::io::{self, BufRead, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::sync::OnceLock;
+use std::sync::{Arc, Mutex, OnceLock};
 
+use crate::agent_session::attachment_placeholder;
 use crate::cli_app::{
-    CliSession, CliSessionFactory, CliSessionRequest, ReplCommand, ReplCommandParser,
+    format_file_changes, CliSession, CliSessionFactory, CliSessionRequest, ReplCommand,
+    ReplCommandParser,
+};
+use crate::headless_output::{
+    assistant_event, error_event, init_event, result_event, text_delta_event, tool_call_event,
+    tool_result_event, usage_event, HeadlessEventWriter, OutputFormat,
+};
+use crate::{
+    serve_mcp_session, worktree_name, AgentMode, AgentSession, AgentSessionStreamUpdate,
+    DiffReviewDecision, DiffReviewRequest, ResolvedRuntime, RuntimeOverrides, SessionWorktree,
+    Skill, WorktreeExitAction,
 };
-use crate::{AgentMode, AgentSession, AgentSessionStreamUpdate, RuntimeOverrides, Skill};
 use clap::{Args, Parser, Subcommand};
-use pixy_ai::{AssistantContentBlock, Message, StopReason, ToolResultContentBlock};
-use pixy_tui::{parse_key_id, KeyBinding, TuiKeyBindings, TuiOptions, TuiTheme};
+use pixy_ai::{AssistantContentBlock, HttpTransport, Message, StopReason, ToolResultContentBlock};
+use pixy_tui::{parse_key_id, KeyBinding, ResumeCandidate, TuiKeyBindings, TuiOptions, TuiTheme};
 use serde::Deserialize;
+use serde_json::Value;
 use tracing_appender::non_blocking::WorkerGuard;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use tracing_subscriber::{
+    fmt::MakeWriter, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter,
+};
 
 const RESUME_PICKER_LIMIT: usize = 10;
 const DEFAULT_PIXY_HOME_DIR_NAME: &str = ".pixy";
@@ -40,9 +53,12 @@ struct Cli {
 }
 
 #[derive(Subcommand, Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 enum RootCommand {
     Cli(ChatArgs),
     Gateway(GatewayArgs),
+    /// Serve the coding tools and a session as an MCP server on stdin and stdout.
+    McpServe(ChatArgs),
 }
 
 #[derive(Args, Debug, Clone)]
@@ -59,6 +75,27 @@ pub struct ChatArgs {
     context_window: Option<u32>,
     #[arg(long)]
     max_tokens: Option<u32>,
+    /// Sampling temperature for every request; overrides `[sampling]` in pixy.toml.
+    #[arg(long)]
+    temperature: Option<f64>,
+    /// Sampling seed for providers that support one, for repeatable runs.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Proxy URL for provider requests; overrides `[transport]` in pixy.toml.
+    #[arg(long)]
+    proxy: Option<String>,
+    /// Comma-separated hosts, domains or CIDR ranges reached without the proxy.
+    #[arg(long, value_delimiter = ',')]
+    no_proxy: Vec<String>,
+    /// PEM bundle of extra root certificates, e.g. for a TLS-intercepting proxy.
+    #[arg(long)]
+    ca_bundle: Option<PathBuf>,
+    /// PEM client certificate for mutual TLS.
+    #[arg(long)]
+    client_cert: Option<PathBuf>,
+    /// PEM private key for `--client-cert`, when kept in a separate file.
+    #[arg(long)]
+    client_key: Option<PathBuf>,
     #[arg(long)]
     agent_dir: Option<PathBuf>,
     #[arg(long)]
@@ -69,8 +106,19 @@ pub struct ChatArgs {
     session_file: Option<PathBuf>,
     #[arg(long)]
     system_prompt: Option<String>,
-    #[arg(long)]
+    /// Run a single prompt and exit. Pass `-` to read the prompt from stdin.
+    #[arg(short = 'p', long)]
     prompt: Option<String>,
+    /// Stop a `--prompt` run after this many assistant turns; exits nonzero if more were needed.
+    #[arg(long)]
+    max_turns: Option<usize>,
+    /// Only print the assistant response for `--prompt` runs.
+    #[arg(short = 'q', long, default_value_t = false)]
+    quiet: bool,
+    /// Output format for `--prompt` runs: human-readable text, a single JSON document, or
+    /// newline-delimited JSON events.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output_format: OutputFormat,
     #[arg(long, default_value_t = false)]
     continue_first: bool,
     #[arg(long, default_value_t = false)]
@@ -85,6 +133,15 @@ pub struct ChatArgs {
     no_tui: bool,
     #[arg(long)]
     theme: Option<String>,
+    /// Run the session in a new git worktree and branch, leaving the current checkout untouched.
+    #[arg(long, default_value_t = false)]
+    worktree: bool,
+    /// Ask for approval of each file diff before edits are written (interactive sessions only).
+    #[arg(long, default_value_t = false)]
+    review: bool,
+    /// Run up to this many tool calls of one turn at once, overriding `tool_parallelism`.
+    #[arg(long)]
+    tool_parallelism: Option<usize>,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -122,10 +179,15 @@ pub async fn run_chat_with_conf(args: ChatArgs, conf_dir: Option<PathBuf>) -> Re
 async fn run_parsed_cli(cli: Cli) -> Result<(), String> {
     let conf_dir = cli.conf_dir.clone();
     init_conf_dir(conf_dir.as_deref());
-    init_tracing();
+    if matches!(cli.command, Some(RootCommand::McpServe(_))) {
+        init_tracing_with_console(io::stderr);
+    } else {
+        init_tracing();
+    }
     match cli.command {
         Some(RootCommand::Cli(args)) => run(args).await,
         Some(RootCommand::Gateway(args)) => run_gateway(args, conf_dir.as_deref()).await,
+        Some(RootCommand::McpServe(args)) => run_mcp_serve(args).await,
         None => run(cli.chat).await,
     }
 }
@@ -175,6 +237,14 @@ fn gateway_command_tokens(command: &GatewaySubcommand, conf_dir: Option<&Path>)
 }
 
 fn init_tracing() {
+    init_tracing_with_console(io::stdout);
+}
+
+/// Sets up logging to the log file and, with `[log] stdout = true`, to `console` as well.
+fn init_tracing_with_console<W>(console: W)
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
     static TRACE_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
 
     let config = load_runtime_log_config("pixy.log");
@@ -194,7 +264,7 @@ fn init_tracing() {
     let file_layer = tracing_subscriber::fmt::layer()
         .with_ansi(false)
         .with_writer(non_blocking);
-    let mut stdout_layer = tracing_subscriber::fmt::layer();
+    let mut stdout_layer = tracing_subscriber::fmt::layer().with_writer(console);
     stdout_layer.set_ansi(false);
 
     let init_result = if config.stdout {
@@ -380,7 +450,44 @@ impl Write for SizeRotatingFileWriter {
     }
 }
 
-async fn run(args: ChatArgs) -> Result<(), String> {
+/// Serves a session over MCP on stdin and stdout until the client disconnects. The chat flags
+/// choose the model, working directory and session as they do for `pixy cli`.
+pub async fn run_mcp_serve_with_conf(
+    args: ChatArgs,
+    conf_dir: Option<PathBuf>,
+) -> Result<(), String> {
+    init_conf_dir(conf_dir.as_deref());
+    // Stdout carries the protocol, so console logging goes to stderr.
+    init_tracing_with_console(io::stderr);
+    run_mcp_serve(args).await
+}
+
+async fn run_mcp_serve(args: ChatArgs) -> Result<(), String> {
+    let (cwd, agent_dir, session_dir) = resolve_session_dirs(&args)?;
+    let session_factory = CliSessionFactory::new(current_pixy_home_dir());
+    let session_request = build_session_request(&args, &cwd);
+    let runtime = session_factory.resolve_runtime(&session_request, &cwd, &agent_dir)?;
+    apply_runtime_transport(&runtime)?;
+    pixy_ai::set_default_retry_policy(pixy_ai::RetryPolicy {
+        max_retries: runtime.transport_retry_count,
+        ..pixy_ai::RetryPolicy::default()
+    });
+    let mut session =
+        session_factory.session_for_runtime(&session_request, runtime, &cwd, &session_dir);
+    let active_session = session.ensure_session()?;
+    active_session.set_max_turns(args.max_turns);
+    let result = serve_mcp_session(
+        active_session,
+        tokio::io::BufReader::new(tokio::io::stdin()),
+        tokio::io::stdout(),
+    )
+    .await;
+    active_session.end_session().await;
+    result
+}
+
+/// The working, agent and session directories selected by `args`.
+fn resolve_session_dirs(args: &ChatArgs) -> Result<(PathBuf, PathBuf, PathBuf), String> {
     let process_cwd =
         std::env::current_dir().map_err(|error| format!("read cwd failed: {error}"))?;
     let cwd = args
@@ -399,9 +506,11 @@ async fn run(args: ChatArgs) -> Result<(), String> {
         .as_ref()
         .map(|path| resolve_path(&cwd, path))
         .unwrap_or_else(|| default_agent_dir().join("sessions"));
+    Ok((cwd, agent_dir, session_dir))
+}
 
-    let session_factory = CliSessionFactory::new(current_pixy_home_dir());
-    let session_request = CliSessionRequest {
+fn build_session_request(args: &ChatArgs, cwd: &Path) -> CliSessionRequest {
+    CliSessionRequest {
         session_file: args.session_file.clone(),
         include_default_skills: !args.no_skills,
         skill_paths: args.skills.clone(),
@@ -412,42 +521,176 @@ async fn run(args: ChatArgs) -> Result<(), String> {
             base_url: args.base_url.clone(),
             context_window: args.context_window,
             max_tokens: args.max_tokens,
+            temperature: args.temperature,
+            seed: args.seed,
+            http_transport: HttpTransport {
+                proxy: args.proxy.clone(),
+                no_proxy: args.no_proxy.clone(),
+                ca_bundle: args
+                    .ca_bundle
+                    .as_deref()
+                    .map(|path| resolve_path(cwd, path)),
+                client_cert: args
+                    .client_cert
+                    .as_deref()
+                    .map(|path| resolve_path(cwd, path)),
+                client_key: args
+                    .client_key
+                    .as_deref()
+                    .map(|path| resolve_path(cwd, path)),
+            },
             ..RuntimeOverrides::default()
         },
         custom_system_prompt: args.system_prompt.clone(),
         no_tools: args.no_tools,
+    }
+}
+
+fn apply_runtime_transport(runtime: &ResolvedRuntime) -> Result<(), String> {
+    runtime.http_transport.apply()?;
+    for (key, limit) in &runtime.rate_limits {
+        pixy_ai::set_rate_limit(key.clone(), limit.clone());
+    }
+    Ok(())
+}
+
+async fn run(args: ChatArgs) -> Result<(), String> {
+    let (cwd, agent_dir, session_dir) = resolve_session_dirs(&args)?;
+    let session_factory = CliSessionFactory::new(current_pixy_home_dir());
+    if args.output_format != OutputFormat::Text && args.prompt.is_none() {
+        return Err("--output-format json|stream-json requires --prompt".to_string());
+    }
+
+    let session_request = build_session_request(&args, &cwd);
+    let runtime = session_factory.resolve_runtime(&session_request, &cwd, &agent_dir)?;
+    apply_runtime_transport(&runtime)?;
+    let worktree = if args.worktree || runtime.worktree.enabled {
+        let worktree = SessionWorktree::create(
+            &cwd,
+            &runtime.worktree,
+            &worktree_name(chrono::Local::now()),
+        )?;
+        eprintln!(
+            "worktree: {} (branch {})",
+            worktree.path.display(),
+            worktree.branch
+        );
+        Some(worktree)
+    } else {
+        None
     };
-    let mut session =
-        session_factory.create_session(&session_request, &cwd, &agent_dir, &session_dir)?;
+    let mut session = match worktree.as_ref() {
+        // Skills and project files are loaded again from inside the worktree.
+        Some(worktree) => session_factory.create_session(
+            &session_request,
+            &worktree.cwd,
+            &agent_dir,
+            &session_dir,
+        )?,
+        None => session_factory.session_for_runtime(&session_request, runtime, &cwd, &session_dir),
+    };
+    if args.review {
+        session.enable_diff_review();
+    }
+    if let Some(tool_parallelism) = args.tool_parallelism {
+        session.set_tool_parallelism(tool_parallelism);
+    }
+    let cwd = worktree
+        .as_ref()
+        .map(|worktree| worktree.cwd.clone())
+        .unwrap_or(cwd);
+    let result = run_session(&args, &mut session, &cwd, &agent_dir).await;
+    match worktree {
+        Some(worktree) => {
+            let on_exit = session.runtime().worktree.on_exit;
+            result.and(finish_worktree(&worktree, on_exit, args.prompt.is_none()))
+        }
+        None => result,
+    }
+}
+
+async fn run_session(
+    args: &ChatArgs,
+    session: &mut CliSession,
+    cwd: &Path,
+    agent_dir: &Path,
+) -> Result<(), String> {
     let runtime = session.runtime().clone();
-    pixy_ai::set_transport_retry_count(runtime.transport_retry_count);
+    pixy_ai::set_default_retry_policy(pixy_ai::RetryPolicy {
+        max_retries: runtime.transport_retry_count,
+        ..pixy_ai::RetryPolicy::default()
+    });
     let runtime_model = runtime.model.clone();
     let discovered_skills = runtime.skills.clone();
     let use_tui = args.prompt.is_none() && !args.no_tui;
 
     if let Some(prompt) = args.prompt.as_deref() {
+        let prompt = read_prompt_input(prompt, io::stdin())?;
         let active_session = session.ensure_session()?;
-        println!(
-            "session: {}",
-            active_session
+        active_session.set_max_turns(args.max_turns);
+        if args.output_format != OutputFormat::Text {
+            let session_file = active_session
                 .session_file()
                 .ok_or_else(|| "session file unavailable".to_string())?
                 .display()
-        );
-        println!("cwd: {}", cwd.display());
-        println!(
-            "model: {}/{}/{}",
-            runtime_model.api, runtime_model.provider, runtime_model.id
-        );
-        run_prompt_streaming_cli(active_session, prompt, !args.hide_tool_results).await?;
-        return Ok(());
+                .to_string();
+            let init = init_event(
+                &session_file,
+                &cwd.display().to_string(),
+                &runtime_model.provider,
+                &runtime_model.id,
+            );
+            let result = run_prompt_headless(
+                active_session,
+                &prompt,
+                io::stdout(),
+                args.output_format,
+                init,
+                args.max_turns,
+            )
+            .await;
+            active_session.end_session().await;
+            return result;
+        }
+        if !args.quiet {
+            println!(
+                "session: {}",
+                active_session
+                    .session_file()
+                    .ok_or_else(|| "session file unavailable".to_string())?
+                    .display()
+            );
+            println!("cwd: {}", cwd.display());
+            println!(
+                "model: {}/{}/{}",
+                runtime_model.api, runtime_model.provider, runtime_model.id
+            );
+        }
+        let show_tool_results = !args.hide_tool_results && !args.quiet;
+        let result = run_prompt_streaming_cli(active_session, &prompt, show_tool_results)
+            .await
+            .and_then(|produced| {
+                prompt_run_status(
+                    &produced,
+                    active_session.turn_limit_reached(),
+                    args.max_turns,
+                )
+            });
+        active_session.end_session().await;
+        return result;
     }
 
+    session.set_auto_title(true);
+    if runtime.discover_models {
+        for warning in session.discover_models().await {
+            eprintln!("warning: {warning}");
+        }
+    }
     if use_tui {
         let theme_name = resolve_tui_theme_name(args.theme.as_deref(), runtime.theme.as_deref())?;
         let theme = TuiTheme::from_name(theme_name.as_str())
             .ok_or_else(|| format!("unsupported theme '{theme_name}', expected dark or light"))?;
-        let status_top = build_status_top_line(&cwd);
+        let status_top = build_status_top_line(cwd);
         let status_left = AgentMode::default().label().to_string();
         let status_right = format_status_model_label(
             runtime_model.provider.as_str(),
@@ -463,7 +706,7 @@ async fn run(args: ChatArgs) -> Result<(), String> {
             })
             .unwrap_or(false);
         let startup_resource_lines =
-            build_startup_resource_lines(&cwd, &agent_dir, &discovered_skills);
+            build_startup_resource_lines(cwd, agent_dir, &discovered_skills);
         let mut tui_options = TuiOptions {
             app_name: "pixy".to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
@@ -477,10 +720,12 @@ async fn run(args: ChatArgs) -> Result<(), String> {
             startup_resource_lines,
             ..TuiOptions::default()
         };
-        if let Some(keybindings) = load_tui_keybindings(&agent_dir) {
+        if let Some(keybindings) = load_tui_keybindings(agent_dir) {
             tui_options.keybindings = keybindings;
         }
-        return pixy_tui::run_tui(&mut session, tui_options).await;
+        let result = pixy_tui::run_tui(session, tui_options).await;
+        session.end_session().await;
+        return result;
     }
 
     if let Some(session_file) = session.session_file() {
@@ -494,11 +739,127 @@ async fn run(args: ChatArgs) -> Result<(), String> {
 
     if args.continue_first {
         let active_session = session.ensure_session()?;
+        attach_stdin_diff_reviewer(active_session);
         run_continue_streaming_cli(active_session, !args.hide_tool_results).await?;
     }
 
-    println!("commands: /new, /continue, /resume [session], /session, /help, /exit");
-    repl_loop(&mut session, !args.hide_tool_results).await
+    println!(
+        "commands: /new, /fork [name], /continue, /resume [session], /undo, /redo, /skills, /skill <name>, /remember [note], /cost, /session, /help, /exit"
+    );
+    let result = repl_loop(session, !args.hide_tool_results).await;
+    session.end_session().await;
+    result
+}
+
+/// Settles the session worktree, asking interactive users what to do with changes when the
+/// configured action is `ask`.
+fn finish_worktree(
+    worktree: &SessionWorktree,
+    on_exit: WorktreeExitAction,
+    interactive: bool,
+) -> Result<(), String> {
+    let action = if on_exit == WorktreeExitAction::Ask && interactive && worktree.has_changes()? {
+        prompt_worktree_exit_action(worktree)?
+    } else {
+        on_exit
+    };
+    let outcome = worktree.finish(action)?;
+    eprintln!("{outcome}");
+    Ok(())
+}
+
+fn prompt_worktree_exit_action(worktree: &SessionWorktree) -> Result<WorktreeExitAction, String> {
+    println!(
+        "worktree {} has changes on branch {}",
+        worktree.path.display(),
+        worktree.branch
+    );
+    loop {
+        print!("[m]erge back, create [p]ull request, [k]eep, or [d]iscard? [k]: ");
+        io::stdout()
+            .flush()
+            .map_err(|error| format!("stdout flush failed: {error}"))?;
+
+        let mut answer = String::new();
+        let read = io::stdin()
+            .read_line(&mut answer)
+            .map_err(|error| format!("stdin read failed: {error}"))?;
+        if read == 0 || answer.trim().is_empty() {
+            return Ok(WorktreeExitAction::Keep);
+        }
+        match WorktreeExitAction::parse(&answer) {
+            Some(WorktreeExitAction::Ask) | None => {
+                eprintln!("expected m, p, k or d");
+            }
+            Some(action) => return Ok(action),
+        }
+    }
+}
+
+/// Prompts on the terminal for each proposed file change while a REPL run is in progress.
+fn attach_stdin_diff_reviewer(session: &AgentSession) {
+    let prompt_lock = Arc::new(Mutex::new(()));
+    session.attach_diff_reviewer(move |request| {
+        let prompt_lock = prompt_lock.clone();
+        tokio::task::spawn_blocking(move || {
+            // Parallel sub-agents can propose changes at once; ask about one at a time.
+            let _guard = prompt_lock.lock();
+            let decision = prompt_diff_review(&request, io::stdin().lock(), io::stdout())
+                .unwrap_or(DiffReviewDecision::Reject { feedback: None });
+            request.respond(decision);
+        });
+    });
+}
+
+fn prompt_diff_review(
+    request: &DiffReviewRequest,
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> Result<DiffReviewDecision, String> {
+    let write_error = |error: io::Error| format!("stdout write failed: {error}");
+    writeln!(
+        output,
+        "\nreview {} {}:\n{}",
+        request.tool, request.path, request.diff
+    )
+    .map_err(write_error)?;
+    loop {
+        write!(
+            output,
+            "apply? [y]es, [n]o [reason], [a]ll for this session: "
+        )
+        .map_err(write_error)?;
+        output.flush().map_err(write_error)?;
+
+        let mut answer = String::new();
+        let read = input
+            .read_line(&mut answer)
+            .map_err(|error| format!("stdin read failed: {error}"))?;
+        if read == 0 {
+            return Ok(DiffReviewDecision::Reject { feedback: None });
+        }
+        match parse_diff_review_answer(&answer) {
+            Some(decision) => return Ok(decision),
+            None => writeln!(output, "expected y, n or a").map_err(write_error)?,
+        }
+    }
+}
+
+/// Parses `y`, `a` or `n [reason]`; the reason is passed to the model with the rejection.
+fn parse_diff_review_answer(answer: &str) -> Option<DiffReviewDecision> {
+    let answer = answer.trim();
+    let (word, reason) = answer
+        .split_once(char::is_whitespace)
+        .map(|(word, reason)| (word, reason.trim()))
+        .unwrap_or((answer, ""));
+    match word.to_ascii_lowercase().as_str() {
+        "y" | "yes" => Some(DiffReviewDecision::Approve),
+        "a" | "all" => Some(DiffReviewDecision::ApproveAll),
+        "n" | "no" => Some(DiffReviewDecision::Reject {
+            feedback: (!reason.is_empty()).then(|| reason.to_string()),
+        }),
+        _ => None,
+    }
 }
 
 fn build_status_top_line(cwd: &Path) -> String {
@@ -552,14 +913,68 @@ async fn repl_loop(session: &mut CliSession, show_tool_results: bool) -> Result<
                 Ok(path) => println!("new session: {}", path.display()),
                 Err(error) => eprintln!("new session failed: {error}"),
             },
+            ReplCommand::Fork { name } => match session.fork_session(name.as_deref()) {
+                Ok(path) => println!("forked session: {}", path.display()),
+                Err(error) => eprintln!("fork failed: {error}"),
+            },
+            ReplCommand::Undo => match session.undo_file_changes() {
+                Ok(paths) => println!("{}", format_file_changes("reverted", &paths)),
+                Err(error) => eprintln!("undo failed: {error}"),
+            },
+            ReplCommand::Redo => match session.redo_file_changes() {
+                Ok(paths) => println!("{}", format_file_changes("reapplied", &paths)),
+                Err(error) => eprintln!("redo failed: {error}"),
+            },
+            ReplCommand::Skills { args } => match session.skills_command(&args) {
+                Ok(lines) => {
+                    for line in lines {
+                        println!("{line}");
+                    }
+                }
+                Err(error) => eprintln!("skills failed: {error}"),
+            },
+            ReplCommand::Skill { args } => {
+                let active_session = match session.ensure_session() {
+                    Ok(active) => active,
+                    Err(error) => {
+                        eprintln!("skill failed: {error}");
+                        continue;
+                    }
+                };
+                let text = match active_session.render_skill_invocation(&args) {
+                    Ok(text) => text,
+                    Err(error) => {
+                        eprintln!("skill failed: {error}");
+                        continue;
+                    }
+                };
+                attach_stdin_diff_reviewer(active_session);
+                if let Err(error) =
+                    run_prompt_streaming_cli(active_session, text.as_str(), show_tool_results).await
+                {
+                    eprintln!("prompt failed: {error}");
+                }
+            }
+            ReplCommand::Remember { note } => match session.remember(note.as_deref()).await {
+                Ok(status) => println!("{status}"),
+                Err(error) => eprintln!("remember failed: {error}"),
+            },
+            ReplCommand::Cost => match session.cost_report() {
+                Ok(report) => println!("{}", report.render()),
+                Err(error) => eprintln!("cost failed: {error}"),
+            },
             ReplCommand::Resume { target } => {
                 let target = if let Some(target) = target {
                     Some(target)
                 } else {
                     match prompt_resume_target_selection(
-                        session.recent_resumable_sessions(RESUME_PICKER_LIMIT)?,
+                        session
+                            .recent_resumable_session_candidates(RESUME_PICKER_LIMIT)?
+                            .into_iter()
+                            .map(ResumeCandidate::from)
+                            .collect(),
                     ) {
-                        Ok(Some(path)) => Some(path.display().to_string()),
+                        Ok(Some(candidate)) => Some(candidate.session_ref),
                         Ok(None) => {
                             println!("resume cancelled");
                             continue;
@@ -580,12 +995,21 @@ async fn repl_loop(session: &mut CliSession, show_tool_results: bool) -> Result<
             ReplCommand::Help => {
                 println!("commands:");
                 println!("  /new       start a new session and reset current context");
+                println!("  /fork [name]  branch the current history into a new session file");
                 println!(
                     "  /continue  continue from current context without adding a user message"
                 );
                 println!(
                     "  /resume [session]  choose from recent sessions (or pass a session file directly)"
                 );
+                println!("  /undo      revert files changed by the last agent run");
+                println!("  /redo      re-apply the last undone file changes");
+                println!(
+                    "  /skills [reload|enable <name>|disable <name>]  list, reload or toggle skills"
+                );
+                println!("  /skill <name> [key=value ...]  run a skill with arguments");
+                println!("  /remember [note]  save a note, or distill session learnings, to project memory");
+                println!("  /cost      show token usage and cost for this session");
                 println!("  /session   print current session file path");
                 println!("  /help      show this help");
                 println!("  /exit      quit");
@@ -605,6 +1029,7 @@ async fn repl_loop(session: &mut CliSession, show_tool_results: bool) -> Result<
                         continue;
                     }
                 };
+                attach_stdin_diff_reviewer(active_session);
                 if let Err(error) =
                     run_continue_streaming_cli(active_session, show_tool_results).await
                 {
@@ -619,6 +1044,7 @@ async fn repl_loop(session: &mut CliSession, show_tool_results: bool) -> Result<
                         continue;
                     }
                 };
+                attach_stdin_diff_reviewer(active_session);
                 if let Err(error) =
                     run_prompt_streaming_cli(active_session, text.as_str(), show_tool_results).await
                 {
@@ -629,23 +1055,19 @@ async fn repl_loop(session: &mut CliSession, show_tool_results: bool) -> Result<
     }
 }
 
-fn prompt_resume_target_selection(candidates: Vec<PathBuf>) -> Result<Option<PathBuf>, String> {
+fn prompt_resume_target_selection(
+    candidates: Vec<ResumeCandidate>,
+) -> Result<Option<ResumeCandidate>, String> {
     if candidates.is_empty() {
         return Err("No historical sessions available to resume".to_string());
     }
 
     println!("recent sessions:");
     for (index, candidate) in candidates.iter().enumerate() {
-        println!(
-            "  {}. {}",
-            index + 1,
-            format_resume_candidate_name(candidate)
-        );
+        println!("  {:>2}. {}", index + 1, candidate.headline());
+        println!("      {}", candidate.details());
     }
-    println!(
-        "  0. latest ({})",
-        format_resume_candidate_name(&candidates[0])
-    );
+    println!("   0. latest ({})", candidates[0].title);
 
     loop {
         print!(
@@ -668,17 +1090,10 @@ fn prompt_resume_target_selection(candidates: Vec<PathBuf>) -> Result<Option<Pat
     }
 }
 
-fn format_resume_candidate_name(path: &Path) -> String {
-    path.file_name()
-        .and_then(|name| name.to_str())
-        .map(ToOwned::to_owned)
-        .unwrap_or_else(|| path.display().to_string())
-}
-
-fn resolve_resume_picker_selection(
-    candidates: &[PathBuf],
+fn resolve_resume_picker_selection<T: Clone>(
+    candidates: &[T],
     raw_input: &str,
-) -> Result<Option<PathBuf>, String> {
+) -> Result<Option<T>, String> {
     if candidates.is_empty() {
         return Err("No historical sessions available to resume".to_string());
     }
@@ -709,11 +1124,57 @@ fn resolve_resume_picker_selection(
     Ok(Some(candidates[choice - 1].clone()))
 }
 
+fn read_prompt_input(prompt: &str, mut stdin: impl Read) -> Result<String, String> {
+    if prompt != "-" {
+        return Ok(prompt.to_string());
+    }
+    let mut input = String::new();
+    stdin
+        .read_to_string(&mut input)
+        .map_err(|error| format!("read prompt from stdin failed: {error}"))?;
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("prompt from stdin is empty".to_string());
+    }
+    Ok(input.to_string())
+}
+
+/// Maps the outcome of a `--prompt` run to the process result so shell pipelines see a
+/// nonzero exit code on provider errors, aborts and `--max-turns` exhaustion.
+fn prompt_run_status(
+    produced: &[Message],
+    turn_limit_reached: bool,
+    max_turns: Option<usize>,
+) -> Result<(), String> {
+    if turn_limit_reached {
+        return Err(format!(
+            "stopped after reaching --max-turns {}",
+            max_turns.unwrap_or_default()
+        ));
+    }
+    let last_assistant = produced.iter().rev().find_map(|message| match message {
+        Message::Assistant {
+            stop_reason,
+            error_message,
+            ..
+        } => Some((stop_reason, error_message)),
+        _ => None,
+    });
+    match last_assistant {
+        Some((StopReason::Error, error_message)) => Err(error_message
+            .clone()
+            .unwrap_or_else(|| "agent run failed".to_string())),
+        Some((StopReason::Aborted, _)) => Err("agent run aborted".to_string()),
+        Some(_) => Ok(()),
+        None => Err("agent run produced no assistant response".to_string()),
+    }
+}
+
 async fn run_prompt_streaming_cli(
     session: &mut AgentSession,
     input: &str,
     show_tool_results: bool,
-) -> Result<(), String> {
+) -> Result<Vec<Message>, String> {
     let mut renderer = CliStreamRenderer::new(io::stdout(), show_tool_results);
     let mut render_error: Option<String> = None;
     let produced = session
@@ -734,7 +1195,80 @@ async fn run_prompt_streaming_cli(
     if !renderer.saw_updates() {
         render_messages(&produced, show_tool_results);
     }
-    Ok(())
+    print_cost_summary(session);
+    Ok(produced)
+}
+
+async fn run_prompt_headless<W: Write>(
+    session: &mut AgentSession,
+    input: &str,
+    output: W,
+    format: OutputFormat,
+    init: Value,
+    max_turns: Option<usize>,
+) -> Result<(), String> {
+    let mut writer = HeadlessEventWriter::new(output, format);
+    writer.emit(init)?;
+    let mut write_error: Option<String> = None;
+    let run_result = session
+        .prompt_streaming(input, |update| {
+            if write_error.is_some() {
+                return;
+            }
+            let emitted = match update {
+                AgentSessionStreamUpdate::AssistantTextDelta(delta) if !delta.is_empty() => {
+                    writer.emit_streaming(text_delta_event(&delta))
+                }
+                AgentSessionStreamUpdate::AssistantMessageEnd(text) => {
+                    writer.emit(assistant_event(&text))
+                }
+                AgentSessionStreamUpdate::ToolCallStart {
+                    tool_call_id,
+                    tool_name,
+                    args,
+                } => writer.emit(tool_call_event(&tool_call_id, &tool_name, &args)),
+                AgentSessionStreamUpdate::ToolCallEnd {
+                    tool_call_id,
+                    tool_name,
+                    content,
+                    is_error,
+                } => writer.emit(tool_result_event(
+                    &tool_call_id,
+                    &tool_name,
+                    &content,
+                    is_error,
+                )),
+                _ => Ok(()),
+            };
+            if let Err(error) = emitted {
+                write_error = Some(error);
+            }
+        })
+        .await;
+    if let Some(error) = write_error {
+        return Err(error);
+    }
+    let produced = match run_result {
+        Ok(produced) => produced,
+        Err(error) => {
+            writer.emit(error_event(&error))?;
+            let mut result = result_event(&[], usage_event(&[]));
+            result["errorMessage"] = Value::String(error.clone());
+            writer.finish(result)?;
+            return Err(error);
+        }
+    };
+
+    let usage = usage_event(&produced);
+    writer.emit(usage.clone())?;
+    let mut result = result_event(&produced, usage);
+    let status = prompt_run_status(&produced, session.turn_limit_reached(), max_turns);
+    if let Err(error) = &status {
+        result["isError"] = Value::Bool(true);
+        result["errorMessage"] = Value::String(error.clone());
+    }
+    writer.finish(result)?;
+    status
 }
 
 async fn run_continue_streaming_cli(
@@ -761,9 +1295,17 @@ async fn run_continue_streaming_cli(
     if !renderer.saw_updates() {
         render_messages(&produced, show_tool_results);
     }
+    print_cost_summary(session);
     Ok(())
 }
 
+fn print_cost_summary(session: &AgentSession) {
+    let report = session.cost_report();
+    if report.last_turn.total_tokens > 0 {
+        println!("{}", report.summary_line());
+    }
+}
+
 struct CliStreamRenderer<W: Write> {
     writer: W,
     show_tool_results: bool,
@@ -854,6 +1396,23 @@ impl<W: Write> CliStreamRenderer<W> {
                 writeln!(self.writer, "{line}")
                     .map_err(|error| format!("stdout write failed: {error}"))?;
             }
+            AgentSessionStreamUpdate::SubagentProgress {
+                task_id,
+                subagent,
+                line,
+            } => {
+                return self.on_update(AgentSessionStreamUpdate::ToolLine(format!(
+                    "Subagent {subagent} (task_id={task_id}): {line}"
+                )));
+            }
+            // The todo tool result is already printed as a tool line.
+            AgentSessionStreamUpdate::Todos(_) => {}
+            AgentSessionStreamUpdate::UsageDelta(_)
+            | AgentSessionStreamUpdate::ToolCallPreview(_)
+            | AgentSessionStreamUpdate::Stats(_)
+            | AgentSessionStreamUpdate::AssistantMessageEnd(_)
+            | AgentSessionStreamUpdate::ToolCallStart { .. }
+            | AgentSessionStreamUpdate::ToolCallEnd { .. } => {}
         }
         Ok(())
     }
@@ -985,7 +1544,7 @@ impl MarkdownCodeBlockRenderer {
             write!(writer, "{line}")?;
         }
         if append_newline {
-            write!(writer, "\n")?;
+            writeln!(writer)?;
         }
         Ok(())
     }
@@ -1279,6 +1838,10 @@ fn render_messages_to_writer<W: Write>(
                             }
                         }
                         AssistantContentBlock::ToolCall { .. } => {}
+                        AssistantContentBlock::Image { data, mime_type }
+                        | AssistantContentBlock::Audio { data, mime_type } => {
+                            writeln!(writer, "{}", attachment_placeholder(mime_type, data))?;
+                        }
                     }
                 }
 
@@ -1308,8 +1871,8 @@ fn render_messages_to_writer<W: Write>(
                             ToolResultContentBlock::Text { text, .. } => {
                                 writeln!(writer, "{text}")?
                             }
-                            ToolResultContentBlock::Image { .. } => {
-                                writeln!(writer, "(image tool result omitted)")?
+                            ToolResultContentBlock::Image { data, mime_type } => {
+                                writeln!(writer, "{}", attachment_placeholder(mime_type, data))?
                             }
                         }
                     }
@@ -1406,12 +1969,10 @@ fn default_agent_dir() -> PathBuf {
 }
 
 fn first_non_empty<const N: usize>(candidates: [Option<String>; N]) -> Option<String> {
-    for candidate in candidates {
-        if let Some(value) = candidate {
-            let trimmed = value.trim();
-            if !trimmed.is_empty() {
-                return Some(trimmed.to_string());
-            }
+    for value in candidates.into_iter().flatten() {
+        let trimmed = value.trim();
+        if !trimmed.is_empty() {
+            return Some(trimmed.to_string());
         }
     }
     None
@@ -1,11 +1,12 @@
 use std::path::{Path, PathBuf};
 
+use pixy_agent_core::AgentRunMetrics;
 use pixy_ai::Message;
 
 use crate::{
     agent_session::{build_session_resume_candidate, SessionResumeCandidate},
     create_session_from_runtime, AgentSession, ResolvedRuntime, RuntimeLoadOptions,
-    RuntimeOverrides, SessionManager,
+    RuntimeOverrides, SessionCostReport, SessionManager, TodoItem,
 };
 
 #[derive(Debug, Clone)]
@@ -56,18 +57,29 @@ impl CliSessionFactory {
         session_dir: &Path,
     ) -> Result<CliSession, String> {
         let runtime = self.resolve_runtime(request, cwd, agent_dir)?;
+        Ok(self.session_for_runtime(request, runtime, cwd, session_dir))
+    }
+
+    /// Builds a session around an already resolved runtime.
+    pub(crate) fn session_for_runtime(
+        &self,
+        request: &CliSessionRequest,
+        runtime: ResolvedRuntime,
+        cwd: &Path,
+        session_dir: &Path,
+    ) -> CliSession {
         let resolved_session_file = request
             .session_file
             .as_ref()
             .map(|path| resolve_path(cwd, path));
-        Ok(CliSession::new(
+        CliSession::new(
             cwd.to_path_buf(),
             session_dir.to_path_buf(),
             runtime,
             request.custom_system_prompt.clone(),
             request.no_tools,
             resolved_session_file,
-        ))
+        )
     }
 }
 
@@ -79,6 +91,7 @@ pub(crate) struct CliSession {
     no_tools: bool,
     resolved_session_file: Option<PathBuf>,
     session: Option<AgentSession>,
+    auto_title: bool,
 }
 
 impl CliSession {
@@ -98,9 +111,43 @@ impl CliSession {
             no_tools,
             resolved_session_file,
             session: None,
+            auto_title: false,
         }
     }
 
+    /// Titles sessions after their first exchange; used by the interactive front ends only so
+    /// one-shot prompts cost a single model request.
+    pub(crate) fn set_auto_title(&mut self, enabled: bool) {
+        self.auto_title = enabled;
+        if let Some(session) = self.session.as_mut() {
+            session.set_auto_title(enabled);
+        }
+    }
+
+    /// Turns on diff review for sessions installed from now on.
+    pub(crate) fn enable_diff_review(&mut self) {
+        self.runtime.review.enabled = true;
+    }
+
+    /// Overrides how many tool calls of one turn may run at once, for the active session and
+    /// any installed later.
+    pub(crate) fn set_tool_parallelism(&mut self, tool_parallelism: usize) {
+        self.runtime.tool_parallelism = tool_parallelism.max(1);
+        if let Some(session) = self.session.as_mut() {
+            session.set_tool_parallelism(tool_parallelism);
+        }
+    }
+
+    /// Adds the models providers list to the catalog used for model cycling, including the
+    /// active session's. Returns a warning per provider that could not be listed.
+    pub(crate) async fn discover_models(&mut self) -> Vec<String> {
+        let warnings = self.runtime.discover_models().await;
+        if let Some(session) = self.session.as_mut() {
+            session.set_model_catalog(self.runtime.model_catalog.clone());
+        }
+        warnings
+    }
+
     pub(crate) fn runtime(&self) -> &ResolvedRuntime {
         &self.runtime
     }
@@ -118,6 +165,21 @@ impl CliSession {
             .map(|session| session.build_session_context().messages)
     }
 
+    pub(crate) fn todos(&self) -> Vec<TodoItem> {
+        self.session
+            .as_ref()
+            .map(AgentSession::todos)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn context_usage(&self) -> Option<(u64, u64)> {
+        self.session.as_ref().map(AgentSession::context_usage)
+    }
+
+    pub(crate) fn metrics_snapshot(&self) -> Option<AgentRunMetrics> {
+        self.session.as_ref().map(AgentSession::metrics_snapshot)
+    }
+
     pub(crate) fn ensure_session(&mut self) -> Result<&mut AgentSession, String> {
         if self.session.is_none() {
             let manager = if let Some(session_file) = self.resolved_session_file.take() {
@@ -150,6 +212,36 @@ impl CliSession {
             .start_new_session()
     }
 
+    pub(crate) fn fork_session(&mut self, name: Option<&str>) -> Result<PathBuf, String> {
+        self.ensure_session()?.fork_session(name)
+    }
+
+    pub(crate) async fn end_session(&mut self) {
+        if let Some(session) = self.session.as_mut() {
+            session.end_session().await;
+        }
+    }
+
+    pub(crate) fn undo_file_changes(&mut self) -> Result<Vec<PathBuf>, String> {
+        self.ensure_session()?.undo_file_changes()
+    }
+
+    pub(crate) fn redo_file_changes(&mut self) -> Result<Vec<PathBuf>, String> {
+        self.ensure_session()?.redo_file_changes()
+    }
+
+    pub(crate) fn skills_command(&mut self, args: &str) -> Result<Vec<String>, String> {
+        run_skills_command(self.ensure_session()?, args)
+    }
+
+    pub(crate) async fn remember(&mut self, note: Option<&str>) -> Result<String, String> {
+        self.ensure_session()?.remember(note).await
+    }
+
+    pub(crate) fn cost_report(&mut self) -> Result<SessionCostReport, String> {
+        Ok(self.ensure_session()?.cost_report())
+    }
+
     pub(crate) fn resume(&mut self, target: Option<&str>) -> Result<PathBuf, String> {
         if let Some(session) = self.session.as_mut() {
             return session.resume(target);
@@ -191,13 +283,14 @@ impl CliSession {
     }
 
     fn install_session(&mut self, session_manager: SessionManager) {
-        let session = create_session_from_runtime(
+        let mut session = create_session_from_runtime(
             &self.cwd,
             session_manager,
             &self.runtime,
             self.custom_system_prompt.as_deref(),
             self.no_tools,
         );
+        session.set_auto_title(self.auto_title);
         self.session = Some(session);
         self.resolved_session_file = None;
     }
@@ -211,7 +304,7 @@ impl CliSession {
     }
 }
 
-fn resolve_resume_target_without_active_session(
+pub(crate) fn resolve_resume_target_without_active_session(
     target: Option<&str>,
     cwd: &Path,
     session_dir: &Path,
@@ -333,6 +426,13 @@ fn paths_equal(left: &Path, right: &Path) -> bool {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum ReplCommand {
     NewSession,
+    Fork { name: Option<String> },
+    Undo,
+    Redo,
+    Skills { args: String },
+    Skill { args: String },
+    Remember { note: Option<String> },
+    Cost,
     Resume { target: Option<String> },
     Continue,
     Session,
@@ -354,6 +454,48 @@ impl ReplCommandParser {
             return Some(ReplCommand::NewSession);
         }
 
+        if let Some(rest) = trimmed.strip_prefix("/fork") {
+            if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+                let name = rest.trim();
+                return Some(ReplCommand::Fork {
+                    name: if name.is_empty() {
+                        None
+                    } else {
+                        Some(name.to_string())
+                    },
+                });
+            }
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("/skills") {
+            if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+                return Some(ReplCommand::Skills {
+                    args: rest.trim().to_string(),
+                });
+            }
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("/skill") {
+            if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+                return Some(ReplCommand::Skill {
+                    args: rest.trim().to_string(),
+                });
+            }
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("/remember") {
+            if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+                let note = rest.trim();
+                return Some(ReplCommand::Remember {
+                    note: if note.is_empty() {
+                        None
+                    } else {
+                        Some(note.to_string())
+                    },
+                });
+            }
+        }
+
         if let Some(rest) = trimmed.strip_prefix("/resume") {
             let target = rest.trim();
             return Some(ReplCommand::Resume {
@@ -369,7 +511,10 @@ impl ReplCommandParser {
             "/exit" | "/quit" => Some(ReplCommand::Exit),
             "/help" | "?" => Some(ReplCommand::Help),
             "/session" => Some(ReplCommand::Session),
+            "/cost" => Some(ReplCommand::Cost),
             "/continue" => Some(ReplCommand::Continue),
+            "/undo" => Some(ReplCommand::Undo),
+            "/redo" => Some(ReplCommand::Redo),
             _ => Some(ReplCommand::Prompt {
                 text: trimmed.to_string(),
             }),
@@ -377,6 +522,70 @@ impl ReplCommandParser {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SkillsCommand {
+    List,
+    Reload,
+    Enable { name: String },
+    Disable { name: String },
+}
+
+impl SkillsCommand {
+    pub(crate) fn parse(args: &str) -> Result<Self, String> {
+        let mut parts = args.split_whitespace();
+        let command = match (parts.next(), parts.next()) {
+            (None, _) | (Some("list"), None) => Self::List,
+            (Some("reload"), None) => Self::Reload,
+            (Some("enable"), Some(name)) => Self::Enable {
+                name: name.to_string(),
+            },
+            (Some("disable"), Some(name)) => Self::Disable {
+                name: name.to_string(),
+            },
+            _ => return Err(SKILLS_USAGE.to_string()),
+        };
+        if parts.next().is_some() {
+            return Err(SKILLS_USAGE.to_string());
+        }
+        Ok(command)
+    }
+}
+
+const SKILLS_USAGE: &str = "usage: /skills [list|reload|enable <name>|disable <name>]";
+
+pub(crate) fn run_skills_command(
+    session: &mut AgentSession,
+    args: &str,
+) -> Result<Vec<String>, String> {
+    match SkillsCommand::parse(args)? {
+        SkillsCommand::List => session.describe_skills(),
+        SkillsCommand::Reload => {
+            let count = session.reload_skills()?;
+            let mut lines = vec![format!("reloaded {count} skills")];
+            lines.extend(session.describe_skills()?);
+            Ok(lines)
+        }
+        SkillsCommand::Enable { name } => {
+            session.set_skill_enabled(&name, true)?;
+            Ok(vec![format!("enabled skill: {name}")])
+        }
+        SkillsCommand::Disable { name } => {
+            session.set_skill_enabled(&name, false)?;
+            Ok(vec![format!("disabled skill: {name}")])
+        }
+    }
+}
+
+pub(crate) fn format_file_changes(action: &str, paths: &[PathBuf]) -> String {
+    let noun = if paths.len() == 1 { "file" } else { "files" };
+    let listed = paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{action} {} {noun}: {listed}", paths.len())
+}
+
 fn resolve_path(cwd: &Path, path: &Path) -> PathBuf {
     if path.is_absolute() {
         path.to_path_buf()
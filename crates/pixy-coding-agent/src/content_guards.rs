@@ -0,0 +1,40 @@
+//! Content guards on model traffic, configured by `[guards]` in `pixy.toml`.
+//!
+//! Guards see every prompt before it is sent and every reply before it is shown, so they
+//! cover what tool-output redaction cannot: secrets pasted by the user or echoed back by the
+//! model, and terms a deployment must never send or show. They apply to CLI, TUI and gateway
+//! sessions alike.
+
+use std::sync::Arc;
+
+use pixy_ai::{BlockedTermsGuard, ContentGuardRef};
+use serde::Deserialize;
+
+use crate::secret_redaction::SecretRedactor;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct GuardConfig {
+    /// Masks secrets in user messages and assistant replies with the `[redaction]` rules.
+    pub redact_messages: bool,
+    /// Rejects prompts and replies that mention any of these terms, ignoring case.
+    pub blocked_terms: Vec<String>,
+}
+
+impl GuardConfig {
+    /// Guards for a session, in the order they run. Message redaction needs `redactor`, so it
+    /// is skipped when `[redaction]` is disabled.
+    pub fn guards(&self, redactor: Option<&Arc<SecretRedactor>>) -> Vec<ContentGuardRef> {
+        let mut guards: Vec<ContentGuardRef> = Vec::new();
+        if self.redact_messages {
+            if let Some(redactor) = redactor {
+                guards.push(redactor.clone());
+            }
+        }
+        let blocked_terms = BlockedTermsGuard::new(self.blocked_terms.iter().cloned());
+        if !blocked_terms.is_empty() {
+            guards.push(Arc::new(blocked_terms));
+        }
+        guards
+    }
+}
@@ -0,0 +1,357 @@
+//! Interactive review of file changes before the edit and write tools touch disk.
+//!
+//! When review is enabled, every proposed change is rendered as a unified diff and handed to the
+//! reviewer attached by the front end (the TUI or the CLI prompt). The tool waits for the
+//! decision and only writes the file once it is approved; a rejection is reported back to the
+//! model as a failed tool call. Runs without an attached reviewer, such as `--prompt`, write
+//! through unreviewed.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Deserialize;
+use tokio::sync::oneshot;
+
+/// Number of unchanged lines shown around each hunk.
+const DIFF_CONTEXT_LINES: usize = 3;
+/// Longest diff handed to the reviewer; the rest is summarized.
+const MAX_REVIEW_DIFF_LINES: usize = 400;
+/// Above this many line pairs the changed region is shown as a whole-block replacement.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+pub(crate) type SharedDiffReview = Arc<DiffReview>;
+
+type Reviewer = Arc<dyn Fn(DiffReviewRequest) + Send + Sync>;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct DiffReviewConfig {
+    /// Holds back edits until the user approves each file diff in interactive sessions.
+    pub enabled: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffReviewDecision {
+    Approve,
+    /// Approves this change and every later one in the session.
+    ApproveAll,
+    Reject {
+        feedback: Option<String>,
+    },
+}
+
+/// A proposed file change waiting for the user's decision.
+#[derive(Debug)]
+pub struct DiffReviewRequest {
+    pub tool: String,
+    pub path: String,
+    pub diff: String,
+    responder: oneshot::Sender<DiffReviewDecision>,
+}
+
+impl DiffReviewRequest {
+    /// Dropping a request without responding rejects the change.
+    pub fn respond(self, decision: DiffReviewDecision) {
+        let _ = self.responder.send(decision);
+    }
+}
+
+#[derive(Default)]
+pub struct DiffReview {
+    reviewer: Mutex<Option<Reviewer>>,
+    auto_approve: AtomicBool,
+}
+
+impl DiffReview {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Routes later review requests to `reviewer`, replacing any previous one.
+    pub fn attach(&self, reviewer: impl Fn(DiffReviewRequest) + Send + Sync + 'static) {
+        if let Ok(mut slot) = self.reviewer.lock() {
+            *slot = Some(Arc::new(reviewer));
+        }
+    }
+
+    pub fn auto_approve(&self) -> bool {
+        self.auto_approve.load(Ordering::Relaxed)
+    }
+
+    /// Waits for the user to approve the change from `before` to `after`; `before` is `None` for
+    /// new files. Returns the message reported to the model when the change is rejected.
+    pub(crate) async fn review(
+        &self,
+        tool: &str,
+        path: &str,
+        before: Option<&str>,
+        after: &str,
+    ) -> Result<(), String> {
+        if self.auto_approve() {
+            return Ok(());
+        }
+        let Some(reviewer) = self
+            .reviewer
+            .lock()
+            .ok()
+            .and_then(|reviewer| reviewer.clone())
+        else {
+            return Ok(());
+        };
+
+        let (responder, decision) = oneshot::channel();
+        reviewer(DiffReviewRequest {
+            tool: tool.to_string(),
+            path: path.to_string(),
+            diff: truncate_diff(unified_diff(path, before, after)),
+            responder,
+        });
+        match decision.await {
+            Ok(DiffReviewDecision::Approve) => Ok(()),
+            Ok(DiffReviewDecision::ApproveAll) => {
+                self.auto_approve.store(true, Ordering::Relaxed);
+                Ok(())
+            }
+            Ok(DiffReviewDecision::Reject {
+                feedback: Some(feedback),
+            }) if !feedback.trim().is_empty() => Err(format!(
+                "The user rejected the change to {path}; nothing was written. Feedback: {}",
+                feedback.trim()
+            )),
+            Ok(DiffReviewDecision::Reject { .. }) | Err(_) => Err(format!(
+                "The user rejected the change to {path}; nothing was written."
+            )),
+        }
+    }
+}
+
+/// Unified diff of `before` (`None` for a new file) against `after`.
+pub fn unified_diff(path: &str, before: Option<&str>, after: &str) -> String {
+    let old_lines = before
+        .map(|text| text.lines().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let new_lines = after.lines().collect::<Vec<_>>();
+    let ops = diff_lines(&old_lines, &new_lines);
+
+    let mut out = vec![
+        match before {
+            Some(_) => format!("--- a/{path}"),
+            None => "--- /dev/null".to_string(),
+        },
+        format!("+++ b/{path}"),
+    ];
+    for (start, end) in hunk_ranges(&ops) {
+        let old_start = ops[..start].iter().filter(|op| op.0 != '+').count();
+        let new_start = ops[..start].iter().filter(|op| op.0 != '-').count();
+        let old_len = ops[start..end].iter().filter(|op| op.0 != '+').count();
+        let new_len = ops[start..end].iter().filter(|op| op.0 != '-').count();
+        out.push(format!(
+            "@@ -{} +{} @@",
+            hunk_range(old_start, old_len),
+            hunk_range(new_start, new_len)
+        ));
+        out.extend(
+            ops[start..end]
+                .iter()
+                .map(|(tag, line)| format!("{tag}{line}")),
+        );
+    }
+    out.join("\n")
+}
+
+fn hunk_range(start: usize, len: usize) -> String {
+    let start = if len == 0 { start } else { start + 1 };
+    if len == 1 {
+        start.to_string()
+    } else {
+        format!("{start},{len}")
+    }
+}
+
+/// Line edit script as `(' ' | '-' | '+', line)` pairs.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(char, &'a str)> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(left, right)| left == right)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(left, right)| left == right)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut ops = old[..prefix]
+        .iter()
+        .map(|line| (' ', *line))
+        .collect::<Vec<_>>();
+    if old_mid.len().saturating_mul(new_mid.len()) > MAX_LCS_CELLS {
+        ops.extend(old_mid.iter().map(|line| ('-', *line)));
+        ops.extend(new_mid.iter().map(|line| ('+', *line)));
+    } else {
+        ops.extend(lcs_diff(old_mid, new_mid));
+    }
+    ops.extend(old[old.len() - suffix..].iter().map(|line| (' ', *line)));
+    ops
+}
+
+fn lcs_diff<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(char, &'a str)> {
+    let width = new.len() + 1;
+    // lengths[i * width + j] is the LCS length of old[i..] and new[j..].
+    let mut lengths = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i] == new[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut ops = Vec::with_capacity(old.len() + new.len());
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            ops.push((' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            ops.push(('-', old[i]));
+            i += 1;
+        } else {
+            ops.push(('+', new[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[i..].iter().map(|line| ('-', *line)));
+    ops.extend(new[j..].iter().map(|line| ('+', *line)));
+    ops
+}
+
+/// Half-open op ranges for each hunk, merging changes whose context would overlap.
+fn hunk_ranges(ops: &[(char, &str)]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for (index, _) in ops.iter().enumerate().filter(|(_, op)| op.0 != ' ') {
+        let start = index.saturating_sub(DIFF_CONTEXT_LINES);
+        let end = (index + 1 + DIFF_CONTEXT_LINES).min(ops.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+}
+
+fn truncate_diff(diff: String) -> String {
+    let total = diff.lines().count();
+    if total <= MAX_REVIEW_DIFF_LINES {
+        return diff;
+    }
+    let mut lines = diff
+        .lines()
+        .take(MAX_REVIEW_DIFF_LINES)
+        .collect::<Vec<_>>()
+        .join("\n");
+    lines.push_str(&format!(
+        "\n... ({} more diff lines)",
+        total - MAX_REVIEW_DIFF_LINES
+    ));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unified_diff_shows_changed_lines_with_context() {
+        let before = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let after = "a\nb\nc\nd\nE\nf\ng\nh\n";
+        assert_eq!(
+            unified_diff("src/x.txt", Some(before), after),
+            "--- a/src/x.txt\n+++ b/src/x.txt\n@@ -2,7 +2,7 @@\n b\n c\n d\n-e\n+E\n f\n g\n h"
+        );
+    }
+
+    #[test]
+    fn unified_diff_marks_new_files() {
+        assert_eq!(
+            unified_diff("new.txt", None, "one\ntwo\n"),
+            "--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1,2 @@\n+one\n+two"
+        );
+    }
+
+    #[tokio::test]
+    async fn review_waits_for_the_reviewer_and_remembers_approve_all() {
+        let review = DiffReview::new();
+        assert!(review.review("write", "a.txt", None, "x").await.is_ok());
+
+        let decisions = Arc::new(Mutex::new(vec![
+            DiffReviewDecision::ApproveAll,
+            DiffReviewDecision::Reject {
+                feedback: Some("keep the old name".to_string()),
+            },
+        ]));
+        let seen = Arc::new(Mutex::new(vec![]));
+        let (queue, requests) = (decisions.clone(), seen.clone());
+        review.attach(move |request| {
+            requests.lock().unwrap().push(request.path.clone());
+            let decision = queue.lock().unwrap().pop().unwrap();
+            request.respond(decision);
+        });
+
+        let error = review
+            .review("edit", "a.txt", Some("old\n"), "new\n")
+            .await
+            .unwrap_err();
+        assert!(error.contains("keep the old name"), "{error}");
+        assert!(review.review("edit", "b.txt", Some("1"), "2").await.is_ok());
+        assert!(review.auto_approve());
+        assert!(review.review("edit", "c.txt", Some("1"), "2").await.is_ok());
+        assert_eq!(*seen.lock().unwrap(), vec!["a.txt", "b.txt"]);
+    }
+
+    #[tokio::test]
+    async fn dropped_requests_reject_the_change() {
+        let review = DiffReview::new();
+        review.attach(drop);
+        let error = review
+            .review("write", "a.txt", None, "x")
+            .await
+            .unwrap_err();
+        assert!(error.contains("rejected"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn rejected_writes_leave_the_file_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "before\n").unwrap();
+        let review = Arc::new(DiffReview::new());
+        review.attach(|request| {
+            assert!(request.diff.contains("-before\n+after"), "{}", request.diff);
+            request.respond(DiffReviewDecision::Reject { feedback: None });
+        });
+        let tools =
+            crate::tools::create_coding_tools_with_snapshots(dir.path(), None, None, Some(review));
+        let write = tools.iter().find(|tool| tool.name == "write").unwrap();
+
+        let error = write
+            .execute
+            .execute(
+                "call-1".to_string(),
+                serde_json::json!({ "path": "a.txt", "content": "after\n" }),
+            )
+            .await
+            .unwrap_err();
+
+        assert!(error.message.contains("rejected"), "{}", error.message);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "before\n"
+        );
+    }
+}
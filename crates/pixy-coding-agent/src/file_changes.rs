@@ -0,0 +1,283 @@
+//! Detection of changes to files the agent previously read or edited.
+//!
+//! This is not a workspace watcher: only files a file tool has fingerprinted are tracked, and
+//! they are compared when a prompt starts and between the turns of a run. File tools update a
+//! fingerprint whenever they write, and at the end of a run the session re-baselines the files
+//! that run's tools wrote, so anything else that differs was changed by someone else (an editor
+//! save, a `git pull`, a command) and is reported to the model.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use pixy_agent_core::{AgentHooks, AgentMessage};
+use pixy_ai::{Message, UserContent};
+use sha2::{Digest, Sha256};
+
+/// Upper bound on tracked files; the least recently observed ones are forgotten first.
+const MAX_TRACKED_FILES: usize = 512;
+
+pub(crate) type SharedFileChangeTracker = Arc<Mutex<FileChangeTracker>>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Fingerprint {
+    modified: Option<SystemTime>,
+    len: u64,
+    digest: [u8; 32],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExternalChangeKind {
+    Modified,
+    Deleted,
+    Created,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExternalFileChange {
+    pub path: PathBuf,
+    pub kind: ExternalChangeKind,
+}
+
+#[derive(Debug)]
+pub struct FileChangeTracker {
+    /// Workspace root; reported paths are shown relative to it.
+    cwd: PathBuf,
+    /// `None` records that the file did not exist when last observed.
+    known: HashMap<PathBuf, (Option<Fingerprint>, u64)>,
+    /// Changes made on the user's behalf, e.g. by `/undo`, waiting for the next notice.
+    recorded: Vec<ExternalFileChange>,
+    clock: u64,
+}
+
+impl FileChangeTracker {
+    pub fn new(cwd: &Path) -> Self {
+        Self {
+            cwd: cwd.to_path_buf(),
+            known: HashMap::new(),
+            recorded: Vec::new(),
+            clock: 0,
+        }
+    }
+
+    /// Records the current on-disk state of `path` as seen by the agent.
+    pub fn observe(&mut self, path: &Path) {
+        self.clock += 1;
+        self.known
+            .insert(path.to_path_buf(), (fingerprint(path), self.clock));
+        if self.known.len() > MAX_TRACKED_FILES {
+            if let Some(oldest) = self
+                .known
+                .iter()
+                .min_by_key(|(_, (_, seen))| *seen)
+                .map(|(path, _)| path.clone())
+            {
+                self.known.remove(&oldest);
+            }
+        }
+    }
+
+    /// Reports `path` with the next notice even though it was changed in-process, as `/undo`
+    /// and `/redo` do, then tracks its new state.
+    pub fn record_change(&mut self, path: &Path) {
+        let previous = self.known.get(path).map(|(known, _)| known.is_some());
+        let exists = path.is_file();
+        let kind = match (previous, exists) {
+            (Some(false), true) => ExternalChangeKind::Created,
+            (_, true) => ExternalChangeKind::Modified,
+            (_, false) => ExternalChangeKind::Deleted,
+        };
+        self.recorded.retain(|change| change.path != path);
+        self.recorded.push(ExternalFileChange {
+            path: path.to_path_buf(),
+            kind,
+        });
+        self.observe(path);
+    }
+
+    /// Returns tracked files whose content changed since they were last observed, and records
+    /// their new state so each change is reported once.
+    pub fn take_external_changes(&mut self) -> Vec<ExternalFileChange> {
+        let mut changes = std::mem::take(&mut self.recorded);
+        for (path, (known, _)) in self.known.iter_mut() {
+            let current = match (known.as_ref(), fs::metadata(path)) {
+                (Some(previous), Ok(metadata))
+                    if metadata.len() == previous.len
+                        && metadata.modified().ok() == previous.modified =>
+                {
+                    continue;
+                }
+                (None, Err(_)) => continue,
+                _ => fingerprint(path),
+            };
+            let kind = match (known.as_ref(), current.as_ref()) {
+                (Some(previous), Some(current)) if previous.digest == current.digest => None,
+                (Some(_), Some(_)) => Some(ExternalChangeKind::Modified),
+                (Some(_), None) => Some(ExternalChangeKind::Deleted),
+                (None, Some(_)) => Some(ExternalChangeKind::Created),
+                (None, None) => None,
+            };
+            if let Some(kind) = kind.filter(|_| !changes.iter().any(|change| change.path == *path))
+            {
+                changes.push(ExternalFileChange {
+                    path: path.clone(),
+                    kind,
+                });
+            }
+            *known = current;
+        }
+        changes.sort_by(|left, right| left.path.cmp(&right.path));
+        changes
+    }
+
+    /// Takes pending external changes and renders them as a notice for the next user message.
+    pub(crate) fn take_external_change_notice(&mut self) -> Option<String> {
+        let changes = self.take_external_changes();
+        format_external_change_notice(&self.cwd, &changes)
+    }
+}
+
+/// Sends the `<file_changes>` notice between the turns of a run, so the model learns about
+/// edits made while its tools were running.
+pub(crate) struct FileChangeNotices {
+    tracker: SharedFileChangeTracker,
+}
+
+impl FileChangeNotices {
+    pub(crate) fn new(tracker: SharedFileChangeTracker) -> Self {
+        Self { tracker }
+    }
+}
+
+#[async_trait]
+impl AgentHooks for FileChangeNotices {
+    async fn before_next_turn(&self) -> Vec<AgentMessage> {
+        let Some(notice) = self
+            .tracker
+            .lock()
+            .ok()
+            .and_then(|mut tracker| tracker.take_external_change_notice())
+        else {
+            return vec![];
+        };
+        vec![Message::User {
+            content: UserContent::Text(notice),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        }]
+    }
+}
+
+/// Notice injected ahead of the next user message, or `None` when nothing changed.
+fn format_external_change_notice(cwd: &Path, changes: &[ExternalFileChange]) -> Option<String> {
+    if changes.is_empty() {
+        return None;
+    }
+    let mut lines = vec![
+        "<file_changes>".to_string(),
+        "These files changed on disk since you last read or wrote them with your file tools. Re-read them before editing:".to_string(),
+    ];
+    for change in changes {
+        let path = change.path.strip_prefix(cwd).unwrap_or(&change.path);
+        let kind = match change.kind {
+            ExternalChangeKind::Modified => "modified",
+            ExternalChangeKind::Deleted => "deleted",
+            ExternalChangeKind::Created => "created",
+        };
+        lines.push(format!("- {} ({kind})", path.display()));
+    }
+    lines.push("</file_changes>".to_string());
+    Some(lines.join("\n"))
+}
+
+fn fingerprint(path: &Path) -> Option<Fingerprint> {
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.is_file() {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    Some(Fingerprint {
+        modified: metadata.modified().ok(),
+        len: metadata.len(),
+        digest: Sha256::digest(&bytes).into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn take_external_changes_reports_each_change_once() {
+        let dir = tempdir().expect("tempdir");
+        let kept = dir.path().join("kept.txt");
+        let edited = dir.path().join("edited.txt");
+        let removed = dir.path().join("removed.txt");
+        for path in [&kept, &edited, &removed] {
+            fs::write(path, "original").expect("seed file");
+        }
+        let mut tracker = FileChangeTracker::new(dir.path());
+        for path in [&kept, &edited, &removed] {
+            tracker.observe(path);
+        }
+
+        fs::write(&edited, "changed by the user").expect("edit file");
+        fs::remove_file(&removed).expect("remove file");
+        // Touching a file without changing its content is not a change.
+        fs::write(&kept, "original").expect("rewrite kept file");
+
+        let changes = tracker.take_external_changes();
+        assert_eq!(
+            changes,
+            vec![
+                ExternalFileChange {
+                    path: edited.clone(),
+                    kind: ExternalChangeKind::Modified
+                },
+                ExternalFileChange {
+                    path: removed.clone(),
+                    kind: ExternalChangeKind::Deleted
+                },
+            ]
+        );
+        assert!(tracker.take_external_changes().is_empty());
+
+        let notice = format_external_change_notice(dir.path(), &changes).expect("notice");
+        assert!(notice.contains("- edited.txt (modified)"));
+        assert!(notice.contains("- removed.txt (deleted)"));
+    }
+
+    #[test]
+    fn recorded_changes_are_reported_even_for_untracked_files() {
+        let dir = tempdir().expect("tempdir");
+        let tracked = dir.path().join("tracked.txt");
+        let untracked = dir.path().join("untracked.txt");
+        fs::write(&tracked, "agent version").expect("seed file");
+        let mut tracker = FileChangeTracker::new(dir.path());
+        tracker.observe(&tracked);
+
+        // Same length, so a metadata check alone could miss the revert.
+        fs::write(&tracked, "user version!").expect("revert tracked file");
+        tracker.record_change(&tracked);
+        tracker.record_change(&untracked);
+
+        assert_eq!(
+            tracker.take_external_changes(),
+            vec![
+                ExternalFileChange {
+                    path: tracked,
+                    kind: ExternalChangeKind::Modified
+                },
+                ExternalFileChange {
+                    path: untracked,
+                    kind: ExternalChangeKind::Deleted
+                },
+            ]
+        );
+        assert!(tracker.take_external_changes().is_empty());
+    }
+}
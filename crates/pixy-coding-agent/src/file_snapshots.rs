@@ -0,0 +1,349 @@
+//! Content-addressed file snapshots backing `/undo` and `/redo`.
+//!
+//! Mutating file tools record the pre-image of every file they touch. All changes made during
+//! one agent run form a change set that can be reverted and re-applied without relying on git.
+//! Only the latest change sets are kept, and objects none of them references are deleted.
+//! The store lives in the working tree, so it carries a `.gitignore` that hides it from git and
+//! from the search and listing tools.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use sha2::{Digest, Sha256};
+
+pub(crate) const SNAPSHOT_DIR: &str = ".pixy/snapshots";
+const MAX_CHANGE_SETS: usize = 64;
+const OBJECTS_DIR: &str = "objects";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct FileChange {
+    path: PathBuf,
+    /// Blob hash of the content before the change; `None` means the file did not exist.
+    before: Option<String>,
+    /// Blob hash of the content after the change, captured on undo so redo can restore it.
+    after: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ChangeSet {
+    changes: Vec<FileChange>,
+}
+
+impl ChangeSet {
+    fn objects(&self) -> impl Iterator<Item = &str> {
+        self.changes.iter().flat_map(|change| {
+            [change.before.as_deref(), change.after.as_deref()]
+                .into_iter()
+                .flatten()
+        })
+    }
+}
+
+pub(crate) type SharedFileSnapshots = Arc<Mutex<FileSnapshotStore>>;
+
+#[derive(Debug)]
+pub struct FileSnapshotStore {
+    root: PathBuf,
+    objects_dir: PathBuf,
+    pending: Vec<FileChange>,
+    undo_stack: Vec<ChangeSet>,
+    redo_stack: Vec<ChangeSet>,
+}
+
+impl FileSnapshotStore {
+    pub fn new(cwd: &Path) -> Self {
+        Self::with_root(cwd.join(SNAPSHOT_DIR))
+    }
+
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        Self {
+            objects_dir: root.join(OBJECTS_DIR),
+            root,
+            pending: vec![],
+            undo_stack: vec![],
+            redo_stack: vec![],
+        }
+    }
+
+    /// Stores the current content of `path` before a tool overwrites it.
+    /// Only the first pre-image per file is kept within one change set.
+    pub fn record_before_write(&mut self, path: &Path) -> Result<(), String> {
+        if self.pending.iter().any(|change| change.path == path) {
+            return Ok(());
+        }
+        let before = self.snapshot(path)?;
+        self.pending.push(FileChange {
+            path: path.to_path_buf(),
+            before,
+            after: None,
+        });
+        Ok(())
+    }
+
+    /// Files written since the last change set was closed.
+    pub fn pending_paths(&self) -> Vec<PathBuf> {
+        self.pending
+            .iter()
+            .map(|change| change.path.clone())
+            .collect()
+    }
+
+    /// Closes the current change set. Returns the number of files it touched.
+    pub fn commit_pending(&mut self) -> usize {
+        if self.pending.is_empty() {
+            return 0;
+        }
+        let changes = std::mem::take(&mut self.pending);
+        let count = changes.len();
+        self.undo_stack.push(ChangeSet { changes });
+        let mut dropped = std::mem::take(&mut self.redo_stack);
+        if self.undo_stack.len() > MAX_CHANGE_SETS {
+            dropped.push(self.undo_stack.remove(0));
+        }
+        self.remove_unreferenced_objects(&dropped);
+        count
+    }
+
+    /// Deletes the objects of `dropped` change sets that no remaining change set still needs.
+    /// Failures are ignored: a leftover object only costs disk space.
+    fn remove_unreferenced_objects(&self, dropped: &[ChangeSet]) {
+        let referenced: HashSet<&str> = self
+            .undo_stack
+            .iter()
+            .chain(&self.redo_stack)
+            .flat_map(ChangeSet::objects)
+            .chain(
+                self.pending
+                    .iter()
+                    .filter_map(|change| change.before.as_deref()),
+            )
+            .collect();
+        for object in dropped.iter().flat_map(ChangeSet::objects) {
+            if !referenced.contains(object) {
+                let _ = fs::remove_file(self.objects_dir.join(object));
+            }
+        }
+    }
+
+    /// Drops the pre-images recorded by a run that ended without being persisted, so they do
+    /// not end up in the next run's change set.
+    pub fn discard_pending(&mut self) {
+        let changes = std::mem::take(&mut self.pending);
+        self.remove_unreferenced_objects(&[ChangeSet { changes }]);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Reverts the most recent change set and returns the restored paths.
+    pub fn undo(&mut self) -> Result<Vec<PathBuf>, String> {
+        let Some(mut change_set) = self.undo_stack.pop() else {
+            return Err("Nothing to undo".to_string());
+        };
+        for change in change_set.changes.iter_mut().rev() {
+            change.after = self.snapshot(&change.path)?;
+            self.restore(&change.path, change.before.as_deref())?;
+        }
+        let paths = change_set
+            .changes
+            .iter()
+            .map(|change| change.path.clone())
+            .collect();
+        self.redo_stack.push(change_set);
+        Ok(paths)
+    }
+
+    /// Re-applies the most recently undone change set and returns the restored paths.
+    pub fn redo(&mut self) -> Result<Vec<PathBuf>, String> {
+        let Some(change_set) = self.redo_stack.pop() else {
+            return Err("Nothing to redo".to_string());
+        };
+        for change in &change_set.changes {
+            self.restore(&change.path, change.after.as_deref())?;
+        }
+        let paths = change_set
+            .changes
+            .iter()
+            .map(|change| change.path.clone())
+            .collect();
+        self.undo_stack.push(change_set);
+        Ok(paths)
+    }
+
+    fn snapshot(&self, path: &Path) -> Result<Option<String>, String> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(format!("snapshot {} failed: {error}", path.display())),
+        };
+        let hash = object_hash(&bytes);
+        let object_path = self.objects_dir.join(&hash);
+        if !object_path.exists() {
+            create_objects_dir(&self.root)?;
+            fs::write(&object_path, &bytes)
+                .map_err(|error| format!("write snapshot {hash} failed: {error}"))?;
+        }
+        Ok(Some(hash))
+    }
+
+    fn restore(&self, path: &Path, hash: Option<&str>) -> Result<(), String> {
+        match hash {
+            Some(hash) => {
+                let bytes = fs::read(self.objects_dir.join(hash))
+                    .map_err(|error| format!("read snapshot {hash} failed: {error}"))?;
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).map_err(|error| {
+                        format!("create directory {} failed: {error}", parent.display())
+                    })?;
+                }
+                fs::write(path, bytes)
+                    .map_err(|error| format!("restore {} failed: {error}", path.display()))
+            }
+            None => match fs::remove_file(path) {
+                Ok(()) => Ok(()),
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                Err(error) => Err(format!("remove {} failed: {error}", path.display())),
+            },
+        }
+    }
+}
+
+/// Name an object with `bytes` as content is stored under.
+pub(crate) fn object_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Creates `<root>/objects` and the `.gitignore` that keeps the whole store out of git.
+pub(crate) fn create_objects_dir(root: &Path) -> Result<PathBuf, String> {
+    let objects_dir = root.join(OBJECTS_DIR);
+    fs::create_dir_all(&objects_dir).map_err(|error| {
+        format!(
+            "create snapshot dir {} failed: {error}",
+            objects_dir.display()
+        )
+    })?;
+    let gitignore = root.join(".gitignore");
+    if !gitignore.exists() {
+        fs::write(&gitignore, "*\n")
+            .map_err(|error| format!("write {} failed: {error}", gitignore.display()))?;
+    }
+    Ok(objects_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::{object_hash, FileSnapshotStore, MAX_CHANGE_SETS, SNAPSHOT_DIR};
+
+    #[test]
+    fn undo_and_redo_restore_modified_and_created_files() {
+        let dir = tempdir().expect("tempdir should be created");
+        let existing = dir.path().join("existing.txt");
+        let created = dir.path().join("nested/created.txt");
+        fs::write(&existing, "before").expect("seed file");
+
+        let mut store = FileSnapshotStore::new(dir.path());
+        store.record_before_write(&existing).expect("snapshot");
+        fs::write(&existing, "after").expect("modify");
+        store.record_before_write(&created).expect("snapshot");
+        fs::create_dir_all(created.parent().expect("parent")).expect("mkdir");
+        fs::write(&created, "new").expect("create");
+        assert_eq!(store.commit_pending(), 2);
+
+        let undone = store.undo().expect("undo");
+        assert_eq!(undone.len(), 2);
+        assert_eq!(fs::read_to_string(&existing).expect("read"), "before");
+        assert!(!created.exists());
+        assert!(store.can_redo());
+
+        store.redo().expect("redo");
+        assert_eq!(fs::read_to_string(&existing).expect("read"), "after");
+        assert_eq!(fs::read_to_string(&created).expect("read"), "new");
+        assert!(store.undo().is_ok());
+        assert!(store.undo().is_err());
+        assert_eq!(
+            fs::read_to_string(dir.path().join(SNAPSHOT_DIR).join(".gitignore"))
+                .expect("read .gitignore"),
+            "*\n"
+        );
+    }
+
+    #[test]
+    fn discarded_pending_changes_stay_out_of_the_next_change_set() {
+        let dir = tempdir().expect("tempdir should be created");
+        let aborted = dir.path().join("aborted.txt");
+        let next = dir.path().join("next.txt");
+        fs::write(&aborted, "v1").expect("seed file");
+
+        let mut store = FileSnapshotStore::new(dir.path());
+        store.record_before_write(&aborted).expect("snapshot");
+        fs::write(&aborted, "v2").expect("modify");
+        store.discard_pending();
+
+        store.record_before_write(&next).expect("snapshot");
+        fs::write(&next, "new").expect("create");
+        assert_eq!(store.commit_pending(), 1);
+
+        assert_eq!(store.undo().expect("undo"), vec![next.clone()]);
+        assert_eq!(fs::read_to_string(&aborted).expect("read"), "v2");
+    }
+
+    #[test]
+    fn trimmed_change_sets_delete_objects_nothing_else_references() {
+        let dir = tempdir().expect("tempdir should be created");
+        let file = dir.path().join("file.txt");
+        let objects_dir = dir.path().join(SNAPSHOT_DIR).join("objects");
+        fs::write(&file, "shared").expect("seed file");
+
+        let mut store = FileSnapshotStore::new(dir.path());
+        for version in 0..MAX_CHANGE_SETS {
+            store.record_before_write(&file).expect("snapshot");
+            fs::write(&file, format!("v{version}")).expect("modify");
+            store.commit_pending();
+        }
+        fs::write(&file, "shared").expect("restore seed content");
+        for next in ["final", "last"] {
+            store.record_before_write(&file).expect("snapshot");
+            fs::write(&file, next).expect("modify");
+            store.commit_pending();
+        }
+
+        // The oldest set's "shared" pre-image is also the newest one's, so only "v0" goes.
+        assert!(objects_dir.join(object_hash(b"shared")).exists());
+        assert!(!objects_dir.join(object_hash(b"v0")).exists());
+        assert!(objects_dir.join(object_hash(b"v1")).exists());
+        let objects = fs::read_dir(&objects_dir).expect("objects dir").count();
+        assert_eq!(objects, MAX_CHANGE_SETS);
+    }
+
+    #[test]
+    fn new_change_set_clears_redo_history() {
+        let dir = tempdir().expect("tempdir should be created");
+        let file = dir.path().join("file.txt");
+        fs::write(&file, "v1").expect("seed file");
+
+        let mut store = FileSnapshotStore::new(dir.path());
+        store.record_before_write(&file).expect("snapshot");
+        fs::write(&file, "v2").expect("modify");
+        store.commit_pending();
+        store.undo().expect("undo");
+
+        store.record_before_write(&file).expect("snapshot");
+        fs::write(&file, "v3").expect("modify");
+        store.commit_pending();
+
+        assert!(!store.can_redo());
+        assert_eq!(store.commit_pending(), 0);
+    }
+}
@@ -0,0 +1,342 @@
+//! Structured output for headless (`--prompt`) runs.
+//!
+//! `json` prints a single result document once the run completes; `stream-json` prints
+//! newline-delimited events as the run progresses so scripts can consume them incrementally.
+//! Tool calls, tool results and finished assistant messages are emitted from the session's
+//! stream updates as they happen, so both formats list events in the order they occurred.
+
+use std::io::Write;
+
+use clap::ValueEnum;
+use pixy_ai::{AssistantContentBlock, Message, StopReason, ToolResultContentBlock};
+use serde_json::{json, Map, Value};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    #[value(name = "stream-json")]
+    StreamJson,
+}
+
+pub(crate) struct HeadlessEventWriter<W: Write> {
+    writer: W,
+    format: OutputFormat,
+    events: Vec<Value>,
+}
+
+impl<W: Write> HeadlessEventWriter<W> {
+    pub(crate) fn new(writer: W, format: OutputFormat) -> Self {
+        Self {
+            writer,
+            format,
+            events: vec![],
+        }
+    }
+
+    /// Emits an event immediately in `stream-json` mode; buffers it for the final document in `json` mode.
+    pub(crate) fn emit(&mut self, event: Value) -> Result<(), String> {
+        match self.format {
+            OutputFormat::StreamJson => self.write_line(&event),
+            _ => {
+                self.events.push(event);
+                Ok(())
+            }
+        }
+    }
+
+    /// Emits events that only make sense while streaming, such as text deltas.
+    pub(crate) fn emit_streaming(&mut self, event: Value) -> Result<(), String> {
+        if self.format == OutputFormat::StreamJson {
+            self.write_line(&event)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self, result: Value) -> Result<(), String> {
+        match self.format {
+            OutputFormat::StreamJson => self.write_line(&result),
+            _ => {
+                let mut document = result;
+                if let Value::Object(fields) = &mut document {
+                    fields.insert(
+                        "events".to_string(),
+                        Value::Array(std::mem::take(&mut self.events)),
+                    );
+                }
+                self.write_line(&document)
+            }
+        }
+    }
+
+    fn write_line(&mut self, event: &Value) -> Result<(), String> {
+        let line = serde_json::to_string(event)
+            .map_err(|error| format!("serialize output event failed: {error}"))?;
+        writeln!(self.writer, "{line}")
+            .and_then(|_| self.writer.flush())
+            .map_err(|error| format!("stdout write failed: {error}"))
+    }
+}
+
+pub(crate) fn init_event(session_file: &str, cwd: &str, provider: &str, model: &str) -> Value {
+    json!({
+        "type": "init",
+        "session": session_file,
+        "cwd": cwd,
+        "provider": provider,
+        "model": model,
+    })
+}
+
+pub(crate) fn text_delta_event(delta: &str) -> Value {
+    json!({ "type": "text_delta", "text": delta })
+}
+
+pub(crate) fn error_event(message: &str) -> Value {
+    json!({ "type": "error", "message": message })
+}
+
+pub(crate) fn assistant_event(text: &str) -> Value {
+    json!({ "type": "assistant", "text": text })
+}
+
+pub(crate) fn tool_call_event(id: &str, name: &str, arguments: &Value) -> Value {
+    json!({
+        "type": "tool_call",
+        "id": id,
+        "name": name,
+        "arguments": arguments,
+    })
+}
+
+pub(crate) fn tool_result_event(
+    tool_call_id: &str,
+    tool_name: &str,
+    content: &[ToolResultContentBlock],
+    is_error: bool,
+) -> Value {
+    json!({
+        "type": "tool_result",
+        "toolCallId": tool_call_id,
+        "toolName": tool_name,
+        "isError": is_error,
+        "text": tool_result_text(content),
+    })
+}
+
+/// Sums token usage and cost across every assistant message in the run.
+pub(crate) fn usage_event(messages: &[Message]) -> Value {
+    let mut input = 0u64;
+    let mut output = 0u64;
+    let mut cache_read = 0u64;
+    let mut cache_write = 0u64;
+    let mut total_tokens = 0u64;
+    let mut cost = 0f64;
+    for message in messages {
+        if let Message::Assistant { usage, .. } = message {
+            input += usage.input;
+            output += usage.output;
+            cache_read += usage.cache_read;
+            cache_write += usage.cache_write;
+            total_tokens += usage.total_tokens;
+            cost += usage.cost.total;
+        }
+    }
+    json!({
+        "type": "usage",
+        "input": input,
+        "output": output,
+        "cacheRead": cache_read,
+        "cacheWrite": cache_write,
+        "totalTokens": total_tokens,
+        "cost": cost,
+    })
+}
+
+/// Builds the final `result` event from the last assistant message of the run.
+pub(crate) fn result_event(messages: &[Message], usage: Value) -> Value {
+    let mut fields = Map::new();
+    fields.insert("type".to_string(), json!("result"));
+    let last_assistant = messages.iter().rev().find_map(|message| match message {
+        Message::Assistant {
+            content,
+            stop_reason,
+            error_message,
+            ..
+        } => Some((content, stop_reason, error_message)),
+        _ => None,
+    });
+    match last_assistant {
+        Some((content, stop_reason, error_message)) => {
+            let is_error = matches!(stop_reason, StopReason::Error | StopReason::Aborted);
+            fields.insert("isError".to_string(), json!(is_error));
+            fields.insert("stopReason".to_string(), json!(stop_reason));
+            fields.insert("text".to_string(), json!(assistant_text(content)));
+            if let Some(error_message) = error_message {
+                fields.insert("errorMessage".to_string(), json!(error_message));
+            }
+        }
+        None => {
+            fields.insert("isError".to_string(), json!(true));
+            fields.insert("text".to_string(), json!(""));
+            fields.insert(
+                "errorMessage".to_string(),
+                json!("run produced no assistant message"),
+            );
+        }
+    }
+    let mut usage = usage;
+    if let Value::Object(usage_fields) = &mut usage {
+        usage_fields.remove("type");
+    }
+    fields.insert("usage".to_string(), usage);
+    Value::Object(fields)
+}
+
+fn assistant_text(content: &[AssistantContentBlock]) -> String {
+    content
+        .iter()
+        .filter_map(|block| match block {
+            AssistantContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+fn tool_result_text(content: &[ToolResultContentBlock]) -> String {
+    content
+        .iter()
+        .filter_map(|block| match block {
+            ToolResultContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use pixy_ai::{Cost, Usage};
+
+    use super::*;
+
+    fn assistant(content: Vec<AssistantContentBlock>, stop_reason: StopReason) -> Message {
+        Message::Assistant {
+            content,
+            api: "openai-completions".to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-test".to_string(),
+            usage: Usage {
+                input: 10,
+                output: 5,
+                cache_read: 0,
+                cache_write: 0,
+                reasoning: 0,
+                total_tokens: 15,
+                cost: Cost {
+                    input: 0.0,
+                    output: 0.0,
+                    cache_read: 0.0,
+                    cache_write: 0.0,
+                    total: 0.5,
+                },
+            },
+            stop_reason,
+            error_message: None,
+            timestamp: 1,
+            stats: None,
+        }
+    }
+
+    fn sample_run() -> Vec<Message> {
+        vec![
+            assistant(
+                vec![AssistantContentBlock::ToolCall {
+                    id: "call-1".to_string(),
+                    name: "read".to_string(),
+                    arguments: json!({ "path": "README.md" }),
+                    thought_signature: None,
+                }],
+                StopReason::ToolUse,
+            ),
+            assistant(
+                vec![AssistantContentBlock::Text {
+                    text: "done".to_string(),
+                    text_signature: None,
+                }],
+                StopReason::Stop,
+            ),
+        ]
+    }
+
+    fn emit_sample_events<W: Write>(writer: &mut HeadlessEventWriter<W>) {
+        writer
+            .emit(tool_call_event(
+                "call-1",
+                "read",
+                &json!({ "path": "README.md" }),
+            ))
+            .expect("emit");
+        let content = vec![ToolResultContentBlock::Text {
+            text: "hello".to_string(),
+            text_signature: None,
+        }];
+        writer
+            .emit(tool_result_event("call-1", "read", &content, false))
+            .expect("emit");
+        writer.emit_streaming(text_delta_event("do")).expect("emit");
+        writer.emit(assistant_event("done")).expect("emit");
+    }
+
+    #[test]
+    fn json_format_writes_single_document_with_buffered_events() {
+        let messages = sample_run();
+        let mut buffer = Vec::new();
+        let mut writer = HeadlessEventWriter::new(&mut buffer, OutputFormat::Json);
+        emit_sample_events(&mut writer);
+        writer
+            .finish(result_event(&messages, usage_event(&messages)))
+            .expect("finish");
+
+        let output = String::from_utf8(buffer).expect("utf8");
+        assert_eq!(output.lines().count(), 1);
+        let document: Value = serde_json::from_str(output.trim()).expect("valid json");
+        assert_eq!(document["type"], "result");
+        assert_eq!(document["isError"], false);
+        assert_eq!(document["stopReason"], "stop");
+        assert_eq!(document["usage"]["totalTokens"], 30);
+        assert_eq!(document["events"].as_array().map(Vec::len), Some(3));
+    }
+
+    #[test]
+    fn stream_json_format_writes_one_event_per_line() {
+        let messages = sample_run();
+        let mut buffer = Vec::new();
+        let mut writer = HeadlessEventWriter::new(&mut buffer, OutputFormat::StreamJson);
+        emit_sample_events(&mut writer);
+        writer
+            .finish(result_event(&messages, usage_event(&messages)))
+            .expect("finish");
+
+        let output = String::from_utf8(buffer).expect("utf8");
+        let kinds = output
+            .lines()
+            .map(|line| {
+                let event: Value = serde_json::from_str(line).expect("valid json line");
+                event["type"].as_str().unwrap_or_default().to_string()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                "tool_call",
+                "tool_result",
+                "text_delta",
+                "assistant",
+                "result"
+            ]
+        );
+    }
+}
@@ -0,0 +1,28 @@
+//! Proxy, trusted roots and client certificates for provider requests, configured by
+//! `[transport]` in `pixy.toml`, per provider under `[transport.providers.<provider>]`, and by
+//! the `--proxy`, `--no-proxy`, `--ca-bundle`, `--client-cert` and `--client-key` flags.
+
+use std::collections::HashMap;
+
+use pixy_ai::{set_default_http_transport, set_provider_http_transport, HttpTransport};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpTransportConfig {
+    pub default: HttpTransport,
+    /// Settings for one provider, keyed by provider name; unset ones fall back to `default`.
+    pub providers: HashMap<String, HttpTransport>,
+}
+
+impl HttpTransportConfig {
+    /// Installs the transports for every provider request this process makes. Fails on an
+    /// invalid proxy URL or an unreadable certificate file.
+    pub fn apply(&self) -> Result<(), String> {
+        set_default_http_transport(self.default.clone())
+            .map_err(|error| format!("[transport]: {}", error.message))?;
+        for (provider, transport) in &self.providers {
+            set_provider_http_transport(provider.clone(), transport.clone())
+                .map_err(|error| format!("[transport.providers.{provider}]: {}", error.message))?;
+        }
+        Ok(())
+    }
+}
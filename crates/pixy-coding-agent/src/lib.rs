@@ -5,22 +5,59 @@ mod agent_session_services;
 mod bash_command;
 pub mod cli;
 mod cli_app;
+mod config_layers;
+mod content_guards;
+mod diff_review;
+mod file_changes;
+mod file_snapshots;
+mod headless_output;
+mod http_transport;
+mod lifecycle_hooks;
+mod mcp;
+mod mcp_server;
 pub mod memory;
 mod memory_tool;
 mod messages;
 mod multi_agent;
+mod post_edit;
+mod project_memory;
 mod runtime_config;
+mod sampling;
+mod secret_redaction;
+#[cfg(feature = "session-archive")]
+mod session_archive;
+mod session_cost;
 mod session_manager;
+mod session_migration;
+mod session_search;
 mod skills;
 pub mod system_prompt;
+mod telemetry;
+mod tool_approval;
+mod tool_failures;
+mod tool_output;
 mod tools;
 mod tui_backend;
+mod worktree;
 
 pub use agent_session::{
     create_session, create_session_from_runtime, AgentMode, AgentSession, AgentSessionConfig,
     AgentSessionStreamUpdate, AutoCompactionConfig, CreatedSession, SessionCreateOptions,
 };
-pub use memory_tool::create_memory_tool;
+pub use config_layers::{config_layer_paths, LayeredConfig};
+pub use content_guards::GuardConfig;
+pub use diff_review::{
+    unified_diff, DiffReview, DiffReviewConfig, DiffReviewDecision, DiffReviewRequest,
+};
+pub use file_changes::{ExternalChangeKind, ExternalFileChange, FileChangeTracker};
+pub use file_snapshots::FileSnapshotStore;
+pub use http_transport::HttpTransportConfig;
+pub use lifecycle_hooks::{
+    LifecycleHookEvent, LifecycleHookOutcome, LifecycleHookSpec, LifecycleHooks,
+};
+pub use mcp::{load_mcp_tools, LoadMcpToolsResult, McpConfig, McpServerConfig};
+pub use mcp_server::serve_mcp_session;
+pub use memory_tool::{create_memory_tool, create_memory_tool_with_semantic_index};
 pub use messages::{
     bash_execution_to_text, convert_to_llm, BashExecutionMessage, BranchSummaryMessage,
     CodingMessage, CompactionSummaryMessage, CustomMessage, BRANCH_SUMMARY_PREFIX,
@@ -29,26 +66,56 @@ pub use messages::{
 pub use multi_agent::{
     create_multi_agent_plugin_runtime, create_multi_agent_plugin_runtime_from_specs,
     create_task_tool, load_and_merge_plugins, load_and_merge_plugins_from_paths,
-    load_plugin_manifests, AfterTaskResultHookContext, BeforeTaskDispatchHookContext,
-    BeforeToolDefinitionHookContext, BeforeUserMessageHookContext, ChildSessionStore,
-    DeclarativeHookAction, DeclarativeHookSpec, DeclarativeHookStage, DefaultSubAgentRegistry,
-    DispatchPolicyConfig, DispatchPolicyDecision, DispatchPolicyRule, LoadedPluginManifest,
+    load_plugin_manifests, load_project_subagents, AfterTaskResultHookContext,
+    BeforeTaskDispatchHookContext, BeforeToolDefinitionHookContext, BeforeUserMessageHookContext,
+    ChildSessionControls, ChildSessionStore, ChildShellToolFn, DeclarativeHookAction,
+    DeclarativeHookSpec, DeclarativeHookStage, DefaultSubAgentRegistry, DispatchMetrics,
+    DispatchPolicyConditions, DispatchPolicyConfig, DispatchPolicyDecision, DispatchPolicyRule,
+    LoadPluginManifestsResult, LoadProjectSubAgentsResult, LoadedPluginManifest,
     MergedPluginConfig, MultiAgentHook, MultiAgentPluginManifest, MultiAgentPluginRuntime,
-    PluginSubAgentSpec, PolicyRuleEffect, SubAgentMode, SubAgentPromptMetadata,
-    SubAgentPromptTrigger, SubAgentRegistryBuilder, SubAgentResolver, SubAgentSpec,
-    TaskDispatchResult, TaskDispatcher, TaskDispatcherConfig, TaskToolInput, TaskToolOutput,
+    PluginSubAgentSpec, PolicyRuleEffect, ProjectSubAgentSpec, SharedChildSessionControls,
+    SubAgentMode, SubAgentPromptMetadata, SubAgentPromptTrigger, SubAgentRegistryBuilder,
+    SubAgentResolver, SubAgentSpec, TaskDispatchResult, TaskDispatcher, TaskDispatcherConfig,
+    TaskToolInput, TaskToolOutput, PLUGIN_SCHEMA_VERSION, SUPPORTED_PLUGIN_CAPABILITIES,
 };
+pub use post_edit::{PostEditChecks, PostEditCommand};
+pub use project_memory::{ProjectMemoryConfig, ProjectMemoryFile, ProjectMemoryTarget};
 pub use runtime_config::{
-    LLMRouter, ResolvedMemoryConfig, ResolvedMemorySearchConfig, ResolvedMultiAgentConfig,
-    ResolvedRuntime, RuntimeLoadOptions, RuntimeOverrides,
+    LLMRouter, ResolvedMemoryConfig, ResolvedMemoryEmbeddingConfig, ResolvedMemorySearchConfig,
+    ResolvedMultiAgentConfig, ResolvedRuntime, RuntimeLoadOptions, RuntimeOverrides,
+};
+pub use sampling::SamplingConfig;
+pub use secret_redaction::{Redaction, RedactionConfig, SecretRedactor};
+#[cfg(feature = "session-archive")]
+pub use session_archive::{
+    export_session_archive, import_session_archive, SessionArchiveManifest, SessionExportOptions,
+    SessionImportOptions, SessionImportSummary, SESSION_ARCHIVE_FORMAT,
+    SESSION_ARCHIVE_FORMAT_VERSION,
 };
+pub use session_cost::{CostTotals, ModelCost, SessionCostReport};
 pub use session_manager::{SessionContext, SessionManager, CURRENT_SESSION_VERSION};
+pub use session_migration::{
+    fsck_session_file, SessionFsckReport, SessionIssue, SessionIssueKind, SESSION_QUARANTINE_SUFFIX,
+};
+pub use session_search::{find_session_file, find_sessions, list_sessions, SessionListing};
 pub use skills::{
-    format_skills_for_prompt, load_skills, load_skills_from_dir, LoadSkillsOptions,
-    LoadSkillsResult, Skill, SkillDiagnostic, SkillDiagnosticKind, SkillSource,
+    format_skills_for_prompt, load_skills, load_skills_from_dir, parse_skill_invocation,
+    render_skill, LoadSkillsOptions, LoadSkillsResult, Skill, SkillArgument, SkillCatalog,
+    SkillDiagnostic, SkillDiagnosticKind, SkillSource,
 };
 pub use system_prompt::build_system_prompt;
+pub use telemetry::{SessionTelemetry, TelemetryConfig, TelemetryTurn, DEFAULT_OTLP_ENDPOINT};
+pub use tool_approval::ToolApprovalConfig;
+pub use tool_failures::{ToolFailureConfig, ToolFailureFeedback, ToolFailureOutput};
+pub use tool_output::{
+    artifact_dir_for_session, OutputLimits, ToolOutputConfig, ToolOutputLimiter,
+};
 pub use tools::{
-    create_bash_tool, create_coding_tools, create_coding_tools_with_extra, create_edit_tool,
-    create_list_directory_tool, create_read_tool, create_write_tool,
+    create_apply_patch_tool, create_bash_background_tool, create_bash_tool, create_coding_tools,
+    create_coding_tools_with_extra, create_edit_tool, create_git_tools, create_list_directory_tool,
+    create_notebook_edit_tool, create_read_image_tool, create_read_tool, create_search_tool,
+    create_shell_tool, create_todo_tool, create_web_fetch_tool, create_web_search_tool,
+    create_write_tool, todos_from_messages, BackgroundProcesses, PersistentShell, TodoItem,
+    TodoStatus, WebSearchBackend, WebSearchBackendKind, WebSearchConfig, WebSearchResult,
 };
+pub use worktree::{worktree_name, SessionWorktree, WorktreeConfig, WorktreeExitAction};
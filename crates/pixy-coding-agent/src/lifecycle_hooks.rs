@@ -0,0 +1,484 @@
+//! User-configured lifecycle hooks declared as `[[hooks]]` in `pixy.toml`.
+//!
+//! Each hook runs a shell command (payload on stdin) or POSTs the payload to a URL. A failing
+//! `before_tool` hook blocks the tool call; failures of other events are only logged.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use pixy_agent_core::{AgentTool, AgentToolExecuteFn, AgentToolExecutor, AgentToolResult};
+use pixy_ai::{PiAiError, PiAiErrorCode, ToolResultContentBlock};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::warn;
+
+const DEFAULT_HOOK_TIMEOUT_MS: u64 = 10_000;
+pub(crate) const FILE_EDIT_TOOLS: &[&str] = &["write", "edit", "apply_patch", "notebook_edit"];
+
+/// Files a successful file-edit tool call left on disk: `path` for `write`, `edit` and
+/// `notebook_edit` (except its `read` action), and every created or updated file of an
+/// `apply_patch` call.
+pub(crate) fn edited_paths(details: &Value) -> Vec<String> {
+    if details.get("action").and_then(Value::as_str) == Some("read") {
+        return Vec::new();
+    }
+    if let Some(path) = details.get("path").and_then(Value::as_str) {
+        return vec![path.to_string()];
+    }
+    details
+        .get("files")
+        .and_then(Value::as_array)
+        .map(|files| {
+            files
+                .iter()
+                .filter(|file| file.get("operation").and_then(Value::as_str) != Some("delete"))
+                .filter_map(|file| file.get("path").and_then(Value::as_str))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleHookEvent {
+    SessionStart,
+    SessionEnd,
+    BeforeTool,
+    AfterTool,
+    AfterFileEdit,
+}
+
+impl LifecycleHookEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::SessionStart => "session_start",
+            Self::SessionEnd => "session_end",
+            Self::BeforeTool => "before_tool",
+            Self::AfterTool => "after_tool",
+            Self::AfterFileEdit => "after_file_edit",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LifecycleHookSpec {
+    pub event: LifecycleHookEvent,
+    /// Restricts tool events to a single tool name.
+    #[serde(default)]
+    pub tool_name: Option<String>,
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+impl LifecycleHookSpec {
+    pub fn validate(&self) -> Result<(), String> {
+        let has_command = self
+            .command
+            .as_deref()
+            .is_some_and(|command| !command.trim().is_empty());
+        let has_url = self
+            .url
+            .as_deref()
+            .is_some_and(|url| !url.trim().is_empty());
+        match (has_command, has_url) {
+            (true, true) => Err(format!(
+                "{} hook must set either command or url, not both",
+                self.event.as_str()
+            )),
+            (false, false) => Err(format!(
+                "{} hook requires a command or url",
+                self.event.as_str()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn matches_tool(&self, tool_name: Option<&str>) -> bool {
+        match (self.tool_name.as_deref(), tool_name) {
+            (Some(expected), Some(actual)) => expected == actual,
+            (Some(_), None) => false,
+            (None, _) => true,
+        }
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.unwrap_or(DEFAULT_HOOK_TIMEOUT_MS).max(1))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LifecycleHookOutcome {
+    Continue,
+    Block { reason: String },
+}
+
+#[derive(Debug)]
+pub struct LifecycleHooks {
+    cwd: PathBuf,
+    specs: Vec<LifecycleHookSpec>,
+    client: reqwest::Client,
+}
+
+impl LifecycleHooks {
+    /// Builds the hook runner, skipping invalid specs with a warning.
+    pub fn new(cwd: &Path, specs: Vec<LifecycleHookSpec>) -> Self {
+        let specs = specs
+            .into_iter()
+            .filter(|spec| match spec.validate() {
+                Ok(()) => true,
+                Err(error) => {
+                    eprintln!("warning: skip invalid lifecycle hook: {error}");
+                    false
+                }
+            })
+            .collect();
+        Self {
+            cwd: cwd.to_path_buf(),
+            specs,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.specs.is_empty()
+    }
+
+    pub fn has_event(&self, event: LifecycleHookEvent) -> bool {
+        self.specs.iter().any(|spec| spec.event == event)
+    }
+
+    /// Runs every hook registered for `event`. The first failing hook stops the chain and, for
+    /// `before_tool`, is reported as a block.
+    pub async fn run(
+        &self,
+        event: LifecycleHookEvent,
+        tool_name: Option<&str>,
+        mut payload: Value,
+    ) -> LifecycleHookOutcome {
+        if let Value::Object(fields) = &mut payload {
+            fields.insert("event".to_string(), json!(event.as_str()));
+            fields.insert("cwd".to_string(), json!(self.cwd.display().to_string()));
+        }
+        for spec in self
+            .specs
+            .iter()
+            .filter(|spec| spec.event == event && spec.matches_tool(tool_name))
+        {
+            let result = match (&spec.command, &spec.url) {
+                (Some(command), _) => self.run_command(spec, command, tool_name, &payload).await,
+                (None, Some(url)) => self.post_url(spec, url, &payload).await,
+                (None, None) => Ok(()),
+            };
+            if let Err(reason) = result {
+                if event == LifecycleHookEvent::BeforeTool {
+                    return LifecycleHookOutcome::Block { reason };
+                }
+                warn!(event = event.as_str(), "lifecycle hook failed: {reason}");
+                return LifecycleHookOutcome::Continue;
+            }
+        }
+        LifecycleHookOutcome::Continue
+    }
+
+    /// Wraps a tool so `before_tool`, `after_tool` and `after_file_edit` hooks run around it.
+    pub fn wrap_tool(self: &Arc<Self>, mut tool: AgentTool) -> AgentTool {
+        tool.execute = Arc::new(HookedToolExecutor {
+            tool_name: tool.name.clone(),
+            inner: tool.execute.clone(),
+            hooks: self.clone(),
+        });
+        tool
+    }
+
+    async fn run_command(
+        &self,
+        spec: &LifecycleHookSpec,
+        command: &str,
+        tool_name: Option<&str>,
+        payload: &Value,
+    ) -> Result<(), String> {
+        let mut process = Command::new("bash");
+        process
+            .arg("-lc")
+            .arg(command)
+            .current_dir(&self.cwd)
+            .env("PIXY_HOOK_EVENT", spec.event.as_str())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(tool_name) = tool_name {
+            process.env("PIXY_TOOL_NAME", tool_name);
+        }
+        let mut child = process
+            .spawn()
+            .map_err(|error| format!("spawn hook command '{command}' failed: {error}"))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            let body = payload.to_string();
+            // Hooks may exit without reading stdin; a broken pipe is not an error.
+            let _ = stdin.write_all(body.as_bytes()).await;
+        }
+        let output = tokio::time::timeout(spec.timeout(), child.wait_with_output())
+            .await
+            .map_err(|_| {
+                format!(
+                    "hook command '{command}' timed out after {}ms",
+                    spec.timeout().as_millis()
+                )
+            })?
+            .map_err(|error| format!("hook command '{command}' failed: {error}"))?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        let detail = if !stderr.is_empty() { stderr } else { stdout };
+        if detail.is_empty() {
+            Err(format!(
+                "hook command '{command}' exited with {}",
+                output.status
+            ))
+        } else {
+            Err(detail)
+        }
+    }
+
+    async fn post_url(
+        &self,
+        spec: &LifecycleHookSpec,
+        url: &str,
+        payload: &Value,
+    ) -> Result<(), String> {
+        let response = self
+            .client
+            .post(url)
+            .timeout(spec.timeout())
+            .json(payload)
+            .send()
+            .await
+            .map_err(|error| format!("hook request to {url} failed: {error}"))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let body = response.text().await.unwrap_or_default();
+        let body = body.trim();
+        if body.is_empty() {
+            Err(format!("hook request to {url} returned {status}"))
+        } else {
+            Err(body.to_string())
+        }
+    }
+}
+
+struct HookedToolExecutor {
+    tool_name: String,
+    inner: AgentToolExecuteFn,
+    hooks: Arc<LifecycleHooks>,
+}
+
+#[async_trait]
+impl AgentToolExecutor for HookedToolExecutor {
+    async fn execute(
+        &self,
+        tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        let tool_name = Some(self.tool_name.as_str());
+        let payload = json!({
+            "toolName": self.tool_name,
+            "toolCallId": tool_call_id,
+            "args": args,
+        });
+        if let LifecycleHookOutcome::Block { reason } = self
+            .hooks
+            .run(LifecycleHookEvent::BeforeTool, tool_name, payload)
+            .await
+        {
+            return Err(PiAiError::new(
+                PiAiErrorCode::ToolExecutionFailed,
+                format!("Tool call blocked by hook: {reason}"),
+            ));
+        }
+
+        let result = self.inner.execute(tool_call_id.clone(), args.clone()).await;
+
+        if self.hooks.has_event(LifecycleHookEvent::AfterTool) {
+            let (is_error, output) = match &result {
+                Ok(result) => (false, tool_result_text(result)),
+                Err(error) => (true, error.message.clone()),
+            };
+            let payload = json!({
+                "toolName": self.tool_name,
+                "toolCallId": tool_call_id,
+                "args": args,
+                "isError": is_error,
+                "result": output,
+            });
+            self.hooks
+                .run(LifecycleHookEvent::AfterTool, tool_name, payload)
+                .await;
+        }
+
+        if let (true, Ok(tool_result)) =
+            (FILE_EDIT_TOOLS.contains(&self.tool_name.as_str()), &result)
+        {
+            for path in edited_paths(&tool_result.details) {
+                let payload = json!({
+                    "toolName": self.tool_name,
+                    "toolCallId": tool_call_id,
+                    "path": path,
+                });
+                self.hooks
+                    .run(LifecycleHookEvent::AfterFileEdit, tool_name, payload)
+                    .await;
+            }
+        }
+
+        result
+    }
+}
+
+fn tool_result_text(result: &AgentToolResult) -> String {
+    result
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ToolResultContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::Arc;
+
+    use pixy_agent_core::AgentTool;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::create_write_tool;
+
+    fn command_hook(
+        event: LifecycleHookEvent,
+        tool_name: Option<&str>,
+        command: &str,
+    ) -> LifecycleHookSpec {
+        LifecycleHookSpec {
+            event,
+            tool_name: tool_name.map(ToOwned::to_owned),
+            command: Some(command.to_string()),
+            url: None,
+            timeout_ms: None,
+        }
+    }
+
+    fn wrapped_write_tool(hooks: LifecycleHooks, cwd: &Path) -> AgentTool {
+        Arc::new(hooks).wrap_tool(create_write_tool(cwd))
+    }
+
+    #[test]
+    fn spec_validation_requires_exactly_one_target() {
+        let mut spec = command_hook(LifecycleHookEvent::AfterTool, None, "true");
+        assert!(spec.validate().is_ok());
+        spec.url = Some("http://127.0.0.1:1".to_string());
+        assert!(spec.validate().is_err());
+        spec.command = None;
+        spec.url = None;
+        assert!(spec.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn failing_before_tool_hook_blocks_tool_call() {
+        let dir = tempdir().expect("tempdir should be created");
+        let hooks = LifecycleHooks::new(
+            dir.path(),
+            vec![command_hook(
+                LifecycleHookEvent::BeforeTool,
+                Some("write"),
+                "echo 'writes are frozen' >&2; exit 1",
+            )],
+        );
+        let tool = wrapped_write_tool(hooks, dir.path());
+
+        let error = tool
+            .execute
+            .execute(
+                "call-1".to_string(),
+                json!({ "path": "out.txt", "content": "hello" }),
+            )
+            .await
+            .expect_err("hook should block the write");
+
+        assert!(error.message.contains("writes are frozen"));
+        assert!(!dir.path().join("out.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn before_tool_hook_for_other_tool_does_not_block() {
+        let dir = tempdir().expect("tempdir should be created");
+        let hooks = LifecycleHooks::new(
+            dir.path(),
+            vec![command_hook(
+                LifecycleHookEvent::BeforeTool,
+                Some("bash"),
+                "exit 1",
+            )],
+        );
+        let tool = wrapped_write_tool(hooks, dir.path());
+
+        tool.execute
+            .execute(
+                "call-1".to_string(),
+                json!({ "path": "out.txt", "content": "hello" }),
+            )
+            .await
+            .expect("write should not be blocked");
+
+        assert!(dir.path().join("out.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn after_file_edit_hook_receives_payload_on_stdin() {
+        let dir = tempdir().expect("tempdir should be created");
+        let hooks = LifecycleHooks::new(
+            dir.path(),
+            vec![command_hook(
+                LifecycleHookEvent::AfterFileEdit,
+                None,
+                "cat > hook-payload.json",
+            )],
+        );
+        let tool = wrapped_write_tool(hooks, dir.path());
+
+        tool.execute
+            .execute(
+                "call-1".to_string(),
+                json!({ "path": "out.txt", "content": "hello" }),
+            )
+            .await
+            .expect("write should succeed");
+
+        let payload: Value = serde_json::from_str(
+            &fs::read_to_string(dir.path().join("hook-payload.json")).expect("payload file"),
+        )
+        .expect("payload should be json");
+        assert_eq!(payload["event"], "after_file_edit");
+        assert_eq!(payload["toolName"], "write");
+        assert_eq!(payload["path"], "out.txt");
+    }
+}
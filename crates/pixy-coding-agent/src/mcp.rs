@@ -0,0 +1,910 @@
+//! Client for Model Context Protocol servers declared under `[mcp.servers.<name>]` in `pixy.toml`.
+//!
+//! A server with a `command` is launched as a child process speaking JSON-RPC over stdio; one
+//! with a `url` is reached over HTTP with server-sent events. Each tool a server offers becomes an
+//! agent tool named `mcp__<server>__<tool>`, and servers offering resources or prompts also get
+//! `mcp__<server>__read_resource` and `mcp__<server>__get_prompt`.
+//!
+//! Connections live on a dedicated runtime so sessions, which are created synchronously, can
+//! connect at startup and the servers outlive whichever runtime later calls their tools.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use pixy_agent_core::{AgentTool, AgentToolExecutor, AgentToolResult};
+use pixy_ai::{PiAiError, PiAiErrorCode, ToolResultContentBlock};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::runtime::Runtime;
+use tokio::sync::{oneshot, Mutex as AsyncMutex};
+use tokio::task::AbortHandle;
+
+const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+const DEFAULT_MCP_TIMEOUT_MS: u64 = 30_000;
+const MCP_TOOL_PREFIX: &str = "mcp__";
+/// Providers reject longer tool names.
+const MAX_TOOL_NAME_CHARS: usize = 64;
+/// Resources and prompts named in a tool description beyond this many are only counted.
+const MAX_LISTED_ITEMS: usize = 20;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct McpConfig {
+    /// Servers keyed by the name their tools are prefixed with.
+    #[serde(default)]
+    pub servers: BTreeMap<String, McpServerConfig>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct McpServerConfig {
+    /// Program launched for the stdio transport.
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Variables added to the program's environment; `$NAME` values are read from `[env]` or
+    /// the environment.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Endpoint of the SSE transport, used when `command` is unset.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Headers sent with every SSE request, such as `Authorization`; `$NAME` values are resolved
+    /// like `env`.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Limit for connecting and for each request; defaults to 30 seconds.
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+impl McpServerConfig {
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.unwrap_or(DEFAULT_MCP_TIMEOUT_MS))
+    }
+}
+
+pub struct LoadMcpToolsResult {
+    pub tools: Vec<AgentTool>,
+    /// One message per server that could not be started, reached or listed.
+    pub errors: Vec<String>,
+}
+
+/// Connects to every configured server, in name order, and returns the tools they offer.
+/// Servers that fail are left out and reported in `errors`; the others stay connected for as
+/// long as any of their tools is alive.
+pub fn load_mcp_tools(cwd: &Path, config: &McpConfig) -> LoadMcpToolsResult {
+    let mut result = LoadMcpToolsResult {
+        tools: vec![],
+        errors: vec![],
+    };
+    if config.servers.is_empty() {
+        return result;
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    for (name, server) in &config.servers {
+        let sender = sender.clone();
+        let name = name.clone();
+        let server = server.clone();
+        let cwd = cwd.to_path_buf();
+        mcp_runtime().spawn(async move {
+            let timeout = server.timeout();
+            let connected =
+                match tokio::time::timeout(timeout, connect_server(&name, &server, &cwd)).await {
+                    Ok(connected) => connected,
+                    Err(_) => Err(format!("no answer within {} ms", timeout.as_millis())),
+                };
+            let _ = sender.send((name, connected));
+        });
+    }
+    drop(sender);
+
+    let mut servers: Vec<(String, Result<McpServer, String>)> = receiver.iter().collect();
+    servers.sort_by(|left, right| left.0.cmp(&right.0));
+    for (name, connected) in servers {
+        match connected {
+            Ok(server) => result.tools.extend(server.into_tools()),
+            Err(error) => result.errors.push(format!("MCP server '{name}': {error}")),
+        }
+    }
+    result
+}
+
+fn mcp_runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("pixy-mcp")
+            .enable_all()
+            .build()
+            .expect("failed to start the MCP runtime")
+    })
+}
+
+/// A connected server and what it offered when listed.
+struct McpServer {
+    name: String,
+    client: Arc<McpClient>,
+    tools: Vec<Value>,
+    resources: Vec<Value>,
+    prompts: Vec<Value>,
+}
+
+async fn connect_server(
+    name: &str,
+    config: &McpServerConfig,
+    cwd: &Path,
+) -> Result<McpServer, String> {
+    let client = Arc::new(McpClient::connect(name, config, cwd).await?);
+    let initialized = client
+        .request(
+            "initialize",
+            json!({
+                "protocolVersion": MCP_PROTOCOL_VERSION,
+                "capabilities": {},
+                "clientInfo": { "name": "pixy", "version": env!("CARGO_PKG_VERSION") },
+            }),
+        )
+        .await?;
+    client.notify("notifications/initialized").await?;
+
+    let capabilities = initialized.get("capabilities").cloned().unwrap_or_default();
+    let mut server = McpServer {
+        name: name.to_string(),
+        client: client.clone(),
+        tools: vec![],
+        resources: vec![],
+        prompts: vec![],
+    };
+    if capabilities.get("tools").is_some() {
+        server.tools = client.list_all("tools/list", "tools").await?;
+    }
+    if capabilities.get("resources").is_some() {
+        server.resources = client.list_all("resources/list", "resources").await?;
+    }
+    if capabilities.get("prompts").is_some() {
+        server.prompts = client.list_all("prompts/list", "prompts").await?;
+    }
+    Ok(server)
+}
+
+impl McpServer {
+    fn into_tools(self) -> Vec<AgentTool> {
+        let mut tools: Vec<AgentTool> = self
+            .tools
+            .iter()
+            .filter_map(|tool| self.tool(tool))
+            .collect();
+        if !self.resources.is_empty() {
+            tools.push(self.read_resource_tool());
+        }
+        if !self.prompts.is_empty() {
+            tools.push(self.get_prompt_tool());
+        }
+        tools
+    }
+
+    fn tool(&self, tool: &Value) -> Option<AgentTool> {
+        let name = tool.get("name")?.as_str()?;
+        let description = tool
+            .get("description")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|description| !description.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("`{name}` tool of the {} MCP server.", self.name));
+        let parameters = tool
+            .get("inputSchema")
+            .filter(|schema| schema.is_object())
+            .cloned()
+            .unwrap_or_else(|| json!({ "type": "object", "properties": {} }));
+        Some(self.agent_tool(
+            name,
+            description,
+            parameters,
+            McpCall::Tool(name.to_string()),
+        ))
+    }
+
+    fn read_resource_tool(&self) -> AgentTool {
+        let listed = self.resources.iter().map(|resource| {
+            let uri = resource.get("uri").and_then(Value::as_str).unwrap_or("?");
+            let mut line = format!("- {uri}");
+            if let Some(name) = resource.get("name").and_then(Value::as_str) {
+                line.push_str(&format!(" ({name})"));
+            }
+            if let Some(description) = resource.get("description").and_then(Value::as_str) {
+                line.push_str(&format!(": {}", description.trim()));
+            }
+            line
+        });
+        let description = format!(
+            "Read a resource of the {} MCP server by URI. Available resources:\n{}",
+            self.name,
+            listing(listed)
+        );
+        self.agent_tool(
+            "read_resource",
+            description,
+            json!({
+                "type": "object",
+                "properties": {
+                    "uri": { "type": "string", "description": "URI of the resource to read." }
+                },
+                "required": ["uri"],
+                "additionalProperties": false
+            }),
+            McpCall::ReadResource,
+        )
+    }
+
+    fn get_prompt_tool(&self) -> AgentTool {
+        let names: Vec<&str> = self
+            .prompts
+            .iter()
+            .filter_map(|prompt| prompt.get("name").and_then(Value::as_str))
+            .collect();
+        let listed = self.prompts.iter().map(|prompt| {
+            let name = prompt.get("name").and_then(Value::as_str).unwrap_or("?");
+            let arguments: Vec<String> = prompt
+                .get("arguments")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|argument| {
+                    let name = argument.get("name")?.as_str()?;
+                    let required = argument.get("required").and_then(Value::as_bool);
+                    Some(if required == Some(true) {
+                        name.to_string()
+                    } else {
+                        format!("{name}?")
+                    })
+                })
+                .collect();
+            let mut line = format!("- {name}({})", arguments.join(", "));
+            if let Some(description) = prompt.get("description").and_then(Value::as_str) {
+                line.push_str(&format!(": {}", description.trim()));
+            }
+            line
+        });
+        let description = format!(
+            "Fetch a prompt template of the {} MCP server, filled in with the given arguments. Available prompts (`?` marks optional arguments):\n{}",
+            self.name,
+            listing(listed)
+        );
+        self.agent_tool(
+            "get_prompt",
+            description,
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "enum": names, "description": "Prompt to fetch." },
+                    "arguments": {
+                        "type": "object",
+                        "additionalProperties": { "type": "string" },
+                        "description": "Values of the prompt's arguments."
+                    }
+                },
+                "required": ["name"],
+                "additionalProperties": false
+            }),
+            McpCall::GetPrompt,
+        )
+    }
+
+    fn agent_tool(
+        &self,
+        name: &str,
+        description: String,
+        parameters: Value,
+        call: McpCall,
+    ) -> AgentTool {
+        AgentTool {
+            name: mcp_tool_name(&self.name, name),
+            label: format!("{}/{name}", self.name),
+            description,
+            parameters,
+            conflict_key: None,
+            execute: Arc::new(McpToolExecutor {
+                client: self.client.clone(),
+                call,
+            }),
+        }
+    }
+}
+
+/// `mcp__<server>__<tool>`, with characters providers reject replaced by `_`.
+fn mcp_tool_name(server: &str, tool: &str) -> String {
+    format!("{MCP_TOOL_PREFIX}{server}__{tool}")
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '_' || ch == '-' {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .take(MAX_TOOL_NAME_CHARS)
+        .collect()
+}
+
+fn listing(lines: impl ExactSizeIterator<Item = String>) -> String {
+    let total = lines.len();
+    let mut listed: Vec<String> = lines.take(MAX_LISTED_ITEMS).collect();
+    if total > MAX_LISTED_ITEMS {
+        listed.push(format!("- ... and {} more", total - MAX_LISTED_ITEMS));
+    }
+    listed.join("\n")
+}
+
+#[derive(Clone, Debug)]
+enum McpCall {
+    Tool(String),
+    ReadResource,
+    GetPrompt,
+}
+
+struct McpToolExecutor {
+    client: Arc<McpClient>,
+    call: McpCall,
+}
+
+#[async_trait]
+impl AgentToolExecutor for McpToolExecutor {
+    async fn execute(
+        &self,
+        _tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        let client = self.client.clone();
+        let call = self.call.clone();
+        mcp_runtime()
+            .spawn(async move { call.run(&client, args).await })
+            .await
+            .map_err(|error| tool_failed(format!("MCP request failed: {error}")))?
+    }
+}
+
+impl McpCall {
+    async fn run(&self, client: &McpClient, args: Value) -> Result<AgentToolResult, PiAiError> {
+        match self {
+            Self::Tool(name) => {
+                let arguments = if args.is_null() { json!({}) } else { args };
+                let result = client
+                    .request(
+                        "tools/call",
+                        json!({ "name": name, "arguments": arguments }),
+                    )
+                    .await
+                    .map_err(tool_failed)?;
+                let content: Vec<ToolResultContentBlock> = result
+                    .get("content")
+                    .and_then(Value::as_array)
+                    .map(|blocks| blocks.iter().filter_map(content_block).collect())
+                    .unwrap_or_default();
+                if result.get("isError").and_then(Value::as_bool) == Some(true) {
+                    return Err(tool_failed(content_text(&content)));
+                }
+                Ok(AgentToolResult {
+                    content,
+                    details: json!({
+                        "server": client.server,
+                        "tool": name,
+                        "structuredContent": result.get("structuredContent"),
+                    }),
+                })
+            }
+            Self::ReadResource => {
+                let uri = args
+                    .get("uri")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| invalid_args("Missing `uri` string"))?;
+                let result = client
+                    .request("resources/read", json!({ "uri": uri }))
+                    .await
+                    .map_err(tool_failed)?;
+                let content = result
+                    .get("contents")
+                    .and_then(Value::as_array)
+                    .map(|contents| contents.iter().map(resource_block).collect())
+                    .unwrap_or_default();
+                Ok(AgentToolResult {
+                    content,
+                    details: json!({ "server": client.server, "uri": uri }),
+                })
+            }
+            Self::GetPrompt => {
+                let name = args
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| invalid_args("Missing `name` string"))?;
+                let arguments = args.get("arguments").cloned().unwrap_or_else(|| json!({}));
+                let result = client
+                    .request(
+                        "prompts/get",
+                        json!({ "name": name, "arguments": arguments }),
+                    )
+                    .await
+                    .map_err(tool_failed)?;
+                let messages: Vec<String> = result
+                    .get("messages")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .map(|message| {
+                        let role = message
+                            .get("role")
+                            .and_then(Value::as_str)
+                            .unwrap_or("user");
+                        let text = message
+                            .get("content")
+                            .and_then(content_block)
+                            .map(|block| content_text(&[block]))
+                            .unwrap_or_default();
+                        format!("[{role}]\n{text}")
+                    })
+                    .collect();
+                Ok(AgentToolResult {
+                    content: vec![text_block(messages.join("\n\n"))],
+                    details: json!({ "server": client.server, "prompt": name }),
+                })
+            }
+        }
+    }
+}
+
+/// One block of a tool result or prompt message; embedded resources are inlined as text.
+fn content_block(block: &Value) -> Option<ToolResultContentBlock> {
+    match block.get("type")?.as_str()? {
+        "text" => Some(text_block(block.get("text")?.as_str()?.to_string())),
+        "image" => Some(ToolResultContentBlock::Image {
+            data: block.get("data")?.as_str()?.to_string(),
+            mime_type: block.get("mimeType")?.as_str()?.to_string(),
+        }),
+        "resource" => Some(resource_block(block.get("resource")?)),
+        other => Some(text_block(format!("[unsupported {other} content]"))),
+    }
+}
+
+fn resource_block(resource: &Value) -> ToolResultContentBlock {
+    let uri = resource.get("uri").and_then(Value::as_str).unwrap_or("?");
+    let mime_type = resource
+        .get("mimeType")
+        .and_then(Value::as_str)
+        .unwrap_or("application/octet-stream");
+    if let Some(text) = resource.get("text").and_then(Value::as_str) {
+        return text_block(text.to_string());
+    }
+    match resource.get("blob").and_then(Value::as_str) {
+        Some(blob) if mime_type.starts_with("image/") => ToolResultContentBlock::Image {
+            data: blob.to_string(),
+            mime_type: mime_type.to_string(),
+        },
+        _ => text_block(format!("[binary resource {uri} ({mime_type})]")),
+    }
+}
+
+fn text_block(text: String) -> ToolResultContentBlock {
+    ToolResultContentBlock::Text {
+        text,
+        text_signature: None,
+    }
+}
+
+fn content_text(content: &[ToolResultContentBlock]) -> String {
+    content
+        .iter()
+        .filter_map(|block| match block {
+            ToolResultContentBlock::Text { text, .. } => Some(text.as_str()),
+            ToolResultContentBlock::Image { .. } => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn tool_failed(message: impl Into<String>) -> PiAiError {
+    PiAiError::new(PiAiErrorCode::ToolExecutionFailed, message.into())
+}
+
+fn invalid_args(message: impl Into<String>) -> PiAiError {
+    PiAiError::new(PiAiErrorCode::ToolArgumentsInvalid, message.into())
+}
+
+type PendingRequests = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>>;
+
+/// A JSON-RPC connection; responses are matched to requests by id on a reader task.
+struct McpClient {
+    server: String,
+    sender: McpSender,
+    pending: PendingRequests,
+    next_id: AtomicU64,
+    timeout: Duration,
+    reader: AbortHandle,
+    /// Killed on drop, which ends the reader.
+    _child: Option<Child>,
+}
+
+impl Drop for McpClient {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+impl McpClient {
+    async fn connect(name: &str, config: &McpServerConfig, cwd: &Path) -> Result<Self, String> {
+        let pending = PendingRequests::default();
+        if let Some(program) = config.command.as_deref().map(str::trim) {
+            let mut child = Command::new(program)
+                .args(&config.args)
+                .envs(&config.env)
+                .current_dir(cwd)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|error| format!("failed to start `{program}`: {error}"))?;
+            let stdin = child.stdin.take().ok_or("missing stdin pipe")?;
+            let stdout = child.stdout.take().ok_or("missing stdout pipe")?;
+            let sender = McpSender::Stdio(Arc::new(AsyncMutex::new(stdin)));
+            let reader = tokio::spawn(read_stdio(stdout, pending.clone(), sender.clone()));
+            return Ok(Self::new(
+                name,
+                config,
+                sender,
+                pending,
+                reader.abort_handle(),
+                Some(child),
+            ));
+        }
+
+        let url = config
+            .url
+            .as_deref()
+            .map(str::trim)
+            .ok_or("needs a `command` or a `url`")?;
+        let (sender, reader) = connect_sse(url, &config.headers, pending.clone()).await?;
+        Ok(Self::new(name, config, sender, pending, reader, None))
+    }
+
+    fn new(
+        name: &str,
+        config: &McpServerConfig,
+        sender: McpSender,
+        pending: PendingRequests,
+        reader: AbortHandle,
+        child: Option<Child>,
+    ) -> Self {
+        Self {
+            server: name.to_string(),
+            sender,
+            pending,
+            next_id: AtomicU64::new(1),
+            timeout: config.timeout(),
+            reader,
+            _child: child,
+        }
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (reply, response) = oneshot::channel();
+        self.pending
+            .lock()
+            .expect("MCP pending requests poisoned")
+            .insert(id, reply);
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        if let Err(error) = self.sender.send(&message).await {
+            self.forget(id);
+            return Err(format!("{method}: {error}"));
+        }
+        match tokio::time::timeout(self.timeout, response).await {
+            Ok(Ok(result)) => result.map_err(|error| format!("{method}: {error}")),
+            Ok(Err(_)) => Err(format!("{method}: the server closed the connection")),
+            Err(_) => {
+                self.forget(id);
+                Err(format!(
+                    "{method}: no response within {} ms",
+                    self.timeout.as_millis()
+                ))
+            }
+        }
+    }
+
+    async fn notify(&self, method: &str) -> Result<(), String> {
+        self.sender
+            .send(&json!({ "jsonrpc": "2.0", "method": method }))
+            .await
+            .map_err(|error| format!("{method}: {error}"))
+    }
+
+    /// Every item of a paginated list, following `nextCursor`.
+    async fn list_all(&self, method: &str, key: &str) -> Result<Vec<Value>, String> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let page = self.request(method, params).await?;
+            if let Some(page_items) = page.get(key).and_then(Value::as_array) {
+                items.extend(page_items.iter().cloned());
+            }
+            cursor = page
+                .get("nextCursor")
+                .and_then(Value::as_str)
+                .filter(|next| !next.is_empty())
+                .map(str::to_string);
+            if cursor.is_none() {
+                return Ok(items);
+            }
+        }
+    }
+
+    fn forget(&self, id: u64) {
+        self.pending
+            .lock()
+            .expect("MCP pending requests poisoned")
+            .remove(&id);
+    }
+}
+
+#[derive(Clone)]
+enum McpSender {
+    Stdio(Arc<AsyncMutex<ChildStdin>>),
+    Sse {
+        http: reqwest::Client,
+        endpoint: reqwest::Url,
+        headers: HashMap<String, String>,
+    },
+}
+
+impl McpSender {
+    async fn send(&self, message: &Value) -> Result<(), String> {
+        match self {
+            Self::Stdio(stdin) => {
+                let mut line = message.to_string();
+                line.push('\n');
+                let mut stdin = stdin.lock().await;
+                stdin
+                    .write_all(line.as_bytes())
+                    .await
+                    .map_err(|error| format!("failed to write to the server: {error}"))?;
+                stdin
+                    .flush()
+                    .await
+                    .map_err(|error| format!("failed to write to the server: {error}"))
+            }
+            Self::Sse {
+                http,
+                endpoint,
+                headers,
+            } => {
+                let mut request = http.post(endpoint.clone()).json(message);
+                for (name, value) in headers {
+                    request = request.header(name, value);
+                }
+                let response = request
+                    .send()
+                    .await
+                    .map_err(|error| format!("failed to post to {endpoint}: {error}"))?;
+                if !response.status().is_success() {
+                    return Err(format!("{endpoint} answered {}", response.status()));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+async fn read_stdio(stdout: ChildStdout, pending: PendingRequests, sender: McpSender) {
+    let mut lines = BufReader::new(stdout).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        handle_message(&line, &pending, &sender).await;
+    }
+    close_pending(&pending);
+}
+
+/// Opens the event stream and waits for the `endpoint` event naming where requests are posted.
+async fn connect_sse(
+    url: &str,
+    headers: &HashMap<String, String>,
+    pending: PendingRequests,
+) -> Result<(McpSender, AbortHandle), String> {
+    let url = reqwest::Url::parse(url).map_err(|error| format!("invalid url `{url}`: {error}"))?;
+    let http = reqwest::Client::new();
+    let mut request = http.get(url.clone()).header("Accept", "text/event-stream");
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    let mut response = request
+        .send()
+        .await
+        .map_err(|error| format!("failed to connect to {url}: {error}"))?;
+    if !response.status().is_success() {
+        return Err(format!("{url} answered {}", response.status()));
+    }
+
+    let mut decoder = SseDecoder::default();
+    let mut early_events = Vec::new();
+    let endpoint = loop {
+        let chunk = response
+            .chunk()
+            .await
+            .map_err(|error| format!("failed to read from {url}: {error}"))?
+            .ok_or_else(|| format!("{url} closed the stream before naming an endpoint"))?;
+        let mut events = decoder.push(&chunk).into_iter();
+        if let Some(endpoint) = events.by_ref().find(|event| event.event == "endpoint") {
+            early_events.extend(events);
+            break url
+                .join(endpoint.data.trim())
+                .map_err(|error| format!("invalid endpoint `{}`: {error}", endpoint.data))?;
+        }
+    };
+
+    let sender = McpSender::Sse {
+        http,
+        endpoint,
+        headers: headers.clone(),
+    };
+    let reader_sender = sender.clone();
+    let reader = tokio::spawn(async move {
+        for event in early_events {
+            handle_sse_event(&event, &pending, &reader_sender).await;
+        }
+        while let Ok(Some(chunk)) = response.chunk().await {
+            for event in decoder.push(&chunk) {
+                handle_sse_event(&event, &pending, &reader_sender).await;
+            }
+        }
+        close_pending(&pending);
+    });
+    Ok((sender, reader.abort_handle()))
+}
+
+async fn handle_sse_event(event: &SseEvent, pending: &PendingRequests, sender: &McpSender) {
+    if event.event == "message" {
+        handle_message(&event.data, pending, sender).await;
+    }
+}
+
+/// Resolves the pending request a response answers; requests from the server are answered
+/// with an empty result for `ping` and "method not found" otherwise, and notifications are
+/// ignored.
+async fn handle_message(text: &str, pending: &PendingRequests, sender: &McpSender) {
+    let Ok(message) = serde_json::from_str::<Value>(text) else {
+        return;
+    };
+    let Some(id) = message.get("id") else {
+        return;
+    };
+    if let Some(method) = message.get("method").and_then(Value::as_str) {
+        let reply = if method == "ping" {
+            json!({ "jsonrpc": "2.0", "id": id, "result": {} })
+        } else {
+            json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": format!("Method not found: {method}") },
+            })
+        };
+        let _ = sender.send(&reply).await;
+        return;
+    }
+
+    let Some(reply) = id.as_u64().and_then(|id| {
+        pending
+            .lock()
+            .expect("MCP pending requests poisoned")
+            .remove(&id)
+    }) else {
+        return;
+    };
+    let result = match message.get("error") {
+        Some(error) => Err(error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown error")
+            .to_string()),
+        None => Ok(message.get("result").cloned().unwrap_or_default()),
+    };
+    let _ = reply.send(result);
+}
+
+/// Fails every request still waiting, once the connection is gone.
+fn close_pending(pending: &PendingRequests) {
+    pending
+        .lock()
+        .expect("MCP pending requests poisoned")
+        .clear();
+}
+
+#[derive(Debug, PartialEq)]
+struct SseEvent {
+    event: String,
+    data: String,
+}
+
+#[derive(Default)]
+struct SseDecoder {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    /// The events completed by `chunk`; a partial line is kept for the next one.
+    fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(SseEvent {
+                        event: self.event.take().unwrap_or_else(|| "message".to_string()),
+                        data: std::mem::take(&mut self.data).join("\n"),
+                    });
+                }
+                self.event = None;
+                continue;
+            }
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.event = Some(value.to_string()),
+                "data" => self.data.push(value.to_string()),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mcp_tool_name, SseDecoder, SseEvent};
+
+    #[test]
+    fn tool_names_are_prefixed_sanitized_and_capped() {
+        assert_eq!(
+            mcp_tool_name("github", "create_issue"),
+            "mcp__github__create_issue"
+        );
+        assert_eq!(
+            mcp_tool_name("my docs", "search.v2"),
+            "mcp__my_docs__search_v2"
+        );
+        assert_eq!(mcp_tool_name("s", &"x".repeat(100)).len(), 64);
+    }
+
+    #[test]
+    fn sse_decoder_joins_events_split_across_chunks() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.push(b"event: endpoint\r\ndata: /mess").is_empty());
+        assert_eq!(
+            decoder.push(b"ages?id=1\r\n\r\n: keepalive\n\ndata: {\"a\":\ndata: 1}\n\n"),
+            vec![
+                SseEvent {
+                    event: "endpoint".to_string(),
+                    data: "/messages?id=1".to_string(),
+                },
+                SseEvent {
+                    event: "message".to_string(),
+                    data: "{\"a\":\n1}".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! `pixy mcp-serve`: a session offered to other clients as a Model Context Protocol server.
+//!
+//! Requests arrive as JSON-RPC lines on stdin and responses are written to stdout. The
+//! session's tools are listed as they are, plus a `prompt` tool that sends a message to the
+//! session's agent and returns its reply. Enabled skills are listed as prompts. Direct tool
+//! calls go through the session's tool policy and `[approval]` flags like calls of the agent;
+//! tools the policy denies are not listed. Requests are handled one at a time, in order, so a
+//! `prompt` call holds later requests until it finishes.
+
+use std::collections::HashMap;
+
+use pixy_agent_core::{unattended_tool_error, AgentToolResult, ApprovalDecision};
+use pixy_ai::{Message, StopReason, ToolCall, ToolResultContentBlock};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::multi_agent::last_assistant_text;
+use crate::AgentSession;
+
+const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+const MCP_PROMPT_TOOL_NAME: &str = "prompt";
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Answers requests read from `input` on `output` until `input` ends.
+pub async fn serve_mcp_session<R, W>(
+    session: &mut AgentSession,
+    input: R,
+    mut output: W,
+) -> Result<(), String>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut lines = input.lines();
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|error| format!("read MCP request failed: {error}"))?
+    {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some(response) = handle_line(session, &line).await else {
+            continue;
+        };
+        let mut text = response.to_string();
+        text.push('\n');
+        output
+            .write_all(text.as_bytes())
+            .await
+            .map_err(|error| format!("write MCP response failed: {error}"))?;
+        output
+            .flush()
+            .await
+            .map_err(|error| format!("write MCP response failed: {error}"))?;
+    }
+    Ok(())
+}
+
+/// The response to one line, or `None` for notifications.
+async fn handle_line(session: &mut AgentSession, line: &str) -> Option<Value> {
+    let Ok(request) = serde_json::from_str::<Value>(line) else {
+        return Some(error_response(&Value::Null, PARSE_ERROR, "Parse error"));
+    };
+    let id = request.get("id")?.clone();
+    let method = request.get("method").and_then(Value::as_str).unwrap_or("");
+    let params = request.get("params").cloned().unwrap_or_else(|| json!({}));
+    let result = match method {
+        "initialize" => Ok(initialize_result(&params)),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": list_tools(session) })),
+        "tools/call" => call_tool(session, &params).await,
+        "prompts/list" => Ok(json!({ "prompts": list_prompts(session) })),
+        "prompts/get" => get_prompt(session, &params),
+        _ => Err((METHOD_NOT_FOUND, format!("Method not found: {method}"))),
+    };
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error_response(&id, code, &message),
+    })
+}
+
+fn error_response(id: &Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn initialize_result(params: &Value) -> Value {
+    let protocol_version = params
+        .get("protocolVersion")
+        .and_then(Value::as_str)
+        .unwrap_or(MCP_PROTOCOL_VERSION);
+    json!({
+        "protocolVersion": protocol_version,
+        "capabilities": { "tools": {}, "prompts": {} },
+        "serverInfo": { "name": "pixy", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+fn list_tools(session: &AgentSession) -> Vec<Value> {
+    let mut tools: Vec<Value> = session
+        .tools()
+        .iter()
+        .filter(|tool| {
+            session
+                .tool_policy()
+                .is_none_or(|policy| policy.allows_tool(&tool.name))
+        })
+        .map(|tool| {
+            json!({
+                "name": tool.name,
+                "description": tool.description,
+                "inputSchema": tool.parameters,
+            })
+        })
+        .collect();
+    tools.push(json!({
+        "name": MCP_PROMPT_TOOL_NAME,
+        "description": "Send a message to the pixy coding agent and return its final reply. The agent works in its own session with its own tools, and remembers earlier messages of this connection.",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "message": { "type": "string", "description": "Message for the agent." }
+            },
+            "required": ["message"],
+            "additionalProperties": false
+        },
+    }));
+    tools
+}
+
+async fn call_tool(session: &mut AgentSession, params: &Value) -> Result<Value, (i64, String)> {
+    let name = params
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| (INVALID_PARAMS, "Missing tool name".to_string()))?;
+    let arguments = params
+        .get("arguments")
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+
+    if name == MCP_PROMPT_TOOL_NAME {
+        let message = arguments
+            .get("message")
+            .and_then(Value::as_str)
+            .ok_or_else(|| (INVALID_PARAMS, "Missing `message` string".to_string()))?;
+        return Ok(match session.prompt(message).await {
+            Ok(produced) => prompt_result(&produced),
+            Err(error) => tool_error(&error),
+        });
+    }
+
+    let tool = session
+        .tools()
+        .iter()
+        .find(|tool| tool.name == name)
+        .ok_or_else(|| (INVALID_PARAMS, format!("Unknown tool: {name}")))?;
+    let call_id = format!("mcp-{}", chrono::Utc::now().timestamp_micros());
+    let policy = session.tool_policy();
+    if let Some(policy) = policy {
+        if let Err(error) = policy.check(name, &arguments) {
+            return Ok(tool_error(&error.message));
+        }
+        if policy.dry_run {
+            return Ok(tool_result(policy.dry_run_result(name, &arguments)));
+        }
+    }
+    let approval = session.tool_approval();
+    if let Some((approval, risk)) = approval
+        .as_ref()
+        .and_then(|approval| Some((approval, approval.risk(name)?)))
+    {
+        if approval.approver.is_none() {
+            return Ok(tool_error(&unattended_tool_error(name).message));
+        }
+        let call = ToolCall {
+            id: call_id.clone(),
+            name: name.to_string(),
+            arguments: arguments.clone(),
+        };
+        if let ApprovalDecision::Deny { .. } = approval.request(call, risk).await {
+            return Ok(tool_error(&format!(
+                "The user denied the {name} call; it was not run."
+            )));
+        }
+    }
+    Ok(match tool.execute.execute(call_id, arguments).await {
+        Ok(result) => tool_result(match policy {
+            Some(policy) => policy.limit_output(result),
+            None => result,
+        }),
+        Err(error) => tool_error(&error.message),
+    })
+}
+
+fn prompt_result(produced: &[Message]) -> Value {
+    let last_assistant = produced
+        .iter()
+        .rev()
+        .find(|message| matches!(message, Message::Assistant { .. }));
+    if let Some(Message::Assistant {
+        stop_reason: StopReason::Error | StopReason::Aborted,
+        error_message,
+        ..
+    }) = last_assistant
+    {
+        return tool_error(error_message.as_deref().unwrap_or("the run was aborted"));
+    }
+    json!({
+        "content": [{
+            "type": "text",
+            "text": last_assistant_text(produced).unwrap_or_default(),
+        }],
+        "isError": false,
+    })
+}
+
+fn tool_result(result: AgentToolResult) -> Value {
+    let content: Vec<Value> = result
+        .content
+        .into_iter()
+        .map(|block| match block {
+            ToolResultContentBlock::Text { text, .. } => json!({ "type": "text", "text": text }),
+            ToolResultContentBlock::Image { data, mime_type } => {
+                json!({ "type": "image", "data": data, "mimeType": mime_type })
+            }
+        })
+        .collect();
+    json!({ "content": content, "isError": false })
+}
+
+fn tool_error(message: &str) -> Value {
+    json!({ "content": [{ "type": "text", "text": message }], "isError": true })
+}
+
+fn list_prompts(session: &AgentSession) -> Vec<Value> {
+    session
+        .enabled_skills()
+        .into_iter()
+        .map(|skill| {
+            let arguments: Vec<Value> = skill
+                .arguments
+                .iter()
+                .map(|argument| {
+                    json!({
+                        "name": argument.name,
+                        "description": argument.description,
+                        "required": argument.required,
+                    })
+                })
+                .collect();
+            json!({
+                "name": skill.name,
+                "description": skill.description,
+                "arguments": arguments,
+            })
+        })
+        .collect()
+}
+
+fn get_prompt(session: &AgentSession, params: &Value) -> Result<Value, (i64, String)> {
+    let name = params
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| (INVALID_PARAMS, "Missing prompt name".to_string()))?;
+    let arguments: HashMap<String, String> = params
+        .get("arguments")
+        .cloned()
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|error| (INVALID_PARAMS, format!("Invalid prompt arguments: {error}")))?
+        .unwrap_or_default();
+    let text = session
+        .render_skill(name, &arguments)
+        .map_err(|error| (INVALID_PARAMS, error))?;
+    Ok(json!({
+        "messages": [{ "role": "user", "content": { "type": "text", "text": text } }],
+    }))
+}
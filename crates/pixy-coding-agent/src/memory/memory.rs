@@ -1,6 +1,7 @@
 use super::config::MemoryConfig;
 use super::file_store::{FileStore, FileStoreError};
 use super::search::{MemorySearch, SearchResult};
+use super::semantic::{chunk_memory_file, MemoryChunk};
 use chrono::{Local, NaiveDate};
 use std::path::PathBuf;
 use thiserror::Error;
@@ -167,6 +168,23 @@ impl MemoryManager {
         Ok(search.search_text_with_options(query, max_results, threshold))
     }
 
+    /// Split every dated memory file into chunks for semantic indexing.
+    pub fn chunks(&self) -> Result<Vec<MemoryChunk>, MemoryError> {
+        let mut chunks = Vec::new();
+        for path in self.file_store.list_files()? {
+            let Some(date) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| self.file_store.parse_date_from_filename(name))
+            else {
+                continue;
+            };
+            let content = self.file_store.read_file(&path)?;
+            chunks.extend(chunk_memory_file(&path, date, &content));
+        }
+        Ok(chunks)
+    }
+
     /// Perform memory flush.
     pub fn flush(&self, context: &MemoryFlushContext) -> Result<(), MemoryError> {
         let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
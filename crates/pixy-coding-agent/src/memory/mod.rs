@@ -5,13 +5,18 @@
 
 pub mod config;
 pub mod file_store;
+#[allow(clippy::module_inception)]
 pub mod memory;
 pub mod search;
+pub mod semantic;
 
 pub use config::MemoryConfig;
 pub use file_store::{FileStore, FileStoreError, MemoryEntry};
 pub use memory::{MemoryError, MemoryFlushContext, MemoryManager};
 pub use search::{MemorySearch, SearchResult};
+pub use semantic::{
+    chunk_memory_file, MemoryChunk, MemoryEmbedder, PixyAiMemoryEmbedder, SemanticMemoryIndex,
+};
 
 /// Re-export common memory types.
 pub mod prelude {
@@ -19,4 +24,5 @@ pub mod prelude {
     pub use super::file_store::{FileStore, FileStoreError, MemoryEntry};
     pub use super::memory::{MemoryError, MemoryFlushContext, MemoryManager};
     pub use super::search::{MemorySearch, SearchResult};
+    pub use super::semantic::{MemoryChunk, MemoryEmbedder, SemanticMemoryIndex};
 }
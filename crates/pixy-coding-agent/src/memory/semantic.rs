@@ -0,0 +1,292 @@
+//! Embedding-backed memory search.
+//!
+//! Memory files are split into chunks, each chunk is embedded once and cached by content hash in
+//! `<memory_dir>/.embeddings.json`, and queries are ranked by cosine similarity.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use pixy_ai::{EmbeddingModel, EmbeddingOptions};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::search::SearchResult;
+
+const CACHE_FILE_NAME: &str = ".embeddings.json";
+const MAX_CHUNK_CHARS: usize = 1_200;
+const EMBED_BATCH_SIZE: usize = 64;
+const SNIPPET_CHARS: usize = 180;
+
+/// Produces embedding vectors for memory chunks and queries.
+#[async_trait]
+pub trait MemoryEmbedder: Send + Sync {
+    /// Stable identifier of the embedding model; cached vectors from other models are discarded.
+    fn model_key(&self) -> String;
+
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, String>;
+}
+
+/// [`MemoryEmbedder`] backed by the pixy-ai embeddings API.
+pub struct PixyAiMemoryEmbedder {
+    model: EmbeddingModel,
+    options: EmbeddingOptions,
+}
+
+impl PixyAiMemoryEmbedder {
+    pub fn new(model: EmbeddingModel, api_key: Option<String>) -> Self {
+        Self {
+            model,
+            options: EmbeddingOptions {
+                api_key,
+                headers: None,
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl MemoryEmbedder for PixyAiMemoryEmbedder {
+    fn model_key(&self) -> String {
+        format!("{}/{}", self.model.provider, self.model.id)
+    }
+
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        pixy_ai::embed(&self.model, inputs, Some(&self.options))
+            .await
+            .map_err(|error| error.to_string())
+    }
+}
+
+/// A searchable slice of a memory file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryChunk {
+    pub path: PathBuf,
+    pub date: NaiveDate,
+    /// 1-based line where the chunk starts.
+    pub line: usize,
+    pub text: String,
+}
+
+impl MemoryChunk {
+    fn hash(&self) -> String {
+        let digest = Sha256::digest(self.text.as_bytes());
+        digest.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+}
+
+/// Splits a memory file into chunks at markdown headings and blank-line paragraph boundaries,
+/// keeping each chunk under a size budget.
+pub fn chunk_memory_file(path: &Path, date: NaiveDate, content: &str) -> Vec<MemoryChunk> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut start_line = 1;
+
+    let mut flush = |current: &mut String, start_line: usize| {
+        let text = current.trim();
+        if !text.is_empty() {
+            chunks.push(MemoryChunk {
+                path: path.to_path_buf(),
+                date,
+                line: start_line,
+                text: text.to_string(),
+            });
+        }
+        current.clear();
+    };
+
+    for (index, line) in content.lines().enumerate() {
+        let line_no = index + 1;
+        let starts_section = line.starts_with('#');
+        let paragraph_break = line.trim().is_empty() && current.len() >= MAX_CHUNK_CHARS / 2;
+        if (starts_section || current.len() + line.len() > MAX_CHUNK_CHARS) && !current.is_empty() {
+            flush(&mut current, start_line);
+        }
+        if current.is_empty() {
+            start_line = line_no;
+        }
+        current.push_str(line);
+        current.push('\n');
+        if paragraph_break {
+            flush(&mut current, start_line);
+        }
+    }
+    flush(&mut current, start_line);
+    chunks
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct EmbeddingCache {
+    model: String,
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+/// Embedding index over memory chunks with an on-disk vector cache.
+pub struct SemanticMemoryIndex {
+    embedder: Arc<dyn MemoryEmbedder>,
+    cache_path: PathBuf,
+    cache: tokio::sync::Mutex<Option<EmbeddingCache>>,
+}
+
+impl SemanticMemoryIndex {
+    pub fn new(embedder: Arc<dyn MemoryEmbedder>, memory_dir: &Path) -> Self {
+        Self {
+            embedder,
+            cache_path: memory_dir.join(CACHE_FILE_NAME),
+            cache: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// Embeds chunks that have no cached vector yet and drops vectors for chunks that no longer
+    /// exist.
+    pub async fn index(&self, chunks: &[MemoryChunk]) -> Result<(), String> {
+        let mut guard = self.cache.lock().await;
+        let cache = guard.get_or_insert_with(|| self.load_cache());
+        self.refresh(cache, chunks).await
+    }
+
+    /// Ranks chunks by cosine similarity to `query`.
+    pub async fn search(
+        &self,
+        query: &str,
+        chunks: &[MemoryChunk],
+        max_results: usize,
+        min_score: f32,
+    ) -> Result<Vec<SearchResult>, String> {
+        if query.trim().is_empty() || max_results == 0 || chunks.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut guard = self.cache.lock().await;
+        let cache = guard.get_or_insert_with(|| self.load_cache());
+        self.refresh(cache, chunks).await?;
+
+        let query_vector = self
+            .embedder
+            .embed(&[query.to_string()])
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| "embedding provider returned no query vector".to_string())?;
+
+        let mut results = chunks
+            .iter()
+            .filter_map(|chunk| {
+                let vector = cache.vectors.get(&chunk.hash())?;
+                let score = cosine_similarity(&query_vector, vector).clamp(0.0, 1.0);
+                (score > 0.0 && score >= min_score).then(|| SearchResult {
+                    path: chunk.path.clone(),
+                    date: chunk.date,
+                    snippet: snippet(&chunk.text),
+                    score,
+                    line_numbers: vec![chunk.line],
+                })
+            })
+            .collect::<Vec<_>>();
+        results.sort_by(|left, right| {
+            right
+                .score
+                .partial_cmp(&left.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| right.date.cmp(&left.date))
+                .then_with(|| left.path.cmp(&right.path))
+                .then_with(|| left.line_numbers.cmp(&right.line_numbers))
+        });
+        results.truncate(max_results);
+        Ok(results)
+    }
+
+    async fn refresh(
+        &self,
+        cache: &mut EmbeddingCache,
+        chunks: &[MemoryChunk],
+    ) -> Result<(), String> {
+        let model_key = self.embedder.model_key();
+        if cache.model != model_key {
+            *cache = EmbeddingCache {
+                model: model_key,
+                vectors: HashMap::new(),
+            };
+        }
+
+        let mut live = HashMap::new();
+        for chunk in chunks {
+            live.entry(chunk.hash()).or_insert(chunk);
+        }
+        let before = cache.vectors.len();
+        cache.vectors.retain(|hash, _| live.contains_key(hash));
+        let mut changed = cache.vectors.len() != before;
+
+        let missing = live
+            .iter()
+            .filter(|(hash, _)| !cache.vectors.contains_key(*hash))
+            .map(|(hash, chunk)| (hash.clone(), chunk.text.clone()))
+            .collect::<Vec<_>>();
+        for batch in missing.chunks(EMBED_BATCH_SIZE) {
+            let inputs = batch
+                .iter()
+                .map(|(_, text)| text.clone())
+                .collect::<Vec<_>>();
+            let vectors = self.embedder.embed(&inputs).await?;
+            if vectors.len() != inputs.len() {
+                return Err(format!(
+                    "embedding provider returned {} vectors for {} inputs",
+                    vectors.len(),
+                    inputs.len()
+                ));
+            }
+            for ((hash, _), vector) in batch.iter().zip(vectors) {
+                cache.vectors.insert(hash.clone(), vector);
+            }
+            changed = true;
+        }
+
+        if changed {
+            self.save_cache(cache)?;
+        }
+        Ok(())
+    }
+
+    fn load_cache(&self) -> EmbeddingCache {
+        std::fs::read_to_string(&self.cache_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_cache(&self, cache: &EmbeddingCache) -> Result<(), String> {
+        let content = serde_json::to_string(cache)
+            .map_err(|error| format!("serialize embedding cache failed: {error}"))?;
+        std::fs::write(&self.cache_path, content)
+            .map_err(|error| format!("write {} failed: {error}", self.cache_path.display()))
+    }
+}
+
+fn cosine_similarity(left: &[f32], right: &[f32]) -> f32 {
+    if left.len() != right.len() || left.is_empty() {
+        return 0.0;
+    }
+    let mut dot = 0.0_f32;
+    let mut left_norm = 0.0_f32;
+    let mut right_norm = 0.0_f32;
+    for (left, right) in left.iter().zip(right) {
+        dot += left * right;
+        left_norm += left * left;
+        right_norm += right * right;
+    }
+    if left_norm == 0.0 || right_norm == 0.0 {
+        return 0.0;
+    }
+    dot / (left_norm.sqrt() * right_norm.sqrt())
+}
+
+fn snippet(text: &str) -> String {
+    if text.chars().count() <= SNIPPET_CHARS {
+        return text.to_string();
+    }
+    let mut snippet = text.chars().take(SNIPPET_CHARS).collect::<String>();
+    snippet.push_str("...");
+    snippet
+}
@@ -5,13 +5,24 @@ use pixy_agent_core::{AgentTool, AgentToolExecutor, AgentToolResult};
 use pixy_ai::{PiAiError, PiAiErrorCode, ToolResultContentBlock};
 use serde_json::{json, Value};
 
-use crate::memory::{MemoryFlushContext, MemoryManager};
+use crate::memory::{MemoryFlushContext, MemoryManager, SearchResult, SemanticMemoryIndex};
 
 /// Build a `memory` tool backed by a shared `MemoryManager`.
 pub fn create_memory_tool(
     memory: Arc<Mutex<MemoryManager>>,
     default_max_results: usize,
     default_min_score: f32,
+) -> AgentTool {
+    create_memory_tool_with_semantic_index(memory, None, default_max_results, default_min_score)
+}
+
+/// Build a `memory` tool whose search ranks entries by embedding similarity when a semantic index
+/// is provided, falling back to keyword search if embedding fails.
+pub fn create_memory_tool_with_semantic_index(
+    memory: Arc<Mutex<MemoryManager>>,
+    semantic_index: Option<Arc<SemanticMemoryIndex>>,
+    default_max_results: usize,
+    default_min_score: f32,
 ) -> AgentTool {
     AgentTool {
         name: "memory".to_string(),
@@ -44,8 +55,10 @@ pub fn create_memory_tool(
             "required": ["action"],
             "additionalProperties": false
         }),
+        conflict_key: None,
         execute: Arc::new(MemoryToolExecutor {
             memory,
+            semantic_index,
             default_max_results: default_max_results.max(1),
             default_min_score: default_min_score.clamp(0.0, 1.0),
         }),
@@ -54,6 +67,7 @@ pub fn create_memory_tool(
 
 struct MemoryToolExecutor {
     memory: Arc<Mutex<MemoryManager>>,
+    semantic_index: Option<Arc<SemanticMemoryIndex>>,
     default_max_results: usize,
     default_min_score: f32,
 }
@@ -67,8 +81,8 @@ impl AgentToolExecutor for MemoryToolExecutor {
     ) -> Result<AgentToolResult, PiAiError> {
         let action = required_string(&args, "action")?;
         match action.as_str() {
-            "record" => self.execute_record(&args),
-            "search" => self.execute_search(&args),
+            "record" => self.execute_record(&args).await,
+            "search" => self.execute_search(&args).await,
             "get" => self.execute_get(&args),
             "flush" => self.execute_flush(&args),
             "cleanup" => self.execute_cleanup(),
@@ -80,12 +94,21 @@ impl AgentToolExecutor for MemoryToolExecutor {
 }
 
 impl MemoryToolExecutor {
-    fn execute_record(&self, args: &Value) -> Result<AgentToolResult, PiAiError> {
+    async fn execute_record(&self, args: &Value) -> Result<AgentToolResult, PiAiError> {
         let content = required_string(args, "content")?;
-        let manager = lock_memory(&self.memory)?;
-        manager
+        lock_memory(&self.memory)?
             .record(&content)
             .map_err(|error| tool_execution_failed(error.to_string()))?;
+        if let Some(index) = &self.semantic_index {
+            let chunks = lock_memory(&self.memory)?.chunks();
+            let indexed = match chunks {
+                Ok(chunks) => index.index(&chunks).await,
+                Err(error) => Err(error.to_string()),
+            };
+            if let Err(error) = indexed {
+                tracing::warn!("memory embedding index update failed: {error}");
+            }
+        }
         Ok(text_result(
             "Memory recorded.".to_string(),
             json!({
@@ -95,15 +118,20 @@ impl MemoryToolExecutor {
         ))
     }
 
-    fn execute_search(&self, args: &Value) -> Result<AgentToolResult, PiAiError> {
+    async fn execute_search(&self, args: &Value) -> Result<AgentToolResult, PiAiError> {
         let query = required_string(args, "query")?;
         let max_results = optional_usize(args, "max_results")?.unwrap_or(self.default_max_results);
         let min_score = optional_f32(args, "min_score")?.unwrap_or(self.default_min_score);
 
-        let manager = lock_memory(&self.memory)?;
-        let results = manager
-            .search_scored(&query, max_results, min_score)
-            .map_err(|error| tool_execution_failed(error.to_string()))?;
+        let (results, mode) = match self.semantic_search(&query, max_results, min_score).await? {
+            Some(results) => (results, "semantic"),
+            None => (
+                lock_memory(&self.memory)?
+                    .search_scored(&query, max_results, min_score)
+                    .map_err(|error| tool_execution_failed(error.to_string()))?,
+                "keyword",
+            ),
+        };
 
         if results.is_empty() {
             return Ok(text_result(
@@ -111,6 +139,7 @@ impl MemoryToolExecutor {
                 json!({
                     "action": "search",
                     "query": query,
+                    "mode": mode,
                     "count": 0,
                 }),
             ));
@@ -149,12 +178,36 @@ impl MemoryToolExecutor {
             json!({
                 "action": "search",
                 "query": query,
+                "mode": mode,
                 "count": details.len(),
                 "results": details,
             }),
         ))
     }
 
+    /// Returns `None` when no semantic index is configured or embedding fails, so callers fall
+    /// back to keyword search.
+    async fn semantic_search(
+        &self,
+        query: &str,
+        max_results: usize,
+        min_score: f32,
+    ) -> Result<Option<Vec<SearchResult>>, PiAiError> {
+        let Some(index) = &self.semantic_index else {
+            return Ok(None);
+        };
+        let chunks = lock_memory(&self.memory)?
+            .chunks()
+            .map_err(|error| tool_execution_failed(error.to_string()))?;
+        match index.search(query, &chunks, max_results, min_score).await {
+            Ok(results) => Ok(Some(results)),
+            Err(error) => {
+                tracing::warn!("semantic memory search failed, using keyword search: {error}");
+                Ok(None)
+            }
+        }
+    }
+
     fn execute_get(&self, args: &Value) -> Result<AgentToolResult, PiAiError> {
         let date = optional_string(args, "date")?;
         let manager = lock_memory(&self.memory)?;
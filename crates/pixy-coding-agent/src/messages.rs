@@ -104,6 +104,7 @@ pub fn convert_to_llm(messages: &[CodingMessage]) -> Vec<Message> {
                     content: UserContent::Blocks(vec![UserContentBlock::Text {
                         text: bash_execution_to_text(message),
                         text_signature: None,
+                        cache: None,
                     }]),
                     timestamp: message.timestamp,
                 })
@@ -119,6 +120,7 @@ pub fn convert_to_llm(messages: &[CodingMessage]) -> Vec<Message> {
                         message.summary
                     ),
                     text_signature: None,
+                    cache: None,
                 }]),
                 timestamp: message.timestamp,
             }),
@@ -129,6 +131,7 @@ pub fn convert_to_llm(messages: &[CodingMessage]) -> Vec<Message> {
                         message.summary
                     ),
                     text_signature: None,
+                    cache: None,
                 }]),
                 timestamp: message.timestamp,
             }),
@@ -1,19 +1,43 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
-use pixy_agent_core::{AgentTool, ParentChildRunEvent, ParentChildRunEventSink, StreamFn};
+use async_trait::async_trait;
+use pixy_agent_core::{
+    AbortReason, AgentAbortController, AgentAbortSignal, AgentTool, AgentToolExecuteFn,
+    AgentToolExecutor, AgentToolResult, ParentChildRunEvent, ParentChildRunEventSink, StreamFn,
+    ToolApproverFn, ToolPolicy, ToolRisk,
+};
 use pixy_ai::{AssistantContentBlock, Message, Model, PiAiError, PiAiErrorCode, StopReason};
-use serde_json::json;
+use serde_json::{json, Value};
 use tokio::sync::Mutex;
 
+use crate::agent_session::format_tool_start_line;
 use crate::{
     AfterTaskResultHookContext, AgentSession, AgentSessionConfig, BeforeTaskDispatchHookContext,
-    ChildSessionStore, DispatchPolicyConfig, MultiAgentPluginRuntime, SessionManager,
-    SubAgentResolver, TaskToolInput, TaskToolOutput,
+    ChildSessionStore, DispatchMetrics, DispatchPolicyConfig, DispatchPolicyDecision,
+    MultiAgentPluginRuntime, PersistentShell, SessionManager, SubAgentResolver, TaskToolInput,
+    TaskToolOutput,
 };
 
+/// Builds the `shell` tool of one child session around that session's own shell.
+pub type ChildShellToolFn = Arc<dyn Fn(PersistentShell) -> AgentTool + Send + Sync>;
+
+/// Restrictions of the parent session that every child session runs under too.
+#[derive(Clone, Default)]
+pub struct ChildSessionControls {
+    pub tool_policy: Option<ToolPolicy>,
+    pub approval_tools: HashMap<String, ToolRisk>,
+    /// Asked about the flagged calls of children too; without one they are refused.
+    pub tool_approver: Option<ToolApproverFn>,
+}
+
+/// [`ChildSessionControls`] the parent session keeps current; each child run reads them when
+/// it starts.
+pub type SharedChildSessionControls = Arc<StdMutex<ChildSessionControls>>;
+
 #[derive(Clone)]
 pub struct TaskDispatcherConfig {
     pub cwd: PathBuf,
@@ -26,6 +50,11 @@ pub struct TaskDispatcherConfig {
     /// Tool set exposed to child sessions.
     /// In V1 this intentionally excludes `task` to avoid recursive fan-out.
     pub child_tools: Vec<AgentTool>,
+    /// Gives every child run a `shell` tool with a fresh shell, closed when the run ends, so
+    /// children never share working directory or environment with the parent or each other.
+    /// `child_tools` should not carry a `shell` tool of their own.
+    pub child_shell_tool: Option<ChildShellToolFn>,
+    pub child_controls: SharedChildSessionControls,
     pub subagent_registry: Arc<dyn SubAgentResolver>,
     pub session_store: Arc<Mutex<ChildSessionStore>>,
     pub dispatch_policy: DispatchPolicyConfig,
@@ -34,9 +63,11 @@ pub struct TaskDispatcherConfig {
 }
 
 const UNRESOLVED_CHILD_SESSION_FILE: &str = "<child-session-unresolved>";
+/// `kind` in the details of the error a dispatch returns when its parent run was aborted.
+pub(crate) const TASK_CANCELLED_KIND: &str = "task_cancelled";
 static TASK_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct TaskDispatchResult {
     pub output: TaskToolOutput,
     pub summary: String,
@@ -44,19 +75,81 @@ pub struct TaskDispatchResult {
     pub routing_hint_applied: bool,
     pub duration_ms: u64,
     pub trace_lines: Vec<String>,
+    /// Tokens used by the child run's assistant turns.
+    pub total_tokens: u64,
+    /// Cost of the child run's assistant turns.
+    pub cost: f64,
 }
 
 #[derive(Clone)]
 pub struct TaskDispatcher {
     config: TaskDispatcherConfig,
+    usage: Arc<DispatchUsage>,
+}
+
+/// What the session's sub-agents have used so far, shared by clones of a dispatcher.
+#[derive(Default)]
+struct DispatchUsage {
+    spent_cost: StdMutex<f64>,
+    queued: AtomicUsize,
+}
+
+/// Counts a dispatch toward the queue depth until it is dropped.
+pub(crate) struct QueuedDispatch {
+    usage: Arc<DispatchUsage>,
+}
+
+impl Drop for QueuedDispatch {
+    fn drop(&mut self) {
+        self.usage.queued.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 impl TaskDispatcher {
     pub fn new(config: TaskDispatcherConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            usage: Arc::new(DispatchUsage::default()),
+        }
+    }
+
+    /// Total cost of the sub-agent runs dispatched so far.
+    pub fn spent_cost(&self) -> f64 {
+        *self
+            .usage
+            .spent_cost
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Registers a dispatch that may still wait for a slot, so policy rules see it in the queue
+    /// depth.
+    pub(crate) fn enqueue(&self) -> QueuedDispatch {
+        self.usage.queued.fetch_add(1, Ordering::SeqCst);
+        QueuedDispatch {
+            usage: self.usage.clone(),
+        }
     }
 
+    /// Runs `input` in a child session. The child run is aborted with the run that called the
+    /// tool, if any.
     pub async fn dispatch(&self, input: TaskToolInput) -> Result<TaskDispatchResult, PiAiError> {
+        self.dispatch_queued(self.enqueue(), input, AgentAbortSignal::current())
+            .await
+    }
+
+    pub(crate) async fn dispatch_queued(
+        &self,
+        _queued: QueuedDispatch,
+        input: TaskToolInput,
+        parent_signal: Option<AgentAbortSignal>,
+    ) -> Result<TaskDispatchResult, PiAiError> {
+        if parent_signal
+            .as_ref()
+            .is_some_and(AgentAbortSignal::is_aborted)
+        {
+            return Err(task_cancelled_error(&input.subagent_type));
+        }
         let mut dispatch_ctx = BeforeTaskDispatchHookContext { input };
         self.config
             .plugin_runtime
@@ -66,11 +159,13 @@ impl TaskDispatcher {
             .validate()
             .map_err(|error| PiAiError::new(PiAiErrorCode::ToolArgumentsInvalid, error))?;
 
-        let policy_decision = self.config.dispatch_policy.evaluate(
+        let policy_decision = self.config.dispatch_policy.evaluate_with_metrics(
             "task",
             &input.subagent_type,
             self.config.subagent_registry.as_ref(),
+            self.dispatch_metrics(&input),
         );
+        log_policy_decision(&policy_decision);
         if policy_decision.blocked {
             return Err(PiAiError::new(
                 PiAiErrorCode::ToolExecutionFailed,
@@ -85,6 +180,8 @@ impl TaskDispatcher {
                 "requested_subagent": policy_decision.requested_subagent,
                 "resolved_subagent": policy_decision.resolved_subagent,
                 "routing_hint_applied": policy_decision.routing_hint_applied,
+                "matched_rule": policy_decision.matched_rule,
+                "remaining_budget_usd": policy_decision.metrics.remaining_budget_usd,
             })));
         }
 
@@ -114,7 +211,13 @@ impl TaskDispatcher {
                         ),
                     )
                 })?;
-        let child_tools = resolve_child_tools(&self.config.child_tools, &subagent);
+        let mut child_tools = resolve_child_tools(&self.config.child_tools, &subagent);
+        let child_shell = self.config.child_shell_tool.as_ref().and_then(|build| {
+            let shell = PersistentShell::new();
+            let tool = resolve_child_tools(&[build(shell.clone())], &subagent).pop()?;
+            child_tools.push(tool);
+            Some(shell)
+        });
 
         let task_id = input
             .task_id
@@ -146,13 +249,26 @@ impl TaskDispatcher {
             subagent: subagent_name.clone(),
         });
         let run_started_at = Instant::now();
+        let mut run = ChildRunReport {
+            sink: self.config.lifecycle_event_sink.clone(),
+            cancelled: Some(ParentChildRunEvent::ChildRunError {
+                parent_session_id: parent_session_id.clone(),
+                child_session_file: child_session_file_text.clone(),
+                task_id: task_id.clone(),
+                subagent: subagent_name.clone(),
+                error: task_cancelled_error(&subagent_name).message,
+            }),
+        };
+        let child_abort = parent_signal
+            .as_ref()
+            .map(AgentAbortSignal::child_controller);
 
         let child_manager = SessionManager::load(&child_session_file).map_err(|error| {
             let error_message = format!(
                 "failed to load child session {}: {error}",
                 child_session_file.display()
             );
-            self.emit_lifecycle_event(ParentChildRunEvent::ChildRunError {
+            run.emit(ParentChildRunEvent::ChildRunError {
                 parent_session_id: parent_session_id.clone(),
                 child_session_file: child_session_file_text.clone(),
                 task_id: task_id.clone(),
@@ -162,6 +278,24 @@ impl TaskDispatcher {
             PiAiError::new(PiAiErrorCode::ToolExecutionFailed, error_message)
         })?;
 
+        let child_tools = match &self.config.lifecycle_event_sink {
+            Some(sink) => child_tools
+                .into_iter()
+                .map(|tool| {
+                    report_child_tool_starts(
+                        tool,
+                        ChildToolProgress {
+                            sink: sink.clone(),
+                            parent_session_id: parent_session_id.clone(),
+                            child_session_file: child_session_file_text.clone(),
+                            task_id: task_id.clone(),
+                            subagent: subagent_name.clone(),
+                        },
+                    )
+                })
+                .collect(),
+            None => child_tools,
+        };
         let mut child_session = AgentSession::new(
             child_manager,
             AgentSessionConfig {
@@ -173,10 +307,28 @@ impl TaskDispatcher {
             },
         );
         child_session.set_multi_agent_plugin_runtime(self.config.plugin_runtime.clone());
+        let controls = self
+            .config
+            .child_controls
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        child_session.set_tool_policy(controls.tool_policy);
+        child_session.set_approval_tools(controls.approval_tools);
+        if let Some(approver) = controls.tool_approver {
+            child_session.attach_tool_approver(move |request| approver(request));
+        }
 
-        let produced = child_session.prompt(&input.prompt).await.map_err(|error| {
+        let child_signal = child_abort.as_ref().map(AgentAbortController::signal);
+        let produced = child_session
+            .prompt_with_abort(&input.prompt, child_signal.clone())
+            .await;
+        if let Some(shell) = &child_shell {
+            shell.close().await;
+        }
+        let produced = produced.map_err(|error| {
             let error_message = format!("subagent '{}' failed: {error}", subagent_name);
-            self.emit_lifecycle_event(ParentChildRunEvent::ChildRunError {
+            run.emit(ParentChildRunEvent::ChildRunError {
                 parent_session_id: parent_session_id.clone(),
                 child_session_file: child_session_file_text.clone(),
                 task_id: task_id.clone(),
@@ -186,6 +338,24 @@ impl TaskDispatcher {
             PiAiError::new(PiAiErrorCode::ToolExecutionFailed, error_message)
         })?;
         let trace_lines = collect_subagent_trace_lines(&produced);
+        let (total_tokens, cost) = sum_assistant_usage(&produced);
+        *self
+            .usage
+            .spent_cost
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) += cost;
+        if child_signal.is_some_and(|signal| signal.reason() == Some(AbortReason::ParentCancelled))
+        {
+            let error = task_cancelled_error(&subagent_name);
+            run.emit(ParentChildRunEvent::ChildRunError {
+                parent_session_id: parent_session_id.clone(),
+                child_session_file: child_session_file_text.clone(),
+                task_id: task_id.clone(),
+                subagent: subagent_name.clone(),
+                error: error.message.clone(),
+            });
+            return Err(error);
+        }
         if let Some((stop_reason, error_message)) = last_assistant_stop_reason(&produced) {
             if matches!(stop_reason, StopReason::Error | StopReason::Aborted) {
                 let failure = error_message.unwrap_or_else(|| {
@@ -194,7 +364,7 @@ impl TaskDispatcher {
                         subagent_name
                     )
                 });
-                self.emit_lifecycle_event(ParentChildRunEvent::ChildRunError {
+                run.emit(ParentChildRunEvent::ChildRunError {
                     parent_session_id: parent_session_id.clone(),
                     child_session_file: child_session_file_text.clone(),
                     task_id: task_id.clone(),
@@ -219,7 +389,7 @@ impl TaskDispatcher {
         };
         self.config.plugin_runtime.after_task_result(&mut after_ctx);
         after_ctx.output.validate().map_err(|error| {
-            self.emit_lifecycle_event(ParentChildRunEvent::ChildRunError {
+            run.emit(ParentChildRunEvent::ChildRunError {
                 parent_session_id: parent_session_id.clone(),
                 child_session_file: child_session_file_text.clone(),
                 task_id: task_id.clone(),
@@ -231,7 +401,7 @@ impl TaskDispatcher {
 
         let duration_ms = u64::try_from(run_started_at.elapsed().as_millis()).unwrap_or(u64::MAX);
 
-        self.emit_lifecycle_event(ParentChildRunEvent::ChildRunEnd {
+        run.emit(ParentChildRunEvent::ChildRunEnd {
             parent_session_id,
             child_session_file: child_session_file_text,
             task_id: task_id.clone(),
@@ -247,9 +417,24 @@ impl TaskDispatcher {
             routing_hint_applied: after_ctx.routing_hint_applied,
             duration_ms,
             trace_lines,
+            total_tokens,
+            cost,
         })
     }
 
+    fn dispatch_metrics(&self, input: &TaskToolInput) -> DispatchMetrics {
+        DispatchMetrics {
+            remaining_budget_usd: self
+                .config
+                .dispatch_policy
+                .budget_usd
+                .map(|budget| budget - self.spent_cost()),
+            // About four bytes per token, as for tool output budgets.
+            task_tokens: input.prompt.len().div_ceil(4) as u64,
+            queue_depth: self.usage.queued.load(Ordering::SeqCst),
+        }
+    }
+
     fn emit_lifecycle_event(&self, event: ParentChildRunEvent) {
         if let Some(sink) = &self.config.lifecycle_event_sink {
             sink(event);
@@ -382,6 +567,51 @@ fn resolve_child_tools(base_tools: &[AgentTool], subagent: &crate::SubAgentSpec)
         .collect()
 }
 
+/// Where a child run reports the tool calls it makes, so the parent can show them live.
+#[derive(Clone)]
+struct ChildToolProgress {
+    sink: ParentChildRunEventSink,
+    parent_session_id: String,
+    child_session_file: String,
+    task_id: String,
+    subagent: String,
+}
+
+fn report_child_tool_starts(mut tool: AgentTool, progress: ChildToolProgress) -> AgentTool {
+    tool.execute = Arc::new(ChildToolProgressExecutor {
+        tool_name: tool.name.clone(),
+        inner: tool.execute.clone(),
+        progress,
+    });
+    tool
+}
+
+struct ChildToolProgressExecutor {
+    tool_name: String,
+    inner: AgentToolExecuteFn,
+    progress: ChildToolProgress,
+}
+
+#[async_trait]
+impl AgentToolExecutor for ChildToolProgressExecutor {
+    async fn execute(
+        &self,
+        tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        let progress = &self.progress;
+        (progress.sink)(ParentChildRunEvent::ChildToolStart {
+            parent_session_id: progress.parent_session_id.clone(),
+            child_session_file: progress.child_session_file.clone(),
+            task_id: progress.task_id.clone(),
+            subagent: progress.subagent.clone(),
+            tool_name: self.tool_name.clone(),
+            headline: format_tool_start_line(&self.tool_name, &args),
+        });
+        self.inner.execute(tool_call_id, args).await
+    }
+}
+
 fn build_child_system_prompt(parent_system_prompt: &str, subagent: &crate::SubAgentSpec) -> String {
     let mut prompt = format!(
         "{parent_system_prompt}\n\n<subagent_context>\nYou are running as subagent '{}'. Focus on the delegated task and report concise actionable results.\nSubagent description: {}\n</subagent_context>",
@@ -401,7 +631,19 @@ fn build_child_system_prompt(parent_system_prompt: &str, subagent: &crate::SubAg
     prompt
 }
 
-fn last_assistant_text(messages: &[Message]) -> Option<String> {
+fn sum_assistant_usage(messages: &[Message]) -> (u64, f64) {
+    messages
+        .iter()
+        .filter_map(|message| match message {
+            Message::Assistant { usage, .. } => Some(usage),
+            _ => None,
+        })
+        .fold((0, 0.0), |(tokens, cost), usage| {
+            (tokens + usage.total_tokens, cost + usage.cost.total)
+        })
+}
+
+pub(crate) fn last_assistant_text(messages: &[Message]) -> Option<String> {
     messages.iter().rev().find_map(|message| {
         let Message::Assistant { content, .. } = message else {
             return None;
@@ -472,6 +714,56 @@ fn collect_subagent_trace_lines(messages: &[Message]) -> Vec<String> {
     lines
 }
 
+/// Reports the end of a started child run exactly once. A run dropped before it ends, e.g.
+/// because the parent run was aborted while the task tool was executing, is reported as
+/// cancelled so every `ChildRunStart` gets a matching end.
+struct ChildRunReport {
+    sink: Option<ParentChildRunEventSink>,
+    cancelled: Option<ParentChildRunEvent>,
+}
+
+impl ChildRunReport {
+    fn emit(&mut self, event: ParentChildRunEvent) {
+        self.cancelled = None;
+        if let Some(sink) = &self.sink {
+            sink(event);
+        }
+    }
+}
+
+impl Drop for ChildRunReport {
+    fn drop(&mut self) {
+        if let (Some(sink), Some(event)) = (&self.sink, self.cancelled.take()) {
+            sink(event);
+        }
+    }
+}
+
+fn log_policy_decision(decision: &DispatchPolicyDecision) {
+    if decision.matched_rule.is_none() && !decision.blocked && !decision.routing_hint_applied {
+        return;
+    }
+    tracing::info!(
+        requested_subagent = decision.requested_subagent,
+        resolved_subagent = decision.resolved_subagent,
+        blocked = decision.blocked,
+        matched_rule = decision.matched_rule,
+        remaining_budget_usd = decision.metrics.remaining_budget_usd,
+        task_tokens = decision.metrics.task_tokens,
+        queue_depth = decision.metrics.queue_depth,
+        reason = decision.reason.as_deref().unwrap_or_default(),
+        "task dispatch policy decision"
+    );
+}
+
+fn task_cancelled_error(subagent: &str) -> PiAiError {
+    PiAiError::new(
+        PiAiErrorCode::ToolExecutionFailed,
+        format!("subagent '{subagent}' cancelled: the parent run was aborted"),
+    )
+    .with_details(json!({ "kind": TASK_CANCELLED_KIND, "tool": "task" }))
+}
+
 fn generate_task_id() -> String {
     let millis = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -522,6 +814,9 @@ mod tests {
             },
             context_window: 128_000,
             max_tokens: 8_192,
+            deployment: None,
+            api_version: None,
+            compat: None,
         }
     }
 
@@ -531,6 +826,7 @@ mod tests {
             output: 1,
             cache_read: 0,
             cache_write: 0,
+            reasoning: 0,
             total_tokens: 2,
             cost: Cost {
                 input: 0.0,
@@ -543,6 +839,12 @@ mod tests {
     }
 
     fn done_stream(text: String) -> AssistantMessageEventStream {
+        done_stream_with_cost(text, 0.0)
+    }
+
+    fn done_stream_with_cost(text: String, cost: f64) -> AssistantMessageEventStream {
+        let mut usage = sample_usage();
+        usage.cost.total = cost;
         let message = AssistantMessage {
             role: "assistant".to_string(),
             content: vec![AssistantContentBlock::Text {
@@ -552,10 +854,11 @@ mod tests {
             api: "openai-responses".to_string(),
             provider: "openai".to_string(),
             model: "test-model".to_string(),
-            usage: sample_usage(),
+            usage,
             stop_reason: StopReason::Stop,
             error_message: None,
             timestamp: 1,
+            stats: None,
         };
         let stream = AssistantMessageEventStream::new();
         stream.push(AssistantMessageEvent::Start {
@@ -591,6 +894,7 @@ mod tests {
             label: name.to_string(),
             description: format!("{name} tool"),
             parameters: serde_json::json!({}),
+            conflict_key: None,
             execute: Arc::new(
                 |_tool_call_id: String, _args: serde_json::Value| -> ToolFuture {
                     Box::pin(async {
@@ -620,6 +924,8 @@ mod tests {
                 Ok(done_stream("child done".to_string()))
             }),
             child_tools: vec![],
+            child_shell_tool: None,
+            child_controls: Default::default(),
             subagent_registry: registry(),
             session_store: Arc::new(Mutex::new(ChildSessionStore::new("parent-session"))),
             dispatch_policy: DispatchPolicyConfig::default(),
@@ -644,6 +950,53 @@ mod tests {
         assert_eq!(calls.load(Ordering::SeqCst), 1);
     }
 
+    #[tokio::test]
+    async fn dispatch_rejects_tasks_once_the_budget_is_spent() {
+        let dir = tempdir().expect("tempdir");
+        let dispatcher = TaskDispatcher::new(TaskDispatcherConfig {
+            cwd: dir.path().to_path_buf(),
+            parent_session_id: "parent-session".to_string(),
+            parent_session_dir: dir.path().to_path_buf(),
+            model: sample_model(),
+            model_catalog: vec![sample_model()],
+            system_prompt: "You are parent".to_string(),
+            stream_fn: Arc::new(move |_model, _context, _options| {
+                Ok(done_stream_with_cost("child done".to_string(), 0.75))
+            }),
+            child_tools: vec![],
+            child_shell_tool: None,
+            child_controls: Default::default(),
+            subagent_registry: registry(),
+            session_store: Arc::new(Mutex::new(ChildSessionStore::new("parent-session"))),
+            dispatch_policy: DispatchPolicyConfig {
+                budget_usd: Some(0.5),
+                ..DispatchPolicyConfig::default()
+            },
+            plugin_runtime: Arc::new(MultiAgentPluginRuntime::default()),
+            lifecycle_event_sink: None,
+        });
+        let input = TaskToolInput {
+            subagent_type: "general".to_string(),
+            prompt: "investigate".to_string(),
+            task_id: None,
+        };
+
+        dispatcher
+            .dispatch(input.clone())
+            .await
+            .expect("first dispatch fits the budget");
+        assert_eq!(dispatcher.spent_cost(), 0.75);
+
+        let error = dispatcher
+            .dispatch(input)
+            .await
+            .expect_err("second dispatch should be rejected");
+        assert!(error.message.contains("budget of $0.50 is used up"));
+        let details = error.details.expect("blocked dispatch has details");
+        assert_eq!(details["kind"], "task_dispatch_blocked");
+        assert_eq!(details["remaining_budget_usd"], -0.25);
+    }
+
     #[tokio::test]
     async fn dispatch_rejects_unknown_subagent_type() {
         let dir = tempdir().expect("tempdir");
@@ -659,6 +1012,8 @@ mod tests {
                 Ok(done_stream("child done".to_string()))
             }),
             child_tools: vec![],
+            child_shell_tool: None,
+            child_controls: Default::default(),
             subagent_registry: registry(),
             session_store: Arc::new(Mutex::new(ChildSessionStore::new("parent-session"))),
             dispatch_policy: DispatchPolicyConfig::default(),
@@ -696,6 +1051,8 @@ mod tests {
                 Ok(done_stream(format!("turn {turn}")))
             }),
             child_tools: vec![],
+            child_shell_tool: None,
+            child_controls: Default::default(),
             subagent_registry: registry(),
             session_store: Arc::new(Mutex::new(ChildSessionStore::new("parent-session"))),
             dispatch_policy: DispatchPolicyConfig::default(),
@@ -798,6 +1155,8 @@ mod tests {
                 Ok(done_stream("child done".to_string()))
             }),
             child_tools: vec![],
+            child_shell_tool: None,
+            child_controls: Default::default(),
             subagent_registry: registry(),
             session_store: Arc::new(Mutex::new(ChildSessionStore::new("parent-session"))),
             dispatch_policy: DispatchPolicyConfig::default(),
@@ -852,6 +1211,9 @@ mod tests {
             },
             context_window: 1_048_576,
             max_tokens: 8_192,
+            deployment: None,
+            api_version: None,
+            compat: None,
         };
         let subagent = SubAgentSpec {
             name: "code".to_string(),
@@ -913,6 +1275,9 @@ mod tests {
             },
             context_window: 200_000,
             max_tokens: 8_192,
+            deployment: None,
+            api_version: None,
+            compat: None,
         };
         let subagent = SubAgentSpec {
             name: "review".to_string(),
@@ -5,6 +5,7 @@ mod plugin_loader;
 mod plugin_manifest;
 mod plugin_runtime;
 mod policy;
+mod project_agents;
 mod registry;
 mod session_store;
 mod task_tool;
@@ -14,20 +15,29 @@ pub use declarative_hooks::{
     create_multi_agent_plugin_runtime_from_specs, DeclarativeHookAction, DeclarativeHookSpec,
     DeclarativeHookStage,
 };
-pub use dispatcher::{TaskDispatchResult, TaskDispatcher, TaskDispatcherConfig};
+pub(crate) use dispatcher::last_assistant_text;
+pub use dispatcher::{
+    ChildSessionControls, ChildShellToolFn, SharedChildSessionControls, TaskDispatchResult,
+    TaskDispatcher, TaskDispatcherConfig,
+};
 pub use hooks::{
     AfterTaskResultHookContext, BeforeTaskDispatchHookContext, BeforeToolDefinitionHookContext,
     BeforeUserMessageHookContext, MultiAgentHook,
 };
 pub use plugin_loader::{
     load_and_merge_plugins, load_and_merge_plugins_from_paths, load_plugin_manifests,
-    LoadedPluginManifest, MergedPluginConfig, PluginSubAgentSpec,
+    LoadPluginManifestsResult, LoadedPluginManifest, MergedPluginConfig, PluginSubAgentSpec,
+};
+pub use plugin_manifest::{
+    MultiAgentPluginManifest, PLUGIN_SCHEMA_VERSION, SUPPORTED_PLUGIN_CAPABILITIES,
 };
-pub use plugin_manifest::MultiAgentPluginManifest;
 pub use plugin_runtime::{create_multi_agent_plugin_runtime, MultiAgentPluginRuntime};
 pub use policy::{
-    DispatchPolicyConfig, DispatchPolicyDecision, DispatchPolicyRule, PolicyRuleEffect,
+    DispatchMetrics, DispatchPolicyConditions, DispatchPolicyConfig, DispatchPolicyDecision,
+    DispatchPolicyRule, PolicyRuleEffect,
 };
+pub(crate) use project_agents::PROJECT_AGENTS_DIR;
+pub use project_agents::{load_project_subagents, LoadProjectSubAgentsResult, ProjectSubAgentSpec};
 pub use registry::{DefaultSubAgentRegistry, SubAgentRegistryBuilder, SubAgentResolver};
 pub use session_store::ChildSessionStore;
 pub use task_tool::create_task_tool;
@@ -5,19 +5,22 @@ use serde::Deserialize;
 
 use crate::{DeclarativeHookSpec, SubAgentMode, SubAgentPromptMetadata, SubAgentSpec};
 
+use super::plugin_manifest::PLUGIN_SCHEMA_VERSION;
 use super::{resolve_subagent_model_target, DispatchPolicyConfig, MultiAgentPluginManifest};
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct LoadedPluginManifest {
     pub path: PathBuf,
     pub manifest: MultiAgentPluginManifest,
 }
 
-#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct MergedPluginConfig {
     pub subagents: Vec<PluginSubAgentSpec>,
     pub hooks: Vec<DeclarativeHookSpec>,
     pub policy: DispatchPolicyConfig,
+    /// Plugins skipped because they need a newer schema or unsupported capabilities.
+    pub warnings: Vec<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -26,25 +29,52 @@ pub struct PluginSubAgentSpec {
     pub spec: SubAgentSpec,
 }
 
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LoadPluginManifestsResult {
+    pub plugins: Vec<LoadedPluginManifest>,
+    /// One message per plugin this build cannot run; the other plugins are still returned.
+    pub warnings: Vec<String>,
+}
+
 pub fn load_plugin_manifests(
     plugin_paths: &[PathBuf],
-) -> Result<Vec<LoadedPluginManifest>, String> {
-    let mut loaded = Vec::with_capacity(plugin_paths.len());
+) -> Result<LoadPluginManifestsResult, String> {
+    let mut result = LoadPluginManifestsResult::default();
     for path in plugin_paths {
         let content = std::fs::read_to_string(path)
             .map_err(|error| format!("read plugin manifest {} failed: {error}", path.display()))?;
+        // The version is checked before the full parse so a newer manifest is reported as such
+        // rather than as a confusing field error.
+        let table = toml::from_str::<toml::Table>(&content)
+            .map_err(|error| format!("parse plugin manifest {} failed: {error}", path.display()))?;
+        if let Some(warning) = check_schema_version(path, &table)? {
+            result.warnings.push(warning);
+            continue;
+        }
         let manifest = toml::from_str::<MultiAgentPluginManifest>(&content)
             .map_err(|error| format!("parse plugin manifest {} failed: {error}", path.display()))?;
         manifest.validate()?;
-        loaded.push(LoadedPluginManifest {
+        let missing = manifest.missing_capabilities();
+        if !missing.is_empty() {
+            result.warnings.push(format!(
+                "plugin '{}' ({}) requires unsupported capabilities: {}",
+                manifest.name,
+                path.display(),
+                missing.join(", ")
+            ));
+            continue;
+        }
+        result.plugins.push(LoadedPluginManifest {
             path: path.clone(),
             manifest,
         });
     }
 
-    loaded.sort_by(|left, right| left.manifest.name.cmp(&right.manifest.name));
+    result
+        .plugins
+        .sort_by(|left, right| left.manifest.name.cmp(&right.manifest.name));
 
-    for pair in loaded.windows(2) {
+    for pair in result.plugins.windows(2) {
         if pair[0].manifest.name == pair[1].manifest.name {
             return Err(format!(
                 "duplicate plugin name '{}' found in {} and {}",
@@ -55,13 +85,43 @@ pub fn load_plugin_manifests(
         }
     }
 
-    Ok(loaded)
+    Ok(result)
+}
+
+/// Returns a warning for manifests written for a newer schema than this build supports.
+fn check_schema_version(path: &Path, table: &toml::Table) -> Result<Option<String>, String> {
+    let Some(value) = table.get("schema_version") else {
+        return Ok(None);
+    };
+    let version = value
+        .as_integer()
+        .filter(|version| *version >= 1)
+        .ok_or_else(|| {
+            format!(
+                "plugin manifest {} has invalid schema_version {value}; expected an integer >= 1",
+                path.display()
+            )
+        })?;
+    if version <= i64::from(PLUGIN_SCHEMA_VERSION) {
+        return Ok(None);
+    }
+    let name = table
+        .get("name")
+        .and_then(toml::Value::as_str)
+        .unwrap_or("unnamed");
+    Ok(Some(format!(
+        "plugin '{name}' ({}) uses schema_version {version}, but this pixy supports up to {PLUGIN_SCHEMA_VERSION}; upgrade pixy to load it",
+        path.display()
+    )))
 }
 
 pub fn load_and_merge_plugins(plugin_paths: &[PathBuf]) -> Result<MergedPluginConfig, String> {
     let loaded = load_plugin_manifests(plugin_paths)?;
-    let mut merged = MergedPluginConfig::default();
-    for plugin in loaded {
+    let mut merged = MergedPluginConfig {
+        warnings: loaded.warnings,
+        ..MergedPluginConfig::default()
+    };
+    for plugin in loaded.plugins {
         let merged_subagents = merge_plugin_subagent_sources(&plugin)?;
         merged.hooks.extend(plugin.manifest.hooks);
         for spec in merged_subagents {
@@ -197,7 +257,7 @@ fn load_sidecar_subagents_from_agents_dir(
 }
 
 #[derive(Clone, Debug, Default, Deserialize)]
-struct PluginAgentFile {
+pub(super) struct PluginAgentFile {
     #[serde(default)]
     name: Option<String>,
     #[serde(default)]
@@ -205,7 +265,7 @@ struct PluginAgentFile {
     #[serde(default)]
     mode: Option<SubAgentMode>,
     #[serde(default)]
-    prompt: Option<String>,
+    pub(super) prompt: Option<String>,
     #[serde(default)]
     provider: Option<String>,
     #[serde(default)]
@@ -219,7 +279,7 @@ struct PluginAgentFile {
 }
 
 impl PluginAgentFile {
-    fn into_spec(self, fallback_name: Option<&str>) -> Result<SubAgentSpec, String> {
+    pub(super) fn into_spec(self, fallback_name: Option<&str>) -> Result<SubAgentSpec, String> {
         let explicit_name = self
             .name
             .as_deref()
@@ -301,7 +361,9 @@ mode = "subagent"
 "#,
         );
 
-        let loaded = load_plugin_manifests(&[plugin]).expect("plugin should parse");
+        let loaded = load_plugin_manifests(&[plugin])
+            .expect("plugin should parse")
+            .plugins;
         assert_eq!(loaded.len(), 1);
         assert_eq!(loaded[0].manifest.name, "alpha");
         assert_eq!(loaded[0].manifest.subagents.len(), 1);
@@ -365,7 +427,9 @@ mode = "subagent"
         let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("../../docs/fixtures/multi-agent/plugins/basic-plugin.toml");
 
-        let loaded = load_plugin_manifests(&[fixture]).expect("fixture plugin should parse");
+        let loaded = load_plugin_manifests(&[fixture])
+            .expect("fixture plugin should parse")
+            .plugins;
         assert_eq!(loaded.len(), 1);
         assert_eq!(loaded[0].manifest.name, "basic-plugin");
         assert!(!loaded[0].manifest.subagents.is_empty());
@@ -389,7 +453,9 @@ blocked_tools = ["task"]
 "#,
         );
 
-        let loaded = load_plugin_manifests(&[plugin]).expect("plugin should parse");
+        let loaded = load_plugin_manifests(&[plugin])
+            .expect("plugin should parse")
+            .plugins;
         assert_eq!(loaded.len(), 1);
         assert_eq!(loaded[0].manifest.subagents.len(), 1);
         let code = &loaded[0].manifest.subagents[0];
@@ -551,4 +617,67 @@ stage = "before_task_dispatch"
         let error = load_plugin_manifests(&[plugin]).expect_err("invalid hooks should fail");
         assert!(error.contains("invalid hook"));
     }
+
+    #[test]
+    fn plugin_loader_skips_newer_schema_and_unsupported_capabilities() {
+        let dir = tempdir().expect("tempdir");
+        let current = dir.path().join("current.toml");
+        let future = dir.path().join("future.toml");
+        let needy = dir.path().join("needy.toml");
+        write_plugin(
+            &current,
+            r#"
+schema_version = 1
+name = "current"
+capabilities = ["subagents", "policy"]
+
+[[subagents]]
+name = "general"
+description = "General helper"
+"#,
+        );
+        write_plugin(
+            &future,
+            r#"
+schema_version = 2
+name = "future"
+
+[subagents]
+layout = "something new"
+"#,
+        );
+        write_plugin(
+            &needy,
+            r#"
+name = "needy"
+capabilities = ["hooks", "remote_dispatch"]
+"#,
+        );
+
+        let merged =
+            load_and_merge_plugins(&[current, future, needy]).expect("plugins should merge");
+        assert_eq!(merged.subagents.len(), 1);
+        assert_eq!(merged.subagents[0].plugin_name, "current");
+        assert_eq!(merged.warnings.len(), 2);
+        assert!(merged.warnings[0].contains("'future'"));
+        assert!(merged.warnings[0].contains("schema_version 2"));
+        assert!(merged.warnings[1].contains("'needy'"));
+        assert!(merged.warnings[1].contains("unsupported capabilities: remote_dispatch"));
+    }
+
+    #[test]
+    fn plugin_loader_rejects_invalid_schema_version() {
+        let dir = tempdir().expect("tempdir");
+        let plugin = dir.path().join("zero.toml");
+        write_plugin(
+            &plugin,
+            r#"
+schema_version = 0
+name = "zero"
+"#,
+        );
+
+        let error = load_plugin_manifests(&[plugin]).expect_err("version 0 should fail");
+        assert!(error.contains("invalid schema_version 0"));
+    }
 }
@@ -6,9 +6,22 @@ use crate::{DeclarativeHookSpec, SubAgentMode, SubAgentPromptMetadata, SubAgentS
 
 use super::{policy::DispatchPolicyConfig, resolve_subagent_model_target};
 
-#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// Newest manifest schema this build understands; manifests without `schema_version` are v1.
+pub const PLUGIN_SCHEMA_VERSION: u32 = 1;
+
+/// Capabilities a manifest may list under `capabilities`; plugins that need anything else are
+/// skipped when loading instead of failing later at dispatch time.
+pub const SUPPORTED_PLUGIN_CAPABILITIES: &[&str] =
+    &["subagents", "agents_dir", "hooks", "hooks.bash", "policy"];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct MultiAgentPluginManifest {
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
     pub name: String,
+    /// Host features the plugin relies on, such as `hooks` or `policy`.
+    #[serde(default)]
+    pub capabilities: Vec<String>,
     #[serde(default)]
     pub description: Option<String>,
     #[serde(default, deserialize_with = "deserialize_subagents")]
@@ -19,12 +32,42 @@ pub struct MultiAgentPluginManifest {
     pub policy: DispatchPolicyConfig,
 }
 
+impl Default for MultiAgentPluginManifest {
+    fn default() -> Self {
+        Self {
+            schema_version: PLUGIN_SCHEMA_VERSION,
+            name: String::new(),
+            capabilities: vec![],
+            description: None,
+            subagents: vec![],
+            hooks: vec![],
+            policy: DispatchPolicyConfig::default(),
+        }
+    }
+}
+
+fn default_schema_version() -> u32 {
+    PLUGIN_SCHEMA_VERSION
+}
+
 impl MultiAgentPluginManifest {
+    /// Declared capabilities this build does not provide, in manifest order.
+    pub fn missing_capabilities(&self) -> Vec<&str> {
+        self.capabilities
+            .iter()
+            .map(|capability| capability.trim())
+            .filter(|capability| !SUPPORTED_PLUGIN_CAPABILITIES.contains(capability))
+            .collect()
+    }
+
     pub fn validate(&self) -> Result<(), String> {
         let name = self.name.trim();
         if name.is_empty() {
             return Err("plugin manifest name cannot be empty".to_string());
         }
+        if self.schema_version == 0 {
+            return Err(format!("plugin '{name}' schema_version must be at least 1"));
+        }
 
         let mut seen_subagents = std::collections::BTreeSet::new();
         for spec in &self.subagents {
@@ -153,7 +196,21 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::MultiAgentPluginManifest;
+    use super::{MultiAgentPluginManifest, PLUGIN_SCHEMA_VERSION};
+
+    #[test]
+    fn manifest_defaults_schema_version_and_reports_missing_capabilities() {
+        let manifest: MultiAgentPluginManifest = toml::from_str(
+            r#"
+name = "caps"
+capabilities = ["hooks", "streaming_hooks", "policy"]
+"#,
+        )
+        .expect("manifest should parse");
+
+        assert_eq!(manifest.schema_version, PLUGIN_SCHEMA_VERSION);
+        assert_eq!(manifest.missing_capabilities(), vec!["streaming_hooks"]);
+    }
 
     #[test]
     fn manifest_supports_map_style_subagents() {
@@ -7,20 +7,73 @@ use super::SubAgentResolver;
 pub enum PolicyRuleEffect {
     Allow,
     Deny,
+    /// Dispatch to the rule's `route_to` sub-agent instead.
+    Route,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct DispatchPolicyRule {
     pub subagent: String,
     #[serde(default = "default_tool_name")]
     pub tool: String,
     pub effect: PolicyRuleEffect,
+    /// Sub-agent used when `effect = "route"`.
+    #[serde(default)]
+    pub route_to: Option<String>,
+    #[serde(flatten)]
+    pub conditions: DispatchPolicyConditions,
+}
+
+/// Live metrics a rule can require; a rule only matches when every condition set on it holds.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DispatchPolicyConditions {
+    /// Matches once the remaining `budget_usd` is at or below this many dollars.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_remaining_budget_usd: Option<f64>,
+    /// Matches task prompts estimated at this many tokens or more.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_task_tokens: Option<u64>,
+    /// Matches when at least this many dispatches, including this one, are running or queued.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_queue_depth: Option<usize>,
+}
+
+impl DispatchPolicyConditions {
+    fn matches(&self, metrics: &DispatchMetrics) -> bool {
+        let budget_matches = self.max_remaining_budget_usd.is_none_or(|limit| {
+            metrics
+                .remaining_budget_usd
+                .is_some_and(|remaining| remaining <= limit)
+        });
+        budget_matches
+            && self
+                .min_task_tokens
+                .is_none_or(|min| metrics.task_tokens >= min)
+            && self
+                .min_queue_depth
+                .is_none_or(|min| metrics.queue_depth >= min)
+    }
+}
+
+/// Metrics sampled when a task is dispatched.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DispatchMetrics {
+    /// `budget_usd` minus what sub-agents have spent so far; `None` without a budget.
+    pub remaining_budget_usd: Option<f64>,
+    /// Rough token count of the task prompt.
+    pub task_tokens: u64,
+    /// Dispatches running or waiting in this session, including this one.
+    pub queue_depth: usize,
 }
 
-#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct DispatchPolicyConfig {
     #[serde(default)]
     pub fallback_subagent: Option<String>,
+    /// Spending limit in USD for sub-agent runs in one session; dispatches are rejected once it is
+    /// used up.
+    #[serde(default)]
+    pub budget_usd: Option<f64>,
     #[serde(default)]
     pub rules: Vec<DispatchPolicyRule>,
 }
@@ -33,6 +86,13 @@ impl DispatchPolicyConfig {
             }
         }
 
+        if self
+            .budget_usd
+            .is_some_and(|budget| !budget.is_finite() || budget < 0.0)
+        {
+            return Err("policy budget_usd must be a non-negative number".to_string());
+        }
+
         for rule in &self.rules {
             if rule.subagent.trim().is_empty() {
                 return Err("policy rule subagent cannot be empty".to_string());
@@ -40,6 +100,17 @@ impl DispatchPolicyConfig {
             if rule.tool.trim().is_empty() {
                 return Err("policy rule tool cannot be empty".to_string());
             }
+            let route_to = rule
+                .route_to
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty());
+            if rule.effect == PolicyRuleEffect::Route && route_to.is_none() {
+                return Err(format!(
+                    "policy rule for subagent '{}' uses effect route without route_to",
+                    rule.subagent
+                ));
+            }
         }
 
         Ok(())
@@ -54,18 +125,38 @@ impl DispatchPolicyConfig {
         {
             self.fallback_subagent = Some(fallback_subagent.to_string());
         }
+        if other.budget_usd.is_some() {
+            self.budget_usd = other.budget_usd;
+        }
         self.rules.extend(other.rules.clone());
     }
 
+    /// Evaluate policy for a dispatch target without live metrics.
+    pub fn evaluate(
+        &self,
+        tool_name: &str,
+        requested_subagent: &str,
+        resolver: &dyn SubAgentResolver,
+    ) -> DispatchPolicyDecision {
+        self.evaluate_with_metrics(
+            tool_name,
+            requested_subagent,
+            resolver,
+            DispatchMetrics::default(),
+        )
+    }
+
     /// Evaluate policy for a dispatch target.
     ///
-    /// Rules are matched in declaration order. The first matching rule decides
-    /// allow/deny behavior ("first-match-wins").
-    pub fn evaluate(
+    /// An exhausted `budget_usd` rejects every dispatch. Otherwise rules are matched in
+    /// declaration order against the tool, the resolved sub-agent and `metrics`, and the first
+    /// matching rule allows, denies or reroutes the task ("first-match-wins").
+    pub fn evaluate_with_metrics(
         &self,
         tool_name: &str,
         requested_subagent: &str,
         resolver: &dyn SubAgentResolver,
+        metrics: DispatchMetrics,
     ) -> DispatchPolicyDecision {
         let requested_subagent = requested_subagent.trim().to_string();
         let mut resolved_subagent = requested_subagent.clone();
@@ -85,42 +176,77 @@ impl DispatchPolicyConfig {
             }
         }
 
-        let mut blocked = false;
-        let mut reason = None;
-        for rule in &self.rules {
-            if !policy_tool_matches(rule, tool_name) {
-                continue;
-            }
-            if !policy_subagent_matches(rule, &resolved_subagent) {
-                continue;
+        let mut decision = DispatchPolicyDecision {
+            requested_subagent,
+            resolved_subagent,
+            routing_hint_applied,
+            blocked: false,
+            reason: None,
+            matched_rule: None,
+            metrics,
+        };
+
+        if let (Some(budget), Some(remaining)) = (self.budget_usd, metrics.remaining_budget_usd) {
+            if remaining <= 0.0 {
+                decision.blocked = true;
+                decision.reason = Some(format!(
+                    "task dispatch denied: sub-agent budget of ${budget:.2} is used up"
+                ));
+                return decision;
             }
-            if matches!(rule.effect, PolicyRuleEffect::Deny) {
-                blocked = true;
-                reason = Some(format!(
+        }
+
+        let Some((index, rule)) = self.rules.iter().enumerate().find(|(_, rule)| {
+            policy_tool_matches(rule, tool_name)
+                && policy_subagent_matches(rule, &decision.resolved_subagent)
+                && rule.conditions.matches(&metrics)
+        }) else {
+            return decision;
+        };
+        decision.matched_rule = Some(index);
+        match rule.effect {
+            PolicyRuleEffect::Allow => {}
+            PolicyRuleEffect::Deny => {
+                decision.blocked = true;
+                decision.reason = Some(format!(
                     "task dispatch denied by policy rule (tool='{}', subagent='{}')",
                     rule.tool, rule.subagent
                 ));
             }
-            break;
-        }
-
-        DispatchPolicyDecision {
-            requested_subagent,
-            resolved_subagent,
-            routing_hint_applied,
-            blocked,
-            reason,
+            PolicyRuleEffect::Route => {
+                let target = rule.route_to.as_deref().unwrap_or_default().trim();
+                if resolver.resolve(target).is_some() {
+                    decision.reason = Some(format!(
+                        "task for '{}' routed to '{target}' by policy rule #{}",
+                        decision.resolved_subagent,
+                        index + 1
+                    ));
+                    decision.resolved_subagent = target.to_string();
+                    decision.routing_hint_applied = true;
+                } else {
+                    decision.blocked = true;
+                    decision.reason = Some(format!(
+                        "policy rule #{} routes to unknown subagent '{target}'",
+                        index + 1
+                    ));
+                }
+            }
         }
+        decision
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct DispatchPolicyDecision {
     pub requested_subagent: String,
     pub resolved_subagent: String,
     pub routing_hint_applied: bool,
     pub blocked: bool,
     pub reason: Option<String>,
+    /// Index of the rule that decided the dispatch.
+    pub matched_rule: Option<usize>,
+    /// Metrics the decision was made with.
+    pub metrics: DispatchMetrics,
 }
 
 fn default_tool_name() -> String {
@@ -174,16 +300,21 @@ mod tests {
     fn policy_denies_subagent_by_tool_scope() {
         let policy = DispatchPolicyConfig {
             fallback_subagent: None,
+            budget_usd: None,
             rules: vec![
                 DispatchPolicyRule {
                     subagent: "explore".to_string(),
                     tool: "task".to_string(),
                     effect: PolicyRuleEffect::Deny,
+                    route_to: None,
+                    conditions: DispatchPolicyConditions::default(),
                 },
                 DispatchPolicyRule {
                     subagent: "*".to_string(),
                     tool: "task".to_string(),
                     effect: PolicyRuleEffect::Allow,
+                    route_to: None,
+                    conditions: DispatchPolicyConditions::default(),
                 },
             ],
         };
@@ -197,6 +328,7 @@ mod tests {
     fn policy_applies_fallback_subagent_when_requested_one_missing() {
         let policy = DispatchPolicyConfig {
             fallback_subagent: Some("general".to_string()),
+            budget_usd: None,
             rules: vec![],
         };
 
@@ -205,4 +337,90 @@ mod tests {
         assert_eq!(decision.resolved_subagent, "general");
         assert!(decision.routing_hint_applied);
     }
+
+    fn budget_policy() -> DispatchPolicyConfig {
+        toml::from_str(
+            r#"
+budget_usd = 2.0
+
+[[rules]]
+subagent = "general"
+effect = "route"
+route_to = "explore"
+max_remaining_budget_usd = 0.5
+
+[[rules]]
+subagent = "*"
+effect = "deny"
+min_task_tokens = 1000
+min_queue_depth = 3
+"#,
+        )
+        .expect("policy should parse")
+    }
+
+    #[test]
+    fn policy_routes_to_cheaper_subagent_when_budget_runs_low() {
+        let policy = budget_policy();
+        policy.validate().expect("policy should be valid");
+        let metrics = |remaining| DispatchMetrics {
+            remaining_budget_usd: Some(remaining),
+            task_tokens: 10,
+            queue_depth: 1,
+        };
+
+        let plenty = policy.evaluate_with_metrics("task", "general", &registry(), metrics(1.5));
+        assert_eq!(plenty.resolved_subagent, "general");
+        assert_eq!(plenty.matched_rule, None);
+
+        let low = policy.evaluate_with_metrics("task", "general", &registry(), metrics(0.25));
+        assert!(!low.blocked);
+        assert_eq!(low.resolved_subagent, "explore");
+        assert!(low.routing_hint_applied);
+        assert_eq!(low.matched_rule, Some(0));
+        assert_eq!(low.metrics.remaining_budget_usd, Some(0.25));
+
+        let spent = policy.evaluate_with_metrics("task", "general", &registry(), metrics(0.0));
+        assert!(spent.blocked);
+        assert!(spent
+            .reason
+            .as_deref()
+            .is_some_and(|reason| reason.contains("budget of $2.00 is used up")));
+    }
+
+    #[test]
+    fn policy_rules_require_every_condition_to_match() {
+        let policy = budget_policy();
+        let metrics = |task_tokens, queue_depth| DispatchMetrics {
+            remaining_budget_usd: Some(1.0),
+            task_tokens,
+            queue_depth,
+        };
+
+        let small = policy.evaluate_with_metrics("task", "explore", &registry(), metrics(50, 4));
+        assert!(!small.blocked);
+        let idle = policy.evaluate_with_metrics("task", "explore", &registry(), metrics(5000, 1));
+        assert!(!idle.blocked);
+        let busy = policy.evaluate_with_metrics("task", "explore", &registry(), metrics(5000, 3));
+        assert!(busy.blocked);
+        assert_eq!(busy.matched_rule, Some(1));
+    }
+
+    #[test]
+    fn policy_validation_rejects_route_without_target() {
+        let policy = DispatchPolicyConfig {
+            fallback_subagent: None,
+            budget_usd: None,
+            rules: vec![DispatchPolicyRule {
+                subagent: "general".to_string(),
+                tool: "task".to_string(),
+                effect: PolicyRuleEffect::Route,
+                route_to: None,
+                conditions: DispatchPolicyConditions::default(),
+            }],
+        };
+
+        let error = policy.validate().expect_err("route needs a target");
+        assert!(error.contains("without route_to"));
+    }
 }
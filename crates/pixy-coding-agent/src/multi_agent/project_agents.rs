@@ -0,0 +1,173 @@
+use std::path::{Path, PathBuf};
+
+use crate::SubAgentSpec;
+
+use super::plugin_loader::PluginAgentFile;
+
+/// Project sub-agent definitions live in `<cwd>/.pixy/agents/*.md`.
+pub(crate) const PROJECT_AGENTS_DIR: &str = ".pixy/agents";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProjectSubAgentSpec {
+    pub path: PathBuf,
+    pub spec: SubAgentSpec,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoadProjectSubAgentsResult {
+    pub subagents: Vec<ProjectSubAgentSpec>,
+    /// One message per file that could not be loaded; valid files are still returned.
+    pub warnings: Vec<String>,
+}
+
+/// Loads sub-agent definitions from markdown files in `agents_dir`.
+///
+/// Each file carries the same fields as a plugin agent file in YAML frontmatter (`name`,
+/// `description`, `provider`, `model`, `tools`, `blocked_tools`, `metadata`); the markdown body
+/// becomes the sub-agent prompt. The name defaults to the file stem.
+pub fn load_project_subagents(agents_dir: &Path) -> LoadProjectSubAgentsResult {
+    let mut result = LoadProjectSubAgentsResult::default();
+    let Ok(entries) = std::fs::read_dir(agents_dir) else {
+        return result;
+    };
+
+    let mut agent_files = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("md"))
+        })
+        .collect::<Vec<_>>();
+    agent_files.sort();
+
+    for agent_file in agent_files {
+        match load_project_subagent(&agent_file) {
+            Ok(spec) => result.subagents.push(ProjectSubAgentSpec {
+                path: agent_file,
+                spec,
+            }),
+            Err(error) => result.warnings.push(format!(
+                "agent file {} is invalid: {error}",
+                agent_file.display()
+            )),
+        }
+    }
+    result
+}
+
+fn load_project_subagent(path: &Path) -> Result<SubAgentSpec, String> {
+    let content = std::fs::read_to_string(path).map_err(|error| format!("read failed: {error}"))?;
+    let (frontmatter, body) = split_frontmatter(&content);
+    let mut raw = match frontmatter {
+        Some(yaml) if !yaml.trim().is_empty() => serde_yaml::from_str::<PluginAgentFile>(yaml)
+            .map_err(|error| format!("parse frontmatter failed: {error}"))?,
+        _ => PluginAgentFile::default(),
+    };
+
+    let body = body.trim();
+    if !body.is_empty() {
+        if raw
+            .prompt
+            .as_deref()
+            .is_some_and(|prompt| !prompt.trim().is_empty())
+        {
+            return Err("prompt is defined in both frontmatter and body".to_string());
+        }
+        raw.prompt = Some(body.to_string());
+    }
+
+    let fallback_name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    let spec = raw.into_spec(fallback_name)?;
+    spec.validate()?;
+    Ok(spec)
+}
+
+fn split_frontmatter(content: &str) -> (Option<&str>, &str) {
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return (None, content);
+    };
+    let Some(end) = rest.find("\n---") else {
+        return (None, content);
+    };
+    let after = &rest[end + 4..];
+    let body = after.split_once('\n').map(|(_, body)| body).unwrap_or("");
+    (Some(&rest[..end]), body)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::load_project_subagents;
+    use crate::SubAgentMode;
+
+    #[test]
+    fn load_project_subagents_parses_frontmatter_and_body_prompt() {
+        let dir = tempdir().expect("tempdir");
+        fs::write(
+            dir.path().join("reviewer.md"),
+            "---\ndescription: Reviews diffs for bugs\nprovider: openai\nmodel: gpt-5.3-codex\ntools: [read, bash]\n---\n\nYou review code.\nBe terse.\n",
+        )
+        .expect("write agent");
+        fs::write(dir.path().join("notes.txt"), "ignored").expect("write notes");
+
+        let loaded = load_project_subagents(dir.path());
+
+        assert!(loaded.warnings.is_empty(), "{:?}", loaded.warnings);
+        assert_eq!(loaded.subagents.len(), 1);
+        let spec = &loaded.subagents[0].spec;
+        assert_eq!(spec.name, "reviewer");
+        assert_eq!(spec.description, "Reviews diffs for bugs");
+        assert_eq!(spec.mode, SubAgentMode::SubAgent);
+        assert_eq!(spec.model.as_deref(), Some("openai/gpt-5.3-codex"));
+        assert_eq!(spec.tools, vec!["read".to_string(), "bash".to_string()]);
+        assert_eq!(spec.prompt.as_deref(), Some("You review code.\nBe terse."));
+    }
+
+    #[test]
+    fn load_project_subagents_reports_invalid_files_and_keeps_valid_ones() {
+        let dir = tempdir().expect("tempdir");
+        fs::write(
+            dir.path().join("a-valid.md"),
+            "---\ndescription: Valid agent\n---\nDo things.\n",
+        )
+        .expect("write valid agent");
+        fs::write(
+            dir.path().join("b-mismatch.md"),
+            "---\nname: other\ndescription: Wrong name\n---\n",
+        )
+        .expect("write mismatched agent");
+        fs::write(
+            dir.path().join("c-conflict.md"),
+            "---\ndescription: Conflicting tools\ntools: [read]\nblocked_tools: [read]\n---\n",
+        )
+        .expect("write conflicting agent");
+
+        let loaded = load_project_subagents(dir.path());
+
+        assert_eq!(loaded.subagents.len(), 1);
+        assert_eq!(loaded.subagents[0].spec.name, "a-valid");
+        assert_eq!(loaded.warnings.len(), 2);
+        assert!(loaded.warnings[0].contains("does not match file name"));
+        assert!(loaded.warnings[1].contains("cannot exist in both"));
+    }
+
+    #[test]
+    fn load_project_subagents_returns_empty_for_missing_dir() {
+        let dir = tempdir().expect("tempdir");
+        let loaded = load_project_subagents(&dir.path().join("missing"));
+        assert!(loaded.subagents.is_empty());
+        assert!(loaded.warnings.is_empty());
+    }
+}
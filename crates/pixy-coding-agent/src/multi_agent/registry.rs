@@ -1,4 +1,5 @@
 use std::collections::BTreeMap;
+use std::path::Path;
 
 use super::types::SubAgentSpec;
 
@@ -75,6 +76,15 @@ impl SubAgentRegistryBuilder {
         self.register_with_source_mut(spec, format!("plugin:{plugin_name}"))
     }
 
+    /// Registers a sub-agent defined in a project `.pixy/agents/*.md` file.
+    pub fn register_project_subagent_mut(
+        &mut self,
+        path: &Path,
+        spec: SubAgentSpec,
+    ) -> Result<(), String> {
+        self.register_with_source_mut(spec, format!("project:{}", path.display()))
+    }
+
     fn register_with_source_mut(
         &mut self,
         spec: SubAgentSpec,
@@ -1,34 +1,60 @@
 use std::sync::Arc;
+use std::time::Instant;
 
 use async_trait::async_trait;
-use pixy_agent_core::{AgentTool, AgentToolExecutor, AgentToolResult};
+use pixy_agent_core::{AgentAbortSignal, AgentTool, AgentToolExecutor, AgentToolResult};
 use pixy_ai::{PiAiError, PiAiErrorCode, ToolResultContentBlock};
+use serde::Deserialize;
 use serde_json::{json, Value};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 
-use super::dispatcher::TaskDispatcher;
+use super::dispatcher::{TaskDispatchResult, TaskDispatcher, TASK_CANCELLED_KIND};
 use crate::TaskToolInput;
 
+/// Upper bound on child sessions running at once for a single `tasks` call.
+pub(crate) const MAX_PARALLEL_TASKS: usize = 4;
+
 pub fn create_task_tool(dispatcher: Arc<TaskDispatcher>) -> AgentTool {
     AgentTool {
         name: "task".to_string(),
         label: "task".to_string(),
-        description:
-            "Delegate work to a registered subagent, optionally reusing prior task context with task_id."
-                .to_string(),
+        description: format!(
+            "Delegate work to a registered subagent, optionally reusing prior task context with task_id. Pass `tasks` instead of `subagent_type`/`prompt` to run independent subtasks concurrently (at most {MAX_PARALLEL_TASKS} at a time)."
+        ),
         parameters: json!({
             "type": "object",
             "properties": {
                 "subagent_type": { "type": "string", "description": "Registered subagent type name." },
                 "prompt": { "type": "string", "description": "Task prompt passed to the subagent." },
-                "task_id": { "type": "string", "description": "Optional child-session reuse identifier." }
+                "task_id": { "type": "string", "description": "Optional child-session reuse identifier." },
+                "tasks": {
+                    "type": "array",
+                    "description": "Independent subtasks to run concurrently; each takes subagent_type, prompt and optional task_id.",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "subagent_type": { "type": "string" },
+                            "prompt": { "type": "string" },
+                            "task_id": { "type": "string" }
+                        },
+                        "required": ["subagent_type", "prompt"],
+                        "additionalProperties": false
+                    }
+                }
             },
-            "required": ["subagent_type", "prompt"],
             "additionalProperties": false
         }),
+        conflict_key: None,
         execute: Arc::new(TaskToolExecutor { dispatcher }),
     }
 }
 
+#[derive(Deserialize)]
+struct ParallelTaskToolInput {
+    tasks: Vec<TaskToolInput>,
+}
+
 struct TaskToolExecutor {
     dispatcher: Arc<TaskDispatcher>,
 }
@@ -40,34 +66,19 @@ impl AgentToolExecutor for TaskToolExecutor {
         _tool_call_id: String,
         args: Value,
     ) -> Result<AgentToolResult, PiAiError> {
-        let input: TaskToolInput = serde_json::from_value(args).map_err(|error| {
-            PiAiError::new(
-                PiAiErrorCode::ToolArgumentsInvalid,
-                format!("invalid task tool arguments: {error}"),
-            )
-        })?;
+        if args.get("tasks").is_some() {
+            let input: ParallelTaskToolInput =
+                serde_json::from_value(args).map_err(invalid_arguments)?;
+            return self.execute_parallel(input.tasks).await;
+        }
+
+        let input: TaskToolInput = serde_json::from_value(args).map_err(invalid_arguments)?;
         input
             .validate()
             .map_err(|error| PiAiError::new(PiAiErrorCode::ToolArgumentsInvalid, error))?;
 
         let dispatched = self.dispatcher.dispatch(input).await?;
-        let mut details = serde_json::to_value(&dispatched.output).map_err(|error| {
-            PiAiError::new(
-                PiAiErrorCode::ToolExecutionFailed,
-                format!("serialize task tool output failed: {error}"),
-            )
-        })?;
-        if let Some(object) = details.as_object_mut() {
-            object.insert(
-                "resolved_subagent".to_string(),
-                json!(dispatched.resolved_subagent),
-            );
-            object.insert(
-                "routing_hint_applied".to_string(),
-                json!(dispatched.routing_hint_applied),
-            );
-            object.insert("duration_ms".to_string(), json!(dispatched.duration_ms));
-        }
+        let details = dispatch_details(&dispatched)?;
 
         let mut content = vec![ToolResultContentBlock::Text {
             text: format!("<task_result>\n{}\n</task_result>", dispatched.summary),
@@ -82,13 +93,204 @@ impl AgentToolExecutor for TaskToolExecutor {
             });
         }
 
+        Ok(AgentToolResult { content, details })
+    }
+}
+
+impl TaskToolExecutor {
+    async fn execute_parallel(
+        &self,
+        tasks: Vec<TaskToolInput>,
+    ) -> Result<AgentToolResult, PiAiError> {
+        if tasks.is_empty() {
+            return Err(PiAiError::new(
+                PiAiErrorCode::ToolArgumentsInvalid,
+                "task tasks cannot be empty",
+            ));
+        }
+        for (index, task) in tasks.iter().enumerate() {
+            task.validate().map_err(|error| {
+                PiAiError::new(
+                    PiAiErrorCode::ToolArgumentsInvalid,
+                    format!("tasks[{index}]: {error}"),
+                )
+            })?;
+        }
+
+        let started_at = Instant::now();
+        let semaphore = Arc::new(Semaphore::new(MAX_PARALLEL_TASKS));
+        // Spawned children do not inherit the task-local abort signal, so it is passed on.
+        // Aborting the parent run cancels the running children and skips the queued ones;
+        // dropping the set on a hard abort stops them at once.
+        let parent_signal = AgentAbortSignal::current();
+        let mut join_set = JoinSet::new();
+        for (index, task) in tasks.iter().cloned().enumerate() {
+            let dispatcher = self.dispatcher.clone();
+            let semaphore = semaphore.clone();
+            let parent_signal = parent_signal.clone();
+            // Waiting tasks count toward the queue depth policy rules see.
+            let queued = dispatcher.enqueue();
+            join_set.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                (
+                    index,
+                    dispatcher
+                        .dispatch_queued(queued, task, parent_signal)
+                        .await,
+                )
+            });
+        }
+
+        let mut results: Vec<Option<Result<TaskDispatchResult, PiAiError>>> =
+            (0..tasks.len()).map(|_| None).collect();
+        while let Some(joined) = join_set.join_next().await {
+            let (index, result) = joined.map_err(|error| {
+                PiAiError::new(
+                    PiAiErrorCode::ToolExecutionFailed,
+                    format!("parallel task join failed: {error}"),
+                )
+            })?;
+            results[index] = Some(result);
+        }
+        let duration_ms = u64::try_from(started_at.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+        let mut result_text = String::from("<task_results>\n");
+        let mut report_lines = Vec::new();
+        let mut task_details = Vec::with_capacity(tasks.len());
+        let mut failed = 0usize;
+        let mut cancelled = 0usize;
+        let mut total_tokens = 0u64;
+        let mut total_cost = 0.0f64;
+        for (task, result) in tasks.iter().zip(results) {
+            match result.expect("every spawned task reports a result") {
+                Ok(dispatched) => {
+                    total_tokens += dispatched.total_tokens;
+                    total_cost += dispatched.cost;
+                    result_text.push_str(&format!(
+                        "<task_result subagent=\"{}\" task_id=\"{}\">\n{}\n</task_result>\n",
+                        dispatched.resolved_subagent, dispatched.output.task_id, dispatched.summary
+                    ));
+                    report_lines.push(format!(
+                        "Subagent {} finished in {}; {}",
+                        dispatched.resolved_subagent,
+                        format_duration(dispatched.duration_ms),
+                        format_usage(dispatched.total_tokens, dispatched.cost)
+                    ));
+                    report_lines.extend(
+                        dispatched
+                            .trace_lines
+                            .iter()
+                            .map(|line| line.trim_end())
+                            .filter(|line| !line.trim().is_empty())
+                            .map(|line| format!("  {line}")),
+                    );
+                    task_details.push(dispatch_details(&dispatched)?);
+                }
+                Err(error) => {
+                    failed += 1;
+                    if is_task_cancelled(&error) {
+                        cancelled += 1;
+                    }
+                    result_text.push_str(&format!(
+                        "<task_error subagent=\"{}\">\n{}\n</task_error>\n",
+                        task.subagent_type, error.message
+                    ));
+                    report_lines.push(format!(
+                        "Subagent {} failed: {}",
+                        task.subagent_type, error.message
+                    ));
+                    task_details.push(json!({
+                        "requested_subagent": task.subagent_type,
+                        "error": error.message,
+                    }));
+                }
+            }
+        }
+        result_text.push_str("</task_results>");
+        let cancelled_note = if cancelled > 0 {
+            format!(" ({cancelled} cancelled)")
+        } else {
+            String::new()
+        };
+        report_lines.push(format!(
+            "Subagents: {} of {} succeeded{cancelled_note} in {}; {}",
+            tasks.len() - failed,
+            tasks.len(),
+            format_duration(duration_ms),
+            format_usage(total_tokens, total_cost)
+        ));
+
         Ok(AgentToolResult {
-            content,
-            details,
+            content: vec![
+                ToolResultContentBlock::Text {
+                    text: result_text,
+                    text_signature: None,
+                },
+                ToolResultContentBlock::Text {
+                    text: report_lines.join("\n"),
+                    text_signature: None,
+                },
+            ],
+            details: json!({
+                "tasks": task_details,
+                "failed": failed,
+                "cancelled": cancelled,
+                "duration_ms": duration_ms,
+                "total_tokens": total_tokens,
+                "cost": total_cost,
+            }),
         })
     }
 }
 
+fn is_task_cancelled(error: &PiAiError) -> bool {
+    error
+        .details
+        .as_ref()
+        .and_then(|details| details.get("kind"))
+        .and_then(Value::as_str)
+        == Some(TASK_CANCELLED_KIND)
+}
+
+fn invalid_arguments(error: serde_json::Error) -> PiAiError {
+    PiAiError::new(
+        PiAiErrorCode::ToolArgumentsInvalid,
+        format!("invalid task tool arguments: {error}"),
+    )
+}
+
+fn dispatch_details(dispatched: &TaskDispatchResult) -> Result<Value, PiAiError> {
+    let mut details = serde_json::to_value(&dispatched.output).map_err(|error| {
+        PiAiError::new(
+            PiAiErrorCode::ToolExecutionFailed,
+            format!("serialize task tool output failed: {error}"),
+        )
+    })?;
+    if let Some(object) = details.as_object_mut() {
+        object.insert(
+            "resolved_subagent".to_string(),
+            json!(dispatched.resolved_subagent),
+        );
+        object.insert(
+            "routing_hint_applied".to_string(),
+            json!(dispatched.routing_hint_applied),
+        );
+        object.insert("duration_ms".to_string(), json!(dispatched.duration_ms));
+        object.insert("total_tokens".to_string(), json!(dispatched.total_tokens));
+        object.insert("cost".to_string(), json!(dispatched.cost));
+    }
+    Ok(details)
+}
+
+fn format_duration(duration_ms: u64) -> String {
+    let total_seconds = duration_ms / 1000;
+    format!("{} m {} s", total_seconds / 60, total_seconds % 60)
+}
+
+fn format_usage(total_tokens: u64, cost: f64) -> String {
+    format!("{total_tokens} tokens, ${cost:.4}")
+}
+
 fn format_subagent_trace(subagent: &str, lines: &[String]) -> Option<String> {
     let mut rendered = String::new();
     for line in lines {
@@ -110,8 +312,10 @@ fn format_subagent_trace(subagent: &str, lines: &[String]) -> Option<String> {
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex as StdMutex};
+    use std::time::Duration;
 
+    use pixy_agent_core::{AgentAbortController, ParentChildRunEvent};
     use pixy_ai::{
         AssistantContentBlock, AssistantMessage, AssistantMessageEvent,
         AssistantMessageEventStream, Cost, DoneReason, Model, StopReason, Usage,
@@ -146,6 +350,9 @@ mod tests {
             },
             context_window: 128_000,
             max_tokens: 8_192,
+            deployment: None,
+            api_version: None,
+            compat: None,
         }
     }
 
@@ -155,6 +362,7 @@ mod tests {
             output: 1,
             cache_read: 0,
             cache_write: 0,
+            reasoning: 0,
             total_tokens: 2,
             cost: Cost {
                 input: 0.0,
@@ -180,6 +388,7 @@ mod tests {
             stop_reason: StopReason::Stop,
             error_message: None,
             timestamp: 1,
+            stats: None,
         };
         let stream = AssistantMessageEventStream::new();
         stream.push(AssistantMessageEvent::Start {
@@ -224,6 +433,8 @@ mod tests {
                 Ok(done_stream("child completed".to_string()))
             }),
             child_tools: vec![],
+            child_shell_tool: None,
+            child_controls: Default::default(),
             subagent_registry: registry(),
             session_store: Arc::new(Mutex::new(ChildSessionStore::new("parent-session"))),
             dispatch_policy: DispatchPolicyConfig::default(),
@@ -257,6 +468,175 @@ mod tests {
         assert!(result.details["duration_ms"].as_u64().is_some());
     }
 
+    #[tokio::test]
+    async fn task_tool_runs_parallel_tasks_and_aggregates_results() {
+        let dir = tempdir().expect("tempdir");
+
+        let dispatcher = Arc::new(TaskDispatcher::new(TaskDispatcherConfig {
+            cwd: dir.path().to_path_buf(),
+            parent_session_id: "parent-session".to_string(),
+            parent_session_dir: dir.path().to_path_buf(),
+            model: sample_model(),
+            model_catalog: vec![sample_model()],
+            system_prompt: "You are parent".to_string(),
+            stream_fn: Arc::new(move |_model, _context, _options| {
+                Ok(done_stream("child completed".to_string()))
+            }),
+            child_tools: vec![],
+            child_shell_tool: None,
+            child_controls: Default::default(),
+            subagent_registry: registry(),
+            session_store: Arc::new(Mutex::new(ChildSessionStore::new("parent-session"))),
+            dispatch_policy: DispatchPolicyConfig::default(),
+            plugin_runtime: Arc::new(MultiAgentPluginRuntime::default()),
+            lifecycle_event_sink: None,
+        }));
+
+        let tool = create_task_tool(dispatcher);
+        let result = tool
+            .execute
+            .execute(
+                "tc-1".to_string(),
+                json!({
+                    "tasks": [
+                        {"subagent_type": "general", "prompt": "first"},
+                        {"subagent_type": "missing", "prompt": "second"},
+                        {"subagent_type": "general", "prompt": "third"}
+                    ]
+                }),
+            )
+            .await
+            .expect("parallel task tool should succeed");
+
+        let texts = result
+            .content
+            .iter()
+            .map(|block| match block {
+                pixy_ai::ToolResultContentBlock::Text { text, .. } => text.clone(),
+                _ => panic!("expected text tool result"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texts[0]
+                .matches("<task_result subagent=\"general\"")
+                .count(),
+            2
+        );
+        assert!(texts[0].contains("<task_error subagent=\"missing\">"));
+        assert!(texts[1].contains("Subagent general finished in 0 m 0 s; 2 tokens, $0.0000"));
+        assert!(texts[1].contains("Subagent missing failed: unknown subagent_type 'missing'"));
+        assert!(texts[1].ends_with("Subagents: 2 of 3 succeeded in 0 m 0 s; 4 tokens, $0.0000"));
+        assert_eq!(result.details["failed"], 1);
+        assert_eq!(result.details["total_tokens"], 4);
+        assert_eq!(result.details["tasks"].as_array().map(Vec::len), Some(3));
+        assert_eq!(result.details["tasks"][1]["requested_subagent"], "missing");
+    }
+
+    #[tokio::test]
+    async fn task_tool_cancels_children_when_the_parent_run_is_aborted() {
+        let dir = tempdir().expect("tempdir");
+        let events = Arc::new(StdMutex::new(Vec::<ParentChildRunEvent>::new()));
+        let sink_events = events.clone();
+
+        let dispatcher = Arc::new(TaskDispatcher::new(TaskDispatcherConfig {
+            cwd: dir.path().to_path_buf(),
+            parent_session_id: "parent-session".to_string(),
+            parent_session_dir: dir.path().to_path_buf(),
+            model: sample_model(),
+            model_catalog: vec![sample_model()],
+            system_prompt: "You are parent".to_string(),
+            // Child responses never finish on their own.
+            stream_fn: Arc::new(move |_model, _context, _options| {
+                Ok(AssistantMessageEventStream::new())
+            }),
+            child_tools: vec![],
+            child_shell_tool: None,
+            child_controls: Default::default(),
+            subagent_registry: registry(),
+            session_store: Arc::new(Mutex::new(ChildSessionStore::new("parent-session"))),
+            dispatch_policy: DispatchPolicyConfig::default(),
+            plugin_runtime: Arc::new(MultiAgentPluginRuntime::default()),
+            lifecycle_event_sink: Some(Arc::new(move |event| {
+                sink_events.lock().unwrap().push(event);
+            })),
+        }));
+
+        let tool = create_task_tool(dispatcher);
+        let parent = AgentAbortController::new();
+        let signal = parent.signal();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            parent.abort();
+        });
+        let result = signal
+            .scope(tool.execute.execute(
+                "tc-1".to_string(),
+                json!({
+                    "tasks": [
+                        {"subagent_type": "general", "prompt": "first"},
+                        {"subagent_type": "general", "prompt": "second"}
+                    ]
+                }),
+            ))
+            .await
+            .expect("parallel task tool reports cancelled children");
+
+        let report = match &result.content[1] {
+            pixy_ai::ToolResultContentBlock::Text { text, .. } => text.clone(),
+            _ => panic!("expected text tool result"),
+        };
+        assert!(report.contains("Subagents: 0 of 2 succeeded (2 cancelled)"));
+        assert_eq!(result.details["cancelled"], 2);
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 4);
+        let errors = events
+            .iter()
+            .filter_map(|event| match event {
+                ParentChildRunEvent::ChildRunError { error, .. } => Some(error.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec!["subagent 'general' cancelled: the parent run was aborted"; 2]
+        );
+    }
+
+    #[tokio::test]
+    async fn task_tool_rejects_empty_parallel_tasks() {
+        let dir = tempdir().expect("tempdir");
+
+        let dispatcher = Arc::new(TaskDispatcher::new(TaskDispatcherConfig {
+            cwd: dir.path().to_path_buf(),
+            parent_session_id: "parent-session".to_string(),
+            parent_session_dir: dir.path().to_path_buf(),
+            model: sample_model(),
+            model_catalog: vec![sample_model()],
+            system_prompt: "You are parent".to_string(),
+            stream_fn: Arc::new(move |_model, _context, _options| {
+                Ok(done_stream("child completed".to_string()))
+            }),
+            child_tools: vec![],
+            child_shell_tool: None,
+            child_controls: Default::default(),
+            subagent_registry: registry(),
+            session_store: Arc::new(Mutex::new(ChildSessionStore::new("parent-session"))),
+            dispatch_policy: DispatchPolicyConfig::default(),
+            plugin_runtime: Arc::new(MultiAgentPluginRuntime::default()),
+            lifecycle_event_sink: None,
+        }));
+
+        let tool = create_task_tool(dispatcher);
+        let error = tool
+            .execute
+            .execute("tc-1".to_string(), json!({"tasks": []}))
+            .await
+            .expect_err("empty tasks should fail");
+
+        assert!(error.message.contains("tasks cannot be empty"));
+    }
+
     #[test]
     fn format_subagent_trace_prefixes_lines_and_omits_empty_entries() {
         let trace = super::format_subagent_trace(
@@ -287,6 +667,8 @@ mod tests {
                 Ok(done_stream("child completed".to_string()))
             }),
             child_tools: vec![],
+            child_shell_tool: None,
+            child_controls: Default::default(),
             subagent_registry: registry(),
             session_store: Arc::new(Mutex::new(ChildSessionStore::new("parent-session"))),
             dispatch_policy: DispatchPolicyConfig::default(),
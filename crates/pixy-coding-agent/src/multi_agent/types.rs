@@ -2,20 +2,15 @@ use std::collections::BTreeSet;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub enum SubAgentMode {
     #[serde(rename = "primary")]
     Primary,
+    #[default]
     #[serde(rename = "subagent", alias = "sub_agent")]
     SubAgent,
 }
 
-impl Default for SubAgentMode {
-    fn default() -> Self {
-        Self::SubAgent
-    }
-}
-
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SubAgentPromptTrigger {
     pub domain: String,
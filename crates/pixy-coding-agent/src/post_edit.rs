@@ -0,0 +1,401 @@
+//! Formatters and linters declared as `[[post_edit]]` in `pixy.toml`.
+//!
+//! After `write`, `edit`, `apply_patch` or `notebook_edit` succeeds, every command whose `files`
+//! patterns match a touched file runs on it. The diagnostics of failing commands, and a note when a
+//! formatter rewrote the file, are appended to the tool result so the model can fix problems in its
+//! next call instead of much later.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use pixy_agent_core::{AgentTool, AgentToolExecuteFn, AgentToolExecutor, AgentToolResult};
+use pixy_ai::{PiAiError, ToolResultContentBlock};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::process::Command;
+
+use crate::file_changes::SharedFileChangeTracker;
+use crate::lifecycle_hooks::{edited_paths, FILE_EDIT_TOOLS};
+
+const DEFAULT_POST_EDIT_TIMEOUT_MS: u64 = 30_000;
+/// Diagnostics beyond this many lines are cut; the model can rerun the command with `bash`.
+const MAX_DIAGNOSTIC_LINES: usize = 40;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PostEditCommand {
+    /// Label shown with the diagnostics; defaults to the command's first word.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Shell command; `{file}` is replaced by the quoted file path, which is appended otherwise.
+    pub command: String,
+    /// Gitignore-style patterns the edited path must match, such as `*.rs`; empty matches all.
+    #[serde(default)]
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+impl PostEditCommand {
+    fn label(&self) -> &str {
+        self.name
+            .as_deref()
+            .filter(|name| !name.trim().is_empty())
+            .or_else(|| self.command.split_whitespace().next())
+            .unwrap_or("post_edit")
+    }
+
+    fn command_line(&self, path: &Path) -> String {
+        let path = path.to_string_lossy();
+        let quoted = shlex::try_quote(&path)
+            .map(|quoted| quoted.into_owned())
+            .unwrap_or_else(|_| path.to_string());
+        if self.command.contains("{file}") {
+            self.command.replace("{file}", &quoted)
+        } else {
+            format!("{} {quoted}", self.command)
+        }
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(
+            self.timeout_ms
+                .unwrap_or(DEFAULT_POST_EDIT_TIMEOUT_MS)
+                .max(1),
+        )
+    }
+}
+
+/// Outcome of one command on one file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct PostEditReport {
+    name: String,
+    exit_code: Option<i32>,
+    /// The command rewrote the file.
+    changed: bool,
+    output: String,
+}
+
+impl PostEditReport {
+    fn render(&self, path: &str) -> Option<String> {
+        let mut lines = Vec::new();
+        if self.changed {
+            lines.push(format!(
+                "[{}] reformatted {path}; read it again before editing it further.",
+                self.name
+            ));
+        }
+        // Linters signal problems through their exit status; output of passing runs is noise.
+        if self.exit_code != Some(0) {
+            let status = match self.exit_code {
+                Some(code) => format!(" (exit code {code})"),
+                None => " (did not finish)".to_string(),
+            };
+            lines.push(format!("[{}] reported{status}:", self.name));
+            if !self.output.is_empty() {
+                lines.push(self.output.clone());
+            }
+        }
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+}
+
+#[derive(Debug)]
+pub struct PostEditChecks {
+    cwd: PathBuf,
+    commands: Vec<(PostEditCommand, Gitignore)>,
+    file_changes: Option<SharedFileChangeTracker>,
+}
+
+impl PostEditChecks {
+    /// Returns `None` when no valid command is configured. Commands with invalid `files`
+    /// patterns are skipped with a warning.
+    pub fn new(cwd: &Path, commands: &[PostEditCommand]) -> Option<Self> {
+        let commands = commands
+            .iter()
+            .filter(|command| !command.command.trim().is_empty())
+            .filter_map(|command| {
+                let mut builder = GitignoreBuilder::new(cwd);
+                for pattern in &command.files {
+                    if let Err(error) = builder.add_line(None, pattern) {
+                        eprintln!(
+                            "warning: skip post_edit command {}: invalid pattern {pattern}: {error}",
+                            command.label()
+                        );
+                        return None;
+                    }
+                }
+                let matcher = builder.build().ok()?;
+                Some((command.clone(), matcher))
+            })
+            .collect::<Vec<_>>();
+        (!commands.is_empty()).then(|| Self {
+            cwd: cwd.to_path_buf(),
+            commands,
+            file_changes: None,
+        })
+    }
+
+    /// Tracker to refresh after a formatter rewrites a file, so the rewrite is not reported as
+    /// an external change.
+    pub(crate) fn with_file_changes(
+        mut self,
+        file_changes: Option<SharedFileChangeTracker>,
+    ) -> Self {
+        self.file_changes = file_changes;
+        self
+    }
+
+    /// Wraps `write`, `edit`, `apply_patch` and `notebook_edit`; other tools are returned unchanged.
+    pub fn wrap_tool(self: &Arc<Self>, mut tool: AgentTool) -> AgentTool {
+        if !FILE_EDIT_TOOLS.contains(&tool.name.as_str()) {
+            return tool;
+        }
+        tool.execute = Arc::new(PostEditToolExecutor {
+            inner: tool.execute.clone(),
+            checks: self.clone(),
+        });
+        tool
+    }
+
+    async fn run(&self, path: &Path) -> Vec<PostEditReport> {
+        let relative = path.strip_prefix(&self.cwd).unwrap_or(path);
+        let mut reports = Vec::new();
+        for (command, matcher) in &self.commands {
+            let matches = command.files.is_empty()
+                || matcher
+                    .matched_path_or_any_parents(relative, false)
+                    .is_ignore();
+            if matches {
+                reports.push(self.run_command(command, path).await);
+            }
+        }
+        if reports.iter().any(|report| report.changed) {
+            if let Some(Ok(mut tracker)) = self.file_changes.as_ref().map(|tracker| tracker.lock())
+            {
+                tracker.observe(path);
+            }
+        }
+        reports
+    }
+
+    async fn run_command(&self, command: &PostEditCommand, path: &Path) -> PostEditReport {
+        let before = std::fs::read(path).ok();
+        let mut process = Command::new("bash");
+        process
+            .arg("-lc")
+            .arg(command.command_line(path))
+            .current_dir(&self.cwd)
+            .env("PIXY_FILE", path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let result = match process.spawn() {
+            Ok(child) => tokio::time::timeout(command.timeout(), child.wait_with_output())
+                .await
+                .map_err(|_| format!("timed out after {}ms", command.timeout().as_millis()))
+                .and_then(|output| output.map_err(|error| error.to_string())),
+            Err(error) => Err(format!("failed to start: {error}")),
+        };
+        let changed = std::fs::read(path).ok() != before;
+        match result {
+            Ok(output) => {
+                let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+                text.push_str(&String::from_utf8_lossy(&output.stderr));
+                PostEditReport {
+                    name: command.label().to_string(),
+                    exit_code: output.status.code(),
+                    changed,
+                    output: cap_diagnostics(text.trim()),
+                }
+            }
+            Err(error) => PostEditReport {
+                name: command.label().to_string(),
+                exit_code: None,
+                changed,
+                output: error,
+            },
+        }
+    }
+}
+
+fn cap_diagnostics(text: &str) -> String {
+    let lines = text.lines().collect::<Vec<_>>();
+    if lines.len() <= MAX_DIAGNOSTIC_LINES {
+        return text.to_string();
+    }
+    format!(
+        "{}\n[... {} more lines]",
+        lines[..MAX_DIAGNOSTIC_LINES].join("\n"),
+        lines.len() - MAX_DIAGNOSTIC_LINES
+    )
+}
+
+struct PostEditToolExecutor {
+    inner: AgentToolExecuteFn,
+    checks: Arc<PostEditChecks>,
+}
+
+#[async_trait]
+impl AgentToolExecutor for PostEditToolExecutor {
+    async fn execute(
+        &self,
+        tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        let mut result = self.inner.execute(tool_call_id, args).await?;
+        let mut reports = Vec::new();
+        let mut notes = Vec::new();
+        for path in edited_paths(&result.details) {
+            let absolute_path = self.checks.cwd.join(&path);
+            let file_reports = self.checks.run(&absolute_path).await;
+            notes.extend(
+                file_reports
+                    .iter()
+                    .filter_map(|report| report.render(&path)),
+            );
+            reports.extend(file_reports);
+        }
+        if reports.is_empty() {
+            return Ok(result);
+        }
+
+        if !notes.is_empty() {
+            let text = notes.join("\n\n");
+            match result
+                .content
+                .iter_mut()
+                .rev()
+                .find_map(|block| match block {
+                    ToolResultContentBlock::Text { text, .. } => Some(text),
+                    _ => None,
+                }) {
+                Some(existing) => {
+                    existing.push_str("\n\n");
+                    existing.push_str(&text);
+                }
+                None => result.content.push(ToolResultContentBlock::Text {
+                    text,
+                    text_signature: None,
+                }),
+            }
+        }
+        if let Some(details) = result.details.as_object_mut() {
+            details.insert(
+                "postEdit".to_string(),
+                json!(reports
+                    .iter()
+                    .map(|report| json!({
+                        "name": report.name,
+                        "exitCode": report.exit_code,
+                        "changed": report.changed,
+                    }))
+                    .collect::<Vec<_>>()),
+            );
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{create_bash_tool, create_edit_tool, create_write_tool};
+
+    fn command(name: &str, command: &str, files: &[&str]) -> PostEditCommand {
+        PostEditCommand {
+            name: Some(name.to_string()),
+            command: command.to_string(),
+            files: files.iter().map(|pattern| pattern.to_string()).collect(),
+            timeout_ms: None,
+        }
+    }
+
+    fn result_text(result: &AgentToolResult) -> String {
+        match &result.content[0] {
+            ToolResultContentBlock::Text { text, .. } => text.clone(),
+            _ => panic!("text result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn formats_matching_files_and_reports_linter_failures() {
+        let dir = tempdir().expect("tempdir");
+        let checks = Arc::new(
+            PostEditChecks::new(
+                dir.path(),
+                &[
+                    command(
+                        "upper",
+                        "tr a-z A-Z < {file} > {file}.tmp && mv {file}.tmp {file}",
+                        &["*.txt"],
+                    ),
+                    command("lint", "grep -n TODO {file} && exit 1; exit 0", &["*.txt"]),
+                    command("rs-only", "echo should-not-run", &["*.rs"]),
+                ],
+            )
+            .expect("commands configured"),
+        );
+        let write = checks.wrap_tool(create_write_tool(dir.path()));
+
+        let result = write
+            .execute
+            .execute(
+                "call-1".to_string(),
+                json!({ "path": "notes/a.txt", "content": "todo: fix\n" }),
+            )
+            .await
+            .expect("write succeeds");
+        let text = result_text(&result);
+        assert!(text.contains("[upper] reformatted notes/a.txt; read it again"));
+        assert!(text.contains("[lint] reported (exit code 1):"));
+        assert!(text.contains("1:TODO: FIX"));
+        assert!(!text.contains("should-not-run"));
+        assert_eq!(
+            result.details["postEdit"],
+            json!([
+                { "name": "upper", "exitCode": 0, "changed": true },
+                { "name": "lint", "exitCode": 1, "changed": false },
+            ])
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("notes/a.txt")).expect("read"),
+            "TODO: FIX\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn quiet_checks_leave_the_result_text_alone() {
+        let dir = tempdir().expect("tempdir");
+        fs::write(dir.path().join("main.rs"), "fn main() {}\n").expect("seed");
+        let checks = Arc::new(
+            PostEditChecks::new(dir.path(), &[command("check", "true", &[])])
+                .expect("commands configured"),
+        );
+        let edit = checks.wrap_tool(create_edit_tool(dir.path()));
+        let bash = create_bash_tool(dir.path());
+        assert!(Arc::ptr_eq(
+            &bash.execute,
+            &checks.wrap_tool(bash.clone()).execute
+        ));
+
+        let result = edit
+            .execute
+            .execute(
+                "call-1".to_string(),
+                json!({ "path": "main.rs", "oldText": "fn main() {}", "newText": "fn main() { }" }),
+            )
+            .await
+            .expect("edit succeeds");
+        assert!(!result_text(&result).contains("[check]"));
+        assert_eq!(result.details["postEdit"][0]["exitCode"], 0);
+    }
+}
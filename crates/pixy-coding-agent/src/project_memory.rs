@@ -0,0 +1,247 @@
+//! Durable project learnings maintained in `AGENTS.md` or `.pixy/memory.md`.
+//!
+//! Learnings live in a marker-delimited section so pixy can dedup and trim them without touching
+//! hand-written content around it.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+pub(crate) const PROJECT_MEMORY_FILE: &str = ".pixy/memory.md";
+const SECTION_HEADING: &str = "## Project Learnings";
+const SECTION_START: &str = "<!-- pixy:learnings:start -->";
+const SECTION_END: &str = "<!-- pixy:learnings:end -->";
+const DEFAULT_MAX_BYTES: usize = 8_000;
+
+pub(crate) const DISTILL_SYSTEM_PROMPT: &str =
+    "You maintain long-lived project notes for a coding assistant.";
+pub(crate) const DISTILL_PROMPT: &str = "From the conversation above, extract durable learnings about this codebase that will help future sessions: build and test commands, conventions, architecture facts, and pitfalls. Skip task-specific progress, anything already listed in <existing_learnings>, and anything uncertain. Reply with at most 5 lines, each starting with \"- \". Reply with NONE if nothing is worth keeping.";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectMemoryTarget {
+    /// `AGENTS.md` in the workspace root.
+    #[default]
+    AgentsMd,
+    /// `.pixy/memory.md` in the workspace root.
+    PixyMemory,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProjectMemoryConfig {
+    /// Distill learnings automatically when a session ends.
+    pub auto: bool,
+    pub target: ProjectMemoryTarget,
+    /// Size budget for the learnings section; the oldest entries are dropped first.
+    pub max_bytes: usize,
+}
+
+impl Default for ProjectMemoryConfig {
+    fn default() -> Self {
+        Self {
+            auto: false,
+            target: ProjectMemoryTarget::default(),
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProjectMemoryFile {
+    path: PathBuf,
+    max_bytes: usize,
+}
+
+impl ProjectMemoryFile {
+    pub fn new(cwd: &Path, config: &ProjectMemoryConfig) -> Self {
+        let path = match config.target {
+            ProjectMemoryTarget::AgentsMd => cwd.join("AGENTS.md"),
+            ProjectMemoryTarget::PixyMemory => cwd.join(PROJECT_MEMORY_FILE),
+        };
+        Self {
+            path,
+            max_bytes: config.max_bytes.max(1),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Learnings currently recorded in the managed section.
+    pub fn learnings(&self) -> Vec<String> {
+        let content = std::fs::read_to_string(&self.path).unwrap_or_default();
+        split_section(&content)
+            .map(|(_, section, _)| parse_learnings(section))
+            .unwrap_or_default()
+    }
+
+    /// Appends learnings that are not already recorded, then trims the section to the size
+    /// budget. Returns the learnings that were added.
+    pub fn append(&self, learnings: &[String]) -> Result<Vec<String>, String> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(format!("read {} failed: {error}", self.path.display())),
+        };
+
+        let (before, mut entries, after) = match split_section(&content) {
+            Some((before, section, after)) => (
+                before.to_string(),
+                parse_learnings(section),
+                after.to_string(),
+            ),
+            None => {
+                let mut before = content.trim_end().to_string();
+                if !before.is_empty() {
+                    before.push_str("\n\n");
+                }
+                before.push_str(SECTION_HEADING);
+                before.push_str("\n\n");
+                (before, Vec::new(), "\n".to_string())
+            }
+        };
+
+        let mut added = Vec::new();
+        for learning in learnings {
+            let learning = learning.trim();
+            if learning.is_empty() || learning.len() > self.max_bytes {
+                continue;
+            }
+            let key = dedup_key(learning);
+            if entries.iter().any(|existing| dedup_key(existing) == key) {
+                continue;
+            }
+            entries.push(learning.to_string());
+            added.push(learning.to_string());
+        }
+        if added.is_empty() {
+            return Ok(added);
+        }
+
+        while entries.iter().map(|entry| entry.len() + 3).sum::<usize>() > self.max_bytes {
+            let removed = entries.remove(0);
+            added.retain(|entry| entry != &removed);
+        }
+
+        let mut section = String::new();
+        section.push_str(SECTION_START);
+        section.push('\n');
+        for entry in &entries {
+            section.push_str("- ");
+            section.push_str(entry);
+            section.push('\n');
+        }
+        section.push_str(SECTION_END);
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|error| format!("create {} failed: {error}", parent.display()))?;
+        }
+        std::fs::write(&self.path, format!("{before}{section}{after}"))
+            .map_err(|error| format!("write {} failed: {error}", self.path.display()))?;
+        Ok(added)
+    }
+}
+
+/// Extracts `- ` / `* ` bullet lines from model output or a learnings section.
+pub(crate) fn parse_learnings(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            line.strip_prefix("- ")
+                .or_else(|| line.strip_prefix("* "))
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(ToOwned::to_owned)
+        })
+        .collect()
+}
+
+fn split_section(content: &str) -> Option<(&str, &str, &str)> {
+    let start = content.find(SECTION_START)?;
+    let section_start = start + SECTION_START.len();
+    let end = section_start + content[section_start..].find(SECTION_END)?;
+    Some((
+        &content[..start],
+        &content[section_start..end],
+        &content[end + SECTION_END.len()..],
+    ))
+}
+
+fn dedup_key(entry: &str) -> String {
+    entry
+        .to_lowercase()
+        .split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn memory_file(cwd: &Path, max_bytes: usize) -> ProjectMemoryFile {
+        ProjectMemoryFile::new(
+            cwd,
+            &ProjectMemoryConfig {
+                auto: false,
+                target: ProjectMemoryTarget::AgentsMd,
+                max_bytes,
+            },
+        )
+    }
+
+    #[test]
+    fn append_creates_section_and_preserves_existing_content() {
+        let dir = tempdir().expect("tempdir");
+        std::fs::write(dir.path().join("AGENTS.md"), "# Guide\n\nBe nice.\n").expect("seed");
+        let file = memory_file(dir.path(), 8_000);
+
+        let added = file
+            .append(&["Run `cargo nextest run` for tests.".to_string()])
+            .expect("append");
+        assert_eq!(added.len(), 1);
+
+        let content = std::fs::read_to_string(file.path()).expect("read");
+        assert!(content.starts_with("# Guide\n\nBe nice.\n\n## Project Learnings\n"));
+        assert!(content.contains("- Run `cargo nextest run` for tests.\n"));
+        assert_eq!(file.learnings().len(), 1);
+    }
+
+    #[test]
+    fn append_skips_duplicates_and_drops_oldest_over_budget() {
+        let dir = tempdir().expect("tempdir");
+        let file = memory_file(dir.path(), 60);
+
+        file.append(&["First fact about the build.".to_string()])
+            .expect("append first");
+        let added = file
+            .append(&[
+                "first fact about the BUILD".to_string(),
+                "Second fact about the tests.".to_string(),
+                "Third fact about the docs.".to_string(),
+            ])
+            .expect("append more");
+
+        assert_eq!(
+            file.learnings(),
+            vec![
+                "Second fact about the tests.".to_string(),
+                "Third fact about the docs.".to_string()
+            ]
+        );
+        assert_eq!(added, file.learnings());
+    }
+
+    #[test]
+    fn parse_learnings_reads_bullets_only() {
+        assert_eq!(
+            parse_learnings("Here you go:\n- one\n* two\nNONE\n-   \n"),
+            vec!["one".to_string(), "two".to_string()]
+        );
+    }
+}
@@ -2,16 +2,27 @@ use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use pixy_ai::{Cost, Model, DEFAULT_TRANSPORT_RETRY_COUNT};
+use pixy_agent_core::ToolLoopGuard;
+use pixy_ai::{
+    Cost, EmbeddingModel, HttpTransport, Model, ModelCatalog, ModelSource, RateLimit,
+    StreamTransport, DEFAULT_TRANSPORT_RETRY_COUNT,
+};
 use serde::Deserialize;
 
+use crate::config_layers::LayeredConfig;
 use crate::multi_agent::resolve_subagent_model_target;
 use crate::{
-    load_skills, DeclarativeHookSpec, LoadSkillsOptions, Skill, SkillDiagnostic, SubAgentMode,
-    SubAgentPromptMetadata, SubAgentSpec,
+    load_skills, DeclarativeHookSpec, DiffReviewConfig, GuardConfig, HttpTransportConfig,
+    LifecycleHookSpec, LoadSkillsOptions, McpConfig, OutputLimits, PostEditCommand,
+    ProjectMemoryConfig, ProjectMemoryTarget, RedactionConfig, SamplingConfig, Skill,
+    SkillDiagnostic, SubAgentMode, SubAgentPromptMetadata, SubAgentSpec, TelemetryConfig,
+    ToolApprovalConfig, ToolFailureConfig, ToolFailureOutput, ToolOutputConfig, WebSearchConfig,
+    WorktreeConfig,
 };
 
 const DEFAULT_PIXY_HOME_DIR_NAME: &str = ".pixy";
+/// Tool calls of one turn run one after another unless `tool_parallelism` allows more.
+const DEFAULT_TOOL_PARALLELISM: usize = 1;
 
 #[derive(Debug, Clone, Default)]
 pub struct RuntimeOverrides {
@@ -24,6 +35,10 @@ pub struct RuntimeOverrides {
     pub base_url: Option<String>,
     pub context_window: Option<u32>,
     pub max_tokens: Option<u32>,
+    pub temperature: Option<f64>,
+    pub seed: Option<u64>,
+    /// Proxy and TLS flags, taking precedence over `[transport]` for every provider.
+    pub http_transport: HttpTransport,
 }
 
 impl RuntimeOverrides {
@@ -35,6 +50,28 @@ impl RuntimeOverrides {
             ..Self::default()
         }
     }
+
+    /// `configured` with the sampling flags given on the command line.
+    fn sampling(&self, configured: &SamplingConfig) -> SamplingConfig {
+        SamplingConfig {
+            temperature: self.temperature.or(configured.temperature),
+            seed: self.seed.or(configured.seed),
+            ..configured.clone()
+        }
+    }
+
+    /// `configured` with the proxy and TLS flags given on the command line.
+    fn http_transport(&self, configured: &HttpTransportConfig) -> HttpTransportConfig {
+        let flags = &self.http_transport;
+        HttpTransportConfig {
+            default: flags.or(&configured.default),
+            providers: configured
+                .providers
+                .iter()
+                .map(|(provider, transport)| (provider.clone(), flags.or(transport)))
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -87,18 +124,12 @@ impl RuntimeLoadOptions {
         router_seed: u64,
     ) -> Result<ResolvedRuntime, String> {
         let conf_dir = pixy_home_dir(self.conf_dir.as_deref());
-        let config_path = conf_dir.join("pixy.toml");
-        let content = if config_path.exists() {
-            std::fs::read_to_string(&config_path)
-                .map_err(|error| format!("read {} failed: {error}", config_path.display()))?
-        } else {
-            String::new()
-        };
-        let mut local = load_agent_local_config_from_toml_with_base_dir(&content, &conf_dir)?;
+        let layered = LayeredConfig::load(&conf_dir, cwd)?;
+        let mut local = load_agent_local_config_from_table_with_base_dir(layered.table, &conf_dir)?;
         let runtime = RuntimeConfigResolver::new(&self.overrides, &local, router_seed)
             .resolve_runtime_config_with_seed()?;
 
-        let (skills, skill_diagnostics) = if self.load_skills {
+        let (skills, skill_diagnostics, skill_options) = if self.load_skills {
             let agent_dir = self
                 .agent_dir
                 .clone()
@@ -107,10 +138,10 @@ impl RuntimeLoadOptions {
             load_options.include_defaults = self.include_default_skills;
             load_options.skill_paths = local.settings.skills.clone();
             load_options.skill_paths.extend(self.skill_paths.clone());
-            let loaded = load_skills(load_options);
-            (loaded.skills, loaded.diagnostics)
+            let loaded = load_skills(load_options.clone());
+            (loaded.skills, loaded.diagnostics, Some(load_options))
         } else {
-            (vec![], vec![])
+            (vec![], vec![], None)
         };
 
         Ok(ResolvedRuntime {
@@ -125,13 +156,40 @@ impl RuntimeLoadOptions {
                 hooks: local.multi_agent.hooks.clone(),
             },
             memory: local.memory.clone(),
+            hooks: local.settings.hooks.clone(),
+            post_edit: local.settings.post_edit.clone(),
+            project_memory: local.settings.project_memory.clone(),
+            redaction: local.settings.redaction.clone(),
+            guards: local.settings.guards.clone(),
+            sampling: self.overrides.sampling(&local.settings.sampling),
+            stream_transport: local.settings.stream_transport.clone(),
+            http_transport: self
+                .overrides
+                .http_transport(&local.settings.http_transport),
+            rate_limits: local.settings.rate_limits.clone(),
+            tool_failures: local.settings.tool_failures.clone(),
+            tool_output: local.settings.tool_output.clone(),
+            telemetry: local.settings.telemetry.clone(),
+            worktree: local.settings.worktree.clone(),
+            review: local.settings.review.clone(),
+            approval: local.settings.approval.clone(),
+            loop_guard: local.settings.loop_guard.clone(),
+            mcp: local.settings.mcp.clone(),
+            web_search: local.settings.web_search.clone(),
             skills,
             skill_diagnostics,
+            skill_options,
             theme: local.settings.theme.take(),
             transport_retry_count: local
                 .settings
                 .transport_retry_count
                 .unwrap_or(DEFAULT_TRANSPORT_RETRY_COUNT),
+            tool_parallelism: local
+                .settings
+                .tool_parallelism
+                .unwrap_or(DEFAULT_TOOL_PARALLELISM)
+                .max(1),
+            discover_models: local.settings.discover_models,
         })
     }
 
@@ -145,7 +203,7 @@ impl RuntimeLoadOptions {
         let runtime = RuntimeConfigResolver::new(&self.overrides, &local, router_seed)
             .resolve_runtime_config_with_seed()?;
 
-        let (skills, skill_diagnostics) = if self.load_skills {
+        let (skills, skill_diagnostics, skill_options) = if self.load_skills {
             let conf_dir = pixy_home_dir(self.conf_dir.as_deref());
             let agent_dir = self
                 .agent_dir
@@ -155,10 +213,10 @@ impl RuntimeLoadOptions {
             load_options.include_defaults = self.include_default_skills;
             load_options.skill_paths = local.settings.skills.clone();
             load_options.skill_paths.extend(self.skill_paths.clone());
-            let loaded = load_skills(load_options);
-            (loaded.skills, loaded.diagnostics)
+            let loaded = load_skills(load_options.clone());
+            (loaded.skills, loaded.diagnostics, Some(load_options))
         } else {
-            (vec![], vec![])
+            (vec![], vec![], None)
         };
 
         Ok(ResolvedRuntime {
@@ -173,13 +231,40 @@ impl RuntimeLoadOptions {
                 hooks: local.multi_agent.hooks.clone(),
             },
             memory: local.memory.clone(),
+            hooks: local.settings.hooks.clone(),
+            post_edit: local.settings.post_edit.clone(),
+            project_memory: local.settings.project_memory.clone(),
+            redaction: local.settings.redaction.clone(),
+            guards: local.settings.guards.clone(),
+            sampling: self.overrides.sampling(&local.settings.sampling),
+            stream_transport: local.settings.stream_transport.clone(),
+            http_transport: self
+                .overrides
+                .http_transport(&local.settings.http_transport),
+            rate_limits: local.settings.rate_limits.clone(),
+            tool_failures: local.settings.tool_failures.clone(),
+            tool_output: local.settings.tool_output.clone(),
+            telemetry: local.settings.telemetry.clone(),
+            worktree: local.settings.worktree.clone(),
+            review: local.settings.review.clone(),
+            approval: local.settings.approval.clone(),
+            loop_guard: local.settings.loop_guard.clone(),
+            mcp: local.settings.mcp.clone(),
+            web_search: local.settings.web_search.clone(),
             skills,
             skill_diagnostics,
+            skill_options,
             theme: local.settings.theme.take(),
             transport_retry_count: local
                 .settings
                 .transport_retry_count
                 .unwrap_or(DEFAULT_TRANSPORT_RETRY_COUNT),
+            tool_parallelism: local
+                .settings
+                .tool_parallelism
+                .unwrap_or(DEFAULT_TOOL_PARALLELISM)
+                .max(1),
+            discover_models: local.settings.discover_models,
         })
     }
 }
@@ -192,10 +277,91 @@ pub struct ResolvedRuntime {
     pub provider_api_keys: HashMap<String, String>,
     pub multi_agent: ResolvedMultiAgentConfig,
     pub memory: ResolvedMemoryConfig,
+    pub hooks: Vec<LifecycleHookSpec>,
+    pub post_edit: Vec<PostEditCommand>,
+    pub project_memory: ProjectMemoryConfig,
+    pub redaction: RedactionConfig,
+    pub guards: GuardConfig,
+    pub sampling: SamplingConfig,
+    /// Timeouts and resumption for response streams, from `[transport]`.
+    pub stream_transport: StreamTransport,
+    /// Proxy and TLS settings for provider requests; see [`HttpTransportConfig::apply`].
+    pub http_transport: HttpTransportConfig,
+    /// Client-side request limits keyed by provider or `provider/model`, from `[rate_limits]`.
+    pub rate_limits: HashMap<String, RateLimit>,
+    pub tool_failures: ToolFailureConfig,
+    pub tool_output: ToolOutputConfig,
+    pub telemetry: TelemetryConfig,
+    pub worktree: WorktreeConfig,
+    pub review: DiffReviewConfig,
+    pub approval: ToolApprovalConfig,
+    /// Limits on tool iterations and repeated calls per run, from `[loop_guard]`.
+    pub loop_guard: ToolLoopGuard,
+    /// Servers whose tools sessions add, from `[mcp.servers.<name>]`.
+    pub mcp: McpConfig,
+    /// Backend of the `web_search` tool, from `[web_search]` or the environment.
+    pub web_search: WebSearchConfig,
     pub skills: Vec<Skill>,
     pub skill_diagnostics: Vec<SkillDiagnostic>,
+    /// Options the skills were loaded with, kept so sessions can reload them.
+    pub skill_options: Option<LoadSkillsOptions>,
     pub theme: Option<String>,
     pub transport_retry_count: usize,
+    /// Tool calls of one turn that may run at once, from `tool_parallelism`. Calls on the same
+    /// file or shell still run in order.
+    pub tool_parallelism: usize,
+    /// Whether interactive sessions add the models providers list to `model_catalog`.
+    pub discover_models: bool,
+}
+
+impl ResolvedRuntime {
+    /// One listing source per configured provider endpoint in the catalog.
+    pub fn model_sources(&self) -> Vec<ModelSource> {
+        let mut sources: Vec<ModelSource> = Vec::new();
+        for model in &self.model_catalog {
+            if sources.iter().any(|source| {
+                source.provider == model.provider
+                    && source.api == model.api
+                    && source.base_url == model.base_url
+            }) {
+                continue;
+            }
+            let api_key = self
+                .provider_api_keys
+                .get(&model.provider)
+                .cloned()
+                .or_else(|| {
+                    (model.provider == self.model.provider)
+                        .then(|| self.api_key.clone())
+                        .flatten()
+                });
+            sources.push(ModelSource {
+                provider: model.provider.clone(),
+                api: model.api.clone(),
+                base_url: model.base_url.clone(),
+                api_key,
+            });
+        }
+        sources
+    }
+
+    /// Adds every model the configured providers list to `model_catalog`, after the configured
+    /// ones. Returns a warning for each provider that could not be listed.
+    pub async fn discover_models(&mut self) -> Vec<String> {
+        let sources = self.model_sources();
+        let mut catalog = ModelCatalog::new(std::mem::take(&mut self.model_catalog));
+        let failures = catalog.refresh(&sources).await;
+        self.model_catalog = catalog.into_models();
+        failures
+            .into_iter()
+            .map(|(provider, error)| {
+                format!(
+                    "failed to list models for provider '{provider}': {}",
+                    error.message
+                )
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
@@ -211,6 +377,14 @@ pub struct ResolvedMemorySearchConfig {
     pub enabled: bool,
     pub max_results: usize,
     pub min_score: f32,
+    /// Embedding model used for semantic search; keyword search is used when unset.
+    pub embedding: Option<ResolvedMemoryEmbeddingConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedMemoryEmbeddingConfig {
+    pub model: EmbeddingModel,
+    pub api_key: Option<String>,
 }
 
 impl Default for ResolvedMemorySearchConfig {
@@ -219,6 +393,7 @@ impl Default for ResolvedMemorySearchConfig {
             enabled: true,
             max_results: 10,
             min_score: 0.1,
+            embedding: None,
         }
     }
 }
@@ -251,9 +426,29 @@ impl Default for ResolvedMemoryConfig {
 #[derive(Debug, Clone, Default)]
 struct AgentSettingsFile {
     default_provider: Option<String>,
+    discover_models: bool,
     theme: Option<String>,
     transport_retry_count: Option<usize>,
+    tool_parallelism: Option<usize>,
     skills: Vec<String>,
+    hooks: Vec<LifecycleHookSpec>,
+    post_edit: Vec<PostEditCommand>,
+    project_memory: ProjectMemoryConfig,
+    redaction: RedactionConfig,
+    guards: GuardConfig,
+    sampling: SamplingConfig,
+    stream_transport: StreamTransport,
+    http_transport: HttpTransportConfig,
+    rate_limits: HashMap<String, RateLimit>,
+    tool_failures: ToolFailureConfig,
+    tool_output: ToolOutputConfig,
+    telemetry: TelemetryConfig,
+    worktree: WorktreeConfig,
+    review: DiffReviewConfig,
+    approval: ToolApprovalConfig,
+    loop_guard: ToolLoopGuard,
+    mcp: McpConfig,
+    web_search: WebSearchConfig,
     env: HashMap<String, String>,
 }
 
@@ -366,6 +561,12 @@ struct ProviderModelConfig {
     context_window: Option<u32>,
     #[serde(rename = "maxTokens")]
     max_tokens: Option<u32>,
+    #[serde(default)]
+    deployment: Option<String>,
+    #[serde(rename = "apiVersion", default)]
+    api_version: Option<String>,
+    #[serde(default)]
+    compat: Option<pixy_ai::OpenAICompletionsCompat>,
 }
 
 #[derive(Debug, Clone, Default, Deserialize)]
@@ -380,16 +581,190 @@ struct PixyTomlFile {
     #[serde(default)]
     transport_retry_count: Option<usize>,
     #[serde(default)]
+    tool_parallelism: Option<usize>,
+    #[serde(default)]
     skills: Vec<String>,
     #[serde(default)]
+    hooks: Vec<LifecycleHookSpec>,
+    #[serde(default)]
+    post_edit: Vec<PostEditCommand>,
+    #[serde(default)]
+    project_memory: PixyTomlProjectMemory,
+    #[serde(default)]
+    redaction: PixyTomlRedaction,
+    #[serde(default)]
+    guards: GuardConfig,
+    #[serde(default)]
+    sampling: SamplingConfig,
+    #[serde(default)]
+    transport: PixyTomlTransport,
+    #[serde(default)]
+    rate_limits: HashMap<String, PixyTomlRateLimit>,
+    #[serde(default)]
+    tool_failures: PixyTomlToolFailures,
+    #[serde(default)]
+    tool_output: PixyTomlToolOutput,
+    #[serde(default)]
+    telemetry: PixyTomlTelemetry,
+    #[serde(default)]
+    worktree: WorktreeConfig,
+    #[serde(default)]
+    review: DiffReviewConfig,
+    #[serde(default)]
+    approval: ToolApprovalConfig,
+    #[serde(default)]
+    loop_guard: ToolLoopGuard,
+    #[serde(default)]
+    mcp: McpConfig,
+    #[serde(default)]
+    web_search: WebSearchConfig,
+    #[serde(default)]
     env: HashMap<String, String>,
 }
 
+#[derive(Debug, Clone, Default, Deserialize)]
+struct PixyTomlProjectMemory {
+    #[serde(default)]
+    auto: bool,
+    #[serde(default)]
+    target: ProjectMemoryTarget,
+    #[serde(default)]
+    max_bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct PixyTomlTransport {
+    #[serde(default)]
+    idle_timeout_ms: Option<u64>,
+    #[serde(default)]
+    total_timeout_ms: Option<u64>,
+    #[serde(default)]
+    max_resumes: Option<usize>,
+    #[serde(flatten)]
+    http: PixyTomlHttpTransport,
+    #[serde(default)]
+    providers: HashMap<String, PixyTomlHttpTransport>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct PixyTomlHttpTransport {
+    #[serde(default)]
+    proxy: Option<String>,
+    #[serde(default)]
+    no_proxy: Vec<String>,
+    #[serde(default)]
+    ca_bundle: Option<String>,
+    #[serde(default)]
+    client_cert: Option<String>,
+    #[serde(default)]
+    client_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct PixyTomlRateLimit {
+    #[serde(default)]
+    requests_per_minute: Option<f64>,
+    #[serde(default)]
+    burst: Option<u32>,
+    #[serde(default)]
+    max_concurrent: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct PixyTomlTelemetry {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    endpoint: Option<String>,
+    #[serde(default)]
+    service_name: Option<String>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct PixyTomlToolOutput {
+    #[serde(flatten)]
+    limits: PixyTomlOutputLimits,
+    #[serde(default)]
+    spill: Option<bool>,
+    #[serde(default)]
+    tools: BTreeMap<String, PixyTomlOutputLimits>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+struct PixyTomlOutputLimits {
+    #[serde(default)]
+    max_lines: Option<usize>,
+    #[serde(default)]
+    max_bytes: Option<usize>,
+    #[serde(default)]
+    head_lines: Option<usize>,
+}
+
+impl PixyTomlOutputLimits {
+    fn merge_into(self, base: OutputLimits) -> OutputLimits {
+        OutputLimits {
+            max_lines: self.max_lines.unwrap_or(base.max_lines),
+            max_bytes: self.max_bytes.unwrap_or(base.max_bytes),
+            head_lines: self.head_lines.unwrap_or(base.head_lines),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PixyTomlToolFailures {
+    #[serde(default)]
+    output: ToolFailureOutput,
+    #[serde(default = "default_tool_failures_exit_code")]
+    include_exit_code: bool,
+    #[serde(default)]
+    include_next_step: bool,
+    #[serde(default)]
+    max_retries: Option<usize>,
+}
+
+impl Default for PixyTomlToolFailures {
+    fn default() -> Self {
+        Self {
+            output: ToolFailureOutput::default(),
+            include_exit_code: default_tool_failures_exit_code(),
+            include_next_step: false,
+            max_retries: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PixyTomlRedaction {
+    #[serde(default = "default_redaction_enabled")]
+    enabled: bool,
+    #[serde(default = "default_redaction_enabled")]
+    entropy: bool,
+    #[serde(default)]
+    patterns: Vec<String>,
+    #[serde(default)]
+    audit_log: Option<String>,
+}
+
+impl Default for PixyTomlRedaction {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            entropy: true,
+            patterns: vec![],
+            audit_log: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, Deserialize)]
 struct PixyTomlLlm {
     #[serde(default)]
     default_provider: Option<String>,
     #[serde(default)]
+    discover_models: bool,
+    #[serde(default)]
     providers: Vec<PixyTomlProvider>,
 }
 
@@ -443,6 +818,9 @@ struct PixyTomlMemorySearch {
     max_results: usize,
     #[serde(default = "default_memory_search_min_score")]
     min_score: f32,
+    /// Name of an `[[llm.providers]]` entry with `kind = "embedding"`.
+    #[serde(default)]
+    embedding_provider: Option<String>,
 }
 
 impl Default for PixyTomlMemorySearch {
@@ -451,6 +829,7 @@ impl Default for PixyTomlMemorySearch {
             enabled: default_memory_search_enabled(),
             max_results: default_memory_search_max_results(),
             min_score: default_memory_search_min_score(),
+            embedding_provider: None,
         }
     }
 }
@@ -512,6 +891,24 @@ struct PixyTomlProvider {
     context_window: Option<u32>,
     #[serde(default)]
     max_tokens: Option<u32>,
+    #[serde(default)]
+    deployment: Option<String>,
+    #[serde(default)]
+    api_version: Option<String>,
+    #[serde(default)]
+    compat: Option<PixyTomlOpenAICompat>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct PixyTomlOpenAICompat {
+    #[serde(default)]
+    stream_options: Option<bool>,
+    #[serde(default)]
+    tool_choice: Option<bool>,
+    #[serde(default)]
+    max_tokens_field: Option<String>,
+    #[serde(default)]
+    stop_field: Option<String>,
 }
 
 fn default_provider_weight() -> u8 {
@@ -522,6 +919,14 @@ fn default_memory_auto_flush() -> bool {
     true
 }
 
+fn default_redaction_enabled() -> bool {
+    true
+}
+
+fn default_tool_failures_exit_code() -> bool {
+    true
+}
+
 fn default_memory_file_pattern() -> String {
     "%Y-%m-%d.md".to_string()
 }
@@ -551,6 +956,16 @@ fn load_agent_local_config_from_toml_with_base_dir(
     Ok(convert_pixy_toml_to_local_config(config, base_dir))
 }
 
+fn load_agent_local_config_from_table_with_base_dir(
+    table: toml::Table,
+    base_dir: &Path,
+) -> Result<AgentLocalConfig, String> {
+    let config = toml::Value::Table(table)
+        .try_into::<PixyTomlFile>()
+        .map_err(|error| format!("parse merged config failed: {error}"))?;
+    Ok(convert_pixy_toml_to_local_config(config, base_dir))
+}
+
 fn convert_pixy_toml_to_local_config(config: PixyTomlFile, base_dir: &Path) -> AgentLocalConfig {
     let env_map = config.env.clone();
     let mut providers = HashMap::new();
@@ -573,6 +988,9 @@ fn convert_pixy_toml_to_local_config(config: PixyTomlFile, base_dir: &Path) -> A
                     reasoning_effort: provider.reasoning_effort.clone(),
                     context_window: provider.context_window,
                     max_tokens: provider.max_tokens,
+                    deployment: provider.deployment.clone(),
+                    api_version: provider.api_version.clone(),
+                    compat: provider.compat.as_ref().map(resolve_openai_compat),
                 });
 
         let provider_config = ProviderConfig {
@@ -660,15 +1078,104 @@ fn convert_pixy_toml_to_local_config(config: PixyTomlFile, base_dir: &Path) -> A
             enabled: config.memory.search.enabled,
             max_results: config.memory.search.max_results.max(1),
             min_score: config.memory.search.min_score.clamp(0.0, 1.0),
+            embedding: config
+                .memory
+                .search
+                .embedding_provider
+                .as_deref()
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .and_then(|name| resolve_memory_embedding_config(name, &providers, &env_map)),
         },
     };
 
     AgentLocalConfig {
         settings: AgentSettingsFile {
             default_provider: config.llm.default_provider,
+            discover_models: config.llm.discover_models,
             theme: config.theme,
             transport_retry_count: config.transport_retry_count,
+            tool_parallelism: config.tool_parallelism,
             skills: config.skills,
+            hooks: config.hooks,
+            post_edit: config.post_edit,
+            project_memory: ProjectMemoryConfig {
+                auto: config.project_memory.auto,
+                target: config.project_memory.target,
+                max_bytes: config
+                    .project_memory
+                    .max_bytes
+                    .unwrap_or(ProjectMemoryConfig::default().max_bytes),
+            },
+            redaction: RedactionConfig {
+                enabled: config.redaction.enabled,
+                entropy: config.redaction.entropy,
+                patterns: config.redaction.patterns,
+                audit_log: Some(
+                    config
+                        .redaction
+                        .audit_log
+                        .as_deref()
+                        .map(str::trim)
+                        .filter(|path| !path.is_empty())
+                        .map(|path| base_dir.join(path))
+                        .unwrap_or_else(|| base_dir.join("redactions.jsonl")),
+                ),
+            },
+            guards: config.guards,
+            sampling: config.sampling,
+            stream_transport: StreamTransport {
+                idle_timeout_ms: config.transport.idle_timeout_ms,
+                total_timeout_ms: config.transport.total_timeout_ms,
+                max_resumes: config
+                    .transport
+                    .max_resumes
+                    .unwrap_or(StreamTransport::default().max_resumes),
+            },
+            http_transport: HttpTransportConfig {
+                default: resolve_http_transport(&config.transport.http, base_dir, &env_map),
+                providers: config
+                    .transport
+                    .providers
+                    .iter()
+                    .map(|(provider, transport)| {
+                        (
+                            provider.trim().to_string(),
+                            resolve_http_transport(transport, base_dir, &env_map),
+                        )
+                    })
+                    .filter(|(provider, _)| !provider.is_empty())
+                    .collect(),
+            },
+            rate_limits: config
+                .rate_limits
+                .into_iter()
+                .map(|(key, limit)| {
+                    (
+                        key.trim().to_string(),
+                        RateLimit {
+                            requests_per_minute: limit.requests_per_minute,
+                            burst: limit.burst,
+                            max_concurrent: limit.max_concurrent,
+                        },
+                    )
+                })
+                .filter(|(key, _)| !key.is_empty())
+                .collect(),
+            tool_failures: ToolFailureConfig {
+                output: config.tool_failures.output,
+                include_exit_code: config.tool_failures.include_exit_code,
+                include_next_step: config.tool_failures.include_next_step,
+                max_retries: config.tool_failures.max_retries,
+            },
+            tool_output: resolve_tool_output_config(config.tool_output),
+            telemetry: resolve_telemetry_config(config.telemetry, &env_map),
+            worktree: config.worktree,
+            review: config.review,
+            approval: config.approval,
+            loop_guard: config.loop_guard,
+            mcp: resolve_mcp_config(config.mcp, &env_map),
+            web_search: resolve_web_search_config(config.web_search, &env_map),
             env: env_map,
         },
         models: ModelsFile { providers },
@@ -682,6 +1189,129 @@ fn convert_pixy_toml_to_local_config(config: PixyTomlFile, base_dir: &Path) -> A
     }
 }
 
+/// Per-tool limits fall back to the `[tool_output]` values for keys they leave out.
+fn resolve_tool_output_config(tool_output: PixyTomlToolOutput) -> ToolOutputConfig {
+    let defaults = ToolOutputConfig::default();
+    let limits = tool_output.limits.merge_into(defaults.limits);
+    ToolOutputConfig {
+        limits,
+        tools: tool_output
+            .tools
+            .into_iter()
+            .map(|(name, overrides)| (name, overrides.merge_into(limits)))
+            .collect(),
+        spill: tool_output.spill.unwrap_or(defaults.spill),
+    }
+}
+
+fn resolve_telemetry_config(
+    telemetry: PixyTomlTelemetry,
+    env_map: &HashMap<String, String>,
+) -> TelemetryConfig {
+    let defaults = TelemetryConfig::default();
+    TelemetryConfig {
+        enabled: telemetry.enabled,
+        endpoint: telemetry
+            .endpoint
+            .as_deref()
+            .and_then(|endpoint| resolve_config_value(endpoint, env_map))
+            .or_else(|| resolve_config_value("$OTEL_EXPORTER_OTLP_ENDPOINT", env_map))
+            .unwrap_or(defaults.endpoint),
+        service_name: telemetry
+            .service_name
+            .as_deref()
+            .and_then(|name| resolve_config_value(name, env_map))
+            .unwrap_or(defaults.service_name),
+        headers: telemetry
+            .headers
+            .into_iter()
+            .filter_map(|(name, value)| {
+                resolve_config_value(&value, env_map).map(|value| (name, value))
+            })
+            .collect(),
+    }
+}
+
+/// `config` with `$NAME` environment and header values resolved; unresolved ones are dropped.
+fn resolve_mcp_config(mut config: McpConfig, env_map: &HashMap<String, String>) -> McpConfig {
+    for server in config.servers.values_mut() {
+        for values in [&mut server.env, &mut server.headers] {
+            *values = std::mem::take(values)
+                .into_iter()
+                .filter_map(|(name, value)| {
+                    resolve_config_value(&value, env_map).map(|value| (name, value))
+                })
+                .collect();
+        }
+    }
+    config
+}
+
+/// `config` with `$NAME` values resolved; without a `backend` it is taken from the environment.
+fn resolve_web_search_config(
+    config: WebSearchConfig,
+    env_map: &HashMap<String, String>,
+) -> WebSearchConfig {
+    if config.backend.is_none() {
+        return WebSearchConfig::from_vars(|name| {
+            resolve_config_value(&format!("${name}"), env_map)
+        })
+        .unwrap_or_default();
+    }
+    WebSearchConfig {
+        url: config
+            .url
+            .as_deref()
+            .and_then(|url| resolve_config_value(url, env_map)),
+        api_key: config
+            .api_key
+            .as_deref()
+            .and_then(|key| resolve_config_value(key, env_map)),
+        ..config
+    }
+}
+
+fn resolve_memory_embedding_config(
+    provider_key: &str,
+    providers: &HashMap<String, ProviderConfig>,
+    env_map: &HashMap<String, String>,
+) -> Option<ResolvedMemoryEmbeddingConfig> {
+    let Some(provider_config) = providers.get(provider_key) else {
+        eprintln!(
+            "warning: memory.search.embedding_provider '{provider_key}' is not a configured llm provider, using keyword search"
+        );
+        return None;
+    };
+    let Some(model_id) = provider_config.default_model.clone() else {
+        eprintln!(
+            "warning: embedding provider '{provider_key}' has no model, using keyword search"
+        );
+        return None;
+    };
+    let provider_name = resolve_provider_name(provider_key, Some(provider_config));
+    let base_url = provider_config
+        .base_url
+        .as_deref()
+        .and_then(|value| resolve_config_value(value, env_map))
+        .unwrap_or_else(|| "https://api.openai.com/v1".to_string());
+    let api_key = provider_config
+        .api_key
+        .as_ref()
+        .and_then(|value| resolve_config_value(value, env_map))
+        .or_else(|| infer_api_key_from_settings(provider_key, env_map))
+        .or_else(|| infer_api_key_from_settings(&provider_name, env_map));
+
+    Some(ResolvedMemoryEmbeddingConfig {
+        model: EmbeddingModel {
+            id: model_id,
+            provider: provider_name,
+            base_url,
+            dimensions: None,
+        },
+        api_key,
+    })
+}
+
 fn parse_pixy_toml_subagent(
     agent: PixyTomlSubAgent,
     key_name: Option<&str>,
@@ -703,9 +1333,7 @@ fn parse_pixy_toml_subagent(
         }
     }
 
-    let Some(name) = explicit_name.or(key_name) else {
-        return None;
-    };
+    let name = explicit_name.or(key_name)?;
     let description = agent
         .description
         .as_deref()
@@ -893,13 +1521,7 @@ impl<'a> RuntimeConfigResolver<'a> {
             .unwrap_or_else(|| default_reasoning_enabled_for_api(&api));
         let reasoning_effort = selected_model_cfg
             .and_then(|cfg| cfg.reasoning_effort.clone())
-            .or_else(|| {
-                if reasoning {
-                    Some(pixy_ai::ThinkingLevel::Medium)
-                } else {
-                    None
-                }
-            });
+            .or(reasoning.then_some(pixy_ai::ThinkingLevel::Medium));
 
         let model = Model {
             id: model_id.clone(),
@@ -919,6 +1541,9 @@ impl<'a> RuntimeConfigResolver<'a> {
             },
             context_window,
             max_tokens,
+            deployment: selected_model_cfg.and_then(|cfg| cfg.deployment.clone()),
+            api_version: selected_model_cfg.and_then(|cfg| cfg.api_version.clone()),
+            compat: selected_model_cfg.and_then(|cfg| cfg.compat.clone()),
         };
 
         let mut model_catalog = build_chat_model_catalog(self.local, self.overrides);
@@ -1006,7 +1631,7 @@ fn is_chat_provider(config: &ProviderConfig) -> bool {
         .kind
         .as_deref()
         .map(str::trim)
-        .map_or(true, |kind| kind.eq_ignore_ascii_case("chat"))
+        .is_none_or(|kind| kind.eq_ignore_ascii_case("chat"))
 }
 
 fn model_from_config(
@@ -1031,13 +1656,10 @@ fn model_from_config(
     let reasoning = config
         .reasoning
         .unwrap_or_else(|| default_reasoning_enabled_for_api(&api));
-    let reasoning_effort = config.reasoning_effort.clone().or_else(|| {
-        if reasoning {
-            Some(pixy_ai::ThinkingLevel::Medium)
-        } else {
-            None
-        }
-    });
+    let reasoning_effort = config
+        .reasoning_effort
+        .clone()
+        .or(reasoning.then_some(pixy_ai::ThinkingLevel::Medium));
 
     Model {
         id: config.id.clone(),
@@ -1057,6 +1679,9 @@ fn model_from_config(
         },
         context_window: config.context_window.unwrap_or(default_context_window),
         max_tokens: config.max_tokens.unwrap_or(default_max_tokens),
+        deployment: config.deployment.clone(),
+        api_version: config.api_version.clone(),
+        compat: config.compat.clone(),
     }
 }
 
@@ -1093,6 +1718,9 @@ fn model_from_defaults(
         },
         context_window,
         max_tokens,
+        deployment: None,
+        api_version: None,
+        compat: None,
     }
 }
 
@@ -1223,12 +1851,10 @@ fn split_provider_model(input: Option<&str>) -> (Option<String>, Option<String>)
 }
 
 fn first_non_empty<const N: usize>(candidates: [Option<String>; N]) -> Option<String> {
-    for candidate in candidates {
-        if let Some(value) = candidate {
-            let trimmed = value.trim();
-            if !trimmed.is_empty() {
-                return Some(trimmed.to_string());
-            }
+    for value in candidates.into_iter().flatten() {
+        let trimmed = value.trim();
+        if !trimmed.is_empty() {
+            return Some(trimmed.to_string());
         }
     }
     None
@@ -1248,6 +1874,11 @@ fn infer_api_for_provider(provider: &str) -> Option<String> {
         "bedrock" | "amazon-bedrock" | "bedrock-converse-stream" => {
             Some("bedrock-converse-stream".to_string())
         }
+        "ollama" => Some("ollama".to_string()),
+        "xai" | "grok" => Some("xai".to_string()),
+        "deepseek" => Some("deepseek".to_string()),
+        "openrouter" => Some("openrouter".to_string()),
+        "openai-compatible" | "vllm" | "tgi" | "lmstudio" => Some("openai-compatible".to_string()),
         _ => None,
     }
 }
@@ -1266,6 +1897,9 @@ fn default_model_for_provider(provider: &str) -> Option<String> {
         "bedrock" | "amazon-bedrock" | "bedrock-converse-stream" => {
             Some("anthropic.claude-3-5-sonnet-20241022-v2:0".to_string())
         }
+        "xai" | "grok" => Some("grok-4".to_string()),
+        "deepseek" => Some("deepseek-chat".to_string()),
+        "openrouter" => Some("openrouter/auto".to_string()),
         _ => None,
     }
 }
@@ -1279,6 +1913,12 @@ fn default_base_url_for_api(api: &str) -> Option<String> {
         "google-generative-ai" => {
             Some("https://generativelanguage.googleapis.com/v1beta".to_string())
         }
+        "ollama" => Some("http://localhost:11434".to_string()),
+        "xai" => Some("https://api.x.ai/v1".to_string()),
+        "deepseek" => Some("https://api.deepseek.com".to_string()),
+        "openrouter" => Some("https://openrouter.ai/api/v1".to_string()),
+        // vLLM's default port; TGI and LM Studio servers need `base_url` set.
+        "openai-compatible" => Some("http://localhost:8000/v1".to_string()),
         _ => None,
     }
 }
@@ -1297,6 +1937,10 @@ fn primary_env_key_for_provider(provider: &str) -> &'static str {
             "GOOGLE_API_KEY"
         }
         "bedrock" | "amazon-bedrock" | "bedrock-converse-stream" => "AWS_ACCESS_KEY_ID",
+        "xai" | "grok" => "XAI_API_KEY",
+        "deepseek" => "DEEPSEEK_API_KEY",
+        "openrouter" => "OPENROUTER_API_KEY",
+        "openai-compatible" | "vllm" | "tgi" | "lmstudio" => "OPENAI_COMPATIBLE_API_KEY",
         _ => "OPENAI_API_KEY",
     }
 }
@@ -1322,6 +1966,48 @@ fn infer_api_key_from_settings(
     .find(|value| !value.trim().is_empty())
 }
 
+/// Proxy URLs may reference the environment like API keys; certificate paths are relative to
+/// the config file.
+fn resolve_openai_compat(compat: &PixyTomlOpenAICompat) -> pixy_ai::OpenAICompletionsCompat {
+    let defaults = pixy_ai::OpenAICompletionsCompat::default();
+    pixy_ai::OpenAICompletionsCompat {
+        stream_options: compat.stream_options.unwrap_or(defaults.stream_options),
+        tool_choice: compat.tool_choice.unwrap_or(defaults.tool_choice),
+        max_tokens_field: compat
+            .max_tokens_field
+            .clone()
+            .unwrap_or(defaults.max_tokens_field),
+        stop_field: compat.stop_field.clone().unwrap_or(defaults.stop_field),
+    }
+}
+
+fn resolve_http_transport(
+    transport: &PixyTomlHttpTransport,
+    base_dir: &Path,
+    env_map: &HashMap<String, String>,
+) -> HttpTransport {
+    let resolve_path = |path: &Option<String>| {
+        path.as_deref()
+            .and_then(|path| resolve_config_value(path, env_map))
+            .map(|path| base_dir.join(path))
+    };
+    HttpTransport {
+        proxy: transport
+            .proxy
+            .as_deref()
+            .and_then(|proxy| resolve_config_value(proxy, env_map)),
+        no_proxy: transport
+            .no_proxy
+            .iter()
+            .map(|host| host.trim().to_string())
+            .filter(|host| !host.is_empty())
+            .collect(),
+        ca_bundle: resolve_path(&transport.ca_bundle),
+        client_cert: resolve_path(&transport.client_cert),
+        client_key: resolve_path(&transport.client_key),
+    }
+}
+
 fn resolve_config_value(value: &str, env_map: &HashMap<String, String>) -> Option<String> {
     let trimmed = value.trim();
     if trimmed.is_empty() {
@@ -1383,16 +2069,67 @@ fn home_dir() -> PathBuf {
 }
 
 #[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
 mod tests {
     use tempfile::tempdir;
 
     use super::*;
+    use crate::{WebSearchBackendKind, WorktreeExitAction};
+    use pixy_agent_core::ToolRisk;
+
+    #[test]
+    fn resolve_runtime_from_toml_lists_one_model_source_per_provider() {
+        let content = r#"
+[llm]
+default_provider = "openai"
+discover_models = true
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-completions"
+base_url = "https://api.openai.com/v1"
+api_key = "openai-key"
+model = "gpt-4o"
+
+[[llm.providers]]
+name = "local"
+kind = "chat"
+provider = "ollama"
+api = "ollama"
+base_url = "http://localhost:11434"
+model = "qwen3"
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        assert!(resolved.discover_models);
+        let sources = resolved.model_sources();
+        assert_eq!(sources.len(), 2);
+        let openai = sources
+            .iter()
+            .find(|source| source.provider == "openai")
+            .expect("openai source");
+        assert_eq!(openai.api, "openai-completions");
+        assert_eq!(openai.api_key.as_deref(), Some("openai-key"));
+        let local = sources
+            .iter()
+            .find(|source| source.provider == "local")
+            .expect("local source");
+        assert_eq!(local.base_url, "http://localhost:11434");
+    }
 
     #[test]
     fn resolve_runtime_from_toml_resolves_model_and_runtime_settings() {
         let content = r#"
 theme = "light"
 transport_retry_count = 7
+tool_parallelism = 3
 
 [llm]
 default_provider = "openai"
@@ -1418,6 +2155,7 @@ weight = 1
         assert_eq!(resolved.model.id, "gpt-5.3-codex");
         assert_eq!(resolved.api_key.as_deref(), Some("key"));
         assert_eq!(resolved.transport_retry_count, 7);
+        assert_eq!(resolved.tool_parallelism, 3);
         assert_eq!(resolved.theme.as_deref(), Some("light"));
         assert!(resolved.skills.is_empty());
     }
@@ -1766,10 +2504,27 @@ weight = 1
     }
 
     #[test]
-    fn wildcard_default_provider_uses_weights_for_chat_providers() {
+    fn resolve_runtime_from_toml_resolves_memory_embedding_provider() {
         let content = r#"
+[env]
+EMBED_KEY = "embed-secret"
+
+[memory]
+enabled = true
+
+[memory.search]
+embedding_provider = "embedder"
+
 [llm]
-default_provider = "*"
+default_provider = "openai"
+
+[[llm.providers]]
+name = "embedder"
+kind = "embedding"
+provider = "openai"
+base_url = "https://embeddings.example.com/v1"
+api_key = "$EMBED_KEY"
+model = "text-embedding-3-small"
 
 [[llm.providers]]
 name = "openai"
@@ -1777,7 +2532,785 @@ kind = "chat"
 provider = "openai"
 api = "openai-responses"
 base_url = "https://api.openai.com/v1"
-api_key = "openai"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        let embedding = resolved
+            .memory
+            .search
+            .embedding
+            .expect("embedding config should resolve");
+        assert_eq!(embedding.model.id, "text-embedding-3-small");
+        assert_eq!(embedding.model.provider, "openai");
+        assert_eq!(
+            embedding.model.base_url,
+            "https://embeddings.example.com/v1"
+        );
+        assert_eq!(embedding.api_key.as_deref(), Some("embed-secret"));
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_parses_lifecycle_hooks() {
+        let content = r#"
+[[hooks]]
+event = "before_tool"
+tool_name = "bash"
+command = "./check.sh"
+
+[[hooks]]
+event = "session_end"
+url = "http://127.0.0.1:9000/hook"
+timeout_ms = 500
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(resolved.hooks.len(), 2);
+        assert_eq!(
+            resolved.hooks[0].event,
+            crate::LifecycleHookEvent::BeforeTool
+        );
+        assert_eq!(resolved.hooks[0].tool_name.as_deref(), Some("bash"));
+        assert_eq!(resolved.hooks[0].command.as_deref(), Some("./check.sh"));
+        assert_eq!(
+            resolved.hooks[1].event,
+            crate::LifecycleHookEvent::SessionEnd
+        );
+        assert_eq!(resolved.hooks[1].timeout_ms, Some(500));
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_parses_post_edit_commands() {
+        let content = r#"
+[[post_edit]]
+command = "rustfmt --edition 2021"
+files = ["*.rs"]
+
+[[post_edit]]
+name = "ruff"
+command = "ruff check {file}"
+files = ["*.py", "scripts/*"]
+timeout_ms = 5000
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(
+            resolved.post_edit,
+            vec![
+                PostEditCommand {
+                    name: None,
+                    command: "rustfmt --edition 2021".to_string(),
+                    files: vec!["*.rs".to_string()],
+                    timeout_ms: None,
+                },
+                PostEditCommand {
+                    name: Some("ruff".to_string()),
+                    command: "ruff check {file}".to_string(),
+                    files: vec!["*.py".to_string(), "scripts/*".to_string()],
+                    timeout_ms: Some(5000),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_parses_worktree_and_review_settings() {
+        let content = r#"
+[worktree]
+enabled = true
+on_exit = "pr"
+
+[review]
+enabled = true
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(
+            resolved.worktree,
+            WorktreeConfig {
+                enabled: true,
+                on_exit: WorktreeExitAction::Pr,
+                ..WorktreeConfig::default()
+            }
+        );
+        assert!(resolved.review.enabled);
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_parses_tool_approval() {
+        let llm = r#"
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+
+        let content = format!("[approval.tools]\nbash = \"high\"\nwrite = \"Medium\"\n{llm}");
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), &content, 0)
+            .expect("runtime should resolve");
+        assert_eq!(
+            resolved.approval.tools,
+            HashMap::from([
+                ("bash".to_string(), ToolRisk::High),
+                ("write".to_string(), ToolRisk::Medium),
+            ])
+        );
+
+        let content = format!("[approval.tools]\nbash = \"severe\"\n{llm}");
+        let error = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), &content, 0)
+            .expect_err("unknown risk levels are rejected");
+        assert!(error.contains("must be low, medium or high"), "{error}");
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_parses_loop_guard() {
+        let content = r#"
+[loop_guard]
+max_tool_iterations = 40
+max_identical_turns = 3
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(
+            resolved.loop_guard,
+            ToolLoopGuard {
+                max_tool_iterations: Some(40),
+                max_identical_turns: Some(3),
+            }
+        );
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_parses_mcp_servers() {
+        let content = r#"
+[env]
+DOCS_TOKEN = "secret"
+
+[mcp.servers.github]
+command = "npx"
+args = ["-y", "@modelcontextprotocol/server-github"]
+env = { GITHUB_TOKEN = "$PIXY_TEST_UNSET_MCP_TOKEN", LOG_LEVEL = "warn" }
+
+[mcp.servers.docs]
+url = "https://docs.example.com/sse"
+headers = { Authorization = "$DOCS_TOKEN" }
+timeout_ms = 5000
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        let github = &resolved.mcp.servers["github"];
+        assert_eq!(github.command.as_deref(), Some("npx"));
+        assert_eq!(
+            github.args,
+            vec!["-y", "@modelcontextprotocol/server-github"]
+        );
+        assert_eq!(
+            github.env,
+            HashMap::from([("LOG_LEVEL".to_string(), "warn".to_string())])
+        );
+        let docs = &resolved.mcp.servers["docs"];
+        assert_eq!(docs.url.as_deref(), Some("https://docs.example.com/sse"));
+        assert_eq!(
+            docs.headers,
+            HashMap::from([("Authorization".to_string(), "secret".to_string())])
+        );
+        assert_eq!(docs.timeout_ms, Some(5000));
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_parses_web_search() {
+        let content = r#"
+[env]
+SEARCH_KEY = "brave-key"
+
+[web_search]
+backend = "brave"
+api_key = "$SEARCH_KEY"
+timeout_ms = 5000
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(
+            resolved.web_search,
+            WebSearchConfig {
+                backend: Some(WebSearchBackendKind::Brave),
+                url: None,
+                api_key: Some("brave-key".to_string()),
+                timeout_ms: Some(5000),
+            }
+        );
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_parses_project_memory() {
+        let content = r#"
+[project_memory]
+target = "pixy_memory"
+auto = true
+max_bytes = 2048
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(
+            resolved.project_memory,
+            ProjectMemoryConfig {
+                auto: true,
+                target: ProjectMemoryTarget::PixyMemory,
+                max_bytes: 2048,
+            }
+        );
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_parses_redaction() {
+        let content = r#"
+[redaction]
+entropy = false
+patterns = ["corp_[A-Za-z0-9]{24}"]
+audit_log = "logs/redactions.jsonl"
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("/workspace"), content, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(
+            resolved.redaction,
+            RedactionConfig {
+                enabled: true,
+                entropy: false,
+                patterns: vec!["corp_[A-Za-z0-9]{24}".to_string()],
+                audit_log: Some(PathBuf::from("/workspace/logs/redactions.jsonl")),
+            }
+        );
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_parses_guards() {
+        let content = r#"
+[guards]
+redact_messages = true
+blocked_terms = ["Project Falcon"]
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(
+            resolved.guards,
+            GuardConfig {
+                redact_messages: true,
+                blocked_terms: vec!["Project Falcon".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_parses_sampling_under_cli_overrides() {
+        let content = r#"
+[sampling]
+temperature = 0.7
+top_p = 0.9
+seed = 1
+
+[sampling.extra]
+min_p = 0.05
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        options.overrides.seed = Some(42);
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(resolved.sampling.temperature, Some(0.7));
+        assert_eq!(resolved.sampling.top_p, Some(0.9));
+        assert_eq!(resolved.sampling.seed, Some(42));
+        assert_eq!(resolved.sampling.extra["min_p"], 0.05);
+
+        let mut stream = pixy_ai::StreamOptions {
+            temperature: Some(0.0),
+            ..pixy_ai::StreamOptions::default()
+        };
+        resolved.sampling.apply(&mut stream);
+        assert_eq!(stream.temperature, Some(0.0));
+        assert_eq!(stream.seed, Some(42));
+        assert_eq!(stream.extra_sampling["min_p"], 0.05);
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_parses_stream_transport() {
+        let content = r#"
+[transport]
+idle_timeout_ms = 45000
+total_timeout_ms = 600000
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(
+            resolved.stream_transport,
+            StreamTransport {
+                idle_timeout_ms: Some(45_000),
+                total_timeout_ms: Some(600_000),
+                max_resumes: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_layers_proxy_flags_over_transport_config() {
+        let content = r#"
+[transport]
+proxy = "http://proxy.corp:3128"
+ca_bundle = "corp-ca.pem"
+
+[transport.providers.anthropic]
+proxy = "http://egress.corp:8080"
+client_cert = "anthropic.pem"
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("/etc/pixy"), content, 0)
+            .expect("runtime should resolve");
+        assert_eq!(
+            resolved.http_transport.default.ca_bundle,
+            Some(PathBuf::from("/etc/pixy/corp-ca.pem"))
+        );
+        let anthropic = &resolved.http_transport.providers["anthropic"];
+        assert_eq!(anthropic.proxy.as_deref(), Some("http://egress.corp:8080"));
+        assert_eq!(
+            anthropic.client_cert,
+            Some(PathBuf::from("/etc/pixy/anthropic.pem"))
+        );
+
+        options.overrides.http_transport.proxy = Some("http://127.0.0.1:9000".to_string());
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("/etc/pixy"), content, 0)
+            .expect("runtime should resolve");
+        assert_eq!(
+            resolved.http_transport.default.proxy.as_deref(),
+            Some("http://127.0.0.1:9000")
+        );
+        assert_eq!(
+            resolved.http_transport.providers["anthropic"]
+                .proxy
+                .as_deref(),
+            Some("http://127.0.0.1:9000")
+        );
+        assert_eq!(
+            resolved.http_transport.default.ca_bundle,
+            Some(PathBuf::from("/etc/pixy/corp-ca.pem"))
+        );
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_parses_rate_limits() {
+        let content = r#"
+[rate_limits.openai]
+max_concurrent = 4
+
+[rate_limits."openai/gpt-5.3-codex"]
+requests_per_minute = 30
+burst = 5
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(resolved.rate_limits["openai"].max_concurrent, Some(4));
+        assert_eq!(
+            resolved.rate_limits["openai/gpt-5.3-codex"],
+            RateLimit {
+                requests_per_minute: Some(30.0),
+                burst: Some(5),
+                max_concurrent: None,
+            }
+        );
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_parses_telemetry() {
+        let content = r#"
+[env]
+COLLECTOR_KEY = "secret-key"
+
+[telemetry]
+enabled = true
+endpoint = "https://otel.example.com/"
+service_name = "pixy-ci"
+headers = { "x-api-key" = "$COLLECTOR_KEY", "x-unset" = "$PIXY_TEST_UNSET_HEADER" }
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(
+            resolved.telemetry,
+            TelemetryConfig {
+                enabled: true,
+                endpoint: "https://otel.example.com/".to_string(),
+                service_name: "pixy-ci".to_string(),
+                headers: BTreeMap::from([("x-api-key".to_string(), "secret-key".to_string())]),
+            }
+        );
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_parses_tool_failures() {
+        let content = r#"
+[tool_failures]
+output = "summary"
+include_next_step = true
+max_retries = 2
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(
+            resolved.tool_failures,
+            ToolFailureConfig {
+                output: ToolFailureOutput::Summary,
+                include_exit_code: true,
+                include_next_step: true,
+                max_retries: Some(2),
+            }
+        );
+    }
+
+    #[test]
+    fn resolve_runtime_from_toml_merges_tool_output_limits() {
+        let content = r#"
+[tool_output]
+max_lines = 500
+spill = false
+
+[tool_output.tools.bash]
+max_bytes = 8192
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        let defaults = OutputLimits::default();
+        assert!(!resolved.tool_output.spill);
+        assert_eq!(
+            resolved.tool_output.limits_for("grep"),
+            OutputLimits {
+                max_lines: 500,
+                ..defaults
+            }
+        );
+        assert_eq!(
+            resolved.tool_output.limits_for("bash"),
+            OutputLimits {
+                max_lines: 500,
+                max_bytes: 8192,
+                head_lines: defaults.head_lines,
+            }
+        );
+    }
+
+    #[test]
+    fn wildcard_default_provider_uses_weights_for_chat_providers() {
+        let content = r#"
+[llm]
+default_provider = "*"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "openai"
 model = "gpt-5.3-codex"
 weight = 90
 
@@ -1950,6 +3483,58 @@ weight = 30
         assert_eq!(cycled.base_url, "https://api.deepseek.com/anthropic");
     }
 
+    #[test]
+    fn resolve_runtime_merges_project_config_over_user_config() {
+        let dir = tempdir().expect("tempdir");
+        let conf_dir = dir.path().join("home/.pixy");
+        let repo = dir.path().join("repo");
+        std::fs::create_dir_all(&conf_dir).expect("create conf dir");
+        std::fs::create_dir_all(repo.join(".git")).expect("create .git");
+        std::fs::create_dir_all(repo.join(".pixy")).expect("create repo .pixy");
+        std::fs::write(
+            conf_dir.join("pixy.toml"),
+            r#"
+theme = "dark"
+transport_retry_count = 2
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "key"
+model = "gpt-5.3-codex"
+weight = 1
+"#,
+        )
+        .expect("write user config");
+        std::fs::write(
+            repo.join(".pixy/config.toml"),
+            "transport_retry_count = 5\n\n[memory]\nenabled = true\ndir = \"notes\"\n",
+        )
+        .expect("write project config");
+
+        let options = RuntimeLoadOptions {
+            conf_dir: Some(conf_dir),
+            load_skills: false,
+            include_default_skills: false,
+            ..RuntimeLoadOptions::default()
+        };
+        let resolved = options
+            .resolve_runtime_with_seed(&repo, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(resolved.model.id, "gpt-5.3-codex");
+        assert_eq!(resolved.theme.as_deref(), Some("dark"));
+        assert_eq!(resolved.transport_retry_count, 5);
+        assert!(resolved.memory.enabled);
+        assert_eq!(resolved.memory.dir, repo.join(".pixy/notes"));
+    }
+
     #[test]
     fn resolve_runtime_from_toml_loads_config_and_explicit_skills() {
         let dir = tempdir().expect("tempdir");
@@ -2041,6 +3626,9 @@ weight = 1
             },
             context_window: 200_000,
             max_tokens: 8_192,
+            deployment: None,
+            api_version: None,
+            compat: None,
         };
         let options = RuntimeLoadOptions::from_fixed_model(model.clone(), Some("key".to_string()));
         let resolved = options
@@ -2240,4 +3828,95 @@ weight = 1
             Some(pixy_ai::ThinkingLevel::Medium)
         );
     }
+
+    #[test]
+    fn resolve_runtime_carries_azure_deployment_and_api_version() {
+        let content = r#"
+[llm]
+default_provider = "azure"
+
+[[llm.providers]]
+name = "azure"
+kind = "chat"
+provider = "azure"
+api = "azure-openai"
+base_url = "https://example.openai.azure.com"
+api_key = "key"
+model = "gpt-4o"
+deployment = "prod-gpt4o"
+api_version = "2024-10-21"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(resolved.model.api, "azure-openai");
+        assert_eq!(resolved.model.deployment.as_deref(), Some("prod-gpt4o"));
+        assert_eq!(resolved.model.api_version.as_deref(), Some("2024-10-21"));
+    }
+
+    #[test]
+    fn resolve_runtime_infers_deepseek_api_and_endpoint() {
+        let content = r#"
+[llm]
+default_provider = "deepseek"
+
+[[llm.providers]]
+name = "deepseek"
+kind = "chat"
+provider = "deepseek"
+api_key = "key"
+weight = 1
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(resolved.model.api, "deepseek");
+        assert_eq!(resolved.model.base_url, "https://api.deepseek.com");
+        assert_eq!(resolved.model.id, "deepseek-chat");
+    }
+
+    #[test]
+    fn resolve_runtime_carries_openai_compat_quirks_for_vllm() {
+        let content = r#"
+[llm]
+default_provider = "vllm"
+
+[[llm.providers]]
+name = "vllm"
+kind = "chat"
+provider = "vllm"
+model = "Qwen/Qwen3-32B"
+weight = 1
+
+[llm.providers.compat]
+stream_options = true
+stop_field = "stop_sequences"
+"#;
+
+        let mut options = RuntimeLoadOptions::default();
+        options.load_skills = false;
+        let resolved = options
+            .resolve_runtime_from_toml_with_seed(Path::new("."), content, 0)
+            .expect("runtime should resolve");
+
+        assert_eq!(resolved.model.api, "openai-compatible");
+        assert_eq!(resolved.model.base_url, "http://localhost:8000/v1");
+        assert_eq!(
+            resolved.model.compat,
+            Some(pixy_ai::OpenAICompletionsCompat {
+                stream_options: true,
+                stop_field: "stop_sequences".to_string(),
+                ..Default::default()
+            })
+        );
+    }
 }
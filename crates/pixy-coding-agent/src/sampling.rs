@@ -0,0 +1,37 @@
+//! Sampling parameters for every model request, configured by `[sampling]` in `pixy.toml` or
+//! `--temperature` and `--seed`. Evaluation runs pin them to make results repeatable.
+
+use pixy_ai::StreamOptions;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SamplingConfig {
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    pub top_k: Option<u32>,
+    pub seed: Option<u64>,
+    pub frequency_penalty: Option<f64>,
+    pub presence_penalty: Option<f64>,
+    /// Provider-specific fields sent as-is, e.g. `min_p`.
+    pub extra: Map<String, Value>,
+}
+
+impl SamplingConfig {
+    /// Fills in the parameters `options` leaves unset; values set per request win.
+    pub fn apply(&self, options: &mut StreamOptions) {
+        options.temperature = options.temperature.or(self.temperature);
+        options.top_p = options.top_p.or(self.top_p);
+        options.top_k = options.top_k.or(self.top_k);
+        options.seed = options.seed.or(self.seed);
+        options.frequency_penalty = options.frequency_penalty.or(self.frequency_penalty);
+        options.presence_penalty = options.presence_penalty.or(self.presence_penalty);
+        for (name, value) in &self.extra {
+            options
+                .extra_sampling
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+}
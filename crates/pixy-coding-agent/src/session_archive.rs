@@ -0,0 +1,551 @@
+//! `.tar.zst` archives of a session for `pixy session export` / `pixy session import`.
+//!
+//! An archive holds `manifest.json`, the session JSONL, the memory directory, the project memory
+//! file and the file snapshot objects of the session's workspace. Imports check the archive
+//! format, session version and that every snapshot object hashes to its name before writing
+//! anything, and only warn about a different pixy version or config fingerprint.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufRead, Read};
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::file_snapshots::{create_objects_dir, object_hash, SNAPSHOT_DIR};
+use crate::project_memory::PROJECT_MEMORY_FILE;
+use crate::session_manager::{SessionHeader, CURRENT_SESSION_VERSION};
+
+pub const SESSION_ARCHIVE_FORMAT: &str = "pixy-session-archive";
+pub const SESSION_ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const SESSION_ENTRY: &str = "session.jsonl";
+const PROJECT_MEMORY_ENTRY: &str = "project-memory.md";
+const MEMORY_PREFIX: &str = "memory/";
+const SNAPSHOTS_PREFIX: &str = "snapshots/";
+const ZSTD_LEVEL: i32 = 9;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionArchiveManifest {
+    pub format: String,
+    pub format_version: u32,
+    pub pixy_version: String,
+    pub session_version: u32,
+    pub session_id: String,
+    pub session_file_name: String,
+    /// Workspace the session ran in on the exporting machine.
+    pub cwd: String,
+    pub exported_at: String,
+    #[serde(default)]
+    pub config_fingerprint: Option<String>,
+    #[serde(default)]
+    pub memory_files: Vec<String>,
+    #[serde(default)]
+    pub project_memory: bool,
+    #[serde(default)]
+    pub snapshot_objects: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct SessionExportOptions {
+    pub session_file: PathBuf,
+    /// Memory directory to bundle; skipped when unset or missing.
+    pub memory_dir: Option<PathBuf>,
+    pub config_fingerprint: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SessionImportOptions {
+    pub session_dir: PathBuf,
+    /// Workspace the imported session is attached to; the session header `cwd` is rewritten.
+    pub cwd: PathBuf,
+    pub memory_dir: Option<PathBuf>,
+    pub config_fingerprint: Option<String>,
+    /// Replace an existing session file with the same name.
+    pub force: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct SessionImportSummary {
+    pub manifest: SessionArchiveManifest,
+    pub session_file: PathBuf,
+    pub memory_files_written: usize,
+    /// Memory files that already existed locally and were left untouched.
+    pub memory_files_skipped: usize,
+    pub snapshot_objects: usize,
+    pub warnings: Vec<String>,
+}
+
+pub fn export_session_archive(
+    archive_path: &Path,
+    options: &SessionExportOptions,
+) -> Result<SessionArchiveManifest, String> {
+    let session_bytes = fs::read(&options.session_file).map_err(|error| {
+        format!(
+            "read session file {} failed: {error}",
+            options.session_file.display()
+        )
+    })?;
+    let header = parse_session_header(&session_bytes)?;
+    let session_cwd = PathBuf::from(&header.cwd);
+
+    let mut entries = BTreeMap::<String, Vec<u8>>::new();
+    let mut memory_files = Vec::new();
+    if let Some(memory_dir) = options.memory_dir.as_deref().filter(|dir| dir.is_dir()) {
+        for (name, bytes) in read_flat_dir(memory_dir)? {
+            memory_files.push(name.clone());
+            entries.insert(format!("{MEMORY_PREFIX}{name}"), bytes);
+        }
+    }
+    let project_memory = fs::read(session_cwd.join(PROJECT_MEMORY_FILE)).ok();
+    let snapshot_dir = session_cwd.join(SNAPSHOT_DIR).join("objects");
+    let snapshots = if snapshot_dir.is_dir() {
+        read_flat_dir(&snapshot_dir)?
+    } else {
+        vec![]
+    };
+
+    let manifest = SessionArchiveManifest {
+        format: SESSION_ARCHIVE_FORMAT.to_string(),
+        format_version: SESSION_ARCHIVE_FORMAT_VERSION,
+        pixy_version: env!("CARGO_PKG_VERSION").to_string(),
+        session_version: header.version,
+        session_id: header.id.clone(),
+        session_file_name: options
+            .session_file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| format!("{}.jsonl", header.id)),
+        cwd: header.cwd.clone(),
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        config_fingerprint: options.config_fingerprint.clone(),
+        memory_files,
+        project_memory: project_memory.is_some(),
+        snapshot_objects: snapshots.len(),
+    };
+
+    if let Some(bytes) = project_memory {
+        entries.insert(PROJECT_MEMORY_ENTRY.to_string(), bytes);
+    }
+    for (name, bytes) in snapshots {
+        entries.insert(format!("{SNAPSHOTS_PREFIX}{name}"), bytes);
+    }
+    entries.insert(SESSION_ENTRY.to_string(), session_bytes);
+    let manifest_bytes = serde_json::to_vec_pretty(&manifest)
+        .map_err(|error| format!("serialize archive manifest failed: {error}"))?;
+
+    if let Some(parent) = archive_path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)
+            .map_err(|error| format!("create {} failed: {error}", parent.display()))?;
+    }
+    let file = File::create(archive_path)
+        .map_err(|error| format!("create {} failed: {error}", archive_path.display()))?;
+    let encoder = zstd::Encoder::new(file, ZSTD_LEVEL)
+        .map_err(|error| format!("start zstd stream failed: {error}"))?;
+    let mut builder = tar::Builder::new(encoder);
+    append_entry(&mut builder, MANIFEST_ENTRY, &manifest_bytes)?;
+    for (name, bytes) in &entries {
+        append_entry(&mut builder, name, bytes)?;
+    }
+    builder
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(|error| format!("write {} failed: {error}", archive_path.display()))?;
+    Ok(manifest)
+}
+
+pub fn import_session_archive(
+    archive_path: &Path,
+    options: &SessionImportOptions,
+) -> Result<SessionImportSummary, String> {
+    let mut entries = read_archive_entries(archive_path)?;
+    let manifest_bytes = entries
+        .remove(MANIFEST_ENTRY)
+        .ok_or_else(|| format!("{} has no {MANIFEST_ENTRY}", archive_path.display()))?;
+    let manifest: SessionArchiveManifest = serde_json::from_slice(&manifest_bytes)
+        .map_err(|error| format!("parse {MANIFEST_ENTRY} failed: {error}"))?;
+    check_versions(&manifest)?;
+    check_snapshot_objects(&entries)?;
+
+    let session_bytes = entries
+        .remove(SESSION_ENTRY)
+        .ok_or_else(|| format!("{} has no {SESSION_ENTRY}", archive_path.display()))?;
+    let session_bytes = rewrite_session_cwd(&session_bytes, &options.cwd)?;
+    let file_name = Path::new(&manifest.session_file_name)
+        .file_name()
+        .filter(|name| !name.is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| format!("invalid session file name '{}'", manifest.session_file_name))?;
+    let session_file = options.session_dir.join(file_name);
+    if session_file.exists() && !options.force {
+        return Err(format!(
+            "{} already exists; pass --force to replace it",
+            session_file.display()
+        ));
+    }
+
+    let mut warnings = Vec::new();
+    if manifest.pixy_version != env!("CARGO_PKG_VERSION") {
+        warnings.push(format!(
+            "archive was exported by pixy {}, this is pixy {}",
+            manifest.pixy_version,
+            env!("CARGO_PKG_VERSION")
+        ));
+    }
+    if let (Some(archived), Some(local)) =
+        (&manifest.config_fingerprint, &options.config_fingerprint)
+    {
+        if archived != local {
+            warnings.push(
+                "config differs from the exporting machine (model, providers or tool settings may not match)"
+                    .to_string(),
+            );
+        }
+    }
+
+    fs::create_dir_all(&options.session_dir)
+        .map_err(|error| format!("create {} failed: {error}", options.session_dir.display()))?;
+    fs::write(&session_file, session_bytes)
+        .map_err(|error| format!("write {} failed: {error}", session_file.display()))?;
+
+    let mut memory_files_written = 0;
+    let mut memory_files_skipped = 0;
+    let mut snapshot_objects = 0;
+    for (name, bytes) in entries {
+        let target = if let Some(file) = name.strip_prefix(MEMORY_PREFIX) {
+            let Some(memory_dir) = &options.memory_dir else {
+                memory_files_skipped += 1;
+                continue;
+            };
+            memory_dir.join(file)
+        } else if let Some(object) = name.strip_prefix(SNAPSHOTS_PREFIX) {
+            snapshot_objects += 1;
+            create_objects_dir(&options.cwd.join(SNAPSHOT_DIR))?.join(object)
+        } else if name == PROJECT_MEMORY_ENTRY {
+            options.cwd.join(PROJECT_MEMORY_FILE)
+        } else {
+            warnings.push(format!("ignored unknown archive entry {name}"));
+            continue;
+        };
+        if target.exists() {
+            if !name.starts_with(SNAPSHOTS_PREFIX) {
+                memory_files_skipped += 1;
+            }
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|error| format!("create {} failed: {error}", parent.display()))?;
+        }
+        fs::write(&target, bytes)
+            .map_err(|error| format!("write {} failed: {error}", target.display()))?;
+        if !name.starts_with(SNAPSHOTS_PREFIX) {
+            memory_files_written += 1;
+        }
+    }
+
+    Ok(SessionImportSummary {
+        manifest,
+        session_file,
+        memory_files_written,
+        memory_files_skipped,
+        snapshot_objects,
+        warnings,
+    })
+}
+
+/// Snapshot objects are content addressed; one whose content does not hash to its name would
+/// make `/undo` restore the wrong contents.
+fn check_snapshot_objects(entries: &BTreeMap<String, Vec<u8>>) -> Result<(), String> {
+    for (name, bytes) in entries {
+        if let Some(object) = name.strip_prefix(SNAPSHOTS_PREFIX) {
+            if object_hash(bytes) != object {
+                return Err(format!(
+                    "snapshot object {object} does not match its content; the archive is corrupted"
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_versions(manifest: &SessionArchiveManifest) -> Result<(), String> {
+    if manifest.format != SESSION_ARCHIVE_FORMAT {
+        return Err(format!(
+            "not a pixy session archive (format '{}')",
+            manifest.format
+        ));
+    }
+    if manifest.format_version > SESSION_ARCHIVE_FORMAT_VERSION {
+        return Err(format!(
+            "archive format v{} is newer than this pixy supports (v{SESSION_ARCHIVE_FORMAT_VERSION}); upgrade pixy to import it",
+            manifest.format_version
+        ));
+    }
+    if manifest.session_version > CURRENT_SESSION_VERSION {
+        return Err(format!(
+            "session format v{} is newer than this pixy supports (v{CURRENT_SESSION_VERSION}); upgrade pixy to import it",
+            manifest.session_version
+        ));
+    }
+    Ok(())
+}
+
+fn parse_session_header(session_bytes: &[u8]) -> Result<SessionHeader, String> {
+    let first_line = session_bytes
+        .lines()
+        .next()
+        .ok_or_else(|| "session file is empty".to_string())?
+        .map_err(|error| format!("read session header failed: {error}"))?;
+    serde_json::from_str(&first_line)
+        .map_err(|error| format!("parse session header failed: {error}"))
+}
+
+/// Points the session header at the importing workspace; entries are kept byte for byte.
+fn rewrite_session_cwd(session_bytes: &[u8], cwd: &Path) -> Result<Vec<u8>, String> {
+    let mut header = parse_session_header(session_bytes)?;
+    header.cwd = cwd.display().to_string();
+    let rest = session_bytes
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .map(|index| &session_bytes[index + 1..])
+        .unwrap_or_default();
+    let mut output = serde_json::to_vec(&header)
+        .map_err(|error| format!("serialize session header failed: {error}"))?;
+    output.push(b'\n');
+    output.extend_from_slice(rest);
+    Ok(output)
+}
+
+fn read_flat_dir(dir: &Path) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut files = Vec::new();
+    let entries =
+        fs::read_dir(dir).map_err(|error| format!("read {} failed: {error}", dir.display()))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let bytes =
+            fs::read(&path).map_err(|error| format!("read {} failed: {error}", path.display()))?;
+        files.push((entry.file_name().to_string_lossy().to_string(), bytes));
+    }
+    files.sort_by(|left, right| left.0.cmp(&right.0));
+    Ok(files)
+}
+
+fn append_entry<W: std::io::Write>(
+    builder: &mut tar::Builder<W>,
+    name: &str,
+    bytes: &[u8],
+) -> Result<(), String> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, name, bytes)
+        .map_err(|error| format!("add {name} to archive failed: {error}"))
+}
+
+/// Reads every regular file in the archive. Entry names must be plain relative paths with at
+/// most one directory level, so a crafted archive cannot write outside the import targets.
+fn read_archive_entries(archive_path: &Path) -> Result<BTreeMap<String, Vec<u8>>, String> {
+    let file = File::open(archive_path)
+        .map_err(|error| format!("open {} failed: {error}", archive_path.display()))?;
+    let decoder = zstd::Decoder::new(file)
+        .map_err(|error| format!("read {} failed: {error}", archive_path.display()))?;
+    let mut archive = tar::Archive::new(decoder);
+    let mut entries = BTreeMap::new();
+    let iter = archive
+        .entries()
+        .map_err(|error| format!("read {} failed: {error}", archive_path.display()))?;
+    for entry in iter {
+        let mut entry =
+            entry.map_err(|error| format!("read {} failed: {error}", archive_path.display()))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry
+            .path()
+            .map_err(|error| format!("invalid archive entry name: {error}"))?
+            .into_owned();
+        let components = path.components().collect::<Vec<_>>();
+        if components.is_empty()
+            || components.len() > 2
+            || components
+                .iter()
+                .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return Err(format!("unsafe archive entry {}", path.display()));
+        }
+        let mut bytes = Vec::new();
+        entry
+            .read_to_end(&mut bytes)
+            .map_err(|error| format!("read archive entry {} failed: {error}", path.display()))?;
+        entries.insert(path.to_string_lossy().replace('\\', "/"), bytes);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::SessionManager;
+
+    fn seed_session(session_dir: &Path, cwd: &Path) -> PathBuf {
+        let mut manager = SessionManager::create(&cwd.display().to_string(), session_dir)
+            .expect("session should be created");
+        manager
+            .append_custom_entry("note", Some(serde_json::json!({ "text": "hello" })))
+            .expect("entry should be appended");
+        manager
+            .session_file()
+            .cloned()
+            .expect("session file should exist")
+    }
+
+    #[test]
+    fn export_then_import_restores_session_memory_and_snapshots() {
+        let source = tempdir().expect("tempdir should be created");
+        let source_cwd = source.path().join("project");
+        let memory_dir = source.path().join("memory");
+        fs::create_dir_all(source_cwd.join(SNAPSHOT_DIR).join("objects")).expect("snapshots dir");
+        fs::create_dir_all(&memory_dir).expect("memory dir");
+        fs::write(memory_dir.join("2026-10-01.md"), "- remembered").expect("memory file");
+        fs::write(source_cwd.join(PROJECT_MEMORY_FILE), "## Project Learnings").expect("project");
+        let object = object_hash(b"old content");
+        fs::write(
+            source_cwd.join(SNAPSHOT_DIR).join("objects").join(&object),
+            "old content",
+        )
+        .expect("snapshot object");
+        let session_file = seed_session(&source.path().join("sessions"), &source_cwd);
+        let archive = source.path().join("out/session.tar.zst");
+
+        let manifest = export_session_archive(
+            &archive,
+            &SessionExportOptions {
+                session_file: session_file.clone(),
+                memory_dir: Some(memory_dir),
+                config_fingerprint: Some("aaa".to_string()),
+            },
+        )
+        .expect("export should succeed");
+        assert_eq!(manifest.memory_files, vec!["2026-10-01.md".to_string()]);
+        assert!(manifest.project_memory);
+        assert_eq!(manifest.snapshot_objects, 1);
+
+        let target = tempdir().expect("tempdir should be created");
+        let target_cwd = target.path().join("checkout");
+        let options = SessionImportOptions {
+            session_dir: target.path().join("sessions"),
+            cwd: target_cwd.clone(),
+            memory_dir: Some(target.path().join("memory")),
+            config_fingerprint: Some("bbb".to_string()),
+            force: false,
+        };
+        let summary = import_session_archive(&archive, &options).expect("import should succeed");
+
+        assert_eq!(summary.manifest.session_id, manifest.session_id);
+        assert_eq!(summary.memory_files_written, 2);
+        assert_eq!(summary.snapshot_objects, 1);
+        assert!(summary
+            .warnings
+            .iter()
+            .any(|warning| warning.contains("config differs")));
+        let imported = SessionManager::load(&summary.session_file).expect("session should load");
+        assert_eq!(imported.cwd(), target_cwd.display().to_string());
+        assert_eq!(
+            fs::read_to_string(target.path().join("memory/2026-10-01.md")).expect("memory"),
+            "- remembered"
+        );
+        assert!(target_cwd.join(PROJECT_MEMORY_FILE).is_file());
+        assert!(target_cwd
+            .join(SNAPSHOT_DIR)
+            .join("objects")
+            .join(&object)
+            .is_file());
+
+        let error = import_session_archive(&archive, &options)
+            .expect_err("second import should refuse to overwrite");
+        assert!(error.contains("--force"));
+    }
+
+    #[test]
+    fn import_rejects_snapshot_objects_that_do_not_match_their_name() {
+        let source = tempdir().expect("tempdir should be created");
+        let source_cwd = source.path().join("project");
+        let objects_dir = source_cwd.join(SNAPSHOT_DIR).join("objects");
+        fs::create_dir_all(&objects_dir).expect("snapshots dir");
+        fs::write(
+            objects_dir.join(object_hash(b"original")),
+            "planted content",
+        )
+        .expect("snapshot object");
+        let session_file = seed_session(&source.path().join("sessions"), &source_cwd);
+        let archive = source.path().join("session.tar.zst");
+        export_session_archive(
+            &archive,
+            &SessionExportOptions {
+                session_file,
+                memory_dir: None,
+                config_fingerprint: None,
+            },
+        )
+        .expect("export should succeed");
+
+        let target = tempdir().expect("tempdir should be created");
+        let options = SessionImportOptions {
+            session_dir: target.path().join("sessions"),
+            cwd: target.path().join("checkout"),
+            memory_dir: None,
+            config_fingerprint: None,
+            force: false,
+        };
+        let error = import_session_archive(&archive, &options)
+            .expect_err("mismatched object should be rejected");
+
+        assert!(error.contains("does not match its content"), "{error}");
+        assert!(!target.path().join("sessions").exists());
+        assert!(!target.path().join("checkout").exists());
+    }
+
+    #[test]
+    fn import_rejects_newer_archive_and_session_versions() {
+        let mut manifest = SessionArchiveManifest {
+            format: SESSION_ARCHIVE_FORMAT.to_string(),
+            format_version: SESSION_ARCHIVE_FORMAT_VERSION,
+            pixy_version: "0.0.0".to_string(),
+            session_version: CURRENT_SESSION_VERSION,
+            session_id: "session-1".to_string(),
+            session_file_name: "session-1.jsonl".to_string(),
+            cwd: "/tmp".to_string(),
+            exported_at: String::new(),
+            config_fingerprint: None,
+            memory_files: vec![],
+            project_memory: false,
+            snapshot_objects: 0,
+        };
+        assert!(check_versions(&manifest).is_ok());
+
+        manifest.session_version = CURRENT_SESSION_VERSION + 1;
+        assert!(check_versions(&manifest)
+            .expect_err("newer session format should be rejected")
+            .contains("session format"));
+
+        manifest.session_version = CURRENT_SESSION_VERSION;
+        manifest.format_version = SESSION_ARCHIVE_FORMAT_VERSION + 1;
+        assert!(check_versions(&manifest)
+            .expect_err("newer archive format should be rejected")
+            .contains("archive format"));
+    }
+}
@@ -0,0 +1,238 @@
+//! Token and cost accounting over persisted session messages.
+
+use pixy_ai::{calculate_cost, Message, Usage};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CostTotals {
+    pub input: u64,
+    pub output: u64,
+    pub cache_read: u64,
+    pub cache_write: u64,
+    pub total_tokens: u64,
+    pub cost: f64,
+}
+
+impl CostTotals {
+    fn add(&mut self, usage: &Usage) {
+        self.input += usage.input;
+        self.output += usage.output;
+        self.cache_read += usage.cache_read;
+        self.cache_write += usage.cache_write;
+        self.total_tokens += if usage.total_tokens > 0 {
+            usage.total_tokens
+        } else {
+            usage.input + usage.output + usage.cache_read + usage.cache_write
+        };
+        self.cost += usage.cost.total;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelCost {
+    /// `provider/model`.
+    pub model: String,
+    pub totals: CostTotals,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SessionCostReport {
+    /// Number of user turns on the current branch.
+    pub turns: usize,
+    /// Usage of the assistant messages after the latest user message.
+    pub last_turn: CostTotals,
+    pub session: CostTotals,
+    /// Per-model breakdown in first-use order.
+    pub models: Vec<ModelCost>,
+}
+
+impl SessionCostReport {
+    pub fn from_messages(messages: &[Message]) -> Self {
+        let mut report = Self::default();
+        for message in messages {
+            match message {
+                Message::User { .. } => {
+                    report.turns += 1;
+                    report.last_turn = CostTotals::default();
+                }
+                Message::Assistant {
+                    provider,
+                    model,
+                    usage,
+                    ..
+                } => {
+                    report.last_turn.add(usage);
+                    report.session.add(usage);
+                    let key = format!("{provider}/{model}");
+                    match report.models.iter_mut().find(|entry| entry.model == key) {
+                        Some(entry) => entry.totals.add(usage),
+                        None => {
+                            let mut totals = CostTotals::default();
+                            totals.add(usage);
+                            report.models.push(ModelCost { model: key, totals });
+                        }
+                    }
+                }
+                Message::ToolResult { .. } => {}
+            }
+        }
+        report
+    }
+
+    /// One-line summary printed at the end of a run.
+    pub fn summary_line(&self) -> String {
+        let mut line = format!(
+            "Cost: turn {} ({} tokens), session {} ({} tokens)",
+            format_usd(self.last_turn.cost),
+            self.last_turn.total_tokens,
+            format_usd(self.session.cost),
+            self.session.total_tokens
+        );
+        if !self.models.is_empty() {
+            let models = self
+                .models
+                .iter()
+                .map(|entry| format!("{} {}", entry.model, format_usd(entry.totals.cost)))
+                .collect::<Vec<_>>();
+            line.push_str(&format!(" [{}]", models.join(", ")));
+        }
+        line
+    }
+
+    /// Multi-line report for `/cost`.
+    pub fn render(&self) -> String {
+        let mut lines = vec![
+            format!(
+                "Last turn: {} ({} tokens)",
+                format_usd(self.last_turn.cost),
+                self.last_turn.total_tokens
+            ),
+            format!(
+                "Session: {} over {} turn(s) ({} input, {} output, {} cache read, {} cache write)",
+                format_usd(self.session.cost),
+                self.turns,
+                self.session.input,
+                self.session.output,
+                self.session.cache_read,
+                self.session.cache_write
+            ),
+        ];
+        for entry in &self.models {
+            lines.push(format!(
+                "  {}: {} ({} tokens)",
+                entry.model,
+                format_usd(entry.totals.cost),
+                entry.totals.total_tokens
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Fills in `usage.cost` from per-million-token `pricing` unless the provider already reported
+/// a cost.
+pub(crate) fn apply_pricing(usage: &mut Usage, pricing: &pixy_ai::Cost) {
+    if usage.cost.total > 0.0 {
+        return;
+    }
+    usage.cost = calculate_cost(pricing, usage);
+}
+
+fn format_usd(cost: f64) -> String {
+    if cost > 0.0 && cost < 0.01 {
+        format!("${cost:.4}")
+    } else {
+        format!("${cost:.2}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pixy_ai::{Cost, StopReason, UserContent};
+
+    use super::*;
+
+    fn zero_cost() -> Cost {
+        Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        }
+    }
+
+    fn user(text: &str) -> Message {
+        Message::User {
+            content: UserContent::Text(text.to_string()),
+            timestamp: 1,
+        }
+    }
+
+    fn assistant(model: &str, tokens: u64, cost: f64) -> Message {
+        Message::Assistant {
+            content: vec![],
+            api: "openai-responses".to_string(),
+            provider: "openai".to_string(),
+            model: model.to_string(),
+            usage: Usage {
+                input: tokens,
+                output: 0,
+                cache_read: 0,
+                cache_write: 0,
+                reasoning: 0,
+                total_tokens: tokens,
+                cost: Cost {
+                    total: cost,
+                    ..zero_cost()
+                },
+            },
+            stop_reason: StopReason::Stop,
+            error_message: None,
+            timestamp: 2,
+            stats: None,
+        }
+    }
+
+    #[test]
+    fn session_cost_report_tracks_last_turn_and_model_breakdown() {
+        let report = SessionCostReport::from_messages(&[
+            user("one"),
+            assistant("gpt-4o", 100, 0.5),
+            user("two"),
+            assistant("gpt-4o", 50, 0.25),
+            assistant("gpt-4o-mini", 10, 0.001),
+        ]);
+
+        assert_eq!(report.turns, 2);
+        assert_eq!(report.last_turn.total_tokens, 60);
+        assert!((report.last_turn.cost - 0.251).abs() < 1e-9);
+        assert_eq!(report.session.total_tokens, 160);
+        assert_eq!(report.models.len(), 2);
+        assert_eq!(report.models[0].model, "openai/gpt-4o");
+        assert_eq!(report.models[0].totals.total_tokens, 150);
+        assert_eq!(
+            report.summary_line(),
+            "Cost: turn $0.25 (60 tokens), session $0.75 (160 tokens) [openai/gpt-4o $0.75, openai/gpt-4o-mini $0.0010]"
+        );
+        assert!(report
+            .render()
+            .contains("  openai/gpt-4o-mini: $0.0010 (10 tokens)"));
+    }
+
+    #[test]
+    fn apply_pricing_keeps_provider_reported_cost() {
+        let pricing = Cost {
+            input: 1.0,
+            ..zero_cost()
+        };
+        let Message::Assistant { mut usage, .. } = assistant("gpt-4o", 1_000_000, 0.0) else {
+            unreachable!();
+        };
+        apply_pricing(&mut usage, &pricing);
+        assert!((usage.cost.total - 1.0).abs() < 1e-9);
+
+        usage.cost.total = 3.0;
+        apply_pricing(&mut usage, &pricing);
+        assert_eq!(usage.cost.total, 3.0);
+    }
+}
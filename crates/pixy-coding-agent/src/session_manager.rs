@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::fs::{self, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -12,6 +12,7 @@ use crate::messages::{
     BRANCH_SUMMARY_PREFIX, BRANCH_SUMMARY_SUFFIX, COMPACTION_SUMMARY_PREFIX,
     COMPACTION_SUMMARY_SUFFIX,
 };
+use crate::session_migration::ValidatedSession;
 
 pub const CURRENT_SESSION_VERSION: u32 = 3;
 
@@ -34,8 +35,12 @@ pub struct SessionHeader {
     pub cwd: String,
     #[serde(rename = "parentSession", skip_serializing_if = "Option::is_none")]
     pub parent_session: Option<String>,
+    /// Short generated summary of what the session is about, used by resume and search.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum SessionEntry {
@@ -144,7 +149,7 @@ pub enum SessionEntry {
 }
 
 impl SessionEntry {
-    fn id(&self) -> &str {
+    pub(crate) fn id(&self) -> &str {
         match self {
             SessionEntry::Message { id, .. } => id,
             SessionEntry::ThinkingLevelChange { id, .. } => id,
@@ -158,7 +163,7 @@ impl SessionEntry {
         }
     }
 
-    fn parent_id(&self) -> Option<&str> {
+    pub(crate) fn parent_id(&self) -> Option<&str> {
         match self {
             SessionEntry::Message { parent_id, .. } => parent_id.as_deref(),
             SessionEntry::ThinkingLevelChange { parent_id, .. } => parent_id.as_deref(),
@@ -172,6 +177,20 @@ impl SessionEntry {
         }
     }
 
+    pub(crate) fn set_parent_id(&mut self, new_parent_id: Option<String>) {
+        match self {
+            SessionEntry::Message { parent_id, .. }
+            | SessionEntry::ThinkingLevelChange { parent_id, .. }
+            | SessionEntry::ModelChange { parent_id, .. }
+            | SessionEntry::BranchSummary { parent_id, .. }
+            | SessionEntry::Compaction { parent_id, .. }
+            | SessionEntry::Custom { parent_id, .. }
+            | SessionEntry::CustomMessage { parent_id, .. }
+            | SessionEntry::Label { parent_id, .. }
+            | SessionEntry::SessionInfo { parent_id, .. } => *parent_id = new_parent_id,
+        }
+    }
+
     fn to_context_message(&self) -> Option<Message> {
         match self {
             SessionEntry::Message { message, .. } => Some(message.clone()),
@@ -187,6 +206,7 @@ impl SessionEntry {
                 content: UserContent::Blocks(vec![UserContentBlock::Text {
                     text: format!("{BRANCH_SUMMARY_PREFIX}{summary}{BRANCH_SUMMARY_SUFFIX}"),
                     text_signature: None,
+                    cache: None,
                 }]),
                 timestamp: parse_timestamp_millis(timestamp),
             }),
@@ -209,6 +229,7 @@ impl SessionEntry {
                         "{COMPACTION_SUMMARY_PREFIX}{summary}{COMPACTION_SUMMARY_SUFFIX}"
                     ),
                     text_signature: None,
+                    cache: None,
                 }]),
                 timestamp: parse_timestamp_millis(timestamp),
             }),
@@ -258,9 +279,7 @@ impl SessionManager {
         fs::create_dir_all(session_dir)
             .map_err(|error| format!("create session dir failed: {error}"))?;
 
-        let timestamp = now_millis().to_string();
-        let session_id = format!("session-{timestamp}");
-        let session_file = session_dir.join(format!("{session_id}.jsonl"));
+        let (session_id, session_file, timestamp) = allocate_session_file(session_dir);
         let header = SessionHeader {
             type_field: "session".to_string(),
             version: CURRENT_SESSION_VERSION,
@@ -268,6 +287,7 @@ impl SessionManager {
             timestamp: timestamp.clone(),
             cwd: cwd.to_string(),
             parent_session: parent_session.map(ToOwned::to_owned),
+            title: None,
         };
 
         let manager = Self {
@@ -282,42 +302,38 @@ impl SessionManager {
         Ok(manager)
     }
 
+    /// Loads a session, migrating older formats in place. Corrupt records are skipped with a
+    /// warning rather than failing the load; `pixy session fsck` quarantines them for good.
     pub fn load(session_file: impl AsRef<Path>) -> Result<Self, String> {
         let session_file = session_file.as_ref().to_path_buf();
-        let file = OpenOptions::new()
-            .read(true)
-            .open(&session_file)
-            .map_err(|error| format!("open session file failed: {error}"))?;
-        let mut lines = BufReader::new(file).lines();
-
-        let header_line = lines
-            .next()
-            .ok_or_else(|| "session file is empty".to_string())?
-            .map_err(|error| format!("read session header failed: {error}"))?;
-        let header: SessionHeader = serde_json::from_str(&header_line)
-            .map_err(|error| format!("parse session header failed: {error}"))?;
+        let session = ValidatedSession::read(&session_file)?;
+        if session.from_version < CURRENT_SESSION_VERSION {
+            session.write(&session_file)?;
+            tracing::info!(
+                session_file = %session_file.display(),
+                from_version = session.from_version,
+                to_version = CURRENT_SESSION_VERSION,
+                "migrated session file"
+            );
+        } else if !session.issues.is_empty() {
+            tracing::warn!(
+                session_file = %session_file.display(),
+                issues = session.issues.len(),
+                "skipped corrupt session records; run `pixy session fsck` to repair"
+            );
+        }
+        let header = session.header;
+        let entries = session.entries;
 
-        let mut entries = Vec::new();
         let mut by_id = HashMap::new();
         let mut leaf_id = None;
         let mut max_numeric_id = 0_u64;
-
-        for line_result in lines {
-            let line =
-                line_result.map_err(|error| format!("read session entry failed: {error}"))?;
-            if line.trim().is_empty() {
-                continue;
-            }
-            let entry: SessionEntry = serde_json::from_str(&line)
-                .map_err(|error| format!("parse session entry failed: {error}"))?;
-
+        for (index, entry) in entries.iter().enumerate() {
             if let Ok(id_value) = u64::from_str_radix(entry.id(), 16) {
                 max_numeric_id = max_numeric_id.max(id_value);
             }
-
-            by_id.insert(entry.id().to_string(), entries.len());
+            by_id.insert(entry.id().to_string(), index);
             leaf_id = Some(entry.id().to_string());
-            entries.push(entry);
         }
 
         let fallback_next_id = entries.len() as u64 + 1;
@@ -333,6 +349,37 @@ impl SessionManager {
         })
     }
 
+    /// Creates a new session file in `session_dir` whose history is the current branch path
+    /// of this session. Entry ids are preserved so later branching keeps working in the fork.
+    pub fn fork(&self, session_dir: impl AsRef<Path>, name: Option<&str>) -> Result<Self, String> {
+        let mut forked =
+            Self::create_with_parent(&self.header.cwd, session_dir, Some(&self.header.id))?;
+        for entry in self.current_path_entries() {
+            forked
+                .by_id
+                .insert(entry.id().to_string(), forked.entries.len());
+            forked.entries.push(entry.clone());
+            forked.append_entry(entry)?;
+        }
+        forked.leaf_id = self.leaf_id.clone();
+        forked.next_id = self.next_id;
+
+        if let Some(name) = name.map(str::trim).filter(|name| !name.is_empty()) {
+            forked.append_session_info(Some(name))?;
+        }
+        Ok(forked)
+    }
+
+    pub fn session_name(&self) -> Option<String> {
+        self.current_path_entries()
+            .iter()
+            .rev()
+            .find_map(|entry| match entry {
+                SessionEntry::SessionInfo { name, .. } => name.clone(),
+                _ => None,
+            })
+    }
+
     pub fn append_message(&mut self, message: Message) -> Result<String, String> {
         let id = format!("{:08x}", self.next_id);
         self.next_id += 1;
@@ -569,6 +616,16 @@ impl SessionManager {
         &self.header
     }
 
+    /// Stores `title` in the session header, rewriting the header line in place.
+    pub fn set_title(&mut self, title: &str) -> Result<(), String> {
+        let title = title.trim();
+        if title.is_empty() {
+            return Err("session title is empty".to_string());
+        }
+        self.header.title = Some(title.to_string());
+        self.rewrite_header()
+    }
+
     pub fn cwd(&self) -> &str {
         &self.header.cwd
     }
@@ -618,6 +675,17 @@ impl SessionManager {
             .count()
     }
 
+    /// Messages on the current branch, including ones already folded into a compaction summary.
+    pub fn current_path_messages(&self) -> Vec<Message> {
+        self.current_path_entries()
+            .into_iter()
+            .filter_map(|entry| match entry {
+                SessionEntry::Message { message, .. } => Some(message.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
     pub fn build_session_context(&self) -> SessionContext {
         let path_entries = self.current_path_entries();
 
@@ -707,6 +775,23 @@ impl SessionManager {
         Ok(())
     }
 
+    /// Replaces the first line of the session file, keeping every entry after it.
+    fn rewrite_header(&self) -> Result<(), String> {
+        let content = fs::read_to_string(&self.session_file)
+            .map_err(|error| format!("read session file failed: {error}"))?;
+        let entries = content
+            .split_once('\n')
+            .map(|(_, rest)| rest)
+            .unwrap_or_default();
+        let line = serde_json::to_string(&self.header)
+            .map_err(|error| format!("serialize header failed: {error}"))?;
+        let temp_file = self.session_file.with_extension("jsonl.tmp");
+        fs::write(&temp_file, format!("{line}\n{entries}"))
+            .map_err(|error| format!("write session file failed: {error}"))?;
+        fs::rename(&temp_file, &self.session_file)
+            .map_err(|error| format!("replace session file failed: {error}"))
+    }
+
     fn append_entry(&self, entry: &SessionEntry) -> Result<(), String> {
         let mut file = OpenOptions::new()
             .append(true)
@@ -722,6 +807,19 @@ impl SessionManager {
     }
 }
 
+fn allocate_session_file(session_dir: &Path) -> (String, PathBuf, String) {
+    let mut millis = now_millis();
+    loop {
+        let timestamp = millis.to_string();
+        let session_id = format!("session-{timestamp}");
+        let session_file = session_dir.join(format!("{session_id}.jsonl"));
+        if !session_file.exists() {
+            return (session_id, session_file, timestamp);
+        }
+        millis += 1;
+    }
+}
+
 fn now_millis() -> u128 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -0,0 +1,428 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Map, Value};
+
+use crate::session_manager::{SessionEntry, SessionHeader, CURRENT_SESSION_VERSION};
+
+/// Suffix of the file that receives records removed from a session file. It is not `.jsonl`, so
+/// quarantined records never show up as sessions.
+pub const SESSION_QUARANTINE_SUFFIX: &str = ".quarantine";
+
+type Migration = fn(&mut [(usize, Value)]);
+
+/// `MIGRATIONS[n]` upgrades entries written at version `n + 1` to version `n + 2`.
+const MIGRATIONS: &[Migration] = &[migrate_v1_to_v2, migrate_v2_to_v3];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionIssueKind {
+    /// The line is not JSON; the record is quarantined.
+    InvalidJson,
+    /// Valid JSON that is not a known session entry; the record is quarantined.
+    InvalidEntry,
+    /// A second entry reusing an id; the later record is quarantined.
+    DuplicateId,
+    /// The entry's parent does not exist; it is re-attached to the preceding entry.
+    MissingParent,
+}
+
+impl SessionIssueKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::InvalidJson => "invalid json",
+            Self::InvalidEntry => "invalid entry",
+            Self::DuplicateId => "duplicate id",
+            Self::MissingParent => "missing parent",
+        }
+    }
+
+    pub fn quarantines(self) -> bool {
+        !matches!(self, Self::MissingParent)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionIssue {
+    /// 1-based line in the session file.
+    pub line: usize,
+    pub kind: SessionIssueKind,
+    pub detail: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionFsckReport {
+    pub session_file: PathBuf,
+    pub from_version: u32,
+    pub issues: Vec<SessionIssue>,
+    /// Set when the file was rewritten (migrated or repaired).
+    pub rewritten: bool,
+    pub quarantine_file: Option<PathBuf>,
+}
+
+impl SessionFsckReport {
+    pub fn needs_migration(&self) -> bool {
+        self.from_version < CURRENT_SESSION_VERSION
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty() && !self.needs_migration()
+    }
+}
+
+/// A session file upgraded to the current version and validated, with every problem that was
+/// worked around recorded in `issues`.
+pub(crate) struct ValidatedSession {
+    pub header: SessionHeader,
+    pub entries: Vec<SessionEntry>,
+    pub from_version: u32,
+    pub issues: Vec<SessionIssue>,
+    /// Raw lines of the records listed in `issues` that could not be kept.
+    quarantined: Vec<(usize, String)>,
+}
+
+impl ValidatedSession {
+    pub(crate) fn read(session_file: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(session_file)
+            .map_err(|error| format!("open session file failed: {error}"))?;
+        Self::parse(&content)
+    }
+
+    pub(crate) fn parse(content: &str) -> Result<Self, String> {
+        let mut lines = content.lines().enumerate();
+        let header_line = lines
+            .next()
+            .map(|(_, line)| line)
+            .filter(|line| !line.trim().is_empty())
+            .ok_or_else(|| "session file is empty".to_string())?;
+        let mut header: SessionHeader = serde_json::from_str(header_line)
+            .map_err(|error| format!("parse session header failed: {error}"))?;
+        let from_version = header.version;
+        if from_version > CURRENT_SESSION_VERSION {
+            return Err(format!(
+                "session format version {from_version} is newer than supported version {CURRENT_SESSION_VERSION}; upgrade pixy to open it"
+            ));
+        }
+
+        let mut issues = Vec::new();
+        let mut quarantined = Vec::new();
+        let mut records = Vec::new();
+        let mut raw_lines = HashMap::new();
+        for (index, line) in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let line_number = index + 1;
+            match serde_json::from_str::<Value>(line) {
+                Ok(value) => {
+                    raw_lines.insert(line_number, line);
+                    records.push((line_number, value));
+                }
+                Err(error) => {
+                    issues.push(SessionIssue {
+                        line: line_number,
+                        kind: SessionIssueKind::InvalidJson,
+                        detail: error.to_string(),
+                    });
+                    quarantined.push((line_number, line.to_string()));
+                }
+            }
+        }
+
+        for migration in MIGRATIONS
+            .iter()
+            .skip(from_version.saturating_sub(1) as usize)
+        {
+            migration(&mut records);
+        }
+        header.version = CURRENT_SESSION_VERSION;
+
+        let mut entries: Vec<SessionEntry> = Vec::new();
+        let mut seen_ids = HashSet::new();
+        for (line_number, value) in records {
+            let mut entry = match serde_json::from_value::<SessionEntry>(value) {
+                Ok(entry) => entry,
+                Err(error) => {
+                    issues.push(SessionIssue {
+                        line: line_number,
+                        kind: SessionIssueKind::InvalidEntry,
+                        detail: error.to_string(),
+                    });
+                    quarantined.push((line_number, raw_lines[&line_number].to_string()));
+                    continue;
+                }
+            };
+            if !seen_ids.insert(entry.id().to_string()) {
+                issues.push(SessionIssue {
+                    line: line_number,
+                    kind: SessionIssueKind::DuplicateId,
+                    detail: format!("id {} is already used", entry.id()),
+                });
+                quarantined.push((line_number, raw_lines[&line_number].to_string()));
+                continue;
+            }
+            if let Some(parent_id) = entry.parent_id() {
+                if !seen_ids.contains(parent_id) {
+                    let previous = entries.last().map(|entry| entry.id().to_string());
+                    issues.push(SessionIssue {
+                        line: line_number,
+                        kind: SessionIssueKind::MissingParent,
+                        detail: format!(
+                            "parent {parent_id} not found; attached to {}",
+                            previous.as_deref().unwrap_or("the session root")
+                        ),
+                    });
+                    entry.set_parent_id(previous);
+                }
+            }
+            entries.push(entry);
+        }
+
+        Ok(Self {
+            header,
+            entries,
+            from_version,
+            issues,
+            quarantined,
+        })
+    }
+
+    /// Rewrites `session_file` in the current format. Quarantined records are appended to the
+    /// quarantine file next to it, which is returned when anything was written there.
+    pub(crate) fn write(&self, session_file: &Path) -> Result<Option<PathBuf>, String> {
+        let quarantine_file = if self.quarantined.is_empty() {
+            None
+        } else {
+            let path = quarantine_path(session_file);
+            let mut content = fs::read_to_string(&path).unwrap_or_default();
+            for (line, raw) in &self.quarantined {
+                let issue = self.issues.iter().find(|issue| issue.line == *line);
+                let record = json!({
+                    "line": line,
+                    "kind": issue.map(|issue| issue.kind.label()),
+                    "detail": issue.map(|issue| issue.detail.as_str()),
+                    "raw": raw,
+                });
+                content.push_str(&record.to_string());
+                content.push('\n');
+            }
+            fs::write(&path, content)
+                .map_err(|error| format!("write session quarantine failed: {error}"))?;
+            Some(path)
+        };
+
+        let mut content = serde_json::to_string(&self.header)
+            .map_err(|error| format!("serialize header failed: {error}"))?;
+        content.push('\n');
+        for entry in &self.entries {
+            let line = serde_json::to_string(entry)
+                .map_err(|error| format!("serialize session entry failed: {error}"))?;
+            content.push_str(&line);
+            content.push('\n');
+        }
+        let temp_file = session_file.with_extension("jsonl.tmp");
+        fs::write(&temp_file, content)
+            .map_err(|error| format!("write session file failed: {error}"))?;
+        fs::rename(&temp_file, session_file)
+            .map_err(|error| format!("replace session file failed: {error}"))?;
+        Ok(quarantine_file)
+    }
+}
+
+/// Validates a session file, migrating it to the current format and quarantining corrupt
+/// records when `repair` is set. Without `repair` the file is left untouched.
+pub fn fsck_session_file(session_file: &Path, repair: bool) -> Result<SessionFsckReport, String> {
+    let session = ValidatedSession::read(session_file)?;
+    let mut report = SessionFsckReport {
+        session_file: session_file.to_path_buf(),
+        from_version: session.from_version,
+        issues: session.issues.clone(),
+        rewritten: false,
+        quarantine_file: None,
+    };
+    if repair && !report.is_clean() {
+        report.quarantine_file = session.write(session_file)?;
+        report.rewritten = true;
+    }
+    Ok(report)
+}
+
+pub(crate) fn quarantine_path(session_file: &Path) -> PathBuf {
+    let mut name = session_file
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    name.push(SESSION_QUARANTINE_SUFFIX);
+    session_file.with_file_name(name)
+}
+
+/// Version 1 entries had no ids and formed a single linear history. They get sequential ids and
+/// a parent chain; compactions switch from `firstKeptEntryIndex` to `firstKeptEntryId`.
+fn migrate_v1_to_v2(records: &mut [(usize, Value)]) {
+    let mut next_id = records
+        .iter()
+        .filter_map(|(_, value)| value.get("id")?.as_str())
+        .filter_map(|id| u64::from_str_radix(id, 16).ok())
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let mut ids = Vec::with_capacity(records.len());
+    let mut previous: Option<String> = None;
+    for (_, value) in records.iter_mut() {
+        let Some(object) = value.as_object_mut() else {
+            ids.push(None);
+            continue;
+        };
+        let id = match object.get("id").and_then(Value::as_str) {
+            Some(id) => id.to_string(),
+            None => {
+                let id = format!("{next_id:08x}");
+                next_id += 1;
+                object.insert("id".to_string(), Value::String(id.clone()));
+                id
+            }
+        };
+        if !object.contains_key("parentId") {
+            object.insert(
+                "parentId".to_string(),
+                previous.clone().map(Value::String).unwrap_or(Value::Null),
+            );
+        }
+        ids.push(Some(id.clone()));
+        previous = Some(id);
+    }
+
+    for (_, value) in records.iter_mut() {
+        let Some(object) = value.as_object_mut() else {
+            continue;
+        };
+        let Some(index) = object.remove("firstKeptEntryIndex") else {
+            continue;
+        };
+        if let Some(id) = index
+            .as_u64()
+            .and_then(|index| ids.get(index as usize).cloned().flatten())
+        {
+            object.insert("firstKeptEntryId".to_string(), Value::String(id));
+        }
+    }
+}
+
+/// Version 2 stored extension messages as `message` entries with a `hookMessage` role. Version 3
+/// gives them their own `custom_message` entry type.
+fn migrate_v2_to_v3(records: &mut [(usize, Value)]) {
+    for (_, value) in records.iter_mut() {
+        let Some(object) = value.as_object_mut() else {
+            continue;
+        };
+        let is_hook_message = object.get("type").and_then(Value::as_str) == Some("message")
+            && object
+                .get("message")
+                .and_then(|message| message.get("role"))
+                .and_then(Value::as_str)
+                == Some("hookMessage");
+        if !is_hook_message {
+            continue;
+        }
+        let Some(Value::Object(message)) = object.remove("message") else {
+            continue;
+        };
+        let mut converted = Map::new();
+        converted.insert("type".to_string(), json!("custom_message"));
+        for key in ["id", "parentId", "timestamp"] {
+            if let Some(field) = object.remove(key) {
+                converted.insert(key.to_string(), field);
+            }
+        }
+        converted.insert(
+            "customType".to_string(),
+            message
+                .get("customType")
+                .cloned()
+                .unwrap_or_else(|| json!("hook")),
+        );
+        converted.insert(
+            "content".to_string(),
+            message.get("content").cloned().unwrap_or_else(|| json!("")),
+        );
+        if let Some(details) = message.get("details").filter(|details| !details.is_null()) {
+            converted.insert("details".to_string(), details.clone());
+        }
+        converted.insert(
+            "display".to_string(),
+            message.get("display").cloned().unwrap_or(json!(true)),
+        );
+        *value = Value::Object(converted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_migrates_v1_entries_to_current_version() {
+        let content = [
+            r#"{"type":"session","version":1,"id":"s1","timestamp":"1","cwd":"/repo"}"#,
+            r#"{"type":"message","timestamp":"2","message":{"role":"user","content":"hi","timestamp":2}}"#,
+            r#"{"type":"message","timestamp":"3","message":{"role":"hookMessage","customType":"note","content":"from a hook","display":false,"timestamp":3}}"#,
+            r#"{"type":"compaction","timestamp":"4","summary":"earlier","firstKeptEntryIndex":1,"tokensBefore":10}"#,
+        ]
+        .join("\n");
+
+        let session = ValidatedSession::parse(&content).expect("parse v1 session");
+        assert_eq!(session.from_version, 1);
+        assert_eq!(session.header.version, CURRENT_SESSION_VERSION);
+        assert!(session.issues.is_empty(), "{:?}", session.issues);
+        let values = session
+            .entries
+            .iter()
+            .map(|entry| serde_json::to_value(entry).expect("entry json"))
+            .collect::<Vec<_>>();
+        assert_eq!(values[0]["id"], "00000001");
+        assert_eq!(values[0]["parentId"], Value::Null);
+        assert_eq!(values[1]["type"], "custom_message");
+        assert_eq!(values[1]["customType"], "note");
+        assert_eq!(values[1]["display"], false);
+        assert_eq!(values[1]["parentId"], "00000001");
+        assert_eq!(values[2]["firstKeptEntryId"], "00000002");
+    }
+
+    #[test]
+    fn parse_reports_corrupt_records_and_rejects_newer_versions() {
+        let content = [
+            r#"{"type":"session","version":3,"id":"s1","timestamp":"1","cwd":"/repo"}"#,
+            r#"{"type":"message","id":"a","parentId":null,"timestamp":"2","message":{"role":"user","content":"hi","timestamp":2}}"#,
+            r#"{"type":"message","id":"b","parentId":"a","timestamp":"3","mess"#,
+            r#"{"type":"mystery","id":"c","parentId":"a","timestamp":"4"}"#,
+            r#"{"type":"label","id":"a","parentId":null,"timestamp":"5","targetId":"a"}"#,
+            r#"{"type":"session_info","id":"d","parentId":"zz","timestamp":"6","name":"x"}"#,
+        ]
+        .join("\n");
+
+        let session = ValidatedSession::parse(&content).expect("parse damaged session");
+        let kinds = session
+            .issues
+            .iter()
+            .map(|issue| (issue.line, issue.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                (3, SessionIssueKind::InvalidJson),
+                (4, SessionIssueKind::InvalidEntry),
+                (5, SessionIssueKind::DuplicateId),
+                (6, SessionIssueKind::MissingParent),
+            ]
+        );
+        assert_eq!(session.entries.len(), 2);
+        assert_eq!(session.entries[1].parent_id(), Some("a"));
+        assert_eq!(session.quarantined.len(), 3);
+
+        let newer = r#"{"type":"session","version":99,"id":"s1","timestamp":"1","cwd":"/repo"}"#;
+        let error = ValidatedSession::parse(newer)
+            .err()
+            .expect("newer version is rejected");
+        assert!(error.contains("upgrade pixy"), "{error}");
+    }
+}
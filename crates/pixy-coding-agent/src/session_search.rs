@@ -0,0 +1,207 @@
+use std::path::{Path, PathBuf};
+
+use pixy_ai::{AssistantContentBlock, Message, UserContent, UserContentBlock};
+
+use crate::agent_session::{list_session_files, session_resume_candidate_from_manager};
+use crate::cli_app::resolve_resume_target_without_active_session;
+use crate::SessionManager;
+
+const SNIPPET_CONTEXT_CHARS: usize = 40;
+
+/// One stored session as shown by `pixy sessions list` and `pixy sessions find`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionListing {
+    pub path: PathBuf,
+    pub id: String,
+    pub title: String,
+    pub cwd: String,
+    /// Number of user prompts on the current branch.
+    pub turn_count: usize,
+    pub updated_at: String,
+    /// Message text around the first query term, when the match came from the conversation.
+    pub snippet: Option<String>,
+}
+
+/// Session file for `target` (a path, file name, fragment or `latest`), as `/resume` resolves it.
+pub fn find_session_file(
+    target: Option<&str>,
+    cwd: &Path,
+    session_dir: &Path,
+) -> Result<PathBuf, String> {
+    resolve_resume_target_without_active_session(target, cwd, session_dir)
+}
+
+/// Sessions in `session_dir`, newest first. Files that fail to parse are skipped.
+pub fn list_sessions(session_dir: &Path) -> Result<Vec<SessionListing>, String> {
+    Ok(load_sessions(session_dir)?
+        .into_iter()
+        .map(|(listing, _)| listing)
+        .collect())
+}
+
+/// Sessions whose title, working directory or user/assistant text contain every
+/// whitespace-separated term of `query`, case-insensitively. Newest first.
+pub fn find_sessions(session_dir: &Path, query: &str) -> Result<Vec<SessionListing>, String> {
+    let terms = query
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    if terms.is_empty() {
+        return Err("search query is empty".to_string());
+    }
+
+    Ok(load_sessions(session_dir)?
+        .into_iter()
+        .filter_map(|(mut listing, text)| {
+            let haystack = format!("{}\n{}\n{text}", listing.title, listing.cwd).to_lowercase();
+            if !terms.iter().all(|term| haystack.contains(term.as_str())) {
+                return None;
+            }
+            listing.snippet = snippet(&text, &terms[0]);
+            Some(listing)
+        })
+        .collect())
+}
+
+fn load_sessions(session_dir: &Path) -> Result<Vec<(SessionListing, String)>, String> {
+    if !session_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files = list_session_files(session_dir)?;
+    files.sort_by(|left, right| right.file_name().cmp(&left.file_name()));
+
+    Ok(files
+        .into_iter()
+        .filter_map(|path| {
+            let manager = SessionManager::load(&path).ok()?;
+            let text = conversation_text(&manager.current_path_messages());
+            let candidate = session_resume_candidate_from_manager(path, &manager);
+            let listing = SessionListing {
+                path: candidate.path,
+                id: manager.header().id.clone(),
+                title: candidate.title,
+                cwd: candidate.cwd,
+                turn_count: candidate.turn_count,
+                updated_at: candidate.updated_at,
+                snippet: None,
+            };
+            Some((listing, text))
+        })
+        .collect())
+}
+
+fn conversation_text(messages: &[Message]) -> String {
+    let mut parts = Vec::new();
+    for message in messages {
+        match message {
+            Message::User {
+                content: UserContent::Text(text),
+                ..
+            } => parts.push(text.as_str()),
+            Message::User {
+                content: UserContent::Blocks(blocks),
+                ..
+            } => parts.extend(blocks.iter().filter_map(|block| match block {
+                UserContentBlock::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })),
+            Message::Assistant { content, .. } => {
+                parts.extend(content.iter().filter_map(|block| match block {
+                    AssistantContentBlock::Text { text, .. } => Some(text.as_str()),
+                    _ => None,
+                }))
+            }
+            Message::ToolResult { .. } => {}
+        }
+    }
+    parts.join("\n")
+}
+
+/// A single-line excerpt of `text` around the first occurrence of `term`.
+fn snippet(text: &str, term: &str) -> Option<String> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let lowered = chars
+        .iter()
+        .map(|ch| ch.to_lowercase().next().unwrap_or(*ch))
+        .collect::<String>();
+    let byte_index = lowered.find(term)?;
+    let start = lowered[..byte_index].chars().count();
+    let end = start + term.chars().count();
+    let from = start.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let to = (end + SNIPPET_CONTEXT_CHARS).min(chars.len());
+
+    let excerpt = chars[from..to]
+        .iter()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let prefix = if from > 0 { "…" } else { "" };
+    let suffix = if to < chars.len() { "…" } else { "" };
+    Some(format!("{prefix}{excerpt}{suffix}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use pixy_ai::{Message, UserContent};
+
+    use super::*;
+
+    fn user(text: &str) -> Message {
+        Message::User {
+            content: UserContent::Text(text.to_string()),
+            timestamp: 1_700_000_000_000,
+        }
+    }
+
+    #[test]
+    fn find_sessions_matches_title_cwd_and_message_text() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let session_dir = dir.path().join("sessions");
+
+        let mut websocket = SessionManager::create("/work/server", &session_dir).expect("create");
+        websocket
+            .append_message(user("the WebSocket handshake times out after upgrade"))
+            .expect("append");
+        websocket
+            .set_title("Debug websocket timeout")
+            .expect("title");
+        let mut docs = SessionManager::create("/work/docs", &session_dir).expect("create");
+        docs.append_message(user("rewrite the install guide"))
+            .expect("append");
+        docs.append_message(user("and mention the windows installer"))
+            .expect("append");
+
+        let listed = list_sessions(&session_dir).expect("list");
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].cwd, "/work/docs");
+        assert_eq!(listed[0].turn_count, 2);
+        assert_eq!(listed[1].title, "Debug websocket timeout");
+
+        let found = find_sessions(&session_dir, "handshake UPGRADE").expect("find");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, websocket.header().id);
+        assert_eq!(
+            found[0].snippet.as_deref(),
+            Some("the WebSocket handshake times out after upgrade")
+        );
+
+        let by_cwd = find_sessions(&session_dir, "work/docs").expect("find by cwd");
+        assert_eq!(by_cwd.len(), 1);
+        assert_eq!(by_cwd[0].snippet, None);
+
+        assert!(find_sessions(&session_dir, "kubernetes")
+            .expect("find nothing")
+            .is_empty());
+        assert!(find_sessions(&session_dir, "  ").is_err());
+    }
+
+    #[test]
+    fn snippet_trims_long_text_around_term() {
+        let text = format!("{} needle {}", "a".repeat(100), "b".repeat(100));
+        let excerpt = snippet(&text, "needle").expect("snippet");
+        assert!(excerpt.starts_with('…') && excerpt.ends_with('…'));
+        assert!(excerpt.contains("needle"));
+        assert!(excerpt.chars().count() <= 2 * SNIPPET_CONTEXT_CHARS + 8);
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use serde::Deserialize;
 
@@ -15,6 +16,16 @@ pub enum SkillSource {
     Path,
 }
 
+impl SkillSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Project => "project",
+            Self::Path => "path",
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Skill {
     pub name: String,
@@ -23,6 +34,20 @@ pub struct Skill {
     pub base_dir: PathBuf,
     pub source: SkillSource,
     pub disable_model_invocation: bool,
+    /// Arguments declared in frontmatter and substituted for `{{name}}` placeholders when the
+    /// skill is invoked with `/skill <name> key=value`.
+    pub arguments: Vec<SkillArgument>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct SkillArgument {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub default: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -93,28 +118,8 @@ impl SkillLoader {
     }
 
     fn load_default_sources(&mut self) {
-        self.merge(load_skills_from_dir(
-            &self.options.agent_dir.join("skills"),
-            SkillSource::User,
-        ));
-
-        if let Some(home) = home_dir() {
-            self.merge(load_skills_from_dir(
-                &home.join(".agents").join("skills"),
-                SkillSource::User,
-            ));
-        }
-
-        self.merge(load_skills_from_dir(
-            &self.options.cwd.join(PIXY_CONFIG_DIR_NAME).join("skills"),
-            SkillSource::Project,
-        ));
-
-        for dir in project_ancestor_dirs(&self.options.cwd) {
-            self.merge(load_skills_from_dir(
-                &dir.join(".agents").join("skills"),
-                SkillSource::Project,
-            ));
+        for (dir, source) in default_skill_dirs(&self.options) {
+            self.merge(load_skills_from_dir(&dir, source));
         }
     }
 
@@ -187,6 +192,195 @@ impl SkillLoader {
     }
 }
 
+fn default_skill_dirs(options: &LoadSkillsOptions) -> Vec<(PathBuf, SkillSource)> {
+    let mut dirs = vec![(options.agent_dir.join("skills"), SkillSource::User)];
+    if let Some(home) = home_dir() {
+        dirs.push((home.join(".agents").join("skills"), SkillSource::User));
+    }
+    dirs.push((
+        options.cwd.join(PIXY_CONFIG_DIR_NAME).join("skills"),
+        SkillSource::Project,
+    ));
+    for dir in project_ancestor_dirs(&options.cwd) {
+        dirs.push((dir.join(".agents").join("skills"), SkillSource::Project));
+    }
+    dirs
+}
+
+/// Loaded skills plus the state needed to reload them when their files change and to toggle
+/// individual skills for the current session.
+#[derive(Clone, Debug)]
+pub struct SkillCatalog {
+    options: LoadSkillsOptions,
+    loaded: LoadSkillsResult,
+    disabled: HashSet<String>,
+    fingerprint: Vec<(PathBuf, Option<SystemTime>, u64)>,
+}
+
+impl SkillCatalog {
+    pub fn load(options: LoadSkillsOptions) -> Self {
+        let loaded = load_skills(options.clone());
+        Self::from_loaded(options, loaded)
+    }
+
+    /// Wraps an already loaded result so the first [`Self::reload_if_changed`] only reloads when
+    /// files changed after `loaded` was produced.
+    pub fn from_loaded(options: LoadSkillsOptions, loaded: LoadSkillsResult) -> Self {
+        let fingerprint = skill_files_fingerprint(&options);
+        Self {
+            options,
+            loaded,
+            disabled: HashSet::new(),
+            fingerprint,
+        }
+    }
+
+    pub fn skills(&self) -> &[Skill] {
+        &self.loaded.skills
+    }
+
+    pub fn diagnostics(&self) -> &[SkillDiagnostic] {
+        &self.loaded.diagnostics
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.disabled.contains(name)
+    }
+
+    pub fn enabled_skills(&self) -> Vec<Skill> {
+        self.loaded
+            .skills
+            .iter()
+            .filter(|skill| self.is_enabled(&skill.name))
+            .cloned()
+            .collect()
+    }
+
+    /// Enables or disables a loaded skill. Returns whether the state changed.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<bool, String> {
+        if !self.loaded.skills.iter().any(|skill| skill.name == name) {
+            return Err(format!("unknown skill: {name}"));
+        }
+        Ok(if enabled {
+            self.disabled.remove(name)
+        } else {
+            self.disabled.insert(name.to_string())
+        })
+    }
+
+    pub fn reload(&mut self) {
+        self.fingerprint = skill_files_fingerprint(&self.options);
+        self.loaded = load_skills(self.options.clone());
+    }
+
+    /// Reloads skills when any file under the skill directories was added, removed or modified
+    /// since the last load. Returns whether a reload happened.
+    pub fn reload_if_changed(&mut self) -> bool {
+        if skill_files_fingerprint(&self.options) == self.fingerprint {
+            return false;
+        }
+        self.reload();
+        true
+    }
+
+    /// Human-readable listing used by the `/skills` command.
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = vec![];
+        if self.loaded.skills.is_empty() {
+            lines.push("no skills loaded".to_string());
+        }
+        for skill in &self.loaded.skills {
+            let state = if self.is_enabled(&skill.name) {
+                "on "
+            } else {
+                "off"
+            };
+            let mut line = format!(
+                "[{state}] {} ({}) {}",
+                skill.name,
+                skill.source.as_str(),
+                skill.file_path.display()
+            );
+            if !skill.arguments.is_empty() {
+                let arguments = skill
+                    .arguments
+                    .iter()
+                    .map(|argument| {
+                        if argument.required {
+                            format!("{}*", argument.name)
+                        } else {
+                            argument.name.clone()
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                line.push_str(&format!(" args: {}", arguments.join(", ")));
+            }
+            lines.push(line);
+        }
+        for diagnostic in &self.loaded.diagnostics {
+            let kind = match diagnostic.kind {
+                SkillDiagnosticKind::Warning => "warning",
+                SkillDiagnosticKind::Collision => "collision",
+            };
+            lines.push(format!(
+                "{kind}: {} ({})",
+                diagnostic.message,
+                diagnostic.path.display()
+            ));
+        }
+        lines
+    }
+}
+
+fn skill_files_fingerprint(options: &LoadSkillsOptions) -> Vec<(PathBuf, Option<SystemTime>, u64)> {
+    let mut roots = vec![];
+    if options.include_defaults {
+        roots.extend(default_skill_dirs(options).into_iter().map(|(dir, _)| dir));
+    }
+    roots.extend(
+        options
+            .skill_paths
+            .iter()
+            .map(|raw_path| resolve_skill_path(raw_path, &options.cwd)),
+    );
+
+    let mut entries = vec![];
+    for root in roots {
+        collect_fingerprint_entries(&root, &mut entries);
+    }
+    entries.sort();
+    entries
+}
+
+fn collect_fingerprint_entries(path: &Path, out: &mut Vec<(PathBuf, Option<SystemTime>, u64)>) {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return;
+    };
+    out.push((
+        path.to_path_buf(),
+        metadata.modified().ok(),
+        if metadata.is_file() {
+            metadata.len()
+        } else {
+            0
+        },
+    ));
+    if !metadata.is_dir() {
+        return;
+    }
+    let Ok(read_dir) = std::fs::read_dir(path) else {
+        return;
+    };
+    for entry in read_dir.filter_map(Result::ok) {
+        let name = entry.file_name();
+        let name_text = name.to_string_lossy();
+        if name_text.starts_with('.') || name_text == "node_modules" {
+            continue;
+        }
+        collect_fingerprint_entries(&entry.path(), out);
+    }
+}
+
 pub fn load_skills_from_dir(dir: &Path, source: SkillSource) -> LoadSkillsResult {
     let mut result = LoadSkillsResult::default();
     load_skills_from_dir_internal(dir, &source, true, &mut result);
@@ -248,7 +442,7 @@ fn load_skills_from_dir_internal(
         Ok(read_dir) => read_dir.filter_map(Result::ok).collect::<Vec<_>>(),
         Err(_) => return,
     };
-    entries.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    entries.sort_by_key(|entry| entry.file_name());
 
     for entry in entries {
         let name = entry.file_name();
@@ -371,6 +565,7 @@ fn load_skill_from_file(
                 .to_path_buf(),
             source,
             disable_model_invocation: frontmatter.disable_model_invocation,
+            arguments: frontmatter.arguments,
         }),
         diagnostics,
     )
@@ -383,6 +578,147 @@ struct SkillFrontmatter {
     #[serde(default)]
     #[serde(rename = "disable-model-invocation")]
     disable_model_invocation: bool,
+    #[serde(default)]
+    arguments: Vec<SkillArgument>,
+}
+
+/// Splits `/skill` arguments into the skill name and `key=value` pairs. Values may be wrapped in
+/// double quotes to include whitespace.
+pub fn parse_skill_invocation(input: &str) -> Result<(String, HashMap<String, String>), String> {
+    let tokens = split_invocation_tokens(input)?;
+    let mut tokens = tokens.into_iter();
+    let name = tokens
+        .next()
+        .ok_or_else(|| "usage: /skill <name> [key=value ...]".to_string())?;
+    let mut arguments = HashMap::new();
+    for token in tokens {
+        let (key, value) = token
+            .split_once('=')
+            .filter(|(key, _)| !key.is_empty())
+            .ok_or_else(|| format!("skill argument must be key=value: {token}"))?;
+        arguments.insert(key.to_string(), value.to_string());
+    }
+    Ok((name, arguments))
+}
+
+/// Renders a skill body with the given arguments, applying declared defaults and rejecting
+/// unknown or missing required arguments.
+pub fn render_skill(skill: &Skill, arguments: &HashMap<String, String>) -> Result<String, String> {
+    let content = std::fs::read_to_string(&skill.file_path)
+        .map_err(|error| format!("read {} failed: {error}", skill.file_path.display()))?;
+
+    let mut unknown = arguments
+        .keys()
+        .filter(|key| {
+            !skill
+                .arguments
+                .iter()
+                .any(|argument| &argument.name == *key)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        unknown.sort();
+        return Err(format!(
+            "unknown argument(s) for skill {}: {}",
+            skill.name,
+            unknown.join(", ")
+        ));
+    }
+
+    let mut values = HashMap::new();
+    for argument in &skill.arguments {
+        match arguments.get(&argument.name).or(argument.default.as_ref()) {
+            Some(value) => {
+                values.insert(argument.name.as_str(), value.as_str());
+            }
+            None if argument.required => {
+                return Err(format!(
+                    "missing required argument for skill {}: {}",
+                    skill.name, argument.name
+                ));
+            }
+            None => {
+                values.insert(argument.name.as_str(), "");
+            }
+        }
+    }
+
+    let body = substitute_placeholders(strip_frontmatter(&content), &values);
+    Ok(format!(
+        "Use the \"{}\" skill (base directory: {}).\n\n{}",
+        skill.name,
+        skill.base_dir.display(),
+        body.trim()
+    ))
+}
+
+fn split_invocation_tokens(input: &str) -> Result<Vec<String>, String> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_token = false;
+    for ch in input.chars() {
+        match ch {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_token = true;
+            }
+            ch if ch.is_whitespace() && !in_quotes => {
+                if has_token {
+                    tokens.push(std::mem::take(&mut current));
+                    has_token = false;
+                }
+            }
+            ch => {
+                current.push(ch);
+                has_token = true;
+            }
+        }
+    }
+    if in_quotes {
+        return Err("unterminated quote in skill arguments".to_string());
+    }
+    if has_token {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+fn strip_frontmatter(content: &str) -> String {
+    let normalized = content.replace("\r\n", "\n").replace('\r', "\n");
+    if let Some(rest) = normalized.strip_prefix("---\n") {
+        if let Some(end) = rest.find("\n---") {
+            let after = &rest[end + 4..];
+            return after
+                .split_once('\n')
+                .map(|(_, body)| body)
+                .unwrap_or_default()
+                .to_string();
+        }
+    }
+    normalized
+}
+
+fn substitute_placeholders(body: String, values: &HashMap<&str, &str>) -> String {
+    let mut output = String::with_capacity(body.len());
+    let mut rest = body.as_str();
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after_open = &rest[start + 2..];
+        let Some(end) = after_open.find("}}") else {
+            output.push_str(&rest[start..]);
+            return output;
+        };
+        let key = after_open[..end].trim();
+        match values.get(key) {
+            Some(value) => output.push_str(value),
+            None => output.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after_open[end + 2..];
+    }
+    output.push_str(rest);
+    output
 }
 
 fn parse_frontmatter(content: &str) -> Result<SkillFrontmatter, String> {
@@ -587,6 +923,7 @@ description: A valid skill for testing.
                 base_dir: PathBuf::from("/skills/visible"),
                 source: SkillSource::Path,
                 disable_model_invocation: false,
+                arguments: vec![],
             },
             Skill {
                 name: "hidden-skill".to_string(),
@@ -595,6 +932,7 @@ description: A valid skill for testing.
                 base_dir: PathBuf::from("/skills/hidden"),
                 source: SkillSource::Path,
                 disable_model_invocation: true,
+                arguments: vec![],
             },
         ];
 
@@ -779,4 +1117,107 @@ description: beta description
         assert_eq!(object_result.skills, public_result.skills);
         assert_eq!(object_result.diagnostics, public_result.diagnostics);
     }
+
+    #[test]
+    fn skill_catalog_reloads_when_skill_files_change() {
+        let dir = tempdir().expect("temp dir");
+        let skills_root = dir.path().join("extra");
+        std::fs::create_dir_all(&skills_root).expect("create skills root");
+
+        let mut options =
+            LoadSkillsOptions::new(dir.path().to_path_buf(), dir.path().join(".pixy/agents"));
+        options.include_defaults = false;
+        options.skill_paths = vec!["extra".to_string()];
+
+        let mut catalog = SkillCatalog::load(options);
+        assert!(catalog.skills().is_empty());
+        assert!(!catalog.reload_if_changed());
+
+        let skill_dir = skills_root.join("new-skill");
+        std::fs::create_dir_all(&skill_dir).expect("create skill dir");
+        std::fs::write(
+            skill_dir.join("SKILL.md"),
+            r#"---
+name: new-skill
+description: added while running.
+---
+"#,
+        )
+        .expect("write skill");
+
+        assert!(catalog.reload_if_changed());
+        assert_eq!(catalog.skills().len(), 1);
+        assert_eq!(catalog.skills()[0].name, "new-skill");
+        assert!(!catalog.reload_if_changed());
+    }
+
+    #[test]
+    fn render_skill_substitutes_arguments_and_defaults() {
+        let dir = tempdir().expect("temp dir");
+        let skill_dir = dir.path().join("release-notes");
+        std::fs::create_dir_all(&skill_dir).expect("create skill dir");
+        std::fs::write(
+            skill_dir.join("SKILL.md"),
+            r#"---
+name: release-notes
+description: Draft release notes.
+arguments:
+  - name: version
+    required: true
+  - name: audience
+    default: users
+---
+Write notes for {{version}} aimed at {{ audience }}. Keep {{unknown}} as is.
+"#,
+        )
+        .expect("write skill");
+        let result = load_skills_from_dir(dir.path(), SkillSource::Path);
+        let skill = &result.skills[0];
+        assert_eq!(skill.arguments.len(), 2);
+
+        let (name, arguments) =
+            parse_skill_invocation(r#"release-notes version="v1.2 beta""#).expect("parse");
+        assert_eq!(name, "release-notes");
+        let rendered = render_skill(skill, &arguments).expect("render");
+        assert!(rendered.contains("Write notes for v1.2 beta aimed at users."));
+        assert!(rendered.contains("{{unknown}}"));
+        assert!(!rendered.contains("description:"));
+
+        let missing = render_skill(skill, &HashMap::new()).expect_err("version is required");
+        assert!(missing.contains("version"));
+        let (_, unknown) = parse_skill_invocation("release-notes tone=dry").expect("parse");
+        assert!(render_skill(skill, &unknown)
+            .expect_err("tone is not declared")
+            .contains("tone"));
+        assert!(parse_skill_invocation("release-notes version").is_err());
+    }
+
+    #[test]
+    fn skill_catalog_toggles_skills_per_session() {
+        let dir = tempdir().expect("temp dir");
+        let skill_dir = dir.path().join("extra").join("toggle-skill");
+        std::fs::create_dir_all(&skill_dir).expect("create skill dir");
+        std::fs::write(
+            skill_dir.join("SKILL.md"),
+            r#"---
+name: toggle-skill
+description: can be disabled.
+---
+"#,
+        )
+        .expect("write skill");
+
+        let mut options =
+            LoadSkillsOptions::new(dir.path().to_path_buf(), dir.path().join(".pixy/agents"));
+        options.include_defaults = false;
+        options.skill_paths = vec!["extra".to_string()];
+        let mut catalog = SkillCatalog::load(options);
+
+        assert_eq!(catalog.set_enabled("toggle-skill", false), Ok(true));
+        assert!(catalog.enabled_skills().is_empty());
+        assert!(catalog.describe()[0].starts_with("[off] toggle-skill (path)"));
+        assert_eq!(catalog.set_enabled("toggle-skill", true), Ok(true));
+        assert_eq!(catalog.enabled_skills().len(), 1);
+        assert!(catalog.set_enabled("missing", false).is_err());
+    }
 }
@@ -1,8 +1,10 @@
 use std::path::{Path, PathBuf};
 
+use crate::project_memory::PROJECT_MEMORY_FILE;
 use crate::{format_skills_for_prompt, Skill, SkillSource, SubAgentSpec};
 use chrono::Local;
 use pixy_agent_core::AgentTool;
+use pixy_ai::Model;
 
 const DEFAULT_PROMPT_INTRO: &str = "You are pixy, an expert coding assistant and coding agent harness. You help users by reading files, executing commands, editing code, and writing new files.";
 
@@ -11,12 +13,44 @@ pub fn build_system_prompt(
     cwd: &Path,
     tools: &[AgentTool],
     skills: &[Skill],
+    model: Option<&Model>,
 ) -> String {
     let now_text = Local::now()
         .format("%A, %B %-d, %Y, %I:%M:%S %p %Z")
         .to_string();
     let tool_names: Vec<&str> = tools.iter().map(|tool| tool.name.as_str()).collect();
-    build_system_prompt_with_now(custom_prompt, cwd, &tool_names, skills, &now_text)
+    let variables = PromptVariables::current(cwd, model);
+    build_system_prompt_with_now(
+        custom_prompt,
+        cwd,
+        &tool_names,
+        skills,
+        &now_text,
+        &variables,
+    )
+}
+
+/// Values substituted for `{cwd}`, `{git_branch}`, `{date}` and `{model}` in a custom system
+/// prompt. `{skills}` is filled from the skills passed to [`build_system_prompt`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PromptVariables {
+    pub cwd: String,
+    pub git_branch: String,
+    pub date: String,
+    pub model: String,
+}
+
+impl PromptVariables {
+    pub fn current(cwd: &Path, model: Option<&Model>) -> Self {
+        Self {
+            cwd: cwd.display().to_string(),
+            git_branch: git_branch(cwd).unwrap_or_default(),
+            date: Local::now().format("%Y-%m-%d").to_string(),
+            model: model
+                .map(|model| format!("{}/{}", model.provider, model.id))
+                .unwrap_or_default(),
+        }
+    }
 }
 
 pub fn append_multi_agent_prompt_section(
@@ -56,18 +90,27 @@ fn build_system_prompt_with_now(
     selected_tools: &[&str],
     skills: &[Skill],
     now_text: &str,
+    variables: &PromptVariables,
 ) -> String {
-    let mut prompt = build_default_prompt(custom_prompt, cwd, selected_tools);
     let has_read_tool = selected_tools.contains(&"read");
-    if has_read_tool {
-        let skills_prompt = format_skills_for_prompt(skills);
-        if !skills_prompt.is_empty() {
-            prompt.push_str(&skills_prompt);
-        }
+    let skills_prompt = if has_read_tool {
+        format_skills_for_prompt(skills)
+    } else {
+        String::new()
+    };
+    let body = resolve_prompt_body(custom_prompt, cwd);
+    let places_skills = body.contains("{skills}");
+    let body = render_prompt_template(&body, variables, skills_prompt.trim());
+    let mut prompt = build_default_prompt(&body, selected_tools);
+    if !places_skills && !skills_prompt.is_empty() {
+        prompt.push_str(&skills_prompt);
     }
     if let Some(workspace_agents) = load_workspace_agents_prompt(cwd) {
         append_prompt_section(&mut prompt, &workspace_agents);
     }
+    if let Some(project_memory) = load_project_memory_prompt(cwd) {
+        append_prompt_section(&mut prompt, &project_memory);
+    }
     let workspace_skills = format_workspace_skills_for_prompt(cwd, skills);
     if !workspace_skills.is_empty() {
         append_prompt_section(&mut prompt, &workspace_skills);
@@ -118,6 +161,15 @@ fn load_workspace_agents_prompt(cwd: &Path) -> Option<String> {
     ))
 }
 
+fn load_project_memory_prompt(cwd: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(cwd.join(PROJECT_MEMORY_FILE)).ok()?;
+    let trimmed = content.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    Some(format!("<PROJECT_MEMORY>\n{trimmed}\n</PROJECT_MEMORY>"))
+}
+
 fn find_workspace_context_file(cwd: &Path) -> Option<PathBuf> {
     for name in ["AGENTS.md", "CLAUDE.md"] {
         let candidate = cwd.join(name);
@@ -189,12 +241,7 @@ fn escape_xml(value: &str) -> String {
         .replace('>', "&gt;")
 }
 
-fn build_default_prompt(
-    custom_prompt: Option<&str>,
-    cwd: &Path,
-    selected_tools: &[&str],
-) -> String {
-    let prompt = resolve_prompt_body(custom_prompt, cwd);
+fn build_default_prompt(prompt: &str, selected_tools: &[&str]) -> String {
     let tool_lines: Vec<String> = selected_tools
         .iter()
         .copied()
@@ -261,20 +308,116 @@ fn resolve_prompt_body(custom_prompt: Option<&str>, cwd: &Path) -> String {
     }
 }
 
+/// Substitutes known `{variable}` placeholders. Anything else in braces is left alone, so code
+/// and JSON in a prompt survive; `{{name}}` yields a literal `{name}`.
+fn render_prompt_template(template: &str, variables: &PromptVariables, skills: &str) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        if let Some(name) = tail
+            .strip_prefix("{{")
+            .and_then(|inner| inner.split_once("}}"))
+            .map(|(name, _)| name)
+            .filter(|name| prompt_variable(name, variables, skills).is_some())
+        {
+            rendered.push_str(&format!("{{{name}}}"));
+            rest = &tail[name.len() + 4..];
+            continue;
+        }
+        let value = tail[1..]
+            .split_once('}')
+            .and_then(|(name, _)| Some((name, prompt_variable(name, variables, skills)?)));
+        match value {
+            Some((name, value)) => {
+                rendered.push_str(value);
+                rest = &tail[name.len() + 2..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &tail[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn prompt_variable<'a>(
+    name: &str,
+    variables: &'a PromptVariables,
+    skills: &'a str,
+) -> Option<&'a str> {
+    match name {
+        "cwd" => Some(&variables.cwd),
+        "git_branch" => Some(&variables.git_branch),
+        "date" => Some(&variables.date),
+        "model" => Some(&variables.model),
+        "skills" => Some(skills),
+        _ => None,
+    }
+}
+
+/// Branch checked out in the repository containing `cwd`, or the short commit id when HEAD is
+/// detached. Reads `.git` directly so rendering never spawns a process.
+fn git_branch(cwd: &Path) -> Option<String> {
+    let dot_git = cwd
+        .ancestors()
+        .map(|dir| dir.join(".git"))
+        .find(|path| path.exists())?;
+    let git_dir = if dot_git.is_file() {
+        let pointer = std::fs::read_to_string(&dot_git).ok()?;
+        let target = PathBuf::from(pointer.trim().strip_prefix("gitdir:")?.trim());
+        if target.is_absolute() {
+            target
+        } else {
+            dot_git.parent()?.join(target)
+        }
+    } else {
+        dot_git
+    };
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref: ") {
+        Some(reference) => Some(
+            reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_string(),
+        ),
+        None => Some(head.chars().take(12).collect()),
+    }
+}
+
 fn tool_description(name: &str) -> Option<&'static str> {
     match name {
         "list_directory" => Some("List directory entries"),
         "read" => Some("Read file contents"),
+        "search" => Some("Search file contents or list files, respecting .gitignore"),
+        "read_image" => Some("View a local image file (screenshots, diagrams)"),
         "bash" => Some("Execute bash commands in the current directory"),
+        "shell" => Some("Run commands in a persistent shell that keeps cwd and environment"),
+        "bash_background" => Some("Start, poll, read logs of, and kill long-running commands"),
         "edit" => Some("Make surgical edits to existing files"),
+        "apply_patch" => Some("Apply multi-hunk, multi-file patches atomically"),
+        "notebook_edit" => Some("Read and edit Jupyter notebook cells"),
         "write" => Some("Create or overwrite files"),
         "task" => Some("Delegate a prompt to a configured subagent"),
+        "todo" => Some("Track a multi-step task list shown to the user"),
+        "git_status" => Some("Show branch and staged, unstaged and untracked files"),
+        "git_diff" => Some("Show unstaged, staged or commit diffs"),
+        "git_log" => Some("List recent commits"),
+        "git_commit" => Some("Stage files and commit them"),
+        "git_branch" => Some("List, create, switch and delete branches"),
+        "web_fetch" => Some("Fetch a web page as readable text"),
+        "web_search" => Some("Search the web for current documentation and references"),
         _ => None,
     }
 }
 
 fn build_guidelines(selected_tools: &[&str]) -> String {
-    let has = |name: &str| selected_tools.iter().any(|tool| *tool == name);
+    let has = |name: &str| selected_tools.contains(&name);
 
     let mut lines = vec![
         "- For any concrete action (creating/editing files, running commands, inspecting logs, etc.), you MUST use the available tools directly.".to_string(),
@@ -299,9 +442,41 @@ fn build_guidelines(selected_tools: &[&str]) -> String {
         lines.push(
             "- Use list_directory to inspect folders before targeting file edits.".to_string(),
         );
+        lines.push(
+            "- Files ignored by .gitignore or .pixyignore are hidden; pass includeIgnored=true only when you need them."
+                .to_string(),
+        );
+    }
+    if has("search") {
+        lines.push(
+            "- Use search instead of grep, rg or find through bash to locate code and files."
+                .to_string(),
+        );
+    }
+    if has("read_image") {
+        lines.push(
+            "- Use read_image to look at image files the user mentions instead of reading them as text."
+                .to_string(),
+        );
     }
     if has("edit") {
         lines.push("- Use edit for precise changes when replacing exact text.".to_string());
+        lines.push(
+            "- When edit reports a non-exact match, check its preview before continuing."
+                .to_string(),
+        );
+    }
+    if has("apply_patch") {
+        lines.push(
+            "- Use apply_patch for changes spanning several hunks or files; if a hunk fails, nothing is written, so fix it and resend the whole patch."
+                .to_string(),
+        );
+    }
+    if has("notebook_edit") {
+        lines.push(
+            "- Use notebook_edit to read and change .ipynb cells; never edit or rewrite notebook JSON directly."
+                .to_string(),
+        );
     }
     if has("write") {
         lines.push("- Use write for new files or complete rewrites.".to_string());
@@ -313,6 +488,43 @@ fn build_guidelines(selected_tools: &[&str]) -> String {
                 .to_string(),
         );
     }
+    if has("shell") {
+        lines.push(
+            "- Use shell when later commands depend on earlier ones (cd, exported variables, an activated virtualenv); call it with reset=true to start over."
+                .to_string(),
+        );
+    }
+    if has("git_status") {
+        lines.push(
+            "- Use the git_* tools for status, diffs, history, commits and branches instead of running git through bash; commit only when the user asks."
+                .to_string(),
+        );
+    }
+    if has("web_search") {
+        lines.push(
+            "- Use web_search when the answer depends on current documentation, releases or error reports, and cite the URLs you rely on."
+                .to_string(),
+        );
+    }
+    if has("web_fetch") {
+        lines.push(
+            "- Use web_fetch to read a URL instead of curl through bash; raise maxChars only when the page was cut short."
+                .to_string(),
+        );
+    }
+    if has("bash_background") {
+        lines.push(
+            "- Use bash_background for servers, watchers and builds that outlive a single command; poll it for progress and kill processes you no longer need."
+                .to_string(),
+        );
+    }
+
+    if has("todo") {
+        lines.push(
+            "- For work with three or more steps, keep a todo list with the todo tool and update it as each step starts and finishes."
+                .to_string(),
+        );
+    }
 
     lines.push(
         "- Ask for confirmation only before clearly destructive or irreversible actions."
@@ -335,15 +547,25 @@ mod tests {
     use tempfile::tempdir;
 
     use super::append_multi_agent_prompt_section;
-    use super::build_system_prompt_with_now;
+    use super::{build_system_prompt_with_now, render_prompt_template, PromptVariables};
     use pixy_agent_core::AgentTool;
 
+    fn test_variables() -> PromptVariables {
+        PromptVariables {
+            cwd: "/workspace".to_string(),
+            git_branch: "main".to_string(),
+            date: "2026-02-23".to_string(),
+            model: "openai/gpt-4o-mini".to_string(),
+        }
+    }
+
     fn no_op_tool(name: &str) -> AgentTool {
         AgentTool {
             name: name.to_string(),
             label: name.to_string(),
             description: name.to_string(),
             parameters: serde_json::json!({}),
+            conflict_key: None,
             execute: std::sync::Arc::new(
                 |_tool_call_id: String, _args: serde_json::Value| -> ToolFuture {
                     Box::pin(async {
@@ -365,6 +587,7 @@ mod tests {
             &["list_directory", "read", "bash", "edit", "write"],
             &[],
             "Monday, February 23, 2026, 11:00:00 AM UTC",
+            &test_variables(),
         );
 
         assert!(prompt.contains("<identity>"));
@@ -382,6 +605,7 @@ mod tests {
             &["list_directory", "read", "bash", "edit", "write"],
             &[],
             "Monday, February 23, 2026, 11:00:00 AM UTC",
+            &test_variables(),
         );
 
         assert!(prompt.contains("You are pixy"));
@@ -405,6 +629,7 @@ mod tests {
             &[],
             &[],
             "Monday, February 23, 2026, 11:00:00 AM UTC",
+            &test_variables(),
         );
 
         assert!(prompt.contains("You have access to the following built-in tools:\n(none)"));
@@ -422,6 +647,7 @@ mod tests {
             &["list_directory", "read", "bash", "edit", "write"],
             &[],
             "Monday, February 23, 2026, 11:00:00 AM UTC",
+            &test_variables(),
         );
 
         assert!(prompt.contains("You are custom from file."));
@@ -443,6 +669,7 @@ mod tests {
                     base_dir: "/skills/visible".into(),
                     source: SkillSource::Path,
                     disable_model_invocation: false,
+                    arguments: vec![],
                 },
                 Skill {
                     name: "hidden-skill".to_string(),
@@ -451,9 +678,11 @@ mod tests {
                     base_dir: "/skills/hidden".into(),
                     source: SkillSource::Path,
                     disable_model_invocation: true,
+                    arguments: vec![],
                 },
             ],
             "Monday, February 23, 2026, 11:00:00 AM UTC",
+            &test_variables(),
         );
 
         assert!(prompt.contains("<available_skills>"));
@@ -474,8 +703,10 @@ mod tests {
                 base_dir: "/skills/visible".into(),
                 source: SkillSource::Path,
                 disable_model_invocation: false,
+                arguments: vec![],
             }],
             "Monday, February 23, 2026, 11:00:00 AM UTC",
+            &test_variables(),
         );
 
         assert!(!prompt.contains("<available_skills>"));
@@ -489,6 +720,7 @@ mod tests {
             &["list_directory", "read", "bash", "edit", "write"],
             &[],
             "Monday, February 23, 2026, 11:00:00 AM UTC",
+            &test_variables(),
         );
 
         assert!(prompt.contains(
@@ -513,6 +745,7 @@ mod tests {
             &["list_directory", "read", "bash", "edit", "write"],
             &[],
             "Monday, February 23, 2026, 11:00:00 AM UTC",
+            &test_variables(),
         );
 
         assert!(prompt.contains("<WORKSPACE_AGENTS>"));
@@ -532,12 +765,36 @@ mod tests {
             &["list_directory", "read", "bash", "edit", "write"],
             &[],
             "Monday, February 23, 2026, 11:00:00 AM UTC",
+            &test_variables(),
         );
 
         assert!(prompt.contains("<WORKSPACE_AGENTS>"));
         assert!(prompt.contains("workspace claude prompt"));
     }
 
+    #[test]
+    fn prompt_includes_project_memory_file_when_present() {
+        let dir = tempdir().expect("temp dir");
+        std::fs::create_dir_all(dir.path().join(".pixy")).expect("create .pixy");
+        std::fs::write(
+            dir.path().join(".pixy/memory.md"),
+            "- Run `make check` before committing.",
+        )
+        .expect("write memory");
+
+        let prompt = build_system_prompt_with_now(
+            None,
+            dir.path(),
+            &["list_directory", "read", "bash", "edit", "write"],
+            &[],
+            "Monday, February 23, 2026, 11:00:00 AM UTC",
+            &test_variables(),
+        );
+
+        assert!(prompt.contains("<PROJECT_MEMORY>"));
+        assert!(prompt.contains("Run `make check` before committing."));
+    }
+
     #[test]
     fn prompt_includes_workspace_skills_only() {
         let dir = tempdir().expect("temp dir");
@@ -556,6 +813,7 @@ mod tests {
                     base_dir: PathBuf::from("/skills/project"),
                     source: SkillSource::Project,
                     disable_model_invocation: false,
+                    arguments: vec![],
                 },
                 Skill {
                     name: "path-skill".to_string(),
@@ -564,6 +822,7 @@ mod tests {
                     base_dir: PathBuf::from("/skills/path"),
                     source: SkillSource::Path,
                     disable_model_invocation: false,
+                    arguments: vec![],
                 },
                 Skill {
                     name: "user-skill".to_string(),
@@ -572,9 +831,11 @@ mod tests {
                     base_dir: PathBuf::from("/users/demo/.agents/skills/user"),
                     source: SkillSource::User,
                     disable_model_invocation: false,
+                    arguments: vec![],
                 },
             ],
             "Monday, February 23, 2026, 11:00:00 AM UTC",
+            &test_variables(),
         );
 
         assert!(prompt.contains("<WORKSPACE_SKILLS>"));
@@ -584,6 +845,45 @@ mod tests {
         assert!(prompt.contains("</WORKSPACE_SKILLS>"));
     }
 
+    #[test]
+    fn custom_prompt_template_renders_variables() {
+        let skill = Skill {
+            name: "deploy".to_string(),
+            description: "Ship a release".to_string(),
+            file_path: PathBuf::from("/users/demo/.agents/skills/deploy/SKILL.md"),
+            base_dir: PathBuf::from("/users/demo/.agents/skills/deploy"),
+            source: SkillSource::User,
+            disable_model_invocation: false,
+            arguments: vec![],
+        };
+        let prompt = build_system_prompt_with_now(
+            Some("You work in {cwd} on {git_branch} with {model} ({date}).\nSkills:\n{skills}\nKeep {unknown} and {{cwd}} as is; json: {\"a\": 1}"),
+            Path::new("/workspace"),
+            &["read"],
+            std::slice::from_ref(&skill),
+            "Monday, February 23, 2026, 11:00:00 AM UTC",
+            &test_variables(),
+        );
+
+        assert!(
+            prompt.contains("You work in /workspace on main with openai/gpt-4o-mini (2026-02-23).")
+        );
+        assert!(prompt.contains("Keep {unknown} and {cwd} as is; json: {\"a\": 1}"));
+        assert_eq!(
+            prompt.matches("<name>deploy</name>").count(),
+            1,
+            "{{skills}} replaces the appended skills section: {prompt}"
+        );
+        let identity_end = prompt.find("</identity>").expect("identity section");
+        assert!(prompt.find("<name>deploy</name>").expect("skill listed") < identity_end);
+    }
+
+    #[test]
+    fn render_prompt_template_handles_unterminated_braces() {
+        let rendered = render_prompt_template("{model} {cwd", &test_variables(), "");
+        assert_eq!(rendered, "openai/gpt-4o-mini {cwd");
+    }
+
     #[test]
     fn append_multi_agent_prompt_section_includes_subagent_names_when_task_tool_present() {
         let mut prompt = "base prompt".to_string();
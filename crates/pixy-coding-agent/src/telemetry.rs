@@ -0,0 +1,737 @@
+//! Opt-in OpenTelemetry export, configured by `[telemetry]` in `pixy.toml`.
+//!
+//! Each session turn becomes a root span with one child span per provider call and tool
+//! execution. Provider spans carry token and cost attributes named after the OpenTelemetry GenAI
+//! semantic conventions. Spans are buffered and sent as OTLP/HTTP JSON to `<endpoint>/v1/traces`
+//! when a turn finishes; export failures are logged and the spans dropped.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use pixy_agent_core::{
+    AgentMessage, AgentTool, AgentToolExecuteFn, AgentToolExecutor, AgentToolResult, StreamFn,
+};
+use pixy_ai::{
+    calculate_cost, model_pricing, AssistantMessage, Context, Message, Model, PiAiError,
+    SimpleStreamOptions, StopReason,
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+pub const DEFAULT_OTLP_ENDPOINT: &str = "http://localhost:4318";
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+const SPAN_KIND_INTERNAL: u8 = 1;
+const SPAN_KIND_CLIENT: u8 = 3;
+const STATUS_CODE_ERROR: u8 = 2;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TelemetryConfig {
+    pub enabled: bool,
+    /// OTLP/HTTP base URL; spans are posted to `<endpoint>/v1/traces`.
+    pub endpoint: String,
+    pub service_name: String,
+    /// Extra request headers, typically an API key for a hosted collector.
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: DEFAULT_OTLP_ENDPOINT.to_string(),
+            service_name: "pixy".to_string(),
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum AttributeValue {
+    String(String),
+    Int(i64),
+    Double(f64),
+    Bool(bool),
+}
+
+impl AttributeValue {
+    fn to_otlp(&self) -> Value {
+        match self {
+            Self::String(value) => json!({ "stringValue": value }),
+            // OTLP JSON encodes 64-bit integers as strings.
+            Self::Int(value) => json!({ "intValue": value.to_string() }),
+            Self::Double(value) => json!({ "doubleValue": value }),
+            Self::Bool(value) => json!({ "boolValue": value }),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct SpanRecord {
+    trace_id: String,
+    span_id: String,
+    parent_span_id: Option<String>,
+    name: String,
+    kind: u8,
+    start_unix_nanos: u128,
+    end_unix_nanos: u128,
+    attributes: Vec<(&'static str, AttributeValue)>,
+    error: Option<String>,
+}
+
+impl SpanRecord {
+    fn to_otlp(&self) -> Value {
+        let mut span = json!({
+            "traceId": self.trace_id,
+            "spanId": self.span_id,
+            "name": self.name,
+            "kind": self.kind,
+            "startTimeUnixNano": self.start_unix_nanos.to_string(),
+            "endTimeUnixNano": self.end_unix_nanos.to_string(),
+            "attributes": self
+                .attributes
+                .iter()
+                .map(|(key, value)| json!({ "key": key, "value": value.to_otlp() }))
+                .collect::<Vec<_>>(),
+        });
+        if let Some(parent_span_id) = &self.parent_span_id {
+            span["parentSpanId"] = json!(parent_span_id);
+        }
+        if let Some(error) = &self.error {
+            span["status"] = json!({ "code": STATUS_CODE_ERROR, "message": error });
+        }
+        span
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct SpanParent {
+    trace_id: String,
+    span_id: String,
+}
+
+/// A session turn whose span is recorded by [`SessionTelemetry::finish_turn`].
+#[derive(Debug)]
+pub struct TelemetryTurn {
+    parent: SpanParent,
+    kind: &'static str,
+    session_id: String,
+    start_unix_nanos: u128,
+}
+
+/// Records spans for one session and exports them to an OTLP collector.
+#[derive(Debug)]
+pub struct SessionTelemetry {
+    client: reqwest::Client,
+    traces_url: String,
+    service_name: String,
+    headers: BTreeMap<String, String>,
+    current_turn: Mutex<Option<SpanParent>>,
+    pending: Mutex<Vec<SpanRecord>>,
+}
+
+impl SessionTelemetry {
+    /// Returns `None` when telemetry is disabled.
+    pub fn from_config(config: &TelemetryConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let client = reqwest::Client::builder()
+            .timeout(EXPORT_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Some(Self {
+            client,
+            traces_url: format!("{}/v1/traces", config.endpoint.trim_end_matches('/')),
+            service_name: config.service_name.clone(),
+            headers: config.headers.clone(),
+            current_turn: Mutex::new(None),
+            pending: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Starts a turn span; provider calls and tool executions until [`Self::finish_turn`]
+    /// become its children.
+    pub fn start_turn(&self, kind: &'static str, session_id: &str) -> TelemetryTurn {
+        let parent = SpanParent {
+            trace_id: new_id(16),
+            span_id: new_id(8),
+        };
+        *lock(&self.current_turn) = Some(parent.clone());
+        TelemetryTurn {
+            parent,
+            kind,
+            session_id: session_id.to_string(),
+            start_unix_nanos: unix_nanos(),
+        }
+    }
+
+    /// Records the turn span with totals over `produced`, then exports everything buffered.
+    pub async fn finish_turn(&self, turn: TelemetryTurn, produced: &[AgentMessage]) {
+        {
+            let mut current = lock(&self.current_turn);
+            if current.as_ref() == Some(&turn.parent) {
+                *current = None;
+            }
+        }
+
+        let mut input_tokens = 0;
+        let mut output_tokens = 0;
+        let mut cost = 0.0;
+        let mut llm_calls = 0;
+        let mut tool_calls = 0;
+        let mut error = None;
+        for message in produced {
+            match message {
+                Message::Assistant {
+                    usage,
+                    stop_reason,
+                    error_message,
+                    ..
+                } => {
+                    llm_calls += 1;
+                    input_tokens += usage.input;
+                    output_tokens += usage.output;
+                    cost += usage.cost.total;
+                    if *stop_reason == StopReason::Error {
+                        error = error_message.clone().or(Some("provider error".to_string()));
+                    }
+                }
+                Message::ToolResult { .. } => tool_calls += 1,
+                Message::User { .. } => {}
+            }
+        }
+
+        self.push(SpanRecord {
+            trace_id: turn.parent.trace_id,
+            span_id: turn.parent.span_id,
+            parent_span_id: None,
+            name: format!("pixy.turn {}", turn.kind),
+            kind: SPAN_KIND_INTERNAL,
+            start_unix_nanos: turn.start_unix_nanos,
+            end_unix_nanos: unix_nanos(),
+            attributes: vec![
+                ("session.id", AttributeValue::String(turn.session_id)),
+                (
+                    "pixy.turn.kind",
+                    AttributeValue::String(turn.kind.to_string()),
+                ),
+                ("pixy.llm_calls", AttributeValue::Int(llm_calls)),
+                ("pixy.tool_calls", AttributeValue::Int(tool_calls)),
+                (
+                    "gen_ai.usage.input_tokens",
+                    AttributeValue::Int(input_tokens as i64),
+                ),
+                (
+                    "gen_ai.usage.output_tokens",
+                    AttributeValue::Int(output_tokens as i64),
+                ),
+                ("pixy.cost.usd", AttributeValue::Double(cost)),
+            ],
+            error,
+        });
+        self.flush().await;
+    }
+
+    /// Exports buffered spans.
+    pub async fn flush(&self) {
+        let spans = std::mem::take(&mut *lock(&self.pending));
+        if spans.is_empty() {
+            return;
+        }
+        if let Err(error) = self.export(&spans).await {
+            warn!(
+                spans = spans.len(),
+                "failed to export telemetry to {}: {error}", self.traces_url
+            );
+        }
+    }
+
+    /// Wraps a provider stream function so each call is recorded as a client span.
+    pub fn wrap_stream_fn(self: &Arc<Self>, inner: StreamFn) -> StreamFn {
+        let telemetry = self.clone();
+        Arc::new(
+            move |model: Model, context: Context, options: Option<SimpleStreamOptions>| {
+                let parent = telemetry.current_parent();
+                let start_unix_nanos = unix_nanos();
+                let stream = match inner.stream(model.clone(), context, options) {
+                    Ok(stream) => stream,
+                    Err(error) => {
+                        telemetry.record_provider_call(
+                            parent,
+                            &model,
+                            start_unix_nanos,
+                            Err(&error.message),
+                        );
+                        return Err(error);
+                    }
+                };
+                if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                    let observed = stream.clone();
+                    let telemetry = telemetry.clone();
+                    runtime.spawn(async move {
+                        match observed.result().await {
+                            Some(message) => telemetry.record_provider_call(
+                                parent,
+                                &model,
+                                start_unix_nanos,
+                                Ok(&message),
+                            ),
+                            None => telemetry.record_provider_call(
+                                parent,
+                                &model,
+                                start_unix_nanos,
+                                Err("stream ended without a response"),
+                            ),
+                        }
+                    });
+                }
+                Ok(stream)
+            },
+        )
+    }
+
+    /// Wraps a tool so each execution is recorded as a span.
+    pub fn wrap_tool(self: &Arc<Self>, mut tool: AgentTool) -> AgentTool {
+        tool.execute = Arc::new(TelemetryToolExecutor {
+            tool_name: tool.name.clone(),
+            inner: tool.execute.clone(),
+            telemetry: self.clone(),
+        });
+        tool
+    }
+
+    fn current_parent(&self) -> Option<SpanParent> {
+        lock(&self.current_turn).clone()
+    }
+
+    fn push(&self, span: SpanRecord) {
+        lock(&self.pending).push(span);
+    }
+
+    fn child_ids(parent: &Option<SpanParent>) -> (String, Option<String>) {
+        match parent {
+            Some(parent) => (parent.trace_id.clone(), Some(parent.span_id.clone())),
+            None => (new_id(16), None),
+        }
+    }
+
+    fn record_provider_call(
+        &self,
+        parent: Option<SpanParent>,
+        model: &Model,
+        start_unix_nanos: u128,
+        outcome: Result<&AssistantMessage, &str>,
+    ) {
+        let (trace_id, parent_span_id) = Self::child_ids(&parent);
+        let mut attributes = vec![
+            (
+                "gen_ai.operation.name",
+                AttributeValue::String("chat".to_string()),
+            ),
+            (
+                "gen_ai.system",
+                AttributeValue::String(model.provider.clone()),
+            ),
+            (
+                "gen_ai.request.model",
+                AttributeValue::String(model.id.clone()),
+            ),
+        ];
+        let error = match outcome {
+            Ok(message) => {
+                let cost = model_pricing(model)
+                    .map(|pricing| calculate_cost(&pricing, &message.usage).total)
+                    .unwrap_or(message.usage.cost.total);
+                let finish_reason = serde_json::to_value(&message.stop_reason)
+                    .ok()
+                    .and_then(|value| value.as_str().map(str::to_string))
+                    .unwrap_or_default();
+                attributes.extend([
+                    (
+                        "gen_ai.response.model",
+                        AttributeValue::String(message.model.clone()),
+                    ),
+                    (
+                        "gen_ai.response.finish_reason",
+                        AttributeValue::String(finish_reason),
+                    ),
+                    (
+                        "gen_ai.usage.input_tokens",
+                        AttributeValue::Int(message.usage.input as i64),
+                    ),
+                    (
+                        "gen_ai.usage.output_tokens",
+                        AttributeValue::Int(message.usage.output as i64),
+                    ),
+                    (
+                        "pixy.usage.cache_read_tokens",
+                        AttributeValue::Int(message.usage.cache_read as i64),
+                    ),
+                    (
+                        "pixy.usage.cache_write_tokens",
+                        AttributeValue::Int(message.usage.cache_write as i64),
+                    ),
+                    ("pixy.cost.usd", AttributeValue::Double(cost)),
+                ]);
+                (message.stop_reason == StopReason::Error).then(|| {
+                    message
+                        .error_message
+                        .clone()
+                        .unwrap_or_else(|| "provider error".to_string())
+                })
+            }
+            Err(error) => Some(error.to_string()),
+        };
+        self.push(SpanRecord {
+            trace_id,
+            span_id: new_id(8),
+            parent_span_id,
+            name: format!("chat {}", model.id),
+            kind: SPAN_KIND_CLIENT,
+            start_unix_nanos,
+            end_unix_nanos: unix_nanos(),
+            attributes,
+            error,
+        });
+    }
+
+    async fn export(&self, spans: &[SpanRecord]) -> Result<(), String> {
+        let body = json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [
+                        { "key": "service.name", "value": { "stringValue": self.service_name } },
+                        { "key": "service.version", "value": { "stringValue": env!("CARGO_PKG_VERSION") } },
+                    ],
+                },
+                "scopeSpans": [{
+                    "scope": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+                    "spans": spans.iter().map(SpanRecord::to_otlp).collect::<Vec<_>>(),
+                }],
+            }],
+        });
+        let mut request = self.client.post(&self.traces_url).json(&body);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let response = request.send().await.map_err(|error| error.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("collector returned HTTP {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+struct TelemetryToolExecutor {
+    tool_name: String,
+    inner: AgentToolExecuteFn,
+    telemetry: Arc<SessionTelemetry>,
+}
+
+#[async_trait]
+impl AgentToolExecutor for TelemetryToolExecutor {
+    async fn execute(
+        &self,
+        tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        let parent = self.telemetry.current_parent();
+        let start_unix_nanos = unix_nanos();
+        let result = self.inner.execute(tool_call_id.clone(), args).await;
+
+        let (trace_id, parent_span_id) = SessionTelemetry::child_ids(&parent);
+        self.telemetry.push(SpanRecord {
+            trace_id,
+            span_id: new_id(8),
+            parent_span_id,
+            name: format!("execute_tool {}", self.tool_name),
+            kind: SPAN_KIND_INTERNAL,
+            start_unix_nanos,
+            end_unix_nanos: unix_nanos(),
+            attributes: vec![
+                (
+                    "gen_ai.operation.name",
+                    AttributeValue::String("execute_tool".to_string()),
+                ),
+                (
+                    "gen_ai.tool.name",
+                    AttributeValue::String(self.tool_name.clone()),
+                ),
+                ("gen_ai.tool.call.id", AttributeValue::String(tool_call_id)),
+                ("pixy.tool.is_error", AttributeValue::Bool(result.is_err())),
+            ],
+            error: result.as_ref().err().map(|error| error.message.clone()),
+        });
+        result
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn unix_nanos() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default()
+}
+
+/// A random-enough hex id of `bytes` bytes: 16 for trace ids, 8 for span ids.
+fn new_id(bytes: usize) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let seed = format!(
+        "{}:{}:{}",
+        unix_nanos(),
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    Sha256::digest(seed.as_bytes())
+        .iter()
+        .take(bytes)
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use pixy_ai::{
+        AssistantContentBlock, AssistantMessageEvent, AssistantMessageEventStream, Cost,
+        DoneReason, Usage,
+    };
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::create_bash_tool;
+
+    fn spawn_collector() -> (String, Arc<Mutex<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind collector");
+        let address = listener.local_addr().expect("collector addr");
+        let captured = Arc::new(Mutex::new(String::new()));
+        let captured_request = captured.clone();
+        thread::spawn(move || {
+            if let Ok((mut socket, _)) = listener.accept() {
+                socket
+                    .set_read_timeout(Some(Duration::from_millis(500)))
+                    .expect("set read timeout");
+                let mut request = Vec::new();
+                let mut buffer = [0_u8; 8192];
+                while let Ok(read) = socket.read(&mut buffer) {
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request);
+                    if text.contains("\r\n\r\n") && text.trim_end().ends_with('}') {
+                        break;
+                    }
+                }
+                *captured_request.lock().expect("capture lock") =
+                    String::from_utf8_lossy(&request).to_string();
+                let _ = socket.write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}",
+                );
+            }
+        });
+        (format!("http://{address}/"), captured)
+    }
+
+    fn sample_model() -> Model {
+        Model {
+            id: "gpt-5.3-codex".to_string(),
+            name: "gpt-5.3-codex".to_string(),
+            api: "openai-responses".to_string(),
+            provider: "openai".to_string(),
+            base_url: "http://localhost".to_string(),
+            reasoning: false,
+            reasoning_effort: None,
+            input: vec!["text".to_string()],
+            cost: Cost {
+                input: 2.0,
+                output: 10.0,
+                cache_read: 0.0,
+                cache_write: 0.0,
+                total: 0.0,
+            },
+            context_window: 128_000,
+            max_tokens: 8_192,
+            deployment: None,
+            api_version: None,
+            compat: None,
+        }
+    }
+
+    fn assistant(model: &Model) -> AssistantMessage {
+        AssistantMessage {
+            role: "assistant".to_string(),
+            content: vec![AssistantContentBlock::Text {
+                text: "done".to_string(),
+                text_signature: None,
+            }],
+            api: model.api.clone(),
+            provider: model.provider.clone(),
+            model: model.id.clone(),
+            usage: Usage {
+                input: 1_000,
+                output: 100,
+                cache_read: 0,
+                cache_write: 0,
+                reasoning: 0,
+                total_tokens: 1_100,
+                cost: Cost {
+                    input: 0.0,
+                    output: 0.0,
+                    cache_read: 0.0,
+                    cache_write: 0.0,
+                    total: 0.0,
+                },
+            },
+            stop_reason: StopReason::Stop,
+            error_message: None,
+            timestamp: 1_700_000_000_000,
+            stats: None,
+        }
+    }
+
+    fn attribute<'a>(span: &'a Value, key: &str) -> &'a Value {
+        span["attributes"]
+            .as_array()
+            .and_then(|attributes| attributes.iter().find(|attribute| attribute["key"] == key))
+            .map(|attribute| &attribute["value"])
+            .unwrap_or(&Value::Null)
+    }
+
+    #[test]
+    fn disabled_config_creates_no_telemetry() {
+        assert!(SessionTelemetry::from_config(&TelemetryConfig::default()).is_none());
+    }
+
+    #[tokio::test]
+    async fn exports_turn_provider_and_tool_spans_as_otlp_json() {
+        let (endpoint, captured) = spawn_collector();
+        let mut headers = BTreeMap::new();
+        headers.insert("x-api-key".to_string(), "collector-key".to_string());
+        let telemetry = Arc::new(
+            SessionTelemetry::from_config(&TelemetryConfig {
+                enabled: true,
+                endpoint,
+                service_name: "pixy-test".to_string(),
+                headers,
+            })
+            .expect("enabled"),
+        );
+        let model = sample_model();
+        let response = assistant(&model);
+        let stream_response = response.clone();
+        let stream_fn = telemetry.wrap_stream_fn(Arc::new(
+            move |_model: Model, _context: Context, _options: Option<SimpleStreamOptions>| {
+                let stream = AssistantMessageEventStream::new();
+                stream.push(AssistantMessageEvent::Done {
+                    reason: DoneReason::Stop,
+                    message: stream_response.clone(),
+                });
+                Ok(stream)
+            },
+        ));
+        let dir = tempdir().expect("tempdir");
+        let tool = telemetry.wrap_tool(create_bash_tool(dir.path()));
+
+        let turn = telemetry.start_turn("prompt", "session-1");
+        let stream = stream_fn
+            .stream(
+                model.clone(),
+                Context {
+                    system_prompt: None,
+                    messages: vec![],
+                    tools: None,
+                    system_prompt_cache: None,
+                    branch: None,
+                },
+                None,
+            )
+            .expect("stream");
+        stream.result().await.expect("provider result");
+        tool.execute
+            .execute("call-1".to_string(), json!({ "command": "exit 4" }))
+            .await
+            .expect_err("tool fails");
+        tokio::task::yield_now().await;
+        telemetry
+            .finish_turn(
+                turn,
+                &[Message::Assistant {
+                    content: response.content.clone(),
+                    api: response.api.clone(),
+                    provider: response.provider.clone(),
+                    model: response.model.clone(),
+                    usage: response.usage.clone(),
+                    stop_reason: StopReason::Stop,
+                    error_message: None,
+                    timestamp: response.timestamp,
+                    stats: None,
+                }],
+            )
+            .await;
+
+        let request = captured.lock().expect("capture lock").clone();
+        assert!(request.starts_with("POST /v1/traces "));
+        assert!(request.contains("x-api-key: collector-key"));
+        let body: Value =
+            serde_json::from_str(request.split("\r\n\r\n").nth(1).expect("body")).expect("json");
+        let resource = &body["resourceSpans"][0];
+        assert_eq!(
+            resource["resource"]["attributes"][0]["value"]["stringValue"],
+            "pixy-test"
+        );
+        let spans = resource["scopeSpans"][0]["spans"]
+            .as_array()
+            .expect("spans");
+        assert_eq!(spans.len(), 3);
+
+        let turn_span = spans
+            .iter()
+            .find(|span| span["name"] == "pixy.turn prompt")
+            .expect("turn span");
+        let provider_span = spans
+            .iter()
+            .find(|span| span["name"] == "chat gpt-5.3-codex")
+            .expect("provider span");
+        let tool_span = spans
+            .iter()
+            .find(|span| span["name"] == "execute_tool bash")
+            .expect("tool span");
+
+        assert!(turn_span.get("parentSpanId").is_none());
+        assert_eq!(
+            attribute(turn_span, "session.id")["stringValue"],
+            "session-1"
+        );
+        for child in [provider_span, tool_span] {
+            assert_eq!(child["traceId"], turn_span["traceId"]);
+            assert_eq!(child["parentSpanId"], turn_span["spanId"]);
+        }
+        assert_eq!(provider_span["kind"], SPAN_KIND_CLIENT);
+        assert_eq!(
+            attribute(provider_span, "gen_ai.usage.input_tokens")["intValue"],
+            "1000"
+        );
+        assert_eq!(
+            attribute(provider_span, "pixy.cost.usd")["doubleValue"],
+            0.003
+        );
+        assert_eq!(tool_span["status"]["code"], STATUS_CODE_ERROR);
+        assert!(tool_span["status"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("Command exited with code 4")));
+    }
+}
@@ -0,0 +1,33 @@
+//! Tools that ask the user before they run, configured by `[approval]` in `pixy.toml`.
+//!
+//! Interactive front ends attach an approver to the session: the TUI shows an approve/deny
+//! prompt and the gateway asks over the chat channel. Runs without an attached approver, such as
+//! `--prompt`, execute flagged tools directly.
+
+use std::collections::HashMap;
+
+use pixy_agent_core::ToolRisk;
+use serde::{Deserialize, Deserializer};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ToolApprovalConfig {
+    /// Tool names mapped to `low`, `medium` or `high` risk.
+    #[serde(deserialize_with = "deserialize_tool_risks")]
+    pub tools: HashMap<String, ToolRisk>,
+}
+
+fn deserialize_tool_risks<'de, D>(deserializer: D) -> Result<HashMap<String, ToolRisk>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = HashMap::<String, String>::deserialize(deserializer)?;
+    raw.into_iter()
+        .map(|(tool, risk)| match ToolRisk::parse(&risk) {
+            Some(risk) => Ok((tool, risk)),
+            None => Err(serde::de::Error::custom(format!(
+                "approval risk for tool '{tool}' must be low, medium or high, got '{risk}'"
+            ))),
+        })
+        .collect()
+}
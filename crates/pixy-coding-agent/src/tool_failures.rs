@@ -0,0 +1,365 @@
+//! Shapes how failed tool calls are reported back to the model, configured by `[tool_failures]`
+//! in `pixy.toml`.
+//!
+//! Errors can be passed through raw or summarized to the lines that matter, with or without the
+//! exit code, and followed by a suggested next step. A per-tool cap stops the model from retrying
+//! the same failing tool indefinitely: once a tool has failed `max_retries + 1` times in a row
+//! within one request, further calls to it are refused until a call succeeds or the next request
+//! starts.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use pixy_agent_core::{AgentTool, AgentToolExecuteFn, AgentToolExecutor, AgentToolResult};
+use pixy_ai::{PiAiError, PiAiErrorCode};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::tools::exit_status_line;
+
+/// Failures up to this many lines are never summarized.
+const SUMMARY_MAX_LINES: usize = 20;
+/// Trailing lines always kept by a summary; the final lines usually carry the actual error.
+const SUMMARY_TAIL_LINES: usize = 6;
+const SUMMARY_KEYWORDS: &[&str] = &[
+    "error",
+    "failed",
+    "failure",
+    "panicked",
+    "exception",
+    "fatal",
+    "traceback",
+    "not found",
+    "denied",
+    "cannot",
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolFailureOutput {
+    /// The full error message, as the tool produced it.
+    #[default]
+    Raw,
+    /// Error-looking lines plus the last few lines of long failures.
+    Summary,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ToolFailureConfig {
+    pub output: ToolFailureOutput,
+    /// Keep the `Command exited with code N` trailer of failed commands.
+    pub include_exit_code: bool,
+    /// Append a `Next step:` hint matching the kind of failure.
+    pub include_next_step: bool,
+    /// Consecutive failed retries allowed per tool and request; unlimited when unset.
+    pub max_retries: Option<usize>,
+}
+
+impl Default for ToolFailureConfig {
+    fn default() -> Self {
+        Self {
+            output: ToolFailureOutput::Raw,
+            include_exit_code: true,
+            include_next_step: false,
+            max_retries: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ToolFailureFeedback {
+    config: ToolFailureConfig,
+    consecutive_failures: Mutex<HashMap<String, usize>>,
+}
+
+impl ToolFailureFeedback {
+    /// Returns `None` for the default configuration, which leaves tool errors untouched.
+    pub fn from_config(config: &ToolFailureConfig) -> Option<Self> {
+        if *config == ToolFailureConfig::default() {
+            return None;
+        }
+        Some(Self {
+            config: config.clone(),
+            consecutive_failures: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Wraps a tool so its errors are reformatted and its consecutive failures are counted.
+    pub fn wrap_tool(self: &Arc<Self>, mut tool: AgentTool) -> AgentTool {
+        tool.execute = Arc::new(FeedbackToolExecutor {
+            tool_name: tool.name.clone(),
+            inner: tool.execute.clone(),
+            feedback: self.clone(),
+        });
+        tool
+    }
+
+    /// Clears the failure counts; called when a new request starts.
+    pub fn reset(&self) {
+        self.failures().clear();
+    }
+
+    /// Consecutive failures of `tool_name` since its last success or the last reset.
+    pub fn failure_count(&self, tool_name: &str) -> usize {
+        self.failures().get(tool_name).copied().unwrap_or(0)
+    }
+
+    fn failures(&self) -> std::sync::MutexGuard<'_, HashMap<String, usize>> {
+        self.consecutive_failures
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn retries_exhausted(&self, tool_name: &str) -> bool {
+        self.config
+            .max_retries
+            .is_some_and(|max_retries| self.failure_count(tool_name) > max_retries)
+    }
+
+    fn record_success(&self, tool_name: &str) {
+        self.failures().remove(tool_name);
+    }
+
+    fn record_failure(&self, tool_name: &str) -> usize {
+        let mut failures = self.failures();
+        let count = failures.entry(tool_name.to_string()).or_default();
+        *count += 1;
+        *count
+    }
+
+    fn format_failure(&self, tool_name: &str, error: &PiAiError, failures: usize) -> String {
+        let exit_status = error
+            .details
+            .as_ref()
+            .and_then(|details| details.get("exitCode"))
+            .map(|code| exit_status_line(code.as_i64().map(|code| code as i32)));
+
+        let mut body = error.message.clone();
+        if let Some(trailer) = &exit_status {
+            if let Some(stripped) = body.strip_suffix(trailer.as_str()) {
+                body = stripped.trim_end().to_string();
+            }
+        }
+        if self.config.output == ToolFailureOutput::Summary {
+            body = summarize_failure(&body);
+        }
+        if self.config.include_exit_code {
+            if let Some(trailer) = &exit_status {
+                body.push_str("\n\n");
+                body.push_str(trailer);
+            }
+        }
+        if self.config.include_next_step {
+            body.push_str("\n\nNext step: ");
+            body.push_str(&suggest_next_step(tool_name, error));
+        }
+        if let Some(max_retries) = self.config.max_retries {
+            if failures > max_retries {
+                body.push_str(&format!(
+                    "\n\n`{tool_name}` has failed {failures} times in a row. Do not call it again for this request; explain the problem to the user or take a different approach."
+                ));
+            }
+        }
+        body
+    }
+}
+
+/// Keeps the lines of `text` that look like errors plus its last few lines.
+pub(crate) fn summarize_failure(text: &str) -> String {
+    let lines = text.lines().collect::<Vec<_>>();
+    if lines.len() <= SUMMARY_MAX_LINES {
+        return text.to_string();
+    }
+
+    let tail_start = lines.len() - SUMMARY_TAIL_LINES;
+    let mut kept = Vec::new();
+    let mut omitted = 0;
+    for (index, line) in lines.iter().enumerate() {
+        let lowered = line.to_lowercase();
+        let keep = index >= tail_start
+            || (kept.len() < SUMMARY_MAX_LINES - SUMMARY_TAIL_LINES
+                && SUMMARY_KEYWORDS
+                    .iter()
+                    .any(|keyword| lowered.contains(keyword)));
+        if keep {
+            if omitted > 0 {
+                kept.push(format!("[... {omitted} lines omitted]"));
+                omitted = 0;
+            }
+            kept.push((*line).to_string());
+        } else {
+            omitted += 1;
+        }
+    }
+    kept.join("\n")
+}
+
+fn suggest_next_step(tool_name: &str, error: &PiAiError) -> String {
+    let exit_code = error
+        .details
+        .as_ref()
+        .and_then(|details| details.get("exitCode"))
+        .and_then(Value::as_i64);
+    match error.code {
+        PiAiErrorCode::ToolArgumentsInvalid => format!(
+            "check the arguments against the `{tool_name}` parameter schema and call it again with corrected values."
+        ),
+        PiAiErrorCode::ToolNotFound => {
+            "use one of the tools listed in the system prompt instead.".to_string()
+        }
+        _ if error.message.contains("timed out") => {
+            "rerun with a larger `timeout` or split the work into a narrower command.".to_string()
+        }
+        _ if exit_code == Some(127) => {
+            "the command was not found; check its spelling or whether it is installed before retrying."
+                .to_string()
+        }
+        _ => format!(
+            "read the error above and fix its cause before calling `{tool_name}` again; do not repeat the same call unchanged."
+        ),
+    }
+}
+
+struct FeedbackToolExecutor {
+    tool_name: String,
+    inner: AgentToolExecuteFn,
+    feedback: Arc<ToolFailureFeedback>,
+}
+
+#[async_trait]
+impl AgentToolExecutor for FeedbackToolExecutor {
+    async fn execute(
+        &self,
+        tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        if self.feedback.retries_exhausted(&self.tool_name) {
+            return Err(PiAiError::new(
+                PiAiErrorCode::ToolExecutionFailed,
+                format!(
+                    "Not run: `{}` already failed {} times in a row during this request. Explain the problem to the user or take a different approach.",
+                    self.tool_name,
+                    self.feedback.failure_count(&self.tool_name)
+                ),
+            ));
+        }
+
+        match self.inner.execute(tool_call_id, args).await {
+            Ok(result) => {
+                self.feedback.record_success(&self.tool_name);
+                Ok(result)
+            }
+            Err(mut error) => {
+                let failures = self.feedback.record_failure(&self.tool_name);
+                error.message = self
+                    .feedback
+                    .format_failure(&self.tool_name, &error, failures);
+                Err(error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::create_bash_tool;
+
+    fn feedback(config: ToolFailureConfig) -> Arc<ToolFailureFeedback> {
+        Arc::new(ToolFailureFeedback::from_config(&config).expect("non-default config"))
+    }
+
+    #[test]
+    fn default_config_leaves_errors_untouched() {
+        assert!(ToolFailureFeedback::from_config(&ToolFailureConfig::default()).is_none());
+    }
+
+    #[test]
+    fn summarize_failure_keeps_error_lines_and_tail() {
+        let mut lines = (0..40)
+            .map(|i| format!("compiling crate{i}"))
+            .collect::<Vec<_>>();
+        lines[10] = "error[E0308]: mismatched types".to_string();
+        let summary = summarize_failure(&lines.join("\n"));
+
+        assert!(summary.starts_with("[... 10 lines omitted]\nerror[E0308]: mismatched types"));
+        assert!(summary.contains("[... 23 lines omitted]\ncompiling crate34"));
+        assert!(summary.ends_with("compiling crate39"));
+        assert_eq!(summarize_failure("short\nfailure"), "short\nfailure");
+    }
+
+    #[tokio::test]
+    async fn formats_bash_failures_and_caps_retries() {
+        let dir = tempdir().expect("tempdir");
+        let feedback = feedback(ToolFailureConfig {
+            output: ToolFailureOutput::Summary,
+            include_exit_code: false,
+            include_next_step: true,
+            max_retries: Some(1),
+        });
+        let tool = feedback.wrap_tool(create_bash_tool(dir.path()));
+        let failing = json!({ "command": "echo boom >&2; exit 3" });
+
+        let first = tool
+            .execute
+            .execute("call-1".to_string(), failing.clone())
+            .await
+            .expect_err("command fails");
+        assert!(first
+            .message
+            .contains("boom\n\nNext step: read the error above"));
+        assert!(!first.message.contains("exited with code"));
+        assert_eq!(first.details, Some(json!({ "exitCode": 3 })));
+
+        let second = tool
+            .execute
+            .execute("call-2".to_string(), failing.clone())
+            .await
+            .expect_err("command fails again");
+        assert!(second
+            .message
+            .contains("`bash` has failed 2 times in a row"));
+
+        let refused = tool
+            .execute
+            .execute("call-3".to_string(), json!({ "command": "echo ok" }))
+            .await
+            .expect_err("retries exhausted");
+        assert!(refused
+            .message
+            .starts_with("Not run: `bash` already failed 2 times"));
+
+        feedback.reset();
+        tool.execute
+            .execute("call-4".to_string(), json!({ "command": "echo ok" }))
+            .await
+            .expect("runs again after reset");
+        assert_eq!(feedback.failure_count("bash"), 0);
+    }
+
+    #[tokio::test]
+    async fn keeps_exit_code_and_suggests_install_for_missing_commands() {
+        let dir = tempdir().expect("tempdir");
+        let feedback = feedback(ToolFailureConfig {
+            include_next_step: true,
+            ..ToolFailureConfig::default()
+        });
+        let tool = feedback.wrap_tool(create_bash_tool(dir.path()));
+
+        let error = tool
+            .execute
+            .execute(
+                "call-1".to_string(),
+                json!({ "command": "definitely-not-a-command-xyz" }),
+            )
+            .await
+            .expect_err("missing command");
+        assert!(error
+            .message
+            .contains("Command exited with code 127\n\nNext step: the command was not found"));
+    }
+}
@@ -0,0 +1,405 @@
+//! Per-tool output limits, configured by `[tool_output]` in `pixy.toml`.
+//!
+//! Output over a tool's line or byte cap keeps its first `head_lines` lines and fills the rest of
+//! the budget from the end, where errors and summaries usually are. The full text is spilled to a
+//! file next to the session, and the omission marker points the model at it so it can `read` the
+//! part it needs.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use pixy_agent_core::{AgentTool, AgentToolExecuteFn, AgentToolExecutor, AgentToolResult};
+use pixy_ai::{PiAiError, ToolResultContentBlock};
+use serde_json::{json, Value};
+use tracing::warn;
+
+/// Tools that page their own output; limiting them would break their continuation hints.
+const SELF_PAGING_TOOLS: &[&str] = &["read"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutputLimits {
+    pub max_lines: usize,
+    pub max_bytes: usize,
+    /// Lines kept from the start of oversized output; the rest of the budget goes to the end.
+    pub head_lines: usize,
+}
+
+impl Default for OutputLimits {
+    fn default() -> Self {
+        Self {
+            max_lines: 2000,
+            max_bytes: 64 * 1024,
+            head_lines: 200,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ToolOutputConfig {
+    pub limits: OutputLimits,
+    /// Overrides by tool name.
+    pub tools: BTreeMap<String, OutputLimits>,
+    /// Write the full output of truncated results to a session artifact file.
+    pub spill: bool,
+}
+
+impl Default for ToolOutputConfig {
+    fn default() -> Self {
+        Self {
+            limits: OutputLimits::default(),
+            tools: BTreeMap::new(),
+            spill: true,
+        }
+    }
+}
+
+impl ToolOutputConfig {
+    pub fn limits_for(&self, tool_name: &str) -> OutputLimits {
+        self.tools.get(tool_name).copied().unwrap_or(self.limits)
+    }
+}
+
+/// Head and tail of an oversized text, as produced by [`limit_output`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct LimitedOutput {
+    pub head: String,
+    pub tail: String,
+    pub omitted_lines: usize,
+    pub omitted_bytes: usize,
+}
+
+impl LimitedOutput {
+    fn render(&self, full_output: Option<&Path>) -> String {
+        let location = match full_output {
+            Some(path) => format!(" Full output: {}", path.display()),
+            None => String::new(),
+        };
+        let marker = format!(
+            "[... {} lines ({} bytes) omitted.{location} ...]",
+            self.omitted_lines, self.omitted_bytes
+        );
+        [self.head.as_str(), marker.as_str(), self.tail.as_str()]
+            .iter()
+            .filter(|part| !part.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Returns `None` when `text` fits within `limits`.
+pub(crate) fn limit_output(text: &str, limits: OutputLimits) -> Option<LimitedOutput> {
+    // A trailing newline ends the last line rather than starting an empty one.
+    let body = text.strip_suffix('\n').unwrap_or(text);
+    let lines = body.split('\n').collect::<Vec<_>>();
+    if lines.len() <= limits.max_lines && text.len() <= limits.max_bytes {
+        return None;
+    }
+
+    let max_lines = limits.max_lines.max(1);
+    let head_lines = limits.head_lines.min(max_lines);
+    let head_bytes = limits.max_bytes * head_lines / max_lines;
+    let tail_bytes = limits.max_bytes - head_bytes;
+
+    let mut head = Vec::new();
+    let mut used = 0;
+    for line in &lines {
+        if head.len() == head_lines || used + line.len() + 1 > head_bytes {
+            break;
+        }
+        used += line.len() + 1;
+        head.push(*line);
+    }
+    // Whatever the head leaves unused goes to the tail.
+    let mut tail = Vec::new();
+    for line in lines[head.len()..].iter().rev() {
+        if head.len() + tail.len() == max_lines || used + line.len() + 1 > limits.max_bytes {
+            break;
+        }
+        used += line.len() + 1;
+        tail.push(*line);
+    }
+    tail.reverse();
+
+    if head.is_empty() && tail.is_empty() {
+        // A few huge lines: fall back to cutting bytes.
+        let head = prefix_bytes(body, head_bytes);
+        let tail = suffix_bytes(body, tail_bytes);
+        let omitted = &body[head.len()..body.len() - tail.len()];
+        return Some(LimitedOutput {
+            omitted_lines: omitted.matches('\n').count(),
+            omitted_bytes: text.len() - head.len() - tail.len(),
+            head: head.to_string(),
+            tail: tail.to_string(),
+        });
+    }
+
+    let omitted_lines = lines.len() - head.len() - tail.len();
+    let head = head.join("\n");
+    let tail = tail.join("\n");
+    Some(LimitedOutput {
+        omitted_lines,
+        omitted_bytes: text.len().saturating_sub(head.len() + tail.len()),
+        head,
+        tail,
+    })
+}
+
+fn prefix_bytes(text: &str, max_bytes: usize) -> &str {
+    let mut end = max_bytes.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+fn suffix_bytes(text: &str, max_bytes: usize) -> &str {
+    let mut start = text.len().saturating_sub(max_bytes);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+#[derive(Debug)]
+pub struct ToolOutputLimiter {
+    config: ToolOutputConfig,
+    artifact_dir: Mutex<Option<PathBuf>>,
+}
+
+impl ToolOutputLimiter {
+    pub fn new(config: ToolOutputConfig) -> Self {
+        Self {
+            config,
+            artifact_dir: Mutex::new(None),
+        }
+    }
+
+    /// Directory for spilled output, usually derived from the session file with
+    /// [`artifact_dir_for_session`]. Nothing is spilled while unset.
+    pub fn set_artifact_dir(&self, dir: Option<PathBuf>) {
+        *self
+            .artifact_dir
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = dir;
+    }
+
+    /// Wraps a tool so oversized text output and error messages are cut to its limits.
+    pub fn wrap_tool(self: &Arc<Self>, mut tool: AgentTool) -> AgentTool {
+        if SELF_PAGING_TOOLS.contains(&tool.name.as_str()) {
+            return tool;
+        }
+        tool.execute = Arc::new(LimitingToolExecutor {
+            tool_name: tool.name.clone(),
+            inner: tool.execute.clone(),
+            limiter: self.clone(),
+        });
+        tool
+    }
+
+    /// Limits `text`, returning the replacement and the spill file if one was written.
+    fn apply(
+        &self,
+        tool_name: &str,
+        tool_call_id: &str,
+        part: usize,
+        text: &str,
+    ) -> Option<(String, Option<PathBuf>)> {
+        let limited = limit_output(text, self.config.limits_for(tool_name))?;
+        let spilled = if self.config.spill {
+            self.spill(tool_name, tool_call_id, part, text)
+        } else {
+            None
+        };
+        Some((limited.render(spilled.as_deref()), spilled))
+    }
+
+    fn spill(
+        &self,
+        tool_name: &str,
+        tool_call_id: &str,
+        part: usize,
+        text: &str,
+    ) -> Option<PathBuf> {
+        let dir = self
+            .artifact_dir
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()?;
+        let call_id = tool_call_id
+            .chars()
+            .map(|ch| {
+                if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' {
+                    ch
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        let suffix = if part == 0 {
+            String::new()
+        } else {
+            format!("-{part}")
+        };
+        let path = dir.join(format!("{tool_name}-{call_id}{suffix}.txt"));
+        match std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, text)) {
+            Ok(()) => Some(path),
+            Err(error) => {
+                warn!(
+                    "failed to spill {tool_name} output to {}: {error}",
+                    path.display()
+                );
+                None
+            }
+        }
+    }
+}
+
+/// `<session>.artifacts` next to a `<session>.jsonl` file.
+pub fn artifact_dir_for_session(session_file: &Path) -> PathBuf {
+    session_file.with_extension("artifacts")
+}
+
+struct LimitingToolExecutor {
+    tool_name: String,
+    inner: AgentToolExecuteFn,
+    limiter: Arc<ToolOutputLimiter>,
+}
+
+#[async_trait]
+impl AgentToolExecutor for LimitingToolExecutor {
+    async fn execute(
+        &self,
+        tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        match self.inner.execute(tool_call_id.clone(), args).await {
+            Ok(mut result) => {
+                let mut spilled = Vec::new();
+                for (part, block) in result.content.iter_mut().enumerate() {
+                    let ToolResultContentBlock::Text { text, .. } = block else {
+                        continue;
+                    };
+                    if let Some((limited, path)) =
+                        self.limiter
+                            .apply(&self.tool_name, &tool_call_id, part, text)
+                    {
+                        *text = limited;
+                        spilled.extend(path);
+                    }
+                }
+                if let Some(details) = result.details.as_object_mut() {
+                    if !spilled.is_empty() {
+                        details.insert("outputTruncated".to_string(), json!(true));
+                        details.insert("fullOutputPaths".to_string(), json!(spilled));
+                    }
+                }
+                Ok(result)
+            }
+            Err(mut error) => {
+                if let Some((limited, _)) =
+                    self.limiter
+                        .apply(&self.tool_name, &tool_call_id, 0, &error.message)
+                {
+                    error.message = limited;
+                }
+                Err(error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{create_bash_tool, create_read_tool};
+
+    fn limits(max_lines: usize, max_bytes: usize, head_lines: usize) -> OutputLimits {
+        OutputLimits {
+            max_lines,
+            max_bytes,
+            head_lines,
+        }
+    }
+
+    #[test]
+    fn limit_output_keeps_head_and_tail_lines() {
+        let text = (1..=100)
+            .map(|i| format!("line {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(limit_output(&text, limits(100, 10_000, 10)), None);
+
+        let limited = limit_output(&text, limits(10, 10_000, 3)).expect("over line cap");
+        assert_eq!(limited.head, "line 1\nline 2\nline 3");
+        assert_eq!(limited.tail.lines().next(), Some("line 94"));
+        assert!(limited.tail.ends_with("line 100"));
+        assert_eq!(limited.omitted_lines, 90);
+        assert_eq!(
+            limited.render(Some(Path::new("/tmp/out.txt"))),
+            format!(
+                "line 1\nline 2\nline 3\n[... 90 lines ({} bytes) omitted. Full output: /tmp/out.txt ...]\n{}",
+                limited.omitted_bytes, limited.tail
+            )
+        );
+    }
+
+    #[test]
+    fn limit_output_respects_byte_cap_and_huge_lines() {
+        let text = format!("{}\n{}", "a".repeat(50), "b".repeat(50));
+        let limited = limit_output(&text, limits(100, 60, 50)).expect("over byte cap");
+        assert!(limited.head.len() + limited.tail.len() <= 60);
+        assert_eq!(limited.tail, "b".repeat(50));
+
+        let single = "é".repeat(1000);
+        let limited = limit_output(&single, limits(100, 101, 50)).expect("one huge line");
+        assert!(limited.head.starts_with('é') && limited.tail.ends_with('é'));
+        assert!(limited.head.len() + limited.tail.len() <= 101);
+        assert_eq!(limited.omitted_lines, 0);
+    }
+
+    #[tokio::test]
+    async fn spills_full_bash_output_and_leaves_read_alone() {
+        let dir = tempdir().expect("tempdir");
+        let session_file = dir.path().join("sessions").join("s1.jsonl");
+        let mut config = ToolOutputConfig::default();
+        config
+            .tools
+            .insert("bash".to_string(), limits(5, 10_000, 2));
+        let limiter = Arc::new(ToolOutputLimiter::new(config));
+        limiter.set_artifact_dir(Some(artifact_dir_for_session(&session_file)));
+
+        let bash = limiter.wrap_tool(create_bash_tool(dir.path()));
+        let result = bash
+            .execute
+            .execute("call/1".to_string(), json!({ "command": "seq 1 50" }))
+            .await
+            .expect("bash succeeds");
+        let ToolResultContentBlock::Text { text, .. } = &result.content[0] else {
+            panic!("text result");
+        };
+        let artifact = dir.path().join("sessions/s1.artifacts/bash-call_1.txt");
+        assert!(text.starts_with("1\n2\n[... "));
+        assert!(text.contains(&format!("Full output: {}", artifact.display())));
+        assert!(text.contains("\n49\n50"));
+        assert_eq!(result.details["fullOutputPaths"], json!([artifact]));
+        let full_output = std::fs::read_to_string(&artifact).expect("artifact");
+        assert!(full_output.starts_with(&(1..=50).map(|i| format!("{i}\n")).collect::<String>()));
+
+        std::fs::write(dir.path().join("long.txt"), "x\n".repeat(50)).expect("seed file");
+        let read = limiter.wrap_tool(create_read_tool(dir.path()));
+        let result = read
+            .execute
+            .execute("call-2".to_string(), json!({ "path": "long.txt" }))
+            .await
+            .expect("read succeeds");
+        let ToolResultContentBlock::Text { text, .. } = &result.content[0] else {
+            panic!("text result");
+        };
+        assert!(!text.contains("omitted"));
+    }
+}
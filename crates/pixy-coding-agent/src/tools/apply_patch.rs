@@ -0,0 +1,813 @@
+use std::collections::HashSet;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use pixy_agent_core::{AgentTool, AgentToolArgs, AgentToolExecutor, AgentToolResult};
+use pixy_ai::PiAiError;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::common::{
+    first_changed_line, format_diff_stat_line, invalid_tool_args, line_change_counts, observe_file,
+    record_file_snapshot, resolve_to_cwd, review_file_change, text_result, tool_execution_failed,
+};
+use super::edit_match::{
+    adapt_replacement, find_fuzzy_match, format_replacement_preview, FuzzyMatch, FuzzyOutcome,
+};
+use crate::diff_review::SharedDiffReview;
+use crate::file_changes::SharedFileChangeTracker;
+use crate::file_snapshots::SharedFileSnapshots;
+
+pub fn create_apply_patch_tool(cwd: impl AsRef<Path>) -> AgentTool {
+    create_apply_patch_tool_with_snapshots(cwd, None, None, None)
+}
+
+pub(crate) fn create_apply_patch_tool_with_snapshots(
+    cwd: impl AsRef<Path>,
+    snapshots: Option<SharedFileSnapshots>,
+    file_changes: Option<SharedFileChangeTracker>,
+    review: Option<SharedDiffReview>,
+) -> AgentTool {
+    let cwd = cwd.as_ref().to_path_buf();
+    let conflict_cwd = cwd.clone();
+    AgentTool {
+        name: "apply_patch".to_string(),
+        label: "apply_patch".to_string(),
+        description: "Apply several hunks across one or more files in one call, either as a unified diff (`patch`) or as structured `files` with oldText/newText hunks. Hunk context that has drifted (line numbers, whitespace, indentation, near-identical lines) is matched fuzzily and reported. The patch is atomic: if any hunk fails, no file is changed and the error lists every hunk's status.".to_string(),
+        parameters: ApplyPatchArgs::schema(),
+        conflict_key: Some(Arc::new(move |args: &Value| {
+            // A patch queues behind other calls on any of the files it touches.
+            let Some(files) = ApplyPatchArgs::parse(args.clone())
+                .ok()
+                .and_then(|args| args.file_patches().ok())
+            else {
+                return Vec::new();
+            };
+            files
+                .iter()
+                .map(|file| {
+                    resolve_to_cwd(&conflict_cwd, &file.path)
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect()
+        })),
+        execute: Arc::new(ApplyPatchToolExecutor {
+            cwd,
+            snapshots,
+            file_changes,
+            review,
+        }),
+    }
+}
+
+#[derive(Deserialize, AgentToolArgs)]
+#[serde(rename_all = "camelCase")]
+struct ApplyPatchArgs {
+    /// Unified diff covering one or more files: `--- a/path` and `+++ b/path` headers, each followed by `@@ -line,count +line,count @@` hunks of ` ` context, `-` removed and `+` added lines. Use /dev/null as the old path to create a file and as the new path to delete one.
+    patch: Option<String>,
+    /// Structured alternative to `patch`: the files to change and their replacements.
+    #[serde(default)]
+    files: Vec<FilePatchArgs>,
+}
+
+#[derive(Deserialize, AgentToolArgs)]
+#[serde(rename_all = "camelCase")]
+struct FilePatchArgs {
+    /// Path to change, absolute or relative to workspace cwd.
+    path: String,
+    /// Replacements applied in order. A single hunk with an empty oldText creates the file with its newText.
+    #[serde(default)]
+    hunks: Vec<HunkArgs>,
+    /// Delete the file. Defaults to false.
+    delete: Option<bool>,
+}
+
+#[derive(Deserialize, AgentToolArgs)]
+#[serde(rename_all = "camelCase")]
+struct HunkArgs {
+    /// Text to replace, with enough surrounding lines to be unique in the file.
+    old_text: String,
+    /// Replacement text.
+    new_text: String,
+}
+
+impl ApplyPatchArgs {
+    fn file_patches(self) -> Result<Vec<FilePatch>, String> {
+        let files = match (self.patch, self.files.is_empty()) {
+            (Some(patch), true) => parse_unified_diff(&patch)?,
+            (None, false) => self
+                .files
+                .into_iter()
+                .map(FilePatch::from_args)
+                .collect::<Result<_, _>>()?,
+            (Some(_), false) => return Err("Pass either `patch` or `files`, not both".to_string()),
+            (None, true) => return Err("Missing `patch` or `files`".to_string()),
+        };
+        let mut seen = HashSet::new();
+        for file in &files {
+            if !seen.insert(file.path.as_str()) {
+                return Err(format!(
+                    "{} appears more than once; put all of its hunks under one entry",
+                    file.path
+                ));
+            }
+        }
+        Ok(files)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FileOperation {
+    Update,
+    Create,
+    Delete,
+}
+
+impl FileOperation {
+    fn key(self) -> &'static str {
+        match self {
+            Self::Update => "update",
+            Self::Create => "create",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+struct FilePatch {
+    path: String,
+    operation: FileOperation,
+    hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    fn from_args(args: FilePatchArgs) -> Result<Self, String> {
+        let hunks = args
+            .hunks
+            .into_iter()
+            .map(|hunk| Hunk {
+                old_text: hunk.old_text,
+                new_text: hunk.new_text,
+                line_hint: None,
+                whole_lines: false,
+            })
+            .collect::<Vec<_>>();
+        let operation = if args.delete.unwrap_or(false) {
+            FileOperation::Delete
+        } else if matches!(hunks.as_slice(), [hunk] if hunk.old_text.is_empty()) {
+            FileOperation::Create
+        } else if hunks.is_empty() {
+            return Err(format!("{} has no hunks", args.path));
+        } else {
+            FileOperation::Update
+        };
+        Ok(Self {
+            path: args.path,
+            operation,
+            hunks,
+        })
+    }
+}
+
+struct Hunk {
+    old_text: String,
+    new_text: String,
+    /// 1-based line of the hunk in the original file, from a unified diff `@@` header.
+    line_hint: Option<usize>,
+    /// Diff hunks cover whole lines; structured hunks may start and end mid-line, like `edit`.
+    whole_lines: bool,
+}
+
+/// Splits a unified diff into per-file patches. Hunk line counts are ignored, since models
+/// often get them wrong; a hunk runs until the next hunk or file header.
+fn parse_unified_diff(patch: &str) -> Result<Vec<FilePatch>, String> {
+    let lines = patch.trim_end_matches('\n').lines().collect::<Vec<_>>();
+    let is_file_header = |index: usize| {
+        lines[index].starts_with("--- ")
+            && lines
+                .get(index + 1)
+                .is_some_and(|next| next.starts_with("+++ "))
+    };
+
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        if is_file_header(index) {
+            let old_path = diff_header_path(&line[4..], "a/");
+            let new_path = diff_header_path(&lines[index + 1][4..], "b/");
+            let (path, operation) = match (old_path, new_path) {
+                (None, Some(path)) => (path, FileOperation::Create),
+                (Some(path), None) => (path, FileOperation::Delete),
+                (Some(old), Some(new)) if old == new => (new, FileOperation::Update),
+                (Some(old), Some(new)) => {
+                    return Err(format!(
+                        "Renaming {old} to {new} is not supported; delete the old file and create the new one"
+                    ))
+                }
+                (None, None) => return Err("A file header names /dev/null twice".to_string()),
+            };
+            files.push(FilePatch {
+                path,
+                operation,
+                hunks: Vec::new(),
+            });
+            index += 2;
+            continue;
+        }
+        if !line.starts_with("@@") {
+            // `diff --git`, `index` and mode lines, or prose around the diff.
+            index += 1;
+            continue;
+        }
+
+        let file = files
+            .last_mut()
+            .ok_or_else(|| "Found a hunk before any `---`/`+++` file header".to_string())?;
+        let line_hint = hunk_start_line(line);
+        let mut old_lines = Vec::new();
+        let mut new_lines = Vec::new();
+        index += 1;
+        while index < lines.len() {
+            let body = lines[index];
+            if body.starts_with("@@") || body.starts_with("diff --git ") || is_file_header(index) {
+                break;
+            }
+            match body.chars().next() {
+                Some('+') => new_lines.push(&body[1..]),
+                Some('-') => old_lines.push(&body[1..]),
+                Some(' ') => {
+                    old_lines.push(&body[1..]);
+                    new_lines.push(&body[1..]);
+                }
+                // `\ No newline at end of file`
+                Some('\\') => {}
+                // Editors and models often strip the space of blank context lines.
+                None => {
+                    old_lines.push("");
+                    new_lines.push("");
+                }
+                Some(_) => {
+                    return Err(format!(
+                        "Line {} of the patch is not a hunk line (it must start with ' ', '-' or '+'): {body}",
+                        index + 1
+                    ))
+                }
+            }
+            index += 1;
+        }
+        file.hunks.push(Hunk {
+            old_text: old_lines.join("\n"),
+            new_text: new_lines.join("\n"),
+            line_hint,
+            whole_lines: true,
+        });
+    }
+
+    if files.is_empty() {
+        return Err("No `--- a/path` / `+++ b/path` file headers found in `patch`".to_string());
+    }
+    if let Some(file) = files
+        .iter()
+        .find(|file| file.operation != FileOperation::Delete && file.hunks.is_empty())
+    {
+        return Err(format!("{} has no hunks", file.path));
+    }
+    Ok(files)
+}
+
+/// The path of a `---`/`+++` header, without its `a/`/`b/` prefix or timestamp; `None` for
+/// `/dev/null`.
+fn diff_header_path(raw: &str, prefix: &str) -> Option<String> {
+    let path = raw.split('\t').next().unwrap_or(raw).trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// The old-file start line of a `@@ -12,5 +12,6 @@` header.
+fn hunk_start_line(header: &str) -> Option<usize> {
+    let old_range = header.split_whitespace().nth(1)?.strip_prefix('-')?;
+    old_range.split(',').next()?.parse().ok()
+}
+
+struct ApplyPatchToolExecutor {
+    cwd: PathBuf,
+    snapshots: Option<SharedFileSnapshots>,
+    file_changes: Option<SharedFileChangeTracker>,
+    review: Option<SharedDiffReview>,
+}
+
+#[async_trait]
+impl AgentToolExecutor for ApplyPatchToolExecutor {
+    async fn execute(
+        &self,
+        _tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        let files = ApplyPatchArgs::parse(args)?
+            .file_patches()
+            .map_err(invalid_tool_args)?;
+        execute_apply_patch(
+            &self.cwd,
+            files,
+            self.snapshots.as_ref(),
+            self.file_changes.as_ref(),
+            self.review.as_ref(),
+        )
+        .await
+    }
+}
+
+/// One file of the patch, worked out in memory before anything is written.
+struct PlannedFile {
+    path: String,
+    absolute_path: PathBuf,
+    operation: FileOperation,
+    before: Option<String>,
+    /// `None` deletes the file.
+    after: Option<String>,
+    hunks: Vec<Result<AppliedHunk, HunkFailure>>,
+    /// Why the file as a whole cannot be patched, e.g. it does not exist.
+    failure: Option<HunkFailure>,
+}
+
+impl PlannedFile {
+    fn failed(&self) -> bool {
+        self.failure.is_some() || self.hunks.iter().any(Result::is_err)
+    }
+}
+
+struct AppliedHunk {
+    /// 1-based line where the hunk landed, counted in the file as it was before this hunk.
+    start_line: usize,
+    /// Set when the hunk's text had no exact match; carries the matched and replacement text.
+    fuzzy: Option<(FuzzyMatch, String, String)>,
+}
+
+struct HunkFailure {
+    reason: &'static str,
+    message: String,
+}
+
+impl HunkFailure {
+    fn new(reason: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            reason,
+            message: message.into(),
+        }
+    }
+}
+
+async fn execute_apply_patch(
+    cwd: &Path,
+    files: Vec<FilePatch>,
+    snapshots: Option<&SharedFileSnapshots>,
+    file_changes: Option<&SharedFileChangeTracker>,
+    review: Option<&SharedDiffReview>,
+) -> Result<AgentToolResult, PiAiError> {
+    let planned = files
+        .into_iter()
+        .map(|file| plan_file(cwd, file))
+        .collect::<Vec<_>>();
+    if planned.iter().any(PlannedFile::failed) {
+        return Err(tool_execution_failed(failure_message(&planned))
+            .with_details(json!({ "applied": false, "files": files_details(&planned) })));
+    }
+
+    for file in &planned {
+        review_file_change(
+            review,
+            "apply_patch",
+            &file.path,
+            file.before.as_deref(),
+            file.after.as_deref().unwrap_or_default(),
+        )
+        .await?;
+    }
+    for (index, file) in planned.iter().enumerate() {
+        if let Err(error) = write_planned_file(file, snapshots) {
+            let restored = rollback(&planned[..index]);
+            return Err(tool_execution_failed(format!(
+                "{}; restored {restored} already written file(s), so no files were changed.",
+                error.message
+            )));
+        }
+    }
+    for file in &planned {
+        observe_file(file_changes, &file.absolute_path);
+    }
+
+    let mut text = planned
+        .iter()
+        .map(|file| {
+            format_diff_stat_line(
+                &file.path,
+                file.before.as_deref().unwrap_or_default(),
+                file.after.as_deref().unwrap_or_default(),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    for file in &planned {
+        for (index, hunk) in file.hunks.iter().enumerate() {
+            if let Ok(AppliedHunk {
+                fuzzy: Some((matched, before, replacement)),
+                ..
+            }) = hunk
+            {
+                text.push_str(&format!(
+                    "\n\n{} hunk {}: no exact match; applied a match {} ({:.0}% confidence) at lines {}-{}:\n{}",
+                    file.path,
+                    index + 1,
+                    matched.strategy.describe(),
+                    matched.confidence * 100.0,
+                    matched.start_line,
+                    matched.end_line,
+                    format_replacement_preview(before, replacement)
+                ));
+            }
+        }
+    }
+    Ok(text_result(
+        text,
+        json!({ "applied": true, "files": files_details(&planned) }),
+    ))
+}
+
+fn plan_file(cwd: &Path, file: FilePatch) -> PlannedFile {
+    let absolute_path = resolve_to_cwd(cwd, &file.path);
+    let before = match fs::read_to_string(&absolute_path) {
+        Ok(content) => Some(content),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => None,
+        Err(error) => {
+            let message = format!("Failed to read {}: {error}", file.path);
+            return planned_failure(file, absolute_path, None, "read_failed", message);
+        }
+    };
+
+    match (file.operation, before) {
+        (FileOperation::Create, Some(before)) => {
+            let message = format!("{} already exists", file.path);
+            planned_failure(file, absolute_path, Some(before), "already_exists", message)
+        }
+        (FileOperation::Update | FileOperation::Delete, None) => {
+            let message = format!("{} does not exist", file.path);
+            planned_failure(file, absolute_path, None, "not_found", message)
+        }
+        (FileOperation::Create, None) => {
+            let mut content = file
+                .hunks
+                .iter()
+                .map(|hunk| hunk.new_text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            if file.hunks.iter().all(|hunk| hunk.whole_lines) && !content.is_empty() {
+                content.push('\n');
+            }
+            let hunks = file
+                .hunks
+                .iter()
+                .map(|_| {
+                    Ok(AppliedHunk {
+                        start_line: 1,
+                        fuzzy: None,
+                    })
+                })
+                .collect();
+            PlannedFile {
+                path: file.path,
+                absolute_path,
+                operation: file.operation,
+                before: None,
+                after: Some(content),
+                hunks,
+                failure: None,
+            }
+        }
+        (FileOperation::Delete, Some(before)) => PlannedFile {
+            path: file.path,
+            absolute_path,
+            operation: file.operation,
+            before: Some(before),
+            after: None,
+            hunks: Vec::new(),
+            failure: None,
+        },
+        (FileOperation::Update, Some(before)) => {
+            let mut content = before.clone();
+            let mut line_delta = 0isize;
+            let mut hunks = Vec::new();
+            for hunk in &file.hunks {
+                let line_hint = hunk
+                    .line_hint
+                    .map(|line| line.saturating_add_signed(line_delta));
+                let result = apply_hunk(&content, hunk, line_hint);
+                let result = result.map(|(updated, applied)| {
+                    line_delta +=
+                        line_count(&hunk.new_text) as isize - line_count(&hunk.old_text) as isize;
+                    content = updated;
+                    applied
+                });
+                hunks.push(result);
+            }
+            PlannedFile {
+                path: file.path,
+                absolute_path,
+                operation: file.operation,
+                before: Some(before),
+                after: Some(content),
+                hunks,
+                failure: None,
+            }
+        }
+    }
+}
+
+fn planned_failure(
+    file: FilePatch,
+    absolute_path: PathBuf,
+    before: Option<String>,
+    reason: &'static str,
+    message: String,
+) -> PlannedFile {
+    PlannedFile {
+        path: file.path,
+        absolute_path,
+        operation: file.operation,
+        before,
+        after: None,
+        hunks: Vec::new(),
+        failure: Some(HunkFailure::new(reason, message)),
+    }
+}
+
+fn line_count(text: &str) -> usize {
+    if text.is_empty() {
+        0
+    } else {
+        text.split('\n').count()
+    }
+}
+
+/// Applies one hunk to `content`, returning the updated content.
+fn apply_hunk(
+    content: &str,
+    hunk: &Hunk,
+    line_hint: Option<usize>,
+) -> Result<(String, AppliedHunk), HunkFailure> {
+    if hunk.old_text.is_empty() {
+        return insert_hunk(content, hunk, line_hint);
+    }
+
+    let (mut range, fuzzy) = locate_hunk(content, hunk, line_hint)?;
+    let replacement = match &fuzzy {
+        Some(matched) => adapt_replacement(content, &hunk.old_text, &hunk.new_text, matched),
+        None => hunk.new_text.clone(),
+    };
+    if hunk.whole_lines && replacement.is_empty() {
+        // Removing whole lines also removes the line break that ended them.
+        if content[range.end..].starts_with('\n') {
+            range.end += 1;
+        } else if range.start > 0 {
+            range.start -= 1;
+        }
+    }
+    let start_line = content[..range.start].matches('\n').count() + 1;
+    let updated = format!(
+        "{}{replacement}{}",
+        &content[..range.start],
+        &content[range.end..]
+    );
+    let fuzzy = fuzzy.map(|matched| {
+        let before = content[matched.range.clone()].to_string();
+        (matched, before, replacement)
+    });
+    Ok((updated, AppliedHunk { start_line, fuzzy }))
+}
+
+/// A diff hunk without old lines inserts its new lines after line `line_hint`.
+fn insert_hunk(
+    content: &str,
+    hunk: &Hunk,
+    line_hint: Option<usize>,
+) -> Result<(String, AppliedHunk), HunkFailure> {
+    let Some(after_line) = line_hint.filter(|_| hunk.whole_lines) else {
+        return Err(HunkFailure::new(
+            "empty_old_text",
+            "oldText is empty; include the lines around the insertion point as context",
+        ));
+    };
+    let mut offset = 0;
+    for _ in 0..after_line {
+        match content[offset..].find('\n') {
+            Some(end) => offset += end + 1,
+            None => {
+                offset = content.len();
+                break;
+            }
+        }
+    }
+    let mut inserted = String::new();
+    if offset == content.len() && !content.is_empty() && !content.ends_with('\n') {
+        inserted.push('\n');
+    }
+    inserted.push_str(&hunk.new_text);
+    inserted.push('\n');
+    let updated = format!("{}{inserted}{}", &content[..offset], &content[offset..]);
+    Ok((
+        updated,
+        AppliedHunk {
+            start_line: after_line + 1,
+            fuzzy: None,
+        },
+    ))
+}
+
+/// Finds the text a hunk replaces: a unique exact match, the exact match nearest the diff's
+/// line number, or a unique fuzzy match.
+fn locate_hunk(
+    content: &str,
+    hunk: &Hunk,
+    line_hint: Option<usize>,
+) -> Result<(Range<usize>, Option<FuzzyMatch>), HunkFailure> {
+    let old_text = hunk.old_text.as_str();
+    let starts = content
+        .match_indices(old_text)
+        .map(|(start, _)| start)
+        .filter(|&start| {
+            let end = start + old_text.len();
+            !hunk.whole_lines
+                || ((start == 0 || content[..start].ends_with('\n'))
+                    && (end == content.len() || content[end..].starts_with(['\n', '\r'])))
+        })
+        .collect::<Vec<_>>();
+    match (starts.as_slice(), line_hint) {
+        ([start], _) => return Ok((*start..*start + old_text.len(), None)),
+        ([], _) => {}
+        (_, Some(line_hint)) => {
+            let distance =
+                |start: usize| (content[..start].matches('\n').count() + 1).abs_diff(line_hint);
+            let nearest = starts.iter().map(|&start| distance(start)).min();
+            let closest = starts
+                .iter()
+                .copied()
+                .filter(|&start| Some(distance(start)) == nearest)
+                .collect::<Vec<_>>();
+            if let [start] = closest.as_slice() {
+                return Ok((*start..*start + old_text.len(), None));
+            }
+            return Err(ambiguous(closest.len(), "exactly"));
+        }
+        (_, None) => return Err(ambiguous(starts.len(), "exactly")),
+    }
+
+    match find_fuzzy_match(content, old_text) {
+        FuzzyOutcome::Unique(matched) => Ok((matched.range.clone(), Some(matched))),
+        FuzzyOutcome::Ambiguous { strategy, count } => Err(ambiguous(count, strategy.describe())),
+        FuzzyOutcome::NotFound(closest) => {
+            let mut message = "Could not find the hunk's old lines in the file.".to_string();
+            if let Some(closest) = closest {
+                message.push_str(&format!(
+                    "\nClosest match ({:.0}% similar) at lines {}-{}:\n{}",
+                    closest.confidence * 100.0,
+                    closest.start_line,
+                    closest.end_line,
+                    &content[closest.range.clone()]
+                ));
+            }
+            Err(HunkFailure::new("not_found", message))
+        }
+    }
+}
+
+fn ambiguous(count: usize, how: &str) -> HunkFailure {
+    HunkFailure::new(
+        "ambiguous",
+        format!("{count} locations match the hunk's old lines {how}; include more context lines so it is unique."),
+    )
+}
+
+fn write_planned_file(
+    file: &PlannedFile,
+    snapshots: Option<&SharedFileSnapshots>,
+) -> Result<(), PiAiError> {
+    record_file_snapshot(snapshots, &file.absolute_path)?;
+    match &file.after {
+        Some(after) => {
+            if let Some(parent) = file.absolute_path.parent() {
+                fs::create_dir_all(parent).map_err(|error| {
+                    tool_execution_failed(format!(
+                        "Failed to create parent directories of {}: {error}",
+                        file.path
+                    ))
+                })?;
+            }
+            fs::write(&file.absolute_path, after.as_bytes()).map_err(|error| {
+                tool_execution_failed(format!("Failed to write {}: {error}", file.path))
+            })
+        }
+        None => fs::remove_file(&file.absolute_path).map_err(|error| {
+            tool_execution_failed(format!("Failed to delete {}: {error}", file.path))
+        }),
+    }
+}
+
+/// Puts back the original content of files already written; returns how many were restored.
+fn rollback(written: &[PlannedFile]) -> usize {
+    written
+        .iter()
+        .filter(|file| match &file.before {
+            Some(before) => fs::write(&file.absolute_path, before.as_bytes()).is_ok(),
+            None => fs::remove_file(&file.absolute_path).is_ok(),
+        })
+        .count()
+}
+
+fn failure_message(planned: &[PlannedFile]) -> String {
+    let mut lines = vec!["Patch not applied; no files were changed.".to_string()];
+    for file in planned {
+        if let Some(failure) = &file.failure {
+            lines.push(format!("{}: {}", file.path, failure.message));
+        }
+        for (index, hunk) in file.hunks.iter().enumerate() {
+            if let Err(failure) = hunk {
+                lines.push(format!(
+                    "{} hunk {}: {}",
+                    file.path,
+                    index + 1,
+                    failure.message
+                ));
+            }
+        }
+    }
+    lines.push(
+        "Hunks not listed here matched. Fix the failed ones and send the whole patch again."
+            .to_string(),
+    );
+    lines.join("\n")
+}
+
+fn files_details(planned: &[PlannedFile]) -> Value {
+    planned
+        .iter()
+        .map(|file| {
+            let before = file.before.as_deref().unwrap_or_default();
+            let after = file.after.as_deref().unwrap_or_default();
+            let (insertions, deletions) = line_change_counts(before, after);
+            let hunks = file
+                .hunks
+                .iter()
+                .enumerate()
+                .map(|(index, hunk)| hunk_details(index, hunk))
+                .collect::<Vec<_>>();
+            let mut details = json!({
+                "path": file.path,
+                "operation": file.operation.key(),
+                "status": if file.failed() { "failed" } else { "ok" },
+                "hunks": hunks,
+            });
+            if let Some(failure) = &file.failure {
+                details["reason"] = json!(failure.reason);
+                details["message"] = json!(failure.message);
+            } else if !file.failed() {
+                details["insertions"] = json!(insertions);
+                details["deletions"] = json!(deletions);
+                details["firstChangedLine"] = json!(first_changed_line(before, after));
+            }
+            details
+        })
+        .collect()
+}
+
+fn hunk_details(index: usize, hunk: &Result<AppliedHunk, HunkFailure>) -> Value {
+    match hunk {
+        Ok(applied) => match &applied.fuzzy {
+            None => json!({
+                "hunk": index + 1,
+                "status": "applied",
+                "strategy": "exact",
+                "confidence": 1.0,
+                "startLine": applied.start_line,
+            }),
+            Some((matched, _, _)) => json!({
+                "hunk": index + 1,
+                "status": "applied",
+                "strategy": matched.strategy.key(),
+                "confidence": (matched.confidence * 100.0).round() / 100.0,
+                "startLine": matched.start_line,
+                "endLine": matched.end_line,
+            }),
+        },
+        Err(failure) => json!({
+            "hunk": index + 1,
+            "status": "failed",
+            "reason": failure.reason,
+            "message": failure.message,
+        }),
+    }
+}
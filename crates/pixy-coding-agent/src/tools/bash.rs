@@ -1,19 +1,25 @@
+use std::io;
 use std::path::{Path, PathBuf};
+use std::process::{Output, Stdio};
 use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
-use pixy_agent_core::{AgentTool, AgentToolExecutor, AgentToolResult};
+use pixy_agent_core::{
+    AgentTool, AgentToolArgs, AgentToolExecutor, AgentToolResult, ToolOutputKind, ToolOutputStream,
+};
 use pixy_ai::PiAiError;
+use serde::Deserialize;
 use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::process::Command;
 use tokio::time::timeout;
 
 use crate::bash_command::normalize_nested_bash_lc;
 
 use super::common::{
-    format_timeout, get_optional_f64, get_required_string, invalid_tool_args, text_result,
-    tool_execution_failed, truncate_tail, truncated_by_str, DEFAULT_MAX_BYTES, DEFAULT_MAX_LINES,
+    format_timeout, text_result, tool_execution_failed, truncate_tail, truncated_by_str,
+    DEFAULT_MAX_BYTES, DEFAULT_MAX_LINES,
 };
 
 pub fn create_bash_tool(cwd: impl AsRef<Path>) -> AgentTool {
@@ -24,19 +30,21 @@ pub fn create_bash_tool(cwd: impl AsRef<Path>) -> AgentTool {
         description:
             "Execute a shell command in the cwd and return combined stdout/stderr. This tool already runs via `bash -lc`."
                 .to_string(),
-        parameters: json!({
-            "type": "object",
-            "properties": {
-                "command": { "type": "string", "description": "Shell command to execute (do not prefix with `bash -lc`; the tool already does that)." },
-                "timeout": { "type": "number", "exclusiveMinimum": 0, "description": "Optional timeout in seconds." }
-            },
-            "required": ["command"],
-            "additionalProperties": false
-        }),
+        parameters: BashArgs::schema(),
+        conflict_key: None,
         execute: Arc::new(BashToolExecutor { cwd }),
     }
 }
 
+#[derive(Deserialize, AgentToolArgs)]
+struct BashArgs {
+    /// Shell command to execute (do not prefix with `bash -lc`; the tool already does that).
+    command: String,
+    /// Optional timeout in seconds.
+    #[tool(exclusive_minimum = 0)]
+    timeout: Option<f64>,
+}
+
 struct BashToolExecutor {
     cwd: PathBuf,
 }
@@ -53,6 +61,65 @@ impl AgentToolExecutor for BashToolExecutor {
     }
 }
 
+/// Runs `process`, writing each stdout/stderr line to `output` as it arrives.
+async fn run_streaming(
+    mut process: Command,
+    output: Option<&ToolOutputStream>,
+) -> io::Result<Output> {
+    let mut child = process.spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| io::Error::other("stdout was not captured"))?;
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| io::Error::other("stderr was not captured"))?;
+    let (stdout, stderr, status) = tokio::try_join!(
+        read_lines(stdout, ToolOutputKind::Stdout, output),
+        read_lines(stderr, ToolOutputKind::Stderr, output),
+        child.wait(),
+    )?;
+    Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+async fn read_lines(
+    reader: impl AsyncRead + Unpin,
+    kind: ToolOutputKind,
+    stream: Option<&ToolOutputStream>,
+) -> io::Result<Vec<u8>> {
+    let mut reader = BufReader::new(reader);
+    let mut output = Vec::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line).await? == 0 {
+            return Ok(output);
+        }
+        let text = String::from_utf8_lossy(&line);
+        report_line(stream, kind, text.trim_end_matches(['\n', '\r']));
+        output.extend_from_slice(&line);
+    }
+}
+
+fn report_line(stream: Option<&ToolOutputStream>, kind: ToolOutputKind, line: &str) {
+    if let Some(stream) = stream {
+        stream.line(kind, line);
+    }
+}
+
+/// Trailer appended to the output of a command that exited unsuccessfully.
+pub(crate) fn exit_status_line(code: Option<i32>) -> String {
+    match code {
+        Some(code) => format!("Command exited with code {code}"),
+        None => "Command exited with unknown status".to_string(),
+    }
+}
+
 async fn execute_bash_tool(cwd: &Path, args: Value) -> Result<AgentToolResult, PiAiError> {
     if !cwd.exists() {
         return Err(tool_execution_failed(format!(
@@ -61,37 +128,38 @@ async fn execute_bash_tool(cwd: &Path, args: Value) -> Result<AgentToolResult, P
         )));
     }
 
-    let command = get_required_string(&args, "command")?;
+    let BashArgs {
+        command,
+        timeout: timeout_seconds,
+    } = BashArgs::parse(args)?;
     let normalized_command = normalize_nested_bash_lc(&command);
-    let timeout_seconds = get_optional_f64(&args, "timeout")?;
-    if let Some(seconds) = timeout_seconds {
-        if seconds <= 0.0 {
-            return Err(invalid_tool_args("`timeout` must be > 0"));
-        }
-    }
 
     let mut process = Command::new("bash");
     process
         .arg("-lc")
         .arg(normalized_command.as_ref())
-        .current_dir(cwd);
+        .current_dir(cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
 
+    let stream = ToolOutputStream::current();
+    let run = run_streaming(process, stream.as_ref());
     let output = match timeout_seconds {
-        Some(seconds) => timeout(Duration::from_secs_f64(seconds), process.output())
+        Some(seconds) => timeout(Duration::from_secs_f64(seconds), run)
             .await
             .map_err(|_| {
-                tool_execution_failed(format!(
+                let message = format!(
                     "Command timed out after {} seconds",
                     format_timeout(seconds)
-                ))
-            })?
-            .map_err(|error| {
-                tool_execution_failed(format!("Failed to execute command: {error}"))
+                );
+                report_line(stream.as_ref(), ToolOutputKind::Status, &message);
+                tool_execution_failed(message)
             })?,
-        None => process.output().await.map_err(|error| {
-            tool_execution_failed(format!("Failed to execute command: {error}"))
-        })?,
-    };
+        None => run.await,
+    }
+    .map_err(|error| tool_execution_failed(format!("Failed to execute command: {error}")))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout);
     let stderr = String::from_utf8_lossy(&output.stderr);
@@ -109,6 +177,8 @@ async fn execute_bash_tool(cwd: &Path, args: Value) -> Result<AgentToolResult, P
         combined = "(no output)".to_string();
     }
 
+    // A hard cap for callers without a session; sessions cut the output further per tool and
+    // spill the rest to a file (see `tool_output`).
     let truncation = truncate_tail(&combined, DEFAULT_MAX_LINES, DEFAULT_MAX_BYTES);
     let mut output_text = truncation.content.clone();
     if truncation.truncated {
@@ -119,12 +189,15 @@ async fn execute_bash_tool(cwd: &Path, args: Value) -> Result<AgentToolResult, P
     }
 
     if !output.status.success() {
-        if let Some(code) = output.status.code() {
-            output_text.push_str(&format!("\n\nCommand exited with code {code}"));
-        } else {
-            output_text.push_str("\n\nCommand exited with unknown status");
-        }
-        return Err(tool_execution_failed(output_text));
+        let code = output.status.code();
+        report_line(
+            stream.as_ref(),
+            ToolOutputKind::Status,
+            &exit_status_line(code),
+        );
+        output_text.push_str("\n\n");
+        output_text.push_str(&exit_status_line(code));
+        return Err(tool_execution_failed(output_text).with_details(json!({ "exitCode": code })));
     }
 
     Ok(text_result(
@@ -0,0 +1,403 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use async_trait::async_trait;
+use pixy_agent_core::{AgentTool, AgentToolExecutor, AgentToolResult};
+use pixy_ai::PiAiError;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+use tokio::process::{Child, Command};
+
+use crate::bash_command::normalize_nested_bash_lc;
+
+use super::common::{
+    get_optional_usize, get_required_string, invalid_tool_args, text_result, tool_execution_failed,
+    truncate_tail, DEFAULT_MAX_BYTES,
+};
+
+/// Lines kept per process; older output is dropped once a process exceeds this.
+const MAX_BUFFERED_LINES: usize = 10_000;
+const DEFAULT_LOG_LINES: usize = 200;
+/// Longer lines are split so one line without a newline cannot grow the buffer without bound.
+const MAX_LINE_BYTES: u64 = 16 * 1024;
+
+/// Background processes started by `bash_background`, shared between the tool and the session
+/// so they can be killed when the session ends.
+#[derive(Clone, Default)]
+pub struct BackgroundProcesses {
+    inner: Arc<Mutex<BackgroundProcessTable>>,
+}
+
+#[derive(Default)]
+struct BackgroundProcessTable {
+    next_id: usize,
+    processes: BTreeMap<String, BackgroundProcess>,
+}
+
+struct BackgroundProcess {
+    command: String,
+    started_at: Instant,
+    child: Child,
+    exit_code: Option<Option<i32>>,
+    output: Arc<Mutex<ProcessOutput>>,
+}
+
+#[derive(Default)]
+struct ProcessOutput {
+    lines: VecDeque<String>,
+    dropped: usize,
+    /// Absolute index of the first line `poll` has not returned yet.
+    poll_cursor: usize,
+}
+
+impl ProcessOutput {
+    fn push(&mut self, line: String) {
+        self.lines.push_back(line);
+        if self.lines.len() > MAX_BUFFERED_LINES {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    fn take_unpolled(&mut self) -> Vec<String> {
+        let start = self.poll_cursor.saturating_sub(self.dropped);
+        let lines = self.lines.iter().skip(start).cloned().collect();
+        self.poll_cursor = self.dropped + self.lines.len();
+        lines
+    }
+
+    fn tail(&self, count: usize) -> Vec<String> {
+        let skip = self.lines.len().saturating_sub(count);
+        self.lines.iter().skip(skip).cloned().collect()
+    }
+}
+
+impl BackgroundProcess {
+    fn status(&mut self) -> String {
+        if self.exit_code.is_none() {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                self.exit_code = Some(status.code());
+            }
+        }
+        match self.exit_code {
+            None => "running".to_string(),
+            Some(Some(code)) => format!("exited with code {code}"),
+            Some(None) => "terminated by signal".to_string(),
+        }
+    }
+}
+
+impl BackgroundProcesses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Kills every process that is still running. Returns how many were signalled.
+    pub fn kill_all(&self) -> usize {
+        let mut table = self.inner.lock().expect("background process lock poisoned");
+        let mut killed = 0;
+        for process in table.processes.values_mut() {
+            if process.status() == "running" {
+                kill_process(&mut process.child);
+                killed += 1;
+            }
+        }
+        table.processes.clear();
+        killed
+    }
+
+    fn start(&self, cwd: &Path, command: &str) -> Result<(String, Option<u32>), PiAiError> {
+        let mut process = Command::new("bash");
+        process
+            .arg("-lc")
+            .arg(normalize_nested_bash_lc(command).as_ref())
+            .current_dir(cwd)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        #[cfg(unix)]
+        process.process_group(0);
+
+        let mut child = process
+            .spawn()
+            .map_err(|error| tool_execution_failed(format!("Failed to start command: {error}")))?;
+        let pid = child.id();
+        let output = Arc::new(Mutex::new(ProcessOutput::default()));
+        if let Some(stdout) = child.stdout.take() {
+            spawn_line_reader(stdout, output.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            spawn_line_reader(stderr, output.clone());
+        }
+
+        let mut table = self.inner.lock().expect("background process lock poisoned");
+        table.next_id += 1;
+        let id = format!("bg-{}", table.next_id);
+        table.processes.insert(
+            id.clone(),
+            BackgroundProcess {
+                command: command.to_string(),
+                started_at: Instant::now(),
+                child,
+                exit_code: None,
+                output,
+            },
+        );
+        Ok((id, pid))
+    }
+
+    fn with_process<T>(
+        &self,
+        id: &str,
+        f: impl FnOnce(&mut BackgroundProcess) -> T,
+    ) -> Result<T, PiAiError> {
+        let mut table = self.inner.lock().expect("background process lock poisoned");
+        let known = table.processes.keys().cloned().collect::<Vec<_>>();
+        let process = table.processes.get_mut(id).ok_or_else(|| {
+            invalid_tool_args(format!(
+                "Unknown background process `{id}`. Known: {}",
+                if known.is_empty() {
+                    "(none)".to_string()
+                } else {
+                    known.join(", ")
+                }
+            ))
+        })?;
+        Ok(f(process))
+    }
+
+    fn list(&self) -> Vec<(String, String, String)> {
+        let mut table = self.inner.lock().expect("background process lock poisoned");
+        table
+            .processes
+            .iter_mut()
+            .map(|(id, process)| (id.clone(), process.status(), process.command.clone()))
+            .collect()
+    }
+}
+
+fn spawn_line_reader(
+    stream: impl AsyncRead + Unpin + Send + 'static,
+    output: Arc<Mutex<ProcessOutput>>,
+) {
+    // Reads raw bytes so non-UTF-8 output cannot stop the reader and leave the pipe undrained.
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(stream);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match (&mut reader)
+                .take(MAX_LINE_BYTES)
+                .read_until(b'\n', &mut line)
+                .await
+            {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            if line.ends_with(b"\n") {
+                line.pop();
+                if line.ends_with(b"\r") {
+                    line.pop();
+                }
+            }
+            output
+                .lock()
+                .expect("background output lock poisoned")
+                .push(String::from_utf8_lossy(&line).into_owned());
+        }
+    });
+}
+
+pub(super) fn kill_process(child: &mut Child) {
+    // Signal the whole process group so servers spawned by the shell go down with it.
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        let _ = std::process::Command::new("kill")
+            .arg("-TERM")
+            .arg("--")
+            .arg(format!("-{pid}"))
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+    let _ = child.start_kill();
+}
+
+pub fn create_bash_background_tool(
+    cwd: impl AsRef<Path>,
+    processes: BackgroundProcesses,
+) -> AgentTool {
+    let cwd = cwd.as_ref().to_path_buf();
+    AgentTool {
+        name: "bash_background".to_string(),
+        label: "bash_background".to_string(),
+        description: "Manage long-running shell commands (dev servers, watchers, slow builds) without blocking. `start` launches a command via `bash -lc` and returns an id; `poll` reports status and output produced since the last poll; `logs` returns the latest output lines; `kill` stops the process. Processes are killed when the session ends."
+            .to_string(),
+        parameters: json!({
+            "type": "object",
+            "properties": {
+                "action": { "type": "string", "enum": ["start", "poll", "logs", "kill"] },
+                "command": { "type": "string", "description": "Shell command to run for `start`." },
+                "id": { "type": "string", "description": "Process id returned by `start`. Omit for `poll` to list every process." },
+                "lines": { "type": "number", "minimum": 1, "description": "Number of trailing lines for `logs` (default 200)." }
+            },
+            "required": ["action"],
+            "additionalProperties": false
+        }),
+        conflict_key: None,
+        execute: Arc::new(BashBackgroundToolExecutor { cwd, processes }),
+    }
+}
+
+struct BashBackgroundToolExecutor {
+    cwd: PathBuf,
+    processes: BackgroundProcesses,
+}
+
+#[async_trait]
+impl AgentToolExecutor for BashBackgroundToolExecutor {
+    async fn execute(
+        &self,
+        _tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        let action = get_required_string(&args, "action")?;
+        match action.as_str() {
+            "start" => self.start(&args),
+            "poll" => self.poll(&args),
+            "logs" => self.logs(&args),
+            "kill" => self.kill(&args),
+            other => Err(invalid_tool_args(format!(
+                "Unknown action `{other}`; expected start, poll, logs or kill"
+            ))),
+        }
+    }
+}
+
+impl BashBackgroundToolExecutor {
+    fn start(&self, args: &Value) -> Result<AgentToolResult, PiAiError> {
+        if !self.cwd.exists() {
+            return Err(tool_execution_failed(format!(
+                "Working directory does not exist: {}",
+                self.cwd.display()
+            )));
+        }
+        let command = get_required_string(args, "command")?;
+        let (id, pid) = self.processes.start(&self.cwd, &command)?;
+        Ok(text_result(
+            format!("Started {id}: {command}"),
+            json!({ "id": id, "pid": pid, "status": "running" }),
+        ))
+    }
+
+    fn poll(&self, args: &Value) -> Result<AgentToolResult, PiAiError> {
+        let Some(id) = optional_id(args) else {
+            let processes = self.processes.list();
+            if processes.is_empty() {
+                return Ok(text_result(
+                    "No background processes.".to_string(),
+                    json!({ "processes": [] }),
+                ));
+            }
+            let text = processes
+                .iter()
+                .map(|(id, status, command)| format!("{id} [{status}] {command}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let details = processes
+                .into_iter()
+                .map(|(id, status, command)| json!({ "id": id, "status": status, "command": command }))
+                .collect::<Vec<_>>();
+            return Ok(text_result(text, json!({ "processes": details })));
+        };
+
+        let (status, elapsed, lines) = self.processes.with_process(&id, |process| {
+            let status = process.status();
+            let lines = process
+                .output
+                .lock()
+                .expect("background output lock poisoned")
+                .take_unpolled();
+            (status, process.started_at.elapsed().as_secs(), lines)
+        })?;
+        let mut text = format!("{id} {status} after {elapsed}s");
+        if lines.is_empty() {
+            text.push_str("\n(no new output)");
+        } else {
+            text.push('\n');
+            text.push_str(&render_lines(&lines));
+        }
+        Ok(text_result(
+            text,
+            json!({ "id": id, "status": status, "newLines": lines.len() }),
+        ))
+    }
+
+    fn logs(&self, args: &Value) -> Result<AgentToolResult, PiAiError> {
+        let id = optional_id(args).ok_or_else(|| invalid_tool_args("`id` is required for logs"))?;
+        let count = get_optional_usize(args, "lines")?.unwrap_or(DEFAULT_LOG_LINES);
+        if count == 0 {
+            return Err(invalid_tool_args("`lines` must be >= 1"));
+        }
+        let (status, lines, dropped) = self.processes.with_process(&id, |process| {
+            let status = process.status();
+            let output = process
+                .output
+                .lock()
+                .expect("background output lock poisoned");
+            (status, output.tail(count), output.dropped)
+        })?;
+        let text = if lines.is_empty() {
+            format!("{id} {status}\n(no output)")
+        } else {
+            format!("{id} {status}\n{}", render_lines(&lines))
+        };
+        Ok(text_result(
+            text,
+            json!({ "id": id, "status": status, "lines": lines.len(), "droppedLines": dropped }),
+        ))
+    }
+
+    fn kill(&self, args: &Value) -> Result<AgentToolResult, PiAiError> {
+        let id = optional_id(args).ok_or_else(|| invalid_tool_args("`id` is required for kill"))?;
+        let status = self.processes.with_process(&id, |process| {
+            let status = process.status();
+            if status == "running" {
+                kill_process(&mut process.child);
+                process.exit_code = Some(None);
+                "killed".to_string()
+            } else {
+                status
+            }
+        })?;
+        Ok(text_result(
+            format!("{id} {status}"),
+            json!({ "id": id, "status": status }),
+        ))
+    }
+}
+
+fn optional_id(args: &Value) -> Option<String> {
+    args.get("id")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(ToOwned::to_owned)
+}
+
+fn render_lines(lines: &[String]) -> String {
+    let joined = lines.join("\n");
+    let truncation = truncate_tail(&joined, lines.len(), DEFAULT_MAX_BYTES);
+    if truncation.truncated {
+        format!(
+            "[showing last {} bytes of output]\n{}",
+            truncation.output_bytes, truncation.content
+        )
+    } else {
+        truncation.content
+    }
+}
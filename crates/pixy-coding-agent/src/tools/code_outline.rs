@@ -0,0 +1,352 @@
+use std::path::Path;
+
+use tree_sitter::{Language, Node, Parser};
+
+/// Signatures are collapsed to one line and cut at this many characters.
+const MAX_SIGNATURE_CHARS: usize = 200;
+/// Nodes that belong to the definition after them: doc comments, attributes and decorators.
+const LEADING_NODE_KINDS: &[&str] = &[
+    "line_comment",
+    "block_comment",
+    "comment",
+    "attribute_item",
+    "decorator",
+];
+
+/// Nodes that wrap a single definition, such as `export` and Go's `type` keyword; comments before
+/// them belong to the definition.
+const WRAPPER_NODE_KINDS: &[&str] = &[
+    "decorated_definition",
+    "export_statement",
+    "lexical_declaration",
+    "variable_declaration",
+    "type_declaration",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum CodeLanguage {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Go,
+}
+
+impl CodeLanguage {
+    pub(super) fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        Some(match extension.as_str() {
+            "rs" => Self::Rust,
+            "py" | "pyi" => Self::Python,
+            "js" | "jsx" | "mjs" | "cjs" => Self::JavaScript,
+            "ts" | "mts" | "cts" => Self::TypeScript,
+            "tsx" => Self::Tsx,
+            "go" => Self::Go,
+            _ => return None,
+        })
+    }
+
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            Self::Rust => "rust",
+            Self::Python => "python",
+            Self::JavaScript => "javascript",
+            Self::TypeScript => "typescript",
+            Self::Tsx => "tsx",
+            Self::Go => "go",
+        }
+    }
+
+    fn grammar(self) -> Language {
+        match self {
+            Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Self::Python => tree_sitter_python::LANGUAGE.into(),
+            Self::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Self::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Self::Go => tree_sitter_go::LANGUAGE.into(),
+        }
+    }
+
+    /// Separator used when showing qualified names; lookups accept both `::` and `.`.
+    fn path_separator(self) -> &'static str {
+        match self {
+            Self::Rust => "::",
+            _ => ".",
+        }
+    }
+
+    /// The label of a definition node, or `None` for nodes that are only searched for nested
+    /// definitions.
+    fn definition_label(self, node: Node<'_>) -> Option<&'static str> {
+        let kind = node.kind();
+        match self {
+            Self::Rust => match kind {
+                "function_item" | "function_signature_item" => Some("fn"),
+                "struct_item" => Some("struct"),
+                "enum_item" => Some("enum"),
+                "union_item" => Some("union"),
+                "trait_item" => Some("trait"),
+                "impl_item" => Some("impl"),
+                "mod_item" => Some("mod"),
+                "type_item" => Some("type"),
+                "const_item" => Some("const"),
+                "static_item" => Some("static"),
+                "macro_definition" => Some("macro"),
+                _ => None,
+            },
+            Self::Python => match kind {
+                "function_definition" => Some("def"),
+                "class_definition" => Some("class"),
+                _ => None,
+            },
+            Self::JavaScript | Self::TypeScript | Self::Tsx => match kind {
+                "function_declaration" | "generator_function_declaration" => Some("function"),
+                "class_declaration" | "abstract_class_declaration" => Some("class"),
+                "method_definition" | "abstract_method_signature" => Some("method"),
+                "interface_declaration" => Some("interface"),
+                "type_alias_declaration" => Some("type"),
+                "enum_declaration" => Some("enum"),
+                "internal_module" | "module" => Some("namespace"),
+                "variable_declarator"
+                    if node.child_by_field_name("value").is_some_and(|value| {
+                        matches!(
+                            value.kind(),
+                            "arrow_function" | "function_expression" | "generator_function"
+                        )
+                    }) =>
+                {
+                    Some("function")
+                }
+                _ => None,
+            },
+            Self::Go => match kind {
+                "function_declaration" | "method_declaration" => Some("func"),
+                "type_spec" | "type_alias" => Some("type"),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// A function, type or other named item found in a source file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct Definition {
+    pub label: &'static str,
+    /// Names of the enclosing definitions followed by this one's name.
+    pub path: Vec<String>,
+    pub signature: String,
+    pub depth: usize,
+    /// 1-based line of the first doc comment, attribute or decorator, or of the item itself.
+    pub doc_start_line: usize,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+impl Definition {
+    pub(super) fn qualified_name(&self, language: CodeLanguage) -> String {
+        self.path.join(language.path_separator())
+    }
+
+    fn is_function(&self) -> bool {
+        matches!(self.label, "fn" | "def" | "function" | "method" | "func")
+    }
+}
+
+/// Definitions of `source` in document order. Function bodies are not searched, so local helpers
+/// and closures stay out of the outline.
+pub(super) fn outline(language: CodeLanguage, source: &str) -> Result<Vec<Definition>, String> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&language.grammar())
+        .map_err(|error| format!("Failed to load the {} parser: {error}", language.name()))?;
+    let tree = parser
+        .parse(source, None)
+        .ok_or_else(|| format!("Failed to parse {} source", language.name()))?;
+
+    let mut definitions = Vec::new();
+    collect_definitions(language, source, tree.root_node(), &[], &mut definitions);
+    Ok(definitions)
+}
+
+fn collect_definitions(
+    language: CodeLanguage,
+    source: &str,
+    node: Node<'_>,
+    parents: &[String],
+    definitions: &mut Vec<Definition>,
+) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        let Some(label) = language.definition_label(child) else {
+            collect_definitions(language, source, child, parents, definitions);
+            continue;
+        };
+        let mut path = parents.to_vec();
+        path.push(definition_name(language, source, child, label));
+        let definition = Definition {
+            label,
+            signature: signature(source, child),
+            depth: parents.len(),
+            doc_start_line: leading_start(child).start_position().row + 1,
+            start_line: child.start_position().row + 1,
+            end_line: child.end_position().row + 1,
+            path,
+        };
+        let nested_path = (!definition.is_function()).then(|| definition.path.clone());
+        definitions.push(definition);
+        if let Some(path) = nested_path {
+            collect_definitions(language, source, child, &path, definitions);
+        }
+    }
+}
+
+fn node_text<'a>(source: &'a str, node: Node<'_>) -> &'a str {
+    &source[node.byte_range()]
+}
+
+fn definition_name(language: CodeLanguage, source: &str, node: Node<'_>, label: &str) -> String {
+    // `impl Display for Config` and Go methods are named after the type they belong to, so
+    // `Config::fmt` and `Server.Start` resolve.
+    let name = match node.kind() {
+        "impl_item" => node
+            .child_by_field_name("type")
+            .map(|ty| node_text(source, ty)),
+        _ => node
+            .child_by_field_name("name")
+            .map(|name| node_text(source, name)),
+    };
+    let name = name
+        .map(|name| name.split('<').next().unwrap_or(name).trim().to_string())
+        .unwrap_or_else(|| label.to_string());
+    if language == CodeLanguage::Go && node.kind() == "method_declaration" {
+        if let Some(receiver) = node
+            .child_by_field_name("receiver")
+            .and_then(|receiver| go_receiver_type(node_text(source, receiver)))
+        {
+            return format!("{receiver}.{name}");
+        }
+    }
+    name
+}
+
+/// `(s *Server[T])` -> `Server`.
+fn go_receiver_type(receiver: &str) -> Option<String> {
+    let inner = receiver
+        .trim()
+        .trim_start_matches('(')
+        .trim_end_matches(')');
+    let ty = inner.split_whitespace().last()?.trim_start_matches('*');
+    let ty = ty.split('[').next().unwrap_or(ty);
+    (!ty.is_empty()).then(|| ty.to_string())
+}
+
+/// The definition's header up to its body, collapsed to one line.
+fn signature(source: &str, node: Node<'_>) -> String {
+    let body = node.child_by_field_name("body").or_else(|| {
+        node.child_by_field_name("value")
+            .and_then(|value| value.child_by_field_name("body"))
+    });
+    let header = match body {
+        Some(body) => &source[node.start_byte()..body.start_byte()],
+        None => node_text(source, node).lines().next().unwrap_or_default(),
+    };
+    let mut signature = header.split_whitespace().collect::<Vec<_>>().join(" ");
+    for suffix in ["{", "=>", ":", "="] {
+        if let Some(stripped) = signature.strip_suffix(suffix) {
+            signature = stripped.trim_end().to_string();
+        }
+    }
+
+    // Go type specs and JS `const f = () => ...` declarators leave their keyword in the parent.
+    if matches!(node.kind(), "type_spec" | "type_alias") {
+        signature = format!("type {signature}");
+    } else if node.kind() == "variable_declarator" {
+        if let Some(keyword) = node.parent().and_then(|parent| parent.child(0)) {
+            signature = format!("{} {signature}", node_text(source, keyword));
+        }
+    }
+
+    match signature.char_indices().nth(MAX_SIGNATURE_CHARS) {
+        Some((cut, _)) => format!("{}…", &signature[..cut]),
+        None => signature,
+    }
+}
+
+/// The first node of the definition including its doc comments, attributes and decorators.
+fn leading_start(node: Node<'_>) -> Node<'_> {
+    let mut start = node;
+    while let Some(parent) = start.parent() {
+        let wraps_only_start = parent.named_child_count() == 1
+            || parent.kind() == "decorated_definition"
+            || parent.kind() == "export_statement";
+        if !WRAPPER_NODE_KINDS.contains(&parent.kind()) || !wraps_only_start {
+            break;
+        }
+        start = parent;
+    }
+    while let Some(previous) = start.prev_named_sibling() {
+        let adjacent = previous.end_position().row + 1 >= start.start_position().row;
+        if !adjacent || !LEADING_NODE_KINDS.contains(&previous.kind()) {
+            break;
+        }
+        start = previous;
+    }
+    start
+}
+
+/// Resolves `query` (`name`, `Type::name` or `Type.name`) against the definitions' paths.
+pub(super) fn find_symbol<'a>(
+    language: CodeLanguage,
+    definitions: &'a [Definition],
+    query: &str,
+) -> Result<&'a Definition, String> {
+    let normalized = query.replace("::", ".");
+    let segments = normalized
+        .split('.')
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    if segments.is_empty() {
+        return Err("`symbol` must name a definition".to_string());
+    }
+
+    let matches = definitions
+        .iter()
+        .filter(|definition| {
+            // Go method names already carry their receiver (`Server.Start`).
+            let path = definition
+                .path
+                .iter()
+                .flat_map(|name| name.split('.'))
+                .collect::<Vec<_>>();
+            path.ends_with(&segments)
+        })
+        .collect::<Vec<_>>();
+    // A type and its `impl` blocks share a name; the type itself is the better answer.
+    let preferred = matches
+        .iter()
+        .filter(|definition| definition.label != "impl")
+        .collect::<Vec<_>>();
+    match (matches.as_slice(), preferred.as_slice()) {
+        ([], _) => Err(format!(
+            "No definition named `{query}` found. Call read with outline=true to list the definitions in this file."
+        )),
+        ([only], _) => Ok(only),
+        (_, [only]) => Ok(only),
+        _ => Err(format!(
+            "`{query}` matches {} definitions: {}. Pass a qualified name to pick one.",
+            matches.len(),
+            matches
+                .iter()
+                .map(|definition| format!(
+                    "{} (line {})",
+                    definition.qualified_name(language),
+                    definition.start_line
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
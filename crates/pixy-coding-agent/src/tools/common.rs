@@ -1,13 +1,23 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use pixy_agent_core::AgentToolResult;
+use pixy_agent_core::{resolve_tool_path, AgentToolResult, ToolConflictKeyFn};
 use pixy_ai::{PiAiError, PiAiErrorCode, ToolResultContentBlock};
 use serde_json::Value;
 
+use crate::diff_review::SharedDiffReview;
+use crate::file_changes::SharedFileChangeTracker;
+use crate::file_snapshots::SharedFileSnapshots;
+
 pub(super) const DEFAULT_MAX_LINES: usize = 4096;
 pub(super) const DEFAULT_MAX_BYTES: usize = 256 * 1024;
 
+/// Rough token count for budgeting tool output (about four bytes per token).
+pub(super) fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
 #[derive(Clone, Copy)]
 pub(super) enum TruncatedBy {
     Lines,
@@ -24,44 +34,6 @@ pub(super) struct TruncateResult {
     pub truncated_by: Option<TruncatedBy>,
 }
 
-pub(super) fn truncate_head(content: &str, max_lines: usize, max_bytes: usize) -> TruncateResult {
-    let total_lines = content.lines().count().max(1);
-    let total_bytes = content.len();
-    let mut lines: Vec<&str> = content.split('\n').collect();
-    let mut truncated = false;
-    let mut truncated_by = None;
-
-    if lines.len() > max_lines {
-        lines.truncate(max_lines);
-        truncated = true;
-        truncated_by = Some(TruncatedBy::Lines);
-    }
-
-    let mut output = lines.join("\n");
-    if output.len() > max_bytes {
-        output = truncate_prefix_bytes(&output, max_bytes);
-        truncated = true;
-        truncated_by = Some(TruncatedBy::Bytes);
-    }
-
-    let output_lines = if output.is_empty() {
-        0
-    } else {
-        output.lines().count()
-    };
-    let output_bytes = output.len();
-
-    TruncateResult {
-        content: output,
-        output_lines,
-        output_bytes,
-        total_lines,
-        total_bytes,
-        truncated,
-        truncated_by,
-    }
-}
-
 pub(super) fn truncate_tail(content: &str, max_lines: usize, max_bytes: usize) -> TruncateResult {
     let total_lines = content.lines().count().max(1);
     let total_bytes = content.len();
@@ -100,18 +72,6 @@ pub(super) fn truncate_tail(content: &str, max_lines: usize, max_bytes: usize) -
     }
 }
 
-pub(super) fn truncate_prefix_bytes(content: &str, max_bytes: usize) -> String {
-    if content.len() <= max_bytes {
-        return content.to_string();
-    }
-
-    let mut end = max_bytes;
-    while end > 0 && !content.is_char_boundary(end) {
-        end -= 1;
-    }
-    content[..end].to_string()
-}
-
 pub(super) fn truncate_suffix_bytes(content: &str, max_bytes: usize) -> String {
     if content.len() <= max_bytes {
         return content.to_string();
@@ -125,31 +85,19 @@ pub(super) fn truncate_suffix_bytes(content: &str, max_bytes: usize) -> String {
 }
 
 pub(super) fn resolve_to_cwd(cwd: &Path, file_path: &str) -> PathBuf {
-    let normalized = if let Some(stripped) = file_path.strip_prefix('@') {
-        stripped
-    } else {
-        file_path
-    };
-
-    let expanded = expand_home(normalized);
-    let path = PathBuf::from(expanded);
-    if path.is_absolute() {
-        path
-    } else {
-        cwd.join(path)
-    }
+    resolve_tool_path(cwd, file_path)
 }
 
-pub(super) fn expand_home(path: &str) -> String {
-    if path == "~" {
-        return std::env::var("HOME").unwrap_or_else(|_| path.to_string());
-    }
-    if let Some(rest) = path.strip_prefix("~/") {
-        if let Ok(home) = std::env::var("HOME") {
-            return format!("{home}/{rest}");
-        }
-    }
-    path.to_string()
+/// Conflict key of tools that take a `path` argument, so calls on the same file run in order.
+pub(super) fn path_conflict_key(cwd: &Path) -> ToolConflictKeyFn {
+    let cwd = cwd.to_path_buf();
+    Arc::new(move |args: &Value| {
+        args.get("path")
+            .and_then(Value::as_str)
+            .map(|path| resolve_to_cwd(&cwd, path).to_string_lossy().into_owned())
+            .into_iter()
+            .collect()
+    })
 }
 
 pub(super) fn get_required_string(args: &Value, key: &str) -> Result<String, PiAiError> {
@@ -196,17 +144,6 @@ pub(super) fn get_optional_usize(args: &Value, key: &str) -> Result<Option<usize
     }
 }
 
-pub(super) fn get_optional_f64(args: &Value, key: &str) -> Result<Option<f64>, PiAiError> {
-    match args.get(key) {
-        None => Ok(None),
-        Some(value) if value.is_null() => Ok(None),
-        Some(value) => value
-            .as_f64()
-            .map(Some)
-            .ok_or_else(|| invalid_tool_args(format!("Missing or invalid `{key}`"))),
-    }
-}
-
 pub(super) fn invalid_tool_args(message: impl Into<String>) -> PiAiError {
     PiAiError::new(PiAiErrorCode::ToolArgumentsInvalid, message.into())
 }
@@ -215,6 +152,46 @@ pub(super) fn tool_execution_failed(message: impl Into<String>) -> PiAiError {
     PiAiError::new(PiAiErrorCode::ToolExecutionFailed, message.into())
 }
 
+pub(super) fn record_file_snapshot(
+    snapshots: Option<&SharedFileSnapshots>,
+    path: &Path,
+) -> Result<(), PiAiError> {
+    let Some(snapshots) = snapshots else {
+        return Ok(());
+    };
+    snapshots
+        .lock()
+        .map_err(|_| tool_execution_failed("file snapshot store lock poisoned"))?
+        .record_before_write(path)
+        .map_err(tool_execution_failed)
+}
+
+/// Holds the write back until the attached reviewer approves it.
+pub(super) async fn review_file_change(
+    review: Option<&SharedDiffReview>,
+    tool: &str,
+    path: &str,
+    before: Option<&str>,
+    after: &str,
+) -> Result<(), PiAiError> {
+    match review {
+        Some(review) => review
+            .review(tool, path, before, after)
+            .await
+            .map_err(tool_execution_failed),
+        None => Ok(()),
+    }
+}
+
+/// Records that the agent has seen the current content of `path`.
+pub(super) fn observe_file(file_changes: Option<&SharedFileChangeTracker>, path: &Path) {
+    if let Some(tracker) = file_changes {
+        if let Ok(mut tracker) = tracker.lock() {
+            tracker.observe(path);
+        }
+    }
+}
+
 pub(super) fn text_result(text: String, details: Value) -> AgentToolResult {
     AgentToolResult {
         content: vec![ToolResultContentBlock::Text {
@@ -285,10 +262,15 @@ pub(super) fn line_change_counts(before: &str, after: &str) -> (usize, usize) {
 }
 
 pub(super) fn format_diff_stat_line(path: &str, before: &str, after: &str) -> String {
+    let (added, removed) = line_change_counts(before, after);
+    format_diff_stat_counts(path, added, removed)
+}
+
+/// Diff-stat line of `path` from its added and removed line counts.
+pub(super) fn format_diff_stat_counts(path: &str, added: usize, removed: usize) -> String {
     const PATH_WIDTH: usize = 48;
     const BAR_WIDTH: usize = 20;
 
-    let (added, removed) = line_change_counts(before, after);
     let changed = added.saturating_add(removed);
     let bar = diff_stat_bar(added, removed, BAR_WIDTH);
     let display_path = truncate_path_for_stat(path, PATH_WIDTH);
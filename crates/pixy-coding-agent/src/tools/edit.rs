@@ -3,37 +3,65 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use pixy_agent_core::{AgentTool, AgentToolExecutor, AgentToolResult};
+use pixy_agent_core::{AgentTool, AgentToolArgs, AgentToolExecutor, AgentToolResult};
 use pixy_ai::PiAiError;
+use serde::Deserialize;
 use serde_json::{json, Value};
 
 use super::common::{
-    first_changed_line, format_diff_stat_line, get_required_string, invalid_tool_args,
-    line_change_counts, resolve_to_cwd, text_result, tool_execution_failed,
+    first_changed_line, format_diff_stat_line, invalid_tool_args, line_change_counts, observe_file,
+    path_conflict_key, record_file_snapshot, resolve_to_cwd, review_file_change, text_result,
+    tool_execution_failed,
 };
+use super::edit_match::{
+    adapt_replacement, find_fuzzy_match, format_replacement_preview, FuzzyMatch, FuzzyOutcome,
+};
+use crate::diff_review::SharedDiffReview;
+use crate::file_changes::SharedFileChangeTracker;
+use crate::file_snapshots::SharedFileSnapshots;
 
 pub fn create_edit_tool(cwd: impl AsRef<Path>) -> AgentTool {
+    create_edit_tool_with_snapshots(cwd, None, None, None)
+}
+
+pub(crate) fn create_edit_tool_with_snapshots(
+    cwd: impl AsRef<Path>,
+    snapshots: Option<SharedFileSnapshots>,
+    file_changes: Option<SharedFileChangeTracker>,
+    review: Option<SharedDiffReview>,
+) -> AgentTool {
     let cwd = cwd.as_ref().to_path_buf();
     AgentTool {
         name: "edit".to_string(),
         label: "edit".to_string(),
-        description: "Replace exactly one unique text fragment in a UTF-8 file.".to_string(),
-        parameters: json!({
-            "type": "object",
-            "properties": {
-                "path": { "type": "string", "description": "Path to edit, absolute or relative to workspace cwd." },
-                "oldText": { "type": "string", "description": "Exact original text to replace. Must be unique in file." },
-                "newText": { "type": "string", "description": "Replacement text." }
-            },
-            "required": ["path", "oldText", "newText"],
-            "additionalProperties": false
+        description: "Replace exactly one unique text fragment in a UTF-8 file. If oldText has no exact match, a unique match that differs only in whitespace or indentation (or is near-identical line by line) is applied and reported with a preview.".to_string(),
+        parameters: EditArgs::schema(),
+        conflict_key: Some(path_conflict_key(&cwd)),
+        execute: Arc::new(EditToolExecutor {
+            cwd,
+            snapshots,
+            file_changes,
+            review,
         }),
-        execute: Arc::new(EditToolExecutor { cwd }),
     }
 }
 
+#[derive(Deserialize, AgentToolArgs)]
+#[serde(rename_all = "camelCase")]
+struct EditArgs {
+    /// Path to edit, absolute or relative to workspace cwd.
+    path: String,
+    /// Exact original text to replace. Must be unique in file.
+    old_text: String,
+    /// Replacement text.
+    new_text: String,
+}
+
 struct EditToolExecutor {
     cwd: PathBuf,
+    snapshots: Option<SharedFileSnapshots>,
+    file_changes: Option<SharedFileChangeTracker>,
+    review: Option<SharedDiffReview>,
 }
 
 #[async_trait]
@@ -44,45 +72,110 @@ impl AgentToolExecutor for EditToolExecutor {
         args: Value,
     ) -> Result<AgentToolResult, PiAiError> {
         let cwd = self.cwd.clone();
-        execute_edit_tool(&cwd, args)
+        execute_edit_tool(
+            &cwd,
+            args,
+            self.snapshots.as_ref(),
+            self.file_changes.as_ref(),
+            self.review.as_ref(),
+        )
+        .await
     }
 }
 
-fn execute_edit_tool(cwd: &Path, args: Value) -> Result<AgentToolResult, PiAiError> {
-    let path = get_required_string(&args, "path")?;
-    let old_text = get_required_string(&args, "oldText")?;
-    let new_text = get_required_string(&args, "newText")?;
+async fn execute_edit_tool(
+    cwd: &Path,
+    args: Value,
+    snapshots: Option<&SharedFileSnapshots>,
+    file_changes: Option<&SharedFileChangeTracker>,
+    review: Option<&SharedDiffReview>,
+) -> Result<AgentToolResult, PiAiError> {
+    let EditArgs {
+        path,
+        old_text,
+        new_text,
+    } = EditArgs::parse(args)?;
     if old_text.is_empty() {
         return Err(invalid_tool_args("`oldText` must not be empty"));
     }
+    if path.ends_with(".ipynb") {
+        return Err(invalid_tool_args(
+            "edit does not change Jupyter notebooks; use notebook_edit to change their cells",
+        ));
+    }
 
     let absolute_path = resolve_to_cwd(cwd, &path);
     let content = fs::read_to_string(&absolute_path)
         .map_err(|error| tool_execution_failed(format!("Failed to read {path}: {error}")))?;
     let occurrences = content.matches(&old_text).count();
-    if occurrences == 0 {
-        return Err(tool_execution_failed(format!(
-            "Could not find the exact text in {path}. The old text must match exactly."
-        )));
-    }
     if occurrences > 1 {
         return Err(tool_execution_failed(format!(
             "Found {occurrences} occurrences of the text in {path}. The text must be unique."
         )));
     }
 
-    let updated = content.replacen(&old_text, &new_text, 1);
+    let mut fuzzy = None;
+    let updated = if occurrences == 1 {
+        content.replacen(&old_text, &new_text, 1)
+    } else {
+        let matched = match find_fuzzy_match(&content, &old_text) {
+            FuzzyOutcome::Unique(matched) => matched,
+            FuzzyOutcome::Ambiguous { strategy, count } => {
+                return Err(tool_execution_failed(format!(
+                    "Could not find the exact text in {path}, and {count} locations match {}. Include more surrounding lines so the text is unique.",
+                    strategy.describe()
+                )));
+            }
+            FuzzyOutcome::NotFound(closest) => {
+                let mut message = format!(
+                    "Could not find the exact text in {path}. The old text must match exactly."
+                );
+                if let Some(closest) = closest {
+                    message.push_str(&format!(
+                        "\nClosest match ({:.0}% similar) at lines {}-{}:\n{}",
+                        closest.confidence * 100.0,
+                        closest.start_line,
+                        closest.end_line,
+                        &content[closest.range.clone()]
+                    ));
+                }
+                return Err(tool_execution_failed(message));
+            }
+        };
+        let replacement = adapt_replacement(&content, &old_text, &new_text, &matched);
+        let updated = format!(
+            "{}{replacement}{}",
+            &content[..matched.range.start],
+            &content[matched.range.end..]
+        );
+        fuzzy = Some((matched, replacement));
+        updated
+    };
     if updated == content {
         return Err(tool_execution_failed(format!(
             "No changes made to {path}. The replacement produced identical content."
         )));
     }
 
+    review_file_change(review, "edit", &path, Some(&content), &updated).await?;
+    record_file_snapshot(snapshots, &absolute_path)?;
     fs::write(&absolute_path, updated.as_bytes())
         .map_err(|error| tool_execution_failed(format!("Failed to write {path}: {error}")))?;
+    observe_file(file_changes, &absolute_path);
     let (insertions, deletions) = line_change_counts(&content, &updated);
+    let mut text = format_diff_stat_line(&path, &content, &updated);
+    if let Some((matched, replacement)) = &fuzzy {
+        text.push_str(&format!(
+            "\n\nNo exact match; applied a match {} ({:.0}% confidence) at lines {}-{}:\n{}",
+            matched.strategy.describe(),
+            matched.confidence * 100.0,
+            matched.start_line,
+            matched.end_line,
+            format_replacement_preview(&content[matched.range.clone()], replacement)
+        ));
+    }
     Ok(text_result(
-        format_diff_stat_line(&path, &content, &updated),
+        text,
         json!({
             "path": path,
             "firstChangedLine": first_changed_line(&content, &updated),
@@ -90,6 +183,19 @@ fn execute_edit_tool(cwd: &Path, args: Value) -> Result<AgentToolResult, PiAiErr
             "insertions": insertions,
             "deletions": deletions,
             "changedLines": insertions + deletions,
+            "match": match_details(fuzzy.as_ref().map(|(matched, _)| matched)),
         }),
     ))
 }
+
+fn match_details(fuzzy: Option<&FuzzyMatch>) -> Value {
+    match fuzzy {
+        None => json!({ "strategy": "exact", "confidence": 1.0 }),
+        Some(matched) => json!({
+            "strategy": matched.strategy.key(),
+            "confidence": (matched.confidence * 100.0).round() / 100.0,
+            "startLine": matched.start_line,
+            "endLine": matched.end_line,
+        }),
+    }
+}
@@ -0,0 +1,362 @@
+use std::ops::Range;
+
+/// Minimum similarity for the last-resort match over whitespace-normalized lines.
+const SIMILARITY_THRESHOLD: f64 = 0.9;
+/// Closest candidates below this score are not worth showing in the failure message.
+const MIN_REPORTED_SIMILARITY: f64 = 0.5;
+/// Similarity matching is quadratic per line pair; larger `oldText` blocks only get the cheap
+/// whitespace strategies.
+const MAX_SIMILARITY_LINES: usize = 200;
+/// Edit-distance cells the similarity search may compute before giving up, which bounds its cost
+/// on large files or very long lines. Exhausting it reports a plain not-found.
+const MAX_SIMILARITY_CELLS: usize = 20_000_000;
+const PREVIEW_MAX_LINES: usize = 12;
+
+/// Fallbacks tried in order when `oldText` is not found verbatim. Each is looser than the last.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum MatchStrategy {
+    TrailingWhitespace,
+    Indentation,
+    Whitespace,
+    Similarity,
+}
+
+impl MatchStrategy {
+    pub(super) fn key(self) -> &'static str {
+        match self {
+            Self::TrailingWhitespace => "trailing_whitespace",
+            Self::Indentation => "indentation",
+            Self::Whitespace => "whitespace",
+            Self::Similarity => "similarity",
+        }
+    }
+
+    pub(super) fn describe(self) -> &'static str {
+        match self {
+            Self::TrailingWhitespace => "ignoring trailing whitespace",
+            Self::Indentation => "ignoring indentation",
+            Self::Whitespace => "ignoring whitespace differences",
+            Self::Similarity => "by line similarity",
+        }
+    }
+
+    /// Fixed confidence for the normalization strategies; similarity matches report their score.
+    fn confidence(self) -> f64 {
+        match self {
+            Self::TrailingWhitespace => 0.99,
+            Self::Indentation => 0.97,
+            Self::Whitespace => 0.95,
+            Self::Similarity => SIMILARITY_THRESHOLD,
+        }
+    }
+
+    fn normalize(self, line: &str) -> String {
+        match self {
+            Self::TrailingWhitespace => line.trim_end().to_string(),
+            Self::Indentation => line.trim().to_string(),
+            Self::Whitespace | Self::Similarity => {
+                line.split_whitespace().collect::<Vec<_>>().join(" ")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct FuzzyMatch {
+    pub strategy: MatchStrategy,
+    pub confidence: f64,
+    /// Byte range of the matched lines, excluding the final line break.
+    pub range: Range<usize>,
+    /// 1-based, inclusive.
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+pub(super) enum FuzzyOutcome {
+    Unique(FuzzyMatch),
+    Ambiguous {
+        strategy: MatchStrategy,
+        count: usize,
+    },
+    /// Nothing met the threshold; carries the closest candidate, if any was reasonably close.
+    NotFound(Option<FuzzyMatch>),
+}
+
+struct FileLine<'a> {
+    text: &'a str,
+    start: usize,
+}
+
+/// Line-based fallback search for an `oldText` that has no exact occurrence in `content`.
+pub(super) fn find_fuzzy_match(content: &str, old_text: &str) -> FuzzyOutcome {
+    let old_lines = split_lines(old_text.trim_matches('\n'));
+    if old_lines.iter().all(|line| line.trim().is_empty()) {
+        return FuzzyOutcome::NotFound(None);
+    }
+    let file_lines = file_lines(content);
+    if old_lines.len() > file_lines.len() {
+        return FuzzyOutcome::NotFound(None);
+    }
+
+    for strategy in [
+        MatchStrategy::TrailingWhitespace,
+        MatchStrategy::Indentation,
+        MatchStrategy::Whitespace,
+    ] {
+        let wanted = normalize_lines(strategy, &old_lines);
+        let normalized_file = file_lines
+            .iter()
+            .map(|line| strategy.normalize(line.text))
+            .collect::<Vec<_>>();
+        let starts = (0..=file_lines.len() - old_lines.len())
+            .filter(|start| normalized_file[*start..*start + wanted.len()] == wanted[..])
+            .collect::<Vec<_>>();
+        match starts.as_slice() {
+            [] => continue,
+            [start] => {
+                return FuzzyOutcome::Unique(build_match(
+                    &file_lines,
+                    *start,
+                    old_lines.len(),
+                    strategy,
+                    strategy.confidence(),
+                ))
+            }
+            _ => {
+                return FuzzyOutcome::Ambiguous {
+                    strategy,
+                    count: starts.len(),
+                }
+            }
+        }
+    }
+
+    if old_lines.len() > MAX_SIMILARITY_LINES {
+        return FuzzyOutcome::NotFound(None);
+    }
+    similarity_match(&file_lines, &old_lines)
+}
+
+fn similarity_match(file_lines: &[FileLine<'_>], old_lines: &[&str]) -> FuzzyOutcome {
+    let strategy = MatchStrategy::Similarity;
+    let wanted = normalize_lines(strategy, old_lines)
+        .into_iter()
+        .map(|line| line.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let normalized_file = file_lines
+        .iter()
+        .map(|line| strategy.normalize(line.text).chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let mut accepted = Vec::new();
+    let mut closest: Option<(usize, f64)> = None;
+    let mut budget = MAX_SIMILARITY_CELLS;
+    for start in 0..=file_lines.len() - old_lines.len() {
+        let window = &normalized_file[start..start + wanted.len()];
+        let score = window_similarity(&wanted, window, MIN_REPORTED_SIMILARITY, &mut budget);
+        if budget == 0 {
+            return FuzzyOutcome::NotFound(None);
+        }
+        let Some(score) = score else {
+            continue;
+        };
+        if score >= SIMILARITY_THRESHOLD {
+            accepted.push((start, score));
+        }
+        if closest.is_none_or(|(_, best)| score > best) {
+            closest = Some((start, score));
+        }
+    }
+
+    match accepted.as_slice() {
+        [(start, score)] => FuzzyOutcome::Unique(build_match(
+            file_lines,
+            *start,
+            old_lines.len(),
+            strategy,
+            *score,
+        )),
+        [] => FuzzyOutcome::NotFound(closest.map(|(start, score)| {
+            build_match(file_lines, start, old_lines.len(), strategy, score)
+        })),
+        _ => FuzzyOutcome::Ambiguous {
+            strategy,
+            count: accepted.len(),
+        },
+    }
+}
+
+/// Character-level similarity of two line blocks (1 - edit distance / length), or `None` once
+/// the score is known to fall below `floor`. Each line comparison is charged against `budget`,
+/// which is set to zero when it cannot cover the next one.
+fn window_similarity(
+    wanted: &[Vec<char>],
+    window: &[Vec<char>],
+    floor: f64,
+    budget: &mut usize,
+) -> Option<f64> {
+    let total = wanted
+        .iter()
+        .zip(window)
+        .map(|(a, b)| a.len().max(b.len()))
+        .sum::<usize>();
+    if total == 0 {
+        return Some(1.0);
+    }
+    let allowed = ((1.0 - floor) * total as f64) as usize;
+    let lower_bound = wanted
+        .iter()
+        .zip(window)
+        .map(|(a, b)| a.len().abs_diff(b.len()))
+        .sum::<usize>();
+    if lower_bound > allowed {
+        return None;
+    }
+
+    let mut distance = 0;
+    for (a, b) in wanted.iter().zip(window) {
+        if a != b {
+            let cells = a.len().saturating_mul(b.len());
+            if cells > *budget {
+                *budget = 0;
+                return None;
+            }
+            *budget -= cells;
+        }
+        distance += levenshtein(a, b);
+        if distance > allowed {
+            return None;
+        }
+    }
+    Some(1.0 - distance as f64 / total as f64)
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    if a == b {
+        return 0;
+    }
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+    for (i, left) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, right) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(left != right);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Replacement text for a fuzzy match: `newText` loses the blank lines trimmed from `oldText`
+/// and is re-indented by the same offset the matched lines have relative to `oldText`.
+pub(super) fn adapt_replacement(
+    content: &str,
+    old_text: &str,
+    new_text: &str,
+    matched: &FuzzyMatch,
+) -> String {
+    let leading = old_text.len() - old_text.trim_start_matches('\n').len();
+    let trailing = old_text.len() - old_text.trim_end_matches('\n').len();
+    let mut replacement = new_text;
+    for _ in 0..leading {
+        replacement = replacement.strip_prefix('\n').unwrap_or(replacement);
+    }
+    for _ in 0..trailing {
+        replacement = replacement.strip_suffix('\n').unwrap_or(replacement);
+    }
+    if matched.strategy == MatchStrategy::TrailingWhitespace {
+        return replacement.to_string();
+    }
+
+    let old_lines = split_lines(old_text.trim_matches('\n'));
+    let matched_lines = split_lines(&content[matched.range.clone()]);
+    let Some((index, old_line)) = old_lines
+        .iter()
+        .enumerate()
+        .find(|(_, line)| !line.trim().is_empty())
+    else {
+        return replacement.to_string();
+    };
+    let old_indent = leading_whitespace(old_line);
+    let file_indent = matched_lines
+        .get(index)
+        .map(|line| leading_whitespace(line))
+        .unwrap_or(old_indent);
+    if old_indent == file_indent {
+        return replacement.to_string();
+    }
+
+    replacement
+        .split('\n')
+        .map(|line| match line.strip_prefix(old_indent) {
+            Some(rest) if !line.trim().is_empty() => format!("{file_indent}{rest}"),
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `-`/`+` preview of the lines a fuzzy edit replaced, capped per side.
+pub(super) fn format_replacement_preview(before: &str, after: &str) -> String {
+    let mut lines = Vec::new();
+    for (marker, text) in [('-', before), ('+', after)] {
+        let block = split_lines(text);
+        for line in block.iter().take(PREVIEW_MAX_LINES) {
+            lines.push(format!("{marker} {line}"));
+        }
+        if block.len() > PREVIEW_MAX_LINES {
+            lines.push(format!(
+                "{marker} … ({} more lines)",
+                block.len() - PREVIEW_MAX_LINES
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+fn build_match(
+    file_lines: &[FileLine<'_>],
+    start: usize,
+    len: usize,
+    strategy: MatchStrategy,
+    confidence: f64,
+) -> FuzzyMatch {
+    let last = &file_lines[start + len - 1];
+    FuzzyMatch {
+        strategy,
+        confidence,
+        range: file_lines[start].start..last.start + last.text.len(),
+        start_line: start + 1,
+        end_line: start + len,
+    }
+}
+
+fn file_lines(content: &str) -> Vec<FileLine<'_>> {
+    let mut start = 0;
+    content
+        .split('\n')
+        .map(|line| {
+            let entry = FileLine {
+                text: line.strip_suffix('\r').unwrap_or(line),
+                start,
+            };
+            start += line.len() + 1;
+            entry
+        })
+        .collect()
+}
+
+fn split_lines(text: &str) -> Vec<&str> {
+    text.split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect()
+}
+
+fn normalize_lines(strategy: MatchStrategy, lines: &[&str]) -> Vec<String> {
+    lines.iter().map(|line| strategy.normalize(line)).collect()
+}
+
+fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
@@ -0,0 +1,851 @@
+//! Structured git tools: `git_status`, `git_diff`, `git_log`, `git_commit` and `git_branch`.
+//!
+//! Each runs the git CLI with machine-readable output options and parses it, so results carry
+//! both a compact text for the model and structured details (file states, diff hunks, commits)
+//! for front ends.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use pixy_agent_core::{AgentTool, AgentToolArgs, AgentToolExecutor, AgentToolResult};
+use pixy_ai::PiAiError;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::process::Command;
+
+use super::common::{
+    format_diff_stat_counts, invalid_tool_args, text_result, tool_execution_failed,
+    DEFAULT_MAX_BYTES,
+};
+
+/// Calls that change the repository run one at a time.
+const GIT_CONFLICT_KEY: &str = "git";
+const DEFAULT_LOG_COUNT: usize = 20;
+const DEFAULT_DIFF_CONTEXT: usize = 3;
+const FIELD_SEPARATOR: char = '\u{1f}';
+const RECORD_SEPARATOR: char = '\u{1e}';
+
+/// The git tools, in the order they are offered to the model.
+pub fn create_git_tools(cwd: impl AsRef<Path>) -> Vec<AgentTool> {
+    let cwd = cwd.as_ref().to_path_buf();
+    vec![
+        git_tool(
+            "git_status",
+            "Show the current branch, its upstream and ahead/behind counts, and staged, unstaged, untracked and conflicted files.",
+            GitStatusArgs::schema(),
+            None,
+            GitToolExecutor { cwd: cwd.clone(), command: GitCommand::Status },
+        ),
+        git_tool(
+            "git_diff",
+            "Show a unified diff of unstaged changes, of staged changes (staged=true), or of the working tree against a commit (base). Limit it to some paths with `paths`. Details list every file with its hunks and lines.",
+            GitDiffArgs::schema(),
+            None,
+            GitToolExecutor { cwd: cwd.clone(), command: GitCommand::Diff },
+        ),
+        git_tool(
+            "git_log",
+            "List recent commits (hash, date, author, subject), optionally for a revision range such as `main..HEAD` or for one path.",
+            GitLogArgs::schema(),
+            None,
+            GitToolExecutor { cwd: cwd.clone(), command: GitCommand::Log },
+        ),
+        git_tool(
+            "git_commit",
+            "Commit the staged changes with a message. Stage `paths` first, or every change (including untracked files) with all=true. Reports the new commit and the files it changed.",
+            GitCommitArgs::schema(),
+            Some(GIT_CONFLICT_KEY),
+            GitToolExecutor { cwd: cwd.clone(), command: GitCommand::Commit },
+        ),
+        git_tool(
+            "git_branch",
+            "List local branches with their upstreams, create a branch and switch to it, switch to an existing branch, or delete a merged branch (force=true deletes unmerged ones).",
+            GitBranchArgs::schema(),
+            Some(GIT_CONFLICT_KEY),
+            GitToolExecutor { cwd, command: GitCommand::Branch },
+        ),
+    ]
+}
+
+/// Whether `cwd` is inside a git work tree; reads the file system only.
+pub(crate) fn in_git_repository(cwd: &Path) -> bool {
+    cwd.ancestors().any(|dir| dir.join(".git").exists())
+}
+
+fn git_tool(
+    name: &str,
+    description: &str,
+    parameters: Value,
+    conflict_key: Option<&str>,
+    executor: GitToolExecutor,
+) -> AgentTool {
+    AgentTool {
+        name: name.to_string(),
+        label: name.to_string(),
+        description: description.to_string(),
+        parameters,
+        conflict_key: conflict_key.map(|key| {
+            let key = key.to_string();
+            Arc::new(move |_: &Value| vec![key.clone()]) as _
+        }),
+        execute: Arc::new(executor),
+    }
+}
+
+#[derive(Deserialize, AgentToolArgs)]
+struct GitStatusArgs {}
+
+#[derive(Deserialize, AgentToolArgs)]
+struct GitDiffArgs {
+    /// Diff the index against HEAD instead of the working tree against the index. Defaults to false.
+    staged: Option<bool>,
+    /// Commit, branch or tag to compare the working tree (or the index, when staged) against.
+    base: Option<String>,
+    /// Only diff these files or directories, relative to the workspace.
+    #[serde(default)]
+    paths: Vec<String>,
+    /// Lines of context around each change. Defaults to 3.
+    #[tool(maximum = 50)]
+    context: Option<usize>,
+}
+
+#[derive(Deserialize, AgentToolArgs)]
+#[serde(rename_all = "camelCase")]
+struct GitLogArgs {
+    /// Revision or range to list, such as `main..HEAD`. Defaults to HEAD.
+    revision: Option<String>,
+    /// Only list commits that touch this file or directory.
+    path: Option<String>,
+    /// Maximum number of commits to list. Defaults to 20.
+    #[tool(minimum = 1, maximum = 200)]
+    max_count: Option<usize>,
+}
+
+#[derive(Deserialize, AgentToolArgs)]
+struct GitCommitArgs {
+    /// Commit message; the first line is the subject.
+    message: String,
+    /// Files or directories to stage before committing.
+    #[serde(default)]
+    paths: Vec<String>,
+    /// Stage every change, including untracked files, before committing. Defaults to false.
+    all: Option<bool>,
+}
+
+#[derive(Deserialize, AgentToolArgs)]
+#[serde(rename_all = "camelCase")]
+struct GitBranchArgs {
+    /// What to do. Defaults to `list`.
+    #[tool(values("list", "create", "switch", "delete"))]
+    action: Option<String>,
+    /// Branch to create, switch to or delete.
+    name: Option<String>,
+    /// Commit the created branch starts from. Defaults to HEAD.
+    start_point: Option<String>,
+    /// With `delete`, also delete a branch that is not merged. Defaults to false.
+    force: Option<bool>,
+}
+
+#[derive(Clone, Copy)]
+enum GitCommand {
+    Status,
+    Diff,
+    Log,
+    Commit,
+    Branch,
+}
+
+struct GitToolExecutor {
+    cwd: PathBuf,
+    command: GitCommand,
+}
+
+#[async_trait]
+impl AgentToolExecutor for GitToolExecutor {
+    async fn execute(
+        &self,
+        _tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        match self.command {
+            GitCommand::Status => {
+                GitStatusArgs::parse(args)?;
+                git_status(&self.cwd).await
+            }
+            GitCommand::Diff => git_diff(&self.cwd, GitDiffArgs::parse(args)?).await,
+            GitCommand::Log => git_log(&self.cwd, GitLogArgs::parse(args)?).await,
+            GitCommand::Commit => git_commit(&self.cwd, GitCommitArgs::parse(args)?).await,
+            GitCommand::Branch => git_branch(&self.cwd, GitBranchArgs::parse(args)?).await,
+        }
+    }
+}
+
+/// A branch, commit or range given by the model, refused when git would read it as an option
+/// (`--output=...` would let a read-only diff write files).
+fn ref_argument<'a>(key: &str, value: &'a str) -> Result<&'a str, PiAiError> {
+    if value.starts_with('-') {
+        return Err(invalid_tool_args(format!(
+            "`{key}` must name a branch, commit or range, not an option: {value}"
+        )));
+    }
+    Ok(value)
+}
+
+/// Stdout of `git args`; a failure carries git's own error output.
+async fn run_git(cwd: &Path, args: &[&str]) -> Result<String, PiAiError> {
+    let output = Command::new("git")
+        .args(["-c", "core.quotepath=false", "-c", "color.ui=false"])
+        .args(args)
+        .current_dir(cwd)
+        .env("GIT_PAGER", "cat")
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|error| tool_execution_failed(format!("Failed to run git: {error}")))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let message = if stderr.trim().is_empty() {
+            stdout.trim()
+        } else {
+            stderr.trim()
+        };
+        return Err(
+            tool_execution_failed(format!("`git {}` failed: {message}", args.join(" ")))
+                .with_details(json!({
+                    "args": args,
+                    "exitCode": output.status.code(),
+                })),
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatusEntry {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    orig_path: Option<String>,
+    status: &'static str,
+}
+
+async fn git_status(cwd: &Path) -> Result<AgentToolResult, PiAiError> {
+    let output = run_git(cwd, &["status", "--porcelain=v2", "--branch", "-z"]).await?;
+    let mut branch = None;
+    let mut head = None;
+    let mut upstream = None;
+    let (mut ahead, mut behind) = (0i64, 0i64);
+    let mut staged = Vec::new();
+    let mut unstaged = Vec::new();
+    let mut untracked = Vec::new();
+    let mut conflicted = Vec::new();
+
+    let mut records = output.split('\0').filter(|record| !record.is_empty());
+    while let Some(record) = records.next() {
+        if let Some(header) = record.strip_prefix("# ") {
+            let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+            match key {
+                "branch.oid" if value != "(initial)" => head = Some(value.to_string()),
+                "branch.head" if value != "(detached)" => branch = Some(value.to_string()),
+                "branch.upstream" => upstream = Some(value.to_string()),
+                "branch.ab" => {
+                    for count in value.split_whitespace() {
+                        if let Some(count) = count.strip_prefix('+') {
+                            ahead = count.parse().unwrap_or_default();
+                        } else if let Some(count) = count.strip_prefix('-') {
+                            behind = count.parse().unwrap_or_default();
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        let kind = record.chars().next().unwrap_or_default();
+        match kind {
+            '1' | '2' => {
+                let fields: Vec<&str> = record
+                    .splitn(if kind == '1' { 9 } else { 10 }, ' ')
+                    .collect();
+                let (Some(xy), Some(path)) = (fields.get(1), fields.last()) else {
+                    continue;
+                };
+                let orig_path = if kind == '2' {
+                    records.next().map(str::to_string)
+                } else {
+                    None
+                };
+                let mut codes = xy.chars();
+                let (index, worktree) = (codes.next().unwrap_or('.'), codes.next().unwrap_or('.'));
+                if index != '.' {
+                    staged.push(StatusEntry {
+                        path: path.to_string(),
+                        orig_path: orig_path.clone(),
+                        status: change_name(index),
+                    });
+                }
+                if worktree != '.' {
+                    unstaged.push(StatusEntry {
+                        path: path.to_string(),
+                        orig_path: None,
+                        status: change_name(worktree),
+                    });
+                }
+            }
+            'u' => {
+                let fields: Vec<&str> = record.splitn(11, ' ').collect();
+                if let (Some(xy), Some(path)) = (fields.get(1), fields.last()) {
+                    conflicted.push(StatusEntry {
+                        path: path.to_string(),
+                        orig_path: None,
+                        status: conflict_name(xy),
+                    });
+                }
+            }
+            '?' => untracked.push(record[2..].to_string()),
+            _ => {}
+        }
+    }
+
+    let mut text = match (&branch, &head) {
+        (Some(branch), Some(_)) => format!("On branch {branch}"),
+        (Some(branch), None) => format!("On branch {branch} (no commits yet)"),
+        (None, Some(head)) => format!("HEAD detached at {}", short_hash(head)),
+        (None, None) => "HEAD detached".to_string(),
+    };
+    if let Some(upstream) = &upstream {
+        text.push_str(&format!(
+            " (upstream {upstream}, ahead {ahead}, behind {behind})"
+        ));
+    }
+    let sections = [
+        ("Staged", &staged),
+        ("Unstaged", &unstaged),
+        ("Conflicted", &conflicted),
+    ];
+    for (title, entries) in sections {
+        if entries.is_empty() {
+            continue;
+        }
+        text.push_str(&format!("\n{title}:"));
+        for entry in entries {
+            match &entry.orig_path {
+                Some(orig_path) => text.push_str(&format!(
+                    "\n  {}: {orig_path} -> {}",
+                    entry.status, entry.path
+                )),
+                None => text.push_str(&format!("\n  {}: {}", entry.status, entry.path)),
+            }
+        }
+    }
+    if !untracked.is_empty() {
+        text.push_str("\nUntracked:");
+        for path in &untracked {
+            text.push_str(&format!("\n  {path}"));
+        }
+    }
+    if staged.is_empty() && unstaged.is_empty() && conflicted.is_empty() && untracked.is_empty() {
+        text.push_str("\nWorking tree clean.");
+    }
+
+    Ok(text_result(
+        text,
+        json!({
+            "branch": branch,
+            "head": head,
+            "upstream": upstream,
+            "ahead": ahead,
+            "behind": behind,
+            "staged": staged,
+            "unstaged": unstaged,
+            "untracked": untracked,
+            "conflicted": conflicted,
+        }),
+    ))
+}
+
+fn change_name(code: char) -> &'static str {
+    match code {
+        'M' => "modified",
+        'A' => "added",
+        'D' => "deleted",
+        'R' => "renamed",
+        'C' => "copied",
+        'T' => "type changed",
+        _ => "changed",
+    }
+}
+
+fn conflict_name(xy: &str) -> &'static str {
+    match xy {
+        "DD" => "both deleted",
+        "AU" => "added by us",
+        "UD" => "deleted by them",
+        "UA" => "added by them",
+        "DU" => "deleted by us",
+        "AA" => "both added",
+        _ => "both modified",
+    }
+}
+
+async fn git_diff(cwd: &Path, args: GitDiffArgs) -> Result<AgentToolResult, PiAiError> {
+    let context = format!("-U{}", args.context.unwrap_or(DEFAULT_DIFF_CONTEXT));
+    let staged = args.staged.unwrap_or(false);
+    let mut git_args = vec!["diff", "--no-ext-diff", "--find-renames", context.as_str()];
+    if staged {
+        git_args.push("--cached");
+    }
+    if let Some(base) = args.base.as_deref() {
+        git_args.push(ref_argument("base", base)?);
+    }
+    git_args.push("--");
+    git_args.extend(args.paths.iter().map(String::as_str));
+    let output = run_git(cwd, &git_args).await?;
+
+    let (diff, truncated) = truncate_at_line(&output, DEFAULT_MAX_BYTES);
+    let files = parse_unified_diff(diff);
+    let insertions: usize = files.iter().map(|file| file.insertions).sum();
+    let deletions: usize = files.iter().map(|file| file.deletions).sum();
+    let mut text = if files.is_empty() {
+        if staged {
+            "No staged changes.".to_string()
+        } else {
+            "No changes.".to_string()
+        }
+    } else {
+        let mut summary: Vec<String> = files
+            .iter()
+            .map(|file| format_diff_stat_counts(&file.path, file.insertions, file.deletions))
+            .collect();
+        summary.push(format!(
+            "{} file{} changed, {insertions} insertion{}(+), {deletions} deletion{}(-)",
+            files.len(),
+            plural(files.len()),
+            plural(insertions),
+            plural(deletions)
+        ));
+        format!("{}\n\n{}", summary.join("\n"), diff.trim_end())
+    };
+    if truncated {
+        text.push_str(&format!(
+            "\n\n[Diff truncated at {} KB; narrow it with paths.]",
+            DEFAULT_MAX_BYTES / 1024
+        ));
+    }
+    Ok(text_result(
+        text,
+        json!({
+            "staged": staged,
+            "base": args.base,
+            "files": files,
+            "insertions": insertions,
+            "deletions": deletions,
+            "truncated": truncated,
+        }),
+    ))
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// The longest prefix of `text` of whole lines within `max_bytes`, and whether it was cut.
+fn truncate_at_line(text: &str, max_bytes: usize) -> (&str, bool) {
+    if text.len() <= max_bytes {
+        return (text, false);
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let end = text[..end].rfind('\n').map_or(0, |newline| newline + 1);
+    (&text[..end], true)
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiffFile {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old_path: Option<String>,
+    status: &'static str,
+    binary: bool,
+    insertions: usize,
+    deletions: usize,
+    hunks: Vec<DiffHunk>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiffHunk {
+    old_start: usize,
+    old_lines: usize,
+    new_start: usize,
+    new_lines: usize,
+    /// Text after the closing `@@`, usually the enclosing function.
+    header: String,
+    lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiffLine {
+    /// `context`, `add` or `delete`.
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old_line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new_line: Option<usize>,
+    text: String,
+}
+
+/// Files of `git diff` output with their hunks and numbered lines.
+fn parse_unified_diff(diff: &str) -> Vec<DiffFile> {
+    let mut files: Vec<DiffFile> = Vec::new();
+    let (mut old_line, mut new_line) = (0, 0);
+    for line in diff.lines() {
+        if let Some(header) = line.strip_prefix("diff --git ") {
+            let (old_path, new_path) = split_diff_header(header);
+            files.push(DiffFile {
+                path: new_path,
+                old_path: Some(old_path),
+                status: "modified",
+                ..DiffFile::default()
+            });
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+        if let Some(hunk) = file.hunks.last_mut() {
+            let entry = match line.chars().next() {
+                Some('+') if !line.starts_with("+++ ") || hunk_open(hunk) => {
+                    file.insertions += 1;
+                    new_line += 1;
+                    Some(("add", None, Some(new_line)))
+                }
+                Some('-') if !line.starts_with("--- ") || hunk_open(hunk) => {
+                    file.deletions += 1;
+                    old_line += 1;
+                    Some(("delete", Some(old_line), None))
+                }
+                Some(' ') => {
+                    old_line += 1;
+                    new_line += 1;
+                    Some(("context", Some(old_line), Some(new_line)))
+                }
+                Some('\\') => continue,
+                _ => None,
+            };
+            if let Some((kind, old, new)) = entry {
+                hunk.lines.push(DiffLine {
+                    kind,
+                    old_line: old,
+                    new_line: new,
+                    text: line[1..].to_string(),
+                });
+                continue;
+            }
+        }
+        if let Some(range) = line.strip_prefix("@@ ") {
+            let (ranges, header) = range.split_once(" @@").unwrap_or((range, ""));
+            let mut hunk = DiffHunk {
+                header: header.trim().to_string(),
+                ..DiffHunk::default()
+            };
+            for range in ranges.split_whitespace() {
+                let (start, count) = parse_range(&range[1..]);
+                if range.starts_with('-') {
+                    (hunk.old_start, hunk.old_lines) = (start, count);
+                } else {
+                    (hunk.new_start, hunk.new_lines) = (start, count);
+                }
+            }
+            old_line = hunk.old_start.saturating_sub(1);
+            new_line = hunk.new_start.saturating_sub(1);
+            file.hunks.push(hunk);
+        } else if line.starts_with("new file mode") {
+            file.status = "added";
+        } else if line.starts_with("deleted file mode") {
+            file.status = "deleted";
+        } else if let Some(path) = line.strip_prefix("rename from ") {
+            file.status = "renamed";
+            file.old_path = Some(path.to_string());
+        } else if let Some(path) = line.strip_prefix("rename to ") {
+            file.path = path.to_string();
+        } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            file.binary = true;
+        } else if let Some(path) = line.strip_prefix("--- a/") {
+            file.old_path = Some(path.to_string());
+        } else if let Some(path) = line.strip_prefix("+++ b/") {
+            file.path = path.to_string();
+        }
+    }
+    for file in &mut files {
+        if file.status != "renamed" {
+            file.old_path = None;
+        }
+    }
+    files
+}
+
+/// Whether `hunk` still expects lines, so `+++`/`---` lines are content rather than headers.
+fn hunk_open(hunk: &DiffHunk) -> bool {
+    let (old, new) = hunk
+        .lines
+        .iter()
+        .fold((0, 0), |(old, new), line| match line.kind {
+            "add" => (old, new + 1),
+            "delete" => (old + 1, new),
+            _ => (old + 1, new + 1),
+        });
+    old < hunk.old_lines || new < hunk.new_lines
+}
+
+/// `start,count` of a hunk range; the count defaults to 1.
+fn parse_range(range: &str) -> (usize, usize) {
+    let (start, count) = range.split_once(',').unwrap_or((range, "1"));
+    (
+        start.parse().unwrap_or_default(),
+        count.parse().unwrap_or_default(),
+    )
+}
+
+/// Old and new paths of a `diff --git a/<old> b/<new>` header.
+fn split_diff_header(header: &str) -> (String, String) {
+    let header = header.strip_prefix("a/").unwrap_or(header);
+    match header.split_once(" b/") {
+        Some((old, new)) => (old.to_string(), new.to_string()),
+        None => (header.to_string(), header.to_string()),
+    }
+}
+
+async fn git_log(cwd: &Path, args: GitLogArgs) -> Result<AgentToolResult, PiAiError> {
+    let max_count = format!(
+        "--max-count={}",
+        args.max_count.unwrap_or(DEFAULT_LOG_COUNT)
+    );
+    let mut git_args = vec![
+        "log",
+        max_count.as_str(),
+        "--format=%H%x1f%h%x1f%an%x1f%ae%x1f%aI%x1f%s%x1e",
+    ];
+    if let Some(revision) = args.revision.as_deref() {
+        git_args.push(ref_argument("revision", revision)?);
+    }
+    if let Some(path) = args.path.as_deref() {
+        git_args.extend(["--", path]);
+    }
+    let output = run_git(cwd, &git_args).await?;
+
+    let commits: Vec<Value> = output
+        .split(RECORD_SEPARATOR)
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.trim_start().split(FIELD_SEPARATOR).collect();
+            let [hash, short_hash, author, email, date, subject] = fields[..] else {
+                return None;
+            };
+            Some(json!({
+                "hash": hash,
+                "shortHash": short_hash,
+                "author": author,
+                "email": email,
+                "date": date,
+                "subject": subject,
+            }))
+        })
+        .collect();
+    let text = if commits.is_empty() {
+        "No commits found.".to_string()
+    } else {
+        commits
+            .iter()
+            .map(|commit| {
+                let date = commit["date"].as_str().unwrap_or_default();
+                format!(
+                    "{} {} {}: {}",
+                    commit["shortHash"].as_str().unwrap_or_default(),
+                    date.get(..10).unwrap_or(date),
+                    commit["author"].as_str().unwrap_or_default(),
+                    commit["subject"].as_str().unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    Ok(text_result(text, json!({ "commits": commits })))
+}
+
+async fn git_commit(cwd: &Path, args: GitCommitArgs) -> Result<AgentToolResult, PiAiError> {
+    if args.message.trim().is_empty() {
+        return Err(invalid_tool_args("`message` must not be empty"));
+    }
+    if args.all.unwrap_or(false) {
+        run_git(cwd, &["add", "--all"]).await?;
+    } else if !args.paths.is_empty() {
+        let mut add = vec!["add", "--all", "--"];
+        add.extend(args.paths.iter().map(String::as_str));
+        run_git(cwd, &add).await?;
+    }
+    run_git(cwd, &["commit", "--quiet", "--message", &args.message]).await?;
+
+    let hash = run_git(cwd, &["rev-parse", "HEAD"])
+        .await?
+        .trim()
+        .to_string();
+    let branch = run_git(cwd, &["branch", "--show-current"])
+        .await?
+        .trim()
+        .to_string();
+    let numstat = run_git(cwd, &["show", "--numstat", "--format=", "HEAD"]).await?;
+    let files: Vec<Value> = numstat
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let insertions = fields.next()?.parse::<usize>().ok();
+            let deletions = fields.next()?.parse::<usize>().ok();
+            let path = fields.next()?;
+            Some(json!({
+                "path": path,
+                "insertions": insertions,
+                "deletions": deletions,
+            }))
+        })
+        .collect();
+
+    let subject = args.message.lines().next().unwrap_or_default().trim();
+    let short = short_hash(&hash);
+    let mut lines = vec![if branch.is_empty() {
+        format!("[detached {short}] {subject}")
+    } else {
+        format!("[{branch} {short}] {subject}")
+    }];
+    for file in &files {
+        let path = file["path"].as_str().unwrap_or_default();
+        lines.push(
+            match (file["insertions"].as_u64(), file["deletions"].as_u64()) {
+                (Some(added), Some(removed)) => {
+                    format_diff_stat_counts(path, added as usize, removed as usize)
+                }
+                _ => format!("{path} | binary"),
+            },
+        );
+    }
+    Ok(text_result(
+        lines.join("\n"),
+        json!({
+            "hash": hash,
+            "shortHash": short,
+            "branch": (!branch.is_empty()).then_some(branch),
+            "subject": subject,
+            "files": files,
+        }),
+    ))
+}
+
+async fn git_branch(cwd: &Path, args: GitBranchArgs) -> Result<AgentToolResult, PiAiError> {
+    let action = args.action.as_deref().unwrap_or("list");
+    if action == "list" {
+        return list_branches(cwd).await;
+    }
+    let Some(name) = args.name.as_deref().filter(|name| !name.trim().is_empty()) else {
+        return Err(invalid_tool_args(format!(
+            "`name` is required to {action} a branch"
+        )));
+    };
+    let name = ref_argument("name", name)?;
+    let text = match action {
+        "create" => {
+            let mut git_args = vec!["switch", "--quiet", "--create", name];
+            if let Some(start_point) = args.start_point.as_deref() {
+                git_args.push(ref_argument("startPoint", start_point)?);
+            }
+            run_git(cwd, &git_args).await?;
+            format!("Created branch {name} and switched to it.")
+        }
+        "switch" => {
+            run_git(cwd, &["switch", "--quiet", name]).await?;
+            format!("Switched to branch {name}.")
+        }
+        _ => {
+            let flag = if args.force.unwrap_or(false) {
+                "-D"
+            } else {
+                "-d"
+            };
+            run_git(cwd, &["branch", flag, name]).await?;
+            format!("Deleted branch {name}.")
+        }
+    };
+    Ok(text_result(
+        text,
+        json!({
+            "action": action,
+            "name": name,
+        }),
+    ))
+}
+
+async fn list_branches(cwd: &Path) -> Result<AgentToolResult, PiAiError> {
+    let output = run_git(
+        cwd,
+        &[
+            "for-each-ref",
+            "--format=%(HEAD)%1f%(refname:short)%1f%(upstream:short)%1f%(upstream:track,nobracket)%1f%(objectname:short)%1f%(contents:subject)",
+            "refs/heads",
+        ],
+    )
+    .await?;
+    let mut lines = Vec::new();
+    let mut branches = Vec::new();
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split(FIELD_SEPARATOR).collect();
+        let [head, name, upstream, track, commit, subject] = fields[..] else {
+            continue;
+        };
+        let current = head == "*";
+        let mut entry = format!("{} {name} {commit}", if current { "*" } else { " " });
+        if !upstream.is_empty() {
+            entry.push_str(&format!(" [{upstream}"));
+            if !track.is_empty() {
+                entry.push_str(&format!(": {track}"));
+            }
+            entry.push(']');
+        }
+        entry.push_str(&format!(" {subject}"));
+        lines.push(entry);
+        branches.push(json!({
+            "name": name,
+            "current": current,
+            "upstream": (!upstream.is_empty()).then_some(upstream),
+            "track": (!track.is_empty()).then_some(track),
+            "commit": commit,
+            "subject": subject,
+        }));
+    }
+    let text = if lines.is_empty() {
+        "No branches yet.".to_string()
+    } else {
+        lines.join("\n")
+    };
+    Ok(text_result(
+        text,
+        json!({
+            "action": "list",
+            "branches": branches,
+        }),
+    ))
+}
+
+fn short_hash(hash: &str) -> &str {
+    hash.get(..7).unwrap_or(hash)
+}
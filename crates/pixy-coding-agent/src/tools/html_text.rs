@@ -0,0 +1,572 @@
+//! Readability-style HTML to text conversion for `web_fetch`.
+//!
+//! There is no DOM: the page is split into tags and text, the main content is taken from the
+//! largest `<article>`, else `<main>`, else `<body>`, and navigation chrome, scripts and forms are
+//! dropped. What remains is rendered as light markdown (headings, lists, links, code blocks).
+
+use reqwest::Url;
+
+/// Elements whose whole subtree is dropped.
+const SKIPPED_ELEMENTS: &[&str] = &[
+    "aside", "button", "canvas", "dialog", "footer", "form", "iframe", "nav", "noscript", "object",
+    "script", "select", "style", "svg", "template",
+];
+/// Elements whose content is raw text rather than markup.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "textarea", "title"];
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "blockquote",
+    "body",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "hr",
+    "main",
+    "ol",
+    "p",
+    "section",
+    "summary",
+    "table",
+    "tr",
+    "ul",
+];
+
+pub(super) struct ReadablePage {
+    pub title: Option<String>,
+    pub text: String,
+}
+
+#[derive(Debug)]
+enum Token<'a> {
+    Text(&'a str),
+    Open {
+        name: String,
+        attributes: &'a str,
+        self_closing: bool,
+    },
+    Close(String),
+}
+
+/// Extracts the readable text of `html`; relative links are resolved against `base`.
+pub(super) fn extract_readable_text(html: &str, base: Option<&Url>) -> ReadablePage {
+    let tokens = tokenize(html);
+    let title = page_title(&tokens);
+    let (range, root) = content_range(&tokens);
+    let mut renderer = Renderer {
+        base,
+        // Page headers outside an article or main element are site chrome.
+        skip_header: root == "body",
+        ..Renderer::default()
+    };
+    for token in &tokens[range] {
+        renderer.token(token);
+    }
+    ReadablePage {
+        title,
+        text: renderer.finish(),
+    }
+}
+
+/// Text of an HTML fragment, such as a search snippet, with tags removed.
+pub(super) fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    for token in tokenize(html) {
+        if let Token::Text(value) = token {
+            text.push_str(&decode_entities(value));
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let lower = html.to_ascii_lowercase();
+    let bytes = html.as_bytes();
+    let mut tokens = Vec::new();
+    let mut position = 0;
+    let mut text_start = 0;
+    while let Some(offset) = html[position..].find('<') {
+        let start = position + offset;
+        let rest = &html[start..];
+        let next = bytes.get(start + 1).copied().unwrap_or_default();
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map(|end| start + end + 3)
+        } else if next == b'!' || next == b'?' {
+            rest.find('>').map(|end| start + end + 1)
+        } else if next == b'/' || next.is_ascii_alphabetic() {
+            tag_end(html, start)
+        } else {
+            None
+        };
+        let Some(end) = end else {
+            position = start + 1;
+            continue;
+        };
+        if text_start < start {
+            tokens.push(Token::Text(&html[text_start..start]));
+        }
+        position = end;
+        text_start = end;
+
+        if next == b'/' {
+            tokens.push(Token::Close(tag_name(&lower[start + 2..end])));
+        } else if next.is_ascii_alphabetic() {
+            let name = tag_name(&lower[start + 1..end]);
+            let inner = html[start + 1 + name.len()..end - 1].trim_end();
+            let self_closing = inner.ends_with('/') || VOID_ELEMENTS.contains(&name.as_str());
+            let attributes = inner.trim_end_matches('/');
+            let raw = RAW_TEXT_ELEMENTS.contains(&name.as_str()) && !self_closing;
+            tokens.push(Token::Open {
+                name: name.clone(),
+                attributes,
+                self_closing,
+            });
+            if raw {
+                let close = format!("</{name}");
+                let content_end = lower[end..]
+                    .find(&close)
+                    .map_or(html.len(), |offset| end + offset);
+                if end < content_end {
+                    tokens.push(Token::Text(&html[end..content_end]));
+                }
+                let close_end = html[content_end..]
+                    .find('>')
+                    .map_or(html.len(), |offset| content_end + offset + 1);
+                tokens.push(Token::Close(name));
+                position = close_end;
+                text_start = close_end;
+            }
+        }
+    }
+    if text_start < html.len() {
+        tokens.push(Token::Text(&html[text_start..]));
+    }
+    tokens
+}
+
+/// Index just past the `>` closing the tag at `start`, skipping quoted attribute values.
+fn tag_end(html: &str, start: usize) -> Option<usize> {
+    let mut quote = None;
+    for (offset, byte) in html.as_bytes()[start..].iter().enumerate() {
+        match (quote, byte) {
+            (Some(open), _) if open == *byte => quote = None,
+            (Some(_), _) => {}
+            (None, b'"' | b'\'') => quote = Some(*byte),
+            (None, b'>') => return Some(start + offset + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn tag_name(tag: &str) -> String {
+    tag.chars()
+        .take_while(|character| character.is_ascii_alphanumeric() || *character == '-')
+        .collect()
+}
+
+fn attribute(attributes: &str, wanted: &str) -> Option<String> {
+    let mut rest = attributes;
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return None;
+        }
+        let name_end = rest
+            .find(|character: char| character.is_whitespace() || character == '=')
+            .unwrap_or(rest.len());
+        let name = &rest[..name_end];
+        rest = rest[name_end..].trim_start();
+        let mut value = "";
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (parsed, remaining) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => match after[1..].find(quote) {
+                    Some(end) => (&after[1..end + 1], &after[end + 2..]),
+                    None => (&after[1..], ""),
+                },
+                _ => {
+                    let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            value = parsed;
+            rest = remaining;
+        } else if name.is_empty() {
+            // A stray `=` or quote; drop one character and keep going.
+            rest = rest.get(1..).unwrap_or_default();
+        }
+        if name.eq_ignore_ascii_case(wanted) {
+            return Some(decode_entities(value));
+        }
+    }
+}
+
+fn page_title(tokens: &[Token<'_>]) -> Option<String> {
+    let mut in_title = false;
+    for token in tokens {
+        match token {
+            Token::Open { name, .. } if name == "title" => in_title = true,
+            Token::Text(text) if in_title => {
+                let title = strip_tags(text);
+                return (!title.is_empty()).then_some(title);
+            }
+            Token::Close(name) if name == "title" => in_title = false,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Token range of the main content and the name of the element it was taken from.
+fn content_range(tokens: &[Token<'_>]) -> (std::ops::Range<usize>, &'static str) {
+    for root in ["article", "main", "body"] {
+        let best = element_ranges(tokens, root)
+            .into_iter()
+            .max_by_key(|range| text_len(&tokens[range.clone()]));
+        if let Some(range) = best {
+            return (range, root);
+        }
+    }
+    (0..tokens.len(), "body")
+}
+
+/// Ranges of the outermost `name` elements, end exclusive; an unclosed one runs to the end.
+fn element_ranges(tokens: &[Token<'_>], wanted: &str) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+    let mut depth = 0usize;
+    for (index, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Open {
+                name,
+                self_closing: false,
+                ..
+            } if name == wanted => {
+                if depth == 0 {
+                    start = Some(index);
+                }
+                depth += 1;
+            }
+            Token::Close(name) if name == wanted && depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    ranges.extend(start.take().map(|start| start..index + 1));
+                }
+            }
+            _ => {}
+        }
+    }
+    ranges.extend(start.map(|start| start..tokens.len()));
+    ranges
+}
+
+fn text_len(tokens: &[Token<'_>]) -> usize {
+    tokens
+        .iter()
+        .map(|token| match token {
+            Token::Text(text) => text.trim().len(),
+            _ => 0,
+        })
+        .sum()
+}
+
+#[derive(Default)]
+struct Renderer<'a> {
+    base: Option<&'a Url>,
+    skip_header: bool,
+    out: String,
+    pending_space: bool,
+    /// Element being skipped and how deeply it is nested in itself.
+    skipping: Option<(String, usize)>,
+    pre_depth: usize,
+    /// Item counters of the open lists; `None` for unordered ones.
+    lists: Vec<Option<usize>>,
+    /// Targets and text offsets of the open links.
+    links: Vec<(Option<String>, usize)>,
+}
+
+impl Renderer<'_> {
+    fn token(&mut self, token: &Token<'_>) {
+        if let Some((skipped, depth)) = &mut self.skipping {
+            match token {
+                Token::Open {
+                    name,
+                    self_closing: false,
+                    ..
+                } if name == skipped => *depth += 1,
+                Token::Close(name) if name == skipped => {
+                    *depth -= 1;
+                    if *depth == 0 {
+                        self.skipping = None;
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
+        match token {
+            Token::Text(text) => self.text(text),
+            Token::Open {
+                name,
+                attributes,
+                self_closing,
+            } => {
+                let skipped = SKIPPED_ELEMENTS.contains(&name.as_str())
+                    || name == "title"
+                    || (name == "header" && self.skip_header);
+                if skipped {
+                    if !self_closing {
+                        self.skipping = Some((name.clone(), 1));
+                    }
+                    return;
+                }
+                self.open(name, attributes);
+                if *self_closing {
+                    self.close(name);
+                }
+            }
+            Token::Close(name) => self.close(name),
+        }
+    }
+
+    fn open(&mut self, name: &str, attributes: &str) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.break_lines(2);
+                let level = usize::from(name.as_bytes()[1] - b'0');
+                self.out.push_str(&"#".repeat(level));
+                self.out.push(' ');
+            }
+            "li" => {
+                self.break_lines(1);
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(counter)) => {
+                        *counter += 1;
+                        format!("{counter}. ")
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.out.push_str(&indent);
+                self.out.push_str(&marker);
+            }
+            "ul" | "ol" => {
+                self.break_lines(if self.lists.is_empty() { 2 } else { 1 });
+                self.lists.push((name == "ol").then_some(0));
+            }
+            "pre" => {
+                self.break_lines(2);
+                self.out.push_str("```\n");
+                self.pre_depth += 1;
+            }
+            "code" if self.pre_depth == 0 => {
+                self.flush_space();
+                self.out.push('`');
+            }
+            "br" => {
+                self.trim_trailing_spaces();
+                self.out.push('\n');
+                self.pending_space = false;
+            }
+            "td" | "th" => self.pending_space = true,
+            "a" => {
+                self.flush_space();
+                let target = attribute(attributes, "href").and_then(|href| self.link(&href));
+                self.links.push((target, self.out.len()));
+            }
+            "p" | "blockquote" | "table" | "figure" | "section" | "article" | "main" => {
+                self.break_lines(2)
+            }
+            _ if BLOCK_ELEMENTS.contains(&name) => self.break_lines(1),
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" | "blockquote" | "table" | "figure" => {
+                self.break_lines(2)
+            }
+            "ul" | "ol" => {
+                self.lists.pop();
+                self.break_lines(if self.lists.is_empty() { 2 } else { 1 });
+            }
+            "pre" if self.pre_depth > 0 => {
+                self.pre_depth -= 1;
+                if !self.out.ends_with('\n') {
+                    self.out.push('\n');
+                }
+                self.out.push_str("```");
+                self.break_lines(2);
+            }
+            "code" if self.pre_depth == 0 => self.out.push('`'),
+            "a" => {
+                let Some((target, start)) = self.links.pop() else {
+                    return;
+                };
+                let label = self.out[start..].trim();
+                if let Some(target) = target.filter(|_| !label.is_empty()) {
+                    let label = label.to_string();
+                    self.out.truncate(start);
+                    self.out.push_str(&format!("[{label}]({target})"));
+                }
+            }
+            _ if BLOCK_ELEMENTS.contains(&name) || name == "li" => self.break_lines(1),
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        let text = decode_entities(text);
+        if self.pre_depth > 0 {
+            self.out.push_str(&text);
+            return;
+        }
+        if text.starts_with(char::is_whitespace) {
+            self.pending_space = true;
+        }
+        for (index, word) in text.split_whitespace().enumerate() {
+            if index > 0 {
+                self.pending_space = true;
+            }
+            self.flush_space();
+            self.out.push_str(word);
+        }
+        if text.ends_with(char::is_whitespace) {
+            self.pending_space = true;
+        }
+    }
+
+    /// Absolute form of a link target; fragments and script links are dropped.
+    fn link(&self, href: &str) -> Option<String> {
+        let href = href.trim();
+        if href.is_empty() || href.starts_with('#') || href.starts_with("javascript:") {
+            return None;
+        }
+        match self.base {
+            Some(base) => base.join(href).ok().map(String::from),
+            None => Some(href.to_string()),
+        }
+    }
+
+    fn flush_space(&mut self) {
+        if std::mem::take(&mut self.pending_space)
+            && !self.out.is_empty()
+            && !self.out.ends_with(char::is_whitespace)
+        {
+            self.out.push(' ');
+        }
+    }
+
+    fn trim_trailing_spaces(&mut self) {
+        let trimmed = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(trimmed);
+    }
+
+    /// Ends the current line so that `count` newlines (at most) separate it from what follows.
+    fn break_lines(&mut self, count: usize) {
+        self.pending_space = false;
+        if self.pre_depth > 0 {
+            return;
+        }
+        self.trim_trailing_spaces();
+        if self.out.is_empty() {
+            return;
+        }
+        let existing = self.out.len() - self.out.trim_end_matches('\n').len();
+        for _ in existing..count {
+            self.out.push('\n');
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut text = String::new();
+        let mut blank_lines = 0;
+        let mut in_code = false;
+        for line in self.out.lines() {
+            if line.starts_with("```") {
+                in_code = !in_code;
+            }
+            let line = if in_code { line } else { line.trim_end() };
+            if line.trim().is_empty() && !in_code {
+                blank_lines += 1;
+                continue;
+            }
+            if !text.is_empty() {
+                text.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+            }
+            blank_lines = 0;
+            text.push_str(line);
+        }
+        text
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| decode_entity(&rest[1..end + 1]).map(|value| (value, end + 2)));
+        match entity {
+            Some((value, consumed)) => {
+                decoded.push(value);
+                rest = &rest[consumed..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(number) = entity.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "middot" => '·',
+        "bull" => '•',
+        "times" => '×',
+        _ => return None,
+    })
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use ignore::gitignore::Gitignore;
+use ignore::Match;
+
+const PIXY_IGNORE_FILE: &str = ".pixyignore";
+const IGNORE_FILES: &[&str] = &[PIXY_IGNORE_FILE, ".gitignore"];
+
+/// `.gitignore` and `.pixyignore` rules that apply inside one directory, collected from the
+/// repository root (or the workspace when it is not in a git repository) down to that directory.
+/// `.git` itself is always ignored.
+pub(super) struct IgnoreRules {
+    /// Repository root, or the workspace outside a repository; ignore files above it are not read.
+    base: PathBuf,
+    /// Deepest directory first; within a directory `.pixyignore` comes before `.gitignore`.
+    matchers: Vec<(PathBuf, Gitignore)>,
+}
+
+impl IgnoreRules {
+    pub(super) fn for_dir(cwd: &Path, dir: &Path) -> Self {
+        let cwd = normalize(cwd);
+        let dir = normalize(dir);
+        let base = git_root(&cwd).unwrap_or(cwd);
+        let mut matchers = Vec::new();
+        if dir.starts_with(&base) {
+            for ancestor in dir.ancestors().take_while(|path| path.starts_with(&base)) {
+                matchers.extend(matchers_in(ancestor));
+            }
+        }
+        Self { base, matchers }
+    }
+
+    /// Rules for `dir`, a direct child of the directory these rules were built for.
+    fn for_child_dir(&self, dir: &Path) -> Self {
+        let dir = normalize(dir);
+        let mut matchers = if dir.starts_with(&self.base) {
+            matchers_in(&dir)
+        } else {
+            Vec::new()
+        };
+        matchers.extend(self.matchers.iter().cloned());
+        Self {
+            base: self.base.clone(),
+            matchers,
+        }
+    }
+
+    /// The ignore file that excludes `path`, or `.git` for repository internals.
+    pub(super) fn ignored_by(&self, path: &Path, is_dir: bool) -> Option<String> {
+        let path = normalize(path);
+        if path
+            .components()
+            .any(|component| component.as_os_str() == ".git")
+        {
+            return Some(".git".to_string());
+        }
+        for (root, matcher) in &self.matchers {
+            if !path.starts_with(root) || path == *root {
+                continue;
+            }
+            match matcher.matched_path_or_any_parents(&path, is_dir) {
+                Match::Ignore(glob) => {
+                    return Some(
+                        glob.from()
+                            .map(|file| file.display().to_string())
+                            .unwrap_or_else(|| glob.original().to_string()),
+                    )
+                }
+                Match::Whitelist(_) => return None,
+                Match::None => {}
+            }
+        }
+        None
+    }
+}
+
+/// [`IgnoreRules`] for every directory of a recursive walk, so walking tools hide exactly the
+/// entries `list_directory` hides. Each directory's rules extend its parent's.
+pub(super) struct IgnoreWalkFilter {
+    cwd: PathBuf,
+    rules: Mutex<HashMap<PathBuf, Arc<IgnoreRules>>>,
+}
+
+impl IgnoreWalkFilter {
+    pub(super) fn new(cwd: &Path) -> Self {
+        Self {
+            cwd: cwd.to_path_buf(),
+            rules: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(super) fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let path = normalize(path);
+        let Some(parent) = path.parent() else {
+            return false;
+        };
+        self.rules_for(parent).ignored_by(&path, is_dir).is_some()
+    }
+
+    fn rules_for(&self, dir: &Path) -> Arc<IgnoreRules> {
+        let mut rules = self
+            .rules
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(known) = rules.get(dir) {
+            return known.clone();
+        }
+        let built = match dir.parent().and_then(|parent| rules.get(parent)) {
+            Some(parent) => parent.for_child_dir(dir),
+            None => IgnoreRules::for_dir(&self.cwd, dir),
+        };
+        let built = Arc::new(built);
+        rules.insert(dir.to_path_buf(), built.clone());
+        built
+    }
+}
+
+/// Non-empty matchers of the ignore files directly inside `dir`.
+fn matchers_in(dir: &Path) -> Vec<(PathBuf, Gitignore)> {
+    IGNORE_FILES
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|file| file.is_file())
+        .map(|file| Gitignore::new(&file).0)
+        .filter(|matcher| !matcher.is_empty())
+        .map(|matcher| (dir.to_path_buf(), matcher))
+        .collect()
+}
+
+fn git_root(cwd: &Path) -> Option<PathBuf> {
+    cwd.ancestors()
+        .find(|path| path.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Lexical normalization so `..` segments cannot step around the prefix checks.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
@@ -2,32 +2,35 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use pixy_agent_core::{AgentTool, AgentToolExecutor, AgentToolResult};
+use pixy_agent_core::{AgentTool, AgentToolArgs, AgentToolExecutor, AgentToolResult};
 use pixy_ai::PiAiError;
+use serde::Deserialize;
 use serde_json::{json, Value};
 
-use super::common::{invalid_tool_args, text_result};
+use super::common::text_result;
+use super::ignore_rules::IgnoreRules;
 
 pub fn create_list_directory_tool(cwd: impl AsRef<Path>) -> AgentTool {
     let cwd = cwd.as_ref().to_path_buf();
     AgentTool {
         name: "list_directory".to_string(),
         label: "list_directory".to_string(),
-        description: "List directory entries.".to_string(),
-        parameters: json!({
-            "type": "object",
-            "properties": {
-                "path": {
-                    "type": "string",
-                    "description": "Directory path to list. Empty value lists workspace root."
-                }
-            },
-            "additionalProperties": false
-        }),
+        description: "List directory entries. Entries excluded by .gitignore or .pixyignore are hidden unless includeIgnored is true.".to_string(),
+        parameters: ListDirectoryArgs::schema(),
+        conflict_key: None,
         execute: Arc::new(ListDirectoryToolExecutor { cwd }),
     }
 }
 
+#[derive(Deserialize, AgentToolArgs)]
+#[serde(rename_all = "camelCase")]
+struct ListDirectoryArgs {
+    /// Directory path to list. Empty value lists workspace root.
+    path: Option<String>,
+    /// Also list entries excluded by .gitignore or .pixyignore. Defaults to false.
+    include_ignored: Option<bool>,
+}
+
 struct ListDirectoryToolExecutor {
     cwd: PathBuf,
 }
@@ -45,15 +48,9 @@ impl AgentToolExecutor for ListDirectoryToolExecutor {
 }
 
 fn execute_list_directory_tool(cwd: &Path, args: Value) -> Result<AgentToolResult, PiAiError> {
-    let path = match args.get("path") {
-        Some(value) if value.is_null() => String::new(),
-        Some(value) => value
-            .as_str()
-            .map(|value| value.to_string())
-            .ok_or_else(|| invalid_tool_args("Missing or invalid `path`"))?,
-        None => String::new(),
-    };
-    let requested_path = path.trim();
+    let args = ListDirectoryArgs::parse(args)?;
+    let requested_path = args.path.as_deref().unwrap_or_default().trim();
+    let include_ignored = args.include_ignored.unwrap_or(false);
 
     let target = if requested_path.is_empty() {
         cwd.to_path_buf()
@@ -87,8 +84,18 @@ fn execute_list_directory_tool(cwd: &Path, args: Value) -> Result<AgentToolResul
 
     match std::fs::read_dir(&target) {
         Ok(entries) => {
+            let ignore_rules = (!include_ignored).then(|| IgnoreRules::for_dir(cwd, &target));
+            let mut ignored_count = 0usize;
             let mut items = entries
                 .filter_map(|entry| entry.ok())
+                .filter(|entry| {
+                    let is_dir = entry.file_type().is_ok_and(|kind| kind.is_dir());
+                    let ignored = ignore_rules
+                        .as_ref()
+                        .is_some_and(|rules| rules.ignored_by(&entry.path(), is_dir).is_some());
+                    ignored_count += usize::from(ignored);
+                    !ignored
+                })
                 .map(|entry| {
                     let name = entry.file_name().to_string_lossy().to_string();
                     let entry_type = entry.file_type().ok();
@@ -101,17 +108,23 @@ fn execute_list_directory_tool(cwd: &Path, args: Value) -> Result<AgentToolResul
                 })
                 .collect::<Vec<_>>();
             items.sort();
-            let text = if items.is_empty() {
+            let mut text = if items.is_empty() {
                 "(empty directory)".to_string()
             } else {
                 items.join("\n")
             };
+            if ignored_count > 0 {
+                text.push_str(&format!(
+                    "\n\n[{ignored_count} ignored entries hidden (.gitignore/.pixyignore). Use includeIgnored=true to list them.]"
+                ));
+            }
             Ok(text_result(
                 text,
                 json!({
                     "path": requested_path,
                     "resolvedPath": target.display().to_string(),
                     "entryCount": items.len(),
+                    "ignoredCount": ignored_count,
                 }),
             ))
         }
@@ -1,28 +1,96 @@
+mod apply_patch;
 mod bash;
+mod bash_background;
+#[cfg(feature = "code-index")]
+mod code_outline;
 mod common;
 mod edit;
+mod edit_match;
+mod git;
+mod html_text;
+mod ignore_rules;
 mod list_directory;
+mod notebook_edit;
+mod pty;
 mod read;
+mod read_image;
+mod search;
+mod shell;
+mod todo;
+mod web_fetch;
+mod web_search;
 mod write;
 
 use std::path::Path;
 
 use pixy_agent_core::AgentTool;
 
+pub use apply_patch::create_apply_patch_tool;
+use apply_patch::create_apply_patch_tool_with_snapshots;
 pub use bash::create_bash_tool;
+pub(crate) use bash::exit_status_line;
+pub use bash_background::{create_bash_background_tool, BackgroundProcesses};
 pub use edit::create_edit_tool;
+use edit::create_edit_tool_with_snapshots;
+pub use git::create_git_tools;
+pub(crate) use git::in_git_repository;
 pub use list_directory::create_list_directory_tool;
+pub use notebook_edit::create_notebook_edit_tool;
+use notebook_edit::create_notebook_edit_tool_with_snapshots;
 pub use read::create_read_tool;
+use read::create_read_tool_with_file_changes;
+pub use read_image::create_read_image_tool;
+pub use search::create_search_tool;
+pub use shell::{create_shell_tool, PersistentShell};
+pub(crate) use todo::todos_from_tool_result;
+pub use todo::{create_todo_tool, todos_from_messages, TodoItem, TodoStatus};
+pub use web_fetch::create_web_fetch_tool;
+pub use web_search::{
+    create_web_search_tool, WebSearchBackend, WebSearchBackendKind, WebSearchConfig,
+    WebSearchResult,
+};
 pub use write::create_write_tool;
+use write::create_write_tool_with_snapshots;
+
+use crate::diff_review::SharedDiffReview;
+use crate::file_changes::SharedFileChangeTracker;
+use crate::file_snapshots::SharedFileSnapshots;
 
 pub fn create_coding_tools(cwd: impl AsRef<Path>) -> Vec<AgentTool> {
+    create_coding_tools_with_snapshots(cwd, None, None, None)
+}
+
+pub(crate) fn create_coding_tools_with_snapshots(
+    cwd: impl AsRef<Path>,
+    snapshots: Option<SharedFileSnapshots>,
+    file_changes: Option<SharedFileChangeTracker>,
+    review: Option<SharedDiffReview>,
+) -> Vec<AgentTool> {
     let cwd = cwd.as_ref().to_path_buf();
     vec![
         create_list_directory_tool(&cwd),
-        create_read_tool(&cwd),
+        create_read_tool_with_file_changes(&cwd, file_changes.clone()),
+        create_search_tool(&cwd),
         create_bash_tool(&cwd),
-        create_edit_tool(&cwd),
-        create_write_tool(&cwd),
+        create_edit_tool_with_snapshots(
+            &cwd,
+            snapshots.clone(),
+            file_changes.clone(),
+            review.clone(),
+        ),
+        create_apply_patch_tool_with_snapshots(
+            &cwd,
+            snapshots.clone(),
+            file_changes.clone(),
+            review.clone(),
+        ),
+        create_notebook_edit_tool_with_snapshots(
+            &cwd,
+            snapshots.clone(),
+            file_changes.clone(),
+            review.clone(),
+        ),
+        create_write_tool_with_snapshots(&cwd, snapshots, file_changes, review),
     ]
 }
 
@@ -31,6 +99,17 @@ pub fn create_coding_tools_with_extra(
     mut extra_tools: Vec<AgentTool>,
 ) -> Vec<AgentTool> {
     let mut tools = create_coding_tools(cwd);
+    tools.extend(create_web_tools(WebSearchConfig::from_env().as_ref()));
     tools.append(&mut extra_tools);
     tools
 }
+
+/// `web_fetch`, plus `web_search` when `web_search` names a usable backend.
+pub(crate) fn create_web_tools(web_search: Option<&WebSearchConfig>) -> Vec<AgentTool> {
+    let mut tools = vec![create_web_fetch_tool()];
+    match web_search.map(WebSearchConfig::build_backend).transpose() {
+        Ok(backend) => tools.extend(backend.flatten().map(create_web_search_tool)),
+        Err(error) => eprintln!("warning: {error}, web_search is disabled"),
+    }
+    tools
+}
@@ -0,0 +1,359 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use pixy_agent_core::{AgentTool, AgentToolArgs, AgentToolExecutor, AgentToolResult};
+use pixy_ai::PiAiError;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use super::common::{
+    format_diff_stat_line, invalid_tool_args, observe_file, path_conflict_key,
+    record_file_snapshot, resolve_to_cwd, review_file_change, text_result, tool_execution_failed,
+};
+use crate::diff_review::SharedDiffReview;
+use crate::file_changes::SharedFileChangeTracker;
+use crate::file_snapshots::SharedFileSnapshots;
+
+pub fn create_notebook_edit_tool(cwd: impl AsRef<Path>) -> AgentTool {
+    create_notebook_edit_tool_with_snapshots(cwd, None, None, None)
+}
+
+pub(crate) fn create_notebook_edit_tool_with_snapshots(
+    cwd: impl AsRef<Path>,
+    snapshots: Option<SharedFileSnapshots>,
+    file_changes: Option<SharedFileChangeTracker>,
+    review: Option<SharedDiffReview>,
+) -> AgentTool {
+    let cwd = cwd.as_ref().to_path_buf();
+    AgentTool {
+        name: "notebook_edit".to_string(),
+        label: "notebook_edit".to_string(),
+        description: "Read or change the cells of a Jupyter notebook (.ipynb) as structured JSON instead of editing its raw file. `read` lists cells with their index, id, type and source (outputs are left out and only counted). `replace` sets a cell's source (and optionally its type), `insert` adds a cell, `delete` removes one. Editing a code cell clears its stale outputs. The rest of the notebook is kept as is."
+            .to_string(),
+        parameters: NotebookEditArgs::schema(),
+        conflict_key: Some(path_conflict_key(&cwd)),
+        execute: Arc::new(NotebookEditToolExecutor {
+            cwd,
+            snapshots,
+            file_changes,
+            review,
+        }),
+    }
+}
+
+#[derive(Deserialize, AgentToolArgs)]
+#[serde(rename_all = "camelCase")]
+struct NotebookEditArgs {
+    /// Path to the .ipynb file, absolute or relative to workspace cwd.
+    path: String,
+    /// What to do. Defaults to `read`.
+    #[tool(values("read", "replace", "insert", "delete"))]
+    action: Option<String>,
+    /// 0-based index of the cell to read, replace or delete. For insert, the position of the new cell; defaults to the end.
+    cell_index: Option<usize>,
+    /// Id of the cell to read, replace or delete, instead of cellIndex. For insert, the new cell goes after it.
+    cell_id: Option<String>,
+    /// New cell source, for replace and insert.
+    source: Option<String>,
+    /// Cell type for insert (defaults to `code`); with replace, converts the cell.
+    #[tool(values("code", "markdown", "raw"))]
+    cell_type: Option<String>,
+}
+
+struct NotebookEditToolExecutor {
+    cwd: PathBuf,
+    snapshots: Option<SharedFileSnapshots>,
+    file_changes: Option<SharedFileChangeTracker>,
+    review: Option<SharedDiffReview>,
+}
+
+#[async_trait]
+impl AgentToolExecutor for NotebookEditToolExecutor {
+    async fn execute(
+        &self,
+        _tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        let args = NotebookEditArgs::parse(args)?;
+        let absolute_path = resolve_to_cwd(&self.cwd, &args.path);
+        let path = args.path.as_str();
+        let content = fs::read_to_string(&absolute_path)
+            .map_err(|error| tool_execution_failed(format!("Failed to read {path}: {error}")))?;
+        let mut notebook: Value = serde_json::from_str(&content).map_err(|error| {
+            tool_execution_failed(format!("{path} is not a valid notebook: {error}"))
+        })?;
+        let uses_ids = cell_ids_supported(&notebook);
+        let Some(cells) = notebook.get_mut("cells").and_then(Value::as_array_mut) else {
+            return Err(tool_execution_failed(format!(
+                "{path} is not a valid notebook: it has no `cells` array"
+            )));
+        };
+
+        let action = args.action.as_deref().unwrap_or("read");
+        if action == "read" {
+            observe_file(self.file_changes.as_ref(), &absolute_path);
+            let selected = match (args.cell_index, args.cell_id.as_deref()) {
+                (None, None) => None,
+                _ => Some(find_cell(cells, &args, path)?),
+            };
+            let summaries: Vec<CellSummary> = cells
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| selected.is_none_or(|selected| selected == *index))
+                .map(|(index, cell)| CellSummary::new(index, cell))
+                .collect();
+            let cell_count = cells.len();
+            let listing = json!({
+                "path": path,
+                "language": language(&notebook),
+                "cellCount": cell_count,
+                "cells": summaries,
+            });
+            let text = serde_json::to_string_pretty(&listing).unwrap_or_default();
+            return Ok(text_result(text, listing));
+        }
+
+        let (index, before_source, cell_type) = match action {
+            "insert" => {
+                let index = match (args.cell_id.as_deref(), args.cell_index) {
+                    (Some(_), _) => find_cell(cells, &args, path)? + 1,
+                    (None, Some(index)) if index > cells.len() => {
+                        return Err(out_of_range(path, index, cells.len()));
+                    }
+                    (None, Some(index)) => index,
+                    (None, None) => cells.len(),
+                };
+                let cell_type = args.cell_type.as_deref().unwrap_or("code");
+                let mut cell = new_cell(cell_type);
+                if uses_ids {
+                    cell["id"] = Value::String(unique_cell_id(cells));
+                }
+                cell["source"] = source_lines(args.source.as_deref().unwrap_or_default());
+                cells.insert(index, cell);
+                (index, String::new(), cell_type.to_string())
+            }
+            "replace" => {
+                let Some(source) = args.source.as_deref() else {
+                    return Err(invalid_tool_args("`source` is required to replace a cell"));
+                };
+                let index = find_cell(cells, &args, path)?;
+                let cell = cells[index].as_object_mut().ok_or_else(|| {
+                    tool_execution_failed(format!("Cell {index} of {path} is not an object"))
+                })?;
+                let before = cell_source(cell.get("source"));
+                if let Some(cell_type) = args.cell_type.as_deref() {
+                    convert_cell(cell, cell_type);
+                }
+                cell.insert("source".to_string(), source_lines(source));
+                clear_outputs(cell);
+                let cell_type = cell["cell_type"].as_str().unwrap_or("code").to_string();
+                (index, before, cell_type)
+            }
+            _ => {
+                let index = find_cell(cells, &args, path)?;
+                let removed = cells.remove(index);
+                let cell_type = removed["cell_type"].as_str().unwrap_or("code").to_string();
+                (index, cell_source(removed.get("source")), cell_type)
+            }
+        };
+        let summary = cells
+            .get(index)
+            .filter(|_| action != "delete")
+            .map(|cell| CellSummary::new(index, cell));
+        let after_source = summary
+            .as_ref()
+            .map(|summary| summary.source.clone())
+            .unwrap_or_default();
+        let cell_count = cells.len();
+
+        let mut updated = to_notebook_json(&notebook);
+        if content.ends_with('\n') {
+            updated.push('\n');
+        }
+        if updated == content {
+            return Err(tool_execution_failed(format!(
+                "No changes made to {path}. The cell already has this content."
+            )));
+        }
+        review_file_change(
+            self.review.as_ref(),
+            "notebook_edit",
+            path,
+            Some(&content),
+            &updated,
+        )
+        .await?;
+        record_file_snapshot(self.snapshots.as_ref(), &absolute_path)?;
+        fs::write(&absolute_path, updated.as_bytes())
+            .map_err(|error| tool_execution_failed(format!("Failed to write {path}: {error}")))?;
+        observe_file(self.file_changes.as_ref(), &absolute_path);
+
+        let verb = match action {
+            "insert" => "Inserted",
+            "replace" => "Replaced",
+            _ => "Deleted",
+        };
+        let mut text = format!("{verb} {cell_type} cell {index} in {path} ({cell_count} cells).");
+        text.push('\n');
+        text.push_str(&format_diff_stat_line(
+            &format!("{path} [cell {index}]"),
+            &before_source,
+            &after_source,
+        ));
+        Ok(text_result(
+            text,
+            json!({
+                "path": path,
+                "action": action,
+                "cellIndex": index,
+                "cell": summary,
+                "cellCount": cell_count,
+            }),
+        ))
+    }
+}
+
+/// A cell as shown to the model: its source without outputs.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CellSummary {
+    index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    cell_type: String,
+    source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    execution_count: Option<u64>,
+    /// Number of stripped outputs, for code cells.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outputs: Option<usize>,
+}
+
+impl CellSummary {
+    fn new(index: usize, cell: &Value) -> Self {
+        Self {
+            index,
+            id: cell["id"].as_str().map(str::to_string),
+            cell_type: cell["cell_type"].as_str().unwrap_or("code").to_string(),
+            source: cell_source(cell.get("source")),
+            execution_count: cell["execution_count"].as_u64(),
+            outputs: cell["outputs"].as_array().map(Vec::len),
+        }
+    }
+}
+
+fn find_cell(cells: &[Value], args: &NotebookEditArgs, path: &str) -> Result<usize, PiAiError> {
+    if let Some(id) = args.cell_id.as_deref() {
+        return cells
+            .iter()
+            .position(|cell| cell["id"].as_str() == Some(id))
+            .ok_or_else(|| tool_execution_failed(format!("No cell with id `{id}` in {path}")));
+    }
+    match args.cell_index {
+        Some(index) if index < cells.len() => Ok(index),
+        Some(index) => Err(out_of_range(path, index, cells.len())),
+        None => Err(invalid_tool_args("`cellIndex` or `cellId` is required")),
+    }
+}
+
+fn out_of_range(path: &str, index: usize, count: usize) -> PiAiError {
+    tool_execution_failed(format!(
+        "Cell index {index} is out of range; {path} has {count} cells"
+    ))
+}
+
+/// Cell ids exist from nbformat 4.5 on; older notebooks get them only if they already use them.
+fn cell_ids_supported(notebook: &Value) -> bool {
+    let major = notebook["nbformat"].as_u64().unwrap_or_default();
+    let minor = notebook["nbformat_minor"].as_u64().unwrap_or_default();
+    major > 4
+        || (major == 4 && minor >= 5)
+        || notebook["cells"]
+            .as_array()
+            .is_some_and(|cells| cells.iter().any(|cell| cell.get("id").is_some()))
+}
+
+fn language(notebook: &Value) -> Option<&str> {
+    let metadata = &notebook["metadata"];
+    metadata["language_info"]["name"]
+        .as_str()
+        .or_else(|| metadata["kernelspec"]["language"].as_str())
+}
+
+/// Notebook sources are a string or a list of lines.
+fn cell_source(source: Option<&Value>) -> String {
+    match source {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+/// `source` split into lines that keep their newline, as Jupyter stores it.
+fn source_lines(source: &str) -> Value {
+    Value::Array(
+        source
+            .split_inclusive('\n')
+            .map(|line| Value::String(line.to_string()))
+            .collect(),
+    )
+}
+
+fn new_cell(cell_type: &str) -> Value {
+    let mut cell = Map::new();
+    cell.insert("cell_type".to_string(), json!(cell_type));
+    cell.insert("metadata".to_string(), json!({}));
+    cell.insert("source".to_string(), json!([]));
+    if cell_type == "code" {
+        cell.insert("execution_count".to_string(), Value::Null);
+        cell.insert("outputs".to_string(), json!([]));
+    }
+    Value::Object(cell)
+}
+
+fn convert_cell(cell: &mut Map<String, Value>, cell_type: &str) {
+    cell.insert("cell_type".to_string(), json!(cell_type));
+    if cell_type == "code" {
+        cell.entry("execution_count").or_insert(Value::Null);
+        cell.entry("outputs").or_insert_with(|| json!([]));
+    } else {
+        cell.remove("execution_count");
+        cell.remove("outputs");
+    }
+}
+
+/// Outputs of an edited code cell no longer match its source.
+fn clear_outputs(cell: &mut Map<String, Value>) {
+    if let Some(outputs) = cell.get_mut("outputs") {
+        *outputs = json!([]);
+        cell.insert("execution_count".to_string(), Value::Null);
+    }
+}
+
+fn unique_cell_id(cells: &[Value]) -> String {
+    let mut seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default();
+    loop {
+        let id = format!("{:08x}", seed & 0xffff_ffff);
+        if !cells.iter().any(|cell| cell["id"].as_str() == Some(&id)) {
+            return id;
+        }
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+    }
+}
+
+/// Serializes like Jupyter does: keys sorted, one-space indentation.
+fn to_notebook_json(notebook: &Value) -> String {
+    let mut buffer = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b" ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut buffer, formatter);
+    notebook
+        .serialize(&mut serializer)
+        .expect("JSON values serialize");
+    String::from_utf8(buffer).expect("serde_json writes UTF-8")
+}
@@ -0,0 +1,193 @@
+//! Pseudo-terminal behind the `shell` tool, so the commands it runs see a real TTY.
+
+#[cfg(unix)]
+pub(super) use unix::{Pty, PtyReader};
+
+#[cfg(not(unix))]
+pub(super) use unsupported::{Pty, PtyReader};
+
+/// Terminal size reported to programs; wide enough that tables and paths are rarely wrapped.
+#[cfg(unix)]
+const PTY_COLUMNS: u16 = 160;
+#[cfg(unix)]
+const PTY_ROWS: u16 = 50;
+
+#[cfg(unix)]
+mod unix {
+    use std::fs::File;
+    use std::io::{self, Read};
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+    use std::process::Stdio;
+
+    use tokio::io::unix::AsyncFd;
+    use tokio::process::Command;
+
+    /// A freshly opened pseudo-terminal, before its terminal side is handed to a child.
+    pub(in crate::tools) struct Pty {
+        master: OwnedFd,
+        slave: OwnedFd,
+    }
+
+    /// The controlling side of a pseudo-terminal whose terminal side only the child holds.
+    pub(in crate::tools) struct PtyReader {
+        master: AsyncFd<File>,
+    }
+
+    impl Pty {
+        pub(in crate::tools) fn open() -> io::Result<Self> {
+            let (mut master, mut slave) = (-1, -1);
+            // SAFETY: both out-pointers are valid; null name, termios and window size pointers
+            // are allowed.
+            let opened = unsafe {
+                libc::openpty(
+                    &mut master,
+                    &mut slave,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                )
+            };
+            if opened != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: openpty succeeded, so both descriptors are open and owned by nobody else.
+            let pty = unsafe {
+                Self {
+                    master: OwnedFd::from_raw_fd(master),
+                    slave: OwnedFd::from_raw_fd(slave),
+                }
+            };
+            set_cloexec(pty.master.as_raw_fd())?;
+            set_cloexec(pty.slave.as_raw_fd())?;
+            configure_terminal(pty.slave.as_raw_fd())?;
+            Ok(pty)
+        }
+
+        /// Sends the child's stdout and stderr to the terminal and makes it the controlling
+        /// terminal of a new session, so `/dev/tty` opens it and the processes of the session
+        /// get `SIGHUP` when its leader dies.
+        pub(in crate::tools) fn attach(&self, command: &mut Command) -> io::Result<()> {
+            command
+                .stdout(Stdio::from(self.slave.try_clone()?))
+                .stderr(Stdio::from(self.slave.try_clone()?));
+            // SAFETY: the hook only makes async-signal-safe calls.
+            unsafe {
+                command.pre_exec(|| {
+                    if libc::setsid() == -1
+                        || libc::ioctl(libc::STDOUT_FILENO, libc::TIOCSCTTY as _, 0) == -1
+                    {
+                        return Err(io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+            Ok(())
+        }
+
+        /// Drops this process's handle on the terminal side, so reads see the end of output
+        /// once the child's processes are gone. Call it after the child was spawned.
+        pub(in crate::tools) fn into_reader(self) -> io::Result<PtyReader> {
+            drop(self.slave);
+            set_nonblocking(self.master.as_raw_fd())?;
+            Ok(PtyReader {
+                master: AsyncFd::new(File::from(self.master))?,
+            })
+        }
+    }
+
+    impl PtyReader {
+        /// Reads terminal output; 0 means every process holding the terminal has exited.
+        pub(in crate::tools) async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+            loop {
+                let mut guard = self.master.readable().await?;
+                match guard.try_io(|master| master.get_ref().read(buf)) {
+                    Ok(Ok(read)) => return Ok(read),
+                    // Linux reports a terminal without writers as EIO rather than end of file.
+                    Ok(Err(error)) if error.raw_os_error() == Some(libc::EIO) => return Ok(0),
+                    Ok(Err(error)) => return Err(error),
+                    Err(_would_block) => continue,
+                }
+            }
+        }
+    }
+
+    /// No output post-processing (lines end in `\n`, not `\r\n`), no echo, and the window size
+    /// programs lay out their output for.
+    fn configure_terminal(fd: RawFd) -> io::Result<()> {
+        // SAFETY: `termios` is plain data, filled in by tcgetattr before it is read.
+        let mut termios = unsafe { std::mem::zeroed::<libc::termios>() };
+        // SAFETY: `fd` is an open terminal and `termios` is a valid out-pointer.
+        if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        termios.c_oflag &= !libc::OPOST;
+        termios.c_lflag &= !(libc::ECHO | libc::ECHONL);
+        // SAFETY: as above.
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let size = libc::winsize {
+            ws_row: super::PTY_ROWS,
+            ws_col: super::PTY_COLUMNS,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        // SAFETY: `fd` is an open terminal and `size` outlives the call.
+        if unsafe { libc::ioctl(fd, libc::TIOCSWINSZ as _, &size) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn set_cloexec(fd: RawFd) -> io::Result<()> {
+        // SAFETY: `fd` is open for the duration of the call.
+        if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn set_nonblocking(fd: RawFd) -> io::Result<()> {
+        // SAFETY: `fd` is open for the duration of both calls.
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+        if flags == -1 || unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } == -1
+        {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(unix))]
+mod unsupported {
+    use std::io;
+
+    use tokio::process::Command;
+
+    pub(in crate::tools) struct Pty;
+
+    pub(in crate::tools) struct PtyReader;
+
+    impl Pty {
+        pub(in crate::tools) fn open() -> io::Result<Self> {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "pseudo-terminals need a Unix host",
+            ))
+        }
+
+        pub(in crate::tools) fn attach(&self, _command: &mut Command) -> io::Result<()> {
+            Ok(())
+        }
+
+        pub(in crate::tools) fn into_reader(self) -> io::Result<PtyReader> {
+            Ok(PtyReader)
+        }
+    }
+
+    impl PtyReader {
+        pub(in crate::tools) async fn read(&self, _buf: &mut [u8]) -> io::Result<usize> {
+            Ok(0)
+        }
+    }
+}
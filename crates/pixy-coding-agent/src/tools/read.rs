@@ -3,38 +3,73 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use pixy_agent_core::{AgentTool, AgentToolExecutor, AgentToolResult};
+use pixy_agent_core::{AgentTool, AgentToolArgs, AgentToolExecutor, AgentToolResult};
 use pixy_ai::PiAiError;
+use serde::Deserialize;
 use serde_json::{json, Value};
 
+#[cfg(feature = "code-index")]
+use super::code_outline::{find_symbol, outline, CodeLanguage, Definition};
 use super::common::{
-    get_optional_usize, get_required_string, invalid_tool_args, resolve_to_cwd, text_result,
-    tool_execution_failed, truncate_head, truncated_by_str, DEFAULT_MAX_BYTES, DEFAULT_MAX_LINES,
+    estimate_tokens, invalid_tool_args, observe_file, path_conflict_key, resolve_to_cwd,
+    text_result, tool_execution_failed,
 };
+use super::ignore_rules::IgnoreRules;
+use crate::file_changes::SharedFileChangeTracker;
 
 pub fn create_read_tool(cwd: impl AsRef<Path>) -> AgentTool {
+    create_read_tool_with_file_changes(cwd, None)
+}
+
+pub(crate) fn create_read_tool_with_file_changes(
+    cwd: impl AsRef<Path>,
+    file_changes: Option<SharedFileChangeTracker>,
+) -> AgentTool {
     let cwd = cwd.as_ref().to_path_buf();
     AgentTool {
         name: "read".to_string(),
         label: "read".to_string(),
-        description: "Read UTF-8 text file content from disk. Supports offset/limit pagination."
-            .to_string(),
-        parameters: json!({
-            "type": "object",
-            "properties": {
-                "path": { "type": "string", "description": "Path to the file, absolute or relative to workspace cwd, no need to ask for permission." },
-                "offset": { "type": "integer", "minimum": 1, "description": "1-based start line offset." },
-                "limit": { "type": "integer", "minimum": 1, "description": "Maximum number of lines to return." }
-            },
-            "required": ["path"],
-            "additionalProperties": false
-        }),
-        execute: Arc::new(ReadToolExecutor { cwd }),
+        description: read_tool_description(),
+        parameters: ReadArgs::schema(),
+        conflict_key: Some(path_conflict_key(&cwd)),
+        execute: Arc::new(ReadToolExecutor { cwd, file_changes }),
     }
 }
 
+fn read_tool_description() -> String {
+    let mut description = "Read UTF-8 text file content from disk. Lines are prefixed with `cat -n` style line numbers and a tab; the prefix is not part of the file. Long files are returned in pages bounded by line count and an estimated token budget; follow the `Continue with offset=N` marker to read further.".to_string();
+    #[cfg(feature = "code-index")]
+    description.push_str(" For Rust, Python, JavaScript, TypeScript and Go files, `outline=true` lists the definitions with their signatures and line ranges, and `symbol` returns one definition's source; use them to navigate large files.");
+    description.push_str(
+        " Files excluded by .gitignore or .pixyignore are refused unless includeIgnored is true.",
+    );
+    description
+}
+
+#[derive(Deserialize, AgentToolArgs)]
+#[serde(rename_all = "camelCase")]
+struct ReadArgs {
+    /// Path to the file, absolute or relative to workspace cwd, no need to ask for permission.
+    path: String,
+    /// 1-based start line offset.
+    #[tool(minimum = 1)]
+    offset: Option<usize>,
+    /// Maximum number of lines to return.
+    #[tool(minimum = 1)]
+    limit: Option<usize>,
+    /// Return the file's functions, types and other definitions with their signatures and line ranges instead of its content.
+    #[cfg(feature = "code-index")]
+    outline: Option<bool>,
+    /// Return the source of one definition, with its doc comments. Accepts `name`, `Type::name` or `Type.name`.
+    #[cfg(feature = "code-index")]
+    symbol: Option<String>,
+    /// Read the file even if .gitignore or .pixyignore excludes it. Defaults to false.
+    include_ignored: Option<bool>,
+}
+
 struct ReadToolExecutor {
     cwd: PathBuf,
+    file_changes: Option<SharedFileChangeTracker>,
 }
 
 #[async_trait]
@@ -44,25 +79,71 @@ impl AgentToolExecutor for ReadToolExecutor {
         _tool_call_id: String,
         args: Value,
     ) -> Result<AgentToolResult, PiAiError> {
-        let path = get_required_string(&args, "path")?;
-        let offset = get_optional_usize(&args, "offset")?.unwrap_or(1);
-        if offset == 0 {
-            return Err(invalid_tool_args("`offset` must be >= 1"));
-        }
-        let limit = get_optional_usize(&args, "limit")?;
-        if let Some(limit_value) = limit {
-            if limit_value == 0 {
-                return Err(invalid_tool_args("`limit` must be >= 1"));
-            }
-        }
+        let ReadArgs {
+            path,
+            offset,
+            limit,
+            #[cfg(feature = "code-index")]
+                outline: outline_requested,
+            #[cfg(feature = "code-index")]
+            symbol,
+            include_ignored,
+        } = ReadArgs::parse(args)?;
+        let offset = offset.unwrap_or(1);
+        let include_ignored = include_ignored.unwrap_or(false);
+        #[cfg(feature = "code-index")]
+        let structure_language =
+            structure_language(&path, outline_requested.unwrap_or(false), symbol.as_deref())?;
 
         let absolute_path = resolve_to_cwd(&self.cwd, &path);
+        if !include_ignored {
+            let parent = absolute_path.parent().unwrap_or(&absolute_path);
+            if let Some(source) =
+                IgnoreRules::for_dir(&self.cwd, parent).ignored_by(&absolute_path, false)
+            {
+                return Err(tool_execution_failed(format!(
+                    "{path} is excluded by {source}. Pass includeIgnored=true if you really need to read it."
+                )));
+            }
+        }
         let bytes = fs::read(&absolute_path)
             .map_err(|error| tool_execution_failed(format!("Failed to read {path}: {error}")))?;
         let full_content = String::from_utf8(bytes).map_err(|_| {
             tool_execution_failed(format!("Failed to read {path}: file is not valid UTF-8"))
         })?;
-        let all_lines: Vec<&str> = full_content.split('\n').collect();
+        observe_file(self.file_changes.as_ref(), &absolute_path);
+        let body = full_content.strip_suffix('\n').unwrap_or(&full_content);
+        let all_lines: Vec<&str> = if body.is_empty() {
+            Vec::new()
+        } else {
+            body.split('\n').collect()
+        };
+        #[cfg(feature = "code-index")]
+        if let Some(language) = structure_language {
+            let definitions = outline(language, &full_content).map_err(tool_execution_failed)?;
+            return match symbol {
+                Some(symbol) => {
+                    let definition = find_symbol(language, &definitions, &symbol)
+                        .map_err(tool_execution_failed)?;
+                    Ok(read_symbol(&path, language, definition, &all_lines))
+                }
+                None => Ok(read_outline(&path, language, &definitions, all_lines.len())),
+            };
+        }
+        if all_lines.is_empty() && offset == 1 {
+            return Ok(text_result(
+                "(empty file)".to_string(),
+                json!({
+                    "path": path,
+                    "offset": offset,
+                    "limit": limit,
+                    "totalLines": 0,
+                    "outputLines": 0,
+                    "truncated": false,
+                    "totalBytes": full_content.len(),
+                }),
+            ));
+        }
 
         if offset > all_lines.len() {
             return Err(invalid_tool_args(format!(
@@ -71,48 +152,233 @@ impl AgentToolExecutor for ReadToolExecutor {
             )));
         }
 
-        let start_index = offset - 1;
-        let end_index = match limit {
-            Some(limit_value) => start_index.saturating_add(limit_value).min(all_lines.len()),
-            None => all_lines.len(),
-        };
-        let selected = all_lines[start_index..end_index].join("\n");
-        let truncation = truncate_head(&selected, DEFAULT_MAX_LINES, DEFAULT_MAX_BYTES);
-
-        let mut output = truncation.content.clone();
-        if output.is_empty() && full_content.is_empty() {
-            output = "(empty file)".to_string();
-        }
-
-        if truncation.truncated {
-            let shown_start = offset;
-            let shown_end = shown_start + truncation.output_lines.saturating_sub(1);
-            let next_offset = shown_end + 1;
+        let page = paginate_lines(&all_lines, offset, limit);
+        let mut output = page.content;
+        if let Some(stop) = page.stopped_by {
+            let shown_end = offset + page.output_lines - 1;
             output.push_str(&format!(
-                "\n\n[Showing lines {shown_start}-{shown_end} of {}. Use offset={next_offset} to continue.]",
-                all_lines.len()
-            ));
-        } else if end_index < all_lines.len() {
-            let next_offset = end_index + 1;
-            let remaining = all_lines.len() - end_index;
-            output.push_str(&format!(
-                "\n\n[{remaining} more lines in file. Use offset={next_offset} to continue.]"
+                "\n\n[Truncated by {}: showing lines {offset}-{shown_end} of {}. Continue with offset={}.]",
+                stop.label(),
+                all_lines.len(),
+                shown_end + 1
             ));
         }
-
         Ok(text_result(
             output,
             json!({
                 "path": path,
                 "offset": offset,
                 "limit": limit,
-                "totalLines": truncation.total_lines,
-                "outputLines": truncation.output_lines,
-                "truncated": truncation.truncated,
-                "truncatedBy": truncation.truncated_by.map(truncated_by_str),
-                "outputBytes": truncation.output_bytes,
-                "totalBytes": truncation.total_bytes,
+                "totalLines": all_lines.len(),
+                "outputLines": page.output_lines,
+                "truncated": page.stopped_by.is_some(),
+                "truncatedBy": page.stopped_by.map(ReadStop::key),
+                "estimatedTokens": page.estimated_tokens,
+                "truncatedLongLines": page.truncated_long_lines,
+                "totalBytes": full_content.len(),
             }),
         ))
     }
 }
+
+/// Language to outline when `outline` or `symbol` was requested, or `None` for a plain read.
+#[cfg(feature = "code-index")]
+fn structure_language(
+    path: &str,
+    outline_requested: bool,
+    symbol: Option<&str>,
+) -> Result<Option<CodeLanguage>, PiAiError> {
+    if outline_requested && symbol.is_some() {
+        return Err(invalid_tool_args(
+            "Pass either `outline` or `symbol`, not both",
+        ));
+    }
+    if !outline_requested && symbol.is_none() {
+        return Ok(None);
+    }
+    CodeLanguage::from_path(Path::new(path)).map(Some).ok_or_else(|| {
+        invalid_tool_args(format!(
+            "`outline` and `symbol` support Rust, Python, JavaScript, TypeScript and Go files; read {path} without them."
+        ))
+    })
+}
+
+#[cfg(feature = "code-index")]
+fn read_outline(
+    path: &str,
+    language: CodeLanguage,
+    definitions: &[Definition],
+    total_lines: usize,
+) -> AgentToolResult {
+    let mut output = format!(
+        "{path}: {} definitions in {total_lines} lines ({})",
+        definitions.len(),
+        language.name()
+    );
+    let mut estimated_tokens = 0;
+    let mut shown = 0;
+    for definition in definitions {
+        let entry = format!(
+            "{}{}-{}: {}",
+            "  ".repeat(definition.depth),
+            definition.start_line,
+            definition.end_line,
+            definition.signature
+        );
+        let tokens = estimate_tokens(&entry);
+        if estimated_tokens + tokens > MAX_READ_TOKENS {
+            break;
+        }
+        estimated_tokens += tokens;
+        shown += 1;
+        output.push('\n');
+        output.push_str(&entry);
+    }
+    if shown < definitions.len() {
+        output.push_str(&format!(
+            "\n\n[Truncated by ~{MAX_READ_TOKENS} token budget: showing {shown} of {} definitions. Read the rest with offset and limit.]",
+            definitions.len()
+        ));
+    }
+    text_result(
+        output,
+        json!({
+            "path": path,
+            "mode": "outline",
+            "language": language.name(),
+            "totalLines": total_lines,
+            "definitions": definitions.len(),
+            "outputDefinitions": shown,
+            "truncated": shown < definitions.len(),
+        }),
+    )
+}
+
+#[cfg(feature = "code-index")]
+fn read_symbol(
+    path: &str,
+    language: CodeLanguage,
+    definition: &Definition,
+    all_lines: &[&str],
+) -> AgentToolResult {
+    let start = definition.doc_start_line;
+    let end = definition.end_line;
+    let page = paginate_lines(all_lines, start, Some(end - start + 1));
+    let shown_end = start + page.output_lines - 1;
+    let mut output = format!(
+        "{path}: {} {} (lines {start}-{end})\n{}",
+        definition.label,
+        definition.qualified_name(language),
+        page.content
+    );
+    // Hitting the symbol's own end is not a truncation.
+    let stopped_by = page.stopped_by.filter(|_| shown_end < end);
+    if let Some(stop) = stopped_by {
+        output.push_str(&format!(
+            "\n\n[Truncated by {}: showing lines {start}-{shown_end} of {start}-{end}. Continue with offset={}.]",
+            stop.label(),
+            shown_end + 1
+        ));
+    }
+    text_result(
+        output,
+        json!({
+            "path": path,
+            "mode": "symbol",
+            "language": language.name(),
+            "symbol": definition.qualified_name(language),
+            "startLine": start,
+            "endLine": end,
+            "totalLines": all_lines.len(),
+            "outputLines": page.output_lines,
+            "truncated": stopped_by.is_some(),
+            "truncatedBy": stopped_by.map(ReadStop::key),
+        }),
+    )
+}
+
+/// Lines returned by one call when the caller does not pass a smaller `limit`.
+const MAX_READ_LINES: usize = 2000;
+/// Estimated tokens returned by one call, so a single read cannot flood the context window.
+const MAX_READ_TOKENS: usize = 20_000;
+/// Characters kept from a single line; minified or generated files otherwise defeat the budget.
+const MAX_LINE_CHARS: usize = 2000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ReadStop {
+    /// The caller's `limit` ended before the file did.
+    Limit,
+    Lines,
+    Tokens,
+}
+
+impl ReadStop {
+    fn key(self) -> &'static str {
+        match self {
+            Self::Limit => "limit",
+            Self::Lines => "lines",
+            Self::Tokens => "tokens",
+        }
+    }
+
+    fn label(self) -> String {
+        match self {
+            Self::Limit => "limit".to_string(),
+            Self::Lines => format!("{MAX_READ_LINES}-line page size"),
+            Self::Tokens => format!("~{MAX_READ_TOKENS} token budget"),
+        }
+    }
+}
+
+struct ReadPage {
+    content: String,
+    output_lines: usize,
+    estimated_tokens: usize,
+    truncated_long_lines: usize,
+    stopped_by: Option<ReadStop>,
+}
+
+/// Renders lines from the 1-based `offset` with `cat -n` style numbers, stopping at the first of
+/// the caller's `limit`, [`MAX_READ_LINES`] or [`MAX_READ_TOKENS`]. At least one line is always
+/// returned so pagination makes progress.
+fn paginate_lines(all_lines: &[&str], offset: usize, limit: Option<usize>) -> ReadPage {
+    let line_budget = limit.unwrap_or(MAX_READ_LINES).min(MAX_READ_LINES);
+    let number_width = all_lines.len().to_string().len().max(6);
+    let mut rendered = Vec::new();
+    let mut estimated_tokens = 0;
+    let mut truncated_long_lines = 0;
+    let mut stopped_by = None;
+
+    for (index, line) in all_lines.iter().enumerate().skip(offset - 1) {
+        if rendered.len() == line_budget {
+            stopped_by = Some(match limit {
+                Some(limit) if limit <= MAX_READ_LINES => ReadStop::Limit,
+                _ => ReadStop::Lines,
+            });
+            break;
+        }
+        let text = match line.char_indices().nth(MAX_LINE_CHARS) {
+            Some((cut, _)) => {
+                truncated_long_lines += 1;
+                format!("{}… [line truncated]", &line[..cut])
+            }
+            None => (*line).to_string(),
+        };
+        let numbered = format!("{:>number_width$}\t{text}", index + 1);
+        let tokens = estimate_tokens(&numbered);
+        if !rendered.is_empty() && estimated_tokens + tokens > MAX_READ_TOKENS {
+            stopped_by = Some(ReadStop::Tokens);
+            break;
+        }
+        estimated_tokens += tokens;
+        rendered.push(numbered);
+    }
+
+    ReadPage {
+        output_lines: rendered.len(),
+        content: rendered.join("\n"),
+        estimated_tokens,
+        truncated_long_lines,
+        stopped_by,
+    }
+}
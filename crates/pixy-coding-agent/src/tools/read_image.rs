@@ -0,0 +1,203 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageReader};
+use pixy_agent_core::{AgentTool, AgentToolArgs, AgentToolExecutor, AgentToolResult};
+use pixy_ai::{PiAiError, ToolResultContentBlock};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::common::{resolve_to_cwd, tool_execution_failed};
+
+/// Longest edge sent to the model; larger images are downscaled.
+const MAX_IMAGE_DIMENSION: u32 = 2000;
+/// Encoded size limit. Base64 inflates this by a third, which keeps payloads under 5 MB.
+const MAX_IMAGE_BYTES: usize = 3_750_000;
+const JPEG_QUALITIES: &[u8] = &[85, 70, 55, 40];
+
+pub fn create_read_image_tool(cwd: impl AsRef<Path>) -> AgentTool {
+    let cwd = cwd.as_ref().to_path_buf();
+    AgentTool {
+        name: "read_image".to_string(),
+        label: "read_image".to_string(),
+        description: "Read a local PNG, JPEG, GIF or WebP image (screenshots, diagrams, mockups) and attach it for visual inspection. Large images are downscaled and re-encoded to fit model limits."
+            .to_string(),
+        parameters: ReadImageArgs::schema(),
+        conflict_key: None,
+        execute: Arc::new(ReadImageToolExecutor { cwd }),
+    }
+}
+
+#[derive(Deserialize, AgentToolArgs)]
+struct ReadImageArgs {
+    /// Path to the image, absolute or relative to workspace cwd.
+    path: String,
+}
+
+struct ReadImageToolExecutor {
+    cwd: PathBuf,
+}
+
+#[async_trait]
+impl AgentToolExecutor for ReadImageToolExecutor {
+    async fn execute(
+        &self,
+        _tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        let ReadImageArgs { path } = ReadImageArgs::parse(args)?;
+        let absolute_path = resolve_to_cwd(&self.cwd, &path);
+        let bytes = fs::read(&absolute_path)
+            .map_err(|error| tool_execution_failed(format!("Failed to read {path}: {error}")))?;
+        let prepared = prepare_image(&bytes)
+            .map_err(|error| tool_execution_failed(format!("Failed to read {path}: {error}")))?;
+
+        let mut summary = format!(
+            "Read image {path} ({}x{}, {}, {} KB",
+            prepared.width,
+            prepared.height,
+            prepared.mime_type,
+            prepared.bytes.len().div_ceil(1024)
+        );
+        if prepared.resized {
+            summary.push_str(&format!(
+                ", downscaled from {}x{}",
+                prepared.original_width, prepared.original_height
+            ));
+        }
+        summary.push(')');
+
+        Ok(AgentToolResult {
+            content: vec![
+                ToolResultContentBlock::Text {
+                    text: summary,
+                    text_signature: None,
+                },
+                ToolResultContentBlock::Image {
+                    data: BASE64_STANDARD.encode(&prepared.bytes),
+                    mime_type: prepared.mime_type.to_string(),
+                },
+            ],
+            details: json!({
+                "path": path,
+                "mimeType": prepared.mime_type,
+                "width": prepared.width,
+                "height": prepared.height,
+                "originalWidth": prepared.original_width,
+                "originalHeight": prepared.original_height,
+                "bytes": prepared.bytes.len(),
+                "resized": prepared.resized,
+                "reencoded": prepared.reencoded,
+            }),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct PreparedImage {
+    bytes: Vec<u8>,
+    mime_type: &'static str,
+    width: u32,
+    height: u32,
+    original_width: u32,
+    original_height: u32,
+    resized: bool,
+    reencoded: bool,
+}
+
+/// Passes small images through untouched; otherwise downscales to [`MAX_IMAGE_DIMENSION`] and
+/// re-encodes (PNG when the image has transparency, JPEG otherwise) until it fits
+/// [`MAX_IMAGE_BYTES`].
+fn prepare_image(bytes: &[u8]) -> Result<PreparedImage, String> {
+    let reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|error| error.to_string())?;
+    let format = reader
+        .format()
+        .filter(|format| mime_type_for(*format).is_some())
+        .ok_or_else(|| "unsupported image format (expected PNG, JPEG, GIF or WebP)".to_string())?;
+    let (original_width, original_height) = reader
+        .into_dimensions()
+        .map_err(|error| format!("invalid image: {error}"))?;
+
+    if original_width.max(original_height) <= MAX_IMAGE_DIMENSION && bytes.len() <= MAX_IMAGE_BYTES
+    {
+        return Ok(PreparedImage {
+            bytes: bytes.to_vec(),
+            mime_type: mime_type_for(format).unwrap_or("image/png"),
+            width: original_width,
+            height: original_height,
+            original_width,
+            original_height,
+            resized: false,
+            reencoded: false,
+        });
+    }
+
+    let mut image =
+        image::load_from_memory_with_format(bytes, format).map_err(|error| error.to_string())?;
+    if original_width.max(original_height) > MAX_IMAGE_DIMENSION {
+        image = image.resize(
+            MAX_IMAGE_DIMENSION,
+            MAX_IMAGE_DIMENSION,
+            FilterType::Triangle,
+        );
+    }
+
+    loop {
+        let (width, height) = image.dimensions();
+        if let Some((bytes, mime_type)) = encode_within_limit(&image)? {
+            return Ok(PreparedImage {
+                bytes,
+                mime_type,
+                width,
+                height,
+                original_width,
+                original_height,
+                resized: (width, height) != (original_width, original_height),
+                reencoded: true,
+            });
+        }
+        if width.max(height) <= 64 {
+            return Err("image is too large to encode within the size limit".to_string());
+        }
+        image = image.resize(width * 3 / 4, height * 3 / 4, FilterType::Triangle);
+    }
+}
+
+fn encode_within_limit(image: &DynamicImage) -> Result<Option<(Vec<u8>, &'static str)>, String> {
+    if image.color().has_alpha() {
+        let mut png = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .map_err(|error| error.to_string())?;
+        return Ok((png.len() <= MAX_IMAGE_BYTES).then_some((png, "image/png")));
+    }
+
+    let rgb = image.to_rgb8();
+    for quality in JPEG_QUALITIES {
+        let mut jpeg = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, *quality)
+            .encode_image(&rgb)
+            .map_err(|error| error.to_string())?;
+        if jpeg.len() <= MAX_IMAGE_BYTES {
+            return Ok(Some((jpeg, "image/jpeg")));
+        }
+    }
+    Ok(None)
+}
+
+fn mime_type_for(format: ImageFormat) -> Option<&'static str> {
+    match format {
+        ImageFormat::Png => Some("image/png"),
+        ImageFormat::Jpeg => Some("image/jpeg"),
+        ImageFormat::Gif => Some("image/gif"),
+        ImageFormat::WebP => Some("image/webp"),
+        _ => None,
+    }
+}
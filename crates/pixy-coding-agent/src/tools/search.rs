@@ -0,0 +1,306 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use ignore::overrides::OverrideBuilder;
+use ignore::types::TypesBuilder;
+use ignore::WalkBuilder;
+use pixy_agent_core::{AgentTool, AgentToolArgs, AgentToolExecutor, AgentToolResult};
+use pixy_ai::PiAiError;
+use regex::RegexBuilder;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::common::{invalid_tool_args, resolve_to_cwd, text_result, tool_execution_failed};
+use super::ignore_rules::IgnoreWalkFilter;
+
+const DEFAULT_MAX_RESULTS: usize = 100;
+const MAX_LINE_CHARS: usize = 500;
+/// Files with a NUL byte in their first block are treated as binary and skipped, like ripgrep.
+const BINARY_PROBE_BYTES: usize = 8 * 1024;
+
+pub fn create_search_tool(cwd: impl AsRef<Path>) -> AgentTool {
+    let cwd = cwd.as_ref().to_path_buf();
+    AgentTool {
+        name: "search".to_string(),
+        label: "search".to_string(),
+        description: "Search file contents with a regular expression, ripgrep style, without shelling out to grep. Matches are returned as `path:line:text`, context lines as `path-line-text`, and `--` separates groups. Without a pattern, lists the files that pass the filters, which makes it a recursive glob. Narrow the search with `path`, `glob` (e.g. `*.rs`, `!tests/**`) and `type` (ripgrep type names such as `rust`, `py`, `ts`). Files excluded by .gitignore or .pixyignore are skipped unless includeIgnored is true; `.git` internals and binary files are always skipped."
+            .to_string(),
+        parameters: SearchArgs::schema(),
+        conflict_key: None,
+        execute: Arc::new(SearchToolExecutor { cwd }),
+    }
+}
+
+#[derive(Deserialize, AgentToolArgs)]
+#[serde(rename_all = "camelCase")]
+struct SearchArgs {
+    /// Regular expression (Rust regex syntax) to search for. Omit it to list matching files instead.
+    pattern: Option<String>,
+    /// File or directory to search, absolute or relative to workspace cwd. Defaults to the workspace root.
+    path: Option<String>,
+    /// Glob filters on paths relative to the searched directory; prefix with `!` to exclude.
+    #[serde(default)]
+    glob: Vec<String>,
+    /// Only search files of this ripgrep type, such as `rust`, `py`, `js`, `ts`, `go` or `md`.
+    #[serde(rename = "type")]
+    file_type: Option<String>,
+    /// Match case-insensitively. Defaults to false.
+    ignore_case: Option<bool>,
+    /// Treat the pattern as literal text instead of a regular expression. Defaults to false.
+    literal: Option<bool>,
+    /// Return only the paths of files that contain a match. Defaults to false.
+    files_only: Option<bool>,
+    /// Number of lines to show before and after each match. Defaults to 0.
+    #[tool(maximum = 10)]
+    context: Option<usize>,
+    /// Maximum number of matches (or files, when listing files) to return. Defaults to 100.
+    #[tool(minimum = 1, maximum = 1000)]
+    max_results: Option<usize>,
+    /// Also search files excluded by .gitignore or .pixyignore. Defaults to false.
+    include_ignored: Option<bool>,
+}
+
+struct SearchToolExecutor {
+    cwd: PathBuf,
+}
+
+#[async_trait]
+impl AgentToolExecutor for SearchToolExecutor {
+    async fn execute(
+        &self,
+        _tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        let args = SearchArgs::parse(args)?;
+        let cwd = self.cwd.clone();
+        tokio::task::spawn_blocking(move || execute_search(&cwd, args))
+            .await
+            .map_err(|error| tool_execution_failed(format!("search task failed: {error}")))?
+    }
+}
+
+fn execute_search(cwd: &Path, args: SearchArgs) -> Result<AgentToolResult, PiAiError> {
+    let requested_path = args.path.as_deref().unwrap_or_default().trim();
+    let target = if requested_path.is_empty() {
+        cwd.to_path_buf()
+    } else {
+        resolve_to_cwd(cwd, requested_path)
+    };
+    if !target.exists() {
+        return Ok(text_result(
+            format!("Path not found: {requested_path}"),
+            json!({
+                "path": requested_path,
+                "error": "not_found",
+            }),
+        ));
+    }
+
+    let matcher = args
+        .pattern
+        .as_deref()
+        .map(|pattern| {
+            let pattern = if args.literal.unwrap_or(false) {
+                regex::escape(pattern)
+            } else {
+                pattern.to_string()
+            };
+            RegexBuilder::new(&pattern)
+                .case_insensitive(args.ignore_case.unwrap_or(false))
+                .build()
+                .map_err(|error| invalid_tool_args(format!("Invalid `pattern`: {error}")))
+        })
+        .transpose()?;
+    let walker = build_walker(cwd, &target, &args)?;
+    let max_results = args.max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+    let context = args.context.unwrap_or(0);
+    let files_only = args.files_only.unwrap_or(false) || matcher.is_none();
+
+    let mut output = Vec::new();
+    let mut match_count = 0usize;
+    let mut file_count = 0usize;
+    let mut truncated = false;
+    for entry in walker.flatten() {
+        if !entry.file_type().is_some_and(|kind| kind.is_file()) {
+            continue;
+        }
+        let display = display_path(cwd, entry.path());
+        let Some(matcher) = &matcher else {
+            if file_count == max_results {
+                truncated = true;
+                break;
+            }
+            file_count += 1;
+            output.push(display);
+            continue;
+        };
+        let Some(content) = read_text(entry.path()) else {
+            continue;
+        };
+        let lines: Vec<&str> = content.lines().collect();
+        let matched: Vec<usize> = (0..lines.len())
+            .filter(|&index| matcher.is_match(lines[index]))
+            .collect();
+        if matched.is_empty() {
+            continue;
+        }
+        if files_only {
+            if file_count == max_results {
+                truncated = true;
+                break;
+            }
+            file_count += 1;
+            match_count += matched.len();
+            output.push(display);
+            continue;
+        }
+
+        if match_count == max_results {
+            truncated = true;
+            break;
+        }
+        let remaining = max_results - match_count;
+        if matched.len() > remaining {
+            truncated = true;
+        }
+        let matched = &matched[..matched.len().min(remaining)];
+        file_count += 1;
+        match_count += matched.len();
+        if !output.is_empty() && context > 0 {
+            output.push("--".to_string());
+        }
+        push_matches(&mut output, &display, &lines, matched, context);
+    }
+
+    let mut text = if output.is_empty() {
+        if matcher.is_some() {
+            "No matches found.".to_string()
+        } else {
+            "No files found.".to_string()
+        }
+    } else {
+        output.join("\n")
+    };
+    if truncated {
+        text.push_str(&format!(
+            "\n\n[Stopped after {max_results} {}; narrow the pattern, path, glob or type, or raise maxResults.]",
+            if files_only { "files" } else { "matches" }
+        ));
+    }
+    Ok(text_result(
+        text,
+        json!({
+            "path": requested_path,
+            "resolvedPath": target.display().to_string(),
+            "matchCount": match_count,
+            "fileCount": file_count,
+            "truncated": truncated,
+        }),
+    ))
+}
+
+/// Walks `target` with the same `.gitignore`/`.pixyignore` rules as `list_directory` and `read`,
+/// instead of the `ignore` crate's own filters (global gitignore, `.ignore`, parents above the
+/// repository), so every tool agrees on which files are visible.
+fn build_walker(cwd: &Path, target: &Path, args: &SearchArgs) -> Result<ignore::Walk, PiAiError> {
+    let mut builder = WalkBuilder::new(target);
+    builder
+        .standard_filters(false)
+        .sort_by_file_name(|left, right| left.cmp(right));
+    if args.include_ignored.unwrap_or(false) {
+        builder.filter_entry(|entry| entry.file_name() != ".git");
+    } else {
+        let ignore_filter = IgnoreWalkFilter::new(cwd);
+        builder.filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|kind| kind.is_dir());
+            !ignore_filter.is_ignored(entry.path(), is_dir)
+        });
+    }
+
+    if !args.glob.is_empty() {
+        let root = if target.is_dir() {
+            target
+        } else {
+            target.parent().unwrap_or(target)
+        };
+        let mut overrides = OverrideBuilder::new(root);
+        for glob in &args.glob {
+            overrides
+                .add(glob)
+                .map_err(|error| invalid_tool_args(format!("Invalid `glob` {glob}: {error}")))?;
+        }
+        builder.overrides(
+            overrides
+                .build()
+                .map_err(|error| invalid_tool_args(format!("Invalid `glob`: {error}")))?,
+        );
+    }
+    if let Some(file_type) = args.file_type.as_deref() {
+        let mut types = TypesBuilder::new();
+        types.add_defaults();
+        types.select(file_type);
+        let types = types
+            .build()
+            .map_err(|error| invalid_tool_args(format!("Invalid `type`: {error}")))?;
+        builder.types(types);
+    }
+    Ok(builder.build())
+}
+
+/// Appends `matched` lines (0-based) of one file with `context` lines around each; groups
+/// that are not adjacent are separated by `--` when context is shown.
+fn push_matches(
+    output: &mut Vec<String>,
+    display: &str,
+    lines: &[&str],
+    matched: &[usize],
+    context: usize,
+) {
+    let mut next_line = None;
+    for &index in matched {
+        let start = index
+            .saturating_sub(context)
+            .max(next_line.unwrap_or_default());
+        let end = (index + context + 1).min(lines.len());
+        if context > 0 && next_line.is_some_and(|next_line| start > next_line) {
+            output.push("--".to_string());
+        }
+        for (line, text) in lines.iter().enumerate().take(end).skip(start) {
+            let separator = if matched.binary_search(&line).is_ok() {
+                ':'
+            } else {
+                '-'
+            };
+            output.push(format!(
+                "{display}{separator}{}{separator}{}",
+                line + 1,
+                truncate_line(text)
+            ));
+        }
+        next_line = Some(end.max(next_line.unwrap_or_default()));
+    }
+}
+
+fn read_text(path: &Path) -> Option<String> {
+    let bytes = std::fs::read(path).ok()?;
+    if bytes[..bytes.len().min(BINARY_PROBE_BYTES)].contains(&0) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn truncate_line(line: &str) -> String {
+    if line.chars().count() <= MAX_LINE_CHARS {
+        return line.to_string();
+    }
+    let truncated: String = line.chars().take(MAX_LINE_CHARS).collect();
+    format!("{truncated}... [line truncated]")
+}
+
+fn display_path(cwd: &Path, path: &Path) -> String {
+    path.strip_prefix(cwd)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
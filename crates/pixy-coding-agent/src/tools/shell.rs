@@ -0,0 +1,491 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use pixy_agent_core::{
+    AgentTool, AgentToolArgs, AgentToolExecutor, AgentToolResult, ToolOutputKind, ToolOutputStream,
+};
+use pixy_ai::PiAiError;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::AsyncWriteExt;
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::Mutex;
+
+use super::bash::exit_status_line;
+use super::bash_background::kill_process;
+use super::common::{format_timeout, invalid_tool_args, text_result, tool_execution_failed};
+use super::pty::{Pty, PtyReader};
+
+const DEFAULT_COMMAND_TIMEOUT_SECS: f64 = 120.0;
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const SHELL_STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+/// Output kept per command; earlier bytes are dropped and counted.
+const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+const READ_CHUNK_BYTES: usize = 8 * 1024;
+
+/// The long-lived shell behind the `shell` tool, shared between the tool and the session that owns
+/// it so it is closed when the session ends. Every session, including each subagent run, gets its
+/// own. It starts with the first command, and is closed after sitting idle, on `reset`, and when
+/// a command times out.
+#[derive(Clone)]
+pub struct PersistentShell {
+    inner: Arc<Mutex<ShellState>>,
+    idle_timeout: Duration,
+}
+
+#[derive(Default)]
+struct ShellState {
+    process: Option<ShellProcess>,
+    /// Counts commands, so an idle timer only closes the shell when no command ran since.
+    commands: u64,
+    /// Why the previous shell went away, reported with the next command.
+    closed_because: Option<String>,
+}
+
+/// Bash reads the tool's scripts from a pipe, so commands cannot consume them, while its output
+/// and the commands' stdin, stdout and stderr are a pseudo-terminal.
+struct ShellProcess {
+    child: Child,
+    stdin: ChildStdin,
+    terminal: PtyReader,
+}
+
+enum CommandOutcome {
+    Finished {
+        exit_code: i32,
+        cwd: String,
+        output: Output,
+    },
+    /// The shell itself exited, e.g. after `exit`.
+    ShellExited {
+        code: Option<i32>,
+        output: Output,
+    },
+    TimedOut {
+        output: Output,
+    },
+}
+
+/// Command output capped at `MAX_OUTPUT_BYTES`, keeping the end.
+#[derive(Default)]
+struct Output {
+    bytes: Vec<u8>,
+    dropped: usize,
+}
+
+impl Output {
+    fn text(&self) -> String {
+        let text = String::from_utf8_lossy(&self.bytes);
+        let text = text.trim_end_matches('\n');
+        if self.dropped > 0 {
+            format!("[{} earlier bytes of output dropped]\n{text}", self.dropped)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+impl Default for PersistentShell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PersistentShell {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::default(),
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+        }
+    }
+
+    /// Closes the shell once it has been idle this long (30 minutes by default).
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Kills the shell and the processes it started. Returns whether one was running.
+    pub async fn close(&self) -> bool {
+        let mut state = self.inner.lock().await;
+        state.closed_because = None;
+        match state.process.take() {
+            Some(mut process) => {
+                kill_process(&mut process.child);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Starts a login shell on a new pseudo-terminal and waits until its profile has run,
+    /// dropping anything the profile printed.
+    async fn spawn(cwd: &Path) -> Result<ShellProcess, PiAiError> {
+        let start_failed = |error: std::io::Error| {
+            tool_execution_failed(format!("Failed to start shell: {error}"))
+        };
+        let pty = Pty::open().map_err(start_failed)?;
+        let mut process = Command::new("bash");
+        process
+            .arg("-l")
+            .arg("-s")
+            .current_dir(cwd)
+            .env("TERM", "dumb")
+            .env("PAGER", "cat")
+            .env("GIT_PAGER", "cat")
+            .stdin(Stdio::piped())
+            .kill_on_drop(true);
+        pty.attach(&mut process).map_err(start_failed)?;
+
+        let mut child = process.spawn().map_err(start_failed)?;
+        let terminal = pty.into_reader().map_err(start_failed)?;
+        let Some(stdin) = child.stdin.take() else {
+            return Err(tool_execution_failed("Shell stdin was not captured"));
+        };
+        let mut shell = ShellProcess {
+            child,
+            stdin,
+            terminal,
+        };
+        match shell.run(":", SHELL_STARTUP_TIMEOUT, None).await? {
+            CommandOutcome::Finished { .. } => Ok(shell),
+            CommandOutcome::ShellExited { output, .. } | CommandOutcome::TimedOut { output } => {
+                kill_process(&mut shell.child);
+                Err(tool_execution_failed(format!(
+                    "Failed to start shell: {}",
+                    output.text()
+                )))
+            }
+        }
+    }
+
+    /// Closes the shell after `idle_timeout` unless another command ran in the meantime.
+    fn schedule_idle_close(&self, commands: u64) {
+        let inner: Weak<Mutex<ShellState>> = Arc::downgrade(&self.inner);
+        let idle_timeout = self.idle_timeout;
+        tokio::spawn(async move {
+            tokio::time::sleep(idle_timeout).await;
+            let Some(inner) = inner.upgrade() else {
+                return;
+            };
+            // A locked shell is running a command, so it is not idle.
+            let Ok(mut state) = inner.try_lock() else {
+                return;
+            };
+            if state.commands != commands {
+                return;
+            }
+            if let Some(mut process) = state.process.take() {
+                kill_process(&mut process.child);
+                state.closed_because = Some(format!(
+                    "the previous shell was closed after {} idle",
+                    format_duration(idle_timeout)
+                ));
+            }
+        });
+    }
+}
+
+impl ShellProcess {
+    /// Runs `command` through `eval`, so syntax errors are reported instead of breaking the
+    /// shell, with stdin from the terminal. A marker line then reports the exit code and working
+    /// directory.
+    async fn run(
+        &mut self,
+        command: &str,
+        timeout: Duration,
+        stream: Option<&ToolOutputStream>,
+    ) -> Result<CommandOutcome, PiAiError> {
+        let nonce = format!(
+            "{:x}{:x}",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        );
+        let marker = format!("__PIXY_DONE_{nonce}__");
+        let script = format!(
+            "IFS= read -r -d '' __pixy_command <<'__PIXY_EOF_{nonce}'\n{command}\n__PIXY_EOF_{nonce}\neval \"$__pixy_command\" </dev/tty\n__pixy_status=$?\nunset __pixy_command\nprintf '\\n{marker} %d %s\\n' \"$__pixy_status\" \"$PWD\"\n"
+        );
+        let sent = match self.stdin.write_all(script.as_bytes()).await {
+            Ok(()) => self.stdin.flush().await,
+            Err(error) => Err(error),
+        };
+        sent.map_err(|error| tool_execution_failed(format!("Failed to send command: {error}")))?;
+
+        let mut output = Output::default();
+        let mut pending = Vec::new();
+        let read = async {
+            let mut chunk = vec![0; READ_CHUNK_BYTES];
+            loop {
+                let read = self.terminal.read(&mut chunk).await?;
+                if read == 0 {
+                    return Ok::<_, std::io::Error>(None);
+                }
+                pending.extend_from_slice(&chunk[..read]);
+                if let Some(done) = take_marker(&mut pending, &marker, &mut output, stream) {
+                    return Ok(Some(done));
+                }
+            }
+        };
+        match tokio::time::timeout(timeout, read).await {
+            Ok(Ok(Some((exit_code, cwd)))) => Ok(CommandOutcome::Finished {
+                exit_code,
+                cwd,
+                output,
+            }),
+            Ok(Ok(None)) => {
+                flush_pending(&mut pending, &mut output, stream);
+                let code = self
+                    .child
+                    .wait()
+                    .await
+                    .ok()
+                    .and_then(|status| status.code());
+                Ok(CommandOutcome::ShellExited { code, output })
+            }
+            Ok(Err(error)) => Err(tool_execution_failed(format!(
+                "Failed to read shell output: {error}"
+            ))),
+            Err(_) => {
+                flush_pending(&mut pending, &mut output, stream);
+                Ok(CommandOutcome::TimedOut { output })
+            }
+        }
+    }
+}
+
+/// Moves complete lines from `pending` to `output`, streaming them, until the marker line is
+/// found; returns its exit code and working directory.
+fn take_marker(
+    pending: &mut Vec<u8>,
+    marker: &str,
+    output: &mut Output,
+    stream: Option<&ToolOutputStream>,
+) -> Option<(i32, String)> {
+    while let Some(end) = pending.iter().position(|byte| *byte == b'\n') {
+        let line = pending.drain(..=end).collect::<Vec<_>>();
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim_end_matches(['\n', '\r']);
+        if let Some(rest) = text.strip_prefix(marker) {
+            let (code, cwd) = rest.trim_start().split_once(' ').unwrap_or((rest, ""));
+            // The marker is printed after a line break that is not part of the output.
+            if output.bytes.last() == Some(&b'\n') {
+                output.bytes.pop();
+            }
+            return Some((code.trim().parse().unwrap_or(-1), cwd.to_string()));
+        }
+        if let Some(stream) = stream {
+            stream.line(ToolOutputKind::Stdout, text);
+        }
+        push_capped(output, &line);
+    }
+    None
+}
+
+fn flush_pending(pending: &mut Vec<u8>, output: &mut Output, stream: Option<&ToolOutputStream>) {
+    if pending.is_empty() {
+        return;
+    }
+    if let Some(stream) = stream {
+        stream.line(ToolOutputKind::Stdout, &String::from_utf8_lossy(pending));
+    }
+    let bytes = std::mem::take(pending);
+    push_capped(output, &bytes);
+}
+
+fn push_capped(output: &mut Output, bytes: &[u8]) {
+    output.bytes.extend_from_slice(bytes);
+    if output.bytes.len() > MAX_OUTPUT_BYTES {
+        let excess = output.bytes.len() - MAX_OUTPUT_BYTES;
+        output.bytes.drain(..excess);
+        output.dropped += excess;
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 60 && seconds.is_multiple_of(60) {
+        format!("{} minutes", seconds / 60)
+    } else {
+        format!("{seconds} seconds")
+    }
+}
+
+pub fn create_shell_tool(cwd: impl AsRef<Path>, shell: PersistentShell) -> AgentTool {
+    let cwd = cwd.as_ref().to_path_buf();
+    AgentTool {
+        name: "shell".to_string(),
+        label: "shell".to_string(),
+        description: "Run a command in a persistent bash session that keeps its state between calls: `cd`, exported variables, activated virtualenvs and shell functions carry over. Commands run on a pseudo-terminal (`TERM=dumb`), so programs that need a TTY work; returns the terminal output, capped to the last 64 KB. Nobody types into the terminal: a command waiting for keyboard input (a prompt, `cat` without a file) blocks until the timeout, so pass non-interactive flags or pipe input in. A command that times out kills the session, as does `reset`; the next command then starts a fresh shell in the workspace. The session also closes after 30 minutes idle. Use `bash` for independent one-off commands and `bash_background` for servers and watchers."
+            .to_string(),
+        parameters: ShellArgs::schema(),
+        // Commands share one shell, so they must run one at a time and in order.
+        conflict_key: Some(Arc::new(|_: &Value| vec!["shell".to_string()])),
+        execute: Arc::new(ShellToolExecutor { cwd, shell }),
+    }
+}
+
+#[derive(Deserialize, AgentToolArgs)]
+struct ShellArgs {
+    /// Command to run in the persistent shell.
+    command: Option<String>,
+    /// Kill the current shell first, discarding its state. Pass it alone to just reset. Defaults to false.
+    reset: Option<bool>,
+    /// Timeout in seconds (default 120). A command that times out kills the shell.
+    #[tool(exclusive_minimum = 0)]
+    timeout: Option<f64>,
+}
+
+struct ShellToolExecutor {
+    cwd: PathBuf,
+    shell: PersistentShell,
+}
+
+#[async_trait]
+impl AgentToolExecutor for ShellToolExecutor {
+    async fn execute(
+        &self,
+        _tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        let ShellArgs {
+            command,
+            reset,
+            timeout,
+        } = ShellArgs::parse(args)?;
+        let command = command.filter(|command| !command.trim().is_empty());
+        let reset = reset.unwrap_or(false);
+        if command.is_none() && !reset {
+            return Err(invalid_tool_args("Pass a `command`, or `reset: true`"));
+        }
+        if reset {
+            self.shell.close().await;
+        }
+        let Some(command) = command else {
+            return Ok(text_result(
+                format!(
+                    "Shell reset; the next command starts a fresh shell in {}.",
+                    self.cwd.display()
+                ),
+                json!({ "reset": true }),
+            ));
+        };
+        if !self.cwd.exists() {
+            return Err(tool_execution_failed(format!(
+                "Working directory does not exist: {}",
+                self.cwd.display()
+            )));
+        }
+        let timeout_seconds = timeout.unwrap_or(DEFAULT_COMMAND_TIMEOUT_SECS);
+
+        let mut state = self.shell.inner.lock().await;
+        let mut notes = Vec::new();
+        let mut process = match state.process.take() {
+            Some(process) => process,
+            None => {
+                if let Some(reason) = state.closed_because.take() {
+                    notes.push(format!(
+                        "[Note: {reason}; started a new one in {}.]",
+                        self.cwd.display()
+                    ));
+                }
+                PersistentShell::spawn(&self.cwd).await?
+            }
+        };
+        // If this call is dropped mid-command, the shell is dropped (and killed) with it.
+        state.closed_because =
+            Some("the previous command was interrupted, which closed its shell".to_string());
+        state.commands += 1;
+        let commands = state.commands;
+
+        let stream = ToolOutputStream::current();
+        let outcome = process
+            .run(
+                &command,
+                Duration::from_secs_f64(timeout_seconds),
+                stream.as_ref(),
+            )
+            .await;
+        state.closed_because = None;
+        let outcome = match outcome {
+            Ok(outcome) => outcome,
+            Err(error) => {
+                kill_process(&mut process.child);
+                return Err(error);
+            }
+        };
+
+        let (exit_code, cwd, output) = match outcome {
+            CommandOutcome::Finished {
+                exit_code,
+                cwd,
+                output,
+            } => {
+                state.process = Some(process);
+                drop(state);
+                self.shell.schedule_idle_close(commands);
+                (exit_code, cwd, output)
+            }
+            CommandOutcome::ShellExited { code, output } => {
+                let mut text = with_notes(&notes, output.text());
+                let status = match code {
+                    Some(code) => format!("Shell exited with code {code}"),
+                    None => "Shell exited".to_string(),
+                };
+                text.push_str(&format!(
+                    "\n\n{status}; the next command starts a fresh shell in {}.",
+                    self.cwd.display()
+                ));
+                return Err(tool_execution_failed(text)
+                    .with_details(json!({ "exitCode": code, "shellExited": true })));
+            }
+            CommandOutcome::TimedOut { output } => {
+                kill_process(&mut process.child);
+                let message = format!(
+                    "Command timed out after {} seconds; the shell was killed, so its state (working directory, variables) is lost. Use bash_background for long-running commands.",
+                    format_timeout(timeout_seconds)
+                );
+                if let Some(stream) = &stream {
+                    stream.line(ToolOutputKind::Status, &message);
+                }
+                let text = with_notes(&notes, format!("{}\n\n{message}", output.text()));
+                return Err(tool_execution_failed(text)
+                    .with_details(json!({ "timedOut": true, "droppedBytes": output.dropped })));
+            }
+        };
+
+        let mut text = output.text();
+        if text.is_empty() {
+            text = "(no output)".to_string();
+        }
+        if Path::new(&cwd) != self.cwd {
+            text.push_str(&format!("\n\n[cwd: {cwd}]"));
+        }
+        let mut text = with_notes(&notes, text);
+        let details = json!({
+            "exitCode": exit_code,
+            "cwd": cwd,
+            "totalBytes": output.bytes.len() + output.dropped,
+            "droppedBytes": output.dropped,
+        });
+        if exit_code != 0 {
+            let status = exit_status_line(Some(exit_code));
+            if let Some(stream) = &stream {
+                stream.line(ToolOutputKind::Status, &status);
+            }
+            text.push_str("\n\n");
+            text.push_str(&status);
+            return Err(tool_execution_failed(text).with_details(details));
+        }
+        Ok(text_result(text, details))
+    }
+}
+
+fn with_notes(notes: &[String], text: String) -> String {
+    if notes.is_empty() {
+        return text;
+    }
+    format!("{}\n{text}", notes.join("\n"))
+}
@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use pixy_agent_core::{AgentTool, AgentToolExecutor, AgentToolResult};
+use pixy_ai::{Message, PiAiError};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::common::{invalid_tool_args, text_result};
+
+pub(crate) const TODO_TOOL_NAME: &str = "todo";
+const MAX_TODO_ITEMS: usize = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoStatus {
+    Pending,
+    InProgress,
+    Done,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoItem {
+    pub content: String,
+    pub status: TodoStatus,
+}
+
+/// The todo list is stateless on the tool side: every call replaces the whole list and the
+/// result details carry it, so the session file is the source of truth and branches, forks and
+/// `/resume` restore the list that was current at that point.
+pub fn create_todo_tool() -> AgentTool {
+    AgentTool {
+        name: TODO_TOOL_NAME.to_string(),
+        label: TODO_TOOL_NAME.to_string(),
+        description: "Maintain a structured task list for multi-step work. Each call replaces the whole list; keep exactly one item `in_progress` while working and mark items `done` as soon as they are finished."
+            .to_string(),
+        parameters: json!({
+            "type": "object",
+            "properties": {
+                "todos": {
+                    "type": "array",
+                    "description": "The complete, updated task list.",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "content": { "type": "string", "description": "Short imperative task description." },
+                            "status": { "type": "string", "enum": ["pending", "in_progress", "done"] }
+                        },
+                        "required": ["content", "status"],
+                        "additionalProperties": false
+                    }
+                }
+            },
+            "required": ["todos"],
+            "additionalProperties": false
+        }),
+        conflict_key: None,
+        execute: Arc::new(TodoToolExecutor),
+    }
+}
+
+struct TodoToolExecutor;
+
+#[async_trait]
+impl AgentToolExecutor for TodoToolExecutor {
+    async fn execute(
+        &self,
+        _tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        let todos = parse_todos(&args)?;
+        Ok(text_result(render_todos(&todos), json!({ "todos": todos })))
+    }
+}
+
+fn parse_todos(args: &Value) -> Result<Vec<TodoItem>, PiAiError> {
+    let raw = args
+        .get("todos")
+        .cloned()
+        .ok_or_else(|| invalid_tool_args("Missing `todos` array"))?;
+    let todos = serde_json::from_value::<Vec<TodoItem>>(raw)
+        .map_err(|error| invalid_tool_args(format!("Invalid `todos`: {error}")))?;
+    if todos.len() > MAX_TODO_ITEMS {
+        return Err(invalid_tool_args(format!(
+            "Too many todos ({}); keep the list under {MAX_TODO_ITEMS} items",
+            todos.len()
+        )));
+    }
+    let todos = todos
+        .into_iter()
+        .map(|item| TodoItem {
+            content: item.content.trim().to_string(),
+            status: item.status,
+        })
+        .collect::<Vec<_>>();
+    if todos.iter().any(|item| item.content.is_empty()) {
+        return Err(invalid_tool_args("Todo `content` must not be empty"));
+    }
+    Ok(todos)
+}
+
+/// Checklist text returned to the model, e.g. `[x] Add parser` / `[~] Wire CLI` / `[ ] Docs`.
+fn render_todos(todos: &[TodoItem]) -> String {
+    if todos.is_empty() {
+        return "Todo list cleared.".to_string();
+    }
+    let done = todos
+        .iter()
+        .filter(|item| item.status == TodoStatus::Done)
+        .count();
+    let mut lines = vec![format!("Todos ({done}/{} done):", todos.len())];
+    for item in todos {
+        let marker = match item.status {
+            TodoStatus::Pending => "[ ]",
+            TodoStatus::InProgress => "[~]",
+            TodoStatus::Done => "[x]",
+        };
+        lines.push(format!("{marker} {}", item.content));
+    }
+    lines.join("\n")
+}
+
+/// Todo list carried by a successful `todo` tool result.
+pub(crate) fn todos_from_tool_result(
+    tool_name: &str,
+    details: Option<&Value>,
+    is_error: bool,
+) -> Option<Vec<TodoItem>> {
+    if tool_name != TODO_TOOL_NAME || is_error {
+        return None;
+    }
+    serde_json::from_value(details?.get("todos")?.clone()).ok()
+}
+
+/// Latest todo list recorded in `messages`, or an empty list when the model never set one.
+pub fn todos_from_messages(messages: &[Message]) -> Vec<TodoItem> {
+    messages
+        .iter()
+        .rev()
+        .find_map(|message| match message {
+            Message::ToolResult {
+                tool_name,
+                details,
+                is_error,
+                ..
+            } => todos_from_tool_result(tool_name, details.as_ref(), *is_error),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,423 @@
+//! `web_fetch`: GET a page and reduce it to readable text.
+//!
+//! Redirects are followed by hand so robots.txt is checked for every URL the tool requests,
+//! not only the first one.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use pixy_agent_core::{AgentTool, AgentToolArgs, AgentToolExecutor, AgentToolResult};
+use pixy_ai::PiAiError;
+use regex::Regex;
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::common::{invalid_tool_args, text_result, tool_execution_failed};
+use super::html_text::extract_readable_text;
+
+/// Token matched against `User-agent` lines of robots.txt.
+const ROBOTS_AGENT: &str = "pixy";
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+const ROBOTS_TIMEOUT: Duration = Duration::from_secs(10);
+/// Bodies are read up to this size; the rest is dropped.
+const MAX_DOWNLOAD_BYTES: usize = 5 * 1024 * 1024;
+const MAX_ROBOTS_BYTES: usize = 512 * 1024;
+const DEFAULT_MAX_CHARS: usize = 20_000;
+/// Files with a NUL byte in their first block are treated as binary, like in `search`.
+const BINARY_PROBE_BYTES: usize = 8 * 1024;
+const MAX_REDIRECTS: usize = 10;
+
+fn user_agent() -> String {
+    format!(
+        "{ROBOTS_AGENT}/{} (+https://github.com/sundy-li/pixy)",
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+pub(super) fn http_client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent(user_agent())
+        .timeout(timeout)
+        .build()
+        .unwrap_or_default()
+}
+
+pub fn create_web_fetch_tool() -> AgentTool {
+    AgentTool {
+        name: "web_fetch".to_string(),
+        label: "web_fetch".to_string(),
+        description: "Fetch a web page or document over HTTP(S) GET. HTML is reduced to its main readable content as markdown-like text (headings, lists, links), dropping navigation, scripts and page chrome; plain text and JSON are returned as is. Output is capped by maxChars. Pages that the site's robots.txt disallows are not fetched."
+            .to_string(),
+        parameters: WebFetchArgs::schema(),
+        conflict_key: None,
+        execute: Arc::new(WebFetchToolExecutor {
+            client: http_client(FETCH_TIMEOUT),
+            page_client: reqwest::Client::builder()
+                .user_agent(user_agent())
+                .timeout(FETCH_TIMEOUT)
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .unwrap_or_default(),
+            robots: Mutex::default(),
+        }),
+    }
+}
+
+#[derive(Deserialize, AgentToolArgs)]
+#[serde(rename_all = "camelCase")]
+struct WebFetchArgs {
+    /// Absolute http or https URL to fetch.
+    url: String,
+    /// Maximum number of characters of text to return. Defaults to 20000.
+    #[tool(minimum = 1, maximum = 200000)]
+    max_chars: Option<usize>,
+    /// Return the HTML source instead of the extracted text. Defaults to false.
+    raw: Option<bool>,
+}
+
+struct WebFetchToolExecutor {
+    /// Fetches robots.txt, following redirects.
+    client: reqwest::Client,
+    /// Fetches pages without following redirects, so each hop passes the robots.txt check.
+    page_client: reqwest::Client,
+    /// Parsed robots.txt per origin, fetched once per session.
+    robots: Mutex<HashMap<String, Arc<RobotsRules>>>,
+}
+
+#[async_trait]
+impl AgentToolExecutor for WebFetchToolExecutor {
+    async fn execute(
+        &self,
+        _tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        let args = WebFetchArgs::parse(args)?;
+        let url = Url::parse(args.url.trim())
+            .map_err(|error| invalid_tool_args(format!("Invalid `url`: {error}")))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(invalid_tool_args(format!(
+                "Invalid `url`: only http and https are supported, got {}",
+                url.scheme()
+            )));
+        }
+
+        let mut response = self.fetch_following_redirects(&url).await?;
+        let status = response.status();
+        let final_url = response.url().clone();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let (body, download_truncated) = read_body(&mut response, MAX_DOWNLOAD_BYTES)
+            .await
+            .map_err(|error| tool_execution_failed(format!("Failed to read {url}: {error}")))?;
+
+        let kind = body_kind(&content_type, &body);
+        if kind == BodyKind::Binary {
+            return Err(tool_execution_failed(format!(
+                "{final_url} returned binary content ({}), which web_fetch cannot show",
+                if content_type.is_empty() {
+                    "no content type"
+                } else {
+                    content_type.as_str()
+                }
+            ))
+            .with_details(json!({
+                "url": url.as_str(),
+                "finalUrl": final_url.as_str(),
+                "status": status.as_u16(),
+                "contentType": content_type,
+                "reason": "unsupported_content_type",
+            })));
+        }
+        let source = String::from_utf8_lossy(&body);
+        let (title, text) = if kind == BodyKind::Html && !args.raw.unwrap_or(false) {
+            let page = extract_readable_text(&source, Some(&final_url));
+            (page.title, page.text)
+        } else {
+            (None, source.into_owned())
+        };
+
+        let total_chars = text.chars().count();
+        let max_chars = args.max_chars.unwrap_or(DEFAULT_MAX_CHARS);
+        let truncated = total_chars > max_chars;
+        let mut output = String::new();
+        if let Some(title) = &title {
+            output.push_str(&format!("Title: {title}\n"));
+        }
+        output.push_str(&format!("URL: {final_url}\n\n"));
+        output.extend(text.chars().take(max_chars));
+        if truncated {
+            output.push_str(&format!(
+                "\n\n[Showing the first {max_chars} of {total_chars} characters; raise maxChars to see more.]"
+            ));
+        }
+        if download_truncated {
+            output.push_str(&format!(
+                "\n\n[Download stopped at {} MB.]",
+                MAX_DOWNLOAD_BYTES / (1024 * 1024)
+            ));
+        }
+
+        if !status.is_success() {
+            return Err(
+                tool_execution_failed(format!("HTTP {status} fetching {url}\n\n{output}"))
+                    .with_details(json!({
+                        "url": url.as_str(),
+                        "finalUrl": final_url.as_str(),
+                        "status": status.as_u16(),
+                        "reason": "http_status",
+                    })),
+            );
+        }
+        Ok(text_result(
+            output,
+            json!({
+                "url": url.as_str(),
+                "finalUrl": final_url.as_str(),
+                "status": status.as_u16(),
+                "contentType": content_type,
+                "title": title,
+                "bytes": body.len(),
+                "chars": total_chars,
+                "truncated": truncated || download_truncated,
+            }),
+        ))
+    }
+}
+
+impl WebFetchToolExecutor {
+    /// GETs `url` and every redirect it leads to, refusing any hop that robots.txt disallows.
+    async fn fetch_following_redirects(&self, url: &Url) -> Result<reqwest::Response, PiAiError> {
+        let mut target = url.clone();
+        for _ in 0..=MAX_REDIRECTS {
+            self.ensure_robots_allow(&target).await?;
+            let response = self
+                .page_client
+                .get(target.clone())
+                .send()
+                .await
+                .map_err(|error| {
+                    tool_execution_failed(format!("Failed to fetch {target}: {error}"))
+                })?;
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|value| value.to_str().ok());
+            let Some(location) = location.filter(|_| response.status().is_redirection()) else {
+                return Ok(response);
+            };
+            target = target.join(location).map_err(|error| {
+                tool_execution_failed(format!(
+                    "{target} redirected to an invalid location {location}: {error}"
+                ))
+            })?;
+            if !matches!(target.scheme(), "http" | "https") {
+                return Err(tool_execution_failed(format!(
+                    "{url} redirected to unsupported URL {target}"
+                )));
+            }
+        }
+        Err(
+            tool_execution_failed(format!("{url} redirected more than {MAX_REDIRECTS} times"))
+                .with_details(json!({
+                    "url": url.as_str(),
+                    "reason": "too_many_redirects",
+                })),
+        )
+    }
+
+    async fn ensure_robots_allow(&self, url: &Url) -> Result<(), PiAiError> {
+        let robots = self.robots_for(url).await;
+        if robots.allows(&path_and_query(url)) {
+            return Ok(());
+        }
+        Err(tool_execution_failed(format!(
+            "robots.txt of {} disallows fetching {url}",
+            url.origin().ascii_serialization()
+        ))
+        .with_details(json!({
+            "url": url.as_str(),
+            "reason": "robots_disallowed",
+        })))
+    }
+
+    async fn robots_for(&self, url: &Url) -> Arc<RobotsRules> {
+        let origin = url.origin().ascii_serialization();
+        if let Some(rules) = self.robots.lock().expect("robots cache").get(&origin) {
+            return rules.clone();
+        }
+        let rules = Arc::new(fetch_robots(&self.client, &origin).await);
+        self.robots
+            .lock()
+            .expect("robots cache")
+            .insert(origin, rules.clone());
+        rules
+    }
+}
+
+/// Rules for `origin`; a missing or unreachable robots.txt allows everything.
+async fn fetch_robots(client: &reqwest::Client, origin: &str) -> RobotsRules {
+    let response = client
+        .get(format!("{origin}/robots.txt"))
+        .timeout(ROBOTS_TIMEOUT)
+        .send()
+        .await;
+    let Ok(mut response) = response else {
+        return RobotsRules::default();
+    };
+    if !response.status().is_success() {
+        return RobotsRules::default();
+    }
+    match read_body(&mut response, MAX_ROBOTS_BYTES).await {
+        Ok((body, _)) => RobotsRules::parse(&String::from_utf8_lossy(&body), ROBOTS_AGENT),
+        Err(_) => RobotsRules::default(),
+    }
+}
+
+/// Reads at most `limit` bytes of the body; the flag tells whether the rest was dropped.
+async fn read_body(
+    response: &mut reqwest::Response,
+    limit: usize,
+) -> Result<(Vec<u8>, bool), reqwest::Error> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        let room = limit - body.len();
+        if chunk.len() > room {
+            body.extend_from_slice(&chunk[..room]);
+            return Ok((body, true));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok((body, false))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum BodyKind {
+    Html,
+    Text,
+    Binary,
+}
+
+fn body_kind(content_type: &str, body: &[u8]) -> BodyKind {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    if mime.contains("html") {
+        return BodyKind::Html;
+    }
+    let textual = mime.starts_with("text/")
+        || ["json", "xml", "javascript", "yaml", "toml"]
+            .iter()
+            .any(|kind| mime.contains(kind));
+    if textual {
+        return BodyKind::Text;
+    }
+    if body[..body.len().min(BINARY_PROBE_BYTES)].contains(&0) || !mime.is_empty() {
+        return BodyKind::Binary;
+    }
+    let head = String::from_utf8_lossy(&body[..body.len().min(1024)]).to_ascii_lowercase();
+    if head.contains("<html") || head.trim_start().starts_with("<!doctype html") {
+        BodyKind::Html
+    } else {
+        BodyKind::Text
+    }
+}
+
+fn path_and_query(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{query}", url.path()),
+        None => url.path().to_string(),
+    }
+}
+
+type RobotsGroup = (Vec<String>, Vec<(bool, String)>);
+
+/// `Allow`/`Disallow` rules of the robots.txt group that applies to one user agent.
+#[derive(Debug, Default)]
+struct RobotsRules {
+    /// Patterns with their length, which decides precedence, and whether they allow.
+    rules: Vec<(Regex, usize, bool)>,
+}
+
+impl RobotsRules {
+    /// Uses the groups naming `agent`, or the `*` groups when none does.
+    fn parse(body: &str, agent: &str) -> Self {
+        // User agents of each group with its (allow, pattern) rules.
+        let mut groups: Vec<RobotsGroup> = Vec::new();
+        let mut in_rules = true;
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        groups.push((Vec::new(), Vec::new()));
+                        in_rules = false;
+                    }
+                    if let Some((agents, _)) = groups.last_mut() {
+                        agents.push(value.to_ascii_lowercase());
+                    }
+                }
+                key @ ("allow" | "disallow") => {
+                    in_rules = true;
+                    if let Some((_, rules)) = groups.last_mut() {
+                        rules.push((key == "allow", value.to_string()));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let agent = agent.to_ascii_lowercase();
+        let named = |wanted: &str| {
+            groups
+                .iter()
+                .filter(|(agents, _)| agents.iter().any(|name| name == wanted))
+                .flat_map(|(_, rules)| rules.iter())
+                .collect::<Vec<_>>()
+        };
+        let mut selected = named(&agent);
+        if selected.is_empty() {
+            selected = named("*");
+        }
+        Self {
+            rules: selected
+                .into_iter()
+                .filter(|(_, pattern)| !pattern.is_empty())
+                .filter_map(|(allow, pattern)| {
+                    robots_pattern(pattern).map(|regex| (regex, pattern.len(), *allow))
+                })
+                .collect(),
+        }
+    }
+
+    /// The longest matching pattern decides; `Allow` wins ties.
+    fn allows(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(regex, _, _)| regex.is_match(path))
+            .max_by_key(|(_, length, allow)| (*length, *allow))
+            .is_none_or(|(_, _, allow)| *allow)
+    }
+}
+
+/// Regex for a robots.txt path pattern, where `*` matches anything and a final `$` anchors.
+fn robots_pattern(pattern: &str) -> Option<Regex> {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let body = pattern
+        .split('*')
+        .map(regex::escape)
+        .collect::<Vec<_>>()
+        .join(".*");
+    Regex::new(&format!("^{body}{}", if anchored { "$" } else { "" })).ok()
+}
@@ -0,0 +1,333 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use pixy_agent_core::{AgentTool, AgentToolArgs, AgentToolExecutor, AgentToolResult};
+use pixy_ai::PiAiError;
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::common::{text_result, tool_execution_failed};
+use super::html_text::strip_tags;
+use super::web_fetch::http_client;
+
+const DEFAULT_RESULT_COUNT: usize = 5;
+const DEFAULT_SEARCH_TIMEOUT_MS: u64 = 15_000;
+const BRAVE_ENDPOINT: &str = "https://api.search.brave.com/res/v1/web/search";
+const BING_ENDPOINT: &str = "https://api.bing.microsoft.com/v7.0/search";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebSearchBackendKind {
+    Searxng,
+    Brave,
+    Bing,
+}
+
+impl WebSearchBackendKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Searxng => "searxng",
+            Self::Brave => "brave",
+            Self::Bing => "bing",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct WebSearchConfig {
+    /// Search service queried by `web_search`; the tool is left out when unset.
+    #[serde(default)]
+    pub backend: Option<WebSearchBackendKind>,
+    /// SearxNG instance, required for `searxng`; overrides the API endpoint of the others.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// API key of `brave` and `bing`; `$NAME` values are read from `[env]` or the environment.
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Limit for each search request; defaults to 15 seconds.
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+impl WebSearchConfig {
+    /// Configuration picked from `SEARXNG_URL`, `BRAVE_API_KEY` or `BING_SEARCH_API_KEY`, in that
+    /// order.
+    pub fn from_env() -> Option<Self> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Like [`WebSearchConfig::from_env`], reading variables through `var`.
+    pub(crate) fn from_vars(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let var = |name: &str| var(name).filter(|value| !value.trim().is_empty());
+        let (backend, url, api_key) = if let Some(url) = var("SEARXNG_URL") {
+            (WebSearchBackendKind::Searxng, Some(url), None)
+        } else if let Some(key) = var("BRAVE_API_KEY") {
+            (WebSearchBackendKind::Brave, None, Some(key))
+        } else {
+            (
+                WebSearchBackendKind::Bing,
+                None,
+                Some(var("BING_SEARCH_API_KEY")?),
+            )
+        };
+        Some(Self {
+            backend: Some(backend),
+            url,
+            api_key,
+            timeout_ms: None,
+        })
+    }
+
+    /// The configured backend, `Ok(None)` when none is set, or why the settings are unusable.
+    pub fn build_backend(&self) -> Result<Option<Arc<dyn WebSearchBackend>>, String> {
+        let Some(kind) = self.backend else {
+            return Ok(None);
+        };
+        let client = http_client(Duration::from_millis(
+            self.timeout_ms.unwrap_or(DEFAULT_SEARCH_TIMEOUT_MS),
+        ));
+        let endpoint = |url: &str| {
+            Url::parse(url).map_err(|error| format!("invalid web_search url `{url}`: {error}"))
+        };
+        let api_key = || {
+            self.api_key
+                .clone()
+                .ok_or_else(|| format!("web_search backend `{}` needs an api_key", kind.name()))
+        };
+        let backend: Arc<dyn WebSearchBackend> = match kind {
+            WebSearchBackendKind::Searxng => {
+                let Some(url) = &self.url else {
+                    return Err("web_search backend `searxng` needs a url".to_string());
+                };
+                let search = format!("{}/search", url.trim_end_matches('/'));
+                Arc::new(SearxngBackend {
+                    client,
+                    endpoint: endpoint(&search)?,
+                })
+            }
+            WebSearchBackendKind::Brave => Arc::new(BraveBackend {
+                client,
+                endpoint: endpoint(self.url.as_deref().unwrap_or(BRAVE_ENDPOINT))?,
+                api_key: api_key()?,
+            }),
+            WebSearchBackendKind::Bing => Arc::new(BingBackend {
+                client,
+                endpoint: endpoint(self.url.as_deref().unwrap_or(BING_ENDPOINT))?,
+                api_key: api_key()?,
+            }),
+        };
+        Ok(Some(backend))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WebSearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+/// A search service behind `web_search`. SearxNG, Brave and Bing are built in; embedders can
+/// pass their own to [`create_web_search_tool`].
+#[async_trait]
+pub trait WebSearchBackend: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Up to `count` results for `query`, best first.
+    async fn search(&self, query: &str, count: usize) -> Result<Vec<WebSearchResult>, String>;
+}
+
+pub fn create_web_search_tool(backend: Arc<dyn WebSearchBackend>) -> AgentTool {
+    AgentTool {
+        name: "web_search".to_string(),
+        label: "web_search".to_string(),
+        description: format!(
+            "Search the web (via {}) and return result titles, URLs and snippets. Use it to find current documentation, release notes or error reports, then open promising results with web_fetch.",
+            backend.name()
+        ),
+        parameters: WebSearchArgs::schema(),
+        conflict_key: None,
+        execute: Arc::new(WebSearchToolExecutor { backend }),
+    }
+}
+
+#[derive(Deserialize, AgentToolArgs)]
+struct WebSearchArgs {
+    /// Search query.
+    query: String,
+    /// Number of results to return. Defaults to 5.
+    #[tool(minimum = 1, maximum = 20)]
+    count: Option<usize>,
+}
+
+struct WebSearchToolExecutor {
+    backend: Arc<dyn WebSearchBackend>,
+}
+
+#[async_trait]
+impl AgentToolExecutor for WebSearchToolExecutor {
+    async fn execute(
+        &self,
+        _tool_call_id: String,
+        args: Value,
+    ) -> Result<AgentToolResult, PiAiError> {
+        let WebSearchArgs { query, count } = WebSearchArgs::parse(args)?;
+        let count = count.unwrap_or(DEFAULT_RESULT_COUNT);
+        let backend = self.backend.name();
+        let mut results = self.backend.search(&query, count).await.map_err(|error| {
+            tool_execution_failed(format!("web_search via {backend} failed: {error}"))
+        })?;
+        results.truncate(count);
+
+        let text = if results.is_empty() {
+            format!("No results for \"{query}\".")
+        } else {
+            results
+                .iter()
+                .enumerate()
+                .map(|(index, result)| {
+                    let mut entry = format!("{}. {}\n   {}", index + 1, result.title, result.url);
+                    if !result.snippet.is_empty() {
+                        entry.push_str(&format!("\n   {}", result.snippet));
+                    }
+                    entry
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        };
+        Ok(text_result(
+            text,
+            json!({
+                "query": query,
+                "backend": backend,
+                "results": results
+                    .iter()
+                    .map(|result| json!({
+                        "title": result.title,
+                        "url": result.url,
+                        "snippet": result.snippet,
+                    }))
+                    .collect::<Vec<_>>(),
+            }),
+        ))
+    }
+}
+
+struct SearxngBackend {
+    client: reqwest::Client,
+    endpoint: Url,
+}
+
+#[async_trait]
+impl WebSearchBackend for SearxngBackend {
+    fn name(&self) -> &str {
+        "searxng"
+    }
+
+    async fn search(&self, query: &str, _count: usize) -> Result<Vec<WebSearchResult>, String> {
+        let mut url = self.endpoint.clone();
+        url.query_pairs_mut()
+            .append_pair("q", query)
+            .append_pair("format", "json");
+        let body = get_json(self.client.get(url)).await?;
+        Ok(collect_results(&body["results"], "title", "url", "content"))
+    }
+}
+
+struct BraveBackend {
+    client: reqwest::Client,
+    endpoint: Url,
+    api_key: String,
+}
+
+#[async_trait]
+impl WebSearchBackend for BraveBackend {
+    fn name(&self) -> &str {
+        "brave"
+    }
+
+    async fn search(&self, query: &str, count: usize) -> Result<Vec<WebSearchResult>, String> {
+        let mut url = self.endpoint.clone();
+        url.query_pairs_mut()
+            .append_pair("q", query)
+            .append_pair("count", &count.to_string());
+        let request = self
+            .client
+            .get(url)
+            .header("Accept", "application/json")
+            .header("X-Subscription-Token", &self.api_key);
+        let body = get_json(request).await?;
+        Ok(collect_results(
+            &body["web"]["results"],
+            "title",
+            "url",
+            "description",
+        ))
+    }
+}
+
+struct BingBackend {
+    client: reqwest::Client,
+    endpoint: Url,
+    api_key: String,
+}
+
+#[async_trait]
+impl WebSearchBackend for BingBackend {
+    fn name(&self) -> &str {
+        "bing"
+    }
+
+    async fn search(&self, query: &str, count: usize) -> Result<Vec<WebSearchResult>, String> {
+        let mut url = self.endpoint.clone();
+        url.query_pairs_mut()
+            .append_pair("q", query)
+            .append_pair("count", &count.to_string());
+        let request = self
+            .client
+            .get(url)
+            .header("Ocp-Apim-Subscription-Key", &self.api_key);
+        let body = get_json(request).await?;
+        Ok(collect_results(
+            &body["webPages"]["value"],
+            "name",
+            "url",
+            "snippet",
+        ))
+    }
+}
+
+async fn get_json(request: reqwest::RequestBuilder) -> Result<Value, String> {
+    let response = request.send().await.map_err(|error| error.to_string())?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        let body: String = body.chars().take(300).collect();
+        return Err(format!("HTTP {status}: {}", body.trim()));
+    }
+    response
+        .json()
+        .await
+        .map_err(|error| format!("invalid response: {error}"))
+}
+
+/// Results of a JSON array whose items name their fields `title`, `url` and `snippet`; snippets
+/// may carry highlighting markup, which is stripped.
+fn collect_results(items: &Value, title: &str, url: &str, snippet: &str) -> Vec<WebSearchResult> {
+    items
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|item| {
+            let url = item[url].as_str()?.to_string();
+            Some(WebSearchResult {
+                title: strip_tags(item[title].as_str().unwrap_or(&url)),
+                snippet: strip_tags(item[snippet].as_str().unwrap_or_default()),
+                url,
+            })
+        })
+        .collect()
+}
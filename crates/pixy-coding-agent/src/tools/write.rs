@@ -9,10 +9,23 @@ use serde_json::{json, Value};
 
 use super::common::{
     format_diff_stat_line, get_required_string, get_required_string_alias, invalid_tool_args,
-    line_change_counts, resolve_to_cwd, text_result, tool_execution_failed,
+    line_change_counts, observe_file, path_conflict_key, record_file_snapshot, resolve_to_cwd,
+    review_file_change, text_result, tool_execution_failed,
 };
+use crate::diff_review::SharedDiffReview;
+use crate::file_changes::SharedFileChangeTracker;
+use crate::file_snapshots::SharedFileSnapshots;
 
 pub fn create_write_tool(cwd: impl AsRef<Path>) -> AgentTool {
+    create_write_tool_with_snapshots(cwd, None, None, None)
+}
+
+pub(crate) fn create_write_tool_with_snapshots(
+    cwd: impl AsRef<Path>,
+    snapshots: Option<SharedFileSnapshots>,
+    file_changes: Option<SharedFileChangeTracker>,
+    review: Option<SharedDiffReview>,
+) -> AgentTool {
     let cwd = cwd.as_ref().to_path_buf();
     AgentTool {
         name: "write".to_string(),
@@ -28,12 +41,21 @@ pub fn create_write_tool(cwd: impl AsRef<Path>) -> AgentTool {
             "required": ["path", "content"],
             "additionalProperties": false
         }),
-        execute: Arc::new(WriteToolExecutor { cwd }),
+        conflict_key: Some(path_conflict_key(&cwd)),
+        execute: Arc::new(WriteToolExecutor {
+            cwd,
+            snapshots,
+            file_changes,
+            review,
+        }),
     }
 }
 
 struct WriteToolExecutor {
     cwd: PathBuf,
+    snapshots: Option<SharedFileSnapshots>,
+    file_changes: Option<SharedFileChangeTracker>,
+    review: Option<SharedDiffReview>,
 }
 
 #[async_trait]
@@ -44,11 +66,24 @@ impl AgentToolExecutor for WriteToolExecutor {
         args: Value,
     ) -> Result<AgentToolResult, PiAiError> {
         let cwd = self.cwd.clone();
-        execute_write_tool(&cwd, args)
+        execute_write_tool(
+            &cwd,
+            args,
+            self.snapshots.as_ref(),
+            self.file_changes.as_ref(),
+            self.review.as_ref(),
+        )
+        .await
     }
 }
 
-fn execute_write_tool(cwd: &Path, args: Value) -> Result<AgentToolResult, PiAiError> {
+async fn execute_write_tool(
+    cwd: &Path,
+    args: Value,
+    snapshots: Option<&SharedFileSnapshots>,
+    file_changes: Option<&SharedFileChangeTracker>,
+    review: Option<&SharedDiffReview>,
+) -> Result<AgentToolResult, PiAiError> {
     let path = get_required_string_alias(
         &args,
         &[
@@ -87,11 +122,19 @@ fn execute_write_tool(cwd: &Path, args: Value) -> Result<AgentToolResult, PiAiEr
     })?;
     let content = get_required_string(&args, "content")?;
     let absolute_path = resolve_to_cwd(cwd, &path);
-    let previous_content = match fs::read(&absolute_path) {
-        Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
-        Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
-        Err(_) => String::new(),
-    };
+    let existing_content = fs::read(&absolute_path)
+        .ok()
+        .map(|bytes| String::from_utf8_lossy(&bytes).to_string());
+    review_file_change(
+        review,
+        "write",
+        &path,
+        existing_content.as_deref(),
+        &content,
+    )
+    .await?;
+    let previous_content = existing_content.unwrap_or_default();
+    record_file_snapshot(snapshots, &absolute_path)?;
     if let Some(parent) = absolute_path.parent() {
         fs::create_dir_all(parent).map_err(|error| {
             tool_execution_failed(format!("Failed to create parent directories: {error}"))
@@ -100,6 +143,7 @@ fn execute_write_tool(cwd: &Path, args: Value) -> Result<AgentToolResult, PiAiEr
 
     fs::write(&absolute_path, &content)
         .map_err(|error| tool_execution_failed(format!("Failed to write {path}: {error}")))?;
+    observe_file(file_changes, &absolute_path);
     let (insertions, deletions) = line_change_counts(&previous_content, &content);
     Ok(text_result(
         format_diff_stat_line(&path, &previous_content, &content),
@@ -1,9 +1,33 @@
 use std::path::PathBuf;
 
-use pixy_agent_core::AgentAbortSignal;
-use pixy_tui::{BackendFuture, ResumeCandidate, StreamUpdate, TuiBackend};
-
-use crate::{cli_app::CliSession, AgentSession, AgentSessionStreamUpdate};
+use pixy_agent_core::{AgentAbortSignal, AgentRunMetrics, ApprovalDecision, QueuedMessages};
+use pixy_tui::{
+    BackendFuture, BackendStatusFuture, DiffReviewChoice, DiffReviewPrompt, ResumeCandidate,
+    StreamUpdate, ToolApprovalChoice, ToolApprovalPrompt, TuiBackend,
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    agent_session::SessionResumeCandidate,
+    cli_app::{format_file_changes, run_skills_command, CliSession},
+    AgentSession, AgentSessionStreamUpdate, DiffReviewDecision, TodoItem, TodoStatus,
+};
+
+impl From<SessionResumeCandidate> for ResumeCandidate {
+    fn from(candidate: SessionResumeCandidate) -> Self {
+        Self {
+            session_ref: candidate.path.display().to_string(),
+            title: candidate.title,
+            first_prompt: candidate.first_prompt,
+            updated_at: candidate.updated_at,
+            turn_count: candidate.turn_count,
+            message_count: candidate.message_count,
+            model: candidate.model,
+            total_cost: candidate.total_cost,
+            cwd: candidate.cwd,
+        }
+    }
+}
 
 impl TuiBackend for AgentSession {
     fn prompt<'a>(&'a mut self, input: &'a str) -> BackendFuture<'a> {
@@ -98,18 +122,8 @@ impl TuiBackend for AgentSession {
         &mut self,
         limit: usize,
     ) -> Result<Option<Vec<ResumeCandidate>>, String> {
-        AgentSession::recent_resumable_session_candidates(self, limit).map(|candidates| {
-            Some(
-                candidates
-                    .into_iter()
-                    .map(|candidate| ResumeCandidate {
-                        session_ref: candidate.path.display().to_string(),
-                        title: candidate.title,
-                        updated_at: candidate.updated_at,
-                    })
-                    .collect(),
-            )
-        })
+        AgentSession::recent_resumable_session_candidates(self, limit)
+            .map(|candidates| Some(candidates.into_iter().map(ResumeCandidate::from).collect()))
     }
 
     fn resume_session(&mut self, session_ref: Option<&str>) -> Result<Option<String>, String> {
@@ -122,10 +136,71 @@ impl TuiBackend for AgentSession {
             .map(|path| Some(format!("session: {}", path.display())))
     }
 
+    fn fork_session(&mut self, name: Option<&str>) -> Result<Option<String>, String> {
+        AgentSession::fork_session(self, name)
+            .map(|path| Some(format!("forked session: {}", path.display())))
+    }
+
+    fn undo_file_changes(&mut self) -> Result<Option<String>, String> {
+        AgentSession::undo_file_changes(self)
+            .map(|paths| Some(format_file_changes("reverted", &paths)))
+    }
+
+    fn redo_file_changes(&mut self) -> Result<Option<String>, String> {
+        AgentSession::redo_file_changes(self)
+            .map(|paths| Some(format_file_changes("reapplied", &paths)))
+    }
+
+    fn skills_command(&mut self, args: &str) -> Result<Option<Vec<String>>, String> {
+        run_skills_command(self, args).map(Some)
+    }
+
+    fn expand_skill_invocation(&mut self, args: &str) -> Result<Option<String>, String> {
+        AgentSession::render_skill_invocation(self, args).map(Some)
+    }
+
+    fn remember<'a>(&'a mut self, note: Option<&'a str>) -> BackendStatusFuture<'a> {
+        Box::pin(async move { AgentSession::remember(self, note).await.map(Some) })
+    }
+
+    fn cost_report(&mut self) -> Result<Option<Vec<String>>, String> {
+        Ok(Some(
+            AgentSession::cost_report(self)
+                .render()
+                .lines()
+                .map(ToOwned::to_owned)
+                .collect(),
+        ))
+    }
+
     fn session_messages(&self) -> Option<Vec<pixy_ai::Message>> {
         Some(AgentSession::build_session_context(self).messages)
     }
 
+    fn todo_items(&self) -> Option<Vec<pixy_tui::TodoItem>> {
+        Some(map_todos(AgentSession::todos(self)))
+    }
+
+    fn context_usage(&self) -> Option<(u64, u64)> {
+        Some(AgentSession::context_usage(self))
+    }
+
+    fn run_metrics(&self) -> Option<AgentRunMetrics> {
+        Some(AgentSession::metrics_snapshot(self))
+    }
+
+    fn diff_reviews(&mut self) -> Option<mpsc::UnboundedReceiver<DiffReviewPrompt>> {
+        attach_tui_diff_reviewer(self)
+    }
+
+    fn queued_messages(&mut self) -> Option<QueuedMessages> {
+        Some(AgentSession::queued_messages(self))
+    }
+
+    fn tool_approvals(&mut self) -> Option<mpsc::UnboundedReceiver<ToolApprovalPrompt>> {
+        attach_tui_tool_approver(self)
+    }
+
     fn session_file(&self) -> Option<PathBuf> {
         AgentSession::session_file(self).cloned()
     }
@@ -238,18 +313,7 @@ impl TuiBackend for CliSession {
         limit: usize,
     ) -> Result<Option<Vec<ResumeCandidate>>, String> {
         self.recent_resumable_session_candidates(limit)
-            .map(|candidates| {
-                Some(
-                    candidates
-                        .into_iter()
-                        .map(|candidate| ResumeCandidate {
-                            session_ref: candidate.path.display().to_string(),
-                            title: candidate.title,
-                            updated_at: candidate.updated_at,
-                        })
-                        .collect(),
-                )
-            })
+            .map(|candidates| Some(candidates.into_iter().map(ResumeCandidate::from).collect()))
     }
 
     fn resume_session(&mut self, session_ref: Option<&str>) -> Result<Option<String>, String> {
@@ -262,15 +326,123 @@ impl TuiBackend for CliSession {
             .map(|path| Some(format!("session: {}", path.display())))
     }
 
+    fn fork_session(&mut self, name: Option<&str>) -> Result<Option<String>, String> {
+        CliSession::fork_session(self, name)
+            .map(|path| Some(format!("forked session: {}", path.display())))
+    }
+
+    fn undo_file_changes(&mut self) -> Result<Option<String>, String> {
+        CliSession::undo_file_changes(self)
+            .map(|paths| Some(format_file_changes("reverted", &paths)))
+    }
+
+    fn redo_file_changes(&mut self) -> Result<Option<String>, String> {
+        CliSession::redo_file_changes(self)
+            .map(|paths| Some(format_file_changes("reapplied", &paths)))
+    }
+
+    fn skills_command(&mut self, args: &str) -> Result<Option<Vec<String>>, String> {
+        CliSession::skills_command(self, args).map(Some)
+    }
+
+    fn expand_skill_invocation(&mut self, args: &str) -> Result<Option<String>, String> {
+        self.ensure_session()?
+            .render_skill_invocation(args)
+            .map(Some)
+    }
+
+    fn remember<'a>(&'a mut self, note: Option<&'a str>) -> BackendStatusFuture<'a> {
+        Box::pin(async move { CliSession::remember(self, note).await.map(Some) })
+    }
+
+    fn cost_report(&mut self) -> Result<Option<Vec<String>>, String> {
+        Ok(Some(
+            CliSession::cost_report(self)?
+                .render()
+                .lines()
+                .map(ToOwned::to_owned)
+                .collect(),
+        ))
+    }
+
     fn session_messages(&self) -> Option<Vec<pixy_ai::Message>> {
         self.session_messages()
     }
 
+    fn todo_items(&self) -> Option<Vec<pixy_tui::TodoItem>> {
+        Some(map_todos(self.todos()))
+    }
+
+    fn context_usage(&self) -> Option<(u64, u64)> {
+        self.context_usage()
+    }
+
+    fn run_metrics(&self) -> Option<AgentRunMetrics> {
+        self.metrics_snapshot()
+    }
+
+    fn diff_reviews(&mut self) -> Option<mpsc::UnboundedReceiver<DiffReviewPrompt>> {
+        attach_tui_diff_reviewer(self.ensure_session().ok()?)
+    }
+
+    fn queued_messages(&mut self) -> Option<QueuedMessages> {
+        Some(AgentSession::queued_messages(self.ensure_session().ok()?))
+    }
+
+    fn tool_approvals(&mut self) -> Option<mpsc::UnboundedReceiver<ToolApprovalPrompt>> {
+        attach_tui_tool_approver(self.ensure_session().ok()?)
+    }
+
     fn session_file(&self) -> Option<PathBuf> {
         self.session_file()
     }
 }
 
+fn attach_tui_diff_reviewer(
+    session: &AgentSession,
+) -> Option<mpsc::UnboundedReceiver<DiffReviewPrompt>> {
+    let (prompt_tx, prompt_rx) = mpsc::unbounded_channel();
+    let attached = session.attach_diff_reviewer(move |request| {
+        let prompt = DiffReviewPrompt::new(
+            request.tool.clone(),
+            request.path.clone(),
+            request.diff.clone(),
+            move |choice| {
+                request.respond(match choice {
+                    DiffReviewChoice::Approve => DiffReviewDecision::Approve,
+                    DiffReviewChoice::ApproveAll => DiffReviewDecision::ApproveAll,
+                    DiffReviewChoice::Reject => DiffReviewDecision::Reject { feedback: None },
+                })
+            },
+        );
+        let _ = prompt_tx.send(prompt);
+    });
+    attached.then_some(prompt_rx)
+}
+
+fn attach_tui_tool_approver(
+    session: &mut AgentSession,
+) -> Option<mpsc::UnboundedReceiver<ToolApprovalPrompt>> {
+    let (prompt_tx, prompt_rx) = mpsc::unbounded_channel();
+    let attached = session.attach_tool_approver(move |request| {
+        let arguments = serde_json::to_string_pretty(&request.call.arguments)
+            .unwrap_or_else(|_| request.call.arguments.to_string());
+        let prompt = ToolApprovalPrompt::new(
+            request.call.name.clone(),
+            request.risk.as_str().to_string(),
+            arguments,
+            move |choice| {
+                request.respond(match choice {
+                    ToolApprovalChoice::Approve => ApprovalDecision::Approve,
+                    ToolApprovalChoice::Deny => ApprovalDecision::Deny { reason: None },
+                })
+            },
+        );
+        let _ = prompt_tx.send(prompt);
+    });
+    attached.then_some(prompt_rx)
+}
+
 #[derive(Default)]
 struct ThinkingStreamMapper {
     thinking_buffer: String,
@@ -288,6 +460,28 @@ impl ThinkingStreamMapper {
                 self.thinking_buffer.clear();
                 Some(StreamUpdate::ToolLine(line))
             }
+            AgentSessionStreamUpdate::Todos(todos) => Some(StreamUpdate::Todos(map_todos(todos))),
+            AgentSessionStreamUpdate::UsageDelta(usage) => Some(StreamUpdate::UsageDelta {
+                input_tokens: usage.input + usage.cache_read + usage.cache_write,
+                output_tokens: usage.output,
+            }),
+            AgentSessionStreamUpdate::ToolCallPreview(preview) => {
+                Some(StreamUpdate::ToolCallPreview(preview))
+            }
+            AgentSessionStreamUpdate::Stats(stats) => Some(StreamUpdate::TurnStats(stats)),
+            AgentSessionStreamUpdate::SubagentProgress {
+                task_id,
+                subagent,
+                line,
+            } => Some(StreamUpdate::SubagentProgress {
+                task_id,
+                subagent,
+                line,
+            }),
+            // Already rendered from the text deltas and tool lines.
+            AgentSessionStreamUpdate::AssistantMessageEnd(_)
+            | AgentSessionStreamUpdate::ToolCallStart { .. }
+            | AgentSessionStreamUpdate::ToolCallEnd { .. } => None,
         }
     }
 
@@ -311,6 +505,20 @@ impl ThinkingStreamMapper {
     }
 }
 
+fn map_todos(todos: Vec<TodoItem>) -> Vec<pixy_tui::TodoItem> {
+    todos
+        .into_iter()
+        .map(|item| pixy_tui::TodoItem {
+            content: item.content,
+            status: match item.status {
+                TodoStatus::Pending => pixy_tui::TodoStatus::Pending,
+                TodoStatus::InProgress => pixy_tui::TodoStatus::InProgress,
+                TodoStatus::Done => pixy_tui::TodoStatus::Done,
+            },
+        })
+        .collect()
+}
+
 fn parse_thinking_line_content(line: &str) -> Option<&str> {
     line.strip_prefix("[thinking]")
         .map(|rest| rest.strip_prefix(' ').unwrap_or(rest))
@@ -0,0 +1,361 @@
+//! Session worktrees: `--worktree` (or `[worktree] enabled = true`) runs a session inside a
+//! dedicated git worktree on its own branch so the user's checkout stays untouched.
+//!
+//! When the session ends the work is merged back into the branch it started from, pushed as a
+//! pull request, kept for later, or discarded.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_BRANCH_PREFIX: &str = "pixy/";
+/// Worktrees live in the repository's git directory so they never show up in the checkout.
+const WORKTREES_DIR_NAME: &str = "pixy-worktrees";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorktreeExitAction {
+    /// Ask in interactive sessions; keep the worktree after `--prompt` runs.
+    #[default]
+    Ask,
+    Merge,
+    #[serde(alias = "pull_request")]
+    Pr,
+    Keep,
+    Discard,
+}
+
+impl WorktreeExitAction {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "a" | "ask" => Some(Self::Ask),
+            "m" | "merge" => Some(Self::Merge),
+            "p" | "pr" | "pull_request" => Some(Self::Pr),
+            "k" | "keep" => Some(Self::Keep),
+            "d" | "discard" => Some(Self::Discard),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorktreeConfig {
+    pub enabled: bool,
+    /// Directory the worktrees are created in; defaults to `<git dir>/pixy-worktrees`.
+    pub dir: Option<PathBuf>,
+    pub branch_prefix: String,
+    pub on_exit: WorktreeExitAction,
+}
+
+impl Default for WorktreeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: None,
+            branch_prefix: DEFAULT_BRANCH_PREFIX.to_string(),
+            on_exit: WorktreeExitAction::Ask,
+        }
+    }
+}
+
+/// A worktree created for one session.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionWorktree {
+    /// Top level of the user's checkout.
+    pub repo_root: PathBuf,
+    pub path: PathBuf,
+    pub branch: String,
+    /// Branch checked out in the user's checkout when the session started; `None` when detached.
+    pub base_branch: Option<String>,
+    pub base_commit: String,
+    /// Working directory inside the worktree matching the directory pixy was started in.
+    pub cwd: PathBuf,
+}
+
+impl SessionWorktree {
+    /// Creates a worktree for the repository containing `cwd`, branched from its `HEAD`.
+    pub fn create(cwd: &Path, config: &WorktreeConfig, name: &str) -> Result<Self, String> {
+        let repo_root = PathBuf::from(
+            git(cwd, &["rev-parse", "--show-toplevel"])
+                .map_err(|error| format!("--worktree needs a git repository: {error}"))?,
+        );
+        let base_commit = git(&repo_root, &["rev-parse", "HEAD"])
+            .map_err(|error| format!("--worktree needs at least one commit: {error}"))?;
+        let base_branch = git(&repo_root, &["symbolic-ref", "--short", "-q", "HEAD"]).ok();
+
+        let dir = match config.dir.as_ref() {
+            Some(dir) if dir.is_absolute() => dir.clone(),
+            Some(dir) => repo_root.join(dir),
+            None => {
+                let common_dir = PathBuf::from(git(
+                    &repo_root,
+                    &["rev-parse", "--path-format=absolute", "--git-common-dir"],
+                )?);
+                common_dir.join(WORKTREES_DIR_NAME)
+            }
+        };
+        let path = dir.join(name);
+        let branch = format!("{}{name}", config.branch_prefix);
+        std::fs::create_dir_all(&dir)
+            .map_err(|error| format!("create {} failed: {error}", dir.display()))?;
+        git(
+            &repo_root,
+            &[
+                "worktree",
+                "add",
+                "-b",
+                &branch,
+                &path.to_string_lossy(),
+                &base_commit,
+            ],
+        )?;
+
+        let relative = cwd
+            .canonicalize()
+            .ok()
+            .zip(repo_root.canonicalize().ok())
+            .and_then(|(cwd, root)| cwd.strip_prefix(root).ok().map(Path::to_path_buf))
+            .unwrap_or_default();
+        let session_cwd = path.join(relative);
+        let session_cwd = if session_cwd.is_dir() {
+            session_cwd
+        } else {
+            path.clone()
+        };
+
+        Ok(Self {
+            repo_root,
+            cwd: session_cwd,
+            path,
+            branch,
+            base_branch,
+            base_commit,
+        })
+    }
+
+    /// Whether the session left uncommitted files or commits on its branch.
+    pub fn has_changes(&self) -> Result<bool, String> {
+        if !git(&self.path, &["status", "--porcelain"])?.is_empty() {
+            return Ok(true);
+        }
+        let ahead = git(
+            &self.path,
+            &[
+                "rev-list",
+                "--count",
+                &format!("{}..HEAD", self.base_commit),
+            ],
+        )?;
+        Ok(ahead != "0")
+    }
+
+    /// Commits everything left in the worktree so it can leave through the branch.
+    pub fn commit_pending(&self, message: &str) -> Result<bool, String> {
+        if git(&self.path, &["status", "--porcelain"])?.is_empty() {
+            return Ok(false);
+        }
+        git(&self.path, &["add", "-A"])?;
+        git(&self.path, &["commit", "-m", message])?;
+        Ok(true)
+    }
+
+    /// Applies `action` and returns a line describing the outcome. `Ask` keeps the worktree.
+    pub fn finish(&self, action: WorktreeExitAction) -> Result<String, String> {
+        if !self.has_changes()? {
+            self.remove(true)?;
+            return Ok(format!(
+                "worktree: no changes; removed {}",
+                self.path.display()
+            ));
+        }
+        match action {
+            WorktreeExitAction::Ask | WorktreeExitAction::Keep => Ok(format!(
+                "worktree: kept {} on branch {}",
+                self.path.display(),
+                self.branch
+            )),
+            WorktreeExitAction::Discard => {
+                self.remove(true)?;
+                Ok(format!("worktree: discarded branch {}", self.branch))
+            }
+            WorktreeExitAction::Merge => self.merge_back(),
+            WorktreeExitAction::Pr => self.create_pull_request(),
+        }
+    }
+
+    fn commit_message(&self) -> String {
+        format!("pixy session {}", self.branch)
+    }
+
+    fn merge_back(&self) -> Result<String, String> {
+        let Some(base_branch) = self.base_branch.as_deref() else {
+            return Err(format!(
+                "the session started on a detached HEAD; merge {} manually",
+                self.branch
+            ));
+        };
+        let current = git(&self.repo_root, &["symbolic-ref", "--short", "-q", "HEAD"]).ok();
+        if current.as_deref() != Some(base_branch) {
+            return Err(format!(
+                "{} is no longer on {base_branch}; merge {} manually",
+                self.repo_root.display(),
+                self.branch
+            ));
+        }
+        self.commit_pending(&self.commit_message())?;
+        git(
+            &self.repo_root,
+            &["merge", "--no-edit", "--no-ff", &self.branch],
+        )
+        .map_err(|error| {
+            format!(
+                "merging {} failed, the worktree was kept at {}: {error}",
+                self.branch,
+                self.path.display()
+            )
+        })?;
+        self.remove(true)?;
+        Ok(format!(
+            "worktree: merged {} into {base_branch}",
+            self.branch
+        ))
+    }
+
+    fn create_pull_request(&self) -> Result<String, String> {
+        self.commit_pending(&self.commit_message())?;
+        git(&self.path, &["push", "-u", "origin", &self.branch])?;
+        let mut args = vec!["pr", "create", "--fill", "--head", self.branch.as_str()];
+        if let Some(base_branch) = self.base_branch.as_deref() {
+            args.extend(["--base", base_branch]);
+        }
+        let url = run(&self.path, "gh", &args).map_err(|error| {
+            format!(
+                "pushed {} but `gh pr create` failed; open the pull request manually: {error}",
+                self.branch
+            )
+        })?;
+        // The branch lives on in the pull request; only the checkout goes away.
+        self.remove(false)?;
+        Ok(format!("worktree: opened pull request {url}"))
+    }
+
+    fn remove(&self, delete_branch: bool) -> Result<(), String> {
+        git(
+            &self.repo_root,
+            &[
+                "worktree",
+                "remove",
+                "--force",
+                &self.path.to_string_lossy(),
+            ],
+        )?;
+        if delete_branch {
+            git(&self.repo_root, &["branch", "-D", &self.branch])?;
+        }
+        Ok(())
+    }
+}
+
+/// Worktree name for a session started at `now`, such as `20260102-150405`.
+pub fn worktree_name(now: chrono::DateTime<chrono::Local>) -> String {
+    now.format("%Y%m%d-%H%M%S").to_string()
+}
+
+fn git(cwd: &Path, args: &[&str]) -> Result<String, String> {
+    run(cwd, "git", args)
+}
+
+fn run(cwd: &Path, program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(cwd)
+        .output()
+        .map_err(|error| format!("run {program} failed: {error}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "`{program} {}` failed: {}",
+            args.join(" "),
+            stderr.trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_repo(dir: &Path) {
+        for args in [
+            vec!["init", "-q", "-b", "main"],
+            vec!["config", "user.email", "dev@example.com"],
+            vec!["config", "user.name", "dev"],
+        ] {
+            git(dir, &args).expect("git setup");
+        }
+        std::fs::create_dir_all(dir.join("src")).expect("mkdir");
+        std::fs::write(dir.join("src/lib.rs"), "fn main() {}\n").expect("write");
+        git(dir, &["add", "-A"]).expect("add");
+        git(dir, &["commit", "-q", "-m", "init"]).expect("commit");
+    }
+
+    #[test]
+    fn worktree_runs_in_matching_subdirectory_and_merges_back() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let repo = temp.path().join("repo");
+        std::fs::create_dir_all(&repo).expect("mkdir");
+        init_repo(&repo);
+
+        let worktree = SessionWorktree::create(
+            &repo.join("src"),
+            &WorktreeConfig::default(),
+            "20260102-150405",
+        )
+        .expect("create worktree");
+        assert_eq!(worktree.branch, "pixy/20260102-150405");
+        assert_eq!(worktree.base_branch.as_deref(), Some("main"));
+        assert!(worktree.cwd.ends_with("20260102-150405/src"));
+        assert!(!worktree.has_changes().expect("status"));
+
+        std::fs::write(worktree.cwd.join("lib.rs"), "fn main() { run(); }\n").expect("edit");
+        assert!(worktree.has_changes().expect("status"));
+        assert_eq!(
+            std::fs::read_to_string(repo.join("src/lib.rs")).expect("read"),
+            "fn main() {}\n"
+        );
+
+        let outcome = worktree
+            .finish(WorktreeExitAction::Merge)
+            .expect("merge back");
+        assert!(outcome.contains("merged pixy/20260102-150405 into main"));
+        assert_eq!(
+            std::fs::read_to_string(repo.join("src/lib.rs")).expect("read"),
+            "fn main() { run(); }\n"
+        );
+        assert!(!worktree.path.exists());
+        assert!(git(&repo, &["rev-parse", "--verify", "-q", &worktree.branch]).is_err());
+    }
+
+    #[test]
+    fn unchanged_worktree_is_removed_and_discard_drops_the_branch() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        init_repo(temp.path());
+        let config = WorktreeConfig::default();
+
+        let clean = SessionWorktree::create(temp.path(), &config, "clean").expect("create");
+        let outcome = clean.finish(WorktreeExitAction::Keep).expect("finish");
+        assert!(outcome.contains("no changes"));
+        assert!(!clean.path.exists());
+
+        let edited = SessionWorktree::create(temp.path(), &config, "edited").expect("create");
+        std::fs::write(edited.path.join("notes.md"), "draft\n").expect("write");
+        let outcome = edited.finish(WorktreeExitAction::Discard).expect("discard");
+        assert!(outcome.contains("discarded branch pixy/edited"));
+        assert!(!edited.path.exists());
+        assert!(!temp.path().join("notes.md").exists());
+        assert!(git(temp.path(), &["rev-parse", "--verify", "-q", "pixy/edited"]).is_err());
+    }
+}
@@ -18,6 +18,7 @@ fn sample_usage() -> Usage {
         output: 1,
         cache_read: 0,
         cache_write: 0,
+        reasoning: 0,
         total_tokens: 2,
         cost: Cost {
             input: 0.0,
@@ -48,6 +49,9 @@ fn sample_model() -> Model {
         },
         context_window: 128_000,
         max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
     }
 }
 
@@ -66,6 +70,7 @@ fn assistant_message(
         stop_reason,
         error_message: None,
         timestamp,
+        stats: None,
     }
 }
 
@@ -1,14 +1,18 @@
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
+use pixy_agent_core::{ToolPolicy, ToolRisk};
 use pixy_ai::{
     AssistantContentBlock, AssistantMessage, AssistantMessageEvent, AssistantMessageEventStream,
     Context, Cost, DoneReason, ErrorReason, Message, Model, StopReason, ToolResultContentBlock,
     Usage,
 };
 use pixy_coding_agent::{
-    create_coding_tools, AgentSession, AgentSessionConfig, AgentSessionStreamUpdate,
-    AutoCompactionConfig, SessionManager, COMPACTION_SUMMARY_PREFIX,
+    create_coding_tools, create_shell_tool, create_todo_tool, serve_mcp_session, AgentSession,
+    AgentSessionConfig, AgentSessionStreamUpdate, AutoCompactionConfig, PersistentShell,
+    ProjectMemoryConfig, ProjectMemoryTarget, SessionManager, TodoItem, TodoStatus,
+    COMPACTION_SUMMARY_PREFIX,
 };
 use serde_json::json;
 use tempfile::tempdir;
@@ -19,6 +23,7 @@ fn sample_usage() -> Usage {
         output: 5,
         cache_read: 0,
         cache_write: 0,
+        reasoning: 0,
         total_tokens: 15,
         cost: Cost {
             input: 0.01,
@@ -49,6 +54,9 @@ fn sample_model(api: &str) -> Model {
         },
         context_window: 128_000,
         max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
     }
 }
 
@@ -67,6 +75,7 @@ fn assistant_message(
         stop_reason,
         error_message: None,
         timestamp: ts,
+        stats: None,
     }
 }
 
@@ -85,6 +94,7 @@ fn assistant_error_message(error_message: &str, ts: i64, input_tokens: u64) -> A
             output: 0,
             cache_read: 0,
             cache_write: 0,
+            reasoning: 0,
             total_tokens: 0,
             cost: Cost {
                 input: 0.0,
@@ -97,6 +107,7 @@ fn assistant_error_message(error_message: &str, ts: i64, input_tokens: u64) -> A
         stop_reason: StopReason::Error,
         error_message: Some(error_message.to_string()),
         timestamp: ts,
+        stats: None,
     }
 }
 
@@ -418,6 +429,60 @@ async fn agent_session_prompt_runs_tool_loop_and_persists_messages() {
     assert_eq!(stream_call_count.load(Ordering::SeqCst), 2);
 }
 
+#[tokio::test]
+async fn agent_session_max_turns_aborts_run_that_keeps_calling_tools() {
+    let dir = tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("note.txt"), "hello from file").expect("seed note");
+
+    let stream_call_count = Arc::new(AtomicUsize::new(0));
+    let stream_call_count_in_fn = stream_call_count.clone();
+    let stream_fn = Arc::new(
+        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let call_index = stream_call_count_in_fn.fetch_add(1, Ordering::SeqCst);
+            let msg = assistant_message(
+                vec![AssistantContentBlock::ToolCall {
+                    id: format!("tool-{call_index}"),
+                    name: "read".to_string(),
+                    arguments: json!({"path":"note.txt"}),
+                    thought_signature: None,
+                }],
+                StopReason::ToolUse,
+                1_700_000_000_010 + call_index as i64,
+            );
+            Ok(done_stream(msg, DoneReason::ToolUse))
+        },
+    );
+
+    let manager = SessionManager::create(
+        dir.path().to_str().expect("cwd utf-8"),
+        dir.path().join("sessions"),
+    )
+    .expect("create session manager");
+    let config = AgentSessionConfig {
+        model: sample_model("test-api"),
+        system_prompt: "You are helpful".to_string(),
+        stream_fn,
+        tools: create_coding_tools(dir.path()),
+    };
+    let mut session = AgentSession::new(manager, config);
+    session.set_max_turns(Some(2));
+
+    let produced = session
+        .prompt("keep reading")
+        .await
+        .expect("prompt returns aborted run");
+
+    assert!(session.turn_limit_reached());
+    assert_eq!(stream_call_count.load(Ordering::SeqCst), 2);
+    assert!(matches!(
+        produced.last(),
+        Some(Message::Assistant {
+            stop_reason: StopReason::Aborted,
+            ..
+        })
+    ));
+}
+
 #[tokio::test]
 async fn agent_session_continue_run_after_reload_uses_history_and_persists() {
     let dir = tempdir().expect("tempdir");
@@ -1060,6 +1125,73 @@ async fn agent_session_auto_compaction_triggers_when_threshold_exceeded() {
     );
 }
 
+#[tokio::test]
+async fn agent_session_auto_compaction_estimates_tokens_when_usage_is_missing() {
+    let dir = tempdir().expect("tempdir");
+    let session_dir = dir.path().join("sessions");
+
+    let stream_fn = Arc::new(
+        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let mut answer = assistant_message(
+                vec![AssistantContentBlock::Text {
+                    text: "answer without usage".to_string(),
+                    text_signature: None,
+                }],
+                StopReason::Stop,
+                1_700_000_000_010,
+            );
+            answer.usage.input = 0;
+            answer.usage.output = 0;
+            answer.usage.total_tokens = 0;
+            Ok(done_stream(answer, DoneReason::Stop))
+        },
+    );
+
+    let mut model = sample_model("test-api");
+    model.context_window = 200;
+
+    let manager = SessionManager::create(dir.path().to_str().expect("cwd utf-8"), &session_dir)
+        .expect("create manager");
+    let config = AgentSessionConfig {
+        model,
+        system_prompt: "You are helpful".to_string(),
+        stream_fn,
+        tools: create_coding_tools(dir.path()),
+    };
+    let mut session = AgentSession::new(manager, config);
+    let (tokens, window) = session.context_usage();
+    assert_eq!(window, 200);
+    assert!(
+        tokens > 200,
+        "tool definitions alone exceed the tiny window"
+    );
+
+    session.set_auto_compaction_config(AutoCompactionConfig {
+        enabled: true,
+        reserve_tokens: 10,
+        keep_recent_messages: 1,
+        max_summary_chars: 800,
+    });
+    session
+        .prompt("first prompt")
+        .await
+        .expect("prompt succeeds with auto compaction");
+
+    let session_file = session.session_file().expect("session file").clone();
+    let content = std::fs::read_to_string(&session_file).expect("read session file");
+    let compaction_entry: serde_json::Value =
+        serde_json::from_str(content.lines().last().expect("compaction entry"))
+            .expect("compaction entry json");
+    assert_eq!(compaction_entry["type"], "compaction");
+    assert!(
+        compaction_entry["tokensBefore"]
+            .as_u64()
+            .expect("tokensBefore")
+            > tokens,
+        "estimate covers the new prompt and answer"
+    );
+}
+
 #[tokio::test]
 async fn agent_session_auto_compaction_falls_back_to_rule_summary_when_llm_summary_fails() {
     let dir = tempdir().expect("tempdir");
@@ -1129,6 +1261,216 @@ async fn agent_session_auto_compaction_falls_back_to_rule_summary_when_llm_summa
     );
 }
 
+#[tokio::test]
+async fn agent_session_remember_records_notes_and_distilled_learnings() {
+    let dir = tempdir().expect("tempdir");
+    let session_dir = dir.path().join("sessions");
+
+    let stream_fn = Arc::new(
+        move |_model: Model, context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let is_distill_request = context.messages.iter().any(|message| {
+                matches!(
+                    message,
+                    Message::User {
+                        content: pixy_ai::UserContent::Text(text),
+                        ..
+                    } if text.contains("<existing_learnings>")
+                        && text.contains("Run tests with `make test`.")
+                        && text.contains("first prompt")
+                )
+            });
+            let text = if is_distill_request {
+                "- run tests with make test\n- Config lives in `conf/app.toml`."
+            } else {
+                "answer"
+            };
+            let answer = assistant_message(
+                vec![AssistantContentBlock::Text {
+                    text: text.to_string(),
+                    text_signature: None,
+                }],
+                StopReason::Stop,
+                1_700_000_000_010,
+            );
+            Ok(done_stream(answer, DoneReason::Stop))
+        },
+    );
+
+    let manager = SessionManager::create(dir.path().to_str().expect("cwd utf-8"), &session_dir)
+        .expect("create manager");
+    let config = AgentSessionConfig {
+        model: sample_model("test-api"),
+        system_prompt: "You are helpful".to_string(),
+        stream_fn,
+        tools: vec![],
+    };
+    let mut session = AgentSession::new(manager, config);
+    session.set_project_memory(
+        dir.path(),
+        &ProjectMemoryConfig {
+            target: ProjectMemoryTarget::PixyMemory,
+            ..ProjectMemoryConfig::default()
+        },
+    );
+
+    assert!(session
+        .remember(None)
+        .await
+        .expect_err("empty session has nothing to distill")
+        .contains("No conversation"));
+
+    let status = session
+        .remember(Some("Run tests with `make test`."))
+        .await
+        .expect("remember note");
+    assert!(status.starts_with("Remembered 1 learning in"));
+
+    session.prompt("first prompt").await.expect("prompt");
+    let status = session.remember(None).await.expect("distill learnings");
+    assert!(status.starts_with("Remembered 1 learning in"));
+
+    let content =
+        std::fs::read_to_string(dir.path().join(".pixy/memory.md")).expect("read memory file");
+    assert!(content.contains("- Run tests with `make test`.\n"));
+    assert!(content.contains("- Config lives in `conf/app.toml`.\n"));
+    assert!(!content.contains("run tests with make test"));
+}
+
+#[tokio::test]
+async fn agent_session_generates_title_once_after_first_exchange() {
+    let dir = tempdir().expect("tempdir");
+    let title_requests = Arc::new(AtomicUsize::new(0));
+    let title_requests_in_fn = title_requests.clone();
+    let stream_fn = Arc::new(
+        move |_model: Model, context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let is_title_request = context.messages.iter().any(|message| {
+                matches!(
+                    message,
+                    Message::User {
+                        content: pixy_ai::UserContent::Text(text),
+                        ..
+                    } if text.contains("<conversation>") && text.contains("short title")
+                )
+            });
+            let text = if is_title_request {
+                title_requests_in_fn.fetch_add(1, Ordering::SeqCst);
+                "Title: \"Fix flaky websocket test.\"\n"
+            } else {
+                "answer"
+            };
+            let answer = assistant_message(
+                vec![AssistantContentBlock::Text {
+                    text: text.to_string(),
+                    text_signature: None,
+                }],
+                StopReason::Stop,
+                1_700_000_000_010,
+            );
+            Ok(done_stream(answer, DoneReason::Stop))
+        },
+    );
+
+    let manager = SessionManager::create(
+        dir.path().to_str().expect("cwd utf-8"),
+        dir.path().join("sessions"),
+    )
+    .expect("create manager");
+    let config = AgentSessionConfig {
+        model: sample_model("test-api"),
+        system_prompt: "You are helpful".to_string(),
+        stream_fn,
+        tools: vec![],
+    };
+    let mut session = AgentSession::new(manager, config);
+    session.set_auto_title(true);
+
+    session
+        .prompt("the websocket test times out")
+        .await
+        .expect("first prompt");
+    session.prompt("try again").await.expect("second prompt");
+
+    assert_eq!(title_requests.load(Ordering::SeqCst), 1);
+    assert_eq!(session.session_title(), Some("Fix flaky websocket test"));
+    let file = session.session_file().expect("session file").clone();
+    let reloaded = SessionManager::load(&file).expect("reload");
+    assert_eq!(
+        reloaded.header().title.as_deref(),
+        Some("Fix flaky websocket test")
+    );
+    assert_eq!(reloaded.build_session_context().messages.len(), 4);
+}
+
+#[tokio::test]
+async fn agent_session_prices_turns_and_persists_cost() {
+    let dir = tempdir().expect("tempdir");
+    let session_dir = dir.path().join("sessions");
+
+    let stream_fn = Arc::new(
+        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let mut answer = assistant_message(
+                vec![AssistantContentBlock::Text {
+                    text: "answer".to_string(),
+                    text_signature: None,
+                }],
+                StopReason::Stop,
+                1_700_000_000_010,
+            );
+            answer.usage = Usage {
+                input: 1_000_000,
+                output: 100_000,
+                cache_read: 0,
+                cache_write: 0,
+                reasoning: 0,
+                total_tokens: 1_100_000,
+                cost: Cost {
+                    input: 0.0,
+                    output: 0.0,
+                    cache_read: 0.0,
+                    cache_write: 0.0,
+                    total: 0.0,
+                },
+            };
+            Ok(done_stream(answer, DoneReason::Stop))
+        },
+    );
+
+    let mut model = sample_model("test-api");
+    model.cost.input = 2.0;
+    model.cost.output = 10.0;
+    let manager = SessionManager::create(dir.path().to_str().expect("cwd utf-8"), &session_dir)
+        .expect("create manager");
+    let config = AgentSessionConfig {
+        model: model.clone(),
+        system_prompt: "You are helpful".to_string(),
+        stream_fn: stream_fn.clone(),
+        tools: vec![],
+    };
+    let mut session = AgentSession::new(manager, config);
+
+    session.prompt("first").await.expect("first prompt");
+    session.prompt("second").await.expect("second prompt");
+
+    let report = session.cost_report();
+    assert_eq!(report.turns, 2);
+    assert!((report.last_turn.cost - 3.0).abs() < 1e-9);
+    assert!((report.session.cost - 6.0).abs() < 1e-9);
+    assert_eq!(report.models.len(), 1);
+    assert_eq!(report.models[0].model, "test/test-model");
+
+    let session_file = session.session_file().expect("session file").clone();
+    let reloaded = AgentSession::new(
+        SessionManager::load(&session_file).expect("load session"),
+        AgentSessionConfig {
+            model,
+            system_prompt: "You are helpful".to_string(),
+            stream_fn,
+            tools: vec![],
+        },
+    );
+    assert_eq!(reloaded.cost_report(), report);
+}
+
 #[tokio::test]
 async fn agent_session_overflow_triggers_auto_compaction_and_retry() {
     let dir = tempdir().expect("tempdir");
@@ -1300,11 +1642,95 @@ async fn agent_session_prompt_streaming_emits_text_delta_updates() {
             AgentSessionStreamUpdate::AssistantTextDelta("hello".to_string()),
             AgentSessionStreamUpdate::AssistantTextDelta(" world".to_string()),
             AgentSessionStreamUpdate::AssistantLine(String::new()),
+            AgentSessionStreamUpdate::AssistantMessageEnd("hello world".to_string()),
         ]
     );
     assert_eq!(produced.len(), 2, "user + assistant");
 }
 
+#[tokio::test]
+async fn agent_session_todo_tool_streams_and_persists_todo_list() {
+    let dir = tempdir().expect("tempdir");
+    let session_dir = dir.path().join("sessions");
+
+    let stream_call_count = Arc::new(AtomicUsize::new(0));
+    let stream_call_count_in_fn = stream_call_count.clone();
+    let stream_fn = Arc::new(
+        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            if stream_call_count_in_fn.fetch_add(1, Ordering::SeqCst) == 0 {
+                let msg = assistant_message(
+                    vec![AssistantContentBlock::ToolCall {
+                        id: "tool-1".to_string(),
+                        name: "todo".to_string(),
+                        arguments: json!({
+                            "todos": [
+                                { "content": "Explore", "status": "done" },
+                                { "content": "Implement", "status": "in_progress" }
+                            ]
+                        }),
+                        thought_signature: None,
+                    }],
+                    StopReason::ToolUse,
+                    1_700_000_000_010,
+                );
+                Ok(done_stream(msg, DoneReason::ToolUse))
+            } else {
+                let msg = assistant_message(
+                    vec![AssistantContentBlock::Text {
+                        text: "working on it".to_string(),
+                        text_signature: None,
+                    }],
+                    StopReason::Stop,
+                    1_700_000_000_020,
+                );
+                Ok(done_stream(msg, DoneReason::Stop))
+            }
+        },
+    );
+
+    let manager = SessionManager::create(dir.path().to_str().expect("cwd utf-8"), &session_dir)
+        .expect("create manager");
+    let config = AgentSessionConfig {
+        model: sample_model("test-api"),
+        system_prompt: "You are helpful".to_string(),
+        stream_fn,
+        tools: vec![create_todo_tool()],
+    };
+    let mut session = AgentSession::new(manager, config);
+    assert!(session.todos().is_empty());
+
+    let mut updates = vec![];
+    session
+        .prompt_streaming("plan and implement", |update| updates.push(update))
+        .await
+        .expect("prompt streaming succeeds");
+
+    let expected = vec![
+        TodoItem {
+            content: "Explore".to_string(),
+            status: TodoStatus::Done,
+        },
+        TodoItem {
+            content: "Implement".to_string(),
+            status: TodoStatus::InProgress,
+        },
+    ];
+    assert!(updates.contains(&AgentSessionStreamUpdate::Todos(expected.clone())));
+    assert_eq!(session.todos(), expected);
+
+    let file = session.session_file().expect("session file").clone();
+    let reloaded = AgentSession::new(
+        SessionManager::load(&file).expect("reload session"),
+        AgentSessionConfig {
+            model: sample_model("test-api"),
+            system_prompt: "You are helpful".to_string(),
+            stream_fn: Arc::new(|_, _, _| unreachable!("no model calls after reload")),
+            tools: vec![],
+        },
+    );
+    assert_eq!(reloaded.todos(), expected);
+}
+
 #[tokio::test]
 async fn agent_session_prompt_streaming_hides_read_tool_result_text() {
     let dir = tempdir().expect("tempdir");
@@ -1372,6 +1798,80 @@ async fn agent_session_prompt_streaming_hides_read_tool_result_text() {
     );
 }
 
+#[tokio::test]
+async fn agent_session_prompt_streaming_streams_bash_output_lines_once() {
+    let dir = tempdir().expect("tempdir");
+    let session_dir = dir.path().join("sessions");
+
+    let stream_call_count = Arc::new(AtomicUsize::new(0));
+    let stream_call_count_in_fn = stream_call_count.clone();
+    let stream_fn = Arc::new(
+        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let call_index = stream_call_count_in_fn.fetch_add(1, Ordering::SeqCst);
+            if call_index == 0 {
+                let msg = assistant_message(
+                    vec![AssistantContentBlock::ToolCall {
+                        id: "tool-bash-1".to_string(),
+                        name: "bash".to_string(),
+                        arguments: json!({"command": "echo first-line; echo second-line"}),
+                        thought_signature: None,
+                    }],
+                    StopReason::ToolUse,
+                    1_700_000_000_010,
+                );
+                Ok(done_stream(msg, DoneReason::ToolUse))
+            } else {
+                let msg = assistant_message(
+                    vec![AssistantContentBlock::Text {
+                        text: "done".to_string(),
+                        text_signature: None,
+                    }],
+                    StopReason::Stop,
+                    1_700_000_000_020,
+                );
+                Ok(done_stream(msg, DoneReason::Stop))
+            }
+        },
+    );
+
+    let manager = SessionManager::create(dir.path().to_str().expect("cwd utf-8"), &session_dir)
+        .expect("create manager");
+    let config = AgentSessionConfig {
+        model: sample_model("test-api"),
+        system_prompt: "You are helpful".to_string(),
+        stream_fn,
+        tools: create_coding_tools(dir.path()),
+    };
+    let mut session = AgentSession::new(manager, config);
+
+    let mut updates = vec![];
+    session
+        .prompt_streaming("run it", |update| updates.push(update))
+        .await
+        .expect("prompt streaming succeeds");
+
+    let tool_lines = updates
+        .iter()
+        .filter_map(|update| match update {
+            AgentSessionStreamUpdate::ToolLine(line) => Some(line.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let first = tool_lines
+        .iter()
+        .position(|line| *line == "first-line")
+        .expect("first line streamed");
+    assert_eq!(tool_lines.get(first + 1), Some(&"second-line"));
+    assert_eq!(
+        tool_lines
+            .iter()
+            .filter(|line| line.starts_with("first-line"))
+            .count(),
+        1,
+        "the final tool result should not repeat streamed output: {tool_lines:?}"
+    );
+}
+
 #[tokio::test]
 async fn agent_session_continue_run_streaming_emits_updates() {
     let dir = tempdir().expect("tempdir");
@@ -1425,6 +1925,7 @@ async fn agent_session_continue_run_streaming_emits_updates() {
             AgentSessionStreamUpdate::AssistantTextDelta("cont".to_string()),
             AgentSessionStreamUpdate::AssistantTextDelta("inue".to_string()),
             AgentSessionStreamUpdate::AssistantLine(String::new()),
+            AgentSessionStreamUpdate::AssistantMessageEnd("continue".to_string()),
         ]
     );
     assert_eq!(produced.len(), 1, "continue should only append assistant");
@@ -1510,6 +2011,7 @@ async fn agent_session_prompt_streaming_keeps_final_thinking_when_only_text_delt
             AgentSessionStreamUpdate::AssistantLine(
                 "[thinking] Need to inspect repository structure first.".to_string(),
             ),
+            AgentSessionStreamUpdate::AssistantMessageEnd("hello world".to_string()),
         ]
     );
     assert_eq!(produced.len(), 2, "user + assistant");
@@ -1663,3 +2165,195 @@ async fn agent_session_select_model_maps_to_next_catalog_entry() {
     assert_eq!(selected.id, "model-b");
     assert_eq!(session.current_model().id, "model-b");
 }
+
+#[tokio::test]
+async fn agent_session_serves_tools_and_prompts_over_mcp() {
+    let dir = tempdir().expect("tempdir");
+    let session_dir = dir.path().join("sessions");
+    let stream_fn = Arc::new(
+        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let msg = assistant_message(
+                vec![AssistantContentBlock::Text {
+                    text: "all done".to_string(),
+                    text_signature: None,
+                }],
+                StopReason::Stop,
+                1_700_000_000_010,
+            );
+            Ok(done_stream(msg, DoneReason::Stop))
+        },
+    );
+    let manager = SessionManager::create(dir.path().to_str().expect("cwd utf-8"), &session_dir)
+        .expect("create manager");
+    let mut session = AgentSession::new(
+        manager,
+        AgentSessionConfig {
+            model: sample_model("test-api"),
+            system_prompt: "You are helpful".to_string(),
+            stream_fn,
+            tools: create_coding_tools(dir.path()),
+        },
+    );
+
+    let requests = [
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "protocolVersion": "2024-11-05" } }),
+        json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": { "name": "write", "arguments": { "path": "note.txt", "content": "hello" } } }),
+        json!({ "jsonrpc": "2.0", "id": 4, "method": "tools/call", "params": { "name": "read", "arguments": { "path": "missing.txt" } } }),
+        json!({ "jsonrpc": "2.0", "id": 5, "method": "tools/call", "params": { "name": "prompt", "arguments": { "message": "finish up" } } }),
+        json!({ "jsonrpc": "2.0", "id": 6, "method": "prompts/list" }),
+        json!({ "jsonrpc": "2.0", "id": 7, "method": "resources/list" }),
+    ];
+    let input = requests
+        .iter()
+        .map(|request| format!("{request}\n"))
+        .collect::<String>();
+    let mut output = Vec::new();
+    serve_mcp_session(&mut session, input.as_bytes(), &mut output)
+        .await
+        .expect("serve succeeds");
+
+    let responses: Vec<serde_json::Value> = String::from_utf8(output)
+        .expect("utf-8 output")
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("json response"))
+        .collect();
+    let ids: Vec<i64> = responses
+        .iter()
+        .map(|response| response["id"].as_i64().expect("id"))
+        .collect();
+    assert_eq!(ids, vec![1, 2, 3, 4, 5, 6, 7]);
+
+    assert_eq!(responses[0]["result"]["serverInfo"]["name"], "pixy");
+    let tool_names: Vec<&str> = responses[1]["result"]["tools"]
+        .as_array()
+        .expect("tools")
+        .iter()
+        .map(|tool| tool["name"].as_str().expect("tool name"))
+        .collect();
+    assert!(tool_names.contains(&"read"));
+    assert!(tool_names.contains(&"bash"));
+    assert_eq!(tool_names.last(), Some(&"prompt"));
+
+    assert_eq!(responses[2]["result"]["isError"], false);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("note.txt")).expect("note written"),
+        "hello"
+    );
+    assert_eq!(responses[3]["result"]["isError"], true);
+    assert_eq!(
+        responses[4]["result"],
+        json!({ "content": [{ "type": "text", "text": "all done" }], "isError": false })
+    );
+    assert_eq!(session.build_session_context().messages.len(), 2);
+    assert_eq!(responses[5]["result"]["prompts"], json!([]));
+    assert_eq!(responses[6]["error"]["code"], -32601);
+}
+
+#[tokio::test]
+async fn mcp_tool_calls_go_through_the_tool_policy_and_approval() {
+    let dir = tempdir().expect("tempdir");
+    let stream_fn = Arc::new(
+        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let msg = assistant_message(vec![], StopReason::Stop, 1_700_000_000_010);
+            Ok(done_stream(msg, DoneReason::Stop))
+        },
+    );
+    let manager = SessionManager::create(
+        dir.path().to_str().expect("cwd utf-8"),
+        dir.path().join("sessions"),
+    )
+    .expect("create manager");
+    let mut session = AgentSession::new(
+        manager,
+        AgentSessionConfig {
+            model: sample_model("test-api"),
+            system_prompt: "You are helpful".to_string(),
+            stream_fn,
+            tools: create_coding_tools(dir.path()),
+        },
+    );
+    session.set_tool_policy(Some(ToolPolicy {
+        deny_tools: vec!["bash".to_string()],
+        allowed_paths: vec![dir.path().to_path_buf()],
+        ..ToolPolicy::default()
+    }));
+    session.set_approval_tools(HashMap::from([("write".to_string(), ToolRisk::High)]));
+
+    let requests = [
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": { "name": "bash", "arguments": { "command": "touch ran.txt" } } }),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": { "name": "read", "arguments": { "path": "/etc/hostname" } } }),
+        json!({ "jsonrpc": "2.0", "id": 4, "method": "tools/call", "params": { "name": "write", "arguments": { "path": "note.txt", "content": "hello" } } }),
+    ];
+    let input = requests
+        .iter()
+        .map(|request| format!("{request}\n"))
+        .collect::<String>();
+    let mut output = Vec::new();
+    serve_mcp_session(&mut session, input.as_bytes(), &mut output)
+        .await
+        .expect("serve succeeds");
+    let responses: Vec<serde_json::Value> = String::from_utf8(output)
+        .expect("utf-8 output")
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("json response"))
+        .collect();
+
+    let tool_names: Vec<&str> = responses[0]["result"]["tools"]
+        .as_array()
+        .expect("tools")
+        .iter()
+        .map(|tool| tool["name"].as_str().expect("tool name"))
+        .collect();
+    assert!(tool_names.contains(&"read"));
+    assert!(!tool_names.contains(&"bash"));
+    for response in &responses[1..] {
+        assert_eq!(response["result"]["isError"], true, "{response}");
+    }
+    assert!(responses[3]["result"]["content"][0]["text"]
+        .as_str()
+        .expect("text")
+        .contains("needs approval"));
+    assert!(!dir.path().join("ran.txt").exists());
+    assert!(!dir.path().join("note.txt").exists());
+}
+
+#[tokio::test]
+async fn end_session_closes_the_shell_even_when_session_start_never_fired() {
+    let dir = tempdir().expect("tempdir");
+    let stream_fn = Arc::new(
+        move |_model: Model, _context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let msg = assistant_message(vec![], StopReason::Stop, 1_700_000_000_010);
+            Ok(done_stream(msg, DoneReason::Stop))
+        },
+    );
+    let shell = PersistentShell::new();
+    let shell_tool = create_shell_tool(dir.path(), shell.clone());
+    let manager = SessionManager::create(
+        dir.path().to_str().expect("cwd utf-8"),
+        dir.path().join("sessions"),
+    )
+    .expect("create manager");
+    let mut session = AgentSession::new(
+        manager,
+        AgentSessionConfig {
+            model: sample_model("test-api"),
+            system_prompt: "You are helpful".to_string(),
+            stream_fn,
+            tools: vec![shell_tool.clone()],
+        },
+    );
+    session.set_persistent_shell(Some(shell.clone()));
+
+    shell_tool
+        .execute
+        .execute("call".to_string(), json!({ "command": "true" }))
+        .await
+        .expect("shell command runs");
+    session.start_new_session().expect("start new session");
+    session.end_session().await;
+
+    assert!(!shell.close().await, "the shell outlived the session");
+}
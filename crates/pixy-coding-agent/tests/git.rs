@@ -0,0 +1,247 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use pixy_agent_core::AgentTool;
+use pixy_ai::ToolResultContentBlock;
+use pixy_coding_agent::create_git_tools;
+use serde_json::{json, Value};
+use tempfile::tempdir;
+
+fn git(cwd: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(args)
+        .current_dir(cwd)
+        .status()
+        .expect("run git");
+    assert!(status.success(), "git {args:?} failed");
+}
+
+fn init_repo(cwd: &Path) {
+    git(cwd, &["init", "--quiet", "--initial-branch=main"]);
+    git(cwd, &["config", "user.name", "Pixy Test"]);
+    git(cwd, &["config", "user.email", "pixy@example.com"]);
+    git(cwd, &["config", "commit.gpgsign", "false"]);
+}
+
+fn tool<'a>(tools: &'a [AgentTool], name: &str) -> &'a AgentTool {
+    tools
+        .iter()
+        .find(|tool| tool.name == name)
+        .expect("tool exists")
+}
+
+async fn call(tools: &[AgentTool], name: &str, args: Value) -> (String, Value) {
+    let result = tool(tools, name)
+        .execute
+        .execute("call".to_string(), args)
+        .await
+        .unwrap_or_else(|error| panic!("{name} failed: {}", error.message));
+    let text = result
+        .content
+        .iter()
+        .find_map(|block| match block {
+            ToolResultContentBlock::Text { text, .. } => Some(text.clone()),
+            _ => None,
+        })
+        .unwrap_or_default();
+    (text, result.details)
+}
+
+#[tokio::test]
+async fn git_tools_report_status_diff_and_commits() {
+    let dir = tempdir().expect("tempdir");
+    init_repo(dir.path());
+    let tools = create_git_tools(dir.path());
+    let names: Vec<&str> = tools.iter().map(|tool| tool.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "git_status",
+            "git_diff",
+            "git_log",
+            "git_commit",
+            "git_branch"
+        ]
+    );
+
+    fs::write(dir.path().join("app.txt"), "one\ntwo\nthree\n").expect("write");
+    let (text, details) = call(&tools, "git_status", json!({})).await;
+    assert_eq!(
+        text,
+        "On branch main (no commits yet)\nUntracked:\n  app.txt"
+    );
+    assert_eq!(details["untracked"], json!(["app.txt"]));
+
+    let (text, details) = call(
+        &tools,
+        "git_commit",
+        json!({ "message": "Add app\n\nFirst version.", "all": true }),
+    )
+    .await;
+    assert!(text.starts_with("[main "), "{text}");
+    assert!(text.contains("] Add app\napp.txt "), "{text}");
+    assert!(text.ends_with("|    3 ++++++++++++++++++++"), "{text}");
+    assert_eq!(details["subject"], "Add app");
+    assert_eq!(
+        details["files"],
+        json!([{ "path": "app.txt", "insertions": 3, "deletions": 0 }])
+    );
+
+    fs::write(dir.path().join("app.txt"), "one\n2\nthree\n").expect("write");
+    fs::write(dir.path().join("new.txt"), "fresh\n").expect("write");
+    git(dir.path(), &["add", "new.txt"]);
+
+    let (text, details) = call(&tools, "git_status", json!({})).await;
+    assert_eq!(
+        text,
+        "On branch main\nStaged:\n  added: new.txt\nUnstaged:\n  modified: app.txt"
+    );
+    assert_eq!(details["branch"], "main");
+    assert_eq!(
+        details["staged"],
+        json!([{ "path": "new.txt", "status": "added" }])
+    );
+
+    let (text, details) = call(&tools, "git_diff", json!({ "context": 1 })).await;
+    assert!(text.contains("\n1 file changed, 1 insertion(+), 1 deletion(-)\n\ndiff --git"));
+    assert!(text.contains("\n-two\n+2\n"));
+    let file = &details["files"][0];
+    assert_eq!(file["path"], "app.txt");
+    assert_eq!(file["status"], "modified");
+    assert_eq!(
+        file["hunks"],
+        json!([{
+            "oldStart": 1, "oldLines": 3, "newStart": 1, "newLines": 3, "header": "",
+            "lines": [
+                { "kind": "context", "oldLine": 1, "newLine": 1, "text": "one" },
+                { "kind": "delete", "oldLine": 2, "text": "two" },
+                { "kind": "add", "newLine": 2, "text": "2" },
+                { "kind": "context", "oldLine": 3, "newLine": 3, "text": "three" },
+            ],
+        }])
+    );
+
+    let (_, details) = call(&tools, "git_diff", json!({ "staged": true })).await;
+    assert_eq!(details["files"][0]["path"], "new.txt");
+    assert_eq!(details["files"][0]["status"], "added");
+    assert_eq!(details["insertions"], 1);
+
+    let (_, details) = call(
+        &tools,
+        "git_commit",
+        json!({ "message": "Update app", "paths": ["app.txt"] }),
+    )
+    .await;
+    assert_eq!(details["files"].as_array().map(Vec::len), Some(2));
+
+    let (text, details) = call(&tools, "git_log", json!({ "maxCount": 5 })).await;
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with(" Pixy Test: Update app"), "{text}");
+    assert!(lines[1].ends_with(" Pixy Test: Add app"), "{text}");
+    assert_eq!(details["commits"][1]["subject"], "Add app");
+    assert_eq!(details["commits"][0]["email"], "pixy@example.com");
+
+    let (text, _) = call(&tools, "git_status", json!({})).await;
+    assert_eq!(text, "On branch main\nWorking tree clean.");
+    let (text, _) = call(&tools, "git_diff", json!({})).await;
+    assert_eq!(text, "No changes.");
+
+    let error = tool(&tools, "git_commit")
+        .execute
+        .execute("call".to_string(), json!({ "message": "Nothing" }))
+        .await
+        .expect_err("empty commits fail");
+    assert!(error.message.contains("`git commit"), "{}", error.message);
+}
+
+#[tokio::test]
+async fn git_branch_tool_lists_creates_switches_and_deletes() {
+    let dir = tempdir().expect("tempdir");
+    init_repo(dir.path());
+    fs::write(dir.path().join("a.txt"), "a\n").expect("write");
+    git(dir.path(), &["add", "."]);
+    git(dir.path(), &["commit", "--quiet", "-m", "Initial"]);
+    let tools = create_git_tools(dir.path());
+
+    let (text, _) = call(
+        &tools,
+        "git_branch",
+        json!({ "action": "create", "name": "feature" }),
+    )
+    .await;
+    assert_eq!(text, "Created branch feature and switched to it.");
+
+    let (text, details) = call(&tools, "git_branch", json!({})).await;
+    let lines: Vec<&str> = text.lines().collect();
+    assert!(lines[0].starts_with("* feature "), "{text}");
+    assert!(lines[0].ends_with(" Initial"), "{text}");
+    assert!(lines[1].starts_with("  main "), "{text}");
+    assert_eq!(details["branches"][0]["current"], true);
+    assert_eq!(details["branches"][1]["name"], "main");
+
+    call(
+        &tools,
+        "git_branch",
+        json!({ "action": "switch", "name": "main" }),
+    )
+    .await;
+    let (text, _) = call(
+        &tools,
+        "git_branch",
+        json!({ "action": "delete", "name": "feature" }),
+    )
+    .await;
+    assert_eq!(text, "Deleted branch feature.");
+
+    let missing_name = tool(&tools, "git_branch")
+        .execute
+        .execute("call".to_string(), json!({ "action": "switch" }))
+        .await
+        .expect_err("name is required");
+    assert_eq!(
+        missing_name.message,
+        "`name` is required to switch a branch"
+    );
+
+    let unknown = tool(&tools, "git_branch")
+        .execute
+        .execute("call".to_string(), json!({ "action": "rebase" }))
+        .await
+        .expect_err("unknown actions are rejected");
+    assert!(unknown.message.contains("action"), "{}", unknown.message);
+}
+
+#[tokio::test]
+async fn git_tools_refuse_options_in_place_of_refs() {
+    let dir = tempdir().expect("tempdir");
+    init_repo(dir.path());
+    fs::write(dir.path().join("app.txt"), "one\n").expect("write");
+    git(dir.path(), &["add", "app.txt"]);
+    git(dir.path(), &["commit", "--quiet", "-m", "Add app"]);
+    let tools = create_git_tools(dir.path());
+    let target = dir.path().join("pwned");
+    let output_option = format!("--output={}", target.display());
+
+    for (name, args) in [
+        ("git_diff", json!({ "base": output_option })),
+        ("git_log", json!({ "revision": output_option })),
+        (
+            "git_branch",
+            json!({ "action": "create", "name": "--orphan=x" }),
+        ),
+        (
+            "git_branch",
+            json!({ "action": "create", "name": "topic", "startPoint": "--detach" }),
+        ),
+    ] {
+        let error = tool(&tools, name)
+            .execute
+            .execute("call".to_string(), args)
+            .await
+            .expect_err("option refused");
+        assert!(error.message.contains("not an option"), "{}", error.message);
+    }
+    assert!(!target.exists());
+}
@@ -0,0 +1,261 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use pixy_agent_core::AgentTool;
+use pixy_ai::ToolResultContentBlock;
+use pixy_coding_agent::{load_mcp_tools, McpConfig, McpServerConfig};
+use serde_json::{json, Value};
+use tempfile::tempdir;
+
+/// Answers each request by matching its method; `serde_json` sorts keys, so `id` comes first.
+const STUB_SERVER: &str = r#"
+reply() { printf '{"jsonrpc":"2.0","id":%s,"result":%s}\n' "$id" "$1"; }
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/^{"id":\([0-9]*\),.*/\1/p')
+  [ -z "$id" ] && continue
+  case "$line" in
+    *'"method":"initialize"'*) reply '{"protocolVersion":"2024-11-05","capabilities":{"tools":{},"resources":{},"prompts":{}},"serverInfo":{"name":"stub","version":"1"}}' ;;
+    *'"method":"tools/list"'*)
+      case "$line" in
+        *'"cursor"'*) reply '{"tools":[{"name":"fail"}]}' ;;
+        *) reply '{"tools":[{"name":"echo","description":"Echo text.","inputSchema":{"type":"object","properties":{"text":{"type":"string"}},"required":["text"]}}],"nextCursor":"2"}' ;;
+      esac ;;
+    *'"name":"fail"'*) reply '{"content":[{"type":"text","text":"it broke"}],"isError":true}' ;;
+    *'"method":"tools/call"'*)
+      text=$(printf '%s' "$line" | sed -n 's/.*"text":"\([^"]*\)".*/\1/p')
+      reply "{\"content\":[{\"type\":\"text\",\"text\":\"echo: $text\"}]}" ;;
+    *'"method":"resources/list"'*) reply '{"resources":[{"uri":"memo://readme","name":"readme"}]}' ;;
+    *'"method":"resources/read"'*) reply '{"contents":[{"uri":"memo://readme","text":"hello from a resource"}]}' ;;
+    *'"method":"prompts/list"'*) reply '{"prompts":[{"name":"review","arguments":[{"name":"file","required":true}]}]}' ;;
+    *'"method":"prompts/get"'*) reply '{"messages":[{"role":"user","content":{"type":"text","text":"Review the file."}}]}' ;;
+    *) printf '{"jsonrpc":"2.0","id":%s,"error":{"code":-32601,"message":"unknown"}}\n' "$id" ;;
+  esac
+done
+"#;
+
+fn first_text(blocks: &[ToolResultContentBlock]) -> String {
+    blocks
+        .iter()
+        .find_map(|block| match block {
+            ToolResultContentBlock::Text { text, .. } => Some(text.clone()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+fn find_tool<'a>(tools: &'a [AgentTool], name: &str) -> &'a AgentTool {
+    tools
+        .iter()
+        .find(|tool| tool.name == name)
+        .unwrap_or_else(|| panic!("missing tool {name}"))
+}
+
+fn config(servers: impl IntoIterator<Item = (&'static str, McpServerConfig)>) -> McpConfig {
+    McpConfig {
+        servers: servers
+            .into_iter()
+            .map(|(name, server)| (name.to_string(), server))
+            .collect::<BTreeMap<_, _>>(),
+    }
+}
+
+#[tokio::test]
+async fn mcp_stdio_server_tools_resources_and_prompts_become_agent_tools() {
+    let dir = tempdir().expect("tempdir");
+    let script = dir.path().join("server.sh");
+    std::fs::write(&script, STUB_SERVER).expect("write stub server");
+    let loaded = load_mcp_tools(
+        dir.path(),
+        &config([(
+            "stub",
+            McpServerConfig {
+                command: Some("sh".to_string()),
+                args: vec![script.to_string_lossy().into_owned()],
+                ..McpServerConfig::default()
+            },
+        )]),
+    );
+
+    assert!(loaded.errors.is_empty(), "{:?}", loaded.errors);
+    let names: Vec<&str> = loaded.tools.iter().map(|tool| tool.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "mcp__stub__echo",
+            "mcp__stub__fail",
+            "mcp__stub__read_resource",
+            "mcp__stub__get_prompt",
+        ]
+    );
+
+    let echo = find_tool(&loaded.tools, "mcp__stub__echo");
+    assert_eq!(echo.description, "Echo text.");
+    assert_eq!(echo.parameters["required"], json!(["text"]));
+    let result = echo
+        .execute
+        .execute("call-1".to_string(), json!({ "text": "hi" }))
+        .await
+        .expect("echo succeeds");
+    assert_eq!(first_text(&result.content), "echo: hi");
+    assert_eq!(result.details["server"], "stub");
+
+    let error = find_tool(&loaded.tools, "mcp__stub__fail")
+        .execute
+        .execute("call-2".to_string(), json!({}))
+        .await
+        .expect_err("fail reports an error");
+    assert!(error.message.contains("it broke"), "{}", error.message);
+
+    let read_resource = find_tool(&loaded.tools, "mcp__stub__read_resource");
+    assert!(read_resource
+        .description
+        .contains("- memo://readme (readme)"));
+    let result = read_resource
+        .execute
+        .execute("call-3".to_string(), json!({ "uri": "memo://readme" }))
+        .await
+        .expect("read succeeds");
+    assert_eq!(first_text(&result.content), "hello from a resource");
+
+    let get_prompt = find_tool(&loaded.tools, "mcp__stub__get_prompt");
+    assert!(get_prompt.description.contains("- review(file)"));
+    assert_eq!(
+        get_prompt.parameters["properties"]["name"]["enum"],
+        json!(["review"])
+    );
+    let result = get_prompt
+        .execute
+        .execute(
+            "call-4".to_string(),
+            json!({ "name": "review", "arguments": { "file": "main.rs" } }),
+        )
+        .await
+        .expect("prompt succeeds");
+    assert_eq!(first_text(&result.content), "[user]\nReview the file.");
+}
+
+#[test]
+fn mcp_servers_that_cannot_start_are_reported_and_skipped() {
+    let dir = tempdir().expect("tempdir");
+    let loaded = load_mcp_tools(
+        dir.path(),
+        &config([
+            (
+                "missing",
+                McpServerConfig {
+                    command: Some("pixy-missing-mcp-server".to_string()),
+                    ..McpServerConfig::default()
+                },
+            ),
+            ("unset", McpServerConfig::default()),
+        ]),
+    );
+
+    assert!(loaded.tools.is_empty());
+    assert_eq!(loaded.errors.len(), 2);
+    assert!(loaded.errors[0].starts_with("MCP server 'missing': failed to start"));
+    assert_eq!(
+        loaded.errors[1],
+        "MCP server 'unset': needs a `command` or a `url`"
+    );
+}
+
+/// Serves the SSE transport: `GET /sse` opens the event stream, whose first event names
+/// `/messages`, and responses to posted requests are sent as `message` events on it.
+fn start_sse_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+    let address = listener.local_addr().expect("address");
+    let events: Arc<Mutex<Option<TcpStream>>> = Arc::default();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let events = events.clone();
+            thread::spawn(move || serve_sse_connection(stream, events));
+        }
+    });
+    format!("http://{address}/sse")
+}
+
+fn serve_sse_connection(stream: TcpStream, events: Arc<Mutex<Option<TcpStream>>>) {
+    let mut writer = stream.try_clone().expect("clone stream");
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+            return;
+        }
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).expect("header");
+            if header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().expect("content length");
+                }
+            }
+        }
+
+        if request_line.starts_with("GET /sse ") {
+            writer
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\r\nevent: endpoint\ndata: /messages\n\n",
+                )
+                .expect("open events");
+            *events.lock().expect("events") = Some(writer);
+            return;
+        }
+
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).expect("body");
+        let request: Value = serde_json::from_slice(&body).expect("json body");
+        let result = match request["method"].as_str() {
+            Some("initialize") => Some(json!({ "capabilities": { "tools": {} } })),
+            Some("tools/list") => Some(json!({ "tools": [{ "name": "add" }] })),
+            Some("tools/call") => {
+                let sum = request["params"]["arguments"]["a"].as_i64().unwrap_or(0)
+                    + request["params"]["arguments"]["b"].as_i64().unwrap_or(0);
+                Some(json!({ "content": [{ "type": "text", "text": sum.to_string() }] }))
+            }
+            _ => None,
+        };
+        if let Some(result) = result {
+            let response = json!({ "jsonrpc": "2.0", "id": request["id"], "result": result });
+            let mut events = events.lock().expect("events");
+            let stream = events.as_mut().expect("event stream is open");
+            write!(stream, "event: message\ndata: {response}\n\n").expect("send event");
+            stream.flush().expect("flush event");
+        }
+        writer
+            .write_all(b"HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\n\r\n")
+            .expect("accept");
+    }
+}
+
+#[tokio::test]
+async fn mcp_sse_server_tools_are_called_over_the_event_stream() {
+    let dir = tempdir().expect("tempdir");
+    let loaded = load_mcp_tools(
+        dir.path(),
+        &config([(
+            "remote",
+            McpServerConfig {
+                url: Some(start_sse_server()),
+                ..McpServerConfig::default()
+            },
+        )]),
+    );
+
+    assert!(loaded.errors.is_empty(), "{:?}", loaded.errors);
+    assert_eq!(loaded.tools.len(), 1);
+    let result = find_tool(&loaded.tools, "mcp__remote__add")
+        .execute
+        .execute("call-1".to_string(), json!({ "a": 2, "b": 3 }))
+        .await
+        .expect("add succeeds");
+    assert_eq!(first_text(&result.content), "5");
+}
@@ -1,15 +1,63 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
 use chrono::{Days, Local, NaiveDate};
+use pixy_coding_agent::memory::chunk_memory_file;
 use pixy_coding_agent::memory::prelude::*;
 use tempfile::tempdir;
 
+/// Maps words onto a few topic axes so related wording lands close together.
+struct TopicEmbedder {
+    embedded_inputs: AtomicUsize,
+}
+
+impl TopicEmbedder {
+    fn new() -> Self {
+        Self {
+            embedded_inputs: AtomicUsize::new(0),
+        }
+    }
+
+    fn vector(text: &str) -> Vec<f32> {
+        const TOPICS: [&[&str]; 3] = [
+            &["sqlite", "postgres", "database", "storage", "persistence"],
+            &["terminal", "tui", "ratatui", "interface", "rendering"],
+            &["deploy", "release", "ci", "pipeline", "shipping"],
+        ];
+        let lower = text.to_lowercase();
+        TOPICS
+            .iter()
+            .map(|words| words.iter().filter(|word| lower.contains(*word)).count() as f32)
+            .collect()
+    }
+}
+
+#[async_trait]
+impl MemoryEmbedder for TopicEmbedder {
+    fn model_key(&self) -> String {
+        "test/topics".to_string()
+    }
+
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        self.embedded_inputs
+            .fetch_add(inputs.len(), Ordering::SeqCst);
+        Ok(inputs.iter().map(|input| Self::vector(input)).collect())
+    }
+}
+
 #[test]
 fn memory_config_validation_rejects_invalid_search_settings() {
-    let mut config = MemoryConfig::default();
-    config.search_max_results = 0;
+    let config = MemoryConfig {
+        search_max_results: 0,
+        ..MemoryConfig::default()
+    };
     assert!(config.validate().is_err());
 
-    let mut config = MemoryConfig::default();
-    config.search_min_score = 1.5;
+    let config = MemoryConfig {
+        search_min_score: 1.5,
+        ..MemoryConfig::default()
+    };
     assert!(config.validate().is_err());
 }
 
@@ -85,3 +133,51 @@ fn memory_flush_persists_summary_and_metadata() -> Result<(), Box<dyn std::error
     assert!(content.contains("\"source\": \"unit-test\""));
     Ok(())
 }
+
+#[test]
+fn memory_files_are_chunked_at_headings() {
+    let date = NaiveDate::from_ymd_opt(2026, 1, 2).expect("valid date");
+    let chunks = chunk_memory_file(
+        std::path::Path::new("2026-01-02.md"),
+        date,
+        "# 2026-01-02\n\nfirst note\n\n## Second\nsecond note\n",
+    );
+
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(chunks[0].line, 1);
+    assert!(chunks[0].text.contains("first note"));
+    assert_eq!(chunks[1].line, 5);
+    assert!(chunks[1].text.starts_with("## Second"));
+}
+
+#[tokio::test]
+async fn semantic_index_ranks_related_entries_and_caches_vectors(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = tempdir()?;
+    let manager = MemoryManager::new(MemoryConfig::new(temp_dir.path()))?;
+    manager.record("## Storage\nWe picked SQLite for session persistence.")?;
+    manager.record("## UI\nThe ratatui interface redraws on every tick.")?;
+
+    let embedder = Arc::new(TopicEmbedder::new());
+    let index = SemanticMemoryIndex::new(embedder.clone(), temp_dir.path());
+    let chunks = manager.chunks()?;
+
+    let results = index
+        .search("which database do we use?", &chunks, 5, 0.5)
+        .await?;
+    assert_eq!(results.len(), 1);
+    assert!(results[0].snippet.contains("SQLite"));
+    assert!(temp_dir.path().join(".embeddings.json").exists());
+
+    let embedded_after_first_search = embedder.embedded_inputs.load(Ordering::SeqCst);
+    let reloaded = SemanticMemoryIndex::new(embedder.clone(), temp_dir.path());
+    reloaded
+        .search("terminal rendering", &chunks, 5, 0.5)
+        .await?;
+    assert_eq!(
+        embedder.embedded_inputs.load(Ordering::SeqCst),
+        embedded_after_first_search + 1,
+        "only the query should be embedded when chunk vectors are cached"
+    );
+    Ok(())
+}
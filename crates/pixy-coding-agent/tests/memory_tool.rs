@@ -1,9 +1,10 @@
 use std::sync::{Arc, Mutex};
 
+use async_trait::async_trait;
 use pixy_ai::ToolResultContentBlock;
 use pixy_coding_agent::{
-    create_memory_tool,
-    memory::{MemoryConfig, MemoryManager},
+    create_memory_tool, create_memory_tool_with_semantic_index,
+    memory::{MemoryConfig, MemoryEmbedder, MemoryManager, SemanticMemoryIndex},
 };
 use serde_json::json;
 use tempfile::tempdir;
@@ -147,3 +148,87 @@ async fn memory_tool_rejects_unknown_action() {
         .expect_err("unknown action should fail");
     assert_eq!(error.code, pixy_ai::PiAiErrorCode::ToolArgumentsInvalid);
 }
+
+struct FailingEmbedder;
+
+#[async_trait]
+impl MemoryEmbedder for FailingEmbedder {
+    fn model_key(&self) -> String {
+        "test/failing".to_string()
+    }
+
+    async fn embed(&self, _inputs: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        Err("embedding service unavailable".to_string())
+    }
+}
+
+struct LengthEmbedder;
+
+#[async_trait]
+impl MemoryEmbedder for LengthEmbedder {
+    fn model_key(&self) -> String {
+        "test/length".to_string()
+    }
+
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        Ok(inputs.iter().map(|_| vec![1.0, 0.5]).collect())
+    }
+}
+
+#[tokio::test]
+async fn memory_tool_uses_semantic_index_and_falls_back_to_keywords() {
+    let dir = tempdir().expect("tempdir");
+    let manager = Arc::new(Mutex::new(
+        MemoryManager::new(MemoryConfig::new(dir.path())).expect("memory manager"),
+    ));
+
+    let semantic_tool = create_memory_tool_with_semantic_index(
+        manager.clone(),
+        Some(Arc::new(SemanticMemoryIndex::new(
+            Arc::new(LengthEmbedder),
+            dir.path(),
+        ))),
+        10,
+        0.0,
+    );
+    semantic_tool
+        .execute
+        .execute(
+            "call-1".to_string(),
+            json!({ "action": "record", "content": "Release checklist lives in docs." }),
+        )
+        .await
+        .expect("record action should succeed");
+    assert!(dir.path().join(".embeddings.json").exists());
+
+    let search = semantic_tool
+        .execute
+        .execute(
+            "call-2".to_string(),
+            json!({ "action": "search", "query": "how do we ship?" }),
+        )
+        .await
+        .expect("semantic search should succeed");
+    assert_eq!(search.details["mode"], "semantic");
+    assert!(first_text(&search.content).contains("Release checklist"));
+
+    let fallback_tool = create_memory_tool_with_semantic_index(
+        manager,
+        Some(Arc::new(SemanticMemoryIndex::new(
+            Arc::new(FailingEmbedder),
+            dir.path(),
+        ))),
+        10,
+        0.0,
+    );
+    let search = fallback_tool
+        .execute
+        .execute(
+            "call-3".to_string(),
+            json!({ "action": "search", "query": "checklist" }),
+        )
+        .await
+        .expect("keyword fallback should succeed");
+    assert_eq!(search.details["mode"], "keyword");
+    assert!(first_text(&search.content).contains("Release checklist"));
+}
@@ -1,16 +1,20 @@
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use pixy_agent_core::ParentChildRunEvent;
+use pixy_agent_core::{
+    ApprovalDecision, ParentChildRunEvent, ToolApprovalRequest, ToolPolicy, ToolRisk,
+};
 use pixy_ai::{
     AssistantContentBlock, AssistantMessage, AssistantMessageEvent, AssistantMessageEventStream,
-    Context, Cost, DoneReason, Message, Model, StopReason, Usage,
+    Context, Cost, DoneReason, Message, Model, StopReason, ToolResultContentBlock, Usage,
 };
 use pixy_coding_agent::{
-    create_task_tool, AgentSession, AgentSessionConfig, ChildSessionStore, DefaultSubAgentRegistry,
-    DispatchPolicyConfig, DispatchPolicyRule, MultiAgentPluginRuntime, PolicyRuleEffect,
-    SessionManager, SubAgentMode, SubAgentResolver, SubAgentSpec, TaskDispatcher,
-    TaskDispatcherConfig,
+    create_read_tool, create_shell_tool, create_task_tool, AgentSession, AgentSessionConfig,
+    ChildSessionControls, ChildSessionStore, ChildShellToolFn, DefaultSubAgentRegistry,
+    DispatchPolicyConditions, DispatchPolicyConfig, DispatchPolicyRule, MultiAgentPluginRuntime,
+    PersistentShell, PolicyRuleEffect, SessionManager, SharedChildSessionControls, SubAgentMode,
+    SubAgentResolver, SubAgentSpec, TaskDispatcher, TaskDispatcherConfig,
 };
 use serde_json::json;
 use tempfile::tempdir;
@@ -35,6 +39,9 @@ fn sample_model() -> Model {
         },
         context_window: 128_000,
         max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
     }
 }
 
@@ -44,6 +51,7 @@ fn sample_usage() -> Usage {
         output: 1,
         cache_read: 0,
         cache_write: 0,
+        reasoning: 0,
         total_tokens: 2,
         cost: Cost {
             input: 0.0,
@@ -69,6 +77,7 @@ fn assistant_message(
         stop_reason,
         error_message: None,
         timestamp: 1,
+        stats: None,
     }
 }
 
@@ -172,6 +181,8 @@ async fn parent_tool_call_roundtrips_into_child_and_back() {
         system_prompt: "You are parent".to_string(),
         stream_fn: stream_fn.clone(),
         child_tools: vec![],
+        child_shell_tool: None,
+        child_controls: Default::default(),
         subagent_registry: registry(),
         session_store: store.clone(),
         dispatch_policy: DispatchPolicyConfig::default(),
@@ -279,6 +290,8 @@ async fn repeated_task_id_reuses_child_session_history() {
         system_prompt: "You are parent".to_string(),
         stream_fn: stream_fn.clone(),
         child_tools: vec![],
+        child_shell_tool: None,
+        child_controls: Default::default(),
         subagent_registry: registry(),
         session_store: store.clone(),
         dispatch_policy: DispatchPolicyConfig::default(),
@@ -375,10 +388,13 @@ async fn policy_fallback_routes_unknown_subagent_to_configured_default() {
         system_prompt: "You are parent".to_string(),
         stream_fn: stream_fn.clone(),
         child_tools: vec![],
+        child_shell_tool: None,
+        child_controls: Default::default(),
         subagent_registry: registry(),
         session_store: store,
         dispatch_policy: DispatchPolicyConfig {
             fallback_subagent: Some("general".to_string()),
+            budget_usd: None,
             rules: vec![],
         },
         plugin_runtime: Arc::new(MultiAgentPluginRuntime::default()),
@@ -461,14 +477,19 @@ async fn policy_block_returns_explicit_blocked_error_details() {
         system_prompt: "You are parent".to_string(),
         stream_fn: stream_fn.clone(),
         child_tools: vec![],
+        child_shell_tool: None,
+        child_controls: Default::default(),
         subagent_registry: registry(),
         session_store: Arc::new(Mutex::new(ChildSessionStore::new("parent-session"))),
         dispatch_policy: DispatchPolicyConfig {
             fallback_subagent: None,
+            budget_usd: None,
             rules: vec![DispatchPolicyRule {
                 subagent: "general".to_string(),
                 tool: "task".to_string(),
                 effect: PolicyRuleEffect::Deny,
+                route_to: None,
+                conditions: DispatchPolicyConditions::default(),
             }],
         },
         plugin_runtime: Arc::new(MultiAgentPluginRuntime::default()),
@@ -569,6 +590,8 @@ async fn lifecycle_events_emit_child_run_start_and_end_with_task_id_correlation(
         system_prompt: "You are parent".to_string(),
         stream_fn: stream_fn.clone(),
         child_tools: vec![],
+        child_shell_tool: None,
+        child_controls: Default::default(),
         subagent_registry: registry(),
         session_store: Arc::new(Mutex::new(ChildSessionStore::new("parent-session"))),
         dispatch_policy: DispatchPolicyConfig::default(),
@@ -611,6 +634,123 @@ async fn lifecycle_events_emit_child_run_start_and_end_with_task_id_correlation(
     );
 }
 
+#[tokio::test]
+async fn lifecycle_events_report_child_tool_calls_while_the_child_runs() {
+    let dir = tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("note.txt"), "hello").expect("seed note");
+    let events = Arc::new(std::sync::Mutex::new(Vec::<ParentChildRunEvent>::new()));
+    let events_for_sink = events.clone();
+    let stream_fn = Arc::new(
+        move |_model: Model, context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let is_child = context
+                .system_prompt
+                .as_deref()
+                .unwrap_or_default()
+                .contains("<subagent_context>");
+            let message = if has_tool_result_after_latest_user(&context) {
+                assistant_message(
+                    vec![AssistantContentBlock::Text {
+                        text: "done".to_string(),
+                        text_signature: None,
+                    }],
+                    StopReason::Stop,
+                )
+            } else if is_child {
+                assistant_message(
+                    vec![AssistantContentBlock::ToolCall {
+                        id: "child-read".to_string(),
+                        name: "read".to_string(),
+                        arguments: json!({ "path": "note.txt" }),
+                        thought_signature: None,
+                    }],
+                    StopReason::ToolUse,
+                )
+            } else {
+                assistant_message(
+                    vec![AssistantContentBlock::ToolCall {
+                        id: "task-call-event-3".to_string(),
+                        name: "task".to_string(),
+                        arguments: json!({
+                            "subagent_type": "general",
+                            "prompt": "read the note",
+                            "task_id": "task-event-3"
+                        }),
+                        thought_signature: None,
+                    }],
+                    StopReason::ToolUse,
+                )
+            };
+            let reason = match message.stop_reason {
+                StopReason::ToolUse => DoneReason::ToolUse,
+                _ => DoneReason::Stop,
+            };
+            Ok(done_stream(message, reason))
+        },
+    );
+    let dispatcher = Arc::new(TaskDispatcher::new(TaskDispatcherConfig {
+        cwd: dir.path().to_path_buf(),
+        parent_session_id: "parent-session".to_string(),
+        parent_session_dir: dir.path().to_path_buf(),
+        model: sample_model(),
+        model_catalog: vec![sample_model()],
+        system_prompt: "You are parent".to_string(),
+        stream_fn: stream_fn.clone(),
+        child_tools: vec![create_read_tool(dir.path())],
+        child_shell_tool: None,
+        child_controls: Default::default(),
+        subagent_registry: registry(),
+        session_store: Arc::new(Mutex::new(ChildSessionStore::new("parent-session"))),
+        dispatch_policy: DispatchPolicyConfig::default(),
+        plugin_runtime: Arc::new(MultiAgentPluginRuntime::default()),
+        lifecycle_event_sink: Some(Arc::new(move |event| {
+            events_for_sink.lock().expect("lock events").push(event);
+        })),
+    }));
+
+    let mut session = AgentSession::new(
+        SessionManager::create(
+            dir.path().to_str().expect("utf-8 cwd"),
+            dir.path().join("sessions"),
+        )
+        .expect("create session"),
+        AgentSessionConfig {
+            model: sample_model(),
+            system_prompt: "You are parent".to_string(),
+            stream_fn,
+            tools: vec![create_task_tool(dispatcher)],
+        },
+    );
+    session
+        .prompt("trigger child run")
+        .await
+        .expect("prompt succeeds");
+
+    let events = events.lock().expect("lock events");
+    let kinds = events
+        .iter()
+        .map(ParentChildRunEvent::kind)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec!["child_run_start", "child_tool_start", "child_run_end"]
+    );
+    match &events[1] {
+        ParentChildRunEvent::ChildToolStart {
+            task_id,
+            subagent,
+            tool_name,
+            headline,
+            ..
+        } => {
+            assert_eq!(task_id, "task-event-3");
+            assert_eq!(subagent, "general");
+            assert_eq!(tool_name, "read");
+            assert_eq!(headline, "• Ran read note.txt");
+        }
+        other => panic!("unexpected event: {other:?}"),
+    }
+}
+
 #[tokio::test]
 async fn lifecycle_events_emit_child_run_error_with_task_id_correlation() {
     let dir = tempdir().expect("tempdir");
@@ -664,6 +804,8 @@ async fn lifecycle_events_emit_child_run_error_with_task_id_correlation() {
         system_prompt: "You are parent".to_string(),
         stream_fn: stream_fn.clone(),
         child_tools: vec![],
+        child_shell_tool: None,
+        child_controls: Default::default(),
         subagent_registry: registry(),
         session_store: Arc::new(Mutex::new(ChildSessionStore::new("parent-session"))),
         dispatch_policy: DispatchPolicyConfig::default(),
@@ -752,3 +894,295 @@ async fn single_agent_mode_keeps_existing_flow_without_task_tool() {
         )
     }));
 }
+
+#[tokio::test]
+async fn every_child_run_gets_its_own_shell_closed_when_the_run_ends() {
+    let dir = tempdir().expect("tempdir");
+
+    let stream_fn = Arc::new(
+        move |_model: Model, context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let is_child = context
+                .system_prompt
+                .as_deref()
+                .unwrap_or_default()
+                .contains("<subagent_context>");
+            let tool_results = context
+                .messages
+                .iter()
+                .filter_map(|message| match message {
+                    Message::ToolResult { content, .. } => Some(
+                        content
+                            .iter()
+                            .filter_map(|block| match block {
+                                ToolResultContentBlock::Text { text, .. } => Some(text.as_str()),
+                                _ => None,
+                            })
+                            .collect::<String>(),
+                    ),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            if is_child {
+                if let Some(output) = tool_results.last() {
+                    let message = assistant_message(
+                        vec![AssistantContentBlock::Text {
+                            text: output.clone(),
+                            text_signature: None,
+                        }],
+                        StopReason::Stop,
+                    );
+                    return Ok(done_stream(message, DoneReason::Stop));
+                }
+                let message = assistant_message(
+                    vec![AssistantContentBlock::ToolCall {
+                        id: "shell-call".to_string(),
+                        name: "shell".to_string(),
+                        arguments: json!({
+                            "command": "echo \"seen=${PIXY_TEST_MARK:-none}\"; export PIXY_TEST_MARK=child"
+                        }),
+                        thought_signature: None,
+                    }],
+                    StopReason::ToolUse,
+                );
+                return Ok(done_stream(message, DoneReason::ToolUse));
+            }
+
+            if tool_results.len() == 2 {
+                let message = assistant_message(
+                    vec![AssistantContentBlock::Text {
+                        text: "both children finished".to_string(),
+                        text_signature: None,
+                    }],
+                    StopReason::Stop,
+                );
+                return Ok(done_stream(message, DoneReason::Stop));
+            }
+            let message = assistant_message(
+                vec![AssistantContentBlock::ToolCall {
+                    id: format!("task-call-{}", tool_results.len()),
+                    name: "task".to_string(),
+                    arguments: json!({
+                        "subagent_type": "general",
+                        "prompt": "check the shell",
+                        "task_id": format!("task-{}", tool_results.len())
+                    }),
+                    thought_signature: None,
+                }],
+                StopReason::ToolUse,
+            );
+            Ok(done_stream(message, DoneReason::ToolUse))
+        },
+    );
+
+    let shells = Arc::new(std::sync::Mutex::new(Vec::<PersistentShell>::new()));
+    let built_shells = shells.clone();
+    let cwd = dir.path().to_path_buf();
+    let child_shell_tool: ChildShellToolFn = Arc::new(move |shell: PersistentShell| {
+        built_shells
+            .lock()
+            .expect("shells lock")
+            .push(shell.clone());
+        create_shell_tool(&cwd, shell)
+    });
+
+    let store = Arc::new(Mutex::new(ChildSessionStore::new("parent-session")));
+    let dispatcher = Arc::new(TaskDispatcher::new(TaskDispatcherConfig {
+        cwd: dir.path().to_path_buf(),
+        parent_session_id: "parent-session".to_string(),
+        parent_session_dir: dir.path().to_path_buf(),
+        model: sample_model(),
+        model_catalog: vec![sample_model()],
+        system_prompt: "You are parent".to_string(),
+        stream_fn: stream_fn.clone(),
+        child_tools: vec![],
+        child_shell_tool: Some(child_shell_tool),
+        child_controls: Default::default(),
+        subagent_registry: registry(),
+        session_store: store.clone(),
+        dispatch_policy: DispatchPolicyConfig::default(),
+        plugin_runtime: Arc::new(MultiAgentPluginRuntime::default()),
+        lifecycle_event_sink: None,
+    }));
+
+    let mut session = AgentSession::new(
+        SessionManager::create(
+            dir.path().to_str().expect("utf-8 cwd"),
+            dir.path().join("sessions"),
+        )
+        .expect("create session"),
+        AgentSessionConfig {
+            model: sample_model(),
+            system_prompt: "You are parent".to_string(),
+            stream_fn,
+            tools: vec![create_task_tool(dispatcher)],
+        },
+    );
+
+    let produced = session
+        .prompt("delegate twice")
+        .await
+        .expect("prompt succeeds");
+    let task_outputs = produced
+        .iter()
+        .filter_map(|message| match message {
+            Message::ToolResult {
+                tool_name,
+                content,
+                is_error: false,
+                ..
+            } if tool_name == "task" => Some(format!("{content:?}")),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(task_outputs.len(), 2, "{produced:?}");
+    // The second child does not see the variable the first one exported.
+    for output in &task_outputs {
+        assert!(output.contains("seen=none"), "{output}");
+    }
+
+    let shells = shells.lock().expect("shells lock").clone();
+    assert_eq!(shells.len(), 2);
+    for shell in &shells {
+        assert!(!shell.close().await, "child shell outlived its run");
+    }
+}
+
+#[tokio::test]
+async fn child_sessions_run_under_the_parent_tool_policy() {
+    let task_output = run_child_read(ChildSessionControls {
+        tool_policy: Some(ToolPolicy {
+            deny_tools: vec!["read".to_string()],
+            ..ToolPolicy::default()
+        }),
+        ..ChildSessionControls::default()
+    })
+    .await;
+    assert!(
+        task_output.contains("not allowed by the tool policy"),
+        "{task_output}"
+    );
+    assert!(!task_output.contains("secret"), "{task_output}");
+}
+
+#[tokio::test]
+async fn child_sessions_refuse_flagged_tools_without_the_parent_approver() {
+    let task_output = run_child_read(ChildSessionControls {
+        approval_tools: HashMap::from([("read".to_string(), ToolRisk::Low)]),
+        ..ChildSessionControls::default()
+    })
+    .await;
+    assert!(
+        task_output.contains("nobody can approve it"),
+        "{task_output}"
+    );
+    assert!(!task_output.contains("secret"), "{task_output}");
+
+    let task_output = run_child_read(ChildSessionControls {
+        approval_tools: HashMap::from([("read".to_string(), ToolRisk::Low)]),
+        tool_approver: Some(Arc::new(|request: ToolApprovalRequest| {
+            request.respond(ApprovalDecision::Approve)
+        })),
+        ..ChildSessionControls::default()
+    })
+    .await;
+    assert!(task_output.contains("secret"), "{task_output}");
+}
+
+/// Runs a child that reads `notes.txt` under `controls` and returns the parent's task result.
+async fn run_child_read(controls: ChildSessionControls) -> String {
+    let dir = tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("notes.txt"), "secret").expect("write notes");
+
+    let stream_fn = Arc::new(
+        move |_model: Model, context: Context, _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let is_child = context
+                .system_prompt
+                .as_deref()
+                .unwrap_or_default()
+                .contains("<subagent_context>");
+            if has_tool_result_after_latest_user(&context) {
+                // The child answers with what its tool returned.
+                let text = context
+                    .messages
+                    .iter()
+                    .rev()
+                    .find_map(|message| match message {
+                        Message::ToolResult { content, .. } => Some(format!("{content:?}")),
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+                let message = assistant_message(
+                    vec![AssistantContentBlock::Text {
+                        text,
+                        text_signature: None,
+                    }],
+                    StopReason::Stop,
+                );
+                return Ok(done_stream(message, DoneReason::Stop));
+            }
+            let (name, arguments) = if is_child {
+                ("read", json!({ "path": "notes.txt" }))
+            } else {
+                (
+                    "task",
+                    json!({ "subagent_type": "general", "prompt": "read the notes" }),
+                )
+            };
+            let message = assistant_message(
+                vec![AssistantContentBlock::ToolCall {
+                    id: format!("{name}-call"),
+                    name: name.to_string(),
+                    arguments,
+                    thought_signature: None,
+                }],
+                StopReason::ToolUse,
+            );
+            Ok(done_stream(message, DoneReason::ToolUse))
+        },
+    );
+
+    let child_controls: SharedChildSessionControls = Arc::new(std::sync::Mutex::new(controls));
+    let store = Arc::new(Mutex::new(ChildSessionStore::new("parent-session")));
+    let dispatcher = Arc::new(TaskDispatcher::new(TaskDispatcherConfig {
+        cwd: dir.path().to_path_buf(),
+        parent_session_id: "parent-session".to_string(),
+        parent_session_dir: dir.path().to_path_buf(),
+        model: sample_model(),
+        model_catalog: vec![sample_model()],
+        system_prompt: "You are parent".to_string(),
+        stream_fn: stream_fn.clone(),
+        child_tools: vec![create_read_tool(dir.path())],
+        child_shell_tool: None,
+        child_controls: child_controls.clone(),
+        subagent_registry: registry(),
+        session_store: store.clone(),
+        dispatch_policy: DispatchPolicyConfig::default(),
+        plugin_runtime: Arc::new(MultiAgentPluginRuntime::default()),
+        lifecycle_event_sink: None,
+    }));
+
+    let mut session = AgentSession::new(
+        SessionManager::create(
+            dir.path().to_str().expect("utf-8 cwd"),
+            dir.path().join("sessions"),
+        )
+        .expect("create session"),
+        AgentSessionConfig {
+            model: sample_model(),
+            system_prompt: "You are parent".to_string(),
+            stream_fn,
+            tools: vec![create_task_tool(dispatcher)],
+        },
+    );
+    let produced = session.prompt("delegate").await.expect("prompt succeeds");
+    produced
+        .iter()
+        .find_map(|message| match message {
+            Message::ToolResult {
+                tool_name, content, ..
+            } if tool_name == "task" => Some(format!("{content:?}")),
+            _ => None,
+        })
+        .expect("task result")
+}
@@ -2,8 +2,9 @@ use std::fs;
 
 use pixy_ai::{Message, StopReason, UserContent};
 use pixy_coding_agent::{
-    SessionManager, BRANCH_SUMMARY_PREFIX, BRANCH_SUMMARY_SUFFIX, COMPACTION_SUMMARY_PREFIX,
-    COMPACTION_SUMMARY_SUFFIX, CURRENT_SESSION_VERSION,
+    fsck_session_file, SessionIssueKind, SessionManager, BRANCH_SUMMARY_PREFIX,
+    BRANCH_SUMMARY_SUFFIX, COMPACTION_SUMMARY_PREFIX, COMPACTION_SUMMARY_SUFFIX,
+    CURRENT_SESSION_VERSION,
 };
 use serde_json::{json, Value};
 use tempfile::tempdir;
@@ -38,6 +39,7 @@ fn assistant_message_with_stop_reason(
             output: 1,
             cache_read: 0,
             cache_write: 0,
+            reasoning: 0,
             total_tokens: 2,
             cost: pixy_ai::Cost {
                 input: 0.0,
@@ -50,6 +52,7 @@ fn assistant_message_with_stop_reason(
         stop_reason,
         error_message: error_message.map(ToOwned::to_owned),
         timestamp: ts,
+        stats: None,
     }
 }
 
@@ -141,6 +144,51 @@ fn session_manager_branch_changes_leaf_and_context_path() {
     }
 }
 
+#[test]
+fn session_manager_fork_copies_current_path_into_new_session_file() {
+    let dir = tempdir().expect("tempdir");
+    let mut manager = SessionManager::create("/repo", dir.path()).expect("create session manager");
+
+    let first_id = manager
+        .append_message(user_message("root", 1_700_000_000_000))
+        .expect("append first");
+    manager
+        .append_message(assistant_message("abandoned", 1_700_000_000_010))
+        .expect("append second");
+    manager.branch(&first_id).expect("branch to first");
+    manager
+        .append_message(assistant_message("kept", 1_700_000_000_020))
+        .expect("append branch message");
+
+    let mut forked = manager
+        .fork(dir.path(), Some("try-sqlite"))
+        .expect("fork session");
+
+    assert_ne!(forked.session_file(), manager.session_file());
+    assert_eq!(
+        forked.header().parent_session.as_deref(),
+        Some(manager.header().id.as_str())
+    );
+    assert_eq!(forked.session_name().as_deref(), Some("try-sqlite"));
+    assert_eq!(
+        forked.build_session_context(),
+        manager.build_session_context()
+    );
+
+    forked
+        .append_message(user_message("fork-only", 1_700_000_000_030))
+        .expect("append to fork");
+    assert_eq!(forked.build_session_context().messages.len(), 3);
+    assert_eq!(manager.build_session_context().messages.len(), 2);
+
+    let reloaded =
+        SessionManager::load(forked.session_file().expect("fork file")).expect("reload fork");
+    assert_eq!(
+        reloaded.build_session_context(),
+        forked.build_session_context()
+    );
+}
+
 #[test]
 fn session_manager_load_restores_state_and_appends_with_new_id() {
     let dir = tempdir().expect("tempdir");
@@ -171,6 +219,111 @@ fn session_manager_load_restores_state_and_appends_with_new_id() {
     assert_eq!(third_entry["parentId"], Value::String(second_id));
 }
 
+#[test]
+fn session_manager_set_title_rewrites_header_and_keeps_entries() {
+    let dir = tempdir().expect("tempdir");
+    let mut manager = SessionManager::create("/repo", dir.path()).expect("create session manager");
+    manager
+        .append_message(user_message("fix the build", 1_700_000_000_000))
+        .expect("append user");
+    manager
+        .append_message(assistant_message("done", 1_700_000_000_010))
+        .expect("append assistant");
+
+    manager
+        .set_title("  Fix the workspace build  ")
+        .expect("set title");
+    let third_id = manager
+        .append_message(user_message("thanks", 1_700_000_000_020))
+        .expect("append after title");
+
+    let file_path = manager.session_file().expect("session file path").clone();
+    let content = fs::read_to_string(&file_path).expect("read session file");
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines.len(), 4, "header + three message entries expected");
+    let header: Value = serde_json::from_str(lines[0]).expect("header json");
+    assert_eq!(header["title"], "Fix the workspace build");
+
+    let loaded = SessionManager::load(&file_path).expect("load session manager");
+    assert_eq!(
+        loaded.header().title.as_deref(),
+        Some("Fix the workspace build")
+    );
+    assert_eq!(loaded.build_session_context().messages.len(), 3);
+    assert!(manager.set_title("   ").is_err());
+    assert!(content.contains(&third_id));
+}
+
+#[test]
+fn session_manager_load_migrates_old_files_and_skips_corrupt_records() {
+    let dir = tempdir().expect("tempdir");
+    let v1_file = dir.path().join("session-v1.jsonl");
+    fs::write(
+        &v1_file,
+        [
+            r#"{"type":"session","version":1,"id":"session-v1","timestamp":"1","cwd":"/repo"}"#,
+            r#"{"type":"message","timestamp":"2","message":{"role":"user","content":"old prompt","timestamp":2}}"#,
+        ]
+        .join("\n"),
+    )
+    .expect("write v1 session");
+    let mut migrated = SessionManager::load(&v1_file).expect("load v1 session");
+    assert_eq!(migrated.header().version, CURRENT_SESSION_VERSION);
+    migrated
+        .append_message(assistant_message("new answer", 3))
+        .expect("append after migration");
+    let lines = fs::read_to_string(&v1_file).expect("read migrated file");
+    let lines: Vec<Value> = lines
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("json line"))
+        .collect();
+    assert_eq!(lines[0]["version"], CURRENT_SESSION_VERSION);
+    assert_eq!(lines[1]["id"], "00000001");
+    assert_eq!(lines[2]["parentId"], "00000001");
+
+    let damaged_file = dir.path().join("session-damaged.jsonl");
+    let damaged = [
+        r#"{"type":"session","version":3,"id":"session-damaged","timestamp":"1","cwd":"/repo"}"#,
+        r#"{"type":"message","id":"00000001","parentId":null,"timestamp":"2","message":{"role":"user","content":"keep me","timestamp":2}}"#,
+        r#"{"type":"message","id":"00000002","parentId":"00000001","timest"#,
+        r#"{"type":"message","id":"00000003","parentId":"00000002","timestamp":"4","message":{"role":"user","content":"orphan","timestamp":4}}"#,
+    ]
+    .join("\n");
+    fs::write(&damaged_file, &damaged).expect("write damaged session");
+
+    let loaded = SessionManager::load(&damaged_file).expect("damaged session still loads");
+    assert_eq!(loaded.build_session_context().messages.len(), 2);
+    assert_eq!(
+        fs::read_to_string(&damaged_file).expect("read damaged"),
+        damaged,
+        "load must not rewrite a current-version file"
+    );
+
+    let report = fsck_session_file(&damaged_file, false).expect("check");
+    assert!(!report.rewritten);
+    assert_eq!(
+        report
+            .issues
+            .iter()
+            .map(|issue| issue.kind)
+            .collect::<Vec<_>>(),
+        vec![
+            SessionIssueKind::InvalidJson,
+            SessionIssueKind::MissingParent
+        ]
+    );
+
+    let report = fsck_session_file(&damaged_file, true).expect("repair");
+    assert!(report.rewritten);
+    let quarantine = report.quarantine_file.expect("quarantine file");
+    assert!(fs::read_to_string(&quarantine)
+        .expect("read quarantine")
+        .contains("invalid json"));
+    assert!(fsck_session_file(&damaged_file, false)
+        .expect("recheck")
+        .is_clean());
+}
+
 #[test]
 fn session_manager_branch_with_summary_adds_summary_message_on_target_branch() {
     let dir = tempdir().expect("tempdir");
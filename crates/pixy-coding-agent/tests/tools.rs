@@ -1,9 +1,15 @@
 use std::fs;
+use std::sync::{Arc, Mutex};
 
-use pixy_ai::{PiAiErrorCode, ToolResultContentBlock};
+use base64::Engine as _;
+use pixy_agent_core::ToolProgress;
+use pixy_ai::{Message, PiAiErrorCode, ToolResultContentBlock};
 use pixy_coding_agent::{
-    create_bash_tool, create_coding_tools, create_edit_tool, create_list_directory_tool,
-    create_read_tool, create_write_tool,
+    create_apply_patch_tool, create_bash_background_tool, create_bash_tool, create_coding_tools,
+    create_edit_tool, create_list_directory_tool, create_notebook_edit_tool,
+    create_read_image_tool, create_read_tool, create_search_tool, create_shell_tool,
+    create_todo_tool, create_write_tool, todos_from_messages, BackgroundProcesses, PersistentShell,
+    TodoItem, TodoStatus,
 };
 use serde_json::json;
 use tempfile::tempdir;
@@ -55,7 +61,182 @@ async fn write_tool_creates_parent_dirs_and_read_tool_supports_offset_limit() {
         .expect("read should succeed");
 
     let text = first_text(&result.content);
-    assert!(text.starts_with("line-2\nline-3"));
+    assert!(text.starts_with("     2\tline-2\n     3\tline-3"));
+    assert!(text.contains("Continue with offset=4."));
+}
+
+#[tokio::test]
+async fn read_tool_pages_large_files_by_token_budget() {
+    let dir = tempdir().expect("tempdir");
+    let line = "x".repeat(200);
+    let content = vec![line.as_str(); 1500].join("\n");
+    std::fs::write(dir.path().join("big.txt"), format!("{content}\n")).expect("write big file");
+    let read_tool = create_read_tool(dir.path());
+
+    let first = read_tool
+        .execute
+        .execute("call-read-1".to_string(), json!({ "path": "big.txt" }))
+        .await
+        .expect("read should succeed");
+    let details = first.details.clone();
+    assert_eq!(details["totalLines"], 1500);
+    assert_eq!(details["truncatedBy"], "tokens");
+    let shown = details["outputLines"].as_u64().expect("output lines") as usize;
+    assert!(shown > 0 && shown < 1500);
+    assert!(details["estimatedTokens"].as_u64().expect("tokens") <= 20_000);
+    let text = first_text(&first.content);
+    assert!(text.starts_with("     1\txxx"));
+    assert!(text.contains(&format!("Continue with offset={}.", shown + 1)));
+
+    let second = read_tool
+        .execute
+        .execute(
+            "call-read-2".to_string(),
+            json!({ "path": "big.txt", "offset": shown + 1 }),
+        )
+        .await
+        .expect("continued read should succeed");
+    assert!(first_text(&second.content).starts_with(&format!("{:>6}\t", shown + 1)));
+}
+
+#[cfg(feature = "code-index")]
+#[tokio::test]
+async fn read_tool_returns_outlines_and_symbol_bodies() {
+    let dir = tempdir().expect("tempdir");
+    let source = r#"use std::fmt;
+
+/// Runtime settings.
+#[derive(Debug)]
+pub struct Config {
+    name: String,
+}
+
+impl Config {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+fn new() {}
+"#;
+    fs::write(dir.path().join("config.rs"), source).expect("write source");
+    fs::write(
+        dir.path().join("app.py"),
+        "class App:\n    @property\n    def name(self) -> str:\n        return 'app'\n",
+    )
+    .expect("write python");
+    let read_tool = create_read_tool(dir.path());
+
+    let outline = read_tool
+        .execute
+        .execute(
+            "call-outline".to_string(),
+            json!({ "path": "config.rs", "outline": true }),
+        )
+        .await
+        .expect("outline should succeed");
+    assert_eq!(
+        first_text(&outline.content),
+        [
+            "config.rs: 6 definitions in 23 lines (rust)",
+            "5-7: pub struct Config",
+            "9-15: impl Config",
+            "  10-14: pub fn new(name: &str) -> Self",
+            "17-21: impl fmt::Display for Config",
+            "  18-20: fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result",
+            "23-23: fn new()",
+        ]
+        .join("\n")
+    );
+
+    let symbol = read_tool
+        .execute
+        .execute(
+            "call-symbol".to_string(),
+            json!({ "path": "config.rs", "symbol": "Config" }),
+        )
+        .await
+        .expect("struct should resolve over its impl blocks");
+    let text = first_text(&symbol.content);
+    assert!(text.starts_with("config.rs: struct Config (lines 3-7)\n     3\t/// Runtime settings."));
+    assert!(text.ends_with("     7\t}"));
+    assert_eq!(symbol.details["truncated"], false);
+
+    let method = read_tool
+        .execute
+        .execute(
+            "call-method".to_string(),
+            json!({ "path": "config.rs", "symbol": "Config::fmt" }),
+        )
+        .await
+        .expect("qualified method should resolve");
+    assert!(first_text(&method.content).contains("    19\t        write!"));
+
+    let ambiguous = read_tool
+        .execute
+        .execute(
+            "call-ambiguous".to_string(),
+            json!({ "path": "config.rs", "symbol": "new" }),
+        )
+        .await
+        .expect_err("two definitions named new");
+    assert!(ambiguous
+        .message
+        .contains("matches 2 definitions: Config::new (line 10), new (line 23)"));
+
+    let python = read_tool
+        .execute
+        .execute(
+            "call-python".to_string(),
+            json!({ "path": "app.py", "symbol": "App.name" }),
+        )
+        .await
+        .expect("python method should resolve");
+    assert!(first_text(&python.content)
+        .starts_with("app.py: def App.name (lines 2-4)\n     2\t    @property"));
+
+    fs::write(
+        dir.path().join("server.go"),
+        "package main\n\n// Server serves.\ntype Server struct{}\n\nfunc (s *Server) Start() error {\n\treturn nil\n}\n",
+    )
+    .expect("write go");
+    let go_type = read_tool
+        .execute
+        .execute(
+            "call-go-type".to_string(),
+            json!({ "path": "server.go", "symbol": "Server" }),
+        )
+        .await
+        .expect("go type should resolve");
+    assert!(first_text(&go_type.content).contains("     3\t// Server serves."));
+    let go_method = read_tool
+        .execute
+        .execute(
+            "call-go-method".to_string(),
+            json!({ "path": "server.go", "symbol": "Server.Start" }),
+        )
+        .await
+        .expect("go method should resolve by receiver");
+    assert_eq!(go_method.details["startLine"], 6);
+
+    fs::write(dir.path().join("notes.txt"), "plain text").expect("write text");
+    let unsupported = read_tool
+        .execute
+        .execute(
+            "call-unsupported".to_string(),
+            json!({ "path": "notes.txt", "outline": true }),
+        )
+        .await
+        .expect_err("no parser for text files");
+    assert_eq!(unsupported.code, PiAiErrorCode::ToolArgumentsInvalid);
 }
 
 #[tokio::test]
@@ -188,6 +369,125 @@ async fn edit_tool_rejects_non_unique_match() {
     assert!(error.message.contains("must be unique"));
 }
 
+#[tokio::test]
+async fn edit_tool_falls_back_to_indentation_tolerant_match() {
+    let dir = tempdir().expect("tempdir");
+    fs::write(
+        dir.path().join("lib.rs"),
+        "fn main() {\n        let value = 1;  \n        println!(\"{value}\");\n}\n",
+    )
+    .expect("seed file");
+    let edit_tool = create_edit_tool(dir.path());
+
+    let result = edit_tool
+        .execute
+        .execute(
+            "call-edit".to_string(),
+            json!({
+                "path": "lib.rs",
+                "oldText": "    let value = 1;\n    println!(\"{value}\");\n",
+                "newText": "    let value = 2;\n    println!(\"{value}\");\n"
+            }),
+        )
+        .await
+        .expect("indentation-tolerant edit should succeed");
+
+    let updated = fs::read_to_string(dir.path().join("lib.rs")).expect("read edited file");
+    assert_eq!(
+        updated,
+        "fn main() {\n        let value = 2;\n        println!(\"{value}\");\n}\n"
+    );
+    let text = first_text(&result.content);
+    assert!(text.contains("ignoring indentation"));
+    assert!(text.contains("at lines 2-3"));
+    assert!(text.contains("-         let value = 1;"));
+    assert!(text.contains("+         let value = 2;"));
+    assert_eq!(result.details["match"]["strategy"], "indentation");
+}
+
+#[tokio::test]
+async fn edit_tool_applies_near_identical_match_and_reports_closest_otherwise() {
+    let dir = tempdir().expect("tempdir");
+    fs::write(
+        dir.path().join("config.py"),
+        "DEBUG = False\nTIMEOUT_SECONDS = 30\nRETRIES = 3\n",
+    )
+    .expect("seed file");
+    let edit_tool = create_edit_tool(dir.path());
+
+    let result = edit_tool
+        .execute
+        .execute(
+            "call-edit-similar".to_string(),
+            json!({
+                "path": "config.py",
+                "oldText": "TIMEOUT_SECOND = 30\nRETRIES = 3",
+                "newText": "TIMEOUT_SECONDS = 60\nRETRIES = 3"
+            }),
+        )
+        .await
+        .expect("near-identical edit should succeed");
+    assert_eq!(result.details["match"]["strategy"], "similarity");
+    assert_eq!(
+        fs::read_to_string(dir.path().join("config.py")).expect("read edited file"),
+        "DEBUG = False\nTIMEOUT_SECONDS = 60\nRETRIES = 3\n"
+    );
+
+    let error = edit_tool
+        .execute
+        .execute(
+            "call-edit-far".to_string(),
+            json!({
+                "path": "config.py",
+                "oldText": "TIMEOUT = 90\nRETRY_COUNT = 5",
+                "newText": "TIMEOUT = 10"
+            }),
+        )
+        .await
+        .expect_err("dissimilar text should not be applied");
+    assert_eq!(error.code, PiAiErrorCode::ToolExecutionFailed);
+    assert!(error.message.contains("must match exactly"));
+    assert!(error.message.contains("Closest match"));
+    assert!(error.message.contains("TIMEOUT_SECONDS = 60"));
+}
+
+#[tokio::test]
+async fn edit_tool_gives_up_on_similarity_search_over_large_inputs() {
+    let dir = tempdir().expect("tempdir");
+    let content = (0..20_000)
+        .map(|index| format!("let value_{index:05} = compute(alpha, beta, gamma, delta, epsilon);"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    fs::write(dir.path().join("large.rs"), content).expect("seed file");
+    let old_text = (0..150)
+        .map(|index| format!("fn missing_{index:05}() -> Result<(), Error> {{ unimplemented!() }}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let edit_tool = create_edit_tool(dir.path());
+
+    let started = std::time::Instant::now();
+    let error = edit_tool
+        .execute
+        .execute(
+            "call-edit-large".to_string(),
+            json!({
+                "path": "large.rs",
+                "oldText": old_text,
+                "newText": "fn replaced() {}"
+            }),
+        )
+        .await
+        .expect_err("missing text should not be applied");
+    assert!(
+        started.elapsed() < std::time::Duration::from_secs(5),
+        "similarity search took {:?}",
+        started.elapsed()
+    );
+    assert_eq!(error.code, PiAiErrorCode::ToolExecutionFailed);
+    assert!(error.message.contains("must match exactly"));
+    assert!(!error.message.contains("Closest match"));
+}
+
 #[tokio::test]
 async fn bash_tool_returns_output_and_exit_code_errors() {
     let dir = tempdir().expect("tempdir");
@@ -220,6 +520,330 @@ async fn bash_tool_returns_output_and_exit_code_errors() {
     assert!(error.message.contains("fail"));
 }
 
+#[tokio::test]
+async fn bash_tool_caps_output_without_a_session() {
+    let dir = tempdir().expect("tempdir");
+    let bash_tool = create_bash_tool(dir.path());
+
+    let result = bash_tool
+        .execute
+        .execute(
+            "call-bash-long".to_string(),
+            json!({ "command": "seq 1 5000" }),
+        )
+        .await
+        .expect("bash should succeed");
+    let text = first_text(&result.content);
+    assert!(text.contains("\n5000\n"));
+    assert!(!text.contains("\n900\n"));
+    assert!(text.contains("[Output truncated: showing "));
+    assert_eq!(result.details["truncated"], true);
+    assert_eq!(result.details["truncatedBy"], "lines");
+    assert!(result.details["outputLines"].as_u64() <= Some(4096));
+    assert!(result.details["totalLines"].as_u64() >= Some(5000));
+}
+
+#[tokio::test]
+async fn bash_tool_streams_output_lines_to_tool_progress() {
+    let dir = tempdir().expect("tempdir");
+    let bash_tool = create_bash_tool(dir.path());
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let sink = reported.clone();
+    let progress = ToolProgress::new(move |partial| {
+        sink.lock().expect("progress lock").push(partial);
+    });
+
+    let error = progress
+        .scope(bash_tool.execute.execute(
+            "call-bash-stream".to_string(),
+            json!({ "command": "echo one; echo two >&2; printf three; exit 2" }),
+        ))
+        .await
+        .expect_err("command fails");
+    assert!(error.message.contains("Command exited with code 2"));
+
+    let reported = reported.lock().expect("progress lock").clone();
+    let line = |stream: &str, line: &str| json!({ "stream": stream, "line": line });
+    for expected in [
+        line("stdout", "one"),
+        line("stderr", "two"),
+        line("stdout", "three"),
+    ] {
+        assert!(reported.contains(&expected), "missing {expected}");
+    }
+    assert_eq!(
+        reported.last(),
+        Some(&line("status", "Command exited with code 2"))
+    );
+}
+
+#[tokio::test]
+async fn bash_background_tool_starts_polls_and_kills_processes() {
+    let dir = tempdir().expect("tempdir");
+    let processes = BackgroundProcesses::new();
+    let tool = create_bash_background_tool(dir.path(), processes.clone());
+
+    let started = tool
+        .execute
+        .execute(
+            "call-bg-start".to_string(),
+            json!({ "action": "start", "command": "echo first; echo second >&2" }),
+        )
+        .await
+        .expect("start should succeed");
+    assert_eq!(started.details["id"], "bg-1");
+
+    let mut polled = String::new();
+    for _ in 0..50 {
+        let poll = tool
+            .execute
+            .execute(
+                "call-bg-poll".to_string(),
+                json!({ "action": "poll", "id": "bg-1" }),
+            )
+            .await
+            .expect("poll should succeed");
+        polled.push_str(&first_text(&poll.content));
+        if poll.details["status"] != "running" && polled.contains("second") {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert!(polled.contains("exited with code 0"), "{polled}");
+    assert!(
+        polled.contains("first") && polled.contains("second"),
+        "{polled}"
+    );
+
+    let repoll = tool
+        .execute
+        .execute(
+            "call-bg-repoll".to_string(),
+            json!({ "action": "poll", "id": "bg-1" }),
+        )
+        .await
+        .expect("repoll should succeed");
+    assert!(first_text(&repoll.content).contains("(no new output)"));
+
+    let logs = tool
+        .execute
+        .execute(
+            "call-bg-logs".to_string(),
+            json!({ "action": "logs", "id": "bg-1", "lines": 1 }),
+        )
+        .await
+        .expect("logs should succeed");
+    assert_eq!(logs.details["lines"], 1);
+
+    tool.execute
+        .execute(
+            "call-bg-sleep".to_string(),
+            json!({ "action": "start", "command": "sleep 30" }),
+        )
+        .await
+        .expect("start sleep should succeed");
+    let killed = tool
+        .execute
+        .execute(
+            "call-bg-kill".to_string(),
+            json!({ "action": "kill", "id": "bg-2" }),
+        )
+        .await
+        .expect("kill should succeed");
+    assert_eq!(killed.details["status"], "killed");
+
+    tool.execute
+        .execute(
+            "call-bg-sleep-again".to_string(),
+            json!({ "action": "start", "command": "sleep 30" }),
+        )
+        .await
+        .expect("start second sleep should succeed");
+    assert_eq!(processes.kill_all(), 1);
+
+    let error = tool
+        .execute
+        .execute(
+            "call-bg-missing".to_string(),
+            json!({ "action": "poll", "id": "bg-9" }),
+        )
+        .await
+        .expect_err("unknown id should fail");
+    assert_eq!(error.code, PiAiErrorCode::ToolArgumentsInvalid);
+}
+
+#[tokio::test]
+async fn bash_background_tool_keeps_reading_after_non_utf8_output() {
+    let dir = tempdir().expect("tempdir");
+    let tool = create_bash_background_tool(dir.path(), BackgroundProcesses::new());
+
+    tool.execute
+        .execute(
+            "call-bg-start".to_string(),
+            json!({ "action": "start", "command": "printf 'bad \\377 byte\\n'; echo after" }),
+        )
+        .await
+        .expect("start should succeed");
+
+    let mut polled = String::new();
+    for _ in 0..50 {
+        let poll = tool
+            .execute
+            .execute(
+                "call-bg-poll".to_string(),
+                json!({ "action": "poll", "id": "bg-1" }),
+            )
+            .await
+            .expect("poll should succeed");
+        polled.push_str(&first_text(&poll.content));
+        if poll.details["status"] != "running" && polled.contains("after") {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert!(polled.contains("bad \u{FFFD} byte"), "{polled}");
+    assert!(polled.contains("after"), "{polled}");
+}
+
+#[tokio::test]
+async fn todo_tool_replaces_list_and_is_recoverable_from_messages() {
+    let todo_tool = create_todo_tool();
+
+    let result = todo_tool
+        .execute
+        .execute(
+            "call-todo".to_string(),
+            json!({
+                "todos": [
+                    { "content": "Add parser", "status": "done" },
+                    { "content": " Wire CLI ", "status": "in_progress" },
+                    { "content": "Write docs", "status": "pending" }
+                ]
+            }),
+        )
+        .await
+        .expect("todo should succeed");
+    assert_eq!(
+        first_text(&result.content),
+        "Todos (1/3 done):\n[x] Add parser\n[~] Wire CLI\n[ ] Write docs"
+    );
+
+    let message = Message::ToolResult {
+        tool_call_id: "call-todo".to_string(),
+        tool_name: "todo".to_string(),
+        content: result.content,
+        details: Some(result.details),
+        is_error: false,
+        timestamp: 1,
+    };
+    assert_eq!(
+        todos_from_messages(&[message]),
+        vec![
+            TodoItem {
+                content: "Add parser".to_string(),
+                status: TodoStatus::Done
+            },
+            TodoItem {
+                content: "Wire CLI".to_string(),
+                status: TodoStatus::InProgress
+            },
+            TodoItem {
+                content: "Write docs".to_string(),
+                status: TodoStatus::Pending
+            },
+        ]
+    );
+
+    let error = todo_tool
+        .execute
+        .execute(
+            "call-todo-2".to_string(),
+            json!({ "todos": [{ "content": "x", "status": "blocked" }] }),
+        )
+        .await
+        .expect_err("unknown status should be rejected");
+    assert_eq!(error.code, PiAiErrorCode::ToolArgumentsInvalid);
+}
+
+fn write_test_image(path: &std::path::Path, image: image::DynamicImage) {
+    image.save(path).expect("write test image");
+}
+
+#[tokio::test]
+async fn read_image_tool_returns_image_blocks_and_downscales_large_images() {
+    let dir = tempdir().expect("tempdir");
+    write_test_image(
+        &dir.path().join("small.png"),
+        image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            40,
+            20,
+            image::Rgb([200, 0, 0]),
+        )),
+    );
+    write_test_image(
+        &dir.path().join("wide.png"),
+        image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            4000,
+            1000,
+            image::Rgb([0, 120, 255]),
+        )),
+    );
+    write_test_image(
+        &dir.path().join("tall.png"),
+        image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            1000,
+            3000,
+            image::Rgba([0, 0, 0, 0]),
+        )),
+    );
+    fs::write(dir.path().join("notes.txt"), "not an image").expect("seed text");
+    let tool = create_read_image_tool(dir.path());
+
+    let small = tool
+        .execute
+        .execute("call-1".to_string(), json!({ "path": "small.png" }))
+        .await
+        .expect("small image");
+    assert_eq!(
+        first_text(&small.content),
+        "Read image small.png (40x20, image/png, 1 KB)"
+    );
+    let original = fs::read(dir.path().join("small.png")).expect("read small");
+    assert!(matches!(
+        &small.content[1],
+        ToolResultContentBlock::Image { data, mime_type }
+            if mime_type == "image/png"
+                && *data == base64::prelude::BASE64_STANDARD.encode(&original)
+    ));
+    assert_eq!(small.details["resized"], false);
+
+    let wide = tool
+        .execute
+        .execute("call-2".to_string(), json!({ "path": "wide.png" }))
+        .await
+        .expect("wide image");
+    assert_eq!(wide.details["width"], 2000);
+    assert_eq!(wide.details["height"], 500);
+    assert_eq!(wide.details["mimeType"], "image/jpeg");
+    assert!(first_text(&wide.content).ends_with("downscaled from 4000x1000)"));
+
+    let tall = tool
+        .execute
+        .execute("call-3".to_string(), json!({ "path": "tall.png" }))
+        .await
+        .expect("tall image");
+    assert_eq!(tall.details["height"], 2000);
+    assert_eq!(tall.details["mimeType"], "image/png", "alpha keeps PNG");
+
+    let error = tool
+        .execute
+        .execute("call-4".to_string(), json!({ "path": "notes.txt" }))
+        .await
+        .expect_err("text file is not an image");
+    assert_eq!(error.code, PiAiErrorCode::ToolExecutionFailed);
+    assert!(error.message.contains("unsupported image format"));
+}
+
 #[tokio::test]
 async fn bash_tool_unwraps_nested_bash_lc_commands_before_execution() {
     let dir = tempdir().expect("tempdir");
@@ -273,6 +897,72 @@ async fn list_directory_tool_lists_entries_and_allows_absolute_paths_outside_wor
     assert!(outside_text.contains("outside.txt  (7 bytes)"));
 }
 
+#[tokio::test]
+async fn list_directory_and_read_respect_gitignore_and_pixyignore() {
+    let dir = tempdir().expect("tempdir");
+    fs::write(
+        dir.path().join(".gitignore"),
+        "node_modules/\n*.log\n!keep.log\n",
+    )
+    .expect("seed gitignore");
+    fs::write(dir.path().join(".pixyignore"), "fixtures/big.json\n").expect("seed pixyignore");
+    fs::create_dir_all(dir.path().join("node_modules/dep")).expect("create node_modules");
+    fs::create_dir_all(dir.path().join("fixtures")).expect("create fixtures");
+    fs::write(dir.path().join("node_modules/dep/index.js"), "x").expect("seed dep");
+    fs::write(dir.path().join("debug.log"), "noise").expect("seed log");
+    fs::write(dir.path().join("keep.log"), "kept").expect("seed kept log");
+    fs::write(dir.path().join("main.rs"), "fn main() {}").expect("seed source");
+    fs::write(dir.path().join("fixtures/big.json"), "{}").expect("seed fixture");
+    let list_directory_tool = create_list_directory_tool(dir.path());
+    let read_tool = create_read_tool(dir.path());
+
+    let listed = list_directory_tool
+        .execute
+        .execute("call-list".to_string(), json!({ "path": "" }))
+        .await
+        .expect("list should succeed");
+    let listed_text = first_text(&listed.content);
+    assert!(listed_text.contains("main.rs"));
+    assert!(listed_text.contains("keep.log"));
+    assert!(!listed_text.contains("node_modules"));
+    assert!(!listed_text.contains("debug.log"));
+    assert!(listed_text.contains("2 ignored entries hidden"));
+    assert_eq!(listed.details["ignoredCount"], 2);
+
+    let all = list_directory_tool
+        .execute
+        .execute(
+            "call-list-all".to_string(),
+            json!({ "path": "", "includeIgnored": true }),
+        )
+        .await
+        .expect("list with ignored entries should succeed");
+    let all_text = first_text(&all.content);
+    assert!(all_text.contains("node_modules/"));
+    assert!(all_text.contains("debug.log"));
+
+    let error = read_tool
+        .execute
+        .execute(
+            "call-read-ignored".to_string(),
+            json!({ "path": "fixtures/big.json" }),
+        )
+        .await
+        .expect_err("pixyignored file should be refused");
+    assert!(error.message.contains(".pixyignore"));
+    assert!(error.message.contains("includeIgnored=true"));
+
+    let forced = read_tool
+        .execute
+        .execute(
+            "call-read-forced".to_string(),
+            json!({ "path": "node_modules/dep/index.js", "includeIgnored": true }),
+        )
+        .await
+        .expect("includeIgnored should allow the read");
+    assert!(first_text(&forced.content).contains("x"));
+}
+
 #[test]
 fn create_coding_tools_returns_expected_order() {
     let dir = tempdir().expect("tempdir");
@@ -280,6 +970,638 @@ fn create_coding_tools_returns_expected_order() {
     let names = tools.into_iter().map(|tool| tool.name).collect::<Vec<_>>();
     assert_eq!(
         names,
-        vec!["list_directory", "read", "bash", "edit", "write"]
+        vec![
+            "list_directory",
+            "read",
+            "search",
+            "bash",
+            "edit",
+            "apply_patch",
+            "notebook_edit",
+            "write"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn search_tool_reports_matches_with_context_and_filters() {
+    let dir = tempdir().expect("tempdir");
+    fs::create_dir_all(dir.path().join("src")).expect("create src");
+    fs::write(
+        dir.path().join("src/lib.rs"),
+        "use std::io;\n\nfn alpha() {}\nfn beta() {}\n\nfn gamma() {}\n",
+    )
+    .expect("write lib.rs");
+    fs::write(dir.path().join("notes.md"), "fn alpha in prose\n").expect("write notes");
+    fs::write(dir.path().join("blob.bin"), b"fn alpha\0binary").expect("write blob");
+    let search_tool = create_search_tool(dir.path());
+
+    let result = search_tool
+        .execute
+        .execute(
+            "call-search".to_string(),
+            json!({ "pattern": "fn (alpha|gamma)" }),
+        )
+        .await
+        .expect("search should succeed");
+    assert_eq!(
+        first_text(&result.content),
+        "notes.md:1:fn alpha in prose\nsrc/lib.rs:3:fn alpha() {}\nsrc/lib.rs:6:fn gamma() {}"
+    );
+    assert_eq!(result.details["matchCount"], 3);
+    assert_eq!(result.details["fileCount"], 2);
+
+    let result = search_tool
+        .execute
+        .execute(
+            "call-search-context".to_string(),
+            json!({ "pattern": "FN ALPHA()", "literal": true, "ignoreCase": true, "type": "rust", "context": 1 }),
+        )
+        .await
+        .expect("search with context should succeed");
+    assert_eq!(
+        first_text(&result.content),
+        "src/lib.rs-2-\nsrc/lib.rs:3:fn alpha() {}\nsrc/lib.rs-4-fn beta() {}"
+    );
+
+    let result = search_tool
+        .execute
+        .execute(
+            "call-search-files".to_string(),
+            json!({ "pattern": "alpha", "filesOnly": true, "glob": ["*.md"] }),
+        )
+        .await
+        .expect("files-only search should succeed");
+    assert_eq!(first_text(&result.content), "notes.md");
+
+    let error = search_tool
+        .execute
+        .execute("call-search-bad".to_string(), json!({ "pattern": "fn (" }))
+        .await
+        .expect_err("invalid regex should fail");
+    assert_eq!(error.code, PiAiErrorCode::ToolArgumentsInvalid);
+}
+
+#[tokio::test]
+async fn search_tool_lists_files_respects_ignores_and_caps_results() {
+    let dir = tempdir().expect("tempdir");
+    fs::write(dir.path().join(".gitignore"), "target/\n").expect("write gitignore");
+    fs::write(dir.path().join(".pixyignore"), "fixtures/\n").expect("write pixyignore");
+    for path in ["a.rs", "b.rs", "c.txt", "target/out.rs", "fixtures/data.rs"] {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().expect("parent")).expect("create dir");
+        fs::write(path, "needle\nneedle\n").expect("write file");
+    }
+    let search_tool = create_search_tool(dir.path());
+
+    let listed = search_tool
+        .execute
+        .execute("call-glob".to_string(), json!({ "glob": ["*.rs"] }))
+        .await
+        .expect("listing should succeed");
+    assert_eq!(first_text(&listed.content), "a.rs\nb.rs");
+
+    let all = search_tool
+        .execute
+        .execute(
+            "call-glob-all".to_string(),
+            json!({ "glob": ["*.rs"], "includeIgnored": true }),
+        )
+        .await
+        .expect("listing ignored files should succeed");
+    assert_eq!(
+        first_text(&all.content),
+        "a.rs\nb.rs\nfixtures/data.rs\ntarget/out.rs"
+    );
+
+    let capped = search_tool
+        .execute
+        .execute(
+            "call-capped".to_string(),
+            json!({ "pattern": "needle", "maxResults": 3 }),
+        )
+        .await
+        .expect("capped search should succeed");
+    let text = first_text(&capped.content);
+    assert!(
+        text.starts_with("a.rs:1:needle\na.rs:2:needle\nb.rs:1:needle\n\n[Stopped after 3 matches")
+    );
+    assert_eq!(capped.details["truncated"], true);
+}
+
+#[tokio::test]
+async fn search_and_list_directory_agree_on_ignored_files() {
+    let dir = tempdir().expect("tempdir");
+    fs::write(dir.path().join(".gitignore"), "*.log\n").expect("write gitignore");
+    fs::write(dir.path().join(".ignore"), "notes.rs\n").expect("write .ignore");
+    fs::create_dir_all(dir.path().join("src/generated")).expect("create src");
+    fs::write(dir.path().join("src/.gitignore"), "generated/\n!keep.log\n")
+        .expect("write nested gitignore");
+    for path in [
+        "main.rs",
+        "notes.rs",
+        "debug.log",
+        "src/lib.rs",
+        "src/keep.log",
+        "src/generated/out.rs",
+    ] {
+        fs::write(dir.path().join(path), "x").expect("write file");
+    }
+    let search_tool = create_search_tool(dir.path());
+    let list_directory_tool = create_list_directory_tool(dir.path());
+
+    let searched = search_tool
+        .execute
+        .execute("call-search-visible".to_string(), json!({}))
+        .await
+        .expect("listing should succeed");
+    assert_eq!(
+        first_text(&searched.content),
+        ".gitignore\n.ignore\nmain.rs\nnotes.rs\nsrc/.gitignore\nsrc/keep.log\nsrc/lib.rs"
+    );
+
+    for (path, visible, hidden) in [
+        ("", vec!["main.rs", "notes.rs", "src/"], vec!["debug.log"]),
+        ("src", vec!["keep.log", "lib.rs"], vec!["generated"]),
+    ] {
+        let listed = list_directory_tool
+            .execute
+            .execute("call-list-visible".to_string(), json!({ "path": path }))
+            .await
+            .expect("list should succeed");
+        let listed_text = first_text(&listed.content);
+        for name in visible {
+            assert!(listed_text.contains(name), "{path}: {listed_text}");
+        }
+        for name in hidden {
+            assert!(!listed_text.contains(name), "{path}: {listed_text}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn read_tool_schema_and_argument_checks_come_from_its_argument_struct() {
+    let dir = tempdir().expect("tempdir");
+    let read_tool = create_read_tool(dir.path());
+
+    assert_eq!(read_tool.parameters["required"], json!(["path"]));
+    assert_eq!(read_tool.parameters["additionalProperties"], json!(false));
+    assert_eq!(
+        read_tool.parameters["properties"]["offset"],
+        json!({ "type": "integer", "minimum": 1, "description": "1-based start line offset." })
+    );
+    assert_eq!(
+        read_tool.parameters["properties"]["includeIgnored"]["type"],
+        "boolean"
+    );
+
+    let error = read_tool
+        .execute
+        .execute(
+            "call-read-offset".to_string(),
+            json!({ "path": "a.txt", "offset": 0 }),
+        )
+        .await
+        .expect_err("offset 0 is rejected");
+    assert_eq!(error.code, PiAiErrorCode::ToolArgumentsInvalid);
+    assert_eq!(error.message, "`offset` must be >= 1");
+}
+
+#[tokio::test]
+async fn apply_patch_tool_applies_unified_diff_across_files() {
+    let dir = tempdir().expect("tempdir");
+    fs::write(
+        dir.path().join("a.txt"),
+        "one\ntwo\nthree\nfour\nfive\nsix\n",
+    )
+    .expect("write a.txt");
+    fs::write(dir.path().join("old.txt"), "bye\n").expect("write old.txt");
+    let patch = "diff --git a/a.txt b/a.txt
+--- a/a.txt
++++ b/a.txt
+@@ -4,2 +4,2 @@
+ one
+-two
++TWO
+@@ -9,3 +9,4 @@
+ five
++five and a half
+ six
+--- /dev/null
++++ b/new/hello.txt
+@@ -0,0 +1,2 @@
++hello
++world
+--- a/old.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+";
+    let tool = create_apply_patch_tool(dir.path());
+    let result = tool
+        .execute
+        .execute("call-patch".to_string(), json!({ "patch": patch }))
+        .await
+        .expect("patch should apply");
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("a.txt")).expect("read a.txt"),
+        "one\nTWO\nthree\nfour\nfive\nfive and a half\nsix\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("new/hello.txt")).expect("read hello.txt"),
+        "hello\nworld\n"
+    );
+    assert!(!dir.path().join("old.txt").exists());
+    let text = first_text(&result.content);
+    assert!(text.contains("a.txt"));
+    assert!(text.contains("new/hello.txt"));
+    assert_eq!(result.details["applied"], true);
+    let files = &result.details["files"];
+    assert_eq!(files[0]["operation"], "update");
+    assert_eq!(files[0]["hunks"][0]["startLine"], 1);
+    assert_eq!(files[0]["hunks"][1]["startLine"], 5);
+    assert_eq!(files[1]["operation"], "create");
+    assert_eq!(files[2]["operation"], "delete");
+}
+
+#[test]
+fn apply_patch_tool_conflicts_with_every_file_it_touches() {
+    let dir = tempdir().expect("tempdir");
+    let tool = create_apply_patch_tool(dir.path());
+    let conflict_key = tool.conflict_key.expect("apply_patch has a conflict key");
+
+    let keys = conflict_key(&json!({
+        "files": [
+            { "path": "a.txt", "hunks": [{ "oldText": "", "newText": "a" }] },
+            { "path": "nested/b.txt", "delete": true },
+        ]
+    }));
+
+    assert_eq!(
+        keys,
+        vec![
+            dir.path().join("a.txt").to_string_lossy().into_owned(),
+            dir.path()
+                .join("nested/b.txt")
+                .to_string_lossy()
+                .into_owned(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn apply_patch_tool_applies_structured_hunks_with_fuzzy_context() {
+    let dir = tempdir().expect("tempdir");
+    fs::write(
+        dir.path().join("lib.rs"),
+        "fn main() {\n    let value = 1;\n    println!(\"{value}\");\n}\n",
+    )
+    .expect("write lib.rs");
+    let tool = create_apply_patch_tool(dir.path());
+    let result = tool
+        .execute
+        .execute(
+            "call-structured".to_string(),
+            json!({
+                "files": [{
+                    "path": "lib.rs",
+                    "hunks": [
+                        { "oldText": "let value = 1;\nprintln!(\"{value}\");", "newText": "let value = 2;\nprintln!(\"{value}\");" },
+                        { "oldText": "fn main()", "newText": "pub fn main()" }
+                    ]
+                }]
+            }),
+        )
+        .await
+        .expect("structured patch should apply");
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("lib.rs")).expect("read lib.rs"),
+        "pub fn main() {\n    let value = 2;\n    println!(\"{value}\");\n}\n"
+    );
+    let hunks = &result.details["files"][0]["hunks"];
+    assert_eq!(hunks[0]["strategy"], "indentation");
+    assert_eq!(hunks[1]["strategy"], "exact");
+    assert!(first_text(&result.content).contains("lib.rs hunk 1: no exact match"));
+}
+
+#[tokio::test]
+async fn apply_patch_tool_changes_nothing_when_a_hunk_fails() {
+    let dir = tempdir().expect("tempdir");
+    fs::write(dir.path().join("a.txt"), "alpha\nbeta\n").expect("write a.txt");
+    fs::write(dir.path().join("b.txt"), "same\nsame\n").expect("write b.txt");
+    let tool = create_apply_patch_tool(dir.path());
+    let error = tool
+        .execute
+        .execute(
+            "call-failing".to_string(),
+            json!({
+                "files": [
+                    { "path": "a.txt", "hunks": [{ "oldText": "alpha", "newText": "ALPHA" }] },
+                    { "path": "b.txt", "hunks": [
+                        { "oldText": "same", "newText": "other" },
+                        { "oldText": "missing text entirely", "newText": "x" }
+                    ] },
+                    { "path": "c.txt", "delete": true }
+                ]
+            }),
+        )
+        .await
+        .expect_err("patch should fail");
+
+    assert_eq!(error.code, PiAiErrorCode::ToolExecutionFailed);
+    assert!(error
+        .message
+        .starts_with("Patch not applied; no files were changed."));
+    assert!(error.message.contains("b.txt hunk 1: 2 locations match"));
+    assert!(error.message.contains("c.txt: c.txt does not exist"));
+    let details = error.details.expect("failure details");
+    assert_eq!(details["applied"], false);
+    assert_eq!(details["files"][0]["status"], "ok");
+    assert_eq!(details["files"][0]["hunks"][0]["status"], "applied");
+    assert_eq!(details["files"][1]["hunks"][0]["reason"], "ambiguous");
+    assert_eq!(details["files"][1]["hunks"][1]["reason"], "not_found");
+    assert_eq!(details["files"][2]["reason"], "not_found");
+    assert_eq!(
+        fs::read_to_string(dir.path().join("a.txt")).expect("read a.txt"),
+        "alpha\nbeta\n"
+    );
+}
+
+/// A notebook as Jupyter writes it: sorted keys, one-space indentation, trailing newline.
+const NOTEBOOK: &str = r##"{
+ "cells": [
+  {
+   "cell_type": "markdown",
+   "id": "intro",
+   "metadata": {},
+   "source": [
+    "# Analysis"
+   ]
+  },
+  {
+   "cell_type": "code",
+   "execution_count": 3,
+   "id": "load",
+   "metadata": {
+    "tags": [
+     "setup"
+    ]
+   },
+   "outputs": [
+    {
+     "name": "stdout",
+     "output_type": "stream",
+     "text": [
+      "loaded 10 rows\n"
+     ]
+    }
+   ],
+   "source": [
+    "import pandas as pd\n",
+    "df = pd.read_csv(\"data.csv\")"
+   ]
+  }
+ ],
+ "metadata": {
+  "language_info": {
+   "name": "python"
+  }
+ },
+ "nbformat": 4,
+ "nbformat_minor": 5
+}
+"##;
+
+#[tokio::test]
+async fn notebook_edit_tool_reads_cells_without_outputs() {
+    let dir = tempdir().expect("tempdir");
+    fs::write(dir.path().join("analysis.ipynb"), NOTEBOOK).expect("write notebook");
+    let tool = create_notebook_edit_tool(dir.path());
+
+    let result = tool
+        .execute
+        .execute("call-read".to_string(), json!({ "path": "analysis.ipynb" }))
+        .await
+        .expect("read should succeed");
+    assert_eq!(
+        result.details,
+        json!({
+            "path": "analysis.ipynb",
+            "language": "python",
+            "cellCount": 2,
+            "cells": [
+                { "index": 0, "id": "intro", "cellType": "markdown", "source": "# Analysis" },
+                {
+                    "index": 1,
+                    "id": "load",
+                    "cellType": "code",
+                    "source": "import pandas as pd\ndf = pd.read_csv(\"data.csv\")",
+                    "executionCount": 3,
+                    "outputs": 1,
+                },
+            ],
+        })
+    );
+    assert!(!first_text(&result.content).contains("loaded 10 rows"));
+
+    let single = tool
+        .execute
+        .execute(
+            "call-read-one".to_string(),
+            json!({ "path": "analysis.ipynb", "cellId": "load" }),
+        )
+        .await
+        .expect("read should succeed");
+    assert_eq!(single.details["cells"].as_array().map(Vec::len), Some(1));
+    assert_eq!(single.details["cells"][0]["index"], 1);
+
+    let error = create_edit_tool(dir.path())
+        .execute
+        .execute(
+            "call-edit".to_string(),
+            json!({ "path": "analysis.ipynb", "oldText": "Analysis", "newText": "Report" }),
+        )
+        .await
+        .expect_err("edit should refuse notebooks");
+    assert!(error.message.contains("notebook_edit"));
+}
+
+#[tokio::test]
+async fn notebook_edit_tool_replaces_inserts_and_deletes_cells() {
+    let dir = tempdir().expect("tempdir");
+    let path = dir.path().join("analysis.ipynb");
+    fs::write(&path, NOTEBOOK).expect("write notebook");
+    let tool = create_notebook_edit_tool(dir.path());
+
+    let result = tool
+        .execute
+        .execute(
+            "call-replace".to_string(),
+            json!({
+                "path": "analysis.ipynb",
+                "action": "replace",
+                "cellIndex": 1,
+                "source": "import pandas as pd\ndf = pd.read_csv(\"rows.csv\")\n"
+            }),
+        )
+        .await
+        .expect("replace should succeed");
+    assert!(first_text(&result.content)
+        .starts_with("Replaced code cell 1 in analysis.ipynb (2 cells)."));
+    let expected = NOTEBOOK
+        .replace(
+            "   \"execution_count\": 3,",
+            "   \"execution_count\": null,",
+        )
+        .replace(
+            "   \"outputs\": [\n    {\n     \"name\": \"stdout\",\n     \"output_type\": \"stream\",\n     \"text\": [\n      \"loaded 10 rows\\n\"\n     ]\n    }\n   ],",
+            "   \"outputs\": [],",
+        )
+        .replace(
+            "    \"df = pd.read_csv(\\\"data.csv\\\")\"",
+            "    \"df = pd.read_csv(\\\"rows.csv\\\")\\n\"",
+        );
+    assert_eq!(fs::read_to_string(&path).expect("read notebook"), expected);
+
+    let result = tool
+        .execute
+        .execute(
+            "call-insert".to_string(),
+            json!({
+                "path": "analysis.ipynb",
+                "action": "insert",
+                "cellId": "intro",
+                "cellType": "markdown",
+                "source": "Load the data."
+            }),
+        )
+        .await
+        .expect("insert should succeed");
+    assert_eq!(result.details["cellIndex"], 1);
+    assert_eq!(result.details["cellCount"], 3);
+    let notebook: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).expect("read notebook"))
+            .expect("valid json");
+    let inserted = &notebook["cells"][1];
+    assert_eq!(inserted["cell_type"], "markdown");
+    assert_eq!(inserted["source"], json!(["Load the data."]));
+    assert_eq!(inserted["id"].as_str().map(str::len), Some(8));
+    assert!(inserted.get("outputs").is_none());
+
+    tool.execute
+        .execute(
+            "call-delete".to_string(),
+            json!({ "path": "analysis.ipynb", "action": "delete", "cellIndex": 1 }),
+        )
+        .await
+        .expect("delete should succeed");
+    assert_eq!(fs::read_to_string(&path).expect("read notebook"), expected);
+
+    let error = tool
+        .execute
+        .execute(
+            "call-missing".to_string(),
+            json!({ "path": "analysis.ipynb", "action": "delete", "cellIndex": 5 }),
+        )
+        .await
+        .expect_err("out of range should fail");
+    assert_eq!(
+        error.message,
+        "Cell index 5 is out of range; analysis.ipynb has 2 cells"
+    );
+}
+
+#[tokio::test]
+async fn shell_tool_keeps_state_between_commands_and_resets() {
+    let dir = tempdir().expect("tempdir");
+    fs::create_dir_all(dir.path().join("sub")).expect("create sub");
+    let shell = PersistentShell::new();
+    let tool = create_shell_tool(dir.path(), shell.clone());
+    let run = |args: serde_json::Value| tool.execute.execute("call-shell".to_string(), args);
+
+    let result = run(json!({ "command": "cd sub && export GREETING=hi" }))
+        .await
+        .expect("cd succeeds");
+    assert!(first_text(&result.content).starts_with("(no output)\n\n[cwd: "));
+    assert!(result.details["cwd"]
+        .as_str()
+        .expect("cwd")
+        .ends_with("/sub"));
+
+    let result =
+        run(json!({ "command": "echo \"$GREETING from $(basename \"$PWD\")\"; echo oops >&2" }))
+            .await
+            .expect("echo succeeds");
+    let text = first_text(&result.content);
+    assert!(text.starts_with("hi from sub\noops\n"), "{text}");
+
+    let error = run(json!({ "command": "if then" }))
+        .await
+        .expect_err("syntax errors fail the command");
+    assert!(error.message.contains("syntax error"), "{}", error.message);
+    let error = run(json!({ "command": "test -t 0 && test -t 1 && test -t 2 && tty; false" }))
+        .await
+        .expect_err("nonzero exit codes are errors");
+    assert!(error.message.starts_with("/dev/"), "{}", error.message);
+    assert!(error.message.ends_with("Command exited with code 1"));
+    assert_eq!(error.details.expect("details")["exitCode"], 1);
+
+    let result = run(json!({ "command": "echo $GREETING" }))
+        .await
+        .expect("state survives failures");
+    assert!(first_text(&result.content).starts_with("hi"));
+
+    let result = run(json!({ "reset": true, "command": "echo \"[$GREETING]\"; pwd" }))
+        .await
+        .expect("reset and run");
+    let text = first_text(&result.content);
+    assert!(text.starts_with("[]\n"), "{text}");
+    assert!(!text.contains("[cwd:"), "{text}");
+    assert!(shell.close().await);
+    assert!(!shell.close().await);
+}
+
+#[tokio::test]
+async fn shell_tool_restarts_after_timeouts_exits_and_idle() {
+    let dir = tempdir().expect("tempdir");
+    let tool = create_shell_tool(
+        dir.path(),
+        PersistentShell::new().with_idle_timeout(std::time::Duration::from_millis(200)),
+    );
+    let run = |args: serde_json::Value| tool.execute.execute("call-shell".to_string(), args);
+
+    run(json!({ "command": "export MARK=1" }))
+        .await
+        .expect("export succeeds");
+    let error = run(json!({ "command": "echo started; sleep 5", "timeout": 0.5 }))
+        .await
+        .expect_err("timeout fails");
+    assert!(error
+        .message
+        .starts_with("started\n\nCommand timed out after 0.5 seconds"));
+    let result = run(json!({ "command": "echo \"[$MARK]\"" }))
+        .await
+        .expect("a fresh shell starts");
+    assert!(first_text(&result.content).starts_with("[]"));
+
+    let error = run(json!({ "command": "exit 3" }))
+        .await
+        .expect_err("exit ends the shell");
+    assert!(error.message.contains("Shell exited with code 3"));
+    run(json!({ "command": "export MARK=2" }))
+        .await
+        .expect("a fresh shell starts after exit");
+
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    let result = run(json!({ "command": "echo \"[$MARK]\"" }))
+        .await
+        .expect("a fresh shell starts after idling");
+    let text = first_text(&result.content);
+    assert!(
+        text.starts_with("[Note: the previous shell was closed after 0 seconds idle"),
+        "{text}"
     );
+    assert!(text.ends_with("\n[]"), "{text}");
 }
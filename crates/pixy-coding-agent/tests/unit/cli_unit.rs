@@ -18,12 +18,22 @@ fn test_chat_args() -> ChatArgs {
         base_url: None,
         context_window: None,
         max_tokens: None,
+        temperature: None,
+        seed: None,
+        proxy: None,
+        no_proxy: Vec::new(),
+        ca_bundle: None,
+        client_cert: None,
+        client_key: None,
         agent_dir: None,
         cwd: None,
         session_dir: None,
         session_file: None,
         system_prompt: Some("test".to_string()),
         prompt: None,
+        output_format: OutputFormat::Text,
+        max_turns: None,
+        quiet: false,
         continue_first: false,
         no_tools: false,
         skills: vec![],
@@ -31,6 +41,9 @@ fn test_chat_args() -> ChatArgs {
         hide_tool_results: false,
         no_tui: false,
         theme: None,
+        worktree: false,
+        review: false,
+        tool_parallelism: None,
     }
 }
 
@@ -407,13 +420,7 @@ impl<'a> CliRuntimeConfigResolver<'a> {
             .unwrap_or_else(|| default_reasoning_enabled_for_api(&api));
         let reasoning_effort = selected_model_cfg
             .and_then(|cfg| cfg.reasoning_effort.clone())
-            .or_else(|| {
-                if reasoning {
-                    Some(pixy_ai::ThinkingLevel::Medium)
-                } else {
-                    None
-                }
-            });
+            .or(reasoning.then_some(pixy_ai::ThinkingLevel::Medium));
 
         let model = Model {
             id: model_id.clone(),
@@ -433,6 +440,9 @@ impl<'a> CliRuntimeConfigResolver<'a> {
             },
             context_window,
             max_tokens,
+            deployment: None,
+            api_version: None,
+            compat: None,
         };
 
         let mut model_catalog = provider_config
@@ -535,7 +545,7 @@ fn is_chat_provider(config: &ProviderConfig) -> bool {
         .kind
         .as_deref()
         .map(str::trim)
-        .map_or(true, |kind| kind.eq_ignore_ascii_case("chat"))
+        .is_none_or(|kind| kind.eq_ignore_ascii_case("chat"))
 }
 
 fn model_from_config(
@@ -558,13 +568,10 @@ fn model_from_config(
     let reasoning = config
         .reasoning
         .unwrap_or_else(|| default_reasoning_enabled_for_api(&api));
-    let reasoning_effort = config.reasoning_effort.clone().or_else(|| {
-        if reasoning {
-            Some(pixy_ai::ThinkingLevel::Medium)
-        } else {
-            None
-        }
-    });
+    let reasoning_effort = config
+        .reasoning_effort
+        .clone()
+        .or(reasoning.then_some(pixy_ai::ThinkingLevel::Medium));
 
     Model {
         id: config.id.clone(),
@@ -584,6 +591,9 @@ fn model_from_config(
         },
         context_window: config.context_window.unwrap_or(default_context_window),
         max_tokens: config.max_tokens.unwrap_or(default_max_tokens),
+        deployment: None,
+        api_version: None,
+        compat: None,
     }
 }
 
@@ -626,12 +636,10 @@ fn split_provider_model(input: Option<&str>) -> (Option<String>, Option<String>)
 }
 
 fn first_non_empty<const N: usize>(candidates: [Option<String>; N]) -> Option<String> {
-    for candidate in candidates {
-        if let Some(value) = candidate {
-            let trimmed = value.trim();
-            if !trimmed.is_empty() {
-                return Some(trimmed.to_string());
-            }
+    for value in candidates.into_iter().flatten() {
+        let trimmed = value.trim();
+        if !trimmed.is_empty() {
+            return Some(trimmed.to_string());
         }
     }
     None
@@ -767,6 +775,254 @@ fn cli_accepts_conf_dir_global_flag() {
     );
 }
 
+#[test]
+fn cli_parses_output_format_values() {
+    let parsed = Cli::try_parse_from(["pixy", "--prompt", "hi", "--output-format", "stream-json"])
+        .expect("stream-json should be accepted");
+    assert_eq!(parsed.chat.output_format, OutputFormat::StreamJson);
+
+    let parsed = Cli::try_parse_from(["pixy", "--prompt", "hi", "--output-format", "json"])
+        .expect("json should be accepted");
+    assert_eq!(parsed.chat.output_format, OutputFormat::Json);
+
+    let parsed = Cli::try_parse_from(["pixy", "--prompt", "hi"]).expect("default format");
+    assert_eq!(parsed.chat.output_format, OutputFormat::Text);
+
+    assert!(Cli::try_parse_from(["pixy", "--output-format", "yaml"]).is_err());
+}
+
+fn headless_assistant_message(
+    content: Vec<pixy_ai::AssistantContentBlock>,
+    stop_reason: pixy_ai::StopReason,
+) -> pixy_ai::AssistantMessage {
+    pixy_ai::AssistantMessage {
+        role: "assistant".to_string(),
+        content,
+        api: "test-api".to_string(),
+        provider: "test".to_string(),
+        model: "test-model".to_string(),
+        usage: pixy_ai::Usage {
+            input: 0,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            reasoning: 0,
+            total_tokens: 0,
+            cost: Cost {
+                input: 0.0,
+                output: 0.0,
+                cache_read: 0.0,
+                cache_write: 0.0,
+                total: 0.0,
+            },
+        },
+        stop_reason,
+        error_message: None,
+        timestamp: 1_700_000_000_000,
+        stats: None,
+    }
+}
+
+fn headless_test_model() -> Model {
+    Model {
+        id: "test-model".to_string(),
+        name: "Test Model".to_string(),
+        api: "test-api".to_string(),
+        provider: "test".to_string(),
+        base_url: "http://localhost".to_string(),
+        reasoning: false,
+        reasoning_effort: None,
+        input: vec!["text".to_string()],
+        cost: Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+        context_window: 128_000,
+        max_tokens: 8_192,
+        deployment: None,
+        api_version: None,
+        compat: None,
+    }
+}
+
+#[tokio::test]
+async fn stream_json_writes_tool_events_as_they_happen() {
+    use pixy_ai::{
+        AssistantContentBlock, AssistantMessageEvent, AssistantMessageEventStream, DoneReason,
+        StopReason,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let dir = tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("note.txt"), "hello from file").expect("seed note");
+    let calls = Arc::new(AtomicUsize::new(0));
+    let stream_fn = Arc::new(
+        move |_model: Model,
+              _context: pixy_ai::Context,
+              _options: Option<pixy_ai::SimpleStreamOptions>| {
+            let stream = AssistantMessageEventStream::new();
+            if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                let message = headless_assistant_message(
+                    vec![AssistantContentBlock::ToolCall {
+                        id: "call-1".to_string(),
+                        name: "read".to_string(),
+                        arguments: serde_json::json!({ "path": "note.txt" }),
+                        thought_signature: None,
+                    }],
+                    StopReason::ToolUse,
+                );
+                stream.push(AssistantMessageEvent::Start {
+                    partial: message.clone(),
+                });
+                stream.push(AssistantMessageEvent::Done {
+                    reason: DoneReason::ToolUse,
+                    message,
+                });
+            } else {
+                let partial = headless_assistant_message(vec![], StopReason::Stop);
+                stream.push(AssistantMessageEvent::Start {
+                    partial: partial.clone(),
+                });
+                stream.push(AssistantMessageEvent::TextDelta {
+                    content_index: 0,
+                    delta: "It says hello.".to_string(),
+                    partial,
+                });
+                stream.push(AssistantMessageEvent::Done {
+                    reason: DoneReason::Stop,
+                    message: headless_assistant_message(
+                        vec![AssistantContentBlock::Text {
+                            text: "It says hello.".to_string(),
+                            text_signature: None,
+                        }],
+                        StopReason::Stop,
+                    ),
+                });
+            }
+            Ok(stream)
+        },
+    );
+    let manager = crate::SessionManager::create(
+        dir.path().to_str().expect("cwd utf-8"),
+        dir.path().join("sessions"),
+    )
+    .expect("create manager");
+    let mut session = AgentSession::new(
+        manager,
+        crate::AgentSessionConfig {
+            model: headless_test_model(),
+            system_prompt: "You are helpful".to_string(),
+            stream_fn,
+            tools: crate::create_coding_tools(dir.path()),
+        },
+    );
+
+    let mut output = Vec::new();
+    run_prompt_headless(
+        &mut session,
+        "read the note",
+        &mut output,
+        OutputFormat::StreamJson,
+        serde_json::json!({ "type": "init" }),
+        None,
+    )
+    .await
+    .expect("headless run succeeds");
+
+    let events = String::from_utf8(output)
+        .expect("utf8")
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).expect("valid json line"))
+        .collect::<Vec<_>>();
+    let kinds = events
+        .iter()
+        .map(|event| event["type"].as_str().unwrap_or_default().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            "init",
+            "tool_call",
+            "tool_result",
+            "text_delta",
+            "assistant",
+            "usage",
+            "result"
+        ]
+    );
+    assert_eq!(events[1]["arguments"]["path"], "note.txt");
+    assert_eq!(events[2]["toolCallId"], "call-1");
+    assert_eq!(events[2]["isError"], false);
+    assert_eq!(events[4]["text"], "It says hello.");
+}
+
+#[test]
+fn read_prompt_input_reads_stdin_for_dash() {
+    assert_eq!(
+        read_prompt_input("fix it", io::empty()).expect("literal prompt"),
+        "fix it"
+    );
+    assert_eq!(
+        read_prompt_input("-", "  fix the failing test\n".as_bytes()).expect("stdin prompt"),
+        "fix the failing test"
+    );
+    assert!(read_prompt_input("-", " \n".as_bytes()).is_err());
+}
+
+#[test]
+fn cli_accepts_short_prompt_quiet_and_max_turns_flags() {
+    let parsed = Cli::try_parse_from(["pixy", "-p", "-", "-q", "--max-turns", "3"])
+        .expect("pipe-friendly flags should parse");
+    assert_eq!(parsed.chat.prompt.as_deref(), Some("-"));
+    assert!(parsed.chat.quiet);
+    assert_eq!(parsed.chat.max_turns, Some(3));
+}
+
+#[test]
+fn prompt_run_status_fails_on_error_abort_and_turn_limit() {
+    let assistant = |stop_reason: StopReason, error_message: Option<&str>| Message::Assistant {
+        content: vec![],
+        api: "test".to_string(),
+        provider: "test".to_string(),
+        model: "test".to_string(),
+        usage: pixy_ai::Usage {
+            input: 0,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            reasoning: 0,
+            total_tokens: 0,
+            cost: pixy_ai::Cost {
+                input: 0.0,
+                output: 0.0,
+                cache_read: 0.0,
+                cache_write: 0.0,
+                total: 0.0,
+            },
+        },
+        stop_reason,
+        error_message: error_message.map(ToOwned::to_owned),
+        timestamp: 0,
+        stats: None,
+    };
+
+    assert!(prompt_run_status(&[assistant(StopReason::Stop, None)], false, None).is_ok());
+    assert_eq!(
+        prompt_run_status(&[assistant(StopReason::Error, Some("boom"))], false, None),
+        Err("boom".to_string())
+    );
+    assert!(prompt_run_status(&[assistant(StopReason::Aborted, None)], false, None).is_err());
+    assert_eq!(
+        prompt_run_status(&[assistant(StopReason::Aborted, None)], true, Some(4)),
+        Err("stopped after reaching --max-turns 4".to_string())
+    );
+    assert!(prompt_run_status(&[], false, None).is_err());
+}
+
 #[test]
 fn gateway_command_tokens_include_daemon_flag_when_requested() {
     let tokens = gateway_command_tokens(
@@ -840,6 +1096,47 @@ fn repl_command_parser_recognizes_control_commands() {
             target: Some("abc.jsonl".to_string())
         })
     );
+    assert_eq!(
+        ReplCommandParser::parse("/fork"),
+        Some(ReplCommand::Fork { name: None })
+    );
+    assert_eq!(
+        ReplCommandParser::parse("/fork  retry-with-sqlite "),
+        Some(ReplCommand::Fork {
+            name: Some("retry-with-sqlite".to_string())
+        })
+    );
+    assert_eq!(ReplCommandParser::parse("/undo"), Some(ReplCommand::Undo));
+    assert_eq!(ReplCommandParser::parse(" /redo "), Some(ReplCommand::Redo));
+    assert_eq!(
+        ReplCommandParser::parse("/skill review depth=deep"),
+        Some(ReplCommand::Skill {
+            args: "review depth=deep".to_string()
+        })
+    );
+    assert_eq!(
+        ReplCommandParser::parse("/skills disable review"),
+        Some(ReplCommand::Skills {
+            args: "disable review".to_string()
+        })
+    );
+    assert_eq!(
+        ReplCommandParser::parse("/remember"),
+        Some(ReplCommand::Remember { note: None })
+    );
+    assert_eq!(
+        ReplCommandParser::parse("/remember  use nextest for tests "),
+        Some(ReplCommand::Remember {
+            note: Some("use nextest for tests".to_string())
+        })
+    );
+    assert_eq!(ReplCommandParser::parse("/cost"), Some(ReplCommand::Cost));
+    assert_eq!(
+        ReplCommandParser::parse("/forked"),
+        Some(ReplCommand::Prompt {
+            text: "/forked".to_string()
+        })
+    );
     assert_eq!(
         ReplCommandParser::parse("/continue"),
         Some(ReplCommand::Continue)
@@ -876,12 +1173,22 @@ fn resolves_default_provider_model_from_settings_and_models() {
         base_url: None,
         context_window: None,
         max_tokens: None,
+        temperature: None,
+        seed: None,
+        proxy: None,
+        no_proxy: Vec::new(),
+        ca_bundle: None,
+        client_cert: None,
+        client_key: None,
         agent_dir: None,
         cwd: None,
         session_dir: None,
         session_file: None,
         system_prompt: Some("test".to_string()),
         prompt: None,
+        output_format: OutputFormat::Text,
+        max_turns: None,
+        quiet: false,
         continue_first: false,
         no_tools: false,
         skills: vec![],
@@ -889,6 +1196,9 @@ fn resolves_default_provider_model_from_settings_and_models() {
         hide_tool_results: false,
         no_tui: false,
         theme: None,
+        worktree: false,
+        review: false,
+        tool_parallelism: None,
     };
 
     let local = AgentLocalConfig {
@@ -1269,12 +1579,22 @@ fn cli_model_with_provider_prefix_overrides_settings_provider() {
         base_url: None,
         context_window: None,
         max_tokens: None,
+        temperature: None,
+        seed: None,
+        proxy: None,
+        no_proxy: Vec::new(),
+        ca_bundle: None,
+        client_cert: None,
+        client_key: None,
         agent_dir: None,
         cwd: None,
         session_dir: None,
         session_file: None,
         system_prompt: Some("test".to_string()),
         prompt: None,
+        output_format: OutputFormat::Text,
+        max_turns: None,
+        quiet: false,
         continue_first: false,
         no_tools: false,
         skills: vec![],
@@ -1282,6 +1602,9 @@ fn cli_model_with_provider_prefix_overrides_settings_provider() {
         hide_tool_results: false,
         no_tui: false,
         theme: None,
+        worktree: false,
+        review: false,
+        tool_parallelism: None,
     };
 
     let local = AgentLocalConfig {
@@ -1311,12 +1634,22 @@ fn provider_api_key_can_be_resolved_from_settings_env() {
         base_url: Some("https://api.anthropic.com/v1".to_string()),
         context_window: None,
         max_tokens: None,
+        temperature: None,
+        seed: None,
+        proxy: None,
+        no_proxy: Vec::new(),
+        ca_bundle: None,
+        client_cert: None,
+        client_key: None,
         agent_dir: None,
         cwd: None,
         session_dir: None,
         session_file: None,
         system_prompt: Some("test".to_string()),
         prompt: None,
+        output_format: OutputFormat::Text,
+        max_turns: None,
+        quiet: false,
         continue_first: false,
         no_tools: false,
         skills: vec![],
@@ -1324,6 +1657,9 @@ fn provider_api_key_can_be_resolved_from_settings_env() {
         hide_tool_results: false,
         no_tui: false,
         theme: None,
+        worktree: false,
+        review: false,
+        tool_parallelism: None,
     };
 
     let local = AgentLocalConfig {
@@ -1390,12 +1726,22 @@ fn resolve_runtime_config_builds_model_catalog_with_selected_model_first() {
         base_url: None,
         context_window: None,
         max_tokens: None,
+        temperature: None,
+        seed: None,
+        proxy: None,
+        no_proxy: Vec::new(),
+        ca_bundle: None,
+        client_cert: None,
+        client_key: None,
         agent_dir: None,
         cwd: None,
         session_dir: None,
         session_file: None,
         system_prompt: Some("test".to_string()),
         prompt: None,
+        output_format: OutputFormat::Text,
+        max_turns: None,
+        quiet: false,
         continue_first: false,
         no_tools: false,
         skills: vec![],
@@ -1403,6 +1749,9 @@ fn resolve_runtime_config_builds_model_catalog_with_selected_model_first() {
         hide_tool_results: false,
         no_tui: false,
         theme: None,
+        worktree: false,
+        review: false,
+        tool_parallelism: None,
     };
 
     let local = AgentLocalConfig {
@@ -1743,12 +2092,22 @@ fn resolve_runtime_config_supports_google_provider_inference() {
         base_url: None,
         context_window: None,
         max_tokens: None,
+        temperature: None,
+        seed: None,
+        proxy: None,
+        no_proxy: Vec::new(),
+        ca_bundle: None,
+        client_cert: None,
+        client_key: None,
         agent_dir: None,
         cwd: None,
         session_dir: None,
         session_file: None,
         system_prompt: Some("test".to_string()),
         prompt: None,
+        output_format: OutputFormat::Text,
+        max_turns: None,
+        quiet: false,
         continue_first: false,
         no_tools: false,
         skills: vec![],
@@ -1756,6 +2115,9 @@ fn resolve_runtime_config_supports_google_provider_inference() {
         hide_tool_results: false,
         no_tui: false,
         theme: None,
+        worktree: false,
+        review: false,
+        tool_parallelism: None,
     };
     let local = AgentLocalConfig::default();
 
@@ -1777,12 +2139,22 @@ fn resolve_runtime_config_defaults_reasoning_to_medium_when_unconfigured() {
         base_url: None,
         context_window: None,
         max_tokens: None,
+        temperature: None,
+        seed: None,
+        proxy: None,
+        no_proxy: Vec::new(),
+        ca_bundle: None,
+        client_cert: None,
+        client_key: None,
         agent_dir: None,
         cwd: None,
         session_dir: None,
         session_file: None,
         system_prompt: Some("test".to_string()),
         prompt: None,
+        output_format: OutputFormat::Text,
+        max_turns: None,
+        quiet: false,
         continue_first: false,
         no_tools: false,
         skills: vec![],
@@ -1790,6 +2162,9 @@ fn resolve_runtime_config_defaults_reasoning_to_medium_when_unconfigured() {
         hide_tool_results: false,
         no_tui: false,
         theme: None,
+        worktree: false,
+        review: false,
+        tool_parallelism: None,
     };
     let local = AgentLocalConfig::default();
 
@@ -2100,6 +2475,7 @@ fn build_startup_resource_lines_is_empty_for_d_style_banner() {
         base_dir: cwd.join(".agents/skills/project"),
         source: SkillSource::Project,
         disable_model_invocation: false,
+        arguments: vec![],
     }];
 
     let lines = build_startup_resource_lines(&cwd, &agent_dir, &skills);
@@ -2169,3 +2545,48 @@ fn resolve_resume_picker_selection_rejects_out_of_range_choice() {
         resolve_resume_picker_selection(&candidates, "2").expect_err("selection should fail");
     assert!(error.contains("between 1 and 1"));
 }
+
+#[test]
+fn skills_command_parses_actions() {
+    use crate::cli_app::SkillsCommand;
+
+    assert_eq!(SkillsCommand::parse(""), Ok(SkillsCommand::List));
+    assert_eq!(SkillsCommand::parse("reload"), Ok(SkillsCommand::Reload));
+    assert_eq!(
+        SkillsCommand::parse("enable review"),
+        Ok(SkillsCommand::Enable {
+            name: "review".to_string()
+        })
+    );
+    assert_eq!(
+        SkillsCommand::parse("disable review"),
+        Ok(SkillsCommand::Disable {
+            name: "review".to_string()
+        })
+    );
+    assert!(SkillsCommand::parse("disable").is_err());
+    assert!(SkillsCommand::parse("enable a b").is_err());
+}
+
+#[test]
+fn parse_diff_review_answer_accepts_reasons_for_rejections() {
+    assert_eq!(
+        parse_diff_review_answer("y\n"),
+        Some(DiffReviewDecision::Approve)
+    );
+    assert_eq!(
+        parse_diff_review_answer(" A "),
+        Some(DiffReviewDecision::ApproveAll)
+    );
+    assert_eq!(
+        parse_diff_review_answer("n\n"),
+        Some(DiffReviewDecision::Reject { feedback: None })
+    );
+    assert_eq!(
+        parse_diff_review_answer("no   keep the old signature\n"),
+        Some(DiffReviewDecision::Reject {
+            feedback: Some("keep the old signature".to_string())
+        })
+    );
+    assert_eq!(parse_diff_review_answer("maybe"), None);
+}
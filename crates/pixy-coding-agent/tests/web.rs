@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use pixy_ai::ToolResultContentBlock;
+use pixy_coding_agent::{
+    create_web_fetch_tool, create_web_search_tool, WebSearchBackendKind, WebSearchConfig,
+};
+use serde_json::json;
+
+const ARTICLE_PAGE: &str = r#"<!DOCTYPE html>
+<html><head><title>Release Notes &amp; Changes</title>
+<style>body { color: red; }</style>
+<script>var tracking = "<p>not content</p>";</script></head>
+<body>
+<nav><a href="/">Home</a> <a href="/docs">Docs</a></nav>
+<article>
+  <h1>Version 2.0</h1>
+  <p>The new   <code>run</code> command replaces <a href="/guide/run">the old runner</a>.</p>
+  <ul><li>Faster builds</li><li>Smaller binaries</li></ul>
+  <pre>cargo run
+  --release</pre>
+</article>
+<aside>Subscribe to our newsletter</aside>
+<footer>Copyright 2026</footer>
+</body></html>"#;
+
+struct Route {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+    location: Option<&'static str>,
+}
+
+type Requests = Arc<Mutex<Vec<String>>>;
+
+/// Serves `routes` by path (query string excluded) and records each request line with its
+/// headers; unknown paths get a 404.
+fn serve(routes: HashMap<&'static str, Route>) -> (String, Requests) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+    let address = listener.local_addr().expect("address");
+    let requests: Requests = Arc::default();
+    let recorded = requests.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(stream.try_clone().expect("clone stream"));
+            let mut request = String::new();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line.trim().is_empty() {
+                    break;
+                }
+                request.push_str(&line);
+            }
+            let path = request
+                .split_whitespace()
+                .nth(1)
+                .unwrap_or_default()
+                .split('?')
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            recorded.lock().expect("requests").push(request);
+            let (status, content_type, body, location) = match routes.get(path.as_str()) {
+                Some(route) => (
+                    route.status,
+                    route.content_type,
+                    route.body.as_str(),
+                    route.location,
+                ),
+                None => (404, "text/plain", "not found", None),
+            };
+            let location = location
+                .map(|location| format!("Location: {location}\r\n"))
+                .unwrap_or_default();
+            let mut stream = stream;
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {status} X\r\nContent-Type: {content_type}\r\n{location}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+        }
+    });
+    (format!("http://{address}"), requests)
+}
+
+fn route(status: u16, content_type: &'static str, body: impl Into<String>) -> Route {
+    Route {
+        status,
+        content_type,
+        body: body.into(),
+        location: None,
+    }
+}
+
+fn redirect(location: &'static str) -> Route {
+    Route {
+        location: Some(location),
+        ..route(302, "text/plain", "")
+    }
+}
+
+fn first_text(blocks: &[ToolResultContentBlock]) -> String {
+    blocks
+        .iter()
+        .find_map(|block| match block {
+            ToolResultContentBlock::Text { text, .. } => Some(text.clone()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+#[tokio::test]
+async fn web_fetch_tool_extracts_readable_text_and_caps_output() {
+    let (base, _) = serve(HashMap::from([
+        (
+            "/release",
+            route(200, "text/html; charset=utf-8", ARTICLE_PAGE),
+        ),
+        (
+            "/data.json",
+            route(200, "application/json", r#"{"ok":true}"#),
+        ),
+        ("/logo.png", route(200, "image/png", "\u{0}PNG")),
+        ("/missing", route(404, "text/html", "<p>Gone</p>")),
+    ]));
+    let tool = create_web_fetch_tool();
+
+    let result = tool
+        .execute
+        .execute(
+            "call-1".to_string(),
+            json!({ "url": format!("{base}/release") }),
+        )
+        .await
+        .expect("page should be fetched");
+    let text = first_text(&result.content);
+    assert_eq!(
+        text,
+        format!(
+            "Title: Release Notes & Changes\nURL: {base}/release\n\n# Version 2.0\n\nThe new `run` command replaces [the old runner]({base}/guide/run).\n\n- Faster builds\n- Smaller binaries\n\n```\ncargo run\n  --release\n```"
+        )
+    );
+    assert_eq!(result.details["status"], 200);
+    assert_eq!(result.details["title"], "Release Notes & Changes");
+    assert_eq!(result.details["truncated"], false);
+
+    let capped = tool
+        .execute
+        .execute(
+            "call-2".to_string(),
+            json!({ "url": format!("{base}/release"), "maxChars": 13 }),
+        )
+        .await
+        .expect("page should be fetched");
+    let text = first_text(&capped.content);
+    assert!(text.contains("\n\n# Version 2.0\n\n[Showing the first 13 of "));
+    assert_eq!(capped.details["truncated"], true);
+
+    let json_body = tool
+        .execute
+        .execute(
+            "call-3".to_string(),
+            json!({ "url": format!("{base}/data.json") }),
+        )
+        .await
+        .expect("json should be fetched");
+    assert!(first_text(&json_body.content).ends_with(r#"{"ok":true}"#));
+
+    let binary = tool
+        .execute
+        .execute(
+            "call-4".to_string(),
+            json!({ "url": format!("{base}/logo.png") }),
+        )
+        .await
+        .expect_err("binary content should fail");
+    assert_eq!(
+        binary.details.as_ref().expect("details")["reason"],
+        "unsupported_content_type"
+    );
+
+    let missing = tool
+        .execute
+        .execute(
+            "call-5".to_string(),
+            json!({ "url": format!("{base}/missing") }),
+        )
+        .await
+        .expect_err("404 should fail");
+    assert!(missing.message.starts_with("HTTP 404"));
+    assert!(missing.message.ends_with("Gone"));
+
+    let unsupported = tool
+        .execute
+        .execute("call-6".to_string(), json!({ "url": "file:///etc/passwd" }))
+        .await
+        .expect_err("file urls should be rejected");
+    assert!(unsupported.message.contains("only http and https"));
+}
+
+#[tokio::test]
+async fn web_fetch_tool_honours_robots_txt() {
+    let robots = "User-agent: *\nDisallow: /\n\nUser-agent: pixy\nDisallow: /private\nAllow: /private/public$\n";
+    let (base, requests) = serve(HashMap::from([
+        ("/robots.txt", route(200, "text/plain", robots)),
+        ("/open", route(200, "text/plain", "open page")),
+        ("/private/public", route(200, "text/plain", "public page")),
+        ("/private/secret", route(200, "text/plain", "secret page")),
+    ]));
+    let tool = create_web_fetch_tool();
+
+    for path in ["/open", "/private/public"] {
+        tool.execute
+            .execute(
+                "call".to_string(),
+                json!({ "url": format!("{base}{path}") }),
+            )
+            .await
+            .expect("allowed page should be fetched");
+    }
+    let blocked = tool
+        .execute
+        .execute(
+            "call".to_string(),
+            json!({ "url": format!("{base}/private/secret") }),
+        )
+        .await
+        .expect_err("disallowed page should fail");
+    assert!(blocked.message.contains("robots.txt"));
+    assert_eq!(
+        blocked.details.as_ref().expect("details")["reason"],
+        "robots_disallowed"
+    );
+
+    let requests = requests.lock().expect("requests");
+    let robots_fetches = requests
+        .iter()
+        .filter(|request| request.starts_with("GET /robots.txt"))
+        .count();
+    assert_eq!(robots_fetches, 1, "robots.txt is cached per origin");
+    assert!(!requests
+        .iter()
+        .any(|request| request.starts_with("GET /private/secret")));
+    assert!(requests[0]
+        .to_ascii_lowercase()
+        .contains("user-agent: pixy/"));
+}
+
+#[tokio::test]
+async fn web_fetch_tool_checks_robots_txt_on_every_redirect() {
+    let robots = "User-agent: *\nDisallow: /private\n";
+    let (base, requests) = serve(HashMap::from([
+        ("/robots.txt", route(200, "text/plain", robots)),
+        ("/moved", redirect("/open")),
+        ("/open", route(200, "text/plain", "open page")),
+        ("/sneaky", redirect("/private/secret")),
+        ("/private/secret", route(200, "text/plain", "secret page")),
+    ]));
+    let tool = create_web_fetch_tool();
+
+    let fetched = tool
+        .execute
+        .execute(
+            "call".to_string(),
+            json!({ "url": format!("{base}/moved") }),
+        )
+        .await
+        .expect("redirect to an allowed page should be followed");
+    assert!(first_text(&fetched.content).contains("open page"));
+    assert_eq!(fetched.details["finalUrl"], format!("{base}/open"));
+
+    let blocked = tool
+        .execute
+        .execute(
+            "call".to_string(),
+            json!({ "url": format!("{base}/sneaky") }),
+        )
+        .await
+        .expect_err("redirect to a disallowed page should fail");
+    assert_eq!(
+        blocked.details.as_ref().expect("details")["reason"],
+        "robots_disallowed"
+    );
+    assert!(!requests
+        .lock()
+        .expect("requests")
+        .iter()
+        .any(|request| request.starts_with("GET /private/secret")));
+}
+
+#[tokio::test]
+async fn web_search_tool_queries_configured_backends() {
+    let (base, requests) = serve(HashMap::from([
+        (
+            "/search",
+            route(
+                200,
+                "application/json",
+                json!({ "results": [
+                    { "title": "Tokio docs", "url": "https://docs.rs/tokio", "content": "An <b>async</b> runtime" },
+                    { "title": "Tokio site", "url": "https://tokio.rs" },
+                ]})
+                .to_string(),
+            ),
+        ),
+        (
+            "/brave",
+            route(
+                200,
+                "application/json",
+                json!({ "web": { "results": [
+                    { "title": "Brave hit", "url": "https://example.com/a", "description": "first &amp; best" },
+                ]}})
+                .to_string(),
+            ),
+        ),
+        ("/bing", route(401, "application/json", r#"{"error":"bad key"}"#)),
+    ]));
+
+    let searxng = WebSearchConfig {
+        backend: Some(WebSearchBackendKind::Searxng),
+        url: Some(format!("{base}/")),
+        ..WebSearchConfig::default()
+    };
+    let tool = create_web_search_tool(
+        searxng
+            .build_backend()
+            .expect("valid config")
+            .expect("backend"),
+    );
+    let result = tool
+        .execute
+        .execute(
+            "call-1".to_string(),
+            json!({ "query": "tokio runtime", "count": 1 }),
+        )
+        .await
+        .expect("search should succeed");
+    assert_eq!(
+        first_text(&result.content),
+        "1. Tokio docs\n   https://docs.rs/tokio\n   An async runtime"
+    );
+    assert_eq!(result.details["backend"], "searxng");
+    assert_eq!(result.details["results"].as_array().map(Vec::len), Some(1));
+
+    let brave = WebSearchConfig {
+        backend: Some(WebSearchBackendKind::Brave),
+        url: Some(format!("{base}/brave")),
+        api_key: Some("brave-key".to_string()),
+        timeout_ms: None,
+    };
+    let tool = create_web_search_tool(brave.build_backend().unwrap().unwrap());
+    let result = tool
+        .execute
+        .execute("call-2".to_string(), json!({ "query": "pixy" }))
+        .await
+        .expect("search should succeed");
+    assert_eq!(
+        first_text(&result.content),
+        "1. Brave hit\n   https://example.com/a\n   first & best"
+    );
+
+    let bing = WebSearchConfig {
+        backend: Some(WebSearchBackendKind::Bing),
+        url: Some(format!("{base}/bing")),
+        api_key: Some("bing-key".to_string()),
+        timeout_ms: None,
+    };
+    let tool = create_web_search_tool(bing.build_backend().unwrap().unwrap());
+    let error = tool
+        .execute
+        .execute("call-3".to_string(), json!({ "query": "pixy" }))
+        .await
+        .expect_err("401 should fail");
+    assert!(error
+        .message
+        .contains("web_search via bing failed: HTTP 401"));
+
+    let requests = requests.lock().expect("requests");
+    assert!(requests[0].starts_with("GET /search?q=tokio+runtime&format=json "));
+    assert!(requests[1].starts_with("GET /brave?q=pixy&count=5 "));
+    assert!(requests[1]
+        .to_ascii_lowercase()
+        .contains("x-subscription-token: brave-key"));
+    assert!(requests[2]
+        .to_ascii_lowercase()
+        .contains("ocp-apim-subscription-key: bing-key"));
+
+    let missing_key = WebSearchConfig {
+        backend: Some(WebSearchBackendKind::Brave),
+        ..WebSearchConfig::default()
+    };
+    assert_eq!(
+        missing_key.build_backend().err().as_deref(),
+        Some("web_search backend `brave` needs an api_key")
+    );
+    assert!(WebSearchConfig::default()
+        .build_backend()
+        .expect("no backend is valid")
+        .is_none());
+}
@@ -12,10 +12,15 @@ use serde_json::Value;
 use tokio::sync::{mpsc, Mutex};
 use tokio::time::Instant;
 
-use crate::channels::{Channel, ChannelFuture, SessionDispatcher};
+use crate::channels::{
+    multipart_form, Channel, ChannelFuture, DispatchReply, MediaAttachment, SessionDispatcher,
+};
 use crate::config::FeishuChannelConfig;
 
 const FEISHU_MESSAGE_TYPE_TEXT: &str = "text";
+const FEISHU_MESSAGE_TYPE_IMAGE: &str = "image";
+const FEISHU_MESSAGE_TYPE_AUDIO: &str = "audio";
+const FEISHU_MESSAGE_TYPE_FILE: &str = "file";
 const FEISHU_CHAT_TYPE_PRIVATE: &str = "p2p";
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -160,12 +165,30 @@ struct FeishuTenantAccessTokenResponse {
 }
 
 #[derive(Debug, Serialize)]
-struct FeishuSendTextRequest {
+struct FeishuSendMessageRequest {
     receive_id: String,
     msg_type: &'static str,
     content: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct FeishuUploadResponse {
+    #[serde(default)]
+    code: i32,
+    #[serde(default)]
+    msg: Option<String>,
+    #[serde(default)]
+    data: Option<FeishuUploadData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FeishuUploadData {
+    #[serde(default)]
+    image_key: Option<String>,
+    #[serde(default)]
+    file_key: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct FeishuApiStatusResponse {
     #[serde(default)]
@@ -174,14 +197,35 @@ struct FeishuApiStatusResponse {
     msg: Option<String>,
 }
 
-fn build_send_text_request(chat_id: &str, text: &str) -> FeishuSendTextRequest {
-    FeishuSendTextRequest {
+fn build_send_text_request(chat_id: &str, text: &str) -> FeishuSendMessageRequest {
+    FeishuSendMessageRequest {
         receive_id: chat_id.to_string(),
         msg_type: FEISHU_MESSAGE_TYPE_TEXT,
         content: serde_json::json!({ "text": text }).to_string(),
     }
 }
 
+fn build_send_image_request(chat_id: &str, image_key: &str) -> FeishuSendMessageRequest {
+    FeishuSendMessageRequest {
+        receive_id: chat_id.to_string(),
+        msg_type: FEISHU_MESSAGE_TYPE_IMAGE,
+        content: serde_json::json!({ "image_key": image_key }).to_string(),
+    }
+}
+
+/// Feishu only plays Opus clips as voice messages; other audio is sent as a file.
+fn build_send_audio_request(chat_id: &str, file_key: &str, opus: bool) -> FeishuSendMessageRequest {
+    FeishuSendMessageRequest {
+        receive_id: chat_id.to_string(),
+        msg_type: if opus {
+            FEISHU_MESSAGE_TYPE_AUDIO
+        } else {
+            FEISHU_MESSAGE_TYPE_FILE
+        },
+        content: serde_json::json!({ "file_key": file_key }).to_string(),
+    }
+}
+
 fn parse_feishu_webhook_payload(
     payload: Value,
     expected_token: &str,
@@ -422,19 +466,32 @@ impl Channel for FeishuChannel {
                     .dispatch_text(&self.name, &inbound.user_id, &inbound.text)
                     .await
                 {
-                    Ok(text) => text,
+                    Ok(reply) => reply,
                     Err(error) => {
                         eprintln!(
                             "warning: route '{}:{}' failed: {error}",
                             self.name, inbound.user_id
                         );
-                        "Sorry, I hit an internal error while processing your message.".to_string()
+                        DispatchReply::from(
+                            "Sorry, I hit an internal error while processing your message."
+                                .to_string(),
+                        )
                     }
                 };
 
                 self.client
-                    .send_text_message(&inbound.chat_id, &reply)
+                    .send_text_message(&inbound.chat_id, &reply.text)
                     .await?;
+                for image in &reply.images {
+                    self.client
+                        .send_image_message(&inbound.chat_id, image)
+                        .await?;
+                }
+                for audio in &reply.audio {
+                    self.client
+                        .send_audio_message(&inbound.chat_id, audio)
+                        .await?;
+                }
             }
             Ok(())
         })
@@ -471,14 +528,86 @@ impl FeishuClient {
     }
 
     async fn send_text_message(&self, chat_id: &str, text: &str) -> Result<(), String> {
+        self.send_message(&build_send_text_request(chat_id, text))
+            .await
+    }
+
+    async fn send_image_message(
+        &self,
+        chat_id: &str,
+        image: &MediaAttachment,
+    ) -> Result<(), String> {
+        let image_key = self.upload_image(image).await?;
+        self.send_message(&build_send_image_request(chat_id, &image_key))
+            .await
+    }
+
+    async fn send_audio_message(
+        &self,
+        chat_id: &str,
+        audio: &MediaAttachment,
+    ) -> Result<(), String> {
+        let opus =
+            audio.mime_type.starts_with("audio/ogg") || audio.mime_type.starts_with("audio/opus");
+        let file_name = audio.file_name();
+        let fields = [
+            ("file_type", if opus { "opus" } else { "stream" }),
+            ("file_name", file_name.as_str()),
+        ];
+        let file_key = self.upload("files", &fields, "file", audio).await?;
+        self.send_message(&build_send_audio_request(chat_id, &file_key, opus))
+            .await
+    }
+
+    async fn upload_image(&self, image: &MediaAttachment) -> Result<String, String> {
+        self.upload("images", &[("image_type", "message")], "image", image)
+            .await
+    }
+
+    /// Uploads to `im/v1/{kind}` and returns the resulting image or file key.
+    async fn upload(
+        &self,
+        kind: &str,
+        fields: &[(&str, &str)],
+        file_field: &str,
+        file: &MediaAttachment,
+    ) -> Result<String, String> {
+        let token = self.tenant_access_token().await?;
+        let url = format!("{}/im/v1/{kind}", self.api_base);
+        let (content_type, body) = multipart_form(fields, file_field, file);
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(token)
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(body)
+            .send()
+            .await
+            .map_err(|error| format!("feishu {kind} upload request failed: {error}"))?;
+        let parsed = response
+            .json::<FeishuUploadResponse>()
+            .await
+            .map_err(|error| format!("feishu {kind} upload decode failed: {error}"))?;
+        if parsed.code != 0 {
+            return Err(parsed.msg.unwrap_or_else(|| {
+                format!("feishu {kind} upload failed with code {}", parsed.code)
+            }));
+        }
+        parsed
+            .data
+            .and_then(|data| data.image_key.or(data.file_key))
+            .filter(|value| !value.trim().is_empty())
+            .ok_or_else(|| format!("feishu {kind} upload returned no key"))
+    }
+
+    async fn send_message(&self, payload: &FeishuSendMessageRequest) -> Result<(), String> {
         let token = self.tenant_access_token().await?;
         let url = format!("{}/im/v1/messages?receive_id_type=chat_id", self.api_base);
-        let payload = build_send_text_request(chat_id, text);
         let response = self
             .client
             .post(url)
             .bearer_auth(token)
-            .json(&payload)
+            .json(payload)
             .send()
             .await
             .map_err(|error| format!("feishu send message request failed: {error}"))?;
@@ -653,6 +782,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn build_send_image_request_serializes_as_feishu_image_message() {
+        let request = build_send_image_request("oc_1", "img_v2_abc");
+        let value = serde_json::to_value(request).expect("request should serialize");
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "receive_id": "oc_1",
+                "msg_type": "image",
+                "content": "{\"image_key\":\"img_v2_abc\"}"
+            })
+        );
+    }
+
+    #[test]
+    fn build_send_audio_request_uses_audio_only_for_opus() {
+        let voice = serde_json::to_value(build_send_audio_request("oc_1", "file_v2_abc", true))
+            .expect("request should serialize");
+        assert_eq!(voice["msg_type"], "audio");
+        assert_eq!(voice["content"], "{\"file_key\":\"file_v2_abc\"}");
+
+        let wav = serde_json::to_value(build_send_audio_request("oc_1", "file_v2_abc", false))
+            .expect("request should serialize");
+        assert_eq!(wav["msg_type"], "file");
+    }
+
     #[tokio::test]
     async fn feishu_webhook_router_routes_message_to_channel_queue() {
         let (sender, mut receiver) = mpsc::unbounded_channel();
@@ -8,7 +8,46 @@ pub mod feishu;
 pub mod telegram;
 
 pub type ChannelFuture<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + 'a>>;
-pub type DispatchFuture<'a> = Pin<Box<dyn Future<Output = Result<String, String>> + 'a>>;
+pub type DispatchFuture<'a> = Pin<Box<dyn Future<Output = Result<DispatchReply, String>> + 'a>>;
+
+/// What a channel sends back for one inbound message.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DispatchReply {
+    pub text: String,
+    pub images: Vec<MediaAttachment>,
+    pub audio: Vec<MediaAttachment>,
+}
+
+impl From<String> for DispatchReply {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            ..Self::default()
+        }
+    }
+}
+
+/// Decoded image or audio clip, either received from a user or produced during a turn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaAttachment {
+    pub mime_type: String,
+    pub bytes: Vec<u8>,
+}
+
+impl MediaAttachment {
+    /// File name derived from the MIME type, e.g. `image/png` -> `image.png`.
+    pub fn file_name(&self) -> String {
+        let (kind, subtype) = self.mime_type.split_once('/').unwrap_or(("file", "bin"));
+        let subtype = subtype.split(';').next().unwrap_or_default().trim();
+        let extension = match subtype {
+            "" => "bin",
+            "mpeg" => "mp3",
+            "x-wav" => "wav",
+            other => other,
+        };
+        format!("{kind}.{extension}")
+    }
+}
 
 pub trait SessionDispatcher {
     fn dispatch_text<'a>(
@@ -17,6 +56,14 @@ pub trait SessionDispatcher {
         user_id: &'a str,
         text: &'a str,
     ) -> DispatchFuture<'a>;
+
+    /// Sends a voice note to the session as audio input.
+    fn dispatch_voice<'a>(
+        &'a mut self,
+        channel_name: &'a str,
+        user_id: &'a str,
+        audio: &'a MediaAttachment,
+    ) -> DispatchFuture<'a>;
 }
 
 pub trait Channel: Send {
@@ -27,3 +74,82 @@ pub trait Channel: Send {
         dispatcher: &'a mut dyn SessionDispatcher,
     ) -> ChannelFuture<'a>;
 }
+
+/// Builds a `multipart/form-data` body with text fields followed by one file part.
+/// Returns the content type (including the boundary) and the encoded body.
+pub(crate) fn multipart_form(
+    fields: &[(&str, &str)],
+    file_field: &str,
+    file: &MediaAttachment,
+) -> (String, Vec<u8>) {
+    let boundary = format!(
+        "pixy-{:x}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or_default()
+    );
+    let mut body = Vec::with_capacity(file.bytes.len() + 512);
+    for (name, value) in fields {
+        body.extend_from_slice(
+            format!(
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"{file_field}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+            file.file_name(),
+            file.mime_type
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(&file.bytes);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+    (format!("multipart/form-data; boundary={boundary}"), body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multipart_form_encodes_fields_and_file_part() {
+        let file = MediaAttachment {
+            mime_type: "image/jpeg".to_string(),
+            bytes: vec![0xff, 0xd8, 0xff],
+        };
+        let (content_type, body) = multipart_form(&[("chat_id", "42")], "photo", &file);
+        let boundary = content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .expect("boundary");
+
+        let expected_head = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"chat_id\"\r\n\r\n42\r\n--{boundary}\r\nContent-Disposition: form-data; name=\"photo\"; filename=\"image.jpeg\"\r\nContent-Type: image/jpeg\r\n\r\n"
+        );
+        assert!(body.starts_with(expected_head.as_bytes()));
+        let tail = format!("\r\n--{boundary}--\r\n");
+        assert!(body.ends_with(tail.as_bytes()));
+        assert_eq!(
+            &body[expected_head.len()..body.len() - tail.len()],
+            &[0xff, 0xd8, 0xff]
+        );
+    }
+
+    #[test]
+    fn media_attachment_file_name_follows_mime_type() {
+        let name = |mime_type: &str| {
+            MediaAttachment {
+                mime_type: mime_type.to_string(),
+                bytes: Vec::new(),
+            }
+            .file_name()
+        };
+        assert_eq!(name("image/png"), "image.png");
+        assert_eq!(name("audio/mpeg"), "audio.mp3");
+        assert_eq!(name("audio/ogg; codecs=opus"), "audio.ogg");
+        assert_eq!(name("application"), "file.bin");
+    }
+}
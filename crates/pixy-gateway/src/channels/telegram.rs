@@ -5,12 +5,16 @@ use reqwest::{Client, Proxy};
 use serde::{Deserialize, Serialize};
 use tokio::time::Instant;
 
-use crate::channels::{Channel, ChannelFuture, SessionDispatcher};
+use crate::channels::{
+    multipart_form, Channel, ChannelFuture, DispatchReply, MediaAttachment, SessionDispatcher,
+};
 use crate::config::TelegramChannelConfig;
 
 const TELEGRAM_MAX_TEXT_CHARS: usize = 4_000;
 const TELEGRAM_TYPING_ACTION: &str = "typing";
 const TELEGRAM_TYPING_REFRESH_INTERVAL: Duration = Duration::from_secs(4);
+/// Telegram records voice notes as OGG/Opus.
+const TELEGRAM_VOICE_MIME_TYPE: &str = "audio/ogg";
 
 #[derive(Debug, Clone)]
 pub struct TelegramClient {
@@ -26,6 +30,7 @@ pub struct TelegramInboundMessage {
     pub chat_id: i64,
     pub user_id: String,
     pub text: String,
+    pub voice: Option<TelegramVoice>,
 }
 
 pub struct TelegramChannel {
@@ -69,6 +74,30 @@ pub struct TelegramMessage {
     pub from: Option<TelegramUser>,
     #[serde(default)]
     pub text: Option<String>,
+    #[serde(default)]
+    pub voice: Option<TelegramVoice>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TelegramVoice {
+    pub file_id: String,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramGetFileResponse {
+    ok: bool,
+    #[serde(default)]
+    result: Option<TelegramFile>,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramFile {
+    #[serde(default)]
+    file_path: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -98,6 +127,11 @@ struct SendMessageRequest<'a> {
     text: &'a str,
 }
 
+#[derive(Debug, Serialize)]
+struct GetFileRequest<'a> {
+    file_id: &'a str,
+}
+
 #[derive(Debug, Serialize)]
 struct SendChatActionRequest<'a> {
     chat_id: i64,
@@ -125,7 +159,8 @@ impl TelegramChannel {
         &self,
         dispatcher: &mut dyn SessionDispatcher,
         inbound: &TelegramInboundMessage,
-    ) -> Result<String, String> {
+        voice: Option<&MediaAttachment>,
+    ) -> Result<DispatchReply, String> {
         if let Err(error) = self.client.send_typing_action(inbound.chat_id).await {
             eprintln!(
                 "warning: channel '{}' failed to send typing action for route '{}:{}': {error}",
@@ -133,7 +168,10 @@ impl TelegramChannel {
             );
         }
 
-        let dispatch = dispatcher.dispatch_text(&self.name, &inbound.user_id, &inbound.text);
+        let dispatch = match voice {
+            Some(audio) => dispatcher.dispatch_voice(&self.name, &inbound.user_id, audio),
+            None => dispatcher.dispatch_text(&self.name, &inbound.user_id, &inbound.text),
+        };
         tokio::pin!(dispatch);
 
         loop {
@@ -182,25 +220,54 @@ impl Channel for TelegramChannel {
                 .await?;
             for update in updates {
                 self.offset = Some(update.update_id + 1);
-                let Some(inbound) = extract_private_text_message(&update, &self.allowed_user_ids)
-                else {
+                let Some(inbound) = extract_private_message(&update, &self.allowed_user_ids) else {
                     continue;
                 };
+                let voice = match &inbound.voice {
+                    Some(voice) => match self.client.download_file(&voice.file_id).await {
+                        Ok(bytes) => Some(MediaAttachment {
+                            mime_type: voice
+                                .mime_type
+                                .clone()
+                                .unwrap_or_else(|| TELEGRAM_VOICE_MIME_TYPE.to_string()),
+                            bytes,
+                        }),
+                        Err(error) => {
+                            eprintln!(
+                                "warning: channel '{}' failed to download voice note for route '{}:{}': {error}",
+                                self.name, self.name, inbound.user_id
+                            );
+                            continue;
+                        }
+                    },
+                    None => None,
+                };
 
-                let reply = match self.dispatch_with_typing(dispatcher, &inbound).await {
-                    Ok(text) => text,
+                let reply = match self
+                    .dispatch_with_typing(dispatcher, &inbound, voice.as_ref())
+                    .await
+                {
+                    Ok(reply) => reply,
                     Err(error) => {
                         eprintln!(
                             "warning: route '{}:{}' failed: {error}",
                             self.name, inbound.user_id
                         );
-                        "Sorry, I hit an internal error while processing your message.".to_string()
+                        "Sorry, I hit an internal error while processing your message."
+                            .to_string()
+                            .into()
                     }
                 };
 
-                for chunk in split_telegram_message(&reply, TELEGRAM_MAX_TEXT_CHARS) {
+                for chunk in split_telegram_message(&reply.text, TELEGRAM_MAX_TEXT_CHARS) {
                     self.client.send_message(inbound.chat_id, &chunk).await?;
                 }
+                for image in &reply.images {
+                    self.client.send_photo(inbound.chat_id, image).await?;
+                }
+                for audio in &reply.audio {
+                    self.client.send_audio(inbound.chat_id, audio).await?;
+                }
             }
             Ok(())
         })
@@ -283,6 +350,95 @@ impl TelegramClient {
         }
     }
 
+    pub async fn send_photo(&self, chat_id: i64, image: &MediaAttachment) -> Result<(), String> {
+        self.send_file("sendPhoto", "photo", chat_id, image).await
+    }
+
+    /// Sends OGG clips as voice notes and any other audio as a document.
+    pub async fn send_audio(&self, chat_id: i64, audio: &MediaAttachment) -> Result<(), String> {
+        if audio.mime_type.starts_with(TELEGRAM_VOICE_MIME_TYPE) {
+            self.send_file("sendVoice", "voice", chat_id, audio).await
+        } else {
+            self.send_file("sendDocument", "document", chat_id, audio)
+                .await
+        }
+    }
+
+    async fn send_file(
+        &self,
+        method: &str,
+        field: &str,
+        chat_id: i64,
+        file: &MediaAttachment,
+    ) -> Result<(), String> {
+        let url = format!("{}/bot{}/{method}", self.api_base, self.bot_token);
+        let chat_id = chat_id.to_string();
+        let (content_type, body) = multipart_form(&[("chat_id", &chat_id)], field, file);
+        let response = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(body)
+            .send()
+            .await
+            .map_err(|error| format!("telegram {method} request failed: {error}"))?;
+        let parsed = response
+            .json::<TelegramApiStatusResponse>()
+            .await
+            .map_err(|error| format!("telegram {method} decode failed: {error}"))?;
+        if parsed.ok {
+            Ok(())
+        } else {
+            Err(parsed
+                .description
+                .unwrap_or_else(|| format!("telegram {method} returned ok=false")))
+        }
+    }
+
+    pub async fn download_file(&self, file_id: &str) -> Result<Vec<u8>, String> {
+        let url = format!("{}/bot{}/getFile", self.api_base, self.bot_token);
+        let response = self
+            .client
+            .post(url)
+            .json(&GetFileRequest { file_id })
+            .send()
+            .await
+            .map_err(|error| format!("telegram getFile request failed: {error}"))?;
+        let parsed = response
+            .json::<TelegramGetFileResponse>()
+            .await
+            .map_err(|error| format!("telegram getFile decode failed: {error}"))?;
+        if !parsed.ok {
+            return Err(parsed
+                .description
+                .unwrap_or_else(|| "telegram getFile returned ok=false".to_string()));
+        }
+        let file_path = parsed
+            .result
+            .and_then(|file| file.file_path)
+            .filter(|path| !path.is_empty())
+            .ok_or_else(|| "telegram getFile returned no file_path".to_string())?;
+
+        let url = format!("{}/file/bot{}/{file_path}", self.api_base, self.bot_token);
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(|error| format!("telegram file download failed: {error}"))?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "telegram file download failed with status {}",
+                response.status()
+            ));
+        }
+        response
+            .bytes()
+            .await
+            .map(|bytes| bytes.to_vec())
+            .map_err(|error| format!("telegram file download read failed: {error}"))
+    }
+
     pub async fn send_typing_action(&self, chat_id: i64) -> Result<(), String> {
         self.send_chat_action(chat_id, TELEGRAM_TYPING_ACTION).await
     }
@@ -323,7 +479,8 @@ impl TelegramClient {
     }
 }
 
-pub fn extract_private_text_message(
+/// Accepts text messages and voice notes from allowed users in private chats.
+pub fn extract_private_message(
     update: &TelegramUpdate,
     allowed_user_ids: &HashSet<String>,
 ) -> Option<TelegramInboundMessage> {
@@ -339,7 +496,11 @@ pub fn extract_private_text_message(
         .text
         .as_deref()
         .map(str::trim)
-        .filter(|value| !value.is_empty())?;
+        .filter(|value| !value.is_empty());
+    let voice = message.voice.clone();
+    if text.is_none() && voice.is_none() {
+        return None;
+    }
     let user_id = from.id.to_string();
     if !allowed_user_ids.contains(&user_id) {
         return None;
@@ -350,7 +511,8 @@ pub fn extract_private_text_message(
         message_id: message.message_id,
         chat_id: message.chat.id,
         user_id,
-        text: text.to_string(),
+        text: text.unwrap_or_default().to_string(),
+        voice,
     })
 }
 
@@ -384,7 +546,7 @@ mod tests {
     use super::*;
 
     #[test]
-    fn extract_private_text_message_accepts_allowed_private_chat() {
+    fn extract_private_message_accepts_allowed_private_chat() {
         let update = TelegramUpdate {
             update_id: 42,
             message: Some(TelegramMessage {
@@ -398,21 +560,49 @@ mod tests {
                     is_bot: Some(false),
                 }),
                 text: Some("hello pixy".to_string()),
+                voice: None,
             }),
         };
 
         let allowed = HashSet::from(["10001".to_string()]);
-        let inbound = extract_private_text_message(&update, &allowed)
+        let inbound = extract_private_message(&update, &allowed)
             .expect("allowed private text update should be accepted");
         assert_eq!(inbound.update_id, 42);
         assert_eq!(inbound.message_id, 7);
         assert_eq!(inbound.chat_id, 555);
         assert_eq!(inbound.user_id, "10001");
         assert_eq!(inbound.text, "hello pixy");
+        assert_eq!(inbound.voice, None);
+    }
+
+    #[test]
+    fn extract_private_message_accepts_voice_notes_without_text() {
+        let update: TelegramUpdate = serde_json::from_value(serde_json::json!({
+            "update_id": 43,
+            "message": {
+                "message_id": 8,
+                "chat": { "id": 555, "type": "private" },
+                "from": { "id": 10001, "is_bot": false },
+                "voice": { "file_id": "voice-1", "duration": 3, "mime_type": "audio/ogg" }
+            }
+        }))
+        .expect("voice update should deserialize");
+
+        let allowed = HashSet::from(["10001".to_string()]);
+        let inbound = extract_private_message(&update, &allowed)
+            .expect("allowed private voice update should be accepted");
+        assert_eq!(inbound.text, "");
+        assert_eq!(
+            inbound.voice,
+            Some(TelegramVoice {
+                file_id: "voice-1".to_string(),
+                mime_type: Some("audio/ogg".to_string()),
+            })
+        );
     }
 
     #[test]
-    fn extract_private_text_message_rejects_group_and_disallowed_users() {
+    fn extract_private_message_rejects_group_and_disallowed_users() {
         let group_update = TelegramUpdate {
             update_id: 1,
             message: Some(TelegramMessage {
@@ -426,6 +616,7 @@ mod tests {
                     is_bot: Some(false),
                 }),
                 text: Some("should ignore".to_string()),
+                voice: None,
             }),
         };
         let disallowed_update = TelegramUpdate {
@@ -441,16 +632,17 @@ mod tests {
                     is_bot: Some(false),
                 }),
                 text: Some("should ignore".to_string()),
+                voice: None,
             }),
         };
 
         let allowed = HashSet::from(["10001".to_string()]);
         assert!(
-            extract_private_text_message(&group_update, &allowed).is_none(),
+            extract_private_message(&group_update, &allowed).is_none(),
             "group chat update should be ignored"
         );
         assert!(
-            extract_private_text_message(&disallowed_update, &allowed).is_none(),
+            extract_private_message(&disallowed_update, &allowed).is_none(),
             "disallowed user should be ignored"
         );
     }
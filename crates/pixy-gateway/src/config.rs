@@ -3,8 +3,9 @@ use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 use std::time::Duration;
 
-use pixy_ai::Model;
-use pixy_coding_agent::{ResolvedRuntime, RuntimeLoadOptions};
+use pixy_agent_core::ToolPolicy;
+use pixy_ai::{Model, PiAiErrorCode, RateLimit, RetryPolicy};
+use pixy_coding_agent::{HttpTransportConfig, ResolvedRuntime, RuntimeLoadOptions};
 use serde::Deserialize;
 
 #[derive(Debug, Clone)]
@@ -13,8 +14,16 @@ pub struct GatewayConfig {
     pub bind_addr: String,
     pub request_timeout: Duration,
     pub transport_retry_count: Option<usize>,
+    /// Retry policies keyed by provider name, from `[gateway.retry.<provider>]`.
+    pub retry_policies: HashMap<String, RetryPolicy>,
+    /// Proxy and TLS settings from `[transport]`, applied before serving.
+    pub http_transport: HttpTransportConfig,
+    /// Client-side request limits from `[rate_limits]`, shared by every channel.
+    pub rate_limits: HashMap<String, RateLimit>,
     pub model: Model,
     pub api_key: Option<String>,
+    /// Tool restrictions from `[gateway.tool_policy]`, applied to every gateway session.
+    pub tool_policy: Option<ToolPolicy>,
     pub channels: Vec<GatewayChannelConfig>,
 }
 
@@ -68,9 +77,58 @@ struct PixyTomlGateway {
     #[serde(default)]
     request_timeout_ms: Option<u64>,
     #[serde(default)]
+    retry: HashMap<String, PixyTomlRetryPolicy>,
+    #[serde(default)]
+    tool_policy: Option<PixyTomlToolPolicy>,
+    #[serde(default)]
     channels: Vec<PixyTomlGatewayChannel>,
 }
 
+#[derive(Debug, Deserialize, Default)]
+struct PixyTomlToolPolicy {
+    #[serde(default, alias = "allowed_tools")]
+    allow_tools: Option<Vec<String>>,
+    #[serde(default, alias = "denied_tools")]
+    deny_tools: Vec<String>,
+    #[serde(default)]
+    allowed_paths: Vec<PathBuf>,
+    #[serde(default)]
+    max_output_bytes: Option<usize>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+impl PixyTomlToolPolicy {
+    fn resolve(&self) -> ToolPolicy {
+        ToolPolicy {
+            allow_tools: self.allow_tools.clone(),
+            deny_tools: self.deny_tools.clone(),
+            allowed_paths: self.allowed_paths.clone(),
+            cwd: None,
+            max_output_bytes: self.max_output_bytes,
+            dry_run: self.dry_run,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct PixyTomlRetryPolicy {
+    #[serde(default)]
+    max_retries: Option<usize>,
+    #[serde(default)]
+    base_backoff_ms: Option<u64>,
+    #[serde(default)]
+    max_backoff_ms: Option<u64>,
+    #[serde(default)]
+    jitter: Option<f64>,
+    #[serde(default)]
+    honor_retry_after: Option<bool>,
+    #[serde(default)]
+    retryable_codes: Option<Vec<PiAiErrorCode>>,
+    #[serde(default)]
+    retryable_http_statuses: Option<Vec<u16>>,
+}
+
 #[derive(Debug, Deserialize, Default)]
 struct PixyTomlGatewayChannel {
     name: String,
@@ -211,12 +269,68 @@ fn parse_gateway_config_with_seed_and_base_dir(
         bind_addr,
         request_timeout,
         transport_retry_count: parsed.transport_retry_count,
+        retry_policies: resolve_retry_policies(
+            &parsed.gateway.retry,
+            parsed.transport_retry_count,
+        )?,
+        http_transport: runtime.http_transport,
+        rate_limits: runtime.rate_limits,
         model: runtime.model,
         api_key: runtime.api_key,
+        tool_policy: parsed
+            .gateway
+            .tool_policy
+            .as_ref()
+            .map(PixyTomlToolPolicy::resolve),
         channels,
     })
 }
 
+fn resolve_retry_policies(
+    policies: &HashMap<String, PixyTomlRetryPolicy>,
+    transport_retry_count: Option<usize>,
+) -> Result<HashMap<String, RetryPolicy>, String> {
+    let mut resolved = HashMap::new();
+    for (provider, policy) in policies {
+        let provider = provider.trim();
+        if provider.is_empty() {
+            continue;
+        }
+        let mut retry_policy = RetryPolicy::default();
+        if let Some(retry_count) = transport_retry_count {
+            retry_policy.max_retries = retry_count;
+        }
+        if let Some(max_retries) = policy.max_retries {
+            retry_policy.max_retries = max_retries;
+        }
+        if let Some(base_backoff_ms) = policy.base_backoff_ms {
+            retry_policy.base_backoff_ms = base_backoff_ms;
+        }
+        if let Some(max_backoff_ms) = policy.max_backoff_ms {
+            retry_policy.max_backoff_ms = max_backoff_ms;
+        }
+        if let Some(jitter) = policy.jitter {
+            if !(0.0..=1.0).contains(&jitter) {
+                return Err(format!(
+                    "gateway retry policy for '{provider}' has jitter {jitter}; expected 0.0 to 1.0"
+                ));
+            }
+            retry_policy.jitter = jitter;
+        }
+        if let Some(honor_retry_after) = policy.honor_retry_after {
+            retry_policy.honor_retry_after = honor_retry_after;
+        }
+        if let Some(retryable_codes) = &policy.retryable_codes {
+            retry_policy.retryable_codes = retryable_codes.clone();
+        }
+        if let Some(retryable_http_statuses) = &policy.retryable_http_statuses {
+            retry_policy.retryable_http_statuses = retryable_http_statuses.clone();
+        }
+        resolved.insert(provider.to_string(), retry_policy);
+    }
+    Ok(resolved)
+}
+
 fn resolve_gateway_runtime_with_seed(
     content: &str,
     router_seed: u64,
@@ -523,6 +637,147 @@ allowed_user_ids = ["10001"]
         );
     }
 
+    #[test]
+    fn parse_gateway_config_resolves_proxy_and_tls_transport() {
+        let content = r#"
+[env]
+CORP_PROXY = "http://proxy.corp:3128"
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "literal"
+model = "gpt-5.3-codex"
+weight = 1
+
+[transport]
+proxy = "$CORP_PROXY"
+no_proxy = [".corp", "10.0.0.0/8"]
+ca_bundle = "certs/corp-ca.pem"
+
+[transport.providers.openai]
+client_cert = "/etc/pixy/openai-client.pem"
+"#;
+
+        let dir = tempdir().expect("tempdir");
+        let config = parse_gateway_config_with_seed_and_base_dir(content, 0, dir.path())
+            .expect("config should parse successfully");
+        let transport = &config.http_transport;
+        assert_eq!(
+            transport.default.proxy.as_deref(),
+            Some("http://proxy.corp:3128")
+        );
+        assert_eq!(transport.default.no_proxy, vec![".corp", "10.0.0.0/8"]);
+        assert_eq!(
+            transport.default.ca_bundle,
+            Some(dir.path().join("certs/corp-ca.pem"))
+        );
+        assert_eq!(
+            transport.providers["openai"].client_cert,
+            Some(PathBuf::from("/etc/pixy/openai-client.pem"))
+        );
+        assert_eq!(transport.providers["openai"].proxy, None);
+    }
+
+    #[test]
+    fn parse_gateway_config_resolves_per_provider_retry_policies() {
+        let content = r#"
+transport_retry_count = 2
+
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "literal"
+model = "gpt-5.3-codex"
+weight = 1
+
+[gateway]
+enabled = true
+
+[gateway.retry.openai]
+base_backoff_ms = 250
+jitter = 0.0
+retryable_codes = ["provider_transport", "provider_http"]
+retryable_http_statuses = [429]
+
+[gateway.retry.anthropic]
+max_retries = 6
+honor_retry_after = false
+"#;
+
+        let config =
+            parse_gateway_config_with_seed(content, 0).expect("config should parse successfully");
+        let openai = &config.retry_policies["openai"];
+        assert_eq!(openai.max_retries, 2);
+        assert_eq!(openai.base_backoff_ms, 250);
+        assert_eq!(openai.jitter, 0.0);
+        assert_eq!(openai.retryable_http_statuses, vec![429]);
+        let anthropic = &config.retry_policies["anthropic"];
+        assert_eq!(anthropic.max_retries, 6);
+        assert!(!anthropic.honor_retry_after);
+        assert_eq!(
+            anthropic.retryable_codes,
+            RetryPolicy::default().retryable_codes
+        );
+
+        let invalid = content.replace("jitter = 0.0", "jitter = 1.5");
+        let error = parse_gateway_config_with_seed(&invalid, 0)
+            .expect_err("out-of-range jitter should be rejected");
+        assert!(error.contains("jitter"));
+    }
+
+    #[test]
+    fn parse_gateway_config_reads_tool_policy() {
+        let content = r#"
+[llm]
+default_provider = "openai"
+
+[[llm.providers]]
+name = "openai"
+kind = "chat"
+provider = "openai"
+api = "openai-responses"
+base_url = "https://api.openai.com/v1"
+api_key = "literal"
+model = "gpt-5.3-codex"
+weight = 1
+
+[gateway]
+enabled = true
+
+[gateway.tool_policy]
+deny_tools = ["bash"]
+allowed_paths = ["workspace"]
+max_output_bytes = 4096
+"#;
+
+        let config =
+            parse_gateway_config_with_seed(content, 0).expect("config should parse successfully");
+        let policy = config.tool_policy.expect("tool policy");
+        assert_eq!(policy.allow_tools, None);
+        assert_eq!(policy.deny_tools, vec!["bash".to_string()]);
+        assert_eq!(policy.allowed_paths, vec![PathBuf::from("workspace")]);
+        assert_eq!(policy.max_output_bytes, Some(4096));
+        assert!(!policy.dry_run);
+
+        let without = content.replace("[gateway.tool_policy]", "[unused]");
+        let config =
+            parse_gateway_config_with_seed(&without, 0).expect("config should parse successfully");
+        assert_eq!(config.tool_policy, None);
+    }
+
     #[test]
     fn parse_gateway_config_rejects_empty_allowed_user_ids_for_telegram() {
         let content = r#"
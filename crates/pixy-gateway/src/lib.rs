@@ -313,7 +313,17 @@ pub async fn run_gateway_serve() -> Result<(), String> {
     let config_path = config::default_pixy_config_path();
     let config = config::load_gateway_config(&config_path)?;
     if let Some(retry_count) = config.transport_retry_count {
-        pixy_ai::set_transport_retry_count(retry_count);
+        pixy_ai::set_default_retry_policy(pixy_ai::RetryPolicy {
+            max_retries: retry_count,
+            ..pixy_ai::RetryPolicy::default()
+        });
+    }
+    for (provider, policy) in &config.retry_policies {
+        pixy_ai::set_provider_retry_policy(provider.clone(), policy.clone());
+    }
+    config.http_transport.apply()?;
+    for (key, limit) in &config.rate_limits {
+        pixy_ai::set_rate_limit(key.clone(), limit.clone());
     }
     runtime::serve_gateway(config).await
 }
@@ -499,6 +509,7 @@ async fn wait_for_shutdown_signal() -> Result<(), String> {
 }
 
 #[cfg(test)]
+#[allow(clippy::await_holding_lock)]
 mod tests {
     use std::collections::HashMap;
     use std::path::{Path, PathBuf};
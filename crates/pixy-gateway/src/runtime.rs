@@ -1,24 +1,43 @@
 use std::collections::HashMap;
 use std::fs;
+use std::future::Future;
 use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use base64::prelude::{Engine as _, BASE64_STANDARD};
 use chrono::{Datelike, Local};
-use pixy_ai::{AssistantContentBlock, Message, Model, StopReason};
+use pixy_agent_core::{AgentRunMetrics, ApprovalDecision, ToolApprovalRequest, ToolPolicy};
+use pixy_ai::{
+    error_remediation, AssistantContentBlock, Message, Model, StopReason, ToolResultContentBlock,
+    UserContentBlock,
+};
 use pixy_coding_agent::{
     create_session, AgentSession, RuntimeLoadOptions, RuntimeOverrides, SessionCreateOptions,
     SessionManager,
 };
+use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 use tokio::time::Instant;
 
 use crate::channels::feishu::{build_feishu_webhook_router, FeishuChannel, FeishuWebhookBinding};
 use crate::channels::telegram::TelegramChannel;
-use crate::channels::{Channel, DispatchFuture, SessionDispatcher};
+use crate::channels::{Channel, DispatchFuture, DispatchReply, MediaAttachment, SessionDispatcher};
 use crate::config::{GatewayChannelConfig, GatewayConfig};
 use crate::DEFAULT_PROMPT_INTRO;
 
 const NEW_SESSION_COMMAND_REPLY: &str = "Started a new session. Send your next message.";
+const NO_STATS_REPLY: &str = "No runs have finished in this session yet.";
+
+/// A prompt run that owns its session until it finishes.
+type PromptRun = Pin<Box<dyn Future<Output = (AgentSession, Result<Vec<Message>, String>)>>>;
+
+/// A run paused on a tool call until the user replies `/approve` or `/deny`.
+struct PendingApproval {
+    run: PromptRun,
+    approvals: mpsc::UnboundedReceiver<ToolApprovalRequest>,
+    request: ToolApprovalRequest,
+}
 
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 struct ChannelPromptConfig {
@@ -32,7 +51,9 @@ pub struct SessionRouter {
     model: Model,
     api_key: Option<String>,
     channel_prompts: HashMap<String, ChannelPromptConfig>,
+    tool_policy: Option<ToolPolicy>,
     sessions: HashMap<String, AgentSession>,
+    pending_approvals: HashMap<String, PendingApproval>,
 }
 
 impl SessionRouter {
@@ -42,6 +63,7 @@ impl SessionRouter {
         model: Model,
         api_key: Option<String>,
         channel_prompts: HashMap<String, ChannelPromptConfig>,
+        tool_policy: Option<ToolPolicy>,
     ) -> Self {
         Self {
             cwd,
@@ -49,7 +71,9 @@ impl SessionRouter {
             model,
             api_key,
             channel_prompts,
+            tool_policy,
             sessions: HashMap::new(),
+            pending_approvals: HashMap::new(),
         }
     }
 
@@ -58,11 +82,11 @@ impl SessionRouter {
         channel_name: &str,
         user_id: &str,
         text: &str,
-    ) -> Result<String, String> {
+    ) -> Result<DispatchReply, String> {
         let key = session_key(channel_name, user_id);
         let channel_prompt = self.channel_prompts.get(channel_name);
         if is_new_session_command(text) {
-            let session = create_gateway_session(
+            let mut session = create_gateway_session(
                 &self.cwd,
                 &self.session_root,
                 channel_name,
@@ -72,30 +96,169 @@ impl SessionRouter {
                 self.api_key.clone(),
                 false,
             )?;
+            session.set_tool_policy(self.tool_policy.clone());
+            self.pending_approvals.remove(&key);
             self.sessions.insert(key, session);
-            return Ok(NEW_SESSION_COMMAND_REPLY.to_string());
+            return Ok(NEW_SESSION_COMMAND_REPLY.to_string().into());
+        }
+        if is_stats_command(text) {
+            // A session paused on an approval is owned by its run, so it reports nothing yet.
+            let reply = self
+                .sessions
+                .get(&key)
+                .map(AgentSession::metrics_snapshot)
+                .filter(|metrics| metrics.assistant_request_count > 0)
+                .map_or_else(
+                    || NO_STATS_REPLY.to_string(),
+                    |metrics| stats_text(&metrics),
+                );
+            return Ok(reply.into());
+        }
+
+        if let Some(pending) = self.pending_approvals.remove(&key) {
+            let Some(decision) = parse_approval_reply(text) else {
+                let reply = approval_reminder_text(&pending.request);
+                self.pending_approvals.insert(key, pending);
+                return Ok(reply.into());
+            };
+            let PendingApproval {
+                run,
+                approvals,
+                request,
+            } = pending;
+            request.respond(decision);
+            return self
+                .drive_run(key, channel_name, user_id, run, approvals)
+                .await;
+        }
+
+        self.start_run(channel_name, user_id, text.to_string(), None)
+            .await
+    }
+
+    pub async fn process_voice_message(
+        &mut self,
+        channel_name: &str,
+        user_id: &str,
+        audio: &MediaAttachment,
+    ) -> Result<DispatchReply, String> {
+        if let Some(pending) = self
+            .pending_approvals
+            .get(&session_key(channel_name, user_id))
+        {
+            return Ok(approval_reminder_text(&pending.request).into());
         }
+        let block = UserContentBlock::Audio {
+            data: BASE64_STANDARD.encode(&audio.bytes),
+            mime_type: audio.mime_type.clone(),
+        };
+        self.start_run(channel_name, user_id, String::new(), Some(vec![block]))
+            .await
+    }
 
+    /// Prompts the routed session. Sessions with tools flagged in `[approval]` run with an
+    /// approver that asks over the channel, so the run can pause between messages.
+    async fn start_run(
+        &mut self,
+        channel_name: &str,
+        user_id: &str,
+        text: String,
+        blocks: Option<Vec<UserContentBlock>>,
+    ) -> Result<DispatchReply, String> {
+        let key = session_key(channel_name, user_id);
+        let session = self.route_session(channel_name, user_id)?;
+        if session.approval_tools().is_empty() {
+            let produced = match blocks {
+                Some(blocks) => {
+                    session
+                        .prompt_streaming_blocks_with_abort(&text, Some(blocks), None, |_| {})
+                        .await?
+                }
+                None => session.prompt(&text).await?,
+            };
+            log_turn_stats(channel_name, user_id, &produced);
+            return Ok(build_dispatch_reply(&produced));
+        }
+
+        let mut session = self
+            .sessions
+            .remove(&key)
+            .ok_or_else(|| format!("gateway route session '{key}' was not initialized"))?;
+        let (approval_tx, approvals) = mpsc::unbounded_channel();
+        session.attach_tool_approver(move |request| {
+            let _ = approval_tx.send(request);
+        });
+        let run: PromptRun = Box::pin(async move {
+            let result = match blocks {
+                Some(blocks) => {
+                    session
+                        .prompt_streaming_blocks_with_abort(&text, Some(blocks), None, |_| {})
+                        .await
+                }
+                None => session.prompt(&text).await,
+            };
+            (session, result)
+        });
+        self.drive_run(key, channel_name, user_id, run, approvals)
+            .await
+    }
+
+    /// Runs until the prompt finishes or the next tool call needs approval.
+    async fn drive_run(
+        &mut self,
+        key: String,
+        channel_name: &str,
+        user_id: &str,
+        mut run: PromptRun,
+        mut approvals: mpsc::UnboundedReceiver<ToolApprovalRequest>,
+    ) -> Result<DispatchReply, String> {
+        tokio::select! {
+            (session, result) = &mut run => {
+                self.sessions.insert(key, session);
+                let produced = result?;
+                log_turn_stats(channel_name, user_id, &produced);
+                Ok(build_dispatch_reply(&produced))
+            }
+            Some(request) = approvals.recv() => {
+                let reply = approval_prompt_text(&request);
+                self.pending_approvals.insert(
+                    key,
+                    PendingApproval {
+                        run,
+                        approvals,
+                        request,
+                    },
+                );
+                Ok(reply.into())
+            }
+        }
+    }
+
+    /// Returns the session for this route, resuming or creating one on first use.
+    fn route_session(
+        &mut self,
+        channel_name: &str,
+        user_id: &str,
+    ) -> Result<&mut AgentSession, String> {
+        let key = session_key(channel_name, user_id);
         if !self.sessions.contains_key(&key) {
-            let session = create_gateway_session(
+            let mut session = create_gateway_session(
                 &self.cwd,
                 &self.session_root,
                 channel_name,
-                channel_prompt,
+                self.channel_prompts.get(channel_name),
                 user_id,
                 &self.model,
                 self.api_key.clone(),
                 true,
             )?;
+            session.set_tool_policy(self.tool_policy.clone());
             self.sessions.insert(key.clone(), session);
         }
 
-        let session = self
-            .sessions
+        self.sessions
             .get_mut(&key)
-            .ok_or_else(|| format!("gateway route session '{key}' was not initialized"))?;
-        let produced = session.prompt(text).await?;
-        Ok(extract_assistant_reply(&produced))
+            .ok_or_else(|| format!("gateway route session '{key}' was not initialized"))
     }
 }
 
@@ -108,6 +271,18 @@ impl SessionDispatcher for SessionRouter {
     ) -> DispatchFuture<'a> {
         Box::pin(async move { self.process_text_message(channel_name, user_id, text).await })
     }
+
+    fn dispatch_voice<'a>(
+        &'a mut self,
+        channel_name: &'a str,
+        user_id: &'a str,
+        audio: &'a MediaAttachment,
+    ) -> DispatchFuture<'a> {
+        Box::pin(async move {
+            self.process_voice_message(channel_name, user_id, audio)
+                .await
+        })
+    }
 }
 
 pub async fn serve_gateway(config: GatewayConfig) -> Result<(), String> {
@@ -116,8 +291,12 @@ pub async fn serve_gateway(config: GatewayConfig) -> Result<(), String> {
         bind_addr,
         request_timeout,
         transport_retry_count: _,
+        retry_policies: _,
+        http_transport: _,
+        rate_limits: _,
         model,
         api_key,
+        tool_policy,
         channels,
     } = config;
 
@@ -138,7 +317,23 @@ pub async fn serve_gateway(config: GatewayConfig) -> Result<(), String> {
         println!("{line}");
     }
     let channel_prompts = collect_channel_prompt_configs(&channels);
-    let mut router = SessionRouter::new(cwd, session_root, model, api_key, channel_prompts);
+    // Relative allowed paths are relative to the directory the gateway serves from.
+    let tool_policy = tool_policy.map(|mut policy| {
+        policy.allowed_paths = policy
+            .allowed_paths
+            .iter()
+            .map(|path| cwd.join(path))
+            .collect();
+        policy
+    });
+    let mut router = SessionRouter::new(
+        cwd,
+        session_root,
+        model,
+        api_key,
+        channel_prompts,
+        tool_policy,
+    );
     let BuiltChannels {
         mut channels,
         feishu_webhook_bindings,
@@ -349,14 +544,21 @@ pub fn extract_assistant_reply(messages: &[Message]) -> String {
                 .filter_map(|block| match block {
                     AssistantContentBlock::Text { text, .. } => Some(text.as_str()),
                     AssistantContentBlock::Thinking { .. }
-                    | AssistantContentBlock::ToolCall { .. } => None,
+                    | AssistantContentBlock::ToolCall { .. }
+                    | AssistantContentBlock::Image { .. }
+                    | AssistantContentBlock::Audio { .. } => None,
                 })
                 .collect::<String>();
             if !text.trim().is_empty() {
                 last_text = Some(text);
             }
             if *stop_reason == StopReason::Error {
-                last_error = error_message.clone();
+                last_error = error_message
+                    .as_deref()
+                    .map(|error| match error_remediation(error) {
+                        Some(hint) => format!("{error}\n\n{hint}"),
+                        None => error.to_string(),
+                    });
             }
         }
     }
@@ -366,6 +568,97 @@ pub fn extract_assistant_reply(messages: &[Message]) -> String {
         .unwrap_or_else(|| "Done.".to_string())
 }
 
+/// Logs the latency and throughput of every model call a turn made.
+fn log_turn_stats(channel_name: &str, user_id: &str, messages: &[Message]) {
+    for message in messages {
+        let Message::Assistant {
+            model,
+            stats: Some(stats),
+            ..
+        } = message
+        else {
+            continue;
+        };
+        tracing::info!(
+            channel = channel_name,
+            user = user_id,
+            provider = %stats.provider,
+            model = model.as_str(),
+            ttft_ms = stats.time_to_first_token_ms,
+            duration_ms = stats.duration_ms,
+            tokens_per_sec = stats.tokens_per_sec,
+            retries = stats.retries,
+            "model call finished"
+        );
+    }
+}
+
+fn build_dispatch_reply(messages: &[Message]) -> DispatchReply {
+    DispatchReply {
+        text: extract_assistant_reply(messages),
+        images: extract_reply_images(messages),
+        audio: extract_reply_audio(messages),
+    }
+}
+
+/// Collects images from assistant output and tool results, in order. Images whose payload
+/// is not valid base64 are skipped.
+pub fn extract_reply_images(messages: &[Message]) -> Vec<MediaAttachment> {
+    let mut images = Vec::new();
+    for message in messages {
+        match message {
+            Message::Assistant { content, .. } => {
+                images.extend(content.iter().filter_map(|block| match block {
+                    AssistantContentBlock::Image { data, mime_type } => {
+                        decode_media(data, mime_type)
+                    }
+                    _ => None,
+                }))
+            }
+            Message::ToolResult { content, .. } => {
+                images.extend(content.iter().filter_map(|block| match block {
+                    ToolResultContentBlock::Image { data, mime_type } => {
+                        decode_media(data, mime_type)
+                    }
+                    ToolResultContentBlock::Text { .. } => None,
+                }))
+            }
+            Message::User { .. } => {}
+        }
+    }
+    images
+}
+
+/// Collects speech produced by the model, in order.
+pub fn extract_reply_audio(messages: &[Message]) -> Vec<MediaAttachment> {
+    messages
+        .iter()
+        .filter_map(|message| match message {
+            Message::Assistant { content, .. } => Some(content),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|block| match block {
+            AssistantContentBlock::Audio { data, mime_type } => decode_media(data, mime_type),
+            _ => None,
+        })
+        .collect()
+}
+
+fn decode_media(data: &str, mime_type: &str) -> Option<MediaAttachment> {
+    match BASE64_STANDARD.decode(data) {
+        Ok(bytes) => Some(MediaAttachment {
+            mime_type: mime_type.to_string(),
+            bytes,
+        }),
+        Err(error) => {
+            eprintln!("warning: skipping undecodable {mime_type} attachment: {error}");
+            None
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn create_gateway_session(
     cwd: &Path,
     session_root: &Path,
@@ -452,16 +745,96 @@ fn create_session_manager(
 }
 
 fn is_new_session_command(input: &str) -> bool {
+    is_bare_command(input, "/new")
+}
+
+fn is_stats_command(input: &str) -> bool {
+    is_bare_command(input, "/stats")
+}
+
+/// `command` alone, optionally with an `@bot` mention.
+fn is_bare_command(input: &str, command: &str) -> bool {
     let trimmed = input.trim();
-    if trimmed.eq_ignore_ascii_case("/new") {
+    if trimmed.eq_ignore_ascii_case(command) {
         return true;
     }
-    if let Some(mention) = trimmed.strip_prefix("/new@") {
+    if let Some(mention) = trimmed
+        .get(..command.len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case(command))
+        .and_then(|_| trimmed[command.len()..].strip_prefix('@'))
+    {
         return !mention.trim().is_empty() && !mention.chars().any(char::is_whitespace);
     }
     false
 }
 
+fn stats_text(metrics: &AgentRunMetrics) -> String {
+    let mut lines = vec![format!(
+        "Requests: {} in {:.1}s, retries: {}",
+        metrics.assistant_request_count,
+        metrics.assistant_request_total_ms as f64 / 1_000.0,
+        metrics.retry_count
+    )];
+    if let Some(last) = metrics.turn_tokens.last() {
+        let total = metrics.turn_tokens.iter().sum::<u64>();
+        lines.push(format!(
+            "Tokens: {total} over {} responses, {last} in the last",
+            metrics.turn_tokens.len()
+        ));
+    }
+    lines.push(format!(
+        "Tools: {} calls, {} failed, {:.1}s",
+        metrics.tool_execution_count,
+        metrics.tool_failure_count(),
+        metrics.tool_execution_total_ms as f64 / 1_000.0
+    ));
+    for (name, tool) in &metrics.tools {
+        lines.push(format!(
+            "- {name}: {} calls, {:.0}% failed, {}ms",
+            tool.invocation_count,
+            tool.failure_rate() * 100.0,
+            tool.total_ms
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Reads `/approve` or `/deny [reason]`, with an optional `@bot` mention on the command.
+fn parse_approval_reply(input: &str) -> Option<ApprovalDecision> {
+    let trimmed = input.trim();
+    let (command, rest) = trimmed
+        .split_once(char::is_whitespace)
+        .unwrap_or((trimmed, ""));
+    let command = command.split_once('@').map_or(command, |(name, _)| name);
+    if command.eq_ignore_ascii_case("/approve") && rest.trim().is_empty() {
+        return Some(ApprovalDecision::Approve);
+    }
+    if command.eq_ignore_ascii_case("/deny") {
+        let reason = rest.trim();
+        return Some(ApprovalDecision::Deny {
+            reason: (!reason.is_empty()).then(|| reason.to_string()),
+        });
+    }
+    None
+}
+
+fn approval_prompt_text(request: &ToolApprovalRequest) -> String {
+    let arguments = serde_json::to_string_pretty(&request.call.arguments)
+        .unwrap_or_else(|_| request.call.arguments.to_string());
+    format!(
+        "Approval needed: {} ({} risk)\n{arguments}\n\nReply /approve to run it or /deny [reason] to refuse.",
+        request.call.name,
+        request.risk.as_str()
+    )
+}
+
+fn approval_reminder_text(request: &ToolApprovalRequest) -> String {
+    format!(
+        "Still waiting for approval of {}. Reply /approve or /deny [reason], or /new to start over.",
+        request.call.name
+    )
+}
+
 fn sanitize_session_segment(segment: &str) -> String {
     let cleaned = segment
         .chars()
@@ -545,6 +918,7 @@ fn gateway_session_runtime_options(model: &Model, api_key: Option<String>) -> Ru
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pixy_ai::{PiAiError, PiAiErrorCode};
     use tempfile::tempdir;
 
     fn usage_stub() -> pixy_ai::Usage {
@@ -553,6 +927,7 @@ mod tests {
             output: 1,
             cache_read: 0,
             cache_write: 0,
+            reasoning: 0,
             total_tokens: 2,
             cost: pixy_ai::Cost {
                 input: 0.0,
@@ -583,6 +958,9 @@ mod tests {
             },
             context_window: 200_000,
             max_tokens: 8_192,
+            deployment: None,
+            api_version: None,
+            compat: None,
         }
     }
 
@@ -650,6 +1028,67 @@ mod tests {
         assert!(is_new_session_command("/new@pixy_bot"));
         assert!(!is_new_session_command("/new please"));
         assert!(!is_new_session_command("hello /new"));
+        assert!(is_stats_command("/STATS@pixy_bot"));
+        assert!(!is_stats_command("/stats now"));
+        assert!(!is_stats_command("/new"));
+    }
+
+    #[test]
+    fn stats_text_lists_totals_and_per_tool_metrics() {
+        let mut metrics = AgentRunMetrics {
+            assistant_request_count: 3,
+            assistant_request_total_ms: 4_500,
+            tool_execution_count: 4,
+            tool_execution_total_ms: 1_200,
+            retry_count: 1,
+            turn_tokens: vec![1_000, 1_500, 2_000],
+            ..AgentRunMetrics::default()
+        };
+        for (name, duration_ms, is_error) in [
+            ("bash", 900, true),
+            ("bash", 100, false),
+            ("read", 100, false),
+            ("read", 100, false),
+        ] {
+            metrics
+                .tools
+                .entry(name.to_string())
+                .or_default()
+                .record(duration_ms, is_error);
+        }
+
+        assert_eq!(
+            stats_text(&metrics),
+            "Requests: 3 in 4.5s, retries: 1\n\
+             Tokens: 4500 over 3 responses, 2000 in the last\n\
+             Tools: 4 calls, 1 failed, 1.2s\n\
+             - bash: 2 calls, 50% failed, 1000ms\n\
+             - read: 2 calls, 0% failed, 200ms"
+        );
+    }
+
+    #[test]
+    fn parse_approval_reply_reads_approve_and_deny_with_reason() {
+        assert_eq!(
+            parse_approval_reply(" /approve "),
+            Some(ApprovalDecision::Approve)
+        );
+        assert_eq!(
+            parse_approval_reply("/approve@pixy_bot"),
+            Some(ApprovalDecision::Approve)
+        );
+        assert_eq!(
+            parse_approval_reply("/deny"),
+            Some(ApprovalDecision::Deny { reason: None })
+        );
+        assert_eq!(
+            parse_approval_reply("/DENY use git stash instead"),
+            Some(ApprovalDecision::Deny {
+                reason: Some("use git stash instead".to_string())
+            })
+        );
+        assert_eq!(parse_approval_reply("/approve everything"), None);
+        assert_eq!(parse_approval_reply("yes please"), None);
     }
 
     #[test]
@@ -690,6 +1129,7 @@ mod tests {
                 stop_reason: StopReason::Stop,
                 error_message: None,
                 timestamp: 0,
+                stats: None,
             },
             Message::Assistant {
                 content: vec![AssistantContentBlock::Text {
@@ -703,12 +1143,100 @@ mod tests {
                 stop_reason: StopReason::Stop,
                 error_message: None,
                 timestamp: 0,
+                stats: None,
             },
         ];
 
         assert_eq!(extract_assistant_reply(&messages), "second");
     }
 
+    #[test]
+    fn extract_assistant_reply_appends_remediation_to_errors() {
+        let error = PiAiError::new(PiAiErrorCode::AuthInvalid, "OpenAI HTTP 401: bad key");
+        let messages = vec![Message::Assistant {
+            content: vec![],
+            api: "openai-responses".to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-5.3-codex".to_string(),
+            usage: usage_stub(),
+            stop_reason: StopReason::Error,
+            error_message: Some(error.as_compact_json()),
+            timestamp: 0,
+            stats: None,
+        }];
+
+        let reply = extract_assistant_reply(&messages);
+        assert!(reply.starts_with(&error.as_compact_json()));
+        assert!(reply.ends_with(error.remediation().expect("auth hint")));
+    }
+
+    #[test]
+    fn extract_reply_media_collects_images_and_audio() {
+        let messages = vec![
+            Message::ToolResult {
+                tool_call_id: "call_1".to_string(),
+                tool_name: "read".to_string(),
+                content: vec![ToolResultContentBlock::Image {
+                    data: BASE64_STANDARD.encode([1u8, 2, 3]),
+                    mime_type: "image/png".to_string(),
+                }],
+                details: None,
+                is_error: false,
+                timestamp: 0,
+            },
+            Message::Assistant {
+                content: vec![
+                    AssistantContentBlock::Text {
+                        text: "here you go".to_string(),
+                        text_signature: None,
+                    },
+                    AssistantContentBlock::Image {
+                        data: BASE64_STANDARD.encode([4u8, 5]),
+                        mime_type: "image/jpeg".to_string(),
+                    },
+                    AssistantContentBlock::Image {
+                        data: "not base64!".to_string(),
+                        mime_type: "image/png".to_string(),
+                    },
+                    AssistantContentBlock::Audio {
+                        data: BASE64_STANDARD.encode([6u8]),
+                        mime_type: "audio/wav".to_string(),
+                    },
+                ],
+                api: "openai-responses".to_string(),
+                provider: "openai".to_string(),
+                model: "gpt-5".to_string(),
+                usage: usage_stub(),
+                stop_reason: StopReason::Stop,
+                error_message: None,
+                timestamp: 0,
+                stats: None,
+            },
+        ];
+
+        assert_eq!(extract_assistant_reply(&messages), "here you go");
+        assert_eq!(
+            extract_reply_images(&messages),
+            vec![
+                MediaAttachment {
+                    mime_type: "image/png".to_string(),
+                    bytes: vec![1, 2, 3],
+                },
+                MediaAttachment {
+                    mime_type: "image/jpeg".to_string(),
+                    bytes: vec![4, 5],
+                },
+            ]
+        );
+        assert_eq!(
+            extract_reply_audio(&messages),
+            vec![MediaAttachment {
+                mime_type: "audio/wav".to_string(),
+                bytes: vec![6],
+            }]
+        );
+    }
+
     #[test]
     fn startup_log_lines_include_runtime_overview_and_channels() {
         let model = sample_model();
@@ -1,6 +1,8 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use pixy_coding_agent::LayeredConfig;
+
 use crate::pixy_home::resolve_pixy_home_dir;
 
 const PIXY_TOML_SAMPLE: &str = include_str!("../../../pixy.toml.sample");
@@ -26,6 +28,39 @@ pub fn run_config_init(conf_dir: Option<PathBuf>) -> Result<(), String> {
     Ok(())
 }
 
+pub fn run_config_show(
+    conf_dir: Option<PathBuf>,
+    cwd: Option<PathBuf>,
+    with_origin: bool,
+) -> Result<(), String> {
+    let pixy_home_dir = resolve_pixy_home_dir(conf_dir.as_deref());
+    let cwd = match cwd {
+        Some(cwd) => cwd,
+        None => std::env::current_dir()
+            .map_err(|error| format!("resolve current directory failed: {error}"))?,
+    };
+    let config = LayeredConfig::load(&pixy_home_dir, &cwd)?;
+
+    if config.layers.is_empty() {
+        println!("no config files found");
+        return Ok(());
+    }
+    println!("# layers (lowest precedence first):");
+    for layer in &config.layers {
+        println!("#   {}", layer.display());
+    }
+    for (key, path) in &config.ignored {
+        println!(
+            "# ignored {key} from {} (repository config)",
+            path.display()
+        );
+    }
+    for line in config.render(with_origin) {
+        println!("{line}");
+    }
+    Ok(())
+}
+
 fn init_directories(pixy_home_dir: &Path) -> Vec<PathBuf> {
     vec![
         pixy_home_dir.to_path_buf(),
@@ -7,6 +7,7 @@ use pixy_gateway::{run_gateway_command, GatewayCommand, GatewayStartOptions};
 mod config_cmd;
 mod doctor;
 mod pixy_home;
+mod session_cmd;
 mod update_cmd;
 
 #[derive(Parser, Debug)]
@@ -21,12 +22,17 @@ struct Cli {
 }
 
 #[derive(Subcommand, Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 enum RootCommand {
     Cli(ChatArgs),
     Gateway(GatewayArgs),
     Config(ConfigArgs),
+    #[command(visible_alias = "sessions")]
+    Session(SessionArgs),
     Doctor,
     Update(UpdateArgs),
+    /// Serve the coding tools and a session as an MCP server on stdin and stdout.
+    McpServe(ChatArgs),
 }
 
 #[derive(Args, Debug, Clone)]
@@ -59,6 +65,85 @@ struct ConfigArgs {
 #[derive(Subcommand, Debug, Clone)]
 enum ConfigSubcommand {
     Init,
+    /// Print the effective config merged from every layer that applies to the working directory.
+    Show(ConfigShowArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+struct ConfigShowArgs {
+    /// Annotate each value with the file it came from.
+    #[arg(long, default_value_t = false)]
+    origin: bool,
+    #[arg(long)]
+    cwd: Option<PathBuf>,
+}
+
+#[derive(Args, Debug, Clone)]
+struct SessionArgs {
+    #[command(subcommand)]
+    command: SessionSubcommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum SessionSubcommand {
+    /// Bundle a session with its memory and file snapshots into a `.tar.zst` archive.
+    #[cfg(feature = "session-archive")]
+    Export(SessionExportArgs),
+    /// Restore a session archive into the local session directory.
+    #[cfg(feature = "session-archive")]
+    Import(SessionImportArgs),
+    /// List stored sessions, newest first, with their titles and turn counts.
+    List(SessionListArgs),
+    /// Search session titles, working directories and conversation text.
+    Find(SessionFindArgs),
+    /// Validate session files, migrating old formats and quarantining corrupt records.
+    Fsck(SessionFsckArgs),
+}
+
+#[cfg(feature = "session-archive")]
+#[derive(Args, Debug, Clone)]
+struct SessionExportArgs {
+    file: PathBuf,
+    /// Session file, id or name fragment; defaults to the latest session.
+    #[arg(long)]
+    session: Option<String>,
+    #[arg(long)]
+    cwd: Option<PathBuf>,
+}
+
+#[cfg(feature = "session-archive")]
+#[derive(Args, Debug, Clone)]
+struct SessionImportArgs {
+    file: PathBuf,
+    /// Workspace to attach the imported session to; defaults to the current directory.
+    #[arg(long)]
+    cwd: Option<PathBuf>,
+    /// Replace an existing session with the same id.
+    #[arg(long, default_value_t = false)]
+    force: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+struct SessionListArgs {
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+}
+
+#[derive(Args, Debug, Clone)]
+struct SessionFindArgs {
+    #[arg(required = true, num_args = 1..)]
+    query: Vec<String>,
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+}
+
+#[derive(Args, Debug, Clone)]
+struct SessionFsckArgs {
+    /// Session file, id or name fragment; defaults to every stored session.
+    session: Option<String>,
+    /// Report problems without rewriting any file.
+    #[arg(long, default_value_t = false)]
+    check: bool,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -79,8 +164,12 @@ async fn main() {
         }
         Some(RootCommand::Gateway(args)) => run_gateway(args.command, conf_dir).await,
         Some(RootCommand::Config(args)) => run_config(args.command, conf_dir),
+        Some(RootCommand::Session(args)) => run_session(args.command, conf_dir),
         Some(RootCommand::Doctor) => doctor::run_doctor(conf_dir),
         Some(RootCommand::Update(args)) => run_update(args),
+        Some(RootCommand::McpServe(args)) => {
+            pixy_coding_agent::cli::run_mcp_serve_with_conf(args, conf_dir).await
+        }
         None => pixy_coding_agent::cli::run_chat_with_conf(cli.chat, conf_dir).await,
     };
 
@@ -110,6 +199,29 @@ async fn run_gateway(command: GatewaySubcommand, conf_dir: Option<PathBuf>) -> R
 fn run_config(command: ConfigSubcommand, conf_dir: Option<PathBuf>) -> Result<(), String> {
     match command {
         ConfigSubcommand::Init => config_cmd::run_config_init(conf_dir),
+        ConfigSubcommand::Show(args) => {
+            config_cmd::run_config_show(conf_dir, args.cwd, args.origin)
+        }
+    }
+}
+
+fn run_session(command: SessionSubcommand, conf_dir: Option<PathBuf>) -> Result<(), String> {
+    match command {
+        #[cfg(feature = "session-archive")]
+        SessionSubcommand::Export(args) => {
+            session_cmd::run_session_export(conf_dir, args.file, args.session, args.cwd)
+        }
+        #[cfg(feature = "session-archive")]
+        SessionSubcommand::Import(args) => {
+            session_cmd::run_session_import(conf_dir, args.file, args.cwd, args.force)
+        }
+        SessionSubcommand::List(args) => session_cmd::run_session_list(conf_dir, args.limit),
+        SessionSubcommand::Find(args) => {
+            session_cmd::run_session_find(conf_dir, &args.query.join(" "), args.limit)
+        }
+        SessionSubcommand::Fsck(args) => {
+            session_cmd::run_session_fsck(conf_dir, args.session, args.check)
+        }
     }
 }
 
@@ -164,6 +276,64 @@ mod tests {
         assert!(parsed.is_ok(), "pixy config init should be accepted");
     }
 
+    #[test]
+    fn cli_accepts_config_show_origin_subcommand() {
+        let parsed = Cli::try_parse_from(["pixy", "config", "show", "--origin"]);
+        assert!(
+            parsed.is_ok(),
+            "pixy config show --origin should be accepted"
+        );
+    }
+
+    #[cfg(feature = "session-archive")]
+    #[test]
+    fn cli_accepts_session_export_and_import_subcommands() {
+        let export = Cli::try_parse_from([
+            "pixy",
+            "session",
+            "export",
+            "bug.tar.zst",
+            "--session",
+            "latest",
+        ]);
+        assert!(export.is_ok(), "pixy session export should be accepted");
+        let import = Cli::try_parse_from(["pixy", "session", "import", "bug.tar.zst", "--force"]);
+        assert!(import.is_ok(), "pixy session import should be accepted");
+    }
+
+    #[test]
+    fn cli_accepts_sessions_list_and_find_subcommands() {
+        let list = Cli::try_parse_from(["pixy", "sessions", "list", "--limit", "5"]);
+        assert!(list.is_ok(), "pixy sessions list should be accepted");
+        let find = Cli::try_parse_from(["pixy", "sessions", "find", "websocket", "timeout"])
+            .expect("pixy sessions find should be accepted");
+        match find.command {
+            Some(RootCommand::Session(SessionArgs {
+                command: SessionSubcommand::Find(args),
+            })) => assert_eq!(args.query, vec!["websocket", "timeout"]),
+            other => panic!("unexpected command: {other:?}"),
+        }
+        assert!(Cli::try_parse_from(["pixy", "sessions", "find"]).is_err());
+    }
+
+    #[test]
+    fn cli_accepts_session_fsck_subcommand() {
+        let all = Cli::try_parse_from(["pixy", "session", "fsck", "--check"]);
+        assert!(all.is_ok(), "pixy session fsck --check should be accepted");
+        let one = Cli::try_parse_from(["pixy", "session", "fsck", "session-1739"]);
+        assert!(
+            one.is_ok(),
+            "pixy session fsck <session> should be accepted"
+        );
+    }
+
+    #[test]
+    fn cli_accepts_mcp_serve_subcommand() {
+        let parsed = Cli::try_parse_from(["pixy", "mcp-serve", "--cwd", "/tmp", "--model", "m"])
+            .expect("pixy mcp-serve should be accepted");
+        assert!(matches!(parsed.command, Some(RootCommand::McpServe(_))));
+    }
+
     #[test]
     fn cli_accepts_update_subcommand() {
         let parsed = Cli::try_parse_from(["pixy", "update", "--version", "v0.1.0"]);
@@ -0,0 +1,257 @@
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "session-archive")]
+use pixy_coding_agent::{
+    export_session_archive, import_session_archive, LayeredConfig, RuntimeLoadOptions,
+    SessionExportOptions, SessionImportOptions,
+};
+use pixy_coding_agent::{
+    find_session_file, find_sessions, fsck_session_file, list_sessions, SessionFsckReport,
+    SessionListing,
+};
+
+use crate::pixy_home::resolve_pixy_home_dir;
+
+#[cfg(feature = "session-archive")]
+pub fn run_session_export(
+    conf_dir: Option<PathBuf>,
+    archive: PathBuf,
+    session: Option<String>,
+    cwd: Option<PathBuf>,
+) -> Result<(), String> {
+    let pixy_home_dir = resolve_pixy_home_dir(conf_dir.as_deref());
+    let cwd = resolve_cwd(cwd)?;
+    let session_file =
+        find_session_file(session.as_deref(), &cwd, &session_dir_for(&pixy_home_dir))?;
+
+    let manifest = export_session_archive(
+        &archive,
+        &SessionExportOptions {
+            session_file: session_file.clone(),
+            memory_dir: Some(memory_dir_for(&pixy_home_dir, &cwd)),
+            config_fingerprint: config_fingerprint(&pixy_home_dir, &cwd),
+        },
+    )?;
+    println!(
+        "exported {} to {}",
+        session_file.display(),
+        archive.display()
+    );
+    println!(
+        "  memory files: {}, project memory: {}, snapshot objects: {}",
+        manifest.memory_files.len(),
+        if manifest.project_memory { "yes" } else { "no" },
+        manifest.snapshot_objects
+    );
+    Ok(())
+}
+
+#[cfg(feature = "session-archive")]
+pub fn run_session_import(
+    conf_dir: Option<PathBuf>,
+    archive: PathBuf,
+    cwd: Option<PathBuf>,
+    force: bool,
+) -> Result<(), String> {
+    let pixy_home_dir = resolve_pixy_home_dir(conf_dir.as_deref());
+    let cwd = resolve_cwd(cwd)?;
+
+    let summary = import_session_archive(
+        &archive,
+        &SessionImportOptions {
+            session_dir: session_dir_for(&pixy_home_dir),
+            cwd: cwd.clone(),
+            memory_dir: Some(memory_dir_for(&pixy_home_dir, &cwd)),
+            config_fingerprint: config_fingerprint(&pixy_home_dir, &cwd),
+            force,
+        },
+    )?;
+    for warning in &summary.warnings {
+        eprintln!("warning: {warning}");
+    }
+    println!(
+        "imported session {} (exported from {}) to {}",
+        summary.manifest.session_id,
+        summary.manifest.cwd,
+        summary.session_file.display()
+    );
+    println!(
+        "  memory files written: {}, kept existing: {}, snapshot objects: {}",
+        summary.memory_files_written, summary.memory_files_skipped, summary.snapshot_objects
+    );
+    println!(
+        "resume it with: pixy --session-file {}",
+        summary.session_file.display()
+    );
+    Ok(())
+}
+
+pub fn run_session_list(conf_dir: Option<PathBuf>, limit: usize) -> Result<(), String> {
+    let pixy_home_dir = resolve_pixy_home_dir(conf_dir.as_deref());
+    let sessions = list_sessions(&session_dir_for(&pixy_home_dir))?;
+    if sessions.is_empty() {
+        println!("no sessions found");
+        return Ok(());
+    }
+    print_session_listings(&sessions, limit);
+    Ok(())
+}
+
+pub fn run_session_find(
+    conf_dir: Option<PathBuf>,
+    query: &str,
+    limit: usize,
+) -> Result<(), String> {
+    let pixy_home_dir = resolve_pixy_home_dir(conf_dir.as_deref());
+    let sessions = find_sessions(&session_dir_for(&pixy_home_dir), query)?;
+    if sessions.is_empty() {
+        println!("no sessions match \"{query}\"");
+        return Ok(());
+    }
+    print_session_listings(&sessions, limit);
+    Ok(())
+}
+
+pub fn run_session_fsck(
+    conf_dir: Option<PathBuf>,
+    session: Option<String>,
+    check: bool,
+) -> Result<(), String> {
+    let pixy_home_dir = resolve_pixy_home_dir(conf_dir.as_deref());
+    let session_dir = session_dir_for(&pixy_home_dir);
+    let files = match session.as_deref() {
+        Some(target) => vec![find_session_file(
+            Some(target),
+            &resolve_cwd(None)?,
+            &session_dir,
+        )?],
+        None => session_files(&session_dir)?,
+    };
+
+    let mut unreadable = 0;
+    let mut dirty = 0;
+    for file in &files {
+        match fsck_session_file(file, !check) {
+            Ok(report) if report.is_clean() => {}
+            Ok(report) => {
+                dirty += 1;
+                print_fsck_report(&report);
+            }
+            Err(error) => {
+                unreadable += 1;
+                println!("{}: {error}", file.display());
+            }
+        }
+    }
+    println!(
+        "checked {} session{}: {} clean, {} {}, {} unreadable",
+        files.len(),
+        if files.len() == 1 { "" } else { "s" },
+        files.len() - dirty - unreadable,
+        dirty,
+        if check { "need repair" } else { "repaired" },
+        unreadable
+    );
+    if check && dirty > 0 {
+        println!("run `pixy session fsck` without --check to repair them");
+    }
+    if unreadable > 0 {
+        return Err(format!("{unreadable} session file(s) could not be read"));
+    }
+    Ok(())
+}
+
+/// Every `.jsonl` file in the session directory, including ones too damaged to list.
+fn session_files(session_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    if !session_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = std::fs::read_dir(session_dir)
+        .map_err(|error| format!("read {} failed: {error}", session_dir.display()))?;
+    let mut files = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file() && path.extension().and_then(|ext| ext.to_str()) == Some("jsonl")
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+fn print_fsck_report(report: &SessionFsckReport) {
+    println!("{}", report.session_file.display());
+    if report.needs_migration() {
+        println!("  format version {} is outdated", report.from_version);
+    }
+    for issue in &report.issues {
+        println!(
+            "  line {}: {} ({})",
+            issue.line,
+            issue.kind.label(),
+            issue.detail
+        );
+    }
+    if let Some(quarantine_file) = &report.quarantine_file {
+        println!("  quarantined records: {}", quarantine_file.display());
+    }
+}
+
+fn print_session_listings(sessions: &[SessionListing], limit: usize) {
+    for session in sessions.iter().take(limit) {
+        println!(
+            "{}  {}  ({} turn{})",
+            session.updated_at,
+            session.title,
+            session.turn_count,
+            if session.turn_count == 1 { "" } else { "s" }
+        );
+        println!("    cwd: {}", session.cwd);
+        println!("    file: {}", session.path.display());
+        if let Some(snippet) = &session.snippet {
+            println!("    {snippet}");
+        }
+    }
+    if sessions.len() > limit {
+        println!(
+            "… {} more (use --limit to show them)",
+            sessions.len() - limit
+        );
+    }
+    println!("resume one with: pixy --session-file <file>");
+}
+
+fn resolve_cwd(cwd: Option<PathBuf>) -> Result<PathBuf, String> {
+    let process_cwd = std::env::current_dir()
+        .map_err(|error| format!("resolve current directory failed: {error}"))?;
+    Ok(match cwd {
+        Some(cwd) if cwd.is_absolute() => cwd,
+        Some(cwd) => process_cwd.join(cwd),
+        None => process_cwd,
+    })
+}
+
+fn session_dir_for(pixy_home_dir: &Path) -> PathBuf {
+    pixy_home_dir.join("agents").join("sessions")
+}
+
+/// The configured `[memory] dir`, or its default when the runtime config cannot be resolved.
+#[cfg(feature = "session-archive")]
+fn memory_dir_for(pixy_home_dir: &Path, cwd: &Path) -> PathBuf {
+    let options = RuntimeLoadOptions {
+        conf_dir: Some(pixy_home_dir.to_path_buf()),
+        load_skills: false,
+        include_default_skills: false,
+        ..RuntimeLoadOptions::default()
+    };
+    options
+        .resolve_runtime(cwd)
+        .map(|runtime| runtime.memory.dir)
+        .unwrap_or_else(|_| pixy_home_dir.join("memory"))
+}
+
+#[cfg(feature = "session-archive")]
+fn config_fingerprint(pixy_home_dir: &Path, cwd: &Path) -> Option<String> {
+    LayeredConfig::load(pixy_home_dir, cwd)
+        .ok()
+        .map(|config| config.fingerprint())
+}
@@ -50,6 +50,44 @@ fn config_init_keeps_existing_pixy_toml() {
     );
 }
 
+#[test]
+fn config_show_origin_reports_closest_layer_for_each_value() {
+    let conf_dir = tempdir().expect("create temp conf dir");
+    let workspace = tempdir().expect("create temp workspace");
+    let repo = workspace.path().join("repo");
+    let sub = repo.join("service");
+    fs::create_dir_all(repo.join(".git")).expect("create .git");
+    fs::create_dir_all(sub.join(".pixy")).expect("create sub .pixy");
+    fs::write(
+        conf_dir.path().join("pixy.toml"),
+        "theme = \"dark\"\ntransport_retry_count = 2\n",
+    )
+    .expect("seed pixy.toml");
+    fs::write(sub.join(".pixy/config.toml"), "theme = \"light\"\n").expect("seed sub config");
+
+    let output = Command::new(pixy_binary_path())
+        .arg("--conf-dir")
+        .arg(conf_dir.path())
+        .arg("config")
+        .arg("show")
+        .arg("--origin")
+        .arg("--cwd")
+        .arg(&sub)
+        .output()
+        .expect("execute pixy binary");
+    assert_command_succeeded(&output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!(
+        "theme = \"light\"  # {}",
+        sub.join(".pixy/config.toml").display()
+    )));
+    assert!(stdout.contains(&format!(
+        "transport_retry_count = 2  # {}",
+        conf_dir.path().join("pixy.toml").display()
+    )));
+}
+
 fn run_pixy_config_init(conf_dir: &Path) -> Output {
     Command::new(pixy_binary_path())
         .arg("--conf-dir")
@@ -2,24 +2,176 @@ use std::future::Future;
 use std::path::PathBuf;
 use std::pin::Pin;
 
-use pixy_agent_core::AgentAbortSignal;
-use pixy_ai::{Message, UserContentBlock};
+use pixy_agent_core::{AgentAbortSignal, AgentRunMetrics, QueuedMessages};
+use pixy_ai::{Message, StreamStats, UserContentBlock};
+use tokio::sync::mpsc;
 
 pub type BackendFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<Message>, String>> + 'a>>;
+pub type BackendStatusFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Option<String>, String>> + 'a>>;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum StreamUpdate {
     AssistantTextDelta(String),
     AssistantThinkingDelta(String),
     AssistantLine(String),
     ToolLine(String),
+    /// Full replacement for the pinned todo panel.
+    Todos(Vec<TodoItem>),
+    /// Tokens consumed by the in-flight response since the previous update.
+    UsageDelta {
+        input_tokens: u64,
+        output_tokens: u64,
+    },
+    /// A tool call the model is still writing, shown in the working line until it runs.
+    ToolCallPreview(String),
+    /// Latency and throughput of the model call that just finished, shown in the footer.
+    TurnStats(StreamStats),
+    /// One step of a running subagent, grouped with the other steps of its `task_id`.
+    SubagentProgress {
+        task_id: String,
+        subagent: String,
+        line: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TodoStatus {
+    Pending,
+    InProgress,
+    Done,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TodoItem {
+    pub content: String,
+    pub status: TodoStatus,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffReviewChoice {
+    Approve,
+    /// Approves this change and every later one in the session.
+    ApproveAll,
+    Reject,
+}
+
+/// A proposed file change held back until the user approves or rejects it.
+pub struct DiffReviewPrompt {
+    pub tool: String,
+    pub path: String,
+    pub diff: String,
+    respond: Box<dyn FnOnce(DiffReviewChoice) + Send>,
+}
+
+impl DiffReviewPrompt {
+    pub fn new(
+        tool: String,
+        path: String,
+        diff: String,
+        respond: impl FnOnce(DiffReviewChoice) + Send + 'static,
+    ) -> Self {
+        Self {
+            tool,
+            path,
+            diff,
+            respond: Box::new(respond),
+        }
+    }
+
+    pub fn respond(self, choice: DiffReviewChoice) {
+        (self.respond)(choice);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToolApprovalChoice {
+    Approve,
+    Deny,
+}
+
+/// A call to a flagged tool held back until the user approves or denies it.
+pub struct ToolApprovalPrompt {
+    pub tool: String,
+    /// `low`, `medium` or `high`.
+    pub risk: String,
+    /// Pretty-printed call arguments.
+    pub arguments: String,
+    respond: Box<dyn FnOnce(ToolApprovalChoice) + Send>,
+}
+
+impl ToolApprovalPrompt {
+    pub fn new(
+        tool: String,
+        risk: String,
+        arguments: String,
+        respond: impl FnOnce(ToolApprovalChoice) + Send + 'static,
+    ) -> Self {
+        Self {
+            tool,
+            risk,
+            arguments,
+            respond: Box::new(respond),
+        }
+    }
+
+    pub fn respond(self, choice: ToolApprovalChoice) {
+        (self.respond)(choice);
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct ResumeCandidate {
     pub session_ref: String,
     pub title: String,
+    /// First user prompt, shown when the title was generated from something else.
+    pub first_prompt: Option<String>,
     pub updated_at: String,
+    pub turn_count: usize,
+    pub message_count: usize,
+    /// Model of the latest assistant reply.
+    pub model: Option<String>,
+    /// Total cost in USD.
+    pub total_cost: f64,
+    pub cwd: String,
+}
+
+impl ResumeCandidate {
+    /// The title, followed by the first prompt when the two differ.
+    pub fn headline(&self) -> String {
+        match self
+            .first_prompt
+            .as_deref()
+            .filter(|prompt| *prompt != self.title)
+        {
+            Some(prompt) => format!("{} - \"{prompt}\"", self.title),
+            None => self.title.clone(),
+        }
+    }
+
+    /// `updated · turns · messages · model · cost · cwd`, skipping what is unknown.
+    pub fn details(&self) -> String {
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+        let mut parts = vec![
+            self.updated_at.clone(),
+            format!("{} turn{}", self.turn_count, plural(self.turn_count)),
+            format!(
+                "{} message{}",
+                self.message_count,
+                plural(self.message_count)
+            ),
+        ];
+        parts.extend(self.model.clone());
+        if self.total_cost > 0.0 {
+            parts.push(if self.total_cost < 0.01 {
+                format!("${:.4}", self.total_cost)
+            } else {
+                format!("${:.2}", self.total_cost)
+            });
+        }
+        parts.push(self.cwd.clone());
+        parts.join(" · ")
+    }
 }
 
 pub trait TuiBackend {
@@ -70,8 +222,55 @@ pub trait TuiBackend {
     fn new_session(&mut self) -> Result<Option<String>, String> {
         Ok(None)
     }
+    fn fork_session(&mut self, _name: Option<&str>) -> Result<Option<String>, String> {
+        Ok(None)
+    }
+    fn undo_file_changes(&mut self) -> Result<Option<String>, String> {
+        Ok(None)
+    }
+    fn redo_file_changes(&mut self) -> Result<Option<String>, String> {
+        Ok(None)
+    }
+    fn skills_command(&mut self, _args: &str) -> Result<Option<Vec<String>>, String> {
+        Ok(None)
+    }
+    fn expand_skill_invocation(&mut self, _args: &str) -> Result<Option<String>, String> {
+        Ok(None)
+    }
+    fn remember<'a>(&'a mut self, _note: Option<&'a str>) -> BackendStatusFuture<'a> {
+        Box::pin(async { Ok(None) })
+    }
+    fn cost_report(&mut self) -> Result<Option<Vec<String>>, String> {
+        Ok(None)
+    }
     fn session_messages(&self) -> Option<Vec<Message>> {
         None
     }
+    /// Current todo list, used to restore the pinned panel after startup, `/resume`, `/new` and
+    /// `/fork`.
+    fn todo_items(&self) -> Option<Vec<TodoItem>> {
+        None
+    }
+    /// Estimated context tokens and the model's context window, shown as `12.3%/200k`.
+    fn context_usage(&self) -> Option<(u64, u64)> {
+        None
+    }
+    /// Tool and request totals of the finished runs, summarized on the status line.
+    fn run_metrics(&self) -> Option<AgentRunMetrics> {
+        None
+    }
+    /// Channel of file changes awaiting review during the next run; `None` writes unreviewed.
+    fn diff_reviews(&mut self) -> Option<mpsc::UnboundedReceiver<DiffReviewPrompt>> {
+        None
+    }
+    /// Queue the next run polls for steering messages, so input typed while it streams joins
+    /// the run before its next model turn; `None` holds that input until the run ends.
+    fn queued_messages(&mut self) -> Option<QueuedMessages> {
+        None
+    }
+    /// Channel of tool calls awaiting approval during the next run; `None` runs them unasked.
+    fn tool_approvals(&mut self) -> Option<mpsc::UnboundedReceiver<ToolApprovalPrompt>> {
+        None
+    }
     fn session_file(&self) -> Option<PathBuf>;
 }
@@ -2,6 +2,7 @@ pub(crate) const FORCE_EXIT_SIGNAL: &str = "__FORCE_EXIT__";
 pub(crate) const FORCE_EXIT_STATUS: &str = "force exiting...";
 pub(crate) const PASTED_TEXT_PREVIEW_LIMIT: usize = 100;
 pub(crate) const RESUME_LIST_LIMIT: usize = 10;
+pub(crate) const TODO_PANEL_MAX_ITEMS: usize = 8;
 pub(crate) const INPUT_RENDER_LEFT_PADDING: &str = " ";
 pub(crate) const INPUT_PLACEHOLDER_HINTS: &[&str] =
     &["Try \"Search the documentation for this library\""];
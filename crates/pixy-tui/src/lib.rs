@@ -10,8 +10,8 @@ use crossterm::event::{
     Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseEvent, MouseEventKind,
 };
 use futures_util::StreamExt;
-use pixy_agent_core::AgentAbortController;
-use pixy_ai::{Message, StopReason, UserContentBlock};
+use pixy_agent_core::{AgentAbortController, AgentRunMetrics, QueuePriority, QueuedMessages};
+use pixy_ai::{Message, StopReason, StreamStats, UserContent, UserContentBlock};
 use ratatui::backend::CrosstermBackend;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 #[cfg(test)]
@@ -34,11 +34,14 @@ mod terminal;
 pub mod theme;
 mod transcript;
 
-pub use backend::{BackendFuture, ResumeCandidate, StreamUpdate, TuiBackend};
+pub use backend::{
+    BackendFuture, BackendStatusFuture, DiffReviewChoice, DiffReviewPrompt, ResumeCandidate,
+    StreamUpdate, TodoItem, TodoStatus, ToolApprovalChoice, ToolApprovalPrompt, TuiBackend,
+};
 use constants::{
     primary_input_placeholder_hint, FORCE_EXIT_SIGNAL, FORCE_EXIT_STATUS, INPUT_AREA_FIXED_HEIGHT,
     INPUT_RENDER_LEFT_PADDING, PASTED_TEXT_PREVIEW_LIMIT, RESUME_LIST_LIMIT, STATUS_HINT_LEFT,
-    STATUS_HINT_RIGHT,
+    STATUS_HINT_RIGHT, TODO_PANEL_MAX_ITEMS,
 };
 pub use keybindings::{parse_key_id, KeyBinding, TuiKeyBindings};
 pub use options::TuiOptions;
@@ -68,7 +71,7 @@ struct PendingImageAttachment {
     block: UserContentBlock,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 struct ResumePickerState {
     candidates: Vec<ResumeCandidate>,
     selected: usize,
@@ -162,6 +165,13 @@ struct TuiApp {
     working_tick: usize,
     working_started_at: Option<Instant>,
     working_elapsed_accumulated: Duration,
+    streamed_input_tokens: u64,
+    streamed_output_tokens: u64,
+    /// Estimated context tokens and the model's context window, from the backend.
+    context_usage: Option<(u64, u64)>,
+    run_metrics: Option<AgentRunMetrics>,
+    /// Latency and throughput of the last finished model call, shown in the idle footer.
+    last_turn_stats: Option<StreamStats>,
     interrupt_hint_label: String,
     dequeue_hint_label: String,
     last_clear_key_at_ms: i64,
@@ -172,6 +182,13 @@ struct TuiApp {
     status_right: String,
     resume_picker: Option<ResumePickerState>,
     welcome_lines: Vec<String>,
+    todos: Vec<TodoItem>,
+    pending_review: Option<DiffReviewPrompt>,
+    pending_approval: Option<ToolApprovalPrompt>,
+    /// Steering queue of the running prompt, when the backend has one.
+    steering: Option<QueuedMessages>,
+    /// Input sent to `steering` that the run has not picked up yet, oldest first.
+    steered_messages: Vec<String>,
 }
 
 impl TuiApp {
@@ -196,6 +213,11 @@ impl TuiApp {
             working_tick: 0,
             working_started_at: None,
             working_elapsed_accumulated: Duration::ZERO,
+            streamed_input_tokens: 0,
+            streamed_output_tokens: 0,
+            context_usage: None,
+            run_metrics: None,
+            last_turn_stats: None,
             interrupt_hint_label: "esc".to_string(),
             dequeue_hint_label: "Alt+Up".to_string(),
             last_clear_key_at_ms: 0,
@@ -206,6 +228,11 @@ impl TuiApp {
             status_right: String::new(),
             resume_picker: None,
             welcome_lines: vec![],
+            todos: vec![],
+            pending_review: None,
+            pending_approval: None,
+            steering: None,
+            steered_messages: vec![],
         }
     }
 
@@ -219,6 +246,60 @@ impl TuiApp {
         self.welcome_lines = lines;
     }
 
+    fn sync_todos<B: TuiBackend>(&mut self, backend: &B) {
+        if let Some(todos) = backend.todo_items() {
+            self.todos = todos;
+        }
+    }
+
+    fn sync_context_usage<B: TuiBackend>(&mut self, backend: &B) {
+        self.context_usage = backend.context_usage();
+        self.run_metrics = backend.run_metrics();
+    }
+
+    /// Pinned todo panel; hidden once every item is done.
+    fn todo_panel_lines(&self) -> Vec<String> {
+        if self
+            .todos
+            .iter()
+            .all(|item| item.status == TodoStatus::Done)
+        {
+            return vec![];
+        }
+
+        let done = self
+            .todos
+            .iter()
+            .filter(|item| item.status == TodoStatus::Done)
+            .count();
+        let mut lines = vec![format!("Todos {done}/{}", self.todos.len())];
+        // Drop finished items from the top first so the active work stays visible.
+        let hidden_done = self
+            .todos
+            .len()
+            .saturating_sub(TODO_PANEL_MAX_ITEMS)
+            .min(done);
+        let visible = self
+            .todos
+            .iter()
+            .enumerate()
+            .filter(|(index, item)| item.status != TodoStatus::Done || *index >= hidden_done)
+            .map(|(_, item)| item)
+            .collect::<Vec<_>>();
+        for item in visible.iter().take(TODO_PANEL_MAX_ITEMS) {
+            let marker = match item.status {
+                TodoStatus::Pending => "○",
+                TodoStatus::InProgress => "◐",
+                TodoStatus::Done => "✔",
+            };
+            lines.push(format!("  {marker} {}", item.content));
+        }
+        if visible.len() > TODO_PANEL_MAX_ITEMS {
+            lines.push(format!("  … {} more", visible.len() - TODO_PANEL_MAX_ITEMS));
+        }
+        lines
+    }
+
     fn set_interrupt_hint_label(&mut self, label: String) {
         if label.trim().is_empty() {
             return;
@@ -383,6 +464,7 @@ impl TuiApp {
                 out.push(UserContentBlock::Text {
                     text: text_for_blocks.clone(),
                     text_signature: None,
+                    cache: None,
                 });
                 out.extend(blocks);
                 Some(out)
@@ -581,6 +663,83 @@ impl TuiApp {
         self.transcript.extend(lines);
     }
 
+    /// Shows a proposed file change and holds it until the user answers with y, n or a.
+    fn show_diff_review(&mut self, review: DiffReviewPrompt) {
+        self.assistant_stream_open = false;
+        self.transcript.push(TranscriptLine::new(
+            format!(
+                "review {} {}: [y] apply  [n] reject  [a] apply all for this session",
+                review.tool, review.path
+            ),
+            TranscriptLineKind::Review,
+        ));
+        self.transcript.extend(
+            review
+                .diff
+                .lines()
+                .map(|line| TranscriptLine::new(line.to_string(), TranscriptLineKind::Review)),
+        );
+        self.scroll_transcript_to_latest();
+        self.status = format!("review {}: y apply · n reject · a apply all", review.path);
+        self.pending_review = Some(review);
+    }
+
+    fn answer_diff_review(&mut self, choice: DiffReviewChoice) -> bool {
+        let Some(review) = self.pending_review.take() else {
+            return false;
+        };
+        let outcome = match choice {
+            DiffReviewChoice::Approve => "applied",
+            DiffReviewChoice::ApproveAll => "applied (auto-approving for this session)",
+            DiffReviewChoice::Reject => "rejected",
+        };
+        self.status = format!("{outcome} {}", review.path);
+        self.transcript.push(TranscriptLine::new(
+            format!("review {}: {outcome}", review.path),
+            TranscriptLineKind::Review,
+        ));
+        review.respond(choice);
+        true
+    }
+
+    /// Shows a flagged tool call and holds it until the user answers with y or n.
+    fn show_tool_approval(&mut self, approval: ToolApprovalPrompt) {
+        self.assistant_stream_open = false;
+        self.transcript.push(TranscriptLine::new(
+            format!(
+                "approve {} ({} risk): [y] allow  [n] deny",
+                approval.tool, approval.risk
+            ),
+            TranscriptLineKind::Review,
+        ));
+        self.transcript.extend(
+            approval
+                .arguments
+                .lines()
+                .map(|line| TranscriptLine::new(line.to_string(), TranscriptLineKind::Review)),
+        );
+        self.scroll_transcript_to_latest();
+        self.status = format!("approve {}: y allow · n deny", approval.tool);
+        self.pending_approval = Some(approval);
+    }
+
+    fn answer_tool_approval(&mut self, choice: ToolApprovalChoice) -> bool {
+        let Some(approval) = self.pending_approval.take() else {
+            return false;
+        };
+        let outcome = match choice {
+            ToolApprovalChoice::Approve => "allowed",
+            ToolApprovalChoice::Deny => "denied",
+        };
+        self.status = format!("{outcome} {}", approval.tool);
+        self.transcript.push(TranscriptLine::new(
+            format!("approve {}: {outcome}", approval.tool),
+            TranscriptLineKind::Review,
+        ));
+        approval.respond(choice);
+        true
+    }
+
     fn replace_transcript_with_messages(&mut self, messages: &[Message]) {
         self.assistant_stream_open = false;
         self.transcript = render_messages(messages);
@@ -640,6 +799,48 @@ impl TuiApp {
         }
     }
 
+    fn streamed_tokens_label(&self) -> Option<String> {
+        if self.streamed_input_tokens == 0 && self.streamed_output_tokens == 0 {
+            return None;
+        }
+        Some(format!(
+            "↑{} ↓{}",
+            format_token_count(self.streamed_input_tokens),
+            format_token_count(self.streamed_output_tokens)
+        ))
+    }
+
+    /// Share of the context window in use, e.g. `12.3%/200k`.
+    fn context_usage_label(&self) -> Option<String> {
+        let (tokens, window) = self.context_usage.filter(|(_, window)| *window > 0)?;
+        let percent = tokens as f64 * 100.0 / window as f64;
+        let window = format_token_count(window)
+            .replace(".0k", "k")
+            .replace(".0M", "M");
+        Some(format!("{percent:.1}%/{window}"))
+    }
+
+    /// Tool calls of the session so far, e.g. `12 tools · 8% failed`.
+    fn run_metrics_label(&self) -> Option<String> {
+        let metrics = self
+            .run_metrics
+            .as_ref()
+            .filter(|metrics| metrics.tool_execution_count > 0)?;
+        let calls = metrics.tool_execution_count;
+        let label = if calls == 1 {
+            "1 tool".to_string()
+        } else {
+            format!("{calls} tools")
+        };
+        match metrics.tool_failure_count() {
+            0 => Some(label),
+            failed => Some(format!(
+                "{label} · {:.0}% failed",
+                failed as f64 * 100.0 / calls as f64
+            )),
+        }
+    }
+
     fn status_for_render(&self) -> String {
         self.status.clone()
     }
@@ -648,27 +849,72 @@ impl TuiApp {
         self.queued_follow_ups.push(input);
     }
 
+    /// Sends `input` to the running prompt before its next model turn.
+    fn steer(&mut self, input: String) -> bool {
+        let Some(steering) = &self.steering else {
+            return false;
+        };
+        steering.push(
+            Message::User {
+                content: UserContent::Text(input.clone()),
+                timestamp: now_millis(),
+            },
+            QueuePriority::SteerNow,
+        );
+        self.steered_messages.push(input);
+        true
+    }
+
+    /// Moves steering input the run has picked up into the transcript.
+    fn sync_steering(&mut self, input_prompt: &str) {
+        let Some(steering) = &self.steering else {
+            return;
+        };
+        let pending = steering.pending_steering().len();
+        let injected = self.steered_messages.len().saturating_sub(pending);
+        for input in self.steered_messages.drain(..injected).collect::<Vec<_>>() {
+            self.push_user_input_line(format_user_input_line(&input, input_prompt));
+        }
+    }
+
+    /// Detaches the steering queue when a run ends; input it never picked up runs next, ahead
+    /// of the queued follow-ups.
+    fn finish_steering(&mut self, input_prompt: &str) {
+        self.sync_steering(input_prompt);
+        if let Some(steering) = self.steering.take() {
+            steering.take_steering();
+        }
+        let leftover = std::mem::take(&mut self.steered_messages);
+        self.queued_follow_ups.splice(0..0, leftover);
+    }
+
     fn dequeue_follow_ups_to_editor(&mut self) -> Option<usize> {
-        let count = self.queued_follow_ups.len();
+        if let Some(steering) = &self.steering {
+            steering.take_steering();
+        }
+        let mut queued = std::mem::take(&mut self.steered_messages);
+        queued.append(&mut self.queued_follow_ups);
+        let count = queued.len();
         if count == 0 {
             return None;
         }
-        self.input = self.queued_follow_ups.join("\n");
+        self.input = queued.join("\n");
         self.cursor_pos = self.input_char_count();
-        self.queued_follow_ups.clear();
         self.reset_input_history_navigation();
         self.scroll_transcript_to_latest();
         Some(count)
     }
 
     fn steering_status_lines(&self) -> Vec<String> {
-        if !self.is_working || self.queued_follow_ups.is_empty() {
+        if !self.is_working || self.steered_messages.is_empty() && self.queued_follow_ups.is_empty()
+        {
             return vec![];
         }
 
         let mut lines = self
-            .queued_follow_ups
+            .steered_messages
             .iter()
+            .chain(&self.queued_follow_ups)
             .map(|queued| format!("Steering: {}", summarize_steering_message(queued)))
             .collect::<Vec<_>>();
         lines.push(format!(
@@ -779,12 +1025,19 @@ impl TuiApp {
                     "Streaming...".to_string()
                 };
             }
+            StreamUpdate::Todos(_)
+            | StreamUpdate::UsageDelta { .. }
+            | StreamUpdate::TurnStats(_) => {}
+            StreamUpdate::ToolCallPreview(preview) => {
+                self.working_message = preview.clone();
+            }
+            StreamUpdate::SubagentProgress { subagent, .. } => {
+                self.working_message = format!("Subagent {subagent} is working...");
+            }
             StreamUpdate::ToolLine(line) => {
                 if let Some(subagent) = parse_task_subagent(line) {
                     self.working_message = format!("Subagent {subagent} is working...");
-                } else if is_tool_run_line(line) {
-                    self.working_message = "Invoking tools...".to_string();
-                } else if parse_tool_name(line).is_some() {
+                } else if is_tool_run_line(line) || parse_tool_name(line).is_some() {
                     self.working_message = "Invoking tools...".to_string();
                 } else {
                     self.working_message = "Working...".to_string();
@@ -874,6 +1127,55 @@ impl TuiApp {
                     }
                 }
             }
+            StreamUpdate::Todos(todos) => {
+                self.todos = todos;
+            }
+            StreamUpdate::UsageDelta {
+                input_tokens,
+                output_tokens,
+            } => {
+                self.streamed_input_tokens =
+                    self.streamed_input_tokens.saturating_add(input_tokens);
+                self.streamed_output_tokens =
+                    self.streamed_output_tokens.saturating_add(output_tokens);
+            }
+            StreamUpdate::ToolCallPreview(_) => {}
+            StreamUpdate::TurnStats(stats) => {
+                self.last_turn_stats = Some(stats);
+            }
+            StreamUpdate::SubagentProgress {
+                task_id,
+                subagent,
+                line,
+            } => {
+                self.assistant_stream_open = false;
+                self.push_subagent_progress(task_id, &subagent, line);
+            }
+        }
+    }
+
+    /// Adds a step to the block of `task_id` in the current run, opening the block on its first
+    /// step. Parallel subagents report interleaved, so each step goes after its block's last line.
+    fn push_subagent_progress(&mut self, task_id: String, subagent: &str, line: String) {
+        let run_start = self
+            .transcript
+            .iter()
+            .rposition(|line| line.kind == TranscriptLineKind::UserInput)
+            .map_or(0, |index| index + 1);
+        let block_end = self.transcript[run_start..]
+            .iter()
+            .rposition(|line| line.subagent_task_id() == Some(task_id.as_str()))
+            .map(|index| run_start + index + 1);
+        let step = TranscriptLine::new_subagent_step(line, task_id.clone());
+        match block_end {
+            Some(index) => self.transcript.insert(index, step),
+            None => {
+                self.transcript.push(TranscriptLine::new_subagent_header(
+                    format!("Subagent {subagent} (task_id={task_id})"),
+                    task_id,
+                ));
+                self.transcript.push(step);
+            }
         }
     }
 
@@ -1408,6 +1710,69 @@ async fn handle_slash_command<B: TuiBackend>(
             app.status = backend
                 .new_session()?
                 .unwrap_or_else(|| "new session is not supported by this backend".to_string());
+            app.sync_todos(backend);
+            app.sync_context_usage(backend);
+            Ok(true)
+        }
+        command if command == "/fork" || command.starts_with("/fork ") => {
+            let name = command
+                .strip_prefix("/fork")
+                .map(str::trim)
+                .filter(|value| !value.is_empty());
+            app.status = backend
+                .fork_session(name)?
+                .unwrap_or_else(|| "fork is not supported by this backend".to_string());
+            app.sync_todos(backend);
+            app.sync_context_usage(backend);
+            Ok(true)
+        }
+        "/undo" => {
+            app.status = backend
+                .undo_file_changes()?
+                .unwrap_or_else(|| "undo is not supported by this backend".to_string());
+            Ok(true)
+        }
+        "/redo" => {
+            app.status = backend
+                .redo_file_changes()?
+                .unwrap_or_else(|| "redo is not supported by this backend".to_string());
+            Ok(true)
+        }
+        command if command == "/skills" || command.starts_with("/skills ") => {
+            let args = command.trim_start_matches("/skills").trim();
+            match backend.skills_command(args) {
+                Ok(Some(lines)) => {
+                    app.push_lines(lines);
+                    app.scroll_transcript_to_latest();
+                    app.status = "skills".to_string();
+                }
+                Ok(None) => app.status = "skills are not supported by this backend".to_string(),
+                Err(error) => app.status = error,
+            }
+            Ok(true)
+        }
+        command if command == "/remember" || command.starts_with("/remember ") => {
+            let note = command.trim_start_matches("/remember").trim();
+            let note = (!note.is_empty()).then_some(note);
+            app.status = match backend.remember(note).await {
+                Ok(Some(status)) => status,
+                Ok(None) => "project memory is not supported by this backend".to_string(),
+                Err(error) => error,
+            };
+            Ok(true)
+        }
+        "/cost" => {
+            match backend.cost_report() {
+                Ok(Some(lines)) => {
+                    app.push_lines(lines);
+                    app.scroll_transcript_to_latest();
+                    app.status = "cost".to_string();
+                }
+                Ok(None) => {
+                    app.status = "cost tracking is not supported by this backend".to_string()
+                }
+                Err(error) => app.status = error,
+            }
             Ok(true)
         }
         command if command.starts_with("/resume") => {
@@ -1418,6 +1783,11 @@ async fn handle_slash_command<B: TuiBackend>(
     }
 }
 
+fn skill_invocation_args(input: &str) -> Option<&str> {
+    let rest = input.strip_prefix("/skill")?;
+    (rest.is_empty() || rest.starts_with(char::is_whitespace)).then(|| rest.trim())
+}
+
 fn handle_resume_picker_key_event<B: TuiBackend>(
     key: KeyEvent,
     backend: &mut B,
@@ -1426,6 +1796,7 @@ fn handle_resume_picker_key_event<B: TuiBackend>(
     resume::handle_resume_picker_key_event(key, backend, app)
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn run_submitted_input<B: TuiBackend>(
     backend: &mut B,
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
@@ -1442,10 +1813,28 @@ async fn run_submitted_input<B: TuiBackend>(
 
     if blocks.is_none() && submitted_input == "/continue" {
         handle_continue_streaming(backend, terminal, app, options, events).await?;
+        app.sync_context_usage(backend);
         process_queued_follow_ups(backend, terminal, app, options, events).await?;
         return Ok(());
     }
 
+    let mut submitted_input = submitted_input;
+    if blocks.is_none() {
+        if let Some(args) = skill_invocation_args(&submitted_input) {
+            match backend.expand_skill_invocation(args) {
+                Ok(Some(rendered)) => submitted_input = rendered,
+                Ok(None) => {
+                    app.status = "skills are not supported by this backend".to_string();
+                    return Ok(());
+                }
+                Err(error) => {
+                    app.status = error;
+                    return Ok(());
+                }
+            }
+        }
+    }
+
     if blocks.is_none() {
         match handle_slash_command(&submitted_input, backend, app).await {
             Ok(true) => return Ok(()),
@@ -1470,6 +1859,7 @@ async fn run_submitted_input<B: TuiBackend>(
         events,
     )
     .await?;
+    app.sync_context_usage(backend);
 
     process_queued_follow_ups(backend, terminal, app, options, events).await
 }
@@ -1494,6 +1884,9 @@ async fn run_prompt_streaming<B: TuiBackend>(
     let mut on_update = move |update: StreamUpdate| {
         let _ = update_tx.send(update);
     };
+    let mut diff_reviews = backend.diff_reviews();
+    let mut tool_approvals = backend.tool_approvals();
+    app.steering = backend.queued_messages();
     let stream_future = backend.prompt_stream_with_blocks(
         input,
         blocks,
@@ -1536,20 +1929,33 @@ async fn run_prompt_streaming<B: TuiBackend>(
                     app.note_working_from_update(&options.app_name, &update);
                     app.bump_working_tick();
                     app.apply_stream_update(update);
+                    app.sync_steering(options.theme.input_prompt());
                     let _ = draw_ui_frame(terminal, app, options);
                 }
             }
+            Some(review) = next_diff_review(&mut diff_reviews), if app.pending_review.is_none() => {
+                app.show_diff_review(review);
+                let _ = draw_ui_frame(terminal, app, options);
+            }
+            Some(approval) = next_tool_approval(&mut tool_approvals), if app.pending_approval.is_none() => {
+                app.show_tool_approval(approval);
+                let _ = draw_ui_frame(terminal, app, options);
+            }
             _ = ticker.tick() => {
                 app.bump_working_tick();
+                app.sync_steering(options.theme.input_prompt());
                 let _ = draw_ui_frame(terminal, app, options);
             }
             result = &mut stream_future => {
+                app.pending_review = None;
+                app.pending_approval = None;
                 while let Ok(update) = update_rx.try_recv() {
                     saw_update = true;
                     app.note_working_from_update(&options.app_name, &update);
                     app.bump_working_tick();
                     app.apply_stream_update(update);
                 }
+                app.finish_steering(options.theme.input_prompt());
 
                 app.stop_working();
                 match result {
@@ -1592,6 +1998,9 @@ async fn handle_continue_streaming<B: TuiBackend>(
     let mut on_update = move |update: StreamUpdate| {
         let _ = update_tx.send(update);
     };
+    let mut diff_reviews = backend.diff_reviews();
+    let mut tool_approvals = backend.tool_approvals();
+    app.steering = backend.queued_messages();
     let stream_future =
         backend.continue_run_stream(Some(abort_controller.signal()), &mut on_update);
     tokio::pin!(stream_future);
@@ -1630,20 +2039,33 @@ async fn handle_continue_streaming<B: TuiBackend>(
                     app.note_working_from_update(&options.app_name, &update);
                     app.bump_working_tick();
                     app.apply_stream_update(update);
+                    app.sync_steering(options.theme.input_prompt());
                     let _ = draw_ui_frame(terminal, app, options);
                 }
             }
+            Some(review) = next_diff_review(&mut diff_reviews), if app.pending_review.is_none() => {
+                app.show_diff_review(review);
+                let _ = draw_ui_frame(terminal, app, options);
+            }
+            Some(approval) = next_tool_approval(&mut tool_approvals), if app.pending_approval.is_none() => {
+                app.show_tool_approval(approval);
+                let _ = draw_ui_frame(terminal, app, options);
+            }
             _ = ticker.tick() => {
                 app.bump_working_tick();
+                app.sync_steering(options.theme.input_prompt());
                 let _ = draw_ui_frame(terminal, app, options);
             }
             result = &mut stream_future => {
+                app.pending_review = None;
+                app.pending_approval = None;
                 while let Ok(update) = update_rx.try_recv() {
                     saw_update = true;
                     app.note_working_from_update(&options.app_name, &update);
                     app.bump_working_tick();
                     app.apply_stream_update(update);
                 }
+                app.finish_steering(options.theme.input_prompt());
 
                 app.stop_working();
                 match result {
@@ -1681,10 +2103,29 @@ async fn process_queued_follow_ups<B: TuiBackend>(
             options.theme.input_prompt(),
         ));
         run_prompt_streaming(backend, terminal, app, options, &queued, None, events).await?;
+        app.sync_context_usage(backend);
     }
     Ok(())
 }
 
+async fn next_diff_review(
+    reviews: &mut Option<mpsc::UnboundedReceiver<DiffReviewPrompt>>,
+) -> Option<DiffReviewPrompt> {
+    match reviews {
+        Some(reviews) => reviews.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn next_tool_approval(
+    approvals: &mut Option<mpsc::UnboundedReceiver<ToolApprovalPrompt>>,
+) -> Option<ToolApprovalPrompt> {
+    match approvals {
+        Some(approvals) => approvals.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 struct StreamingEventOutcome {
     interrupted: bool,
@@ -1692,6 +2133,7 @@ struct StreamingEventOutcome {
     force_exit: bool,
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_streaming_event(
     event: Event,
     quit_bindings: &[KeyBinding],
@@ -1740,6 +2182,8 @@ fn handle_streaming_event(
             return StreamingEventOutcome::default();
         }
 
+        app.answer_diff_review(DiffReviewChoice::Reject);
+        app.answer_tool_approval(ToolApprovalChoice::Deny);
         abort_controller.abort();
         app.status = "interrupting...".to_string();
         app.start_working("interrupting...".to_string());
@@ -1750,9 +2194,43 @@ fn handle_streaming_event(
         };
     }
 
+    if app.pending_review.is_some() && key.modifiers == KeyModifiers::NONE {
+        let choice = match key.code {
+            KeyCode::Char('y') => Some(DiffReviewChoice::Approve),
+            KeyCode::Char('n') => Some(DiffReviewChoice::Reject),
+            KeyCode::Char('a') => Some(DiffReviewChoice::ApproveAll),
+            _ => None,
+        };
+        if let Some(choice) = choice {
+            app.answer_diff_review(choice);
+            return StreamingEventOutcome {
+                interrupted: false,
+                ui_changed: true,
+                force_exit: false,
+            };
+        }
+    }
+
+    if app.pending_approval.is_some() && key.modifiers == KeyModifiers::NONE {
+        let choice = match key.code {
+            KeyCode::Char('y') => Some(ToolApprovalChoice::Approve),
+            KeyCode::Char('n') => Some(ToolApprovalChoice::Deny),
+            _ => None,
+        };
+        if let Some(choice) = choice {
+            app.answer_tool_approval(choice);
+            return StreamingEventOutcome {
+                interrupted: false,
+                ui_changed: true,
+                force_exit: false,
+            };
+        }
+    }
+
     let plain_enter_during_streaming =
         key.code == KeyCode::Enter && key.modifiers == KeyModifiers::NONE;
-    if matches_keybinding(follow_up_bindings, key) || plain_enter_during_streaming {
+    let follow_up = matches_keybinding(follow_up_bindings, key);
+    if follow_up || plain_enter_during_streaming {
         let queued = app.input.trim().to_string();
         if queued.is_empty() {
             return StreamingEventOutcome::default();
@@ -1760,8 +2238,13 @@ fn handle_streaming_event(
         app.record_input_history(&queued);
         app.clear_input();
         app.scroll_transcript_to_latest();
-        app.queue_follow_up(queued);
-        app.status = format!("queued follow-up ({})", app.queued_follow_up_count());
+        // Plain Enter redirects the running agent; the follow-up key waits for the run to end.
+        if !follow_up && app.steer(queued.clone()) {
+            app.status = format!("steering ({})", app.steered_messages.len());
+        } else {
+            app.queue_follow_up(queued);
+            app.status = format!("queued follow-up ({})", app.queued_follow_up_count());
+        }
         return StreamingEventOutcome {
             interrupted: false,
             ui_changed: true,
@@ -1926,12 +2409,23 @@ fn render_ui(frame: &mut Frame, app: &TuiApp, options: &TuiOptions) {
             .saturating_sub(total_status_height)
             .saturating_sub(1),
     );
-    let reserved_height = total_status_height.saturating_add(steering_height);
+    let todo_height = todo_panel_height(app).min(
+        frame
+            .area()
+            .height
+            .saturating_sub(total_status_height)
+            .saturating_sub(steering_height)
+            .saturating_sub(INPUT_AREA_FIXED_HEIGHT + 1),
+    );
+    let reserved_height = total_status_height
+        .saturating_add(steering_height)
+        .saturating_add(todo_height);
     let input_height = input_area_height(app, frame.area(), input_prompt, reserved_height);
     let areas = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Min(1),
+            Constraint::Length(todo_height),
             Constraint::Length(steering_height),
             Constraint::Length(status_top_height),
             Constraint::Length(input_height),
@@ -1940,10 +2434,11 @@ fn render_ui(frame: &mut Frame, app: &TuiApp, options: &TuiOptions) {
         .split(frame.area());
 
     let transcript_area = areas[0];
-    let steering_area = areas[1];
-    let status_top_area = areas[2];
-    let input_area = areas[3];
-    let footer_area = areas[4];
+    let todo_area = areas[1];
+    let steering_area = areas[2];
+    let status_top_area = areas[3];
+    let input_area = areas[4];
+    let footer_area = areas[5];
 
     let visible_lines = visible_transcript_lines(
         &app.transcript,
@@ -1984,6 +2479,17 @@ fn render_ui(frame: &mut Frame, app: &TuiApp, options: &TuiOptions) {
     let transcript = Paragraph::new(Text::from(lines)).style(options.theme.transcript_style());
     frame.render_widget(transcript, transcript_area);
 
+    if todo_height > 0 {
+        let todos = Paragraph::new(Text::from(
+            app.todo_panel_lines()
+                .into_iter()
+                .map(Line::from)
+                .collect::<Vec<_>>(),
+        ))
+        .style(options.theme.transcript_style());
+        frame.render_widget(todos, todo_area);
+    }
+
     if steering_height > 0 {
         let steering = Paragraph::new(render_steering_panel_lines(
             app,
@@ -2083,8 +2589,13 @@ fn render_ui(frame: &mut Frame, app: &TuiApp, options: &TuiOptions) {
             Line::from(""),
             Line::from("Slash Commands"),
             Line::from(format!(
-                "  /new /continue ({continue_key}) /resume [session] /session /help /exit"
+                "  /new /fork [name] /continue ({continue_key}) /resume [session] /session /help /exit"
             )),
+            Line::from("  /undo /redo revert or re-apply the last agent file changes"),
+            Line::from("  /skills [reload|enable <name>|disable <name>] list or toggle skills"),
+            Line::from("  /skill <name> [key=value ...] run a skill with arguments"),
+            Line::from("  /remember [note] save a note or session learnings to project memory"),
+            Line::from("  /cost           show token usage and cost for this session"),
             Line::from("  Ctrl+A / Ctrl+E move cursor"),
             Line::from("  Ctrl+W / Ctrl+U delete backward"),
             Line::from(format!(
@@ -2118,18 +2629,16 @@ fn render_ui(frame: &mut Frame, app: &TuiApp, options: &TuiOptions) {
 
         for (index, candidate) in picker.candidates.iter().enumerate() {
             let indicator = if index == picker.selected { ">" } else { " " };
-            let label = format!(
-                "{indicator} {:>2}. {} ({})",
-                index + 1,
-                candidate.title,
-                candidate.updated_at
-            );
-            let line = if index == picker.selected {
-                Line::from(label).style(Style::default().add_modifier(Modifier::REVERSED))
+            let headline = format!("{indicator} {:>2}. {}", index + 1, candidate.headline());
+            let details = format!("      {}", candidate.details());
+            if index == picker.selected {
+                let selected = Style::default().add_modifier(Modifier::REVERSED);
+                lines.push(Line::from(headline).style(selected));
+                lines.push(Line::from(details).style(selected));
             } else {
-                Line::from(label)
-            };
-            lines.push(line);
+                lines.push(Line::from(headline));
+                lines.push(Line::from(details).style(Style::default().add_modifier(Modifier::DIM)));
+            }
         }
 
         if let Some(latest) = picker.candidates.first() {
@@ -2312,6 +2821,10 @@ fn status_bar_height(app: &TuiApp) -> u16 {
     lines
 }
 
+fn todo_panel_height(app: &TuiApp) -> u16 {
+    app.todo_panel_lines().len().min(u16::MAX as usize) as u16
+}
+
 fn steering_panel_height(app: &TuiApp) -> u16 {
     app.steering_status_lines().len().min(u16::MAX as usize) as u16
 }
@@ -2336,9 +2849,14 @@ fn render_status_bar_lines(app: &TuiApp, width: usize, theme: TuiTheme) -> Text<
         lines.push(Line::from(top));
     }
 
+    let primary_right = match app.context_usage_label() {
+        Some(usage) if app.status_right.is_empty() => usage,
+        Some(usage) => format!("{} · {usage}", app.status_right),
+        None => app.status_right.clone(),
+    };
     lines.push(compose_left_right_status_line_with_styles(
         primary_status_left_label_for_render(app.status_left.as_str()).as_str(),
-        app.status_right.as_str(),
+        primary_right.as_str(),
         width,
         theme.status_primary_left_style(),
         theme.status_primary_right_style(),
@@ -2353,7 +2871,17 @@ fn render_status_bar_lines(app: &TuiApp, width: usize, theme: TuiTheme) -> Text<
     ));
 
     let bottom_left = if app.is_working {
-        format!(" [⏱ {}]? for help", app.working_elapsed_label())
+        match app.streamed_tokens_label() {
+            Some(tokens) => format!(" [⏱ {} · {tokens}]? for help", app.working_elapsed_label()),
+            None => format!(" [⏱ {}]? for help", app.working_elapsed_label()),
+        }
+    } else if let Some(stats) = app.last_turn_stats.as_ref() {
+        match app.run_metrics_label() {
+            Some(tools) => format!(" [{} · {tools}]? for help", turn_stats_label(stats)),
+            None => format!(" [{}]? for help", turn_stats_label(stats)),
+        }
+    } else if let Some(tools) = app.run_metrics_label() {
+        format!(" [{tools}]? for help")
     } else {
         " ? for help".to_string()
     };
@@ -2368,6 +2896,41 @@ fn render_status_bar_lines(app: &TuiApp, width: usize, theme: TuiTheme) -> Text<
     Text::from(lines)
 }
 
+fn turn_stats_label(stats: &StreamStats) -> String {
+    let mut parts = Vec::new();
+    if let Some(ttft) = stats.time_to_first_token_ms {
+        parts.push(format!("ttft {}", format_millis(ttft)));
+    }
+    parts.push(format!("⏱ {}", format_millis(stats.duration_ms)));
+    if let Some(rate) = stats.tokens_per_sec {
+        parts.push(format!("{rate:.0} tok/s"));
+    }
+    match stats.retries {
+        0 => {}
+        1 => parts.push("1 retry".to_string()),
+        retries => parts.push(format!("{retries} retries")),
+    }
+    parts.join(" · ")
+}
+
+fn format_millis(millis: u64) -> String {
+    if millis >= 1_000 {
+        format!("{:.1}s", millis as f64 / 1_000.0)
+    } else {
+        format!("{millis}ms")
+    }
+}
+
+fn format_token_count(tokens: u64) -> String {
+    if tokens >= 1_000_000 {
+        format!("{:.1}M", tokens as f64 / 1_000_000.0)
+    } else if tokens >= 1_000 {
+        format!("{:.1}k", tokens as f64 / 1_000.0)
+    } else {
+        tokens.to_string()
+    }
+}
+
 fn primary_status_left_label_for_render(status_left: &str) -> String {
     let trimmed = status_left.trim();
     if trimmed.is_empty() || is_mode_status_label(trimmed) {
@@ -118,6 +118,8 @@ fn apply_resume_result<B: TuiBackend>(
             if let Some(messages) = backend.session_messages() {
                 app.replace_transcript_with_messages(&messages);
             }
+            app.sync_todos(backend);
+            app.sync_context_usage(backend);
             true
         }
         Ok(None) => {
@@ -98,6 +98,8 @@ impl<'a, B: TuiBackend> TuiRuntime<'a, B> {
             options.status_right.clone(),
         );
         app.set_welcome_lines(build_welcome_banner(&options));
+        app.sync_todos(backend);
+        app.sync_context_usage(backend);
         persist_welcome_into_transcript(&mut app);
 
         let mut fullscreen_init_error: Option<String> = None;
@@ -227,6 +229,7 @@ impl<'a, B: TuiBackend> TuiRuntime<'a, B> {
                 Ok(Some(status)) => {
                     self.app
                         .maybe_update_status_right_from_backend_status(&status);
+                    self.app.sync_context_usage(self.backend);
                     normalize_backend_model_status(status)
                 }
                 Ok(None) => "".to_string(),
@@ -239,6 +242,7 @@ impl<'a, B: TuiBackend> TuiRuntime<'a, B> {
                 Ok(Some(status)) => {
                     self.app
                         .maybe_update_status_right_from_backend_status(&status);
+                    self.app.sync_context_usage(self.backend);
                     normalize_backend_model_status(status)
                 }
                 Ok(None) => "".to_string(),
@@ -251,6 +255,7 @@ impl<'a, B: TuiBackend> TuiRuntime<'a, B> {
                 Ok(Some(status)) => {
                     self.app
                         .maybe_update_status_right_from_backend_status(&status);
+                    self.app.sync_context_usage(self.backend);
                     normalize_backend_model_status(status)
                 }
                 Ok(None) => "".to_string(),
@@ -291,6 +296,7 @@ impl<'a, B: TuiBackend> TuiRuntime<'a, B> {
                     }
                     return Err(error);
                 }
+                self.app.sync_context_usage(self.backend);
                 if let Err(error) = self.process_queued_follow_ups().await {
                     if is_force_exit_signal(&error) {
                         return Ok(RuntimeControl::Exit);
@@ -10,8 +10,9 @@ const LIGHT_THEME_JSON: &str = include_str!("../themes/light.json");
 const DEFAULT_INPUT_PROMPT: &str = "> ";
 const DEFAULT_OUTPUT_PROMPT: &str = "⛬  ";
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
 pub enum TuiTheme {
+    #[default]
     Dark,
     Light,
 }
@@ -130,7 +131,9 @@ impl TuiTheme {
                 .fg(palette.colors.user_input_fg)
                 .bg(palette.colors.input_block_bg),
             TranscriptLineKind::Thinking => Style::default().fg(palette.colors.thinking_fg),
-            TranscriptLineKind::Tool => Style::default().fg(palette.colors.tool_fg),
+            TranscriptLineKind::Tool
+            | TranscriptLineKind::Review
+            | TranscriptLineKind::Subagent => Style::default().fg(palette.colors.tool_fg),
             TranscriptLineKind::Working => Style::default()
                 .fg(palette.colors.working_fg)
                 .bg(palette.colors.working_bg)
@@ -221,12 +224,6 @@ impl TuiTheme {
     }
 }
 
-impl Default for TuiTheme {
-    fn default() -> Self {
-        Self::Dark
-    }
-}
-
 #[derive(Clone, Copy, Debug)]
 struct ThemeColors {
     transcript_fg: Color,
@@ -1,4 +1,8 @@
-use pixy_ai::{AssistantContentBlock, Message, StopReason, ToolResultContentBlock};
+use std::collections::HashMap;
+
+use pixy_ai::{
+    error_remediation, AssistantContentBlock, Message, StopReason, ToolResultContentBlock,
+};
 use ratatui::style::{Modifier, Style};
 use ratatui::text::{Line, Span};
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
@@ -15,7 +19,11 @@ pub(crate) enum TranscriptLineKind {
     UserInput,
     Thinking,
     Tool,
+    /// Proposed file change awaiting the user's approval; shown even when tool output is hidden.
+    Review,
     Working,
+    /// A subagent run's header and progress steps. The steps collapse with tool output.
+    Subagent,
 }
 
 const TOOL_COMPACTION_HEAD_LINES: usize = 2;
@@ -28,6 +36,14 @@ pub(crate) struct TranscriptLine {
     code_language: Option<String>,
     markdown_line_style: Option<MarkdownLineStyle>,
     working_marquee: Option<WorkingMarquee>,
+    subagent: Option<SubagentLine>,
+}
+
+/// Ties a [`TranscriptLineKind::Subagent`] line to the block of its task.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct SubagentLine {
+    task_id: String,
+    header: bool,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -53,6 +69,7 @@ impl TranscriptLine {
             code_language: None,
             markdown_line_style: None,
             working_marquee: None,
+            subagent: None,
         }
     }
 
@@ -63,6 +80,7 @@ impl TranscriptLine {
             code_language: language,
             markdown_line_style: None,
             working_marquee: None,
+            subagent: None,
         }
     }
 
@@ -77,6 +95,7 @@ impl TranscriptLine {
             code_language: None,
             markdown_line_style: Some(markdown_line_style),
             working_marquee: None,
+            subagent: None,
         }
     }
 
@@ -98,19 +117,46 @@ impl TranscriptLine {
                 highlight_start,
                 highlight_len,
             }),
+            subagent: None,
+        }
+    }
+
+    pub(crate) fn new_subagent_header(text: String, task_id: String) -> Self {
+        Self {
+            subagent: Some(SubagentLine {
+                task_id,
+                header: true,
+            }),
+            ..Self::new(text, TranscriptLineKind::Subagent)
         }
     }
 
+    pub(crate) fn new_subagent_step(text: String, task_id: String) -> Self {
+        Self {
+            subagent: Some(SubagentLine {
+                task_id,
+                header: false,
+            }),
+            ..Self::new(text, TranscriptLineKind::Subagent)
+        }
+    }
+
+    pub(crate) fn subagent_task_id(&self) -> Option<&str> {
+        self.subagent.as_ref().map(|line| line.task_id.as_str())
+    }
+
     pub(crate) fn to_line(&self, width: usize, theme: TuiTheme) -> Line<'static> {
         let mut base = theme.line_style(self.kind.clone());
         if let Some(markdown_line_style) = &self.markdown_line_style {
             base = apply_markdown_line_style(base, markdown_line_style);
         }
 
-        let is_tool_diff_removed = matches!(self.kind, TranscriptLineKind::Tool)
-            && self.text.trim_start().starts_with('-');
-        let is_tool_diff_added = matches!(self.kind, TranscriptLineKind::Tool)
-            && self.text.trim_start().starts_with('+');
+        let is_diff_kind = matches!(
+            self.kind,
+            TranscriptLineKind::Tool | TranscriptLineKind::Review
+        );
+        let is_tool_diff_removed = is_diff_kind && self.text.trim_start().starts_with('-');
+        let is_tool_diff_added = is_diff_kind && self.text.trim_start().starts_with('+');
         if is_tool_diff_removed || is_tool_diff_added {
             let style = if is_tool_diff_removed {
                 base.fg(theme.tool_diff_removed())
@@ -1174,10 +1220,7 @@ fn parse_markdown_quote(line: &str) -> Option<String> {
     let mut depth = 0usize;
     let mut rest = line;
 
-    loop {
-        let Some(stripped) = rest.strip_prefix('>') else {
-            break;
-        };
+    while let Some(stripped) = rest.strip_prefix('>') {
         depth += 1;
         rest = stripped.trim_start();
     }
@@ -1585,6 +1628,7 @@ fn is_compound_key_token(normalized: &str) -> bool {
     true
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn visible_transcript_lines(
     lines: &[TranscriptLine],
     supplemental_lines: &[TranscriptLine],
@@ -1600,7 +1644,7 @@ pub(crate) fn visible_transcript_lines(
         return vec![];
     }
 
-    let mut filtered = lines
+    let filtered = lines
         .iter()
         .filter(|line| match line.kind {
             TranscriptLineKind::Normal => true,
@@ -1612,10 +1656,13 @@ pub(crate) fn visible_transcript_lines(
             TranscriptLineKind::Tool => {
                 show_tool_results || is_subagent_tool_line(line.text.as_str())
             }
+            TranscriptLineKind::Review => true,
             TranscriptLineKind::Working => true,
+            TranscriptLineKind::Subagent => true,
         })
         .cloned()
         .collect::<Vec<_>>();
+    let mut filtered = fold_subagent_blocks(filtered, show_tool_results);
 
     filtered.extend(supplemental_lines.iter().cloned());
 
@@ -1638,6 +1685,37 @@ pub(crate) fn visible_transcript_lines(
         .collect()
 }
 
+/// Marks each subagent header as expanded (`▾`) or collapsed (`▸`). Collapsed blocks hide their
+/// steps and show the latest one on the header instead.
+fn fold_subagent_blocks(lines: Vec<TranscriptLine>, expanded: bool) -> Vec<TranscriptLine> {
+    let latest_steps = lines
+        .iter()
+        .filter_map(|line| {
+            let subagent = line.subagent.as_ref().filter(|subagent| !subagent.header)?;
+            Some((subagent.task_id.clone(), line.text.clone()))
+        })
+        .collect::<HashMap<_, _>>();
+    lines
+        .into_iter()
+        .filter_map(|line| {
+            let Some(subagent) = &line.subagent else {
+                return Some(line);
+            };
+            if !subagent.header {
+                return expanded.then(|| {
+                    TranscriptLine::new(format!("  {}", line.text), TranscriptLineKind::Subagent)
+                });
+            }
+            let text = match latest_steps.get(&subagent.task_id) {
+                Some(step) if !expanded => format!("▸ {} · {step}", line.text),
+                _ if expanded => format!("▾ {}", line.text),
+                _ => format!("▸ {}", line.text),
+            };
+            Some(TranscriptLine::new(text, TranscriptLineKind::Subagent))
+        })
+        .collect()
+}
+
 fn decorate_assistant_output_prefix(
     lines: &[TranscriptLine],
     output_prompt: &str,
@@ -1906,6 +1984,20 @@ pub(crate) fn wrap_text_by_display_width(text: &str, max_width: usize) -> Vec<St
     lines
 }
 
+/// One-line stand-in for media the terminal cannot show, e.g. `[image] image/png, 12 KB`.
+pub(crate) fn attachment_placeholder(mime_type: &str, base64_data: &str) -> String {
+    let kind = mime_type.split('/').next().unwrap_or("attachment");
+    let bytes = base64_data.trim_end_matches('=').len() * 3 / 4;
+    if bytes >= 1024 * 1024 {
+        format!(
+            "[{kind}] {mime_type}, {:.1} MB",
+            bytes as f64 / (1024.0 * 1024.0)
+        )
+    } else {
+        format!("[{kind}] {mime_type}, {} KB", bytes.div_ceil(1024))
+    }
+}
+
 pub(crate) fn render_messages(messages: &[Message]) -> Vec<TranscriptLine> {
     let mut lines = Vec::new();
     for message in messages {
@@ -1935,6 +2027,13 @@ pub(crate) fn render_messages(messages: &[Message]) -> Vec<TranscriptLine> {
                             }
                         }
                         AssistantContentBlock::ToolCall { .. } => {}
+                        AssistantContentBlock::Image { data, mime_type }
+                        | AssistantContentBlock::Audio { data, mime_type } => {
+                            lines.push(TranscriptLine::new(
+                                attachment_placeholder(mime_type, data),
+                                TranscriptLineKind::Assistant,
+                            ));
+                        }
                     }
                 }
                 if matches!(stop_reason, StopReason::Error | StopReason::Aborted) {
@@ -1943,6 +2042,12 @@ pub(crate) fn render_messages(messages: &[Message]) -> Vec<TranscriptLine> {
                             format!("[assistant_{}] {error}", stop_reason_label(stop_reason)),
                             TranscriptLineKind::Assistant,
                         ));
+                        if let Some(hint) = error_remediation(error) {
+                            lines.push(TranscriptLine::new(
+                                format!("hint: {hint}"),
+                                TranscriptLineKind::Assistant,
+                            ));
+                        }
                     }
                 }
             }
@@ -1969,9 +2074,9 @@ pub(crate) fn render_messages(messages: &[Message]) -> Vec<TranscriptLine> {
                                     ));
                                 }
                             }
-                            ToolResultContentBlock::Image { .. } => {
+                            ToolResultContentBlock::Image { data, mime_type } => {
                                 lines.push(TranscriptLine::new(
-                                    "(image tool result omitted)".to_string(),
+                                    attachment_placeholder(mime_type, data),
                                     TranscriptLineKind::Tool,
                                 ))
                             }
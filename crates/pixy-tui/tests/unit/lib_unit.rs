@@ -1,7 +1,7 @@
 use std::fs;
 use std::path::PathBuf;
 
-use pixy_agent_core::AgentAbortSignal;
+use pixy_agent_core::{AgentAbortSignal, QueueMode};
 use pixy_ai::{AssistantContentBlock, Cost, StopReason, ToolResultContentBlock, Usage};
 
 use super::*;
@@ -30,6 +30,7 @@ struct TestBackend {
     recent_sessions_limits: Vec<usize>,
     new_session_result: Result<Option<String>, String>,
     new_session_calls: usize,
+    fork_names: Vec<Option<String>>,
 }
 
 impl TuiBackend for TestBackend {
@@ -78,6 +79,11 @@ impl TuiBackend for TestBackend {
         self.new_session_result.clone()
     }
 
+    fn fork_session(&mut self, name: Option<&str>) -> Result<Option<String>, String> {
+        self.fork_names.push(name.map(ToOwned::to_owned));
+        Ok(Some("forked session: /tmp/forked.jsonl".to_string()))
+    }
+
     fn session_messages(&self) -> Option<Vec<Message>> {
         self.session_messages.clone()
     }
@@ -778,21 +784,112 @@ fn visible_transcript_keeps_subagent_lines_even_when_tools_hidden() {
             "Subagent review finished in 0 m 3 s;".to_string(),
             TranscriptLineKind::Tool,
         ),
-        TranscriptLine::new("• Ran bash -lc 'echo hidden'".to_string(), TranscriptLineKind::Tool),
+        TranscriptLine::new(
+            "• Ran bash -lc 'echo hidden'".to_string(),
+            TranscriptLineKind::Tool,
+        ),
     ];
     let visible =
         visible_transcript_lines(&lines, &[], 20, 120, false, false, None, 0, TuiTheme::Dark);
 
-    let rendered = visible
-        .iter()
-        .map(line_text)
-        .collect::<Vec<_>>()
-        .join("\n");
+    let rendered = visible.iter().map(line_text).collect::<Vec<_>>().join("\n");
     assert!(rendered.contains("• Ran task subagent=review task_id=mission-review"));
     assert!(rendered.contains("Subagent review finished in 0 m 3 s;"));
     assert!(!rendered.contains("• Ran bash -lc 'echo hidden'"));
 }
 
+#[test]
+fn subagent_progress_groups_steps_per_task_and_collapses_with_tool_toggle() {
+    let mut app = TuiApp::new("ready".to_string(), true, false);
+    for (task_id, subagent, line) in [
+        ("task-a", "explore", "started"),
+        ("task-b", "review", "started"),
+        ("task-a", "explore", "• Ran read src/lib.rs"),
+        ("task-b", "review", "• Ran grep TODO"),
+        ("task-a", "explore", "completed in 0 m 2 s"),
+    ] {
+        app.apply_stream_update(StreamUpdate::SubagentProgress {
+            task_id: task_id.to_string(),
+            subagent: subagent.to_string(),
+            line: line.to_string(),
+        });
+    }
+
+    let grouped = app
+        .transcript
+        .iter()
+        .filter(|line| line.kind == TranscriptLineKind::Subagent)
+        .map(|line| {
+            (
+                line.subagent_task_id().map(str::to_string),
+                line.text.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        grouped,
+        vec![
+            (
+                Some("task-a".to_string()),
+                "Subagent explore (task_id=task-a)".to_string()
+            ),
+            (Some("task-a".to_string()), "started".to_string()),
+            (
+                Some("task-a".to_string()),
+                "• Ran read src/lib.rs".to_string()
+            ),
+            (
+                Some("task-a".to_string()),
+                "completed in 0 m 2 s".to_string()
+            ),
+            (
+                Some("task-b".to_string()),
+                "Subagent review (task_id=task-b)".to_string()
+            ),
+            (Some("task-b".to_string()), "started".to_string()),
+            (Some("task-b".to_string()), "• Ran grep TODO".to_string()),
+        ]
+    );
+
+    let collapsed = visible_transcript_lines(
+        &app.transcript,
+        &[],
+        40,
+        120,
+        false,
+        false,
+        None,
+        0,
+        TuiTheme::Dark,
+    )
+    .iter()
+    .map(line_text)
+    .collect::<Vec<_>>()
+    .join("\n");
+    assert!(collapsed.contains("▸ Subagent explore (task_id=task-a) · completed in 0 m 2 s"));
+    assert!(collapsed.contains("▸ Subagent review (task_id=task-b) · • Ran grep TODO"));
+    assert!(!collapsed.contains("• Ran read src/lib.rs"));
+
+    let expanded = visible_transcript_lines(
+        &app.transcript,
+        &[],
+        40,
+        120,
+        true,
+        false,
+        None,
+        0,
+        TuiTheme::Dark,
+    )
+    .iter()
+    .map(line_text)
+    .collect::<Vec<_>>()
+    .join("\n");
+    assert!(expanded.contains("▾ Subagent explore (task_id=task-a)"));
+    assert!(expanded.contains("  • Ran read src/lib.rs"));
+    assert!(expanded.contains("  • Ran grep TODO"));
+}
+
 #[test]
 fn visible_transcript_appends_working_line() {
     let lines = vec![TranscriptLine::new(
@@ -974,6 +1071,18 @@ fn thinking_stream_update_keeps_working_message_as_thinking() {
     assert_eq!(app.working_message, "Thinking...");
 }
 
+#[test]
+fn tool_call_preview_update_names_the_pending_tool_call() {
+    let mut app = TuiApp::new("ready".to_string(), true, false);
+    app.start_working("pixy is working...".to_string());
+
+    let update = StreamUpdate::ToolCallPreview("Editing src/lib.rs...".to_string());
+    app.note_working_from_update("pixy", &update);
+    app.apply_stream_update(update);
+    assert_eq!(app.working_message, "Editing src/lib.rs...");
+    assert!(app.transcript.is_empty());
+}
+
 #[test]
 fn working_line_uses_single_space_after_spinner_prefix() {
     let mut app = TuiApp::new("ready".to_string(), true, false);
@@ -993,8 +1102,7 @@ fn working_elapsed_label_accumulates_across_multiple_work_cycles() {
     app.working_elapsed_accumulated = std::time::Duration::from_secs(70);
 
     app.start_working("pixy is working...".to_string());
-    app.working_started_at =
-        Some(std::time::Instant::now() - std::time::Duration::from_secs(40));
+    app.working_started_at = Some(std::time::Instant::now() - std::time::Duration::from_secs(40));
     assert_eq!(app.working_elapsed_label(), "1m 50s");
 
     app.stop_working();
@@ -1577,6 +1685,7 @@ fn multiline_assistant_markdown_tables_are_rendered_in_transcript() {
             output: 0,
             cache_read: 0,
             cache_write: 0,
+            reasoning: 0,
             total_tokens: 0,
             cost: Cost {
                 input: 0.0,
@@ -1589,6 +1698,7 @@ fn multiline_assistant_markdown_tables_are_rendered_in_transcript() {
         stop_reason: StopReason::Stop,
         error_message: None,
         timestamp: 1_700_000_000_000,
+        stats: None,
     }];
 
     let rendered = render_messages(&messages);
@@ -2134,6 +2244,7 @@ async fn slash_resume_command_updates_status_when_backend_supports_resume() {
         recent_sessions_limits: vec![],
         new_session_result: Ok(None),
         new_session_calls: 0,
+        fork_names: vec![],
     };
     let mut app = TuiApp::new("ready".to_string(), true, false);
 
@@ -2165,6 +2276,7 @@ async fn slash_resume_command_renders_resumed_session_messages() {
                 output: 4,
                 cache_read: 0,
                 cache_write: 0,
+                reasoning: 0,
                 total_tokens: 16,
                 cost: Cost {
                     input: 0.0,
@@ -2177,6 +2289,7 @@ async fn slash_resume_command_renders_resumed_session_messages() {
             stop_reason: StopReason::Stop,
             error_message: None,
             timestamp: 1_700_000_000_000,
+            stats: None,
         },
         Message::ToolResult {
             tool_call_id: "call_1".to_string(),
@@ -2198,6 +2311,7 @@ async fn slash_resume_command_renders_resumed_session_messages() {
         recent_sessions_limits: vec![],
         new_session_result: Ok(None),
         new_session_calls: 0,
+        fork_names: vec![],
     };
     let mut app = TuiApp::new("ready".to_string(), true, false);
     app.push_lines(["welcome".to_string()]);
@@ -2221,6 +2335,7 @@ async fn slash_new_command_starts_new_session() {
         recent_sessions_limits: vec![],
         new_session_result: Ok(Some("session: /tmp/new-session.jsonl".to_string())),
         new_session_calls: 0,
+        fork_names: vec![],
     };
     let mut app = TuiApp::new("ready".to_string(), true, false);
 
@@ -2233,6 +2348,145 @@ async fn slash_new_command_starts_new_session() {
     assert_eq!(app.status, "session: /tmp/new-session.jsonl");
 }
 
+#[tokio::test]
+async fn slash_fork_command_forwards_optional_name() {
+    let mut backend = TestBackend {
+        resume_result: Ok(None),
+        resume_targets: vec![],
+        session_messages: None,
+        recent_sessions_result: Ok(None),
+        recent_sessions_limits: vec![],
+        new_session_result: Ok(None),
+        new_session_calls: 0,
+        fork_names: vec![],
+    };
+    let mut app = TuiApp::new("ready".to_string(), true, false);
+
+    let handled = handle_slash_command("/fork", &mut backend, &mut app)
+        .await
+        .expect("/fork should be handled");
+    assert!(handled);
+    let handled = handle_slash_command("/fork  sqlite-attempt ", &mut backend, &mut app)
+        .await
+        .expect("/fork with name should be handled");
+    assert!(handled);
+
+    assert_eq!(
+        backend.fork_names,
+        vec![None, Some("sqlite-attempt".to_string())]
+    );
+    assert_eq!(app.status, "forked session: /tmp/forked.jsonl");
+}
+
+#[tokio::test]
+async fn slash_undo_and_redo_report_unsupported_backend() {
+    let mut backend = TestBackend {
+        resume_result: Ok(None),
+        resume_targets: vec![],
+        session_messages: None,
+        recent_sessions_result: Ok(None),
+        recent_sessions_limits: vec![],
+        new_session_result: Ok(None),
+        new_session_calls: 0,
+        fork_names: vec![],
+    };
+    let mut app = TuiApp::new("ready".to_string(), true, false);
+
+    let handled = handle_slash_command("/undo", &mut backend, &mut app)
+        .await
+        .expect("/undo should be handled");
+    assert!(handled);
+    assert_eq!(app.status, "undo is not supported by this backend");
+
+    let handled = handle_slash_command("/redo", &mut backend, &mut app)
+        .await
+        .expect("/redo should be handled");
+    assert!(handled);
+    assert_eq!(app.status, "redo is not supported by this backend");
+}
+
+#[tokio::test]
+async fn slash_skills_reports_unsupported_backend() {
+    let mut backend = TestBackend {
+        resume_result: Ok(None),
+        resume_targets: vec![],
+        session_messages: None,
+        recent_sessions_result: Ok(None),
+        recent_sessions_limits: vec![],
+        new_session_result: Ok(None),
+        new_session_calls: 0,
+        fork_names: vec![],
+    };
+    let mut app = TuiApp::new("ready".to_string(), true, false);
+
+    let handled = handle_slash_command("/skills reload", &mut backend, &mut app)
+        .await
+        .expect("/skills should be handled");
+    assert!(handled);
+    assert_eq!(app.status, "skills are not supported by this backend");
+
+    let handled = handle_slash_command("/skillset", &mut backend, &mut app)
+        .await
+        .expect("unknown command should not error");
+    assert!(!handled);
+}
+
+#[tokio::test]
+async fn slash_cost_reports_unsupported_backend() {
+    let mut backend = TestBackend {
+        resume_result: Ok(None),
+        resume_targets: vec![],
+        session_messages: None,
+        recent_sessions_result: Ok(None),
+        recent_sessions_limits: vec![],
+        new_session_result: Ok(None),
+        new_session_calls: 0,
+        fork_names: vec![],
+    };
+    let mut app = TuiApp::new("ready".to_string(), true, false);
+
+    let handled = handle_slash_command("/cost", &mut backend, &mut app)
+        .await
+        .expect("/cost should be handled");
+    assert!(handled);
+    assert_eq!(app.status, "cost tracking is not supported by this backend");
+}
+
+#[tokio::test]
+async fn slash_remember_reports_unsupported_backend() {
+    let mut backend = TestBackend {
+        resume_result: Ok(None),
+        resume_targets: vec![],
+        session_messages: None,
+        recent_sessions_result: Ok(None),
+        recent_sessions_limits: vec![],
+        new_session_result: Ok(None),
+        new_session_calls: 0,
+        fork_names: vec![],
+    };
+    let mut app = TuiApp::new("ready".to_string(), true, false);
+
+    let handled = handle_slash_command("/remember use nextest", &mut backend, &mut app)
+        .await
+        .expect("/remember should be handled");
+    assert!(handled);
+    assert_eq!(
+        app.status,
+        "project memory is not supported by this backend"
+    );
+}
+
+#[test]
+fn skill_invocation_args_only_match_skill_command() {
+    assert_eq!(
+        skill_invocation_args("/skill review depth=deep"),
+        Some("review depth=deep")
+    );
+    assert_eq!(skill_invocation_args("/skill"), Some(""));
+    assert_eq!(skill_invocation_args("/skills"), None);
+    assert_eq!(skill_invocation_args("please /skill review"), None);
+}
+
 #[tokio::test]
 async fn slash_session_command_avoids_none_placeholder_when_uninitialized() {
     let mut backend = TestBackend {
@@ -2243,6 +2497,7 @@ async fn slash_session_command_avoids_none_placeholder_when_uninitialized() {
         recent_sessions_limits: vec![],
         new_session_result: Ok(None),
         new_session_calls: 0,
+        fork_names: vec![],
     };
     let mut app = TuiApp::new("ready".to_string(), true, false);
 
@@ -2264,6 +2519,7 @@ async fn slash_resume_command_renders_resume_error() {
         recent_sessions_limits: vec![],
         new_session_result: Ok(None),
         new_session_calls: 0,
+        fork_names: vec![],
     };
     let mut app = TuiApp::new("ready".to_string(), true, false);
 
@@ -2294,16 +2550,23 @@ async fn slash_resume_without_target_lists_recent_sessions() {
                 session_ref: "/tmp/session-2.jsonl".to_string(),
                 title: "first task".to_string(),
                 updated_at: "2026-02-25 12:10".to_string(),
+                turn_count: 2,
+                cwd: "/tmp/project".to_string(),
+                ..ResumeCandidate::default()
             },
             ResumeCandidate {
                 session_ref: "/tmp/session-1.jsonl".to_string(),
                 title: "older task".to_string(),
                 updated_at: "2026-02-25 11:03".to_string(),
+                turn_count: 2,
+                cwd: "/tmp/project".to_string(),
+                ..ResumeCandidate::default()
             },
         ])),
         recent_sessions_limits: vec![],
         new_session_result: Ok(None),
         new_session_calls: 0,
+        fork_names: vec![],
     };
     let mut app = TuiApp::new("ready".to_string(), true, false);
 
@@ -2329,16 +2592,23 @@ async fn slash_resume_numeric_selection_resumes_selected_candidate() {
                 session_ref: "/tmp/session-2.jsonl".to_string(),
                 title: "first task".to_string(),
                 updated_at: "2026-02-25 12:10".to_string(),
+                turn_count: 2,
+                cwd: "/tmp/project".to_string(),
+                ..ResumeCandidate::default()
             },
             ResumeCandidate {
                 session_ref: "/tmp/session-1.jsonl".to_string(),
                 title: "older task".to_string(),
                 updated_at: "2026-02-25 11:03".to_string(),
+                turn_count: 2,
+                cwd: "/tmp/project".to_string(),
+                ..ResumeCandidate::default()
             },
         ])),
         recent_sessions_limits: vec![],
         new_session_result: Ok(None),
         new_session_calls: 0,
+        fork_names: vec![],
     };
     let mut app = TuiApp::new("ready".to_string(), true, false);
 
@@ -2365,10 +2635,14 @@ async fn slash_resume_numeric_selection_rejects_out_of_range_index() {
             session_ref: "/tmp/session-2.jsonl".to_string(),
             title: "first task".to_string(),
             updated_at: "2026-02-25 12:10".to_string(),
+            turn_count: 2,
+            cwd: "/tmp/project".to_string(),
+            ..ResumeCandidate::default()
         }])),
         recent_sessions_limits: vec![],
         new_session_result: Ok(None),
         new_session_calls: 0,
+        fork_names: vec![],
     };
     let mut app = TuiApp::new("ready".to_string(), true, false);
 
@@ -2398,6 +2672,7 @@ fn resume_picker_enter_resumes_selected_item() {
         recent_sessions_limits: vec![],
         new_session_result: Ok(None),
         new_session_calls: 0,
+        fork_names: vec![],
     };
     let mut app = TuiApp::new("ready".to_string(), true, false);
     app.open_resume_picker(vec![
@@ -2405,11 +2680,17 @@ fn resume_picker_enter_resumes_selected_item() {
             session_ref: "/tmp/session-2.jsonl".to_string(),
             title: "first task".to_string(),
             updated_at: "2026-02-25 12:10".to_string(),
+            turn_count: 2,
+            cwd: "/tmp/project".to_string(),
+            ..ResumeCandidate::default()
         },
         ResumeCandidate {
             session_ref: "/tmp/session-1.jsonl".to_string(),
             title: "older task".to_string(),
             updated_at: "2026-02-25 11:03".to_string(),
+            turn_count: 2,
+            cwd: "/tmp/project".to_string(),
+            ..ResumeCandidate::default()
         },
     ]);
     app.resume_picker.as_mut().expect("picker").selected = 1;
@@ -2431,6 +2712,41 @@ fn resume_picker_enter_resumes_selected_item() {
     );
 }
 
+#[test]
+fn resume_candidate_headline_and_details_show_session_metadata() {
+    let candidate = ResumeCandidate {
+        session_ref: "/tmp/session-1.jsonl".to_string(),
+        title: "Fix flaky timeout".to_string(),
+        first_prompt: Some("why does the ws test time out".to_string()),
+        updated_at: "2026-02-25 11:03".to_string(),
+        turn_count: 1,
+        message_count: 4,
+        model: Some("gpt-5.3-codex".to_string()),
+        total_cost: 0.126,
+        cwd: "/tmp/project".to_string(),
+    };
+    assert_eq!(
+        candidate.headline(),
+        "Fix flaky timeout - \"why does the ws test time out\""
+    );
+    assert_eq!(
+        candidate.details(),
+        "2026-02-25 11:03 · 1 turn · 4 messages · gpt-5.3-codex · $0.13 · /tmp/project"
+    );
+
+    let untitled = ResumeCandidate {
+        title: "why does the ws test time out".to_string(),
+        model: None,
+        total_cost: 0.0,
+        ..candidate
+    };
+    assert_eq!(untitled.headline(), "why does the ws test time out");
+    assert_eq!(
+        untitled.details(),
+        "2026-02-25 11:03 · 1 turn · 4 messages · /tmp/project"
+    );
+}
+
 #[test]
 fn resume_picker_escape_cancels_picker() {
     let mut backend = TestBackend {
@@ -2441,12 +2757,16 @@ fn resume_picker_escape_cancels_picker() {
         recent_sessions_limits: vec![],
         new_session_result: Ok(None),
         new_session_calls: 0,
+        fork_names: vec![],
     };
     let mut app = TuiApp::new("ready".to_string(), true, false);
     app.open_resume_picker(vec![ResumeCandidate {
         session_ref: "/tmp/session-2.jsonl".to_string(),
         title: "first task".to_string(),
         updated_at: "2026-02-25 12:10".to_string(),
+        turn_count: 2,
+        cwd: "/tmp/project".to_string(),
+        ..ResumeCandidate::default()
     }]);
 
     let handled = handle_resume_picker_key_event(
@@ -2537,6 +2857,93 @@ fn streaming_plain_enter_queues_input_and_clears_editor() {
     assert_eq!(app.status, "queued follow-up (1)");
 }
 
+#[test]
+fn streaming_plain_enter_steers_the_run_when_a_steering_queue_is_attached() {
+    let mut app = TuiApp::new("ready".to_string(), true, false);
+    let queued = QueuedMessages::new(QueueMode::All, QueueMode::OneAtATime);
+    app.steering = Some(queued.clone());
+    app.input = "use rust instead".to_string();
+    let quit = vec![KeyBinding {
+        code: KeyCode::Char('d'),
+        modifiers: KeyModifiers::CONTROL,
+    }];
+    let interrupt = vec![KeyBinding {
+        code: KeyCode::Esc,
+        modifiers: KeyModifiers::NONE,
+    }];
+    let follow_up = vec![KeyBinding {
+        code: KeyCode::Enter,
+        modifiers: KeyModifiers::ALT,
+    }];
+    let abort_controller = AgentAbortController::new();
+
+    let outcome = handle_streaming_event(
+        Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+        &quit,
+        &interrupt,
+        &follow_up,
+        &[],
+        &[],
+        &abort_controller,
+        &mut app,
+    );
+
+    assert!(outcome.ui_changed);
+    assert!(app.input.is_empty());
+    assert_eq!(app.status, "steering (1)");
+    assert_eq!(app.queued_follow_up_count(), 0);
+    assert!(matches!(
+        queued.pending_steering().as_slice(),
+        [Message::User { content: UserContent::Text(text), .. }] if text == "use rust instead"
+    ));
+
+    app.input = "after the run".to_string();
+    let _ = handle_streaming_event(
+        Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::ALT)),
+        &quit,
+        &interrupt,
+        &follow_up,
+        &[],
+        &[],
+        &abort_controller,
+        &mut app,
+    );
+    assert_eq!(app.queued_follow_up_count(), 1);
+    assert_eq!(queued.pending_steering().len(), 1);
+
+    // Input the run never picked up runs next, ahead of the other follow-ups.
+    app.finish_steering(">");
+    assert!(app.steering.is_none());
+    assert!(queued.is_empty());
+    assert_eq!(
+        app.queued_follow_ups,
+        vec!["use rust instead".to_string(), "after the run".to_string()]
+    );
+}
+
+#[test]
+fn sync_steering_moves_injected_messages_into_the_transcript() {
+    let mut app = TuiApp::new("ready".to_string(), true, false);
+    let queued = QueuedMessages::new(QueueMode::All, QueueMode::OneAtATime);
+    app.steering = Some(queued.clone());
+    assert!(app.steer("first".to_string()));
+    assert!(app.steer("second".to_string()));
+    let shown = |app: &TuiApp, text: &str| {
+        app.transcript
+            .iter()
+            .any(|line| line.kind == TranscriptLineKind::UserInput && line.text.contains(text))
+    };
+
+    app.sync_steering(">");
+    assert!(!shown(&app, "first"));
+
+    queued.take_steering();
+    app.sync_steering(">");
+    assert!(app.steered_messages.is_empty());
+    assert!(shown(&app, "first"));
+    assert!(shown(&app, "second"));
+}
+
 #[test]
 fn streaming_follow_up_key_does_not_queue_empty_input() {
     let mut app = TuiApp::new("ready".to_string(), true, false);
@@ -2606,6 +3013,117 @@ fn streaming_ctrl_d_sets_force_exit_status() {
     assert_eq!(app.status, FORCE_EXIT_STATUS);
 }
 
+#[test]
+fn streaming_review_keys_answer_the_pending_diff_review() {
+    let mut app = TuiApp::new("ready".to_string(), false, false);
+    app.input = "draft".to_string();
+    let answers = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let recorded = answers.clone();
+    app.show_diff_review(DiffReviewPrompt::new(
+        "edit".to_string(),
+        "src/lib.rs".to_string(),
+        "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-old\n+new".to_string(),
+        move |choice| recorded.lock().unwrap().push(choice),
+    ));
+    let lines = visible_transcript_lines(
+        &app.transcript,
+        &[],
+        40,
+        80,
+        false,
+        true,
+        None,
+        0,
+        TuiTheme::Dark,
+    );
+    assert!(
+        lines.iter().any(|line| line_text(line).contains("+new")),
+        "review diffs stay visible while tool output is hidden"
+    );
+
+    let outcome = handle_streaming_event(
+        Event::Key(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::NONE)),
+        &[],
+        &[],
+        &[],
+        &[],
+        &[],
+        &AgentAbortController::new(),
+        &mut app,
+    );
+
+    assert!(outcome.ui_changed);
+    assert!(app.pending_review.is_none());
+    assert_eq!(app.input, "draft");
+    assert_eq!(app.status, "rejected src/lib.rs");
+    assert_eq!(*answers.lock().unwrap(), vec![DiffReviewChoice::Reject]);
+}
+
+#[test]
+fn streaming_approval_keys_answer_the_pending_tool_approval() {
+    let mut app = TuiApp::new("ready".to_string(), false, false);
+    let answers = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let recorded = answers.clone();
+    app.show_tool_approval(ToolApprovalPrompt::new(
+        "bash".to_string(),
+        "high".to_string(),
+        "{\n  \"command\": \"rm -rf target\"\n}".to_string(),
+        move |choice| recorded.lock().unwrap().push(choice),
+    ));
+    assert!(app
+        .transcript
+        .iter()
+        .any(|line| line.text.contains("approve bash (high risk)")));
+
+    let outcome = handle_streaming_event(
+        Event::Key(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE)),
+        &[],
+        &[],
+        &[],
+        &[],
+        &[],
+        &AgentAbortController::new(),
+        &mut app,
+    );
+
+    assert!(outcome.ui_changed);
+    assert!(app.pending_approval.is_none());
+    assert_eq!(app.status, "allowed bash");
+    assert_eq!(*answers.lock().unwrap(), vec![ToolApprovalChoice::Approve]);
+}
+
+#[test]
+fn streaming_interrupt_rejects_the_pending_diff_review() {
+    let mut app = TuiApp::new("ready".to_string(), false, false);
+    let answers = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let recorded = answers.clone();
+    app.show_diff_review(DiffReviewPrompt::new(
+        "write".to_string(),
+        "notes.md".to_string(),
+        "+hello".to_string(),
+        move |choice| recorded.lock().unwrap().push(choice),
+    ));
+    let interrupt = vec![KeyBinding {
+        code: KeyCode::Esc,
+        modifiers: KeyModifiers::NONE,
+    }];
+
+    let outcome = handle_streaming_event(
+        Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)),
+        &[],
+        &interrupt,
+        &[],
+        &[],
+        &[],
+        &AgentAbortController::new(),
+        &mut app,
+    );
+
+    assert!(outcome.interrupted);
+    assert!(app.pending_review.is_none());
+    assert_eq!(*answers.lock().unwrap(), vec![DiffReviewChoice::Reject]);
+}
+
 #[test]
 fn welcome_banner_includes_block_pixy_logo() {
     let lines = build_welcome_banner(&TuiOptions::default());
@@ -2623,8 +3141,10 @@ fn welcome_banner_includes_block_pixy_logo() {
 
 #[test]
 fn welcome_banner_includes_version_line_when_present() {
-    let mut options = TuiOptions::default();
-    options.version = "0.1.0".to_string();
+    let options = TuiOptions {
+        version: "0.1.0".to_string(),
+        ..TuiOptions::default()
+    };
     let lines = build_welcome_banner(&options);
     assert!(
         lines.iter().any(|line| line.contains("v0.1.0")),
@@ -2634,11 +3154,13 @@ fn welcome_banner_includes_version_line_when_present() {
 
 #[test]
 fn welcome_banner_omits_skill_file_names() {
-    let mut options = TuiOptions::default();
-    options.startup_resource_lines = vec![
-        "Loaded skills: 2".to_string(),
-        "/workspace/.agents/skills/demo/SKILL.md".to_string(),
-    ];
+    let options = TuiOptions {
+        startup_resource_lines: vec![
+            "Loaded skills: 2".to_string(),
+            "/workspace/.agents/skills/demo/SKILL.md".to_string(),
+        ],
+        ..TuiOptions::default()
+    };
     let lines = build_welcome_banner(&options);
     assert!(
         lines.iter().any(|line| line == "Loaded skills: 2"),
@@ -2661,8 +3183,10 @@ fn transcript_title_includes_version_when_present() {
 
 #[test]
 fn welcome_banner_includes_startup_resource_lines() {
-    let mut options = TuiOptions::default();
-    options.startup_resource_lines = vec!["Loaded skills: 4".to_string()];
+    let options = TuiOptions {
+        startup_resource_lines: vec!["Loaded skills: 4".to_string()],
+        ..TuiOptions::default()
+    };
 
     let lines = build_welcome_banner(&options);
     assert!(lines.contains(&"Loaded skills: 4".to_string()));
@@ -2678,6 +3202,7 @@ fn startup_status_defaults_to_ready_even_when_session_exists() {
         recent_sessions_limits: vec![],
         new_session_result: Ok(None),
         new_session_calls: 0,
+        fork_names: vec![],
     };
 
     assert_eq!(startup_status_label(&backend), "ready");
@@ -2704,6 +3229,30 @@ fn status_bar_shows_working_without_steering_rows() {
     assert!(line_text(&status.lines[2]).contains("? for help"));
 }
 
+#[test]
+fn status_bar_summarizes_tool_calls_and_failures_when_idle() {
+    let mut app = TuiApp::new("ready".to_string(), true, false);
+    let mut metrics = AgentRunMetrics {
+        tool_execution_count: 4,
+        ..AgentRunMetrics::default()
+    };
+    metrics
+        .tools
+        .entry("bash".to_string())
+        .or_default()
+        .record(10, true);
+    app.run_metrics = Some(metrics);
+
+    let status = render_status_bar_lines(&app, 80, TuiTheme::Dark);
+    let bottom = line_text(status.lines.last().expect("bottom status line"));
+    assert!(bottom.starts_with(" [4 tools · 25% failed]"), "{bottom}");
+
+    app.start_working("pixy is working...".to_string());
+    let status = render_status_bar_lines(&app, 80, TuiTheme::Dark);
+    let bottom = line_text(status.lines.last().expect("bottom status line"));
+    assert!(!bottom.contains("tools"), "{bottom}");
+}
+
 #[test]
 fn status_bar_hides_ready_suffix_when_status_top_present() {
     let mut app = TuiApp::new("ready".to_string(), true, false);
@@ -2828,3 +3377,103 @@ fn dequeue_key_accepts_alt_shift_up_for_terminal_compatibility() {
     assert_eq!(app.input, "first");
     assert_eq!(app.queued_follow_up_count(), 0);
 }
+
+#[test]
+fn todo_stream_update_pins_panel_until_all_items_are_done() {
+    let todo = |content: &str, status: TodoStatus| TodoItem {
+        content: content.to_string(),
+        status,
+    };
+    let mut app = TuiApp::new("ready".to_string(), true, false);
+    assert!(app.todo_panel_lines().is_empty());
+
+    app.apply_stream_update(StreamUpdate::Todos(vec![
+        todo("Add parser", TodoStatus::Done),
+        todo("Wire CLI", TodoStatus::InProgress),
+        todo("Write docs", TodoStatus::Pending),
+    ]));
+    assert_eq!(
+        app.todo_panel_lines(),
+        vec![
+            "Todos 1/3".to_string(),
+            "  ✔ Add parser".to_string(),
+            "  ◐ Wire CLI".to_string(),
+            "  ○ Write docs".to_string(),
+        ]
+    );
+    assert_eq!(todo_panel_height(&app), 4);
+    assert!(
+        app.transcript.is_empty(),
+        "todo updates stay out of transcript"
+    );
+
+    let mut many = (0..6)
+        .map(|index| todo(&format!("done {index}"), TodoStatus::Done))
+        .collect::<Vec<_>>();
+    many.extend((0..4).map(|index| todo(&format!("next {index}"), TodoStatus::Pending)));
+    app.apply_stream_update(StreamUpdate::Todos(many));
+    let lines = app.todo_panel_lines();
+    assert_eq!(lines[0], "Todos 6/10");
+    assert_eq!(lines[1], "  ✔ done 2");
+    assert_eq!(lines.last().map(String::as_str), Some("  ○ next 3"));
+    assert_eq!(lines.len(), TODO_PANEL_MAX_ITEMS + 1);
+
+    app.apply_stream_update(StreamUpdate::Todos(vec![todo(
+        "Add parser",
+        TodoStatus::Done,
+    )]));
+    assert!(app.todo_panel_lines().is_empty());
+}
+
+#[test]
+fn streamed_usage_deltas_accumulate_into_status_label() {
+    let mut app = TuiApp::new("ready".to_string(), true, false);
+    assert_eq!(app.streamed_tokens_label(), None);
+
+    app.apply_stream_update(StreamUpdate::UsageDelta {
+        input_tokens: 1_200,
+        output_tokens: 40,
+    });
+    app.apply_stream_update(StreamUpdate::UsageDelta {
+        input_tokens: 0,
+        output_tokens: 300,
+    });
+
+    assert_eq!(app.streamed_tokens_label().as_deref(), Some("↑1.2k ↓340"));
+}
+
+#[test]
+fn context_usage_renders_next_to_model_label() {
+    let mut app = TuiApp::new("ready".to_string(), true, false);
+    app.set_status_bar_meta(String::new(), String::new(), "openai:gpt-5".to_string());
+    assert_eq!(app.context_usage_label(), None);
+
+    app.context_usage = Some((24_600, 200_000));
+    assert_eq!(app.context_usage_label().as_deref(), Some("12.3%/200k"));
+
+    let status = render_status_bar_lines(&app, 120, TuiTheme::Dark);
+    assert!(line_text(&status.lines[0]).contains("openai:gpt-5 · 12.3%/200k"));
+
+    app.context_usage = Some((1_000, 0));
+    assert_eq!(app.context_usage_label(), None);
+}
+
+#[test]
+fn idle_footer_shows_last_turn_stats() {
+    let mut app = TuiApp::new("ready".to_string(), true, false);
+    app.apply_stream_update(StreamUpdate::TurnStats(StreamStats {
+        time_to_first_token_ms: Some(640),
+        duration_ms: 4_250,
+        tokens_per_sec: Some(41.6),
+        retries: 1,
+        provider: "openai".to_string(),
+    }));
+
+    let status = render_status_bar_lines(&app, 120, TuiTheme::Dark);
+    assert!(line_text(&status.lines[2])
+        .starts_with(" [ttft 640ms · ⏱ 4.2s · 42 tok/s · 1 retry]? for help"));
+
+    app.start_working("pixy is working...".to_string());
+    let status = render_status_bar_lines(&app, 120, TuiTheme::Dark);
+    assert!(!line_text(&status.lines[2]).contains("tok/s"));
+}